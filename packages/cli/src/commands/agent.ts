@@ -1,6 +1,15 @@
+import { readFile } from 'node:fs/promises';
+import { parseHandoffContract, type HandoffContract } from '@defai.digital/shared-runtime';
 import type { CLIOptions, CommandResult } from '../types.js';
-import { createRuntime, failure, success, usageError } from '../utils/formatters.js';
-import { parseOptionalJsonInput, asOptionalString, asOptionalRecord, asStringArray } from '../utils/validation.js';
+import { createTerminalApprover } from '../utils/approvals.js';
+import { createRuntime, failure, failureFromError, formatBudget, success, usageError } from '../utils/formatters.js';
+import { parseBudgetOptions, parseOptionalJsonInput, asOptionalString, asOptionalRecord, asStringArray } from '../utils/validation.js';
+
+const SUGGEST_STATUS = {
+  written: 'written to',
+  exists: 'kept existing',
+  planned: 'would be written to',
+} as const;
 
 interface AgentRegistrationInput {
   agentId: string;
@@ -46,12 +55,21 @@ export async function agentCommand(args: string[], options: CLIOptions): Promise
         `Capabilities: ${agent.capabilities.length > 0 ? agent.capabilities.join(', ') : 'none'}`,
         `Registered: ${agent.registeredAt}`,
         `Updated: ${agent.updatedAt}`,
+        ...await describeProfileLayers(runtime, agentId, options.outputDir),
       ];
 
       return success(lines.join('\n'), agent);
     }
     case 'register': {
-      const parsed = parseRegistrationInput(options.input);
+      let input = options.input;
+      if (args[1] !== undefined) {
+        try {
+          input = await readFile(args[1], 'utf8');
+        } catch (error) {
+          return failureFromError('read the agent profile', error);
+        }
+      }
+      const parsed = parseRegistrationInput(input);
       if (parsed.error !== undefined) {
         return failure(parsed.error);
       }
@@ -84,13 +102,17 @@ export async function agentCommand(args: string[], options: CLIOptions): Promise
     case 'run': {
       const agentId = args[1] ?? options.agent;
       if (agentId === undefined || agentId.length === 0) {
-        return usageError('ax agent run <agent-id> --task <text> [--input <json-object>]');
+        return usageError('ax agent run <agent-id> --task <text> [--input <json-object>] [--max-cost-usd <usd>] [--max-tokens <n>] [--max-duration <duration>] [--approval-policy <file>] [--session-id <session-id> --claim <task-id|next>]');
       }
 
       const parsed = parseOptionalJsonInput(options.input, 'Agent run');
       if (parsed.error !== undefined) {
         return failure(parsed.error);
       }
+      const budget = parseBudgetOptions(options);
+      if (budget.error !== undefined) {
+        return failure(budget.error);
+      }
 
       const result = await runtime.runAgent({
         agentId,
@@ -99,6 +121,12 @@ export async function agentCommand(args: string[], options: CLIOptions): Promise
         provider: options.provider,
         traceId: options.traceId,
         surface: 'cli',
+        noCache: options.noCache,
+        budget: budget.value,
+        approve: createTerminalApprover(),
+        approvalPolicy: options.approvalPolicy,
+        sessionId: options.sessionId,
+        claim: options.claim,
       });
 
       const lines = [
@@ -109,6 +137,81 @@ export async function agentCommand(args: string[], options: CLIOptions): Promise
         `Success: ${result.success ? 'yes' : 'no'}`,
         result.content.length > 0 ? `Output:\n${result.content}` : undefined,
         result.error?.message ? `Error: ${result.error.message}` : undefined,
+        result.budget !== undefined ? formatBudget(result.budget) : undefined,
+        result.claimed !== undefined ? `Scratchpad task: ${result.claimed.taskId} (${result.claimed.status})` : undefined,
+        ...(result.warnings.map((warning) => `Warning: ${warning}`)),
+      ].filter((value): value is string => value !== undefined);
+
+      return result.success
+        ? success(lines.join('\n'), result)
+        : failure(lines.join('\n'), result);
+    }
+    case 'render': {
+      const agentId = args[1] ?? options.agent;
+      if (agentId === undefined || agentId.length === 0) {
+        return usageError('ax agent render <agent-id> [--task <text>] [--input <json-object>]');
+      }
+
+      const parsed = parseOptionalJsonInput(options.input, 'Agent render');
+      if (parsed.error !== undefined) {
+        return failure(parsed.error);
+      }
+
+      try {
+        const preview = await runtime.renderAgentPrompt({ agentId, task: options.task, input: parsed.value });
+        if (preview === undefined) {
+          return failure(`Agent not found: ${agentId}`);
+        }
+
+        const lines = [
+          `Agent prompt: ${preview.agentId}`,
+          `System prompt:\n${preview.systemPrompt}`,
+          `Prompt:\n${preview.prompt}`,
+          preview.template !== undefined && preview.template.includes.length > 0 ? `Includes: ${preview.template.includes.join(', ')}` : undefined,
+          ...(preview.warnings.map((warning) => `Warning: ${warning}`)),
+        ].filter((value): value is string => value !== undefined);
+
+        return success(lines.join('\n'), preview);
+      } catch (error) {
+        return failureFromError('render agent prompt', error);
+      }
+    }
+    case 'delegate': {
+      const agentId = args[1];
+      const contractPath = args[2];
+      if (agentId === undefined || contractPath === undefined || options.task === undefined) {
+        return usageError('ax agent delegate <agent-id> <contract-file> --task <text> [--input <json-object>]');
+      }
+
+      const parsed = parseOptionalJsonInput(options.input, 'Agent delegate');
+      if (parsed.error !== undefined) {
+        return failure(parsed.error);
+      }
+
+      let contract: HandoffContract;
+      try {
+        contract = parseHandoffContract(JSON.parse(await readFile(contractPath, 'utf8')), `The contract file "${contractPath}"`);
+      } catch (error) {
+        return failureFromError('read the contract', error);
+      }
+
+      const result = await runtime.delegateTask({
+        agentId,
+        task: options.task,
+        input: parsed.value,
+        contract: options.provider === undefined ? contract : { ...contract, provider: options.provider },
+        traceId: options.traceId,
+        surface: 'cli',
+      });
+
+      const lines = [
+        `Delegated to: ${result.agentId}`,
+        result.traceId !== undefined ? `Trace: ${result.traceId}` : undefined,
+        result.provider !== undefined ? `Provider: ${result.provider}` : undefined,
+        `Success: ${result.success ? 'yes' : 'no'}`,
+        result.usage !== undefined ? `Tokens: ${result.usage.totalTokens}` : undefined,
+        result.success ? `Output:\n${JSON.stringify(result.output, null, 2)}` : undefined,
+        result.error !== undefined ? `Error: ${result.error.message}` : undefined,
         ...(result.warnings.map((warning) => `Warning: ${warning}`)),
       ].filter((value): value is string => value !== undefined);
 
@@ -139,8 +242,48 @@ export async function agentCommand(args: string[], options: CLIOptions): Promise
 
       return success(lines.join('\n'), recommendations);
     }
+    case 'suggest': {
+      try {
+        const result = await runtime.suggestAgents({ basePath: options.outputDir, dryRun: options.dryRun === true });
+        if (result.agents.length === 0) {
+          return success('No agents to suggest: the symbol index has no files.', result);
+        }
+
+        const lines = [
+          `Languages: ${Object.entries(result.languages).map(([language, files]) => `${language} (${files})`).join(', ')}`,
+          `Frameworks: ${result.frameworks.length > 0 ? result.frameworks.map((framework) => framework.id).join(', ') : 'none detected'}`,
+          `${options.dryRun === true ? 'Would suggest' : 'Suggested'} agents:`,
+          ...result.agents.map((agent) => (
+            `- ${agent.agentId}: ${agent.name} [${agent.capabilities.join(', ')}] ${SUGGEST_STATUS[agent.status]} ${agent.file}`
+          )),
+          options.dryRun === true ? undefined : 'Register one with: ax agent register <profile-file>',
+        ].filter((value): value is string => value !== undefined);
+
+        return success(lines.join('\n'), result);
+      } catch (error) {
+        return failureFromError('suggest agents', error);
+      }
+    }
     default:
-      return usageError('ax agent [list|get|register|remove|capabilities|run|recommend]');
+      return usageError('ax agent [list|get|register|remove|capabilities|run|render|delegate|recommend|suggest]');
+  }
+}
+
+// The profiles and ability packs an agent is layered from, or why they do not resolve.
+async function describeProfileLayers(runtime: ReturnType<typeof createRuntime>, agentId: string, basePath: string | undefined): Promise<string[]> {
+  try {
+    const profile = await runtime.resolveAgentProfile(agentId, basePath);
+    if (profile === undefined) {
+      return [];
+    }
+    return [
+      ...(profile.extends.length > 0
+        ? [`Extends: ${profile.extends.join(', ')}`, `Resolved capabilities: ${profile.agent.capabilities.join(', ') || 'none'}`]
+        : []),
+      ...profile.abilityPacks.map((pack) => `Ability pack: ${pack.name}@${pack.version} (${pack.abilities.join(', ') || 'no abilities'})`),
+    ];
+  } catch (error) {
+    return [`Profile: ${error instanceof Error ? error.message : String(error)}`];
   }
 }
 
@@ -148,7 +291,7 @@ function parseRegistrationInput(input: string | undefined): { value: AgentRegist
   if (input === undefined) {
     return {
       value: { agentId: '', name: '' },
-      error: 'Usage: ax agent register --input <json-object>',
+      error: 'Usage: ax agent register <profile-file> | --input <json-object>',
     };
   }
 
@@ -0,0 +1,29 @@
+import type { CLIOptions, CommandResult } from '../types.js';
+import { createRuntime, failureFromError, success, usageError } from '../utils/formatters.js';
+
+export async function askCommand(args: string[], options: CLIOptions): Promise<CommandResult> {
+  const question = args.join(' ').trim();
+  if (question.length === 0 || args.some((token) => token.startsWith('--'))) {
+    return usageError('ax ask "<question>" [--provider <provider>] [--limit <n>]');
+  }
+
+  try {
+    const result = await createRuntime(options).askProject({
+      question,
+      provider: options.provider,
+      limit: options.limit,
+      basePath: options.outputDir ?? process.cwd(),
+    });
+    const lines = [
+      result.answer,
+      ...result.references.map((reference) => `- ${reference.file}${reference.line !== undefined ? `:${reference.line}` : ''} ${reference.detail}`),
+      '',
+      `Plan (${result.planner}): ${result.plan.intent} "${result.plan.target}"${result.plan.kind !== undefined ? ` kind=${result.plan.kind}` : ''}`,
+      ...result.warnings.map((warning) => `Warning: ${warning}`),
+      `Trace: ${result.traceId}`,
+    ];
+    return success(lines.join('\n'), result);
+  } catch (error) {
+    return failureFromError('answer project question', error);
+  }
+}
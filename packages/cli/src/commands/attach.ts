@@ -0,0 +1,85 @@
+import { open } from 'node:fs/promises';
+import type { CLIOptions, CommandResult } from '../types.js';
+import { isProcessAlive, readDetachedRun } from '../utils/detached-runs.js';
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
+
+const ATTACH_POLL_INTERVAL_MS = 250;
+
+export async function attachCommand(args: string[], options: CLIOptions): Promise<CommandResult> {
+  const follow = !args.includes('--no-follow');
+  const positional = args.filter((token) => token !== '--no-follow');
+  const runId = positional[0];
+  if (runId === undefined || positional.length > 1 || positional.some((token) => token.startsWith('--'))) {
+    return usageError('ax attach <run-id> [--no-follow]');
+  }
+
+  const basePath = options.outputDir ?? process.cwd();
+  const run = await readDetachedRun(basePath, runId);
+  if (run === undefined) {
+    return failure(`No detached run "${runId}" found. Start one with: ax run <workflow-id> --detach`);
+  }
+
+  // JSON output carries the log in the result instead of streaming it.
+  const stream = options.format !== 'json';
+  let log = '';
+  let offset = 0;
+  const drain = async () => {
+    const chunk = await readLogFrom(run.logPath, offset);
+    offset += chunk.length;
+    if (chunk.length > 0) {
+      const text = chunk.toString('utf8');
+      log += text;
+      if (stream) {
+        process.stdout.write(text);
+      }
+    }
+  };
+
+  try {
+    let running = isProcessAlive(run.pid);
+    await drain();
+    while (follow && running) {
+      await new Promise((resolve) => setTimeout(resolve, ATTACH_POLL_INTERVAL_MS));
+      running = isProcessAlive(run.pid);
+      await drain();
+    }
+
+    const trace = await createRuntime(options).getTrace(run.runId);
+    const status = running
+      ? 'running'
+      : trace?.status === 'completed' || trace?.status === 'failed' ? trace.status : 'exited';
+    const data = { ...run, status, ...(stream ? {} : { log }) };
+    switch (status) {
+      case 'running':
+        return success(`Run ${run.runId} (${run.workflowId}) is still running (pid ${run.pid}).`, data);
+      case 'completed':
+        return success(`Run ${run.runId} (${run.workflowId}) completed.`, data);
+      case 'failed':
+        return failure(`Run ${run.runId} (${run.workflowId}) failed.`, data);
+      default:
+        return failure(`Run ${run.runId} (${run.workflowId}) exited without a recorded result. See ${run.logPath}.`, data);
+    }
+  } catch (error) {
+    return failureFromError('attach to run', error);
+  }
+}
+
+async function readLogFrom(logPath: string, offset: number): Promise<Buffer> {
+  let handle;
+  try {
+    handle = await open(logPath, 'r');
+  } catch {
+    return Buffer.alloc(0);
+  }
+  try {
+    const { size } = await handle.stat();
+    if (size <= offset) {
+      return Buffer.alloc(0);
+    }
+    const buffer = Buffer.alloc(size - offset);
+    const { bytesRead } = await handle.read(buffer, 0, buffer.length, offset);
+    return buffer.subarray(0, bytesRead);
+  } finally {
+    await handle.close();
+  }
+}
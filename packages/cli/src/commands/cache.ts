@@ -0,0 +1,38 @@
+import type { CLIOptions, CommandResult } from '../types.js';
+import { createRuntime, failureFromError, success, usageError } from '../utils/formatters.js';
+
+const USAGE = 'ax cache stats | ax cache clear';
+
+export async function cacheCommand(args: string[], options: CLIOptions): Promise<CommandResult> {
+  const subcommand = args[0] ?? 'stats';
+  if (args.length > 1) {
+    return usageError(USAGE);
+  }
+  const runtime = createRuntime(options);
+
+  try {
+    switch (subcommand) {
+      case 'stats': {
+        const stats = await runtime.getProviderCacheStats({ basePath: options.outputDir ?? process.cwd() });
+        const lines = [
+          `Provider response cache: ${stats.enabled
+            ? `on, entries kept ${stats.ttlSeconds}s${stats.semanticThreshold !== undefined ? `, semantic matches at similarity ${stats.semanticThreshold}` : ', exact matches only'}`
+            : 'off (set providers.cache in .automatosx/config.json)'}`,
+          `Entries: ${stats.entries}`,
+          `Hits: ${stats.exactHits + stats.semanticHits} (${stats.exactHits} exact, ${stats.semanticHits} semantic)`,
+          `Tokens saved: ${stats.savedInputTokens.toLocaleString('en-US')} in / ${stats.savedOutputTokens.toLocaleString('en-US')} out`,
+          ...(stats.oldestAt !== undefined ? [`Stored: ${stats.oldestAt} to ${stats.newestAt}`] : []),
+        ];
+        return success(lines.join('\n'), stats);
+      }
+      case 'clear': {
+        const removed = await runtime.clearProviderCache();
+        return success(`Removed ${removed} cached response${removed === 1 ? '' : 's'}.`, { removed });
+      }
+      default:
+        return usageError(USAGE);
+    }
+  } catch (error) {
+    return failureFromError(subcommand === 'clear' ? 'clear the provider cache' : 'read provider cache stats', error);
+  }
+}
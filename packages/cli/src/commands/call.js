@@ -1,11 +1,16 @@
 import { readFile } from 'node:fs/promises';
+import { MAX_CONSENSUS_PROVIDERS, MIN_CONSENSUS_PROVIDERS, } from '@defai.digital/shared-runtime';
 import { createRuntime, failure, success, usageError } from '../utils/formatters.js';
+import { createTextStream, shouldStreamText } from '../utils/text-stream.js';
 import { splitCommaList } from '../utils/validation.js';
 export async function callCommand(args, options) {
     const parsed = parseCallArgs(args);
     if (parsed.error !== undefined) {
         return failure(parsed.error);
     }
+    if (parsed.listModels) {
+        return listModels(options);
+    }
     if (parsed.prompt === undefined || parsed.prompt.length === 0) {
         return usageError('ax call <prompt>');
     }
@@ -20,6 +25,15 @@ export async function callCommand(args, options) {
             options,
         });
     }
+    if ((parsed.consensusProviders !== undefined || parsed.judge !== undefined) && parsed.consensus === undefined) {
+        return failure('Call --providers and --judge need --consensus fastest or --consensus judge.');
+    }
+    const outputSchema = parsed.schemaPath === undefined ? undefined : await readSchema(parsed.schemaPath);
+    if (typeof outputSchema === 'string') {
+        return failure(outputSchema);
+    }
+    // Consensus answers race each other, and schema-checked replies may be thrown away, so neither streams.
+    const stream = shouldStreamText(options) && parsed.consensus === undefined && outputSchema === undefined ? createTextStream() : undefined;
     const result = await runtime.callProvider({
         prompt,
         systemPrompt: parsed.systemPrompt,
@@ -29,8 +43,21 @@ export async function callCommand(args, options) {
         provider: options.provider,
         maxTokens: parsed.maxTokens,
         temperature: parsed.temperature,
+        stopSequences: parsed.stopSequences,
         surface: 'cli',
+        noCache: options.noCache,
+        onText: stream === undefined ? undefined : (text) => stream.write(text),
+        responseFormat: parsed.responseFormat,
+        outputSchema,
+        ...(parsed.consensus !== undefined ? {
+            consensus: {
+                strategy: parsed.consensus,
+                ...(parsed.consensusProviders !== undefined ? { providers: parsed.consensusProviders } : {}),
+                ...(parsed.judge !== undefined ? { judge: parsed.judge } : {}),
+            },
+        } : {}),
     });
+    await stream?.end();
     if (!result.success) {
         return failure(`Provider call failed: ${result.error?.message ?? 'Unknown error'}`, result);
     }
@@ -41,8 +68,13 @@ export async function callCommand(args, options) {
         `Call completed with trace ${result.traceId}.`,
         `Provider: ${result.provider}`,
         `Execution mode: ${result.executionMode}`,
-        '',
-        result.content,
+        ...(result.cache !== undefined
+            ? [`Cache: ${result.cache.match} match${result.cache.score !== undefined ? ` (similarity ${result.cache.score})` : ''}, stored ${result.cache.storedAt}`]
+            : []),
+        ...(result.consensus !== undefined ? formatConsensus(result.consensus) : []),
+        ...(result.structured !== undefined ? [`Output schema: matched after ${result.structured.attempts} attempt${result.structured.attempts === 1 ? '' : 's'}`] : []),
+        // A streamed reply has already been printed above the summary.
+        ...(stream?.written === true ? [] : ['', result.content]),
     ].join('\n') + warningText, result);
 }
 function parseCallArgs(args) {
@@ -50,6 +82,7 @@ function parseCallArgs(args) {
         files: [],
         autonomous: false,
         requireReal: false,
+        listModels: false,
     };
     const positionals = [];
     for (let index = 0; index < args.length; index += 1) {
@@ -70,6 +103,10 @@ function parseCallArgs(args) {
             parsed.requireReal = true;
             continue;
         }
+        if (name === 'list-models') {
+            parsed.listModels = true;
+            continue;
+        }
         const value = args[index + 1];
         if (value === undefined || value.startsWith('--')) {
             return { ...parsed, error: `Missing value for --${name}.` };
@@ -98,6 +135,9 @@ function parseCallArgs(args) {
                 parsed.temperature = temperature;
                 break;
             }
+            case 'stop':
+                parsed.stopSequences = [...(parsed.stopSequences ?? []), value];
+                break;
             case 'goal':
                 parsed.goal = value;
                 break;
@@ -107,6 +147,32 @@ function parseCallArgs(args) {
                 }
                 parsed.intent = value;
                 break;
+            case 'consensus':
+                if (value !== 'fastest' && value !== 'judge') {
+                    return { ...parsed, error: 'Call consensus must be one of: fastest, judge.' };
+                }
+                parsed.consensus = value;
+                break;
+            case 'providers': {
+                const providers = [...new Set(splitCommaList(value))];
+                if (providers.length < MIN_CONSENSUS_PROVIDERS || providers.length > MAX_CONSENSUS_PROVIDERS) {
+                    return { ...parsed, error: `Call --providers must name ${MIN_CONSENSUS_PROVIDERS} to ${MAX_CONSENSUS_PROVIDERS} providers.` };
+                }
+                parsed.consensusProviders = providers;
+                break;
+            }
+            case 'judge':
+                parsed.judge = value;
+                break;
+            case 'schema':
+                parsed.schemaPath = value;
+                break;
+            case 'response-format':
+                if (value !== 'json') {
+                    return { ...parsed, error: 'Call response-format must be json.' };
+                }
+                parsed.responseFormat = value;
+                break;
             case 'max-rounds': {
                 const maxRounds = Number.parseInt(value, 10);
                 if (!Number.isFinite(maxRounds) || maxRounds < 1 || maxRounds > 6) {
@@ -122,6 +188,50 @@ function parseCallArgs(args) {
     parsed.prompt = positionals.join(' ').trim();
     return parsed;
 }
+function formatConsensus(consensus) {
+    const chosen = consensus.candidates.filter((candidate) => candidate.chosen).map((candidate) => candidate.label).join(', ');
+    return [
+        `Consensus: ${consensus.strategy}, ${consensus.verdict === 'merged' ? `merged ${chosen}` : consensus.verdict === 'chosen' ? `chose ${chosen}` : `first in was ${chosen}`}${consensus.judge !== undefined ? ` (judge ${consensus.judge})` : ''}`,
+        ...consensus.candidates.map((candidate) => `- ${candidate.label} ${candidate.provider}: ${candidate.success ? `${candidate.latencyMs}ms` : `failed (${candidate.errorCode ?? 'unknown error'})`}${candidate.chosen ? ' [chosen]' : ''}`),
+    ];
+}
+async function listModels(options) {
+    const runtime = createRuntime(options);
+    const result = await runtime.listProviderModels({
+        provider: options.provider,
+        basePath: options.outputDir ?? process.cwd(),
+    });
+    if (result.error !== undefined) {
+        return failure(`Could not list models: ${result.error}`, result);
+    }
+    if (result.models.length === 0) {
+        return success(`No models on ${result.provider} (${result.baseUrl}).`, result);
+    }
+    return success([
+        `Models on ${result.provider} (${result.baseUrl}):`,
+        ...result.models.map((model) => {
+            const details = [
+                model.parameterSize,
+                model.quantization,
+                model.contextLength === undefined ? undefined : `${model.contextLength}-token context`,
+            ].filter((detail) => detail !== undefined);
+            return `- ${model.name}${details.length > 0 ? ` (${details.join(', ')})` : ''}`;
+        }),
+    ].join('\n'), result);
+}
+// The schema object, or the message to fail with.
+async function readSchema(schemaPath) {
+    let parsed;
+    try {
+        parsed = JSON.parse(await readFile(schemaPath, 'utf8'));
+    }
+    catch (error) {
+        return `Could not read the --schema file "${schemaPath}": ${error instanceof Error ? error.message : String(error)}`;
+    }
+    return typeof parsed === 'object' && parsed !== null && !Array.isArray(parsed)
+        ? parsed
+        : `The --schema file "${schemaPath}" must hold a JSON Schema object.`;
+}
 async function buildPrompt(prompt, files) {
     if (files.length === 0) {
         return prompt;
@@ -166,7 +276,9 @@ async function runAutonomousCall(runtime, request) {
             provider: request.options.provider,
             maxTokens: request.maxTokens,
             temperature: request.temperature,
+            stopSequences: request.stopSequences,
             surface: 'cli',
+            noCache: request.options.noCache,
         });
         if (!result.success) {
             return failure(`Autonomous call failed during ${phase}: ${result.error?.message ?? 'Unknown error'}`, {
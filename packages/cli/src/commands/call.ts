@@ -1,6 +1,13 @@
 import { readFile } from 'node:fs/promises';
+import {
+  MAX_CONSENSUS_PROVIDERS,
+  MIN_CONSENSUS_PROVIDERS,
+  type ConsensusStrategy,
+  type ProviderConsensus,
+} from '@defai.digital/shared-runtime';
 import type { CLIOptions, CommandResult } from '../types.js';
 import { createRuntime, failure, success, usageError } from '../utils/formatters.js';
+import { createTextStream, shouldStreamText } from '../utils/text-stream.js';
 import { splitCommaList } from '../utils/validation.js';
 
 type CallIntent = 'query' | 'analysis' | 'code';
@@ -11,11 +18,18 @@ interface ParsedCallArgs {
   systemPrompt?: string;
   maxTokens?: number;
   temperature?: number;
+  stopSequences?: string[];
   autonomous: boolean;
   requireReal: boolean;
+  listModels: boolean;
   goal?: string;
   intent?: CallIntent;
   maxRounds?: number;
+  consensus?: ConsensusStrategy;
+  consensusProviders?: string[];
+  judge?: string;
+  responseFormat?: 'json';
+  schemaPath?: string;
   error?: string;
 }
 
@@ -24,6 +38,9 @@ export async function callCommand(args: string[], options: CLIOptions): Promise<
   if (parsed.error !== undefined) {
     return failure(parsed.error);
   }
+  if (parsed.listModels) {
+    return listModels(options);
+  }
   if (parsed.prompt === undefined || parsed.prompt.length === 0) {
     return usageError('ax call <prompt>');
   }
@@ -40,6 +57,15 @@ export async function callCommand(args: string[], options: CLIOptions): Promise<
     });
   }
 
+  if ((parsed.consensusProviders !== undefined || parsed.judge !== undefined) && parsed.consensus === undefined) {
+    return failure('Call --providers and --judge need --consensus fastest or --consensus judge.');
+  }
+  const outputSchema = parsed.schemaPath === undefined ? undefined : await readSchema(parsed.schemaPath);
+  if (typeof outputSchema === 'string') {
+    return failure(outputSchema);
+  }
+  // Consensus answers race each other, and schema-checked replies may be thrown away, so neither streams.
+  const stream = shouldStreamText(options) && parsed.consensus === undefined && outputSchema === undefined ? createTextStream() : undefined;
   const result = await runtime.callProvider({
     prompt,
     systemPrompt: parsed.systemPrompt,
@@ -49,8 +75,21 @@ export async function callCommand(args: string[], options: CLIOptions): Promise<
     provider: options.provider,
     maxTokens: parsed.maxTokens,
     temperature: parsed.temperature,
+    stopSequences: parsed.stopSequences,
     surface: 'cli',
+    noCache: options.noCache,
+    onText: stream === undefined ? undefined : (text) => stream.write(text),
+    responseFormat: parsed.responseFormat,
+    outputSchema,
+    ...(parsed.consensus !== undefined ? {
+      consensus: {
+        strategy: parsed.consensus,
+        ...(parsed.consensusProviders !== undefined ? { providers: parsed.consensusProviders } : {}),
+        ...(parsed.judge !== undefined ? { judge: parsed.judge } : {}),
+      },
+    } : {}),
   });
+  await stream?.end();
 
   if (!result.success) {
     return failure(`Provider call failed: ${result.error?.message ?? 'Unknown error'}`, result);
@@ -64,8 +103,13 @@ export async function callCommand(args: string[], options: CLIOptions): Promise<
     `Call completed with trace ${result.traceId}.`,
     `Provider: ${result.provider}`,
     `Execution mode: ${result.executionMode}`,
-    '',
-    result.content,
+    ...(result.cache !== undefined
+      ? [`Cache: ${result.cache.match} match${result.cache.score !== undefined ? ` (similarity ${result.cache.score})` : ''}, stored ${result.cache.storedAt}`]
+      : []),
+    ...(result.consensus !== undefined ? formatConsensus(result.consensus) : []),
+    ...(result.structured !== undefined ? [`Output schema: matched after ${result.structured.attempts} attempt${result.structured.attempts === 1 ? '' : 's'}`] : []),
+    // A streamed reply has already been printed above the summary.
+    ...(stream?.written === true ? [] : ['', result.content]),
   ].join('\n') + warningText, result);
 }
 
@@ -74,6 +118,7 @@ function parseCallArgs(args: string[]): ParsedCallArgs {
     files: [],
     autonomous: false,
     requireReal: false,
+    listModels: false,
   };
   const positionals: string[] = [];
 
@@ -97,6 +142,10 @@ function parseCallArgs(args: string[]): ParsedCallArgs {
       parsed.requireReal = true;
       continue;
     }
+    if (name === 'list-models') {
+      parsed.listModels = true;
+      continue;
+    }
 
     const value = args[index + 1];
     if (value === undefined || value.startsWith('--')) {
@@ -127,6 +176,9 @@ function parseCallArgs(args: string[]): ParsedCallArgs {
         parsed.temperature = temperature;
         break;
       }
+      case 'stop':
+        parsed.stopSequences = [...(parsed.stopSequences ?? []), value];
+        break;
       case 'goal':
         parsed.goal = value;
         break;
@@ -136,6 +188,32 @@ function parseCallArgs(args: string[]): ParsedCallArgs {
         }
         parsed.intent = value;
         break;
+      case 'consensus':
+        if (value !== 'fastest' && value !== 'judge') {
+          return { ...parsed, error: 'Call consensus must be one of: fastest, judge.' };
+        }
+        parsed.consensus = value;
+        break;
+      case 'providers': {
+        const providers = [...new Set(splitCommaList(value))];
+        if (providers.length < MIN_CONSENSUS_PROVIDERS || providers.length > MAX_CONSENSUS_PROVIDERS) {
+          return { ...parsed, error: `Call --providers must name ${MIN_CONSENSUS_PROVIDERS} to ${MAX_CONSENSUS_PROVIDERS} providers.` };
+        }
+        parsed.consensusProviders = providers;
+        break;
+      }
+      case 'judge':
+        parsed.judge = value;
+        break;
+      case 'schema':
+        parsed.schemaPath = value;
+        break;
+      case 'response-format':
+        if (value !== 'json') {
+          return { ...parsed, error: 'Call response-format must be json.' };
+        }
+        parsed.responseFormat = value;
+        break;
       case 'max-rounds': {
         const maxRounds = Number.parseInt(value, 10);
         if (!Number.isFinite(maxRounds) || maxRounds < 1 || maxRounds > 6) {
@@ -153,6 +231,53 @@ function parseCallArgs(args: string[]): ParsedCallArgs {
   return parsed;
 }
 
+function formatConsensus(consensus: ProviderConsensus): string[] {
+  const chosen = consensus.candidates.filter((candidate) => candidate.chosen).map((candidate) => candidate.label).join(', ');
+  return [
+    `Consensus: ${consensus.strategy}, ${consensus.verdict === 'merged' ? `merged ${chosen}` : consensus.verdict === 'chosen' ? `chose ${chosen}` : `first in was ${chosen}`}${consensus.judge !== undefined ? ` (judge ${consensus.judge})` : ''}`,
+    ...consensus.candidates.map((candidate) => `- ${candidate.label} ${candidate.provider}: ${candidate.success ? `${candidate.latencyMs}ms` : `failed (${candidate.errorCode ?? 'unknown error'})`}${candidate.chosen ? ' [chosen]' : ''}`),
+  ];
+}
+
+async function listModels(options: CLIOptions): Promise<CommandResult> {
+  const runtime = createRuntime(options);
+  const result = await runtime.listProviderModels({
+    provider: options.provider,
+    basePath: options.outputDir ?? process.cwd(),
+  });
+  if (result.error !== undefined) {
+    return failure(`Could not list models: ${result.error}`, result);
+  }
+  if (result.models.length === 0) {
+    return success(`No models on ${result.provider} (${result.baseUrl}).`, result);
+  }
+
+  return success([
+    `Models on ${result.provider} (${result.baseUrl}):`,
+    ...result.models.map((model) => {
+      const details = [
+        model.parameterSize,
+        model.quantization,
+        model.contextLength === undefined ? undefined : `${model.contextLength}-token context`,
+      ].filter((detail): detail is string => detail !== undefined);
+      return `- ${model.name}${details.length > 0 ? ` (${details.join(', ')})` : ''}`;
+    }),
+  ].join('\n'), result);
+}
+
+// The schema object, or the message to fail with.
+async function readSchema(schemaPath: string): Promise<Record<string, unknown> | string> {
+  let parsed: unknown;
+  try {
+    parsed = JSON.parse(await readFile(schemaPath, 'utf8'));
+  } catch (error) {
+    return `Could not read the --schema file "${schemaPath}": ${error instanceof Error ? error.message : String(error)}`;
+  }
+  return typeof parsed === 'object' && parsed !== null && !Array.isArray(parsed)
+    ? parsed as Record<string, unknown>
+    : `The --schema file "${schemaPath}" must hold a JSON Schema object.`;
+}
+
 async function buildPrompt(prompt: string, files: string[]): Promise<string> {
   if (files.length === 0) {
     return prompt;
@@ -213,7 +338,9 @@ async function runAutonomousCall(
       provider: request.options.provider,
       maxTokens: request.maxTokens,
       temperature: request.temperature,
+      stopSequences: request.stopSequences,
       surface: 'cli',
+      noCache: request.options.noCache,
     });
 
     if (!result.success) {
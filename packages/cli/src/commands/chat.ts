@@ -0,0 +1,321 @@
+import { existsSync } from 'node:fs';
+import { isAbsolute, join, relative, sep } from 'node:path';
+import { createInterface } from 'node:readline';
+import {
+  createWorkspaceCheckpoint,
+  describeApproval,
+  diffWorkspaceCheckpoint,
+  restoreWorkspaceCheckpoint,
+  type Approver,
+  type RuntimeAgentRunResponse,
+  type WorkspaceCheckpoint,
+} from '@defai.digital/shared-runtime';
+import type { CLIOptions, CommandResult } from '../types.js';
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
+import { createTextStream, shouldStreamText } from '../utils/text-stream.js';
+import { parseBudgetOptions } from '../utils/validation.js';
+
+const USAGE = 'ax chat [agent-id] [--session-id <session-id>] [--provider <name>] [--approval-policy <file>] [--max-cost-usd <usd>]';
+const MEMORY_SEARCH_LIMIT = 10;
+const MEMORY_PREVIEW_CHARS = 120;
+const CHAT_HELP = [
+  'Anything that is not a command goes to the agent, with the conversation so far.',
+  '  /files                  list the files sent with each message',
+  '  /files add <path...>    send these files with each message',
+  '  /files drop <path...>   stop sending them; /files clear stops sending any',
+  '  /agent [agent-id]       show the agent, or switch to another',
+  '  /memory search <query>  search the session\'s memory',
+  '  /diff                   show what changed in the workspace since the last message',
+  '  /undo                   put those files back and drop the last exchange from the conversation',
+  '  /help                   show this help',
+  '  /exit                   end the chat',
+].join('\n');
+
+export interface ChatIo {
+  input: NodeJS.ReadStream;
+  output: NodeJS.WriteStream;
+}
+
+// What `/diff` and `/undo` need of a message sent to the agent.
+interface ChatTurn {
+  /** The workspace as it was before the message was sent. */
+  checkpoint: WorkspaceCheckpoint;
+  /** How many turns the session's thread had before it. */
+  threadTurns: number;
+}
+
+/**
+ * Chats with an agent in one session: each message runs the agent with the conversation so far,
+ * without starting the CLI again. Slash commands pick the files sent along, switch agents, search
+ * memory, and show or undo what the last message changed in the workspace.
+ */
+export async function chatCommand(args: string[], options: CLIOptions): Promise<CommandResult> {
+  if (args.length > 1) {
+    return usageError(USAGE);
+  }
+  return runChat(args[0] ?? options.agent, options, { input: process.stdin, output: process.stdout });
+}
+
+export async function runChat(requestedAgent: string | undefined, options: CLIOptions, io: ChatIo): Promise<CommandResult> {
+  const budget = parseBudgetOptions(options);
+  if (budget.error !== undefined) {
+    return failure(budget.error);
+  }
+  const runtime = createRuntime(options);
+  const basePath = options.outputDir ?? process.cwd();
+
+  // A chat goes on in the session it is given, or in a new one; the agent defaults to the session's.
+  const existing = options.sessionId === undefined ? undefined : await runtime.getSession(options.sessionId);
+  const storedThread = existing === undefined ? undefined : await runtime.getSessionThread(existing.sessionId, options.outputDir);
+  let agentId = requestedAgent ?? storedThread?.agentId;
+  if (agentId === undefined) {
+    return failure(`Name the agent to chat with.\nUsage: ${USAGE}`);
+  }
+  if (await runtime.getAgent(agentId) === undefined) {
+    return failure(`Agent not found: ${agentId}`);
+  }
+  let sessionId: string;
+  let scope: string | undefined;
+  let memoryNamespace: string | undefined;
+  try {
+    if (existing !== undefined) {
+      const resumed = await runtime.resumeSession({ sessionId: existing.sessionId, basePath: options.outputDir });
+      ({ sessionId, scope, memoryNamespace } = resumed.thread);
+    } else {
+      sessionId = (await runtime.createSession({ sessionId: options.sessionId, task: `Chat with ${agentId}`, initiator: 'cli', basePath: options.outputDir })).sessionId;
+    }
+  } catch (error) {
+    return failureFromError('open the chat session', error);
+  }
+
+  const rl = createInterface({ input: io.input, output: io.output, terminal: io.input.isTTY === true && io.output.isTTY === true });
+  // Lines are queued as they come, so piped input is not lost while the agent runs.
+  const pending: string[] = [];
+  let waiting: ((line: string | undefined) => void) | undefined;
+  let closed = false;
+  let running: AbortController | undefined;
+  rl.on('line', (line) => {
+    const resolve = waiting;
+    waiting = undefined;
+    if (resolve !== undefined) {
+      resolve(line);
+    } else {
+      pending.push(line);
+    }
+  });
+  rl.on('close', () => {
+    closed = true;
+    waiting?.(undefined);
+    waiting = undefined;
+  });
+  // Ctrl+C stops the reply underway; at the prompt it ends the chat.
+  rl.on('SIGINT', () => {
+    if (running !== undefined) {
+      running.abort();
+    } else {
+      rl.close();
+    }
+  });
+  const nextLine = (prompt: string): Promise<string | undefined> => {
+    rl.setPrompt(prompt);
+    rl.prompt();
+    const queued = pending.shift();
+    if (queued !== undefined || closed) {
+      return Promise.resolve(queued);
+    }
+    return new Promise((resolve) => {
+      waiting = resolve;
+    });
+  };
+  const write = (text: string) => {
+    io.output.write(`${text}\n`);
+  };
+
+  // Without a terminal, as with piped input, there is nobody to ask: only what the approval policy allows goes ahead.
+  let approvals = Promise.resolve(false);
+  const approve: Approver | undefined = io.input.isTTY !== true ? undefined : (request) => {
+    approvals = approvals.catch(() => false).then(async () => {
+      const answer = await nextLine(`Agent "${request.agentId}" wants to ${describeApproval(request)}. Approve? [y/N] `);
+      const normalized = answer?.toLowerCase().trim();
+      return normalized === 'y' || normalized === 'yes';
+    });
+    return approvals;
+  };
+
+  const files: string[] = [];
+  const turns: ChatTurn[] = [];
+  let latest: WorkspaceCheckpoint | undefined;
+  let messages = 0;
+  let ended = false;
+  write(`Chatting with ${agentId} in session ${sessionId}. /help lists the commands; /exit ends the chat.`);
+  try {
+    while (!ended) {
+      const line = await nextLine(`${agentId}> `);
+      if (line === undefined) {
+        write('');
+        break;
+      }
+      const text = line.trim();
+      if (text.length === 0) {
+        continue;
+      }
+      if (!text.startsWith('/')) {
+        // The workspace is stamped before each message, reusing what the last stamp read of unchanged files.
+        const checkpoint = await createWorkspaceCheckpoint(basePath, { scope, previous: latest });
+        latest = checkpoint;
+        const threadTurns = (await runtime.getSessionThread(sessionId, options.outputDir)).turns.length;
+        const stream = shouldStreamText(options) ? createTextStream(io.output) : undefined;
+        running = new AbortController();
+        let result: RuntimeAgentRunResponse;
+        try {
+          result = await runtime.runAgent({
+            agentId,
+            task: text,
+            sessionId,
+            basePath: options.outputDir,
+            provider: options.provider,
+            surface: 'cli',
+            noCache: options.noCache,
+            budget: budget.value,
+            approve,
+            approvalPolicy: options.approvalPolicy,
+            signal: running.signal,
+            ...(files.length > 0 ? { files: [...files] } : {}),
+            ...(stream !== undefined ? { onText: (chunk: string) => stream.write(chunk) } : {}),
+          });
+        } catch (error) {
+          await stream?.end();
+          write(`Error: ${error instanceof Error ? error.message : String(error)}`);
+          continue;
+        } finally {
+          running = undefined;
+        }
+        await stream?.end();
+        messages += 1;
+        turns.push({ checkpoint, threadTurns });
+        if (stream?.written !== true && result.content.length > 0) {
+          write(`${result.content}\n`);
+        }
+        if (!result.success) {
+          write(`Error: ${result.error?.message ?? 'The run failed.'}`);
+        }
+        for (const warning of result.warnings) {
+          write(`Warning: ${warning}`);
+        }
+        const changes = await diffWorkspaceCheckpoint(checkpoint);
+        if (changes.length > 0) {
+          write(`Changed ${changes.map((change) => change.file).join(', ')}. /diff shows how; /undo puts them back.`);
+        }
+        continue;
+      }
+
+      const [command = '', ...words] = text.slice(1).split(/\s+/);
+      switch (command) {
+        case 'exit':
+        case 'quit':
+          ended = true;
+          break;
+        case 'help':
+          write(CHAT_HELP);
+          break;
+        case 'files': {
+          const [action, ...paths] = words;
+          if (action === undefined) {
+            write(files.length === 0 ? 'No files are sent with messages. /files add <path...> sends some.' : `Sent with each message: ${files.join(', ')}`);
+          } else if (action === 'add' && paths.length > 0) {
+            for (const path of paths) {
+              const file = workspacePath(basePath, path);
+              if (!existsSync(join(basePath, file))) {
+                write(`No such file: ${path}`);
+              } else if (!files.includes(file)) {
+                files.push(file);
+              }
+            }
+            write(`Sent with each message: ${files.length === 0 ? 'no files' : files.join(', ')}`);
+          } else if (action === 'drop' && paths.length > 0) {
+            const dropped = new Set(paths.map((path) => workspacePath(basePath, path)));
+            files.splice(0, files.length, ...files.filter((file) => !dropped.has(file)));
+            write(`Sent with each message: ${files.length === 0 ? 'no files' : files.join(', ')}`);
+          } else if (action === 'clear') {
+            files.length = 0;
+            write('No files are sent with messages.');
+          } else {
+            write('Usage: /files [add <path...> | drop <path...> | clear]');
+          }
+          break;
+        }
+        case 'agent': {
+          const [next] = words;
+          if (next === undefined) {
+            const others = (await runtime.listAgents()).map((agent) => agent.agentId).filter((id) => id !== agentId);
+            write(`Agent: ${agentId}${others.length > 0 ? `\nOthers: ${others.join(', ')}` : ''}`);
+          } else if (await runtime.getAgent(next) === undefined) {
+            write(`Agent not found: ${next}`);
+          } else {
+            agentId = next;
+            write(`Now chatting with ${agentId}.`);
+          }
+          break;
+        }
+        case 'memory': {
+          const [action, ...query] = words;
+          if (action !== 'search' || query.length === 0) {
+            write('Usage: /memory search <query>');
+            break;
+          }
+          const hits = (await runtime.searchMemory(query.join(' '), memoryNamespace)).slice(0, MEMORY_SEARCH_LIMIT);
+          write(hits.length === 0
+            ? `No memory matches "${query.join(' ')}".`
+            : hits.map((entry) => `- ${entry.namespace !== undefined ? `${entry.namespace}/` : ''}${entry.key}: ${previewMemory(entry.value)}`).join('\n'));
+          break;
+        }
+        case 'diff': {
+          const last = turns.at(-1);
+          if (last === undefined) {
+            write('Nothing to show: no message has been sent yet.');
+            break;
+          }
+          const changes = await diffWorkspaceCheckpoint(last.checkpoint);
+          write(changes.length === 0
+            ? 'No files changed since the last message.'
+            : changes.map((change) => change.diff ?? `${change.change} ${change.file} (no diff: its content was not kept or is not text)`).join('\n'));
+          break;
+        }
+        case 'undo': {
+          const last = turns.pop();
+          if (last === undefined) {
+            write('Nothing to undo.');
+            break;
+          }
+          try {
+            const restored = await restoreWorkspaceCheckpoint(last.checkpoint);
+            await runtime.rewindSession(sessionId, last.threadTurns, options.outputDir);
+            write([
+              `Undid the last message${restored.restored.length > 0 ? `; put back ${restored.restored.join(', ')}` : ''}.`,
+              ...(restored.skipped.length > 0 ? [`Warning: could not put back ${restored.skipped.join(', ')}: their earlier content was not kept.`] : []),
+            ].join('\n'));
+          } catch (error) {
+            write(`Error: ${error instanceof Error ? error.message : String(error)}`);
+          }
+          break;
+        }
+        default:
+          write(`Unknown command /${command}. /help lists the commands.`);
+      }
+    }
+  } finally {
+    rl.close();
+  }
+
+  return success(`Chat ended after ${messages} message(s). Continue it with: ax chat --session-id ${sessionId}`, { sessionId, agentId, messages });
+}
+
+// A path as given to `/files`, relative to the workspace root with forward slashes.
+function workspacePath(basePath: string, path: string): string {
+  return relative(basePath, isAbsolute(path) ? path : join(basePath, path)).split(sep).join('/');
+}
+
+function previewMemory(value: unknown): string {
+  const text = (typeof value === 'string' ? value : JSON.stringify(value) ?? '').replace(/\s+/g, ' ').trim();
+  return text.length > MEMORY_PREVIEW_CHARS ? `${text.slice(0, MEMORY_PREVIEW_CHARS - 1)}…` : text;
+}
@@ -0,0 +1,625 @@
+import { spawn } from 'node:child_process';
+import { once } from 'node:events';
+import { readFile } from 'node:fs/promises';
+import { resolve } from 'node:path';
+import { fileURLToPath } from 'node:url';
+import type { CodeSymbol, GraphKind, IndexWatchUpdate } from '@defai.digital/shared-runtime';
+import type { CLIOptions, CommandResult } from '../types.js';
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
+
+const CODE_SYMBOL_KINDS = [
+  'stage',
+  'base-image',
+  'port',
+  'copy',
+  'function',
+  'variable',
+  'resource',
+  'data',
+  'module',
+  'output',
+  'class',
+  'method',
+  'attribute',
+  'trait',
+  'namespace',
+  'record',
+  'property',
+] as const;
+const CODE_LANGUAGES = ['dockerfile', 'shell', 'hcl', 'ruby', 'php', 'csharp', 'rust'] as const;
+const DAEMON_ACTIONS = ['start', 'stop', 'status', 'run'] as const;
+const GRAPH_KINDS = ['modules', 'calls'] as const;
+const EMBEDDING_BACKENDS = ['openai', 'ollama', 'onnx'] as const;
+const DAEMON_START_TIMEOUT_MS = 5_000;
+const DAEMON_POLL_INTERVAL_MS = 100;
+
+type CodeSymbolKind = typeof CODE_SYMBOL_KINDS[number];
+type DaemonAction = typeof DAEMON_ACTIONS[number];
+type EmbeddingBackend = typeof EMBEDDING_BACKENDS[number];
+
+interface ParsedCodeArgs {
+  subcommand: 'index' | 'symbols' | 'parse' | 'signatures' | 'refs' | 'graph' | 'unsafe' | 'unreferenced' | 'embed' | 'daemon' | 'help';
+  positionals: string[];
+  crate?: string;
+  stdin?: boolean;
+  tests?: 'only' | 'exclude';
+  concurrent?: boolean;
+  features?: string[];
+  target?: string;
+  derive?: string;
+  attribute?: string;
+  watch?: boolean;
+  maxFiles?: number;
+  maxMemoryMb?: number;
+  embedder?: EmbeddingBackend;
+  model?: string;
+  id?: string;
+  kind?: CodeSymbolKind;
+  language?: string;
+  file?: string;
+  error?: string;
+}
+
+export async function codeCommand(args: string[], options: CLIOptions): Promise<CommandResult> {
+  const parsed = parseCodeArgs(args);
+
+  if (parsed.error !== undefined) {
+    return failure(parsed.error);
+  }
+
+  switch (parsed.subcommand) {
+    case 'help':
+      return success([
+        'AX Code Intelligence',
+        '',
+        'Usage:',
+        '  ax code index [paths...] [--max-files <n>] [--max-memory-mb <n>] [--watch] [--format jsonl]',
+        '  ax code symbols [query] [--id <stable-id>] [--kind <kind>] [--language <language>] [--file <path>] [--tests|--no-tests] [--concurrent] [--features <a,b>] [--target <target>] [--derive <trait>] [--attribute <path>]',
+        '  ax code parse <path> [--language <language>]',
+        '  ax code parse --stdin --language <language> [--file <path>]',
+        '  ax code signatures <paths...>',
+        '  ax code refs <symbol|file:line|stable-id>',
+        '  ax code graph <modules|calls> [paths...] [--format dot|mermaid]',
+        '  ax code unsafe [paths...] [--crate <name>]',
+        '  ax code unreferenced [paths...] [--kind <kind>]',
+        '  ax code embed [paths...] [--embedder openai|ollama|onnx] [--model <name>]',
+        '  ax code daemon [start|stop|status|run] [--watch]',
+        '',
+        'index --format jsonl writes one symbol per line to stdout as files are parsed, for piping large indexes.',
+        'index --max-memory-mb spills parsed files to disk in batches to stay within that much memory, for small CI',
+        'runners (also codeIntel.maxMemoryMb in .automatosx/config.json); files too large for the limit are skipped.',
+        'Parsed files are cached by content under ~/.automatosx/cache (AUTOMATOSX_CACHE_DIR moves it), so re-indexing',
+        'an unchanged checkout skips parsing; CI can restore that directory. codeIntel.parseCache: false turns it off.',
+        'parse extracts symbols from one file or an unsaved buffer on stdin without touching the index.',
+        'Test code (test files, annotated tests, test classes) is tagged; --tests keeps only it, --no-tests drops it.',
+        'Async functions and the concurrency primitives (Arc, Mutex, channels, spawn, ...) each function uses are tagged;',
+        '--concurrent keeps only functions with either.',
+        'Every symbol has a stableId (sym_...) hashed from its kind, qualified name and parameters, so it survives',
+        'edits above it and moves between files; --id and refs accept it.',
+        'Rust #[cfg(...)] conditions are recorded per symbol; --features and --target drop symbols compiled out of',
+        'that configuration (features not listed count as off, an omitted target matches every target).',
+        'Rust attributes and #[derive(...)] traits are recorded too: --derive Serialize lists serializable types,',
+        '--attribute tokio::main the async entry points.',
+        'Rust has no built-in extractor: register a tree-sitter Rust grammar under codeIntel.grammars (language "rust")',
+        'first; until then Rust files are not indexed and the cfg, attribute, ownership, alias/constant, #[test] and',
+        'concurrency details above are empty for Rust.',
+        'signatures prints files as skeletons (types, fields, signatures) with function bodies elided.',
+        'refs lists the uses of one symbol, resolved through use/using imports, namespaces and method receivers.',
+        'graph draws the file import graph or the function call graph (calls resolved as refs does) as Graphviz DOT',
+        'or a Mermaid flowchart; without --format it prints Mermaid.',
+        'unsafe reports unsafe code, extern/FFI items and raw pointers in Rust sources, grouped by crate.',
+        'unreferenced lists dead-code candidates: indexed symbols nothing else names, excluding entry points,',
+        'test code and trait/interface implementations. Matching is by name, so review before deleting.',
+        'embed splits indexed files into declaration-sized chunks and stores a vector for each in the embeddings',
+        'memory namespace, through OpenAI (OPENAI_API_KEY), Ollama (OLLAMA_HOST) or a local ONNX model; re-running',
+        'embeds only changed chunks. codeIntel.embeddings in .automatosx/config.json sets the default backend and model.',
+        'While the daemon runs, index and symbol queries from every ax process and the MCP server',
+        'share its warm in-memory index instead of re-reading it from disk.',
+        'With --watch, file changes are re-indexed as they happen: index --watch prints symbol changes',
+        'until interrupted, and a watching daemon never answers with stale symbols.',
+      ].join('\n'));
+    case 'index':
+      return parsed.watch === true ? watchIndex(parsed, options) : indexCode(parsed, options);
+    case 'symbols':
+      if (parsed.positionals.length > 1) {
+        return usageError('ax code symbols [query] [--id <stable-id>] [--kind <kind>] [--language <language>] [--file <path>] [--tests|--no-tests] [--concurrent] [--features <a,b>] [--target <target>] [--derive <trait>] [--attribute <path>]');
+      }
+      return listSymbols(parsed, options);
+    case 'parse':
+      return parseSource(parsed, options);
+    case 'signatures':
+      if (parsed.positionals.length === 0) {
+        return usageError('ax code signatures <paths...>');
+      }
+      return printSignatures(parsed.positionals, options);
+    case 'refs':
+      if (parsed.positionals.length !== 1) {
+        return usageError('ax code refs <symbol|file:line|stable-id>');
+      }
+      return listReferences(parsed.positionals[0]!, options);
+    case 'graph':
+      if (!(GRAPH_KINDS as readonly string[]).includes(parsed.positionals[0] ?? '')) {
+        return usageError('ax code graph <modules|calls> [paths...] [--format dot|mermaid]');
+      }
+      return exportGraph(parsed.positionals[0] as GraphKind, parsed.positionals.slice(1), options);
+    case 'unsafe':
+      return reportUnsafe(parsed, options);
+    case 'unreferenced':
+      return listUnreferenced(parsed, options);
+    case 'embed':
+      return embedCode(parsed, options);
+    case 'daemon':
+      return manageDaemon(parsed, options);
+  }
+}
+
+function parseCodeArgs(args: string[]): ParsedCodeArgs {
+  const first = args[0];
+  const subcommand = first === 'index' || first === 'symbols' || first === 'parse' || first === 'signatures' || first === 'refs' || first === 'graph' || first === 'unsafe' || first === 'unreferenced' || first === 'embed' || first === 'daemon' || first === 'help' ? first : 'help';
+  const parsed: ParsedCodeArgs = { subcommand, positionals: [] };
+
+  if (first !== undefined && first !== subcommand) {
+    return { ...parsed, error: `Unknown code subcommand: ${first}.` };
+  }
+
+  for (let index = 1; index < args.length; index += 1) {
+    const token = args[index]!;
+    const value = args[index + 1];
+
+    if (token === '--stdin') {
+      parsed.stdin = true;
+      continue;
+    }
+
+    if (token === '--tests' || token === '--no-tests') {
+      parsed.tests = token === '--tests' ? 'only' : 'exclude';
+      continue;
+    }
+
+    if (token === '--concurrent') {
+      parsed.concurrent = true;
+      continue;
+    }
+
+    if (token === '--watch') {
+      parsed.watch = true;
+      continue;
+    }
+
+    if (token === '--max-files' || token === '--max-memory-mb' || token === '--kind' || token === '--language' || token === '--lang' || token === '--file' || token === '--crate' || token === '--id' || token === '--features' || token === '--target' || token === '--derive' || token === '--attribute' || token === '--embedder' || token === '--model') {
+      if (value === undefined) {
+        return { ...parsed, error: `Missing value for ${token}.` };
+      }
+      index += 1;
+
+      if (token === '--max-files') {
+        const maxFiles = Number.parseInt(value, 10);
+        if (!Number.isFinite(maxFiles) || maxFiles <= 0) {
+          return { ...parsed, error: 'Code max-files must be a positive integer.' };
+        }
+        parsed.maxFiles = maxFiles;
+      } else if (token === '--max-memory-mb') {
+        const maxMemoryMb = Number.parseInt(value, 10);
+        if (!Number.isFinite(maxMemoryMb) || maxMemoryMb <= 0) {
+          return { ...parsed, error: 'Code max-memory-mb must be a positive integer.' };
+        }
+        parsed.maxMemoryMb = maxMemoryMb;
+      } else if (token === '--kind') {
+        if (!(CODE_SYMBOL_KINDS as readonly string[]).includes(value)) {
+          return { ...parsed, error: `Code symbol kind must be one of: ${CODE_SYMBOL_KINDS.join(', ')}.` };
+        }
+        parsed.kind = value as CodeSymbolKind;
+      } else if (token === '--language' || token === '--lang') {
+        parsed.language = value;
+      } else if (token === '--crate') {
+        parsed.crate = value;
+      } else if (token === '--id') {
+        parsed.id = value;
+      } else if (token === '--features') {
+        parsed.features = [...parsed.features ?? [], ...value.split(',').map((feature) => feature.trim()).filter((feature) => feature.length > 0)];
+      } else if (token === '--target') {
+        parsed.target = value;
+      } else if (token === '--derive') {
+        parsed.derive = value;
+      } else if (token === '--attribute') {
+        parsed.attribute = value;
+      } else if (token === '--embedder') {
+        if (!(EMBEDDING_BACKENDS as readonly string[]).includes(value)) {
+          return { ...parsed, error: `Code embedder must be one of: ${EMBEDDING_BACKENDS.join(', ')}.` };
+        }
+        parsed.embedder = value as EmbeddingBackend;
+      } else if (token === '--model') {
+        parsed.model = value;
+      } else {
+        parsed.file = value;
+      }
+      continue;
+    }
+
+    if (token.startsWith('--')) {
+      return { ...parsed, error: `Unknown code flag: ${token}.` };
+    }
+    parsed.positionals.push(token);
+  }
+
+  return parsed;
+}
+
+async function indexCode(parsed: ParsedCodeArgs, options: CLIOptions): Promise<CommandResult> {
+  if (options.format === 'jsonl') {
+    return streamIndex(parsed, options);
+  }
+  const runtime = createRuntime(options);
+  const result = await runtime.indexCode({
+    paths: parsed.positionals,
+    maxFiles: parsed.maxFiles,
+    maxMemoryMb: parsed.maxMemoryMb,
+    basePath: options.outputDir ?? process.cwd(),
+  });
+
+  const languages = Object.entries(result.languages)
+    .map(([language, count]) => `${language}=${count}`)
+    .join(', ');
+  return success(
+    [
+      `Indexed ${result.filesIndexed} file(s) with ${result.symbolCount} symbol(s)${languages.length > 0 ? ` (${languages})` : ''}${result.cached !== undefined && result.cached > 0 ? `, ${result.cached} file(s) unchanged from the parse cache` : ''}. Index: ${result.indexPath}`,
+      ...(result.warnings ?? []).map((warning) => `Warning: ${warning}`),
+    ].join('\n'),
+    result,
+  );
+}
+
+// stdout carries only symbol records, so the summary goes to stderr and the result prints nothing.
+async function streamIndex(parsed: ParsedCodeArgs, options: CLIOptions): Promise<CommandResult> {
+  try {
+    const result = await createRuntime(options).indexCode({
+      paths: parsed.positionals,
+      maxFiles: parsed.maxFiles,
+      maxMemoryMb: parsed.maxMemoryMb,
+      basePath: options.outputDir ?? process.cwd(),
+      onFile: async (entry) => {
+        if (entry.symbols.length === 0) {
+          return;
+        }
+        const chunk = entry.symbols.map((symbol) => `${JSON.stringify(symbol)}\n`).join('');
+        if (!process.stdout.write(chunk)) {
+          await once(process.stdout, 'drain');
+        }
+      },
+    });
+    logProgress(`Indexed ${result.filesIndexed} file(s) with ${result.symbolCount} symbol(s). Index: ${result.indexPath}`);
+    for (const warning of result.warnings ?? []) {
+      logProgress(`Warning: ${warning}`);
+    }
+    return { success: true, message: undefined, data: undefined, exitCode: 0 };
+  } catch (error) {
+    return failureFromError('stream code index', error);
+  }
+}
+
+async function watchIndex(parsed: ParsedCodeArgs, options: CLIOptions): Promise<CommandResult> {
+  try {
+    const watcher = await createRuntime(options).watchCodeIndex({
+      paths: parsed.positionals,
+      maxFiles: parsed.maxFiles,
+      basePath: options.outputDir ?? process.cwd(),
+      onUpdate: (update) => logProgress(formatWatchUpdate(update)),
+      onError: (error) => logProgress(`Warning: ${error.message}`),
+    });
+    const initial = watcher.snapshot();
+    logProgress(`Indexed ${initial.files.length} file(s) with ${initial.files.reduce((total, entry) => total + entry.symbols.length, 0)} symbol(s); watching for changes.`);
+    const shutdown = (): void => {
+      void watcher.close();
+    };
+    process.once('SIGINT', shutdown);
+    process.once('SIGTERM', shutdown);
+    await watcher.closed;
+    process.off('SIGINT', shutdown);
+    process.off('SIGTERM', shutdown);
+    const last = watcher.snapshot();
+    return success('Stopped watching the code index.', { generatedAt: last.generatedAt, filesIndexed: last.files.length });
+  } catch (error) {
+    return failureFromError('watch code index', error);
+  }
+}
+
+function formatWatchUpdate(update: IndexWatchUpdate): string {
+  const marks = { added: '+', removed: '-', changed: '~' } as const;
+  return [
+    `Re-indexed ${update.files.length} file(s): ${update.changes.length} symbol change(s), ${update.symbolCount} symbol(s) indexed.`,
+    ...update.changes.map((change) => `  ${marks[change.type]} ${change.symbol.kind} ${change.symbol.container !== undefined ? `${change.symbol.container}.` : ''}${change.symbol.name} (${change.file}:${change.symbol.line})`),
+  ].join('\n');
+}
+
+function logProgress(message: string): void {
+  process.stderr.write(`[${new Date().toISOString()}] ${message}\n`);
+}
+
+async function listSymbols(parsed: ParsedCodeArgs, options: CLIOptions): Promise<CommandResult> {
+  const runtime = createRuntime(options);
+  if (parsed.language !== undefined) {
+    const languages = [...new Set([...CODE_LANGUAGES, ...await readGrammarLanguages(runtime)])];
+    if (!languages.includes(parsed.language)) {
+      return failure(`Code language must be one of: ${languages.join(', ')}.`);
+    }
+  }
+  const symbols = await runtime.searchCodeSymbols({
+    query: parsed.positionals[0],
+    id: parsed.id,
+    kind: parsed.kind,
+    language: parsed.language,
+    file: parsed.file,
+    tests: parsed.tests,
+    concurrent: parsed.concurrent,
+    features: parsed.features,
+    target: parsed.target,
+    derive: parsed.derive,
+    attribute: parsed.attribute,
+    limit: options.limit,
+    basePath: options.outputDir ?? process.cwd(),
+  });
+
+  if (symbols.length === 0) {
+    return success('No indexed symbols found. Run "ax code index" to refresh the index.', symbols);
+  }
+
+  const lines = [
+    'Indexed symbols:',
+    ...symbols.map((symbol) => formatSymbol(symbol)),
+  ];
+  return success(lines.join('\n'), symbols);
+}
+
+async function parseSource(parsed: ParsedCodeArgs, options: CLIOptions): Promise<CommandResult> {
+  const usage = 'ax code parse <path> [--language <language>] | ax code parse --stdin --language <language> [--file <path>]';
+  const fromStdin = parsed.stdin === true;
+  if (fromStdin ? parsed.positionals.length > 0 : parsed.positionals.length !== 1 || parsed.file !== undefined) {
+    return usageError(usage);
+  }
+  if (fromStdin && parsed.language === undefined && parsed.file === undefined) {
+    return failure('ax code parse --stdin needs --language (or --file to detect it from the path).');
+  }
+  if (fromStdin && process.stdin.isTTY === true) {
+    return failure('ax code parse --stdin expects the buffer to be piped in.');
+  }
+
+  const basePath = options.outputDir ?? process.cwd();
+  try {
+    const file = fromStdin ? parsed.file ?? '<stdin>' : parsed.positionals[0]!;
+    const content = fromStdin ? await readStdin() : await readFile(resolve(basePath, file), 'utf8');
+    const result = await createRuntime(options).parseCodeSource({ content, language: parsed.language, file, basePath });
+    const symbols = result.symbols.filter((symbol) => (parsed.kind === undefined || symbol.kind === parsed.kind)
+      && (parsed.tests === undefined || (symbol.isTest === true) === (parsed.tests === 'only'))
+      && (parsed.concurrent !== true || symbol.isAsync === true || (symbol.concurrency?.length ?? 0) > 0));
+    const lines = [
+      `Parsed ${result.file} as ${result.language}: ${symbols.length} symbol(s).`,
+      ...symbols.map((symbol) => formatSymbol(symbol)),
+      ...(result.warnings ?? []).map((warning) => `Warning: ${warning}`),
+    ];
+    return success(lines.join('\n'), { ...result, symbols });
+  } catch (error) {
+    return failureFromError('parse source', error);
+  }
+}
+
+async function embedCode(parsed: ParsedCodeArgs, options: CLIOptions): Promise<CommandResult> {
+  try {
+    const result = await createRuntime(options).embedCode({
+      backend: parsed.embedder,
+      model: parsed.model,
+      paths: parsed.positionals,
+      basePath: options.outputDir ?? process.cwd(),
+    });
+    const dimensions = result.dimensions !== undefined ? ` (${result.dimensions} dimensions)` : '';
+    return success(
+      `Embedded ${result.embedded} of ${result.chunks} chunk(s) with ${result.backend} ${result.model}${dimensions}; ${result.unchanged} unchanged, ${result.removed} removed.`,
+      result,
+    );
+  } catch (error) {
+    return failureFromError('embed code', error);
+  }
+}
+
+async function reportUnsafe(parsed: ParsedCodeArgs, options: CLIOptions): Promise<CommandResult> {
+  try {
+    const reports = await createRuntime(options).reportUnsafeCode({
+      paths: parsed.positionals,
+      crate: parsed.crate,
+      basePath: options.outputDir ?? process.cwd(),
+    });
+    if (reports.length === 0) {
+      return success(parsed.crate !== undefined ? `No Rust crate named ${parsed.crate} found.` : 'No Rust sources found.', reports);
+    }
+
+    const lines: string[] = [];
+    for (const report of reports) {
+      const counts = Object.entries(report.counts)
+        .filter(([, count]) => count > 0)
+        .map(([kind, count]) => `${kind}=${count}`)
+        .join(', ');
+      lines.push(
+        ...(lines.length > 0 ? [''] : []),
+        `${report.crate}${report.manifest !== undefined ? ` (${report.manifest})` : ''}: ${report.findings.length} finding(s) in ${report.files} file(s)${counts.length > 0 ? ` (${counts})` : ''}.`,
+        ...report.findings.map((finding) => {
+          const name = finding.name !== undefined ? ` ${finding.name}` : '';
+          const abi = finding.abi !== undefined ? ` "${finding.abi}"` : '';
+          const owner = finding.function !== undefined ? ` in ${finding.function}` : '';
+          return `- ${finding.kind}${abi}${name} ${finding.file}:${finding.line}:${finding.column}-${finding.endLine}:${finding.endColumn}${owner}`;
+        }),
+      );
+    }
+    return success(lines.join('\n'), reports);
+  } catch (error) {
+    return failureFromError('report unsafe code', error);
+  }
+}
+
+async function printSignatures(paths: string[], options: CLIOptions): Promise<CommandResult> {
+  try {
+    const views = await createRuntime(options).renderSignatures({ paths, basePath: options.outputDir ?? process.cwd() });
+    if (views.length === 0) {
+      return success('No indexable source files found.', views);
+    }
+    const sections = views.map((view) => `==> ${view.file} (${view.lines} -> ${view.renderedLines} lines) <==\n${view.content}`);
+    return success(sections.join('\n\n'), views);
+  } catch (error) {
+    return failureFromError('render signatures', error);
+  }
+}
+
+async function listReferences(symbol: string, options: CLIOptions): Promise<CommandResult> {
+  try {
+    const result = await createRuntime(options).findReferences({ symbol, basePath: options.outputDir ?? process.cwd() });
+    const target = result.symbol;
+    const lines = [
+      `${result.references.length} reference(s) to ${target.kind} ${target.container !== undefined ? `${target.container}.` : ''}${target.name} (${target.id})${result.unresolved > 0 ? `; ${result.unresolved} ambiguous use(s) skipped` : ''}.`,
+      ...result.references.map((reference) => `- ${reference.file}:${reference.line}:${reference.column} [${reference.via}]${reference.within !== undefined ? ` in ${reference.within}` : ''}: ${reference.snippet}`),
+    ];
+    return success(lines.join('\n'), result);
+  } catch (error) {
+    return failureFromError('find references', error);
+  }
+}
+
+async function exportGraph(graph: GraphKind, paths: string[], options: CLIOptions): Promise<CommandResult> {
+  try {
+    const result = await createRuntime(options).exportCodeGraph({
+      graph,
+      format: options.format === 'dot' ? 'dot' : 'mermaid',
+      paths: paths.length > 0 ? paths : undefined,
+      basePath: options.outputDir ?? process.cwd(),
+    });
+    return success(result.content.trimEnd(), result);
+  } catch (error) {
+    return failureFromError('export code graph', error);
+  }
+}
+
+async function listUnreferenced(parsed: ParsedCodeArgs, options: CLIOptions): Promise<CommandResult> {
+  try {
+    const result = await createRuntime(options).findUnreferencedSymbols({
+      paths: parsed.positionals,
+      kind: parsed.kind,
+      limit: options.limit,
+      basePath: options.outputDir ?? process.cwd(),
+    });
+    const { entryPoints, tests, traitImpls } = result.excluded;
+    const lines = [
+      `${result.candidates.length} unreferenced symbol(s) out of ${result.checked} checked (skipped ${entryPoints} entry point(s), ${tests} test(s), ${traitImpls} trait impl(s)).`,
+      ...result.candidates.map((symbol) => {
+        const container = symbol.container !== undefined ? ` (${symbol.container})` : '';
+        const visibility = symbol.visibility !== undefined ? ` [${symbol.visibility}]` : '';
+        return `- ${symbol.kind} ${symbol.name} ${symbol.file}:${symbol.line}${container}${visibility}`;
+      }),
+    ];
+    return success(lines.join('\n'), result);
+  } catch (error) {
+    return failureFromError('find unreferenced symbols', error);
+  }
+}
+
+async function readStdin(): Promise<string> {
+  const chunks: Buffer[] = [];
+  for await (const chunk of process.stdin) {
+    chunks.push(typeof chunk === 'string' ? Buffer.from(chunk) : chunk as Buffer);
+  }
+  return Buffer.concat(chunks).toString('utf8');
+}
+
+async function readGrammarLanguages(runtime: ReturnType<typeof createRuntime>): Promise<string[]> {
+  const grammars = await runtime.getConfig('codeIntel.grammars');
+  return Array.isArray(grammars)
+    ? grammars.flatMap((grammar: unknown) => {
+      const language = (grammar as { language?: unknown } | null)?.language;
+      return typeof language === 'string' ? [language] : [];
+    })
+    : [];
+}
+
+function formatSymbol(symbol: CodeSymbol): string {
+  const container = symbol.container !== undefined ? ` (${symbol.container})` : '';
+  const tags = [symbol.isTest === true ? 'test' : undefined, symbol.isAsync === true ? 'async' : undefined, symbol.ownership?.receiver].filter((tag) => tag !== undefined);
+  const concurrency = symbol.concurrency !== undefined && symbol.concurrency.length > 0 ? ` uses ${symbol.concurrency.join(', ')}` : '';
+  const resolved = symbol.attributes?.aliasOf ?? symbol.attributes?.value;
+  const value = resolved !== undefined ? ` = ${resolved}` : '';
+  const derives = symbol.derives !== undefined ? ` derives ${symbol.derives.join(', ')}` : '';
+  const cfg = symbol.cfg === undefined ? '' : ` #[cfg(${symbol.cfg.length === 1 ? symbol.cfg[0] : `all(${symbol.cfg.join(', ')})`})]`;
+  return `- ${symbol.kind} ${symbol.name} ${formatLocations(symbol)}${container}${value}${tags.map((tag) => ` [${tag}]`).join('')}${concurrency}${derives}${cfg}`;
+}
+
+function formatLocations(symbol: CodeSymbol): string {
+  return (symbol.locations ?? [symbol]).map((location) => `${location.file}:${location.line}`).join(', ');
+}
+
+async function manageDaemon(parsed: ParsedCodeArgs, options: CLIOptions): Promise<CommandResult> {
+  const requested = parsed.positionals[0] ?? 'status';
+  if (!(DAEMON_ACTIONS as readonly string[]).includes(requested) || parsed.positionals.length > 1) {
+    return usageError('ax code daemon [start|stop|status|run]');
+  }
+  const action = requested as DaemonAction;
+
+  const basePath = options.outputDir ?? process.cwd();
+  const runtime = createRuntime(options);
+
+  try {
+    switch (action) {
+      case 'status': {
+        const status = await runtime.getIndexServerStatus({ basePath });
+        if (status === undefined) {
+          return success('Index daemon is not running.', { running: false });
+        }
+        return success(
+          `Index daemon running (pid ${status.pid}) with ${status.filesIndexed} file(s) and ${status.symbolCount} symbol(s) warm${status.watching ? ', watching for changes' : ''}; ${status.requestsServed} request(s) served. Socket: ${status.socketPath}`,
+          { running: true, ...status },
+        );
+      }
+      case 'stop': {
+        const stopped = await runtime.stopIndexServer({ basePath });
+        return success(stopped ? 'Index daemon stopped.' : 'Index daemon is not running.', { stopped });
+      }
+      case 'run': {
+        const server = await runtime.startIndexServer({ basePath, watch: parsed.watch });
+        const shutdown = (): void => {
+          void server.close();
+        };
+        process.once('SIGINT', shutdown);
+        process.once('SIGTERM', shutdown);
+        await server.closed;
+        process.off('SIGINT', shutdown);
+        process.off('SIGTERM', shutdown);
+        return success('Index daemon stopped.', { stopped: true });
+      }
+      case 'start': {
+        const running = await runtime.getIndexServerStatus({ basePath });
+        if (running !== undefined) {
+          return success(`Index daemon already running (pid ${running.pid}).`, { running: true, ...running });
+        }
+        const child = spawn(
+          process.execPath,
+          [fileURLToPath(new URL('../main.js', import.meta.url)), 'code', 'daemon', 'run', ...(parsed.watch === true ? ['--watch'] : []), '--output-dir', basePath],
+          { detached: true, stdio: 'ignore' },
+        );
+        child.unref();
+        const status = await waitForDaemon(runtime, basePath);
+        if (status === undefined) {
+          return failure(`Index daemon did not start within ${DAEMON_START_TIMEOUT_MS / 1000}s.`);
+        }
+        return success(`Index daemon started (pid ${status.pid}). Socket: ${status.socketPath}`, { running: true, ...status });
+      }
+    }
+  } catch (error) {
+    return failureFromError('manage index daemon', error);
+  }
+}
+
+async function waitForDaemon(runtime: ReturnType<typeof createRuntime>, basePath: string) {
+  const deadline = Date.now() + DAEMON_START_TIMEOUT_MS;
+  while (Date.now() < deadline) {
+    const status = await runtime.getIndexServerStatus({ basePath });
+    if (status !== undefined) {
+      return status;
+    }
+    await new Promise((resolve) => setTimeout(resolve, DAEMON_POLL_INTERVAL_MS));
+  }
+  return undefined;
+}
@@ -0,0 +1,48 @@
+/**
+ * Completions Command
+ *
+ * Print the completion script for a shell, or the completions for a command line, which is what
+ * the scripts ask for on each Tab.
+ *
+ * Usage:
+ *   eval "$(ax completions bash)"
+ *   ax completions fish | source
+ *   ax completions complete "ax session resume "
+ */
+import { COMPLETION_SHELLS, completeCommandLine, formatCompletions, renderCompletionScript, } from '../utils/completions.js';
+import { createRuntime, success, usageError } from '../utils/formatters.js';
+import { resolveWorkflowDir } from './run.js';
+export async function completionsCommand(args, options, definition) {
+    const [target, line] = args;
+    if (target === 'complete') {
+        const completions = await completeCommandLine(line ?? '', definition, (source) => lookupCompletions(source, options));
+        // No output at all, so the shell falls back to its own completion.
+        return completions.length === 0
+            ? { success: true, message: undefined, data: undefined, exitCode: 0 }
+            : success(formatCompletions(completions), { completions });
+    }
+    if (target === undefined || !COMPLETION_SHELLS.includes(target)) {
+        return usageError(`ax completions <${COMPLETION_SHELLS.join('|')}>`);
+    }
+    return success(renderCompletionScript(target), { shell: target });
+}
+async function lookupCompletions(source, options) {
+    const runtime = createRuntime(options);
+    switch (source) {
+        case 'agents':
+            return (await runtime.listAgents()).map((agent) => ({ value: agent.agentId, description: agent.name }));
+        case 'providers':
+            return (await runtime.listProviders()).map((provider) => ({ value: provider }));
+        case 'sessions':
+            // The sessions used last come first.
+            return (await runtime.listSessions())
+                .sort((left, right) => right.updatedAt.localeCompare(left.updatedAt))
+                .map((session) => ({ value: session.sessionId, description: `${session.status}: ${session.task}` }));
+        case 'workflows': {
+            const workflowDir = options.workflowDir ?? resolveWorkflowDir();
+            return workflowDir === undefined
+                ? []
+                : (await runtime.listWorkflows({ workflowDir, basePath: options.outputDir })).map((workflow) => ({ value: workflow.workflowId, description: workflow.name }));
+        }
+    }
+}
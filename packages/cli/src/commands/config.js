@@ -1,4 +1,5 @@
-import { createRuntime, failure, success, usageError } from '../utils/formatters.js';
+import { formatConfigIssue } from '@defai.digital/shared-runtime';
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
 export async function configCommand(args, options) {
     const subcommand = args[0] ?? 'show';
     const runtime = createRuntime(options);
@@ -30,11 +31,53 @@ export async function configCommand(args, options) {
             if (parsed.error !== undefined) {
                 return failure(parsed.error);
             }
-            const config = await runtime.setConfig(path, parsed.value);
-            return success(`Updated config path: ${path}`, config);
+            try {
+                const config = await runtime.setConfig(path, parsed.value);
+                return success(`Updated config path: ${path}`, config);
+            }
+            catch (error) {
+                return failureFromError('update config', error);
+            }
+        }
+        case 'explain': {
+            const path = args[1];
+            if (path === undefined || path.length === 0) {
+                return usageError('ax config explain <path>');
+            }
+            const explanation = await runtime.explainConfig(path);
+            if (explanation.layer === undefined) {
+                return success(`Config value: ${path}\nNot set by any config layer.`, explanation);
+            }
+            return success([
+                `Config value: ${path}`,
+                JSON.stringify(explanation.value, null, 2),
+                `Set by: ${explanation.layer} (${explanation.source})`,
+                '',
+                'Layers, lowest first:',
+                ...explanation.layers.map((layer) => `  ${layer.layer.padEnd(8)} ${layer.source}: ${JSON.stringify(layer.value)}`),
+            ].join('\n'), explanation);
+        }
+        case 'validate': {
+            const validation = await runtime.validateConfig();
+            const lines = [
+                validation.valid ? 'Config is valid.' : 'Config is invalid.',
+                validation.files.length === 0 ? 'No config files found.' : 'Config files, lowest first:',
+                ...validation.files.flatMap((file) => [
+                    `  ${file.layer.padEnd(8)} ${file.source}: ${file.issues.length === 0 ? 'ok' : `${file.issues.length} problem${file.issues.length === 1 ? '' : 's'}`}`,
+                    ...file.migrations.map((migration) => `    migrated: ${migration}`),
+                    ...file.issues.map((issue) => `    - ${formatConfigIssue(issue)}`),
+                ]),
+                ...(validation.overrides.length === 0 ? [] : [
+                    '',
+                    'Profile, environment and --config overrides:',
+                    ...validation.overrides.map((issue) => `  - ${formatConfigIssue(issue)}${issue.layer === undefined ? '' : ` (${issue.layer}: ${issue.source})`}`),
+                ]),
+            ];
+            // A non-zero exit fails a CI step on a config that would break at runtime.
+            return validation.valid ? success(lines.join('\n'), validation) : failure(lines.join('\n'), validation);
         }
         default:
-            return usageError('ax config [show|get|set]');
+            return usageError('ax config [show|get|set|explain|validate]');
     }
 }
 function parseConfigValue(args, input) {
@@ -0,0 +1,78 @@
+import { USAGE_GROUPINGS, type UsageGrouping, type UsageTotals } from '@defai.digital/shared-runtime';
+import type { CLIOptions, CommandResult } from '../types.js';
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
+
+const USAGE = `ax cost [--since <7d|24h|30m|ISO date>] [--by <${USAGE_GROUPINGS.join('|')}>] [--trace-id <id>]`;
+const DEFAULT_SINCE = '7d';
+const DURATION_UNITS_MS: Record<string, number> = {
+  m: 60_000,
+  h: 3_600_000,
+  d: 86_400_000,
+  w: 604_800_000,
+};
+
+export async function costCommand(args: string[], options: CLIOptions): Promise<CommandResult> {
+  let sinceValue = DEFAULT_SINCE;
+  let by: UsageGrouping = 'provider';
+
+  for (let index = 0; index < args.length; index += 1) {
+    const token = args[index]!;
+    const value = args[index + 1];
+    if (token !== '--since' && token !== '--by') {
+      return usageError(USAGE);
+    }
+    if (value === undefined || value.startsWith('--')) {
+      return failure(`Missing value for ${token}.`);
+    }
+    if (token === '--by') {
+      if (!(USAGE_GROUPINGS as readonly string[]).includes(value)) {
+        return failure(`Invalid value for --by: expected one of ${USAGE_GROUPINGS.join(', ')}.`);
+      }
+      by = value as UsageGrouping;
+    } else {
+      sinceValue = value;
+    }
+    index += 1;
+  }
+
+  const since = parseSince(sinceValue, new Date());
+  if (since === undefined) {
+    return failure(`Invalid value for --since: "${sinceValue}". Use a duration such as 7d, 24h or 30m, or an ISO date.`);
+  }
+
+  try {
+    const report = await createRuntime(options).getUsageReport({ since, by, traceId: options.traceId });
+    const scope = `since ${since}${options.traceId !== undefined ? ` for run ${options.traceId}` : ''}`;
+    if (report.total.calls === 0) {
+      return success(`No provider calls recorded ${scope}.`, report);
+    }
+    return success([
+      `Provider usage ${scope}, by ${by}:`,
+      ...report.groups.map((group) => `- ${group.key}: ${formatUsageTotals(group)}`),
+      `Total: ${formatUsageTotals(report.total)}`,
+    ].join('\n'), report);
+  } catch (error) {
+    return failureFromError('report provider usage', error);
+  }
+}
+
+/** One line of calls, tokens, cost and latency, e.g. for a run's summary. */
+export function formatUsageTotals(totals: UsageTotals): string {
+  const parts = [
+    `${totals.calls} call${totals.calls === 1 ? '' : 's'}${totals.failures > 0 ? ` (${totals.failures} failed)` : ''}`,
+    `${totals.inputTokens.toLocaleString('en-US')} in / ${totals.outputTokens.toLocaleString('en-US')} out tokens`,
+    `$${totals.costUsd.toFixed(4)}${totals.unpricedCalls > 0 ? ` + ${totals.unpricedCalls} unpriced` : ''}`,
+    `avg ${totals.averageLatencyMs}ms`,
+  ];
+  return parts.join(', ');
+}
+
+// A duration back from now (`7d`, `24h`, `30m`, `2w`) or an ISO date.
+function parseSince(value: string, now: Date): string | undefined {
+  const duration = /^(\d+)([mhdw])$/.exec(value);
+  if (duration !== null) {
+    return new Date(now.getTime() - Number(duration[1]) * DURATION_UNITS_MS[duration[2]!]!).toISOString();
+  }
+  const parsed = Date.parse(value);
+  return Number.isNaN(parsed) ? undefined : new Date(parsed).toISOString();
+}
@@ -0,0 +1,43 @@
+import packageJson from '../../../../package.json' with { type: 'json' };
+import type { CLIOptions, CommandResult } from '../types.js';
+import { createRuntime, failureFromError, success, usageError } from '../utils/formatters.js';
+
+export async function debugCommand(args: string[], options: CLIOptions): Promise<CommandResult> {
+  const [subcommand, ...rest] = args;
+
+  switch (subcommand) {
+    case undefined:
+    case 'help':
+      return success([
+        'AX Debug',
+        '',
+        'Usage:',
+        '  ax debug bundle [output-path] [--trace-id <trace-id>]',
+        '',
+        'The bundle includes redacted config, logs, the failing trace, provider resolution, and platform info.',
+      ].join('\n'));
+    case 'bundle':
+      if (rest.length > 1 || rest.some((token) => token.startsWith('--'))) {
+        return usageError('ax debug bundle [output-path] [--trace-id <trace-id>]');
+      }
+      try {
+        const result = await createRuntime(options).createDebugBundle({
+          basePath: options.outputDir ?? process.cwd(),
+          traceId: options.traceId,
+          outputPath: rest[0],
+          cliVersion: packageJson.version,
+        });
+        const lines = [
+          `Debug bundle written to ${result.bundlePath} (${result.entries.length} entries, ${result.bytes} bytes).`,
+          result.traceId !== undefined ? `Included trace: ${result.traceId}` : 'No trace included.',
+          ...result.warnings.map((warning) => `Warning: ${warning}`),
+          'Review the archive before attaching it to an issue.',
+        ];
+        return success(lines.join('\n'), result);
+      } catch (error) {
+        return failureFromError('create debug bundle', error);
+      }
+    default:
+      return usageError('ax debug bundle [output-path] [--trace-id <trace-id>]');
+  }
+}
@@ -1,9 +1,14 @@
 import { constants } from 'node:fs';
 import { access, readFile } from 'node:fs/promises';
 import { join } from 'node:path';
-import { createMcpServerSurface } from '@defai.digital/mcp-server';
+import { PassThrough } from 'node:stream';
+import { createMcpServerSurface, createMcpStdioServer } from '@defai.digital/mcp-server';
+import { findExecutable } from '@defai.digital/shared-runtime';
 import { createRuntime, failure, success } from '../utils/formatters.js';
 const REQUIRED_MCP_TOOLS = ['workflow.run', 'trace.list', 'agent.list'];
+// The files `ax init` writes for each MCP client.
+const MCP_CLIENT_CONFIGS = ['.mcp.json', '.automatosx/mcp.json', '.cursor/mcp.json', '.gemini/settings.json', '.ax-grok/settings.json'];
+const MCP_SERVER_ID = 'automatosx';
 export async function doctorCommand(_args, options) {
     const basePath = options.outputDir ?? process.cwd();
     const runtime = createRuntime(options);
@@ -49,7 +54,7 @@ export async function doctorCommand(_args, options) {
         checks.push({
             id: 'workspace-config',
             status: 'ok',
-            message: `Workspace config loaded (${config.defaultProvider ?? 'no default provider set'}).`,
+            message: `Workspace config loaded (${config.providers?.default ?? config.defaultProvider ?? 'no default provider set'}).`,
         });
         const runtimeDirReady = await canAccess(join(basePath, config.runtimeStoreDir), constants.R_OK | constants.W_OK);
         checks.push({
@@ -183,6 +188,17 @@ export async function doctorCommand(_args, options) {
             message: `Shared runtime checks failed: ${message}`,
         });
     }
+    try {
+        checks.push(...(await runtime.diagnoseEnvironment({ basePath })).checks);
+    }
+    catch (error) {
+        const message = error instanceof Error ? error.message : String(error);
+        checks.push({
+            id: 'environment',
+            status: 'fail',
+            message: `Environment checks failed: ${message}`,
+        });
+    }
     try {
         const tools = createMcpServerSurface({ basePath }).listTools();
         const missingTools = REQUIRED_MCP_TOOLS.filter((toolName) => !tools.includes(toolName));
@@ -202,6 +218,11 @@ export async function doctorCommand(_args, options) {
             message: `MCP surface check failed: ${message}`,
         });
     }
+    checks.push(await checkMcpConnection(basePath));
+    const mcpCommandCheck = await checkMcpClientCommand(basePath);
+    if (mcpCommandCheck !== undefined) {
+        checks.push(mcpCommandCheck);
+    }
     const summary = summarizeChecks(checks);
     const overallStatus = summary.fail > 0 ? 'unhealthy' : summary.warn > 0 ? 'warning' : 'healthy';
     const report = renderDoctorReport(basePath, checks, summary);
@@ -213,6 +234,104 @@ export async function doctorCommand(_args, options) {
     };
     return summary.fail > 0 ? failure(report, data) : success(report, data);
 }
+/**
+ * Talks to an MCP server over stdio the way a client does: initialize, list the tools and call
+ * one, so a broken client policy file or a tool that throws shows up here rather than in the client.
+ */
+async function checkMcpConnection(basePath) {
+    const input = new PassThrough();
+    const output = new PassThrough();
+    let received = '';
+    output.on('data', (chunk) => {
+        received += chunk.toString('utf8');
+    });
+    const requests = [
+        { jsonrpc: '2.0', id: 1, method: 'initialize', params: { protocolVersion: '2025-06-18', capabilities: {}, clientInfo: { name: 'ax-doctor', version: '1' } } },
+        { jsonrpc: '2.0', id: 2, method: 'tools/list' },
+        { jsonrpc: '2.0', id: 3, method: 'tools/call', params: { name: 'agent.list', arguments: {} } },
+    ];
+    try {
+        const serving = createMcpStdioServer({ basePath, input, output, audit: false }).serve();
+        input.end(requests.map((request) => `${JSON.stringify(request)}\n`).join(''));
+        await serving;
+        const responses = new Map(received.split('\n').filter((line) => line.trim().length > 0).map((line) => {
+            const response = JSON.parse(line);
+            return [response.id, response];
+        }));
+        const failed = requests.find((request) => {
+            const response = responses.get(request.id);
+            return response === undefined || response.error !== undefined || response.result?.isError === true;
+        });
+        if (failed !== undefined) {
+            const reason = responses.get(failed.id)?.error?.message ?? 'no usable response';
+            return {
+                id: 'mcp-connect',
+                status: 'fail',
+                message: `MCP server did not answer ${failed.method}: ${reason}.`,
+                fix: 'Run "ax mcp serve" in the workspace to see the error, and check .automatosx/mcp-clients.json.',
+            };
+        }
+        return {
+            id: 'mcp-connect',
+            status: 'ok',
+            message: `MCP server answers over stdio (${responses.get(2)?.result?.tools?.length ?? 0} tools listed, agent.list called).`,
+        };
+    }
+    catch (error) {
+        const message = error instanceof Error ? error.message : String(error);
+        return {
+            id: 'mcp-connect',
+            status: 'fail',
+            message: `MCP server could not start: ${message}`,
+            fix: 'Correct the error above; MCP clients cannot connect until the server starts.',
+        };
+    }
+}
+/** Whether the command the MCP client configs start the server with can be found by those clients. */
+async function checkMcpClientCommand(basePath) {
+    const commands = new Map();
+    for (const file of MCP_CLIENT_CONFIGS) {
+        const command = (await readJsonFile(join(basePath, file)))?.mcpServers?.[MCP_SERVER_ID]?.command;
+        if (typeof command === 'string') {
+            commands.set(command, [...(commands.get(command) ?? []), file]);
+        }
+    }
+    if (commands.size === 0) {
+        return undefined;
+    }
+    const missing = [];
+    const shims = [];
+    for (const [command, files] of commands) {
+        const path = await findExecutable(command);
+        if (path === undefined) {
+            missing.push(`"${command}" (${files.join(', ')})`);
+        }
+        else if (process.platform === 'win32' && /\.(?:cmd|bat)$/i.test(path)) {
+            shims.push(`"${command}" (${files.join(', ')})`);
+        }
+    }
+    if (missing.length > 0) {
+        return {
+            id: 'mcp-command',
+            status: 'warn',
+            message: `MCP clients start the server with a command that is not on PATH: ${missing.join(', ')}.`,
+            fix: 'Install the CLI globally (npm install -g @defai.digital/cli), or put the full path to ax in "command".',
+        };
+    }
+    if (shims.length > 0) {
+        return {
+            id: 'mcp-command',
+            status: 'warn',
+            message: `MCP clients start the server with a Windows shim: ${shims.join(', ')}.`,
+            fix: 'Clients that start servers without a shell need "command": "cmd" and "args": ["/c", "ax", "mcp", "serve"].',
+        };
+    }
+    return {
+        id: 'mcp-command',
+        status: 'ok',
+        message: `MCP clients can start the server (${Array.from(commands.keys()).join(', ')}).`,
+    };
+}
 async function canAccess(path, mode) {
     try {
         await access(path, mode);
@@ -245,6 +364,9 @@ function renderDoctorReport(basePath, checks, summary) {
         `Overall status: ${overallStatus}`,
         `Summary: ${summary.ok} ok, ${summary.warn} warning${summary.warn === 1 ? '' : 's'}, ${summary.fail} failure${summary.fail === 1 ? '' : 's'}`,
         '',
-        ...checks.map((check) => `[${check.status.toUpperCase()}] ${check.message}`),
+        ...checks.flatMap((check) => [
+            `[${check.status.toUpperCase()}] ${check.message}`,
+            ...(check.status !== 'ok' && check.fix !== undefined ? [`       Fix: ${check.fix}`] : []),
+        ]),
     ].join('\n');
 }
@@ -0,0 +1,84 @@
+import type { EvalCaseResult, EvalVariantSummary } from '@defai.digital/shared-runtime';
+import type { CLIOptions, CommandResult } from '../types.js';
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
+
+const USAGE = 'ax eval run <suite.yaml> [--agents <id,...>] [--providers <name,...>] [--case <id>]...';
+
+export async function evalCommand(args: string[], options: CLIOptions): Promise<CommandResult> {
+  const [subcommand, file, ...rest] = args;
+  if (subcommand !== 'run' || file === undefined || file.startsWith('--')) {
+    return usageError(USAGE);
+  }
+  let agents: string[] | undefined;
+  let providers = options.provider !== undefined ? [options.provider] : undefined;
+  const cases: string[] = [];
+  for (let index = 0; index < rest.length; index += 1) {
+    const token = rest[index]!;
+    const value = rest[index + 1];
+    if (token !== '--agents' && token !== '--providers' && token !== '--case') {
+      return usageError(USAGE);
+    }
+    if (value === undefined || value.startsWith('--')) {
+      return failure(`Missing value for ${token}.`);
+    }
+    const names = value.split(',').map((name) => name.trim()).filter((name) => name.length > 0);
+    if (token === '--agents') {
+      agents = names;
+    } else if (token === '--providers') {
+      providers = names;
+    } else {
+      cases.push(...names);
+    }
+    index += 1;
+  }
+
+  try {
+    const report = await createRuntime(options).runEval({
+      file,
+      agents,
+      providers,
+      cases,
+      basePath: options.outputDir ?? process.cwd(),
+      surface: 'cli',
+      ...(options.verbose ? { onResult: (result: EvalCaseResult) => logProgress(`${result.caseId} ${describeVariant(result)}: ${result.passed ? 'passed' : 'failed'}`) } : {}),
+    });
+    const failed = report.results.filter((result) => !result.passed);
+    const lines = [
+      `Eval "${report.suite}": ${report.results.length} run(s) of ${new Set(report.results.map((result) => result.caseId)).size} case(s)${report.baseline !== undefined ? `, against the run of ${report.baseline.startedAt}` : ''}.`,
+      '',
+      ...report.variants.map(formatVariant),
+      ...(failed.length > 0 ? ['', 'Failures:', ...failed.map(formatFailure)] : []),
+      '',
+      `Report: ${report.reportFile}`,
+    ];
+    return failed.length === 0 ? success(lines.join('\n'), report) : failure(lines.join('\n'), report);
+  } catch (error) {
+    return failureFromError(`run eval suite "${file}"`, error);
+  }
+}
+
+function formatVariant(variant: EvalVariantSummary): string {
+  const baseline = variant.baseline === undefined
+    ? ''
+    : `; previously ${percent(variant.baseline.passRate)}, $${variant.baseline.costUsd.toFixed(4)}`;
+  return `- ${describeVariant(variant)}: ${variant.passed}/${variant.cases} passed (${percent(variant.passRate)}), $${variant.costUsd.toFixed(4)}, ${variant.totalTokens} tokens, ${variant.averageLatencyMs}ms average${baseline}`;
+}
+
+function formatFailure(result: EvalCaseResult): string {
+  const reasons = result.error !== undefined
+    ? [`the run failed: ${result.error.message ?? result.error.code ?? 'unknown error'}`]
+    : result.assertions.filter((assertion) => !assertion.passed).map((assertion) => `${assertion.assertion}${assertion.detail !== undefined ? ` (${assertion.detail})` : ''}`);
+  return `- ${result.caseId}, ${describeVariant(result)}: ${reasons.join('; ') || 'the run did not succeed'}`;
+}
+
+function describeVariant(variant: { agentId: string; provider?: string }): string {
+  return variant.provider !== undefined ? `${variant.agentId} on ${variant.provider}` : variant.agentId;
+}
+
+function percent(rate: number): string {
+  return `${Math.round(rate * 100)}%`;
+}
+
+function logProgress(message: string): void {
+  process.stderr.write(`[${new Date().toISOString()}] ${message}\n`);
+}
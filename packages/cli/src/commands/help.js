@@ -5,7 +5,7 @@ export const RETAINED_COMMANDS = [
     { command: 'init', description: 'Create project context files and local MCP metadata for AI-tool integration.' },
     { command: 'doctor', description: 'Validate workspace, workflow, and shared runtime readiness.' },
     { command: 'status', description: 'Show active sessions, running traces, and provider/runtime readiness.' },
-    { command: 'config', description: 'Inspect or update workspace config used by the runtime and provider bridge.' },
+    { command: 'config', description: 'Inspect, update, explain and validate workspace config used by the runtime and provider bridge.' },
     { command: 'cleanup', description: 'Auto-close stale sessions and traces from shared runtime storage.' },
     { command: 'resume', description: 'Rerun a prior workflow or discussion trace from stored execution context.' },
     { command: 'call', description: 'Call a provider directly through the shared runtime bridge.' },
@@ -16,15 +16,33 @@ export const RETAINED_COMMANDS = [
 export const ADVANCED_COMMANDS = [
     { command: 'ability', description: 'List built-in runtime abilities or inject matched ability context for a task.' },
     { command: 'feedback', description: 'Capture operator feedback and inspect aggregate agent feedback signals.' },
+    { command: 'cache', description: 'Show provider response cache entries, hits, and tokens saved, or clear the cache.' },
+    { command: 'cost', description: 'Report provider tokens, latency, and cost by agent, provider, or project over a time window.' },
     { command: 'guard', description: 'List, apply, and evaluate workflow guard policies.' },
+    { command: 'workflow', description: 'Run YAML pipelines of agent stages with conditions, loops over files, retries, and artifacts.' },
+    { command: 'eval', description: 'Run eval suites of agent tasks with assertions and compare pass rates and costs across agents and providers.' },
+    { command: 'replay', description: 'Replay a recorded agent run or pipeline offline and report divergences from the recording.' },
     { command: 'agent', description: 'Inspect or register agents through the shared runtime state store.' },
     { command: 'mcp', description: 'Inspect available MCP tools or invoke them through the local MCP surface.' },
-    { command: 'session', description: 'Create and manage collaboration sessions through shared runtime state.' },
+    { command: 'session', description: 'Create, resume, branch and export sessions whose conversation, scope and memory namespace carry across runs, with a scratchpad where parallel runs claim tasks.' },
     { command: 'review', description: 'Run deterministic v14-native code review heuristics with durable artifacts.' },
+    { command: 'code', description: 'Build and query the workspace symbol index for Dockerfiles, shell scripts, Terraform, Ruby, PHP, and C#.' },
+    { command: 'outline', description: 'Print the symbol tree of files or a crate with signatures and doc lines, or as Markdown.' },
+    { command: 'search', description: 'Fuzzy-search indexed symbols, grep file contents, or search both and memory at once.' },
+    { command: 'attach', description: 'Follow the live log of a run started with ax run --detach.' },
+    { command: 'ask', description: 'Answer project questions with grounded file references from the symbol index and module graph.' },
+    { command: 'test', description: 'Find and run only the tests impacted by the current changes.' },
+    { command: 'hook', description: 'Install a git pre-commit hook for syntax, secret, and quick review checks.' },
+    { command: 'debug', description: 'Package a redacted debug bundle (config, logs, failing trace, providers, platform) for issues.' },
+    { command: 'maintain', description: 'Run workspace maintenance: index refresh, memory expiry, log rotation, provider checks, cache pruning.' },
+    { command: 'memory', description: 'List scoped memory and facts, find stale memory, manage namespaces, prune by retention policy, export or import archives, and encrypt at rest.' },
     { command: 'history', description: 'View past workflow run history from the trace store.' },
     { command: 'iterate', description: 'Repeat a command until success, iteration budget, or time budget is exhausted.' },
     { command: 'monitor', description: 'Launch a local HTTP dashboard showing sessions, traces, and agents.' },
-    { command: 'scaffold', description: 'Generate contract-first components: schemas, domain packages, guard policies.' },
+    { command: 'tui', description: 'Watch a run full-screen: stages, streaming output, cost meters, approvals and the symbol index.' },
+    { command: 'chat', description: 'Chat with an agent without restarting: attach files, switch agents, search memory, and diff or undo the last reply.' },
+    { command: 'completions', description: 'Print bash, zsh, fish or PowerShell completion that also completes agent, provider and session names.' },
+    { command: 'scaffold', description: 'Generate contract-first components: schemas, domain packages, guard policies, interface stubs.' },
     { command: 'update', description: 'Check for CLI updates and optionally install the latest version.' },
 ];
 export const WORKFLOW_FIRST_QUICKSTART = [
@@ -45,6 +63,7 @@ export const WORKFLOW_FIRST_QUICKSTART = [
     '  2. Start with one of the five workflow commands above.',
     '  3. Use --dry-run to preview artifacts without runtime side effects.',
     '  4. Inspect manifest.json, summary.json, and artifact markdown in .automatosx/workflows/.',
+    '  5. Use --read-only for analysis-only sessions: write tools, shell execution, and git mutations are refused.',
     '',
     'Retained high-value commands:',
     '  ax doctor',
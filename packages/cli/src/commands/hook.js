@@ -0,0 +1,92 @@
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
+const HOOK_USAGE = 'ax hook install [--force] | ax hook uninstall | ax hook run pre-commit [--mode block|annotate]';
+export async function hookCommand(args, options) {
+    const [subcommand, ...rest] = args;
+    const basePath = options.outputDir ?? process.cwd();
+    switch (subcommand) {
+        case undefined:
+        case 'help':
+            return success([
+                'AX Git Hooks',
+                '',
+                'Usage:',
+                '  ax hook install [--force]',
+                '  ax hook uninstall',
+                '  ax hook run pre-commit [--mode block|annotate]',
+                '',
+                'Configure checks in .automatosx/config.json under hooks.preCommit:',
+                '  { "mode": "block", "syntax": true, "secrets": true, "review": { "enabled": false, "agent": "quality", "timeBudgetMs": 30000 } }',
+                'The review asks the agent about the staged diff and cancels its run once timeBudgetMs is spent.',
+            ].join('\n'));
+        case 'install': {
+            const unknown = rest.find((token) => token !== '--force');
+            if (unknown !== undefined) {
+                return usageError(HOOK_USAGE);
+            }
+            try {
+                const result = await createRuntime(options).installHook({ basePath, force: rest.includes('--force') });
+                const backup = result.backupPath !== undefined ? ` Existing hook backed up to ${result.backupPath}.` : '';
+                return success(`Installed pre-commit hook at ${result.hookPath}.${backup}`, result);
+            }
+            catch (error) {
+                return failureFromError('install hook', error);
+            }
+        }
+        case 'uninstall': {
+            if (rest.length > 0) {
+                return usageError(HOOK_USAGE);
+            }
+            try {
+                const result = await createRuntime(options).uninstallHook({ basePath });
+                return success(`Removed AutomatosX pre-commit hook from ${result.hookPath}.`, result);
+            }
+            catch (error) {
+                return failureFromError('uninstall hook', error);
+            }
+        }
+        case 'run':
+            return runHook(rest, basePath, options);
+        default:
+            return usageError(HOOK_USAGE);
+    }
+}
+async function runHook(args, basePath, options) {
+    const [hookName, ...flags] = args;
+    if (hookName !== 'pre-commit') {
+        return usageError('ax hook run pre-commit [--mode block|annotate]');
+    }
+    let mode;
+    for (let index = 0; index < flags.length; index += 1) {
+        const token = flags[index];
+        if (token === '--mode') {
+            const value = flags[index + 1];
+            if (value !== 'block' && value !== 'annotate') {
+                return failure('Hook mode must be one of: block, annotate.');
+            }
+            mode = value;
+            index += 1;
+        }
+        else {
+            return failure(`Unknown hook flag: ${token}.`);
+        }
+    }
+    let result;
+    try {
+        result = await createRuntime(options).runPreCommitHook({ basePath, mode, sessionId: options.sessionId });
+    }
+    catch (error) {
+        return failureFromError('run pre-commit hook', error);
+    }
+    const checkSummary = Object.entries(result.checks)
+        .map(([check, status]) => `${check}=${status}`)
+        .join(', ');
+    const lines = [
+        `Pre-commit checks (${result.mode}) on ${result.files.length} staged file(s): ${checkSummary}.`,
+        ...result.issues.map((issue) => `- [${issue.severity}] ${issue.check} ${issue.file}${issue.line !== undefined ? `:${issue.line}` : ''} ${issue.message}`),
+    ];
+    if (result.blocked) {
+        lines.push('Commit blocked. Fix the errors above, set hooks.preCommit.mode to "annotate", or bypass with git commit --no-verify.');
+        return failure(lines.join('\n'), result);
+    }
+    return success(lines.join('\n'), result);
+}
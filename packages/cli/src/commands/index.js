@@ -1,4 +1,5 @@
 export { runCommand } from './run.js';
+export { workflowCommand } from './workflow.js';
 export { setupCommand, ensureWorkspaceSetup } from './setup.js';
 export { initCommand } from './init.js';
 export { doctorCommand } from './doctor.js';
@@ -7,20 +8,37 @@ export { cleanupCommand } from './cleanup.js';
 export { callCommand } from './call.js';
 export { abilityCommand } from './ability.js';
 export { listCommand } from './list.js';
+export { maintainCommand } from './maintain.js';
+export { memoryCommand } from './memory.js';
 export { statusCommand } from './status.js';
 export { traceCommand } from './trace.js';
 export { discussCommand } from './discuss.js';
 export { feedbackCommand } from './feedback.js';
+export { costCommand } from './cost.js';
+export { cacheCommand } from './cache.js';
 export { guardCommand } from './guard.js';
 export { resumeCommand } from './resume.js';
+export { replayCommand } from './replay.js';
 export { agentCommand } from './agent.js';
 export { mcpCommand } from './mcp.js';
 export { sessionCommand } from './session.js';
 export { reviewCommand } from './review.js';
+export { codeCommand } from './code.js';
+export { outlineCommand } from './outline.js';
+export { searchCommand } from './search.js';
+export { askCommand } from './ask.js';
+export { testCommand } from './test.js';
+export { attachCommand } from './attach.js';
+export { hookCommand } from './hook.js';
+export { debugCommand } from './debug.js';
+export { evalCommand } from './eval.js';
 export { shipCommand, architectCommand, auditCommand, qaCommand, releaseCommand, WORKFLOW_COMMAND_DEFINITIONS, getWorkflowCommandDefinition, } from './workflows.js';
 export { helpCommand, WORKFLOW_FIRST_QUICKSTART } from './help.js';
 export { historyCommand } from './history.js';
 export { iterateCommand } from './iterate.js';
 export { monitorCommand } from './monitor.js';
+export { tuiCommand } from './tui.js';
+export { chatCommand } from './chat.js';
+export { completionsCommand } from './completions.js';
 export { scaffoldCommand } from './scaffold.js';
 export { updateCommand } from './update.js';
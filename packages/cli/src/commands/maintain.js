@@ -0,0 +1,48 @@
+import { MAINTENANCE_TASKS } from '@defai.digital/shared-runtime';
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
+const USAGE = `ax maintain [--only <${MAINTENANCE_TASKS.join('|')}>[,...]] [--if-due] [--dry-run]`;
+export async function maintainCommand(args, options) {
+    let ifDue = false;
+    let tasks;
+    for (let index = 0; index < args.length; index += 1) {
+        const token = args[index];
+        if (token === '--if-due') {
+            ifDue = true;
+        }
+        else if (token === '--only') {
+            const value = args[index + 1];
+            if (value === undefined || value.startsWith('--')) {
+                return failure('Missing value for --only.');
+            }
+            const requested = value.split(',').map((task) => task.trim()).filter((task) => task.length > 0);
+            const unknown = requested.filter((task) => !MAINTENANCE_TASKS.includes(task));
+            if (unknown.length > 0) {
+                return failure(`Unknown maintenance task(s): ${unknown.join(', ')}. Expected: ${MAINTENANCE_TASKS.join(', ')}.`);
+            }
+            tasks = requested;
+            index += 1;
+        }
+        else {
+            return usageError(USAGE);
+        }
+    }
+    try {
+        const result = await createRuntime(options).runMaintenance({
+            tasks,
+            dryRun: options.dryRun === true,
+            ifDue,
+            basePath: options.outputDir ?? process.cwd(),
+        });
+        if (result.skipped !== undefined) {
+            return success(`${result.skipped} Last run: ${result.lastRunAt}.`, result);
+        }
+        const lines = [
+            `Maintenance ${result.dryRun ? 'dry run' : 'run'}${result.traceId !== undefined ? ` (trace ${result.traceId})` : ''}:`,
+            ...result.tasks.map((task) => `- ${task.task}: ${task.summary}`),
+        ];
+        return result.success ? success(lines.join('\n'), result) : failure(lines.join('\n'), result);
+    }
+    catch (error) {
+        return failureFromError('run maintenance', error);
+    }
+}
@@ -1,10 +1,12 @@
-import { createMcpServerSurface, createMcpStdioServer } from '@defai.digital/mcp-server';
+import { createMcpServerSurface, createMcpStdioServer, startMcpHttpServer } from '@defai.digital/mcp-server';
 import { failure, success, usageError } from '../utils/formatters.js';
 import { parseOptionalJsonInput } from '../utils/validation.js';
+// Read when --token is not given, so the token stays out of the process list.
+const MCP_TOKEN_ENV_VAR = 'AUTOMATOSX_MCP_TOKEN';
 export async function mcpCommand(args, options) {
     const subcommand = args[0] ?? 'tools';
     const basePath = options.outputDir ?? process.cwd();
-    const surface = createMcpServerSurface({ basePath });
+    const surface = createMcpServerSurface({ basePath, readOnly: options.readOnly });
     switch (subcommand) {
         case 'tools': {
             const tools = surface.listToolDefinitions();
@@ -58,7 +60,7 @@ export async function mcpCommand(args, options) {
             ].join('\n'), resource);
         }
         case 'prompts': {
-            const prompts = surface.listPrompts();
+            const prompts = await surface.listPrompts();
             const lines = [
                 'Available MCP prompts:',
                 ...prompts.map((prompt) => `- ${prompt.name}: ${prompt.description}`),
@@ -89,9 +91,44 @@ export async function mcpCommand(args, options) {
             ].join('\n'), prompt);
         }
         case 'serve': {
-            const server = createMcpStdioServer({ basePath });
-            await server.serve();
-            return success('MCP stdio server closed.');
+            const parsed = parseServeArgs(args.slice(1));
+            if (parsed.error !== undefined) {
+                return failure(parsed.error);
+            }
+            if (!parsed.http) {
+                const server = createMcpStdioServer({ basePath, readOnly: options.readOnly, client: parsed.client });
+                try {
+                    await server.serve();
+                }
+                catch (error) {
+                    return failure(`MCP stdio server failed to start: ${error instanceof Error ? error.message : String(error)}`);
+                }
+                return success('MCP stdio server closed.');
+            }
+            let server;
+            try {
+                server = await startMcpHttpServer({
+                    basePath,
+                    readOnly: options.readOnly,
+                    host: parsed.host,
+                    port: parsed.port,
+                    token: parsed.token ?? process.env[MCP_TOKEN_ENV_VAR],
+                    allowedOrigins: parsed.allowedOrigins,
+                });
+            }
+            catch (error) {
+                return failure(`MCP HTTP server failed to start: ${error instanceof Error ? error.message : String(error)}`);
+            }
+            process.stderr.write(`MCP server listening on ${server.url} (legacy SSE clients: ${server.url.replace(/\/mcp$/, '/sse')})\n`);
+            const shutdown = () => {
+                void server.close();
+            };
+            process.once('SIGINT', shutdown);
+            process.once('SIGTERM', shutdown);
+            await server.closed;
+            process.off('SIGINT', shutdown);
+            process.off('SIGTERM', shutdown);
+            return success('MCP HTTP server closed.', { url: server.url });
         }
         case 'call':
         case 'invoke': {
@@ -113,3 +150,47 @@ export async function mcpCommand(args, options) {
             return usageError('ax mcp [tools|describe|resources|read|prompts|prompt|call|serve]');
     }
 }
+function parseServeArgs(args) {
+    const parsed = { http: false };
+    for (let index = 0; index < args.length; index += 1) {
+        const token = args[index];
+        if (token === '--http') {
+            parsed.http = true;
+            continue;
+        }
+        if (token !== '--host' && token !== '--port' && token !== '--token' && token !== '--allow-origin' && token !== '--client') {
+            return { ...parsed, error: `Unknown mcp serve option: ${token}.` };
+        }
+        const value = args[index + 1];
+        if (value === undefined || value.startsWith('--')) {
+            return { ...parsed, error: `Missing value for ${token}.` };
+        }
+        index += 1;
+        if (token === '--port') {
+            const port = Number.parseInt(value, 10);
+            if (!Number.isInteger(port) || port < 0 || port > 65535) {
+                return { ...parsed, error: 'MCP port must be an integer from 0 to 65535.' };
+            }
+            parsed.port = port;
+        }
+        else if (token === '--host') {
+            parsed.host = value;
+        }
+        else if (token === '--token') {
+            parsed.token = value;
+        }
+        else if (token === '--client') {
+            parsed.client = value;
+        }
+        else {
+            parsed.allowedOrigins = [...parsed.allowedOrigins ?? [], value];
+        }
+    }
+    if (!parsed.http && (parsed.host !== undefined || parsed.port !== undefined || parsed.token !== undefined || parsed.allowedOrigins !== undefined)) {
+        return { ...parsed, error: '--host, --port, --token and --allow-origin need --http.' };
+    }
+    if (parsed.http && parsed.client !== undefined) {
+        return { ...parsed, error: '--client applies to stdio; over HTTP the bearer token picks the client.' };
+    }
+    return parsed;
+}
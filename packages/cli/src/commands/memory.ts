@@ -0,0 +1,331 @@
+import { createMemoryKey, MEMORY_KEY_ENV_VAR } from '@defai.digital/shared-runtime';
+import type { CLIOptions, CommandResult } from '../types.js';
+import { createRuntime, failureFromError, success, usageError } from '../utils/formatters.js';
+
+const MEMORY_LIST_USAGE = 'ax memory list [namespace] [--project] [--agent <name>]';
+const MEMORY_FACTS_USAGE = 'ax memory facts [namespace] [--subject <s>] [--predicate <p>] [--object <o>] [--project] [--agent <name>]';
+const MEMORY_STALE_USAGE = 'ax memory stale [namespace] [--project] [--agent <name>] [--invalidate]';
+const MEMORY_NAMESPACES_USAGE = 'ax memory namespaces';
+const MEMORY_COPY_USAGE = 'ax memory copy <source> <target> [--overwrite]';
+const MEMORY_MERGE_USAGE = 'ax memory merge <source>... --into <target>';
+const MEMORY_PRUNE_USAGE = 'ax memory prune [--dry-run]';
+const MEMORY_EXPORT_USAGE = 'ax memory export <file.axm> [namespace...] [--project] [--agent <name>] [--no-embeddings]';
+const MEMORY_IMPORT_USAGE = 'ax memory import <file.axm> [--overwrite]';
+const MEMORY_KEY_USAGE = 'ax memory key [--keychain]';
+const MEMORY_ENCRYPT_USAGE = 'ax memory encrypt';
+const EVICTION_REASONS = {
+  expired: 'expired',
+  age: 'not updated within memoryMaxAgeDays',
+  importance: 'importance decayed below memoryMinImportance',
+  capacity: 'least recently used over the size cap',
+} as const;
+const FACT_FIELD_FLAGS: Record<string, 'subject' | 'predicate' | 'object'> = {
+  '--subject': 'subject',
+  '--predicate': 'predicate',
+  '--object': 'object',
+};
+
+export async function memoryCommand(args: string[], options: CLIOptions): Promise<CommandResult> {
+  const [subcommand, ...rest] = args;
+  const basePath = options.outputDir ?? process.cwd();
+  switch (subcommand) {
+    case undefined:
+    case 'help':
+      return success([
+        'AX Memory',
+        '',
+        'Usage:',
+        `  ${MEMORY_LIST_USAGE}`,
+        `  ${MEMORY_FACTS_USAGE}`,
+        `  ${MEMORY_STALE_USAGE}`,
+        `  ${MEMORY_NAMESPACES_USAGE}`,
+        `  ${MEMORY_COPY_USAGE}`,
+        `  ${MEMORY_MERGE_USAGE}`,
+        `  ${MEMORY_PRUNE_USAGE}`,
+        `  ${MEMORY_EXPORT_USAGE}`,
+        `  ${MEMORY_IMPORT_USAGE}`,
+        `  ${MEMORY_KEY_USAGE}`,
+        `  ${MEMORY_ENCRYPT_USAGE}`,
+        '',
+        'Entries stored without a scope are shared by every project and agent using this store.',
+        '--project narrows to the current workspace and --agent to one agent; together, to that agent in this workspace.',
+        'Scoped namespaces are stored as @project:<name>-<hash>/@agent:<name>/<namespace>, the names copy and merge take.',
+        'facts lists the subject-predicate-object facts agents stored with fact.store, matching each given field exactly.',
+        'stale lists entries whose source file changed or was deleted since they were stored; --invalidate deletes them.',
+        'merge keeps the most recently updated entry for each key and deletes the sources.',
+        'prune applies the memory retention settings under "maintenance" in .automatosx/config.json.',
+        'import keeps an existing entry unless the archived one is newer, or --overwrite is given; project-scoped entries move to this workspace.',
+        `key creates an encryption key for ${MEMORY_KEY_ENV_VAR}, or saves one to the OS keychain; encrypt then encrypts memory stored before "memory": { "encryption" } was set.`,
+        'Encryption covers entry values, embeddings and semantic content; keys, namespaces, tags, sources and facts stay plaintext, as they are what memory is looked up by.',
+      ].join('\n'));
+    case 'list':
+      return listMemory(rest, options, basePath);
+    case 'facts':
+      return listFacts(rest, options, basePath);
+    case 'stale':
+      return staleMemory(rest, options, basePath);
+    case 'namespaces': {
+      if (rest.length > 0) {
+        return usageError(MEMORY_NAMESPACES_USAGE);
+      }
+      try {
+        const namespaces = await createRuntime(options).listMemoryNamespaces();
+        if (namespaces.length === 0) {
+          return success('No memory stored.', namespaces);
+        }
+        return success(namespaces.map((stats) => [
+          `- ${stats.namespace}: ${stats.entries} entr${stats.entries === 1 ? 'y' : 'ies'}`,
+          stats.embedded > 0 ? `, ${stats.embedded} embedded` : '',
+          stats.lastUpdatedAt !== undefined ? `, updated ${stats.lastUpdatedAt}` : '',
+        ].join('')).join('\n'), namespaces);
+      } catch (error) {
+        return failureFromError('list memory namespaces', error);
+      }
+    }
+    case 'copy': {
+      const overwrite = rest.includes('--overwrite');
+      const names = rest.filter((token) => token !== '--overwrite');
+      if (names.length !== 2 || names.some((token) => token.startsWith('--'))) {
+        return usageError(MEMORY_COPY_USAGE);
+      }
+      try {
+        const result = await createRuntime(options).copyMemoryNamespace({ source: names[0]!, target: names[1]!, overwrite });
+        return success(`Copied ${result.copied} entr${result.copied === 1 ? 'y' : 'ies'} from ${names[0]} to ${names[1]}${result.skipped > 0 ? `; kept ${result.skipped} the target already held` : ''}.`, result);
+      } catch (error) {
+        return failureFromError('copy memory namespace', error);
+      }
+    }
+    case 'merge': {
+      const into = rest.indexOf('--into');
+      const target = rest[into + 1];
+      const sources = rest.filter((_token, index) => index !== into && index !== into + 1);
+      if (into < 0 || target === undefined || target.startsWith('--') || sources.length === 0 || sources.some((token) => token.startsWith('--'))) {
+        return usageError(MEMORY_MERGE_USAGE);
+      }
+      try {
+        const result = await createRuntime(options).mergeMemoryNamespaces({ sources, target });
+        return success(`Merged ${result.copied} entr${result.copied === 1 ? 'y' : 'ies'} into ${target}${result.skipped > 0 ? `; ${result.skipped} older duplicate(s) dropped` : ''}; deleted ${sources.join(', ')}.`, result);
+      } catch (error) {
+        return failureFromError('merge memory namespaces', error);
+      }
+    }
+    case 'prune': {
+      if (rest.length > 0) {
+        return usageError(MEMORY_PRUNE_USAGE);
+      }
+      try {
+        const result = await createRuntime(options).pruneMemory({ basePath, dryRun: options.dryRun === true });
+        if (result.evicted.length === 0) {
+          return success('No memory entries to prune.', result);
+        }
+        return success([
+          `${result.dryRun ? 'Would evict' : 'Evicted'} ${result.evicted.length} memory entr${result.evicted.length === 1 ? 'y' : 'ies'}:`,
+          ...result.evicted.map((entry) => `- ${entry.namespace ?? 'default'}/${entry.key}: ${EVICTION_REASONS[entry.reason]}${entry.importance !== undefined ? ` (importance ${entry.importance.toFixed(2)})` : ''}, last used ${entry.lastUsedAt}`),
+        ].join('\n'), result);
+      } catch (error) {
+        return failureFromError('prune memory', error);
+      }
+    }
+    case 'export':
+      return exportMemory(rest, options, basePath);
+    case 'import': {
+      const overwrite = rest.includes('--overwrite');
+      const files = rest.filter((token) => token !== '--overwrite');
+      if (files.length !== 1 || files[0]!.startsWith('--')) {
+        return usageError(MEMORY_IMPORT_USAGE);
+      }
+      try {
+        const result = await createRuntime(options).importMemory({ path: files[0]!, overwrite, basePath });
+        return success(`Imported ${result.imported} of ${result.manifest.entries} memory entr${result.manifest.entries === 1 ? 'y' : 'ies'} from ${result.path}${result.skipped > 0 ? `; kept ${result.skipped} local entr${result.skipped === 1 ? 'y' : 'ies'} at least as new` : ''}.`, result);
+      } catch (error) {
+        return failureFromError('import memory', error);
+      }
+    }
+    case 'key': {
+      const keychain = rest.includes('--keychain');
+      if (rest.some((token) => token !== '--keychain')) {
+        return usageError(MEMORY_KEY_USAGE);
+      }
+      try {
+        const result = createMemoryKey({ keychain });
+        return success(keychain
+          ? 'Saved a new memory key to the OS keychain. Set "memory": { "encryption": "keychain" } in .automatosx/config.json to use it.'
+          : [
+            `export ${MEMORY_KEY_ENV_VAR}=${result.key}`,
+            '',
+            'Keep this key somewhere safe: memory encrypted with it cannot be read without it.',
+            'Set "memory": { "encryption": "env" } in .automatosx/config.json to use it.',
+          ].join('\n'), keychain ? { keychain } : result);
+      } catch (error) {
+        return failureFromError('create memory key', error);
+      }
+    }
+    case 'encrypt': {
+      if (rest.length > 0) {
+        return usageError(MEMORY_ENCRYPT_USAGE);
+      }
+      try {
+        const result = await createRuntime(options).encryptMemory();
+        return success(result.encrypted === 0
+          ? 'All memory is already encrypted.'
+          : `Encrypted ${result.encrypted} memory entr${result.encrypted === 1 ? 'y' : 'ies'}.`, result);
+      } catch (error) {
+        return failureFromError('encrypt memory', error);
+      }
+    }
+    default:
+      return usageError([
+        MEMORY_LIST_USAGE,
+        MEMORY_FACTS_USAGE,
+        MEMORY_STALE_USAGE,
+        MEMORY_NAMESPACES_USAGE,
+        MEMORY_COPY_USAGE,
+        MEMORY_MERGE_USAGE,
+        MEMORY_PRUNE_USAGE,
+        MEMORY_EXPORT_USAGE,
+        MEMORY_IMPORT_USAGE,
+        MEMORY_KEY_USAGE,
+        MEMORY_ENCRYPT_USAGE,
+      ].join('\n       '));
+  }
+}
+
+async function listMemory(args: string[], options: CLIOptions, basePath: string): Promise<CommandResult> {
+  let namespace: string | undefined;
+  let project = false;
+  for (const token of args) {
+    if (token === '--project') {
+      project = true;
+    } else if (token.startsWith('--') || namespace !== undefined) {
+      return usageError(MEMORY_LIST_USAGE);
+    } else {
+      namespace = token;
+    }
+  }
+
+  try {
+    const entries = (await createRuntime(options).listMemory(namespace, memoryScope(project, options.agent, basePath))).slice(0, options.limit);
+    if (entries.length === 0) {
+      return success('No memory entries.', entries);
+    }
+    return success(entries.map((entry) => {
+      const cited = entry.source?.file !== undefined ? ` (${citeSpan({ ...entry.source, file: entry.source.file })})` : '';
+      return `- ${entry.namespace ?? 'default'}/${entry.key}: ${summarizeValue(entry.value)}${cited}`;
+    }).join('\n'), entries);
+  } catch (error) {
+    return failureFromError('list memory', error);
+  }
+}
+
+async function listFacts(args: string[], options: CLIOptions, basePath: string): Promise<CommandResult> {
+  const query: { subject?: string; predicate?: string; object?: string; namespace?: string } = {};
+  let project = false;
+  for (let index = 0; index < args.length; index += 1) {
+    const token = args[index]!;
+    const field = FACT_FIELD_FLAGS[token];
+    const value = args[index + 1];
+    if (token === '--project') {
+      project = true;
+    } else if (field !== undefined && value !== undefined && !value.startsWith('--')) {
+      query[field] = value;
+      index += 1;
+    } else if (token.startsWith('--') || query.namespace !== undefined) {
+      return usageError(MEMORY_FACTS_USAGE);
+    } else {
+      query.namespace = token;
+    }
+  }
+
+  try {
+    const facts = await createRuntime(options).queryFacts({ ...query, limit: options.limit, scope: memoryScope(project, options.agent, basePath) });
+    if (facts.length === 0) {
+      return success('No facts.', facts);
+    }
+    return success(facts.map((fact) => `- ${fact.subject} ${fact.predicate} ${String(fact.object)}${fact.sourceSpan !== undefined ? ` (${citeSpan(fact.sourceSpan)})` : ''}`).join('\n'), facts);
+  } catch (error) {
+    return failureFromError('list facts', error);
+  }
+}
+
+async function staleMemory(args: string[], options: CLIOptions, basePath: string): Promise<CommandResult> {
+  let namespace: string | undefined;
+  let project = false;
+  let invalidate = false;
+  for (const token of args) {
+    if (token === '--project') {
+      project = true;
+    } else if (token === '--invalidate') {
+      invalidate = true;
+    } else if (token.startsWith('--') || namespace !== undefined) {
+      return usageError(MEMORY_STALE_USAGE);
+    } else {
+      namespace = token;
+    }
+  }
+
+  try {
+    const result = await createRuntime(options).checkMemorySources({ namespace, scope: memoryScope(project, options.agent, basePath), basePath, invalidate });
+    if (result.stale.length === 0) {
+      return success(`No stale memory: the sources of ${result.checked} entr${result.checked === 1 ? 'y' : 'ies'} are unchanged.`, result);
+    }
+    return success([
+      `${invalidate ? 'Invalidated' : 'Found'} ${result.stale.length} stale memory entr${result.stale.length === 1 ? 'y' : 'ies'} of ${result.checked} with a source file:`,
+      ...result.stale.map((entry) => `- ${entry.namespace ?? 'default'}/${entry.key}: ${citeSpan({ ...entry.source, file: entry.source.file! })} ${entry.reason === 'missing' ? 'is gone' : 'changed'}`),
+    ].join('\n'), result);
+  } catch (error) {
+    return failureFromError('check memory sources', error);
+  }
+}
+
+async function exportMemory(args: string[], options: CLIOptions, basePath: string): Promise<CommandResult> {
+  const positional: string[] = [];
+  let project = false;
+  let includeEmbeddings = true;
+  for (const token of args) {
+    if (token === '--project') {
+      project = true;
+    } else if (token === '--no-embeddings') {
+      includeEmbeddings = false;
+    } else if (token.startsWith('--')) {
+      return usageError(MEMORY_EXPORT_USAGE);
+    } else {
+      positional.push(token);
+    }
+  }
+  const [path, ...namespaces] = positional;
+  if (path === undefined) {
+    return usageError(MEMORY_EXPORT_USAGE);
+  }
+
+  try {
+    const result = await createRuntime(options).exportMemory({
+      path,
+      namespaces,
+      scope: memoryScope(project, options.agent, basePath),
+      includeEmbeddings,
+      basePath,
+    });
+    const { entries, embedded, namespaces: exported } = result.manifest;
+    return success(`Exported ${entries} memory entr${entries === 1 ? 'y' : 'ies'}${embedded > 0 ? ` (${embedded} with embeddings)` : ''} from ${exported.length} namespace(s) to ${result.path}.`, result);
+  } catch (error) {
+    return failureFromError('export memory', error);
+  }
+}
+
+function memoryScope(project: boolean, agent: string | undefined, basePath: string): { project?: string; agent?: string } | undefined {
+  return project || agent !== undefined
+    ? { ...(project ? { project: basePath } : {}), ...(agent !== undefined ? { agent } : {}) }
+    : undefined;
+}
+
+function citeSpan(span: { file: string; startLine?: number; endLine?: number }): string {
+  if (span.startLine === undefined) {
+    return span.file;
+  }
+  return `${span.file}:${span.startLine}${span.endLine !== undefined && span.endLine !== span.startLine ? `-${span.endLine}` : ''}`;
+}
+
+function summarizeValue(value: unknown): string {
+  const text = typeof value === 'string' ? value : JSON.stringify(value) ?? String(value);
+  return text.length > 80 ? `${text.slice(0, 77)}...` : text;
+}
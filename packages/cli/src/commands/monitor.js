@@ -173,15 +173,16 @@ export async function monitorCommand(args, options) {
         return failure(err instanceof Error ? err.message : String(err));
     }
     const url = `http://localhost:${result.port}`;
-    console.log(`\nAutomatosX Monitor running at: ${url}`);
-    console.log('Localhost access only. Press Ctrl+C to stop.\n');
+    // On stderr, so stdout carries only the result, as --json needs.
+    console.error(`\nAutomatosX Monitor running at: ${url}`);
+    console.error('Localhost access only. Press Ctrl+C to stop.\n');
     if (!noOpen) {
         const { exec } = await import('node:child_process');
         const open = process.platform === 'darwin' ? 'open' : process.platform === 'win32' ? 'start' : 'xdg-open';
         exec(`${open} ${url}`);
     }
     const shutdown = () => {
-        console.log('\nShutting down monitor...');
+        console.error('\nShutting down monitor...');
         result.server.close();
         process.exit(0);
     };
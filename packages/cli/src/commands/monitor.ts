@@ -189,8 +189,9 @@ export async function monitorCommand(args: string[], options: CLIOptions): Promi
   }
 
   const url = `http://localhost:${result.port}`;
-  console.log(`\nAutomatosX Monitor running at: ${url}`);
-  console.log('Localhost access only. Press Ctrl+C to stop.\n');
+  // On stderr, so stdout carries only the result, as --json needs.
+  console.error(`\nAutomatosX Monitor running at: ${url}`);
+  console.error('Localhost access only. Press Ctrl+C to stop.\n');
 
   if (!noOpen) {
     const { exec } = await import('node:child_process');
@@ -199,7 +200,7 @@ export async function monitorCommand(args: string[], options: CLIOptions): Promi
   }
 
   const shutdown = (): void => {
-    console.log('\nShutting down monitor...');
+    console.error('\nShutting down monitor...');
     result.server.close();
     process.exit(0);
   };
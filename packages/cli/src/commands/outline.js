@@ -0,0 +1,43 @@
+import { createRuntime, failureFromError, success, usageError } from '../utils/formatters.js';
+const OUTLINE_USAGE = 'ax outline <paths...> [--format text|md|json]';
+export async function outlineCommand(args, options) {
+    if (args[0] === 'help') {
+        return success([
+            'AX Outline',
+            '',
+            'Usage:',
+            `  ${OUTLINE_USAGE}`,
+            '',
+            'Prints the symbol tree of files or directories (a whole crate with its src directory) with signatures and the first doc line.',
+            '--format md renders it as a Markdown document with nested headings.',
+        ].join('\n'));
+    }
+    if (args.length === 0 || args.some((token) => token.startsWith('--'))) {
+        return usageError(OUTLINE_USAGE);
+    }
+    try {
+        const outline = await createRuntime(options).renderOutline({ paths: args, basePath: options.outputDir ?? process.cwd() });
+        if (outline.files.length === 0) {
+            return success('No indexable source files found.', outline);
+        }
+        if (options.format === 'md') {
+            return success(outline.markdown.trimEnd(), outline);
+        }
+        const sections = outline.files.map((file) => [
+            `${file.file} (${file.language})`,
+            ...file.symbols.flatMap((node) => formatNode(node, 1)),
+        ].join('\n'));
+        return success(sections.join('\n\n'), outline);
+    }
+    catch (error) {
+        return failureFromError('render outline', error);
+    }
+}
+function formatNode(node, depth) {
+    const indent = '  '.repeat(depth);
+    const lines = [`${indent}${node.kind} ${node.name}  line ${node.line}${node.signature !== undefined ? `  ${node.signature}` : ''}`];
+    if (node.doc !== undefined) {
+        lines.push(`${indent}  // ${node.doc}`);
+    }
+    return [...lines, ...node.children.flatMap((child) => formatNode(child, depth + 1))];
+}
@@ -0,0 +1,38 @@
+import { createTerminalApprover } from '../utils/approvals.js';
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
+const USAGE = 'ax replay <run-id>';
+export async function replayCommand(args, options) {
+    const [runId, ...rest] = args;
+    if (runId === undefined || runId.startsWith('--') || rest.length > 0) {
+        return usageError(USAGE);
+    }
+    try {
+        const replay = await createRuntime(options).replayRun({
+            runId,
+            basePath: options.outputDir ?? process.cwd(),
+            surface: 'cli',
+            approve: createTerminalApprover(),
+            approvalPolicy: options.approvalPolicy,
+        });
+        const run = replay.command === 'agent.run' ? 'agent run' : 'pipeline';
+        const content = 'content' in replay.result ? replay.result.content : '';
+        const lines = [
+            `Replayed ${run} ${replay.runId} as ${replay.traceId}: ${replay.calls.replayed} of ${replay.calls.recorded} recorded provider call(s), ${replay.toolCalls} tool call(s) run again, ${replay.edits} file edit(s) written.`,
+            replay.success ? `The ${run} succeeded.` : `The ${run} failed: ${replay.result.error?.message ?? 'unknown error'}`,
+            ...(replay.divergences.length === 0
+                ? ['It went as recorded.']
+                : ['', 'Divergences:', ...replay.divergences.map(formatDivergence)]),
+            ...(content.length > 0 ? ['', content] : []),
+        ];
+        return replay.success && replay.divergences.length === 0
+            ? success(lines.join('\n'), replay)
+            : failure(lines.join('\n'), replay);
+    }
+    catch (error) {
+        return failureFromError(`replay run "${runId}"`, error);
+    }
+}
+function formatDivergence(divergence) {
+    const where = divergence.call !== undefined ? `call ${divergence.call}` : 'no recorded call';
+    return `- ${where}${divergence.agentId !== undefined ? ` (${divergence.agentId})` : ''}: ${divergence.message}`;
+}
@@ -1,13 +1,22 @@
-import { existsSync } from 'node:fs';
-import { join } from 'node:path';
-import { createRuntime, failure, success, usageError } from '../utils/formatters.js';
-import { parseOptionalJsonInput } from '../utils/validation.js';
+import { spawn } from 'node:child_process';
+import { randomUUID } from 'node:crypto';
+import { closeSync, existsSync, mkdirSync, openSync } from 'node:fs';
+import { dirname, join } from 'node:path';
+import { fileURLToPath } from 'node:url';
+import { getBuiltinWorkflow } from '@defai.digital/shared-runtime';
+import { getDetachedRunPaths, isValidRunId, writeDetachedRun } from '../utils/detached-runs.js';
+import { createRuntime, failure, formatBudget, success, usageError } from '../utils/formatters.js';
+import { createTextStream, shouldStreamText } from '../utils/text-stream.js';
+import { parseBudgetOptions, parseOptionalJsonInput } from '../utils/validation.js';
+import { formatUsageTotals } from './cost.js';
 export async function runCommand(args, options) {
     const workflowId = args[0] ?? options.workflowId;
     if (workflowId === undefined) {
         return usageError('ax run <workflow-id>');
     }
-    const workflowDir = options.workflowDir ?? resolveWorkflowDir();
+    // Built-in workflows such as `maintain` run without a workflow directory.
+    const workflowDir = options.workflowDir ?? resolveWorkflowDir()
+        ?? (getBuiltinWorkflow(workflowId) !== undefined ? join(process.cwd(), 'workflows') : undefined);
     if (workflowDir === undefined) {
         return failure('No workflow directory found. Create workflows/ or .automatosx/workflows/.');
     }
@@ -15,8 +24,17 @@ export async function runCommand(args, options) {
     if (workflowInputParse.error !== undefined) {
         return failure(`Invalid JSON in --input parameter: ${workflowInputParse.error}`);
     }
+    const budget = parseBudgetOptions(options);
+    if (budget.error !== undefined) {
+        return failure(budget.error);
+    }
     const basePath = options.outputDir ?? process.cwd();
+    if (options.detach === true) {
+        return detachRun(workflowId, args, options, workflowDir, basePath);
+    }
     const runtime = createRuntime(options);
+    const stream = shouldStreamText(options) ? createTextStream() : undefined;
+    let streamingStepId;
     try {
         const execution = await runtime.runWorkflow({
             workflowId,
@@ -25,10 +43,33 @@ export async function runCommand(args, options) {
             basePath,
             provider: options.provider,
             sessionId: options.sessionId,
+            claim: options.claim,
             model: 'v14-runtime-bridge',
             input: buildWorkflowInput(workflowId, args, options, workflowInputParse.value ?? {}),
             surface: 'cli',
+            noCache: options.noCache,
+            budget: budget.value,
+            ...(stream !== undefined ? {
+                // Each step's reply is headed by its step id once it starts to arrive.
+                onText: (text, stepId) => {
+                    if (stepId !== streamingStepId) {
+                        stream.breakLine();
+                        stream.write(`[${stepId ?? workflowId}]\n`);
+                        streamingStepId = stepId;
+                    }
+                    stream.write(text);
+                },
+            } : {}),
+            // A switch of provider mid-reply is worth a line even without --verbose: the reply's tail came from elsewhere.
+            ...(options.quiet !== true ? {
+                onProviderFailover: (failover) => logProgress(`step ${failover.stepId ?? workflowId}: provider ${failover.from} failed after ${failover.partialChars} characters${failover.errorCode === undefined ? '' : ` (${failover.errorCode})`}; ${failover.to} continued the reply`),
+            } : {}),
+            ...(options.verbose ? {
+                onStepStart: (stepId) => logProgress(`step ${stepId} started`),
+                onStepComplete: (step) => logProgress(`step ${step.stepId} ${step.success ? 'completed' : `failed: ${step.error ?? 'unknown error'}`} (${step.durationMs}ms)`),
+            } : {}),
         });
+        await stream?.end();
         if (!execution.success && execution.error?.code === 'WORKFLOW_NOT_FOUND') {
             const available = await listWorkflowIds(runtime, workflowDir, basePath);
             const availableText = available.length === 0 ? '' : `\n\nAvailable workflows: ${available.slice(0, 5).join(', ')}${available.length > 5 ? '...' : ''}`;
@@ -38,7 +79,9 @@ export async function runCommand(args, options) {
                 workflowDir,
             });
         }
-        const stepSummary = formatStepSummary(execution);
+        // The run's provider calls, the agent runs it started included.
+        const usage = (await runtime.getUsageReport({ traceId: execution.traceId })).total;
+        const stepSummary = `${formatStepSummary(execution)}${usage.calls > 0 ? `\n\nCost: ${formatUsageTotals(usage)}` : ''}${execution.budget !== undefined ? `\n${formatBudget(execution.budget)}` : ''}${formatClaim(execution)}`;
         const data = {
             traceId: execution.traceId,
             workflowId,
@@ -53,6 +96,9 @@ export async function runCommand(args, options) {
                 retryCount: stepResult.retryCount,
                 error: stepResult.error?.message,
             })),
+            usage,
+            budget: execution.budget,
+            claimed: execution.claimed,
         };
         if (execution.success) {
             return success(`Workflow "${workflowId}" completed successfully.${stepSummary}`, data);
@@ -60,11 +106,66 @@ export async function runCommand(args, options) {
         return failure(`Workflow "${workflowId}" failed: ${execution.error?.message ?? 'Unknown error'}.${stepSummary}`, data);
     }
     catch (error) {
+        await stream?.end();
         const message = error instanceof Error ? error.message : String(error);
         return failure(`Failed to run workflow "${workflowId}": ${message}`);
     }
 }
-function buildWorkflowInput(workflowId, args, options, workflowInput) {
+/**
+ * Re-launches this run in a detached child process so it survives the terminal closing.
+ * The child writes verbose progress and its final result to a per-run log that `ax attach` follows.
+ */
+async function detachRun(workflowId, args, options, workflowDir, basePath) {
+    const runId = options.traceId ?? randomUUID();
+    if (!isValidRunId(runId)) {
+        return failure(`Invalid run id "${runId}". Use letters, digits, ".", "_" or "-".`);
+    }
+    const paths = getDetachedRunPaths(basePath, runId);
+    mkdirSync(dirname(paths.log), { recursive: true });
+    const childArgs = [
+        fileURLToPath(new URL('../main.js', import.meta.url)),
+        'run',
+        ...args,
+        '--trace-id', runId,
+        '--workflow-dir', workflowDir,
+        '--output-dir', basePath,
+        '--verbose',
+        ...(options.input !== undefined ? ['--input', options.input] : []),
+        ...(options.provider !== undefined ? ['--provider', options.provider] : []),
+        ...(options.sessionId !== undefined ? ['--session-id', options.sessionId] : []),
+        ...(options.claim !== undefined ? ['--claim', options.claim] : []),
+        ...(options.task !== undefined ? ['--task', options.task] : []),
+        ...(options.readOnly === true ? ['--read-only'] : []),
+        ...(options.noCache === true ? ['--no-cache'] : []),
+        ...(options.noStream === true ? ['--no-stream'] : []),
+        ...(options.maxCostUsd !== undefined ? ['--max-cost-usd', options.maxCostUsd] : []),
+        ...(options.maxTokens !== undefined ? ['--max-tokens', String(options.maxTokens)] : []),
+        ...(options.maxDuration !== undefined ? ['--max-duration', options.maxDuration] : []),
+    ];
+    if (args[0] === undefined) {
+        childArgs.splice(2, 0, workflowId);
+    }
+    const log = openSync(paths.log, 'a');
+    let pid;
+    try {
+        const child = spawn(process.execPath, childArgs, { detached: true, stdio: ['ignore', log, log] });
+        child.unref();
+        pid = child.pid;
+    }
+    finally {
+        closeSync(log);
+    }
+    if (pid === undefined) {
+        return failure(`Failed to start detached run for workflow "${workflowId}".`);
+    }
+    const record = { runId, workflowId, pid, startedAt: new Date().toISOString(), logPath: paths.log };
+    await writeDetachedRun(basePath, record);
+    return success(`Workflow "${workflowId}" detached as run ${runId} (pid ${pid}).\nLog: ${paths.log}\nAttach with: ax attach ${runId}`, record);
+}
+function logProgress(message) {
+    process.stderr.write(`[${new Date().toISOString()}] ${message}\n`);
+}
+export function buildWorkflowInput(workflowId, args, options, workflowInput) {
     const parsedInput = workflowInput;
     const commandTask = parsedInput.task ?? options.task;
     const positionalArgs = args.slice(1).filter((value) => value.length > 0);
@@ -87,6 +188,16 @@ function formatStepSummary(execution) {
     const stepValues = execution.stepResults.map((step) => `${step.stepId} (${step.success ? '✓' : '✗'})`);
     return `\n\nSteps: ${stepValues.join(', ')}`;
 }
+// The scratchpad task a `--claim` run held, as the run left it.
+function formatClaim(execution) {
+    if (execution.claimed === undefined) {
+        return '';
+    }
+    return [
+        `\n\nScratchpad task: ${execution.claimed.taskId} (${execution.claimed.status})`,
+        ...(execution.warnings ?? []).map((warning) => `\nWarning: ${warning}`),
+    ].join('');
+}
 async function listWorkflowIds(runtime, workflowDir, basePath) {
     try {
         const workflows = await runtime.listWorkflows({ workflowDir, basePath });
@@ -96,7 +207,7 @@ async function listWorkflowIds(runtime, workflowDir, basePath) {
         return [];
     }
 }
-function resolveWorkflowDir() {
+export function resolveWorkflowDir() {
     const candidateDirs = ['workflows', '.automatosx/workflows', 'examples/workflows'];
     for (const dir of candidateDirs) {
         const candidate = join(process.cwd(), dir);
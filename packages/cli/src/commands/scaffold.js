@@ -9,16 +9,17 @@
  *   ax scaffold domain <name>             Generate full domain package
  *   ax scaffold guard <policy-id>         Generate guard policy YAML
  *   ax scaffold project <name> -m <domain> Generate full project from template
+ *   ax scaffold impl <interface>          Generate stubs implementing an indexed interface
  */
 import * as fs from 'node:fs';
 import * as path from 'node:path';
-import { failure, success } from '../utils/formatters.js';
+import { createRuntime, failure, failureFromError, success } from '../utils/formatters.js';
 // ---------------------------------------------------------------------------
 // Constants
 // ---------------------------------------------------------------------------
 const GUARD_RADIUS_DEFAULT = 3;
-const GUARD_GATES_DEFAULT = ['contract-review', 'test-coverage', 'invariant-check'];
-const SCAFFOLD_SCOPE_DEFAULT = '@myorg';
+const GUARD_GATES_DEFAULT  = ['contract-review', 'test-coverage', 'invariant-check'];
+const SCAFFOLD_SCOPE_DEFAULT    = '@myorg';
 const SCAFFOLD_TEMPLATE_DEFAULT = 'standalone';
 // ---------------------------------------------------------------------------
 // Template generators
@@ -213,6 +214,9 @@ describe('${P} Contract', () => {
 });
 `;
 }
+// ---------------------------------------------------------------------------
+// File write helper
+// ---------------------------------------------------------------------------
 function writeScaffoldFile(filePath, content, dryRun, files) {
     const action = fs.existsSync(filePath) ? 'overwrite' : 'create';
     files.push({ path: filePath, action });
@@ -227,23 +231,23 @@ function writeScaffoldFile(filePath, content, dryRun, files) {
 // Subcommand handlers
 // ---------------------------------------------------------------------------
 function handleContract(name, opts) {
-    const desc = opts.description ?? `${toPascalCase(name)} domain.`;
+    const desc   = opts.description ?? `${toPascalCase(name)} domain.`;
     const outDir = opts.output ?? `packages/contracts/src/${name}/v1`;
     const files = [];
-    writeScaffoldFile(path.join(outDir, 'schema.ts'), generateSchemaTemplate(name, desc), opts.dryRun, files);
+    writeScaffoldFile(path.join(outDir, 'schema.ts'),     generateSchemaTemplate(name, desc),     opts.dryRun, files);
     writeScaffoldFile(path.join(outDir, 'invariants.md'), generateInvariantsTemplate(name, desc), opts.dryRun, files);
-    writeScaffoldFile(path.join(outDir, 'index.ts'), `export * from './schema.js';\n`, opts.dryRun, files);
+    writeScaffoldFile(path.join(outDir, 'index.ts'),      `export * from './schema.js';\n`,        opts.dryRun, files);
     const verb = opts.dryRun ? 'Would create' : 'Created';
     return success(`${verb} contract for "${name}":\n${files.map((f) => `  ${f.action}: ${f.path}`).join('\n')}`, { name, files, dryRun: opts.dryRun });
 }
 function handleDomain(name, opts) {
-    const scope = opts.scope ?? SCAFFOLD_SCOPE_DEFAULT;
+    const scope    = opts.scope ?? SCAFFOLD_SCOPE_DEFAULT;
     const domainDir = opts.output ?? `packages/core/${name}-domain`;
     const files = [];
-    writeScaffoldFile(path.join(domainDir, 'package.json'), generateDomainPackageJson(name, scope), opts.dryRun, files);
-    writeScaffoldFile(path.join(domainDir, 'src', 'index.ts'), generateDomainIndex(name), opts.dryRun, files);
-    writeScaffoldFile(path.join(domainDir, 'src', 'types.ts'), generateDomainTypes(name), opts.dryRun, files);
-    writeScaffoldFile(path.join(domainDir, 'src', 'service.ts'), generateDomainService(name), opts.dryRun, files);
+    writeScaffoldFile(path.join(domainDir, 'package.json'),      generateDomainPackageJson(name, scope), opts.dryRun, files);
+    writeScaffoldFile(path.join(domainDir, 'src', 'index.ts'),   generateDomainIndex(name),              opts.dryRun, files);
+    writeScaffoldFile(path.join(domainDir, 'src', 'types.ts'),   generateDomainTypes(name),              opts.dryRun, files);
+    writeScaffoldFile(path.join(domainDir, 'src', 'service.ts'), generateDomainService(name),            opts.dryRun, files);
     if (!opts.noTests) {
         writeScaffoldFile(`tests/contract/${name}.test.ts`, generateContractTest(name), opts.dryRun, files);
     }
@@ -256,12 +260,35 @@ function handleDomain(name, opts) {
 function handleGuard(policyId, opts) {
     const domain = opts.domain ?? policyId.replace(/-development$/, '');
     const radius = opts.radius ?? GUARD_RADIUS_DEFAULT;
-    const gates = opts.gates ? opts.gates.split(',').map((g) => g.trim()) : [...GUARD_GATES_DEFAULT];
+    const gates  = opts.gates ? opts.gates.split(',').map((g) => g.trim()) : [...GUARD_GATES_DEFAULT];
     const files = [];
     writeScaffoldFile(`packages/guard/policies/${policyId}.yaml`, generateGuardPolicyTemplate(policyId, domain, radius, gates), opts.dryRun, files);
     const verb = opts.dryRun ? 'Would create' : 'Created';
     return success(`${verb} guard policy "${policyId}":\n${files.map((f) => `  ${f.action}: ${f.path}`).join('\n')}`, { policyId, domain, files, dryRun: opts.dryRun });
 }
+async function handleImplementation(symbol, opts, options) {
+    try {
+        const scaffold = await createRuntime(options).scaffoldImplementation({
+            symbol,
+            className: opts.className,
+            output: opts.output,
+            overwrite: opts.force,
+            dryRun: opts.dryRun,
+            basePath: options.outputDir ?? process.cwd(),
+        });
+        const verb = opts.dryRun ? 'Would create' : 'Created';
+        const lines = [
+            `${verb} ${scaffold.className} implementing ${scaffold.interface.name} (${scaffold.interface.file}:${scaffold.interface.line}):`,
+            `  ${scaffold.file}`,
+            `  ${scaffold.members.length} stubbed member(s)${scaffold.members.length > 0 ? `: ${scaffold.members.join(', ')}` : ''}`,
+            ...(opts.dryRun ? ['', scaffold.content] : []),
+        ];
+        return success(lines.join('\n'), scaffold);
+    }
+    catch (error) {
+        return failureFromError('scaffold implementation', error);
+    }
+}
 // ---------------------------------------------------------------------------
 // Argument parsing helpers
 // ---------------------------------------------------------------------------
@@ -282,33 +309,36 @@ function pickFlag(flags, ...names) {
 export async function scaffoldCommand(args, options) {
     if (args.length === 0 || args[0] === 'help' || options.help) {
         return success('Usage: ax scaffold <subcommand> [options]\n\n' +
-            'Subcommands:\n' +
-            '  contract <name>   Generate Zod schema + invariants doc\n' +
-            '  domain <name>     Generate full domain package\n' +
-            '  guard <policy-id> Generate guard policy YAML\n\n' +
-            'Options:\n' +
-            '  -d, --description <desc>  Description (contract/domain)\n' +
-            '  -o, --output <path>       Output directory\n' +
-            '  -s, --scope <scope>       Package scope (default: @myorg)\n' +
-            '  -r, --radius <n>          Change radius limit (default: 3)\n' +
-            '  -g, --gates <gates>       Comma-separated gate names\n' +
-            '  --no-tests                Skip test scaffold\n' +
-            '  --no-guard                Skip guard policy\n' +
-            '  --dry-run                 Preview without writing files');
+        'Subcommands:\n' +
+        '  contract <name>   Generate Zod schema + invariants doc\n' +
+        '  domain <name>     Generate full domain package\n' +
+        '  guard <policy-id> Generate guard policy YAML\n' +
+        '  impl <interface>  Generate stubs implementing an indexed interface\n\n' +
+        'Options:\n' +
+        '  -d, --description <desc>  Description (contract/domain)\n' +
+        '  -o, --output <path>       Output directory\n' +
+        '  -s, --scope <scope>       Package scope (default: @myorg)\n' +
+        '  -r, --radius <n>          Change radius limit (default: 3)\n' +
+        '  -g, --gates <gates>       Comma-separated gate names\n' +
+        '  --no-tests                Skip test scaffold\n' +
+        '  --no-guard                Skip guard policy\n' +
+        '  --class <name>            Implementation class name (impl)\n' +
+        '  --force                   Overwrite an existing file (impl)\n' +
+        '  --dry-run                 Preview without writing files');
     }
     const subcommand = args[0];
-    const rest = args.slice(1);
-    const dryRun = rest.includes('--dry-run') || (options.dryRun ?? false);
+    const rest       = args.slice(1);
+    const dryRun     = rest.includes('--dry-run') || (options.dryRun ?? false);
     // Collect positional (non-flag) arguments
     const positional = rest.filter((a) => !a.startsWith('-'));
-    const name = positional[0];
+    const name       = positional[0];
     switch (subcommand) {
         case 'contract': {
             if (name === undefined)
                 return failure('Usage: ax scaffold contract <name> [options]');
             return handleContract(name, {
                 description: pickFlag(rest, '-d', '--description'),
-                output: pickFlag(rest, '-o', '--output'),
+                output:      pickFlag(rest, '-o', '--output'),
                 dryRun,
             });
         }
@@ -316,8 +346,8 @@ export async function scaffoldCommand(args, options) {
             if (name === undefined)
                 return failure('Usage: ax scaffold domain <name> [options]');
             return handleDomain(name, {
-                scope: pickFlag(rest, '-s', '--scope'),
-                output: pickFlag(rest, '-o', '--output'),
+                scope:   pickFlag(rest, '-s', '--scope'),
+                output:  pickFlag(rest, '-o', '--output'),
                 noTests: rest.includes('--no-tests'),
                 noGuard: rest.includes('--no-guard'),
                 dryRun,
@@ -330,11 +360,21 @@ export async function scaffoldCommand(args, options) {
             return handleGuard(name, {
                 domain: pickFlag(rest, '-m', '--domain', '-d', '--description'),
                 radius: radiusRaw !== undefined ? parseInt(radiusRaw, 10) : undefined,
-                gates: pickFlag(rest, '-g', '--gates'),
+                gates:  pickFlag(rest, '-g', '--gates'),
                 dryRun,
             });
         }
+        case 'impl': {
+            if (name === undefined)
+                return failure('Usage: ax scaffold impl <interface> [--class <name>] [-o <file>] [--force] [--dry-run]');
+            return handleImplementation(name, {
+                className: pickFlag(rest, '--class'),
+                output:    pickFlag(rest, '-o', '--output'),
+                force:     rest.includes('--force'),
+                dryRun,
+            }, options);
+        }
         default:
-            return failure(`Unknown subcommand: "${subcommand}"\nAvailable: contract, domain, guard\nRun "ax scaffold help" for usage.`);
+            return failure(`Unknown subcommand: "${subcommand}"\nAvailable: contract, domain, guard, impl\nRun "ax scaffold help" for usage.`);
     }
 }
@@ -9,12 +9,13 @@
  *   ax scaffold domain <name>             Generate full domain package
  *   ax scaffold guard <policy-id>         Generate guard policy YAML
  *   ax scaffold project <name> -m <domain> Generate full project from template
+ *   ax scaffold impl <interface>          Generate stubs implementing an indexed interface
  */
 
 import * as fs from 'node:fs';
 import * as path from 'node:path';
 import type { CLIOptions, CommandResult } from '../types.js';
-import { failure, success } from '../utils/formatters.js';
+import { createRuntime, failure, failureFromError, success } from '../utils/formatters.js';
 
 // ---------------------------------------------------------------------------
 // Constants
@@ -313,6 +314,33 @@ function handleGuard(policyId: string, opts: { domain?: string; radius?: number;
   );
 }
 
+async function handleImplementation(
+  symbol: string,
+  opts: { className?: string; output?: string; force: boolean; dryRun: boolean },
+  options: CLIOptions,
+): Promise<CommandResult> {
+  try {
+    const scaffold = await createRuntime(options).scaffoldImplementation({
+      symbol,
+      className: opts.className,
+      output: opts.output,
+      overwrite: opts.force,
+      dryRun: opts.dryRun,
+      basePath: options.outputDir ?? process.cwd(),
+    });
+    const verb = opts.dryRun ? 'Would create' : 'Created';
+    const lines = [
+      `${verb} ${scaffold.className} implementing ${scaffold.interface.name} (${scaffold.interface.file}:${scaffold.interface.line}):`,
+      `  ${scaffold.file}`,
+      `  ${scaffold.members.length} stubbed member(s)${scaffold.members.length > 0 ? `: ${scaffold.members.join(', ')}` : ''}`,
+      ...(opts.dryRun ? ['', scaffold.content] : []),
+    ];
+    return success(lines.join('\n'), scaffold);
+  } catch (error) {
+    return failureFromError('scaffold implementation', error);
+  }
+}
+
 // ---------------------------------------------------------------------------
 // Argument parsing helpers
 // ---------------------------------------------------------------------------
@@ -338,7 +366,8 @@ export async function scaffoldCommand(args: string[], options: CLIOptions): Prom
       'Subcommands:\n' +
       '  contract <name>   Generate Zod schema + invariants doc\n' +
       '  domain <name>     Generate full domain package\n' +
-      '  guard <policy-id> Generate guard policy YAML\n\n' +
+      '  guard <policy-id> Generate guard policy YAML\n' +
+      '  impl <interface>  Generate stubs implementing an indexed interface\n\n' +
       'Options:\n' +
       '  -d, --description <desc>  Description (contract/domain)\n' +
       '  -o, --output <path>       Output directory\n' +
@@ -347,6 +376,8 @@ export async function scaffoldCommand(args: string[], options: CLIOptions): Prom
       '  -g, --gates <gates>       Comma-separated gate names\n' +
       '  --no-tests                Skip test scaffold\n' +
       '  --no-guard                Skip guard policy\n' +
+      '  --class <name>            Implementation class name (impl)\n' +
+      '  --force                   Overwrite an existing file (impl)\n' +
       '  --dry-run                 Preview without writing files',
     );
   }
@@ -391,9 +422,19 @@ export async function scaffoldCommand(args: string[], options: CLIOptions): Prom
       });
     }
 
+    case 'impl': {
+      if (name === undefined) return failure('Usage: ax scaffold impl <interface> [--class <name>] [-o <file>] [--force] [--dry-run]');
+      return handleImplementation(name, {
+        className: pickFlag(rest, '--class'),
+        output:    pickFlag(rest, '-o', '--output'),
+        force:     rest.includes('--force'),
+        dryRun,
+      }, options);
+    }
+
     default:
       return failure(
-        `Unknown subcommand: "${subcommand}"\nAvailable: contract, domain, guard\nRun "ax scaffold help" for usage.`,
+        `Unknown subcommand: "${subcommand}"\nAvailable: contract, domain, guard, impl\nRun "ax scaffold help" for usage.`,
       );
   }
 }
@@ -0,0 +1,146 @@
+import { createRuntime, failureFromError, success, usageError } from '../utils/formatters.js';
+const SEARCH_SYMBOLS_USAGE = 'ax search symbols <query> [--limit <n>]';
+const SEARCH_TEXT_USAGE = 'ax search text <pattern> [--regex] [--case-sensitive] [--path <dir>]... [--reindex] [--limit <n>]';
+const SEARCH_HYBRID_USAGE = 'ax search hybrid <query> [--limit <n>]';
+export async function searchCommand(args, options) {
+    const [subcommand, ...rest] = args;
+    if (subcommand === undefined || subcommand === 'help') {
+        return success([
+            'AX Search',
+            '',
+            'Usage:',
+            `  ${SEARCH_SYMBOLS_USAGE}`,
+            `  ${SEARCH_TEXT_USAGE}`,
+            `  ${SEARCH_HYBRID_USAGE}`,
+            '',
+            'symbols fuzzy-matches indexed symbol names: "DatProc" finds DataProcessor, best matches first.',
+            'A query written as a path (parser::Parser::new, Orders.Order.Submit) must match the module path or container.',
+            'Filters: kind:<kind>, lang:<language>, path:<file or directory>; e.g. "kind:trait path:crates/core Handler".',
+            '',
+            'text searches file contents through a trigram index built on first use, reading only files that can match.',
+            'The pattern is literal and case-insensitive unless --regex or --case-sensitive is given; --reindex picks up edits.',
+            '',
+            'hybrid searches memory, symbols and file contents for the query and each of its words, ranking what most searches agree on first.',
+        ].join('\n'));
+    }
+    if (subcommand === 'text') {
+        return searchText(rest, options);
+    }
+    if (subcommand === 'hybrid') {
+        return searchHybrid(rest, options);
+    }
+    if (subcommand !== 'symbols') {
+        return usageError(`${SEARCH_SYMBOLS_USAGE}\n       ${SEARCH_TEXT_USAGE}\n       ${SEARCH_HYBRID_USAGE}`);
+    }
+    if (rest.length === 0 || rest.some((token) => token.startsWith('--'))) {
+        return usageError(SEARCH_SYMBOLS_USAGE);
+    }
+    try {
+        const hits = await createRuntime(options).searchSymbols({
+            query: rest.join(' '),
+            limit: options.limit,
+            basePath: options.outputDir ?? process.cwd(),
+        });
+        if (hits.length === 0) {
+            return success(`No symbols match "${rest.join(' ')}".`, hits);
+        }
+        return success(hits.map((hit) => formatHit(hit)).join('\n'), hits);
+    }
+    catch (error) {
+        return failureFromError('search symbols', error);
+    }
+}
+async function searchText(args, options) {
+    const parsed = parseTextSearchArgs(args);
+    if (parsed === undefined || parsed.pattern === undefined) {
+        return usageError(SEARCH_TEXT_USAGE);
+    }
+    try {
+        const result = await createRuntime(options).searchText({
+            pattern: parsed.pattern,
+            regex: parsed.regex,
+            caseSensitive: parsed.caseSensitive,
+            paths: parsed.paths,
+            limit: options.limit,
+            reindex: parsed.reindex,
+            basePath: options.outputDir ?? process.cwd(),
+        });
+        return success(formatTextSearch(result), result);
+    }
+    catch (error) {
+        return failureFromError('search text', error);
+    }
+}
+async function searchHybrid(args, options) {
+    if (args.length === 0 || args.some((token) => token.startsWith('--'))) {
+        return usageError(SEARCH_HYBRID_USAGE);
+    }
+    try {
+        const result = await createRuntime(options).hybridSearch({
+            query: args.join(' '),
+            k: options.limit,
+            basePath: options.outputDir ?? process.cwd(),
+        });
+        const lines = result.results.length === 0
+            ? [`Nothing matches "${result.query}".`]
+            : result.results.map((entry) => formatHybridResult(entry));
+        return success([...lines, ...result.warnings.map((warning) => `Warning: ${warning}`)].join('\n'), result);
+    }
+    catch (error) {
+        return failureFromError('search hybrid', error);
+    }
+}
+function parseTextSearchArgs(args) {
+    const parsed = { regex: false, caseSensitive: false, paths: [], reindex: false };
+    for (let index = 0; index < args.length; index += 1) {
+        const token = args[index];
+        if (token === '--regex') {
+            parsed.regex = true;
+        }
+        else if (token === '--case-sensitive') {
+            parsed.caseSensitive = true;
+        }
+        else if (token === '--reindex') {
+            parsed.reindex = true;
+        }
+        else if (token === '--path') {
+            const value = args[index + 1];
+            if (value === undefined || value.startsWith('--')) {
+                return undefined;
+            }
+            parsed.paths.push(value);
+            index += 1;
+        }
+        else if (token.startsWith('--') || parsed.pattern !== undefined) {
+            return undefined;
+        }
+        else {
+            parsed.pattern = token;
+        }
+    }
+    return parsed;
+}
+function formatTextSearch(result) {
+    const summary = `${result.filesScanned} of ${result.filesIndexed} indexed file(s) read`;
+    if (result.matches.length === 0) {
+        return `No matches for "${result.pattern}" (${summary}).`;
+    }
+    return [
+        ...result.matches.map((match) => `${match.file}:${match.line}:${match.column}: ${match.text}`),
+        '',
+        `${result.matches.length}${result.truncated ? '+' : ''} match(es), ${summary}.`,
+    ].join('\n');
+}
+function formatHybridResult(result) {
+    const sources = `[${result.sources.join('+')}]`;
+    if (result.memory !== undefined) {
+        return `- ${sources} memory ${result.memory.namespace !== undefined ? `${result.memory.namespace}/` : ''}${result.memory.key}`;
+    }
+    const detail = result.symbol !== undefined ? `${result.symbol.kind} ${result.symbol.name}` : result.text ?? '';
+    return `- ${sources} ${result.file}:${result.line} ${detail}`;
+}
+function formatHit(hit) {
+    const { symbol } = hit;
+    const container = symbol.container !== undefined ? ` (${symbol.container})` : '';
+    return `- ${symbol.kind} ${symbol.name} ${symbol.file}:${symbol.line}${container}`;
+}
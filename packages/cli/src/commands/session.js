@@ -1,6 +1,9 @@
 import { randomUUID } from 'node:crypto';
-import { createRuntime, failure, success, usageError } from '../utils/formatters.js';
-import { parseJsonInput, asString, asOptionalString, asOptionalRecord, asStringValue } from '../utils/validation.js';
+import { writeFile } from 'node:fs/promises';
+import { extname, resolve } from 'node:path';
+import { createTerminalApprover } from '../utils/approvals.js';
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
+import { parseJsonInput, asString, asOptionalInteger, asOptionalString, asOptionalRecord, asStringArray, asStringValue, isRecord } from '../utils/validation.js';
 export async function sessionCommand(args, options) {
     const subcommand = args[0] ?? 'list';
     const runtime = createRuntime(options);
@@ -12,7 +15,7 @@ export async function sessionCommand(args, options) {
             }
             const lines = [
                 'Sessions:',
-                ...sessions.map((session) => (`- ${session.sessionId} ${session.status} ${session.initiator} ${session.task}`)),
+                ...sessions.map((session) => (`- ${session.sessionId} ${session.status} ${session.initiator} ${session.task}${typeof session.metadata?.branchedFrom === 'string' ? ` (branch of ${session.metadata.branchedFrom})` : ''}`)),
             ];
             return success(lines.join('\n'), sessions);
         }
@@ -25,6 +28,7 @@ export async function sessionCommand(args, options) {
             if (session === undefined) {
                 return failure(`Session not found: ${sessionId}`);
             }
+            const thread = await runtime.getSessionThread(sessionId, options.outputDir);
             const lines = [
                 `Session: ${session.sessionId}`,
                 `Task: ${session.task}`,
@@ -32,8 +36,9 @@ export async function sessionCommand(args, options) {
                 `Status: ${session.status}`,
                 `Workspace: ${session.workspace ?? 'N/A'}`,
                 `Participants: ${session.participants.map((entry) => `${entry.agentId}:${entry.role}${entry.leftAt ? ':left' : ''}`).join(', ')}`,
+                ...formatThread(thread),
             ];
-            return success(lines.join('\n'), session);
+            return success(lines.join('\n'), { ...session, thread });
         }
         case 'create': {
             const parsed = parseJsonInput(options.input);
@@ -55,9 +60,194 @@ export async function sessionCommand(args, options) {
                 initiator: initiator.value,
                 workspace: asStringValue(parsed.value.workspace),
                 metadata: asOptionalRecord(parsed.value.metadata),
+                scope: asStringValue(parsed.value.scope),
+                memoryNamespace: asStringValue(parsed.value.memoryNamespace),
+                basePath: options.outputDir,
             });
             return success(`Session created: ${session.sessionId}`, session);
         }
+        case 'resume': {
+            const sessionId = args[1];
+            if (sessionId === undefined) {
+                return usageError('ax session resume <session-id> [--task <text>] [--agent <agent-id>]');
+            }
+            try {
+                const resumed = await runtime.resumeSession({
+                    sessionId,
+                    task: options.task,
+                    agentId: options.agent,
+                    provider: options.provider,
+                    basePath: options.outputDir,
+                    surface: 'cli',
+                    approve: createTerminalApprover(),
+                    approvalPolicy: options.approvalPolicy,
+                });
+                const { run } = resumed;
+                const lines = [
+                    `Session resumed: ${resumed.session.sessionId}`,
+                    ...formatThread(resumed.thread),
+                    ...(run === undefined ? [] : [
+                        `Trace: ${run.traceId}`,
+                        run.content.length > 0 ? `Output:\n${run.content}` : undefined,
+                        run.error?.message ? `Error: ${run.error.message}` : undefined,
+                        ...run.warnings.map((warning) => `Warning: ${warning}`),
+                    ].filter((value) => value !== undefined)),
+                ];
+                return run === undefined || run.success
+                    ? success(lines.join('\n'), resumed)
+                    : failure(lines.join('\n'), resumed);
+            }
+            catch (error) {
+                return failureFromError(`resume session "${sessionId}"`, error);
+            }
+        }
+        case 'branch': {
+            const sessionId = args[1];
+            if (sessionId === undefined) {
+                return usageError('ax session branch <session-id> [--input <json-object>]');
+            }
+            const parsed = parseJsonInput(options.input, { allowEmpty: true });
+            if (parsed.error !== undefined) {
+                return failure(parsed.error);
+            }
+            const turns = asOptionalInteger(parsed.value.turns, 'turns', { min: 0 });
+            if (turns.error !== undefined) {
+                return failure(turns.error);
+            }
+            try {
+                const branch = await runtime.branchSession({
+                    sessionId,
+                    branchId: asStringValue(parsed.value.branchId),
+                    turns: turns.value,
+                    task: asStringValue(parsed.value.task ?? options.task),
+                    basePath: options.outputDir,
+                });
+                return success([`Session branched: ${branch.session.sessionId} from ${sessionId}`, ...formatThread(branch.thread)].join('\n'), branch);
+            }
+            catch (error) {
+                return failureFromError(`branch session "${sessionId}"`, error);
+            }
+        }
+        case 'export': {
+            const [, sessionId, file] = args;
+            if (sessionId === undefined) {
+                return usageError('ax session export <session-id> [<file>] [--format md|html|json]');
+            }
+            const format = transcriptFormat(options.format, file);
+            try {
+                const exported = await runtime.exportSession({ sessionId, format, basePath: options.outputDir });
+                if (file !== undefined) {
+                    const path = resolve(file);
+                    await writeFile(path, exported.content, 'utf8');
+                    return success(`Transcript of session ${sessionId} (${exported.transcript.runs.length} run(s)) written to ${path}`, { ...exported, file: path });
+                }
+                return options.format === 'json'
+                    ? success('', exported.transcript)
+                    : success(exported.content.trimEnd(), exported);
+            }
+            catch (error) {
+                return failureFromError(`export session "${sessionId}"`, error);
+            }
+        }
+        case 'tasks': {
+            const sessionId = args[1];
+            if (sessionId === undefined) {
+                return usageError('ax session tasks <session-id>');
+            }
+            try {
+                const tasks = await runtime.getScratchpad(sessionId);
+                return success(tasks.length === 0 ? `Session ${sessionId} has no scratchpad tasks.` : ['Scratchpad:', ...tasks.map(formatScratchpadTask)].join('\n'), tasks);
+            }
+            catch (error) {
+                return failureFromError(`read the scratchpad of session "${sessionId}"`, error);
+            }
+        }
+        case 'add-task': {
+            const sessionId = args[1];
+            if (sessionId === undefined) {
+                return usageError('ax session add-task <session-id> --task <title> [--input <json-object>]');
+            }
+            const parsed = parseJsonInput(options.input, { allowEmpty: options.task !== undefined });
+            if (parsed.error !== undefined) {
+                return failure(parsed.error);
+            }
+            // One task from --task and the input's `paths` and `taskId`, or the input's `tasks`.
+            const inputs = Array.isArray(parsed.value.tasks) ? parsed.value.tasks : [{ ...parsed.value, title: parsed.value.title ?? options.task }];
+            const tasks = [];
+            for (const input of inputs) {
+                const record = isRecord(input) ? input : {};
+                const title = asString(record.title, 'title');
+                if (title.error !== undefined) {
+                    return failure(title.error);
+                }
+                tasks.push({ title: title.value, taskId: asStringValue(record.taskId), paths: asStringArray(record.paths) });
+            }
+            try {
+                const added = await runtime.addScratchpadTasks(sessionId, tasks);
+                return success([`Added to the scratchpad of session ${sessionId}:`, ...added.map(formatScratchpadTask)].join('\n'), added);
+            }
+            catch (error) {
+                return failureFromError(`add scratchpad tasks to session "${sessionId}"`, error);
+            }
+        }
+        case 'claim': {
+            const [, sessionId, taskId] = args;
+            if (sessionId === undefined) {
+                return usageError('ax session claim <session-id> [<task-id>] [--agent <claimant>]');
+            }
+            try {
+                const task = await runtime.claimScratchpadTask(sessionId, { taskId, claimant: options.agent ?? 'cli' });
+                return task === undefined
+                    ? failure(`Session ${sessionId} has no open scratchpad task that no claim's paths cover.`)
+                    : success(`Claimed: ${formatScratchpadTask(task).slice(2)}`, task);
+            }
+            catch (error) {
+                return failureFromError(`claim a scratchpad task of session "${sessionId}"`, error);
+            }
+        }
+        case 'post': {
+            const [, sessionId, taskId] = args;
+            if (sessionId === undefined || taskId === undefined) {
+                return usageError('ax session post <session-id> <task-id> --input <json-object> [--agent <claimant>]');
+            }
+            const parsed = parseJsonInput(options.input);
+            if (parsed.error !== undefined) {
+                return failure(parsed.error);
+            }
+            const result = asString(parsed.value.result, 'result');
+            if (result.error !== undefined) {
+                return failure(result.error);
+            }
+            const { status } = parsed.value;
+            if (status !== undefined && status !== 'done' && status !== 'failed') {
+                return failure('Input requires "status" to be one of: done, failed.');
+            }
+            try {
+                const task = await runtime.postScratchpadResult(sessionId, {
+                    taskId,
+                    claimant: options.agent ?? 'cli',
+                    result: result.value,
+                    status,
+                });
+                return success(`Posted: ${formatScratchpadTask(task).slice(2)}`, task);
+            }
+            catch (error) {
+                return failureFromError(`post the result of scratchpad task "${taskId}"`, error);
+            }
+        }
+        case 'release': {
+            const [, sessionId, taskId] = args;
+            if (sessionId === undefined || taskId === undefined) {
+                return usageError('ax session release <session-id> <task-id> [--agent <claimant>]');
+            }
+            try {
+                const task = await runtime.releaseScratchpadTask(sessionId, taskId, options.agent);
+                return success(`Released: ${formatScratchpadTask(task).slice(2)}`, task);
+            }
+            catch (error) {
+                return failureFromError(`release scratchpad task "${taskId}"`, error);
+            }
+        }
         case 'join': {
             const sessionId = args[1];
             if (sessionId === undefined) {
@@ -128,8 +318,31 @@ export async function sessionCommand(args, options) {
             return success(`Session failed: ${session.sessionId}`, session);
         }
         default:
-            return usageError('ax session [list|get|create|join|leave|complete|fail]');
+            return usageError('ax session [list|get|create|resume|branch|export|tasks|add-task|claim|post|release|join|leave|complete|fail]');
+    }
+}
+function formatThread(thread) {
+    return [
+        `Turns: ${thread.turns.length}${thread.branchedFrom !== undefined ? ` (the first ${thread.branchedFrom.turns} from ${thread.branchedFrom.sessionId})` : ''}`,
+        ...(thread.agentId !== undefined ? [`Agent: ${thread.agentId}`] : []),
+        ...(thread.scope !== undefined ? [`Scope: ${thread.scope}`] : []),
+        ...(thread.memoryNamespace !== undefined ? [`Memory namespace: ${thread.memoryNamespace}`] : []),
+    ];
+}
+// e.g. "- api: Limit the API [services/api] claimed by backend until 2026-10-16T12:00:00.000Z".
+function formatScratchpadTask(task) {
+    const holder = task.status === 'claimed'
+        ? ` by ${task.claimedBy} until ${task.leaseExpiresAt}`
+        : task.status === 'open' ? '' : ` by ${task.claimedBy}${task.result !== undefined ? `: ${task.result.split('\n')[0]}` : ''}`;
+    return `- ${task.taskId}: ${task.title}${task.paths.length > 0 ? ` [${task.paths.join(', ')}]` : ''} ${task.status}${holder}`;
+}
+// `--format html` or `json` picks the format; otherwise the file's extension does, and Markdown is the default.
+function transcriptFormat(format, file) {
+    if (format === 'html' || format === 'json') {
+        return format;
     }
+    const extension = file === undefined || format === 'md' ? '' : extname(file).toLowerCase();
+    return extension === '.html' || extension === '.htm' ? 'html' : extension === '.json' ? 'json' : 'md';
 }
 function normalizeRole(value) {
     return value === 'initiator' || value === 'collaborator' || value === 'delegate'
@@ -1,6 +1,6 @@
 import { access, mkdir, writeFile } from 'node:fs/promises';
 import { join } from 'node:path';
-import { createSharedRuntimeService } from '@defai.digital/shared-runtime';
+import { createSharedRuntimeService, WORKSPACE_CONFIG_VERSION } from '@defai.digital/shared-runtime';
 import { migrateJsonToSqlite } from '@defai.digital/state-store';
 import { migrateTraceJsonToSqlite } from '@defai.digital/trace-store';
 import { success } from '../utils/formatters.js';
@@ -50,9 +50,9 @@ export async function ensureWorkspaceSetup(basePath, provider) {
     await mkdir(artifactDir, { recursive: true });
     const writtenFiles = [];
     await writeJsonIfMissing(configPath, {
-        schemaVersion: 1,
+        schemaVersion: WORKSPACE_CONFIG_VERSION,
         productVersion: '14.0.0',
-        defaultProvider: provider ?? 'claude',
+        providers: { default: provider ?? 'claude' },
         workflowArtifactDir: '.automatosx/workflows',
         runtimeStoreDir: '.automatosx/runtime',
         createdBy: 'ax setup',
@@ -113,7 +113,7 @@ export async function setupCommand(args, options) {
             migrationLines.push(`State migration skipped: ${stateResult.reason}`);
         }
         else {
-            migrationLines.push(`State migrated to SQLite: ${stateResult.memory} memory, ${stateResult.agents} agents, ${stateResult.sessions} sessions, ${stateResult.feedback} feedback, ${stateResult.semantic} semantic entries.`);
+            migrationLines.push(`State migrated to SQLite: ${stateResult.memory} memory, ${stateResult.agents} agents, ${stateResult.sessions} sessions, ${stateResult.feedback} feedback, ${stateResult.semantic} semantic entries, ${stateResult.facts} facts.`);
         }
         if (traceResult.skipped) {
             migrationLines.push(`Trace migration skipped: ${traceResult.reason}`);
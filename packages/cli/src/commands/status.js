@@ -1,12 +1,18 @@
 import { createRuntime, failure, success, usageError } from '../utils/formatters.js';
 export async function statusCommand(args, options) {
-    if (args[0] !== undefined) {
-        return args[0].startsWith('--')
-            ? failure(`Unknown status flag: ${args[0]}.`)
-            : usageError('ax status');
+    let probe = false;
+    for (const arg of args) {
+        if (arg === '--probe') {
+            probe = true;
+        }
+        else {
+            return arg.startsWith('--')
+                ? failure(`Unknown status flag: ${arg}.`)
+                : usageError('ax status [--probe]');
+        }
     }
     const runtime = createRuntime(options);
-    const status = await runtime.getStatus({ limit: options.limit });
+    const status = await runtime.getStatus({ limit: options.limit, probe });
     return success([
         'AutomatosX Status',
         '',
@@ -16,6 +22,21 @@ export async function statusCommand(args, options) {
         `Default provider: ${status.runtime.defaultProvider ?? 'n/a'}`,
         `Configured executors: ${status.runtime.configuredExecutors.length > 0 ? status.runtime.configuredExecutors.join(', ') : 'none'}`,
         '',
+        'Provider quotas:',
+        ...(status.runtime.providerQuotas.length > 0
+            ? status.runtime.providerQuotas.map((quota) => `- ${quota.provider} ${formatQuotaWindow('daily', quota.daily)}; ${formatQuotaWindow('monthly', quota.monthly)}${quota.exhausted ? ' [exhausted]' : quota.nearLimit ? ' [near limit]' : ''}`)
+            : ['- none']),
+        '',
+        'Provider rate limits:',
+        ...(status.runtime.providerRateLimits.length > 0
+            ? status.runtime.providerRateLimits.map(formatRateLimit)
+            : ['- none']),
+        '',
+        `Provider health${probe ? '' : ' (last probe; run ax status --probe to refresh)'}:`,
+        ...(status.runtime.providerHealth.length > 0
+            ? status.runtime.providerHealth.map(formatHealth)
+            : ['- none']),
+        '',
         'Active sessions:',
         ...(status.activeSessions.length > 0
             ? status.activeSessions.map((session) => `- ${session.sessionId} ${session.initiator} ${session.task}`)
@@ -32,3 +53,35 @@ export async function statusCommand(args, options) {
             : ['- none']),
     ].join('\n'), status);
 }
+function formatQuotaWindow(label, window) {
+    const requests = window.requestLimit === undefined ? `${window.requests}` : `${window.requests}/${window.requestLimit}`;
+    const tokens = window.tokenLimit === undefined ? `${window.tokens}` : `${window.tokens}/${window.tokenLimit}`;
+    return `${label} ${requests} requests, ${tokens} tokens`;
+}
+function formatRateLimit(limit) {
+    const parts = [
+        `${limit.queued} queued, ${limit.active} active`,
+        ...(limit.cooldownUntil !== undefined ? [`cooling down until ${limit.cooldownUntil}`] : []),
+        ...(limit.consecutiveRateLimits > 0 ? [`${limit.consecutiveRateLimits} rate limit${limit.consecutiveRateLimits === 1 ? '' : 's'} in a row`] : []),
+    ];
+    return `- ${limit.provider} ${parts.join(', ')}`;
+}
+function formatHealth(health) {
+    const parts = [
+        ...(health.auth === 'missing'
+            ? ['no credentials']
+            : health.probe === 'command'
+                ? [health.status === 'healthy' ? 'command found' : 'command missing']
+                : [`${health.latencyMs}ms ${health.probe === 'http' ? 'round trip' : 'credential check'}`]),
+        ...(health.auth === 'expired' ? ['credentials rejected'] : []),
+        ...(health.quota?.requestsRemaining !== undefined
+            ? [`${health.quota.requestsRemaining}${health.quota.requestLimit === undefined ? '' : `/${health.quota.requestLimit}`} requests left`]
+            : []),
+        ...(health.quota?.tokensRemaining !== undefined
+            ? [`${health.quota.tokensRemaining}${health.quota.tokenLimit === undefined ? '' : `/${health.quota.tokenLimit}`} tokens left`]
+            : []),
+        ...(health.quota?.resetsAt !== undefined ? [`resets ${health.quota.resetsAt}`] : []),
+        `checked ${health.checkedAt}`,
+    ];
+    return `- ${health.provider} ${health.status}: ${parts.join(', ')}${health.error === undefined ? '' : ` - ${health.error}`}`;
+}
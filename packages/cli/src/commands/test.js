@@ -0,0 +1,56 @@
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
+const USAGE = 'ax test --impacted [--base <ref>] [--run]';
+export async function testCommand(args, options) {
+    let impacted = false;
+    let run = false;
+    let base;
+    for (let index = 0; index < args.length; index += 1) {
+        const token = args[index];
+        if (token === '--impacted') {
+            impacted = true;
+        }
+        else if (token === '--run') {
+            run = true;
+        }
+        else if (token === '--base') {
+            base = args[index + 1];
+            if (base === undefined || base.startsWith('--')) {
+                return failure('Missing value for --base.');
+            }
+            index += 1;
+        }
+        else {
+            return usageError(USAGE);
+        }
+    }
+    if (!impacted) {
+        return usageError(USAGE);
+    }
+    try {
+        const result = await createRuntime(options).analyzeTestImpact({
+            base,
+            run,
+            basePath: options.outputDir ?? process.cwd(),
+        });
+        const lines = [
+            `${result.impactedTests.length} of ${result.testFileCount} test file(s) impacted by ${result.changedFiles.length} changed file(s) since ${result.base}.`,
+            ...result.impactedTests.map((test) => `- ${test.file} (${test.reasons.join('; ')})`),
+        ];
+        if (result.changedSymbols.length > 0) {
+            lines.push('', 'Changed symbols:', ...result.changedSymbols.map((symbol) => `- ${symbol.kind} ${symbol.name} ${symbol.file}:${symbol.line}`));
+        }
+        if (result.run === undefined) {
+            return success(lines.join('\n'), result);
+        }
+        if (result.run.command.length === 0) {
+            return success([...lines, '', 'No impacted tests to run.'].join('\n'), result);
+        }
+        lines.push('', `$ ${result.run.command.join(' ')}`, result.run.output.trimEnd());
+        return result.run.passed
+            ? success([...lines, `Impacted tests passed in ${result.run.durationMs}ms.`].join('\n'), result)
+            : failure([...lines, `Impacted tests failed (exit ${result.run.exitCode}).`].join('\n'), result);
+    }
+    catch (error) {
+        return failureFromError('analyze test impact', error);
+    }
+}
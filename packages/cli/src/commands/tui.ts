@@ -0,0 +1,355 @@
+import { randomUUID } from 'node:crypto';
+import { readFile } from 'node:fs/promises';
+import { isAbsolute, join, resolve } from 'node:path';
+import { emitKeypressEvents } from 'node:readline';
+import {
+  describeApproval,
+  getBuiltinWorkflow,
+  parsePipeline,
+  type ApprovalRequest,
+  type RuntimePipelineResponse,
+  type RuntimeWorkflowResponse,
+} from '@defai.digital/shared-runtime';
+import type { CLIOptions, CommandResult } from '../types.js';
+import { createRuntime, failure, failureFromError, success, usageError } from '../utils/formatters.js';
+import { appendTuiOutput, createTuiState, renderTui, updateTuiStage, type TuiStage } from '../utils/tui-screen.js';
+import { parseBudgetOptions, parseOptionalJsonInput } from '../utils/validation.js';
+import { formatUsageTotals } from './cost.js';
+import { buildWorkflowInput, resolveWorkflowDir } from './run.js';
+
+const USAGE = 'ax tui <pipeline.yaml|workflow-id> [--input <json-object>] [--provider <name>] [--session-id <session-id>] [--approval-policy <file>]';
+const REDRAW_DELAY_MS = 30;
+const USAGE_POLL_INTERVAL_MS = 1_000;
+const SYMBOL_SEARCH_DELAY_MS = 150;
+const SYMBOL_SEARCH_LIMIT = 50;
+const MAX_PREVIEW_LINES = 200;
+const ENTER_SCREEN = '\u001b[?1049h\u001b[?25l';
+const LEAVE_SCREEN = '\u001b[?25h\u001b[?1049l';
+
+interface Keypress {
+  name?: string;
+  ctrl?: boolean;
+  meta?: boolean;
+}
+
+type RunOutcome =
+  | { result: RuntimePipelineResponse | RuntimeWorkflowResponse }
+  | { error: unknown };
+
+/**
+ * Runs a pipeline (`*.yaml`) or workflow full-screen: its stages as they start and finish, the
+ * replies as they stream, what it has spent, and the actions its agents hold for approval, which
+ * are answered with y or n. Tab switches to a pane that searches the symbol index. The screen
+ * stays up once the run ends, until q; leaving earlier stops the run.
+ */
+export async function tuiCommand(args: string[], options: CLIOptions): Promise<CommandResult> {
+  const [target, ...rest] = args;
+  if (target === undefined || rest.length > 0) {
+    return usageError(USAGE);
+  }
+  if (process.stdin.isTTY !== true || process.stdout.isTTY !== true) {
+    return failure('ax tui needs an interactive terminal. Use ax workflow run or ax run without one.');
+  }
+  const inputParse = parseOptionalJsonInput(options.input);
+  if (inputParse.error !== undefined) {
+    return failure(`Invalid JSON in --input parameter: ${inputParse.error}`);
+  }
+  const budget = parseBudgetOptions(options);
+  if (budget.error !== undefined) {
+    return failure(budget.error);
+  }
+
+  const runtime = createRuntime(options);
+  const basePath = options.outputDir ?? process.cwd();
+  const traceId = options.traceId ?? randomUUID();
+  const isPipeline = /\.ya?ml$/i.test(target);
+  let stages: TuiStage[];
+  let workflowDir: string | undefined;
+  if (isPipeline) {
+    const source = await readFile(isAbsolute(target) ? target : join(basePath, target), 'utf8').catch(() => undefined);
+    if (source === undefined) {
+      return failure(`Pipeline file "${target}" was not found.`);
+    }
+    try {
+      stages = parsePipeline(source, target).stages.map((stage) => ({ id: stage.id, agent: stage.agent, status: 'pending' as const }));
+    } catch (error) {
+      return failureFromError(`read pipeline "${target}"`, error);
+    }
+  } else {
+    workflowDir = options.workflowDir ?? resolveWorkflowDir()
+      ?? (getBuiltinWorkflow(target) !== undefined ? join(process.cwd(), 'workflows') : undefined);
+    if (workflowDir === undefined) {
+      return failure('No workflow directory found. Create workflows/ or .automatosx/workflows/.');
+    }
+    const description = await runtime.describeWorkflow({ workflowId: target, workflowDir, basePath }).catch(() => undefined);
+    stages = (description?.steps ?? []).map((step) => ({ id: step.stepId, status: 'pending' as const }));
+  }
+
+  const what = isPipeline ? `pipeline ${target}` : `workflow ${target}`;
+  const state = createTuiState(what, traceId, stages);
+  const input = process.stdin;
+  const output = process.stdout;
+  const controller = new AbortController();
+  let redrawTimer: NodeJS.Timeout | undefined;
+  let searchTimer: NodeJS.Timeout | undefined;
+  let searches = 0;
+  let leave = () => {};
+  const left = new Promise<void>((resolveLeft) => {
+    leave = resolveLeft;
+  });
+
+  const draw = () => {
+    redrawTimer = undefined;
+    const lines = renderTui(state, output.columns, output.rows);
+    output.write(`\u001b[H${lines.map((line) => `${line}\u001b[K`).join('\r\n')}\u001b[J`);
+  };
+  const redraw = () => {
+    redrawTimer ??= setTimeout(draw, REDRAW_DELAY_MS);
+  };
+  const refreshUsage = async () => {
+    try {
+      state.usage = (await runtime.getUsageReport({ traceId })).total;
+      redraw();
+    } catch {
+      // The meters keep their last reading.
+    }
+  };
+  const addNote = (note: string) => {
+    appendTuiOutput(state, `${state.output.at(-1) === '' ? '' : '\n'}${note}\n`);
+  };
+
+  // Agents wait on their held action until it is answered; the newest question rings the bell.
+  const approve = (request: ApprovalRequest) => new Promise<boolean>((resolveApproval) => {
+    state.approvals.push({ request, resolve: resolveApproval });
+    output.write('\u0007');
+    redraw();
+  });
+  const answer = (approved: boolean) => {
+    const approval = state.approvals.shift();
+    if (approval !== undefined) {
+      addNote(`${approved ? 'Approved' : 'Denied'}: agent "${approval.request.agentId}" ${approved ? 'may' : 'may not'} ${describeApproval(approval.request)}.`);
+      approval.resolve(approved);
+    }
+  };
+
+  const loadPreview = async () => {
+    const symbol = state.symbols.results[state.symbols.selected];
+    if (symbol === undefined) {
+      state.symbols.preview = [];
+      return;
+    }
+    const source = await readFile(resolve(basePath, symbol.file), 'utf8').catch(() => undefined);
+    state.symbols.preview = source === undefined
+      ? ['(the file could not be read)']
+      : source.split('\n').slice(symbol.line - 1, symbol.line - 1 + MAX_PREVIEW_LINES).map((line) => line.replace(/\t/g, '  '));
+  };
+  // Typing searches once it pauses; a search that a newer one overtook is dropped.
+  const search = () => {
+    clearTimeout(searchTimer);
+    searchTimer = setTimeout(async () => {
+      const current = ++searches;
+      const query = state.symbols.query.trim();
+      try {
+        const results = query.length === 0 ? [] : await runtime.searchCodeSymbols({ query, limit: SYMBOL_SEARCH_LIMIT, basePath });
+        if (current !== searches) {
+          return;
+        }
+        Object.assign(state.symbols, { results, selected: 0, error: undefined });
+        await loadPreview();
+      } catch (error) {
+        state.symbols.error = `The symbol index could not be searched: ${error instanceof Error ? error.message : String(error)}`;
+      }
+      redraw();
+    }, SYMBOL_SEARCH_DELAY_MS);
+  };
+
+  const onSymbolKey = (text: string | undefined, key: Keypress | undefined) => {
+    const { symbols } = state;
+    switch (key?.name) {
+      case 'up':
+      case 'down':
+        symbols.selected = Math.min(Math.max(symbols.selected + (key.name === 'up' ? -1 : 1), 0), Math.max(symbols.results.length - 1, 0));
+        void loadPreview().then(redraw);
+        return;
+      case 'escape':
+        symbols.query = '';
+        break;
+      case 'backspace':
+        symbols.query = symbols.query.slice(0, -1);
+        break;
+      default:
+        if (text === undefined || text.length !== 1 || text < ' ' || key?.ctrl === true || key?.meta === true) {
+          return;
+        }
+        symbols.query += text;
+    }
+    symbols.error = undefined;
+    search();
+    redraw();
+  };
+  const onKey = (text: string | undefined, key: Keypress | undefined) => {
+    if (key?.ctrl === true && key.name === 'c') {
+      leave();
+      return;
+    }
+    if (key?.name === 'tab') {
+      state.pane = state.pane === 'run' ? 'symbols' : 'run';
+      redraw();
+      return;
+    }
+    if (state.pane === 'symbols') {
+      onSymbolKey(text, key);
+      return;
+    }
+    const page = Math.max(output.rows - 10, 1);
+    const scrollBy = (lines: number) => {
+      state.scroll = Math.min(Math.max(state.scroll + lines, 0), Math.max(state.output.length - 1, 0));
+    };
+    switch (key?.name ?? text) {
+      case 'q':
+        leave();
+        return;
+      case 'y':
+      case 'n':
+        answer(key?.name === 'y' || text === 'y');
+        break;
+      case 'up':
+        scrollBy(1);
+        break;
+      case 'down':
+        scrollBy(-1);
+        break;
+      case 'pageup':
+        scrollBy(page);
+        break;
+      case 'pagedown':
+        scrollBy(-page);
+        break;
+      case 'end':
+        state.scroll = 0;
+        break;
+      default:
+        return;
+    }
+    redraw();
+  };
+
+  const startRun = (): Promise<RuntimePipelineResponse | RuntimeWorkflowResponse> => isPipeline
+    ? runtime.runPipeline({
+      file: target,
+      input: inputParse.value,
+      provider: options.provider,
+      budget: budget.value,
+      traceId,
+      sessionId: options.sessionId,
+      basePath,
+      surface: 'cli',
+      signal: controller.signal,
+      approve,
+      approvalPolicy: options.approvalPolicy,
+      onStageStart: (stage) => {
+        updateTuiStage(state, { id: stage.id, agent: stage.agent, status: 'running' });
+        redraw();
+      },
+      onStageEnd: (stage) => {
+        updateTuiStage(state, { id: stage.id, agent: stage.agent, status: stage.status, durationMs: stage.durationMs });
+        redraw();
+      },
+      onText: (text, stageId) => {
+        appendTuiOutput(state, text, stageId);
+        redraw();
+      },
+    })
+    : runtime.runWorkflow({
+      workflowId: target,
+      traceId,
+      workflowDir,
+      basePath,
+      provider: options.provider,
+      sessionId: options.sessionId,
+      input: buildWorkflowInput(target, args, options, inputParse.value ?? {}),
+      surface: 'cli',
+      noCache: options.noCache,
+      onStepStart: (stepId) => {
+        updateTuiStage(state, { id: stepId, status: 'running' });
+        redraw();
+      },
+      onStepComplete: (step) => {
+        updateTuiStage(state, { id: step.stepId, status: step.success ? 'succeeded' : 'failed', durationMs: step.durationMs });
+        redraw();
+      },
+      onText: (text, stepId) => {
+        appendTuiOutput(state, text, stepId);
+        redraw();
+      },
+      onProviderFailover: (failover) => {
+        addNote(`Provider ${failover.from} failed after ${failover.partialChars} characters; ${failover.to} continued the reply.`);
+        redraw();
+      },
+    });
+
+  const usageTimer = setInterval(() => {
+    void refreshUsage();
+    redraw();
+  }, USAGE_POLL_INTERVAL_MS);
+  output.write(ENTER_SCREEN);
+  input.setRawMode(true);
+  emitKeypressEvents(input);
+  input.on('keypress', onKey);
+  input.resume();
+  output.on('resize', draw);
+  let outcome: RunOutcome | undefined;
+  try {
+    draw();
+    outcome = await Promise.race([
+      startRun().then((result) => ({ result }), (error: unknown) => ({ error })),
+      left.then(() => undefined),
+    ]);
+    if (outcome !== undefined) {
+      clearInterval(usageTimer);
+      const failed = 'error' in outcome || !outcome.result.success;
+      state.status = failed ? 'failed' : 'succeeded';
+      state.finishedAt = Date.now();
+      state.notice = ` ${failed ? `Failed: ${describeFailure(outcome)}` : 'Completed'}. Press q to leave.`;
+      await refreshUsage();
+      draw();
+      await left;
+    }
+  } finally {
+    clearInterval(usageTimer);
+    clearTimeout(redrawTimer);
+    clearTimeout(searchTimer);
+    for (const approval of state.approvals.splice(0)) {
+      approval.resolve(false);
+    }
+    input.off('keypress', onKey);
+    input.setRawMode(false);
+    input.pause();
+    output.off('resize', draw);
+    output.write(LEAVE_SCREEN);
+  }
+
+  if (outcome === undefined) {
+    controller.abort();
+    return failure(`Left ${what} while it was running (trace ${traceId}).${isPipeline ? `\nResume with: ax workflow resume ${traceId}` : ''}`, { traceId, status: 'stopped' });
+  }
+  if ('error' in outcome) {
+    return failureFromError(`run ${what}`, outcome.error);
+  }
+  const cost = state.usage !== undefined && state.usage.calls > 0 ? [`Cost: ${formatUsageTotals(state.usage)}`] : [];
+  const lines = [
+    `${'pipeline' in outcome.result ? `Pipeline "${outcome.result.pipeline}"` : `Workflow "${target}"`} ${outcome.result.success ? 'completed' : `failed: ${describeFailure(outcome)}`}.`,
+    '',
+    ...state.stages.map((stage) => `${stage.id}: ${stage.status}${stage.durationMs !== undefined ? ` (${stage.durationMs}ms)` : ''}`),
+    ...cost,
+    `Trace: ${traceId}`,
+  ];
+  return outcome.result.success ? success(lines.join('\n'), outcome.result) : failure(lines.join('\n'), outcome.result);
+}
+
+function describeFailure(outcome: RunOutcome): string {
+  if ('error' in outcome) {
+    return outcome.error instanceof Error ? outcome.error.message : String(outcome.error);
+  }
+  return outcome.result.error?.message ?? 'unknown error';
+}
@@ -55,7 +55,7 @@ function isNewer(a, b) {
     return am !== bm ? am > bm : an_ !== bn_ ? an_ > bn_ : ap_ > bp_;
 }
 async function promptConfirm(message) {
-    const rl = createInterface({ input: process.stdin, output: process.stdout });
+    const rl = createInterface({ input: process.stdin, output: process.stderr });
     return new Promise((resolve) => {
         rl.question(message, (answer) => {
             rl.close();
@@ -84,13 +84,14 @@ export async function updateCommand(args, options) {
     }
     const checkOnly = args.includes('--check') || args.includes('-c');
     const skipConfirm = args.includes('--yes') || args.includes('-y');
-    console.log('\nAutomatosX Update Checker\n');
+    // Progress goes to stderr, leaving stdout to the result, as --json needs.
+    console.error('\nAutomatosX Update Checker\n');
     try {
         const currentVersion = CLI_VERSION;
-        console.log(`Current version: ${currentVersion}`);
-        console.log('Checking for updates...');
+        console.error(`Current version: ${currentVersion}`);
+        console.error('Checking for updates...');
         const latestVersion = await getLatestVersion();
-        console.log(`Latest version:  ${latestVersion}\n`);
+        console.error(`Latest version:  ${latestVersion}\n`);
         if (latestVersion === 'unknown') {
             return failure('Could not check for updates. Check your network connection.', { currentVersion, latestVersion });
         }
@@ -100,10 +101,10 @@ export async function updateCommand(args, options) {
         if (!isNewer(latestVersion, currentVersion)) {
             return success(`Your version (${currentVersion}) is newer than published (${latestVersion}).`, { currentVersion, latestVersion, development: true });
         }
-        console.log(`Update available: ${currentVersion} → ${latestVersion}`);
+        console.error(`Update available: ${currentVersion} → ${latestVersion}`);
         if (checkOnly) {
-            console.log(`\nTo install: npm install -g ${PACKAGE_NAME}@${latestVersion}`);
-            console.log('Or run: ax update\n');
+            console.error(`\nTo install: npm install -g ${PACKAGE_NAME}@${latestVersion}`);
+            console.error('Or run: ax update\n');
             return success('', { currentVersion, latestVersion, updateAvailable: true });
         }
         if (!skipConfirm) {
@@ -112,9 +113,9 @@ export async function updateCommand(args, options) {
                 return success('Update cancelled.', { currentVersion, latestVersion, cancelled: true });
             }
         }
-        console.log('\nInstalling update...\n');
+        console.error('\nInstalling update...\n');
         await installUpdate(latestVersion);
-        console.log(`\nUpdated to ${latestVersion}. Run "ax --version" to verify.\n`);
+        console.error(`\nUpdated to ${latestVersion}. Run "ax --version" to verify.\n`);
         return success('', { currentVersion, latestVersion, updated: true });
     }
     catch (error) {
@@ -61,7 +61,7 @@ function isNewer(a: string, b: string): boolean {
 }
 
 async function promptConfirm(message: string): Promise<boolean> {
-  const rl = createInterface({ input: process.stdin, output: process.stdout });
+  const rl = createInterface({ input: process.stdin, output: process.stderr });
   return new Promise<boolean>((resolve) => {
     rl.question(message, (answer) => {
       rl.close();
@@ -95,15 +95,16 @@ export async function updateCommand(args: string[], options: CLIOptions): Promis
   const checkOnly   = args.includes('--check') || args.includes('-c');
   const skipConfirm = args.includes('--yes')   || args.includes('-y');
 
-  console.log('\nAutomatosX Update Checker\n');
+  // Progress goes to stderr, leaving stdout to the result, as --json needs.
+  console.error('\nAutomatosX Update Checker\n');
 
   try {
     const currentVersion = CLI_VERSION;
-    console.log(`Current version: ${currentVersion}`);
-    console.log('Checking for updates...');
+    console.error(`Current version: ${currentVersion}`);
+    console.error('Checking for updates...');
 
     const latestVersion = await getLatestVersion();
-    console.log(`Latest version:  ${latestVersion}\n`);
+    console.error(`Latest version:  ${latestVersion}\n`);
 
     if (latestVersion === 'unknown') {
       return failure('Could not check for updates. Check your network connection.', { currentVersion, latestVersion });
@@ -120,11 +121,11 @@ export async function updateCommand(args: string[], options: CLIOptions): Promis
       );
     }
 
-    console.log(`Update available: ${currentVersion} → ${latestVersion}`);
+    console.error(`Update available: ${currentVersion} → ${latestVersion}`);
 
     if (checkOnly) {
-      console.log(`\nTo install: npm install -g ${PACKAGE_NAME}@${latestVersion}`);
-      console.log('Or run: ax update\n');
+      console.error(`\nTo install: npm install -g ${PACKAGE_NAME}@${latestVersion}`);
+      console.error('Or run: ax update\n');
       return success('', { currentVersion, latestVersion, updateAvailable: true });
     }
 
@@ -135,10 +136,10 @@ export async function updateCommand(args: string[], options: CLIOptions): Promis
       }
     }
 
-    console.log('\nInstalling update...\n');
+    console.error('\nInstalling update...\n');
     await installUpdate(latestVersion);
 
-    console.log(`\nUpdated to ${latestVersion}. Run "ax --version" to verify.\n`);
+    console.error(`\nUpdated to ${latestVersion}. Run "ax --version" to verify.\n`);
     return success('', { currentVersion, latestVersion, updated: true });
 
   } catch (error) {
@@ -0,0 +1,80 @@
+import type { PipelineStageResult } from '@defai.digital/shared-runtime';
+import type { CLIOptions, CommandResult } from '../types.js';
+import { createTerminalApprover } from '../utils/approvals.js';
+import { createRuntime, failure, failureFromError, formatBudget, success, usageError } from '../utils/formatters.js';
+import { parseBudgetOptions, parseOptionalJsonInput } from '../utils/validation.js';
+
+const USAGE = 'ax workflow run <pipeline.yaml> [--input <json-object>] [--provider <name>] [--max-cost-usd <usd>] [--max-tokens <n>] [--max-duration <duration>] [--approval-policy <file>] | ax workflow resume <run-id>';
+
+export async function workflowCommand(args: string[], options: CLIOptions): Promise<CommandResult> {
+  const [subcommand, target, ...rest] = args;
+  if ((subcommand !== 'run' && subcommand !== 'resume') || target === undefined || rest.length > 0) {
+    return usageError(USAGE);
+  }
+  const inputParse = parseOptionalJsonInput(options.input);
+  if (inputParse.error !== undefined) {
+    return failure(`Invalid JSON in --input parameter: ${inputParse.error}`);
+  }
+  const budget = parseBudgetOptions(options);
+  if (budget.error !== undefined) {
+    return failure(budget.error);
+  }
+  const runtime = createRuntime(options);
+  const basePath = options.outputDir ?? process.cwd();
+  // Actions that agents' `approvals` hold are put to the terminal, when there is one.
+  const approvals = { approve: createTerminalApprover(), approvalPolicy: options.approvalPolicy };
+  const progress = options.verbose ? {
+    onStageStart: (stage: { id: string; agent: string }) => logProgress(`stage ${stage.id} started (${stage.agent})`),
+    onStageEnd: (stage: PipelineStageResult) => logProgress(`stage ${stage.id} ${stage.status} (${stage.durationMs}ms)`),
+  } : {};
+
+  try {
+    // A resumed run keeps the pipeline, input and provider it started with, and its budget unless given another.
+    const result = subcommand === 'resume'
+      ? await runtime.resumePipeline({ runId: target, budget: budget.value, basePath, surface: 'cli', ...approvals, ...progress })
+      : await runtime.runPipeline({
+        file: target,
+        input: inputParse.value,
+        provider: options.provider,
+        budget: budget.value,
+        traceId: options.traceId,
+        sessionId: options.sessionId,
+        basePath,
+        surface: 'cli',
+        ...approvals,
+        ...progress,
+      });
+    const lines = [
+      `Pipeline "${result.pipeline}" ${result.success ? 'completed' : 'failed'}.`,
+      '',
+      ...result.stages.map(formatStage),
+      ...(result.budget !== undefined ? [formatBudget(result.budget)] : []),
+      ...result.warnings.map((warning) => `Warning: ${warning}`),
+      '',
+      `Artifacts: ${result.artifactDir}`,
+      `Trace: ${result.traceId}`,
+      ...(result.success ? [] : [`Resume with: ax workflow resume ${result.traceId}`]),
+    ];
+    return result.success ? success(lines.join('\n'), result) : failure(lines.join('\n'), result);
+  } catch (error) {
+    return failureFromError(subcommand === 'resume' ? `resume pipeline run "${target}"` : `run pipeline "${target}"`, error);
+  }
+}
+
+function formatStage(stage: PipelineStageResult): string {
+  if (stage.resumed === true) {
+    return `- ${stage.id}: ${stage.status} earlier, kept from the checkpoint`;
+  }
+  if (stage.status === 'skipped') {
+    return `- ${stage.id}: skipped, ${stage.reason ?? 'it did not run'}`;
+  }
+  const attempts = stage.runs.reduce((count, run) => count + run.attempts, 0);
+  const runs = stage.runs.length > 1 || stage.runs[0]?.item !== undefined ? `${stage.runs.length} item${stage.runs.length === 1 ? '' : 's'}, ` : '';
+  const retries = attempts > stage.runs.length ? `, ${attempts - stage.runs.length} retr${attempts - stage.runs.length === 1 ? 'y' : 'ies'}` : '';
+  const error = stage.status === 'failed' ? `: ${stage.runs.at(-1)?.error?.message ?? 'the agent run did not succeed'}` : '';
+  return `- ${stage.id}: ${stage.status} (${runs}${stage.durationMs}ms${retries})${error}`;
+}
+
+function logProgress(message: string): void {
+  process.stderr.write(`[${new Date().toISOString()}] ${message}\n`);
+}
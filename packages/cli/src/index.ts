@@ -1,40 +1,65 @@
 import packageJson from '../../../package.json' with { type: 'json' };
+import { CONFIG_FLAGS_ENV_VAR, CONFIG_PROFILE_ENV_VAR, isReadOnlyEnv, loadLayeredConfig } from '@defai.digital/shared-runtime';
 import {
   abilityCommand,
   agentCommand,
   architectCommand,
+  askCommand,
+  attachCommand,
   auditCommand,
+  cacheCommand,
   callCommand,
   cleanupCommand,
+  codeCommand,
+  completionsCommand,
   configCommand,
+  costCommand,
+  debugCommand,
   doctorCommand,
   discussCommand,
+  evalCommand,
   feedbackCommand,
   guardCommand,
   helpCommand,
   historyCommand,
+  hookCommand,
   initCommand,
   iterateCommand,
   monitorCommand,
+  tuiCommand,
+  chatCommand,
+  outlineCommand,
   listCommand,
+  maintainCommand,
+  memoryCommand,
   mcpCommand,
   qaCommand,
   releaseCommand,
   reviewCommand,
   resumeCommand,
+  replayCommand,
   runCommand,
   scaffoldCommand,
+  searchCommand,
   sessionCommand,
   setupCommand,
   shipCommand,
   statusCommand,
+  testCommand,
   traceCommand,
   updateCommand,
+  workflowCommand,
 } from './commands/index.js';
 import type { CLIOptions, CommandHandler, CommandResult, ParsedCommand } from './types.js';
+import type { CliDefinition } from './utils/completions.js';
 import { failure, success } from './utils/formatters.js';
 
 export const CLI_VERSION = packageJson.version;
+/**
+ * Version of the `--json` envelope: `schemaVersion`, `command`, `success`, `message`, `data` and
+ * `exitCode`. Adding fields leaves it as it is; renaming, removing or changing one bumps it.
+ */
+export const JSON_OUTPUT_VERSION = 1;
 export const CLI_COMMAND_NAMES = [
   'help',
   'version',
@@ -42,6 +67,9 @@ export const CLI_COMMAND_NAMES = [
   'ability',
   'call',
   'run',
+  'workflow',
+  'eval',
+  'attach',
   'ship',
   'architect',
   'audit',
@@ -54,21 +82,42 @@ export const CLI_COMMAND_NAMES = [
   'config',
   'cleanup',
   'feedback',
+  'cost',
+  'cache',
   'history',
   'list',
   'monitor',
+  'tui',
+  'chat',
+  'completions',
   'scaffold',
   'trace',
   'discuss',
   'guard',
   'resume',
+  'replay',
   'agent',
   'mcp',
   'session',
   'review',
+  'code',
+  'outline',
+  'search',
+  'ask',
+  'test',
+  'hook',
+  'debug',
+  'maintain',
+  'memory',
   'update',
 ] as const;
 
+// `jsonl`, `md`, `html`, `dot` and `mermaid` are read by the commands that stream records or emit
+// documents and diagrams; the rest print text.
+const OUTPUT_FORMATS = ['text', 'json', 'jsonl', 'md', 'html', 'dot', 'mermaid'] as const;
+
+const READ_ONLY_BLOCKED_COMMANDS = new Set(['setup', 'init', 'scaffold', 'cleanup', 'update']);
+
 const GLOBAL_BOOLEAN_FLAGS = new Map<string, keyof CLIOptions>([
   ['--help', 'help'],
   ['--version', 'version'],
@@ -78,6 +127,11 @@ const GLOBAL_BOOLEAN_FLAGS = new Map<string, keyof CLIOptions>([
   ['--compact', 'compact'],
   ['--dry-run', 'dryRun'],
   ['--quiet', 'quiet'],
+  ['--read-only', 'readOnly'],
+  ['--detach', 'detach'],
+  ['--no-cache', 'noCache'],
+  ['--no-stream', 'noStream'],
+  ['--json', 'json'],
 ]);
 
 const GLOBAL_STRING_FLAGS = new Map<string, keyof CLIOptions>([
@@ -88,6 +142,10 @@ const GLOBAL_STRING_FLAGS = new Map<string, keyof CLIOptions>([
   ['--session-id', 'sessionId'],
   ['--input', 'input'],
   ['--max-time', 'maxTime'],
+  ['--max-cost-usd', 'maxCostUsd'],
+  ['--max-duration', 'maxDuration'],
+  ['--approval-policy', 'approvalPolicy'],
+  ['--claim', 'claim'],
   ['--category', 'category'],
   ['--agent', 'agent'],
   ['--task', 'task'],
@@ -95,6 +153,7 @@ const GLOBAL_STRING_FLAGS = new Map<string, keyof CLIOptions>([
   ['--team', 'team'],
   ['--provider', 'provider'],
   ['--output-dir', 'outputDir'],
+  ['--profile', 'profile'],
 ]);
 
 const GLOBAL_NUMBER_FLAGS = new Map<string, keyof CLIOptions>([
@@ -108,9 +167,17 @@ const GLOBAL_ARRAY_FLAGS = new Map<string, keyof CLIOptions>([
   ['--tags', 'tags'],
 ]);
 
+// Flags that may be given more than once; every value is kept, in order.
+const GLOBAL_REPEATED_FLAGS = new Map<string, keyof CLIOptions>([
+  ['--config', 'configOverrides'],
+]);
+
 const COMMAND_REGISTRY: Record<string, CommandHandler> = {
   help: helpCommand,
   run: runCommand,
+  workflow: workflowCommand,
+  eval: evalCommand,
+  attach: attachCommand,
   ship: shipCommand,
   architect: architectCommand,
   audit: auditCommand,
@@ -124,11 +191,16 @@ const COMMAND_REGISTRY: Record<string, CommandHandler> = {
   cleanup: cleanupCommand,
   ability: abilityCommand,
   feedback: feedbackCommand,
+  cost: costCommand,
+  cache: cacheCommand,
   call: callCommand,
   history: historyCommand,
   iterate: iterateCommand,
   list: listCommand,
   monitor: monitorCommand,
+  tui: tuiCommand,
+  chat: chatCommand,
+  completions: (args, options) => completionsCommand(args, options, describeCli()),
   scaffold: scaffoldCommand,
   trace: traceCommand,
   discuss: discussCommand,
@@ -137,7 +209,17 @@ const COMMAND_REGISTRY: Record<string, CommandHandler> = {
   mcp: mcpCommand,
   session: sessionCommand,
   review: reviewCommand,
+  code: codeCommand,
+  outline: outlineCommand,
+  search: searchCommand,
+  ask: askCommand,
+  test: testCommand,
+  hook: hookCommand,
+  debug: debugCommand,
+  maintain: maintainCommand,
+  memory: memoryCommand,
   resume: resumeCommand,
+  replay: replayCommand,
   update: updateCommand,
 };
 
@@ -150,10 +232,32 @@ const COMMAND_HELP: Record<string, { usage: string[]; description: string }> = {
     ],
   },
   run: {
-    description: 'Run a workflow directly through the shared runtime bridge.',
+    description: 'Run a workflow directly through the shared runtime bridge. Provider replies stream as they arrive.',
     usage: [
       'ax run <workflow-id>',
       'ax run <workflow-id> --input <json-object>',
+      'ax run <workflow-id> --detach',
+      'ax run <workflow-id> --no-stream',
+      'ax run <workflow-id> --max-tokens 200000 --max-duration 15m',
+      'ax run <workflow-id> --session-id <session-id> --claim next',
+    ],
+  },
+  workflow: {
+    description: 'Run a YAML pipeline of agent stages with when: conditions, foreach: loops over files, retries and artifacts passed between stages, or resume a run from its last completed stage.',
+    usage: [
+      'ax workflow run <pipeline.yaml>',
+      'ax workflow run <pipeline.yaml> --input <json-object>',
+      'ax workflow run <pipeline.yaml> --provider <name> --verbose',
+      'ax workflow run <pipeline.yaml> --max-cost-usd 2 --max-duration 30m',
+      'ax workflow run <pipeline.yaml> --approval-policy ci/approvals.json',
+      'ax workflow resume <run-id>',
+    ],
+  },
+  attach: {
+    description: 'Stream the log of a detached run until it finishes. Ctrl+C detaches again without stopping the run.',
+    usage: [
+      'ax attach <run-id>',
+      'ax attach <run-id> --no-follow',
     ],
   },
   call: {
@@ -162,8 +266,14 @@ const COMMAND_HELP: Record<string, { usage: string[]; description: string }> = {
       'ax call <prompt>',
       'ax call --files src/index.ts,README.md "<prompt>"',
       'ax call --system "<system-prompt>" "<prompt>"',
+      'ax call --stop "END" --stop "---" "<prompt>"',
       'ax call --autonomous --intent analysis --max-rounds 2 "<prompt>"',
       'ax call --autonomous --goal "<outcome>" --require-real "<prompt>"',
+      'ax call --list-models --provider ollama',
+      'ax call --consensus judge --providers claude,gemini,codex "<prompt>"',
+      'ax call --consensus fastest --providers claude,gemini "<prompt>"',
+      'ax call --response-format json "<prompt>"',
+      'ax call --schema release.schema.json "<prompt>"',
     ],
   },
   ship: {
@@ -215,7 +325,7 @@ const COMMAND_HELP: Record<string, { usage: string[]; description: string }> = {
     ],
   },
   doctor: {
-    description: 'Validate workspace, workflow, and shared runtime readiness.',
+    description: 'Validate workspace, workflow, and shared runtime readiness, plus provider CLIs and logins, config, state directory permissions, grammars and MCP connectivity, with a fix for each problem.',
     usage: [
       'ax doctor',
       'ax doctor --workflow-dir <path>',
@@ -226,16 +336,20 @@ const COMMAND_HELP: Record<string, { usage: string[]; description: string }> = {
     description: 'Show active sessions, running traces, and provider/runtime readiness.',
     usage: [
       'ax status',
+      'ax status --probe',
       'ax status --limit 5',
     ],
   },
   config: {
-    description: 'Inspect or update workspace config used by runtime and provider bridges.',
+    description: 'Inspect or update workspace config used by runtime and provider bridges, explain which layer set a value (system, user, project, profile, environment or --config flag), and validate it against the config schema.',
     usage: [
       'ax config show',
       'ax config get <path>',
       'ax config set <path> <value>',
       'ax config set <path> --input <json-value>',
+      'ax config explain <path>',
+      'ax --profile ci --config providers.default=gemini config explain providers.default',
+      'ax config validate',
     ],
   },
   ability: {
@@ -257,6 +371,24 @@ const COMMAND_HELP: Record<string, { usage: string[]; description: string }> = {
       'ax feedback submit --agent <agent-id> --task "<task>" --input <json-object>',
     ],
   },
+  cost: {
+    description: 'Report provider tokens, latency, and cost by agent, provider, or project.',
+    usage: [
+      'ax cost',
+      'ax cost --since 7d --by agent',
+      'ax cost --since 2026-01-01 --by project',
+      'ax cost --trace-id <trace-id>',
+    ],
+  },
+  cache: {
+    description: 'Inspect or clear the provider response cache.',
+    usage: [
+      'ax cache stats',
+      'ax cache clear',
+      'ax call "<prompt>" --no-cache',
+      'ax run <workflow-id> --no-cache',
+    ],
+  },
   cleanup: {
     description: 'Auto-close stale sessions and traces in shared runtime storage.',
     usage: [
@@ -309,10 +441,16 @@ const COMMAND_HELP: Record<string, { usage: string[]; description: string }> = {
       'ax agent list',
       'ax agent get <agent-id>',
       'ax agent register --input <json-object>',
+      'ax agent register <profile-file>',
       'ax agent remove <agent-id>',
       'ax agent capabilities',
       'ax agent run <agent-id> --task <text>',
+      'ax agent run <agent-id> --task <text> --max-tokens 50000 --max-duration 10m',
+      'ax agent run <agent-id> --session-id <session-id> --claim <task-id|next>',
+      'ax agent render <agent-id> [--task <text>]',
+      'ax agent delegate <agent-id> <contract-file> --task <text>',
       'ax agent recommend --task <text>',
+      'ax agent suggest [--dry-run]',
     ],
   },
   mcp: {
@@ -325,13 +463,23 @@ const COMMAND_HELP: Record<string, { usage: string[]; description: string }> = {
       'ax mcp prompts',
       'ax mcp prompt <prompt-name> --input <json-object>',
       'ax mcp call <tool-name> --input <json-object>',
+      'ax mcp serve',
+      'ax mcp serve --http --port 3100',
     ],
   },
   session: {
-    description: 'Create and manage collaboration sessions.',
+    description: 'Create and manage collaboration sessions, resume, branch or export their conversations, and coordinate parallel runs through their scratchpad.',
     usage: [
       'ax session list',
       'ax session create --input <json-object>',
+      'ax session resume <session-id> --task "Now add tests" [--agent <agent-id>]',
+      'ax session branch <session-id> --input \'{"turns": 2}\'',
+      'ax session export <session-id> transcript.html',
+      'ax session add-task <session-id> --task "Limit the API" --input \'{"paths": ["services/api"]}\'',
+      'ax session tasks <session-id>',
+      'ax session claim <session-id> [<task-id>] [--agent <claimant>]',
+      'ax session post <session-id> <task-id> --input \'{"result": "Done"}\' [--agent <claimant>]',
+      'ax session release <session-id> <task-id>',
       'ax session join <session-id> --input <json-object>',
     ],
   },
@@ -343,6 +491,115 @@ const COMMAND_HELP: Record<string, { usage: string[]; description: string }> = {
       'ax review list',
     ],
   },
+  code: {
+    description: 'Index Dockerfiles, shell scripts, Terraform, Ruby, PHP, and C# into a symbol index for agents to query.',
+    usage: [
+      'ax code index [paths...]',
+      'ax code index deploy --max-files 200',
+      'ax code index --format jsonl > symbols.jsonl',
+      'ax code index --watch',
+      'ax code symbols [query]',
+      'ax code symbols --kind port --language dockerfile',
+      'ax code symbols --kind resource --language hcl',
+      'ax code symbols --kind method --no-tests',
+      'ax code symbols --concurrent',
+      'ax code symbols --id <stable-id>',
+      'ax code symbols --features tls,serde --target windows',
+      'ax code symbols --derive Serialize',
+      'ax code parse --stdin --language php --file src/Draft.php',
+      'ax code unsafe --crate ffi',
+      'ax code signatures src/Billing src/Checkout.php',
+      'ax code refs Shop.MemoryStore.Save',
+      'ax code graph modules src --format dot',
+      'ax code graph calls src/Billing --format mermaid',
+      'ax code unreferenced src --kind method',
+      'ax code embed --embedder ollama',
+      'ax code embed src --embedder openai --model text-embedding-3-large',
+      'ax code daemon start',
+      'ax code daemon start --watch',
+      'ax code daemon status',
+    ],
+  },
+  outline: {
+    description: 'Print the symbol tree of files or directories with signatures and first doc lines; --format md renders Markdown.',
+    usage: [
+      'ax outline src/lib.rs',
+      'ax outline crates/parser --format md',
+      'ax outline src/Billing --format json',
+    ],
+  },
+  search: {
+    description: 'Fuzzy-search indexed symbols, grep file contents through a trigram index, or search both and memory at once.',
+    usage: [
+      'ax search symbols DatProc',
+      'ax search symbols "kind:trait lang:rust Handler"',
+      'ax search symbols parser::Parser::new',
+      'ax search symbols path:src/Billing Invoice --limit 5',
+      'ax search text "TODO(release)"',
+      'ax search text "fn\\s+parse_\\w+" --regex --path crates/parser',
+      'ax search hybrid "where is the retry backoff computed"',
+    ],
+  },
+  ask: {
+    description: 'Answer questions about the project from the symbol index and module graph, with file references.',
+    usage: [
+      'ax ask "which modules depend on the provider router?"',
+      'ax ask "where is the Order class defined?"',
+      'ax ask "what does packages/cli/src/index.ts import?" --provider gemini',
+    ],
+  },
+  test: {
+    description: 'List the tests a change set can affect (imports, changed symbols, naming conventions) and optionally run only those.',
+    usage: [
+      'ax test --impacted',
+      'ax test --impacted --base main',
+      'ax test --impacted --run',
+    ],
+  },
+  hook: {
+    description: 'Install a git pre-commit hook that runs syntax, secret, and optional quick review checks.',
+    usage: [
+      'ax hook install',
+      'ax hook install --force',
+      'ax hook uninstall',
+      'ax hook run pre-commit --mode annotate',
+    ],
+  },
+  debug: {
+    description: 'Package redacted config, logs, the failing trace, provider resolution, and platform info for bug reports.',
+    usage: [
+      'ax debug bundle',
+      'ax debug bundle --trace-id <trace-id>',
+      'ax debug bundle ./ax-debug.tar.gz',
+    ],
+  },
+  maintain: {
+    description: 'Refresh the code index, expire old memory, rotate logs, re-check providers, and prune caches.',
+    usage: [
+      'ax maintain',
+      'ax maintain --dry-run',
+      'ax maintain --if-due',
+      'ax maintain --only logs,caches',
+    ],
+  },
+  memory: {
+    description: 'List memory and stored facts by project or agent scope, find memory whose source changed, manage memory namespaces, prune by retention policy, export or import .axm archives, and encrypt memory at rest.',
+    usage: [
+      'ax memory list --project',
+      'ax memory list notes --project --agent reviewer',
+      'ax memory facts --subject billing-api',
+      'ax memory facts --predicate calls --object ledger-api --project',
+      'ax memory stale --project --invalidate',
+      'ax memory namespaces',
+      'ax memory copy notes @project:automatosx-3f2a9c1b/notes',
+      'ax memory merge scratch drafts --into notes',
+      'ax memory prune --dry-run',
+      'ax memory export team.axm notes --project',
+      'ax memory import team.axm',
+      'ax memory key --keychain',
+      'ax memory encrypt',
+    ],
+  },
   version: {
     description: 'Show the AutomatosX CLI version.',
     usage: [
@@ -377,12 +634,37 @@ const COMMAND_HELP: Record<string, { usage: string[]; description: string }> = {
       'ax monitor --no-open',
     ],
   },
+  tui: {
+    description: 'Run a pipeline or workflow in a full-screen terminal UI: its stages, streaming output, token and cost meters, and pending approvals, with a pane for browsing the symbol index.',
+    usage: [
+      'ax tui <pipeline.yaml>',
+      'ax tui <pipeline.yaml> --input <json-object> --approval-policy ci/approvals.json',
+      'ax tui <workflow-id> --session-id <session-id>',
+    ],
+  },
+  chat: {
+    description: 'Chat with an agent in a persistent loop that keeps the session\'s conversation between messages, with slash commands to attach files (/files), switch agents (/agent <name>), search memory (/memory search), and show or undo the workspace changes of the last reply (/diff, /undo).',
+    usage: [
+      'ax chat <agent-id>',
+      'ax chat --session-id <session-id>',
+      'ax chat <agent-id> --provider <name> --approval-policy ci/approvals.json',
+    ],
+  },
+  completions: {
+    description: 'Print a completion script for bash, zsh, fish or PowerShell. It completes commands, subcommands and flags from this CLI\'s own definition, and agent names, provider names, session IDs and workflow ids from the workspace, so it stays current as both change.',
+    usage: [
+      'ax completions <bash|zsh|fish|powershell>',
+      'eval "$(ax completions bash)"',
+      'ax completions complete "ax session resume "',
+    ],
+  },
   scaffold: {
-    description: 'Generate contract-first components: Zod schemas, domain packages, guard policies.',
+    description: 'Generate contract-first components: Zod schemas, domain packages, guard policies, interface implementations.',
     usage: [
       'ax scaffold contract <name>',
       'ax scaffold domain <name> --scope @myorg',
       'ax scaffold guard <policy-id>',
+      'ax scaffold impl <interface> --class <name>',
       'ax scaffold contract <name> --dry-run',
     ],
   },
@@ -394,12 +676,26 @@ const COMMAND_HELP: Record<string, { usage: string[]; description: string }> = {
       'ax update --yes',
     ],
   },
+  eval: {
+    description: 'Run an eval suite of agent tasks with assertions across agents and providers, and compare pass rates and costs with the previous run.',
+    usage: [
+      'ax eval run .automatosx/evals/helpers.yaml',
+      'ax eval run <suite.yaml> --providers claude,gemini',
+      'ax eval run <suite.yaml> --agents rust-engineer,rust-engineer-v2 --case slugify',
+    ],
+  },
   resume: {
     description: 'Rerun a prior workflow or discussion trace using its stored execution context.',
     usage: [
       'ax resume <trace-id>',
     ],
   },
+  replay: {
+    description: 'Replay a recorded agent run or pipeline from its replay log without calling providers, and report where it diverged from the recording.',
+    usage: [
+      'ax replay <trace-id>',
+    ],
+  },
 };
 
 export async function executeCli(argv: string[]): Promise<CommandResult> {
@@ -437,7 +733,7 @@ export async function executeCli(argv: string[]): Promise<CommandResult> {
   }
 
   if (parsed.command === 'iterate') {
-    return executeIterateCommand(parsed.args, parsed.options);
+    return withConfigFlags(parsed.options, () => executeIterateCommand(parsed.args, parsed.options));
   }
 
   const handler = COMMAND_REGISTRY[parsed.command];
@@ -445,11 +741,72 @@ export async function executeCli(argv: string[]): Promise<CommandResult> {
     return failure(`Unknown command: ${parsed.command}\nRun "ax help" to see the available commands.`);
   }
 
-  if (parsed.options.iterate) {
-    return runIterativeHandler(parsed.command, handler, parsed.args, parsed.options);
+  if (parsed.options.readOnly && READ_ONLY_BLOCKED_COMMANDS.has(parsed.command)) {
+    return failure(`ax ${parsed.command} is disabled in read-only mode.`);
+  }
+
+  return withConfigFlags(parsed.options, () => parsed.options.iterate
+    ? runIterativeHandler(parsed.command, handler, parsed.args, parsed.options)
+    : handler(parsed.args, parsed.options));
+}
+
+// --profile and --config reach every config reader through the environment, so the providers and
+// detached runs this command starts see them too. The environment is put back once it finishes.
+async function withConfigFlags(options: CLIOptions, run: () => Promise<CommandResult>): Promise<CommandResult> {
+  if (options.profile === undefined && options.configOverrides === undefined) {
+    return run();
   }
 
-  return handler(parsed.args, parsed.options);
+  const previous = [CONFIG_PROFILE_ENV_VAR, CONFIG_FLAGS_ENV_VAR].map((name) => [name, process.env[name]] as const);
+  if (options.profile !== undefined) {
+    process.env[CONFIG_PROFILE_ENV_VAR] = options.profile;
+  }
+  if (options.configOverrides !== undefined) {
+    process.env[CONFIG_FLAGS_ENV_VAR] = JSON.stringify({
+      ...parseConfigFlags(process.env[CONFIG_FLAGS_ENV_VAR]),
+      ...Object.fromEntries(options.configOverrides.map((override) => {
+        const separator = override.indexOf('=');
+        return [override.slice(0, separator).trim(), parseConfigFlagValue(override.slice(separator + 1))];
+      })),
+    });
+  }
+
+  try {
+    if (options.profile !== undefined) {
+      const { profiles } = await loadLayeredConfig(options.outputDir ?? process.cwd());
+      if (!profiles.includes(options.profile)) {
+        const defined = profiles.length === 0 ? 'No profiles are defined.' : `Defined profiles: ${profiles.join(', ')}.`;
+        return failure(`Unknown config profile "${options.profile}". Define it under profiles.${options.profile} in .automatosx/config.json. ${defined}`);
+      }
+    }
+    return await run();
+  } finally {
+    for (const [name, value] of previous) {
+      if (value === undefined) {
+        delete process.env[name];
+      } else {
+        process.env[name] = value;
+      }
+    }
+  }
+}
+
+function parseConfigFlags(raw: string | undefined): Record<string, unknown> {
+  try {
+    const parsed = JSON.parse(raw ?? '{}') as unknown;
+    return typeof parsed === 'object' && parsed !== null && !Array.isArray(parsed) ? parsed as Record<string, unknown> : {};
+  } catch {
+    return {};
+  }
+}
+
+// JSON when it parses, as for ax config set; a plain string otherwise.
+function parseConfigFlagValue(raw: string): unknown {
+  try {
+    return JSON.parse(raw);
+  } catch {
+    return raw;
+  }
 }
 
 export function parseCommand(argv: string[]): ParsedCommand {
@@ -483,8 +840,8 @@ export function parseCommand(argv: string[]): ParsedCommand {
         break;
       }
 
-      if (stringKey === 'format' && value !== 'text' && value !== 'json') {
-        parseError = `Invalid value for ${token}: expected "text" or "json".`;
+      if (stringKey === 'format' && !(OUTPUT_FORMATS as readonly string[]).includes(value)) {
+        parseError = `Invalid value for ${token}: expected one of ${OUTPUT_FORMATS.map((format) => `"${format}"`).join(', ')}.`;
         break;
       }
 
@@ -528,6 +885,24 @@ export function parseCommand(argv: string[]): ParsedCommand {
       continue;
     }
 
+    const repeatedKey = GLOBAL_REPEATED_FLAGS.get(token);
+    if (repeatedKey !== undefined) {
+      const value = argv[index + 1];
+      if (value === undefined || value.startsWith('--')) {
+        parseError = `Missing value for ${token}.`;
+        break;
+      }
+
+      if (value.indexOf('=') <= 0) {
+        parseError = `Invalid value for ${token}: expected <path>=<value>.`;
+        break;
+      }
+
+      (options[repeatedKey] as string[] | undefined) = [...(options[repeatedKey] as string[] | undefined) ?? [], value];
+      index += 1;
+      continue;
+    }
+
     if (command === undefined && token === '--version') {
       command = 'version';
       continue;
@@ -541,6 +916,14 @@ export function parseCommand(argv: string[]): ParsedCommand {
     args.push(token);
   }
 
+  if (options.json && parseError === undefined) {
+    if (options.format !== 'text' && options.format !== 'json') {
+      parseError = `--json cannot be combined with --format ${options.format}.`;
+    } else {
+      options.format = 'json';
+    }
+  }
+
   return {
     command: command ?? (options.version ? 'version' : 'help'),
     args,
@@ -549,9 +932,12 @@ export function parseCommand(argv: string[]): ParsedCommand {
   };
 }
 
-export function renderCommandResult(result: CommandResult, options: CLIOptions): string {
+/** The output of a command's result; the JSON envelope names the `command` when it is given. */
+export function renderCommandResult(result: CommandResult, options: CLIOptions, command?: string): string {
   if (options.format === 'json') {
     return `${JSON.stringify({
+      schemaVersion: JSON_OUTPUT_VERSION,
+      ...(command !== undefined ? { command } : {}),
       success: result.success,
       message: result.message,
       data: result.data,
@@ -651,6 +1037,18 @@ function parseMaxTimeMs(value: string | undefined): number {
   }
 }
 
+// What `ax completions` completes from, so completion follows the commands and flags defined here.
+function describeCli(): CliDefinition {
+  return {
+    commands: Object.entries(COMMAND_HELP).map(([name, entry]) => ({ name, ...entry })),
+    flags: [
+      ...Array.from(GLOBAL_BOOLEAN_FLAGS.keys(), (flag) => ({ flag, takesValue: false })),
+      ...[GLOBAL_STRING_FLAGS, GLOBAL_NUMBER_FLAGS, GLOBAL_ARRAY_FLAGS, GLOBAL_REPEATED_FLAGS].flatMap((flags) => Array.from(flags.keys(), (flag) => ({ flag, takesValue: true }))),
+    ],
+    formats: OUTPUT_FORMATS,
+  };
+}
+
 function formatCommandHelp(command: string): string {
   const entry = COMMAND_HELP[command];
   if (entry === undefined) {
@@ -700,5 +1098,16 @@ function createDefaultOptions(): CLIOptions {
     outputDir: undefined,
     dryRun: false,
     quiet: false,
+    readOnly: isReadOnlyEnv(),
+    detach: false,
+    noCache: false,
+    noStream: false,
+    json: false,
+    maxCostUsd: undefined,
+    maxDuration: undefined,
+    approvalPolicy: undefined,
+    claim: undefined,
+    profile: undefined,
+    configOverrides: undefined,
   };
 }
@@ -3,13 +3,13 @@ import { executeCli, parseCommand, renderCommandResult } from './index.js';
 const argv = process.argv.slice(2);
 const parsed = parseCommand(argv);
 const result = await executeCli(argv);
-const output = renderCommandResult(result, parsed.options);
+const output = renderCommandResult(result, parsed.options, parsed.command);
+// JSON goes to stdout even for a failure, so a script always has a document to read.
+const destination = result.exitCode === 0 || parsed.options.format === 'json' ? process.stdout : process.stderr;
+// A pipe may still be taking streamed output, so exit only once the result has been written.
 if (output.length > 0) {
-    if (result.exitCode === 0) {
-        process.stdout.write(output);
-    }
-    else {
-        process.stderr.write(output);
-    }
+    destination.write(output, () => process.exit(result.exitCode));
+}
+else {
+    process.exit(result.exitCode);
 }
-process.exit(result.exitCode);
@@ -20,7 +20,12 @@ export interface CLIOptions {
   /**
    * Output format.
    */
-  format: 'text' | 'json';
+  format: 'text' | 'json' | 'jsonl' | 'md' | 'html' | 'dot' | 'mermaid';
+
+  /**
+   * Print the result as the versioned JSON envelope, as --format json does.
+   */
+  json?: boolean;
 
   /**
    * Optional global workflow directory override.
@@ -136,6 +141,56 @@ export interface CLIOptions {
    * Quiet mode.
    */
   quiet?: boolean;
+
+  /**
+   * Start the run in a detached background process.
+   */
+  detach?: boolean;
+
+  /**
+   * Read-only analysis mode: write tools, shell execution, and git mutations are disabled.
+   */
+  readOnly?: boolean;
+
+  /**
+   * Skip the provider response cache lookup; fresh responses still refresh it.
+   */
+  noCache?: boolean;
+
+  /**
+   * Print provider replies only when they complete instead of streaming them as they arrive.
+   */
+  noStream?: boolean;
+
+  /**
+   * Cost limit of a run in USD, as given to --max-cost-usd.
+   */
+  maxCostUsd?: string;
+
+  /**
+   * Wall-clock limit of a run, such as 90s, 15m or 2h.
+   */
+  maxDuration?: string;
+
+  /**
+   * Approval policy file of agent runs, instead of .automatosx/approval-policy.json.
+   */
+  approvalPolicy?: string;
+
+  /**
+   * Scratchpad task of the session a run claims, or `next` for the next free one.
+   */
+  claim?: string;
+
+  /**
+   * Config profile to apply, from profiles.<name> in the config files.
+   */
+  profile?: string;
+
+  /**
+   * Config values given as --config <path>=<value>, which override every config file.
+   */
+  configOverrides?: string[];
 }
 
 /**
@@ -0,0 +1,24 @@
+import { createInterface } from 'node:readline';
+import { describeApproval } from '@defai.digital/shared-runtime';
+/**
+ * Asks on the terminal whether an agent's held action may go ahead. Without a terminal, as in
+ * CI, there is nobody to ask: only what the approval policy allows goes ahead.
+ */
+export function createTerminalApprover(input = process.stdin, output = process.stderr) {
+    if (input.isTTY !== true) {
+        return undefined;
+    }
+    // Agents running side by side ask one at a time.
+    let queue = Promise.resolve(false);
+    return (request) => {
+        queue = queue.catch(() => false).then(() => new Promise((resolve) => {
+            const rl = createInterface({ input, output });
+            rl.question(`Agent "${request.agentId}" wants to ${describeApproval(request)}. Approve? [y/N] `, (answer) => {
+                rl.close();
+                const normalized = answer.toLowerCase().trim();
+                resolve(normalized === 'y' || normalized === 'yes');
+            });
+        }));
+        return queue;
+    };
+}
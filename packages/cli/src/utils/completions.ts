@@ -0,0 +1,316 @@
+import { readdir } from 'node:fs/promises';
+import { resolve } from 'node:path';
+
+/**
+ * Shell completion for `ax`. The scripts `ax completions <shell>` prints hand the command line to
+ * `ax completions complete`, which answers from the CLI's own commands, usage lines and flags, and
+ * from the workspace for agents, providers, sessions and workflows, so completion keeps up with the
+ * CLI without the script being generated again.
+ */
+export const COMPLETION_SHELLS = ['bash', 'zsh', 'fish', 'powershell'] as const;
+export type CompletionShell = (typeof COMPLETION_SHELLS)[number];
+
+/** What completion knows of the CLI: its commands with their help, and its global flags and output formats. */
+export interface CliDefinition {
+  commands: Array<{ name: string; description: string; usage: string[] }>;
+  flags: Array<{ flag: string; takesValue: boolean }>;
+  formats: readonly string[];
+}
+
+export interface Completion {
+  value: string;
+  description?: string;
+}
+
+/** The names only the workspace knows. */
+export type CompletionSource = 'agents' | 'providers' | 'sessions' | 'workflows';
+
+type CompletionKind = CompletionSource | 'commands' | 'formats' | 'files';
+
+interface UsageToken {
+  /** A subcommand or other word typed as it is; unset for a placeholder. */
+  literal?: string;
+  kind?: CompletionKind;
+  /** Takes every word from here on, as `<paths...>` does. */
+  variadic: boolean;
+}
+
+interface UsageShape {
+  positionals: UsageToken[];
+  /** The flags the usage line shows, with the placeholder of their value when they take one. */
+  flags: Map<string, UsageToken | undefined>;
+}
+
+const PROGRAM_NAMES = ['ax', 'automatosx'];
+const FLAG_KINDS: Record<string, CompletionKind> = {
+  '--agent': 'agents',
+  '--provider': 'providers',
+  '--session-id': 'sessions',
+  '--workflow-id': 'workflows',
+  '--format': 'formats',
+  '--workflow-dir': 'files',
+  '--output-dir': 'files',
+  '--approval-policy': 'files',
+};
+const PLACEHOLDER_KINDS: Record<string, CompletionKind> = {
+  'agent-id': 'agents',
+  'session-id': 'sessions',
+  'workflow-id': 'workflows',
+  command: 'commands',
+};
+const FILE_PLACEHOLDER = /path|file|\.ya?ml$|\.json$/;
+const MAX_DESCRIPTION_LENGTH = 80;
+
+/** The script that hooks `ax` completion into the shell; how to load it is in its first lines. */
+export function renderCompletionScript(shell: CompletionShell): string {
+  const programs = PROGRAM_NAMES.join(' ');
+  switch (shell) {
+    case 'bash':
+      return [
+        '# bash completion for ax. Load it with: eval "$(ax completions bash)"',
+        '_ax_complete() {',
+        '    local IFS=$\'\\n\'',
+        '    COMPREPLY=($("${COMP_WORDS[0]}" completions complete "${COMP_LINE:0:COMP_POINT}" 2>/dev/null | cut -f1))',
+        '    if [[ ${#COMPREPLY[@]} -eq 1 && ${COMPREPLY[0]} == */ ]]; then',
+        '        compopt -o nospace',
+        '    fi',
+        '}',
+        `complete -o default -F _ax_complete ${programs}`,
+      ].join('\n');
+    case 'zsh':
+      return [
+        `#compdef ${programs}`,
+        '# zsh completion for ax. Load it with: eval "$(ax completions zsh)" after compinit,',
+        '# or save it as _ax in a directory on $fpath.',
+        '_ax() {',
+        '    local -a candidates values dirs',
+        '    local line value description',
+        '    candidates=("${(@f)$(${words[1]} completions complete "${(j: :)words[1,CURRENT]}" 2>/dev/null)}")',
+        '    for line in $candidates; do',
+        '        [[ -z $line ]] && continue',
+        '        if [[ $line == */ ]]; then',
+        '            dirs+=("$line")',
+        '            continue',
+        '        fi',
+        '        value=${line%%$\'\\t\'*}',
+        '        description=${line#*$\'\\t\'}',
+        '        [[ $description == $line ]] && description=\'\'',
+        '        values+=("${value//:/\\\\:}${description:+:$description}")',
+        '    done',
+        '    (( ${#dirs} )) && compadd -S \'\' -- $dirs',
+        '    (( ${#values} )) && _describe ax values',
+        '    (( ${#dirs} + ${#values} )) || _files',
+        '}',
+        'if [[ $funcstack[1] == _ax ]]; then',
+        '    _ax "$@"',
+        'else',
+        `    compdef _ax ${programs}`,
+        'fi',
+      ].join('\n');
+    case 'fish':
+      return [
+        '# fish completion for ax. Load it with: ax completions fish | source',
+        'function __ax_complete',
+        '    set -l program (commandline -opc)[1]',
+        '    $program completions complete (commandline -cp) 2>/dev/null',
+        'end',
+        ...PROGRAM_NAMES.map((program) => `complete -c ${program} -f -a '(__ax_complete)'`),
+      ].join('\n');
+    case 'powershell':
+      return [
+        '# PowerShell completion for ax. Load it with: ax completions powershell | Out-String | Invoke-Expression',
+        `Register-ArgumentCompleter -Native -CommandName ${PROGRAM_NAMES.join(', ')} -ScriptBlock {`,
+        '    param($wordToComplete, $commandAst, $cursorPosition)',
+        '    $line = $commandAst.Extent.Text',
+        '    $offset = $cursorPosition - $commandAst.Extent.StartOffset',
+        '    if ($offset -lt $line.Length) { $line = $line.Substring(0, $offset) }',
+        '    if ($wordToComplete -eq \'\' -and -not $line.EndsWith(\' \')) { $line += \' \' }',
+        '    $program = $commandAst.CommandElements[0].Extent.Text',
+        '    & $program completions complete $line 2>$null | Where-Object { $_ } | ForEach-Object {',
+        '        $value, $description = $_ -split "`t", 2',
+        '        if (-not $description) { $description = $value }',
+        '        [System.Management.Automation.CompletionResult]::new($value, $value, \'ParameterValue\', $description)',
+        '    }',
+        '}',
+      ].join('\n');
+  }
+}
+
+/**
+ * The completions for the last word of a command line, the program name first; a line ending in a
+ * space completes a new word. Names from the workspace come from `lookup`, and paths from the
+ * directory `basePath`.
+ */
+export async function completeCommandLine(
+  line: string,
+  definition: CliDefinition,
+  lookup: (source: CompletionSource) => Promise<Completion[]>,
+  basePath = process.cwd(),
+): Promise<Completion[]> {
+  const { words, current } = splitCommandLine(line);
+  const globalFlags = new Map(definition.flags.map((entry) => [entry.flag, entry.takesValue]));
+  let commandName: string | undefined;
+  let shapes: UsageShape[] = [];
+  let pendingFlag: string | undefined;
+  const positionals: string[] = [];
+  for (const word of words.slice(1)) {
+    if (pendingFlag !== undefined) {
+      pendingFlag = undefined;
+    } else if (word.startsWith('-')) {
+      const takesValue = globalFlags.get(word) ?? shapes.some((shape) => shape.flags.get(word) !== undefined);
+      pendingFlag = takesValue ? word : undefined;
+    } else if (commandName === undefined) {
+      commandName = word;
+      const command = definition.commands.find((entry) => entry.name === word);
+      shapes = command === undefined ? [] : command.usage.map((usage) => readUsage(usage, globalFlags));
+    } else {
+      positionals.push(word);
+    }
+  }
+
+  const kinds = new Set<CompletionKind>();
+  const candidates: Completion[] = [];
+  if (pendingFlag !== undefined) {
+    const kind = FLAG_KINDS[pendingFlag] ?? shapes.map((shape) => shape.flags.get(pendingFlag!)?.kind).find((entry) => entry !== undefined);
+    if (kind !== undefined) {
+      kinds.add(kind);
+    }
+  } else if (current.startsWith('-')) {
+    for (const shape of shapes) {
+      candidates.push(...Array.from(shape.flags.keys(), (flag) => ({ value: flag })));
+    }
+    candidates.push(...Array.from(globalFlags.keys(), (flag) => ({ value: flag })));
+  } else if (commandName === undefined) {
+    kinds.add('commands');
+  } else {
+    for (const shape of shapes) {
+      const token = tokenAt(shape.positionals, positionals);
+      if (token?.literal !== undefined) {
+        candidates.push({ value: token.literal });
+      } else if (token?.kind !== undefined) {
+        kinds.add(token.kind);
+      }
+    }
+  }
+
+  for (const kind of kinds) {
+    if (kind === 'commands') {
+      candidates.push(...definition.commands.map((entry) => ({ value: entry.name, description: entry.description })));
+    } else if (kind === 'formats') {
+      candidates.push(...definition.formats.map((format) => ({ value: format })));
+    } else if (kind === 'files') {
+      candidates.push(...await listPaths(current, basePath));
+    } else {
+      // Completion must not fail the shell over a workspace it cannot read.
+      candidates.push(...await lookup(kind).catch(() => []));
+    }
+  }
+
+  const completions = new Map<string, Completion>();
+  for (const candidate of candidates) {
+    if (candidate.value.startsWith(current) && !completions.has(candidate.value)) {
+      completions.set(candidate.value, candidate.description === undefined || candidate.description === candidate.value
+        ? { value: candidate.value }
+        : { value: candidate.value, description: shortenDescription(candidate.description) });
+    }
+  }
+  return Array.from(completions.values());
+}
+
+/** One completion per line, its description after a tab, as the scripts read them. */
+export function formatCompletions(completions: Completion[]): string {
+  return completions.map((completion) => completion.description === undefined ? completion.value : `${completion.value}\t${completion.description}`).join('\n');
+}
+
+// The words of a line as a shell splits them, quotes removed, and the word being typed at its end.
+function splitCommandLine(line: string): { words: string[]; current: string } {
+  const words: string[] = [];
+  let word: string | undefined;
+  let quote: string | undefined;
+  for (const char of line) {
+    if (quote !== undefined) {
+      if (char === quote) {
+        quote = undefined;
+      } else {
+        word += char;
+      }
+    } else if (char === '"' || char === '\'') {
+      quote = char;
+      word ??= '';
+    } else if (/\s/.test(char)) {
+      if (word !== undefined) {
+        words.push(word);
+        word = undefined;
+      }
+    } else {
+      word = (word ?? '') + char;
+    }
+  }
+  return { words, current: word ?? '' };
+}
+
+function readUsage(usage: string, globalFlags: Map<string, boolean>): UsageShape {
+  const tokens = splitCommandLine(`${usage} `).words.slice(2);
+  const shape: UsageShape = { positionals: [], flags: new Map() };
+  for (let index = 0; index < tokens.length; index += 1) {
+    const word = tokens[index]!;
+    // An optional flag shows as `[--flag <value>]`.
+    const token = /^\[-/.test(word) ? word.replace(/^\[|\]$/g, '') : word;
+    if (!token.startsWith('-')) {
+      shape.positionals.push(readUsageToken(token));
+      continue;
+    }
+    // A flag the usage line shows with a word after it takes a value, unless it is a global switch.
+    const value = tokens[index + 1];
+    if (value !== undefined && !value.startsWith('-') && globalFlags.get(token) !== false) {
+      shape.flags.set(token, readUsageToken(value));
+      index += 1;
+    } else {
+      shape.flags.set(token, undefined);
+    }
+  }
+  return shape;
+}
+
+function readUsageToken(token: string): UsageToken {
+  if (/^[a-z][a-z0-9-]*$/.test(token)) {
+    return { literal: token, variadic: false };
+  }
+  const name = token.replace(/[<>[\]]/g, '');
+  const variadic = name.endsWith('...');
+  const bare = variadic ? name.slice(0, -3) : name;
+  const kind = PLACEHOLDER_KINDS[bare] ?? (FILE_PLACEHOLDER.test(bare) ? 'files' : undefined);
+  return { ...(kind !== undefined ? { kind } : {}), variadic };
+}
+
+// The usage token for the word after `typed`, when the usage line fits the words typed so far.
+function tokenAt(pattern: UsageToken[], typed: string[]): UsageToken | undefined {
+  for (let index = 0; index < pattern.length; index += 1) {
+    const token = pattern[index]!;
+    if (token.variadic || index === typed.length) {
+      return token;
+    }
+    if (token.literal !== undefined && token.literal !== typed[index]) {
+      return undefined;
+    }
+  }
+  return undefined;
+}
+
+async function listPaths(current: string, basePath: string): Promise<Completion[]> {
+  const directory = current.slice(0, current.lastIndexOf('/') + 1);
+  const name = current.slice(directory.length);
+  const entries = await readdir(resolve(basePath, directory || '.'), { withFileTypes: true }).catch(() => []);
+  return entries
+    .filter((entry) => !entry.name.startsWith('.') || name.startsWith('.'))
+    .map((entry) => ({ value: `${directory}${entry.name}${entry.isDirectory() ? '/' : ''}` }))
+    .sort((left, right) => left.value.localeCompare(right.value));
+}
+
+// The first sentence, on one line and cut to fit beside the value.
+function shortenDescription(description: string): string {
+  const line = description.replace(/\s+/g, ' ').trim();
+  const sentence = /^(.+?[.!?])(?: |$)/.exec(line)?.[1] ?? line;
+  const trimmed = sentence.endsWith('.') ? sentence.slice(0, -1) : sentence;
+  return trimmed.length > MAX_DESCRIPTION_LENGTH ? `${trimmed.slice(0, MAX_DESCRIPTION_LENGTH - 1)}…` : trimmed;
+}
@@ -0,0 +1,39 @@
+import { mkdir, readFile, writeFile } from 'node:fs/promises';
+import { dirname, join } from 'node:path';
+const RUN_ID_PATTERN = /^[\w.-]+$/;
+export function isValidRunId(runId) {
+    return RUN_ID_PATTERN.test(runId) && runId !== '.' && runId !== '..';
+}
+export function getDetachedRunPaths(basePath, runId) {
+    const runsDir = join(basePath, '.automatosx', 'runtime', 'runs');
+    return {
+        record: join(runsDir, `${runId}.json`),
+        log: join(runsDir, `${runId}.log`),
+    };
+}
+export async function writeDetachedRun(basePath, record) {
+    const { record: recordPath } = getDetachedRunPaths(basePath, record.runId);
+    await mkdir(dirname(recordPath), { recursive: true });
+    await writeFile(recordPath, `${JSON.stringify(record, null, 2)}\n`, 'utf8');
+}
+export async function readDetachedRun(basePath, runId) {
+    if (!isValidRunId(runId)) {
+        return undefined;
+    }
+    try {
+        return JSON.parse(await readFile(getDetachedRunPaths(basePath, runId).record, 'utf8'));
+    }
+    catch {
+        return undefined;
+    }
+}
+export function isProcessAlive(pid) {
+    try {
+        process.kill(pid, 0);
+        return true;
+    }
+    catch (error) {
+        // EPERM means the process exists but belongs to another user.
+        return error.code === 'EPERM';
+    }
+}
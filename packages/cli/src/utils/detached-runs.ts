@@ -0,0 +1,51 @@
+import { mkdir, readFile, writeFile } from 'node:fs/promises';
+import { dirname, join } from 'node:path';
+
+export interface DetachedRunRecord {
+  runId: string;
+  workflowId: string;
+  pid: number;
+  startedAt: string;
+  logPath: string;
+}
+
+const RUN_ID_PATTERN = /^[\w.-]+$/;
+
+export function isValidRunId(runId: string): boolean {
+  return RUN_ID_PATTERN.test(runId) && runId !== '.' && runId !== '..';
+}
+
+export function getDetachedRunPaths(basePath: string, runId: string): { record: string; log: string } {
+  const runsDir = join(basePath, '.automatosx', 'runtime', 'runs');
+  return {
+    record: join(runsDir, `${runId}.json`),
+    log: join(runsDir, `${runId}.log`),
+  };
+}
+
+export async function writeDetachedRun(basePath: string, record: DetachedRunRecord): Promise<void> {
+  const { record: recordPath } = getDetachedRunPaths(basePath, record.runId);
+  await mkdir(dirname(recordPath), { recursive: true });
+  await writeFile(recordPath, `${JSON.stringify(record, null, 2)}\n`, 'utf8');
+}
+
+export async function readDetachedRun(basePath: string, runId: string): Promise<DetachedRunRecord | undefined> {
+  if (!isValidRunId(runId)) {
+    return undefined;
+  }
+  try {
+    return JSON.parse(await readFile(getDetachedRunPaths(basePath, runId).record, 'utf8')) as DetachedRunRecord;
+  } catch {
+    return undefined;
+  }
+}
+
+export function isProcessAlive(pid: number): boolean {
+  try {
+    process.kill(pid, 0);
+    return true;
+  } catch (error) {
+    // EPERM means the process exists but belongs to another user.
+    return (error as NodeJS.ErrnoException).code === 'EPERM';
+  }
+}
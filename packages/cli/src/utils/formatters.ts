@@ -1,10 +1,10 @@
 import { getErrorMessage } from '@defai.digital/contracts';
-import { createSharedRuntimeService } from '@defai.digital/shared-runtime';
+import { createSharedRuntimeService, type BudgetReport } from '@defai.digital/shared-runtime';
 import type { CLIOptions, CommandResult } from '../types.js';
 
 export function createRuntime(options: CLIOptions): ReturnType<typeof createSharedRuntimeService> {
   const basePath = options.outputDir ?? process.cwd();
-  return createSharedRuntimeService({ basePath });
+  return createSharedRuntimeService({ basePath, readOnly: options.readOnly });
 }
 
 export function success(message: string, data: unknown = undefined): CommandResult {
@@ -35,3 +35,17 @@ export function usageError(usage: string): CommandResult {
   return failure(`Usage: ${usage}`);
 }
 
+/** A line on what a run used of its budget, e.g. "Budget: 1240 of 1000 tokens, $0.0000, 3.2s (token limit hit)". */
+export function formatBudget(report: BudgetReport): string {
+  const { limits, consumption } = report;
+  const parts = [
+    `${consumption.totalTokens}${limits.maxTokens !== undefined ? ` of ${limits.maxTokens}` : ''} tokens`,
+    `$${consumption.costUsd.toFixed(4)}${limits.maxCostUsd !== undefined ? ` of $${limits.maxCostUsd}` : ''}`,
+    `${(consumption.durationMs / 1000).toFixed(1)}s${limits.maxDurationMs !== undefined ? ` of ${limits.maxDurationMs / 1000}s` : ''}`,
+  ];
+  const hit = report.exceeded === undefined
+    ? ''
+    : ` (${{ maxTokens: 'token', maxCostUsd: 'cost', maxDurationMs: 'time' }[report.exceeded.limit]} limit hit)`;
+  return `Budget: ${parts.join(', ')}${hit}`;
+}
+
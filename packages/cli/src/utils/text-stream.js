@@ -0,0 +1,56 @@
+import { once } from 'node:events';
+/** Provider replies stream in text output unless `--no-stream` or `--quiet` is given. */
+export function shouldStreamText(options) {
+    return options.noStream !== true && options.quiet !== true && options.format === 'text';
+}
+/**
+ * Writes streamed reply text to stdout. A terminal gets each piece as it arrives; a pipe or
+ * file gets whole lines, so a reader never sees half a line, and the rest when the line breaks.
+ */
+export function createTextStream(output = process.stdout) {
+    const lineBuffered = output.isTTY !== true;
+    let pending = '';
+    let written = false;
+    let atLineStart = true;
+    const emit = (text) => {
+        if (text.length > 0) {
+            output.write(text);
+            atLineStart = text.endsWith('\n');
+        }
+    };
+    const breakLine = () => {
+        emit(pending);
+        pending = '';
+        if (!atLineStart) {
+            emit('\n');
+        }
+    };
+    return {
+        get written() {
+            return written;
+        },
+        write(text) {
+            written ||= text.length > 0;
+            if (!lineBuffered) {
+                emit(text);
+                return;
+            }
+            pending += text;
+            const lineEnd = pending.lastIndexOf('\n');
+            if (lineEnd >= 0) {
+                emit(pending.slice(0, lineEnd + 1));
+                pending = pending.slice(lineEnd + 1);
+            }
+        },
+        breakLine,
+        async end() {
+            breakLine();
+            if (written) {
+                emit('\n');
+            }
+            if (output.writableNeedDrain) {
+                await once(output, 'drain');
+            }
+        },
+    };
+}
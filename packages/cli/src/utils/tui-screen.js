@@ -0,0 +1,168 @@
+import { describeApproval, } from '@defai.digital/shared-runtime';
+const MAX_OUTPUT_LINES = 5_000;
+const STAGE_MARKS = { pending: '·', running: '▶', succeeded: '✓', failed: '✗', skipped: '-' };
+// Terminal control sequences and other control characters in provider output would move the cursor.
+const CONTROL_PATTERN = /\u001b\[[0-9;?]*[ -/]*[@-~]|\u001b[@-_]|[\u0000-\u0008\u000b-\u001f\u007f]/g;
+const INVERSE = '\u001b[7m';
+const DIM = '\u001b[2m';
+const RESET = '\u001b[0m';
+export function createTuiState(title, traceId, stages = [], now = Date.now()) {
+    return {
+        title,
+        traceId,
+        startedAt: now,
+        status: 'running',
+        stages,
+        output: [''],
+        scroll: 0,
+        approvals: [],
+        pane: 'run',
+        symbols: { query: '', results: [], selected: 0, preview: [] },
+    };
+}
+/** Adds streamed text to the output, headed by its stage when it comes from another stage than the text before. */
+export function appendTuiOutput(state, text, stageId) {
+    if (stageId !== undefined && stageId !== state.outputStage) {
+        if (state.output.at(-1) !== '') {
+            state.output.push('');
+        }
+        state.output.splice(-1, 1, `[${stageId}]`, '');
+        state.outputStage = stageId;
+    }
+    const [first, ...rest] = text.replace(/\r\n?/g, '\n').replace(/\t/g, '  ').replace(CONTROL_PATTERN, '').split('\n');
+    state.output[state.output.length - 1] += first ?? '';
+    state.output.push(...rest);
+    if (state.output.length > MAX_OUTPUT_LINES) {
+        state.output.splice(0, state.output.length - MAX_OUTPUT_LINES);
+    }
+}
+/** Marks a stage as started or finished, adding it when the run did not list it up front. */
+export function updateTuiStage(state, update) {
+    const index = state.stages.findIndex((stage) => stage.id === update.id);
+    if (index < 0) {
+        state.stages.push(update);
+    }
+    else {
+        state.stages[index] = { ...state.stages[index], ...update };
+    }
+}
+/** The screen as lines of at most `columns` characters, exactly `rows` of them. */
+export function renderTui(state, columns, rows, now = Date.now()) {
+    const width = Math.max(columns, 20);
+    const height = Math.max(rows, 10);
+    const top = [
+        bar(` ax tui │ ${state.title} │ trace ${state.traceId} │ ${state.status} ${formatElapsed((state.finishedAt ?? now) - state.startedAt)}`, width),
+        ...renderStages(state.stages, Math.max(3, Math.floor(height / 4)), width),
+        ...renderApprovals(state.approvals, width),
+    ];
+    const bottom = [
+        ...(state.notice !== undefined ? [fit(state.notice, width)] : []),
+        fit(formatUsage(state.usage), width),
+        bar(formatKeys(state), width),
+    ];
+    const bodyRows = Math.max(height - top.length - bottom.length - 1, 1);
+    const body = state.pane === 'run'
+        ? [rule(state.outputStage === undefined ? 'Output' : `Output · ${state.outputStage}`, width), ...renderOutput(state, bodyRows, width)]
+        : [rule(`Symbols: ${state.symbols.query}▏`, width), ...renderSymbols(state.symbols, bodyRows, width)];
+    return [...top, ...body, ...bottom].slice(0, height);
+}
+function renderStages(stages, maxRows, width) {
+    if (stages.length === 0) {
+        return [rule('Stages', width), fit(`${DIM} waiting for the first stage${RESET}`, width)];
+    }
+    // With more stages than rows, the running stage stays in view.
+    const running = Math.max(stages.findIndex((stage) => stage.status === 'running'), 0);
+    const start = stages.length <= maxRows ? 0 : Math.min(Math.max(running - 1, 0), stages.length - maxRows);
+    const lines = stages.slice(start, start + maxRows).map((stage) => fit(` ${STAGE_MARKS[stage.status]} ${stage.id}${stage.agent !== undefined ? ` (${stage.agent})` : ''}${stage.durationMs !== undefined ? ` ${formatElapsed(stage.durationMs)}` : ''}`, width));
+    const hidden = stages.length - lines.length;
+    return [rule(`Stages ${stages.filter((stage) => stage.status === 'succeeded' || stage.status === 'skipped').length}/${stages.length}${hidden > 0 ? ` · ${hidden} not shown` : ''}`, width), ...lines];
+}
+function renderApprovals(approvals, width) {
+    const [first] = approvals;
+    if (first === undefined) {
+        return [];
+    }
+    return [
+        rule(`Approval needed${approvals.length > 1 ? ` · ${approvals.length - 1} more waiting` : ''}`, width),
+        fit(` Agent "${first.request.agentId}" wants to ${describeApproval(first.request)}. Approve? [y/n]`, width),
+    ];
+}
+function renderOutput(state, rows, width) {
+    const lines = state.output.at(-1) === '' ? state.output.slice(0, -1) : state.output;
+    if (lines.length === 0) {
+        return pad([fit(`${DIM} no output yet${RESET}`, width)], rows);
+    }
+    const end = Math.max(lines.length - state.scroll, Math.min(rows, lines.length));
+    return pad(lines.slice(Math.max(end - rows, 0), end).map((line) => fit(line, width)), rows);
+}
+function renderSymbols(pane, rows, width) {
+    if (pane.error !== undefined) {
+        return pad([fit(` ${pane.error}`, width)], rows);
+    }
+    if (pane.query.length === 0) {
+        return pad([fit(`${DIM} Type to search the symbol index; ax code index builds it.${RESET}`, width)], rows);
+    }
+    if (pane.results.length === 0) {
+        return pad([fit(`${DIM} No indexed symbols match "${pane.query}".${RESET}`, width)], rows);
+    }
+    // The list takes the upper half and keeps the selection in view; the selected symbol's source fills the rest.
+    const listRows = Math.max(Math.min(pane.results.length, Math.floor(rows / 2)), 1);
+    const start = Math.min(Math.max(pane.selected - listRows + 1, 0), pane.results.length - listRows);
+    const list = pane.results.slice(start, start + listRows).map((symbol, offset) => {
+        const line = fit(` ${symbol.kind} ${symbol.container !== undefined ? `${symbol.container}.` : ''}${symbol.name}  ${symbol.file}:${symbol.line}`, width);
+        return start + offset === pane.selected ? `${INVERSE}${line}${RESET}` : line;
+    });
+    const previewRows = rows - list.length - 1;
+    const selected = pane.results[pane.selected];
+    const preview = selected === undefined || previewRows <= 0
+        ? []
+        : [rule(`${selected.file}:${selected.line}`, width), ...pane.preview.slice(0, previewRows - 1).map((line, offset) => fit(`${DIM}${String(selected.line + offset).padStart(5)}${RESET} ${line}`, width))];
+    return pad([...list, ...preview], rows);
+}
+function formatUsage(usage) {
+    if (usage === undefined || usage.calls === 0) {
+        return ' Tokens: no provider calls yet';
+    }
+    return ` Tokens: ${usage.inputTokens.toLocaleString('en-US')} in / ${usage.outputTokens.toLocaleString('en-US')} out │ Cost: $${usage.costUsd.toFixed(4)}${usage.unpricedCalls > 0 ? ` + ${usage.unpricedCalls} unpriced` : ''} │ ${usage.calls} call${usage.calls === 1 ? '' : 's'}${usage.failures > 0 ? ` (${usage.failures} failed)` : ''}`;
+}
+function formatKeys(state) {
+    if (state.pane === 'symbols') {
+        return ' type to search  ↑↓ select  Esc clear  Tab run  Ctrl+C quit';
+    }
+    return ` ${state.approvals.length > 0 ? 'y approve  n deny  ' : ''}↑↓ PgUp PgDn scroll  Tab symbols  q ${state.status === 'running' ? 'stop and quit' : 'quit'}`;
+}
+function formatElapsed(ms) {
+    if (ms < 10_000) {
+        return `${(Math.max(ms, 0) / 1000).toFixed(1)}s`;
+    }
+    const seconds = Math.floor(ms / 1000);
+    return seconds < 60 ? `${seconds}s` : `${Math.floor(seconds / 60)}m${String(seconds % 60).padStart(2, '0')}s`;
+}
+function rule(title, width) {
+    const head = `── ${title} `;
+    return fit(`${head}${'─'.repeat(Math.max(width - head.length, 0))}`, width);
+}
+function bar(text, width) {
+    return `${INVERSE}${fit(text, width).padEnd(width)}${RESET}`;
+}
+// Cuts a line to the width, not counting the style sequences in it.
+function fit(line, width) {
+    let visible = 0;
+    let result = '';
+    for (const part of line.split(/(\u001b\[[0-9;]*m)/)) {
+        if (part.startsWith('\u001b[')) {
+            result += part;
+            continue;
+        }
+        const room = width - visible;
+        if (part.length > room) {
+            return `${result}${part.slice(0, Math.max(room - 1, 0))}…${RESET}`;
+        }
+        result += part;
+        visible += part.length;
+    }
+    return result;
+}
+function pad(lines, rows) {
+    return [...lines.slice(0, rows), ...Array.from({ length: Math.max(rows - lines.length, 0) }, () => '')];
+}
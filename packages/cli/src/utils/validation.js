@@ -1,3 +1,4 @@
+import { parseDurationMs } from '@defai.digital/shared-runtime';
 export function isRecord(value) {
     return value !== null && typeof value === 'object' && !Array.isArray(value);
 }
@@ -37,6 +38,31 @@ export function parseOptionalJsonInput(input, context) {
         return { value: undefined, error: 'Invalid JSON input. Please provide a valid JSON object.' };
     }
 }
+/** The run budget of --max-cost-usd, --max-tokens and --max-duration; undefined when none is given. */
+export function parseBudgetOptions(options) {
+    const budget = {};
+    if (options.maxCostUsd !== undefined) {
+        const maxCostUsd = Number(options.maxCostUsd);
+        if (!Number.isFinite(maxCostUsd) || maxCostUsd <= 0) {
+            return { value: undefined, error: 'Invalid value for --max-cost-usd: expected a positive amount of USD.' };
+        }
+        budget.maxCostUsd = maxCostUsd;
+    }
+    if (options.maxTokens !== undefined) {
+        if (options.maxTokens <= 0) {
+            return { value: undefined, error: 'Invalid value for --max-tokens: expected a positive integer.' };
+        }
+        budget.maxTokens = options.maxTokens;
+    }
+    if (options.maxDuration !== undefined) {
+        const maxDurationMs = parseDurationMs(options.maxDuration);
+        if (maxDurationMs === undefined || maxDurationMs <= 0) {
+            return { value: undefined, error: 'Invalid value for --max-duration: expected a duration such as 90s, 15m or 2h.' };
+        }
+        budget.maxDurationMs = maxDurationMs;
+    }
+    return { value: Object.keys(budget).length > 0 ? budget : undefined };
+}
 export function asString(value, field) {
     if (typeof value !== 'string' || value.length === 0) {
         return { value: '', error: `Input requires "${field}".` };
@@ -1,3 +1,6 @@
+import { parseDurationMs, type RunBudget } from '@defai.digital/shared-runtime';
+import type { CLIOptions } from '../types.js';
+
 export interface ParseResult<T> {
   value: T;
   error?: string;
@@ -46,6 +49,32 @@ export function parseOptionalJsonInput(input: string | undefined, context?: stri
   }
 }
 
+/** The run budget of --max-cost-usd, --max-tokens and --max-duration; undefined when none is given. */
+export function parseBudgetOptions(options: Pick<CLIOptions, 'maxCostUsd' | 'maxTokens' | 'maxDuration'>): ParseResult<RunBudget | undefined> {
+  const budget: RunBudget = {};
+  if (options.maxCostUsd !== undefined) {
+    const maxCostUsd = Number(options.maxCostUsd);
+    if (!Number.isFinite(maxCostUsd) || maxCostUsd <= 0) {
+      return { value: undefined, error: 'Invalid value for --max-cost-usd: expected a positive amount of USD.' };
+    }
+    budget.maxCostUsd = maxCostUsd;
+  }
+  if (options.maxTokens !== undefined) {
+    if (options.maxTokens <= 0) {
+      return { value: undefined, error: 'Invalid value for --max-tokens: expected a positive integer.' };
+    }
+    budget.maxTokens = options.maxTokens;
+  }
+  if (options.maxDuration !== undefined) {
+    const maxDurationMs = parseDurationMs(options.maxDuration);
+    if (maxDurationMs === undefined || maxDurationMs <= 0) {
+      return { value: undefined, error: 'Invalid value for --max-duration: expected a duration such as 90s, 15m or 2h.' };
+    }
+    budget.maxDurationMs = maxDurationMs;
+  }
+  return { value: Object.keys(budget).length > 0 ? budget : undefined };
+}
+
 export function asString(value: unknown, field: string): ParseResult<string> {
   if (typeof value !== 'string' || value.length === 0) {
     return { value: '', error: `Input requires "${field}".` };
@@ -2,9 +2,10 @@ import { mkdirSync } from 'node:fs';
 import { rm, writeFile } from 'node:fs/promises';
 import { join } from 'node:path';
 import { afterEach, describe, expect, it } from 'vitest';
-import { execFile } from 'node:child_process';
+import { execFile, spawn } from 'node:child_process';
 import { promisify } from 'node:util';
-import { CLI_COMMAND_NAMES, CLI_VERSION, executeCli, parseCommand, renderCommandResult } from '../src/index.js';
+import { CLI_COMMAND_NAMES, CLI_VERSION, JSON_OUTPUT_VERSION, executeCli, parseCommand, renderCommandResult } from '../src/index.js';
+import { appendTuiOutput, createTuiState, renderTui, updateTuiStage } from '../src/utils/tui-screen.js';
 const execFileAsync = promisify(execFile);
 function createTempDir() {
     const dir = join(process.cwd(), '.tmp', `cli-dispatch-${Date.now()}-${Math.random().toString(16).slice(2, 8)}`);
@@ -46,6 +47,18 @@ describe('cli dispatch', () => {
         expect(result.success).toBe(true);
         expect(rendered).toContain(`"version": "${CLI_VERSION}"`);
     });
+    it('prints results as a versioned JSON envelope with --json', async () => {
+        const parsed = parseCommand(['--json', 'trace', '--limit', '1']);
+        expect(parsed.options.format).toBe('json');
+        expect(parseCommand(['status', '--json', '--format', 'md']).parseError).toBe('--json cannot be combined with --format md.');
+        const result = await executeCli(['--json', 'trace', '--limit', '1']);
+        const envelope = JSON.parse(renderCommandResult(result, parsed.options, parsed.command));
+        expect(envelope).toMatchObject({ schemaVersion: JSON_OUTPUT_VERSION, command: 'trace', success: result.success, exitCode: result.exitCode });
+        expect(Object.keys(envelope)).toEqual(['schemaVersion', 'command', 'success', 'message', 'data', 'exitCode']);
+        // A failure is the same document, so scripts read it from stdout and check exitCode.
+        const failed = await executeCli(['help', 'not-a-command', '--json']);
+        expect(JSON.parse(renderCommandResult(failed, parseCommand(['help', 'not-a-command', '--json']).options, 'help'))).toMatchObject({ schemaVersion: JSON_OUTPUT_VERSION, success: false, exitCode: 1 });
+    });
     it('dispatches workflow and review commands through the unified entrypoint', async () => {
         const tempDir = createTempDir();
         tempDirs.push(tempDir);
@@ -243,6 +256,181 @@ describe('cli dispatch', () => {
         expect(result.success).toBe(false);
         expect(result.message).toContain('Unknown command');
     });
+    it('blocks workspace mutations under --read-only', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        expect(parseCommand(['status', '--read-only']).options.readOnly).toBe(true);
+        const initResult = await executeCli(['init', '--read-only', '--output-dir', tempDir]);
+        expect(initResult.success).toBe(false);
+        expect(initResult.message).toContain('disabled in read-only mode');
+        const writeResult = await executeCli([
+            'mcp',
+            'call',
+            'file.write',
+            '--read-only',
+            '--output-dir',
+            tempDir,
+            '--input',
+            JSON.stringify({ path: 'notes.txt', content: 'blocked' }),
+        ]);
+        expect(writeResult.success).toBe(false);
+        expect(writeResult.message).toContain('file.write is disabled in read-only mode');
+        const configResult = await executeCli(['config', 'set', 'providers.default', 'claude', '--read-only', '--output-dir', tempDir]);
+        expect(configResult.success).toBe(false);
+        const readResult = await executeCli(['mcp', 'call', 'file.exists', '--read-only', '--output-dir', tempDir, '--input', JSON.stringify({ path: 'notes.txt' })]);
+        expect(readResult.success).toBe(true);
+    });
+    it('applies --profile and --config over the config files and explains which layer set a value', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+            providers: { default: 'claude' },
+            profiles: { ci: { providers: { default: 'ollama' } } },
+        })}\n`, 'utf8');
+        const parsed = parseCommand(['--profile', 'ci', '--config', 'replay.record=false', '--config', 'providers.default=gemini', 'config', 'explain', 'providers.default']);
+        expect(parsed.command).toBe('config');
+        expect(parsed.options.profile).toBe('ci');
+        expect(parsed.options.configOverrides).toEqual(['replay.record=false', 'providers.default=gemini']);
+        expect(parseCommand(['status', '--config', 'providers.default']).parseError).toBe('Invalid value for --config: expected <path>=<value>.');
+        const project = await executeCli(['config', 'explain', 'providers.default', '--output-dir', tempDir]);
+        expect(project.success).toBe(true);
+        expect(project.message).toContain('Set by: project');
+        const profile = await executeCli(['config', 'explain', 'providers.default', '--profile', 'ci', '--output-dir', tempDir]);
+        expect(profile.message).toContain('Set by: profile (profiles.ci)');
+        expect(profile.message).toContain('"ollama"');
+        const flagged = await executeCli(['config', 'get', 'replay.record', '--profile', 'ci', '--config', 'replay.record=false', '--output-dir', tempDir]);
+        expect(flagged.data).toBe(false);
+        const explained = await executeCli(['config', 'explain', 'providers.default', '--profile', 'ci', '--config', 'providers.default=gemini', '--output-dir', tempDir]);
+        expect(explained.data).toMatchObject({
+            value: 'gemini',
+            layer: 'flags',
+            layers: [{ layer: 'project', value: 'claude' }, { layer: 'profile', value: 'ollama' }, { layer: 'flags', value: 'gemini' }],
+        });
+        // The flags last only as long as the command.
+        expect(process.env.AUTOMATOSX_PROFILE).toBeUndefined();
+        expect(process.env.AUTOMATOSX_CONFIG_FLAGS).toBeUndefined();
+        const unknown = await executeCli(['status', '--profile', 'nightly', '--output-dir', tempDir]);
+        expect(unknown.success).toBe(false);
+        expect(unknown.message).toContain('Unknown config profile "nightly". Define it under profiles.nightly in .automatosx/config.json. Defined profiles: ci.');
+    });
+    it('validates the config files and overrides for CI, with a suggestion for each unknown key', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+        const configPath = join(tempDir, '.automatosx', 'config.json');
+        await writeFile(configPath, `${JSON.stringify({
+            schemaVersion: 2,
+            providers: { default: 'claude', executors: { local: { command: 'local-llm' } } },
+        })}\n`, 'utf8');
+        const valid = await executeCli(['config', 'validate', '--output-dir', tempDir]);
+        expect(valid.exitCode).toBe(0);
+        expect(valid.message).toContain('Config is valid.');
+        expect(valid.message).toContain(`  project  ${configPath}: ok`);
+        const overridden = await executeCli(['config', 'validate', '--config', 'providers.executors.local.timeoutMs=soon', '--output-dir', tempDir]);
+        expect(overridden.exitCode).toBe(1);
+        expect(overridden.message).toContain('  - providers.executors.local.timeoutMs must be a number (flags: --config providers.executors.local.timeoutMs)');
+        const rejected = await executeCli(['config', 'set', 'providers.executors.local.protocl', 'raw-stdin', '--output-dir', tempDir]);
+        expect(rejected.success).toBe(false);
+        expect(rejected.message).toBe('Failed to update config: providers.executors.local.protocl is not a config key; did you mean "protocol"?');
+        await writeFile(configPath, `${JSON.stringify({ providers: { defualt: 'claude' } })}\n`, 'utf8');
+        const invalid = await executeCli(['config', 'validate', '--output-dir', tempDir]);
+        expect(invalid.exitCode).toBe(1);
+        expect(invalid.message).toContain('Config is invalid.');
+        expect(invalid.message).toContain(`  project  ${configPath}: 1 problem\n    - providers.defualt is not a config key; did you mean "default"?`);
+    });
+    it('detaches a workflow run and attaches to its log until it finishes', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await writeFile(join(tempDir, 'background.json'), JSON.stringify({
+            workflowId: 'background',
+            name: 'Background',
+            version: '1.0.0',
+            steps: [{ stepId: 'wait', type: 'tool', config: { toolName: 'noop.wait' } }],
+        }), 'utf8');
+        expect(parseCommand(['run', 'background', '--detach']).options.detach).toBe(true);
+        const detached = await executeCli(['run', 'background', '--detach', '--workflow-dir', tempDir, '--output-dir', tempDir]);
+        expect(detached.success).toBe(true);
+        const { runId, pid } = detached.data;
+        expect(detached.message).toContain(`Attach with: ax attach ${runId}`);
+        expect(pid).toBeGreaterThan(0);
+        const attached = await executeCli(['attach', runId, '--format', 'json', '--output-dir', tempDir]);
+        expect(attached.success).toBe(true);
+        expect(attached.message).toContain('completed');
+        const { log, status } = attached.data;
+        expect(status).toBe('completed');
+        expect(log).toContain('step wait started');
+        expect(log).toContain('step wait completed');
+        expect(log).toContain('Workflow "background" completed successfully.');
+        const missing = await executeCli(['attach', '../escape', '--output-dir', tempDir]);
+        expect(missing.success).toBe(false);
+        expect(missing.message).toContain('No detached run "../escape" found');
+    });
+    it('draws a run full-screen in the TUI and refuses to start it without a terminal', async () => {
+        const state = createTuiState('pipeline review.yaml', 'tui-001', [
+            { id: 'plan', agent: 'architect', status: 'pending' },
+            { id: 'build', agent: 'backend', status: 'pending' },
+        ], 0);
+        updateTuiStage(state, { id: 'plan', status: 'running' });
+        appendTuiOutput(state, 'Planning the\u001b[31m work\nstep 1', 'plan');
+        appendTuiOutput(state, ' done\n', 'plan');
+        updateTuiStage(state, { id: 'plan', status: 'succeeded', durationMs: 1_500 });
+        updateTuiStage(state, { id: 'build', status: 'running' });
+        appendTuiOutput(state, 'Building', 'build');
+        state.approvals.push({ request: { action: 'shell', agentId: 'backend', target: 'npm install' }, resolve: () => {} });
+        state.usage = { calls: 2, failures: 0, inputTokens: 1_200, outputTokens: 300, costUsd: 0.0123, unpricedCalls: 0, averageLatencyMs: 5 };
+        // Control sequences of the provider's output are dropped, and each stage's text is headed by it.
+        expect(state.output).toEqual(['[plan]', 'Planning the work', 'step 1 done', '[build]', 'Building']);
+        const screen = renderTui(state, 80, 24, 42_000).map((line) => line.replace(/\u001b\[[0-9;]*m/g, ''));
+        expect(screen).toHaveLength(24);
+        expect(screen.every((line) => line.length <= 80)).toBe(true);
+        expect(screen[0]).toContain('ax tui │ pipeline review.yaml │ trace tui-001 │ running 42s');
+        expect(screen).toContain(' ✓ plan (architect) 1.5s');
+        expect(screen).toContain(' ▶ build (backend)');
+        expect(screen).toContain(' Agent "backend" wants to run the shell command "npm install". Approve? [y/n]');
+        expect(screen).toContain('step 1 done');
+        expect(screen.at(-2)).toBe(' Tokens: 1,200 in / 300 out │ Cost: $0.0123 │ 2 calls');
+        expect(screen.at(-1)).toContain('y approve  n deny');
+        state.pane = 'symbols';
+        state.symbols = {
+            query: 'parse',
+            results: [
+                { name: 'parsePipeline', kind: 'function', language: 'typescript', file: 'src/pipeline.ts', line: 160 },
+                { name: 'parse', kind: 'method', language: 'typescript', container: 'Yaml', file: 'src/yaml.ts', line: 3 },
+            ],
+            selected: 1,
+            preview: ['parse(source) {', '  return load(source);', '}'],
+        };
+        const symbols = renderTui(state, 80, 24, 42_000).map((line) => line.replace(/\u001b\[[0-9;]*m/g, ''));
+        expect(symbols).toContain(' method Yaml.parse  src/yaml.ts:3');
+        expect(symbols).toContain('    4   return load(source);');
+        const result = await executeCli(['tui', 'review.yaml']);
+        expect(result.success).toBe(false);
+        expect(result.message).toContain('ax tui needs an interactive terminal');
+    });
+    it('prints completion scripts for each shell and completes from the CLI definition and the workspace', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await executeCli(['agent', 'register', '--input', JSON.stringify({ agentId: 'backend', name: 'Backend Engineer', capabilities: [] }), '--output-dir', tempDir]);
+        const session = await executeCli(['session', 'create', '--input', JSON.stringify({ task: 'Fix the login bug', initiator: 'tester' }), '--output-dir', tempDir]);
+        const sessionId = session.data.sessionId;
+        const complete = async (line) => (await executeCli(['completions', 'complete', line, '--output-dir', tempDir])).message?.split('\n');
+        for (const shell of ['bash', 'zsh', 'fish', 'powershell']) {
+            const script = await executeCli(['completions', shell]);
+            expect(script.success).toBe(true);
+            expect(script.message).toContain('completions complete');
+        }
+        expect((await executeCli(['completions', 'tcsh'])).message).toBe('Usage: ax completions <bash|zsh|fish|powershell>');
+        expect(await complete('ax comp')).toEqual(['completions\tPrint a completion script for bash, zsh, fish or PowerShell']);
+        expect(await complete('ax session re')).toEqual(['resume', 'release']);
+        expect(await complete('ax session resume ')).toEqual([`${sessionId}\tactive: Fix the login bug`]);
+        expect(await complete('ax agent run ')).toEqual(['backend\tBackend Engineer']);
+        expect(await complete('ax call --provider ')).toEqual(['claude', 'gemini', 'codex', 'grok']);
+        expect(await complete('ax review --format j')).toEqual(['json', 'jsonl']);
+        expect(await complete('ax agent render backend --t')).toEqual(['--task', '--trace-id', '--team', '--tags']);
+        // Nothing to offer prints nothing, so the shell falls back to its own completion.
+        expect(await complete('ax call ')).toBeUndefined();
+    });
     it('fails fast on invalid global flag usage', async () => {
         const missingValue = await executeCli(['list', '--output-dir']);
         expect(missingValue.success).toBe(false);
@@ -313,6 +501,44 @@ describe('cli dispatch', () => {
         });
         expect(statusStdout).toContain('AutomatosX Status');
     });
+    it('streams workflow replies to piped stdout ahead of the run summary unless --no-stream is given', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const providerPath = join(tempDir, 'slow-provider.mjs');
+        await writeFile(providerPath, [
+            "process.stdin.resume();",
+            "process.stdin.on('end', () => {",
+            "  process.stdout.write('Risk: ');",
+            "  setTimeout(() => process.stdout.write('low.\\nShip it'), 50);",
+            "});",
+        ].join('\n'), 'utf8');
+        mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({
+            providers: { executors: { claude: { command: 'node', args: [providerPath], protocol: 'raw-stdin' } } },
+        }), 'utf8');
+        await writeFile(join(tempDir, 'streamed.json'), JSON.stringify({
+            workflowId: 'streamed',
+            name: 'Streamed',
+            version: '1.0.0',
+            steps: [{ stepId: 'assess', type: 'prompt', config: { prompt: 'Assess the risk.', provider: 'claude' } }],
+        }), 'utf8');
+        const run = (...flags) => execFileAsync('node', [
+            'packages/cli/src/main.js',
+            'run',
+            'streamed',
+            '--workflow-dir',
+            tempDir,
+            '--output-dir',
+            tempDir,
+            ...flags,
+        ], {
+            cwd: process.cwd(),
+        });
+        const { stdout } = await run();
+        expect(stdout).toMatch(/^\[assess\]\nRisk: low\.\nShip it\n\nWorkflow "streamed" completed successfully\./);
+        const { stdout: unstreamed } = await run('--no-stream');
+        expect(unstreamed).toMatch(/^Workflow "streamed" completed successfully\./);
+    });
     it('runs doctor through the main entrypoint as a process', async () => {
         const tempDir = createTempDir();
         tempDirs.push(tempDir);
@@ -347,6 +573,32 @@ describe('cli dispatch', () => {
         expect(stdout).toContain('"status": "warning"');
         expect(stdout).toContain('"fail": 0');
     });
+    it('parses an unsaved buffer piped through stdin', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const parse = (args, input) => new Promise((resolve, reject) => {
+            const child = spawn('node', ['packages/cli/src/main.js', 'code', 'parse', ...args, '--output-dir', tempDir], { cwd: process.cwd() });
+            let stdout = '';
+            child.stdout.on('data', (chunk) => {
+                stdout += chunk.toString('utf8');
+            });
+            child.on('error', reject);
+            child.on('close', (code) => resolve({ code, stdout }));
+            child.stdin.end(input);
+        });
+        const parsed = await parse(['--stdin', '--lang', 'php', '--format', 'json'], '<?php\nclass Draft {\n    public function save() {}\n}\n');
+        expect(parsed.code).toBe(0);
+        const data = JSON.parse(parsed.stdout);
+        expect(data.data.file).toBe('<stdin>');
+        expect(data.data.language).toBe('php');
+        expect(data.data.symbols.map((symbol) => `${symbol.kind} ${symbol.name}`)).toEqual(['class Draft', 'method save']);
+        const detected = await parse(['--stdin', '--file', 'deploy/Dockerfile'], 'FROM node:20\nEXPOSE 8080\n');
+        expect(detected.code).toBe(0);
+        expect(detected.stdout).toContain('Parsed deploy/Dockerfile as dockerfile');
+        expect(detected.stdout).toContain('port 8080 deploy/Dockerfile:2');
+        const unknown = await parse(['--stdin', '--language', 'cobol'], 'IDENTIFICATION DIVISION.\n');
+        expect(unknown.code).not.toBe(0);
+    });
     it('returns process-level failures for invalid invocations', async () => {
         await expect(execFileAsync('node', [
             'packages/cli/src/main.js',
@@ -1,5 +1,5 @@
 import { existsSync, mkdirSync } from 'node:fs';
-import { readFile, rm, unlink } from 'node:fs/promises';
+import { readFile, rm, unlink, writeFile } from 'node:fs/promises';
 import { join } from 'node:path';
 import { afterEach, describe, expect, it } from 'vitest';
 import { createSharedRuntimeService } from '@defai.digital/shared-runtime';
@@ -222,10 +222,11 @@ describe('retained high-value commands', () => {
     expect(result.message).toContain('Provider integration artifacts are present for all enabled providers.');
     expect(result.message).toContain('Trace store is readable but has no traces yet.');
 
-    const data = result.data as { status: string; summary: { fail: number; warn: number } };
+    const data = result.data as { status: string; summary: { fail: number; warn: number }; checks: Array<{ id: string; status: string }> };
     expect(data.status).toBe('warning');
     expect(data.summary.fail).toBe(0);
-    expect(data.summary.warn).toBe(1);
+    // Provider CLIs, their logins and the MCP command depend on the machine running the tests.
+    expect(data.checks.filter((check) => check.status === 'warn' && !/^(provider-cli|provider-auth|mcp-command)\b/.test(check.id))).toHaveLength(1);
   });
 
   it('reports provider integration drift with doctor when generated artifacts are missing', async () => {
@@ -246,10 +247,11 @@ describe('retained high-value commands', () => {
     expect(result.message).toContain('Provider integration drift detected');
     expect(result.message).toContain('.cursor/mcp.json');
 
-    const data = result.data as { status: string; summary: { fail: number; warn: number } };
+    const data = result.data as { status: string; summary: { fail: number; warn: number }; checks: Array<{ id: string; status: string }> };
     expect(data.status).toBe('warning');
     expect(data.summary.fail).toBe(0);
-    expect(data.summary.warn).toBe(2);
+    // Provider CLIs, their logins and the MCP command depend on the machine running the tests.
+    expect(data.checks.filter((check) => check.status === 'warn' && !/^(provider-cli|provider-auth|mcp-command)\b/.test(check.id))).toHaveLength(2);
   });
 
   it('reports setup failures with doctor in an uninitialized workspace', async () => {
@@ -266,6 +268,33 @@ describe('retained high-value commands', () => {
     expect(result.message).toContain('Overall status: unhealthy');
   });
 
+  it('checks the environment with doctor and prints a fix under each problem', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    process.env.AUTOMATOSX_INIT_AVAILABLE_CLIENTS = 'claude,cursor,gemini,codex,grok';
+
+    await setupCommand([], defaultOptions({ outputDir: tempDir }));
+    await initCommand([], defaultOptions({ outputDir: tempDir }));
+    const configPath = join(tempDir, '.automatosx', 'config.json');
+    const config = JSON.parse(await readFile(configPath, 'utf8')) as Record<string, unknown>;
+    await writeFile(configPath, JSON.stringify({ ...config, providers: { executors: { local: { api: 'smoke-signals' } } } }), 'utf8');
+
+    const result = await doctorCommand([], defaultOptions({
+      outputDir: tempDir,
+      workflowDir: join(process.cwd(), 'workflows'),
+    }));
+
+    expect(result.success).toBe(false);
+    expect(result.message).toContain('[FAIL] Workspace config is invalid (.automatosx/config.json): providers.executors.local.api must be one of anthropic, openai, ollama, bedrock, vertex.');
+    expect(result.message).toContain('       Fix: Correct .automatosx/config.json by hand');
+    expect(result.message).toContain('[OK] State directories are writable');
+    expect(result.message).toContain('[OK] MCP server answers over stdio');
+
+    const data = result.data as { checks: Array<{ id: string; status: string; fix?: string }> };
+    expect(data.checks.map((check) => check.id)).toEqual(expect.arrayContaining(['provider-cli:claude', 'provider-cli:local', 'grammars', 'mcp-connect', 'mcp-command']));
+    expect(data.checks.find((check) => check.id === 'provider-cli:local')).toMatchObject({ status: 'warn', fix: expect.stringContaining('providers.executors.local') });
+  });
+
   it('lists available workflows with stable-surface annotations', async () => {
     const tempDir = createTempDir();
     tempDirs.push(tempDir);
@@ -0,0 +1 @@
+export { WORKSPACE_CONFIG_VERSION, ProviderApiSchema, ProviderExecutorSchema, ProvidersConfigSchema, CodeIntelConfigSchema, MaintenanceConfigSchema, MemoryConfigSchema, HooksConfigSchema, ConfigSectionsSchema, WorkspaceConfigSchema, safeValidateWorkspaceConfig, } from './schema.js';
@@ -0,0 +1,22 @@
+export {
+  WORKSPACE_CONFIG_VERSION,
+  ProviderApiSchema,
+  ProviderExecutorSchema,
+  ProvidersConfigSchema,
+  CodeIntelConfigSchema,
+  MaintenanceConfigSchema,
+  MemoryConfigSchema,
+  HooksConfigSchema,
+  ConfigSectionsSchema,
+  WorkspaceConfigSchema,
+  safeValidateWorkspaceConfig,
+  type ProviderApi,
+  type ProviderExecutor,
+  type ProvidersConfig,
+  type CodeIntelConfig,
+  type MaintenanceConfig,
+  type MemoryConfig,
+  type HooksConfig,
+  type ConfigSections,
+  type WorkspaceConfig,
+} from './schema.js';
@@ -0,0 +1,175 @@
+import { z } from 'zod';
+/** The `schemaVersion` of `.automatosx/config.json`; files of an older version are migrated when read. */
+export const WORKSPACE_CONFIG_VERSION = 2;
+const NonEmptyStringSchema = z.string().min(1);
+const PositiveIntegerSchema = z.number().int().positive();
+const NonNegativeIntegerSchema = z.number().int().min(0);
+const SimilaritySchema = z.number().positive().max(1);
+export const ProviderApiSchema = z.enum(['anthropic', 'openai', 'ollama', 'bedrock', 'vertex']);
+export const ProviderExecutorSchema = z.object({
+    api: ProviderApiSchema.optional(),
+    command: NonEmptyStringSchema.optional(),
+    args: z.union([z.string(), z.array(z.string())]).optional(),
+    protocol: z.enum(['json-stdio', 'raw-stdin', 'argv-last']).optional(),
+    promptDialect: z.enum(['plain', 'claude', 'gemini', 'openai']).optional(),
+    timeoutMs: PositiveIntegerSchema.optional(),
+    baseUrl: NonEmptyStringSchema.optional(),
+    apiKeyEnv: NonEmptyStringSchema.optional(),
+    model: NonEmptyStringSchema.optional(),
+    maxTokens: PositiveIntegerSchema.optional(),
+    apiVersion: NonEmptyStringSchema.optional(),
+    pull: z.boolean().optional(),
+    contextLength: PositiveIntegerSchema.optional(),
+    region: NonEmptyStringSchema.optional(),
+    project: NonEmptyStringSchema.optional(),
+    profile: NonEmptyStringSchema.optional(),
+}).strict().refine((executor) => executor.api !== undefined || executor.command !== undefined, {
+    message: 'needs a "command" or an "api"',
+});
+const QuotaLimitsSchema = z.object({
+    requests: PositiveIntegerSchema.optional(),
+    tokens: PositiveIntegerSchema.optional(),
+}).strict();
+export const ProvidersConfigSchema = z.object({
+    default: NonEmptyStringSchema.optional(),
+    fallback: z.array(NonEmptyStringSchema).optional(),
+    nativeAdapters: z.boolean().optional(),
+    executors: z.record(ProviderExecutorSchema).optional(),
+    quotas: z.record(z.object({
+        daily: QuotaLimitsSchema.optional(),
+        monthly: QuotaLimitsSchema.optional(),
+    }).strict()).optional(),
+    quotaWarningThreshold: SimilaritySchema.optional(),
+    routing: z.object({
+        policy: z.enum(['priority', 'cheapest-capable', 'fastest', 'quality-first']).optional(),
+        minQuality: z.number().optional(),
+        requires: z.array(NonEmptyStringSchema).optional(),
+        minContext: PositiveIntegerSchema.optional(),
+    }).strict().optional(),
+    pricing: z.record(z.object({
+        input: z.number().min(0),
+        output: z.number().min(0),
+        quality: z.number().optional(),
+        tokensPerSecond: z.number().positive().optional(),
+        contextWindow: PositiveIntegerSchema.optional(),
+        capabilities: z.array(NonEmptyStringSchema).optional(),
+    }).strict()).optional(),
+    capabilities: z.record(z.object({
+        maxContextTokens: PositiveIntegerSchema.optional(),
+        tools: z.boolean().optional(),
+        vision: z.boolean().optional(),
+        jsonMode: z.boolean().optional(),
+    }).strict()).optional(),
+    consensus: z.object({
+        providers: z.array(NonEmptyStringSchema).optional(),
+        strategy: z.enum(['fastest', 'judge']).optional(),
+        judge: NonEmptyStringSchema.optional(),
+    }).strict().optional(),
+    cache: z.union([z.literal(false), z.object({
+        enabled: z.boolean().optional(),
+        ttlSeconds: z.number().positive().optional(),
+        semantic: z.union([z.boolean(), z.object({ threshold: SimilaritySchema.optional() }).strict()]).optional(),
+    }).strict()]).optional(),
+    concurrency: z.object({
+        maxConcurrent: PositiveIntegerSchema.optional(),
+        perProvider: z.record(PositiveIntegerSchema).optional(),
+    }).strict().optional(),
+    contextBudget: z.object({
+        reserveTokens: NonNegativeIntegerSchema.optional(),
+        strategies: z.array(z.enum(['signature-only', 'drop-oldest', 'summarize-middle'])).optional(),
+    }).strict().optional(),
+    failover: z.object({
+        enabled: z.boolean().optional(),
+        attempts: NonNegativeIntegerSchema.optional(),
+    }).strict().optional(),
+    health: z.object({
+        maxAgeMs: PositiveIntegerSchema.optional(),
+        timeoutMs: PositiveIntegerSchema.optional(),
+        slowMs: PositiveIntegerSchema.optional(),
+    }).strict().optional(),
+    rateLimit: z.object({
+        retries: NonNegativeIntegerSchema.optional(),
+        baseDelayMs: PositiveIntegerSchema.optional(),
+        maxDelayMs: PositiveIntegerSchema.optional(),
+        maxConcurrent: PositiveIntegerSchema.optional(),
+    }).strict().optional(),
+    structuredOutput: z.object({
+        repairAttempts: NonNegativeIntegerSchema.optional(),
+    }).strict().optional(),
+}).strict();
+export const CodeIntelConfigSchema = z.object({
+    grammars: z.array(z.object({
+        language: NonEmptyStringSchema,
+        grammar: NonEmptyStringSchema,
+        query: NonEmptyStringSchema,
+        extensions: z.array(z.string()).optional(),
+        fileNames: z.array(z.string()).optional(),
+        captures: z.record(z.string()).optional(),
+    }).strict()).optional(),
+    grammarRuntime: z.enum(['auto', 'wasm']).optional(),
+    parseCache: z.boolean().optional(),
+    maxMemoryMb: z.number().positive().optional(),
+    embeddings: z.object({
+        backend: z.enum(['openai', 'ollama', 'onnx']),
+        model: NonEmptyStringSchema.optional(),
+        baseUrl: NonEmptyStringSchema.optional(),
+    }).strict().optional(),
+}).strict();
+export const MaintenanceConfigSchema = z.object({
+    intervalHours: z.number().min(0).optional(),
+    memoryMaxAgeDays: z.number().min(0).optional(),
+    memoryMaxEntries: NonNegativeIntegerSchema.optional(),
+    memoryMaxBytes: NonNegativeIntegerSchema.optional(),
+    memoryImportanceHalfLifeDays: z.number().min(0).optional(),
+    memoryMinImportance: z.number().min(0).max(1).optional(),
+    logMaxBytes: NonNegativeIntegerSchema.optional(),
+    logGenerations: NonNegativeIntegerSchema.optional(),
+    logMaxAgeDays: z.number().min(0).optional(),
+    cacheMaxAgeDays: z.number().min(0).optional(),
+}).strict();
+export const MemoryConfigSchema = z.object({
+    encryption: z.union([z.enum(['env', 'keychain']), z.literal(false), z.null()]).optional(),
+    dedup: z.union([z.boolean(), z.object({
+        action: z.enum(['skip', 'merge', 'supersede']).optional(),
+        threshold: SimilaritySchema.optional(),
+    }).strict()]).optional(),
+}).strict();
+export const HooksConfigSchema = z.object({
+    preCommit: z.object({
+        mode: z.enum(['block', 'annotate']).optional(),
+        syntax: z.boolean().optional(),
+        secrets: z.boolean().optional(),
+        review: z.object({
+            enabled: z.boolean().optional(),
+            agent: NonEmptyStringSchema.optional(),
+            focus: z.enum(['security', 'correctness', 'maintainability', 'all']).optional(),
+            timeBudgetMs: PositiveIntegerSchema.optional(),
+        }).strict().optional(),
+    }).strict().optional(),
+}).strict();
+/** The sections a profile may override. */
+export const ConfigSectionsSchema = z.object({
+    providers: ProvidersConfigSchema.optional(),
+    codeIntel: CodeIntelConfigSchema.optional(),
+    maintenance: MaintenanceConfigSchema.optional(),
+    memory: MemoryConfigSchema.optional(),
+    testing: z.object({
+        command: z.union([NonEmptyStringSchema, z.array(NonEmptyStringSchema).min(1)]).optional(),
+        timeoutMs: PositiveIntegerSchema.optional(),
+    }).strict().optional(),
+    replay: z.object({
+        record: z.boolean().optional(),
+    }).strict().optional(),
+    hooks: HooksConfigSchema.optional(),
+}).strict();
+export const WorkspaceConfigSchema = ConfigSectionsSchema.extend({
+    schemaVersion: z.literal(WORKSPACE_CONFIG_VERSION).optional(),
+    productVersion: NonEmptyStringSchema.optional(),
+    createdBy: NonEmptyStringSchema.optional(),
+    workflowArtifactDir: NonEmptyStringSchema.optional(),
+    runtimeStoreDir: NonEmptyStringSchema.optional(),
+    profiles: z.record(ConfigSectionsSchema).optional(),
+}).strict();
+export function safeValidateWorkspaceConfig(data) {
+    return WorkspaceConfigSchema.safeParse(data);
+}
@@ -1,5 +1,6 @@
 export * from './constants.js';
 export * from './workflow/v1/index.js';
+export * from './config/v2/index.js';
 export function getErrorMessage(error, fallback = 'Unknown error') {
     if (error instanceof Error && error.message.length > 0) {
         return error.message;
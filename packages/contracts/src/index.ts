@@ -1,5 +1,6 @@
 export * from './constants.js';
 export * from './workflow/v1/index.js';
+export * from './config/v2/index.js';
 
 export function getErrorMessage(error: unknown, fallback = 'Unknown error'): string {
   if (error instanceof Error && error.message.length > 0) {
@@ -1,10 +1,22 @@
-import { access, mkdir, readFile, writeFile } from 'node:fs/promises';
+import { createHash, randomUUID, timingSafeEqual } from 'node:crypto';
+import { access, appendFile, mkdir, readFile, writeFile } from 'node:fs/promises';
+import { createServer, type IncomingMessage, type ServerResponse } from 'node:http';
 import { dirname, join, relative, resolve } from 'node:path';
 import { createInterface, type Interface } from 'node:readline';
 import type { StepGuardPolicy } from '@defai.digital/contracts';
 import { createDashboardService, type DashboardService } from '@defai.digital/monitoring';
-import { createSharedRuntimeService, type SharedRuntimeService } from '@defai.digital/shared-runtime';
-import type { ReviewFocus } from '@defai.digital/shared-runtime';
+import {
+  createSharedRuntimeService,
+  describeApproval,
+  isMutatingTool,
+  MEMORY_DEDUP_ACTIONS,
+  parseHandoffContract,
+  READ_ONLY_ERROR_CODE,
+  resolveAgentSystemPrompt,
+  type Approver,
+  type SharedRuntimeService,
+} from '@defai.digital/shared-runtime';
+import type { CodeSymbolKind, MemoryDedupAction, ReviewFocus, ScratchpadTaskInput } from '@defai.digital/shared-runtime';
 
 export interface MpcToolResult {
   success: boolean;
@@ -12,8 +24,11 @@ export interface MpcToolResult {
   error?: string;
 }
 
+type JsonSchemaType = 'object' | 'string' | 'number' | 'integer' | 'boolean' | 'array';
+
 interface JsonSchema {
-  type: 'object' | 'string' | 'number' | 'integer' | 'boolean' | 'array';
+  /** Several types accept a value of any of them. */
+  type: JsonSchemaType | JsonSchemaType[];
   properties?: Record<string, JsonSchema>;
   items?: JsonSchema;
   required?: string[];
@@ -35,6 +50,13 @@ export interface McpResourceDefinition {
   mimeType: string;
 }
 
+export interface McpResourceTemplate {
+  uriTemplate: string;
+  name: string;
+  description: string;
+  mimeType: string;
+}
+
 export interface McpPromptArgument {
   name: string;
   description: string;
@@ -60,19 +82,72 @@ export interface McpPromptResult {
   messages: McpPromptMessage[];
 }
 
+export interface McpClientPolicy {
+  name: string;
+  /** Tools the client may call, as patterns over canonical names such as `code.*`. */
+  allow: string[];
+  /** Tools refused even when allowed. */
+  deny: string[];
+  /** Refuses the tools `--read-only` would. */
+  readOnly: boolean;
+  /** Bearer token identifying the client over HTTP. */
+  token?: string;
+  /** Environment variable the token is read from, when it is not in the file. */
+  tokenEnv?: string;
+}
+
+export interface McpAuditEntry {
+  timestamp: string;
+  client: string;
+  /** The tool called, or the one whose policy a resource read or prompt followed. */
+  tool: string;
+  /** The resource read or subscribed to. */
+  resource?: string;
+  /** The prompt got. */
+  prompt?: string;
+  outcome: 'ok' | 'error' | 'denied' | 'cancelled';
+  durationMs: number;
+  /** Argument names only; values can hold file contents or secrets. */
+  arguments: string[];
+  error?: string;
+}
+
 export interface McpResourceContent {
   uri: string;
   mimeType: string;
   text: string;
 }
 
+/** What a long-running tool gets from the call that started it. */
+export interface McpToolCallContext {
+  /** Aborted when the client cancels the call; the provider call or indexing stops with it. */
+  signal?: AbortSignal;
+  /** Reports how far along the tool is; `total` is omitted when it is not known up front. */
+  onProgress?(progress: number, total: number | undefined, message: string): void;
+  /** Receives results as they are found, by tools that can find them a few at a time. */
+  onPartialResult?(items: unknown[]): void;
+  /** Asks the user, through the client, for input of the schema; set when the client supports elicitation. */
+  elicit?(message: string, requestedSchema: Record<string, unknown>): Promise<McpElicitResult>;
+  /** Runs the call as `--read-only` would; set for read-only clients. */
+  readOnly?: boolean;
+}
+
+/** The client's answer to an elicitation. */
+export interface McpElicitResult {
+  action: 'accept' | 'decline' | 'cancel';
+  content?: Record<string, unknown>;
+}
+
 export interface McpServerSurface {
   listTools(): string[];
   listToolDefinitions(): McpToolDefinition[];
-  invokeTool(toolName: string, args?: Record<string, unknown>): Promise<MpcToolResult>;
+  invokeTool(toolName: string, args?: Record<string, unknown>, context?: McpToolCallContext): Promise<MpcToolResult>;
+  /** The canonical name (`code.search`) of a tool or one of its aliases, if it exists. */
+  resolveToolName(toolName: string): string | undefined;
   listResources(): McpResourceDefinition[];
+  listResourceTemplates(): McpResourceTemplate[];
   readResource(uri: string): Promise<McpResourceContent>;
-  listPrompts(): McpPromptDefinition[];
+  listPrompts(): Promise<McpPromptDefinition[]>;
   getPrompt(name: string, args?: Record<string, unknown>): Promise<McpPromptResult>;
 }
 
@@ -91,6 +166,17 @@ interface JsonRpcResponse {
   error?: { code: number; message: string; data?: unknown };
 }
 
+interface JsonRpcNotification {
+  jsonrpc: '2.0';
+  method: string;
+  params?: Record<string, unknown>;
+}
+
+/** A request the server sends the client; it goes out the way notifications do. */
+interface JsonRpcServerRequest extends JsonRpcNotification {
+  id: string;
+}
+
 interface RateLimitConfig {
   maxRequests?: number;
   windowMs?: number;
@@ -103,15 +189,20 @@ interface RateLimiter {
 }
 
 const MCP_VERSION = '2024-11-05';
+// Streamable HTTP arrived with 2025-03-26; a client asking for either gets the version it asked for.
+const SUPPORTED_MCP_VERSIONS = [MCP_VERSION, '2025-03-26', '2025-06-18'];
 const SERVER_NAME = 'automatosx';
 const SERVER_VERSION = '14.0.0';
 const DEFAULT_TOOL_PREFIX = 'ax_';
 
 // JSON-RPC error codes
+const RPC_PARSE_ERROR = -32700;
+const RPC_INVALID_REQUEST = -32600;
 const RPC_METHOD_NOT_FOUND = -32601;
 const RPC_INVALID_PARAMS = -32602;
 const RPC_INTERNAL_ERROR = -32603;
 const RPC_RATE_LIMITED = -32001;
+const RPC_CLIENT_DENIED = -32002;
 const RPC_SERVER_SHUTTING_DOWN = -32000;
 
 const RESOURCE_URIS = {
@@ -120,8 +211,39 @@ const RESOURCE_URIS = {
   workspaceContext: 'ax://workspace/ax-md',
   workflowCatalog: 'ax://workflow/catalog',
   recentTraces: 'ax://trace/recent',
+  codeOutline: 'ax://code/outline',
+  repoMap: 'ax://repomap',
+  agents: 'ax://agents',
+  memory: 'ax://memory',
 } as const;
 
+// How often subscribed resources are re-read to catch changes made outside this server.
+const DEFAULT_RESOURCE_POLL_MS = 5000;
+
+// HTTP transport: Streamable HTTP on MCP_HTTP_PATH, the older HTTP+SSE transport on the other two.
+const DEFAULT_MCP_HTTP_HOST = '127.0.0.1';
+const DEFAULT_MCP_HTTP_PORT = 3100;
+const MCP_HTTP_PATH = '/mcp';
+const MCP_SSE_PATH = '/sse';
+const MCP_SSE_MESSAGES_PATH = '/messages';
+const MCP_SESSION_HEADER = 'mcp-session-id';
+const MAX_HTTP_BODY_BYTES = 4 * 1024 * 1024;
+const DEFAULT_HTTP_SESSION_TTL_MS = 30 * 60_000;
+const SSE_KEEPALIVE_MS = 15_000;
+// Events kept per session for clients resuming a dropped stream with Last-Event-ID.
+const MAX_REPLAY_EVENTS = 1000;
+const STANDALONE_STREAM = 'standalone';
+
+// Per-client tool policies, under .automatosx, and the audit log of tool calls, under .automatosx/logs.
+const MCP_CLIENTS_FILE = 'mcp-clients.json';
+const MCP_AUDIT_LOG_FILE = 'mcp-audit.log';
+const DEFAULT_PAGE_SIZE = 100;
+const MAX_PAGE_SIZE = 1000;
+// The client used without a token or --client; unrestricted unless the clients file defines it.
+const DEFAULT_MCP_CLIENT = 'default';
+const LOOPBACK_HOSTNAMES = new Set(['localhost', '127.0.0.1', '[::1]']);
+const WILDCARD_HOSTS = new Set(['0.0.0.0', '::']);
+
 const PROMPT_DEFINITIONS: McpPromptDefinition[] = [
   {
     name: 'workflow.run',
@@ -157,6 +279,41 @@ const PROMPT_DEFINITIONS: McpPromptDefinition[] = [
   },
 ];
 
+// Every registered agent is also a prompt, `agent.<agentId>`, taking these arguments.
+const AGENT_PROMPT_PREFIX = 'agent.';
+const AGENT_PROMPT_ARGUMENTS: McpPromptArgument[] = [
+  { name: 'task', description: 'What the agent should do.', required: true },
+  { name: 'files', description: 'Comma-separated file or directory paths the task concerns.' },
+  { name: 'constraints', description: 'Constraints the result must respect.' },
+];
+
+// `project` isolates memory to the workspace (`basePath`, else the server's), `agent` to one agent.
+const MEMORY_SCOPE_PROPERTIES = {
+  project: { type: 'boolean', description: 'Scope to the workspace at basePath, or the server workspace.' },
+  agent: { type: 'string', description: 'Scope to one agent.' },
+  basePath: { type: 'string' },
+};
+
+// Taken by the tools in PAGINATED_TOOL_LISTS; the session pages their results, the tools never see them.
+const PAGE_PROPERTIES = {
+  cursor: { type: 'string', description: 'The next_cursor of the previous page.' },
+  pageSize: { type: 'integer', description: `Results per page; ${DEFAULT_PAGE_SIZE} by default.` },
+};
+
+/** Tools returning a list worth paging, with the field holding it, or `''` when the result is the list. */
+const PAGINATED_TOOL_LISTS = new Map<string, string>([
+  ['trace.list', ''],
+  ['agent.list', ''],
+  ['session.list', ''],
+  ['code.search', ''],
+  ['code.search_symbols', ''],
+  ['code.grep', 'matches'],
+  ['code.references', 'references'],
+  ['code.unreferenced', 'candidates'],
+  ['memory.search', ''],
+  ['memory.list', ''],
+]);
+
 const TOOL_DEFINITIONS: McpToolDefinition[] = [
   {
     name: 'workflow.run',
@@ -165,6 +322,7 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
       workflowId: { type: 'string', description: 'Workflow id to execute.' },
       traceId: { type: 'string', description: 'Optional trace id override.' },
       sessionId: { type: 'string', description: 'Optional session id for trace correlation.' },
+      claim: { type: 'string', description: 'A task of the session\'s scratchpad to hold during the run, or next for the next free one; the result is posted to it.' },
       workflowDir: { type: 'string', description: 'Optional workflow directory override.' },
       basePath: { type: 'string', description: 'Optional base path override.' },
       provider: { type: 'string', description: 'Optional provider override.' },
@@ -200,6 +358,7 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
     description: 'List recent traces from the shared trace store.',
     inputSchema: objectSchema({
       limit: { type: 'integer' },
+      ...PAGE_PROPERTIES,
     }),
   },
   {
@@ -243,15 +402,16 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
   },
   {
     name: 'agent.get',
-    description: 'Get a registered agent by id.',
+    description: 'Get a registered agent by id; with resolved, its profile with the agents it extends and its ability packs layered in.',
     inputSchema: objectSchema({
       agentId: { type: 'string' },
+      resolved: { type: 'boolean', description: 'Layer in the agents the profile extends and its ability packs.' },
     }, ['agentId']),
   },
   {
     name: 'agent.list',
     description: 'List registered agents.',
-    inputSchema: objectSchema({}),
+    inputSchema: objectSchema({ ...PAGE_PROPERTIES }),
   },
   {
     name: 'agent.remove',
@@ -267,13 +427,16 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
   },
   {
     name: 'agent.run',
-    description: 'Execute a registered agent through the shared runtime.',
+    description: 'Execute a registered agent through the shared runtime. files attaches workspace files to the task; scope confines the agent to one directory of the workspace, which the provider runs in.',
     inputSchema: objectSchema({
       agentId: { type: 'string' },
       task: { type: 'string' },
       input: objectSchema({}, [], true),
+      files: { type: 'array', items: { type: 'string' }, description: 'Workspace files whose contents go to the agent; they must lie inside scope.' },
+      scope: { type: 'string', description: 'Workspace subdirectory the agent may read and change, such as packages/billing.' },
       traceId: { type: 'string' },
       sessionId: { type: 'string' },
+      claim: { type: 'string', description: 'A task of the session\'s scratchpad to hold during the run, or next for the next free one; the result is posted to it.' },
       basePath: { type: 'string' },
       provider: { type: 'string' },
       model: { type: 'string' },
@@ -282,6 +445,27 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
       rootTraceId: { type: 'string' },
     }, ['agentId']),
   },
+  {
+    name: 'agent.delegate',
+    description: 'Hand a sub-task to a registered agent under a contract: input is checked against contract.input, the agent runs on contract.provider within contract.budget, and the result is returned only when it fits contract.output.',
+    inputSchema: objectSchema({
+      agentId: { type: 'string' },
+      task: { type: 'string' },
+      input: objectSchema({}, [], true),
+      contract: objectSchema({
+        input: objectSchema({}, [], true),
+        output: objectSchema({}, [], true),
+        budget: objectSchema({ maxTokens: { type: 'integer' }, timeoutMs: { type: 'integer' } }),
+        provider: { type: 'string' },
+      }, ['output']),
+      fromAgentId: { type: 'string', description: 'The delegating agent, which may not appear again down the chain.' },
+      traceId: { type: 'string' },
+      sessionId: { type: 'string' },
+      basePath: { type: 'string' },
+      parentTraceId: { type: 'string' },
+      rootTraceId: { type: 'string' },
+    }, ['agentId', 'task', 'contract']),
+  },
   {
     name: 'agent.recommend',
     description: 'Recommend registered agents for a task.',
@@ -367,7 +551,7 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
   {
     name: 'session.list',
     description: 'List collaboration sessions.',
-    inputSchema: objectSchema({}),
+    inputSchema: objectSchema({ ...PAGE_PROPERTIES }),
   },
   {
     name: 'session.join',
@@ -402,6 +586,58 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
       message: { type: 'string' },
     }, ['sessionId', 'message']),
   },
+  {
+    name: 'session.scratchpad',
+    description: 'List the tasks of a session\'s scratchpad: who holds each until when, and the results posted to finished ones.',
+    inputSchema: objectSchema({
+      sessionId: { type: 'string' },
+    }, ['sessionId']),
+  },
+  {
+    name: 'session.add_tasks',
+    description: 'Add tasks to a session\'s scratchpad for its concurrent runs to claim. Tasks whose paths overlap cannot be held at the same time.',
+    inputSchema: objectSchema({
+      sessionId: { type: 'string' },
+      tasks: {
+        type: 'array',
+        items: objectSchema({
+          taskId: { type: 'string', description: 'A slug of the title by default.' },
+          title: { type: 'string' },
+          paths: { type: 'array', items: { type: 'string' }, description: 'Workspace paths the task works on.' },
+        }, ['title']),
+      },
+    }, ['sessionId', 'tasks']),
+  },
+  {
+    name: 'session.claim',
+    description: 'Claim a task of a session\'s scratchpad, or without taskId the next open one whose paths no held task covers. Fails with SCRATCHPAD_CONFLICT when the task is taken.',
+    inputSchema: objectSchema({
+      sessionId: { type: 'string' },
+      taskId: { type: 'string' },
+      claimant: { type: 'string' },
+      leaseMs: { type: 'integer', description: 'How long the claim holds; an hour by default.' },
+    }, ['sessionId', 'claimant']),
+  },
+  {
+    name: 'session.post',
+    description: 'Post the result of a claimed scratchpad task, marking it done or failed. Only its holder can.',
+    inputSchema: objectSchema({
+      sessionId: { type: 'string' },
+      taskId: { type: 'string' },
+      claimant: { type: 'string' },
+      result: { type: 'string' },
+      status: { type: 'string', enum: ['done', 'failed'] },
+    }, ['sessionId', 'taskId', 'claimant', 'result']),
+  },
+  {
+    name: 'session.release',
+    description: 'Open a claimed scratchpad task again; with a claimant, only if it holds the task.',
+    inputSchema: objectSchema({
+      sessionId: { type: 'string' },
+      taskId: { type: 'string' },
+      claimant: { type: 'string' },
+    }, ['sessionId', 'taskId']),
+  },
   {
     name: 'review.analyze',
     description: 'Run deterministic v14 review heuristics and persist artifacts.',
@@ -421,12 +657,190 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
       limit: { type: 'integer' },
     }),
   },
+  {
+    name: 'review.and_annotate',
+    description: 'Review the change between two git refs symbol by symbol: each function, method or other symbol the diff touches goes to an agent with its part of the diff and its code, several at a time, and the findings come back as annotations (path, line span, severity, message) ready to post as pull request review comments.',
+    inputSchema: objectSchema({
+      base: { type: 'string', description: 'Ref the change is made against; main by default.' },
+      head: { type: 'string', description: 'HEAD by default. The change reviewed is base...head.' },
+      agentId: { type: 'string', description: 'Reviewing agent; quality by default.' },
+      maxConcurrent: { type: 'integer', description: 'Agent runs at a time; 4 by default.' },
+      maxSymbols: { type: 'integer', description: 'Changed symbols reviewed at most; 40 by default.' },
+      provider: { type: 'string' },
+      model: { type: 'string' },
+      timeoutMs: { type: 'integer' },
+      sessionId: { type: 'string' },
+      basePath: { type: 'string' },
+    }),
+  },
+  {
+    name: 'code.index',
+    description: 'Build or refresh the symbol index of the workspace, or of some paths in it, that the other code tools read. Reports files indexed as progress and stops without writing when cancelled.',
+    inputSchema: objectSchema({
+      paths: { type: 'array', items: { type: 'string' } },
+      maxFiles: { type: 'integer' },
+      basePath: { type: 'string' },
+    }),
+  },
+  {
+    name: 'code.unsafe',
+    description: 'Report unsafe code, extern/FFI items, and raw-pointer usage per Rust crate with source spans.',
+    inputSchema: objectSchema({
+      paths: { type: 'array', items: { type: 'string' } },
+      crate: { type: 'string' },
+      basePath: { type: 'string' },
+    }),
+  },
+  {
+    name: 'code.signatures',
+    description: 'Render files or directories as skeletons (types, fields, function signatures) with bodies elided, to fit more API surface into context.',
+    inputSchema: objectSchema({
+      paths: { type: 'array', items: { type: 'string' } },
+      basePath: { type: 'string' },
+    }, ['paths']),
+  },
+  {
+    name: 'code.outline',
+    description: 'Outline files or a whole crate as Markdown: nested headings per symbol with its signature and first doc line.',
+    inputSchema: objectSchema({
+      paths: { type: 'array', items: { type: 'string' } },
+      basePath: { type: 'string' },
+    }, ['paths']),
+  },
+  {
+    name: 'code.file_outline',
+    description: 'Render one file as a skeleton with bodies elided, plus its declarations with the ids code.symbol_body takes, to read the API of a file before reading any of its code.',
+    inputSchema: objectSchema({
+      path: { type: 'string' },
+      basePath: { type: 'string' },
+    }, ['path']),
+  },
+  {
+    name: 'code.symbol_body',
+    description: 'Return the source of one function, type or other declaration with its doc comment and attributes, by file:line id from code.file_outline, stable id, or qualified name.',
+    inputSchema: objectSchema({
+      symbol: { type: 'string' },
+      basePath: { type: 'string' },
+    }, ['symbol']),
+  },
+  {
+    name: 'code.graph',
+    description: 'Render the file import graph or the function call graph as Graphviz DOT or a Mermaid flowchart for architecture review.',
+    inputSchema: objectSchema({
+      graph: { type: 'string', enum: ['modules', 'calls'] },
+      format: { type: 'string', enum: ['dot', 'mermaid'] },
+      paths: { type: 'array', items: { type: 'string' } },
+      basePath: { type: 'string' },
+    }, ['graph']),
+  },
+  {
+    name: 'code.search',
+    description: 'Fuzzy-search indexed symbols by name ("DatProc" finds DataProcessor) or qualified path ("parser::Parser::new"), narrowed with kind:, lang: and path: filters.',
+    inputSchema: objectSchema({
+      query: { type: 'string' },
+      limit: { type: 'integer' },
+      basePath: { type: 'string' },
+      ...PAGE_PROPERTIES,
+    }, ['query']),
+  },
+  {
+    name: 'code.search_symbols',
+    description: 'Search indexed symbols by fuzzy name or qualified path, optionally of one kind (class, method, trait, ...) and language. Returns the qualified name, signature, doc line, file and span of each, to navigate the workspace without grepping.',
+    inputSchema: objectSchema({
+      query: { type: 'string' },
+      kind: { type: 'string' },
+      language: { type: 'string' },
+      limit: { type: 'integer' },
+      basePath: { type: 'string' },
+      ...PAGE_PROPERTIES,
+    }),
+  },
+  {
+    name: 'code.grep',
+    description: 'Search file contents for a literal or regular expression through a trigram index, reading only the files that can match. Returns file, line, column and the matching line. Can stream each file\'s matches as partial results.',
+    inputSchema: objectSchema({
+      pattern: { type: 'string' },
+      regex: { type: 'boolean' },
+      caseSensitive: { type: 'boolean' },
+      paths: { type: 'array', items: { type: 'string' } },
+      limit: { type: 'integer' },
+      reindex: { type: 'boolean' },
+      basePath: { type: 'string' },
+      ...PAGE_PROPERTIES,
+    }, ['pattern']),
+  },
+  {
+    name: 'search.hybrid',
+    description: 'Search memory, indexed symbols and file contents at once, fusing the rankings so both natural-language questions and identifier fragments find what they refer to.',
+    inputSchema: objectSchema({
+      query: { type: 'string' },
+      k: { type: 'integer' },
+      namespace: { type: 'string' },
+      basePath: { type: 'string' },
+    }, ['query']),
+  },
+  {
+    name: 'code.references',
+    description: 'Find every use of an indexed symbol (by stable id, file:line id, or qualified name), resolved through imports, namespaces, and method receivers.',
+    inputSchema: objectSchema({
+      symbol: { type: 'string' },
+      basePath: { type: 'string' },
+      ...PAGE_PROPERTIES,
+    }, ['symbol']),
+  },
+  {
+    name: 'code.call_hierarchy',
+    description: 'Walk the call graph from a symbol (stable id, file:line id, or qualified name): its callers and their callers (incoming) or its callees (outgoing), up to depth calls away, to gauge the blast radius of a change.',
+    inputSchema: objectSchema({
+      symbol: { type: 'string' },
+      direction: { type: 'string', enum: ['incoming', 'outgoing'] },
+      depth: { type: 'integer' },
+      basePath: { type: 'string' },
+    }, ['symbol']),
+  },
+  {
+    name: 'code.change_impact',
+    description: 'Assess a change before review: pass a unified diff or the edited files (or neither, for the working tree against base) to get the changed symbols, their callers up to depth calls away, the tests to run, and suggested reviewers from CODEOWNERS and git history.',
+    inputSchema: objectSchema({
+      diff: { type: 'string' },
+      files: { type: 'array', items: { type: 'string' } },
+      base: { type: 'string' },
+      depth: { type: 'integer' },
+      basePath: { type: 'string' },
+    }),
+  },
+  {
+    name: 'code.unreferenced',
+    description: 'List indexed symbols with no inbound references, excluding entry points, test code, and trait or interface implementations.',
+    inputSchema: objectSchema({
+      paths: { type: 'array', items: { type: 'string' } },
+      kind: { type: 'string' },
+      limit: { type: 'integer' },
+      basePath: { type: 'string' },
+      ...PAGE_PROPERTIES,
+    }),
+  },
+  {
+    name: 'test.and_fix',
+    description: 'Run the test command, give the failing tests (cargo test, Jest, Vitest or pytest output) and the code they exercise to an agent, apply the edits it proposes and run the tests again, up to maxIterations times. Returns a report of every attempt; edits are left in the working tree.',
+    inputSchema: objectSchema({
+      agentId: { type: 'string', description: 'Agent that proposes fixes; bug-hunter by default.' },
+      maxIterations: { type: 'integer', description: 'Fix attempts; 3 by default.' },
+      command: { type: 'array', items: { type: 'string' }, description: 'Test command; testing.command from the workspace config by default.' },
+      provider: { type: 'string' },
+      model: { type: 'string' },
+      timeoutMs: { type: 'integer' },
+      sessionId: { type: 'string' },
+      basePath: { type: 'string' },
+    }),
+  },
   {
     name: 'memory.retrieve',
     description: 'Retrieve a single memory entry by key.',
     inputSchema: objectSchema({
       key: { type: 'string' },
       namespace: { type: 'string' },
+      ...MEMORY_SCOPE_PROPERTIES,
     }, ['key']),
   },
   {
@@ -435,6 +849,8 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
     inputSchema: objectSchema({
       query: { type: 'string' },
       namespace: { type: 'string' },
+      ...MEMORY_SCOPE_PROPERTIES,
+      ...PAGE_PROPERTIES,
     }, ['query']),
   },
   {
@@ -443,24 +859,112 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
     inputSchema: objectSchema({
       key: { type: 'string' },
       namespace: { type: 'string' },
+      ...MEMORY_SCOPE_PROPERTIES,
     }, ['key']),
   },
   {
     name: 'memory.store',
-    description: 'Store a memory entry.',
+    description: 'Store a memory entry, optionally expiring after ttlSeconds or with an importance (0-1) that decays while it goes unused. source records the file span, session, and provider it came from, so answers can cite it and memory.stale can tell when the file changed. dedup skips, merges into, or supersedes a near-identical entry in the namespace (memory.dedup in the workspace config sets the default).',
     inputSchema: objectSchema({
       key: { type: 'string' },
       namespace: { type: 'string' },
       value: objectSchema({}, [], true),
+      ttlSeconds: { type: 'number' },
+      importance: { type: 'number' },
+      source: objectSchema({
+        file: { type: 'string' },
+        startLine: { type: 'integer' },
+        endLine: { type: 'integer' },
+        sessionId: { type: 'string' },
+        agent: { type: 'string' },
+        provider: { type: 'string' },
+      }),
+      dedup: { type: 'string', enum: [...MEMORY_DEDUP_ACTIONS, 'off'] },
+      ...MEMORY_SCOPE_PROPERTIES,
     }, ['key']),
   },
   {
     name: 'memory.list',
-    description: 'List memory entries.',
+    description: 'List memory entries, of every namespace in the scope when no namespace is given.',
+    inputSchema: objectSchema({
+      namespace: { type: 'string' },
+      ...MEMORY_SCOPE_PROPERTIES,
+      ...PAGE_PROPERTIES,
+    }),
+  },
+  {
+    name: 'memory.namespaces',
+    description: 'List stored memory namespaces, including project- and agent-scoped ones, with entry counts.',
+    inputSchema: objectSchema({}),
+  },
+  {
+    name: 'memory.copy',
+    description: 'Copy every entry of one memory namespace into another, keeping keys the target already holds unless overwrite is set.',
+    inputSchema: objectSchema({
+      source: { type: 'string' },
+      target: { type: 'string' },
+      overwrite: { type: 'boolean' },
+    }, ['source', 'target']),
+  },
+  {
+    name: 'memory.merge',
+    description: 'Move the entries of memory namespaces into a target, keeping the most recently updated entry per key, and delete the sources.',
+    inputSchema: objectSchema({
+      sources: { type: 'array', items: { type: 'string' } },
+      target: { type: 'string' },
+    }, ['sources', 'target']),
+  },
+  {
+    name: 'memory.prune',
+    description: 'Delete expired, stale, and no longer important memory entries and the least recently used ones over the size caps, per the workspace retention settings.',
+    inputSchema: objectSchema({
+      dryRun: { type: 'boolean' },
+      basePath: { type: 'string' },
+    }),
+  },
+  {
+    name: 'memory.stale',
+    description: 'List memory entries whose source file changed or was deleted since they were stored. invalidate deletes them.',
+    inputSchema: objectSchema({
+      namespace: { type: 'string' },
+      invalidate: { type: 'boolean' },
+      ...MEMORY_SCOPE_PROPERTIES,
+    }),
+  },
+  {
+    name: 'fact.store',
+    description: 'Store a subject-predicate-object fact such as "billing-api calls ledger-api", with the source span it was read from, for exact lookup. Storing the same triple again updates it.',
+    inputSchema: objectSchema({
+      subject: { type: 'string' },
+      predicate: { type: 'string' },
+      object: { type: ['string', 'number', 'boolean'] },
+      namespace: { type: 'string' },
+      sourceSpan: objectSchema({
+        file: { type: 'string' },
+        startLine: { type: 'integer' },
+        endLine: { type: 'integer' },
+      }, ['file']),
+      confidence: { type: 'number' },
+      ...MEMORY_SCOPE_PROPERTIES,
+    }, ['subject', 'predicate', 'object']),
+  },
+  {
+    name: 'fact.query',
+    description: 'Find facts matching the given subject, predicate, and object exactly; leave fields out to match anything.',
     inputSchema: objectSchema({
+      subject: { type: 'string' },
+      predicate: { type: 'string' },
+      object: { type: ['string', 'number', 'boolean'] },
       namespace: { type: 'string' },
+      limit: { type: 'integer' },
+      ...MEMORY_SCOPE_PROPERTIES,
     }),
   },
+  {
+    name: 'fact.delete',
+    description: 'Delete a fact by its factId.',
+    inputSchema: objectSchema({ factId: { type: 'string' } }, ['factId']),
+  },
   {
     name: 'semantic.store',
     description: 'Store semantic content for later similarity search.',
@@ -608,6 +1112,18 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
     description: 'Show the full workspace config.',
     inputSchema: objectSchema({}),
   },
+  {
+    name: 'config.explain',
+    description: 'Show which config layer set a value by dot-separated path, and what each layer had.',
+    inputSchema: objectSchema({
+      path: { type: 'string' },
+    }, ['path']),
+  },
+  {
+    name: 'config.validate',
+    description: 'Check each config file, and the config with every layer applied, against the config schema, with a suggestion for each unknown key.',
+    inputSchema: objectSchema({}),
+  },
   {
     name: 'file.exists',
     description: 'Check whether a workspace-relative path exists.',
@@ -913,6 +1429,18 @@ const TOOL_DEFINITIONS: McpToolDefinition[] = [
       dryRun: { type: 'boolean' },
     }, ['policyId']),
   },
+  {
+    name: 'scaffold.impl',
+    description: 'Scaffold an implementation class with stubbed members from an indexed interface.',
+    inputSchema: objectSchema({
+      symbol: { type: 'string' },
+      className: { type: 'string' },
+      output: { type: 'string' },
+      overwrite: { type: 'boolean' },
+      dryRun: { type: 'boolean' },
+      basePath: { type: 'string' },
+    }, ['symbol']),
+  },
   // ── Ability registry (custom abilities stored in semantic namespace) ────────
   {
     name: 'ability.get',
@@ -1079,191 +1607,578 @@ export interface McpStdioServer {
   serve(): Promise<void>;
 }
 
-export function createMcpStdioServer(config: {
-  runtimeService?: SharedRuntimeService;
-  dashboardService?: DashboardService;
-  basePath?: string;
-  input?: NodeJS.ReadableStream;
-  output?: NodeJS.WritableStream;
-  rateLimit?: RateLimitConfig;
-  toolPrefix?: string;
-} = {}): McpStdioServer {
-  const surface = createMcpServerSurface({
-    runtimeService: config.runtimeService,
-    dashboardService: config.dashboardService,
-    basePath: config.basePath,
-    toolPrefix: config.toolPrefix,
-  });
+interface McpSession {
+  /**
+   * Handles one client message and returns the response to send, if it warrants one; a cancelled
+   * call gets none. Progress of that message goes to `notify` when given, else the session's own.
+   */
+  handle(request: JsonRpcRequest, notify?: (message: JsonRpcNotification) => void): Promise<JsonRpcResponse | undefined>;
+  close(): void;
+  /** Aborts the tool calls still running, for a client that can no longer receive their results. */
+  cancelAll(): void;
+}
 
-  const input = config.input ?? process.stdin;
-  const output = config.output ?? process.stdout;
-  const rateLimiter = createRateLimiter(config.rateLimit);
-  let rl: Interface | undefined;
+/**
+ * The protocol state of one connected client, independent of how messages reach it: the shutdown
+ * flag, resource subscriptions and tool calls in flight. Server-initiated notifications go out
+ * through `notify`.
+ */
+function createMcpSession(config: {
+  surface: McpServerSurface;
+  rateLimiter: RateLimiter;
+  client: McpClientPolicy;
+  audit?: McpAuditLog;
+  resourcePollMs?: number;
+  notify(message: JsonRpcNotification): void;
+  onShutdown?(): void;
+}): McpSession {
+  const { surface, rateLimiter } = config;
   let shuttingDown = false;
+  // Subscribed resource URI -> hash of the content the client was last told about.
+  const subscriptions = new Map<string, string | undefined>();
+  let pollTimer: NodeJS.Timeout | undefined;
+  let checking: Promise<void> | undefined;
+  // Request id of each running tools/call -> what `notifications/cancelled` aborts it with.
+  const inFlight = new Map<string | number, AbortController>();
+  // What the client declared at initialize, and the requests sent to it that wait for its response.
+  let clientCapabilities: Record<string, unknown> = {};
+  const awaiting = new Map<string | number, (message: Partial<JsonRpcResponse>) => void>();
+  let nextRequestId = 0;
 
-  function send(response: JsonRpcResponse): void {
-    output.write(`${JSON.stringify(response)}\n`);
+  // Sends the client a request and resolves with its result; aborting `signal` stops waiting.
+  function requestClient(
+    method: string,
+    params: Record<string, unknown>,
+    notify: (message: JsonRpcNotification) => void,
+    signal: AbortSignal,
+  ): Promise<unknown> {
+    nextRequestId += 1;
+    const id = `ax-${nextRequestId}`;
+    return new Promise((resolve, reject) => {
+      const onAbort = () => {
+        awaiting.delete(id);
+        reject(new Error(`${method} was cancelled.`));
+      };
+      if (signal.aborted) {
+        onAbort();
+        return;
+      }
+      signal.addEventListener('abort', onAbort, { once: true });
+      awaiting.set(id, (message) => {
+        signal.removeEventListener('abort', onAbort);
+        if (message.error !== undefined) {
+          reject(new Error(message.error.message));
+        } else {
+          resolve(message.result);
+        }
+      });
+      const serverRequest: JsonRpcServerRequest = { jsonrpc: '2.0', id, method, params };
+      notify(serverRequest);
+    });
   }
 
-  function sendError(id: string | number | null, code: number, message: string, data?: unknown): void {
-    const response: JsonRpcResponse = { jsonrpc: '2.0', id, error: { code, message } };
-    if (data !== undefined) {
-      response.error!.data = data;
+  async function fingerprint(uri: string): Promise<string | undefined> {
+    try {
+      return createHash('sha256').update((await surface.readResource(uri)).text).digest('hex');
+    } catch {
+      // A resource that cannot be read now (an index not built yet) changes when it can be again.
+      return undefined;
     }
-    send(response);
   }
 
-  function buildToolDefinitions(): McpToolDefinition[] {
-    return surface.listToolDefinitions();
+  // One check at a time; a tool call arriving mid-check waits for it, then checks again after its own writes.
+  async function checkSubscriptions(): Promise<void> {
+    while (checking !== undefined) {
+      await checking;
+    }
+    checking = (async () => {
+      for (const [uri, seen] of subscriptions) {
+        const current = await fingerprint(uri);
+        if (current !== seen && subscriptions.has(uri)) {
+          subscriptions.set(uri, current);
+          config.notify({ jsonrpc: '2.0', method: 'notifications/resources/updated', params: { uri } });
+        }
+      }
+    })().finally(() => {
+      checking = undefined;
+    });
+    return checking;
   }
 
-  async function handleRequest(request: JsonRpcRequest): Promise<void> {
+  function stopPolling(): void {
+    clearInterval(pollTimer);
+    pollTimer = undefined;
+  }
+
+  function error(id: string | number | null, code: number, message: string): JsonRpcResponse {
+    return { jsonrpc: '2.0', id, error: { code, message } };
+  }
+
+  // Resources and prompts show what tools do, so each is read under the client policy of its tool
+  // and audited like a call to it.
+  async function readAsTool<T>(
+    toolName: string,
+    target: { resource: string } | { prompt: string },
+    args: string[],
+    read: () => Promise<T>,
+  ): Promise<{ value: T } | { refusal: string }> {
+    const startedAt = Date.now();
+    const audit = (outcome: McpAuditEntry['outcome'], failure?: string) => config.audit?.({
+      timestamp: new Date(startedAt).toISOString(),
+      client: config.client.name,
+      tool: toolName,
+      ...target,
+      outcome,
+      durationMs: Date.now() - startedAt,
+      arguments: args,
+      ...(failure !== undefined ? { error: failure } : {}),
+    });
+    const refusal = checkClientPolicy(config.client, toolName, { reading: true });
+    if (refusal !== undefined) {
+      await audit('denied', refusal);
+      return { refusal };
+    }
+    try {
+      const value = await read();
+      await audit('ok');
+      return { value };
+    } catch (caught) {
+      await audit('error', caught instanceof Error ? caught.message : String(caught));
+      throw caught;
+    }
+  }
+
+  function readsAllowed(toolName: string | undefined): boolean {
+    return toolName === undefined || checkClientPolicy(config.client, toolName, { reading: true }) === undefined;
+  }
+
+  async function handle(
+    request: JsonRpcRequest,
+    notify: (message: JsonRpcNotification) => void = config.notify,
+  ): Promise<JsonRpcResponse | undefined> {
     const { id, method, params } = request;
 
+    // A response to a request the server sent, such as an elicitation.
+    if (typeof method !== 'string') {
+      if (id !== null && id !== undefined) {
+        awaiting.get(id)?.(request as Partial<JsonRpcResponse>);
+        awaiting.delete(id);
+      }
+      return undefined;
+    }
+
     try {
       if (shuttingDown && method !== 'shutdown') {
-        sendError(id, RPC_SERVER_SHUTTING_DOWN, 'Server is shutting down');
-        return;
+        return error(id, RPC_SERVER_SHUTTING_DOWN, 'Server is shutting down');
       }
 
       if (isRateLimitedMethod(method) && !rateLimiter.allow()) {
-        sendError(
+        return error(
           id,
           RPC_RATE_LIMITED,
           `Rate limit exceeded: max ${rateLimiter.maxRequests} requests per ${rateLimiter.windowMs}ms`,
         );
-        return;
       }
 
       switch (method) {
         case 'initialize': {
-          send({
+          const requested = params?.protocolVersion;
+          clientCapabilities = isRecord(params?.capabilities) ? params.capabilities : {};
+          return {
             jsonrpc: '2.0',
             id,
             result: {
-              protocolVersion: MCP_VERSION,
+              protocolVersion: typeof requested === 'string' && SUPPORTED_MCP_VERSIONS.includes(requested) ? requested : MCP_VERSION,
               serverInfo: { name: SERVER_NAME, version: SERVER_VERSION },
               capabilities: {
                 tools: { listChanged: false },
-                resources: { listChanged: false, subscribe: false },
+                resources: { listChanged: false, subscribe: true },
                 prompts: { listChanged: false },
               },
             },
-          });
-          break;
+          };
         }
 
         case 'notifications/initialized':
-          break;
+          return undefined;
+
+        case 'notifications/cancelled': {
+          const requestId = params?.requestId;
+          if (typeof requestId === 'string' || typeof requestId === 'number') {
+            inFlight.get(requestId)?.abort(params?.reason);
+          }
+          return undefined;
+        }
 
         case 'tools/list': {
-          send({
-            jsonrpc: '2.0',
-            id,
-            result: { tools: buildToolDefinitions() },
-          });
-          break;
+          const tools = surface.listToolDefinitions()
+            .filter((tool) => checkClientPolicy(config.client, surface.resolveToolName(tool.name) ?? tool.name) === undefined);
+          return { jsonrpc: '2.0', id, result: { tools } };
         }
 
         case 'tools/call': {
           const toolName = params?.name;
           if (typeof toolName !== 'string' || toolName.length === 0) {
-            sendError(id, RPC_INVALID_PARAMS, 'tools/call requires params.name');
-            break;
+            return error(id, RPC_INVALID_PARAMS, 'tools/call requires params.name');
           }
           const toolArgs = isRecord(params?.arguments) ? params.arguments : {};
-          const result = await surface.invokeTool(toolName, toolArgs);
+          const startedAt = Date.now();
+          const canonicalToolName = surface.resolveToolName(toolName);
+          const refusal = canonicalToolName === undefined ? undefined : checkClientPolicy(config.client, canonicalToolName);
+          const listField = canonicalToolName === undefined ? undefined : PAGINATED_TOOL_LISTS.get(canonicalToolName);
+          const pageQuery = listField === undefined ? undefined : pageQueryKey(canonicalToolName ?? toolName, toolArgs);
+          let offset = 0;
+          if (pageQuery !== undefined && toolArgs.cursor !== undefined) {
+            const cursorOffset = readPageCursor(toolArgs.cursor, pageQuery);
+            if (cursorOffset === undefined) {
+              return error(id, RPC_INVALID_PARAMS, 'Invalid cursor: pass the next_cursor of the previous page of this same call.');
+            }
+            offset = cursorOffset;
+          }
+          const controller = new AbortController();
+          if (id !== null && id !== undefined) {
+            inFlight.set(id, controller);
+          }
+          let result: MpcToolResult;
+          try {
+            result = refusal === undefined
+              ? await surface.invokeTool(toolName, toolArgs, {
+                signal: controller.signal,
+                onProgress: progressReporter(params?._meta, notify),
+                onPartialResult: partialResultReporter(params?._meta, id, notify),
+                elicit: isRecord(clientCapabilities.elicitation)
+                  ? async (message, requestedSchema) => readElicitResult(
+                    await requestClient('elicitation/create', { message, requestedSchema }, notify, controller.signal),
+                  )
+                  : undefined,
+                readOnly: config.client.readOnly,
+              })
+              : { success: false, error: refusal };
+          } finally {
+            if (id !== null && id !== undefined) {
+              inFlight.delete(id);
+            }
+          }
+          const cancelled = controller.signal.aborted;
+          await config.audit?.({
+            timestamp: new Date(startedAt).toISOString(),
+            client: config.client.name,
+            tool: canonicalToolName ?? toolName,
+            outcome: refusal !== undefined ? 'denied' : cancelled ? 'cancelled' : result.success ? 'ok' : 'error',
+            durationMs: Date.now() - startedAt,
+            arguments: Object.keys(toolArgs),
+            ...(result.success || cancelled ? {} : { error: result.error }),
+          });
+          // The client has stopped waiting for a cancelled call; it is not answered.
+          if (cancelled) {
+            return undefined;
+          }
+          // A tool may have written what a subscribed resource shows; the client hears of it before the result.
+          if (subscriptions.size > 0) {
+            await checkSubscriptions();
+          }
           if (result.success) {
-            send({
-              jsonrpc: '2.0',
-              id,
-              result: {
-                content: [{ type: 'text', text: JSON.stringify(result.data, null, 2) }],
-              },
-            });
-          } else {
-            send({
-              jsonrpc: '2.0',
-              id,
-              result: {
-                content: [{ type: 'text', text: result.error ?? 'Tool failed' }],
-                isError: true,
-              },
-            });
+            const page = listField === undefined ? undefined : pageToolResult(result.data, listField, offset, readPageSize(toolArgs.pageSize));
+            const content = [{ type: 'text', text: JSON.stringify(page === undefined ? result.data : page.data, null, 2) }];
+            if (page?.nextOffset !== undefined && pageQuery !== undefined) {
+              content.push({ type: 'text', text: JSON.stringify({ next_cursor: writePageCursor(page.nextOffset, pageQuery), total: page.total }) });
+            }
+            return { jsonrpc: '2.0', id, result: { content } };
           }
-          break;
-        }
-
-        case 'resources/list': {
-          send({
+          return {
             jsonrpc: '2.0',
             id,
-            result: { resources: surface.listResources() },
-          });
-          break;
+            result: {
+              content: [{ type: 'text', text: result.error ?? 'Tool failed' }],
+              isError: true,
+            },
+          };
         }
 
+        case 'resources/list':
+          return { jsonrpc: '2.0', id, result: { resources: surface.listResources().filter((resource) => readsAllowed(resourceToolName(resource.uri))) } };
+
         case 'resources/read': {
           const uri = params?.uri;
           if (typeof uri !== 'string' || uri.length === 0) {
-            sendError(id, RPC_INVALID_PARAMS, 'resources/read requires params.uri');
-            break;
+            return error(id, RPC_INVALID_PARAMS, 'resources/read requires params.uri');
           }
-          const content = await surface.readResource(uri);
-          send({
-            jsonrpc: '2.0',
-            id,
-            result: { contents: [content] },
-          });
-          break;
+          const read = await readAsTool(resourceToolName(uri) ?? method, { resource: uri }, [], () => surface.readResource(uri));
+          if ('refusal' in read) {
+            return error(id, RPC_CLIENT_DENIED, read.refusal);
+          }
+          return { jsonrpc: '2.0', id, result: { contents: [read.value] } };
         }
 
-        case 'prompts/list': {
-          send({
+        case 'resources/templates/list':
+          return {
             jsonrpc: '2.0',
             id,
-            result: { prompts: surface.listPrompts() },
-          });
-          break;
+            result: { resourceTemplates: surface.listResourceTemplates().filter((template) => readsAllowed(resourceToolName(template.uriTemplate))) },
+          };
+
+        case 'resources/subscribe': {
+          const uri = params?.uri;
+          if (typeof uri !== 'string' || uri.length === 0) {
+            return error(id, RPC_INVALID_PARAMS, 'resources/subscribe requires params.uri');
+          }
+          const read = await readAsTool(resourceToolName(uri) ?? method, { resource: uri }, [], () => surface.readResource(uri));
+          if ('refusal' in read) {
+            return error(id, RPC_CLIENT_DENIED, read.refusal);
+          }
+          subscriptions.set(uri, createHash('sha256').update(read.value.text).digest('hex'));
+          if (pollTimer === undefined) {
+            pollTimer = setInterval(() => {
+              if (checking === undefined) {
+                void checkSubscriptions();
+              }
+            }, config.resourcePollMs ?? DEFAULT_RESOURCE_POLL_MS);
+            pollTimer.unref();
+          }
+          return { jsonrpc: '2.0', id, result: {} };
+        }
+
+        case 'resources/unsubscribe': {
+          const uri = params?.uri;
+          if (typeof uri !== 'string' || uri.length === 0) {
+            return error(id, RPC_INVALID_PARAMS, 'resources/unsubscribe requires params.uri');
+          }
+          subscriptions.delete(uri);
+          if (subscriptions.size === 0) {
+            stopPolling();
+          }
+          return { jsonrpc: '2.0', id, result: {} };
         }
 
+        case 'prompts/list':
+          return { jsonrpc: '2.0', id, result: { prompts: (await surface.listPrompts()).filter((prompt) => readsAllowed(promptToolName(prompt.name))) } };
+
         case 'prompts/get': {
           const name = params?.name;
           if (typeof name !== 'string' || name.length === 0) {
-            sendError(id, RPC_INVALID_PARAMS, 'prompts/get requires params.name');
-            break;
+            return error(id, RPC_INVALID_PARAMS, 'prompts/get requires params.name');
           }
-          const prompt = await surface.getPrompt(name, isRecord(params?.arguments) ? params.arguments : {});
-          send({
-            jsonrpc: '2.0',
-            id,
-            result: prompt,
-          });
-          break;
+          const promptArgs = isRecord(params?.arguments) ? params.arguments : {};
+          const prompt = await readAsTool(promptToolName(name), { prompt: name }, Object.keys(promptArgs), () => surface.getPrompt(name, promptArgs));
+          if ('refusal' in prompt) {
+            return error(id, RPC_CLIENT_DENIED, prompt.refusal);
+          }
+          return { jsonrpc: '2.0', id, result: prompt.value };
         }
 
         case 'shutdown': {
           shuttingDown = true;
-          send({ jsonrpc: '2.0', id, result: {} });
-          queueMicrotask(() => rl?.close());
-          break;
+          stopPolling();
+          config.onShutdown?.();
+          return { jsonrpc: '2.0', id, result: {} };
         }
 
-        case 'ping': {
-          send({ jsonrpc: '2.0', id, result: {} });
-          break;
-        }
+        case 'ping':
+          return { jsonrpc: '2.0', id, result: {} };
 
         default:
-          sendError(id, RPC_METHOD_NOT_FOUND, `Method not found: ${method}`);
+          return error(id, RPC_METHOD_NOT_FOUND, `Method not found: ${method}`);
       }
-    } catch (error) {
-      sendError(id, RPC_INTERNAL_ERROR, error instanceof Error ? error.message : String(error));
+    } catch (caught) {
+      return error(id, RPC_INTERNAL_ERROR, caught instanceof Error ? caught.message : String(caught));
     }
   }
 
   return {
-    serve(): Promise<void> {
+    handle,
+    close() {
+      shuttingDown = true;
+      subscriptions.clear();
+      stopPolling();
+    },
+    cancelAll() {
+      for (const controller of inFlight.values()) {
+        controller.abort();
+      }
+    },
+  };
+}
+
+function readElicitResult(result: unknown): McpElicitResult {
+  const action = isRecord(result) ? result.action : undefined;
+  return {
+    action: action === 'accept' || action === 'decline' ? action : 'cancel',
+    ...(isRecord(result) && isRecord(result.content) ? { content: result.content } : {}),
+  };
+}
+
+/**
+ * Puts the actions an agent's `approvals` hold to the user as elicitations. Without elicitation
+ * support, or when the user declines or cancels, only what the approval policy allows goes ahead.
+ */
+function elicitationApprover(context: McpToolCallContext): Approver | undefined {
+  const { elicit } = context;
+  if (elicit === undefined) {
+    return undefined;
+  }
+  return async (request) => {
+    const answer = await elicit(`Agent "${request.agentId}" wants to ${describeApproval(request)}. Approve?`, {
+      type: 'object',
+      properties: { approve: { type: 'boolean', title: 'Approve', description: 'Let the agent go ahead.' } },
+      required: ['approve'],
+    }).catch(() => undefined);
+    return answer?.action === 'accept' && answer.content?.approve === true;
+  };
+}
+
+/**
+ * Turns a tool's progress into `notifications/progress` for the token the client put in
+ * `_meta.progressToken`; without one the client asked for none. Progress only ever goes up.
+ */
+function progressReporter(
+  meta: unknown,
+  notify: (message: JsonRpcNotification) => void,
+): McpToolCallContext['onProgress'] {
+  const progressToken = isRecord(meta) ? meta.progressToken : undefined;
+  if (typeof progressToken !== 'string' && typeof progressToken !== 'number') {
+    return undefined;
+  }
+  let last = -Infinity;
+  return (progress, total, message) => {
+    if (progress <= last) {
+      return;
+    }
+    last = progress;
+    notify({
+      jsonrpc: '2.0',
+      method: 'notifications/progress',
+      params: { progressToken, progress, ...(total !== undefined ? { total } : {}), message },
+    });
+  };
+}
+
+/**
+ * Sends what a tool finds as it goes as `notifications/tools/partial_result`, tagged with the
+ * request id, when the client opted in with `_meta.partialResults`. The response still follows.
+ */
+function partialResultReporter(
+  meta: unknown,
+  requestId: string | number | null,
+  notify: (message: JsonRpcNotification) => void,
+): McpToolCallContext['onPartialResult'] {
+  if (!isRecord(meta) || meta.partialResults !== true) {
+    return undefined;
+  }
+  return (items) => {
+    notify({ jsonrpc: '2.0', method: 'notifications/tools/partial_result', params: { requestId, items } });
+  };
+}
+
+// Ties a cursor to the tool and arguments it pages through, so it cannot be replayed against another query.
+function pageQueryKey(toolName: string, args: Record<string, unknown>): string {
+  const query = Object.entries(args)
+    .filter(([name]) => name !== 'cursor' && name !== 'pageSize')
+    .sort(([left], [right]) => left.localeCompare(right));
+  return createHash('sha256').update(JSON.stringify([toolName, query])).digest('hex').slice(0, 16);
+}
+
+function writePageCursor(offset: number, query: string): string {
+  return Buffer.from(JSON.stringify({ offset, query }), 'utf8').toString('base64url');
+}
+
+function readPageCursor(cursor: unknown, query: string): number | undefined {
+  if (typeof cursor !== 'string') {
+    return undefined;
+  }
+  try {
+    const parsed = JSON.parse(Buffer.from(cursor, 'base64url').toString('utf8')) as unknown;
+    if (isRecord(parsed) && parsed.query === query && typeof parsed.offset === 'number' && Number.isInteger(parsed.offset) && parsed.offset >= 0) {
+      return parsed.offset;
+    }
+  } catch {
+    // Not one of ours.
+  }
+  return undefined;
+}
+
+function readPageSize(value: unknown): number {
+  return typeof value === 'number' && value >= 1 ? Math.min(Math.floor(value), MAX_PAGE_SIZE) : DEFAULT_PAGE_SIZE;
+}
+
+/**
+ * Cuts the list in a paginated tool's result down to the page at `offset`, keeping the rest of
+ * the result. Each page runs the tool again, so a list that changes in between can shift.
+ */
+function pageToolResult(
+  data: unknown,
+  listField: string,
+  offset: number,
+  pageSize: number,
+): { data: unknown; nextOffset?: number; total: number } | undefined {
+  const list = listField === '' ? data : isRecord(data) ? data[listField] : undefined;
+  if (!Array.isArray(list)) {
+    return undefined;
+  }
+  const page = list.slice(offset, offset + pageSize);
+  return {
+    data: listField === '' ? page : { ...(data as Record<string, unknown>), [listField]: page },
+    ...(offset + pageSize < list.length ? { nextOffset: offset + pageSize } : {}),
+    total: list.length,
+  };
+}
+
+export function createMcpStdioServer(config: {
+  runtimeService?: SharedRuntimeService;
+  dashboardService?: DashboardService;
+  basePath?: string;
+  input?: NodeJS.ReadableStream;
+  output?: NodeJS.WritableStream;
+  rateLimit?: RateLimitConfig;
+  toolPrefix?: string;
+  readOnly?: boolean;
+  /** How often subscribed resources are re-read for changes made elsewhere; 5 seconds by default. */
+  resourcePollMs?: number;
+  /** Client policy to apply, by name from `.automatosx/mcp-clients.json`; `default` when omitted. */
+  client?: string;
+  /** Append every tool call to `.automatosx/logs/mcp-audit.log`; on by default. */
+  audit?: boolean;
+} = {}): McpStdioServer {
+  const surface = createMcpServerSurface({
+    runtimeService: config.runtimeService,
+    dashboardService: config.dashboardService,
+    basePath: config.basePath,
+    toolPrefix: config.toolPrefix,
+    readOnly: config.readOnly,
+  });
+
+  const basePath = config.basePath ?? process.cwd();
+  const input = config.input ?? process.stdin;
+  const output = config.output ?? process.stdout;
+  let rl: Interface | undefined;
+
+  function send(message: JsonRpcResponse | JsonRpcNotification): void {
+    output.write(`${JSON.stringify(message)}\n`);
+  }
+
+  return {
+    async serve(): Promise<void> {
+      const clients = await loadMcpClientPolicies(basePath);
+      const client = clients.find((candidate) => candidate.name === (config.client ?? DEFAULT_MCP_CLIENT));
+      if (client === undefined && config.client !== undefined && config.client !== DEFAULT_MCP_CLIENT) {
+        throw new Error(`Unknown MCP client "${config.client}". Define it in .automatosx/${MCP_CLIENTS_FILE}.`);
+      }
+      const session = createMcpSession({
+        surface,
+        rateLimiter: createRateLimiter(config.rateLimit),
+        client: client ?? unrestrictedClient(DEFAULT_MCP_CLIENT),
+        audit: config.audit === false ? undefined : createMcpAuditLog(basePath),
+        resourcePollMs: config.resourcePollMs,
+        notify: send,
+        onShutdown: () => queueMicrotask(() => rl?.close()),
+      });
+
+      async function handleRequest(request: JsonRpcRequest): Promise<void> {
+        const response = await session.handle(request);
+        if (response !== undefined) {
+          send(response);
+        }
+      }
+
       return new Promise((resolve) => {
         rl = createInterface({ input, terminal: false });
         const pending: Promise<void>[] = [];
@@ -1277,13 +2192,14 @@ export function createMcpStdioServer(config: {
           try {
             request = JSON.parse(trimmed) as JsonRpcRequest;
           } catch {
-            sendError(null, -32700, 'Parse error');
+            send({ jsonrpc: '2.0', id: null, error: { code: RPC_PARSE_ERROR, message: 'Parse error' } });
             return;
           }
           pending.push(handleRequest(request));
         });
 
         rl.on('close', () => {
+          session.close();
           void Promise.all(pending).then(() => { resolve(); });
         });
       });
@@ -1291,14 +2207,638 @@ export function createMcpStdioServer(config: {
   };
 }
 
+export interface McpHttpServerConfig {
+  runtimeService?: SharedRuntimeService;
+  dashboardService?: DashboardService;
+  basePath?: string;
+  rateLimit?: RateLimitConfig;
+  toolPrefix?: string;
+  readOnly?: boolean;
+  resourcePollMs?: number;
+  /** Interface to listen on; loopback by default. Any other interface needs a token. */
+  host?: string;
+  /** 3100 by default; 0 picks a free port. */
+  port?: number;
+  /**
+   * Token of the `default` client. Once it or any client in `.automatosx/mcp-clients.json` has a
+   * token, every request must carry `Authorization: Bearer <token>`, which picks its client.
+   */
+  token?: string;
+  /** Browser origins allowed besides localhost, e.g. `https://ide.example.com`; their hosts are accepted in `Host` too. */
+  allowedOrigins?: string[];
+  /** Sessions without a request or an open stream for this long are closed; 30 minutes by default. */
+  sessionTtlMs?: number;
+  /** Append every tool call to `.automatosx/logs/mcp-audit.log`; on by default. */
+  audit?: boolean;
+}
+
+export interface McpHttpServerHandle {
+  /** The Streamable HTTP endpoint, e.g. `http://127.0.0.1:3100/mcp`. */
+  url: string;
+  port: number;
+  sessionCount(): number;
+  close(): Promise<void>;
+  closed: Promise<void>;
+}
+
+interface HttpSession {
+  id: string;
+  protocol: McpSession;
+  /** The client that opened the session; no other may use it. */
+  client: string;
+  /** Opened over the HTTP+SSE transport, where every message goes down its one stream. */
+  legacy: boolean;
+  lastSeen: number;
+  nextEventId: number;
+  events: Array<{ id: number; stream: string; data: string }>;
+  /** Open streams by id: the standalone one for notifications, and one per POST answered with SSE. */
+  streams: Map<string, { response?: ServerResponse }>;
+}
+
+/**
+ * Serves MCP over HTTP so remote IDEs and web clients can connect without spawning a process.
+ * `/mcp` speaks Streamable HTTP: a POST answers with JSON or an SSE stream, a GET opens a stream
+ * for notifications, and a GET with `Last-Event-ID` resumes a stream that dropped. `/sse` and
+ * `/messages` keep clients of the older HTTP+SSE transport working.
+ */
+export async function startMcpHttpServer(config: McpHttpServerConfig = {}): Promise<McpHttpServerHandle> {
+  const surface = createMcpServerSurface({
+    runtimeService: config.runtimeService,
+    dashboardService: config.dashboardService,
+    basePath: config.basePath,
+    toolPrefix: config.toolPrefix,
+    readOnly: config.readOnly,
+  });
+  const basePath = config.basePath ?? process.cwd();
+  const rateLimiter = createRateLimiter(config.rateLimit);
+  const audit = config.audit === false ? undefined : createMcpAuditLog(basePath);
+  const sessionTtlMs = config.sessionTtlMs ?? DEFAULT_HTTP_SESSION_TTL_MS;
+  const allowedOrigins = new Set(config.allowedOrigins ?? []);
+  const sessions = new Map<string, HttpSession>();
+  const clients = await loadMcpClientPolicies(basePath);
+  // Starting anyway would leave the client locked out, or the server open if it was the only one.
+  const tokenless = clients.find((client) => client.tokenEnv !== undefined && client.token === undefined);
+  if (tokenless !== undefined) {
+    throw new Error(`MCP client "${tokenless.name}" reads its token from ${tokenless.tokenEnv}, which is not set.`);
+  }
+  const defaultClient = clients.find((client) => client.name === DEFAULT_MCP_CLIENT) ?? unrestrictedClient(DEFAULT_MCP_CLIENT);
+  const tokens = [
+    ...clients.filter((client) => client.token !== undefined).map((client) => ({ token: client.token!, client })),
+    ...(config.token !== undefined ? [{ token: config.token, client: defaultClient }] : []),
+  ];
+  const host = config.host ?? DEFAULT_MCP_HTTP_HOST;
+  if (tokens.length === 0 && !isLoopbackHost(host)) {
+    throw new Error(`Listening on ${host} without a token would open every tool to the network; pass --token or give clients tokens in .automatosx/mcp-clients.json.`);
+  }
+  const allowedHosts = new Set([
+    ...LOOPBACK_HOSTNAMES,
+    hostLabel(host).toLowerCase(),
+    ...[...allowedOrigins].flatMap((origin) => originHostname(origin) ?? []),
+  ]);
+
+  // Without any token configured the server is open, as the default client.
+  function authenticate(request: IncomingMessage): McpClientPolicy | undefined {
+    if (tokens.length === 0) {
+      return defaultClient;
+    }
+    const bearer = /^Bearer\s+(\S+)$/i.exec(request.headers.authorization ?? '')?.[1];
+    return bearer === undefined ? undefined : tokens.find((candidate) => sameSecret(candidate.token, bearer))?.client;
+  }
+
+  function openSession(legacy: boolean, client: McpClientPolicy): HttpSession {
+    const session: HttpSession = {
+      id: randomUUID(),
+      client: client.name,
+      legacy,
+      lastSeen: Date.now(),
+      nextEventId: 1,
+      events: [],
+      streams: new Map([[STANDALONE_STREAM, {}]]),
+      protocol: createMcpSession({
+        surface,
+        rateLimiter,
+        client,
+        audit,
+        resourcePollMs: config.resourcePollMs,
+        notify: (message) => emit(session, STANDALONE_STREAM, message),
+      }),
+    };
+    sessions.set(session.id, session);
+    return session;
+  }
+
+  function closeSession(session: HttpSession): void {
+    sessions.delete(session.id);
+    session.protocol.close();
+    session.protocol.cancelAll();
+    for (const stream of session.streams.values()) {
+      stream.response?.end();
+    }
+    session.streams.clear();
+  }
+
+  // Every event is kept, even when its stream has no connection, so a reconnecting client can catch up.
+  function emit(session: HttpSession, streamId: string, message: JsonRpcResponse | JsonRpcNotification): void {
+    const event = { id: session.nextEventId, stream: streamId, data: JSON.stringify(message) };
+    session.nextEventId += 1;
+    session.events.push(event);
+    if (session.events.length > MAX_REPLAY_EVENTS) {
+      session.events.shift();
+    }
+    const response = session.streams.get(streamId)?.response;
+    if (response !== undefined && !response.writableEnded) {
+      writeSseEvent(response, event);
+    }
+  }
+
+  function attachStream(session: HttpSession, streamId: string, response: ServerResponse): void {
+    const stream = session.streams.get(streamId);
+    if (stream === undefined) {
+      return;
+    }
+    if (stream.response !== undefined && stream.response !== response) {
+      stream.response.end();
+    }
+    stream.response = response;
+    response.on('close', () => {
+      if (stream.response === response) {
+        stream.response = undefined;
+        session.lastSeen = Date.now();
+      }
+    });
+  }
+
+  function findSession(response: ServerResponse, id: string | undefined, client: McpClientPolicy): HttpSession | undefined {
+    if (id === undefined || id.length === 0) {
+      sendHttpError(response, 400, RPC_INVALID_REQUEST, 'Missing Mcp-Session-Id header; send initialize first.');
+      return undefined;
+    }
+    const session = sessions.get(id);
+    if (session === undefined) {
+      // 404 tells Streamable HTTP clients to start over with a new initialize.
+      sendHttpError(response, 404, RPC_INVALID_REQUEST, `Unknown or expired session: ${id}`);
+      return undefined;
+    }
+    if (session.client !== client.name) {
+      sendHttpError(response, 403, RPC_INVALID_REQUEST, 'The session belongs to another client.');
+      return undefined;
+    }
+    session.lastSeen = Date.now();
+    return session;
+  }
+
+  async function handlePost(request: IncomingMessage, response: ServerResponse, client: McpClientPolicy): Promise<void> {
+    const body = await readJsonRpcBody(request, response);
+    if (body === undefined) {
+      return;
+    }
+    const { messages } = body;
+    let session: HttpSession | undefined;
+    if (messages.some((message) => message.method === 'initialize')) {
+      if (messages.length > 1) {
+        sendHttpError(response, 400, RPC_INVALID_REQUEST, 'initialize must be sent on its own.');
+        return;
+      }
+      session = openSession(false, client);
+    } else {
+      session = findSession(response, headerValue(request, MCP_SESSION_HEADER), client);
+      if (session === undefined) {
+        return;
+      }
+    }
+    const current = session;
+
+    // Notifications and responses from the client are acknowledged without a body.
+    if (!messages.some((message) => message.id !== undefined && typeof message.method === 'string')) {
+      await Promise.all(messages.map((message) => current.protocol.handle(message)));
+      response.writeHead(202, { 'Mcp-Session-Id': current.id }).end();
+      return;
+    }
+
+    if (accepts(request, 'text/event-stream')) {
+      const streamId = randomUUID();
+      current.streams.set(streamId, {});
+      openSseResponse(response, { 'Mcp-Session-Id': current.id });
+      attachStream(current, streamId, response);
+      await Promise.all(messages.map(async (message) => {
+        const reply = await current.protocol.handle(message, (notification) => emit(current, streamId, notification));
+        if (reply !== undefined && message.id !== undefined) {
+          emit(current, streamId, reply);
+        }
+      }));
+      current.streams.get(streamId)?.response?.end();
+      current.streams.delete(streamId);
+      return;
+    }
+
+    const replies = (await Promise.all(messages.map(async (message) => {
+      const reply = await current.protocol.handle(message);
+      return message.id !== undefined ? reply : undefined;
+    }))).filter((reply): reply is JsonRpcResponse => reply !== undefined);
+    if (replies.length === 0) {
+      // Every request in it was cancelled while it ran.
+      response.writeHead(202, { 'Mcp-Session-Id': current.id }).end();
+      return;
+    }
+    response.writeHead(200, { 'Content-Type': 'application/json', 'Mcp-Session-Id': current.id });
+    response.end(JSON.stringify(body.batch ? replies : replies[0]));
+  }
+
+  function handleGet(request: IncomingMessage, response: ServerResponse, client: McpClientPolicy): void {
+    if (!accepts(request, 'text/event-stream')) {
+      sendHttpError(response, 406, RPC_INVALID_REQUEST, 'GET opens an event stream; send Accept: text/event-stream.');
+      return;
+    }
+    const session = findSession(response, headerValue(request, MCP_SESSION_HEADER), client);
+    if (session === undefined) {
+      return;
+    }
+    openSseResponse(response, { 'Mcp-Session-Id': session.id });
+    const lastEventId = Number(headerValue(request, 'last-event-id'));
+    const resumed = Number.isInteger(lastEventId) ? session.events.find((event) => event.id === lastEventId) : undefined;
+    if (resumed === undefined) {
+      attachStream(session, STANDALONE_STREAM, response);
+      return;
+    }
+    for (const event of session.events.filter((candidate) => candidate.stream === resumed.stream && candidate.id > lastEventId)) {
+      writeSseEvent(response, event);
+    }
+    if (session.streams.has(resumed.stream)) {
+      attachStream(session, resumed.stream, response);
+    } else {
+      // The stream finished while the client was away; everything it carried has now been replayed.
+      response.end();
+    }
+  }
+
+  function handleLegacyStream(response: ServerResponse, client: McpClientPolicy): void {
+    const session = openSession(true, client);
+    openSseResponse(response, {});
+    response.write(`event: endpoint\ndata: ${MCP_SSE_MESSAGES_PATH}?sessionId=${session.id}\n\n`);
+    attachStream(session, STANDALONE_STREAM, response);
+    // The stream is the session: once it closes there is nowhere left to answer.
+    response.on('close', () => closeSession(session));
+  }
+
+  async function handleLegacyMessage(request: IncomingMessage, response: ServerResponse, url: URL, client: McpClientPolicy): Promise<void> {
+    const session = findSession(response, url.searchParams.get('sessionId') ?? undefined, client);
+    if (session === undefined) {
+      return;
+    }
+    const body = await readJsonRpcBody(request, response);
+    if (body === undefined) {
+      return;
+    }
+    response.writeHead(202).end();
+    for (const message of body.messages) {
+      void session.protocol.handle(message).then((reply) => {
+        if (reply !== undefined && message.id !== undefined) {
+          emit(session, STANDALONE_STREAM, reply);
+        }
+      });
+    }
+  }
+
+  async function handle(request: IncomingMessage, response: ServerResponse): Promise<void> {
+    // A rebound DNS name reaches a loopback server under the attacker's host name, with or without
+    // an Origin; a server on every interface needs a token, so any host name may reach it.
+    const hostHeader = headerValue(request, 'host');
+    const hostname = hostHeader === undefined ? undefined : originHostname(`http://${hostHeader}`);
+    if (!WILDCARD_HOSTS.has(host) && (hostname === undefined || !allowedHosts.has(hostname))) {
+      sendHttpError(response, 403, RPC_INVALID_REQUEST, `Host not allowed: ${hostHeader ?? '(none)'}`);
+      return;
+    }
+    const origin = headerValue(request, 'origin');
+    if (origin !== undefined && !allowedOrigins.has(origin) && !isLoopbackOrigin(origin)) {
+      // Stops web pages from reaching a local server through DNS rebinding.
+      sendHttpError(response, 403, RPC_INVALID_REQUEST, `Origin not allowed: ${origin}`);
+      return;
+    }
+    const client = authenticate(request);
+    if (client === undefined) {
+      response.setHeader('WWW-Authenticate', 'Bearer');
+      sendHttpError(response, 401, RPC_INVALID_REQUEST, 'Missing or invalid bearer token.');
+      return;
+    }
+
+    const url = new URL(request.url ?? '/', 'http://localhost');
+    if (url.pathname === MCP_HTTP_PATH) {
+      if (request.method === 'POST') {
+        await handlePost(request, response, client);
+      } else if (request.method === 'GET') {
+        handleGet(request, response, client);
+      } else if (request.method === 'DELETE') {
+        const session = findSession(response, headerValue(request, MCP_SESSION_HEADER), client);
+        if (session !== undefined) {
+          closeSession(session);
+          response.writeHead(204).end();
+        }
+      } else {
+        response.setHeader('Allow', 'GET, POST, DELETE');
+        sendHttpError(response, 405, RPC_INVALID_REQUEST, `Method not allowed: ${request.method}`);
+      }
+    } else if (url.pathname === MCP_SSE_PATH && request.method === 'GET') {
+      handleLegacyStream(response, client);
+    } else if (url.pathname === MCP_SSE_MESSAGES_PATH && request.method === 'POST') {
+      await handleLegacyMessage(request, response, url, client);
+    } else {
+      sendHttpError(response, 404, RPC_INVALID_REQUEST, `Not found: ${url.pathname}`);
+    }
+  }
+
+  const server = createServer((request, response) => {
+    handle(request, response).catch((error: unknown) => {
+      if (!response.headersSent) {
+        sendHttpError(response, 500, RPC_INTERNAL_ERROR, error instanceof Error ? error.message : String(error));
+      } else {
+        response.end();
+      }
+    });
+  });
+  const closed = new Promise<void>((resolve) => server.once('close', () => resolve()));
+
+  // Proxies drop idle connections; a comment line keeps the streams open without the client seeing anything.
+  const keepalive = setInterval(() => {
+    for (const session of sessions.values()) {
+      for (const stream of session.streams.values()) {
+        if (stream.response !== undefined && !stream.response.writableEnded) {
+          stream.response.write(': keepalive\n\n');
+        }
+      }
+    }
+  }, SSE_KEEPALIVE_MS);
+  keepalive.unref();
+  const sweeper = setInterval(() => {
+    const cutoff = Date.now() - sessionTtlMs;
+    for (const session of sessions.values()) {
+      const connected = [...session.streams.values()].some((stream) => stream.response !== undefined);
+      if (!connected && session.lastSeen < cutoff) {
+        closeSession(session);
+      }
+    }
+  }, Math.min(sessionTtlMs, 60_000));
+  sweeper.unref();
+
+  await new Promise<void>((resolve, reject) => {
+    server.once('error', reject);
+    server.listen(config.port ?? DEFAULT_MCP_HTTP_PORT, host, () => {
+      server.off('error', reject);
+      resolve();
+    });
+  });
+  const address = server.address();
+  const port = typeof address === 'object' && address !== null ? address.port : config.port ?? DEFAULT_MCP_HTTP_PORT;
+
+  return {
+    url: `http://${hostLabel(host)}:${port}${MCP_HTTP_PATH}`,
+    port,
+    sessionCount: () => sessions.size,
+    async close() {
+      clearInterval(keepalive);
+      clearInterval(sweeper);
+      for (const session of [...sessions.values()]) {
+        closeSession(session);
+      }
+      server.close();
+      server.closeAllConnections();
+      await closed;
+    },
+    closed,
+  };
+}
+
+type McpAuditLog = (entry: McpAuditEntry) => Promise<void>;
+
+/**
+ * Reads the per-client tool policies from `.automatosx/mcp-clients.json`:
+ * `{ "clients": { "<name>": { "token" | "tokenEnv", "allow", "deny", "readOnly" } } }`.
+ * `tokenEnv` names an environment variable holding the token, which keeps it out of the file.
+ */
+export async function loadMcpClientPolicies(basePath: string, env: NodeJS.ProcessEnv = process.env): Promise<McpClientPolicy[]> {
+  const path = join(basePath, '.automatosx', MCP_CLIENTS_FILE);
+  let parsed: unknown;
+  try {
+    parsed = JSON.parse(await readFile(path, 'utf8'));
+  } catch (error) {
+    if ((error as NodeJS.ErrnoException).code === 'ENOENT') {
+      return [];
+    }
+    throw new Error(`Invalid ${path}: ${error instanceof Error ? error.message : String(error)}`);
+  }
+  if (!isRecord(parsed) || !isRecord(parsed.clients)) {
+    throw new Error(`Invalid ${path}: expected a "clients" object keyed by client name.`);
+  }
+
+  return Object.entries(parsed.clients).map(([name, entry]) => {
+    if (!isRecord(entry)) {
+      throw new Error(`Invalid ${path}: client "${name}" must be an object.`);
+    }
+    const patterns = (field: 'allow' | 'deny', fallback: string[]): string[] => {
+      const value = entry[field] ?? fallback;
+      if (!Array.isArray(value) || !value.every((pattern) => typeof pattern === 'string')) {
+        throw new Error(`Invalid ${path}: "${field}" of client "${name}" must be an array of tool patterns.`);
+      }
+      return value;
+    };
+    const tokenEnv = asOptionalString(entry.tokenEnv);
+    const token = asOptionalString(entry.token) ?? (tokenEnv !== undefined ? env[tokenEnv] : undefined);
+    return {
+      name,
+      allow: patterns('allow', ['*']),
+      deny: patterns('deny', []),
+      readOnly: entry.readOnly === true,
+      ...(token !== undefined && token.length > 0 ? { token } : {}),
+      ...(tokenEnv !== undefined ? { tokenEnv } : {}),
+    };
+  });
+}
+
+/**
+ * Why the client may not call the tool, or undefined when it may. `reading` resources and prompts
+ * changes nothing, so only `allow` and `deny` apply to it.
+ */
+function checkClientPolicy(client: McpClientPolicy, toolName: string, options: { reading?: boolean } = {}): string | undefined {
+  if (client.deny.some((pattern) => matchesToolPattern(pattern, toolName))) {
+    return `${toolName} is denied for MCP client "${client.name}".`;
+  }
+  if (!client.allow.some((pattern) => matchesToolPattern(pattern, toolName))) {
+    return `${toolName} is not allowed for MCP client "${client.name}".`;
+  }
+  if (client.readOnly && options.reading !== true && isMutatingTool(toolName)) {
+    return `${toolName} is disabled for read-only MCP client "${client.name}".`;
+  }
+  return undefined;
+}
+
+/** The tool showing what the resource (or resource template) does, whose client policy its reads follow. */
+function resourceToolName(uri: string): string | undefined {
+  switch (uri) {
+    case RESOURCE_URIS.workspaceConfig:
+    case RESOURCE_URIS.workspaceContext:
+      return 'config.show';
+    case RESOURCE_URIS.workspaceMcp:
+      return 'mcp.server_list';
+    case RESOURCE_URIS.workflowCatalog:
+      return 'workflow.list';
+    case RESOURCE_URIS.recentTraces:
+      return 'trace.list';
+    case RESOURCE_URIS.codeOutline:
+    case RESOURCE_URIS.repoMap:
+      return 'code.outline';
+    case RESOURCE_URIS.agents:
+      return 'agent.list';
+    case RESOURCE_URIS.memory:
+      return 'memory.namespaces';
+  }
+  if (uri.startsWith(`${RESOURCE_URIS.codeOutline}/`)) {
+    return 'code.outline';
+  }
+  if (uri.startsWith(`${RESOURCE_URIS.agents}/`)) {
+    return 'agent.get';
+  }
+  if (uri.startsWith(`${RESOURCE_URIS.memory}/`)) {
+    return 'memory.list';
+  }
+  return undefined;
+}
+
+/** The tool a prompt leads to: the one of its name, or `agent.run` for an agent's prompt. */
+function promptToolName(name: string): string {
+  return name.startsWith(AGENT_PROMPT_PREFIX) ? 'agent.run' : name;
+}
+
+function matchesToolPattern(pattern: string, toolName: string): boolean {
+  const body = pattern.split('*').map((part) => part.replace(/[.+?^${}()|[\]\\]/g, '\\$&')).join('.*');
+  return new RegExp(`^${body}$`).test(toolName);
+}
+
+function unrestrictedClient(name: string): McpClientPolicy {
+  return { name, allow: ['*'], deny: [], readOnly: false };
+}
+
+// Appends one JSON line per tool call. Writes are chained so entries keep their order.
+function createMcpAuditLog(basePath: string): McpAuditLog {
+  const logDir = join(basePath, '.automatosx', 'logs');
+  let tail = Promise.resolve();
+  return (entry) => {
+    tail = tail
+      .then(async () => {
+        await mkdir(logDir, { recursive: true });
+        await appendFile(join(logDir, MCP_AUDIT_LOG_FILE), `${JSON.stringify(entry)}\n`, 'utf8');
+      })
+      // An unwritable log must not take the tools down with it.
+      .catch(() => undefined);
+    return tail;
+  };
+}
+
+// Compares digests so the time taken says nothing about how much of the token matched.
+function sameSecret(expected: string, actual: string): boolean {
+  const digest = (value: string) => createHash('sha256').update(value).digest();
+  return timingSafeEqual(digest(expected), digest(actual));
+}
+
+async function readJsonRpcBody(
+  request: IncomingMessage,
+  response: ServerResponse,
+): Promise<{ messages: JsonRpcRequest[]; batch: boolean } | undefined> {
+  const chunks: Buffer[] = [];
+  let size = 0;
+  for await (const chunk of request as AsyncIterable<Buffer>) {
+    size += chunk.length;
+    // Keep draining past the limit so the client still receives the 413.
+    if (size <= MAX_HTTP_BODY_BYTES) {
+      chunks.push(chunk);
+    }
+  }
+  if (size > MAX_HTTP_BODY_BYTES) {
+    sendHttpError(response, 413, RPC_INVALID_REQUEST, `Request body exceeds ${MAX_HTTP_BODY_BYTES} bytes.`);
+    return undefined;
+  }
+  let body: unknown;
+  try {
+    body = JSON.parse(Buffer.concat(chunks).toString('utf8'));
+  } catch {
+    sendHttpError(response, 400, RPC_PARSE_ERROR, 'Parse error');
+    return undefined;
+  }
+  const messages = Array.isArray(body) ? body : [body];
+  if (messages.length === 0 || !messages.every(isRecord)) {
+    sendHttpError(response, 400, RPC_INVALID_REQUEST, 'Expected a JSON-RPC message or a batch of them.');
+    return undefined;
+  }
+  return { messages: messages as unknown as JsonRpcRequest[], batch: Array.isArray(body) };
+}
+
+function sendHttpError(response: ServerResponse, status: number, code: number, message: string): void {
+  response.writeHead(status, { 'Content-Type': 'application/json' });
+  response.end(JSON.stringify({ jsonrpc: '2.0', id: null, error: { code, message } }));
+}
+
+function openSseResponse(response: ServerResponse, headers: Record<string, string>): void {
+  response.writeHead(200, {
+    'Content-Type': 'text/event-stream',
+    'Cache-Control': 'no-cache',
+    Connection: 'keep-alive',
+    ...headers,
+  });
+  response.flushHeaders();
+}
+
+function writeSseEvent(response: ServerResponse, event: { id: number; data: string }): void {
+  response.write(`id: ${event.id}\nevent: message\ndata: ${event.data}\n\n`);
+}
+
+function headerValue(request: IncomingMessage, name: string): string | undefined {
+  const value = request.headers[name];
+  return Array.isArray(value) ? value[0] : value;
+}
+
+function accepts(request: IncomingMessage, mimeType: string): boolean {
+  return (request.headers.accept ?? '').split(',').some((entry) => entry.split(';')[0]!.trim() === mimeType);
+}
+
+function isLoopbackOrigin(origin: string): boolean {
+  const hostname = originHostname(origin);
+  return hostname !== undefined && LOOPBACK_HOSTNAMES.has(hostname);
+}
+
+function originHostname(origin: string): string | undefined {
+  try {
+    return new URL(origin).hostname;
+  } catch {
+    return undefined;
+  }
+}
+
+function isLoopbackHost(host: string): boolean {
+  return LOOPBACK_HOSTNAMES.has(hostLabel(host).toLowerCase()) || /^127(?:\.\d{1,3}){3}$/.test(host);
+}
+
+/** The host as it appears in a URL, with IPv6 addresses in brackets. */
+function hostLabel(host: string): string {
+  return host.includes(':') ? `[${host}]` : host;
+}
+
 export function createMcpServerSurface(config: {
   runtimeService?: SharedRuntimeService;
   dashboardService?: DashboardService;
   basePath?: string;
   toolPrefix?: string;
+  readOnly?: boolean;
 } = {}): McpServerSurface {
   const basePath = config.basePath ?? process.cwd();
-  const runtimeService = config.runtimeService ?? createSharedRuntimeService({ basePath });
+  const runtimeService = config.runtimeService ?? createSharedRuntimeService({ basePath, readOnly: config.readOnly });
+  const readOnly = config.readOnly ?? runtimeService.isReadOnly();
+  // Calls from read-only clients go through a read-only runtime over the same stores.
+  let readOnlyRuntimeService: SharedRuntimeService | undefined;
+  const runtimeFor = (callReadOnly: boolean): SharedRuntimeService => {
+    if (!callReadOnly || runtimeService.isReadOnly()) {
+      return runtimeService;
+    }
+    readOnlyRuntimeService ??= createSharedRuntimeService({ basePath, ...runtimeService.getStores(), readOnly: true });
+    return readOnlyRuntimeService;
+  };
   const dashboardService = config.dashboardService ?? createDashboardService({
     traceStore: runtimeService.getStores().traceStore,
   });
@@ -1374,6 +2914,11 @@ export function createMcpServerSurface(config: {
       return toolDefinitions.map((definition) => ({ ...definition }));
     },
 
+    resolveToolName(toolName) {
+      const canonicalToolName = aliasToCanonicalMap.get(toolName) ?? toolName;
+      return canonicalToolDefinitionMap.has(canonicalToolName) ? canonicalToolName : undefined;
+    },
+
     listResources() {
       return [
         {
@@ -1406,6 +2951,53 @@ export function createMcpServerSurface(config: {
           description: 'Recent trace summary from the shared trace store.',
           mimeType: 'application/json',
         },
+        {
+          uri: RESOURCE_URIS.codeOutline,
+          name: 'Code Outline',
+          description: 'Markdown outline of the workspace symbols; append a path (ax://code/outline/src/lib.rs) to outline one file or directory.',
+          mimeType: 'text/markdown',
+        },
+        {
+          uri: RESOURCE_URIS.repoMap,
+          name: 'Repo Map',
+          description: 'Every indexed file with its declarations, one signature per line.',
+          mimeType: 'text/plain',
+        },
+        {
+          uri: RESOURCE_URIS.agents,
+          name: 'Agent Profiles',
+          description: 'Registered agent profiles; append an agent id (ax://agents/reviewer) to read one.',
+          mimeType: 'application/json',
+        },
+        {
+          uri: RESOURCE_URIS.memory,
+          name: 'Memory Namespaces',
+          description: 'Memory namespaces with entry counts; append a namespace (ax://memory/notes) to read its entries.',
+          mimeType: 'application/json',
+        },
+      ];
+    },
+
+    listResourceTemplates() {
+      return [
+        {
+          uriTemplate: `${RESOURCE_URIS.codeOutline}/{path}`,
+          name: 'File Outline',
+          description: 'Markdown outline of one file or directory.',
+          mimeType: 'text/markdown',
+        },
+        {
+          uriTemplate: `${RESOURCE_URIS.agents}/{agentId}`,
+          name: 'Agent Profile',
+          description: 'One registered agent profile.',
+          mimeType: 'application/json',
+        },
+        {
+          uriTemplate: `${RESOURCE_URIS.memory}/{namespace}`,
+          name: 'Memory Namespace',
+          description: 'The entries of one memory namespace; scoped namespaces are URI-encoded.',
+          mimeType: 'application/json',
+        },
       ];
     },
 
@@ -1421,39 +3013,127 @@ export function createMcpServerSurface(config: {
           return {
             uri,
             mimeType: 'application/json',
-            text: await readWorkspaceFile(join(basePath, '.automatosx', 'mcp.json')),
+            text: await readWorkspaceFile(join(basePath, '.automatosx', 'mcp.json')),
+          };
+        case RESOURCE_URIS.workspaceContext:
+          return {
+            uri,
+            mimeType: 'text/markdown',
+            text: await readWorkspaceFile(join(basePath, 'AX.md')),
+          };
+        case RESOURCE_URIS.workflowCatalog:
+          return {
+            uri,
+            mimeType: 'application/json',
+            text: `${JSON.stringify(await runtimeService.listWorkflows({ basePath }), null, 2)}\n`,
+          };
+        case RESOURCE_URIS.recentTraces:
+          return {
+            uri,
+            mimeType: 'application/json',
+            text: `${JSON.stringify(await runtimeService.listTraces(10), null, 2)}\n`,
           };
-        case RESOURCE_URIS.workspaceContext:
+        case RESOURCE_URIS.codeOutline:
           return {
             uri,
             mimeType: 'text/markdown',
-            text: await readWorkspaceFile(join(basePath, 'AX.md')),
+            text: (await runtimeService.renderOutline({ paths: ['.'], basePath })).markdown,
           };
-        case RESOURCE_URIS.workflowCatalog:
+        case RESOURCE_URIS.repoMap:
+          return {
+            uri,
+            mimeType: 'text/plain',
+            text: (await runtimeService.renderRepoMap({ basePath })).text,
+          };
+        case RESOURCE_URIS.agents:
           return {
             uri,
             mimeType: 'application/json',
-            text: `${JSON.stringify(await runtimeService.listWorkflows({ basePath }), null, 2)}\n`,
+            text: `${JSON.stringify(await runtimeService.listAgents(), null, 2)}\n`,
           };
-        case RESOURCE_URIS.recentTraces:
+        case RESOURCE_URIS.memory:
           return {
             uri,
             mimeType: 'application/json',
-            text: `${JSON.stringify(await runtimeService.listTraces(10), null, 2)}\n`,
+            text: `${JSON.stringify(await runtimeService.listMemoryNamespaces(), null, 2)}\n`,
           };
         default:
+          if (uri.startsWith(`${RESOURCE_URIS.codeOutline}/`)) {
+            const path = decodeURIComponent(uri.slice(RESOURCE_URIS.codeOutline.length + 1));
+            return {
+              uri,
+              mimeType: 'text/markdown',
+              text: (await runtimeService.renderOutline({ paths: [path], basePath })).markdown,
+            };
+          }
+          if (uri.startsWith(`${RESOURCE_URIS.agents}/`)) {
+            const agentId = decodeURIComponent(uri.slice(RESOURCE_URIS.agents.length + 1));
+            const agent = await runtimeService.getAgent(agentId);
+            if (agent === undefined) {
+              throw new Error(`Agent "${agentId}" is not registered.`);
+            }
+            return { uri, mimeType: 'application/json', text: `${JSON.stringify(agent, null, 2)}\n` };
+          }
+          if (uri.startsWith(`${RESOURCE_URIS.memory}/`)) {
+            const namespace = decodeURIComponent(uri.slice(RESOURCE_URIS.memory.length + 1));
+            return {
+              uri,
+              mimeType: 'application/json',
+              text: `${JSON.stringify(await runtimeService.listMemory(namespace), null, 2)}\n`,
+            };
+          }
           throw new Error(`Unknown resource: ${uri}`);
       }
     },
 
-    listPrompts() {
-      return PROMPT_DEFINITIONS.map((prompt) => ({
-        ...prompt,
-        arguments: prompt.arguments?.map((argument) => ({ ...argument })),
-      }));
+    async listPrompts() {
+      const agents = await runtimeService.listAgents();
+      return [
+        ...PROMPT_DEFINITIONS.map((prompt) => ({
+          ...prompt,
+          arguments: prompt.arguments?.map((argument) => ({ ...argument })),
+        })),
+        ...agents.map((agent) => ({
+          name: `${AGENT_PROMPT_PREFIX}${agent.agentId}`,
+          description: asOptionalString(agent.metadata?.description)
+            ?? `Work on a task as the ${agent.name} agent${agent.capabilities.length > 0 ? ` (${agent.capabilities.join(', ')})` : ''}.`,
+          arguments: AGENT_PROMPT_ARGUMENTS.map((argument) => ({ ...argument })),
+        })),
+      ];
     },
 
     async getPrompt(name, args = {}) {
+      if (name.startsWith(AGENT_PROMPT_PREFIX)) {
+        const agentId = name.slice(AGENT_PROMPT_PREFIX.length);
+        const agent = await runtimeService.getAgent(agentId);
+        if (agent === undefined) {
+          throw new Error(`Unknown prompt: ${name}`);
+        }
+        const task = asString(args.task, 'task');
+        const files = asOptionalString(args.files)?.split(',').map((file) => file.trim()).filter((file) => file.length > 0) ?? [];
+        const constraints = asOptionalString(args.constraints);
+        const preview = await runtimeService.renderAgentPrompt({ agentId, task });
+        return {
+          description: `Prompt a client to work on a task as the ${agent.name} agent.`,
+          messages: [
+            {
+              role: 'user',
+              content: {
+                type: 'text',
+                text: [
+                  preview?.systemPrompt ?? resolveAgentSystemPrompt(agent),
+                  '',
+                  `Task: ${task}`,
+                  files.length > 0 ? `Files: ${files.join(', ')}` : undefined,
+                  constraints === undefined ? undefined : `Constraints: ${constraints}`,
+                  '',
+                  `Answer as this agent, or call the agent.run tool with agentId "${agent.agentId}" to run it through AutomatosX.`,
+                ].filter((line): line is string => line !== undefined).join('\n'),
+              },
+            },
+          ],
+        };
+      }
       switch (name) {
         case 'workflow.run': {
           const workflowId = asString(args.workflowId, 'workflowId');
@@ -1542,7 +3222,9 @@ export function createMcpServerSurface(config: {
       }
     },
 
-    async invokeTool(toolName, args = {}) {
+    async invokeTool(toolName, args = {}, context = {}) {
+      const callReadOnly = readOnly || context.readOnly === true;
+      const runtimeService = runtimeFor(callReadOnly);
       try {
         const canonicalToolName = aliasToCanonicalMap.get(toolName) ?? toolName;
         const definition = canonicalToolDefinitionMap.get(canonicalToolName);
@@ -1561,6 +3243,14 @@ export function createMcpServerSurface(config: {
           };
         }
 
+        if (callReadOnly && isMutatingTool(canonicalToolName)) {
+          return {
+            success: false,
+            error: `${canonicalToolName} is disabled in read-only mode.`,
+            data: { code: READ_ONLY_ERROR_CODE },
+          };
+        }
+
         switch (canonicalToolName) {
           case 'workflow.run':
             return {
@@ -1569,6 +3259,7 @@ export function createMcpServerSurface(config: {
                 workflowId: asString(args.workflowId, 'workflowId'),
                 traceId: asOptionalString(args.traceId),
                 sessionId: asOptionalString(args.sessionId),
+                claim: asOptionalString(args.claim),
                 workflowDir: asOptionalString(args.workflowDir),
                 basePath: asOptionalString(args.basePath),
                 provider: asOptionalString(args.provider),
@@ -1639,7 +3330,9 @@ export function createMcpServerSurface(config: {
           case 'agent.get':
             return {
               success: true,
-              data: await runtimeService.getAgent(asString(args.agentId, 'agentId')),
+              data: args.resolved === true
+                ? await runtimeService.resolveAgentProfile(asString(args.agentId, 'agentId'))
+                : await runtimeService.getAgent(asString(args.agentId, 'agentId')),
             };
           case 'agent.list':
             return {
@@ -1663,8 +3356,11 @@ export function createMcpServerSurface(config: {
                 agentId: asString(args.agentId, 'agentId'),
                 task: asOptionalString(args.task),
                 input: isRecord(args.input) ? args.input : undefined,
+                files: asStringArray(args.files),
+                scope: asOptionalString(args.scope),
                 traceId: asOptionalString(args.traceId),
                 sessionId: asOptionalString(args.sessionId),
+                claim: asOptionalString(args.claim),
                 basePath: asOptionalString(args.basePath),
                 provider: asOptionalString(args.provider),
                 model: asOptionalString(args.model),
@@ -1672,6 +3368,30 @@ export function createMcpServerSurface(config: {
                 parentTraceId: asOptionalString(args.parentTraceId),
                 rootTraceId: asOptionalString(args.rootTraceId),
                 surface: 'mcp',
+                signal: context.signal,
+                onProgress: context.onProgress === undefined
+                  ? undefined
+                  : (percent, message) => context.onProgress?.(percent, 100, message),
+                approve: elicitationApprover(context),
+              }),
+            };
+          case 'agent.delegate':
+            return {
+              success: true,
+              data: await runtimeService.delegateTask({
+                agentId: asString(args.agentId, 'agentId'),
+                task: asString(args.task, 'task'),
+                input: isRecord(args.input) ? args.input : undefined,
+                contract: parseHandoffContract(args.contract),
+                fromAgentId: asOptionalString(args.fromAgentId),
+                traceId: asOptionalString(args.traceId),
+                sessionId: asOptionalString(args.sessionId),
+                basePath: asOptionalString(args.basePath),
+                parentTraceId: asOptionalString(args.parentTraceId),
+                rootTraceId: asOptionalString(args.rootTraceId),
+                surface: 'mcp',
+                signal: context.signal,
+                approve: elicitationApprover(context),
               }),
             };
           case 'agent.recommend':
@@ -1795,6 +3515,44 @@ export function createMcpServerSurface(config: {
                 asString(args.message, 'message'),
               ),
             };
+          case 'session.scratchpad':
+            return {
+              success: true,
+              data: await runtimeService.getScratchpad(asString(args.sessionId, 'sessionId')),
+            };
+          case 'session.add_tasks':
+            return {
+              success: true,
+              data: await runtimeService.addScratchpadTasks(asString(args.sessionId, 'sessionId'), asScratchpadTasks(args.tasks)),
+            };
+          case 'session.claim':
+            return {
+              success: true,
+              data: await runtimeService.claimScratchpadTask(asString(args.sessionId, 'sessionId'), {
+                taskId: asOptionalString(args.taskId),
+                claimant: asString(args.claimant, 'claimant'),
+                leaseMs: asOptionalNumber(args.leaseMs),
+              }),
+            };
+          case 'session.post':
+            return {
+              success: true,
+              data: await runtimeService.postScratchpadResult(asString(args.sessionId, 'sessionId'), {
+                taskId: asString(args.taskId, 'taskId'),
+                claimant: asString(args.claimant, 'claimant'),
+                result: asString(args.result, 'result'),
+                status: args.status === 'failed' ? 'failed' : 'done',
+              }),
+            };
+          case 'session.release':
+            return {
+              success: true,
+              data: await runtimeService.releaseScratchpadTask(
+                asString(args.sessionId, 'sessionId'),
+                asString(args.taskId, 'taskId'),
+                asOptionalString(args.claimant),
+              ),
+            };
           case 'session.close_stuck':
             return {
               success: true,
@@ -1813,17 +3571,211 @@ export function createMcpServerSurface(config: {
                 surface: 'mcp',
               }),
             };
+          case 'code.index': {
+            let filesIndexed = 0;
+            return {
+              success: true,
+              data: await runtimeService.indexCode({
+                paths: asStringArray(args.paths),
+                maxFiles: asOptionalNumber(args.maxFiles),
+                basePath: asOptionalString(args.basePath),
+                signal: context.signal,
+                onFile: context.onProgress === undefined
+                  ? undefined
+                  : (entry) => {
+                    filesIndexed += 1;
+                    context.onProgress?.(filesIndexed, undefined, `Indexed ${entry.file}`);
+                  },
+              }),
+            };
+          }
+          case 'code.unsafe':
+            return {
+              success: true,
+              data: await runtimeService.reportUnsafeCode({
+                paths: asStringArray(args.paths),
+                crate: asOptionalString(args.crate),
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.signatures':
+            return {
+              success: true,
+              data: await runtimeService.renderSignatures({
+                paths: asStringArray(args.paths) ?? [],
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.outline':
+            return {
+              success: true,
+              data: await runtimeService.renderOutline({
+                paths: asStringArray(args.paths) ?? [],
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.file_outline':
+            return {
+              success: true,
+              data: await runtimeService.renderFileSkeleton({
+                path: asString(args.path, 'path'),
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.symbol_body':
+            return {
+              success: true,
+              data: await runtimeService.readSymbolBody({
+                symbol: asString(args.symbol, 'symbol'),
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.graph':
+            return {
+              success: true,
+              data: await runtimeService.exportCodeGraph({
+                graph: args.graph === 'calls' ? 'calls' : 'modules',
+                format: args.format === 'dot' ? 'dot' : 'mermaid',
+                paths: asStringArray(args.paths),
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.search':
+            return {
+              success: true,
+              data: await runtimeService.searchSymbols({
+                query: asString(args.query, 'query'),
+                limit: asOptionalNumber(args.limit),
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.search_symbols':
+            return {
+              success: true,
+              data: await runtimeService.describeSymbols({
+                query: asOptionalString(args.query),
+                kind: asOptionalString(args.kind) as CodeSymbolKind | undefined,
+                language: asOptionalString(args.language),
+                limit: asOptionalNumber(args.limit),
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.grep':
+            return {
+              success: true,
+              data: await runtimeService.searchText({
+                pattern: asString(args.pattern, 'pattern'),
+                regex: args.regex === true,
+                caseSensitive: args.caseSensitive === true,
+                paths: asStringArray(args.paths),
+                limit: asOptionalNumber(args.limit),
+                reindex: args.reindex === true,
+                basePath: asOptionalString(args.basePath),
+                onMatches: context.onPartialResult,
+              }),
+            };
+          case 'search.hybrid':
+            return {
+              success: true,
+              data: await runtimeService.hybridSearch({
+                query: asString(args.query, 'query'),
+                k: asOptionalNumber(args.k),
+                namespace: asOptionalString(args.namespace),
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.references':
+            return {
+              success: true,
+              data: await runtimeService.findReferences({
+                symbol: asString(args.symbol, 'symbol'),
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.call_hierarchy':
+            return {
+              success: true,
+              data: await runtimeService.buildCallHierarchy({
+                symbol: asString(args.symbol, 'symbol'),
+                direction: args.direction === 'outgoing' ? 'outgoing' : 'incoming',
+                depth: asOptionalNumber(args.depth),
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.change_impact':
+            return {
+              success: true,
+              data: await runtimeService.analyzeChangeImpact({
+                diff: asOptionalString(args.diff),
+                files: asStringArray(args.files),
+                base: asOptionalString(args.base),
+                depth: asOptionalNumber(args.depth),
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'code.unreferenced':
+            return {
+              success: true,
+              data: await runtimeService.findUnreferencedSymbols({
+                paths: asStringArray(args.paths),
+                kind: asOptionalString(args.kind) as CodeSymbolKind | undefined,
+                limit: asOptionalNumber(args.limit),
+                basePath: asOptionalString(args.basePath),
+              }),
+            };
+          case 'test.and_fix':
+            return {
+              success: true,
+              data: await runtimeService.testAndFix({
+                agentId: asOptionalString(args.agentId),
+                maxIterations: asOptionalNumber(args.maxIterations),
+                command: asStringArray(args.command),
+                provider: asOptionalString(args.provider),
+                model: asOptionalString(args.model),
+                timeoutMs: asOptionalNumber(args.timeoutMs),
+                sessionId: asOptionalString(args.sessionId),
+                basePath: asOptionalString(args.basePath),
+                surface: 'mcp',
+                signal: context.signal,
+                onProgress: context.onProgress === undefined
+                  ? undefined
+                  : (percent, message) => context.onProgress?.(percent, 100, message),
+                approve: elicitationApprover(context),
+              }),
+            };
           case 'review.list':
             return {
               success: true,
               data: await runtimeService.listReviewTraces(asOptionalNumber(args.limit)),
             };
+          case 'review.and_annotate':
+            return {
+              success: true,
+              data: await runtimeService.reviewAndAnnotate({
+                base: asOptionalString(args.base),
+                head: asOptionalString(args.head),
+                agentId: asOptionalString(args.agentId),
+                maxConcurrent: asOptionalNumber(args.maxConcurrent),
+                maxSymbols: asOptionalNumber(args.maxSymbols),
+                provider: asOptionalString(args.provider),
+                model: asOptionalString(args.model),
+                timeoutMs: asOptionalNumber(args.timeoutMs),
+                sessionId: asOptionalString(args.sessionId),
+                basePath: asOptionalString(args.basePath),
+                surface: 'mcp',
+                signal: context.signal,
+                onProgress: context.onProgress === undefined
+                  ? undefined
+                  : (percent, message) => context.onProgress?.(percent, 100, message),
+              }),
+            };
           case 'memory.retrieve':
             return {
               success: true,
               data: await runtimeService.getMemory(
                 asString(args.key, 'key'),
                 asOptionalString(args.namespace),
+                memoryScope(args, basePath),
               ),
             };
           case 'memory.search':
@@ -1832,6 +3784,7 @@ export function createMcpServerSurface(config: {
               data: await runtimeService.searchMemory(
                 asString(args.query, 'query'),
                 asOptionalString(args.namespace),
+                memoryScope(args, basePath),
               ),
             };
           case 'memory.delete':
@@ -1840,6 +3793,7 @@ export function createMcpServerSurface(config: {
               data: { deleted: await runtimeService.deleteMemory(
                 asString(args.key, 'key'),
                 asOptionalString(args.namespace),
+                memoryScope(args, basePath),
               ) },
             };
           case 'memory.store':
@@ -1849,12 +3803,88 @@ export function createMcpServerSurface(config: {
                 key: asString(args.key, 'key'),
                 namespace: asOptionalString(args.namespace),
                 value: args.value,
+                scope: memoryScope(args, basePath),
+                ttlSeconds: asOptionalNumber(args.ttlSeconds),
+                importance: asOptionalNumber(args.importance),
+                source: isRecord(args.source) ? asMemorySource(args.source) : undefined,
+                dedup: asOptionalMemoryDedup(args.dedup),
               }),
             };
           case 'memory.list':
             return {
               success: true,
-              data: await runtimeService.listMemory(asOptionalString(args.namespace)),
+              data: await runtimeService.listMemory(asOptionalString(args.namespace), memoryScope(args, basePath)),
+            };
+          case 'memory.namespaces':
+            return {
+              success: true,
+              data: await runtimeService.listMemoryNamespaces(),
+            };
+          case 'memory.copy':
+            return {
+              success: true,
+              data: await runtimeService.copyMemoryNamespace({
+                source: asString(args.source, 'source'),
+                target: asString(args.target, 'target'),
+                overwrite: args.overwrite === true,
+              }),
+            };
+          case 'memory.merge':
+            return {
+              success: true,
+              data: await runtimeService.mergeMemoryNamespaces({
+                sources: asStringArray(args.sources) ?? [],
+                target: asString(args.target, 'target'),
+              }),
+            };
+          case 'memory.prune':
+            return {
+              success: true,
+              data: await runtimeService.pruneMemory({
+                basePath: asOptionalString(args.basePath) ?? basePath,
+                dryRun: args.dryRun === true,
+              }),
+            };
+          case 'memory.stale':
+            return {
+              success: true,
+              data: await runtimeService.checkMemorySources({
+                namespace: asOptionalString(args.namespace),
+                scope: memoryScope(args, basePath),
+                invalidate: args.invalidate === true,
+              }),
+            };
+          case 'fact.store':
+            return {
+              success: true,
+              data: await runtimeService.storeFact({
+                subject: asString(args.subject, 'subject'),
+                predicate: asString(args.predicate, 'predicate'),
+                object: asFactObject(args.object) ?? asString(args.object, 'object'),
+                namespace: asOptionalString(args.namespace),
+                sourceSpan: isRecord(args.sourceSpan)
+                  ? { file: asString(args.sourceSpan.file, 'sourceSpan.file'), startLine: asOptionalNumber(args.sourceSpan.startLine), endLine: asOptionalNumber(args.sourceSpan.endLine) }
+                  : undefined,
+                confidence: asOptionalFloat(args.confidence),
+                scope: memoryScope(args, basePath),
+              }),
+            };
+          case 'fact.query':
+            return {
+              success: true,
+              data: await runtimeService.queryFacts({
+                subject: asOptionalString(args.subject),
+                predicate: asOptionalString(args.predicate),
+                object: asFactObject(args.object),
+                namespace: asOptionalString(args.namespace),
+                limit: asOptionalNumber(args.limit),
+                scope: memoryScope(args, basePath),
+              }),
+            };
+          case 'fact.delete':
+            return {
+              success: true,
+              data: await runtimeService.deleteFact(asString(args.factId, 'factId')),
             };
           case 'semantic.store':
             return {
@@ -1991,6 +4021,16 @@ export function createMcpServerSurface(config: {
               success: true,
               data: await runtimeService.showConfig(),
             };
+          case 'config.explain':
+            return {
+              success: true,
+              data: await runtimeService.explainConfig(asString(args.path, 'path')),
+            };
+          case 'config.validate':
+            return {
+              success: true,
+              data: await runtimeService.validateConfig(),
+            };
           case 'file.exists':
             return {
               success: true,
@@ -2446,6 +4486,18 @@ export function createMcpServerSurface(config: {
               },
             };
           }
+          case 'scaffold.impl':
+            return {
+              success: true,
+              data: await runtimeService.scaffoldImplementation({
+                symbol: asString(args.symbol, 'symbol'),
+                className: asOptionalString(args.className),
+                output: asOptionalString(args.output),
+                overwrite: args.overwrite === true,
+                dryRun: args.dryRun === true,
+                basePath: asOptionalString(args.basePath) ?? basePath,
+              }),
+            };
           default:
             return {
               success: false,
@@ -2502,6 +4554,7 @@ function isRateLimitedMethod(method: string): boolean {
     || method === 'tools/call'
     || method === 'resources/list'
     || method === 'resources/read'
+    || method === 'resources/subscribe'
     || method === 'prompts/list'
     || method === 'prompts/get';
 }
@@ -2548,6 +4601,11 @@ function validateInput(value: unknown, schema: JsonSchema): string | undefined {
 }
 
 function validateValue(value: unknown, schema: JsonSchema, path: string): string | undefined {
+  if (Array.isArray(schema.type)) {
+    return schema.type.some((type) => validateValue(value, { ...schema, type }, path) === undefined)
+      ? undefined
+      : `${path} must be a ${schema.type.join(' or ')}`;
+  }
   switch (schema.type) {
     case 'object': {
       if (!isRecord(value)) {
@@ -2624,10 +4682,36 @@ function asOptionalString(value: unknown): string | undefined {
   return typeof value === 'string' && value.length > 0 ? value : undefined;
 }
 
+function memoryScope(args: Record<string, unknown>, basePath: string): { project?: string; agent?: string } | undefined {
+  const agent = asOptionalString(args.agent);
+  if (args.project !== true && agent === undefined) {
+    return undefined;
+  }
+  return {
+    ...(args.project === true ? { project: asOptionalString(args.basePath) ?? basePath } : {}),
+    ...(agent !== undefined ? { agent } : {}),
+  };
+}
+
 function asOptionalNumber(value: unknown): number | undefined {
   return typeof value === 'number' && Number.isFinite(value) ? value : undefined;
 }
 
+function asMemorySource(value: Record<string, unknown>): NonNullable<Parameters<SharedRuntimeService['storeMemory']>[0]['source']> {
+  return {
+    file: asOptionalString(value.file),
+    startLine: asOptionalNumber(value.startLine),
+    endLine: asOptionalNumber(value.endLine),
+    sessionId: asOptionalString(value.sessionId),
+    agent: asOptionalString(value.agent),
+    provider: asOptionalString(value.provider),
+  };
+}
+
+function asFactObject(value: unknown): string | number | boolean | undefined {
+  return typeof value === 'string' || typeof value === 'boolean' || (typeof value === 'number' && Number.isFinite(value)) ? value : undefined;
+}
+
 function asOptionalFloat(value: unknown): number | undefined {
   return typeof value === 'number' && Number.isFinite(value) ? value : undefined;
 }
@@ -2677,6 +4761,24 @@ function asParallelTasks(value: unknown): Array<{
   });
 }
 
+function asScratchpadTasks(value: unknown): ScratchpadTaskInput[] {
+  if (!Array.isArray(value)) {
+    throw new Error('tasks must be an array');
+  }
+
+  return value.map((entry, index) => {
+    if (!isRecord(entry)) {
+      throw new Error(`tasks[${index}] must be an object`);
+    }
+
+    return {
+      taskId: asOptionalString(entry.taskId),
+      title: asString(entry.title, `tasks[${index}].title`),
+      paths: asStringArray(entry.paths),
+    };
+  });
+}
+
 function asParallelFailureStrategy(value: unknown): 'failFast' | 'failSafe' | undefined {
   return value === 'failFast' || value === 'failSafe' ? value : undefined;
 }
@@ -2691,6 +4793,12 @@ function asOptionalRole(value: unknown): 'initiator' | 'collaborator' | 'delegat
     : undefined;
 }
 
+function asOptionalMemoryDedup(value: unknown): MemoryDedupAction | 'off' | undefined {
+  return value === 'off' || (MEMORY_DEDUP_ACTIONS as readonly unknown[]).includes(value)
+    ? value as MemoryDedupAction | 'off'
+    : undefined;
+}
+
 function asOptionalReviewFocus(value: unknown): ReviewFocus | undefined {
   return value === 'all' || value === 'security' || value === 'correctness' || value === 'maintainability'
     ? value
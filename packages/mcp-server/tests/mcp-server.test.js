@@ -1,13 +1,14 @@
-import { mkdirSync } from 'node:fs';
+import { mkdirSync, realpathSync, symlinkSync } from 'node:fs';
 import { execFile } from 'node:child_process';
-import { rm, writeFile } from 'node:fs/promises';
+import { request as httpRequest } from 'node:http';
+import { readFile, rm, writeFile } from 'node:fs/promises';
 import { join } from 'node:path';
-import { Readable, Writable } from 'node:stream';
+import { PassThrough, Readable, Writable } from 'node:stream';
 import { promisify } from 'node:util';
 import { afterEach, describe, expect, it } from 'vitest';
 import { createSharedRuntimeService } from '@defai.digital/shared-runtime';
 import { initCommand, setupCommand } from '../../cli/src/commands/index.js';
-import { createMcpServerSurface, createMcpStdioServer } from '../src/index.js';
+import { createMcpServerSurface, createMcpStdioServer, loadMcpClientPolicies, startMcpHttpServer } from '../src/index.js';
 const execFileAsync = promisify(execFile);
 function createTempDir() {
     const dir = join(process.cwd(), '.tmp', `mcp-surface-${Date.now()}-${Math.random().toString(16).slice(2, 8)}`);
@@ -216,6 +217,47 @@ describe('mcp server surface', () => {
             },
         ]);
     });
+    it('scopes memory tools by project and agent and copies or merges namespaces', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const surface = createMcpServerSurface({ basePath: tempDir, runtimeService: createSharedRuntimeService({ basePath: tempDir }) });
+        await surface.invokeTool('memory.store', { namespace: 'notes', key: 'style', value: { indent: 'tabs' }, project: true });
+        await surface.invokeTool('memory.store', { namespace: 'notes', key: 'focus', value: { area: 'tests' }, agent: 'reviewer' });
+        const projectNotes = await surface.invokeTool('memory.list', { project: true });
+        const unscoped = await surface.invokeTool('memory.retrieve', { namespace: 'notes', key: 'style' });
+        const namespaces = await surface.invokeTool('memory.namespaces');
+        const scopedNames = namespaces.data.map((stats) => stats.namespace);
+        const copied = await surface.invokeTool('memory.copy', { source: scopedNames[1], target: 'team' });
+        const merged = await surface.invokeTool('memory.merge', { sources: [scopedNames[0]], target: 'team' });
+        const team = await surface.invokeTool('memory.list', { namespace: 'team' });
+        expect(projectNotes.data).toMatchObject([{ key: 'style', value: { indent: 'tabs' } }]);
+        expect(unscoped.data).toBeUndefined();
+        expect(scopedNames).toEqual(['@agent:reviewer/notes', expect.stringMatching(/^@project:.+\/notes$/)]);
+        expect(copied.data).toEqual({ copied: 1, skipped: 0 });
+        expect(merged.data).toEqual({ copied: 1, skipped: 0, deleted: 1 });
+        expect(team.data.map((entry) => entry.key).sort()).toEqual(['focus', 'style']);
+    });
+    it('stores expiring and weighted memory and previews a prune', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const surface = createMcpServerSurface({ basePath: tempDir, runtimeService: createSharedRuntimeService({ basePath: tempDir }) });
+        const stored = await surface.invokeTool('memory.store', { key: 'draft', value: { text: 'wip' }, ttlSeconds: 60, importance: 0.3 });
+        const preview = await surface.invokeTool('memory.prune', { dryRun: true });
+        expect(stored.data).toMatchObject({ key: 'draft', importance: 0.3, expiresAt: expect.any(String) });
+        expect(preview).toMatchObject({ success: true, data: { dryRun: true, evicted: [] } });
+    });
+    it('stores and queries typed facts', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const surface = createMcpServerSurface({ basePath: tempDir, runtimeService: createSharedRuntimeService({ basePath: tempDir }) });
+        const stored = await surface.invokeTool('fact.store', { subject: 'billing-api', predicate: 'retries', object: 3, sourceSpan: { file: 'src/billing.ts', startLine: 2 }, project: true });
+        const rejected = await surface.invokeTool('fact.store', { subject: 'billing-api', predicate: 'retries', object: [3] });
+        const found = await surface.invokeTool('fact.query', { object: 3, project: true });
+        expect(stored.data).toMatchObject({ subject: 'billing-api', object: 3, sourceSpan: { file: 'src/billing.ts', startLine: 2 } });
+        expect(rejected).toMatchObject({ success: false, error: 'arguments.object must be a string or number or boolean' });
+        expect(found.data).toMatchObject([{ factId: stored.data.factId }]);
+        expect(await surface.invokeTool('fact.query', { object: '3', project: true })).toMatchObject({ success: true, data: [] });
+    });
     it('exposes feedback, abilities, trace trees, and local git/pr helpers', async () => {
         const tempDir = createTempDir();
         tempDirs.push(tempDir);
@@ -860,6 +902,809 @@ describe('mcp server surface', () => {
         expect(promptResp?.result?.messages?.[0]?.content?.text).toContain('Design audit trail');
         expect(promptResp?.result?.description).toContain('architect');
     });
+    it('offers every registered agent as a prompt with task, files and constraints', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const runtimeService = createSharedRuntimeService({ basePath: tempDir });
+        await runtimeService.registerAgent({ agentId: 'architect', name: 'Architect', capabilities: ['adr', 'planning'] });
+        await runtimeService.registerAgent({
+            agentId: 'security',
+            name: 'Security',
+            capabilities: ['audit'],
+            metadata: { description: 'Audit a change for vulnerabilities.', systemPrompt: 'You are a meticulous security auditor.' },
+        });
+        const surface = createMcpServerSurface({ basePath: tempDir, runtimeService });
+        const prompts = await surface.listPrompts();
+        expect(prompts.some((prompt) => prompt.name === 'workflow.run')).toBe(true);
+        expect(prompts.find((prompt) => prompt.name === 'agent.architect')).toEqual({
+            name: 'agent.architect',
+            description: 'Work on a task as the Architect agent (adr, planning).',
+            arguments: [
+                { name: 'task', description: 'What the agent should do.', required: true },
+                { name: 'files', description: 'Comma-separated file or directory paths the task concerns.' },
+                { name: 'constraints', description: 'Constraints the result must respect.' },
+            ],
+        });
+        expect(prompts.find((prompt) => prompt.name === 'agent.security')?.description).toBe('Audit a change for vulnerabilities.');
+        const prompt = await surface.getPrompt('agent.security', { task: 'Review the login flow', files: 'src/auth.ts, src/session.ts', constraints: 'No new dependencies' });
+        expect(prompt.messages).toHaveLength(1);
+        expect(prompt.messages[0]?.content.text).toBe([
+            'You are a meticulous security auditor.',
+            '',
+            'Task: Review the login flow',
+            'Files: src/auth.ts, src/session.ts',
+            'Constraints: No new dependencies',
+            '',
+            'Answer as this agent, or call the agent.run tool with agentId "security" to run it through AutomatosX.',
+        ].join('\n'));
+        const plain = await surface.getPrompt('agent.architect', { task: 'Plan the cache layer' });
+        expect(plain.messages[0]?.content.text).toContain('You are Architect (architect). Capabilities: adr, planning.');
+        expect(plain.messages[0]?.content.text).not.toContain('Files:');
+        await expect(surface.getPrompt('agent.architect', {})).rejects.toThrow('task');
+        await expect(surface.getPrompt('agent.nobody', { task: 'x' })).rejects.toThrow('Unknown prompt: agent.nobody');
+    });
+    it('serves repo map, agent and memory resources and notifies subscribers of changes', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        mkdirSync(join(tempDir, 'src'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'cart.rb'), 'class Cart\n  def add(price)\n  end\nend\n', 'utf8');
+        const runtimeService = createSharedRuntimeService({ basePath: tempDir });
+        await runtimeService.registerAgent({ agentId: 'reviewer', name: 'Reviewer', capabilities: ['review'] });
+        const input = new PassThrough();
+        let buffered = '';
+        const output = new Writable({
+            write(chunk, _enc, cb) {
+                buffered += chunk.toString();
+                cb();
+            },
+        });
+        const messages = () => buffered.trim().split('\n').filter((line) => line.length > 0).map((line) => JSON.parse(line));
+        const waitFor = async (match) => {
+            for (let attempt = 0; attempt < 200; attempt += 1) {
+                const found = messages().find(match);
+                if (found !== undefined) {
+                    return found;
+                }
+                await new Promise((resolve) => setTimeout(resolve, 10));
+            }
+            throw new Error(`No matching message in: ${buffered}`);
+        };
+        let nextId = 0;
+        const call = async (method, params) => {
+            const id = ++nextId;
+            input.write(`${JSON.stringify({ jsonrpc: '2.0', id, method, params })}\n`);
+            return waitFor((message) => message.id === id);
+        };
+        const updates = () => messages().filter((message) => message.method === 'notifications/resources/updated').map((message) => message.params.uri);
+        const server = createMcpStdioServer({ runtimeService, basePath: tempDir, input, output, resourcePollMs: 20 });
+        const served = server.serve();
+        expect((await call('initialize')).result.capabilities.resources.subscribe).toBe(true);
+        const templates = (await call('resources/templates/list')).result.resourceTemplates.map((template) => template.uriTemplate);
+        expect(templates).toEqual(['ax://code/outline/{path}', 'ax://agents/{agentId}', 'ax://memory/{namespace}']);
+        expect((await call('resources/read', { uri: 'ax://repomap' })).error.message).toContain('Run "ax code index" first');
+        await runtimeService.indexCode();
+        expect((await call('resources/read', { uri: 'ax://repomap' })).result.contents[0].text).toBe('src/cart.rb\n  class Cart\n    def add(price)\n');
+        expect(JSON.parse((await call('resources/read', { uri: 'ax://agents/reviewer' })).result.contents[0].text)).toMatchObject({ agentId: 'reviewer', capabilities: ['review'] });
+        expect((await call('resources/read', { uri: 'ax://agents/nobody' })).error.message).toBe('Agent "nobody" is not registered.');
+        expect((await call('resources/subscribe', { uri: 'ax://memory/notes' })).result).toEqual({});
+        await call('tools/call', { name: 'memory.store', arguments: { namespace: 'notes', key: 'decision', value: { choice: 'sqlite' } } });
+        expect(updates()).toEqual(['ax://memory/notes']);
+        const notes = JSON.parse((await call('resources/read', { uri: 'ax://memory/notes' })).result.contents[0].text);
+        expect(notes.map((entry) => entry.key)).toEqual(['decision']);
+        // Written behind the server's back: only the poll sees it.
+        await runtimeService.storeMemory({ namespace: 'notes', key: 'followup', value: { task: 'add an index' } });
+        await waitFor(() => updates().length === 2);
+        await call('tools/call', { name: 'memory.store', arguments: { namespace: 'other', key: 'unrelated', value: { n: 1 } } });
+        expect(updates()).toEqual(['ax://memory/notes', 'ax://memory/notes']);
+        await call('resources/unsubscribe', { uri: 'ax://memory/notes' });
+        await runtimeService.storeMemory({ namespace: 'notes', key: 'later', value: { ignored: true } });
+        await call('tools/call', { name: 'memory.store', arguments: { namespace: 'notes', key: 'last', value: { ignored: true } } });
+        expect(updates()).toHaveLength(2);
+        await call('shutdown');
+        input.end();
+        await served;
+    });
+    it('serves Streamable HTTP sessions with resumable event streams and the legacy SSE transport', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const runtimeService = createSharedRuntimeService({ basePath: tempDir });
+        const server = await startMcpHttpServer({ runtimeService, basePath: tempDir, port: 0, resourcePollMs: 20 });
+        const origin = server.url.replace(/\/mcp$/, '');
+        const post = (body, headers = {}) => fetch(server.url, {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json', Accept: 'application/json', ...headers },
+            body: JSON.stringify(body),
+        });
+        // Collects SSE events off a response; `until` keeps reading until the events seen so far satisfy it.
+        const eventStream = (response) => {
+            const reader = response.body.getReader();
+            const decoder = new TextDecoder();
+            const events = [];
+            let text = '';
+            return {
+                async until(done) {
+                    while (!done(events)) {
+                        const { value, done: ended } = await reader.read();
+                        if (ended) {
+                            break;
+                        }
+                        text += decoder.decode(value, { stream: true });
+                        let boundary = text.indexOf('\n\n');
+                        while (boundary !== -1) {
+                            const fields = Object.fromEntries(text.slice(0, boundary).split('\n')
+                                .filter((line) => !line.startsWith(':'))
+                                .map((line) => [line.slice(0, line.indexOf(':')), line.slice(line.indexOf(':') + 2)]));
+                            if (fields.data !== undefined) {
+                                events.push(fields);
+                            }
+                            text = text.slice(boundary + 2);
+                            boundary = text.indexOf('\n\n');
+                        }
+                    }
+                    return events;
+                },
+                close: () => reader.cancel(),
+            };
+        };
+        const readEvents = async (response, done) => {
+            const stream = eventStream(response);
+            const events = await stream.until(done);
+            await stream.close();
+            return events;
+        };
+        try {
+            const initialized = await post({ jsonrpc: '2.0', id: 1, method: 'initialize', params: { protocolVersion: '2025-03-26' } });
+            expect(initialized.status).toBe(200);
+            expect((await initialized.json()).result.protocolVersion).toBe('2025-03-26');
+            const sessionId = initialized.headers.get('mcp-session-id');
+            expect(sessionId).toMatch(/^[0-9a-f-]{36}$/);
+            const session = { 'Mcp-Session-Id': sessionId };
+            expect((await post({ jsonrpc: '2.0', id: 2, method: 'ping' })).status).toBe(400);
+            expect((await post({ jsonrpc: '2.0', id: 2, method: 'ping' }, { 'Mcp-Session-Id': 'expired' })).status).toBe(404);
+            expect((await post({ jsonrpc: '2.0', method: 'notifications/initialized' }, session)).status).toBe(202);
+            const batch = await (await post([
+                { jsonrpc: '2.0', id: 3, method: 'ping' },
+                { jsonrpc: '2.0', id: 4, method: 'resources/subscribe', params: { uri: 'ax://memory/notes' } },
+            ], session)).json();
+            expect(batch).toEqual([{ jsonrpc: '2.0', id: 3, result: {} }, { jsonrpc: '2.0', id: 4, result: {} }]);
+            // A tool call answered over SSE.
+            const streamed = await post({ jsonrpc: '2.0', id: 5, method: 'tools/call', params: { name: 'memory.store', arguments: { namespace: 'notes', key: 'a', value: { n: 1 } } } }, { ...session, Accept: 'application/json, text/event-stream' });
+            expect(streamed.headers.get('content-type')).toBe('text/event-stream');
+            const [reply] = await readEvents(streamed, () => false);
+            expect(JSON.parse(reply.data)).toMatchObject({ id: 5, result: { content: [{ type: 'text' }] } });
+            // Notifications arrive on the GET stream; a dropped stream resumes from the last event it saw.
+            const listening = await fetch(server.url, { headers: { ...session, Accept: 'text/event-stream' } });
+            await runtimeService.storeMemory({ namespace: 'notes', key: 'b', value: { n: 2 } });
+            const [first] = await readEvents(listening, (events) => events.length === 1);
+            expect(JSON.parse(first.data)).toEqual({ jsonrpc: '2.0', method: 'notifications/resources/updated', params: { uri: 'ax://memory/notes' } });
+            await runtimeService.storeMemory({ namespace: 'notes', key: 'c', value: { n: 3 } });
+            await new Promise((resolve) => setTimeout(resolve, 150));
+            const resumed = await fetch(server.url, { headers: { ...session, Accept: 'text/event-stream', 'Last-Event-ID': first.id } });
+            const [missed] = await readEvents(resumed, (events) => events.length === 1);
+            expect(Number(missed.id)).toBeGreaterThan(Number(first.id));
+            expect(JSON.parse(missed.data).method).toBe('notifications/resources/updated');
+            expect((await fetch(server.url, { method: 'DELETE', headers: session })).status).toBe(204);
+            expect(server.sessionCount()).toBe(0);
+            expect((await post({ jsonrpc: '2.0', id: 6, method: 'ping' }, session)).status).toBe(404);
+            expect((await post({ jsonrpc: '2.0', id: 7, method: 'initialize' }, { Origin: 'https://evil.example' })).status).toBe(403);
+            // The HTTP+SSE transport: an endpoint event, then replies on the same stream.
+            const legacy = eventStream(await fetch(`${origin}/sse`, { headers: { Accept: 'text/event-stream' } }));
+            const [endpoint] = await legacy.until((events) => events.length === 1);
+            expect(endpoint).toMatchObject({ event: 'endpoint', data: expect.stringMatching(/^\/messages\?sessionId=/) });
+            const accepted = await fetch(`${origin}${endpoint.data}`, { method: 'POST', headers: { 'Content-Type': 'application/json' }, body: JSON.stringify({ jsonrpc: '2.0', id: 8, method: 'ping' }) });
+            expect(accepted.status).toBe(202);
+            const [, answer] = await legacy.until((events) => events.length === 2);
+            expect(answer).toMatchObject({ event: 'message', data: '{"jsonrpc":"2.0","id":8,"result":{}}' });
+            await legacy.close();
+        }
+        finally {
+            await server.close();
+        }
+        const guarded = await startMcpHttpServer({ runtimeService, basePath: tempDir, port: 0, token: 'secret' });
+        try {
+            const init = { method: 'POST', body: JSON.stringify({ jsonrpc: '2.0', id: 1, method: 'initialize' }) };
+            expect((await fetch(guarded.url, init)).status).toBe(401);
+            expect((await fetch(guarded.url, { ...init, headers: { Authorization: 'Bearer secret' } })).status).toBe(200);
+        }
+        finally {
+            await guarded.close();
+        }
+    });
+    it('refuses to serve off loopback without a token and answers only to known host names', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const runtimeService = createSharedRuntimeService({ basePath: tempDir });
+        await expect(startMcpHttpServer({ runtimeService, basePath: tempDir, host: '0.0.0.0', port: 0 }))
+            .rejects.toThrow('Listening on 0.0.0.0 without a token would open every tool to the network');
+        const server = await startMcpHttpServer({ runtimeService, basePath: tempDir, port: 0, allowedOrigins: ['https://ide.example.com'] });
+        // fetch() sets Host itself, so the rebinding case goes through node:http.
+        const statusFor = (host) => new Promise((resolve, reject) => {
+            const sent = httpRequest(server.url, { method: 'POST', headers: { Host: host, 'Content-Type': 'application/json' } }, (response) => {
+                response.resume();
+                resolve(response.statusCode);
+            });
+            sent.on('error', reject);
+            sent.end(JSON.stringify({ jsonrpc: '2.0', id: 1, method: 'initialize' }));
+        });
+        try {
+            expect(await statusFor('evil.example:3100')).toBe(403);
+            expect(await statusFor(`localhost:${server.port}`)).toBe(200);
+            expect(await statusFor(`127.0.0.1:${server.port}`)).toBe(200);
+            expect(await statusFor('ide.example.com')).toBe(200);
+        } finally {
+            await server.close();
+        }
+    });
+    it('authenticates HTTP clients by token, applies their tool policies and audits every call', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'mcp-clients.json'), JSON.stringify({
+            clients: {
+                reader: { token: 'reader-token', allow: ['code.*', 'file.*', 'memory.list'], readOnly: true },
+                ops: { tokenEnv: 'AX_TEST_OPS_TOKEN', deny: ['agent.run'] },
+            },
+        }), 'utf8');
+        expect((await loadMcpClientPolicies(tempDir, {}))[1]).toEqual({ name: 'ops', allow: ['*'], deny: ['agent.run'], readOnly: false, tokenEnv: 'AX_TEST_OPS_TOKEN' });
+        await expect(startMcpHttpServer({ basePath: tempDir, port: 0 })).rejects.toThrow('MCP client "ops" reads its token from AX_TEST_OPS_TOKEN, which is not set.');
+        process.env.AX_TEST_OPS_TOKEN = 'ops-token';
+        const server = await startMcpHttpServer({ basePath: tempDir, port: 0 });
+        const rpc = async (token, body, sessionId) => {
+            const response = await fetch(server.url, {
+                method: 'POST',
+                headers: {
+                    'Content-Type': 'application/json',
+                    Accept: 'application/json',
+                    ...(token !== undefined ? { Authorization: `Bearer ${token}` } : {}),
+                    ...(sessionId !== undefined ? { 'Mcp-Session-Id': sessionId } : {}),
+                },
+                body: JSON.stringify({ jsonrpc: '2.0', id: 1, ...body }),
+            });
+            return { status: response.status, sessionId: response.headers.get('mcp-session-id') ?? undefined, body: await response.json() };
+        };
+        const call = async (token, sessionId, name, args = {}) =>
+            (await rpc(token, { method: 'tools/call', params: { name, arguments: args } }, sessionId)).body.result;
+        try {
+            expect((await rpc(undefined, { method: 'initialize' })).status).toBe(401);
+            expect((await rpc('guess', { method: 'initialize' })).status).toBe(401);
+            const reader = (await rpc('reader-token', { method: 'initialize' })).sessionId;
+            const tools = (await rpc('reader-token', { method: 'tools/list' }, reader)).body.result.tools.map((tool) => tool.name);
+            expect(tools).toContain('code.search_symbols');
+            expect(tools).toContain('file.exists');
+            expect(tools).not.toContain('file.write');
+            expect(tools).not.toContain('memory.store');
+            expect(tools).not.toContain('agent.run');
+            expect(await call('reader-token', reader, 'ax_agent_run', { agentId: 'architect' })).toEqual({
+                content: [{ type: 'text', text: 'agent.run is not allowed for MCP client "reader".' }],
+                isError: true,
+            });
+            expect((await call('reader-token', reader, 'file.write', { path: 'notes.txt', content: 'x' })).content[0].text)
+                .toBe('file.write is disabled for read-only MCP client "reader".');
+            expect((await call('reader-token', reader, 'memory.list', { namespace: 'notes' })).isError).toBeUndefined();
+            // Resources and prompts follow the policy of the tool each mirrors.
+            const resources = (await rpc('reader-token', { method: 'resources/list' }, reader)).body.result.resources.map((resource) => resource.uri);
+            expect(resources).toEqual(['ax://code/outline', 'ax://repomap']);
+            expect((await rpc('reader-token', { method: 'resources/read', params: { uri: 'ax://memory/notes' } }, reader)).body.result.contents[0].text).toBe('[]\n');
+            expect((await rpc('reader-token', { method: 'resources/read', params: { uri: 'ax://agents' } }, reader)).body.error).toEqual({
+                code: -32002,
+                message: 'agent.list is not allowed for MCP client "reader".',
+            });
+            expect((await rpc('reader-token', { method: 'resources/subscribe', params: { uri: 'ax://agents/architect' } }, reader)).body.error?.code).toBe(-32002);
+            expect((await rpc('reader-token', { method: 'prompts/list' }, reader)).body.result.prompts).toEqual([]);
+            expect((await rpc('reader-token', { method: 'prompts/get', params: { name: 'workflow.architect', arguments: { requirement: 'Audit trail' } } }, reader)).body.error?.message)
+                .toBe('workflow.architect is not allowed for MCP client "reader".');
+            const ops = (await rpc('ops-token', { method: 'initialize' })).sessionId;
+            expect((await rpc('ops-token', { method: 'tools/list' }, reader)).status).toBe(403);
+            expect((await call('ops-token', ops, 'agent.run', { agentId: 'architect' })).content[0].text).toBe('agent.run is denied for MCP client "ops".');
+            expect((await call('ops-token', ops, 'memory.store', { key: 'k', value: { v: 1 } })).isError).toBeUndefined();
+            expect((await rpc('ops-token', { method: 'prompts/get', params: { name: 'workflow.architect', arguments: { requirement: 'Audit trail' } } }, ops)).body.result.messages).toHaveLength(1);
+        }
+        finally {
+            delete process.env.AX_TEST_OPS_TOKEN;
+            await server.close();
+        }
+        const audit = (await readFile(join(tempDir, '.automatosx', 'logs', 'mcp-audit.log'), 'utf8'))
+            .trim().split('\n').map((line) => JSON.parse(line));
+        expect(audit.map(({ client, tool, outcome }) => `${client} ${tool} ${outcome}`)).toEqual([
+            'reader agent.run denied',
+            'reader file.write denied',
+            'reader memory.list ok',
+            'reader memory.list ok',
+            'reader agent.list denied',
+            'reader agent.get denied',
+            'reader workflow.architect denied',
+            'ops agent.run denied',
+            'ops memory.store ok',
+            'ops workflow.architect ok',
+        ]);
+        expect(audit[8]).toMatchObject({ arguments: ['key', 'value'], durationMs: expect.any(Number), timestamp: expect.any(String) });
+        expect(audit[0]).toMatchObject({ error: 'agent.run is not allowed for MCP client "reader".' });
+        expect(audit[3]).toMatchObject({ resource: 'ax://memory/notes', arguments: [] });
+        expect(audit[9]).toMatchObject({ prompt: 'workflow.architect', arguments: ['requirement'] });
+        const stdio = createMcpStdioServer({ basePath: tempDir, input: Readable.from([]), output: new PassThrough(), client: 'writer' });
+        await expect(stdio.serve()).rejects.toThrow('Unknown MCP client "writer". Define it in .automatosx/mcp-clients.json.');
+    });
+    it('refuses agent runs and destructive tools to read-only MCP clients', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'mcp-clients.json'), JSON.stringify({
+            clients: { auditor: { token: 'auditor-token', readOnly: true } },
+        }), 'utf8');
+        const runtimeService = createSharedRuntimeService({ basePath: tempDir });
+        await runtimeService.registerAgent({ agentId: 'planner', name: 'Planner', capabilities: ['planning'] });
+        await runtimeService.storeMemory({ namespace: 'notes', key: 'followup', value: { task: 'add an index' } });
+        const server = await startMcpHttpServer({ basePath: tempDir, port: 0, runtimeService });
+        const rpc = async (body, sessionId) => {
+            const response = await fetch(server.url, {
+                method: 'POST',
+                headers: {
+                    'Content-Type': 'application/json',
+                    Accept: 'application/json',
+                    Authorization: 'Bearer auditor-token',
+                    ...(sessionId !== undefined ? { 'Mcp-Session-Id': sessionId } : {}),
+                },
+                body: JSON.stringify({ jsonrpc: '2.0', id: 1, ...body }),
+            });
+            return { sessionId: response.headers.get('mcp-session-id') ?? undefined, body: await response.json() };
+        };
+        const refused = [
+            ['agent.run', { agentId: 'planner', task: 'Plan' }],
+            ['agent.delegate', { fromAgentId: 'planner', toAgentId: 'planner', task: 'Plan' }],
+            ['workflow.run', { workflowId: 'architect' }],
+            ['parallel.run', { tasks: [] }],
+            ['discuss.run', { topic: 'REST or GraphQL' }],
+            ['discuss.quick', { topic: 'REST or GraphQL' }],
+            ['discuss.recursive', { topic: 'REST or GraphQL' }],
+            ['memory.store', { key: 'k', value: { v: 1 } }],
+            ['memory.clear', { namespace: 'notes' }],
+            ['memory.bulk_delete', { keys: ['followup'], namespace: 'notes' }],
+            ['fact.delete', { factId: 'fact-1' }],
+            ['semantic.clear', { namespace: 'notes' }],
+            ['agent.remove', { agentId: 'planner' }],
+            ['ability.remove', { abilityId: 'code-review' }],
+            ['mcp.server_register', { serverId: 'files', command: 'npx' }],
+        ];
+        try {
+            const session = (await rpc({ method: 'initialize' })).sessionId;
+            const tools = (await rpc({ method: 'tools/list' }, session)).body.result.tools.map((tool) => tool.name);
+            expect(tools).toContain('memory.list');
+            for (const [name, args] of refused) {
+                expect(tools).not.toContain(name);
+                expect((await rpc({ method: 'tools/call', params: { name, arguments: args } }, session)).body.result).toEqual({
+                    content: [{ type: 'text', text: `${name} is disabled for read-only MCP client "auditor".` }],
+                    isError: true,
+                });
+            }
+        } finally {
+            await server.close();
+        }
+        expect(await runtimeService.getAgent('planner')).toBeDefined();
+        expect((await runtimeService.listMemory('notes')).map((entry) => entry.key)).toEqual(['followup']);
+        // Other tools run as `--read-only` would.
+        const surface = createMcpServerSurface({ basePath: tempDir, runtimeService });
+        expect(await surface.invokeTool('agent.run', { agentId: 'planner', task: 'Plan' }, { readOnly: true })).toEqual({
+            success: false,
+            error: 'agent.run is disabled in read-only mode.',
+            data: { code: 'READ_ONLY_MODE' },
+        });
+    });
+    it('reports progress of long tool calls and cancels them on notifications/cancelled', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        mkdirSync(join(tempDir, 'src'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'cart.rb'), 'class Cart\n  def add(price)\n  end\nend\n', 'utf8');
+        await writeFile(join(tempDir, 'src', 'order.rb'), 'class Order\nend\n', 'utf8');
+        // A provider that never answers.
+        await writeFile(join(tempDir, 'stuck-provider.mjs'), "process.stdin.resume();\nsetInterval(() => {}, 1000);\n", 'utf8');
+        mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+            providers: { executors: { claude: { command: 'node', args: [join(tempDir, 'stuck-provider.mjs')] } } },
+        }, null, 2)}\n`, 'utf8');
+        const runtimeService = createSharedRuntimeService({ basePath: tempDir });
+        await runtimeService.registerAgent({ agentId: 'planner', name: 'Planner', capabilities: ['planning'] });
+        const input = new PassThrough();
+        let buffered = '';
+        const output = new Writable({
+            write(chunk, _enc, cb) {
+                buffered += chunk.toString();
+                cb();
+            },
+        });
+        const messages = () => buffered.trim().split('\n').filter((line) => line.length > 0).map((line) => JSON.parse(line));
+        const waitFor = async (match) => {
+            for (let attempt = 0; attempt < 200; attempt += 1) {
+                const found = messages().find(match);
+                if (found !== undefined) {
+                    return found;
+                }
+                await new Promise((resolve) => setTimeout(resolve, 10));
+            }
+            throw new Error(`No matching message in: ${buffered}`);
+        };
+        const send = (message) => input.write(`${JSON.stringify({ jsonrpc: '2.0', ...message })}\n`);
+        const progress = (token) => messages()
+            .filter((message) => message.method === 'notifications/progress' && message.params.progressToken === token)
+            .map((message) => message.params);
+        const server = createMcpStdioServer({ runtimeService, basePath: tempDir, input, output });
+        const served = server.serve();
+        send({ id: 1, method: 'initialize' });
+        await waitFor((message) => message.id === 1);
+        send({ id: 2, method: 'tools/call', params: { name: 'code.index', arguments: {}, _meta: { progressToken: 'index' } } });
+        expect(JSON.parse((await waitFor((message) => message.id === 2)).result.content[0].text)).toMatchObject({ filesIndexed: 2 });
+        expect(progress('index')).toEqual([
+            { progressToken: 'index', progress: 1, message: 'Indexed src/cart.rb' },
+            { progressToken: 'index', progress: 2, message: 'Indexed src/order.rb' },
+        ]);
+        send({ id: 3, method: 'tools/call', params: { name: 'agent.run', arguments: { agentId: 'planner', task: 'Plan', traceId: 'cancelled-run' }, _meta: { progressToken: 7 } } });
+        await waitFor((message) => message.method === 'notifications/progress' && message.params.progressToken === 7);
+        send({ method: 'notifications/cancelled', params: { requestId: 3, reason: 'User pressed stop' } });
+        for (let attempt = 0; attempt < 200 && (await runtimeService.getTrace('cancelled-run'))?.status === 'running'; attempt += 1) {
+            await new Promise((resolve) => setTimeout(resolve, 10));
+        }
+        send({ id: 4, method: 'ping' });
+        await waitFor((message) => message.id === 4);
+        expect(messages().some((message) => message.id === 3)).toBe(false);
+        expect(messages().filter((message) => message.method === 'notifications/progress' && message.params.progressToken === 7).map((message) => message.params)).toEqual([
+            { progressToken: 7, progress: 10, total: 100, message: 'Running agent "planner" on claude' },
+            { progressToken: 7, progress: 90, total: 100, message: 'Recording the trace' },
+        ]);
+        expect(await runtimeService.getTrace('cancelled-run')).toMatchObject({
+            status: 'failed',
+            error: { code: 'PROVIDER_CANCELLED' },
+        });
+        const audit = (await readFile(join(tempDir, '.automatosx', 'logs', 'mcp-audit.log'), 'utf8'))
+            .trim().split('\n').map((line) => JSON.parse(line));
+        expect(audit.map(({ tool, outcome }) => `${tool} ${outcome}`)).toEqual(['code.index ok', 'agent.run cancelled']);
+        // An indexing run cancelled before it finishes leaves the last index alone.
+        await writeFile(join(tempDir, 'src', 'invoice.rb'), 'class Invoice\nend\n', 'utf8');
+        const cancelled = await createMcpServerSurface({ basePath: tempDir, runtimeService })
+            .invokeTool('code.index', {}, { signal: AbortSignal.abort() });
+        expect(cancelled.success).toBe(false);
+        const index = JSON.parse(await readFile(join(tempDir, '.automatosx', 'index', 'code-index.json'), 'utf8'));
+        expect(index.files.map((entry) => entry.file)).toEqual(['src/cart.rb', 'src/order.rb']);
+        send({ id: 5, method: 'shutdown' });
+        input.end();
+        await served;
+    });
+    it('puts agents\' held actions to clients that support elicitation', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const runtimeService = createSharedRuntimeService({ basePath: tempDir });
+        // Stands in for an agent whose profile holds its shell commands for approval.
+        runtimeService.runAgent = async (request) => {
+            const approved = await request.approve?.({ action: 'shell', agentId: request.agentId, target: 'npm test' });
+            return { traceId: 'held', agentId: request.agentId, success: true, provider: 'claude', content: String(approved), latencyMs: 0, executionMode: 'simulated', warnings: [] };
+        };
+        const input = new PassThrough();
+        let buffered = '';
+        const output = new Writable({
+            write(chunk, _enc, cb) {
+                buffered += chunk.toString();
+                cb();
+            },
+        });
+        const messages = () => buffered.trim().split('\n').filter((line) => line.length > 0).map((line) => JSON.parse(line));
+        const waitFor = async (match) => {
+            for (let attempt = 0; attempt < 200; attempt += 1) {
+                const found = messages().find(match);
+                if (found !== undefined) {
+                    return found;
+                }
+                await new Promise((resolve) => setTimeout(resolve, 10));
+            }
+            throw new Error(`No matching message in: ${buffered}`);
+        };
+        const send = (message) => input.write(`${JSON.stringify({ jsonrpc: '2.0', ...message })}\n`);
+        const server = createMcpStdioServer({ runtimeService, basePath: tempDir, input, output, audit: false });
+        const served = server.serve();
+        send({ id: 1, method: 'initialize', params: { protocolVersion: '2025-06-18', capabilities: { elicitation: {} } } });
+        expect((await waitFor((message) => message.id === 1)).result.protocolVersion).toBe('2025-06-18');
+        send({ id: 2, method: 'tools/call', params: { name: 'agent.run', arguments: { agentId: 'ops', task: 'Run the tests' } } });
+        const first = await waitFor((message) => message.method === 'elicitation/create');
+        expect(first.params).toMatchObject({
+            message: 'Agent "ops" wants to run the shell command "npm test". Approve?',
+            requestedSchema: { type: 'object', required: ['approve'] },
+        });
+        send({ id: first.id, result: { action: 'accept', content: { approve: true } } });
+        expect(JSON.parse((await waitFor((message) => message.id === 2)).result.content[0].text)).toMatchObject({ content: 'true' });
+        send({ id: 3, method: 'tools/call', params: { name: 'agent.run', arguments: { agentId: 'ops', task: 'Run the tests' } } });
+        const second = await waitFor((message) => message.method === 'elicitation/create' && message.id !== first.id);
+        send({ id: second.id, result: { action: 'decline' } });
+        expect(JSON.parse((await waitFor((message) => message.id === 3)).result.content[0].text)).toMatchObject({ content: 'false' });
+        // A client without elicitation cannot be asked; only the approval policy decides.
+        const unasked = await createMcpServerSurface({ basePath: tempDir, runtimeService }).invokeTool('agent.run', { agentId: 'ops', task: 'Run the tests' });
+        expect(unasked.data).toMatchObject({ content: 'undefined' });
+        send({ id: 4, method: 'shutdown' });
+        input.end();
+        await served;
+    });
+    it('pages list results with next_cursor and streams grep matches as partial results', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        mkdirSync(join(tempDir, 'src'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'a.rb'), 'TODO one\nfine\nTODO two\n', 'utf8');
+        await writeFile(join(tempDir, 'src', 'b.rb'), 'TODO three\n', 'utf8');
+        const runtimeService = createSharedRuntimeService({ basePath: tempDir });
+        for (const key of ['alpha', 'beta', 'gamma', 'delta', 'epsilon']) {
+            await runtimeService.storeMemory({ namespace: 'paged', key, value: { key } });
+        }
+        const input = new PassThrough();
+        let buffered = '';
+        const output = new Writable({
+            write(chunk, _enc, cb) {
+                buffered += chunk.toString();
+                cb();
+            },
+        });
+        const messages = () => buffered.trim().split('\n').filter((line) => line.length > 0).map((line) => JSON.parse(line));
+        let nextId = 0;
+        const call = async (method, params) => {
+            const id = ++nextId;
+            input.write(`${JSON.stringify({ jsonrpc: '2.0', id, method, params })}\n`);
+            for (let attempt = 0; attempt < 200; attempt += 1) {
+                const found = messages().find((message) => message.id === id);
+                if (found !== undefined) {
+                    return found;
+                }
+                await new Promise((resolve) => setTimeout(resolve, 10));
+            }
+            throw new Error(`No response to ${method} in: ${buffered}`);
+        };
+        const server = createMcpStdioServer({ runtimeService, basePath: tempDir, input, output });
+        const served = server.serve();
+        await call('initialize');
+        const first = await call('tools/call', { name: 'memory.list', arguments: { namespace: 'paged', pageSize: 2 } });
+        expect(first.result.content).toHaveLength(2);
+        const firstKeys = JSON.parse(first.result.content[0].text).map((entry) => entry.key);
+        const { next_cursor: cursor, total } = JSON.parse(first.result.content[1].text);
+        expect(firstKeys).toHaveLength(2);
+        expect(total).toBe(5);
+        const second = await call('tools/call', { name: 'memory.list', arguments: { namespace: 'paged', cursor, pageSize: 3 } });
+        expect(second.result.content).toHaveLength(1);
+        const secondKeys = JSON.parse(second.result.content[0].text).map((entry) => entry.key);
+        expect([...firstKeys, ...secondKeys].sort()).toEqual(['alpha', 'beta', 'delta', 'epsilon', 'gamma']);
+        // A cursor only pages the query it came from.
+        const replayed = await call('tools/call', { name: 'memory.list', arguments: { namespace: 'other', cursor } });
+        expect(replayed.error).toMatchObject({ code: -32602, message: expect.stringContaining('Invalid cursor') });
+        expect((await call('tools/call', { name: 'memory.list', arguments: { namespace: 'paged', cursor: 'garbage' } })).error.code).toBe(-32602);
+        const grep = await call('tools/call', { name: 'code.grep', arguments: { pattern: 'TODO', pageSize: 2 }, _meta: { partialResults: true } });
+        const partials = messages()
+            .filter((message) => message.method === 'notifications/tools/partial_result')
+            .map((message) => ({ requestId: message.params.requestId, files: message.params.items.map((match) => `${match.file}:${match.line}`) }));
+        expect(partials).toEqual([
+            { requestId: grep.id, files: ['src/a.rb:1', 'src/a.rb:3'] },
+            { requestId: grep.id, files: ['src/b.rb:1'] },
+        ]);
+        expect(JSON.parse(grep.result.content[0].text)).toMatchObject({ filesScanned: 2, truncated: false, matches: [{ line: 1 }, { line: 3 }] });
+        const grepCursor = JSON.parse(grep.result.content[1].text).next_cursor;
+        const rest = await call('tools/call', { name: 'code.grep', arguments: { pattern: 'TODO', pageSize: 2, cursor: grepCursor } });
+        expect(JSON.parse(rest.result.content[0].text).matches).toEqual([{ file: 'src/b.rb', line: 1, column: 1, text: 'TODO three' }]);
+        await call('shutdown');
+        input.end();
+        await served;
+    });
+    it('runs the tests, applies an agent fix for the failures and runs them again', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        mkdirSync(join(tempDir, 'src'), { recursive: true });
+        mkdirSync(join(tempDir, 'test'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'cart.rb'), 'class Cart\n  def total(price, quantity)\n    price + quantity\n  end\nend\n', 'utf8');
+        await writeFile(join(tempDir, 'test', 'cart_test.rb'), [
+            "require_relative '../src/cart'",
+            'class CartTest < Minitest::Test',
+            '  def test_total',
+            '    assert_equal 6, Cart.new.total(2, 3)',
+            '  end',
+            'end',
+            '',
+        ].join('\n'), 'utf8');
+        // Reports in pytest's format until the cart multiplies.
+        await writeFile(join(tempDir, 'run-tests.mjs'), [
+            "import { readFileSync } from 'node:fs';",
+            "if (readFileSync('src/cart.rb', 'utf8').includes('price * quantity')) { console.log('1 passed'); process.exit(0); }",
+            "console.log('_____ CartTest.test_total _____');",
+            "console.log('test/cart_test.rb:4: AssertionError');",
+            "console.log('FAILED test/cart_test.rb::CartTest::test_total - assert 5 == 6');",
+            'process.exit(1);',
+        ].join('\n'), 'utf8');
+        await writeFile(join(tempDir, 'fixer-provider.mjs'), [
+            "import { writeFileSync } from 'node:fs';",
+            "let input = '';",
+            "process.stdin.on('data', (chunk) => { input += chunk; });",
+            "process.stdin.on('end', () => {",
+            "  writeFileSync('prompt.txt', JSON.parse(input).prompt);",
+            '  const content = [',
+            "    'The total adds instead of multiplying.',",
+            "    'FILE: src/cart.rb', '<<<<<<< SEARCH', '    price + quantity', '=======', '    price * quantity', '>>>>>>> REPLACE',",
+            "    'FILE: ../outside.rb', '<<<<<<< SEARCH', '=======', 'puts 1', '>>>>>>> REPLACE',",
+            "  ].join('\\n');",
+            "  process.stdout.write(JSON.stringify({ success: true, provider: 'claude', content }));",
+            '});',
+        ].join('\n'), 'utf8');
+        mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+            providers: { executors: { claude: { command: 'node', args: [join(tempDir, 'fixer-provider.mjs')] } } },
+            testing: { command: ['node', 'run-tests.mjs'] },
+        }, null, 2)}\n`, 'utf8');
+        const runtimeService = createSharedRuntimeService({ basePath: tempDir });
+        await runtimeService.registerAgent({ agentId: 'bug-hunter', name: 'Bug Hunter', capabilities: ['debugging'] });
+        await runtimeService.indexCode();
+        const surface = createMcpServerSurface({ basePath: tempDir, runtimeService });
+        const stages = [];
+        const result = await surface.invokeTool('test.and_fix', {}, { onProgress: (_progress, _total, message) => stages.push(message) });
+        expect(result.success).toBe(true);
+        expect(result.data).toMatchObject({
+            success: true,
+            stopReason: 'passed',
+            command: ['node', 'run-tests.mjs'],
+            initialFailures: [{ framework: 'pytest', name: 'CartTest.test_total', file: 'test/cart_test.rb', line: 4, message: 'assert 5 == 6' }],
+            remainingFailures: [],
+            filesChanged: ['src/cart.rb'],
+            iterations: [{
+                iteration: 1,
+                passedAfter: true,
+                edits: [
+                    { file: 'src/cart.rb', applied: true },
+                    { file: '../outside.rb', applied: false, reason: 'Outside the workspace, or inside .git.' },
+                ],
+            }],
+        });
+        expect(await readFile(join(tempDir, 'src', 'cart.rb'), 'utf8')).toContain('price * quantity');
+        expect(stages).toEqual(['Running tests', 'Asking bug-hunter to fix 1 failing test (attempt 1 of 3)', 'Running tests again']);
+        const prompt = await readFile(join(tempDir, 'prompt.txt'), 'utf8');
+        expect(prompt).toContain('- test/cart_test.rb:4 CartTest.test_total\n  assert 5 == 6');
+        expect(prompt).toContain('test/cart_test.rb:3 method test_total\n```\n  def test_total\n    assert_equal 6, Cart.new.total(2, 3)\n  end\n```');
+        expect(prompt).toContain('src/cart.rb:2 method Cart::total\n```\n  def total(price, quantity)\n    price + quantity\n  end\n```');
+        const readOnly = createMcpServerSurface({ basePath: tempDir, runtimeService, readOnly: true });
+        expect((await readOnly.invokeTool('test.and_fix', {})).error).toBe('test.and_fix is disabled in read-only mode.');
+    });
+    it('runs an agent inside a workspace scope with attached files', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        mkdirSync(join(tempDir, 'packages', 'billing', 'src'), { recursive: true });
+        mkdirSync(join(tempDir, 'packages', 'shipping'), { recursive: true });
+        await writeFile(join(tempDir, 'packages', 'billing', 'src', 'invoice.rb'), 'class Invoice\nend\n', 'utf8');
+        await writeFile(join(tempDir, 'packages', 'shipping', 'rate.rb'), 'class Rate\nend\n', 'utf8');
+        symlinkSync(join(tempDir, 'packages', 'shipping', 'rate.rb'), join(tempDir, 'packages', 'billing', 'rate.rb'));
+        await writeFile(join(tempDir, 'echo-provider.mjs'), [
+            "let input = '';",
+            "process.stdin.on('data', (chunk) => { input += chunk; });",
+            "process.stdin.on('end', () => {",
+            '  const { prompt, scope } = JSON.parse(input);',
+            "  process.stdout.write(JSON.stringify({ success: true, provider: 'claude', content: JSON.stringify({ cwd: process.cwd(), scope, prompt }) }));",
+            '});',
+        ].join('\n'), 'utf8');
+        mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+            providers: { executors: { claude: { command: 'node', args: [join(tempDir, 'echo-provider.mjs')] } } },
+        }, null, 2)}\n`, 'utf8');
+        const runtimeService = createSharedRuntimeService({ basePath: tempDir });
+        await runtimeService.registerAgent({ agentId: 'billing-dev', name: 'Billing Developer', capabilities: ['ruby'] });
+        const surface = createMcpServerSurface({ basePath: tempDir, runtimeService });
+        const scoped = await surface.invokeTool('agent.run', {
+            agentId: 'billing-dev',
+            task: 'Add a due date to invoices',
+            scope: 'packages/billing/',
+            files: ['packages/billing/src/invoice.rb'],
+        });
+        expect(scoped.data).toMatchObject({ success: true });
+        const echoed = JSON.parse(scoped.data.content);
+        expect(realpathSync(echoed.cwd)).toBe(realpathSync(join(tempDir, 'packages', 'billing')));
+        expect(echoed.scope).toBe('packages/billing');
+        expect(echoed.prompt).toContain('Workspace scope: packages/billing/\nYou are running in this directory.');
+        expect(echoed.prompt).toContain('Attached files, with paths from the workspace root:\n\npackages/billing/src/invoice.rb\n```rb\nclass Invoice\nend\n```');
+        const outside = await surface.invokeTool('agent.run', {
+            agentId: 'billing-dev',
+            task: 'Change shipping rates',
+            scope: 'packages/billing',
+            files: ['packages/shipping/rate.rb'],
+        });
+        expect(outside.data).toMatchObject({
+            success: false,
+            error: { code: 'AGENT_ATTACHMENT_INVALID', message: 'Attached file "packages/shipping/rate.rb" is outside the scope "packages/billing" or does not exist.' },
+        });
+        // The link sits inside the scope but points out of it.
+        const linked = await surface.invokeTool('agent.run', { agentId: 'billing-dev', scope: 'packages/billing', files: ['packages/billing/rate.rb'] });
+        expect(linked.data).toMatchObject({ success: false, error: { code: 'AGENT_ATTACHMENT_INVALID' } });
+        const escaped = await surface.invokeTool('agent.run', { agentId: 'billing-dev', scope: '../' });
+        expect(escaped.data).toMatchObject({
+            success: false,
+            error: { code: 'AGENT_SCOPE_INVALID', message: 'Scope "../" is not a directory inside the workspace.' },
+        });
+        const trace = await surface.invokeTool('trace.get', { traceId: escaped.data.traceId });
+        expect(trace.data).toMatchObject({ status: 'failed', input: { agentId: 'billing-dev', scope: '../' } });
+    });
+    it('reviews each symbol changed between two refs and returns the findings as annotations', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await initializeGitRepo(tempDir);
+        mkdirSync(join(tempDir, 'src'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'cart.rb'), 'class Cart\n  def total(price, quantity)\n    price + quantity\n  end\n\n  def count\n    1\n  end\nend\n', 'utf8');
+        await execFileAsync('git', ['add', '.'], { cwd: tempDir });
+        await execFileAsync('git', ['commit', '-m', 'cart'], { cwd: tempDir });
+        await execFileAsync('git', ['checkout', '-b', 'feature/discount'], { cwd: tempDir });
+        await writeFile(join(tempDir, 'src', 'cart.rb'), [
+            'class Cart',
+            '  def total(price, quantity)',
+            '    price * quantity',
+            '  end',
+            '',
+            '  def count',
+            '    1',
+            '  end',
+            '',
+            '  def discount(amount)',
+            '    amount * 0.9',
+            '  end',
+            'end',
+            '',
+        ].join('\n'), 'utf8');
+        await writeFile(join(tempDir, 'src', 'tax.rb'), 'class Tax\n  def rate\n    0.2\n  end\nend\n', 'utf8');
+        await writeFile(join(tempDir, 'tracked.txt'), 'baseline\nchanged\n', 'utf8');
+        await execFileAsync('git', ['add', '.'], { cwd: tempDir });
+        await execFileAsync('git', ['commit', '-m', 'discount'], { cwd: tempDir });
+        // Left uncommitted: the review reads files as they are at head.
+        await writeFile(join(tempDir, 'src', 'tax.rb'), 'class Tax\nend\n', 'utf8');
+        await writeFile(join(tempDir, 'reviewer-provider.mjs'), [
+            "import { appendFileSync } from 'node:fs';",
+            "let input = '';",
+            "process.stdin.on('data', (chunk) => { input += chunk; });",
+            "process.stdin.on('end', () => {",
+            '  const prompt = JSON.parse(input).prompt;',
+            "  appendFileSync('prompts.txt', `${prompt}\\n=====\\n`);",
+            "  const content = prompt.includes('the method Cart::total ')",
+            `    ? '\`\`\`json\\n[{"line": 99, "severity": "high", "message": "Multiplying drops the old addition."}]\\n\`\`\`'`,
+            "    : prompt.includes('the method Cart::discount ')",
+            `      ? '[{"line": 11, "severity": "note", "message": "Name the 0.9."}, {"severity": "warning"}]'`,
+            "      : prompt.includes('the class Tax ') ? 'Looks fine: []' : 'No comments.';",
+            "  process.stdout.write(JSON.stringify({ success: true, provider: 'claude', content }));",
+            '});',
+        ].join('\n'), 'utf8');
+        mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+            providers: { executors: { claude: { command: 'node', args: [join(tempDir, 'reviewer-provider.mjs')] } } },
+        }, null, 2)}\n`, 'utf8');
+        const runtimeService = createSharedRuntimeService({ basePath: tempDir });
+        await runtimeService.registerAgent({ agentId: 'quality', name: 'Quality', capabilities: ['review'] });
+        const surface = createMcpServerSurface({ basePath: tempDir, runtimeService, readOnly: true });
+        const stages = [];
+        const result = await surface.invokeTool('review.and_annotate', { base: 'main', maxConcurrent: 2 }, {
+            onProgress: (_progress, _total, message) => stages.push(message),
+        });
+        expect(result.success).toBe(true);
+        expect(result.data).toMatchObject({
+            success: false,
+            base: 'main',
+            head: 'HEAD',
+            changedFiles: ['src/cart.rb', 'src/tax.rb', 'tracked.txt'],
+            reviews: [
+                { file: 'src/cart.rb', symbol: 'Cart::total', kind: 'method', status: 'modified', startLine: 2, endLine: 4, findings: 1 },
+                { file: 'src/cart.rb', symbol: 'Cart::discount', kind: 'method', status: 'added', startLine: 10, endLine: 12, findings: 1 },
+                { file: 'src/tax.rb', symbol: 'Tax', kind: 'class', status: 'added', startLine: 1, endLine: 5, findings: 0 },
+                { file: 'tracked.txt', status: 'modified', startLine: 2, endLine: 2, findings: 0, error: { code: 'INVALID_REVIEW_REPLY' } },
+            ],
+            annotations: [
+                { path: 'src/cart.rb', span: { startLine: 4, endLine: 4 }, severity: 'critical', message: 'Multiplying drops the old addition.', symbol: 'Cart::total' },
+                { path: 'src/cart.rb', span: { startLine: 11, endLine: 11 }, severity: 'note', message: 'Name the 0.9.', symbol: 'Cart::discount' },
+            ],
+            summary: { critical: 1, warning: 0, note: 1 },
+            skipped: 0,
+        });
+        expect(stages).toEqual([
+            'Diffing main...HEAD',
+            'Reviewing Cart::total, Cart::discount',
+            'Reviewing Tax, tracked.txt:2',
+            'Reviewed 4 changed symbol(s)',
+        ]);
+        const prompts = (await readFile(join(tempDir, 'prompts.txt'), 'utf8')).split('\n=====\n');
+        const totalPrompt = prompts.find((prompt) => prompt.includes('the method Cart::total '));
+        expect(totalPrompt).toContain('```diff\n@@ -3 +3 @@ class Cart\n-    price + quantity\n+    price * quantity\n```');
+        expect(totalPrompt).toContain('```\n2|   def total(price, quantity)\n3|     price * quantity\n4|   end\n```');
+        expect(prompts.find((prompt) => prompt.includes('the class Tax '))).toContain('1| class Tax\n2|   def rate');
+        const limited = await surface.invokeTool('review.and_annotate', { base: 'main', maxSymbols: 1 });
+        expect(limited.data).toMatchObject({ success: true, reviews: [{ symbol: 'Cart::total' }], skipped: 3 });
+    });
     it('rate limits expensive MCP requests and supports shutdown', async () => {
         const tempDir = createTempDir();
         tempDirs.push(tempDir);
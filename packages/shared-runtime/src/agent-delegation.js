@@ -0,0 +1,117 @@
+import { asRecord } from './records.js';
+import { validateJsonSchema } from './structured-output.js';
+/** How many agents deep a chain of handoffs may go, the first delegating agent included. */
+export const MAX_DELEGATION_DEPTH = 3;
+/**
+ * Reads a contract from JSON, as given to `ax agent delegate --contract` or in a profile's
+ * `handoffs`. Throws an error with the `DELEGATION_CONTRACT_INVALID` code when it has no
+ * output schema or a field has the wrong type.
+ */
+export function parseHandoffContract(value, where = 'The contract') {
+    const entry = asRecord(value) ?? {};
+    if (typeof entry.output !== 'object' || entry.output === null || Array.isArray(entry.output)) {
+        throw contractError(`${where} needs an "output" JSON Schema object.`);
+    }
+    if (entry.input !== undefined && (typeof entry.input !== 'object' || entry.input === null || Array.isArray(entry.input))) {
+        throw contractError(`${where} has an "input" that is not a JSON Schema object.`);
+    }
+    if (entry.provider !== undefined && typeof entry.provider !== 'string') {
+        throw contractError(`${where} has a "provider" that is not a string.`);
+    }
+    const budget = asRecord(entry.budget) ?? {};
+    for (const field of ['maxTokens', 'timeoutMs']) {
+        const limit = budget[field];
+        if (limit !== undefined && !(typeof limit === 'number' && Number.isInteger(limit) && limit > 0)) {
+            throw contractError(`${where} has a budget "${field}" that is not a positive integer.`);
+        }
+    }
+    return {
+        ...(entry.input !== undefined ? { input: asRecord(entry.input) ?? {} } : {}),
+        output: asRecord(entry.output) ?? {},
+        ...(budget.maxTokens !== undefined || budget.timeoutMs !== undefined ? {
+            budget: {
+                ...(typeof budget.maxTokens === 'number' ? { maxTokens: budget.maxTokens } : {}),
+                ...(typeof budget.timeoutMs === 'number' ? { timeoutMs: budget.timeoutMs } : {}),
+            },
+        } : {}),
+        ...(typeof entry.provider === 'string' ? { provider: entry.provider } : {}),
+    };
+}
+/** The `handoffs` of an agent profile's metadata; entries without an agent id or output schema are skipped. */
+export function readAgentHandoffs(metadata) {
+    if (!Array.isArray(metadata.handoffs)) {
+        return [];
+    }
+    return metadata.handoffs.flatMap((value) => {
+        const entry = asRecord(value) ?? {};
+        if (typeof entry.agentId !== 'string' || entry.agentId.length === 0) {
+            return [];
+        }
+        try {
+            return [{
+                agentId: entry.agentId,
+                ...(typeof entry.description === 'string' ? { description: entry.description } : {}),
+                ...parseHandoffContract(entry),
+            }];
+        }
+        catch {
+            return [];
+        }
+    });
+}
+/** Where `input` breaks the contract's input schema; empty when it fits. */
+export function validateHandoffInput(contract, input) {
+    return contract.input === undefined ? [] : validateJsonSchema(input ?? {}, contract.input);
+}
+/** Why a handoff from `chain` to `agentId` may not happen, or undefined when it may. */
+export function checkDelegationChain(chain, agentId) {
+    if (chain.includes(agentId)) {
+        return {
+            code: 'DELEGATION_CIRCULAR',
+            message: `Delegating to "${agentId}" would loop: ${[...chain, agentId].join(' > ')}.`,
+        };
+    }
+    if (chain.length >= MAX_DELEGATION_DEPTH) {
+        return {
+            code: 'DELEGATION_DEPTH_EXCEEDED',
+            message: `Delegating to "${agentId}" would go deeper than ${MAX_DELEGATION_DEPTH} agents: ${[...chain, agentId].join(' > ')}.`,
+        };
+    }
+    return undefined;
+}
+/**
+ * One `delegate_<agent>` tool per handoff of a running agent. The tool takes the sub-task and its
+ * input, has `delegate` run it, and returns the result as JSON; a handoff that fails is an error
+ * result, which the model sees and can work around.
+ */
+export function createHandoffTools(handoffs, delegate) {
+    return handoffs.map((handoff) => ({
+        name: `delegate_${handoff.agentId.replace(/[^A-Za-z0-9_-]/g, '_')}`,
+        description: [
+            `Hands a sub-task to the agent "${handoff.agentId}" and returns its result as JSON fitting ${JSON.stringify(handoff.output)}.`,
+            ...(handoff.description !== undefined ? [handoff.description] : []),
+        ].join(' '),
+        inputSchema: {
+            type: 'object',
+            properties: {
+                task: { type: 'string', description: 'What the agent should do.' },
+                input: handoff.input ?? { type: 'object' },
+            },
+            required: handoff.input === undefined ? ['task'] : ['task', 'input'],
+        },
+        async run(input) {
+            const task = typeof input.task === 'string' ? input.task.trim() : '';
+            if (task.length === 0) {
+                throw new Error('The delegated task is empty.');
+            }
+            const result = await delegate(handoff, task, input.input === undefined ? undefined : asRecord(input.input) ?? {});
+            if (result.error !== undefined) {
+                throw new Error(`${result.error.code}: ${result.error.message}`);
+            }
+            return JSON.stringify(result.output);
+        },
+    }));
+}
+function contractError(message) {
+    return Object.assign(new Error(message), { code: 'DELEGATION_CONTRACT_INVALID' });
+}
@@ -0,0 +1,151 @@
+import type { ProviderTool } from './provider-bridge.js';
+import { asRecord } from './records.js';
+import { validateJsonSchema, type JsonSchema } from './structured-output.js';
+
+/** What a delegating agent and the agent it hands a sub-task to agree on. */
+export interface HandoffContract {
+  /** JSON Schema of the sub-task's input; unset, any object is accepted. */
+  input?: JsonSchema;
+  /** JSON Schema the result has to fit; a reply that does not is repaired or the handoff fails. */
+  output: JsonSchema;
+  budget?: HandoffBudget;
+  /** The provider the sub-task runs on; unset, the target agent's own. */
+  provider?: string;
+}
+
+export interface HandoffBudget {
+  /** Tokens the sub-task may use, repairs included; over it, the handoff fails. */
+  maxTokens?: number;
+  timeoutMs?: number;
+}
+
+/** An entry of an agent profile's `handoffs`: an agent it may delegate to, and on what terms. */
+export interface AgentHandoff extends HandoffContract {
+  agentId: string;
+  /** When to hand work over; the delegating agent sees it as the tool description. */
+  description?: string;
+}
+
+/** How many agents deep a chain of handoffs may go, the first delegating agent included. */
+export const MAX_DELEGATION_DEPTH = 3;
+
+/**
+ * Reads a contract from JSON, as given to `ax agent delegate --contract` or in a profile's
+ * `handoffs`. Throws an error with the `DELEGATION_CONTRACT_INVALID` code when it has no
+ * output schema or a field has the wrong type.
+ */
+export function parseHandoffContract(value: unknown, where = 'The contract'): HandoffContract {
+  const entry = asRecord(value) ?? {};
+  if (typeof entry.output !== 'object' || entry.output === null || Array.isArray(entry.output)) {
+    throw contractError(`${where} needs an "output" JSON Schema object.`);
+  }
+  if (entry.input !== undefined && (typeof entry.input !== 'object' || entry.input === null || Array.isArray(entry.input))) {
+    throw contractError(`${where} has an "input" that is not a JSON Schema object.`);
+  }
+  if (entry.provider !== undefined && typeof entry.provider !== 'string') {
+    throw contractError(`${where} has a "provider" that is not a string.`);
+  }
+  const budget = asRecord(entry.budget) ?? {};
+  for (const field of ['maxTokens', 'timeoutMs']) {
+    const limit = budget[field];
+    if (limit !== undefined && !(typeof limit === 'number' && Number.isInteger(limit) && limit > 0)) {
+      throw contractError(`${where} has a budget "${field}" that is not a positive integer.`);
+    }
+  }
+  return {
+    ...(entry.input !== undefined ? { input: asRecord(entry.input) ?? {} } : {}),
+    output: asRecord(entry.output) ?? {},
+    ...(budget.maxTokens !== undefined || budget.timeoutMs !== undefined ? {
+      budget: {
+        ...(typeof budget.maxTokens === 'number' ? { maxTokens: budget.maxTokens } : {}),
+        ...(typeof budget.timeoutMs === 'number' ? { timeoutMs: budget.timeoutMs } : {}),
+      },
+    } : {}),
+    ...(typeof entry.provider === 'string' ? { provider: entry.provider } : {}),
+  };
+}
+
+/** The `handoffs` of an agent profile's metadata; entries without an agent id or output schema are skipped. */
+export function readAgentHandoffs(metadata: Record<string, unknown>): AgentHandoff[] {
+  if (!Array.isArray(metadata.handoffs)) {
+    return [];
+  }
+  return metadata.handoffs.flatMap((value): AgentHandoff[] => {
+    const entry = asRecord(value) ?? {};
+    if (typeof entry.agentId !== 'string' || entry.agentId.length === 0) {
+      return [];
+    }
+    try {
+      return [{
+        agentId: entry.agentId,
+        ...(typeof entry.description === 'string' ? { description: entry.description } : {}),
+        ...parseHandoffContract(entry),
+      }];
+    } catch {
+      return [];
+    }
+  });
+}
+
+/** Where `input` breaks the contract's input schema; empty when it fits. */
+export function validateHandoffInput(contract: HandoffContract, input: Record<string, unknown> | undefined): string[] {
+  return contract.input === undefined ? [] : validateJsonSchema(input ?? {}, contract.input);
+}
+
+/** Why a handoff from `chain` to `agentId` may not happen, or undefined when it may. */
+export function checkDelegationChain(chain: string[], agentId: string): { code: string; message: string } | undefined {
+  if (chain.includes(agentId)) {
+    return {
+      code: 'DELEGATION_CIRCULAR',
+      message: `Delegating to "${agentId}" would loop: ${[...chain, agentId].join(' > ')}.`,
+    };
+  }
+  if (chain.length >= MAX_DELEGATION_DEPTH) {
+    return {
+      code: 'DELEGATION_DEPTH_EXCEEDED',
+      message: `Delegating to "${agentId}" would go deeper than ${MAX_DELEGATION_DEPTH} agents: ${[...chain, agentId].join(' > ')}.`,
+    };
+  }
+  return undefined;
+}
+
+/**
+ * One `delegate_<agent>` tool per handoff of a running agent. The tool takes the sub-task and its
+ * input, has `delegate` run it, and returns the result as JSON; a handoff that fails is an error
+ * result, which the model sees and can work around.
+ */
+export function createHandoffTools(
+  handoffs: AgentHandoff[],
+  delegate: (handoff: AgentHandoff, task: string, input: Record<string, unknown> | undefined) => Promise<{ output?: unknown; error?: { code: string; message: string } }>,
+): ProviderTool[] {
+  return handoffs.map((handoff) => ({
+    name: `delegate_${handoff.agentId.replace(/[^A-Za-z0-9_-]/g, '_')}`,
+    description: [
+      `Hands a sub-task to the agent "${handoff.agentId}" and returns its result as JSON fitting ${JSON.stringify(handoff.output)}.`,
+      ...(handoff.description !== undefined ? [handoff.description] : []),
+    ].join(' '),
+    inputSchema: {
+      type: 'object',
+      properties: {
+        task: { type: 'string', description: 'What the agent should do.' },
+        input: handoff.input ?? { type: 'object' },
+      },
+      required: handoff.input === undefined ? ['task'] : ['task', 'input'],
+    },
+    async run(input) {
+      const task = typeof input.task === 'string' ? input.task.trim() : '';
+      if (task.length === 0) {
+        throw new Error('The delegated task is empty.');
+      }
+      const result = await delegate(handoff, task, input.input === undefined ? undefined : asRecord(input.input) ?? {});
+      if (result.error !== undefined) {
+        throw new Error(`${result.error.code}: ${result.error.message}`);
+      }
+      return JSON.stringify(result.output);
+    },
+  }));
+}
+
+function contractError(message: string): Error {
+  return Object.assign(new Error(message), { code: 'DELEGATION_CONTRACT_INVALID' });
+}
@@ -0,0 +1,186 @@
+import { lstat, readdir, stat } from 'node:fs/promises';
+import { join } from 'node:path';
+import { listWorkspaceFiles, workspaceGlob } from './code-intel/module-graph.js';
+
+/** What the `permissions` of an agent profile let its runs do; a profile without them may do anything. */
+export interface AgentPermissions {
+  /** Globs of the workspace files the agent may read and change, relative to the workspace root. */
+  paths: string[];
+  /** Whether the agent may run shell commands. */
+  shell: boolean;
+  /** Whether the agent may reach the network. */
+  network: boolean;
+}
+
+/** Something an agent run did, or tried, that its permissions do not allow. */
+export interface AgentPermissionViolation {
+  kind: 'path' | 'shell' | 'network';
+  operation: 'read' | 'list' | 'write' | 'delete' | 'run' | 'connect';
+  /** The workspace file, command or host. */
+  target: string;
+}
+
+/** Modification stamps of workspace files, taken before and after a run to see what it changed. */
+export type WorkspaceSnapshot = Map<string, string>;
+
+export const AGENT_PERMISSION_DENIED_CODE = 'AGENT_PERMISSION_DENIED';
+export const AGENT_PERMISSIONS_INVALID_CODE = 'AGENT_PERMISSIONS_INVALID';
+/** Holds the permissions, as JSON, in the environment of executor commands of agents that declare them. */
+export const AGENT_PERMISSIONS_ENV_VAR = 'AUTOMATOSX_AGENT_PERMISSIONS';
+
+const PERMISSION_FIELDS = new Set(['paths', 'shell', 'network']);
+const MAX_SNAPSHOT_FILES = 20_000;
+const MAX_AUDIT_FILES = 200_000;
+// AutomatosX's own state, and git's, change under any run; of their directories, only what
+// decides what runs and who may call it is audited.
+const STATE_DIRS = new Set(['.git', '.automatosx']);
+const AUDITED_STATE_PATHS = new Set([
+  '.git/config',
+  '.git/hooks',
+  '.automatosx/config.json',
+  '.automatosx/config.yaml',
+  '.automatosx/config.yml',
+  '.automatosx/providers.json',
+  '.automatosx/mcp.json',
+  '.automatosx/mcp-clients.json',
+  '.automatosx/approval-policy.json',
+  '.automatosx/agents',
+  '.automatosx/abilities',
+  '.automatosx/ability-packs',
+  '.automatosx/context',
+]);
+
+/**
+ * Reads the `permissions` of an agent profile's metadata. Once a profile declares them, what it
+ * leaves out is denied: `paths` defaults to the whole workspace, `shell` and `network` to false.
+ * Throws an error with the `AGENT_PERMISSIONS_INVALID` code when a field is unknown or mistyped.
+ */
+export function readAgentPermissions(metadata: Record<string, unknown>, agentId: string): AgentPermissions | undefined {
+  const value = metadata.permissions;
+  if (value === undefined) {
+    return undefined;
+  }
+  const where = `The permissions of agent "${agentId}"`;
+  if (typeof value !== 'object' || value === null || Array.isArray(value)) {
+    throw permissionsError(`${where} have to be a mapping of paths, shell and network.`);
+  }
+  const fields = value as Record<string, unknown>;
+  const unknownField = Object.keys(fields).find((field) => !PERMISSION_FIELDS.has(field));
+  if (unknownField !== undefined) {
+    throw permissionsError(`${where} have an unknown field "${unknownField}"; fields are paths, shell and network.`);
+  }
+  const paths = typeof fields.paths === 'string' ? [fields.paths] : fields.paths ?? ['**'];
+  if (!Array.isArray(paths) || !paths.every((glob): glob is string => typeof glob === 'string' && glob.trim().length > 0)) {
+    throw permissionsError(`${where} have "paths" that are not a glob or a list of them.`);
+  }
+  for (const field of ['shell', 'network']) {
+    if (fields[field] !== undefined && typeof fields[field] !== 'boolean') {
+      throw permissionsError(`${where} have a "${field}" that is not true or false.`);
+    }
+  }
+  return { paths, shell: fields.shell === true, network: fields.network === true };
+}
+
+/** Whether the permissions let the agent read and change a workspace file; always without permissions. */
+export function permitsPath(permissions: AgentPermissions | undefined, file: string): boolean {
+  const path = file.split('\\').join('/').replace(/^\.\//, '');
+  return permissions === undefined || permissions.paths.some((glob) => workspaceGlob(glob.trim()).test(path));
+}
+
+/** The prompt section telling the agent what it may touch. */
+export function describeAgentPermissions(permissions: AgentPermissions): string {
+  return [
+    `Permissions: read and change only files matching ${permissions.paths.join(', ')}.`,
+    permissions.shell ? 'You may run shell commands.' : 'Do not run shell commands.',
+    permissions.network ? 'You may access the network.' : 'Do not access the network.',
+  ].join('\n');
+}
+
+/** One line on a violation, e.g. `write of "src/app.ts" outside the agent's paths`. */
+export function describeViolation(violation: AgentPermissionViolation): string {
+  switch (violation.kind) {
+    case 'path':
+      return `${violation.operation} of "${violation.target}" outside the agent's paths`;
+    case 'shell':
+      return `shell command "${violation.target}" without shell permission`;
+    case 'network':
+      return `connection to "${violation.target}" without network permission`;
+  }
+}
+
+/**
+ * Stamps the files of the workspace, or of `scope` in it, with their modification time and size.
+ * Ignored and build directories are left out, as in `listWorkspaceFiles`.
+ */
+export async function snapshotWorkspace(basePath: string, scope?: string): Promise<WorkspaceSnapshot> {
+  const root = scope === undefined ? basePath : join(basePath, scope);
+  const files = await listWorkspaceFiles(root, { maxFiles: MAX_SNAPSHOT_FILES });
+  const snapshot: WorkspaceSnapshot = new Map();
+  for (const file of files) {
+    const info = await stat(join(root, file)).catch(() => undefined);
+    if (info !== undefined) {
+      snapshot.set(scope === undefined ? file : `${scope}/${file}`, `${info.mtimeMs}:${info.size}`);
+    }
+  }
+  return snapshot;
+}
+
+/**
+ * Stamps every file of the workspace, whatever the scope of the run and ignored and build
+ * directories included, to see all a run changed. Of `.git` only its config and hooks are stamped,
+ * and of `.automatosx` only its configuration, agents and abilities. Symbolic links are stamped
+ * rather than followed.
+ */
+export async function snapshotWholeWorkspace(basePath: string): Promise<WorkspaceSnapshot> {
+  const snapshot: WorkspaceSnapshot = new Map();
+  const pending = [''];
+  while (pending.length > 0 && snapshot.size < MAX_AUDIT_FILES) {
+    const directory = pending.pop()!;
+    const entries = await readdir(join(basePath, directory), { withFileTypes: true }).catch(() => []);
+    for (const entry of entries) {
+      const file = directory.length === 0 ? entry.name : `${directory}/${entry.name}`;
+      if (STATE_DIRS.has(directory) && !AUDITED_STATE_PATHS.has(file)) {
+        continue;
+      }
+      if (entry.isDirectory()) {
+        pending.push(file);
+        continue;
+      }
+      const info = await lstat(join(basePath, file)).catch(() => undefined);
+      if (info !== undefined) {
+        snapshot.set(file, `${info.mtimeMs}:${info.size}`);
+      }
+    }
+  }
+  return snapshot;
+}
+
+/** The files created, changed or deleted between two snapshots. */
+export function listChangedFiles(before: WorkspaceSnapshot, after: WorkspaceSnapshot): string[] {
+  return [...new Set([...before.keys(), ...after.keys()])]
+    .filter((file) => before.get(file) !== after.get(file))
+    .sort((left, right) => left.localeCompare(right));
+}
+
+/**
+ * The files created, changed or deleted between two snapshots that the permissions do not cover.
+ * Whatever else changed the workspace while the run was in flight is counted as the run's doing.
+ */
+export function findPathViolations(before: WorkspaceSnapshot, after: WorkspaceSnapshot, permissions: AgentPermissions): AgentPermissionViolation[] {
+  const violations: AgentPermissionViolation[] = [];
+  for (const [file, stamp] of after) {
+    if (before.get(file) !== stamp && !permitsPath(permissions, file)) {
+      violations.push({ kind: 'path', operation: 'write', target: file });
+    }
+  }
+  for (const file of before.keys()) {
+    if (!after.has(file) && !permitsPath(permissions, file)) {
+      violations.push({ kind: 'path', operation: 'delete', target: file });
+    }
+  }
+  return violations;
+}
+
+function permissionsError(message: string): Error & { code: string } {
+  return Object.assign(new Error(message), { code: AGENT_PERMISSIONS_INVALID_CODE });
+}
@@ -0,0 +1,161 @@
+import { readdir, readFile } from 'node:fs/promises';
+import { join } from 'node:path';
+export const AGENT_PROFILE_INVALID_CODE = 'AGENT_PROFILE_INVALID';
+export const ABILITY_PACKS_DIR = join('.automatosx', 'ability-packs');
+const MAX_EXTENDS_DEPTH = 8;
+const PACK_NAME_PATTERN = /^(?!\.\.?$)[\w.-]+$/;
+/**
+ * Layers an agent profile over the agents its `extends` names, in order: its capabilities add to
+ * theirs, and each metadata field replaces theirs, except that mappings such as `permissions` or
+ * `variables` are merged a field at a time and `abilityPacks` add up. Then loads the ability packs.
+ * Throws an error with the `AGENT_PROFILE_INVALID` code when a base is missing, the bases form a
+ * cycle, or a pack has no matching version.
+ */
+export async function resolveAgentProfile(agent, lookup, basePath) {
+    const bases = [];
+    const layer = async (entry, chain) => {
+        const metadata = entry.metadata ?? {};
+        const names = readExtends(metadata.extends, entry.agentId);
+        if (names.length > 0 && chain.length >= MAX_EXTENDS_DEPTH) {
+            throw profileError(`Agent "${agent.agentId}" extends more than ${MAX_EXTENDS_DEPTH} levels of profiles.`);
+        }
+        let merged;
+        for (const name of names) {
+            if (chain.includes(name)) {
+                throw profileError(`Agent "${entry.agentId}" extends "${name}", which extends it back: ${[...chain, name].join(' -> ')}.`);
+            }
+            const base = await lookup(name);
+            if (base === undefined) {
+                throw profileError(`Agent "${entry.agentId}" extends "${name}", which is not registered.`);
+            }
+            const layered = await layer(base, [...chain, name]);
+            if (!bases.includes(name)) {
+                bases.push(name);
+            }
+            merged = merged === undefined ? layered : mergeAgentProfiles(merged, layered);
+        }
+        return merged === undefined ? entry : mergeAgentProfiles(merged, entry);
+    };
+    const merged = await layer(agent, [agent.agentId]);
+    const metadata = { ...merged.metadata };
+    delete metadata.extends;
+    const packs = await loadAbilityPacks(basePath, readPackRefs(metadata.abilityPacks, agent.agentId));
+    return {
+        agent: { ...agent, capabilities: merged.capabilities, metadata },
+        extends: bases,
+        ...packs,
+    };
+}
+/** `profile` over `base`; see `resolveAgentProfile`. */
+export function mergeAgentProfiles(base, profile) {
+    const metadata = { ...base.metadata };
+    for (const [field, value] of Object.entries(profile.metadata ?? {})) {
+        const under = metadata[field];
+        if (field === 'abilityPacks' && under !== undefined) {
+            metadata[field] = [...toList(under), ...toList(value)];
+        }
+        else if (isMapping(under) && isMapping(value)) {
+            metadata[field] = { ...under, ...value };
+        }
+        else {
+            metadata[field] = value;
+        }
+    }
+    return {
+        ...profile,
+        capabilities: [...new Set([...base.capabilities, ...profile.capabilities])],
+        metadata,
+    };
+}
+/**
+ * Loads ability packs from `.automatosx/ability-packs/<name>/<version>/`, where each `.md` file is
+ * an ability. A reference is `name`, for the latest version, or `name@version`, where a version
+ * such as `2` or `2.1` picks the latest `2.x` or `2.1.x`. A pack named again replaces its earlier
+ * version, and packs later in the list win abilities with the same id.
+ */
+export async function loadAbilityPacks(basePath, refs) {
+    const latest = new Map();
+    for (const ref of refs) {
+        const [name, range] = ref.split('@', 2);
+        latest.delete(name);
+        latest.set(name, range);
+    }
+    const abilityPacks = [];
+    const abilities = new Map();
+    for (const [name, range] of latest) {
+        const root = join(basePath, ABILITY_PACKS_DIR, name);
+        const version = (await listDirectories(root))
+            .filter((candidate) => range === undefined || candidate === range || candidate.startsWith(`${range}.`))
+            .sort(compareVersions)
+            .at(-1);
+        if (version === undefined) {
+            throw profileError(range === undefined
+                ? `Ability pack "${name}" has no versions in ${join(ABILITY_PACKS_DIR, name)}.`
+                : `Ability pack "${name}" has no version matching "${range}" in ${join(ABILITY_PACKS_DIR, name)}.`);
+        }
+        const files = (await readdir(join(root, version))).filter((file) => file.endsWith('.md')).sort();
+        const pack = `${name}@${version}`;
+        for (const file of files) {
+            const abilityId = file.slice(0, -'.md'.length);
+            abilities.delete(abilityId);
+            abilities.set(abilityId, { abilityId, pack, content: (await readFile(join(root, version, file), 'utf8')).trim() });
+        }
+        abilityPacks.push({ name, version, abilities: files.map((file) => file.slice(0, -'.md'.length)) });
+    }
+    return { abilityPacks, abilities: [...abilities.values()] };
+}
+/** What the profile is layered from; undefined for a profile with no bases or packs. */
+export function profileLayers(profile) {
+    return profile.extends.length === 0 && profile.abilityPacks.length === 0
+        ? undefined
+        : { extends: profile.extends, abilityPacks: profile.abilityPacks.map((pack) => `${pack.name}@${pack.version}`) };
+}
+/** The system prompt sections of the abilities a profile gets from its packs. */
+export function describePackAbilities(abilities) {
+    return abilities.length === 0
+        ? undefined
+        : abilities.map((ability) => `## ${ability.abilityId} (${ability.pack})\n${ability.content}`).join('\n\n');
+}
+function readExtends(value, agentId) {
+    const names = value === undefined ? [] : toList(value);
+    if (!names.every((name) => typeof name === 'string' && name.trim().length > 0)) {
+        throw profileError(`Agent "${agentId}" has an "extends" that is not an agent id or a list of them.`);
+    }
+    return names.map((name) => name.trim());
+}
+function readPackRefs(value, agentId) {
+    const refs = value === undefined ? [] : toList(value);
+    if (!refs.every((ref) => typeof ref === 'string' && ref.split('@', 2).every((part) => PACK_NAME_PATTERN.test(part)))) {
+        throw profileError(`Agent "${agentId}" has "abilityPacks" that are not pack references such as "rust@2".`);
+    }
+    return refs;
+}
+async function listDirectories(path) {
+    try {
+        return (await readdir(path, { withFileTypes: true })).filter((entry) => entry.isDirectory()).map((entry) => entry.name);
+    }
+    catch {
+        return [];
+    }
+}
+// Numeric parts compare as numbers, so 1.10.0 comes after 1.9.0.
+function compareVersions(left, right) {
+    const a = left.split('.');
+    const b = right.split('.');
+    for (let index = 0; index < Math.max(a.length, b.length); index += 1) {
+        const order = (a[index] ?? '').localeCompare(b[index] ?? '', undefined, { numeric: true });
+        if (order !== 0) {
+            return order;
+        }
+    }
+    return 0;
+}
+function toList(value) {
+    return Array.isArray(value) ? value : [value];
+}
+function isMapping(value) {
+    return typeof value === 'object' && value !== null && !Array.isArray(value);
+}
+function profileError(message) {
+    return Object.assign(new Error(message), { code: AGENT_PROFILE_INVALID_CODE });
+}
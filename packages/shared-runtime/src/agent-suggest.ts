@@ -0,0 +1,488 @@
+import { mkdir, readFile, writeFile } from 'node:fs/promises';
+import { basename, dirname, join } from 'node:path';
+import { readCodeIndex } from './code-intel/index.js';
+import { listWorkspaceFiles } from './code-intel/module-graph.js';
+import type { CodeSymbol } from './code-intel/types.js';
+
+export interface AgentSuggestRequest {
+  basePath: string;
+  /** Only report the profiles; write nothing. */
+  dryRun?: boolean;
+}
+
+/** A framework found in the workspace, with what gave it away. */
+export interface DetectedFramework {
+  id: string;
+  evidence: string[];
+}
+
+/** A generated agent profile, in the shape `ax agent register` takes. */
+export interface SuggestedAgent {
+  agentId: string;
+  name: string;
+  capabilities: string[];
+  metadata: Record<string, unknown>;
+  /** The profile file, relative to the workspace. */
+  file: string;
+  /** `written`, `exists` when a file was already there and is kept, or `planned` on a dry run. */
+  status: 'written' | 'exists' | 'planned';
+}
+
+export interface AgentSuggestResponse {
+  /** Indexed files by language. */
+  languages: Record<string, number>;
+  frameworks: DetectedFramework[];
+  agents: SuggestedAgent[];
+}
+
+type Ecosystem = 'rust' | 'node' | 'python' | 'ruby' | 'php' | 'dotnet' | 'go' | 'terraform' | 'docker';
+
+interface FrameworkSignature {
+  id: string;
+  ecosystem: Ecosystem;
+  /** Manifest dependencies that give the framework away. */
+  packages?: string[];
+  /** Whether an indexed symbol gives the framework away. */
+  symbol?: (symbol: CodeSymbol) => boolean;
+  /** Frameworks this one builds on, whose agents it replaces, as Next.js does React. */
+  replaces?: string[];
+  agentId: string;
+  name: string;
+  capabilities: string[];
+  focus: string;
+}
+
+export const SUGGESTED_AGENTS_DIR = join('.automatosx', 'agents');
+
+// The indexed languages of each ecosystem; an ecosystem's framework agent covers them.
+const ECOSYSTEM_LANGUAGES: Record<Ecosystem, string[]> = {
+  rust: ['rust'],
+  node: ['typescript', 'javascript', 'tsx', 'jsx'],
+  python: ['python'],
+  ruby: ['ruby'],
+  php: ['php'],
+  dotnet: ['csharp'],
+  go: ['go'],
+  terraform: ['hcl'],
+  docker: ['dockerfile'],
+};
+
+const MANIFESTS: Record<string, Ecosystem> = {
+  'Cargo.toml': 'rust',
+  'package.json': 'node',
+  'pyproject.toml': 'python',
+  'requirements.txt': 'python',
+  Gemfile: 'ruby',
+  'composer.json': 'php',
+  'go.mod': 'go',
+};
+const MAX_MANIFESTS = 200;
+
+const FRAMEWORKS: FrameworkSignature[] = [
+  {
+    id: 'axum',
+    ecosystem: 'rust',
+    packages: ['axum'],
+    agentId: 'axum-backend',
+    name: 'Axum Backend Engineer',
+    capabilities: ['rust', 'axum', 'http-api', 'backend'],
+    focus: 'Build HTTP handlers, extractors and tower middleware with axum. Keep handlers thin, share state through `State`, and return errors that implement `IntoResponse`.',
+  },
+  {
+    id: 'actix-web',
+    ecosystem: 'rust',
+    packages: ['actix-web'],
+    agentId: 'actix-backend',
+    name: 'Actix Web Backend Engineer',
+    capabilities: ['rust', 'actix-web', 'http-api', 'backend'],
+    focus: 'Build actix-web services, extractors and middleware. Keep blocking work off the worker threads and map errors through `ResponseError`.',
+  },
+  {
+    id: 'rocket',
+    ecosystem: 'rust',
+    packages: ['rocket'],
+    symbol: (symbol) => (symbol.annotations ?? []).some((annotation) => annotation.name === 'launch' || annotation.name === 'rocket::launch'),
+    agentId: 'rocket-backend',
+    name: 'Rocket Backend Engineer',
+    capabilities: ['rust', 'rocket', 'http-api', 'backend'],
+    focus: 'Build Rocket routes, guards and fairings. Keep request guards small and validate input through `FromForm` and `FromData`.',
+  },
+  {
+    id: 'clap',
+    ecosystem: 'rust',
+    packages: ['clap'],
+    agentId: 'rust-cli',
+    name: 'Rust CLI Engineer',
+    capabilities: ['rust', 'cli', 'clap'],
+    focus: 'Build command-line tools with clap. Keep argument parsing declarative, exit codes meaningful, and errors readable.',
+  },
+  {
+    id: 'react',
+    ecosystem: 'node',
+    packages: ['react'],
+    agentId: 'react-frontend',
+    name: 'React Frontend Engineer',
+    capabilities: ['typescript', 'react', 'frontend', 'ui'],
+    focus: 'Build React components and hooks. Keep state close to where it is used, effects free of stale closures, and components accessible.',
+  },
+  {
+    id: 'next',
+    ecosystem: 'node',
+    packages: ['next'],
+    replaces: ['react'],
+    agentId: 'nextjs-app',
+    name: 'Next.js Engineer',
+    capabilities: ['typescript', 'nextjs', 'react', 'frontend', 'fullstack'],
+    focus: 'Build Next.js pages, layouts and route handlers. Keep server and client components apart and data fetching on the server where it can be.',
+  },
+  {
+    id: 'vue',
+    ecosystem: 'node',
+    packages: ['vue'],
+    agentId: 'vue-frontend',
+    name: 'Vue Frontend Engineer',
+    capabilities: ['typescript', 'vue', 'frontend', 'ui'],
+    focus: 'Build Vue components with the composition API. Keep reactive state minimal and props typed.',
+  },
+  {
+    id: 'express',
+    ecosystem: 'node',
+    packages: ['express'],
+    agentId: 'express-api',
+    name: 'Express API Engineer',
+    capabilities: ['typescript', 'express', 'http-api', 'backend'],
+    focus: 'Build Express routes and middleware. Validate input at the edge, pass errors to `next`, and keep handlers free of business logic.',
+  },
+  {
+    id: 'fastify',
+    ecosystem: 'node',
+    packages: ['fastify'],
+    agentId: 'fastify-api',
+    name: 'Fastify API Engineer',
+    capabilities: ['typescript', 'fastify', 'http-api', 'backend'],
+    focus: 'Build Fastify routes and plugins with JSON Schema validation of requests and replies.',
+  },
+  {
+    id: 'nestjs',
+    ecosystem: 'node',
+    packages: ['@nestjs/core'],
+    replaces: ['express', 'fastify'],
+    agentId: 'nestjs-api',
+    name: 'NestJS API Engineer',
+    capabilities: ['typescript', 'nestjs', 'http-api', 'backend'],
+    focus: 'Build NestJS modules, controllers and providers. Keep dependencies injected and DTOs validated.',
+  },
+  {
+    id: 'django',
+    ecosystem: 'python',
+    packages: ['django'],
+    agentId: 'django-app',
+    name: 'Django Engineer',
+    capabilities: ['python', 'django', 'backend', 'orm'],
+    focus: 'Build Django models, views and migrations. Keep queries free of N+1 lookups and migrations reversible.',
+  },
+  {
+    id: 'fastapi',
+    ecosystem: 'python',
+    packages: ['fastapi'],
+    agentId: 'fastapi-backend',
+    name: 'FastAPI Backend Engineer',
+    capabilities: ['python', 'fastapi', 'http-api', 'backend'],
+    focus: 'Build FastAPI routes with typed Pydantic models and dependencies. Keep blocking calls out of async routes.',
+  },
+  {
+    id: 'flask',
+    ecosystem: 'python',
+    packages: ['flask'],
+    agentId: 'flask-backend',
+    name: 'Flask Backend Engineer',
+    capabilities: ['python', 'flask', 'http-api', 'backend'],
+    focus: 'Build Flask blueprints and views. Validate request data and keep application state out of module globals.',
+  },
+  {
+    id: 'rails',
+    ecosystem: 'ruby',
+    packages: ['rails'],
+    symbol: (symbol) => ['ApplicationController', 'ApplicationRecord', 'ActiveRecord::Base', 'ActionController::Base'].includes(symbol.attributes?.superclass ?? ''),
+    agentId: 'rails-app',
+    name: 'Rails Engineer',
+    capabilities: ['ruby', 'rails', 'backend', 'orm'],
+    focus: 'Build Rails models, controllers and migrations the Rails way. Keep controllers thin, callbacks few, and queries free of N+1 lookups.',
+  },
+  {
+    id: 'sinatra',
+    ecosystem: 'ruby',
+    packages: ['sinatra'],
+    symbol: (symbol) => symbol.attributes?.superclass === 'Sinatra::Base',
+    agentId: 'sinatra-app',
+    name: 'Sinatra Engineer',
+    capabilities: ['ruby', 'sinatra', 'http-api', 'backend'],
+    focus: 'Build Sinatra routes and helpers. Keep routes small and move logic into plain Ruby objects.',
+  },
+  {
+    id: 'laravel',
+    ecosystem: 'php',
+    packages: ['laravel/framework'],
+    symbol: (symbol) => /(?:^|\\)(?:Illuminate\\Database\\Eloquent\\)?Model$/.test(symbol.attributes?.extends ?? '') && symbol.file.startsWith('app/'),
+    agentId: 'laravel-app',
+    name: 'Laravel Engineer',
+    capabilities: ['php', 'laravel', 'backend', 'orm'],
+    focus: 'Build Laravel controllers, Eloquent models and migrations. Validate with form requests and keep queries eager-loaded.',
+  },
+  {
+    id: 'symfony',
+    ecosystem: 'php',
+    packages: ['symfony/framework-bundle'],
+    symbol: (symbol) => /(?:^|\\)AbstractController$/.test(symbol.attributes?.extends ?? ''),
+    agentId: 'symfony-app',
+    name: 'Symfony Engineer',
+    capabilities: ['php', 'symfony', 'backend'],
+    focus: 'Build Symfony controllers and services. Keep services wired through the container and configuration explicit.',
+  },
+  {
+    id: 'aspnetcore',
+    ecosystem: 'dotnet',
+    packages: ['Microsoft.NET.Sdk.Web', 'Microsoft.AspNetCore.App'],
+    symbol: (symbol) => /\b(?:ControllerBase|Controller)\b/.test(symbol.attributes?.bases ?? ''),
+    agentId: 'aspnet-api',
+    name: 'ASP.NET Core API Engineer',
+    capabilities: ['csharp', 'aspnetcore', 'http-api', 'backend'],
+    focus: 'Build ASP.NET Core controllers and minimal APIs. Keep dependencies injected, async all the way down, and models validated.',
+  },
+  {
+    id: 'gin',
+    ecosystem: 'go',
+    packages: ['github.com/gin-gonic/gin'],
+    agentId: 'gin-backend',
+    name: 'Gin Backend Engineer',
+    capabilities: ['go', 'gin', 'http-api', 'backend'],
+    focus: 'Build Gin handlers and middleware. Bind and validate requests, and pass contexts down to every call that can block.',
+  },
+  {
+    id: 'terraform',
+    ecosystem: 'terraform',
+    symbol: (symbol) => symbol.language === 'hcl' && (symbol.kind === 'resource' || symbol.kind === 'module'),
+    agentId: 'terraform-infra',
+    name: 'Terraform Infrastructure Engineer',
+    capabilities: ['terraform', 'infrastructure', 'iac'],
+    focus: 'Change Terraform modules and resources. Keep changes plan-safe, name resources consistently, and never hard-code secrets.',
+  },
+  {
+    id: 'docker',
+    ecosystem: 'docker',
+    symbol: (symbol) => symbol.language === 'dockerfile' && symbol.kind === 'stage',
+    agentId: 'container-build',
+    name: 'Container Build Engineer',
+    capabilities: ['docker', 'containers', 'build'],
+    focus: 'Change Dockerfiles and image builds. Keep stages cacheable, images small, and builds reproducible with pinned base images.',
+  },
+];
+
+/**
+ * Suggests agent profiles tailored to the workspace: one per framework found in its manifests or
+ * its indexed symbols, and one per indexed language no framework covers. The profiles are written
+ * to `.automatosx/agents/<agent-id>.json`; a file already there is kept. Needs the symbol index.
+ */
+export async function suggestAgents(request: AgentSuggestRequest): Promise<AgentSuggestResponse> {
+  const snapshot = await readCodeIndex(request.basePath);
+  if (snapshot === undefined) {
+    throw new Error('No symbol index found. Run "ax code index" first.');
+  }
+
+  const languages: Record<string, number> = {};
+  for (const entry of snapshot.files) {
+    languages[entry.language] = (languages[entry.language] ?? 0) + 1;
+  }
+  const symbols = snapshot.files.flatMap((entry) => entry.symbols);
+  const dependencies = await readManifestDependencies(request.basePath);
+
+  const found = new Map<string, DetectedFramework>();
+  for (const framework of FRAMEWORKS) {
+    const evidence = [
+      ...(framework.packages ?? []).flatMap((name) => (dependencies.get(`${framework.ecosystem}:${name}`) ?? []).map((manifest) => `${manifest} depends on ${name}`)),
+      ...describeSymbolEvidence(symbols.filter((symbol) => framework.symbol?.(symbol) === true)),
+    ];
+    if (evidence.length > 0) {
+      found.set(framework.id, { id: framework.id, evidence });
+    }
+  }
+  const replaced = new Set(FRAMEWORKS.filter((framework) => found.has(framework.id)).flatMap((framework) => framework.replaces ?? []));
+  const frameworks = FRAMEWORKS.filter((framework) => found.has(framework.id) && !replaced.has(framework.id));
+
+  const profiles = frameworks.map((framework) => buildProfile({
+    agentId: framework.agentId,
+    name: framework.name,
+    capabilities: framework.capabilities,
+    focus: framework.focus,
+    languages: ECOSYSTEM_LANGUAGES[framework.ecosystem].filter((language) => languages[language] !== undefined),
+    frameworks: [framework.id],
+    evidence: found.get(framework.id)!.evidence,
+    symbols,
+  }));
+  const covered = new Set(frameworks.flatMap((framework) => ECOSYSTEM_LANGUAGES[framework.ecosystem]));
+  for (const [language, count] of Object.entries(languages).sort(([left], [right]) => left.localeCompare(right))) {
+    if (covered.has(language)) {
+      continue;
+    }
+    profiles.push(buildProfile({
+      agentId: `${language}-engineer`,
+      name: `${language[0]!.toUpperCase()}${language.slice(1)} Engineer`,
+      capabilities: [language, 'implementation'],
+      focus: `Work on the ${language} code of this workspace, following its existing conventions.`,
+      languages: [language],
+      frameworks: [],
+      evidence: [`${count} ${language} file${count === 1 ? '' : 's'} indexed`],
+      symbols,
+    }));
+  }
+
+  const agents: SuggestedAgent[] = [];
+  for (const profile of profiles) {
+    const file = join(SUGGESTED_AGENTS_DIR, `${profile.agentId}.json`);
+    const target = join(request.basePath, file);
+    const exists = await readFile(target, 'utf8').then(() => true, () => false);
+    if (!exists && request.dryRun !== true) {
+      await mkdir(dirname(target), { recursive: true });
+      await writeFile(target, `${JSON.stringify(profile, null, 2)}\n`, 'utf8');
+    }
+    agents.push({ ...profile, file, status: exists ? 'exists' : request.dryRun === true ? 'planned' : 'written' });
+  }
+  return {
+    languages,
+    frameworks: frameworks.map((framework) => found.get(framework.id)!),
+    agents,
+  };
+}
+
+function buildProfile(options: {
+  agentId: string;
+  name: string;
+  capabilities: string[];
+  focus: string;
+  languages: string[];
+  frameworks: string[];
+  evidence: string[];
+  symbols: CodeSymbol[];
+}): Omit<SuggestedAgent, 'file' | 'status'> {
+  const own = options.symbols.filter((symbol) => options.languages.includes(symbol.language));
+  const concurrent = own.filter((symbol) => symbol.isAsync === true || (symbol.concurrency ?? []).length > 0);
+  const tests = own.filter((symbol) => symbol.isTest === true).length;
+  // Abilities are included through the prompt template, so a workspace ability file overrides them.
+  const abilities = ['code-review', ...(concurrent.length > 0 ? ['concurrency-review'] : [])];
+  return {
+    agentId: options.agentId,
+    name: options.name,
+    capabilities: options.capabilities,
+    metadata: {
+      systemPrompt: [
+        `You are {{agent.name}}. ${options.focus}`,
+        tests > 0 ? 'Add or update tests next to the code you change, as the workspace does.' : undefined,
+        ...abilities.map((ability) => `{{> ${ability}}}`),
+      ].filter((line) => line !== undefined).join('\n'),
+      languages: options.languages,
+      frameworks: options.frameworks,
+      suggestedBy: 'ax agent suggest',
+      evidence: [
+        ...options.evidence,
+        ...(concurrent.length > 0 ? [`${concurrent.length} async or concurrent function${concurrent.length === 1 ? '' : 's'}`] : []),
+        ...(tests > 0 ? [`${tests} test symbol${tests === 1 ? '' : 's'}`] : []),
+      ],
+    },
+  };
+}
+
+function describeSymbolEvidence(symbols: CodeSymbol[]): string[] {
+  if (symbols.length === 0) {
+    return [];
+  }
+  const files = [...new Set(symbols.map((symbol) => symbol.file))];
+  const shown = files.slice(0, 3).join(', ');
+  return [`${symbols.length} symbol${symbols.length === 1 ? '' : 's'} in ${shown}${files.length > 3 ? ` and ${files.length - 3} more file(s)` : ''}`];
+}
+
+/** The dependencies of the workspace's manifests, as `<ecosystem>:<name>` -> the manifests naming it. */
+async function readManifestDependencies(basePath: string): Promise<Map<string, string[]>> {
+  const manifests = await listWorkspaceFiles(basePath, {
+    maxFiles: MAX_MANIFESTS,
+    accept: (file) => MANIFESTS[basename(file)] !== undefined || file.endsWith('.csproj'),
+  });
+  const dependencies = new Map<string, string[]>();
+  for (const manifest of manifests) {
+    const ecosystem = manifest.endsWith('.csproj') ? 'dotnet' : MANIFESTS[basename(manifest)]!;
+    let content: string;
+    try {
+      content = await readFile(join(basePath, manifest), 'utf8');
+    } catch {
+      continue;
+    }
+    for (const name of parseManifest(basename(manifest), content)) {
+      const key = `${ecosystem}:${name}`;
+      dependencies.set(key, [...(dependencies.get(key) ?? []), manifest]);
+    }
+  }
+  return dependencies;
+}
+
+function parseManifest(name: string, content: string): string[] {
+  switch (name) {
+    case 'package.json':
+    case 'composer.json': {
+      let parsed: Record<string, unknown>;
+      try {
+        parsed = JSON.parse(content) as Record<string, unknown>;
+      } catch {
+        return [];
+      }
+      const sections = name === 'package.json' ? ['dependencies', 'devDependencies', 'peerDependencies'] : ['require', 'require-dev'];
+      return sections.flatMap((section) => {
+        const value = parsed[section];
+        return typeof value === 'object' && value !== null ? Object.keys(value) : [];
+      });
+    }
+    case 'Cargo.toml':
+      return parseCargoDependencies(content);
+    case 'Gemfile':
+      return [...content.matchAll(/^\s*gem\s+['"]([^'"]+)['"]/gm)].map((match) => match[1]!);
+    case 'go.mod':
+      return [...content.matchAll(/^\s*(?:require\s+)?([\w.-]+\.[\w.-]+\/[^\s]+)\s+v[\w.+-]+/gm)].map((match) => match[1]!);
+    case 'requirements.txt':
+      return [...content.matchAll(/^\s*([A-Za-z0-9][\w.-]*)/gm)].map((match) => match[1]!.toLowerCase());
+    case 'pyproject.toml':
+      return [
+        ...[...content.matchAll(/^\s*"([A-Za-z0-9][\w.-]*)[^"]*",?\s*$/gm)].map((match) => match[1]!.toLowerCase()),
+        ...sectionKeys(content, /^\[tool\.poetry\.(?:dev-)?dependencies\]$/).map((key) => key.toLowerCase()),
+      ];
+    default:
+      // A .csproj: its SDK and package references.
+      return [
+        ...[...content.matchAll(/<Project\s+Sdk="([^"]+)"/g)].map((match) => match[1]!),
+        ...[...content.matchAll(/<PackageReference\s+Include="([^"]+)"/g)].map((match) => match[1]!),
+      ];
+  }
+}
+
+function parseCargoDependencies(content: string): string[] {
+  const names = sectionKeys(content, /^\[(?:workspace\.|target\..+\.)?(?:dev-|build-)?dependencies\]$/);
+  for (const match of content.matchAll(/^\[(?:workspace\.|target\..+\.)?(?:dev-|build-)?dependencies\.([\w-]+)\]$/gm)) {
+    names.push(match[1]!);
+  }
+  return names;
+}
+
+// The keys of the TOML sections whose header matches, such as the crates under `[dependencies]`.
+function sectionKeys(content: string, header: RegExp): string[] {
+  const keys: string[] = [];
+  let inside = false;
+  for (const raw of content.split(/\r?\n/)) {
+    const line = raw.trim();
+    if (line.startsWith('[')) {
+      inside = header.test(line);
+      continue;
+    }
+    const key = inside ? /^([\w-]+)(?:\.[\w-]+)*\s*=/.exec(line) : null;
+    if (key !== null) {
+      keys.push(key[1]!);
+    }
+  }
+  return keys;
+}
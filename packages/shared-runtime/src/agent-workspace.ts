@@ -0,0 +1,240 @@
+import { spawn } from 'node:child_process';
+import { readdir, readFile, realpath, stat } from 'node:fs/promises';
+import { isAbsolute, join, relative, resolve, sep } from 'node:path';
+import { describeAgentPermissions, permitsPath, type AgentPermissions, type AgentPermissionViolation } from './agent-permissions.js';
+import { renderContextFiles } from './context-budget.js';
+import type { ProviderTool } from './provider-bridge.js';
+
+export interface AgentAttachment {
+  /** Relative to the workspace root. */
+  file: string;
+  content: string;
+}
+
+export interface AgentWorkspace {
+  /** The directory the agent is confined to, relative to the workspace root; unset for the whole workspace. */
+  scope?: string;
+  attachments: AgentAttachment[];
+  /** The permissions of the agent's profile; the workspace tools keep to them. */
+  permissions?: AgentPermissions;
+}
+
+export const AGENT_SCOPE_ERROR_CODE = 'AGENT_SCOPE_INVALID';
+export const AGENT_ATTACHMENT_ERROR_CODE = 'AGENT_ATTACHMENT_INVALID';
+
+const MAX_ATTACHMENTS = 20;
+const MAX_ATTACHMENT_BYTES = 256 * 1024;
+const MAX_LISTED_ENTRIES = 500;
+const COMMAND_TIMEOUT_MS = 120_000;
+
+/**
+ * Checks what an agent run may see: `scope` must be a directory of the workspace, and every
+ * attached file must lie inside it (or the workspace, without a scope). Symlinks are followed
+ * before checking, so a link cannot lead out. Throws with `AGENT_SCOPE_INVALID` or
+ * `AGENT_ATTACHMENT_INVALID`.
+ */
+export async function resolveAgentWorkspace(
+  basePath: string,
+  request: { scope?: string; files?: string[]; permissions?: AgentPermissions },
+): Promise<AgentWorkspace> {
+  const root = await realpath(basePath);
+  let scope: string | undefined;
+  if (request.scope !== undefined && request.scope.trim().length > 0 && request.scope.trim() !== '.') {
+    const resolved = await resolveInside(root, request.scope.trim(), root);
+    const info = resolved === undefined ? undefined : await stat(resolved).catch(() => undefined);
+    if (resolved === undefined || info === undefined || !info.isDirectory()) {
+      throw workspaceError(AGENT_SCOPE_ERROR_CODE, `Scope "${request.scope}" is not a directory inside the workspace.`);
+    }
+    const relativeScope = toWorkspacePath(root, resolved);
+    if (relativeScope === '.git' || relativeScope.startsWith('.git/')) {
+      throw workspaceError(AGENT_SCOPE_ERROR_CODE, `Scope "${request.scope}" is inside .git.`);
+    }
+    scope = relativeScope.length > 0 ? relativeScope : undefined;
+  }
+
+  const files = [...new Set(request.files ?? [])];
+  if (files.length > MAX_ATTACHMENTS) {
+    throw workspaceError(AGENT_ATTACHMENT_ERROR_CODE, `At most ${MAX_ATTACHMENTS} files can be attached; got ${files.length}.`);
+  }
+  const allowed = scope === undefined ? root : resolve(root, scope);
+  const attachments: AgentAttachment[] = [];
+  for (const file of files) {
+    const resolved = await resolveInside(root, file, allowed);
+    if (resolved === undefined) {
+      throw workspaceError(AGENT_ATTACHMENT_ERROR_CODE, scope === undefined
+        ? `Attached file "${file}" is outside the workspace or does not exist.`
+        : `Attached file "${file}" is outside the scope "${scope}" or does not exist.`);
+    }
+    const info = await stat(resolved);
+    if (!info.isFile()) {
+      throw workspaceError(AGENT_ATTACHMENT_ERROR_CODE, `Attached file "${file}" is not a file.`);
+    }
+    if (info.size > MAX_ATTACHMENT_BYTES) {
+      throw workspaceError(AGENT_ATTACHMENT_ERROR_CODE, `Attached file "${file}" is larger than ${MAX_ATTACHMENT_BYTES / 1024} KiB.`);
+    }
+    const content = await readFile(resolved, 'utf8');
+    if (content.includes('\0')) {
+      throw workspaceError(AGENT_ATTACHMENT_ERROR_CODE, `Attached file "${file}" is binary.`);
+    }
+    attachments.push({ file: toWorkspacePath(root, resolved), content });
+  }
+
+  return {
+    ...(scope !== undefined ? { scope } : {}),
+    attachments,
+    ...(request.permissions !== undefined ? { permissions: request.permissions } : {}),
+  };
+}
+
+/** The prompt sections telling the agent where it may work and what it was given. */
+export function describeAgentWorkspace(workspace: AgentWorkspace): string[] {
+  const sections: string[] = [];
+  if (workspace.scope !== undefined) {
+    sections.push(`Workspace scope: ${workspace.scope}/\nYou are running in this directory. Read and change only files under it; leave the rest of the repository alone.`);
+  }
+  if (workspace.permissions !== undefined) {
+    sections.push(describeAgentPermissions(workspace.permissions));
+  }
+  if (workspace.attachments.length > 0) {
+    sections.push(renderContextFiles(workspace.attachments));
+  }
+  return sections;
+}
+
+/**
+ * Read-only `read_file` and `list_files` tools over the agent's scope, for API executors, which
+ * have no file access of their own. They follow the same rules as attachments, and the agent's
+ * permissions: files outside its paths are not listed, and reading one is refused and reported to
+ * `onViolation`. Agents whose permissions allow the shell also get `run_command`, which runs in the
+ * scope once `approveCommand`, when given, allows the command; in read-only mode it is left out.
+ */
+export function createAgentWorkspaceTools(
+  basePath: string,
+  workspace: AgentWorkspace,
+  options: {
+    onViolation?: (violation: AgentPermissionViolation) => void;
+    approveCommand?: (command: string) => Promise<boolean>;
+    readOnly?: boolean;
+  } = {},
+): ProviderTool[] {
+  const { onViolation, approveCommand } = options;
+  const within = async () => {
+    const root = await realpath(basePath);
+    return { root, allowed: workspace.scope === undefined ? root : resolve(root, workspace.scope) };
+  };
+  const where = workspace.scope === undefined ? 'the workspace' : `the scope "${workspace.scope}"`;
+  return [
+    {
+      name: 'read_file',
+      description: `Reads a text file of ${where}. Paths are relative to the workspace root.`,
+      inputSchema: { type: 'object', properties: { path: { type: 'string' } }, required: ['path'] },
+      async run(input) {
+        const { root, allowed } = await within();
+        const path = typeof input.path === 'string' ? input.path : '';
+        const resolved = await resolveInside(root, path, allowed);
+        const info = resolved === undefined ? undefined : await stat(resolved);
+        if (resolved === undefined || info === undefined || !info.isFile() || isInsideGit(root, resolved)) {
+          throw new Error(`"${path}" is not a file inside ${where}.`);
+        }
+        if (!permitsPath(workspace.permissions, toWorkspacePath(root, resolved))) {
+          onViolation?.({ kind: 'path', operation: 'read', target: toWorkspacePath(root, resolved) });
+          throw new Error(`"${path}" is outside the paths this agent may read.`);
+        }
+        if (info.size > MAX_ATTACHMENT_BYTES) {
+          throw new Error(`"${path}" is larger than ${MAX_ATTACHMENT_BYTES / 1024} KiB.`);
+        }
+        const content = await readFile(resolved, 'utf8');
+        if (content.includes('\0')) {
+          throw new Error(`"${path}" is binary.`);
+        }
+        return content;
+      },
+    },
+    {
+      name: 'list_files',
+      description: `Lists a directory of ${where}, one entry per line, directories ending in "/". Paths are relative to the workspace root; omit the path for ${workspace.scope === undefined ? 'the root' : 'the scope'}.`,
+      inputSchema: { type: 'object', properties: { path: { type: 'string' } } },
+      async run(input) {
+        const { root, allowed } = await within();
+        const path = typeof input.path === 'string' && input.path.trim().length > 0 ? input.path : toWorkspacePath(root, allowed) || '.';
+        const resolved = await resolveInside(root, path, allowed);
+        const info = resolved === undefined ? undefined : await stat(resolved);
+        if (resolved === undefined || info === undefined || !info.isDirectory() || isInsideGit(root, resolved)) {
+          throw new Error(`"${path}" is not a directory inside ${where}.`);
+        }
+        const entries = (await readdir(resolved, { withFileTypes: true }))
+          .filter((entry) => entry.name !== '.git')
+          .filter((entry) => entry.isDirectory() || permitsPath(workspace.permissions, toWorkspacePath(root, join(resolved, entry.name))))
+          .map((entry) => entry.isDirectory() ? `${entry.name}/` : entry.name)
+          .sort();
+        return [
+          ...entries.slice(0, MAX_LISTED_ENTRIES),
+          ...(entries.length > MAX_LISTED_ENTRIES ? [`... ${entries.length - MAX_LISTED_ENTRIES} more`] : []),
+        ].join('\n');
+      },
+    },
+    ...(offersAgentCommands(workspace, options.readOnly === true) ? [{
+      name: 'run_command',
+      description: `Runs a shell command in ${where} and returns its exit code and output.`,
+      inputSchema: { type: 'object', properties: { command: { type: 'string' } }, required: ['command'] },
+      async run(input: Record<string, unknown>) {
+        const command = typeof input.command === 'string' ? input.command.trim() : '';
+        if (command.length === 0) {
+          throw new Error('No command was given.');
+        }
+        if (approveCommand !== undefined && !(await approveCommand(command))) {
+          throw new Error(`The command "${command}" was not approved.`);
+        }
+        const { allowed } = await within();
+        return runShellCommand(command, allowed);
+      },
+    }] : []),
+  ];
+}
+
+/** Whether the workspace tools include `run_command`, with which an API executor changes files. */
+export function offersAgentCommands(workspace: AgentWorkspace, readOnly: boolean): boolean {
+  return workspace.permissions?.shell === true && !readOnly;
+}
+
+// The exit code and the end of the combined output; the command is killed after two minutes.
+function runShellCommand(command: string, cwd: string): Promise<string> {
+  return new Promise((resolvePromise) => {
+    const child = spawn(command, { cwd, shell: true, stdio: ['ignore', 'pipe', 'pipe'], timeout: COMMAND_TIMEOUT_MS });
+    let output = '';
+    const collect = (chunk: Buffer) => {
+      output = `${output}${chunk.toString('utf8')}`.slice(-MAX_ATTACHMENT_BYTES);
+    };
+    child.stdout.on('data', collect);
+    child.stderr.on('data', collect);
+    child.on('error', (error) => resolvePromise(`Could not run the command: ${error.message}`));
+    child.on('close', (code, signal) => {
+      resolvePromise(`${code === null ? `Killed by ${signal ?? 'a signal'}` : `Exit code ${code}`}\n${output}`);
+    });
+  });
+}
+
+function isInsideGit(root: string, path: string): boolean {
+  const relativePath = toWorkspacePath(root, path);
+  return relativePath === '.git' || relativePath.startsWith('.git/');
+}
+
+// The real path of `path` when it exists and lies inside `within`, else undefined.
+async function resolveInside(root: string, path: string, within: string): Promise<string | undefined> {
+  let resolved: string;
+  try {
+    resolved = await realpath(resolve(root, path));
+  } catch {
+    return undefined;
+  }
+  const inside = relative(within, resolved);
+  return inside === '' || (!inside.startsWith(`..${sep}`) && inside !== '..' && !isAbsolute(inside)) ? resolved : undefined;
+}
+
+function toWorkspacePath(root: string, path: string): string {
+  return relative(root, path).split(sep).join('/');
+}
+
+function workspaceError(code: string, message: string): Error & { code: string } {
+  return Object.assign(new Error(message), { code });
+}
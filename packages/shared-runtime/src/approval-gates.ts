@@ -0,0 +1,256 @@
+import { readFile } from 'node:fs/promises';
+import { resolve } from 'node:path';
+import { workspaceGlob } from './code-intel/module-graph.js';
+
+/** What the `approvals` of an agent profile hold for someone to allow before a run goes ahead. */
+export interface ApprovalGates {
+  /** Globs of the files the agent may write unasked; a write outside them waits for approval. */
+  writes?: string[];
+  /** Whether each shell command waits for approval. */
+  shell: boolean;
+  /** USD a run may spend unasked; once it spends more, it waits for approval to go on. */
+  costUsd?: number;
+}
+
+/** An action of an agent run that waits for approval. */
+export interface ApprovalRequest {
+  action: 'write' | 'shell' | 'cost';
+  agentId: string;
+  /** The workspace file, the command, or what the run has spent, as `$0.5200`. */
+  target: string;
+  /** For `cost`: what the run has spent so far. */
+  costUsd?: number;
+  traceId?: string;
+}
+
+/** Asks someone whether an action may go ahead; resolves to false when they refuse. */
+export type Approver = (request: ApprovalRequest) => Promise<boolean>;
+
+/** How an approval was decided, as kept in the trace. */
+export interface ApprovalRecord extends ApprovalRequest {
+  approved: boolean;
+  /** `policy` when the approval policy allowed it, `user` when the approver answered, `none` when nobody could be asked. */
+  by: 'policy' | 'user' | 'none';
+}
+
+/** What `.automatosx/approval-policy.json` allows without asking, for runs nobody is there to answer, as in CI. */
+export interface ApprovalPolicy {
+  /** Globs of files that may be written. */
+  writes: string[];
+  /** Commands that may be run; `*` stands for any text. */
+  shell: string[];
+  /** Spending up to which a run may go on. */
+  maxCostUsd?: number;
+}
+
+/** Holds a run that spends more than its threshold until its spending is approved. */
+export interface CostGate {
+  /** Aborted when the spending is refused. */
+  readonly signal: AbortSignal;
+  charge(costUsd: number): Promise<void>;
+  stop(): void;
+}
+
+export const APPROVAL_DENIED_CODE = 'APPROVAL_DENIED';
+export const APPROVALS_INVALID_CODE = 'APPROVALS_INVALID';
+export const APPROVALS_UNSUPPORTED_CODE = 'APPROVALS_UNSUPPORTED';
+export const APPROVAL_POLICY_FILE = 'approval-policy.json';
+
+const GATE_FIELDS = new Set(['writes', 'shell', 'cost_usd', 'costUsd']);
+const POLICY_FIELDS = new Set(['writes', 'shell', 'max_cost_usd', 'maxCostUsd']);
+
+/**
+ * Reads the `approvals` of an agent profile's metadata: `writes`, a glob or list of globs of the
+ * files the agent may write unasked, `shell`, and `cost_usd`. Throws an error with the
+ * `APPROVALS_INVALID` code when a field is unknown or mistyped.
+ */
+export function readApprovalGates(metadata: Record<string, unknown>, agentId: string): ApprovalGates | undefined {
+  const value = metadata.approvals;
+  if (value === undefined) {
+    return undefined;
+  }
+  const where = `The approvals of agent "${agentId}"`;
+  if (typeof value !== 'object' || value === null || Array.isArray(value)) {
+    throw approvalsError(`${where} have to be a mapping of writes, shell and cost_usd.`);
+  }
+  const fields = value as Record<string, unknown>;
+  const unknownField = Object.keys(fields).find((field) => !GATE_FIELDS.has(field));
+  if (unknownField !== undefined) {
+    throw approvalsError(`${where} have an unknown field "${unknownField}"; fields are writes, shell and cost_usd.`);
+  }
+  const writes = readGlobs(fields.writes, `${where} have "writes" that are not a glob or a list of them.`);
+  if (fields.shell !== undefined && typeof fields.shell !== 'boolean') {
+    throw approvalsError(`${where} have a "shell" that is not true or false.`);
+  }
+  const costUsd = fields.cost_usd ?? fields.costUsd;
+  if (costUsd !== undefined && (typeof costUsd !== 'number' || !Number.isFinite(costUsd) || costUsd < 0)) {
+    throw approvalsError(`${where} have a "cost_usd" that is not an amount of USD.`);
+  }
+  return {
+    ...(writes !== undefined ? { writes } : {}),
+    shell: fields.shell === true,
+    ...(costUsd !== undefined ? { costUsd } : {}),
+  };
+}
+
+/** Whether writing a workspace file waits for approval under the gates. */
+export function gatesWrite(gates: ApprovalGates | undefined, file: string): boolean {
+  const path = file.split('\\').join('/').replace(/^\.\//, '');
+  return gates?.writes !== undefined && !gates.writes.some((glob) => workspaceGlob(glob.trim()).test(path));
+}
+
+/**
+ * The gates nothing could hold for a run whose executor writes files and runs commands itself, as
+ * a CLI provider does: `writes` unless the run is read-only, `shell` unless the agent may not use
+ * the shell. Empty when the run leaves them nothing to hold.
+ */
+export function unheldApprovals(gates: ApprovalGates | undefined, run: { readOnly: boolean; shell: boolean }): Array<'writes' | 'shell'> {
+  return [
+    ...(gates?.writes !== undefined && !run.readOnly ? ['writes' as const] : []),
+    ...(gates?.shell === true && run.shell ? ['shell' as const] : []),
+  ];
+}
+
+/**
+ * Reads the approval policy, from `file` relative to the workspace or else from
+ * `.automatosx/approval-policy.json`; undefined when there is no default file. Throws an error
+ * with the `APPROVALS_INVALID` code when the file is malformed or a given one is missing.
+ */
+export async function loadApprovalPolicy(basePath: string, file?: string): Promise<ApprovalPolicy | undefined> {
+  const path = resolve(basePath, file ?? `.automatosx/${APPROVAL_POLICY_FILE}`);
+  let parsed: unknown;
+  try {
+    parsed = JSON.parse(await readFile(path, 'utf8'));
+  } catch (error) {
+    if ((error as NodeJS.ErrnoException).code === 'ENOENT' && file === undefined) {
+      return undefined;
+    }
+    throw approvalsError(`Invalid approval policy ${path}: ${error instanceof Error ? error.message : String(error)}`);
+  }
+  if (typeof parsed !== 'object' || parsed === null || Array.isArray(parsed)) {
+    throw approvalsError(`Invalid approval policy ${path}: expected an object of writes, shell and max_cost_usd.`);
+  }
+  const fields = parsed as Record<string, unknown>;
+  const unknownField = Object.keys(fields).find((field) => !POLICY_FIELDS.has(field));
+  if (unknownField !== undefined) {
+    throw approvalsError(`Invalid approval policy ${path}: unknown field "${unknownField}".`);
+  }
+  const writes = readGlobs(fields.writes, `Invalid approval policy ${path}: "writes" is not a glob or a list of them.`) ?? [];
+  const shell = typeof fields.shell === 'string' ? [fields.shell] : fields.shell ?? [];
+  if (!Array.isArray(shell) || !shell.every((command): command is string => typeof command === 'string' && command.trim().length > 0)) {
+    throw approvalsError(`Invalid approval policy ${path}: "shell" is not a command or a list of them.`);
+  }
+  const maxCostUsd = fields.max_cost_usd ?? fields.maxCostUsd;
+  if (maxCostUsd !== undefined && (typeof maxCostUsd !== 'number' || !Number.isFinite(maxCostUsd) || maxCostUsd < 0)) {
+    throw approvalsError(`Invalid approval policy ${path}: "max_cost_usd" is not an amount of USD.`);
+  }
+  return { writes, shell, ...(maxCostUsd !== undefined ? { maxCostUsd } : {}) };
+}
+
+/** Whether the policy allows the action without asking. */
+export function policyApproves(policy: ApprovalPolicy | undefined, request: ApprovalRequest): boolean {
+  if (policy === undefined) {
+    return false;
+  }
+  switch (request.action) {
+    case 'write':
+      return policy.writes.some((glob) => workspaceGlob(glob.trim()).test(request.target));
+    case 'shell':
+      return policy.shell.some((pattern) => commandPattern(pattern.trim()).test(request.target.trim()));
+    case 'cost':
+      return policy.maxCostUsd !== undefined && (request.costUsd ?? Infinity) <= policy.maxCostUsd;
+  }
+}
+
+/**
+ * Decides approvals: what the policy allows goes ahead, the rest is put to `approver`, and without
+ * one it is refused. Each decision goes to `onDecision`.
+ */
+export function createApprovalGate(options: {
+  policy?: ApprovalPolicy;
+  approver?: Approver;
+  onDecision?: (record: ApprovalRecord) => void;
+}): (request: ApprovalRequest) => Promise<boolean> {
+  return async (request) => {
+    const decide = (approved: boolean, by: ApprovalRecord['by']) => {
+      options.onDecision?.({ ...request, approved, by });
+      return approved;
+    };
+    if (policyApproves(options.policy, request)) {
+      return decide(true, 'policy');
+    }
+    if (options.approver === undefined) {
+      return decide(false, 'none');
+    }
+    return decide(await options.approver(request), 'user');
+  };
+}
+
+/**
+ * Starts a cost gate: once the charged spending goes over `thresholdUsd`, `charge` waits for
+ * `approve`, and a refusal aborts the gate's signal. It asks once a run. Aborting `parent` aborts
+ * the gate's signal too.
+ */
+export function createCostGate(
+  thresholdUsd: number,
+  approve: (spentUsd: number) => Promise<boolean>,
+  parent?: AbortSignal,
+): CostGate {
+  const controller = new AbortController();
+  let spent = 0;
+  let asked = false;
+  const onParentAbort = () => controller.abort(parent?.reason);
+  if (parent?.aborted === true) {
+    onParentAbort();
+  } else {
+    parent?.addEventListener('abort', onParentAbort, { once: true });
+  }
+  return {
+    signal: controller.signal,
+    async charge(costUsd) {
+      spent += costUsd;
+      if (asked || spent <= thresholdUsd || controller.signal.aborted) {
+        return;
+      }
+      asked = true;
+      if (!(await approve(spent))) {
+        controller.abort(Object.assign(
+          new Error(`Spending $${spent.toFixed(4)}, over the approval threshold of $${thresholdUsd}, was not approved.`),
+          { code: APPROVAL_DENIED_CODE },
+        ));
+      }
+    },
+    stop() {
+      parent?.removeEventListener('abort', onParentAbort);
+    },
+  };
+}
+
+/** One line on an approval request, e.g. `write "src/app.ts" outside its write scope`. */
+export function describeApproval(request: ApprovalRequest): string {
+  switch (request.action) {
+    case 'write':
+      return `write "${request.target}" outside its write scope`;
+    case 'shell':
+      return `run the shell command "${request.target}"`;
+    case 'cost':
+      return `go on after spending ${request.target}`;
+  }
+}
+
+function readGlobs(value: unknown, message: string): string[] | undefined {
+  const globs = typeof value === 'string' ? [value] : value;
+  if (globs !== undefined && (!Array.isArray(globs) || !globs.every((glob) => typeof glob === 'string' && glob.trim().length > 0))) {
+    throw approvalsError(message);
+  }
+  return globs as string[] | undefined;
+}
+
+function commandPattern(pattern: string): RegExp {
+  const body = pattern.split('*').map((part) => part.replace(/[.+?^${}()|[\]\\]/g, '\\$&')).join('.*');
+  return new RegExp(`^${body}$`, 's');
+}
+
+function approvalsError(message: string): Error & { code: string } {
+  return Object.assign(new Error(message), { code: APPROVALS_INVALID_CODE });
+}
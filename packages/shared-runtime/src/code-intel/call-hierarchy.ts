@@ -0,0 +1,86 @@
+import { readCodeIndex } from './index.js';
+import { buildCallGraph, getCodeSymbolId, qualifiedName, resolveTarget, type CallGraphEdge, type CallGraphNode, type ReferenceResolution } from './references.js';
+
+export const CALL_HIERARCHY_DIRECTIONS = ['incoming', 'outgoing'] as const;
+
+export type CallHierarchyDirection = typeof CALL_HIERARCHY_DIRECTIONS[number];
+
+export interface CallHierarchyItem extends CallGraphNode {
+  /** Call sites between this function and its parent in the hierarchy. */
+  calls: number;
+  via: ReferenceResolution[];
+  /** Set when the function was already expanded elsewhere in the hierarchy; its own calls are not repeated. */
+  repeated?: true;
+  children: CallHierarchyItem[];
+}
+
+export interface CallHierarchy {
+  root: CallGraphNode;
+  /** `incoming` lists callers, then their callers; `outgoing` lists callees, then theirs. */
+  direction: CallHierarchyDirection;
+  depth: number;
+  items: CallHierarchyItem[];
+  /** Distinct functions in the hierarchy, the root excluded. */
+  functions: number;
+  /** Calls anywhere in the index that could land on several functions and were left out. */
+  unresolved: number;
+}
+
+const DEFAULT_DEPTH = 3;
+const MAX_DEPTH = 10;
+
+/**
+ * Walks the call graph from one symbol, breadth first, up to `depth` calls away. Each function is
+ * expanded once, at the shallowest level it is reached, so cycles and diamonds stay finite.
+ */
+export async function buildCallHierarchy(request: {
+  basePath: string;
+  symbol: string;
+  direction?: CallHierarchyDirection;
+  depth?: number;
+}): Promise<CallHierarchy> {
+  const direction = request.direction ?? 'incoming';
+  if (!CALL_HIERARCHY_DIRECTIONS.includes(direction)) {
+    throw new Error(`Unknown call hierarchy direction "${direction}". Expected one of: ${CALL_HIERARCHY_DIRECTIONS.join(', ')}.`);
+  }
+  const depth = Math.min(Math.max(Math.floor(request.depth ?? DEFAULT_DEPTH), 1), MAX_DEPTH);
+  const snapshot = await readCodeIndex(request.basePath);
+  if (snapshot === undefined) {
+    throw new Error('No symbol index found. Run "ax code index" first.');
+  }
+  const target = resolveTarget(snapshot.files.flatMap((entry) => entry.symbols), request.symbol);
+  const graph = await buildCallGraph({ basePath: request.basePath });
+
+  const nodes = new Map(graph.nodes.map((node) => [node.id, node]));
+  const next = new Map<string, CallGraphEdge[]>();
+  for (const edge of graph.edges) {
+    const key = direction === 'incoming' ? edge.to : edge.from;
+    next.set(key, [...next.get(key) ?? [], edge]);
+  }
+
+  const rootId = getCodeSymbolId(target);
+  const root = nodes.get(rootId)
+    ?? { id: rootId, name: qualifiedName(target), kind: target.kind, language: target.language, file: target.file, line: target.line };
+  const expanded = new Set([rootId]);
+  const items: CallHierarchyItem[] = [];
+  let level: Array<{ id: string; children: CallHierarchyItem[] }> = [{ id: rootId, children: items }];
+  for (let current = 1; current <= depth && level.length > 0; current += 1) {
+    const following: typeof level = [];
+    for (const parent of level) {
+      for (const edge of next.get(parent.id) ?? []) {
+        const id = direction === 'incoming' ? edge.from : edge.to;
+        const item: CallHierarchyItem = { ...nodes.get(id)!, calls: edge.calls, via: edge.via, children: [] };
+        if (expanded.has(id)) {
+          item.repeated = true;
+        } else {
+          expanded.add(id);
+          following.push({ id, children: item.children });
+        }
+        parent.children.push(item);
+      }
+    }
+    level = following;
+  }
+
+  return { root, direction, depth, items, functions: expanded.size - 1, unresolved: graph.unresolved };
+}
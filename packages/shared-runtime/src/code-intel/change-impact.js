@@ -0,0 +1,186 @@
+import { execFile } from 'node:child_process';
+import { readFile, stat } from 'node:fs/promises';
+import { isAbsolute, join, relative, resolve, sep } from 'node:path';
+import { promisify } from 'node:util';
+import { readCodeIndex } from './index.js';
+import { buildCallGraph, getCodeSymbolId } from './references.js';
+import { isTestFile } from './test-detection.js';
+import { analyzeTestImpact, findImpactedTests, parseUnifiedDiff } from './test-impact.js';
+const execFileAsync = promisify(execFile);
+const DEFAULT_DEPTH = 3;
+const MAX_DEPTH = 10;
+const MAX_REVIEWERS = 5;
+const REVIEWER_HISTORY_COMMITS = 200;
+const CODEOWNERS_PATHS = ['.github/CODEOWNERS', 'CODEOWNERS', 'docs/CODEOWNERS'];
+/**
+ * Works out what a change reaches before it is reviewed: the symbols it touches, the functions that
+ * call them up to `depth` calls away, the tests that cover either, and who should look at it. The
+ * change is a unified diff, a list of edited files (treated as changed throughout), or, with
+ * neither, the working tree against `base`.
+ */
+export async function analyzeChangeImpact(request) {
+    const depth = Math.min(Math.max(Math.floor(request.depth ?? DEFAULT_DEPTH), 1), MAX_DEPTH);
+    const warnings = [];
+    let source;
+    let tests;
+    let base;
+    if (request.diff !== undefined) {
+        source = 'diff';
+        const changes = parseUnifiedDiff(request.diff);
+        if (changes.length === 0) {
+            throw new Error('The diff names no files. Pass unified diff output such as "git diff" prints.');
+        }
+        tests = await findImpactedTests(request.basePath, changes);
+    }
+    else if (request.files !== undefined) {
+        source = 'files';
+        tests = await findImpactedTests(request.basePath, await editedFiles(request.basePath, request.files));
+    }
+    else {
+        source = 'git';
+        tests = await analyzeTestImpact({ basePath: request.basePath, base: request.base });
+        base = request.base ?? 'HEAD';
+    }
+    const affectedSymbols = await findAffectedSymbols(request.basePath, tests.changedSymbols, depth, warnings);
+    const impactedTests = new Map(tests.impactedTests.map((test) => [test.file, [...test.reasons]]));
+    for (const symbol of affectedSymbols.filter((candidate) => isTestFile(candidate.file))) {
+        const reasons = impactedTests.get(symbol.file) ?? [];
+        const reason = `calls ${symbol.calls}`;
+        if (!reasons.includes(reason)) {
+            reasons.push(reason);
+        }
+        impactedTests.set(symbol.file, reasons);
+    }
+    return {
+        source,
+        ...(base !== undefined ? { base } : {}),
+        changedFiles: tests.changedFiles,
+        changedSymbols: tests.changedSymbols,
+        testFileCount: tests.testFileCount,
+        impactedTests: [...impactedTests.entries()]
+            .map(([file, reasons]) => ({ file, reasons }))
+            .sort((left, right) => left.file.localeCompare(right.file)),
+        affectedSymbols,
+        reviewers: await suggestReviewers(request.basePath, tests.changedFiles),
+        ...(warnings.length > 0 ? { warnings } : {}),
+    };
+}
+async function editedFiles(basePath, files) {
+    const changes = [];
+    for (const path of new Set(files)) {
+        const file = relative(resolve(basePath), resolve(basePath, path)).split(sep).join('/');
+        if (file === '..' || file.startsWith('../') || isAbsolute(file)) {
+            throw new Error(`Path escapes workspace: ${path}`);
+        }
+        const deleted = (await stat(join(basePath, file)).catch(() => undefined)) === undefined;
+        changes.push({ file, deleted });
+    }
+    return changes;
+}
+// Breadth first over the reverse call graph, so each caller is reported at its shortest distance.
+async function findAffectedSymbols(basePath, changedSymbols, depth, warnings) {
+    if (changedSymbols.length === 0) {
+        return [];
+    }
+    if (await readCodeIndex(basePath) === undefined) {
+        warnings.push('No symbol index found, so callers of the changed symbols were not traced. Run "ax code index" first.');
+        return [];
+    }
+    const graph = await buildCallGraph({ basePath });
+    const nodes = new Map(graph.nodes.map((node) => [node.id, node]));
+    const callers = new Map();
+    for (const edge of graph.edges) {
+        callers.set(edge.to, [...callers.get(edge.to) ?? [], edge]);
+    }
+    const reached = new Set(changedSymbols.map((symbol) => getCodeSymbolId(symbol)));
+    const affected = [];
+    let level = [...reached];
+    for (let distance = 1; distance <= depth && level.length > 0; distance += 1) {
+        const following = [];
+        for (const id of level) {
+            for (const edge of callers.get(id) ?? []) {
+                if (reached.has(edge.from)) {
+                    continue;
+                }
+                reached.add(edge.from);
+                following.push(edge.from);
+                affected.push({ ...nodes.get(edge.from), distance, calls: nodes.get(id).name });
+            }
+        }
+        level = following;
+    }
+    return affected;
+}
+/**
+ * Owners of the changed files in CODEOWNERS come first, then the authors of the most commits
+ * touching them. The configured git user, who is presumably making the change, is left out.
+ */
+async function suggestReviewers(basePath, files) {
+    if (files.length === 0) {
+        return [];
+    }
+    const reviewers = new Map();
+    const entry = (reviewer) => {
+        const existing = reviewers.get(reviewer) ?? { reviewer, owner: false, commits: 0, reasons: [] };
+        reviewers.set(reviewer, existing);
+        return existing;
+    };
+    const rules = await readCodeOwners(basePath);
+    for (const file of files) {
+        // The last matching rule wins, as on GitHub and GitLab.
+        const rule = [...rules].reverse().find((candidate) => candidate.pattern.test(file));
+        for (const owner of rule?.owners ?? []) {
+            const reviewer = entry(owner);
+            reviewer.owner = true;
+            reviewer.reasons.push(`owns ${file}`);
+        }
+    }
+    const self = (await git(basePath, ['config', 'user.email']))?.trim().toLowerCase();
+    const log = await git(basePath, ['log', `--max-count=${REVIEWER_HISTORY_COMMITS}`, '--format=%an <%ae>', '--', ...files]);
+    for (const author of (log ?? '').split('\n').filter((line) => line.length > 0)) {
+        if (self !== undefined && self.length > 0 && author.toLowerCase().endsWith(`<${self}>`)) {
+            continue;
+        }
+        entry(author).commits += 1;
+    }
+    for (const reviewer of reviewers.values()) {
+        if (reviewer.commits > 0) {
+            reviewer.reasons.push(`${reviewer.commits} recent commit(s) to the changed files`);
+        }
+    }
+    return [...reviewers.values()]
+        .sort((left, right) => Number(right.owner) - Number(left.owner) || right.commits - left.commits || left.reviewer.localeCompare(right.reviewer))
+        .slice(0, MAX_REVIEWERS);
+}
+async function readCodeOwners(basePath) {
+    for (const path of CODEOWNERS_PATHS) {
+        let content;
+        try {
+            content = await readFile(join(basePath, path), 'utf8');
+        }
+        catch {
+            continue;
+        }
+        return content.split(/\r?\n/).flatMap((line) => {
+            const [pattern, ...owners] = line.replace(/#.*$/, '').trim().split(/\s+/);
+            return pattern !== undefined && pattern.length > 0 && owners.length > 0 ? [{ pattern: codeOwnersPattern(pattern), owners }] : [];
+        });
+    }
+    return [];
+}
+// gitignore-style: `/docs/` is anchored at the root, `*.ts` matches at any depth, a directory owns what is under it.
+function codeOwnersPattern(pattern) {
+    const anchored = pattern.startsWith('/') || pattern.slice(0, -1).includes('/');
+    const body = pattern.replace(/^\//, '').replace(/\/$/, '')
+        .split('**').map((part) => part.split('*').map((text) => text.replace(/[.+?^${}()|[\]\\]/g, '\\$&')).join('[^/]*')).join('.*');
+    return new RegExp(`${anchored ? '^' : '(?:^|/)'}${body}(?:/|$)`);
+}
+async function git(basePath, args) {
+    try {
+        return (await execFileAsync('git', args, { cwd: basePath, maxBuffer: 1024 * 1024 * 16 })).stdout;
+    }
+    catch {
+        // Outside a repository, or without a configured user, there is simply no history to go on.
+        return undefined;
+    }
+}
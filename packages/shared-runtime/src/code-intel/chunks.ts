@@ -0,0 +1,87 @@
+import { readFile } from 'node:fs/promises';
+import { join } from 'node:path';
+import type { CodeIndexSnapshot } from './index.js';
+import type { CodeLanguage, CodeSymbol } from './types.js';
+
+export interface CodeChunk {
+  /** `file:startLine-endLine`, unique within a workspace. */
+  id: string;
+  file: string;
+  language: CodeLanguage;
+  startLine: number;
+  endLine: number;
+  /** The declaration the chunk covers; unset for the lines between declarations. */
+  symbol?: Pick<CodeSymbol, 'name' | 'kind' | 'container'>;
+  text: string;
+}
+
+const DEFAULT_MAX_LINES = 60;
+
+/**
+ * Splits every indexed file into chunks for embedding. Each declaration small enough becomes one
+ * chunk, larger ones are split into their members, and the lines between declarations are chunked
+ * on their own, so every non-blank line lands in exactly one chunk of at most `maxLines` lines.
+ */
+export async function chunkCodeIndex(
+  basePath: string,
+  snapshot: CodeIndexSnapshot,
+  options: { maxLines?: number; paths?: string[] } = {},
+): Promise<CodeChunk[]> {
+  const maxLines = options.maxLines ?? DEFAULT_MAX_LINES;
+  const scopes = (options.paths ?? []).map((path) => path.replace(/^\.\/|\/+$/g, '')).filter((path) => path.length > 0 && path !== '.');
+  const chunks: CodeChunk[] = [];
+  for (const entry of snapshot.files) {
+    if (scopes.length > 0 && !scopes.some((scope) => entry.file === scope || entry.file.startsWith(`${scope}/`))) {
+      continue;
+    }
+    let content: string;
+    try {
+      content = await readFile(join(basePath, entry.file), 'utf8');
+    } catch {
+      continue;
+    }
+    chunks.push(...chunkFile(entry.file, entry.language, content.replace(/\r?\n$/, '').split(/\r?\n/), entry.symbols, maxLines));
+  }
+  return chunks;
+}
+
+function chunkFile(file: string, language: CodeLanguage, lines: string[], symbols: CodeSymbol[], maxLines: number): CodeChunk[] {
+  const spans = symbols
+    .filter((symbol) => symbol.file === file && symbol.endLine !== undefined && symbol.endLine >= symbol.line)
+    .sort((left, right) => left.line - right.line || right.endLine! - left.endLine!);
+  const chunks: CodeChunk[] = [];
+  const addWindows = (start: number, end: number, symbol?: CodeSymbol) => {
+    for (let first = start; first <= Math.min(end, lines.length); first += maxLines) {
+      const last = Math.min(end, lines.length, first + maxLines - 1);
+      const text = lines.slice(first - 1, last).join('\n');
+      if (text.trim().length === 0) {
+        continue;
+      }
+      chunks.push({
+        id: `${file}:${first}-${last}`,
+        file,
+        language,
+        startLine: first,
+        endLine: last,
+        ...(symbol !== undefined ? { symbol: { name: symbol.name, kind: symbol.kind, ...(symbol.container !== undefined ? { container: symbol.container } : {}) } } : {}),
+        text,
+      });
+    }
+  };
+
+  let covered = 0;
+  for (const span of spans) {
+    if (span.line <= covered) {
+      continue;
+    }
+    const hasMembers = spans.some((other) => other !== span && other.line >= span.line && other.endLine! <= span.endLine! && (other.line > span.line || other.endLine! < span.endLine!));
+    if (span.endLine! - span.line + 1 > maxLines && hasMembers) {
+      continue;
+    }
+    addWindows(covered + 1, span.line - 1);
+    addWindows(span.line, span.endLine!, span);
+    covered = span.endLine!;
+  }
+  addWindows(covered + 1, lines.length);
+  return chunks;
+}
@@ -0,0 +1,52 @@
+export const CONCURRENCY_PRIMITIVES = ['Arc', 'Mutex', 'RwLock', 'Semaphore', 'atomic', 'channel', 'spawn', 'lock'];
+// Patterns cover Rust (std, tokio, crossbeam), C# and Ruby spellings of each primitive.
+const PRIMITIVE_PATTERNS = {
+    Arc: /\bArc\s*(?:<|::)/,
+    Mutex: /\b(?:Mutex|ReentrantMutex)\b/,
+    RwLock: /\b(?:RwLock|ReaderWriterLock(?:Slim)?)\b/,
+    Semaphore: /\b(?:Semaphore|SemaphoreSlim|Condvar|Barrier|Notify)\b/,
+    atomic: /\b(?:Atomic(?:Bool|Usize|Isize|U8|U16|U32|U64|I8|I16|I32|I64|Ptr)|Interlocked)\b/,
+    channel: /\b(?:(?:mpsc|oneshot|broadcast|watch|crossbeam_channel|flume)::\w+|(?:sync_)?channel\s*(?:::<[^>]*>)?\s*\(|Channel\.Create(?:Bounded|Unbounded)|Queue\.new|SizedQueue\.new)/,
+    spawn: /\b(?:(?:tokio|task|thread|rayon|async_std)::spawn(?:_blocking|_local)?|Task\.(?:Run|Factory\.StartNew)|Thread\.new|new\s+Thread\s*\()/,
+    lock: /(?:^|[^\w.])lock\s*\(|\.(?:lock|read|write|try_lock)\s*\(\s*\)/,
+};
+const FUNCTION_KINDS = new Set(['function', 'method']);
+/**
+ * Marks functions and methods with `isAsync` and records the concurrency primitives their
+ * declaration and body use, so a concurrency review can start from the symbols that share state.
+ */
+export function tagConcurrencySymbols(symbols, content) {
+    const lines = content.split(/\r?\n/).map(stripLiterals);
+    for (const symbol of symbols) {
+        if (!FUNCTION_KINDS.has(symbol.kind)) {
+            continue;
+        }
+        if (symbol.attributes?.async === 'true' || isAsyncDeclaration(lines[symbol.line - 1] ?? '', symbol.name)) {
+            symbol.isAsync = true;
+        }
+        const body = lines.slice(symbol.line - 1, symbol.endLine ?? symbol.line).join('\n');
+        const primitives = CONCURRENCY_PRIMITIVES.filter((primitive) => PRIMITIVE_PATTERNS[primitive].test(body));
+        if (primitives.length > 0) {
+            symbol.concurrency = primitives;
+        }
+    }
+    return symbols;
+}
+export function isConcurrentSymbol(symbol) {
+    return symbol.isAsync === true || (symbol.concurrency?.length ?? 0) > 0;
+}
+// `async fn`, `async def`, `async function`, `public async Task<T> Name(`: the keyword comes before the name.
+function isAsyncDeclaration(line, name) {
+    const nameIndex = line.search(new RegExp(`\\b${escapeRegExp(name)}\\b`));
+    const declaration = nameIndex >= 0 ? line.slice(0, nameIndex) : line;
+    return /(?:^|[\s(])async\b/.test(declaration);
+}
+// Drops line comments and the contents of string literals so `"use a Mutex"` is not usage.
+function stripLiterals(line) {
+    return line
+        .replace(/"(?:[^"\\]|\\.)*"/g, '""')
+        .replace(/\/\/.*$/, '');
+}
+function escapeRegExp(value) {
+    return value.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+}
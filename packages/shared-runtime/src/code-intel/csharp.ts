@@ -0,0 +1,403 @@
+import { extname } from 'node:path';
+import type { CodeFileIndex, CodeLanguageExtractor, CodeSymbol, CodeSymbolLocation } from './types.js';
+
+const MODIFIERS = '(?:public|private|protected|internal|static|abstract|sealed|partial|readonly|unsafe|new|file|ref|async|virtual|override|extern|required|volatile)';
+const NAMESPACE_PATTERN = /^namespace\s+([A-Za-z_][\w.]*)$/;
+const TYPE_PATTERN = new RegExp(`^((?:${MODIFIERS}\\s+)*)(class|struct|interface|enum|record(?:\\s+(?:class|struct))?)\\s+([A-Za-z_]\\w*)\\s*(?:<[^>]*>)?\\s*(\\([^)]*\\))?\\s*(?::\\s*(.*?))?\\s*(?:\\bwhere\\b.*)?$`, 's');
+const METHOD_PATTERN = new RegExp(`^((?:${MODIFIERS}\\s+)*)(?:([\\w.]+(?:\\s*<[^()=]*>)?(?:\\[[,\\s]*\\])*\\??)\\s+)?([A-Za-z_]\\w*)\\s*(?:<[^()=]*>)?\\s*\\(`, 's');
+const PROPERTY_PATTERN = new RegExp(`^((?:${MODIFIERS}\\s+)*)([\\w.]+(?:\\s*<[^()=]*>)?(?:\\[[,\\s]*\\])*\\??)\\s+([A-Za-z_]\\w*)$`, 's');
+const VISIBILITY = new Set(['public', 'private', 'protected', 'internal', 'file']);
+const CONTROL_KEYWORDS = new Set(['if', 'for', 'foreach', 'while', 'switch', 'using', 'lock', 'catch', 'return', 'new', 'fixed']);
+const MERGEABLE_KINDS = new Set(['class', 'record']);
+
+type ScopeKind = 'namespace' | 'type' | 'member' | 'block';
+
+interface OpenScope {
+  kind: ScopeKind;
+  name?: string;
+  symbolIndex?: number;
+}
+
+export const csharpExtractor: CodeLanguageExtractor = {
+  language: 'csharp',
+  matchesFile(fileName) {
+    return extname(fileName).toLowerCase() === '.cs';
+  },
+  extract(content, file) {
+    return extractCSharpSymbols(content, file);
+  },
+};
+
+export function extractCSharpSymbols(content: string, file: string): CodeSymbol[] {
+  const code = sanitizeCSharp(content);
+  const symbols: CodeSymbol[] = [];
+  const stack: OpenScope[] = [];
+  let fileNamespace: string | undefined;
+  let header = '';
+  let headerLine = 1;
+  let parenDepth = 0;
+  let line = 1;
+
+  const containerName = (): string | undefined => {
+    const names = stack
+      .filter((scope) => (scope.kind === 'namespace' || scope.kind === 'type') && scope.name !== undefined)
+      .map((scope) => scope.name!);
+    const qualified = [fileNamespace, ...names].filter((name): name is string => name !== undefined);
+    return qualified.length > 0 ? qualified.join('.') : undefined;
+  };
+
+  const declare = (terminator: '{' | ';'): OpenScope => {
+    const parent = stack.at(-1);
+    const trimmed = header.trim();
+    const text = stripAttributes(trimmed);
+    const declarationLine = headerLine + (trimmed.slice(0, trimmed.length - text.length).match(/\n/g)?.length ?? 0);
+    if (parent !== undefined && parent.kind !== 'namespace' && parent.kind !== 'type') {
+      return { kind: 'block' };
+    }
+
+    const namespace = NAMESPACE_PATTERN.exec(text);
+    if (namespace !== null && parent?.kind !== 'type') {
+      symbols.push({ name: namespace[1]!, kind: 'namespace', language: 'csharp', file, line: declarationLine, ...withContainer(containerName()) });
+      if (terminator === ';') {
+        fileNamespace = namespace[1];
+      }
+      return { kind: 'namespace', name: namespace[1], symbolIndex: symbols.length - 1 };
+    }
+
+    const type = TYPE_PATTERN.exec(text);
+    if (type !== null) {
+      const modifiers = type[1]!.trim().split(/\s+/).filter((modifier) => modifier.length > 0);
+      const declaration = type[2]!.replace(/\s+/g, ' ');
+      const attributes: Record<string, string> = {};
+      const visibility = modifiers.filter((modifier) => VISIBILITY.has(modifier)).join(' ');
+      const others = modifiers.filter((modifier) => !VISIBILITY.has(modifier) && modifier !== 'partial');
+      if (declaration !== 'class' && declaration !== 'record') {
+        attributes.declaration = declaration;
+      }
+      if (visibility.length > 0) {
+        attributes.visibility = visibility;
+      }
+      if (others.length > 0) {
+        attributes.modifiers = others.join(' ');
+      }
+      if (modifiers.includes('partial')) {
+        attributes.partial = 'true';
+      }
+      if (type[5] !== undefined && type[5].trim().length > 0) {
+        attributes.bases = splitTopLevel(type[5]).join(', ');
+      }
+      const container = containerName();
+      symbols.push({
+        name: type[3]!,
+        kind: declaration.startsWith('record') ? 'record' : 'class',
+        language: 'csharp',
+        file,
+        line: declarationLine,
+        ...withContainer(container),
+        ...(Object.keys(attributes).length > 0 ? { attributes } : {}),
+      });
+      const typeIndex = symbols.length - 1;
+      if (type[4] !== undefined && declaration.startsWith('record')) {
+        const qualified = container !== undefined ? `${container}.${type[3]}` : type[3]!;
+        for (const parameter of splitTopLevel(type[4].slice(1, -1))) {
+          const match = /([\w.<>,\[\]?\s]+?)\s+([A-Za-z_]\w*)\s*(?:=.*)?$/s.exec(stripAttributes(parameter));
+          if (match !== null) {
+            symbols.push({
+              name: match[2]!,
+              kind: 'property',
+              language: 'csharp',
+              file,
+              line: declarationLine,
+              container: qualified,
+              attributes: { type: match[1]!.trim(), positional: 'true' },
+            });
+          }
+        }
+      }
+      return { kind: 'type', name: type[3], symbolIndex: typeIndex };
+    }
+
+    if (parent?.kind !== 'type') {
+      return { kind: 'block' };
+    }
+
+    const arrow = text.indexOf('=>');
+    const signature = arrow >= 0 ? text.slice(0, arrow).trim() : text;
+    const firstParen = signature.indexOf('(');
+    const assignment = signature.search(/(?<![=!<>])=(?!=)/);
+    if (/\b(?:event|operator|delegate)\b/.test(signature) || (assignment >= 0 && (firstParen < 0 || assignment < firstParen))) {
+      return { kind: 'member' };
+    }
+
+    const method = firstParen >= 0 ? METHOD_PATTERN.exec(signature) : null;
+    if (method !== null && !CONTROL_KEYWORDS.has(method[3]!)) {
+      const modifiers = method[1]!.trim().split(/\s+/).filter((modifier) => modifier.length > 0);
+      const isConstructor = method[2] === undefined;
+      if (isConstructor && method[3] !== parent.name) {
+        return { kind: 'member' };
+      }
+      const attributes: Record<string, string> = {};
+      const visibility = modifiers.filter((modifier) => VISIBILITY.has(modifier)).join(' ');
+      if (visibility.length > 0) {
+        attributes.visibility = visibility;
+      }
+      if (isConstructor) {
+        attributes.role = 'constructor';
+      } else {
+        attributes.returns = method[2]!.replace(/\s+/g, '');
+      }
+      if (modifiers.includes('async')) {
+        attributes.async = 'true';
+      }
+      if (modifiers.includes('static')) {
+        attributes.static = 'true';
+      }
+      symbols.push({
+        name: method[3]!,
+        kind: 'method',
+        language: 'csharp',
+        file,
+        line: declarationLine,
+        ...withContainer(containerName()),
+        attributes,
+      });
+      return { kind: 'member', symbolIndex: symbols.length - 1 };
+    }
+
+    const property = terminator === '{' || arrow >= 0 ? PROPERTY_PATTERN.exec(signature) : null;
+    if (property !== null) {
+      const modifiers = property[1]!.trim().split(/\s+/).filter((modifier) => modifier.length > 0);
+      const attributes: Record<string, string> = { type: property[2]!.replace(/\s+/g, '') };
+      const visibility = modifiers.filter((modifier) => VISIBILITY.has(modifier)).join(' ');
+      if (visibility.length > 0) {
+        attributes.visibility = visibility;
+      }
+      if (modifiers.includes('static')) {
+        attributes.static = 'true';
+      }
+      symbols.push({
+        name: property[3]!,
+        kind: 'property',
+        language: 'csharp',
+        file,
+        line: declarationLine,
+        ...withContainer(containerName()),
+        attributes,
+      });
+      return { kind: 'member', symbolIndex: symbols.length - 1 };
+    }
+
+    return { kind: 'member' };
+  };
+
+  for (const char of code) {
+    if (char === '\n') {
+      line += 1;
+    }
+    if (char === '(') {
+      parenDepth += 1;
+    } else if (char === ')') {
+      parenDepth = Math.max(0, parenDepth - 1);
+    }
+
+    if (char === '{') {
+      stack.push(declare('{'));
+      header = '';
+      parenDepth = 0;
+    } else if (char === '}') {
+      const closed = stack.pop();
+      if (closed?.symbolIndex !== undefined) {
+        symbols[closed.symbolIndex]!.endLine = line;
+      }
+      header = '';
+      parenDepth = 0;
+    } else if (char === ';' && parenDepth === 0) {
+      const declared = declare(';');
+      if (declared.symbolIndex !== undefined && declared.kind !== 'namespace') {
+        symbols[declared.symbolIndex]!.endLine = line;
+      }
+      header = '';
+    } else {
+      if (header.trim().length === 0 && char.trim().length > 0) {
+        headerLine = line;
+      }
+      header += char;
+    }
+  }
+
+  return symbols;
+}
+
+function locationOf(symbol: CodeSymbol): CodeSymbolLocation {
+  return {
+    file: symbol.file,
+    line: symbol.line,
+    endLine: symbol.endLine,
+    ...(symbol.span !== undefined ? { span: symbol.span } : {}),
+  };
+}
+
+export function mergePartialTypes(files: CodeFileIndex[]): void {
+  const primaries = new Map<string, CodeSymbol>();
+
+  for (const entry of files) {
+    if (entry.language !== 'csharp') {
+      continue;
+    }
+    entry.symbols = entry.symbols.filter((symbol) => {
+      if (symbol.attributes?.partial !== 'true' || !MERGEABLE_KINDS.has(symbol.kind)) {
+        return true;
+      }
+      const key = `${symbol.kind}:${symbol.container ?? ''}.${symbol.name}`;
+      const primary = primaries.get(key);
+      if (primary === undefined) {
+        primaries.set(key, symbol);
+        return true;
+      }
+
+      primary.locations = [
+        ...(primary.locations ?? [locationOf(primary)]),
+        locationOf(symbol),
+      ];
+      const bases = new Set([
+        ...splitTopLevel(primary.attributes?.bases ?? ''),
+        ...splitTopLevel(symbol.attributes?.bases ?? ''),
+      ]);
+      primary.attributes = {
+        ...symbol.attributes,
+        ...primary.attributes,
+        ...(bases.size > 0 ? { bases: Array.from(bases).join(', ') } : {}),
+      };
+      return false;
+    });
+  }
+}
+
+function withContainer(container: string | undefined): { container?: string } {
+  return container !== undefined ? { container } : {};
+}
+
+function stripAttributes(text: string): string {
+  let result = text.trimStart();
+  while (result.startsWith('[')) {
+    let depth = 0;
+    let index = 0;
+    for (; index < result.length; index += 1) {
+      if (result[index] === '[') {
+        depth += 1;
+      } else if (result[index] === ']') {
+        depth -= 1;
+        if (depth === 0) {
+          break;
+        }
+      }
+    }
+    result = result.slice(index + 1).trimStart();
+  }
+  return result;
+}
+
+function splitTopLevel(text: string): string[] {
+  const parts: string[] = [];
+  let depth = 0;
+  let current = '';
+  for (const char of text) {
+    if (char === '<' || char === '(' || char === '[') {
+      depth += 1;
+    } else if (char === '>' || char === ')' || char === ']') {
+      depth -= 1;
+    }
+    if (char === ',' && depth === 0) {
+      parts.push(current.trim());
+      current = '';
+      continue;
+    }
+    current += char;
+  }
+  if (current.trim().length > 0) {
+    parts.push(current.trim());
+  }
+  return parts.map((part) => part.replace(/\s+/g, ' ')).filter((part) => part.length > 0);
+}
+
+function sanitizeCSharp(content: string): string {
+  let output = '';
+  let lineStart = true;
+  let index = 0;
+
+  const skipUntil = (end: number): void => {
+    output += content.slice(index, end).replace(/[^\n]/g, '');
+    index = end;
+  };
+
+  while (index < content.length) {
+    const char = content[index]!;
+    const next = content[index + 1];
+
+    if (char === '\n') {
+      output += char;
+      lineStart = true;
+      index += 1;
+      continue;
+    }
+    if (lineStart && char === '#') {
+      const end = content.indexOf('\n', index);
+      skipUntil(end < 0 ? content.length : end);
+      continue;
+    }
+    if (char.trim().length > 0) {
+      lineStart = false;
+    }
+
+    if (char === '/' && next === '/') {
+      const end = content.indexOf('\n', index);
+      skipUntil(end < 0 ? content.length : end);
+      continue;
+    }
+    if (char === '/' && next === '*') {
+      const end = content.indexOf('*/', index + 2);
+      skipUntil(end < 0 ? content.length : end + 2);
+      continue;
+    }
+
+    const prefix = /^[$@]*/.exec(content.slice(index, index + 4))![0];
+    const quoteStart = index + prefix.length;
+    if (content[quoteStart] === '"') {
+      const quotes = /^"*/.exec(content.slice(quoteStart))![0].length;
+      let end: number;
+      if (quotes >= 3) {
+        const closing = content.indexOf('"'.repeat(quotes), quoteStart + quotes);
+        end = closing < 0 ? content.length : closing + quotes;
+      } else if (prefix.includes('@')) {
+        end = quoteStart + 1;
+        while (end < content.length && !(content[end] === '"' && content[end + 1] !== '"')) {
+          end += content[end] === '"' ? 2 : 1;
+        }
+        end += 1;
+      } else {
+        end = quoteStart + 1;
+        while (end < content.length && content[end] !== '"' && content[end] !== '\n') {
+          end += content[end] === '\\' ? 2 : 1;
+        }
+        end += 1;
+      }
+      output += '""';
+      skipUntil(Math.min(end, content.length));
+      continue;
+    }
+    if (char === '\'') {
+      let end = index + 1;
+      while (end < content.length && content[end] !== '\'' && content[end] !== '\n') {
+        end += content[end] === '\\' ? 2 : 1;
+      }
+      output += '\'\'';
+      skipUntil(Math.min(end + 1, content.length));
+      continue;
+    }
+
+    output += char;
+    index += 1;
+  }
+
+  return output;
+}
@@ -0,0 +1,182 @@
+import { readFile } from 'node:fs/promises';
+import { join } from 'node:path';
+import { readCodeIndex } from './index.js';
+const CANDIDATE_KINDS = new Set(['function', 'method', 'class', 'trait', 'record', 'property']);
+const MEMBER_KINDS = new Set(['function', 'method', 'property']);
+const DEFAULT_LIMIT = 500;
+const IDENTIFIER_PATTERN = /[A-Za-z_]\w*[?!]?/g;
+const ENTRY_POINT_NAMES = new Set(['main', 'Main', 'initialize', 'method_missing', 'respond_to_missing?']);
+// PHP magic methods and Python dunders are called by the language, never by name.
+const MAGIC_NAME = /^__\w+$/;
+// Items the linker, a macro or a framework calls: #[no_mangle], #[tokio::main], extern "C" fn, ...
+const ENTRY_POINT_MARKER = /#\[\s*(?:no_mangle|export_name|wasm_bindgen|proc_macro\w*|(?:\w+::)*main)\b|\bextern\s+"[^"]*"\s+fn\b/;
+const OVERRIDE_MARKER = /\boverride\b|@Override\b/;
+const PREAMBLE_LINE = /^\s*(?:#\[|@\w|\[[A-Z])/;
+const VISIBILITY_PATTERN = /^\s*(?:#\[[^\]]*\]\s*)*((?:pub(?:\s*\([^)]*\))?|export|public|private|protected|internal)(?:\s+(?:protected|internal))?)\b/;
+const TRAIT_IMPL_HEADER = /\bimpl\b[^;{]*?\bfor\b[^;{]*\{/g;
+/**
+ * Lists functions, methods, types and properties whose name appears nowhere in the indexed
+ * workspace except their own declaration and body. References are matched by name, so a symbol
+ * sharing a name with a used one is never reported; the result is a starting list for cleanup,
+ * not proof. Entry points (`main`, magic methods, `#[no_mangle]` and `extern` functions), test
+ * code and trait or interface implementations are left out because they are called indirectly.
+ */
+export async function findUnreferencedSymbols(request) {
+    const snapshot = await readCodeIndex(request.basePath);
+    if (snapshot === undefined) {
+        throw new Error('No symbol index found. Run "ax code index" first.');
+    }
+    const symbols = snapshot.files.flatMap((entry) => entry.symbols);
+    const names = new Set(symbols.filter((symbol) => CANDIDATE_KINDS.has(symbol.kind)).map((symbol) => symbol.name));
+    const declarations = new Set(symbols.flatMap((symbol) => (symbol.locations ?? [symbol]).map((location) => `${location.file}:${location.line}:${symbol.name}`)));
+    const interfaces = new Set(symbols
+        .filter((symbol) => symbol.kind === 'trait' || symbol.attributes?.declaration === 'interface')
+        .map((symbol) => symbol.name));
+    const interfaceMembers = new Set(symbols
+        .filter((symbol) => MEMBER_KINDS.has(symbol.kind) && symbol.container !== undefined && interfaces.has(lastSegment(symbol.container)))
+        .map((symbol) => symbol.name));
+    // name -> every file:line mentioning it outside a declaration line of that name
+    const references = new Map();
+    const sources = new Map();
+    for (const entry of snapshot.files) {
+        let content;
+        try {
+            content = await readFile(join(request.basePath, entry.file), 'utf8');
+        }
+        catch {
+            continue;
+        }
+        const lines = content.split(/\r?\n/);
+        sources.set(entry.file, lines);
+        lines.forEach((text, index) => {
+            for (const token of text.match(IDENTIFIER_PATTERN) ?? []) {
+                for (const name of /[?!]$/.test(token) ? [token, token.slice(0, -1)] : [token]) {
+                    if (!names.has(name) || declarations.has(`${entry.file}:${index + 1}:${name}`)) {
+                        continue;
+                    }
+                    const found = references.get(name) ?? [];
+                    found.push({ file: entry.file, line: index + 1 });
+                    references.set(name, found);
+                }
+            }
+        });
+    }
+    const traitImplSpans = new Map();
+    const excluded = { entryPoints: 0, tests: 0, traitImpls: 0 };
+    const candidates = [];
+    let checked = 0;
+    for (const symbol of symbols) {
+        if (!CANDIDATE_KINDS.has(symbol.kind)) {
+            continue;
+        }
+        if (request.kind !== undefined && symbol.kind !== request.kind) {
+            continue;
+        }
+        if (request.paths !== undefined && request.paths.length > 0
+            && !(symbol.locations ?? [symbol]).some((location) => request.paths.some((path) => matchesPath(location.file, path)))) {
+            continue;
+        }
+        checked += 1;
+        const lines = sources.get(symbol.file) ?? [];
+        const preamble = declarationPreamble(lines, symbol.line);
+        if (symbol.isTest === true) {
+            excluded.tests += 1;
+            continue;
+        }
+        if (ENTRY_POINT_NAMES.has(symbol.name) || MAGIC_NAME.test(symbol.name) || symbol.attributes?.role === 'constructor'
+            || preamble.some((line) => ENTRY_POINT_MARKER.test(line))) {
+            excluded.entryPoints += 1;
+            continue;
+        }
+        if (isTraitImpl(symbol, preamble, { interfaces, interfaceMembers }, () => {
+            if (!traitImplSpans.has(symbol.file)) {
+                traitImplSpans.set(symbol.file, findTraitImplSpans(lines));
+            }
+            return traitImplSpans.get(symbol.file);
+        })) {
+            excluded.traitImpls += 1;
+            continue;
+        }
+        const inbound = (references.get(symbol.name) ?? []).filter((reference) => reference.file !== symbol.file
+            || reference.line < symbol.line
+            || reference.line > (symbol.endLine ?? symbol.line));
+        if (inbound.length > 0) {
+            continue;
+        }
+        const visibility = symbol.attributes?.visibility ?? VISIBILITY_PATTERN.exec(lines[symbol.line - 1] ?? '')?.[1]?.replace(/\s+/g, ' ');
+        candidates.push({
+            name: symbol.name,
+            kind: symbol.kind,
+            language: symbol.language,
+            file: symbol.file,
+            line: symbol.line,
+            ...(symbol.endLine !== undefined ? { endLine: symbol.endLine } : {}),
+            ...(symbol.container !== undefined ? { container: symbol.container } : {}),
+            ...(visibility !== undefined ? { visibility } : {}),
+        });
+    }
+    candidates.sort((left, right) => left.file.localeCompare(right.file) || left.line - right.line);
+    return { checked, excluded, candidates: candidates.slice(0, request.limit ?? DEFAULT_LIMIT) };
+}
+function isTraitImpl(symbol, preamble, known, spans) {
+    if (!MEMBER_KINDS.has(symbol.kind)) {
+        return false;
+    }
+    if (preamble.some((line) => OVERRIDE_MARKER.test(line))) {
+        return true;
+    }
+    // Implementations of an interface or trait member are called through the interface, so callers
+    // never name the implementing type. The interface's own declaration is still a candidate.
+    if (symbol.container !== undefined && known.interfaceMembers.has(symbol.name) && !known.interfaces.has(lastSegment(symbol.container))) {
+        return true;
+    }
+    return spans().some(([start, end]) => symbol.line > start && symbol.line <= end);
+}
+// The declaration line plus the attribute and annotation lines directly above it.
+function declarationPreamble(lines, line) {
+    const preamble = [lines[line - 1] ?? ''];
+    for (let index = line - 2; index >= 0 && PREAMBLE_LINE.test(lines[index]); index -= 1) {
+        preamble.push(lines[index]);
+    }
+    return preamble;
+}
+// Line ranges of Rust `impl Trait for Type { ... }` blocks.
+function findTraitImplSpans(lines) {
+    const code = lines.map((line) => line.replace(/"(?:[^"\\]|\\.)*"/g, '""').replace(/\/\/.*$/, '')).join('\n');
+    const spans = [];
+    for (const match of code.matchAll(TRAIT_IMPL_HEADER)) {
+        const open = match.index + match[0].length - 1;
+        let depth = 0;
+        let close = code.length;
+        for (let index = open; index < code.length; index += 1) {
+            if (code[index] === '{') {
+                depth += 1;
+            }
+            else if (code[index] === '}') {
+                depth -= 1;
+                if (depth === 0) {
+                    close = index;
+                    break;
+                }
+            }
+        }
+        spans.push([lineAt(code, match.index), lineAt(code, close)]);
+    }
+    return spans;
+}
+function lineAt(code, offset) {
+    let line = 1;
+    for (let index = 0; index < offset; index += 1) {
+        if (code.charCodeAt(index) === 10) {
+            line += 1;
+        }
+    }
+    return line;
+}
+function lastSegment(container) {
+    return container.split(/[.\\:]+/).at(-1) ?? container;
+}
+function matchesPath(file, path) {
+    const normalized = path.replace(/\/+$/, '');
+    return normalized === '' || normalized === '.' || file === normalized || file.startsWith(`${normalized}/`);
+}
@@ -0,0 +1,181 @@
+import { basename } from 'node:path';
+import { joinContinuationLines, type CodeLanguageExtractor, type CodeSymbol } from './types.js';
+
+const DOCKERFILE_INSTRUCTIONS = new Set([
+  'ADD', 'ARG', 'CMD', 'COPY', 'ENTRYPOINT', 'ENV', 'EXPOSE', 'FROM', 'HEALTHCHECK', 'LABEL',
+  'MAINTAINER', 'ONBUILD', 'RUN', 'SHELL', 'STOPSIGNAL', 'USER', 'VOLUME', 'WORKDIR',
+]);
+
+export interface DockerfileSyntaxIssue {
+  line: number;
+  message: string;
+}
+
+export const dockerfileExtractor: CodeLanguageExtractor = {
+  language: 'dockerfile',
+  matchesFile(fileName) {
+    const name = basename(fileName).toLowerCase();
+    return name === 'dockerfile'
+      || name === 'containerfile'
+      || name.startsWith('dockerfile.')
+      || name.endsWith('.dockerfile');
+  },
+  extract(content, file) {
+    return extractDockerfileSymbols(content, file);
+  },
+};
+
+export function extractDockerfileSymbols(content: string, file: string): CodeSymbol[] {
+  const symbols: CodeSymbol[] = [];
+  const stageNames = new Set<string>();
+  let stageIndex = -1;
+  let currentStage: string | undefined;
+
+  for (const { text, line } of joinContinuationLines(content)) {
+    const match = /^([A-Za-z]+)\s+(.*)$/.exec(text);
+    if (match === null) {
+      continue;
+    }
+
+    const instruction = match[1]!.toUpperCase();
+    const argumentsText = match[2]!.trim();
+
+    if (instruction === 'FROM') {
+      const tokens = stripFlags(splitWords(argumentsText));
+      const image = tokens[0];
+      if (image === undefined) {
+        continue;
+      }
+      stageIndex += 1;
+      const alias = tokens.length >= 3 && tokens[1]!.toUpperCase() === 'AS' ? tokens[2] : undefined;
+      currentStage = alias ?? `stage-${stageIndex}`;
+
+      symbols.push({
+        name: currentStage,
+        kind: 'stage',
+        language: 'dockerfile',
+        file,
+        line,
+        attributes: {
+          index: String(stageIndex),
+          baseImage: image,
+        },
+      });
+      symbols.push({
+        name: image,
+        kind: 'base-image',
+        language: 'dockerfile',
+        file,
+        line,
+        container: currentStage,
+        attributes: stageNames.has(image.toLowerCase()) ? { source: 'stage' } : { source: 'registry' },
+      });
+      if (alias !== undefined) {
+        stageNames.add(alias.toLowerCase());
+      }
+      continue;
+    }
+
+    if (instruction === 'EXPOSE') {
+      for (const token of splitWords(argumentsText)) {
+        const [port, protocol] = token.split('/');
+        if (port === undefined || port.length === 0) {
+          continue;
+        }
+        symbols.push({
+          name: port,
+          kind: 'port',
+          language: 'dockerfile',
+          file,
+          line,
+          container: currentStage,
+          attributes: { protocol: (protocol ?? 'tcp').toLowerCase() },
+        });
+      }
+      continue;
+    }
+
+    if (instruction === 'COPY' || instruction === 'ADD') {
+      const words = parseCopyArguments(argumentsText);
+      const fromStage = splitWords(argumentsText)
+        .find((token) => token.startsWith('--from='))
+        ?.slice('--from='.length);
+      if (words.length < 2) {
+        continue;
+      }
+      const destination = words[words.length - 1]!;
+      for (const source of words.slice(0, -1)) {
+        symbols.push({
+          name: source,
+          kind: 'copy',
+          language: 'dockerfile',
+          file,
+          line,
+          container: currentStage,
+          attributes: {
+            instruction: instruction.toLowerCase(),
+            destination,
+            ...(fromStage !== undefined ? { from: fromStage } : {}),
+          },
+        });
+      }
+    }
+  }
+
+  return symbols;
+}
+
+export function validateDockerfile(content: string): DockerfileSyntaxIssue[] {
+  const issues: DockerfileSyntaxIssue[] = [];
+  let sawFrom = false;
+  let heredocDelimiter: string | undefined;
+
+  for (const { text, line } of joinContinuationLines(content)) {
+    if (heredocDelimiter !== undefined) {
+      if (text === heredocDelimiter) {
+        heredocDelimiter = undefined;
+      }
+      continue;
+    }
+    heredocDelimiter = /<<-?\s*(['"]?)([A-Za-z_]\w*)\1/.exec(text)?.[2];
+
+    const instruction = /^([A-Za-z]+)\b/.exec(text)?.[1]?.toUpperCase();
+    if (instruction === undefined || !DOCKERFILE_INSTRUCTIONS.has(instruction)) {
+      issues.push({ line, message: `Unknown Dockerfile instruction: ${text.split(/\s+/)[0] ?? text}` });
+      continue;
+    }
+    if (instruction === 'FROM') {
+      sawFrom = true;
+      if (stripFlags(splitWords(text).slice(1)).length === 0) {
+        issues.push({ line, message: 'FROM requires a base image.' });
+      }
+    } else if (!sawFrom && instruction !== 'ARG') {
+      issues.push({ line, message: `${instruction} appears before the first FROM instruction.` });
+    }
+  }
+
+  return issues;
+}
+
+function parseCopyArguments(argumentsText: string): string[] {
+  const withoutFlags = argumentsText.replace(/^(?:--[\w-]+(?:=\S*)?\s+)+/, '').trim();
+  if (withoutFlags.startsWith('[')) {
+    try {
+      const parsed = JSON.parse(withoutFlags) as unknown;
+      if (Array.isArray(parsed)) {
+        return parsed.filter((entry): entry is string => typeof entry === 'string');
+      }
+    } catch {
+      return [];
+    }
+  }
+  return splitWords(withoutFlags);
+}
+
+function stripFlags(tokens: string[]): string[] {
+  return tokens.filter((token) => !token.startsWith('--'));
+}
+
+function splitWords(text: string): string[] {
+  return text.split(/\s+/).filter((token) => token.length > 0);
+}
@@ -0,0 +1,193 @@
+import { createHash } from 'node:crypto';
+import { readFile, stat } from 'node:fs/promises';
+import { createRequire } from 'node:module';
+import { basename, extname, join, resolve } from 'node:path';
+import { CODE_SYMBOL_KINDS } from './types.js';
+const TREE_SITTER_MODULE = 'tree-sitter';
+const WEB_TREE_SITTER_MODULE = 'web-tree-sitter';
+const NATIVE_GRAMMAR_EXTENSIONS = new Set(['.node', '.so', '.dylib', '.dll']);
+const MIN_PARSE_BUFFER_SIZE = 32 * 1024;
+const DEFAULT_CAPTURE_KINDS = {
+    'definition.function': 'function',
+    'definition.method': 'method',
+    'definition.class': 'class',
+    'definition.module': 'module',
+    'definition.interface': 'trait',
+    'definition.trait': 'trait',
+    'definition.namespace': 'namespace',
+    'definition.struct': 'record',
+    'definition.record': 'record',
+    'definition.field': 'property',
+    'definition.property': 'property',
+    'definition.constant': 'variable',
+    'definition.variable': 'variable',
+};
+const CONTAINER_KINDS = new Set(['class', 'module', 'trait', 'namespace', 'record']);
+export async function loadGrammarExtractor(grammar, basePath, options = {}) {
+    if (grammar.language.trim().length === 0) {
+        throw new Error('Grammar registrations need a language name.');
+    }
+    if (grammar.extensions.length === 0 && (grammar.fileNames?.length ?? 0) === 0) {
+        throw new Error(`Grammar "${grammar.language}" needs at least one file extension or file name.`);
+    }
+    const grammarPath = resolve(basePath, grammar.grammar);
+    const isWasm = extname(grammarPath).toLowerCase() === '.wasm';
+    if (!isWasm && options.runtime === 'wasm') {
+        throw new Error('Native grammars are disabled by the "wasm" grammar runtime; compile the grammar with `tree-sitter build --wasm`.');
+    }
+    const querySource = await readFile(resolve(basePath, grammar.query), 'utf8');
+    const { parser, query } = isWasm
+        ? await loadWasmGrammar(grammarPath, querySource)
+        : await loadNativeGrammar(grammarPath, querySource);
+    const grammarBytes = await readGrammarVersion(grammarPath);
+    const version = grammarBytes === undefined ? undefined : createHash('sha256')
+        .update(grammarBytes)
+        .update(querySource)
+        .update(JSON.stringify(grammar.captures ?? {}))
+        .digest('hex')
+        .slice(0, 16);
+    return createQueryExtractor({ ...grammar, parser, query, version });
+}
+export function createQueryExtractor(config) {
+    const extensions = new Set(config.extensions.map((extension) => normalizeExtension(extension)));
+    const fileNames = new Set((config.fileNames ?? []).map((fileName) => fileName.toLowerCase()));
+    const captureKinds = { ...DEFAULT_CAPTURE_KINDS, ...validateCaptureKinds(config.language, config.captures ?? {}) };
+    return {
+        language: config.language,
+        ...(config.version !== undefined ? { version: config.version } : {}),
+        matchesFile(fileName) {
+            return extensions.has(extname(fileName).toLowerCase()) || fileNames.has(basename(fileName).toLowerCase());
+        },
+        extract(content, file) {
+            const tree = config.parser.parse(content);
+            return collectQuerySymbols(config.query.matches(tree.rootNode), captureKinds, config.language, file);
+        },
+    };
+}
+function collectQuerySymbols(matches, captureKinds, language, file) {
+    const found = [];
+    const seen = new Set();
+    for (const match of matches) {
+        const definition = match.captures.find((capture) => captureKinds[capture.name] !== undefined);
+        const name = match.captures.find((capture) => capture.name === 'name');
+        if (definition === undefined || name === undefined) {
+            continue;
+        }
+        const kind = captureKinds[definition.name];
+        const key = `${definition.node.startIndex}:${kind}:${name.node.text}`;
+        if (seen.has(key)) {
+            continue;
+        }
+        seen.add(key);
+        found.push({
+            symbol: {
+                name: name.node.text,
+                kind,
+                language,
+                file,
+                line: definition.node.startPosition.row + 1,
+                endLine: definition.node.endPosition.row + 1,
+            },
+            start: definition.node.startIndex,
+            end: definition.node.endIndex,
+        });
+    }
+    found.sort((left, right) => left.start - right.start || right.end - left.end);
+    const open = [];
+    for (const entry of found) {
+        while (open.length > 0 && open.at(-1).end <= entry.start) {
+            open.pop();
+        }
+        const containers = open.filter((candidate) => candidate.end >= entry.end).map((candidate) => candidate.symbol.name);
+        if (containers.length > 0) {
+            entry.symbol.container = containers.join('.');
+        }
+        if (CONTAINER_KINDS.has(entry.symbol.kind)) {
+            open.push(entry);
+        }
+    }
+    return found.map((entry) => entry.symbol);
+}
+function validateCaptureKinds(language, captures) {
+    for (const [capture, kind] of Object.entries(captures)) {
+        if (!CODE_SYMBOL_KINDS.includes(kind)) {
+            throw new Error(`Grammar "${language}" maps capture "${capture}" to unknown symbol kind "${kind}".`);
+        }
+    }
+    return captures;
+}
+async function loadNativeGrammar(grammarPath, querySource) {
+    let TreeSitter;
+    try {
+        TreeSitter = (await import(TREE_SITTER_MODULE)).default;
+    }
+    catch {
+        throw new Error(`Native grammars need the optional "${TREE_SITTER_MODULE}" package (npm install ${TREE_SITTER_MODULE}@^0.22.4).`);
+    }
+    const language = loadNativeLanguage(grammarPath);
+    const parser = new TreeSitter();
+    parser.setLanguage(language);
+    return {
+        parser: {
+            parse: (content) => parser.parse(content, undefined, {
+                bufferSize: Math.max(MIN_PARSE_BUFFER_SIZE, content.length * 2),
+            }),
+        },
+        query: new TreeSitter.Query(language, querySource),
+    };
+}
+async function loadWasmGrammar(grammarPath, querySource) {
+    let WebTreeSitter;
+    try {
+        const loaded = await import(WEB_TREE_SITTER_MODULE);
+        // web-tree-sitter < 0.25 exports Parser as the default export; later releases use named exports.
+        WebTreeSitter = loaded.Parser !== undefined
+            ? Object.assign(loaded.Parser, { Language: loaded.Language ?? loaded.Parser.Language, Query: loaded.Query })
+            : loaded.default;
+    }
+    catch {
+        throw new Error(`WASM grammars need the optional "${WEB_TREE_SITTER_MODULE}" package (npm install ${WEB_TREE_SITTER_MODULE}@^0.25.0).`);
+    }
+    await WebTreeSitter.init();
+    const language = await WebTreeSitter.Language.load(new Uint8Array(await readFile(grammarPath)));
+    const parser = new WebTreeSitter();
+    parser.setLanguage(language);
+    const query = WebTreeSitter.Query !== undefined
+        ? new WebTreeSitter.Query(language, querySource)
+        : language.query(querySource);
+    return {
+        parser: {
+            parse: (content) => {
+                const tree = parser.parse(content);
+                if (tree === null) {
+                    throw new Error('WASM grammar failed to parse the file.');
+                }
+                return tree;
+            },
+        },
+        query,
+    };
+}
+// A compiled grammar's bytes, or the package.json of a grammar package directory; without either
+// the grammar has no version and its files are not cached.
+async function readGrammarVersion(grammarPath) {
+    try {
+        const stats = await stat(grammarPath);
+        return await readFile(stats.isDirectory() ? join(grammarPath, 'package.json') : grammarPath);
+    }
+    catch {
+        return undefined;
+    }
+}
+function loadNativeLanguage(grammarPath) {
+    if (NATIVE_GRAMMAR_EXTENSIONS.has(extname(grammarPath).toLowerCase())) {
+        const binding = { exports: {} };
+        process.dlopen(binding, grammarPath);
+        return binding.exports;
+    }
+    return createRequire(import.meta.url)(grammarPath);
+}
+function normalizeExtension(extension) {
+    const lower = extension.toLowerCase();
+    return lower.startsWith('.') ? lower : `.${lower}`;
+}
@@ -0,0 +1,106 @@
+import { buildModuleGraph } from './module-graph.js';
+import { buildCallGraph } from './references.js';
+export const GRAPH_KINDS = ['modules', 'calls'];
+export const GRAPH_FORMATS = ['dot', 'mermaid'];
+/**
+ * Renders the module import graph or the call graph as Graphviz DOT or a Mermaid flowchart, so
+ * a review can draw the architecture straight from the index. With `paths`, imports are drawn
+ * when the importing file is under one of them, calls when either end is.
+ */
+export async function exportCodeGraph(request) {
+    const diagram = request.graph === 'calls'
+        ? callGraphDiagram(await buildCallGraph({ basePath: request.basePath, paths: request.paths }))
+        : moduleGraphDiagram(await buildModuleGraph(request.basePath), request.paths);
+    return {
+        graph: request.graph,
+        format: request.format,
+        nodes: diagram.nodes.length,
+        edges: diagram.edges.length,
+        unresolved: diagram.unresolved,
+        content: request.format === 'dot' ? renderDot(request.graph, diagram.nodes, diagram.edges) : renderMermaid(diagram.nodes, diagram.edges),
+    };
+}
+/** File-level import edges; imports of packages or files outside the workspace count as unresolved. */
+export function moduleGraphDiagram(graph, paths) {
+    const inScope = createScopeFilter(paths);
+    const nodes = new Map();
+    const edges = new Map();
+    let unresolved = 0;
+    for (const module of graph.modules.filter((entry) => inScope(entry.file))) {
+        nodes.set(module.file, moduleNode(module.file));
+        for (const entry of module.imports) {
+            if (entry.resolved === undefined) {
+                unresolved += 1;
+                continue;
+            }
+            nodes.set(entry.resolved, nodes.get(entry.resolved) ?? moduleNode(entry.resolved));
+            edges.set(`${module.file} ${entry.resolved}`, { from: module.file, to: entry.resolved });
+        }
+    }
+    return { nodes: [...nodes.values()], edges: [...edges.values()], unresolved };
+}
+/** Function-level call edges grouped by file, labelled with the call count when it is above one. */
+export function callGraphDiagram(graph) {
+    return {
+        nodes: graph.nodes.map((node) => ({ id: node.id, label: node.name, group: node.file })),
+        edges: graph.edges.map((edge) => ({ from: edge.from, to: edge.to, ...(edge.calls > 1 ? { label: String(edge.calls) } : {}) })),
+        unresolved: graph.unresolved,
+    };
+}
+export function renderDot(name, nodes, edges) {
+    const lines = [`digraph ${quoteDot(name)} {`, '  rankdir=LR;', '  node [shape=box];'];
+    const declare = (node, indent) => `${indent}${quoteDot(node.id)} [label=${quoteDot(node.label)}];`;
+    for (const [group, members] of groupNodes(nodes)) {
+        if (group === undefined) {
+            lines.push(...members.map((node) => declare(node, '  ')));
+            continue;
+        }
+        lines.push(`  subgraph ${quoteDot(`cluster_${group}`)} {`, `    label=${quoteDot(group)};`);
+        lines.push(...members.map((node) => declare(node, '    ')), '  }');
+    }
+    for (const edge of edges) {
+        lines.push(`  ${quoteDot(edge.from)} -> ${quoteDot(edge.to)}${edge.label !== undefined ? ` [label=${quoteDot(edge.label)}]` : ''};`);
+    }
+    lines.push('}');
+    return `${lines.join('\n')}\n`;
+}
+// Mermaid ids must be plain words, so nodes are numbered and their names go in the label.
+export function renderMermaid(nodes, edges) {
+    const ids = new Map(nodes.map((node, index) => [node.id, `n${index}`]));
+    const lines = ['flowchart LR'];
+    const declare = (node, indent) => `${indent}${ids.get(node.id)}["${escapeMermaid(node.label)}"]`;
+    let groupIndex = 0;
+    for (const [group, members] of groupNodes(nodes)) {
+        if (group === undefined) {
+            lines.push(...members.map((node) => declare(node, '  ')));
+            continue;
+        }
+        lines.push(`  subgraph g${groupIndex}["${escapeMermaid(group)}"]`, ...members.map((node) => declare(node, '    ')), '  end');
+        groupIndex += 1;
+    }
+    for (const edge of edges) {
+        lines.push(`  ${ids.get(edge.from)} -->${edge.label !== undefined ? `|${escapeMermaid(edge.label)}|` : ''} ${ids.get(edge.to)}`);
+    }
+    return `${lines.join('\n')}\n`;
+}
+function groupNodes(nodes) {
+    const groups = new Map();
+    for (const node of nodes) {
+        groups.set(node.group, [...groups.get(node.group) ?? [], node]);
+    }
+    return groups;
+}
+function moduleNode(file) {
+    return { id: file, label: file };
+}
+function createScopeFilter(paths) {
+    const prefixes = (paths ?? []).map((path) => path.replace(/^\.\/|\/+$/g, '')).filter((path) => path.length > 0 && path !== '.');
+    return (file) => prefixes.length === 0 || prefixes.some((prefix) => file === prefix || file.startsWith(`${prefix}/`));
+}
+function quoteDot(value) {
+    return `"${value.replace(/\\/g, '\\\\').replace(/"/g, '\\"')}"`;
+}
+// Quotes end a Mermaid label and `|` an edge label; both have entity escapes.
+function escapeMermaid(value) {
+    return value.replace(/"/g, '#quot;').replace(/\|/g, '#124;');
+}
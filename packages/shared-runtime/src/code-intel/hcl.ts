@@ -0,0 +1,263 @@
+import { extname } from 'node:path';
+import type { CodeLanguageExtractor, CodeSymbol, CodeSymbolKind } from './types.js';
+
+const HCL_EXTENSIONS = new Set(['.tf', '.hcl']);
+const BLOCK_PATTERN = /^\s*(resource|data|module|variable|output)((?:\s+"[^"]*")+)\s*\{/;
+const ATTRIBUTE_PATTERN = /^\s*([A-Za-z_][\w-]*)\s*=\s*(.*)$/;
+const CAPTURED_ATTRIBUTES: Record<string, Set<string>> = {
+  resource: new Set(['provider', 'count', 'for_each']),
+  data: new Set(['provider', 'count', 'for_each']),
+  module: new Set(['source', 'version']),
+  variable: new Set(['type', 'description', 'default', 'sensitive', 'nullable']),
+  output: new Set(['value', 'description', 'sensitive']),
+};
+
+interface OpenBlock {
+  blockType: string;
+  labels: string[];
+  line: number;
+  attributes: Record<string, string>;
+}
+
+interface PendingAttribute {
+  key: string;
+  text: string;
+  balance: number;
+}
+
+export const hclExtractor: CodeLanguageExtractor = {
+  language: 'hcl',
+  matchesFile(fileName) {
+    return HCL_EXTENSIONS.has(extname(fileName).toLowerCase());
+  },
+  extract(content, file) {
+    return extractHclSymbols(content, file);
+  },
+};
+
+export function extractHclSymbols(content: string, file: string): CodeSymbol[] {
+  const lines = content.split(/\r?\n/);
+  const symbols: CodeSymbol[] = [];
+  let depth = 0;
+  let inBlockComment = false;
+  let heredocDelimiter: string | undefined;
+  let block: OpenBlock | undefined;
+  let pending: PendingAttribute | undefined;
+
+  for (let index = 0; index < lines.length; index += 1) {
+    const line = lines[index] ?? '';
+
+    if (heredocDelimiter !== undefined) {
+      if (line.trim() === heredocDelimiter) {
+        heredocDelimiter = undefined;
+      }
+      continue;
+    }
+
+    const commentedOut = inBlockComment;
+    const sanitized = sanitizeLine(line, inBlockComment);
+    inBlockComment = sanitized.inBlockComment;
+
+    if (depth === 0 && block === undefined && !commentedOut) {
+      const match = BLOCK_PATTERN.exec(line);
+      if (match !== null) {
+        block = {
+          blockType: match[1]!,
+          labels: Array.from(match[2]!.matchAll(/"([^"]*)"/g), (label) => label[1]!),
+          line: index + 1,
+          attributes: {},
+        };
+      }
+    } else if (block !== undefined && pending !== undefined) {
+      pending.text += ` ${stripComment(line).trim()}`;
+      pending.balance += bracketBalance(sanitized.code);
+      if (pending.balance <= 0) {
+        block.attributes[pending.key] = normalizeValue(pending.text);
+        pending = undefined;
+      }
+    } else if (block !== undefined && depth === 1) {
+      const attribute = ATTRIBUTE_PATTERN.exec(line);
+      if (attribute !== null && CAPTURED_ATTRIBUTES[block.blockType]?.has(attribute[1]!) === true) {
+        const valueCode = sanitizeLine(attribute[2]!, false).code;
+        const balance = bracketBalance(valueCode);
+        if (valueCode.trimStart().startsWith('<<')) {
+          block.attributes[attribute[1]!] = '<<heredoc>>';
+        } else if (balance > 0) {
+          pending = { key: attribute[1]!, text: attribute[2]!.trim(), balance };
+        } else {
+          block.attributes[attribute[1]!] = normalizeValue(attribute[2]!);
+        }
+      }
+    }
+
+    const heredoc = /<<-?\s*([A-Za-z_]\w*)\s*$/.exec(sanitized.code);
+    if (heredoc !== null) {
+      heredocDelimiter = heredoc[1];
+    }
+
+    depth = Math.max(0, depth + braceBalance(sanitized.code));
+    if (block !== undefined && depth === 0) {
+      const symbol = toSymbol(block, file, index + 1);
+      if (symbol !== undefined) {
+        symbols.push(symbol);
+      }
+      block = undefined;
+      pending = undefined;
+    }
+  }
+
+  return symbols;
+}
+
+function toSymbol(block: OpenBlock, file: string, endLine: number): CodeSymbol | undefined {
+  const [first, second] = block.labels;
+  if (first === undefined) {
+    return undefined;
+  }
+
+  let name = first;
+  let kind: CodeSymbolKind;
+  const attributes: Record<string, string> = { ...block.attributes };
+
+  switch (block.blockType) {
+    case 'resource':
+    case 'data':
+      if (second === undefined) {
+        return undefined;
+      }
+      kind = block.blockType === 'data' ? 'data' : 'resource';
+      name = `${first}.${second}`;
+      attributes.type = first;
+      attributes.address = block.blockType === 'data' ? `data.${name}` : name;
+      break;
+    case 'module':
+      kind = 'module';
+      attributes.address = `module.${first}`;
+      break;
+    case 'variable':
+      kind = 'variable';
+      attributes.address = `var.${first}`;
+      break;
+    default:
+      kind = 'output';
+      break;
+  }
+
+  return {
+    name,
+    kind,
+    language: 'hcl',
+    file,
+    line: block.line,
+    endLine,
+    attributes,
+  };
+}
+
+function sanitizeLine(line: string, inBlockComment: boolean): { code: string; inBlockComment: boolean } {
+  let code = '';
+  let index = 0;
+  let commentOpen = inBlockComment;
+
+  while (index < line.length) {
+    const char = line[index]!;
+    const next = line[index + 1];
+
+    if (commentOpen) {
+      if (char === '*' && next === '/') {
+        commentOpen = false;
+        index += 2;
+      } else {
+        index += 1;
+      }
+      continue;
+    }
+    if (char === '#' || (char === '/' && next === '/')) {
+      break;
+    }
+    if (char === '/' && next === '*') {
+      commentOpen = true;
+      index += 2;
+      continue;
+    }
+    if (char === '"') {
+      index = skipString(line, index + 1);
+      code += '""';
+      continue;
+    }
+    code += char;
+    index += 1;
+  }
+
+  return { code, inBlockComment: commentOpen };
+}
+
+function skipString(line: string, start: number): number {
+  let index = start;
+  let interpolationDepth = 0;
+  while (index < line.length) {
+    const char = line[index]!;
+    if (char === '\\') {
+      index += 2;
+      continue;
+    }
+    if (char === '$' && line[index + 1] === '{') {
+      interpolationDepth += 1;
+      index += 2;
+      continue;
+    }
+    if (char === '}' && interpolationDepth > 0) {
+      interpolationDepth -= 1;
+    } else if (char === '"' && interpolationDepth === 0) {
+      return index + 1;
+    }
+    index += 1;
+  }
+  return index;
+}
+
+function braceBalance(code: string): number {
+  let balance = 0;
+  for (const char of code) {
+    if (char === '{') {
+      balance += 1;
+    } else if (char === '}') {
+      balance -= 1;
+    }
+  }
+  return balance;
+}
+
+function bracketBalance(code: string): number {
+  let balance = 0;
+  for (const char of code) {
+    if (char === '{' || char === '(' || char === '[') {
+      balance += 1;
+    } else if (char === '}' || char === ')' || char === ']') {
+      balance -= 1;
+    }
+  }
+  return balance;
+}
+
+function stripComment(line: string): string {
+  let index = 0;
+  while (index < line.length) {
+    const char = line[index]!;
+    if (char === '"') {
+      index = skipString(line, index + 1);
+      continue;
+    }
+    if (char === '#' || (char === '/' && (line[index + 1] === '/' || line[index + 1] === '*'))) {
+      return line.slice(0, index);
+    }
+    index += 1;
+  }
+  return line;
+}
+
+function normalizeValue(value: string): string {
+  const trimmed = stripComment(value).trim();
+  const quoted = /^"((?:[^"\\]|\\.)*)"$/.exec(trimmed);
+  return quoted !== null ? quoted[1]! : trimmed.replace(/\s+/g, ' ');
+}
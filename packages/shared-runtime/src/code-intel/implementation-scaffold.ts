@@ -0,0 +1,362 @@
+import { readFile } from 'node:fs/promises';
+import { join, posix, relative, resolve } from 'node:path';
+import { readCodeIndex } from './index.js';
+import type { CodeLanguage, CodeSymbol } from './types.js';
+
+export interface ImplementationScaffoldRequest {
+  basePath: string;
+  /** Interface name, optionally namespace-qualified (`App\Contracts\UserRepository`, `Acme.Data.IUserStore`). */
+  symbol: string;
+  className?: string;
+  /** Workspace-relative output file; defaults to `<ClassName>.<ext>` next to the interface. */
+  output?: string;
+}
+
+export interface ImplementationScaffold {
+  interface: { name: string; language: CodeLanguage; file: string; line: number };
+  className: string;
+  file: string;
+  content: string;
+  members: string[];
+}
+
+type ScaffoldLanguage = 'php' | 'csharp';
+
+interface InterfaceMember {
+  docs: string[];
+  signature: string;
+  /** C# accessor list (`get; set;`) for properties and indexers. */
+  accessors?: string;
+}
+
+interface ParsedInterface {
+  header: string;
+  members: InterfaceMember[];
+  imports: string[];
+  namespace?: { name: string; fileScoped: boolean };
+}
+
+const SCAFFOLD_LANGUAGES: Record<ScaffoldLanguage, { extension: string; docPrefix: RegExp }> = {
+  php: { extension: '.php', docPrefix: /^\/\*\*/ },
+  csharp: { extension: '.cs', docPrefix: /^\/\/\// },
+};
+const INDENT = '    ';
+
+/**
+ * Scaffolds an implementation of an indexed interface: every member becomes a stub with the
+ * interface's signature and doc comment, so an implementation agent only has to fill in bodies.
+ */
+export async function generateImplementationScaffold(request: ImplementationScaffoldRequest): Promise<ImplementationScaffold> {
+  const snapshot = await readCodeIndex(request.basePath);
+  if (snapshot === undefined) {
+    throw new Error('No symbol index found. Run "ax code index" first.');
+  }
+
+  const target = findInterface(snapshot.files.flatMap((entry) => entry.symbols), request.symbol);
+  const language = target.language as ScaffoldLanguage;
+  const source = await readFile(join(request.basePath, target.file), 'utf8');
+  const parsed = parseInterface(source, target, language);
+  const className = request.className ?? defaultClassName(target.name, language);
+  if (className === target.name) {
+    throw new Error(`Implementation class name "${className}" must differ from the interface name.`);
+  }
+
+  const file = request.output ?? posix.join(posix.dirname(target.file), `${className}${SCAFFOLD_LANGUAGES[language].extension}`);
+  const relativePath = relative(resolve(request.basePath), resolve(request.basePath, file));
+  if (relativePath === '..' || /^\.\.(?:[\\/]|$)/.test(relativePath)) {
+    throw new Error(`Path escapes workspace: ${file}`);
+  }
+
+  const render = language === 'php' ? renderPhp : renderCSharp;
+  return {
+    interface: { name: target.name, language: target.language, file: target.file, line: target.line },
+    className,
+    file,
+    content: render(parsed, target.name, className),
+    members: parsed.members.map((member) => memberName(member.signature)),
+  };
+}
+
+function findInterface(symbols: CodeSymbol[], query: string): CodeSymbol {
+  const matches = symbols.filter((symbol) => symbol.attributes?.declaration === 'interface'
+    && (symbol.name === query || `${symbol.container}.${symbol.name}` === query || `${symbol.container}\\${symbol.name}` === query.replace(/^\\/, '')));
+  if (matches.length === 0) {
+    throw new Error(`No indexed interface named "${query}". Implementation scaffolds support ${Object.keys(SCAFFOLD_LANGUAGES).join(' and ')} interfaces.`);
+  }
+  if (matches.length > 1) {
+    throw new Error(`"${query}" is ambiguous: ${matches.map((symbol) => `${symbol.file}:${symbol.line}`).join(', ')}. Use a namespace-qualified name.`);
+  }
+  const [match] = matches;
+  if (!(match!.language in SCAFFOLD_LANGUAGES)) {
+    throw new Error(`Implementation scaffolds are not supported for ${match!.language} interfaces.`);
+  }
+  return match!;
+}
+
+function defaultClassName(interfaceName: string, language: ScaffoldLanguage): string {
+  if (language === 'csharp' && /^I[A-Z]/.test(interfaceName)) {
+    return interfaceName.slice(1);
+  }
+  if (language === 'php' && /.Interface$/.test(interfaceName)) {
+    return interfaceName.slice(0, -'Interface'.length);
+  }
+  return `${interfaceName}Impl`;
+}
+
+function parseInterface(source: string, target: CodeSymbol, language: ScaffoldLanguage): ParsedInterface {
+  const lines = source.split(/\r?\n/);
+  const startOffset = lines.slice(0, target.line - 1).reduce((offset, line) => offset + line.length + 1, 0);
+  const normalized = lines.join('\n');
+  const open = findOutsideComments(normalized, '{', startOffset);
+  if (open === -1) {
+    throw new Error(`Could not find the body of interface "${target.name}" in ${target.file}.`);
+  }
+
+  const before = normalized.slice(0, startOffset);
+  const importPattern = language === 'php'
+    ? /^\s*use\s+[^;(]+;/gm
+    : /^\s*(?:global\s+)?using\s+[^;(=]+(?:=[^;]+)?;/gm;
+  const namespaces = [...before.matchAll(/^\s*namespace\s+([A-Za-z_][\w.\\]*)\s*(;)?/gm)];
+  const namespace = namespaces.at(-1);
+
+  return {
+    header: normalized.slice(startOffset, open).replace(/\s+/g, ' ').trim(),
+    members: splitMembers(normalized, open + 1, language),
+    imports: [...before.matchAll(importPattern)].map((match) => match[0].trim()),
+    ...(namespace !== undefined ? { namespace: { name: namespace[1]!, fileScoped: namespace[2] === ';' } } : {}),
+  };
+}
+
+/**
+ * Splits an interface body into member declarations, keeping each member's leading comments.
+ * Members that carry a default body (C# default interface methods) are skipped.
+ */
+function splitMembers(source: string, start: number, language: ScaffoldLanguage): InterfaceMember[] {
+  const members: InterfaceMember[] = [];
+  let segment = '';
+  let index = start;
+
+  while (index < source.length) {
+    const char = source[index]!;
+    const rest = source.slice(index, index + 2);
+    if (rest === '//' || (language === 'php' && char === '#' && source[index + 1] !== '[')) {
+      const end = source.indexOf('\n', index);
+      const stop = end === -1 ? source.length : end + 1;
+      segment += source.slice(index, stop);
+      index = stop;
+      continue;
+    }
+    if (rest === '/*') {
+      const end = source.indexOf('*/', index + 2);
+      const stop = end === -1 ? source.length : end + 2;
+      segment += source.slice(index, stop);
+      index = stop;
+      continue;
+    }
+    if (char === '"' || char === '\'') {
+      const stop = skipString(source, index);
+      segment += source.slice(index, stop);
+      index = stop;
+      continue;
+    }
+    if (char === '}') {
+      break;
+    }
+    if (char === ';') {
+      pushMember(members, segment, undefined, language);
+      segment = '';
+      index += 1;
+      continue;
+    }
+    if (char === '{') {
+      const close = findMatchingBrace(source, index);
+      const signature = stripAttributes(stripComments(segment));
+      if (!signature.includes('(') || /\bthis\s*\[/.test(signature)) {
+        pushMember(members, segment, source.slice(index + 1, close).replace(/\s+/g, ' ').trim(), language);
+      }
+      segment = '';
+      index = close + 1;
+      continue;
+    }
+    segment += char;
+    index += 1;
+  }
+
+  return members;
+}
+
+function pushMember(members: InterfaceMember[], segment: string, accessors: string | undefined, language: ScaffoldLanguage): void {
+  const docs: string[] = [];
+  let rest = segment.trim();
+  for (;;) {
+    const comment = /^(\/\/[^\n]*|#(?!\[)[^\n]*|\/\*[\s\S]*?\*\/)\s*/.exec(rest);
+    if (comment === null) {
+      break;
+    }
+    if (SCAFFOLD_LANGUAGES[language].docPrefix.test(comment[1]!)) {
+      docs.push(...comment[1]!.split('\n').map((line) => line.trim()));
+    } else {
+      docs.length = 0;
+    }
+    rest = rest.slice(comment[0].length);
+  }
+
+  const signature = stripAttributes(stripComments(rest))
+    .replace(/\s+/g, ' ')
+    .replace(/\(\s+/g, '(')
+    .replace(/,?\s+\)/g, ')');
+  // C# static and default (`=> ...`) interface members are implemented on the interface itself.
+  if (
+    signature.length === 0
+    || /^(?:const|case)\b/.test(signature)
+    || (language === 'csharp' && (/\b(?:static|sealed)\b/.test(signature) || signature.includes('=>')))
+  ) {
+    return;
+  }
+  members.push({ docs, signature, ...(accessors !== undefined ? { accessors } : {}) });
+}
+
+function renderPhp(parsed: ParsedInterface, interfaceName: string, className: string): string {
+  const body = parsed.members.map((member) => {
+    const signature = /^(?:public|protected|private)\b/.test(member.signature) ? member.signature : `public ${member.signature}`;
+    return [
+      ...member.docs.map((line) => (line.startsWith('*') ? ` ${line}` : line)),
+      signature,
+      '{',
+      `${INDENT}throw new \\LogicException('Not implemented: ${className}::${memberName(member.signature)}()');`,
+      '}',
+    ];
+  });
+
+  return [
+    '<?php',
+    '',
+    'declare(strict_types=1);',
+    '',
+    ...(parsed.namespace !== undefined ? [`namespace ${parsed.namespace.name};`, ''] : []),
+    ...(parsed.imports.length > 0 ? [...parsed.imports, ''] : []),
+    `final class ${className} implements ${interfaceName}`,
+    '{',
+    ...joinBlocks(body),
+    '}',
+    '',
+  ].join('\n');
+}
+
+function renderCSharp(parsed: ParsedInterface, interfaceName: string, className: string): string {
+  const typeParameters = new RegExp(`\\binterface\\s+${interfaceName}\\s*(<[^>]*>)?`).exec(parsed.header)?.[1] ?? '';
+  const constraints = /\bwhere\b.*$/.exec(parsed.header)?.[0];
+  const notImplemented = 'throw new NotImplementedException()';
+  const body = parsed.members.map((member) => {
+    const signature = member.signature.replace(/^(?:(?:public|internal|protected|private|abstract|virtual|unsafe|new)\s+)*/, '');
+    const docs = member.docs;
+    if (member.accessors !== undefined) {
+      const accessors = member.accessors
+        .split(';')
+        .map((accessor) => accessor.trim())
+        .filter((accessor) => accessor.length > 0)
+        .map((accessor) => `${accessor} => ${notImplemented};`);
+      return [...docs, `public ${signature} { ${accessors.join(' ')} }`];
+    }
+    if (!signature.includes('(') || /^event\b/.test(signature)) {
+      return [...docs, `public ${signature};`];
+    }
+    return [...docs, `public ${signature}`, '{', `${INDENT}${notImplemented};`, '}'];
+  });
+
+  const imports = parsed.imports.some((entry) => /^(?:global\s+)?using\s+System\s*;/.test(entry))
+    ? parsed.imports
+    : ['using System;', ...parsed.imports];
+  const type = [
+    `public class ${className}${typeParameters} : ${interfaceName}${typeParameters}${constraints !== undefined ? ` ${constraints}` : ''}`,
+    '{',
+    ...joinBlocks(body),
+    '}',
+  ];
+  const namespace = parsed.namespace;
+
+  return [
+    ...imports,
+    '',
+    ...(namespace === undefined
+      ? type
+      : namespace.fileScoped
+        ? [`namespace ${namespace.name};`, '', ...type]
+        : [`namespace ${namespace.name}`, '{', ...type.map((line) => (line.length > 0 ? `${INDENT}${line}` : line)), '}']),
+    '',
+  ].join('\n');
+}
+
+function joinBlocks(blocks: string[][]): string[] {
+  return blocks.flatMap((block, index) => [
+    ...(index > 0 ? [''] : []),
+    ...block.map((line) => `${INDENT}${line}`),
+  ]);
+}
+
+function memberName(signature: string): string {
+  const head = signature.split('(')[0]!.replace(/<[^<>]*>/g, '').trim();
+  if (/\bthis\s*\[/.test(signature) && !signature.includes('(')) {
+    return 'this[]';
+  }
+  return /([A-Za-z_]\w*)\s*$/.exec(head)?.[1] ?? head;
+}
+
+function stripComments(text: string): string {
+  return text.replace(/\/\*[\s\S]*?\*\//g, ' ').replace(/\/\/[^\n]*/g, ' ').trim();
+}
+
+function stripAttributes(text: string): string {
+  return text.replace(/^(?:#?\[[^\]]*\]\s*)+/, '').trim();
+}
+
+function findOutsideComments(source: string, needle: string, start: number): number {
+  for (let index = start; index < source.length; index += 1) {
+    const pair = source.slice(index, index + 2);
+    if (pair === '//') {
+      index = source.indexOf('\n', index);
+      if (index === -1) {
+        return -1;
+      }
+    } else if (pair === '/*') {
+      index = source.indexOf('*/', index + 2);
+      if (index === -1) {
+        return -1;
+      }
+      index += 1;
+    } else if (source[index] === needle) {
+      return index;
+    }
+  }
+  return -1;
+}
+
+function findMatchingBrace(source: string, open: number): number {
+  let depth = 0;
+  for (let index = open; index < source.length; index += 1) {
+    const char = source[index];
+    if (char === '"' || char === '\'') {
+      index = skipString(source, index) - 1;
+    } else if (char === '{') {
+      depth += 1;
+    } else if (char === '}') {
+      depth -= 1;
+      if (depth === 0) {
+        return index;
+      }
+    }
+  }
+  return source.length - 1;
+}
+
+function skipString(source: string, start: number): number {
+  const quote = source[start];
+  for (let index = start + 1; index < source.length; index += 1) {
+    if (source[index] === '\\') {
+      index += 1;
+    } else if (source[index] === quote || source[index] === '\n') {
+      return index + 1;
+    }
+  }
+  return source.length;
+}
@@ -0,0 +1,124 @@
+import { mkdir, open, rename, rm, type FileHandle } from 'node:fs/promises';
+import { dirname } from 'node:path';
+import type { CodeFileIndex } from './types.js';
+
+export interface IndexSpill {
+  /** Serializes one file's entry; each full batch is appended to the spill file. */
+  add(entry: CodeFileIndex): Promise<void>;
+  /**
+   * Writes the snapshot from the spilled entries plus `retained` ones, sorted by file, one entry
+   * in memory at a time, then replaces the index and removes the spill file.
+   */
+  finish(retained: CodeFileIndex[]): Promise<{ generatedAt: string }>;
+  /** Drops the spill file after a failed build, leaving the previous index in place. */
+  discard(): Promise<void>;
+}
+
+interface SpilledEntry {
+  file: string;
+  offset: number;
+  length: number;
+}
+
+/**
+ * Keeps a bounded index build's parsed files on disk instead of in memory: entries go to
+ * `<index>.spill` in batches of up to `batchBytes`, and `finish` copies them into the index in
+ * the layout `writeCodeIndex` produces.
+ */
+export async function createIndexSpill(indexPath: string, batchBytes: number): Promise<IndexSpill> {
+  await mkdir(dirname(indexPath), { recursive: true });
+  const spillPath = `${indexPath}.spill`;
+  const tempPath = `${indexPath}.tmp`;
+  const spill = await open(spillPath, 'w+');
+  const writer = createBatchWriter(spill, batchBytes);
+  const spilled: SpilledEntry[] = [];
+
+  const cleanup = async () => {
+    await spill.close().catch(() => undefined);
+    await rm(spillPath, { force: true });
+  };
+
+  return {
+    async add(entry) {
+      const chunk = serializeEntry(entry);
+      spilled.push({ file: entry.file, offset: writer.position, length: chunk.length });
+      await writer.push(chunk);
+    },
+
+    async finish(retained) {
+      await writer.flush();
+      const ordered = [
+        ...spilled.map((location) => ({ file: location.file, location })),
+        ...retained.map((entry) => ({ file: entry.file, entry })),
+      ].sort((left, right) => left.file.localeCompare(right.file));
+
+      const generatedAt = new Date().toISOString();
+      const output = await open(tempPath, 'w');
+      try {
+        const index = createBatchWriter(output, batchBytes);
+        await index.push(Buffer.from(`{\n  "version": 1,\n  "generatedAt": ${JSON.stringify(generatedAt)},\n  "files": [`));
+        for (const [position, item] of ordered.entries()) {
+          await index.push(Buffer.from(position === 0 ? '\n' : ',\n'));
+          await index.push('entry' in item ? serializeEntry(item.entry) : await readSpilled(spill, item.location));
+        }
+        await index.push(Buffer.from(ordered.length > 0 ? '\n  ]\n}\n' : ']\n}\n'));
+        await index.flush();
+      } catch (error) {
+        await output.close();
+        await rm(tempPath, { force: true });
+        throw error;
+      }
+      await output.close();
+      await rename(tempPath, indexPath);
+      await cleanup();
+      return { generatedAt };
+    },
+
+    discard: cleanup,
+  };
+}
+
+// The entry as `JSON.stringify(snapshot, null, 2)` lays it out inside the `files` array.
+function serializeEntry(entry: CodeFileIndex): Buffer {
+  return Buffer.from(`    ${JSON.stringify(entry, null, 2).replace(/\n/g, '\n    ')}`);
+}
+
+async function readSpilled(spill: FileHandle, location: SpilledEntry): Promise<Buffer> {
+  const buffer = Buffer.alloc(location.length);
+  const { bytesRead } = await spill.read(buffer, 0, location.length, location.offset);
+  if (bytesRead !== location.length) {
+    throw new Error(`Spilled index entry for ${location.file} is truncated; re-run the index.`);
+  }
+  return buffer;
+}
+
+function createBatchWriter(handle: FileHandle, batchBytes: number): { readonly position: number; push(chunk: Buffer): Promise<void>; flush(): Promise<void> } {
+  let pending: Buffer[] = [];
+  let pendingBytes = 0;
+  let written = 0;
+
+  const flush = async () => {
+    if (pendingBytes === 0) {
+      return;
+    }
+    const batch = Buffer.concat(pending, pendingBytes);
+    pending = [];
+    pendingBytes = 0;
+    await handle.write(batch, 0, batch.length, written);
+    written += batch.length;
+  };
+
+  return {
+    get position() {
+      return written + pendingBytes;
+    },
+    async push(chunk) {
+      pending.push(chunk);
+      pendingBytes += chunk.length;
+      if (pendingBytes >= batchBytes) {
+        await flush();
+      }
+    },
+    flush,
+  };
+}
@@ -0,0 +1,351 @@
+import { mkdir, readFile, readdir, stat, writeFile } from 'node:fs/promises';
+import { extname, join, relative, resolve, sep } from 'node:path';
+import { mergePartialTypes } from './csharp.js';
+import { createIndexSpill } from './index-spill.js';
+import { openParseCache } from './parse-cache.js';
+import { createLanguageRegistry, loadWorkspaceLanguageRegistry, readCodeIntelConfig } from './registry.js';
+import { isConcurrentSymbol, tagConcurrencySymbols } from './concurrency.js';
+import { tagOwnershipSymbols } from './ownership.js';
+import { derivesTrait, hasAnnotation, tagAttributeSymbols } from './rust-attributes.js';
+import { isCfgActive, tagCfgSymbols } from './rust-cfg.js';
+import { tagValueSymbols } from './rust-values.js';
+import { addSymbolSpans } from './positions.js';
+import { addStableSymbolIds } from './symbol-id.js';
+import { tagTestSymbols } from './test-detection.js';
+const BUILTIN_REGISTRY = createLanguageRegistry();
+export const IGNORED_DIRS = new Set(['.git', 'node_modules', '.tmp', '.automatosx', '.terraform', 'vendor', 'bin', 'obj']);
+const DEFAULT_MAX_FILES = 500;
+const DEFAULT_SYMBOL_LIMIT = 100;
+// Batch size of the spill a streaming build writes without a memory limit.
+const STREAM_BATCH_BYTES = 1024 * 1024;
+const SYMBOL_TAGGERS = [
+    tagConcurrencySymbols,
+    tagOwnershipSymbols,
+    tagCfgSymbols,
+    tagAttributeSymbols,
+    tagValueSymbols,
+    addSymbolSpans,
+    addStableSymbolIds,
+];
+export function getCodeIndexPath(basePath) {
+    return join(basePath, '.automatosx', 'index', 'code-index.json');
+}
+export function detectCodeLanguage(fileName, content, registry = BUILTIN_REGISTRY) {
+    return registry.detect(fileName, content);
+}
+export function extractCodeSymbols(fileName, content, registry = BUILTIN_REGISTRY) {
+    const extractor = detectCodeLanguage(fileName, content, registry);
+    if (extractor === undefined) {
+        return undefined;
+    }
+    return {
+        file: fileName,
+        language: extractor.language,
+        symbols: tagSymbols(extractor.extract(content, fileName), fileName, content),
+    };
+}
+/**
+ * Parses an in-memory buffer (an unsaved editor buffer, stdin) without reading the file system.
+ */
+export function parseCodeSource(content, options = {}) {
+    const registry = options.registry ?? BUILTIN_REGISTRY;
+    const file = options.file ?? '<buffer>';
+    const extractor = options.language !== undefined
+        ? registry.list().find((candidate) => candidate.language === options.language)
+        : detectCodeLanguage(file, content, registry);
+    if (extractor === undefined) {
+        const languages = registry.list().map((candidate) => candidate.language).join(', ');
+        throw new Error(options.language !== undefined
+            ? `Unknown code language "${options.language}". Available: ${languages}.`
+            : `Could not detect a language for ${file}; pass one of: ${languages}.`);
+    }
+    return { file, language: extractor.language, symbols: tagSymbols(extractor.extract(content, file), file, content) };
+}
+/**
+ * Like {@link parseCodeSource}, with the workspace's custom grammars available.
+ */
+export async function parseWorkspaceCodeSource(request) {
+    const { registry, warnings } = await loadWorkspaceLanguageRegistry(request.basePath);
+    return {
+        ...parseCodeSource(request.content, { language: request.language, file: request.file, registry }),
+        ...(warnings.length > 0 ? { warnings } : {}),
+    };
+}
+export async function buildCodeIndex(request) {
+    const { registry, warnings } = await loadWorkspaceLanguageRegistry(request.basePath);
+    const config = await readCodeIntelConfig(request.basePath);
+    const cache = config?.parseCache === false ? undefined : openParseCache();
+    const maxMemoryMb = request.maxMemoryMb ?? readMaxMemoryMb(config);
+    if (maxMemoryMb !== undefined || request.onFile !== undefined) {
+        return buildBoundedCodeIndex({ ...request, registry, maxMemoryMb, cache }, warnings);
+    }
+    const files = await collectCodeFiles({ ...request, registry, cache });
+    const { indexPath, snapshot } = await writeCodeIndex(request.basePath, files);
+    const languages = {};
+    for (const entry of files) {
+        languages[entry.language] = (languages[entry.language] ?? 0) + 1;
+    }
+    return {
+        indexPath,
+        generatedAt: snapshot.generatedAt,
+        filesIndexed: files.length,
+        symbolCount: files.reduce((total, entry) => total + entry.symbols.length, 0),
+        languages,
+        ...(cache !== undefined ? { cached: cache.hits } : {}),
+        ...(warnings.length > 0 ? { warnings } : {}),
+    };
+}
+/**
+ * Extracts symbols from every indexable file under `paths` (the whole workspace by default), up
+ * to `maxFiles`, without merging or writing anything.
+ */
+export async function collectCodeFiles(request) {
+    const files = [];
+    await walkCodeFiles(request, {
+        count: 0,
+        async add(entry) {
+            files.push(entry);
+            await request.onFile?.(entry);
+        },
+    });
+    return files;
+}
+/**
+ * Indexes like {@link buildCodeIndex} while holding at most about `maxMemoryMb` of parsed files,
+ * or without a limit on the files parsed when streaming to `onFile`. Files are parsed one at a
+ * time and every batch write is awaited before the next parse, so nothing queues up; files too
+ * large to parse within the budget are skipped with a warning. Files declaring C# partial types
+ * stay in memory until the end, where they are merged.
+ */
+async function buildBoundedCodeIndex(request, warnings) {
+    // An eighth of the budget per batch of serialized entries; a file being parsed takes several
+    // times its size as text and symbols, so it may use a quarter.
+    const budget = request.maxMemoryMb === undefined ? undefined : request.maxMemoryMb * 1024 * 1024;
+    const indexPath = getCodeIndexPath(request.basePath);
+    const spill = await createIndexSpill(indexPath, budget === undefined ? STREAM_BATCH_BYTES : Math.floor(budget / 8));
+    const retained = [];
+    const languages = {};
+    const skipped = [];
+    let symbolCount = 0;
+    let generatedAt;
+    let filesIndexed;
+    try {
+        filesIndexed = await walkCodeFiles(request, {
+            count: 0,
+            ...(budget !== undefined ? { maxFileBytes: Math.floor(budget / 4) } : {}),
+            async add(entry) {
+                languages[entry.language] = (languages[entry.language] ?? 0) + 1;
+                await request.onFile?.(entry);
+                if (entry.language === 'csharp' && entry.symbols.some((symbol) => symbol.attributes?.partial === 'true')) {
+                    retained.push(entry);
+                    return;
+                }
+                symbolCount += entry.symbols.length;
+                await spill.add(entry);
+            },
+            skip(file, bytes) {
+                skipped.push(`Skipped ${file} (${Math.ceil(bytes / 1024)} KB): too large to parse within the ${request.maxMemoryMb} MB index memory limit.`);
+            },
+        });
+        retained.sort((left, right) => left.file.localeCompare(right.file));
+        mergePartialTypes(retained);
+        symbolCount += retained.reduce((total, entry) => total + entry.symbols.length, 0);
+        ({ generatedAt } = await spill.finish(retained));
+    }
+    catch (error) {
+        await spill.discard();
+        throw error;
+    }
+    const allWarnings = [...warnings, ...skipped];
+    return {
+        indexPath,
+        generatedAt,
+        filesIndexed,
+        symbolCount,
+        languages,
+        ...(request.cache !== undefined ? { cached: request.cache.hits } : {}),
+        ...(allWarnings.length > 0 ? { warnings: allWarnings } : {}),
+    };
+}
+/**
+ * Sorts the per-file entries, merges C# partial types across files and writes the snapshot.
+ * Entries are modified in place by the merge; pass copies to keep the unmerged ones.
+ */
+export async function writeCodeIndex(basePath, files) {
+    files.sort((left, right) => left.file.localeCompare(right.file));
+    mergePartialTypes(files);
+    const snapshot = {
+        version: 1,
+        generatedAt: new Date().toISOString(),
+        files,
+    };
+    const indexPath = getCodeIndexPath(basePath);
+    await mkdir(join(basePath, '.automatosx', 'index'), { recursive: true });
+    await writeFile(indexPath, `${JSON.stringify(snapshot, null, 2)}\n`, 'utf8');
+    return { indexPath, snapshot };
+}
+export async function readCodeIndex(basePath) {
+    try {
+        const raw = await readFile(getCodeIndexPath(basePath), 'utf8');
+        const parsed = JSON.parse(raw);
+        if (parsed.version !== 1 || !Array.isArray(parsed.files)) {
+            return undefined;
+        }
+        return parsed;
+    }
+    catch {
+        return undefined;
+    }
+}
+export async function searchCodeSymbols(request) {
+    const snapshot = await readCodeIndex(request.basePath);
+    return snapshot === undefined ? [] : filterCodeSymbols(snapshot, request);
+}
+export function filterCodeSymbols(snapshot, request) {
+    const query = request.query?.toLowerCase();
+    const limit = request.limit ?? DEFAULT_SYMBOL_LIMIT;
+    const cfgOptions = { features: request.features, target: request.target };
+    const results = [];
+    for (const entry of snapshot.files) {
+        if (request.language !== undefined && entry.language !== request.language) {
+            continue;
+        }
+        for (const symbol of entry.symbols) {
+            if (request.id !== undefined && symbol.stableId !== request.id) {
+                continue;
+            }
+            if (request.kind !== undefined && symbol.kind !== request.kind) {
+                continue;
+            }
+            if (request.file !== undefined && !(symbol.locations ?? [symbol]).some((location) => matchesFile(location.file, request.file))) {
+                continue;
+            }
+            if (query !== undefined && !symbol.name.toLowerCase().includes(query)) {
+                continue;
+            }
+            if (request.tests !== undefined && (symbol.isTest === true) !== (request.tests === 'only')) {
+                continue;
+            }
+            if (request.concurrent === true && !isConcurrentSymbol(symbol)) {
+                continue;
+            }
+            if (!isCfgActive(symbol.cfg, cfgOptions)) {
+                continue;
+            }
+            if (request.derive !== undefined && !derivesTrait(symbol, request.derive)) {
+                continue;
+            }
+            if (request.attribute !== undefined && !hasAnnotation(symbol, request.attribute)) {
+                continue;
+            }
+            results.push(symbol);
+            if (results.length >= limit) {
+                return results;
+            }
+        }
+    }
+    return results;
+}
+function tagSymbols(symbols, file, content) {
+    return SYMBOL_TAGGERS.reduce((tagged, tag) => tag(tagged, content), tagTestSymbols(symbols, file, content));
+}
+function readMaxMemoryMb(config) {
+    const value = config?.maxMemoryMb;
+    return typeof value === 'number' && Number.isFinite(value) && value > 0 ? value : undefined;
+}
+function matchesFile(file, filter) {
+    return file === filter || file.startsWith(`${filter}/`);
+}
+async function walkCodeFiles(request, sink) {
+    const maxFiles = request.maxFiles ?? DEFAULT_MAX_FILES;
+    const paths = request.paths !== undefined && request.paths.length > 0 ? request.paths : ['.'];
+    for (const rawPath of paths) {
+        await visit(resolve(request.basePath, rawPath), request.basePath, sink, maxFiles, request.registry, request.cache, request.signal);
+        if (sink.count >= maxFiles) {
+            break;
+        }
+    }
+    return sink.count;
+}
+async function visit(filePath, basePath, sink, maxFiles, registry, cache, signal) {
+    signal?.throwIfAborted();
+    if (sink.count >= maxFiles) {
+        return;
+    }
+    let stats;
+    try {
+        stats = await stat(filePath);
+    }
+    catch {
+        return;
+    }
+    if (stats.isDirectory()) {
+        let entries;
+        try {
+            entries = await readdir(filePath, { withFileTypes: true, encoding: 'utf8' });
+        }
+        catch {
+            return;
+        }
+        entries.sort((left, right) => left.name.localeCompare(right.name));
+        for (const entry of entries) {
+            if (IGNORED_DIRS.has(entry.name)) {
+                continue;
+            }
+            await visit(join(filePath, entry.name), basePath, sink, maxFiles, registry, cache, signal);
+            if (sink.count >= maxFiles) {
+                break;
+            }
+        }
+        return;
+    }
+    if (!stats.isFile()) {
+        return;
+    }
+    const file = relative(basePath, filePath).split(sep).join('/');
+    const size = Number(stats.size);
+    if (sink.maxFileBytes !== undefined && size > sink.maxFileBytes) {
+        if (detectCodeLanguage(file, undefined, registry) !== undefined) {
+            sink.skip?.(file, size);
+        }
+        return;
+    }
+    const indexed = await indexCodeFile(basePath, file, registry, cache);
+    if (indexed !== undefined) {
+        sink.count += 1;
+        await sink.add(indexed);
+    }
+}
+/**
+ * Extracts the symbols of one workspace-relative file, or `undefined` when it is unreadable or
+ * no extractor claims it. With a `cache`, unchanged files are not parsed again.
+ */
+export async function indexCodeFile(basePath, file, registry, cache) {
+    const matchesByName = detectCodeLanguage(file, undefined, registry) !== undefined;
+    if (!matchesByName && extname(file) !== '') {
+        return undefined;
+    }
+    let content;
+    try {
+        content = await readFile(join(basePath, file), 'utf8');
+    }
+    catch {
+        return undefined;
+    }
+    try {
+        const extract = () => extractCodeSymbols(file, content, registry);
+        return cache !== undefined ? await cache.get(file, content, registry, extract) : extract();
+    }
+    catch {
+        // A user-supplied grammar that cannot parse a file should not abort the whole index.
+        return undefined;
+    }
+}
+export { CACHE_DIR_ENV_VAR } from './parse-cache.js';
+export { createLanguageRegistry, GRAMMAR_RUNTIME_ENV_VAR, loadWorkspaceLanguageRegistry } from './registry.js';
+export { createQueryExtractor } from './grammar.js';
+export { isTestFile } from './test-detection.js';
+export { CONCURRENCY_PRIMITIVES } from './concurrency.js';
+export { isCfgActive } from './rust-cfg.js';
+export { BORROW_MODES } from './ownership.js';
+export { createPositionMapper } from './positions.js';
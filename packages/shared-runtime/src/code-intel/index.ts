@@ -0,0 +1,497 @@
+import { mkdir, readFile, readdir, stat, writeFile } from 'node:fs/promises';
+import { extname, join, relative, resolve, sep } from 'node:path';
+import { mergePartialTypes } from './csharp.js';
+import { createIndexSpill } from './index-spill.js';
+import { openParseCache, type ParseCache } from './parse-cache.js';
+import { createLanguageRegistry, loadWorkspaceLanguageRegistry, readCodeIntelConfig, type LanguageRegistry } from './registry.js';
+import { isConcurrentSymbol, tagConcurrencySymbols } from './concurrency.js';
+import { tagOwnershipSymbols } from './ownership.js';
+import { derivesTrait, hasAnnotation, tagAttributeSymbols } from './rust-attributes.js';
+import { isCfgActive, tagCfgSymbols, type RustCfgOptions } from './rust-cfg.js';
+import { tagValueSymbols } from './rust-values.js';
+import { addSymbolSpans } from './positions.js';
+import { addStableSymbolIds } from './symbol-id.js';
+import { tagTestSymbols } from './test-detection.js';
+import type {
+  CodeFileIndex,
+  CodeLanguage,
+  CodeLanguageExtractor,
+  CodeSymbol,
+  CodeSymbolKind,
+} from './types.js';
+
+export interface RuntimeCodeIndexRequest {
+  paths?: string[];
+  maxFiles?: number;
+  basePath: string;
+  /**
+   * Called with each file's symbols as soon as it is parsed, before C# partial types are merged
+   * and the index is written, so a caller can stream them out. Awaited, which gives backpressure.
+   * The build then spills parsed files to disk as it goes instead of holding them all.
+   */
+  onFile?: (entry: CodeFileIndex) => void | Promise<void>;
+  /**
+   * Bounds the memory the build holds, for CI runners: parsed files are spilled to disk in batches
+   * and the index is written from there. Defaults to `codeIntel.maxMemoryMb` in the workspace config;
+   * unbounded when neither is set.
+   */
+  maxMemoryMb?: number;
+  /** Aborting stops the walk between files and rejects with the signal's reason; no index is written. */
+  signal?: AbortSignal;
+}
+
+export interface RuntimeCodeIndexResponse {
+  indexPath: string;
+  generatedAt: string;
+  filesIndexed: number;
+  symbolCount: number;
+  languages: Partial<Record<CodeLanguage, number>>;
+  /** Files whose symbols came from the parse cache instead of being parsed again. */
+  cached?: number;
+  warnings?: string[];
+}
+
+export interface RuntimeCodeSymbolQuery {
+  query?: string;
+  /** A `stableId`; matches the symbol wherever it now lives. */
+  id?: string;
+  kind?: CodeSymbolKind;
+  language?: CodeLanguage;
+  file?: string;
+  /** `only` keeps test code, `exclude` drops it; both included when omitted. */
+  tests?: CodeTestFilter;
+  /** Keeps only async functions and functions that use concurrency primitives. */
+  concurrent?: boolean;
+  /** Rust: keeps symbols deriving this trait (`Serialize` also matches `serde::Serialize` and `cfg_attr` derives). */
+  derive?: string;
+  /** Rust: keeps symbols carrying this attribute (`tokio::main`, or just `main`). */
+  attribute?: string;
+  /** Rust: drops symbols whose `cfg` cannot hold with these features enabled (all others off). */
+  features?: string[];
+  /** Rust: drops symbols whose `cfg` cannot hold on this target (`windows`, `linux`, a triple, ...). */
+  target?: string;
+  limit?: number;
+  basePath: string;
+}
+
+export type CodeTestFilter = 'only' | 'exclude';
+
+export interface RuntimeCodeParseRequest {
+  content: string;
+  /** Extractor to use; detected from `file` (and a shebang) when omitted. */
+  language?: CodeLanguage;
+  /** Path the buffer belongs to; only labels the returned symbols. */
+  file?: string;
+  basePath: string;
+}
+
+export interface CodeIndexSnapshot {
+  version: 1;
+  generatedAt: string;
+  files: CodeFileIndex[];
+}
+
+const BUILTIN_REGISTRY = createLanguageRegistry();
+export const IGNORED_DIRS = new Set(['.git', 'node_modules', '.tmp', '.automatosx', '.terraform', 'vendor', 'bin', 'obj']);
+const DEFAULT_MAX_FILES = 500;
+const DEFAULT_SYMBOL_LIMIT = 100;
+// Batch size of the spill a streaming build writes without a memory limit.
+const STREAM_BATCH_BYTES = 1024 * 1024;
+const SYMBOL_TAGGERS: Array<(symbols: CodeSymbol[], content: string) => CodeSymbol[]> = [
+  tagConcurrencySymbols,
+  tagOwnershipSymbols,
+  tagCfgSymbols,
+  tagAttributeSymbols,
+  tagValueSymbols,
+  addSymbolSpans,
+  addStableSymbolIds,
+];
+
+export function getCodeIndexPath(basePath: string): string {
+  return join(basePath, '.automatosx', 'index', 'code-index.json');
+}
+
+export function detectCodeLanguage(
+  fileName: string,
+  content?: string,
+  registry: LanguageRegistry = BUILTIN_REGISTRY,
+): CodeLanguageExtractor | undefined {
+  return registry.detect(fileName, content);
+}
+
+export function extractCodeSymbols(
+  fileName: string,
+  content: string,
+  registry: LanguageRegistry = BUILTIN_REGISTRY,
+): CodeFileIndex | undefined {
+  const extractor = detectCodeLanguage(fileName, content, registry);
+  if (extractor === undefined) {
+    return undefined;
+  }
+  return {
+    file: fileName,
+    language: extractor.language,
+    symbols: tagSymbols(extractor.extract(content, fileName), fileName, content),
+  };
+}
+
+/**
+ * Parses an in-memory buffer (an unsaved editor buffer, stdin) without reading the file system.
+ */
+export function parseCodeSource(
+  content: string,
+  options: { language?: CodeLanguage; file?: string; registry?: LanguageRegistry } = {},
+): CodeFileIndex {
+  const registry = options.registry ?? BUILTIN_REGISTRY;
+  const file = options.file ?? '<buffer>';
+  const extractor = options.language !== undefined
+    ? registry.list().find((candidate) => candidate.language === options.language)
+    : detectCodeLanguage(file, content, registry);
+  if (extractor === undefined) {
+    const languages = registry.list().map((candidate) => candidate.language).join(', ');
+    throw new Error(options.language !== undefined
+      ? `Unknown code language "${options.language}". Available: ${languages}.`
+      : `Could not detect a language for ${file}; pass one of: ${languages}.`);
+  }
+  return { file, language: extractor.language, symbols: tagSymbols(extractor.extract(content, file), file, content) };
+}
+
+/**
+ * Like {@link parseCodeSource}, with the workspace's custom grammars available.
+ */
+export async function parseWorkspaceCodeSource(request: RuntimeCodeParseRequest): Promise<CodeFileIndex & { warnings?: string[] }> {
+  const { registry, warnings } = await loadWorkspaceLanguageRegistry(request.basePath);
+  return {
+    ...parseCodeSource(request.content, { language: request.language, file: request.file, registry }),
+    ...(warnings.length > 0 ? { warnings } : {}),
+  };
+}
+
+export async function buildCodeIndex(request: RuntimeCodeIndexRequest): Promise<RuntimeCodeIndexResponse> {
+  const { registry, warnings } = await loadWorkspaceLanguageRegistry(request.basePath);
+  const config = await readCodeIntelConfig(request.basePath);
+  const cache = config?.parseCache === false ? undefined : openParseCache();
+  const maxMemoryMb = request.maxMemoryMb ?? readMaxMemoryMb(config);
+  if (maxMemoryMb !== undefined || request.onFile !== undefined) {
+    return buildBoundedCodeIndex({ ...request, registry, maxMemoryMb, cache }, warnings);
+  }
+  const files = await collectCodeFiles({ ...request, registry, cache });
+  const { indexPath, snapshot } = await writeCodeIndex(request.basePath, files);
+
+  const languages: Partial<Record<CodeLanguage, number>> = {};
+  for (const entry of files) {
+    languages[entry.language] = (languages[entry.language] ?? 0) + 1;
+  }
+
+  return {
+    indexPath,
+    generatedAt: snapshot.generatedAt,
+    filesIndexed: files.length,
+    symbolCount: files.reduce((total, entry) => total + entry.symbols.length, 0),
+    languages,
+    ...(cache !== undefined ? { cached: cache.hits } : {}),
+    ...(warnings.length > 0 ? { warnings } : {}),
+  };
+}
+
+/**
+ * Extracts symbols from every indexable file under `paths` (the whole workspace by default), up
+ * to `maxFiles`, without merging or writing anything.
+ */
+export async function collectCodeFiles(request: RuntimeCodeIndexRequest & { registry: LanguageRegistry; cache?: ParseCache }): Promise<CodeFileIndex[]> {
+  const files: CodeFileIndex[] = [];
+  await walkCodeFiles(request, {
+    count: 0,
+    async add(entry) {
+      files.push(entry);
+      await request.onFile?.(entry);
+    },
+  });
+  return files;
+}
+
+/**
+ * Indexes like {@link buildCodeIndex} while holding at most about `maxMemoryMb` of parsed files,
+ * or without a limit on the files parsed when streaming to `onFile`. Files are parsed one at a
+ * time and every batch write is awaited before the next parse, so nothing queues up; files too
+ * large to parse within the budget are skipped with a warning. Files declaring C# partial types
+ * stay in memory until the end, where they are merged.
+ */
+async function buildBoundedCodeIndex(
+  request: RuntimeCodeIndexRequest & { registry: LanguageRegistry; maxMemoryMb?: number; cache?: ParseCache },
+  warnings: string[],
+): Promise<RuntimeCodeIndexResponse> {
+  // An eighth of the budget per batch of serialized entries; a file being parsed takes several
+  // times its size as text and symbols, so it may use a quarter.
+  const budget = request.maxMemoryMb === undefined ? undefined : request.maxMemoryMb * 1024 * 1024;
+  const indexPath = getCodeIndexPath(request.basePath);
+  const spill = await createIndexSpill(indexPath, budget === undefined ? STREAM_BATCH_BYTES : Math.floor(budget / 8));
+  const retained: CodeFileIndex[] = [];
+  const languages: Partial<Record<CodeLanguage, number>> = {};
+  const skipped: string[] = [];
+  let symbolCount = 0;
+
+  let generatedAt: string;
+  let filesIndexed: number;
+  try {
+    filesIndexed = await walkCodeFiles(request, {
+      count: 0,
+      ...(budget !== undefined ? { maxFileBytes: Math.floor(budget / 4) } : {}),
+      async add(entry) {
+        languages[entry.language] = (languages[entry.language] ?? 0) + 1;
+        await request.onFile?.(entry);
+        if (entry.language === 'csharp' && entry.symbols.some((symbol) => symbol.attributes?.partial === 'true')) {
+          retained.push(entry);
+          return;
+        }
+        symbolCount += entry.symbols.length;
+        await spill.add(entry);
+      },
+      skip(file, bytes) {
+        skipped.push(`Skipped ${file} (${Math.ceil(bytes / 1024)} KB): too large to parse within the ${request.maxMemoryMb} MB index memory limit.`);
+      },
+    });
+    retained.sort((left, right) => left.file.localeCompare(right.file));
+    mergePartialTypes(retained);
+    symbolCount += retained.reduce((total, entry) => total + entry.symbols.length, 0);
+    ({ generatedAt } = await spill.finish(retained));
+  } catch (error) {
+    await spill.discard();
+    throw error;
+  }
+
+  const allWarnings = [...warnings, ...skipped];
+  return {
+    indexPath,
+    generatedAt,
+    filesIndexed,
+    symbolCount,
+    languages,
+    ...(request.cache !== undefined ? { cached: request.cache.hits } : {}),
+    ...(allWarnings.length > 0 ? { warnings: allWarnings } : {}),
+  };
+}
+
+/**
+ * Sorts the per-file entries, merges C# partial types across files and writes the snapshot.
+ * Entries are modified in place by the merge; pass copies to keep the unmerged ones.
+ */
+export async function writeCodeIndex(basePath: string, files: CodeFileIndex[]): Promise<{ indexPath: string; snapshot: CodeIndexSnapshot }> {
+  files.sort((left, right) => left.file.localeCompare(right.file));
+  mergePartialTypes(files);
+
+  const snapshot: CodeIndexSnapshot = {
+    version: 1,
+    generatedAt: new Date().toISOString(),
+    files,
+  };
+  const indexPath = getCodeIndexPath(basePath);
+  await mkdir(join(basePath, '.automatosx', 'index'), { recursive: true });
+  await writeFile(indexPath, `${JSON.stringify(snapshot, null, 2)}\n`, 'utf8');
+  return { indexPath, snapshot };
+}
+
+export async function readCodeIndex(basePath: string): Promise<CodeIndexSnapshot | undefined> {
+  try {
+    const raw = await readFile(getCodeIndexPath(basePath), 'utf8');
+    const parsed = JSON.parse(raw) as Partial<CodeIndexSnapshot>;
+    if (parsed.version !== 1 || !Array.isArray(parsed.files)) {
+      return undefined;
+    }
+    return parsed as CodeIndexSnapshot;
+  } catch {
+    return undefined;
+  }
+}
+
+export async function searchCodeSymbols(request: RuntimeCodeSymbolQuery): Promise<CodeSymbol[]> {
+  const snapshot = await readCodeIndex(request.basePath);
+  return snapshot === undefined ? [] : filterCodeSymbols(snapshot, request);
+}
+
+export function filterCodeSymbols(snapshot: CodeIndexSnapshot, request: Omit<RuntimeCodeSymbolQuery, 'basePath'>): CodeSymbol[] {
+  const query = request.query?.toLowerCase();
+  const limit = request.limit ?? DEFAULT_SYMBOL_LIMIT;
+  const cfgOptions: RustCfgOptions = { features: request.features, target: request.target };
+  const results: CodeSymbol[] = [];
+
+  for (const entry of snapshot.files) {
+    if (request.language !== undefined && entry.language !== request.language) {
+      continue;
+    }
+    for (const symbol of entry.symbols) {
+      if (request.id !== undefined && symbol.stableId !== request.id) {
+        continue;
+      }
+      if (request.kind !== undefined && symbol.kind !== request.kind) {
+        continue;
+      }
+      if (request.file !== undefined && !(symbol.locations ?? [symbol]).some((location) => matchesFile(location.file, request.file!))) {
+        continue;
+      }
+      if (query !== undefined && !symbol.name.toLowerCase().includes(query)) {
+        continue;
+      }
+      if (request.tests !== undefined && (symbol.isTest === true) !== (request.tests === 'only')) {
+        continue;
+      }
+      if (request.concurrent === true && !isConcurrentSymbol(symbol)) {
+        continue;
+      }
+      if (!isCfgActive(symbol.cfg, cfgOptions)) {
+        continue;
+      }
+      if (request.derive !== undefined && !derivesTrait(symbol, request.derive)) {
+        continue;
+      }
+      if (request.attribute !== undefined && !hasAnnotation(symbol, request.attribute)) {
+        continue;
+      }
+      results.push(symbol);
+      if (results.length >= limit) {
+        return results;
+      }
+    }
+  }
+
+  return results;
+}
+
+function tagSymbols(symbols: CodeSymbol[], file: string, content: string): CodeSymbol[] {
+  return SYMBOL_TAGGERS.reduce((tagged, tag) => tag(tagged, content), tagTestSymbols(symbols, file, content));
+}
+
+function readMaxMemoryMb(config: Record<string, unknown> | undefined): number | undefined {
+  const value = config?.maxMemoryMb;
+  return typeof value === 'number' && Number.isFinite(value) && value > 0 ? value : undefined;
+}
+
+function matchesFile(file: string, filter: string): boolean {
+  return file === filter || file.startsWith(`${filter}/`);
+}
+
+// Where the walk hands parsed files: kept in a list, or spilled by the bounded build.
+interface CodeFileSink {
+  count: number;
+  /** Larger files are reported to `skip` instead of being read. */
+  maxFileBytes?: number;
+  add(entry: CodeFileIndex): Promise<void>;
+  skip?(file: string, bytes: number): void;
+}
+
+async function walkCodeFiles(request: RuntimeCodeIndexRequest & { registry: LanguageRegistry; cache?: ParseCache }, sink: CodeFileSink): Promise<number> {
+  const maxFiles = request.maxFiles ?? DEFAULT_MAX_FILES;
+  const paths = request.paths !== undefined && request.paths.length > 0 ? request.paths : ['.'];
+  for (const rawPath of paths) {
+    await visit(resolve(request.basePath, rawPath), request.basePath, sink, maxFiles, request.registry, request.cache, request.signal);
+    if (sink.count >= maxFiles) {
+      break;
+    }
+  }
+  return sink.count;
+}
+
+async function visit(
+  filePath: string,
+  basePath: string,
+  sink: CodeFileSink,
+  maxFiles: number,
+  registry: LanguageRegistry,
+  cache?: ParseCache,
+  signal?: AbortSignal,
+): Promise<void> {
+  signal?.throwIfAborted();
+  if (sink.count >= maxFiles) {
+    return;
+  }
+
+  let stats: Awaited<ReturnType<typeof stat>>;
+  try {
+    stats = await stat(filePath);
+  } catch {
+    return;
+  }
+
+  if (stats.isDirectory()) {
+    let entries;
+    try {
+      entries = await readdir(filePath, { withFileTypes: true, encoding: 'utf8' });
+    } catch {
+      return;
+    }
+    entries.sort((left, right) => left.name.localeCompare(right.name));
+    for (const entry of entries) {
+      if (IGNORED_DIRS.has(entry.name)) {
+        continue;
+      }
+      await visit(join(filePath, entry.name), basePath, sink, maxFiles, registry, cache, signal);
+      if (sink.count >= maxFiles) {
+        break;
+      }
+    }
+    return;
+  }
+
+  if (!stats.isFile()) {
+    return;
+  }
+
+  const file = relative(basePath, filePath).split(sep).join('/');
+  const size = Number(stats.size);
+  if (sink.maxFileBytes !== undefined && size > sink.maxFileBytes) {
+    if (detectCodeLanguage(file, undefined, registry) !== undefined) {
+      sink.skip?.(file, size);
+    }
+    return;
+  }
+  const indexed = await indexCodeFile(basePath, file, registry, cache);
+  if (indexed !== undefined) {
+    sink.count += 1;
+    await sink.add(indexed);
+  }
+}
+
+/**
+ * Extracts the symbols of one workspace-relative file, or `undefined` when it is unreadable or
+ * no extractor claims it. With a `cache`, unchanged files are not parsed again.
+ */
+export async function indexCodeFile(basePath: string, file: string, registry: LanguageRegistry, cache?: ParseCache): Promise<CodeFileIndex | undefined> {
+  const matchesByName = detectCodeLanguage(file, undefined, registry) !== undefined;
+  if (!matchesByName && extname(file) !== '') {
+    return undefined;
+  }
+
+  let content: string;
+  try {
+    content = await readFile(join(basePath, file), 'utf8');
+  } catch {
+    return undefined;
+  }
+
+  try {
+    const extract = () => extractCodeSymbols(file, content, registry);
+    return cache !== undefined ? await cache.get(file, content, registry, extract) : extract();
+  } catch {
+    // A user-supplied grammar that cannot parse a file should not abort the whole index.
+    return undefined;
+  }
+}
+
+export { CACHE_DIR_ENV_VAR, type ParseCache } from './parse-cache.js';
+export { createLanguageRegistry, GRAMMAR_RUNTIME_ENV_VAR, loadWorkspaceLanguageRegistry, type LanguageRegistry } from './registry.js';
+export { createQueryExtractor, type GrammarRegistration, type GrammarRuntime } from './grammar.js';
+export { isTestFile } from './test-detection.js';
+export { CONCURRENCY_PRIMITIVES, type ConcurrencyPrimitive } from './concurrency.js';
+export { isCfgActive, type RustCfgOptions } from './rust-cfg.js';
+export type { SymbolAnnotation } from './rust-attributes.js';
+export { BORROW_MODES, type BorrowMode, type OwnershipParameter, type OwnershipSummary, type ReceiverMode } from './ownership.js';
+export { createPositionMapper, type PositionMapper, type SourcePosition, type SourceSpan } from './positions.js';
+export type {
+  BuiltinCodeLanguage,
+  CodeFileIndex,
+  CodeLanguage,
+  CodeLanguageExtractor,
+  CodeSymbol,
+  CodeSymbolKind,
+  CodeSymbolLocation,
+} from './types.js';
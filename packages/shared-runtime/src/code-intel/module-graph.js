@@ -0,0 +1,195 @@
+import { readFile, readdir, stat } from 'node:fs/promises';
+import { extname, join, posix, relative, sep } from 'node:path';
+import { IGNORED_DIRS } from './index.js';
+const IMPORT_SYNTAX_BY_EXTENSION = {
+    '.ts': 'javascript',
+    '.tsx': 'javascript',
+    '.mts': 'javascript',
+    '.cts': 'javascript',
+    '.js': 'javascript',
+    '.jsx': 'javascript',
+    '.mjs': 'javascript',
+    '.cjs': 'javascript',
+    '.py': 'python',
+    '.rb': 'ruby',
+    '.php': 'php',
+    '.cs': 'csharp',
+    '.sh': 'shell',
+    '.bash': 'shell',
+    '.tf': 'hcl',
+};
+const IMPORT_PATTERNS = {
+    javascript: [
+        /^\s*(?:import|export)\b[^'"]*?\bfrom\s*['"]([^'"]+)['"]/,
+        /^\s*import\s*['"]([^'"]+)['"]/,
+        /\brequire\(\s*['"]([^'"]+)['"]\s*\)/,
+        /\bimport\(\s*['"]([^'"]+)['"]\s*\)/,
+    ],
+    python: [
+        /^\s*from\s+([.\w]+)\s+import\b/,
+        /^\s*import\s+([\w.]+)/,
+    ],
+    ruby: [/^\s*require(?:_relative)?\s*\(?\s*['"]([^'"]+)['"]/],
+    php: [
+        /^\s*use\s+(?:function\s+|const\s+)?\\?([\w\\]+)/,
+        /^\s*(?:require|include)(?:_once)?\s*\(?\s*(?:__DIR__\s*\.\s*)?['"]([^'"]+)['"]/,
+    ],
+    csharp: [/^\s*(?:global\s+)?using\s+(?:static\s+)?([\w.]+)\s*;/],
+    shell: [/^\s*(?:source|\.)\s+['"]?([^\s'";]+)/],
+    hcl: [/^\s*source\s*=\s*"([^"]+)"/],
+};
+const RESOLVABLE_EXTENSIONS = {
+    javascript: ['.ts', '.tsx', '.mts', '.js', '.jsx', '.mjs', '/index.ts', '/index.js'],
+    python: ['.py', '/__init__.py'],
+    ruby: ['.rb'],
+    php: [],
+    csharp: [],
+    shell: [],
+    hcl: [],
+};
+const DEFAULT_MAX_FILES = 2_000;
+/**
+ * Scans source files for import-style statements and resolves relative specifiers to workspace
+ * files, giving `ax ask` a module dependency graph alongside the symbol index.
+ */
+export async function buildModuleGraph(basePath, options = {}) {
+    const files = await listWorkspaceFiles(basePath, {
+        maxFiles: options.maxFiles,
+        accept: (file) => !file.endsWith('.d.ts') && IMPORT_SYNTAX_BY_EXTENSION[extname(file).toLowerCase()] !== undefined,
+    });
+    const known = new Set(files);
+    // Emitted JavaScript next to its TypeScript source would otherwise show up as a second module.
+    const sources = files.filter((file) => !isEmittedSibling(file, known));
+    const modules = [];
+    for (const file of sources) {
+        const syntax = IMPORT_SYNTAX_BY_EXTENSION[extname(file).toLowerCase()];
+        let content;
+        try {
+            content = await readFile(join(basePath, file), 'utf8');
+        }
+        catch {
+            continue;
+        }
+        modules.push({ file, imports: extractImports(content, syntax, file, known) });
+    }
+    return { modules };
+}
+function extractImports(content, syntax, file, known) {
+    const imports = [];
+    const lines = content.split(/\r?\n/);
+    for (let index = 0; index < lines.length; index += 1) {
+        const line = lines[index] ?? '';
+        for (const pattern of IMPORT_PATTERNS[syntax]) {
+            const match = pattern.exec(line);
+            if (match === null) {
+                continue;
+            }
+            const specifier = match[1];
+            const resolved = resolveSpecifier(specifier, syntax, file, known);
+            imports.push({ specifier, line: index + 1, ...(resolved !== undefined ? { resolved } : {}) });
+            break;
+        }
+    }
+    return imports;
+}
+function resolveSpecifier(specifier, syntax, file, known) {
+    const fromDir = posix.dirname(file);
+    let candidate;
+    if (syntax === 'python') {
+        const leadingDots = /^\.*/.exec(specifier)[0].length;
+        const modulePath = specifier.slice(leadingDots).replace(/\./g, '/');
+        let root = leadingDots === 0 ? '' : fromDir;
+        for (let level = 1; level < leadingDots; level += 1) {
+            root = posix.dirname(root);
+        }
+        candidate = posix.join(root, modulePath);
+    }
+    else if (specifier.startsWith('./') || specifier.startsWith('../') || syntax === 'shell' || syntax === 'php') {
+        candidate = posix.join(fromDir, specifier.replace(/^\$\{?\w+\}?\//, ''));
+    }
+    else if (syntax === 'ruby') {
+        candidate = specifier.startsWith('.') ? posix.join(fromDir, specifier) : specifier;
+    }
+    else {
+        return undefined;
+    }
+    candidate = posix.normalize(candidate).replace(/^\.\//, '');
+    // TypeScript sources import their emitted `.js` siblings, so prefer the source file.
+    const withoutJs = candidate.replace(/\.(?:m|c)?js$/, '');
+    for (const extension of RESOLVABLE_EXTENSIONS[syntax]) {
+        if (known.has(`${withoutJs}${extension}`)) {
+            return `${withoutJs}${extension}`;
+        }
+    }
+    return known.has(candidate) ? candidate : undefined;
+}
+/**
+ * Lists workspace files (relative, `/`-separated, sorted), skipping ignored and build directories.
+ */
+export async function listWorkspaceFiles(basePath, options = {}) {
+    const files = [];
+    await collectSourceFiles(basePath, basePath, files, options.maxFiles ?? DEFAULT_MAX_FILES, options.accept ?? (() => true));
+    return files.sort((left, right) => left.localeCompare(right));
+}
+// A workspace path glob as a pattern: `**/` matches any number of directories, `*` and `?` stay within one.
+export function workspaceGlob(glob) {
+    const body = glob.replace(/^\.\//, '').split(/(\*\*\/|\*\*|\*|\?)/).map((part) => {
+        switch (part) {
+            case '**/':
+                return '(?:.*/)?';
+            case '**':
+                return '.*';
+            case '*':
+                return '[^/]*';
+            case '?':
+                return '[^/]';
+            default:
+                return part.replace(/[.+^${}()|[\]\\]/g, '\\$&');
+        }
+    }).join('');
+    return new RegExp(`^${body}$`);
+}
+export function isEmittedSibling(file, known) {
+    const stem = /^(.*)\.(m|c)?js$/.exec(file);
+    if (stem === null) {
+        return false;
+    }
+    const tsExtension = stem[2] === undefined ? '' : stem[2];
+    return known.has(`${stem[1]}.${tsExtension}ts`) || known.has(`${stem[1]}.tsx`);
+}
+async function collectSourceFiles(filePath, basePath, files, maxFiles, accept) {
+    if (files.length >= maxFiles) {
+        return;
+    }
+    let stats;
+    try {
+        stats = await stat(filePath);
+    }
+    catch {
+        return;
+    }
+    if (stats.isDirectory()) {
+        let entries;
+        try {
+            entries = await readdir(filePath, { withFileTypes: true, encoding: 'utf8' });
+        }
+        catch {
+            return;
+        }
+        entries.sort((left, right) => left.name.localeCompare(right.name));
+        for (const entry of entries) {
+            if (IGNORED_DIRS.has(entry.name) || entry.name === 'dist') {
+                continue;
+            }
+            await collectSourceFiles(join(filePath, entry.name), basePath, files, maxFiles, accept);
+            if (files.length >= maxFiles) {
+                break;
+            }
+        }
+        return;
+    }
+    const file = relative(basePath, filePath).split(sep).join('/');
+    if (stats.isFile() && accept(file)) {
+        files.push(file);
+    }
+}
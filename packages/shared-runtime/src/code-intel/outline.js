@@ -0,0 +1,204 @@
+import { readFile } from 'node:fs/promises';
+import { join } from 'node:path';
+import { extractCodeSymbols } from './index.js';
+import { loadWorkspaceLanguageRegistry } from './registry.js';
+import { expandCodePaths } from './signatures.js';
+const HASH_COMMENT_LANGUAGES = new Set(['ruby', 'shell', 'hcl', 'dockerfile', 'python', 'php']);
+const MAX_SIGNATURE_LINES = 6;
+const MAX_SIGNATURE_LENGTH = 200;
+// Markdown stops at `######`; deeper members are listed as bullets under it.
+const MAX_HEADING_LEVEL = 6;
+/**
+ * Outlines files as a Markdown document: a heading per file, nested headings per symbol following
+ * the declaration tree, each with its signature and the first line of its doc comment.
+ * Directories, such as a crate's `src`, expand to the indexable files under them.
+ */
+export async function renderOutline(request) {
+    const { registry } = await loadWorkspaceLanguageRegistry(request.basePath);
+    const files = [];
+    for (const file of await expandCodePaths(request.basePath, request.paths, registry)) {
+        const content = await readFile(join(request.basePath, file), 'utf8');
+        const indexed = extractCodeSymbols(file, content, registry);
+        if (indexed === undefined) {
+            throw new Error(`Could not detect a language for ${file}.`);
+        }
+        files.push({ file, language: indexed.language, symbols: buildOutline(content, indexed.symbols) });
+    }
+    return { paths: request.paths, files, markdown: renderOutlineMarkdown(request.paths.join(', '), files) };
+}
+/**
+ * Nests symbols by the lines they span, falling back to `container` for extractors that report no
+ * end line, and attaches each one's signature and doc line.
+ */
+export function buildOutline(content, symbols) {
+    const lines = content.split(/\r?\n/);
+    const ordered = [...symbols].sort((left, right) => left.line - right.line || (right.endLine ?? right.line) - (left.endLine ?? left.line));
+    const roots = [];
+    const open = [];
+    for (const symbol of ordered) {
+        const node = {
+            name: symbol.name,
+            kind: symbol.kind,
+            line: symbol.line,
+            ...optional('signature', declarationSignature(lines, symbol)),
+            ...optional('doc', leadingDoc(lines, symbol)),
+            children: [],
+        };
+        while (open.length > 0 && !encloses(open.at(-1).symbol, symbol)) {
+            open.pop();
+        }
+        const parent = open.at(-1)?.node
+            ?? (symbol.container !== undefined ? findContainer(roots, symbol.container) : undefined);
+        (parent?.children ?? roots).push(node);
+        open.push({ symbol, node });
+    }
+    return roots;
+}
+/** Renders outlined files under a `# title`, one `##` section per file. */
+export function renderOutlineMarkdown(title, files) {
+    const output = [`# Outline: ${title}`];
+    for (const file of files) {
+        output.push('', `## ${file.file}`, '', `_${file.language}, ${countNodes(file.symbols)} symbol(s)_`);
+        for (const node of file.symbols) {
+            renderNode(node, 3, output);
+        }
+    }
+    return `${output.join('\n')}\n`;
+}
+function renderNode(node, level, output) {
+    if (level > MAX_HEADING_LEVEL) {
+        const indent = '  '.repeat(level - MAX_HEADING_LEVEL - 1);
+        const detail = [node.signature !== undefined ? codeSpan(node.signature) : `_${node.kind}_`, node.doc].filter(Boolean).join(' — ');
+        output.push(`${indent}- **${node.name}** (line ${node.line}): ${detail}`);
+    }
+    else {
+        output.push('', `${'#'.repeat(level)} ${node.name}`, '');
+        output.push(node.signature !== undefined ? codeSpan(node.signature) : `_${node.kind}_`);
+        if (node.doc !== undefined) {
+            output.push('', node.doc);
+        }
+        if (level === MAX_HEADING_LEVEL && node.children.length > 0) {
+            output.push('');
+        }
+    }
+    for (const child of node.children) {
+        renderNode(child, level + 1, output);
+    }
+}
+// A symbol without an end line only holds what names it as container.
+function encloses(outer, inner) {
+    if (outer.endLine === undefined) {
+        return inner.container !== undefined && inner.container === outer.name;
+    }
+    return outer.line <= inner.line && (inner.endLine ?? inner.line) <= outer.endLine;
+}
+function findContainer(nodes, container) {
+    for (let index = nodes.length - 1; index >= 0; index -= 1) {
+        const node = nodes[index];
+        if (node.name === container || container.endsWith(`::${node.name}`) || container.endsWith(`.${node.name}`)) {
+            return node;
+        }
+    }
+    return undefined;
+}
+// `pub fn add(\n    &mut self,\n    item: Item,\n) -> usize {` -> `pub fn add(&mut self, item: Item,) -> usize`
+export function declarationSignature(lines, symbol) {
+    const last = Math.min(symbol.endLine ?? symbol.line, symbol.line + MAX_SIGNATURE_LINES - 1, lines.length);
+    let depth = 0;
+    let angles = 0;
+    let signature = '';
+    for (let index = symbol.line - 1; index < last; index += 1) {
+        const line = lines[index];
+        const code = line.replace(/"(?:[^"\\]|\\.)*"/g, (match) => `"${' '.repeat(match.length - 2)}"`);
+        let cut = line.length;
+        for (let offset = 0; offset < code.length; offset += 1) {
+            const char = code[offset];
+            if (char === '(' || char === '[') {
+                depth += 1;
+            }
+            else if (char === ')' || char === ']') {
+                depth -= 1;
+            }
+            else if (char === '<' && /\w/.test(code[offset - 1] ?? '')) {
+                angles += 1;
+            }
+            else if (char === '>' && angles > 0 && code[offset - 1] !== '-') {
+                angles -= 1;
+            }
+            else if (depth <= 0 && angles === 0 && (char === '{' || char === ';' || (char === '=' && code[offset + 1] === '>'))) {
+                cut = offset;
+                break;
+            }
+        }
+        signature += ` ${line.slice(0, cut)}`;
+        const next = lines[index + 1]?.trim() ?? '';
+        if (cut < line.length || (depth <= 0 && angles === 0 && !/^(?:->|where\b|:|extends\b|implements\b)/.test(next))) {
+            break;
+        }
+    }
+    const collapsed = signature.replace(/\s+/g, ' ').replace(/\(\s/g, '(').replace(/\s\)/g, ')').trim();
+    if (collapsed.length === 0) {
+        return undefined;
+    }
+    return collapsed.length > MAX_SIGNATURE_LENGTH ? `${collapsed.slice(0, MAX_SIGNATURE_LENGTH)} ...` : collapsed;
+}
+// The comment block ending right above the declaration, past any attributes; blank lines break it.
+export function leadingDoc(lines, symbol) {
+    const { comment } = leadingComment(lines, symbol);
+    for (const line of comment) {
+        const text = line
+            .replace(/^(?:\/\/[/!]?|#+|\/\*+!?|\*+\/?)/, '')
+            .replace(/\*+\/$/, '')
+            .replace(/<\/?\w+[^>]*>/g, '')
+            .trim();
+        // Taglines (`@param`) and magic comments (`frozen_string_literal: true`) are not a summary.
+        if (text.length > 0 && !text.startsWith('@') && !/^(?:frozen_string_literal|(?:en)?coding|typed):|-\*-/.test(text)) {
+            return text;
+        }
+    }
+    return undefined;
+}
+/** First line of the doc comment and attributes written above a declaration, or its own line. */
+export function declarationStart(lines, symbol) {
+    return leadingComment(lines, symbol).start;
+}
+function leadingComment(lines, symbol) {
+    const hashComments = HASH_COMMENT_LANGUAGES.has(symbol.language);
+    const comment = [];
+    let start = symbol.line;
+    let inBlock = false;
+    for (let index = symbol.line - 2; index >= 0; index -= 1) {
+        const line = lines[index].trim();
+        if (inBlock) {
+            comment.unshift(line);
+            start = index + 1;
+            inBlock = !line.includes('/*');
+            continue;
+        }
+        if (comment.length === 0 && (/^#!?\[.*\]$/.test(line) || /^\[[^\]]*\]$/.test(line) || /^@\w/.test(line))) {
+            start = index + 1;
+            continue;
+        }
+        if (line.endsWith('*/')) {
+            comment.unshift(line);
+            inBlock = !line.includes('/*');
+        }
+        else if (line.startsWith('//') || (hashComments && line.startsWith('#') && !line.startsWith('#!') && !line.startsWith('#['))) {
+            comment.unshift(line);
+        }
+        else {
+            break;
+        }
+        start = index + 1;
+    }
+    return { comment, start };
+}
+function codeSpan(text) {
+    return text.includes('`') ? `\`\` ${text} \`\`` : `\`${text}\``;
+}
+function countNodes(nodes) {
+    return nodes.reduce((total, node) => total + 1 + countNodes(node.children), 0);
+}
+function optional(key, value) {
+    return value === undefined ? {} : { [key]: value };
+}
@@ -0,0 +1,305 @@
+import type { CodeSymbol } from './types.js';
+
+export const BORROW_MODES = ['owned', 'borrowed', 'borrowed-mut'] as const;
+
+export type BorrowMode = typeof BORROW_MODES[number];
+
+export type ReceiverMode = 'self' | '&self' | '&mut self';
+
+export interface OwnershipParameter {
+  name: string;
+  type: string;
+  mode: BorrowMode;
+  /** First lifetime the type names: `'a` in `&'a str` or in `Parser<'a>`. */
+  lifetime?: string;
+}
+
+export interface OwnershipSummary {
+  /** How a method takes `self`; absent for associated and free functions. */
+  receiver?: ReceiverMode;
+  parameters: OwnershipParameter[];
+  returns?: {
+    type: string;
+    mode: BorrowMode;
+    lifetime?: string;
+    /** Parameters (or `self`) a returned borrow can come from, by named lifetime or elision rules. */
+    borrowsFrom?: string[];
+  };
+  /** Lifetimes in scope with their bounds: the enclosing `impl<'a>` first, then the function's own. */
+  lifetimes: string[];
+}
+
+interface ImplBlock {
+  start: number;
+  end: number;
+  selfType: string;
+  lifetimes: string[];
+}
+
+const FUNCTION_KINDS = new Set(['function', 'method']);
+const SELF_PARAMETER = /^(&\s*(?:'\w+\s*)?)?(mut\s+)?self$/;
+const LIFETIME = /'\w+/;
+
+/**
+ * Records how each Rust function takes `self` and its parameters (owned, `&` or `&mut`), what it
+ * returns, and which inputs a returned borrow is tied to, so a refactoring can tell which call
+ * sites a signature change moves, borrows or invalidates.
+ */
+export function tagOwnershipSymbols(symbols: CodeSymbol[], content: string): CodeSymbol[] {
+  if (!symbols.some((symbol) => symbol.language === 'rust' && FUNCTION_KINDS.has(symbol.kind))) {
+    return symbols;
+  }
+  const code = stripRustComments(content);
+  const lineStarts = [0];
+  for (let index = 0; index < code.length; index += 1) {
+    if (code.charCodeAt(index) === 10) {
+      lineStarts.push(index + 1);
+    }
+  }
+  const impls = findImplBlocks(code);
+
+  for (const symbol of symbols) {
+    if (symbol.language !== 'rust' || !FUNCTION_KINDS.has(symbol.kind)) {
+      continue;
+    }
+    const from = lineStarts[symbol.line - 1] ?? 0;
+    const declaration = new RegExp(`\\bfn\\s+${symbol.name}\\b`, 'g');
+    declaration.lastIndex = from;
+    const match = declaration.exec(code);
+    if (match === null || match.index > (lineStarts[symbol.line + 2] ?? code.length)) {
+      continue;
+    }
+    const impl = impls.filter((block) => block.start < match.index && match.index < block.end).at(-1);
+    const summary = summarizeSignature(code, match.index + match[0].length, impl);
+    if (summary !== undefined) {
+      symbol.ownership = summary;
+    }
+  }
+  return symbols;
+}
+
+function summarizeSignature(code: string, offset: number, impl: ImplBlock | undefined): OwnershipSummary | undefined {
+  let index = skipSpace(code, offset);
+  let generics = '';
+  if (code[index] === '<') {
+    const close = findClose(code, index, '<', '>');
+    generics = code.slice(index + 1, close);
+    index = skipSpace(code, close + 1);
+  }
+  if (code[index] !== '(') {
+    return undefined;
+  }
+  const closeParen = findClose(code, index, '(', ')');
+  const parameters = splitTopLevel(code.slice(index + 1, closeParen));
+  const rest = code.slice(closeParen + 1, findBodyStart(code, closeParen + 1));
+  const [returnPart, wherePart] = splitWhere(rest);
+
+  const lifetimes = [
+    ...(impl?.lifetimes ?? []),
+    ...splitTopLevel(generics).filter((parameter) => parameter.startsWith('\'')),
+    ...splitTopLevel(wherePart).filter((bound) => bound.startsWith('\'')),
+  ].map(normalizeSpace);
+  const summary: OwnershipSummary = { parameters: [], lifetimes: [...new Set(lifetimes)] };
+  let receiverType: string | undefined;
+
+  for (const parameter of parameters) {
+    const text = normalizeSpace(parameter);
+    const self = SELF_PARAMETER.exec(text);
+    if (self !== null) {
+      summary.receiver = self[1] === undefined ? 'self' : self[2] === undefined ? '&self' : '&mut self';
+      receiverType = `${self[1] ?? ''}${self[2] ?? ''}${impl?.selfType ?? 'Self'}`;
+      continue;
+    }
+    const colon = topLevelIndex(text, ':');
+    if (colon === -1) {
+      continue;
+    }
+    const name = text.slice(0, colon).trim().replace(/^mut\s+/, '');
+    const type = text.slice(colon + 1).trim();
+    if (name === 'self') {
+      const mode = borrowMode(type);
+      summary.receiver = mode === 'owned' ? 'self' : mode === 'borrowed' ? '&self' : '&mut self';
+      receiverType = type.replace(/\bSelf\b/g, impl?.selfType ?? 'Self');
+      continue;
+    }
+    const lifetime = LIFETIME.exec(type)?.[0];
+    summary.parameters.push({ name, type, mode: borrowMode(type), ...(lifetime !== undefined ? { lifetime } : {}) });
+  }
+
+  const returned = normalizeSpace(returnPart.replace(/^\s*->/, ''));
+  if (returnPart.trim().startsWith('->') && returned.length > 0) {
+    const mode = borrowMode(returned);
+    // `Self` in an `impl<'a> DataProcessor<'a>` block carries the block's lifetimes.
+    const lifetime = LIFETIME.exec(returned.replace(/\bSelf\b/g, impl?.selfType ?? 'Self'))?.[0];
+    const borrowsFrom = returnedBorrowSources(returned, lifetime, summary, receiverType);
+    summary.returns = {
+      type: returned,
+      mode,
+      ...(lifetime !== undefined ? { lifetime } : {}),
+      ...(borrowsFrom.length > 0 ? { borrowsFrom } : {}),
+    };
+  }
+  return summary;
+}
+
+// A named lifetime ties the result to every input carrying it; an elided one follows the
+// elision rules: `&self` wins, otherwise the single borrowed parameter.
+function returnedBorrowSources(
+  returned: string,
+  lifetime: string | undefined,
+  summary: OwnershipSummary,
+  receiverType: string | undefined,
+): string[] {
+  if (lifetime !== undefined && lifetime !== '\'_') {
+    if (lifetime === '\'static') {
+      return [];
+    }
+    const carries = (type: string) => new RegExp(`${lifetime}\\b`).test(type);
+    return [
+      ...(receiverType !== undefined && carries(receiverType) ? ['self'] : []),
+      ...summary.parameters.filter((parameter) => carries(parameter.type)).map((parameter) => parameter.name),
+    ];
+  }
+  if (lifetime === undefined && !returned.includes('&')) {
+    return [];
+  }
+  if (summary.receiver === '&self' || summary.receiver === '&mut self') {
+    return ['self'];
+  }
+  const borrowed = summary.parameters.filter((parameter) => parameter.mode !== 'owned' || parameter.lifetime !== undefined);
+  return borrowed.length === 1 ? [borrowed[0]!.name] : [];
+}
+
+function borrowMode(type: string): BorrowMode {
+  const borrow = /^&\s*(?:'\w+\s*)?(mut\b)?/.exec(type.trim());
+  if (borrow === null) {
+    return 'owned';
+  }
+  return borrow[1] === undefined ? 'borrowed' : 'borrowed-mut';
+}
+
+function findImplBlocks(code: string): ImplBlock[] {
+  const blocks: ImplBlock[] = [];
+  for (const match of code.matchAll(/\bimpl\b/g)) {
+    // `impl Trait` in argument or return position is not a block.
+    if (!/(?:^|\n)\s*(?:(?:unsafe|default)\s+)*$/.test(code.slice(Math.max(0, match.index - 80), match.index))) {
+      continue;
+    }
+    let index = skipSpace(code, match.index + match[0].length);
+    let generics = '';
+    if (code[index] === '<') {
+      const close = findClose(code, index, '<', '>');
+      generics = code.slice(index + 1, close);
+      index = close + 1;
+    }
+    const open = findBodyStart(code, index);
+    if (code[open] !== '{') {
+      continue;
+    }
+    const header = code.slice(index, open).replace(/\bwhere\b[\s\S]*$/, '');
+    const forIndex = header.search(/\bfor\b/);
+    blocks.push({
+      start: match.index,
+      end: findClose(code, open, '{', '}'),
+      selfType: normalizeSpace(forIndex === -1 ? header : header.slice(forIndex + 3)),
+      lifetimes: splitTopLevel(generics).filter((parameter) => parameter.startsWith('\'')),
+    });
+  }
+  return blocks;
+}
+
+// The `{` or `;` ending a signature, skipping `{` inside generic arguments.
+function findBodyStart(code: string, from: number): number {
+  let depth = 0;
+  for (let index = from; index < code.length; index += 1) {
+    const char = code[index];
+    if (char === '<' || char === '(' || char === '[') {
+      depth += 1;
+    } else if ((char === '>' && code[index - 1] !== '-') || char === ')' || char === ']') {
+      depth -= 1;
+    } else if ((char === '{' || char === ';') && depth <= 0) {
+      return index;
+    }
+  }
+  return code.length;
+}
+
+function findClose(code: string, open: number, opening: string, closing: string): number {
+  let depth = 0;
+  for (let index = open; index < code.length; index += 1) {
+    if (code[index] === opening) {
+      depth += 1;
+    } else if (code[index] === closing && !(closing === '>' && code[index - 1] === '-') && (depth -= 1) === 0) {
+      return index;
+    }
+  }
+  return code.length;
+}
+
+function splitWhere(rest: string): [string, string] {
+  const where = rest.search(/\bwhere\b/);
+  return where === -1 ? [rest, ''] : [rest.slice(0, where), rest.slice(where + 5)];
+}
+
+function splitTopLevel(value: string): string[] {
+  const parts: string[] = [];
+  let depth = 0;
+  let current = '';
+  for (let index = 0; index < value.length; index += 1) {
+    const char = value[index]!;
+    if (char === '<' || char === '(' || char === '[' || char === '{') {
+      depth += 1;
+    } else if ((char === '>' && value[index - 1] !== '-') || char === ')' || char === ']' || char === '}') {
+      depth -= 1;
+    } else if (char === ',' && depth === 0) {
+      parts.push(current.trim());
+      current = '';
+      continue;
+    }
+    current += char;
+  }
+  parts.push(current.trim());
+  return parts.filter((part) => part.length > 0);
+}
+
+function topLevelIndex(value: string, target: string): number {
+  let depth = 0;
+  for (let index = 0; index < value.length; index += 1) {
+    const char = value[index]!;
+    if (char === '<' || char === '(' || char === '[') {
+      depth += 1;
+    } else if ((char === '>' && value[index - 1] !== '-') || char === ')' || char === ']') {
+      depth -= 1;
+    } else if (char === target && depth === 0 && value[index + 1] !== ':' && value[index - 1] !== ':') {
+      return index;
+    }
+  }
+  return -1;
+}
+
+function skipSpace(code: string, from: number): number {
+  let index = from;
+  while (index < code.length && /\s/.test(code[index]!)) {
+    index += 1;
+  }
+  return index;
+}
+
+function normalizeSpace(value: string): string {
+  return value
+    .replace(/\s+/g, ' ')
+    .replace(/\s*([<(])\s*/g, '$1')
+    .replace(/\[\s+/g, '[')
+    .replace(/\s*([>)\]])/g, '$1')
+    .replace(/\s*,\s*/g, ', ')
+    .replace(/&\s+/g, '&')
+    .trim();
+}
+
+// Blanks comments and string contents, keeping offsets; lifetimes rule out stripping `'...'`.
+function stripRustComments(content: string): string {
+  return content
+    .replace(/\/\*[\s\S]*?\*\//g, (match) => match.replace(/[^\n]/g, ' '))
+    .replace(/"(?:[^"\\]|\\.)*"/g, (match) => `"${' '.repeat(match.length - 2)}"`)
+    .replace(/\/\/[^\n]*/g, (match) => ' '.repeat(match.length));
+}
@@ -0,0 +1,99 @@
+import { createHash } from 'node:crypto';
+import { mkdir, readdir, readFile, rename, writeFile } from 'node:fs/promises';
+import { homedir } from 'node:os';
+import { dirname, join } from 'node:path';
+import { fileURLToPath } from 'node:url';
+import { BUILTIN_CODE_EXTRACTORS, type LanguageRegistry } from './registry.js';
+import type { CodeFileIndex } from './types.js';
+
+export const CACHE_DIR_ENV_VAR = 'AUTOMATOSX_CACHE_DIR';
+
+export interface ParseCache {
+  dir: string;
+  hits: number;
+  misses: number;
+  /**
+   * Returns the symbols cached for this file and content, or runs `extract` and caches its
+   * result. Files whose extractor has no version are extracted every time.
+   */
+  get(file: string, content: string, registry: LanguageRegistry, extract: () => CodeFileIndex | undefined): Promise<CodeFileIndex | undefined>;
+}
+
+// Bumped when the cached entry layout changes.
+const CACHE_FORMAT = '1';
+const CODE_INTEL_DIR = dirname(fileURLToPath(import.meta.url));
+let sourceVersion: Promise<string> | undefined;
+
+/**
+ * The cache shared by every workspace on the machine: `AUTOMATOSX_CACHE_DIR`, or
+ * `~/.automatosx/cache`, holds one entry per file under `parse/`. Entries are keyed by a hash of
+ * the extractor version and the file's path and content, so a checkout that has not changed
+ * re-indexes without parsing and CI can restore the directory between runs.
+ */
+export function openParseCache(env: NodeJS.ProcessEnv = process.env): ParseCache {
+  const dir = join(env[CACHE_DIR_ENV_VAR] ?? join(homedir(), '.automatosx', 'cache'), 'parse');
+  const cache: ParseCache = {
+    dir,
+    hits: 0,
+    misses: 0,
+    async get(file, content, registry, extract) {
+      const extractor = registry.detect(file, content);
+      const version = extractor?.version ?? (extractor !== undefined && BUILTIN_CODE_EXTRACTORS.includes(extractor) ? 'builtin' : undefined);
+      if (extractor === undefined || version === undefined) {
+        return extract();
+      }
+
+      const key = createHash('sha256')
+        .update([CACHE_FORMAT, await readSourceVersion(), extractor.language, version, file, content].join('\0'))
+        .digest('hex');
+      const entryPath = join(dir, key.slice(0, 2), `${key}.json`);
+      const cached = await readEntry(entryPath);
+      if (cached !== undefined) {
+        cache.hits += 1;
+        return cached;
+      }
+
+      cache.misses += 1;
+      const indexed = extract();
+      if (indexed !== undefined) {
+        await writeEntry(entryPath, indexed);
+      }
+      return indexed;
+    },
+  };
+  return cache;
+}
+
+// The code-intel sources themselves version the built-in extractors and the symbol taggers every
+// entry passes through, so upgrading ax invalidates the cache without a manual bump.
+async function readSourceVersion(): Promise<string> {
+  sourceVersion ??= (async () => {
+    const hash = createHash('sha256');
+    for (const name of (await readdir(CODE_INTEL_DIR)).filter((entry) => /\.[jt]s$/.test(entry)).sort()) {
+      hash.update(name).update(await readFile(join(CODE_INTEL_DIR, name)));
+    }
+    return hash.digest('hex').slice(0, 16);
+  })();
+  return sourceVersion;
+}
+
+async function readEntry(entryPath: string): Promise<CodeFileIndex | undefined> {
+  try {
+    const parsed = JSON.parse(await readFile(entryPath, 'utf8')) as Partial<CodeFileIndex>;
+    return typeof parsed.file === 'string' && Array.isArray(parsed.symbols) ? parsed as CodeFileIndex : undefined;
+  } catch {
+    return undefined;
+  }
+}
+
+// Written under a temporary name and renamed, so concurrent runs never read half an entry.
+async function writeEntry(entryPath: string, entry: CodeFileIndex): Promise<void> {
+  const tempPath = `${entryPath}.${process.pid}.tmp`;
+  try {
+    await mkdir(dirname(entryPath), { recursive: true });
+    await writeFile(tempPath, JSON.stringify(entry), 'utf8');
+    await rename(tempPath, entryPath);
+  } catch {
+    // A cache that cannot be written (read-only home, full disk) only costs the next run a parse.
+  }
+}
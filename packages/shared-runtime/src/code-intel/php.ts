@@ -0,0 +1,238 @@
+import { extname } from 'node:path';
+import type { CodeLanguageExtractor, CodeSymbol } from './types.js';
+
+const PHP_EXTENSIONS = new Set(['.php', '.phtml']);
+const NAMESPACE_PATTERN = /^\s*namespace\s+([A-Za-z_][\w\\]*)?\s*([;{])?/;
+const CLASS_PATTERN = /^\s*((?:(?:abstract|final|readonly)\s+)*)(class|interface|trait|enum)\s+([A-Za-z_]\w*)([^{;]*)/;
+const FUNCTION_PATTERN = /^\s*((?:(?:public|protected|private|static|abstract|final)\s+)*)function\s+&?\s*([A-Za-z_]\w*)\s*\(/;
+
+type ScopeKind = 'namespace' | 'class' | 'function' | 'block';
+
+interface OpenScope {
+  kind: ScopeKind;
+  name?: string;
+  symbolIndex?: number;
+}
+
+interface PendingDeclaration {
+  kind: ScopeKind;
+  name?: string;
+  symbolIndex: number;
+}
+
+interface LexState {
+  inPhp: boolean;
+  inBlockComment: boolean;
+  quote?: string;
+  heredoc?: string;
+}
+
+export const phpExtractor: CodeLanguageExtractor = {
+  language: 'php',
+  matchesFile(fileName) {
+    return PHP_EXTENSIONS.has(extname(fileName).toLowerCase());
+  },
+  matchesShebang(firstLine) {
+    return /^#!.*\bphp\b/.test(firstLine);
+  },
+  extract(content, file) {
+    return extractPhpSymbols(content, file);
+  },
+};
+
+export function extractPhpSymbols(content: string, file: string): CodeSymbol[] {
+  const lines = content.split(/\r?\n/);
+  const symbols: CodeSymbol[] = [];
+  const stack: OpenScope[] = [];
+  const state: LexState = { inPhp: false, inBlockComment: false };
+  let namespace: string | undefined;
+  let pending: PendingDeclaration | undefined;
+
+  for (let index = 0; index < lines.length; index += 1) {
+    const lineNumber = index + 1;
+    const code = sanitizePhpLine(lines[index] ?? '', state);
+    if (code.trim().length === 0) {
+      continue;
+    }
+
+    const namespaceMatch = NAMESPACE_PATTERN.exec(code);
+    const classMatch = namespaceMatch === null ? CLASS_PATTERN.exec(code) : null;
+    const functionMatch = namespaceMatch === null && classMatch === null ? FUNCTION_PATTERN.exec(code) : null;
+
+    if (namespaceMatch !== null && stack.every((scope) => scope.kind === 'namespace')) {
+      namespace = namespaceMatch[1];
+      if (namespace !== undefined) {
+        symbols.push({ name: namespace, kind: 'namespace', language: 'php', file, line: lineNumber });
+      }
+      if (namespaceMatch[2] !== ';') {
+        pending = { kind: 'namespace', name: namespace, symbolIndex: namespace !== undefined ? symbols.length - 1 : -1 };
+      }
+    } else if (classMatch !== null) {
+      const declaration = classMatch[2]!;
+      const attributes: Record<string, string> = {};
+      const modifiers = classMatch[1]!.trim().split(/\s+/).filter((modifier) => modifier.length > 0);
+      if (declaration === 'interface' || declaration === 'enum') {
+        attributes.declaration = declaration;
+      }
+      if (modifiers.length > 0) {
+        attributes.modifiers = modifiers.join(' ');
+      }
+      const heritage = parseHeritage(classMatch[4]!);
+      Object.assign(attributes, heritage);
+      symbols.push({
+        name: classMatch[3]!,
+        kind: declaration === 'trait' ? 'trait' : 'class',
+        language: 'php',
+        file,
+        line: lineNumber,
+        ...(namespace !== undefined ? { container: namespace } : {}),
+        ...(Object.keys(attributes).length > 0 ? { attributes } : {}),
+      });
+      pending = { kind: 'class', name: qualify(namespace, classMatch[3]!), symbolIndex: symbols.length - 1 };
+    } else if (functionMatch !== null) {
+      const owner = stack.at(-1);
+      const isMethod = owner?.kind === 'class';
+      const modifiers = functionMatch[1]!.trim().split(/\s+/).filter((modifier) => modifier.length > 0);
+      const attributes: Record<string, string> = {};
+      if (isMethod) {
+        attributes.visibility = modifiers.find((modifier) => ['public', 'protected', 'private'].includes(modifier)) ?? 'public';
+        if (modifiers.includes('static')) {
+          attributes.static = 'true';
+        }
+        if (modifiers.includes('abstract')) {
+          attributes.abstract = 'true';
+        }
+      }
+      const container = isMethod ? owner.name : namespace;
+      symbols.push({
+        name: functionMatch[2]!,
+        kind: isMethod ? 'method' : 'function',
+        language: 'php',
+        file,
+        line: lineNumber,
+        ...(container !== undefined ? { container } : {}),
+        ...(Object.keys(attributes).length > 0 ? { attributes } : {}),
+      });
+      pending = { kind: 'function', symbolIndex: symbols.length - 1 };
+    }
+
+    for (const char of code) {
+      if (char === '{') {
+        if (pending !== undefined) {
+          stack.push({
+            kind: pending.kind,
+            name: pending.name,
+            symbolIndex: pending.symbolIndex >= 0 ? pending.symbolIndex : undefined,
+          });
+          pending = undefined;
+        } else {
+          stack.push({ kind: 'block' });
+        }
+      } else if (char === '}') {
+        const closed = stack.pop();
+        if (closed?.symbolIndex !== undefined) {
+          symbols[closed.symbolIndex]!.endLine = lineNumber;
+        }
+        if (closed?.kind === 'namespace') {
+          namespace = undefined;
+        }
+      } else if (char === ';' && pending !== undefined) {
+        if (pending.kind !== 'namespace') {
+          symbols[pending.symbolIndex]!.endLine = lineNumber;
+        }
+        pending = undefined;
+      }
+    }
+  }
+
+  return symbols;
+}
+
+function parseHeritage(text: string): Record<string, string> {
+  const attributes: Record<string, string> = {};
+  const extendsMatch = /\bextends\s+([\w\\\s,]+?)(?=\s+implements\b|$)/.exec(text.trim());
+  const implementsMatch = /\bimplements\s+([\w\\\s,]+)$/.exec(text.trim());
+  if (extendsMatch !== null) {
+    attributes.extends = normalizeList(extendsMatch[1]!);
+  }
+  if (implementsMatch !== null) {
+    attributes.implements = normalizeList(implementsMatch[1]!);
+  }
+  return attributes;
+}
+
+function normalizeList(text: string): string {
+  return text.split(',').map((entry) => entry.trim()).filter((entry) => entry.length > 0).join(', ');
+}
+
+function qualify(namespace: string | undefined, name: string): string {
+  return namespace !== undefined ? `${namespace}\\${name}` : name;
+}
+
+function sanitizePhpLine(line: string, state: LexState): string {
+  if (state.heredoc !== undefined) {
+    if (new RegExp(`^\\s*${state.heredoc}\\b`).test(line)) {
+      const rest = line.trimStart().slice(state.heredoc.length);
+      state.heredoc = undefined;
+      return sanitizePhpLine(rest, state);
+    }
+    return '';
+  }
+
+  let code = '';
+  for (let index = 0; index < line.length; index += 1) {
+    const char = line[index]!;
+    const next = line[index + 1];
+
+    if (!state.inPhp) {
+      if (line.startsWith('<?php', index) || line.startsWith('<?=', index)) {
+        state.inPhp = true;
+        index += line.startsWith('<?php', index) ? 4 : 2;
+      }
+      continue;
+    }
+    if (state.inBlockComment) {
+      if (char === '*' && next === '/') {
+        state.inBlockComment = false;
+        index += 1;
+      }
+      continue;
+    }
+    if (state.quote !== undefined) {
+      if (char === '\\') {
+        index += 1;
+      } else if (char === state.quote) {
+        state.quote = undefined;
+        code += char;
+      }
+      continue;
+    }
+
+    if (char === '?' && next === '>') {
+      state.inPhp = false;
+      index += 1;
+      code += ';';
+      continue;
+    }
+    if (char === '/' && next === '*') {
+      state.inBlockComment = true;
+      index += 1;
+      continue;
+    }
+    if ((char === '/' && next === '/') || (char === '#' && next !== '[')) {
+      break;
+    }
+    const heredoc = /^<<<\s*(['"]?)([A-Za-z_]\w*)\1/.exec(line.slice(index));
+    if (heredoc !== null) {
+      state.heredoc = heredoc[2];
+      code += '""';
+      break;
+    }
+    if (char === '"' || char === '\'' || char === '`') {
+      state.quote = char;
+    }
+    code += char;
+  }
+
+  return code;
+}
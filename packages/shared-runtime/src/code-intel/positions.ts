@@ -0,0 +1,158 @@
+import type { CodeSymbol } from './types.js';
+
+/**
+ * One point in a source file, in every unit a consumer may need: `line` and `column` (1-based,
+ * `column` in characters) for people, `utf16Column` (0-based, the LSP `character`) for editors
+ * and `byteOffset` (0-based, UTF-8, from the start of the file) for byte-oriented tools.
+ * `\r\n` line endings count toward byte offsets but never toward columns.
+ */
+export interface SourcePosition {
+  line: number;
+  column: number;
+  utf16Column: number;
+  byteOffset: number;
+}
+
+/** A half-open range: `end` is the position just past the last character. */
+export interface SourceSpan {
+  start: SourcePosition;
+  end: SourcePosition;
+}
+
+export interface PositionMapper {
+  /** Position of a string offset (UTF-16 code units) into the content. */
+  fromOffset(offset: number): SourcePosition;
+  /** Position of a UTF-16 column on a 1-based line; clamped to the line's text. */
+  at(line: number, utf16Column: number): SourcePosition;
+  /** Text of a 1-based line, without its line ending. */
+  lineText(line: number): string;
+}
+
+/**
+ * Indexes line starts once so positions can be mapped between string offsets, UTF-16 columns,
+ * character columns and UTF-8 byte offsets without rescanning the content.
+ */
+export function createPositionMapper(content: string): PositionMapper {
+  const lineStarts = [0];
+  const byteStarts = [0];
+  let bytes = 0;
+  for (let index = 0; index < content.length; index += 1) {
+    const code = content.charCodeAt(index);
+    bytes += utf8Length(content, index);
+    if (isHighSurrogate(code) && isLowSurrogate(content.charCodeAt(index + 1))) {
+      index += 1;
+    }
+    if (code === 10) {
+      lineStarts.push(index + 1);
+      byteStarts.push(bytes);
+    }
+  }
+
+  const lineText = (line: number): string => {
+    const start = lineStarts[line - 1];
+    if (start === undefined) {
+      return '';
+    }
+    const next = lineStarts[line];
+    const end = next === undefined ? content.length : next - (content.charCodeAt(next - 2) === 13 ? 2 : 1);
+    return content.slice(start, Math.max(start, end));
+  };
+
+  const at = (line: number, utf16Column: number): SourcePosition => {
+    const clampedLine = Math.min(Math.max(line, 1), lineStarts.length);
+    const text = lineText(clampedLine);
+    const column = Math.min(Math.max(utf16Column, 0), text.length);
+    const prefix = text.slice(0, column);
+    let characters = 0;
+    let prefixBytes = 0;
+    for (let index = 0; index < prefix.length; index += 1) {
+      prefixBytes += utf8Length(prefix, index);
+      characters += 1;
+      if (isHighSurrogate(prefix.charCodeAt(index)) && isLowSurrogate(prefix.charCodeAt(index + 1))) {
+        index += 1;
+      }
+    }
+    return {
+      line: clampedLine,
+      column: characters + 1,
+      utf16Column: column,
+      byteOffset: byteStarts[clampedLine - 1]! + prefixBytes,
+    };
+  };
+
+  const fromOffset = (offset: number): SourcePosition => {
+    let low = 0;
+    let high = lineStarts.length - 1;
+    while (low < high) {
+      const middle = Math.ceil((low + high) / 2);
+      if (lineStarts[middle]! <= offset) {
+        low = middle;
+      } else {
+        high = middle - 1;
+      }
+    }
+    return at(low + 1, offset - lineStarts[low]!);
+  };
+
+  return { fromOffset, at, lineText };
+}
+
+/**
+ * Attaches spans to extracted symbols: `span` runs from the start of the declaration line to
+ * the end of its last line, `nameSpan` covers the name where the declaration line spells it.
+ */
+export function addSymbolSpans(symbols: CodeSymbol[], content: string): CodeSymbol[] {
+  const mapper = createPositionMapper(content);
+  for (const symbol of symbols) {
+    const first = mapper.lineText(symbol.line);
+    const last = mapper.lineText(symbol.endLine ?? symbol.line);
+    symbol.span = {
+      start: mapper.at(symbol.line, first.length - first.trimStart().length),
+      end: mapper.at(symbol.endLine ?? symbol.line, last.trimEnd().length),
+    };
+    const nameColumn = findName(first, symbol.name);
+    if (nameColumn !== undefined) {
+      symbol.nameSpan = {
+        start: mapper.at(symbol.line, nameColumn),
+        end: mapper.at(symbol.line, nameColumn + symbol.name.length),
+      };
+    }
+  }
+  return symbols;
+}
+
+function findName(text: string, name: string): number | undefined {
+  if (name.length === 0) {
+    return undefined;
+  }
+  const wordLike = /^\w/.test(name) && /\w$/.test(name);
+  for (let column = text.indexOf(name); column !== -1; column = text.indexOf(name, column + 1)) {
+    if (!wordLike || (!/\w/.test(text[column - 1] ?? '') && !/\w/.test(text[column + name.length] ?? ''))) {
+      return column;
+    }
+  }
+  return undefined;
+}
+
+function utf8Length(text: string, index: number): number {
+  const code = text.charCodeAt(index);
+  if (code < 0x80) {
+    return 1;
+  }
+  if (code < 0x800) {
+    return 2;
+  }
+  // A surrogate pair is one four-byte character; an unpaired surrogate encodes as U+FFFD.
+  if (isHighSurrogate(code) && isLowSurrogate(text.charCodeAt(index + 1))) {
+    return 4;
+  }
+  return 3;
+}
+
+function isHighSurrogate(code: number): boolean {
+  return code >= 0xd800 && code <= 0xdbff;
+}
+
+function isLowSurrogate(code: number): boolean {
+  return code >= 0xdc00 && code <= 0xdfff;
+}
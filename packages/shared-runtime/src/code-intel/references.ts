@@ -0,0 +1,920 @@
+import { readFile } from 'node:fs/promises';
+import { join, posix } from 'node:path';
+import { readCodeIndex } from './index.js';
+import { createPositionMapper, type PositionMapper, type SourceSpan } from './positions.js';
+import { STABLE_SYMBOL_ID_PATTERN } from './symbol-id.js';
+import type { CodeLanguage, CodeSymbol, CodeSymbolKind } from './types.js';
+
+export const REFERENCE_RESOLUTIONS = ['same-file', 'import', 'namespace', 'qualified', 'receiver', 'name'] as const;
+
+export type ReferenceResolution = typeof REFERENCE_RESOLUTIONS[number];
+
+export interface SymbolReferencesRequest {
+  basePath: string;
+  /** Symbol id (`file:line` or a `stableId`) or name, optionally qualified (`Shop.MemoryStore.Save`, `App\Money::add`, `Worker#perform`). */
+  symbol: string;
+}
+
+export interface SymbolReference {
+  file: string;
+  line: number;
+  /** 1-based, in characters; `span` carries the UTF-16 column and byte offset. */
+  column: number;
+  span: SourceSpan;
+  /**
+   * How the use was tied to the symbol: declared in the `same-file`, brought in by an `import`
+   * (`use`/`using`), visible from the enclosing `namespace` or module, spelled out `qualified`,
+   * called on a `receiver` of the owning type, or only by `name` when nothing else shares it.
+   */
+  via: ReferenceResolution;
+  /** Innermost indexed symbol containing the use. */
+  within?: string;
+  snippet: string;
+}
+
+export interface RuntimeSymbolReferences {
+  symbol: {
+    id: string;
+    name: string;
+    kind: CodeSymbolKind;
+    language: CodeLanguage;
+    file: string;
+    line: number;
+    container?: string;
+  };
+  references: SymbolReference[];
+  /** Uses of the name that could belong to this symbol or another one of the same name. */
+  unresolved: number;
+}
+
+interface FileContext {
+  file: string;
+  language: CodeLanguage;
+  raw: string[];
+  code: string[];
+  positions: PositionMapper;
+  /** Declared namespaces and namespace-wide imports (C# `using X;`, Rust `use x::*;`), dot-separated. */
+  namespaces: string[];
+  declaresNamespace: boolean;
+  /** Local name to the dot-separated path it imports. */
+  imports: Map<string, string>;
+  symbols: CodeSymbol[];
+  /** Rust `impl Type { ... }` blocks, which tags queries do not report as symbols. */
+  impls: Array<{ type: string; start: number; end: number }>;
+}
+
+export interface CallGraphNode {
+  /** `file:line` id, as in `getCodeSymbolId`. */
+  id: string;
+  /** Container-qualified: `Shop.Cart.Add`, `store.money.Money.add`. */
+  name: string;
+  kind: CodeSymbolKind;
+  language: CodeLanguage;
+  file: string;
+  line: number;
+}
+
+export interface CallGraphEdge {
+  from: string;
+  to: string;
+  /** Call sites in the caller that resolve to the callee. */
+  calls: number;
+  via: ReferenceResolution[];
+}
+
+export interface CallGraph {
+  /** Functions and methods that call or are called by another indexed one. */
+  nodes: CallGraphNode[];
+  edges: CallGraphEdge[];
+  /** Calls that could land on several indexed functions and were left out. */
+  unresolved: number;
+}
+
+type Resolved = { symbols: CodeSymbol[]; via: ReferenceResolution };
+
+const TYPE_KINDS = new Set<CodeSymbolKind>(['class', 'trait', 'record', 'module']);
+const CALLABLE_KINDS = new Set<CodeSymbolKind>(['function', 'method']);
+// `name(`, `name::<T>(` and `name<T>(`; the name is group 1.
+const CALL_SITE = /(?<![\w$])([A-Za-z_]\w*[?!]?)\s*(?:::)?(?:<[^<>()]*>)?\s*\(/g;
+const MEMBER_KINDS = new Set<CodeSymbolKind>(['method', 'property', 'attribute', 'function']);
+const ACCESSOR = /(?:\?->|->|\?\.|&\.|::|\.)\s*$/;
+// Rust spells return types `-> T`, so only `.` and `::` reach into a value or path there.
+const RUST_ACCESSOR = /(?:::|\.)\s*$/;
+const IMPORT_LINE = /^\s*(?:pub(?:\([^)]*\))?\s+)?(?:global\s+)?(?:use|using)\b/;
+const SELF_RECEIVER = /(?:\$this|\bthis|\bself|\bSelf|\bstatic)\s*$/;
+const SNIPPET_LENGTH = 160;
+// Bounds chains like `a.b().c.d()` and bindings that refer to each other.
+const MAX_INFERENCE_DEPTH = 6;
+
+export function getCodeSymbolId(symbol: Pick<CodeSymbol, 'file' | 'line'>): string {
+  return `${symbol.file}:${symbol.line}`;
+}
+
+/**
+ * Finds the uses of one indexed symbol across the files of its language. A use counts when the
+ * name resolves to the symbol: through the file's imports (`use`, `using`, aliases included), the
+ * enclosing namespace or module, a qualified path, or, for members, the type of the receiver
+ * (`$this`, typed parameters and locals, `new X`, `X::new()`, `X.new`, the declared return type of
+ * a called function and the declared type of a field, wherever those are declared). Rust `impl`
+ * blocks belong to the type their file imports, so methods resolve across files. Uses that could
+ * belong to several same-named symbols are counted in `unresolved` instead of being guessed.
+ */
+export async function findSymbolReferences(request: SymbolReferencesRequest): Promise<RuntimeSymbolReferences> {
+  const snapshot = await readCodeIndex(request.basePath);
+  if (snapshot === undefined) {
+    throw new Error('No symbol index found. Run "ax code index" first.');
+  }
+
+  const symbols = snapshot.files.flatMap((entry) => entry.symbols);
+  const target = resolveTarget(symbols, request.symbol);
+  const sameLanguage = symbols.filter((symbol) => symbol.language === target.language);
+  const contexts = new Map<string, FileContext>();
+  for (const entry of snapshot.files.filter((candidate) => candidate.language === target.language)) {
+    let content: string;
+    try {
+      content = await readFile(join(request.basePath, entry.file), 'utf8');
+    } catch {
+      continue;
+    }
+    contexts.set(entry.file, createFileContext(entry.file, content, target.language, entry.symbols));
+  }
+
+  const resolver = createReferenceResolver(sameLanguage, contexts);
+  const owner = resolver.ownerOf(target);
+  const references: SymbolReference[] = [];
+  let unresolved = 0;
+
+  for (const context of contexts.values()) {
+    const names = new Set([target.name, ...(owner === undefined ? resolver.aliasesFor(target, context) : [])]);
+    const declarationLines = new Set(context.symbols
+      .filter((symbol) => names.has(symbol.name))
+      .flatMap((symbol) => (symbol.locations ?? [symbol]).filter((location) => location.file === context.file).map((location) => location.line)));
+
+    context.code.forEach((text, index) => {
+      const line = index + 1;
+      if (declarationLines.has(line)) {
+        return;
+      }
+      for (const name of names) {
+        for (const column of findOccurrences(text, name)) {
+          const before = text.slice(0, column);
+          if (before.endsWith('$') && !before.endsWith('::$')) {
+            continue;
+          }
+          // The alias in `use x::Money as Cash;` declares the name rather than using it.
+          if (name !== target.name && IMPORT_LINE.test(text)) {
+            continue;
+          }
+          const qualifier = qualifierBefore(before);
+          // `value.name` on a global name is some other member that happens to share it.
+          if (owner === undefined && qualifier === undefined && accessorFor(context).test(before)) {
+            continue;
+          }
+          const resolved = owner === undefined
+            ? resolver.resolveGlobal(qualifier, name, context, line, (symbol) => resolver.ownerOf(symbol) === undefined)
+            : resolver.resolveMember(target, owner, before.replace(/::\$$/, '::'), context, line);
+          if (resolved === undefined || !resolved.symbols.includes(target)) {
+            continue;
+          }
+          if (resolved.symbols.length > 1) {
+            unresolved += 1;
+            continue;
+          }
+          const within = innermostSymbol(context, line);
+          const start = context.positions.at(line, column);
+          references.push({
+            file: context.file,
+            line,
+            column: start.column,
+            span: { start, end: context.positions.at(line, column + name.length) },
+            via: resolved.via,
+            ...(within !== undefined ? { within: qualifiedName(within) } : {}),
+            snippet: (context.raw[index] ?? '').trim().slice(0, SNIPPET_LENGTH),
+          });
+        }
+      }
+    });
+  }
+
+  references.sort((left, right) => left.file.localeCompare(right.file) || left.line - right.line || left.column - right.column);
+  return {
+    symbol: {
+      id: getCodeSymbolId(target),
+      name: target.name,
+      kind: target.kind,
+      language: target.language,
+      file: target.file,
+      line: target.line,
+      ...(target.container !== undefined ? { container: target.container } : {}),
+    },
+    references,
+    unresolved,
+  };
+}
+
+/**
+ * Builds the call graph of the indexed functions and methods: an edge from each caller to every
+ * callee a call site inside it resolves to, with the same rules as `findSymbolReferences`. Only
+ * calls written with parentheses are seen, calls to code outside the index are dropped, and calls
+ * that could land on several same-named functions are counted in `unresolved`. With `paths`, only
+ * edges whose caller or callee is declared under one of them are kept.
+ */
+export async function buildCallGraph(request: { basePath: string; paths?: string[] }): Promise<CallGraph> {
+  const snapshot = await readCodeIndex(request.basePath);
+  if (snapshot === undefined) {
+    throw new Error('No symbol index found. Run "ax code index" first.');
+  }
+
+  const prefixes = (request.paths ?? []).map((path) => path.replace(/^\.\/|\/+$/g, '')).filter((path) => path.length > 0 && path !== '.');
+  const inScope = (symbol: CodeSymbol) => prefixes.length === 0 || prefixes.some((prefix) => symbol.file === prefix || symbol.file.startsWith(`${prefix}/`));
+  const nodes = new Map<string, CallGraphNode>();
+  const edges = new Map<string, CallGraphEdge>();
+  let unresolved = 0;
+
+  const languages = [...new Set(snapshot.files.map((entry) => entry.language))];
+  for (const language of languages) {
+    const entries = snapshot.files.filter((entry) => entry.language === language);
+    const sameLanguage = entries.flatMap((entry) => entry.symbols);
+    if (!sameLanguage.some((symbol) => CALLABLE_KINDS.has(symbol.kind))) {
+      continue;
+    }
+    const contexts = new Map<string, FileContext>();
+    for (const entry of entries) {
+      try {
+        contexts.set(entry.file, createFileContext(entry.file, await readFile(join(request.basePath, entry.file), 'utf8'), language, entry.symbols));
+      } catch {
+        continue;
+      }
+    }
+    const resolver = createReferenceResolver(sameLanguage, contexts);
+    const members = new Map<string, CodeSymbol[]>();
+    for (const symbol of sameLanguage) {
+      if (CALLABLE_KINDS.has(symbol.kind) && resolver.ownerOf(symbol) !== undefined) {
+        members.set(symbol.name, [...members.get(symbol.name) ?? [], symbol]);
+      }
+    }
+
+    for (const context of contexts.values()) {
+      const callables = context.symbols.filter((symbol) => CALLABLE_KINDS.has(symbol.kind) && symbol.endLine !== undefined);
+      const declarations = new Set(context.symbols.map((symbol) => `${symbol.line}:${symbol.name}`));
+      context.code.forEach((text, index) => {
+        const line = index + 1;
+        const caller = callables
+          .filter((symbol) => symbol.line <= line && line <= symbol.endLine!)
+          .sort((left, right) => (left.endLine! - left.line) - (right.endLine! - right.line))[0];
+        if (caller === undefined) {
+          return;
+        }
+        for (const match of text.matchAll(CALL_SITE)) {
+          const name = match[1]!;
+          if (declarations.has(`${line}:${name}`)) {
+            continue;
+          }
+          const before = text.slice(0, match.index);
+          const qualifier = qualifierBefore(before);
+          const global = qualifier !== undefined || !accessorFor(context).test(before)
+            ? resolver.resolveGlobal(qualifier, name, context, line, (symbol) => CALLABLE_KINDS.has(symbol.kind) && resolver.ownerOf(symbol) === undefined)
+            : undefined;
+          const hits: Resolved[] = global !== undefined && global.symbols.length > 0 ? [global] : [];
+          for (const candidate of members.get(name) ?? []) {
+            const resolved = resolver.resolveMember(candidate, resolver.ownerOf(candidate)!, before.replace(/::\$$/, '::'), context, line);
+            // A free function the name resolves to wins over members that merely share it.
+            if (resolved !== undefined && resolved.symbols.includes(candidate) && !(hits.length > 0 && resolved.via === 'name')) {
+              hits.push(resolved.symbols.length === 1 ? resolved : { symbols: [candidate], via: resolved.via });
+            }
+          }
+          const callees = new Set(hits.flatMap((hit) => hit.symbols));
+          if (callees.size !== 1) {
+            unresolved += callees.size > 1 ? 1 : 0;
+            continue;
+          }
+          const callee = [...callees][0]!;
+          const via = hits[0]!.via;
+          if (callee === caller || !(inScope(caller) || inScope(callee))) {
+            continue;
+          }
+          const from = addCallGraphNode(nodes, caller);
+          const to = addCallGraphNode(nodes, callee);
+          const edge = edges.get(`${from} ${to}`) ?? { from, to, calls: 0, via: [] };
+          edge.calls += 1;
+          if (!edge.via.includes(via)) {
+            edge.via.push(via);
+          }
+          edges.set(`${from} ${to}`, edge);
+        }
+      });
+    }
+  }
+
+  const ordered = [...nodes.values()].sort((left, right) => left.file.localeCompare(right.file) || left.line - right.line);
+  const position = new Map(ordered.map((node, index) => [node.id, index]));
+  return {
+    nodes: ordered,
+    edges: [...edges.values()].sort((left, right) => position.get(left.from)! - position.get(right.from)! || position.get(left.to)! - position.get(right.to)!),
+    unresolved,
+  };
+}
+
+function addCallGraphNode(nodes: Map<string, CallGraphNode>, symbol: CodeSymbol): string {
+  const id = getCodeSymbolId(symbol);
+  if (!nodes.has(id)) {
+    nodes.set(id, { id, name: qualifiedName(symbol), kind: symbol.kind, language: symbol.language, file: symbol.file, line: symbol.line });
+  }
+  return id;
+}
+
+function createReferenceResolver(symbols: CodeSymbol[], contexts: Map<string, FileContext>) {
+  const byName = new Map<string, CodeSymbol[]>();
+  const owners = new Map<CodeSymbol, CodeSymbol | undefined>();
+
+  for (const symbol of symbols) {
+    const named = byName.get(symbol.name) ?? [];
+    named.push(symbol);
+    byName.set(symbol.name, named);
+  }
+
+  /** The type declaring a member: its container type, or the Rust `impl` block around it. */
+  function ownerOf(symbol: CodeSymbol): CodeSymbol | undefined {
+    if (!owners.has(symbol)) {
+      owners.set(symbol, findOwner(symbol));
+    }
+    return owners.get(symbol);
+  }
+
+  function findOwner(symbol: CodeSymbol): CodeSymbol | undefined {
+    if (!MEMBER_KINDS.has(symbol.kind)) {
+      return undefined;
+    }
+    if (symbol.container !== undefined) {
+      const container = normalizePath(symbol.container);
+      const owner = symbols.find((candidate) => TYPE_KINDS.has(candidate.kind) && qualifiedName(candidate) === container);
+      if (owner !== undefined || symbol.kind !== 'function') {
+        return owner;
+      }
+    }
+    const context = contexts.get(symbol.file);
+    const impl = context?.impls.filter((block) => block.start <= symbol.line && symbol.line <= block.end).at(-1);
+    return impl === undefined ? undefined : implType(impl, context!);
+  }
+
+  /** Local names other files import the symbol under (`use x::Money as Cash;`). */
+  function aliasesFor(target: CodeSymbol, context: FileContext): string[] {
+    return [...context.imports.entries()]
+      .filter(([local, path]) => local !== target.name && lastSegment(path) === target.name && pathMatches(qualifiedName(target), path))
+      .map(([local]) => local);
+  }
+
+  function resolveGlobal(
+    qualifier: string | undefined,
+    name: string,
+    context: FileContext,
+    line: number,
+    accept: (symbol: CodeSymbol) => boolean,
+  ): Resolved | undefined {
+    const imported = context.imports.get(name);
+    const localName = imported !== undefined ? lastSegment(imported) : name;
+    const candidates = (byName.get(localName) ?? []).filter(accept);
+    if (candidates.length === 0) {
+      return undefined;
+    }
+
+    if (qualifier !== undefined) {
+      const [head, ...rest] = normalizePath(qualifier).split('.');
+      const expanded = [context.imports.get(head!) ?? head!, ...rest].join('.');
+      return { symbols: candidates.filter((candidate) => pathMatches(qualifiedName(candidate), `${expanded}.${localName}`)), via: 'qualified' };
+    }
+
+    const local = candidates.filter((candidate) => candidate.file === context.file);
+    if (local.length > 0) {
+      return { symbols: local, via: 'same-file' };
+    }
+    if (imported !== undefined) {
+      return { symbols: candidates.filter((candidate) => pathMatches(qualifiedName(candidate), imported)), via: 'import' };
+    }
+
+    const scopes = scopesAt(context, line);
+    const scoped = candidates.filter((candidate) => scopes.has(parentPath(candidate)));
+    if (scoped.length > 0) {
+      return { symbols: scoped, via: 'namespace' };
+    }
+    // Top-level declarations are visible everywhere except from PHP namespaces and other Rust modules.
+    const global = candidates.filter((candidate) => parentPath(candidate) === '');
+    if (global.length > 0 && context.language !== 'rust' && !(context.language === 'php' && context.declaresNamespace)) {
+      return { symbols: global, via: 'namespace' };
+    }
+    return { symbols: candidates, via: 'name' };
+  }
+
+  function resolveMember(target: CodeSymbol, owner: CodeSymbol, before: string, context: FileContext, line: number): Resolved | undefined {
+    const candidates = (byName.get(target.name) ?? []).filter((symbol) => ownerOf(symbol) !== undefined);
+    const accessor = accessorFor(context);
+    if (!accessor.test(before)) {
+      // An unqualified call inside a type resolves through the implicit receiver.
+      const enclosing = enclosingType(context, line);
+      if (enclosing !== undefined && dispatchesTo(enclosing, owner, target.name)) {
+        return { symbols: [target], via: 'receiver' };
+      }
+      return enclosing !== undefined && candidates.some((candidate) => ownerOf(candidate) === enclosing)
+        ? { symbols: [], via: 'receiver' }
+        : { symbols: candidates, via: 'name' };
+    }
+
+    const receiver = receiverType(before.replace(accessor, ''), context, line);
+    if (receiver === undefined) {
+      return { symbols: candidates, via: 'name' };
+    }
+    return dispatchesTo(receiver, owner, target.name)
+      ? { symbols: [target], via: 'receiver' }
+      : { symbols: [], via: 'receiver' };
+  }
+
+  function receiverType(expression: string, context: FileContext, line: number, depth = 0): CodeSymbol | undefined {
+    if (depth > MAX_INFERENCE_DEPTH) {
+      return undefined;
+    }
+    const trimmed = expression.replace(/[?!]\s*$/, '');
+    if (SELF_RECEIVER.test(trimmed)) {
+      return enclosingType(context, line);
+    }
+    const constructed = /\bnew\s+\\?([\w\\.]+)\s*\([^()]*\)\s*\)?\s*$/.exec(trimmed)
+      ?? /([A-Z][\w:]*?)(?:::new\([^()]*\)|\.new(?:\([^()]*\))?)\s*$/.exec(trimmed);
+    if (constructed !== null) {
+      return resolveType(constructed[1]!, context, line);
+    }
+    if (/\)\s*$/.test(trimmed)) {
+      return callType(trimmed, context, line, depth);
+    }
+    // `self.store`, `$this->store`, `order.customer`: the declared type of the field.
+    const field = /^(.*?\S)\s*(?:\?->|->|\?\.|&\.|\.)\s*\$?([A-Za-z_]\w*)\s*$/.exec(trimmed);
+    if (field !== null && !/^[A-Z]/.test(field[2]!)) {
+      const owner = receiverType(field[1]!, context, line, depth + 1);
+      return owner === undefined ? undefined : fieldType(owner, field[2]!);
+    }
+    const typeName = /((?:[A-Z]\w*(?:::|\\|\.))*[A-Z]\w*)\s*$/.exec(trimmed);
+    if (typeName !== null) {
+      return resolveType(typeName[1]!, context, line);
+    }
+    const variable = /(\$|@)?([A-Za-z_]\w*)\s*$/.exec(trimmed);
+    if (variable === null) {
+      return undefined;
+    }
+    const binding = inferVariableType(context, `${variable[1] ?? ''}${variable[2]!}`, line);
+    if (binding === undefined) {
+      return undefined;
+    }
+    return 'type' in binding
+      ? resolveType(binding.type, context, line)
+      : receiverType(binding.expression, context, binding.line, depth + 1);
+  }
+
+  // `open()`, `bank::open()`, `factory.Create()`, `Money::of(5)`: the callee's declared return type.
+  function callType(expression: string, context: FileContext, line: number, depth: number): CodeSymbol | undefined {
+    const open = matchingParen(expression);
+    if (open === undefined) {
+      return undefined;
+    }
+    const callee = expression.slice(0, open);
+    const member = /^(.*?\S)\s*(?:\?->|->|\?\.|&\.|\.)\s*([A-Za-z_]\w*)\s*$/.exec(callee);
+    if (member !== null) {
+      const owner = receiverType(member[1]!, context, line, depth + 1);
+      return owner === undefined ? undefined : returnType(findMember(owner, member[2]!), depth);
+    }
+    const path = /((?:[A-Za-z_]\w*(?:::|\\))*)([A-Za-z_]\w*)\s*$/.exec(callee);
+    if (path === null) {
+      return undefined;
+    }
+    const qualifier = path[1]!.replace(/(?:::|\\)$/, '').replace(/^(?:crate|self|super)(?:::|$)/, '');
+    if (qualifier.length > 0 && /^[A-Z]/.test(lastSegment(normalizePath(qualifier)))) {
+      const owner = resolveType(qualifier, context, line);
+      if (owner !== undefined) {
+        return returnType(findMember(owner, path[2]!), depth);
+      }
+    }
+    const resolved = resolveGlobal(qualifier.length > 0 ? qualifier : undefined, path[2]!, context, line, (symbol) => symbol.kind === 'function' && ownerOf(symbol) === undefined);
+    return resolved?.symbols.length === 1 ? returnType(resolved.symbols[0], depth) : undefined;
+  }
+
+  /** The type a function returns: C# `returns`, or the Rust `-> T` / PHP `): T` in its signature. */
+  function returnType(fn: CodeSymbol | undefined, depth: number): CodeSymbol | undefined {
+    if (fn === undefined || depth > MAX_INFERENCE_DEPTH) {
+      return undefined;
+    }
+    const context = contexts.get(fn.file);
+    if (context === undefined) {
+      return undefined;
+    }
+    let declared = fn.attributes?.returns?.replace(/^(?:Task|ValueTask)<(.+)>$/, '$1');
+    if (declared === undefined) {
+      const signature = context.code.slice(fn.line - 1, fn.line + 3).join(' ');
+      const body = signature.search(/[{;]/);
+      const head = body === -1 ? signature : signature.slice(0, body);
+      declared = /->\s*&?(?:'\w+\s+)?(?:mut\s+)?((?:\w+::)*\w+)/.exec(head)?.[1]
+        ?? /\)\s*:\s*\??\s*(\\?[\w\\]+)/.exec(head)?.[1];
+    }
+    if (declared === undefined) {
+      return undefined;
+    }
+    const name = declared.replace(/<.*$/, '').replace(/\?$/, '');
+    return /^(?:Self|self|static)$/.test(name) ? ownerOf(fn) : resolveType(name, context, fn.line);
+  }
+
+  // The declared type of a field or property, looked up in the file that declares the type.
+  function fieldType(type: CodeSymbol, field: string): CodeSymbol | undefined {
+    const property = (byName.get(field) ?? []).find((symbol) => symbol.kind === 'property' && ownerOf(symbol) === type);
+    const context = contexts.get(type.file);
+    if (context === undefined) {
+      return undefined;
+    }
+    if (property?.attributes?.type !== undefined) {
+      return resolveType(property.attributes.type.replace(/<.*$/, '').replace(/\?$/, ''), context, property.line);
+    }
+    for (const name of [field, `$${field}`]) {
+      const binding = inferVariableType(context, name, type.endLine ?? type.line, type.line);
+      if (binding !== undefined && 'type' in binding) {
+        return resolveType(binding.type, context, type.line);
+      }
+    }
+    return undefined;
+  }
+
+  function findMember(type: CodeSymbol, name: string): CodeSymbol | undefined {
+    const members = (byName.get(name) ?? []).filter((symbol) => ownerOf(symbol) !== undefined);
+    return members.find((symbol) => ownerOf(symbol) === type)
+      ?? members.find((symbol) => ancestors(type).has(ownerOf(symbol)!));
+  }
+
+  function resolveType(path: string, context: FileContext, line: number): CodeSymbol | undefined {
+    const segments = normalizePath(path).split('.');
+    const name = segments.pop()!;
+    const resolved = resolveGlobal(segments.length > 0 ? segments.join('.') : undefined, name, context, line, (symbol) => TYPE_KINDS.has(symbol.kind));
+    return resolved?.symbols.length === 1 ? resolved.symbols[0] : undefined;
+  }
+
+  function findType(name: string, preferredFile: string): CodeSymbol | undefined {
+    const types = (byName.get(name) ?? []).filter((symbol) => TYPE_KINDS.has(symbol.kind));
+    return types.find((symbol) => symbol.file === preferredFile) ?? (types.length === 1 ? types[0] : undefined);
+  }
+
+  function enclosingType(context: FileContext, line: number): CodeSymbol | undefined {
+    const declared = context.symbols
+      .filter((symbol) => TYPE_KINDS.has(symbol.kind) && symbol.endLine !== undefined && symbol.line <= line && line <= symbol.endLine)
+      .at(-1);
+    if (declared !== undefined) {
+      return declared;
+    }
+    const impl = context.impls.filter((block) => block.start <= line && line <= block.end).at(-1);
+    return impl === undefined ? undefined : implType(impl, context);
+  }
+
+  // `impl Money { ... }` may sit in another file than `struct Money`; the impl file's imports say which one.
+  function implType(impl: { type: string; start: number }, context: FileContext): CodeSymbol | undefined {
+    return resolveType(impl.type, context, impl.start) ?? findType(impl.type, context.file);
+  }
+
+  // Namespaces visible at a line: the file's namespaces and imports plus every enclosing type or module.
+  function scopesAt(context: FileContext, line: number): Set<string> {
+    const scopes = new Set(context.namespaces);
+    for (const symbol of context.symbols) {
+      if ((TYPE_KINDS.has(symbol.kind) || symbol.kind === 'namespace') && symbol.line <= line && line <= (symbol.endLine ?? symbol.line)) {
+        const path = qualifiedName(symbol).split('.');
+        for (let length = 1; length <= path.length; length += 1) {
+          scopes.add(path.slice(0, length).join('.'));
+        }
+      }
+    }
+    if (context.language === 'rust') {
+      scopes.add(rustModulePath(context.file));
+    }
+    return scopes;
+  }
+
+  // True when calling `member` on `receiver` can land on the member declared by `owner`: the
+  // receiver is the owner, a supertype the owner implements, or a subtype that does not redeclare it.
+  function dispatchesTo(receiver: CodeSymbol, owner: CodeSymbol, member: string): boolean {
+    if (receiver === owner || ancestors(owner).has(receiver)) {
+      return true;
+    }
+    if (!ancestors(receiver).has(owner)) {
+      return false;
+    }
+    const redeclared = (byName.get(member) ?? []).some((symbol) => symbol !== owner && ownerOf(symbol) === receiver);
+    return !redeclared;
+  }
+
+  function ancestors(type: CodeSymbol, seen = new Set<CodeSymbol>()): Set<CodeSymbol> {
+    for (const base of typeBases(type)) {
+      const resolved = findType(lastSegment(normalizePath(base.replace(/<.*$/, ''))), type.file);
+      if (resolved !== undefined && !seen.has(resolved)) {
+        seen.add(resolved);
+        ancestors(resolved, seen);
+      }
+    }
+    return seen;
+  }
+
+  return { ownerOf, aliasesFor, resolveGlobal, resolveMember };
+}
+
+/** The one symbol a `findSymbolReferences` query names; no match or several is an error. */
+export function resolveTarget(symbols: CodeSymbol[], query: string): CodeSymbol {
+  const location = /^(.+):(\d+)$/.exec(query);
+  const normalized = normalizePath(query);
+  const matches = STABLE_SYMBOL_ID_PATTERN.test(query)
+    ? symbols.filter((symbol) => symbol.stableId === query)
+    : location !== null
+    ? symbols.filter((symbol) => (symbol.locations ?? [symbol]).some((entry) => entry.file === location[1] && entry.line === Number(location[2])))
+    : symbols.filter((symbol) => symbol.name === query || qualifiedName(symbol) === normalized || qualifiedName(symbol).endsWith(`.${normalized}`));
+  if (matches.length === 0) {
+    throw new Error(`No indexed symbol matches "${query}".`);
+  }
+  if (matches.length > 1) {
+    throw new Error(`"${query}" is ambiguous: ${matches.map((symbol) => `${qualifiedName(symbol)} (${getCodeSymbolId(symbol)})`).join(', ')}. Use a qualified name or a file:line id.`);
+  }
+  return matches[0]!;
+}
+
+function createFileContext(file: string, content: string, language: CodeLanguage, symbols: CodeSymbol[]): FileContext {
+  const raw = content.split(/\r?\n/);
+  const code = stripComments(raw, language);
+  const namespaces: string[] = [];
+  const imports = new Map<string, string>();
+  const joined = code.join('\n');
+
+  if (language === 'php') {
+    for (const match of joined.matchAll(/^\s*namespace\s+([\w\\]+)\s*[;{]/gm)) {
+      namespaces.push(normalizePath(match[1]!));
+    }
+    for (const match of joined.matchAll(/^\s*use\s+(?:function\s+|const\s+)?\\?([\w\\]+?)\\?(?:\{([^}]*)\})?(?:\s+as\s+(\w+))?\s*;/gm)) {
+      const prefix = normalizePath(match[1]!);
+      if (match[2] !== undefined) {
+        for (const entry of match[2].split(',').map((part) => part.trim()).filter((part) => part.length > 0)) {
+          const [path, alias] = entry.split(/\s+as\s+/);
+          addImport(imports, `${prefix}.${normalizePath(path!)}`, alias);
+        }
+      } else {
+        addImport(imports, prefix, match[3]);
+      }
+    }
+  } else if (language === 'csharp') {
+    for (const match of joined.matchAll(/^\s*namespace\s+([\w.]+)/gm)) {
+      namespaces.push(match[1]!);
+    }
+    for (const match of joined.matchAll(/^\s*(?:global\s+)?using\s+(?:static\s+)?(?:(\w+)\s*=\s*)?([\w.]+)\s*;/gm)) {
+      if (match[1] !== undefined) {
+        addImport(imports, match[2]!, match[1]);
+      } else {
+        namespaces.push(match[2]!);
+      }
+    }
+  } else if (language === 'rust') {
+    for (const match of joined.matchAll(/\buse\s+([^;]+);/g)) {
+      for (const entry of expandRustUse(match[1]!.replace(/\s*(::|[{},])\s*/g, '$1').trim())) {
+        const path = normalizePath(entry.path.replace(/^(?:crate|self|super)::/, '').replace(/^(?:(?:self|super)::)+/, ''));
+        if (path.endsWith('*')) {
+          namespaces.push(path.replace(/\.?\*$/, ''));
+        } else {
+          addImport(imports, path, entry.alias);
+        }
+      }
+    }
+  }
+
+  return {
+    file,
+    language,
+    raw,
+    code,
+    positions: createPositionMapper(content),
+    namespaces,
+    declaresNamespace: /^\s*namespace\s/m.test(joined),
+    imports,
+    symbols,
+    impls: language === 'rust' ? findImplBlocks(joined) : [],
+  };
+}
+
+function addImport(imports: Map<string, string>, path: string, alias: string | undefined): void {
+  const local = alias ?? lastSegment(path);
+  if (local !== '_' && local !== 'self') {
+    imports.set(local, path);
+  }
+}
+
+// `a::{b, c::{d as e, *}}` -> a::b, a::c::d as e, a::c::*
+function expandRustUse(tree: string): Array<{ path: string; alias?: string }> {
+  const open = tree.indexOf('{');
+  if (open === -1) {
+    const [path, alias] = tree.split(/\s+as\s+/);
+    const cleaned = path!.replace(/::self$/, '');
+    return [{ path: cleaned, ...(alias !== undefined ? { alias } : {}) }];
+  }
+  const prefix = tree.slice(0, open);
+  const body = tree.slice(open + 1, tree.lastIndexOf('}'));
+  const parts: string[] = [];
+  let depth = 0;
+  let current = '';
+  for (const char of body) {
+    if (char === ',' && depth === 0) {
+      parts.push(current);
+      current = '';
+      continue;
+    }
+    depth += char === '{' ? 1 : char === '}' ? -1 : 0;
+    current += char;
+  }
+  parts.push(current);
+  return parts.filter((part) => part.length > 0).flatMap((part) => expandRustUse(part === 'self' ? prefix.replace(/::$/, '') : `${prefix}${part}`));
+}
+
+function findImplBlocks(code: string): Array<{ type: string; start: number; end: number }> {
+  const blocks: Array<{ type: string; start: number; end: number }> = [];
+  for (const match of code.matchAll(/\bimpl\b(?:\s*<[^{;]*?>)?\s+(?:[^{;]*?\bfor\s+)?(?:[\w]+::)*([A-Za-z_]\w*)(?:<[^{;]*?>)?[^{;]*?\{/g)) {
+    const open = match.index + match[0].length - 1;
+    let depth = 0;
+    let close = code.length - 1;
+    for (let index = open; index < code.length; index += 1) {
+      if (code[index] === '{') {
+        depth += 1;
+      } else if (code[index] === '}' && (depth -= 1) === 0) {
+        close = index;
+        break;
+      }
+    }
+    blocks.push({ type: match[1]!, start: lineAt(code, match.index), end: lineAt(code, close) });
+  }
+  return blocks;
+}
+
+// The closest earlier binding of the variable: one naming its type (`x = new T(`, `x = T::new(`,
+// `x = T.new`, `let x: T`, `T x`, `T $x`), or a plain `x = expression` whose type the caller infers.
+// With `from`, only lines from there on are searched, e.g. the body of a type for its fields.
+function inferVariableType(
+  context: FileContext,
+  variable: string,
+  line: number,
+  from = 1,
+): { type: string } | { expression: string; line: number } | undefined {
+  const name = escapeRegExp(variable);
+  const boundary = /^\w/.test(variable) ? '\\b' : '';
+  const patterns = [
+    new RegExp(`${boundary}${name}\\s*=\\s*new\\s+\\\\?([\\w\\\\.]+)`),
+    new RegExp(`${boundary}${name}\\s*=\\s*((?:\\w+::)*[A-Z]\\w*)(?:::\\w+\\(|\\.new\\b|\\s*\\{)`),
+    new RegExp(`${boundary}${name}\\s*:\\s*&?(?:'\\w+\\s+)?(?:mut\\s+)?(?:dyn\\s+|impl\\s+)?((?:\\w+::)*[A-Z]\\w*)`),
+    new RegExp(`(?:^|[\\s(,?])\\\\?((?:[A-Z]\\w*[.\\\\])*[A-Z]\\w*)(?:<[^>]*>)?\\??\\s+${name}\\b`),
+  ];
+  const assignment = new RegExp(`${boundary}${name}\\s*=(?![=>~])\\s*([^;]+?)\\s*;?\\s*$`);
+  for (let index = Math.min(line, context.code.length) - 1; index >= from - 1; index -= 1) {
+    const text = context.code[index]!;
+    for (const pattern of patterns) {
+      const match = pattern.exec(text);
+      if (match !== null) {
+        return { type: match[1]! };
+      }
+    }
+    const assigned = assignment.exec(text);
+    if (assigned !== null) {
+      return { expression: assigned[1]!, line: index + 1 };
+    }
+  }
+  return undefined;
+}
+
+// Offset of the `(` matching the `)` that ends the expression.
+function matchingParen(expression: string): number | undefined {
+  let depth = 0;
+  for (let index = expression.trimEnd().length - 1; index >= 0; index -= 1) {
+    if (expression[index] === ')') {
+      depth += 1;
+    } else if (expression[index] === '(' && (depth -= 1) === 0) {
+      return index;
+    }
+  }
+  return undefined;
+}
+
+function accessorFor(context: FileContext): RegExp {
+  return context.language === 'rust' ? RUST_ACCESSOR : ACCESSOR;
+}
+
+function innermostSymbol(context: FileContext, line: number): CodeSymbol | undefined {
+  return context.symbols
+    .filter((symbol) => symbol.endLine !== undefined && symbol.line <= line && line <= symbol.endLine && symbol.kind !== 'namespace')
+    .sort((left, right) => (left.endLine! - left.line) - (right.endLine! - right.line))[0];
+}
+
+function typeBases(symbol: CodeSymbol): string[] {
+  const attributes = symbol.attributes ?? {};
+  return [attributes.bases, attributes.extends, attributes.implements, attributes.superclass]
+    .flatMap((value) => (value ?? '').split(','))
+    .map((value) => value.trim())
+    .filter((value) => value.length > 0);
+}
+
+function findOccurrences(text: string, name: string): number[] {
+  const pattern = new RegExp(`(?<![\\w])${escapeRegExp(name)}(?![\\w?!])`, 'g');
+  return [...text.matchAll(pattern)].map((match) => match.index);
+}
+
+// `App\Models\` in `App\Models\Money`, `Shop.` in `Shop.Money`, `crate::store::` in `crate::store::Money`.
+function qualifierBefore(before: string): string | undefined {
+  const match = /((?:[A-Za-z_]\w*(?:\\|::|\.))+)$/.exec(before);
+  if (match === null) {
+    return undefined;
+  }
+  const qualifier = match[1]!.replace(/(?:\\|::|\.)$/, '').replace(/^(?:crate|self|super)(?:::|$)/, '');
+  // Lowercase receivers (`store.Save`) are values, not namespaces, except Rust module paths.
+  if (qualifier.length === 0 || (/\.$/.test(match[1]!) && /^[a-z]/.test(qualifier) && !qualifier.includes('.'))) {
+    return undefined;
+  }
+  return qualifier;
+}
+
+/** Module path (for Rust), container and name, joined with dots: `parser.Parser.new`. */
+export function qualifiedName(symbol: CodeSymbol): string {
+  const module = symbol.language === 'rust' ? rustModulePath(symbol.file) : '';
+  return [module, symbol.container !== undefined ? normalizePath(symbol.container) : '', symbol.name].filter((part) => part.length > 0).join('.');
+}
+
+function parentPath(symbol: CodeSymbol): string {
+  const path = qualifiedName(symbol);
+  return path.slice(0, Math.max(0, path.length - symbol.name.length - 1));
+}
+
+// src/store/mod.rs -> store, src/store/money.rs -> store.money, src/lib.rs -> ''
+function rustModulePath(file: string): string {
+  const segments = file.split('/');
+  const srcIndex = segments.lastIndexOf('src');
+  const inner = srcIndex >= 0 ? segments.slice(srcIndex + 1) : segments;
+  const stem = posix.basename(inner.pop() ?? '', '.rs');
+  return [...inner, ...(stem === 'mod' || stem === 'lib' || stem === 'main' ? [] : [stem])].join('.');
+}
+
+function normalizePath(path: string): string {
+  return path.replace(/::|\\|#|->/g, '.').replace(/^\.+|\.+$/g, '');
+}
+
+function lastSegment(path: string): string {
+  return path.split('.').at(-1) ?? path;
+}
+
+// `Shop.Money` matches an import of `Shop.Money` and a crate-prefixed `mycrate.Shop.Money`.
+function pathMatches(qualified: string, path: string): boolean {
+  return qualified === path || path.endsWith(`.${qualified}`) || qualified.endsWith(`.${path}`);
+}
+
+// Blanks comments and string contents, keeping columns, so only code is matched.
+function stripComments(lines: string[], language: CodeLanguage): string[] {
+  const hashComments = language === 'ruby' || language === 'php' || language === 'shell';
+  const singleQuotes = language !== 'rust';
+  let inBlock = false;
+  return lines.map((line) => {
+    let output = '';
+    for (let index = 0; index < line.length; index += 1) {
+      const char = line[index]!;
+      if (inBlock) {
+        if (char === '*' && line[index + 1] === '/') {
+          inBlock = false;
+          output += '  ';
+          index += 1;
+        } else {
+          output += ' ';
+        }
+        continue;
+      }
+      if (char === '/' && line[index + 1] === '*') {
+        inBlock = true;
+        output += '  ';
+        index += 1;
+        continue;
+      }
+      if ((char === '/' && line[index + 1] === '/') || (hashComments && char === '#' && line[index + 1] !== '[' && line[index + 1] !== '{')) {
+        break;
+      }
+      if (char === '"' || (singleQuotes && char === '\'')) {
+        let end = index + 1;
+        while (end < line.length && line[end] !== char) {
+          end += line[end] === '\\' ? 2 : 1;
+        }
+        output += `${char}${' '.repeat(Math.max(0, Math.min(end, line.length) - index - 1))}${end < line.length ? char : ''}`;
+        index = end;
+        continue;
+      }
+      output += char;
+    }
+    return output;
+  });
+}
+
+function lineAt(code: string, offset: number): number {
+  let line = 1;
+  for (let index = 0; index < offset; index += 1) {
+    if (code.charCodeAt(index) === 10) {
+      line += 1;
+    }
+  }
+  return line;
+}
+
+function escapeRegExp(value: string): string {
+  return value.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+}
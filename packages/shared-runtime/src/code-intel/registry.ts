@@ -0,0 +1,149 @@
+import { readWorkspaceConfig } from '../config-layers.js';
+import { asRecord } from '../records.js';
+import { csharpExtractor } from './csharp.js';
+import { dockerfileExtractor } from './dockerfile.js';
+import { loadGrammarExtractor, type GrammarRegistration, type GrammarRuntime } from './grammar.js';
+import { hclExtractor } from './hcl.js';
+import { phpExtractor } from './php.js';
+import { rubyExtractor } from './ruby.js';
+import { rustExtractor } from './rust.js';
+import { shellExtractor } from './shell.js';
+import type { CodeLanguageExtractor } from './types.js';
+
+export const BUILTIN_CODE_EXTRACTORS: readonly CodeLanguageExtractor[] = [
+  dockerfileExtractor,
+  shellExtractor,
+  hclExtractor,
+  rubyExtractor,
+  phpExtractor,
+  csharpExtractor,
+  rustExtractor,
+];
+
+export interface LanguageRegistry {
+  register(extractor: CodeLanguageExtractor): void;
+  registerGrammar(grammar: GrammarRegistration, basePath?: string, options?: { runtime?: GrammarRuntime }): Promise<CodeLanguageExtractor>;
+  unregister(language: string): boolean;
+  list(): CodeLanguageExtractor[];
+  detect(fileName: string, content?: string): CodeLanguageExtractor | undefined;
+}
+
+export function createLanguageRegistry(extractors: readonly CodeLanguageExtractor[] = BUILTIN_CODE_EXTRACTORS): LanguageRegistry {
+  const registered: CodeLanguageExtractor[] = [...extractors];
+
+  const registry: LanguageRegistry = {
+    register(extractor) {
+      // User-registered languages take precedence so a grammar can override a built-in extractor.
+      registry.unregister(extractor.language);
+      registered.unshift(extractor);
+    },
+
+    async registerGrammar(grammar, basePath = process.cwd(), options = {}) {
+      const extractor = await loadGrammarExtractor(grammar, basePath, options);
+      registry.register(extractor);
+      return extractor;
+    },
+
+    unregister(language) {
+      const index = registered.findIndex((extractor) => extractor.language === language);
+      if (index === -1) {
+        return false;
+      }
+      registered.splice(index, 1);
+      return true;
+    },
+
+    list() {
+      return [...registered];
+    },
+
+    detect(fileName, content) {
+      const byName = registered.find((extractor) => extractor.matchesFile(fileName));
+      if (byName !== undefined || content === undefined) {
+        return byName;
+      }
+      const firstLine = content.split('\n', 1)[0] ?? '';
+      if (!firstLine.startsWith('#!')) {
+        return undefined;
+      }
+      return registered.find((extractor) => extractor.matchesShebang?.(firstLine) === true);
+    },
+  };
+
+  return registry;
+}
+
+export const GRAMMAR_RUNTIME_ENV_VAR = 'AUTOMATOSX_GRAMMAR_RUNTIME';
+
+/**
+ * Builds a registry with the built-in extractors plus every grammar listed under
+ * `codeIntel.grammars` in `.automatosx/config.json`. Grammars that fail to load are reported
+ * as warnings rather than failing the whole index.
+ */
+export async function loadWorkspaceLanguageRegistry(
+  basePath: string,
+  env: NodeJS.ProcessEnv = process.env,
+): Promise<{ registry: LanguageRegistry; warnings: string[] }> {
+  const registry = createLanguageRegistry();
+  const warnings: string[] = [];
+  const config = await readCodeIntelConfig(basePath);
+  const runtime = resolveGrammarRuntime(env[GRAMMAR_RUNTIME_ENV_VAR] ?? config?.grammarRuntime);
+
+  for (const grammar of readGrammarRegistrations(config)) {
+    try {
+      await registry.registerGrammar(grammar, basePath, { runtime });
+    } catch (error) {
+      warnings.push(`Grammar "${grammar.language}" was not loaded: ${error instanceof Error ? error.message : String(error)}`);
+    }
+  }
+
+  return { registry, warnings };
+}
+
+/** The `codeIntel` section of the workspace config, or `undefined` when it is missing. */
+export async function readCodeIntelConfig(basePath: string): Promise<Record<string, unknown> | undefined> {
+  return asRecord((await readWorkspaceConfig(basePath)).codeIntel);
+}
+
+function resolveGrammarRuntime(value: unknown): GrammarRuntime {
+  return value === 'wasm' ? 'wasm' : 'auto';
+}
+
+function readGrammarRegistrations(config: Record<string, unknown> | undefined): GrammarRegistration[] {
+  const grammars = config?.grammars;
+  if (!Array.isArray(grammars)) {
+    return [];
+  }
+
+  return grammars.flatMap((entry): GrammarRegistration[] => {
+    const grammar = asRecord(entry);
+    if (
+      grammar === undefined
+      || typeof grammar.language !== 'string'
+      || typeof grammar.grammar !== 'string'
+      || typeof grammar.query !== 'string'
+    ) {
+      return [];
+    }
+    return [{
+      language: grammar.language,
+      grammar: grammar.grammar,
+      query: grammar.query,
+      extensions: Array.isArray(grammar.extensions)
+        ? grammar.extensions.filter((extension): extension is string => typeof extension === 'string')
+        : [],
+      fileNames: Array.isArray(grammar.fileNames)
+        ? grammar.fileNames.filter((fileName): fileName is string => typeof fileName === 'string')
+        : undefined,
+      captures: asStringRecord(grammar.captures),
+    }];
+  });
+}
+
+function asStringRecord(value: unknown): Record<string, string> | undefined {
+  const record = asRecord(value);
+  if (record === undefined) {
+    return undefined;
+  }
+  return Object.fromEntries(Object.entries(record).filter((entry): entry is [string, string] => typeof entry[1] === 'string'));
+}
@@ -0,0 +1,46 @@
+import { readFile } from 'node:fs/promises';
+import { join } from 'node:path';
+import { readCodeIndex } from './index.js';
+import { buildOutline } from './outline.js';
+// Fields, variables and Dockerfile instructions would crowd out the declarations worth reading.
+const UNLISTED_KINDS = new Set(['variable', 'property', 'attribute', 'copy', 'port']);
+/**
+ * A compact map of the indexed workspace for a model's context: every file with its declarations
+ * indented by nesting, one signature per line.
+ */
+export async function renderRepoMap(request) {
+    const snapshot = await readCodeIndex(request.basePath);
+    if (snapshot === undefined) {
+        throw new Error('No symbol index found. Run "ax code index" first.');
+    }
+    const output = [];
+    let files = 0;
+    let symbols = 0;
+    for (const entry of [...snapshot.files].sort((left, right) => left.file.localeCompare(right.file))) {
+        const listed = entry.symbols.filter((symbol) => !UNLISTED_KINDS.has(symbol.kind));
+        if (listed.length === 0) {
+            continue;
+        }
+        let content;
+        try {
+            content = await readFile(join(request.basePath, entry.file), 'utf8');
+        }
+        catch {
+            // Deleted since it was indexed.
+            continue;
+        }
+        files += 1;
+        symbols += listed.length;
+        output.push(entry.file);
+        const render = (node, depth) => {
+            output.push(`${'  '.repeat(depth)}${node.signature ?? `${node.kind} ${node.name}`}`);
+            for (const child of node.children) {
+                render(child, depth + 1);
+            }
+        };
+        for (const node of buildOutline(content, listed)) {
+            render(node, 1);
+        }
+    }
+    return { files, symbols, text: output.length > 0 ? `${output.join('\n')}\n` : '' };
+}
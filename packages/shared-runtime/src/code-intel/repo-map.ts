@@ -0,0 +1,56 @@
+import { readFile } from 'node:fs/promises';
+import { join } from 'node:path';
+import { readCodeIndex } from './index.js';
+import { buildOutline, type OutlineNode } from './outline.js';
+import type { CodeSymbolKind } from './types.js';
+
+export interface RepoMap {
+  /** Indexed files with at least one listed declaration. */
+  files: number;
+  symbols: number;
+  text: string;
+}
+
+// Fields, variables and Dockerfile instructions would crowd out the declarations worth reading.
+const UNLISTED_KINDS = new Set<CodeSymbolKind>(['variable', 'property', 'attribute', 'copy', 'port']);
+
+/**
+ * A compact map of the indexed workspace for a model's context: every file with its declarations
+ * indented by nesting, one signature per line.
+ */
+export async function renderRepoMap(request: { basePath: string }): Promise<RepoMap> {
+  const snapshot = await readCodeIndex(request.basePath);
+  if (snapshot === undefined) {
+    throw new Error('No symbol index found. Run "ax code index" first.');
+  }
+
+  const output: string[] = [];
+  let files = 0;
+  let symbols = 0;
+  for (const entry of [...snapshot.files].sort((left, right) => left.file.localeCompare(right.file))) {
+    const listed = entry.symbols.filter((symbol) => !UNLISTED_KINDS.has(symbol.kind));
+    if (listed.length === 0) {
+      continue;
+    }
+    let content: string;
+    try {
+      content = await readFile(join(request.basePath, entry.file), 'utf8');
+    } catch {
+      // Deleted since it was indexed.
+      continue;
+    }
+    files += 1;
+    symbols += listed.length;
+    output.push(entry.file);
+    const render = (node: OutlineNode, depth: number): void => {
+      output.push(`${'  '.repeat(depth)}${node.signature ?? `${node.kind} ${node.name}`}`);
+      for (const child of node.children) {
+        render(child, depth + 1);
+      }
+    };
+    for (const node of buildOutline(content, listed)) {
+      render(node, 1);
+    }
+  }
+  return { files, symbols, text: output.length > 0 ? `${output.join('\n')}\n` : '' };
+}
@@ -0,0 +1,226 @@
+import { basename, extname } from 'node:path';
+import type { CodeLanguageExtractor, CodeSymbol } from './types.js';
+
+const RUBY_EXTENSIONS = new Set(['.rb', '.rake', '.gemspec', '.ru']);
+const RUBY_FILE_NAMES = new Set(['rakefile', 'gemfile', 'guardfile', 'capfile']);
+const CLASS_PATTERN = /^class\s+([A-Z][\w:]*)(?:\s*<\s*([A-Z][\w:]*))?/;
+const MODULE_PATTERN = /^module\s+([A-Z][\w:]*)/;
+const DEF_PATTERN = /^def\s+(self\.)?([A-Za-z_]\w*[?!=]?|\[\]=?|[+\-*/%<>=!~^&|]+)\s*(\([^)]*\))?\s*(=(?![=~>]))?/;
+const ATTR_PATTERN = /^attr_(reader|writer|accessor)\b\s*\(?(.*)$/;
+const VISIBILITY_PATTERN = /^(public|protected|private)(?:\s+(def\b.*)|\s*$)/;
+const CONDITIONAL_OPENERS = /^(?:if|unless|while|until|case|begin|for)\b/;
+const ASSIGNED_OPENERS = /(?:^|[^=!<>])=\s*(?:if|unless|case|begin|while|until)\b/;
+const HEREDOC_PATTERN = /<<([~-]?)(['"`]?)([A-Za-z_]\w*)\2/g;
+
+interface OpenScope {
+  kind: 'class' | 'module' | 'singleton' | 'def' | 'block';
+  name?: string;
+  symbolIndex?: number;
+  visibility: string;
+}
+
+export const rubyExtractor: CodeLanguageExtractor = {
+  language: 'ruby',
+  matchesFile(fileName) {
+    return RUBY_EXTENSIONS.has(extname(fileName).toLowerCase())
+      || RUBY_FILE_NAMES.has(basename(fileName).toLowerCase());
+  },
+  matchesShebang(firstLine) {
+    return /^#!.*\bruby\b/.test(firstLine);
+  },
+  extract(content, file) {
+    return extractRubySymbols(content, file);
+  },
+};
+
+export function extractRubySymbols(content: string, file: string): CodeSymbol[] {
+  const lines = content.split(/\r?\n/);
+  const symbols: CodeSymbol[] = [];
+  const stack: OpenScope[] = [];
+  const heredocs: string[] = [];
+  let inBlockComment = false;
+  let openQuote: string | undefined;
+
+  for (let index = 0; index < lines.length; index += 1) {
+    const line = lines[index] ?? '';
+    const lineNumber = index + 1;
+
+    if (heredocs.length > 0) {
+      if (line.trim() === heredocs[0]) {
+        heredocs.shift();
+      }
+      continue;
+    }
+    if (inBlockComment) {
+      inBlockComment = !line.startsWith('=end');
+      continue;
+    }
+    if (line.startsWith('=begin')) {
+      inBlockComment = true;
+      continue;
+    }
+    if (line === '__END__') {
+      break;
+    }
+
+    const sanitized = sanitizeRubyLine(line, openQuote);
+    openQuote = sanitized.openQuote;
+    for (const heredoc of sanitized.code.matchAll(HEREDOC_PATTERN)) {
+      if (heredoc[1] !== '' || heredoc[2] !== '' || /^[A-Z_][A-Z0-9_]*$/.test(heredoc[3]!)) {
+        heredocs.push(heredoc[3]!);
+      }
+    }
+
+    for (const rawStatement of sanitized.code.split(';')) {
+      const statement = rawStatement.trim();
+      if (statement.length === 0) {
+        continue;
+      }
+      processStatement(statement, lineNumber, file, stack, symbols);
+    }
+  }
+
+  return symbols;
+}
+
+function processStatement(
+  statement: string,
+  lineNumber: number,
+  file: string,
+  stack: OpenScope[],
+  symbols: CodeSymbol[],
+): void {
+  const owner = findOwner(stack);
+  let opened = false;
+  let body = statement;
+
+  const visibility = VISIBILITY_PATTERN.exec(statement);
+  if (visibility !== null && owner !== undefined) {
+    if (visibility[2] === undefined) {
+      owner.visibility = visibility[1]!;
+      return;
+    }
+    body = visibility[2];
+  }
+
+  const classMatch = CLASS_PATTERN.exec(body);
+  const moduleMatch = classMatch === null ? MODULE_PATTERN.exec(body) : null;
+  const defMatch = DEF_PATTERN.exec(body);
+
+  if (/^class\s*<<\s*self\b/.test(body)) {
+    stack.push({ kind: 'singleton', visibility: 'public' });
+    opened = true;
+  } else if (classMatch !== null || moduleMatch !== null) {
+    const match = (classMatch ?? moduleMatch)!;
+    const kind = classMatch !== null ? 'class' : 'module';
+    const container = qualifiedName(stack);
+    symbols.push({
+      name: match[1]!,
+      kind,
+      language: 'ruby',
+      file,
+      line: lineNumber,
+      ...(container !== undefined ? { container } : {}),
+      ...(classMatch?.[2] !== undefined ? { attributes: { superclass: classMatch[2] } } : {}),
+    });
+    stack.push({ kind, name: match[1]!, symbolIndex: symbols.length - 1, visibility: 'public' });
+    opened = true;
+  } else if (defMatch !== null) {
+    const classScope = defMatch[1] !== undefined || stack.at(-1)?.kind === 'singleton';
+    const methodVisibility = visibility?.[1] ?? (defMatch[1] !== undefined ? 'public' : owner?.visibility ?? 'public');
+    const attributes: Record<string, string> = {};
+    if (classScope) {
+      attributes.scope = 'class';
+    }
+    if (methodVisibility !== 'public') {
+      attributes.visibility = methodVisibility;
+    }
+    const container = qualifiedName(stack);
+    symbols.push({
+      name: defMatch[2]!,
+      kind: container === undefined ? 'function' : 'method',
+      language: 'ruby',
+      file,
+      line: lineNumber,
+      ...(container !== undefined ? { container } : {}),
+      ...(Object.keys(attributes).length > 0 ? { attributes } : {}),
+    });
+    if (defMatch[4] === undefined) {
+      stack.push({ kind: 'def', symbolIndex: symbols.length - 1, visibility: 'public' });
+      opened = true;
+    } else {
+      symbols[symbols.length - 1]!.endLine = lineNumber;
+    }
+  } else {
+    const attr = ATTR_PATTERN.exec(body);
+    if (attr !== null && owner !== undefined) {
+      const container = qualifiedName(stack);
+      for (const name of attr[2]!.matchAll(/:([A-Za-z_]\w*)/g)) {
+        symbols.push({
+          name: name[1]!,
+          kind: 'attribute',
+          language: 'ruby',
+          file,
+          line: lineNumber,
+          ...(container !== undefined ? { container } : {}),
+          attributes: { access: attr[1]! },
+        });
+      }
+      return;
+    }
+  }
+
+  if (!opened && (CONDITIONAL_OPENERS.test(body) || ASSIGNED_OPENERS.test(body))) {
+    stack.push({ kind: 'block', visibility: 'public' });
+  }
+  if (/\bdo\s*(?:\|[^|]*\|)?\s*$/.test(body) && !/^(?:while|until|for)\b/.test(body)) {
+    stack.push({ kind: 'block', visibility: 'public' });
+  }
+
+  const closers = body.match(/(?<![.:\w])end\b(?![?!:])/g)?.length ?? 0;
+  for (let closed = 0; closed < closers; closed += 1) {
+    const scope = stack.pop();
+    if (scope?.symbolIndex !== undefined) {
+      symbols[scope.symbolIndex]!.endLine = lineNumber;
+    }
+  }
+}
+
+function findOwner(stack: OpenScope[]): OpenScope | undefined {
+  const top = stack.at(-1);
+  return top !== undefined && (top.kind === 'class' || top.kind === 'module' || top.kind === 'singleton') ? top : undefined;
+}
+
+function qualifiedName(stack: OpenScope[]): string | undefined {
+  const names = stack
+    .filter((scope) => scope.kind === 'class' || scope.kind === 'module')
+    .map((scope) => scope.name!);
+  return names.length > 0 ? names.join('::') : undefined;
+}
+
+function sanitizeRubyLine(line: string, openQuote: string | undefined): { code: string; openQuote?: string } {
+  let code = '';
+  let quote = openQuote;
+
+  for (let index = 0; index < line.length; index += 1) {
+    const char = line[index]!;
+    if (quote !== undefined) {
+      if (char === '\\') {
+        index += 1;
+      } else if (char === quote) {
+        quote = undefined;
+        code += char;
+      }
+      continue;
+    }
+    if (char === '#') {
+      break;
+    }
+    if (char === '"' || char === '\'' || char === '`') {
+      quote = char;
+    }
+    code += char;
+  }
+
+  return { code, openQuote: quote };
+}
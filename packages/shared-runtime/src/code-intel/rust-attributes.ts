@@ -0,0 +1,260 @@
+import type { CodeSymbol } from './types.js';
+
+/** An attribute and the lines it applies to: an outer one the item after it, an inner one its block. */
+export interface RustAttribute {
+  /** Without `#[` and `]`, whitespace collapsed: `derive(Debug, Clone)`. */
+  text: string;
+  inner: boolean;
+  from: number;
+  to: number;
+  /** Line of the item an outer attribute is written on; -1 for inner attributes. */
+  attachedLine: number;
+}
+
+export interface SymbolAnnotation {
+  /** The attribute path: `derive`, `serde`, `tokio::main`. */
+  name: string;
+  /** What follows the path: `Debug, Clone` for `derive(Debug, Clone)`, `"1.0"` for `since = "1.0"`. */
+  args?: string;
+}
+
+interface AttributeToken {
+  start: number;
+  end: number;
+  inner: boolean;
+  text: string;
+}
+
+const ITEM_START = /^(?:pub(?:\([^)]*\))?\s+)?(?:(?:fn|struct|enum|union|mod|impl|trait|const|static|use|type|unsafe|async|extern)\b|macro_rules!)/;
+
+// Kept elsewhere: doc comments are prose and `cfg`/`cfg_attr` become `cfg` and `cfgAttr`.
+const SKIPPED_ATTRIBUTES = new Set(['doc', 'cfg', 'cfg_attr']);
+
+/**
+ * Records the attributes written on each Rust item as `annotations` (`{ name: 'tokio::main' }`,
+ * `{ name: 'serde', args: 'rename_all = "camelCase"' }`) and the traits its `#[derive(...)]`
+ * lists as `derives`, so the index can answer which types are `Serialize` or which function is
+ * the async entry point.
+ */
+export function tagAttributeSymbols(symbols: CodeSymbol[], content: string): CodeSymbol[] {
+  if (!symbols.some((symbol) => symbol.language === 'rust') || !content.includes('#')) {
+    return symbols;
+  }
+  const byLine = new Map<number, RustAttribute[]>();
+  for (const attribute of scanRustAttributes(content)) {
+    if (!attribute.inner) {
+      byLine.set(attribute.attachedLine, [...byLine.get(attribute.attachedLine) ?? [], attribute]);
+    }
+  }
+  for (const symbol of symbols) {
+    if (symbol.language !== 'rust') {
+      continue;
+    }
+    const annotations: SymbolAnnotation[] = [];
+    const derives: string[] = [];
+    for (const attribute of byLine.get(symbol.line) ?? []) {
+      const [name, ...args] = splitAttribute(attribute.text);
+      if (SKIPPED_ATTRIBUTES.has(name!)) {
+        continue;
+      }
+      const value = /^[\w:]+\s*=\s*([\s\S]+)$/.exec(attribute.text)?.[1];
+      const rendered = value ?? (args.length > 0 ? args.join(', ') : undefined);
+      annotations.push({ name: name!, ...(rendered !== undefined ? { args: rendered } : {}) });
+      if (name === 'derive') {
+        derives.push(...args);
+      }
+    }
+    if (annotations.length > 0) {
+      symbol.annotations = annotations;
+    }
+    if (derives.length > 0) {
+      symbol.derives = [...new Set(derives)];
+    }
+  }
+  return symbols;
+}
+
+/** Whether the symbol derives `trait`, unconditionally or under a `cfg_attr`; `Serialize` matches `serde::Serialize`. */
+export function derivesTrait(symbol: CodeSymbol, trait: string): boolean {
+  const conditional = (symbol.cfgAttr ?? []).flatMap((entry) => {
+    const [name, ...args] = splitAttribute(entry.attributes);
+    return name === 'derive' ? args : [];
+  });
+  return [...symbol.derives ?? [], ...conditional].some((derived) => matchesPath(derived, trait));
+}
+
+/** Whether the symbol carries the attribute; `main` matches `tokio::main`. */
+export function hasAnnotation(symbol: CodeSymbol, name: string): boolean {
+  return (symbol.annotations ?? []).some((annotation) => matchesPath(annotation.name, name));
+}
+
+/** Every attribute in a Rust file with the line range it governs. */
+export function scanRustAttributes(content: string): RustAttribute[] {
+  const code = maskRust(content);
+  const lineOf = createLineLookup(content);
+  const tokens = findAttributes(content, code);
+  return tokens.map((token, index) => {
+    if (token.inner) {
+      const open = enclosingBlock(code, token.start);
+      return {
+        text: token.text,
+        inner: true,
+        from: open === -1 ? 1 : lineOf(open),
+        to: open === -1 ? Number.MAX_SAFE_INTEGER : lineOf(matchingBrace(code, open)),
+        attachedLine: -1,
+      };
+    }
+    let item = token.end;
+    for (let next = index + 1; next < tokens.length && skipSpace(code, item) === tokens[next]!.start; next += 1) {
+      item = tokens[next]!.end;
+    }
+    item = skipSpace(code, item);
+    return { text: token.text, inner: false, from: lineOf(item), to: lineOf(itemEnd(code, item)), attachedLine: lineOf(item) };
+  });
+}
+
+// `cfg_attr(feature = "serde", derive(Serialize), cfg(unix))` -> ['cfg_attr', 'feature = "serde"', 'derive(Serialize)', 'cfg(unix)']
+export function splitAttribute(attribute: string): string[] {
+  const open = attribute.indexOf('(');
+  if (open === -1 || !attribute.endsWith(')')) {
+    return [attribute];
+  }
+  return [attribute.slice(0, open).trim(), ...splitTopLevel(attribute.slice(open + 1, -1)).map(normalizeArgument)];
+}
+
+function normalizeArgument(argument: string): string {
+  return argument.replace(/\s*([(),])\s*/g, '$1').replace(/,/g, ', ').replace(/\s*=\s*/g, ' = ').trim();
+}
+
+export function splitTopLevel(value: string): string[] {
+  const parts: string[] = [];
+  let depth = 0;
+  let quoted = false;
+  let current = '';
+  for (let index = 0; index < value.length; index += 1) {
+    const char = value[index]!;
+    if (char === '"' && value[index - 1] !== '\\') {
+      quoted = !quoted;
+    } else if (!quoted && (char === '(' || char === '[')) {
+      depth += 1;
+    } else if (!quoted && (char === ')' || char === ']')) {
+      depth -= 1;
+    } else if (!quoted && char === ',' && depth === 0) {
+      parts.push(current.trim());
+      current = '';
+      continue;
+    }
+    current += char;
+  }
+  parts.push(current.trim());
+  return parts.filter((part) => part.length > 0);
+}
+
+function matchesPath(path: string, name: string): boolean {
+  return path === name || path.endsWith(`::${name}`);
+}
+
+function findAttributes(content: string, code: string): AttributeToken[] {
+  const attributes: AttributeToken[] = [];
+  for (const match of code.matchAll(/#(!)?\s*\[/g)) {
+    const open = match.index + match[0].length - 1;
+    const close = matchingBracket(code, open, '[', ']');
+    attributes.push({
+      start: match.index,
+      end: close + 1,
+      inner: match[1] !== undefined,
+      text: content.slice(open + 1, close).replace(/\s+/g, ' ').trim(),
+    });
+  }
+  return attributes;
+}
+
+// The `;` or closing `}` of the item starting at `from`: `fn f() { ... }`, `struct S;`, `mod m { ... }`.
+function itemEnd(code: string, from: number): number {
+  const isItem = ITEM_START.test(code.slice(from, from + 32));
+  let depth = 0;
+  for (let index = from; index < code.length; index += 1) {
+    const char = code[index];
+    if (char === '(' || char === '[') {
+      depth += 1;
+    } else if (char === ')' || char === ']') {
+      depth -= 1;
+    } else if (depth <= 0 && char === ';') {
+      return index;
+    } else if (depth <= 0 && char === '{') {
+      return matchingBrace(code, index);
+    } else if (!isItem && (depth < 0 || (depth === 0 && (char === ',' || char === '}')))) {
+      // An attribute on a field, variant or parameter ends with it.
+      return index;
+    }
+  }
+  return code.length - 1;
+}
+
+function enclosingBlock(code: string, offset: number): number {
+  let depth = 0;
+  for (let index = offset - 1; index >= 0; index -= 1) {
+    if (code[index] === '}') {
+      depth += 1;
+    } else if (code[index] === '{' && (depth -= 1) < 0) {
+      return index;
+    }
+  }
+  return -1;
+}
+
+function matchingBrace(code: string, open: number): number {
+  return matchingBracket(code, open, '{', '}');
+}
+
+function matchingBracket(code: string, open: number, opening: string, closing: string): number {
+  let depth = 0;
+  for (let index = open; index < code.length; index += 1) {
+    if (code[index] === opening) {
+      depth += 1;
+    } else if (code[index] === closing && (depth -= 1) === 0) {
+      return index;
+    }
+  }
+  return code.length - 1;
+}
+
+function skipSpace(code: string, from: number): number {
+  let index = from;
+  while (index < code.length && /\s/.test(code[index]!)) {
+    index += 1;
+  }
+  return index;
+}
+
+function createLineLookup(content: string): (offset: number) => number {
+  const starts = [0];
+  for (let index = 0; index < content.length; index += 1) {
+    if (content.charCodeAt(index) === 10) {
+      starts.push(index + 1);
+    }
+  }
+  return (offset) => {
+    let low = 0;
+    let high = starts.length - 1;
+    while (low < high) {
+      const middle = Math.ceil((low + high) / 2);
+      if (starts[middle]! <= offset) {
+        low = middle;
+      } else {
+        high = middle - 1;
+      }
+    }
+    return low + 1;
+  };
+}
+
+// Blanks comments and the contents of string and char literals, keeping offsets, so brackets in
+// them are not structure. Lifetimes (`'a`) are left alone.
+export function maskRust(content: string): string {
+  const blank = (match: string) => match.replace(/[^\n]/g, ' ');
+  return content.replace(
+    /\/\/[^\n]*|\/\*[\s\S]*?\*\/|r(#*)"[\s\S]*?"\1|b?"(?:[^"\\]|\\.)*"|b?'(?:[^'\\\n]|\\.)'/g,
+    (match) => match.startsWith('/') ? blank(match) : `${match[0]}${blank(match.slice(1, -1))}${match.at(-1)}`,
+  );
+}
@@ -0,0 +1,143 @@
+import { scanRustAttributes, splitAttribute, splitTopLevel } from './rust-attributes.js';
+// In precedence order: `aarch64-linux-android` is Android, not Linux.
+const TARGET_OS_FAMILIES = {
+    windows: 'windows',
+    android: 'unix',
+    ios: 'unix',
+    macos: 'unix',
+    linux: 'unix',
+    freebsd: 'unix',
+    netbsd: 'unix',
+    openbsd: 'unix',
+};
+/**
+ * Records the `#[cfg(...)]` conditions a Rust symbol is compiled under as `cfg`: its own, those of
+ * the items and modules around it and the file's `#![cfg(...)]`, all of which must hold.
+ * `#[cfg_attr(p, cfg(q))]` adds `any(not(p), q)`; other `cfg_attr` attributes are kept as
+ * `cfgAttr` so a caller can see what a feature switches on.
+ */
+export function tagCfgSymbols(symbols, content) {
+    if (!symbols.some((symbol) => symbol.language === 'rust') || !content.includes('cfg')) {
+        return symbols;
+    }
+    const gates = scanRustAttributes(content).filter((attribute) => /^cfg(?:_attr)?\s*\(/.test(attribute.text));
+    if (gates.length === 0) {
+        return symbols;
+    }
+    for (const symbol of symbols) {
+        if (symbol.language !== 'rust') {
+            continue;
+        }
+        const cfg = [];
+        const cfgAttr = [];
+        for (const gate of gates) {
+            if (symbol.line < gate.from || symbol.line > gate.to) {
+                continue;
+            }
+            const [name, ...args] = splitAttribute(gate.text);
+            if (name === 'cfg' && args[0] !== undefined) {
+                cfg.push(args[0]);
+                continue;
+            }
+            // A conditional attribute only concerns the item it is written on.
+            if (name !== 'cfg_attr' || args.length < 2 || (gate.attachedLine !== symbol.line && !gate.inner)) {
+                continue;
+            }
+            const [predicate, ...applied] = args;
+            for (const attribute of applied) {
+                const gated = /^cfg\s*\(([\s\S]*)\)$/.exec(attribute);
+                if (gated !== null) {
+                    cfg.push(`any(not(${predicate}), ${gated[1]})`);
+                }
+                else {
+                    cfgAttr.push({ predicate: predicate, attributes: attribute });
+                }
+            }
+        }
+        if (cfg.length > 0) {
+            symbol.cfg = [...new Set(cfg)];
+        }
+        if (cfgAttr.length > 0) {
+            symbol.cfgAttr = cfgAttr;
+        }
+    }
+    return symbols;
+}
+/**
+ * Whether a symbol's `cfg` conditions can hold under the given features and target. Conditions
+ * the options say nothing about (`test`, an unset target) are treated as possibly true, so only
+ * symbols that are certainly compiled out are rejected.
+ */
+export function isCfgActive(cfg, options) {
+    if (cfg === undefined || cfg.length === 0) {
+        return true;
+    }
+    const facts = targetFacts(options.target);
+    return cfg.every((predicate) => evaluatePredicate(predicate, options, facts) !== false);
+}
+function evaluatePredicate(predicate, options, facts) {
+    const call = /^(all|any|not)\s*\(([\s\S]*)\)$/.exec(predicate.trim());
+    if (call !== null) {
+        const values = splitTopLevel(call[2]).map((argument) => evaluatePredicate(argument, options, facts));
+        if (call[1] === 'not') {
+            return values[0] === undefined ? undefined : !values[0];
+        }
+        if (call[1] === 'all') {
+            return values.includes(false) ? false : values.includes(undefined) ? undefined : true;
+        }
+        return values.includes(true) ? true : values.includes(undefined) ? undefined : false;
+    }
+    const pair = /^(\w+)\s*=\s*"([^"]*)"$/.exec(predicate.trim());
+    if (pair !== null) {
+        const [, key, value] = pair;
+        if (key === 'feature') {
+            return options.features === undefined ? undefined : options.features.includes(value);
+        }
+        const known = facts.get(key);
+        return known === undefined ? undefined : known.includes(value);
+    }
+    const flag = predicate.trim();
+    // `unix`, `windows` and `wasm` are shorthands for `target_family = "..."`.
+    if (flag === 'unix' || flag === 'windows' || flag === 'wasm') {
+        const families = facts.get('target_family');
+        return families === undefined ? undefined : families.includes(flag);
+    }
+    return undefined;
+}
+function targetFacts(target) {
+    const facts = new Map();
+    if (target === undefined) {
+        return facts;
+    }
+    const normalized = target.toLowerCase();
+    if (!normalized.includes('-')) {
+        if (normalized === 'unix' || normalized === 'wasm') {
+            facts.set('target_family', [normalized]);
+        }
+        else {
+            const os = normalized === 'darwin' || normalized === 'macosx' ? 'macos' : normalized;
+            const family = TARGET_OS_FAMILIES[os];
+            facts.set('target_os', [os]);
+            facts.set('target_family', family !== undefined ? [family] : []);
+        }
+        return facts;
+    }
+    // Triples: `x86_64-pc-windows-msvc`, `aarch64-apple-darwin`, `wasm32-unknown-unknown`.
+    const parts = normalized.split('-');
+    const arch = parts[0].replace(/^i[3-6]86$/, 'x86').replace(/^(?:armv\w+|thumbv\w+)$/, 'arm');
+    facts.set('target_arch', [arch]);
+    const named = parts.map((part) => (part === 'darwin' ? 'macos' : part.replace(/eabi(?:hf)?$/, '')));
+    const os = Object.keys(TARGET_OS_FAMILIES).find((candidate) => named.includes(candidate)) ?? (arch.startsWith('wasm') ? 'unknown' : 'none');
+    const family = arch.startsWith('wasm') ? 'wasm' : TARGET_OS_FAMILIES[os];
+    facts.set('target_os', [os]);
+    facts.set('target_family', family !== undefined ? [family] : []);
+    const env = parts.length > 3 ? parts[3].replace(/eabi(?:hf)?$/, '') : '';
+    facts.set('target_env', [env]);
+    if (/64/.test(arch)) {
+        facts.set('target_pointer_width', ['64']);
+    }
+    else if (/^(?:x86|arm|wasm32|mips|powerpc)$/.test(arch)) {
+        facts.set('target_pointer_width', ['32']);
+    }
+    return facts;
+}
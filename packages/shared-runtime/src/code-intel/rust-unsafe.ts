@@ -0,0 +1,360 @@
+import { readFile } from 'node:fs/promises';
+import { join, posix } from 'node:path';
+import { listWorkspaceFiles } from './module-graph.js';
+import { createPositionMapper, type SourceSpan } from './positions.js';
+
+export const UNSAFE_FINDING_KINDS = [
+  'unsafe-fn',
+  'unsafe-block',
+  'unsafe-impl',
+  'unsafe-trait',
+  'extern-block',
+  'extern-fn',
+  'foreign-item',
+  'raw-pointer',
+] as const;
+
+export type UnsafeFindingKind = typeof UNSAFE_FINDING_KINDS[number];
+
+export interface UnsafeFinding {
+  kind: UnsafeFindingKind;
+  file: string;
+  line: number;
+  column: number;
+  endLine: number;
+  endColumn: number;
+  span: SourceSpan;
+  /** Function, trait or foreign item name; the implemented trait for `unsafe impl`. */
+  name?: string;
+  /** ABI string of `extern` items, e.g. `C` or `system`. */
+  abi?: string;
+  /** Innermost enclosing function. */
+  function?: string;
+  snippet: string;
+}
+
+export interface UnsafeReport {
+  crate: string;
+  /** Cargo.toml of the crate; absent for Rust files outside any crate. */
+  manifest?: string;
+  files: number;
+  counts: Record<UnsafeFindingKind, number>;
+  findings: UnsafeFinding[];
+}
+
+interface Span {
+  start: number;
+  end: number;
+}
+
+const UNSAFE_ITEM_PATTERN = /\bunsafe\s+(?:(impl)\b|(trait)\s+(\w+)|(?:extern\s+("\s*")\s+)?fn\s+(\w+)|(extern)\b(?:\s+("\s*"))?\s*\{|\{)/g;
+const EXTERN_PATTERN = /\bextern\s*(?:("\s*")\s*)?(?:(fn)\s+(\w+)|\{)/g;
+const FOREIGN_ITEM_PATTERN = /\b(?:fn|static(?:\s+mut)?|type)\s+(\w+)/g;
+const RAW_POINTER_PATTERN = /\*\s*(?:const|mut)\b/g;
+const FN_PATTERN = /\bfn\s+(\w+)/g;
+const SNIPPET_LENGTH = 120;
+const UNLISTED_CRATE = '<workspace>';
+
+/**
+ * Finds the unsafe and FFI surface of one Rust source file: `unsafe fn`/`impl`/`trait` items,
+ * `unsafe` blocks, `extern` blocks with their foreign items, `extern "ABI" fn` definitions and
+ * raw-pointer types and casts. Comments and string literals are ignored.
+ */
+export function scanRustUnsafe(content: string, file: string): UnsafeFinding[] {
+  const code = sanitizeRust(content);
+  const braces = matchBraces(code);
+  const positions = createPositionMapper(content);
+  const functions = collectFunctions(code, braces);
+  const findings: UnsafeFinding[] = [];
+  const unsafeExternStarts = new Set<number>();
+
+  const push = (kind: UnsafeFindingKind, span: Span, extra: { name?: string; abi?: string } = {}) => {
+    const start = positions.fromOffset(span.start);
+    const end = positions.fromOffset(Math.max(span.start, span.end - 1));
+    const enclosing = functions.filter((fn) => fn.body.start < span.start && span.start < fn.body.end).at(-1);
+    findings.push({
+      kind,
+      file,
+      line: start.line,
+      column: start.column,
+      endLine: end.line,
+      endColumn: end.column + 1,
+      span: { start, end: positions.fromOffset(Math.max(span.start, span.end)) },
+      ...(extra.name !== undefined ? { name: extra.name } : {}),
+      ...(extra.abi !== undefined ? { abi: extra.abi } : {}),
+      ...(enclosing !== undefined ? { function: enclosing.name } : {}),
+      snippet: snippet(content, span.start),
+    });
+  };
+  const abiAt = (match: RegExpExecArray, group: string | undefined): string | undefined => {
+    if (group === undefined) {
+      return undefined;
+    }
+    const offset = match.index + match[0].indexOf(group);
+    return content.slice(offset + 1, offset + group.length - 1);
+  };
+
+  for (const match of code.matchAll(UNSAFE_ITEM_PATTERN)) {
+    const [, impl, trait, traitName, fnAbi, fnName, externBlock, blockAbi] = match;
+    const start = match.index;
+    if (impl !== undefined) {
+      const header = code.slice(start, findItemEnd(code, start + match[0].length));
+      push('unsafe-impl', itemSpan(code, braces, start, start + match[0].length), {
+        name: header.replace(/^unsafe\s+impl\s*(?:<[^{]*?>\s*)?/, '').replace(/\s+/g, ' ').trim(),
+      });
+    } else if (trait !== undefined) {
+      push('unsafe-trait', itemSpan(code, braces, start, start + match[0].length), { name: traitName });
+    } else if (fnName !== undefined) {
+      const abi = abiAt(match, fnAbi);
+      push('unsafe-fn', itemSpan(code, braces, start, start + match[0].length), { name: fnName, ...(abi !== undefined ? { abi } : {}) });
+    } else if (externBlock !== undefined) {
+      const open = start + match[0].length - 1;
+      unsafeExternStarts.add(open);
+      push('extern-block', { start, end: (braces.get(open) ?? open) + 1 }, { abi: abiAt(match, blockAbi) ?? 'C' });
+    } else {
+      const open = start + match[0].length - 1;
+      push('unsafe-block', { start, end: (braces.get(open) ?? open) + 1 });
+    }
+  }
+
+  const externBlocks: Span[] = [];
+  for (const match of code.matchAll(EXTERN_PATTERN)) {
+    const [, abiGroup, fn, name] = match;
+    const start = match.index;
+    const abi = abiAt(match, abiGroup) ?? 'C';
+    if (fn !== undefined) {
+      // `unsafe extern "C" fn` was already reported as an unsafe fn.
+      if (!/\bunsafe\s+$/.test(code.slice(Math.max(0, start - 16), start))) {
+        push('extern-fn', itemSpan(code, braces, start, start + match[0].length), { name, abi });
+      }
+      continue;
+    }
+    const open = start + match[0].length - 1;
+    const close = braces.get(open) ?? open;
+    externBlocks.push({ start: open, end: close });
+    if (!unsafeExternStarts.has(open)) {
+      push('extern-block', { start, end: close + 1 }, { abi });
+    }
+  }
+
+  for (const block of externBlocks) {
+    const body = code.slice(block.start + 1, block.end);
+    for (const match of body.matchAll(FOREIGN_ITEM_PATTERN)) {
+      const start = block.start + 1 + match.index;
+      push('foreign-item', { start, end: findItemEnd(code, start) + 1 }, { name: match[1] });
+    }
+  }
+
+  for (const match of code.matchAll(RAW_POINTER_PATTERN)) {
+    // `a * mut_value` never matches; `*const`/`*mut` followed by a type is a raw pointer.
+    push('raw-pointer', { start: match.index, end: match.index + match[0].length });
+  }
+
+  return findings.sort((left, right) => left.line - right.line || left.column - right.column);
+}
+
+/**
+ * Scans Rust sources and groups the findings by the crate (nearest `Cargo.toml` with a
+ * `[package]`) each file belongs to.
+ */
+export async function buildUnsafeReports(request: { basePath: string; paths?: string[]; crate?: string }): Promise<UnsafeReport[]> {
+  const files = await listWorkspaceFiles(request.basePath, {
+    maxFiles: 20_000,
+    accept: (file) => file.endsWith('.rs') || posix.basename(file) === 'Cargo.toml',
+  });
+  const crates = new Map<string, string>();
+  for (const manifest of files.filter((file) => posix.basename(file) === 'Cargo.toml')) {
+    const name = readPackageName(await readFile(join(request.basePath, manifest), 'utf8'));
+    if (name !== undefined) {
+      crates.set(posix.dirname(manifest), name);
+    }
+  }
+
+  const scopes = (request.paths ?? []).map((path) => posix.normalize(path.replaceAll('\\', '/')).replace(/^\.\/?|\/$/g, ''));
+  const reports = new Map<string, UnsafeReport>();
+  for (const file of files.filter((candidate) => candidate.endsWith('.rs'))) {
+    if (scopes.length > 0 && !scopes.some((scope) => scope === '' || file === scope || file.startsWith(`${scope}/`))) {
+      continue;
+    }
+    const crateDir = findCrateDir(file, crates);
+    // Build output under the crate's target/ directory is not source.
+    if (file.startsWith(crateDir === undefined || crateDir === '.' ? 'target/' : `${crateDir}/target/`)) {
+      continue;
+    }
+    const crate = crateDir === undefined ? UNLISTED_CRATE : crates.get(crateDir)!;
+    if (request.crate !== undefined && crate !== request.crate) {
+      continue;
+    }
+
+    let report = reports.get(crate);
+    if (report === undefined) {
+      report = {
+        crate,
+        ...(crateDir !== undefined ? { manifest: crateDir === '.' ? 'Cargo.toml' : `${crateDir}/Cargo.toml` } : {}),
+        files: 0,
+        counts: Object.fromEntries(UNSAFE_FINDING_KINDS.map((kind) => [kind, 0])) as Record<UnsafeFindingKind, number>,
+        findings: [],
+      };
+      reports.set(crate, report);
+    }
+    report.files += 1;
+    for (const finding of scanRustUnsafe(await readFile(join(request.basePath, file), 'utf8'), file)) {
+      report.counts[finding.kind] += 1;
+      report.findings.push(finding);
+    }
+  }
+
+  return [...reports.values()].sort((left, right) => left.crate.localeCompare(right.crate));
+}
+
+function findCrateDir(file: string, crates: Map<string, string>): string | undefined {
+  let dir = posix.dirname(file);
+  while (true) {
+    if (crates.has(dir)) {
+      return dir;
+    }
+    if (dir === '.' || dir === '') {
+      return undefined;
+    }
+    dir = posix.dirname(dir);
+  }
+}
+
+function readPackageName(manifest: string): string | undefined {
+  let inPackage = false;
+  for (const line of manifest.split(/\r?\n/)) {
+    const section = /^\s*\[([^\]]+)\]/.exec(line);
+    if (section !== null) {
+      inPackage = section[1]!.trim() === 'package';
+      continue;
+    }
+    const name = inPackage ? /^\s*name\s*=\s*"([^"]+)"/.exec(line) : null;
+    if (name !== null) {
+      return name[1];
+    }
+  }
+  return undefined;
+}
+
+/**
+ * Blanks comments and the contents of string, byte-string, raw-string and char literals while
+ * keeping offsets and newlines, so patterns only match real code.
+ */
+function sanitizeRust(content: string): string {
+  const out = content.split('');
+  const blank = (from: number, to: number) => {
+    for (let index = from; index < to; index += 1) {
+      if (out[index] !== '\n') {
+        out[index] = ' ';
+      }
+    }
+  };
+
+  let index = 0;
+  while (index < content.length) {
+    const char = content[index]!;
+    const next = content[index + 1];
+    if (char === '/' && next === '/') {
+      const end = content.indexOf('\n', index);
+      blank(index, end === -1 ? content.length : end);
+      index = end === -1 ? content.length : end;
+    } else if (char === '/' && next === '*') {
+      let depth = 1;
+      let cursor = index + 2;
+      while (cursor < content.length && depth > 0) {
+        if (content.startsWith('/*', cursor)) {
+          depth += 1;
+          cursor += 2;
+        } else if (content.startsWith('*/', cursor)) {
+          depth -= 1;
+          cursor += 2;
+        } else {
+          cursor += 1;
+        }
+      }
+      blank(index, cursor);
+      index = cursor;
+    } else if ((char === 'r' || (char === 'b' && next === 'r')) && /^b?r#*"/.test(content.slice(index, index + 260)) && !/\w/.test(content[index - 1] ?? '')) {
+      const prefix = /^b?r(#*)"/.exec(content.slice(index))!;
+      const terminator = `"${prefix[1]}`;
+      const end = content.indexOf(terminator, index + prefix[0].length);
+      const stop = end === -1 ? content.length : end + terminator.length;
+      blank(index + prefix[0].length, stop - terminator.length);
+      index = stop;
+    } else if (char === '"') {
+      let cursor = index + 1;
+      while (cursor < content.length && content[cursor] !== '"') {
+        cursor += content[cursor] === '\\' ? 2 : 1;
+      }
+      blank(index + 1, cursor);
+      index = cursor + 1;
+    } else if (char === '\'') {
+      // Char literal ('a', '\n', '\u{1F600}') rather than a lifetime ('a).
+      const literal = /^'(?:\\(?:u\{[0-9a-fA-F]+\}|x[0-9a-fA-F]{2}|.)|[^\\'])'/.exec(content.slice(index, index + 12));
+      if (literal !== null) {
+        blank(index + 1, index + literal[0].length - 1);
+        index += literal[0].length;
+      } else {
+        index += 1;
+      }
+    } else {
+      index += 1;
+    }
+  }
+
+  return out.join('');
+}
+
+function matchBraces(code: string): Map<number, number> {
+  const pairs = new Map<number, number>();
+  const stack: number[] = [];
+  for (let index = 0; index < code.length; index += 1) {
+    if (code[index] === '{') {
+      stack.push(index);
+    } else if (code[index] === '}') {
+      const open = stack.pop();
+      if (open !== undefined) {
+        pairs.set(open, index);
+      }
+    }
+  }
+  return pairs;
+}
+
+function collectFunctions(code: string, braces: Map<number, number>): Array<{ name: string; body: Span }> {
+  const functions: Array<{ name: string; body: Span }> = [];
+  for (const match of code.matchAll(FN_PATTERN)) {
+    const end = findItemEnd(code, match.index + match[0].length);
+    if (code[end] === '{') {
+      functions.push({ name: match[1]!, body: { start: end, end: braces.get(end) ?? code.length } });
+    }
+  }
+  return functions;
+}
+
+/** Offset of the `{` opening the item's body or the `;` ending a bodiless declaration. */
+function findItemEnd(code: string, from: number): number {
+  let depth = 0;
+  for (let index = from; index < code.length; index += 1) {
+    const char = code[index];
+    if (char === '(' || char === '[' || char === '<') {
+      depth += 1;
+    } else if (char === ')' || char === ']' || (char === '>' && code[index - 1] !== '-' && code[index - 1] !== '=')) {
+      depth = Math.max(0, depth - 1);
+    } else if ((char === '{' || char === ';') && depth === 0) {
+      return index;
+    }
+  }
+  return code.length - 1;
+}
+
+function itemSpan(code: string, braces: Map<number, number>, start: number, headerEnd: number): Span {
+  const end = findItemEnd(code, headerEnd);
+  return { start, end: (code[end] === '{' ? braces.get(end) ?? end : end) + 1 };
+}
+
+function snippet(content: string, offset: number): string {
+  const end = content.indexOf('\n', offset);
+  const line = content.slice(offset, end === -1 ? content.length : end).trim();
+  return line.length > SNIPPET_LENGTH ? `${line.slice(0, SNIPPET_LENGTH - 1)}…` : line;
+}
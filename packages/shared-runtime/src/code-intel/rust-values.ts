@@ -0,0 +1,108 @@
+import { maskRust } from './rust-attributes.js';
+import type { CodeSymbol } from './types.js';
+
+const MAX_VALUE_LENGTH = 160;
+
+/**
+ * Records what Rust type aliases and constants stand for, so they resolve without opening the
+ * file: `type CalcResult = Result<f64, String>;` gets `attributes.aliasOf`, and
+ * `const MAX_RETRIES: u32 = 3;` (or a `static`) gets `attributes.type` and `attributes.value`.
+ * Initializers longer than a line or two are left out rather than cut.
+ */
+export function tagValueSymbols(symbols: CodeSymbol[], content: string): CodeSymbol[] {
+  if (!symbols.some((symbol) => symbol.language === 'rust')) {
+    return symbols;
+  }
+  const code = maskRust(content);
+  const lineStarts = [0];
+  for (let index = 0; index < content.length; index += 1) {
+    if (content.charCodeAt(index) === 10) {
+      lineStarts.push(index + 1);
+    }
+  }
+
+  for (const symbol of symbols) {
+    const start = lineStarts[symbol.line - 1];
+    if (symbol.language !== 'rust' || start === undefined) {
+      continue;
+    }
+    const declaration = new RegExp(`[ \\t]*(?:pub(?:\\([^)]*\\))?\\s+)?(type|const|static)\\s+(?:mut\\s+)?${symbol.name}\\b`, 'y');
+    declaration.lastIndex = start;
+    const match = declaration.exec(code);
+    if (match === null) {
+      continue;
+    }
+    const end = statementEnd(code, match.index + match[0].length);
+    const rest = content.slice(match.index + match[0].length, end);
+    const masked = code.slice(match.index + match[0].length, end);
+    const attributes: Record<string, string> = { ...symbol.attributes, declaration: match[1]! };
+
+    if (match[1] === 'type') {
+      const equals = topLevelIndex(masked, '=');
+      if (equals === -1) {
+        continue;
+      }
+      const generics = /^\s*<([\s\S]*)>/.exec(rest.slice(0, equals));
+      if (generics !== null) {
+        attributes.generics = collapse(generics[1]!);
+      }
+      attributes.aliasOf = collapse(rest.slice(equals + 1));
+    } else {
+      const colon = topLevelIndex(masked, ':');
+      const equals = topLevelIndex(masked, '=');
+      if (colon !== -1) {
+        attributes.type = collapse(rest.slice(colon + 1, equals === -1 ? undefined : equals));
+      }
+      const value = equals === -1 ? '' : collapse(rest.slice(equals + 1));
+      if (value.length > 0 && value.length <= MAX_VALUE_LENGTH) {
+        attributes.value = value;
+      }
+    }
+    symbol.attributes = attributes;
+  }
+  return symbols;
+}
+
+// The `;` ending the item, skipping those inside blocks, arrays and calls.
+function statementEnd(code: string, from: number): number {
+  let depth = 0;
+  for (let index = from; index < code.length; index += 1) {
+    const char = code[index];
+    if (char === '(' || char === '[' || char === '{') {
+      depth += 1;
+    } else if (char === ')' || char === ']' || char === '}') {
+      depth -= 1;
+    } else if (char === ';' && depth <= 0) {
+      return index;
+    }
+  }
+  return code.length;
+}
+
+// First `target` outside brackets and generics; `=` in `==`, `=>`, `<=`, `>=` and `::` do not count.
+function topLevelIndex(code: string, target: string): number {
+  let depth = 0;
+  for (let index = 0; index < code.length; index += 1) {
+    const char = code[index]!;
+    const previous = code[index - 1];
+    const next = code[index + 1];
+    if (char === '(' || char === '[' || char === '{' || char === '<') {
+      depth += 1;
+    } else if (char === ')' || char === ']' || char === '}' || (char === '>' && previous !== '-' && previous !== '=')) {
+      depth -= 1;
+    } else if (char === target && depth === 0) {
+      if (target === ':' && (previous === ':' || next === ':')) {
+        continue;
+      }
+      if (target === '=' && (next === '=' || next === '>' || previous === '=' || previous === '!')) {
+        continue;
+      }
+      return index;
+    }
+  }
+  return -1;
+}
+
+function collapse(value: string): string {
+  return value.replace(/\s+/g, ' ').trim();
+}
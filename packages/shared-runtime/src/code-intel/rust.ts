@@ -0,0 +1,209 @@
+import { extname } from 'node:path';
+import { maskRust } from './rust-attributes.js';
+import type { CodeLanguageExtractor, CodeSymbol, CodeSymbolKind } from './types.js';
+
+const RUST_EXTENSIONS = new Set(['.rs']);
+// Item keywords, and macro definitions and calls, whose bodies are stepped over.
+const ITEM_TOKEN = /\b(?:(fn|struct|enum|union|trait|mod|type|const|static|impl)\b|[A-Za-z_]\w*!\s*(?:[A-Za-z_]\w*\s*)?([{([]))/g;
+// What may stand between the end of the previous item and an item's keyword.
+const ITEM_PREFIX = /(?:^|[;{}\]])\s*(?:pub(?:\s*\([^)]*\))?\s+)?(?:(?:default|const|async|unsafe|auto|extern(?:\s+"[^"]*")?)\s+)*$/;
+// Words after `const` that make it a qualifier of a function rather than an item.
+const FUNCTION_QUALIFIERS = new Set(['fn', 'async', 'unsafe', 'extern']);
+const FIELD_PATTERN = /^\s*(?:pub(?:\s*\([^)]*\))?\s+)?([A-Za-z_]\w*)\s*:(?!:)/;
+const TYPE_DECLARATIONS: Record<string, CodeSymbolKind> = { struct: 'class', enum: 'class', union: 'class', trait: 'trait', mod: 'module' };
+
+type ScopeKind = 'root' | 'module' | 'trait' | 'impl';
+
+export const rustExtractor: CodeLanguageExtractor = {
+  language: 'rust',
+  matchesFile(fileName) {
+    return RUST_EXTENSIONS.has(extname(fileName).toLowerCase());
+  },
+  extract(content, file) {
+    return extractRustSymbols(content, file);
+  },
+};
+
+/**
+ * Finds the items of a Rust file: functions (methods inside `impl` and `trait` blocks), structs,
+ * enums and unions with their named fields, traits, modules, type aliases, and `const` and
+ * `static` items. Comments and literals are masked first; items inside function bodies and macro
+ * definitions are left out, as a tree-sitter tags query leaves them.
+ */
+export function extractRustSymbols(content: string, file: string): CodeSymbol[] {
+  const code = maskRust(content);
+  const pairs = matchBrackets(code);
+  const lineStarts = [0];
+  for (let index = 0; index < code.length; index += 1) {
+    if (code.charCodeAt(index) === 10) {
+      lineStarts.push(index + 1);
+    }
+  }
+  const lineAt = (offset: number) => {
+    let low = 0;
+    let high = lineStarts.length - 1;
+    while (low < high) {
+      const middle = (low + high + 1) >> 1;
+      if (lineStarts[middle]! <= offset) {
+        low = middle;
+      } else {
+        high = middle - 1;
+      }
+    }
+    return low + 1;
+  };
+  const symbols: CodeSymbol[] = [];
+  // Offsets of the item's keyword, or the field's name, and of its last character.
+  const push = (name: string, kind: CodeSymbolKind, start: number, end: number, container: string | undefined, attributes?: Record<string, string>) => {
+    symbols.push({
+      name,
+      kind,
+      language: 'rust',
+      file,
+      line: lineAt(start),
+      endLine: lineAt(end),
+      ...(container !== undefined ? { container } : {}),
+      ...(attributes !== undefined ? { attributes } : {}),
+    });
+  };
+
+  const scan = (from: number, to: number, scope: ScopeKind, container: string | undefined) => {
+    const tokens = new RegExp(ITEM_TOKEN.source, 'g');
+    tokens.lastIndex = from;
+    let match: RegExpExecArray | null;
+    while ((match = tokens.exec(code)) !== null && match.index < to) {
+      const after = match.index + match[0].length;
+      if (match[2] !== undefined) {
+        tokens.lastIndex = (pairs.get(after - 1) ?? after - 1) + 1;
+        continue;
+      }
+      const keyword = match[1]!;
+      if (!ITEM_PREFIX.test(code.slice(Math.max(from, match.index - 200), match.index))) {
+        continue;
+      }
+      if (keyword === 'impl') {
+        const open = findItemEnd(code, after, to);
+        if (code[open] === '{') {
+          scan(open + 1, pairs.get(open) ?? to, 'impl', container);
+          tokens.lastIndex = (pairs.get(open) ?? to) + 1;
+        }
+        continue;
+      }
+      const named = /^\s+(?:mut\s+)?([A-Za-z_]\w*)/.exec(code.slice(after, after + 200));
+      // `const fn` is a function and `const _` names nothing; both go on to the next keyword.
+      if (named === null || named[1] === '_' || (keyword === 'const' && FUNCTION_QUALIFIERS.has(named[1]!))) {
+        continue;
+      }
+      const name = named[1]!;
+      const nameEnd = after + named[0].length;
+      const start = match.index;
+
+      if (keyword === 'fn') {
+        const end = findItemEnd(code, nameEnd, to);
+        const close = code[end] === '{' ? pairs.get(end) ?? to : end;
+        push(name, scope === 'impl' || scope === 'trait' ? 'method' : 'function', start, close, container);
+        tokens.lastIndex = close + 1;
+      } else if (keyword === 'type' || keyword === 'const' || keyword === 'static') {
+        const end = findStatementEnd(code, nameEnd, to, pairs);
+        // Associated types name a slot of the trait, not a type of their own.
+        if (keyword !== 'type' || scope === 'root' || scope === 'module') {
+          push(name, keyword === 'type' ? 'class' : 'variable', start, end, container, { declaration: keyword });
+        }
+        tokens.lastIndex = end + 1;
+      } else {
+        const end = findItemEnd(code, nameEnd, to);
+        const body = code[end] === '{';
+        const close = body ? pairs.get(end) ?? to : findStatementEnd(code, end, to, pairs);
+        const kind = TYPE_DECLARATIONS[keyword]!;
+        push(name, kind, start, close, container, kind === 'class' ? { declaration: keyword } : undefined);
+        const inner = container === undefined ? name : `${container}.${name}`;
+        if (body && (keyword === 'mod' || keyword === 'trait')) {
+          scan(end + 1, close, keyword === 'mod' ? 'module' : 'trait', inner);
+        } else if (body && (keyword === 'struct' || keyword === 'union')) {
+          for (const field of splitFields(code, end + 1, close, pairs)) {
+            const declared = FIELD_PATTERN.exec(code.slice(field.start, field.end).replace(/#!?\[[^\]]*\]/g, (attribute) => ' '.repeat(attribute.length)));
+            if (declared !== null) {
+              push(declared[1]!, 'property', field.start + declared[0].lastIndexOf(declared[1]!), field.end - 1, inner);
+            }
+          }
+        }
+        tokens.lastIndex = close + 1;
+      }
+    }
+  };
+
+  scan(0, code.length, 'root', undefined);
+  return symbols;
+}
+
+/** Offset of each `{`, `(` and `[` mapped to the offset of the bracket closing it. */
+function matchBrackets(code: string): Map<number, number> {
+  const pairs = new Map<number, number>();
+  const stack: number[] = [];
+  for (let index = 0; index < code.length; index += 1) {
+    const char = code[index];
+    if (char === '{' || char === '(' || char === '[') {
+      stack.push(index);
+    } else if (char === '}' || char === ')' || char === ']') {
+      const open = stack.pop();
+      if (open !== undefined) {
+        pairs.set(open, index);
+      }
+    }
+  }
+  return pairs;
+}
+
+/** Offset of the `{` opening an item's body or the `;` ending a bodiless one. */
+function findItemEnd(code: string, from: number, to: number): number {
+  let depth = 0;
+  for (let index = from; index < to; index += 1) {
+    const char = code[index];
+    if (char === '(' || char === '[' || char === '<') {
+      depth += 1;
+    } else if (char === ')' || char === ']' || (char === '>' && code[index - 1] !== '-' && code[index - 1] !== '=')) {
+      depth = Math.max(0, depth - 1);
+    } else if ((char === '{' || char === ';') && depth === 0) {
+      return index;
+    }
+  }
+  return Math.max(from, to - 1);
+}
+
+/** Offset of the `;` ending a statement, stepping over the brackets in its initializer. */
+function findStatementEnd(code: string, from: number, to: number, pairs: Map<number, number>): number {
+  for (let index = from; index < to; index += 1) {
+    const char = code[index];
+    if (char === ';') {
+      return index;
+    }
+    if (char === '{' || char === '(' || char === '[') {
+      index = pairs.get(index) ?? index;
+    }
+  }
+  return Math.max(from, to - 1);
+}
+
+/** The comma-separated fields of a struct body, stepping over brackets in their types. */
+function splitFields(code: string, from: number, to: number, pairs: Map<number, number>): Array<{ start: number; end: number }> {
+  const fields: Array<{ start: number; end: number }> = [];
+  let start = from;
+  let angles = 0;
+  for (let index = from; index < to; index += 1) {
+    const char = code[index];
+    if (char === '{' || char === '(' || char === '[') {
+      index = pairs.get(index) ?? index;
+    } else if (char === '<') {
+      angles += 1;
+    } else if (char === '>' && code[index - 1] !== '-') {
+      angles = Math.max(0, angles - 1);
+    } else if (char === ',' && angles === 0) {
+      fields.push({ start, end: index });
+      start = index + 1;
+    }
+  }
+  if (code.slice(start, to).trim().length > 0) {
+    fields.push({ start, end: to });
+  }
+  return fields;
+}
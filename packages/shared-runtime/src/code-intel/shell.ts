@@ -0,0 +1,103 @@
+import { extname } from 'node:path';
+import type { CodeLanguageExtractor, CodeSymbol } from './types.js';
+
+const SHELL_EXTENSIONS = new Set(['.sh', '.bash']);
+const FUNCTION_PATTERNS = [
+  /^\s*function\s+([A-Za-z_][\w:.-]*)\s*(?:\(\s*\))?\s*(?:\{|$)/,
+  /^\s*([A-Za-z_][\w:.-]*)\s*\(\s*\)\s*(?:\{|$)/,
+];
+const EXPORT_PATTERN = /^\s*(?:export|declare\s+-[a-wyzA-Z]*x[a-zA-Z]*|typeset\s+-[a-wyzA-Z]*x[a-zA-Z]*)\s+(.*)$/;
+const HEREDOC_PATTERN = /<<-?\s*(['"]?)([A-Za-z_]\w*)\1/;
+
+export const shellExtractor: CodeLanguageExtractor = {
+  language: 'shell',
+  matchesFile(fileName) {
+    return SHELL_EXTENSIONS.has(extname(fileName).toLowerCase());
+  },
+  matchesShebang(firstLine) {
+    return /^#!.*\b(?:ba|z|da|k)?sh\b/.test(firstLine);
+  },
+  extract(content, file) {
+    return extractShellSymbols(content, file);
+  },
+};
+
+export function extractShellSymbols(content: string, file: string): CodeSymbol[] {
+  const lines = content.split(/\r?\n/);
+  const symbols: CodeSymbol[] = [];
+  let heredocDelimiter: string | undefined;
+
+  for (let index = 0; index < lines.length; index += 1) {
+    const line = lines[index] ?? '';
+    const lineNumber = index + 1;
+
+    if (heredocDelimiter !== undefined) {
+      if (line.trim() === heredocDelimiter) {
+        heredocDelimiter = undefined;
+      }
+      continue;
+    }
+    if (line.trimStart().startsWith('#')) {
+      continue;
+    }
+
+    const heredoc = HEREDOC_PATTERN.exec(line);
+    if (heredoc !== null) {
+      heredocDelimiter = heredoc[2];
+    }
+
+    const functionName = matchFunction(line);
+    if (functionName !== undefined) {
+      symbols.push({
+        name: functionName,
+        kind: 'function',
+        language: 'shell',
+        file,
+        line: lineNumber,
+      });
+      continue;
+    }
+
+    const exported = EXPORT_PATTERN.exec(line);
+    if (exported !== null) {
+      for (const name of parseExportedNames(exported[1] ?? '')) {
+        symbols.push({
+          name,
+          kind: 'variable',
+          language: 'shell',
+          file,
+          line: lineNumber,
+          attributes: { exported: 'true' },
+        });
+      }
+    }
+  }
+
+  return symbols;
+}
+
+function matchFunction(line: string): string | undefined {
+  for (const pattern of FUNCTION_PATTERNS) {
+    const match = pattern.exec(line);
+    if (match !== null) {
+      return match[1];
+    }
+  }
+  return undefined;
+}
+
+function parseExportedNames(text: string): string[] {
+  const names: string[] = [];
+  const unquoted = text.replace(/"(?:[^"\\]|\\.)*"|'[^']*'/g, '""');
+  for (const token of unquoted.split(/\s+/)) {
+    if (token.startsWith('-')) {
+      continue;
+    }
+    const match = /^([A-Za-z_]\w*)(?:=|$)/.exec(token);
+    if (match === null) {
+      break;
+    }
+    names.push(match[1]!);
+  }
+  return names;
+}
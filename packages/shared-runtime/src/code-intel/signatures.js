@@ -0,0 +1,229 @@
+import { readFile, stat } from 'node:fs/promises';
+import { join, relative, resolve, sep } from 'node:path';
+import { detectCodeLanguage, extractCodeSymbols } from './index.js';
+import { listWorkspaceFiles } from './module-graph.js';
+import { getCodeSymbolId } from './references.js';
+import { loadWorkspaceLanguageRegistry } from './registry.js';
+const BODY_KINDS = new Set(['function', 'method']);
+const HASH_COMMENT_LANGUAGES = new Set(['ruby', 'shell', 'hcl', 'dockerfile', 'python']);
+const MAX_DIRECTORY_FILES = 200;
+/**
+ * Renders files as skeletons: types, fields, imports and every function or method signature are
+ * kept, bodies are elided. Directories expand to the indexable files under them.
+ */
+export async function renderSignatures(request) {
+    const { registry } = await loadWorkspaceLanguageRegistry(request.basePath);
+    const files = await expandCodePaths(request.basePath, request.paths, registry);
+    const views = [];
+    for (const file of files) {
+        const { view } = await renderFile(request.basePath, file, registry);
+        views.push(view);
+    }
+    return views;
+}
+/**
+ * Renders one file as a skeleton, listing its declarations with their ids so that a caller can
+ * read the source of just the ones it needs.
+ */
+export async function renderFileSkeleton(request) {
+    const { registry } = await loadWorkspaceLanguageRegistry(request.basePath);
+    const [file] = await expandCodePaths(request.basePath, [request.path], registry);
+    if (file === undefined || (await stat(resolve(request.basePath, request.path))).isDirectory()) {
+        throw new Error(`Not a file: ${request.path}`);
+    }
+    const { view, symbols } = await renderFile(request.basePath, file, registry);
+    return {
+        ...view,
+        symbols: [...symbols].sort((left, right) => left.line - right.line).map((symbol) => ({
+            id: getCodeSymbolId(symbol),
+            name: symbol.name,
+            kind: symbol.kind,
+            line: symbol.line,
+            ...(symbol.endLine !== undefined ? { endLine: symbol.endLine } : {}),
+            ...(symbol.container !== undefined ? { container: symbol.container } : {}),
+        })),
+    };
+}
+async function renderFile(basePath, file, registry) {
+    const content = await readFile(join(basePath, file), 'utf8');
+    const indexed = extractCodeSymbols(file, content, registry);
+    if (indexed === undefined) {
+        throw new Error(`Could not detect a language for ${file}.`);
+    }
+    const rendered = renderSignatureSkeleton(content, indexed.symbols, indexed.language);
+    return {
+        view: {
+            file,
+            language: indexed.language,
+            content: rendered.content,
+            lines: content.split(/\r?\n/).length,
+            renderedLines: rendered.content.split('\n').length,
+            elidedBodies: rendered.elidedBodies,
+        },
+        symbols: indexed.symbols,
+    };
+}
+/**
+ * Resolves workspace-relative paths to the files they name, expanding directories to the
+ * indexable files under them. Paths outside the workspace or missing on disk are rejected.
+ */
+export async function expandCodePaths(basePath, paths, registry) {
+    const files = [];
+    for (const path of paths) {
+        const relativePath = relative(resolve(basePath), resolve(basePath, path));
+        if (relativePath === '..' || /^\.\.(?:[\\/]|$)/.test(relativePath)) {
+            throw new Error(`Path escapes workspace: ${path}`);
+        }
+        const file = relativePath.split(sep).join('/');
+        const stats = await stat(join(basePath, file)).catch(() => undefined);
+        if (stats === undefined) {
+            throw new Error(`File not found: ${path}`);
+        }
+        if (stats.isDirectory()) {
+            files.push(...await listWorkspaceFiles(basePath, {
+                maxFiles: MAX_DIRECTORY_FILES,
+                accept: (candidate) => (file === '' || candidate.startsWith(`${file}/`)) && detectCodeLanguage(candidate, undefined, registry) !== undefined,
+            }));
+        }
+        else {
+            files.push(file);
+        }
+    }
+    return [...new Set(files)];
+}
+/**
+ * Replaces the bodies of the given functions and methods with a placeholder comment, keeping
+ * their signatures and closing lines. One-line bodies collapse to `{ ... }` or `=> ...;`.
+ */
+export function renderSignatureSkeleton(content, symbols, language) {
+    const lines = content.split(/\r?\n/);
+    const placeholder = HASH_COMMENT_LANGUAGES.has(language) ? '# ...' : '// ...';
+    const strip = (line) => stripStrings(line, language !== 'rust');
+    const replaced = new Map();
+    const hidden = new Set();
+    const bodies = [];
+    let elidedBodies = 0;
+    const functions = symbols.filter((symbol) => BODY_KINDS.has(symbol.kind)).sort((left, right) => left.line - right.line);
+    for (const symbol of functions) {
+        const start = symbol.line - 1;
+        // Nested functions disappear with the body around them.
+        if (bodies.some(([from, to]) => from < start && start < to)) {
+            continue;
+        }
+        const end = (symbol.endLine ?? findBlockEnd(lines, start, strip) ?? symbol.line) - 1;
+        const signatureEnd = language === 'ruby' ? start : findSignatureEnd(lines, start, end, strip);
+        if (end <= signatureEnd) {
+            const collapsed = collapseInlineBody(lines[start] ?? '', strip);
+            if (collapsed !== undefined) {
+                replaced.set(start, collapsed);
+                elidedBodies += 1;
+            }
+            continue;
+        }
+        bodies.push([signatureEnd, end]);
+        const opening = cutAfterOpening(lines[signatureEnd] ?? '', strip);
+        if (opening !== undefined) {
+            replaced.set(signatureEnd, opening);
+        }
+        if (end - signatureEnd > 1) {
+            const firstBodyLine = lines.slice(signatureEnd + 1, end).find((line) => line.trim().length > 0) ?? '';
+            const indent = /^\s*/.exec(firstBodyLine)?.[0] ?? '';
+            replaced.set(signatureEnd + 1, `${indent}${placeholder}`);
+            for (let index = signatureEnd + 2; index < end; index += 1) {
+                hidden.add(index);
+            }
+            elidedBodies += 1;
+        }
+    }
+    const output = [];
+    lines.forEach((line, index) => {
+        if (hidden.has(index)) {
+            return;
+        }
+        const text = replaced.get(index) ?? line;
+        // Elision leaves runs of blank lines behind; one is enough.
+        if (text.trim().length === 0 && (output.at(-1) ?? '').trim().length === 0 && output.length > 0) {
+            return;
+        }
+        output.push(text);
+    });
+    return { content: output.join('\n'), elidedBodies };
+}
+// The line that opens the body: the first one with `{` or `=>` at or after the declaration.
+function findSignatureEnd(lines, start, end, strip) {
+    for (let index = start; index <= end; index += 1) {
+        const code = strip(lines[index] ?? '');
+        if (code.includes('{') || code.includes('=>')) {
+            return index;
+        }
+    }
+    return start;
+}
+// For extractors that report no end line (shell functions): the line closing the first brace.
+function findBlockEnd(lines, start, strip) {
+    let depth = 0;
+    let opened = false;
+    for (let index = start; index < lines.length; index += 1) {
+        for (const char of strip(lines[index] ?? '')) {
+            if (char === '{') {
+                depth += 1;
+                opened = true;
+            }
+            else if (char === '}' && opened && (depth -= 1) === 0) {
+                return index + 1;
+            }
+        }
+        if (!opened && index > start + 2) {
+            return undefined;
+        }
+    }
+    return undefined;
+}
+// `fn new(c: i64) -> Self { Money(c) }` -> `fn new(c: i64) -> Self { ... }`
+function collapseInlineBody(line, strip) {
+    const bodyStart = findBodyStart(strip(line));
+    if (bodyStart === undefined) {
+        return undefined;
+    }
+    if (line[bodyStart] === '{') {
+        const close = line.lastIndexOf('}');
+        if (close <= bodyStart || line.slice(bodyStart + 1, close).trim().length === 0) {
+            return undefined;
+        }
+        return `${line.slice(0, bodyStart)}{ ... }${line.slice(close + 1)}`;
+    }
+    const terminator = line.trimEnd().endsWith(';') ? ';' : '';
+    return `${line.slice(0, bodyStart)}=> ...${terminator}`;
+}
+// `fn run() { let a = 1;` -> `fn run() {`
+function cutAfterOpening(line, strip) {
+    const bodyStart = findBodyStart(strip(line));
+    if (bodyStart === undefined || line[bodyStart] !== '{' || line.slice(bodyStart + 1).trim().length === 0) {
+        return undefined;
+    }
+    return line.slice(0, bodyStart + 1);
+}
+// Offset of the `{` or `=>` that starts the body, looking past the parameter list.
+function findBodyStart(code) {
+    let depth = 0;
+    let afterParameters = code.indexOf('(') === -1;
+    for (let index = 0; index < code.length; index += 1) {
+        const char = code[index];
+        if (char === '(') {
+            depth += 1;
+        }
+        else if (char === ')' && (depth -= 1) === 0) {
+            afterParameters = true;
+        }
+        else if (afterParameters && depth === 0 && (char === '{' || (char === '=' && code[index + 1] === '>'))) {
+            return index;
+        }
+    }
+    return undefined;
+}
+// Blanks string contents so braces inside literals are not structure; keeps offsets. Rust
+// lifetimes (`'a`) rule out single-quoted strings there.
+function stripStrings(line, singleQuoted) {
+    const pattern = singleQuoted ? /"(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'/g : /"(?:[^"\\]|\\.)*"/g;
+    return line.replace(pattern, (match) => `${match[0]}${' '.repeat(match.length - 2)}${match[0]}`);
+}
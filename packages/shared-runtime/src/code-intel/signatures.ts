@@ -0,0 +1,257 @@
+import { readFile, stat } from 'node:fs/promises';
+import { join, relative, resolve, sep } from 'node:path';
+import { detectCodeLanguage, extractCodeSymbols } from './index.js';
+import { listWorkspaceFiles } from './module-graph.js';
+import { getCodeSymbolId } from './references.js';
+import { loadWorkspaceLanguageRegistry, type LanguageRegistry } from './registry.js';
+import type { CodeLanguage, CodeSymbol, CodeSymbolKind } from './types.js';
+
+export interface SignatureView {
+  file: string;
+  language: CodeLanguage;
+  /** The file with function and method bodies replaced by a placeholder. */
+  content: string;
+  lines: number;
+  renderedLines: number;
+  elidedBodies: number;
+}
+
+export interface FileSkeleton extends SignatureView {
+  /** Every declaration in the file, with the id `readSymbolBody` takes to read its source. */
+  symbols: Array<{ id: string; name: string; kind: CodeSymbolKind; line: number; endLine?: number; container?: string }>;
+}
+
+const BODY_KINDS = new Set<CodeSymbolKind>(['function', 'method']);
+const HASH_COMMENT_LANGUAGES = new Set(['ruby', 'shell', 'hcl', 'dockerfile', 'python']);
+const MAX_DIRECTORY_FILES = 200;
+
+/**
+ * Renders files as skeletons: types, fields, imports and every function or method signature are
+ * kept, bodies are elided. Directories expand to the indexable files under them.
+ */
+export async function renderSignatures(request: { basePath: string; paths: string[] }): Promise<SignatureView[]> {
+  const { registry } = await loadWorkspaceLanguageRegistry(request.basePath);
+  const files = await expandCodePaths(request.basePath, request.paths, registry);
+
+  const views: SignatureView[] = [];
+  for (const file of files) {
+    const { view } = await renderFile(request.basePath, file, registry);
+    views.push(view);
+  }
+  return views;
+}
+
+/**
+ * Renders one file as a skeleton, listing its declarations with their ids so that a caller can
+ * read the source of just the ones it needs.
+ */
+export async function renderFileSkeleton(request: { basePath: string; path: string }): Promise<FileSkeleton> {
+  const { registry } = await loadWorkspaceLanguageRegistry(request.basePath);
+  const [file] = await expandCodePaths(request.basePath, [request.path], registry);
+  if (file === undefined || (await stat(resolve(request.basePath, request.path))).isDirectory()) {
+    throw new Error(`Not a file: ${request.path}`);
+  }
+  const { view, symbols } = await renderFile(request.basePath, file, registry);
+  return {
+    ...view,
+    symbols: [...symbols].sort((left, right) => left.line - right.line).map((symbol) => ({
+      id: getCodeSymbolId(symbol),
+      name: symbol.name,
+      kind: symbol.kind,
+      line: symbol.line,
+      ...(symbol.endLine !== undefined ? { endLine: symbol.endLine } : {}),
+      ...(symbol.container !== undefined ? { container: symbol.container } : {}),
+    })),
+  };
+}
+
+async function renderFile(basePath: string, file: string, registry: LanguageRegistry): Promise<{ view: SignatureView; symbols: CodeSymbol[] }> {
+  const content = await readFile(join(basePath, file), 'utf8');
+  const indexed = extractCodeSymbols(file, content, registry);
+  if (indexed === undefined) {
+    throw new Error(`Could not detect a language for ${file}.`);
+  }
+  const rendered = renderSignatureSkeleton(content, indexed.symbols, indexed.language);
+  return {
+    view: {
+      file,
+      language: indexed.language,
+      content: rendered.content,
+      lines: content.split(/\r?\n/).length,
+      renderedLines: rendered.content.split('\n').length,
+      elidedBodies: rendered.elidedBodies,
+    },
+    symbols: indexed.symbols,
+  };
+}
+
+/**
+ * Resolves workspace-relative paths to the files they name, expanding directories to the
+ * indexable files under them. Paths outside the workspace or missing on disk are rejected.
+ */
+export async function expandCodePaths(basePath: string, paths: string[], registry: LanguageRegistry): Promise<string[]> {
+  const files: string[] = [];
+  for (const path of paths) {
+    const relativePath = relative(resolve(basePath), resolve(basePath, path));
+    if (relativePath === '..' || /^\.\.(?:[\\/]|$)/.test(relativePath)) {
+      throw new Error(`Path escapes workspace: ${path}`);
+    }
+    const file = relativePath.split(sep).join('/');
+    const stats = await stat(join(basePath, file)).catch(() => undefined);
+    if (stats === undefined) {
+      throw new Error(`File not found: ${path}`);
+    }
+    if (stats.isDirectory()) {
+      files.push(...await listWorkspaceFiles(basePath, {
+        maxFiles: MAX_DIRECTORY_FILES,
+        accept: (candidate) => (file === '' || candidate.startsWith(`${file}/`)) && detectCodeLanguage(candidate, undefined, registry) !== undefined,
+      }));
+    } else {
+      files.push(file);
+    }
+  }
+  return [...new Set(files)];
+}
+
+/**
+ * Replaces the bodies of the given functions and methods with a placeholder comment, keeping
+ * their signatures and closing lines. One-line bodies collapse to `{ ... }` or `=> ...;`.
+ */
+export function renderSignatureSkeleton(content: string, symbols: CodeSymbol[], language: CodeLanguage): { content: string; elidedBodies: number } {
+  const lines = content.split(/\r?\n/);
+  const placeholder = HASH_COMMENT_LANGUAGES.has(language) ? '# ...' : '// ...';
+  const strip = (line: string) => stripStrings(line, language !== 'rust');
+  const replaced = new Map<number, string>();
+  const hidden = new Set<number>();
+  const bodies: Array<[number, number]> = [];
+  let elidedBodies = 0;
+
+  const functions = symbols.filter((symbol) => BODY_KINDS.has(symbol.kind)).sort((left, right) => left.line - right.line);
+  for (const symbol of functions) {
+    const start = symbol.line - 1;
+    // Nested functions disappear with the body around them.
+    if (bodies.some(([from, to]) => from < start && start < to)) {
+      continue;
+    }
+    const end = (symbol.endLine ?? findBlockEnd(lines, start, strip) ?? symbol.line) - 1;
+    const signatureEnd = language === 'ruby' ? start : findSignatureEnd(lines, start, end, strip);
+    if (end <= signatureEnd) {
+      const collapsed = collapseInlineBody(lines[start] ?? '', strip);
+      if (collapsed !== undefined) {
+        replaced.set(start, collapsed);
+        elidedBodies += 1;
+      }
+      continue;
+    }
+
+    bodies.push([signatureEnd, end]);
+    const opening = cutAfterOpening(lines[signatureEnd] ?? '', strip);
+    if (opening !== undefined) {
+      replaced.set(signatureEnd, opening);
+    }
+    if (end - signatureEnd > 1) {
+      const firstBodyLine = lines.slice(signatureEnd + 1, end).find((line) => line.trim().length > 0) ?? '';
+      const indent = /^\s*/.exec(firstBodyLine)?.[0] ?? '';
+      replaced.set(signatureEnd + 1, `${indent}${placeholder}`);
+      for (let index = signatureEnd + 2; index < end; index += 1) {
+        hidden.add(index);
+      }
+      elidedBodies += 1;
+    }
+  }
+
+  const output: string[] = [];
+  lines.forEach((line, index) => {
+    if (hidden.has(index)) {
+      return;
+    }
+    const text = replaced.get(index) ?? line;
+    // Elision leaves runs of blank lines behind; one is enough.
+    if (text.trim().length === 0 && (output.at(-1) ?? '').trim().length === 0 && output.length > 0) {
+      return;
+    }
+    output.push(text);
+  });
+  return { content: output.join('\n'), elidedBodies };
+}
+
+// The line that opens the body: the first one with `{` or `=>` at or after the declaration.
+function findSignatureEnd(lines: string[], start: number, end: number, strip: (line: string) => string): number {
+  for (let index = start; index <= end; index += 1) {
+    const code = strip(lines[index] ?? '');
+    if (code.includes('{') || code.includes('=>')) {
+      return index;
+    }
+  }
+  return start;
+}
+
+// For extractors that report no end line (shell functions): the line closing the first brace.
+function findBlockEnd(lines: string[], start: number, strip: (line: string) => string): number | undefined {
+  let depth = 0;
+  let opened = false;
+  for (let index = start; index < lines.length; index += 1) {
+    for (const char of strip(lines[index] ?? '')) {
+      if (char === '{') {
+        depth += 1;
+        opened = true;
+      } else if (char === '}' && opened && (depth -= 1) === 0) {
+        return index + 1;
+      }
+    }
+    if (!opened && index > start + 2) {
+      return undefined;
+    }
+  }
+  return undefined;
+}
+
+// `fn new(c: i64) -> Self { Money(c) }` -> `fn new(c: i64) -> Self { ... }`
+function collapseInlineBody(line: string, strip: (line: string) => string): string | undefined {
+  const bodyStart = findBodyStart(strip(line));
+  if (bodyStart === undefined) {
+    return undefined;
+  }
+  if (line[bodyStart] === '{') {
+    const close = line.lastIndexOf('}');
+    if (close <= bodyStart || line.slice(bodyStart + 1, close).trim().length === 0) {
+      return undefined;
+    }
+    return `${line.slice(0, bodyStart)}{ ... }${line.slice(close + 1)}`;
+  }
+  const terminator = line.trimEnd().endsWith(';') ? ';' : '';
+  return `${line.slice(0, bodyStart)}=> ...${terminator}`;
+}
+
+// `fn run() { let a = 1;` -> `fn run() {`
+function cutAfterOpening(line: string, strip: (line: string) => string): string | undefined {
+  const bodyStart = findBodyStart(strip(line));
+  if (bodyStart === undefined || line[bodyStart] !== '{' || line.slice(bodyStart + 1).trim().length === 0) {
+    return undefined;
+  }
+  return line.slice(0, bodyStart + 1);
+}
+
+// Offset of the `{` or `=>` that starts the body, looking past the parameter list.
+function findBodyStart(code: string): number | undefined {
+  let depth = 0;
+  let afterParameters = code.indexOf('(') === -1;
+  for (let index = 0; index < code.length; index += 1) {
+    const char = code[index]!;
+    if (char === '(') {
+      depth += 1;
+    } else if (char === ')' && (depth -= 1) === 0) {
+      afterParameters = true;
+    } else if (afterParameters && depth === 0 && (char === '{' || (char === '=' && code[index + 1] === '>'))) {
+      return index;
+    }
+  }
+  return undefined;
+}
+
+// Blanks string contents so braces inside literals are not structure; keeps offsets. Rust
+// lifetimes (`'a`) rule out single-quoted strings there.
+function stripStrings(line: string, singleQuoted: boolean): string {
+  const pattern = singleQuoted ? /"(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'/g : /"(?:[^"\\]|\\.)*"/g;
+  return line.replace(pattern, (match) => `${match[0]}${' '.repeat(match.length - 2)}${match[0]}`);
+}
@@ -0,0 +1,75 @@
+import { readFile } from 'node:fs/promises';
+import { join, relative, resolve, sep } from 'node:path';
+import { extractCodeSymbols, readCodeIndex } from './index.js';
+import { declarationStart } from './outline.js';
+import { getCodeSymbolId, qualifiedName, resolveTarget } from './references.js';
+import { loadWorkspaceLanguageRegistry } from './registry.js';
+import { expandCodePaths } from './signatures.js';
+import type { CodeLanguage, CodeSymbol, CodeSymbolKind } from './types.js';
+
+export interface SymbolBody {
+  /** `file:line` of the declaration. */
+  id: string;
+  stableId?: string;
+  name: string;
+  qualifiedName: string;
+  kind: CodeSymbolKind;
+  language: CodeLanguage;
+  file: string;
+  /** First line of `source`: the doc comment or attributes above the declaration when it has any. */
+  startLine: number;
+  line: number;
+  endLine: number;
+  source: string;
+}
+
+/**
+ * Reads the source of one symbol, from the doc comment and attributes above it to its last line.
+ * A `file:line` id is looked up in the file as it is now, so it needs no index and follows edits
+ * made since indexing; names and stable ids are resolved through the symbol index.
+ */
+export async function readSymbolBody(request: { basePath: string; symbol: string }): Promise<SymbolBody> {
+  const { symbol, content } = await locateSymbol(request.basePath, request.symbol);
+  const lines = content.split(/\r?\n/);
+  const startLine = declarationStart(lines, symbol);
+  const endLine = Math.min(Math.max(symbol.endLine ?? symbol.line, symbol.line), lines.length);
+  return {
+    id: getCodeSymbolId(symbol),
+    ...(symbol.stableId !== undefined ? { stableId: symbol.stableId } : {}),
+    name: symbol.name,
+    qualifiedName: qualifiedName(symbol),
+    kind: symbol.kind,
+    language: symbol.language,
+    file: symbol.file,
+    startLine,
+    line: symbol.line,
+    endLine,
+    source: lines.slice(startLine - 1, endLine).join('\n'),
+  };
+}
+
+async function locateSymbol(basePath: string, query: string): Promise<{ symbol: CodeSymbol; content: string }> {
+  const location = /^(.+):(\d+)$/.exec(query);
+  if (location !== null) {
+    const { registry } = await loadWorkspaceLanguageRegistry(basePath);
+    const file = relative(resolve(basePath), resolve(basePath, location[1]!)).split(sep).join('/');
+    const [found] = await expandCodePaths(basePath, [location[1]!], registry);
+    if (found !== file) {
+      throw new Error(`Not a file: ${location[1]}`);
+    }
+    const content = await readFile(join(basePath, file), 'utf8');
+    const line = Number(location[2]);
+    const symbol = extractCodeSymbols(file, content, registry)?.symbols.find((candidate) => candidate.line === line);
+    if (symbol === undefined) {
+      throw new Error(`No symbol is declared on line ${line} of ${file}.`);
+    }
+    return { symbol, content };
+  }
+
+  const snapshot = await readCodeIndex(basePath);
+  if (snapshot === undefined) {
+    throw new Error('No symbol index found. Run "ax code index" first, or pass a file:line id.');
+  }
+  const symbol = resolveTarget(snapshot.files.flatMap((entry) => entry.symbols), query);
+  return { symbol, content: await readFile(join(basePath, symbol.file), 'utf8') };
+}
@@ -0,0 +1,57 @@
+import { createHash } from 'node:crypto';
+import type { CodeSymbol, CodeSymbolKind } from './types.js';
+
+const CALLABLE_KINDS = new Set<CodeSymbolKind>(['function', 'method']);
+const MAX_SIGNATURE_LINES = 12;
+
+export const STABLE_SYMBOL_ID_PATTERN = /^sym_[0-9a-f]{16}(?:~\d+)?$/;
+
+/**
+ * Gives every symbol a `stableId` derived from what it is rather than where it is: a hash of its
+ * language, kind, container-qualified name and, for functions and methods, the parameter list
+ * with whitespace normalized. Inserting lines above a symbol or moving it to another file keeps
+ * the id; renaming it or changing its parameters yields a new one. Symbols that would share an id
+ * within one file are numbered in order (`~2`, `~3`).
+ */
+export function addStableSymbolIds(symbols: CodeSymbol[], content: string): CodeSymbol[] {
+  const lines = content.split(/\r?\n/);
+  const seen = new Map<string, number>();
+  for (const symbol of symbols) {
+    const signature = CALLABLE_KINDS.has(symbol.kind) ? parameterList(symbol, lines) ?? '' : '';
+    const digest = createHash('sha256')
+      .update([symbol.language, symbol.kind, symbol.container ?? '', symbol.name, signature].join('\0'))
+      .digest('hex')
+      .slice(0, 16);
+    const occurrence = (seen.get(digest) ?? 0) + 1;
+    seen.set(digest, occurrence);
+    symbol.stableId = occurrence === 1 ? `sym_${digest}` : `sym_${digest}~${occurrence}`;
+  }
+  return symbols;
+}
+
+// `add<T>(item: T,\n  qty: u32)` -> `(item:T,qty:u32)`; undefined when the name is not followed by one.
+function parameterList(symbol: CodeSymbol, lines: string[]): string | undefined {
+  const last = Math.min(symbol.endLine ?? symbol.line, symbol.line + MAX_SIGNATURE_LINES - 1);
+  const text = lines.slice(symbol.line - 1, last)
+    .map((line) => line.replace(/\/\/.*$/, ''))
+    .join('\n')
+    .replace(/\/\*[\s\S]*?\*\//g, '');
+  const name = new RegExp(`(?:^|[^\\w$])${escapeRegExp(symbol.name)}\\s*(?:<[^()]*>)?\\s*\\(`).exec(text);
+  if (name === null) {
+    return undefined;
+  }
+  const open = name.index + name[0].length - 1;
+  let depth = 0;
+  for (let index = open; index < text.length; index += 1) {
+    if (text[index] === '(') {
+      depth += 1;
+    } else if (text[index] === ')' && (depth -= 1) === 0) {
+      return text.slice(open, index + 1).replace(/\s+/g, ' ').replace(/\s*([(),:<>[\]&*=|?])\s*/g, '$1');
+    }
+  }
+  return undefined;
+}
+
+function escapeRegExp(value: string): string {
+  return value.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+}
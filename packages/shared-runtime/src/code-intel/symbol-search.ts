@@ -0,0 +1,286 @@
+import { readFile } from 'node:fs/promises';
+import { join } from 'node:path';
+import { readCodeIndex, type CodeIndexSnapshot } from './index.js';
+import { declarationSignature, leadingDoc } from './outline.js';
+import type { SourceSpan } from './positions.js';
+import { qualifiedName } from './references.js';
+import { CODE_SYMBOL_KINDS, type CodeLanguage, type CodeSymbol, type CodeSymbolKind } from './types.js';
+
+export interface SymbolSearchQuery {
+  /** Fuzzy-matched against symbol names; empty lists every symbol the filters keep. */
+  name: string;
+  /** From `Orders.Order.Submit`: the last segment names the container (or module file), earlier ones appear in order in its path. */
+  qualifier: string[];
+  kind?: CodeSymbolKind;
+  language?: CodeLanguage;
+  /** Workspace-relative files or directories the symbol must be declared under. */
+  paths: string[];
+}
+
+export interface SymbolSearchHit {
+  symbol: CodeSymbol;
+  score: number;
+  /** Offsets of the matched characters in the symbol name, for highlighting. */
+  matches: number[];
+}
+
+/** A search hit as an agent navigating the workspace reads it. */
+export interface SymbolSearchResult {
+  name: string;
+  /** Module path (for Rust), container and name, joined with dots: `parser.Parser.new`. */
+  qualifiedName: string;
+  kind: CodeSymbolKind;
+  language: CodeLanguage;
+  file: string;
+  line: number;
+  endLine?: number;
+  /** The whole declaration, with byte offsets and UTF-16 columns. */
+  span?: SourceSpan;
+  /** The declaration up to its body, whitespace collapsed. */
+  signature?: string;
+  /** First line of the comment written directly above the declaration. */
+  doc?: string;
+  score: number;
+}
+
+export interface SymbolSearch {
+  search(query: string | SymbolSearchQuery, options?: { limit?: number }): SymbolSearchHit[];
+}
+
+const DEFAULT_LIMIT = 50;
+const QUALIFIER_SEPARATOR = /::|\.|\\|#/;
+const MODULE_FILE_STEMS = new Set(['mod', 'lib', 'main', 'index', '__init__']);
+// Per matched character: word starts and runs of consecutive characters are what fuzzy queries abbreviate.
+const WORD_START_BONUS = 8;
+const CONSECUTIVE_BONUS = 4;
+const CASE_BONUS = 1;
+const EXACT_BONUS = 100;
+const PREFIX_BONUS = 20;
+
+/**
+ * Splits a query into filters and a name: `kind:trait`, `lang:rust` (or `language:`) and
+ * `path:src/parser` filter, every other word is part of the name, and a name written as a path
+ * (`parser::Parser::new`) is qualified by its leading segments.
+ */
+export function parseSymbolSearchQuery(input: string): SymbolSearchQuery {
+  const query: SymbolSearchQuery = { name: '', qualifier: [], paths: [] };
+  const words: string[] = [];
+  for (const token of input.trim().split(/\s+/).filter((word) => word.length > 0)) {
+    const filter = /^(kind|lang|language|path):(.*)$/.exec(token);
+    if (filter === null) {
+      words.push(token);
+      continue;
+    }
+    const key = filter[1]!;
+    const value = filter[2]!;
+    if (value.length === 0) {
+      throw new Error(`Missing value for "${key}:" in the symbol query.`);
+    }
+    if (key === 'kind') {
+      if (!(CODE_SYMBOL_KINDS as readonly string[]).includes(value)) {
+        throw new Error(`Unknown symbol kind "${value}". Expected one of: ${CODE_SYMBOL_KINDS.join(', ')}.`);
+      }
+      query.kind = value as CodeSymbolKind;
+    } else if (key === 'path') {
+      query.paths.push(value.replace(/^\.\/|\/+$/g, ''));
+    } else {
+      query.language = value.toLowerCase();
+    }
+  }
+
+  const segments = words.join('').split(QUALIFIER_SEPARATOR).filter((segment) => segment.length > 0);
+  query.name = segments.pop() ?? '';
+  query.qualifier = segments;
+  return query;
+}
+
+/**
+ * A search service over one index snapshot; build it once and query it repeatedly. Names are
+ * matched fuzzily, so `DatProc` finds `DataProcessor`: every query character must appear in order,
+ * and matches at word starts, in runs and in the same case rank higher. Exact and prefix matches
+ * rank first, shorter names break ties.
+ */
+export function createSymbolSearch(snapshot: CodeIndexSnapshot): SymbolSearch {
+  const symbols = snapshot.files.flatMap((entry) => entry.symbols);
+  return {
+    search(input, options = {}) {
+      const query = typeof input === 'string' ? parseSymbolSearchQuery(input) : input;
+      const limit = options.limit ?? DEFAULT_LIMIT;
+      const qualifier = query.qualifier.map((segment) => segment.toLowerCase());
+      const hits: SymbolSearchHit[] = [];
+      for (const symbol of symbols) {
+        if (
+          (query.kind !== undefined && symbol.kind !== query.kind)
+          || (query.language !== undefined && symbol.language !== query.language)
+          || (query.paths.length > 0 && !query.paths.some((path) => isUnder(symbol, path)))
+          || (qualifier.length > 0 && !matchesQualifier(symbol, qualifier))
+        ) {
+          continue;
+        }
+        const match = query.name.length > 0 ? fuzzyMatch(query.name, symbol.name) : { score: 0, matches: [] };
+        if (match !== undefined) {
+          hits.push({ symbol, ...match });
+        }
+      }
+      return hits
+        .sort((left, right) => right.score - left.score
+          || left.symbol.name.length - right.symbol.name.length
+          || left.symbol.file.localeCompare(right.symbol.file)
+          || left.symbol.line - right.symbol.line)
+        .slice(0, limit);
+    },
+  };
+}
+
+export async function searchSymbolIndex(request: { basePath: string; query: string; limit?: number }): Promise<SymbolSearchHit[]> {
+  const snapshot = await readCodeIndex(request.basePath);
+  if (snapshot === undefined) {
+    throw new Error('No symbol index found. Run "ax code index" first.');
+  }
+  return createSymbolSearch(snapshot).search(request.query, { limit: request.limit });
+}
+
+/**
+ * Adds each hit's qualified name, signature and doc line, read from the files the hits are in.
+ * Files that can no longer be read leave both out.
+ */
+export async function describeSymbolHits(basePath: string, hits: SymbolSearchHit[]): Promise<SymbolSearchResult[]> {
+  const files = new Map<string, Promise<string[] | undefined>>();
+  const linesOf = (file: string) => {
+    if (!files.has(file)) {
+      files.set(file, readFile(join(basePath, file), 'utf8').then((content) => content.split(/\r?\n/), () => undefined));
+    }
+    return files.get(file)!;
+  };
+  return Promise.all(hits.map(async ({ symbol, score }) => {
+    const lines = await linesOf(symbol.file);
+    const signature = lines !== undefined ? declarationSignature(lines, symbol) : undefined;
+    const doc = lines !== undefined ? leadingDoc(lines, symbol) : undefined;
+    return {
+      name: symbol.name,
+      qualifiedName: qualifiedName(symbol),
+      kind: symbol.kind,
+      language: symbol.language,
+      file: symbol.file,
+      line: symbol.line,
+      ...(symbol.endLine !== undefined ? { endLine: symbol.endLine } : {}),
+      ...(symbol.span !== undefined ? { span: symbol.span } : {}),
+      ...(signature !== undefined ? { signature } : {}),
+      ...(doc !== undefined ? { doc } : {}),
+      score,
+    };
+  }));
+}
+
+/**
+ * Scores `pattern` as a case-insensitive subsequence of `name`, placing each character where it
+ * earns the most; `undefined` when it is not one.
+ */
+export function fuzzyMatch(pattern: string, name: string): { score: number; matches: number[] } | undefined {
+  const needle = pattern.toLowerCase();
+  const haystack = name.toLowerCase();
+  if (!isSubsequence(needle, haystack)) {
+    return undefined;
+  }
+
+  // best[i][j]: score of the best placement of needle[0..i] with needle[i] at haystack[j].
+  const best: number[][] = [];
+  const from: number[][] = [];
+  for (let i = 0; i < needle.length; i += 1) {
+    best.push(new Array<number>(haystack.length).fill(-Infinity));
+    from.push(new Array<number>(haystack.length).fill(-1));
+    // Best score of the previous character anywhere before j - 1, and where it was.
+    let runningBest = -Infinity;
+    let runningFrom = -1;
+    for (let j = i; j < haystack.length; j += 1) {
+      if (i > 0 && j >= 2 && best[i - 1]![j - 2]! > runningBest) {
+        runningBest = best[i - 1]![j - 2]!;
+        runningFrom = j - 2;
+      }
+      if (haystack[j] !== needle[i]) {
+        continue;
+      }
+      const gain = 1 + (isWordStart(name, j) ? WORD_START_BONUS : 0) + (name[j] === pattern[i] ? CASE_BONUS : 0);
+      if (i === 0) {
+        best[i]![j] = gain;
+        continue;
+      }
+      const consecutive = j >= 1 ? best[i - 1]![j - 1]! + CONSECUTIVE_BONUS : -Infinity;
+      if (consecutive >= runningBest && consecutive > -Infinity) {
+        best[i]![j] = consecutive + gain;
+        from[i]![j] = j - 1;
+      } else if (runningBest > -Infinity) {
+        best[i]![j] = runningBest + gain;
+        from[i]![j] = runningFrom;
+      }
+    }
+  }
+
+  const last = needle.length - 1;
+  let end = -1;
+  for (let j = 0; j < haystack.length; j += 1) {
+    if (end === -1 || best[last]![j]! > best[last]![end]!) {
+      end = j;
+    }
+  }
+  const matches: number[] = [];
+  for (let i = last, j = end; i >= 0; j = from[i]![j]!, i -= 1) {
+    matches.unshift(j);
+  }
+  const bonus = haystack === needle ? EXACT_BONUS : haystack.startsWith(needle) ? PREFIX_BONUS : 0;
+  return { score: best[last]![end]! + bonus, matches };
+}
+
+function isSubsequence(needle: string, haystack: string): boolean {
+  let position = 0;
+  for (const char of needle) {
+    position = haystack.indexOf(char, position);
+    if (position === -1) {
+      return false;
+    }
+    position += 1;
+  }
+  return true;
+}
+
+// `DataProcessor`: D and P; `data_processor`: d and p; `HTTPServer`: H and S.
+function isWordStart(name: string, index: number): boolean {
+  if (index === 0) {
+    return true;
+  }
+  const previous = name[index - 1]!;
+  const current = name[index]!;
+  if (!/[A-Za-z0-9]/.test(previous)) {
+    return true;
+  }
+  const isUpper = (char: string | undefined) => char !== undefined && /[A-Z]/.test(char);
+  return isUpper(current) && (!isUpper(previous) || (name[index + 1] !== undefined && /[a-z]/.test(name[index + 1]!)));
+}
+
+// The last qualifier segment names the symbol's parent: its container, or for a top-level item its
+// module file (`mod.rs`, `lib.rs` and `index.ts` stand for their directory). Earlier segments appear
+// in order anywhere in the file path or container.
+function matchesQualifier(symbol: CodeSymbol, qualifier: string[]): boolean {
+  const fileSegments = symbol.file.replace(/\.[^./]+$/, '').split('/');
+  if (fileSegments.length > 1 && MODULE_FILE_STEMS.has(fileSegments.at(-1)!)) {
+    fileSegments.pop();
+  }
+  const containerSegments = (symbol.container ?? '').split(QUALIFIER_SEPARATOR).filter((segment) => segment.length > 0);
+  const scope = [...fileSegments, ...containerSegments].map((segment) => segment.toLowerCase());
+  if (scope.pop() !== qualifier.at(-1)) {
+    return false;
+  }
+  let position = 0;
+  for (const segment of qualifier.slice(0, -1)) {
+    position = scope.indexOf(segment, position);
+    if (position === -1) {
+      return false;
+    }
+    position += 1;
+  }
+  return true;
+}
+
+function isUnder(symbol: CodeSymbol, path: string): boolean {
+  return (symbol.locations ?? [symbol]).some((location) => path === '' || path === '.' || location.file === path || location.file.startsWith(`${path}/`));
+}
@@ -0,0 +1,139 @@
+import type { CodeSymbol } from './types.js';
+
+const TEST_DIRECTORY_PATTERN = /(?:^|\/)(?:tests?|specs?|__tests__|testdata)\//i;
+const TEST_FILE_PATTERNS: readonly RegExp[] = [
+  /_test\.[^/.]+$/,
+  /_spec\.rb$/,
+  /(?:^|\/)test_[^/]+\.py$/,
+  /(?:^|\/)conftest\.py$/,
+  /\.(?:test|spec)\.[cm]?[jt]sx?$/,
+  /Tests?\.(?:cs|php)$/,
+  /\.tftest\.hcl$/,
+  /\.bats$/,
+];
+
+// Markers written on the lines directly above a test definition.
+const TEST_MARKER_PATTERNS: readonly RegExp[] = [
+  // C# (xUnit, NUnit, MSTest)
+  /^\[(?:[\w.]+\.)?(?:Fact|Theory|Test|TestCase|TestCaseSource|TestMethod|DataTestMethod|TestFixture|TestClass)(?:Attribute)?\b/,
+  // PHPUnit
+  /@test\b/,
+  /^#\[(?:\\?PHPUnit\\Framework\\Attributes\\)?Test\]/,
+  // Rust
+  /^#\[(?:[\w:]+::)?test\]/,
+  /^#\[cfg\(test\)\]/,
+  // Python
+  /^@pytest\.(?:mark|fixture)\b/,
+];
+const MARKER_LOOKBEHIND_LINES = 8;
+const TEST_BASE_CLASS_PATTERN = /(?:^|[\\:.])(?:TestCase|Minitest::Test|IntegrationTest|SystemTestCase)$/;
+const CONTAINER_SEPARATORS = ['.', '\\', '::'];
+
+export function isTestFile(file: string): boolean {
+  const normalized = file.replaceAll('\\', '/');
+  return TEST_DIRECTORY_PATTERN.test(normalized) || TEST_FILE_PATTERNS.some((pattern) => pattern.test(normalized));
+}
+
+/**
+ * Marks test code with `isTest`: every symbol of a test file (by path convention), symbols
+ * annotated as tests (`[Fact]`, `#[test]`, `@test`, ...), test classes (by base class or
+ * annotation) and everything they contain.
+ */
+export function tagTestSymbols(symbols: CodeSymbol[], file: string, content: string): CodeSymbol[] {
+  if (isTestFile(file)) {
+    for (const symbol of symbols) {
+      symbol.isTest = true;
+    }
+    return symbols;
+  }
+
+  const lines = content.split(/\r?\n/);
+  for (const symbol of symbols) {
+    if (hasTestMarker(lines, symbol.line) || isTestClass(symbol) || isTestMethodName(symbol, symbols)) {
+      symbol.isTest = true;
+    }
+  }
+
+  // A class holding test methods is a test class even without a recognised base class.
+  for (const symbol of symbols) {
+    if (symbol.kind === 'class' && symbols.some((member) => member.isTest === true && memberContainerNames(symbol).includes(member.container ?? ''))) {
+      symbol.isTest = true;
+    }
+  }
+
+  const testContainers = symbols
+    .filter((symbol) => symbol.isTest === true && (symbol.kind === 'class' || symbol.kind === 'module' || symbol.kind === 'namespace'))
+    .flatMap((symbol) => memberContainerNames(symbol));
+  for (const symbol of symbols) {
+    const container = symbol.container;
+    if (container !== undefined && testContainers.some((name) => isWithin(container, name))) {
+      symbol.isTest = true;
+    }
+  }
+
+  return symbols;
+}
+
+function hasTestMarker(lines: string[], line: number): boolean {
+  // `[Fact] public void Adds()` and `#[test] fn adds()` carry the marker on the declaration line.
+  if (TEST_MARKER_PATTERNS.some((pattern) => pattern.test((lines[line - 1] ?? '').trim()))) {
+    return true;
+  }
+  for (let index = line - 2; index >= 0 && index >= line - 1 - MARKER_LOOKBEHIND_LINES; index -= 1) {
+    const text = (lines[index] ?? '').trim();
+    if (TEST_MARKER_PATTERNS.some((pattern) => pattern.test(text))) {
+      return true;
+    }
+    // Stop at the previous statement; only attributes, decorators and comments sit between a marker and its target.
+    if (text.length > 0 && !/^(?:\[|#\[|@|\/\/|\/\*|\*|#)/.test(text)) {
+      return false;
+    }
+  }
+  return false;
+}
+
+function isTestClass(symbol: CodeSymbol): boolean {
+  if (symbol.kind !== 'class') {
+    return false;
+  }
+  const base = symbol.attributes?.extends ?? symbol.attributes?.superclass;
+  return base !== undefined && TEST_BASE_CLASS_PATTERN.test(base);
+}
+
+// PHPUnit and Minitest run `test*` methods; projects often put their own base class
+// (`ApiTestCase`, `IntegrationTest`) between the test and the framework.
+function isTestMethodName(symbol: CodeSymbol, symbols: CodeSymbol[]): boolean {
+  if (symbol.kind !== 'method' || symbol.container === undefined || !/^test(?:_|[A-Z])/.test(symbol.name)) {
+    return false;
+  }
+  const owner = symbols.find((candidate) => candidate.kind === 'class' && memberContainerNames(candidate).includes(symbol.container!));
+  return owner !== undefined && /Test(?:Case)?$/.test(owner.attributes?.extends ?? owner.attributes?.superclass ?? '');
+}
+
+// PHP methods name their class without its namespace.
+function memberContainerNames(symbol: CodeSymbol): string[] {
+  return symbol.language === 'php' ? [qualifiedName(symbol), symbol.name] : [qualifiedName(symbol)];
+}
+
+function qualifiedName(symbol: CodeSymbol): string {
+  if (symbol.container === undefined) {
+    return symbol.name;
+  }
+  const separator = CONTAINER_SEPARATORS.find((candidate) => symbol.container!.includes(candidate)) ?? defaultSeparator(symbol);
+  return `${symbol.container}${separator}${symbol.name}`;
+}
+
+function defaultSeparator(symbol: CodeSymbol): string {
+  switch (symbol.language) {
+    case 'php':
+      return '\\';
+    case 'ruby':
+      return '::';
+    default:
+      return '.';
+  }
+}
+
+function isWithin(container: string, name: string): boolean {
+  return container === name || CONTAINER_SEPARATORS.some((separator) => container.startsWith(`${name}${separator}`));
+}
@@ -0,0 +1,180 @@
+const MAX_MESSAGE_LINES = 6;
+// Terminal colours some runners keep even when piped.
+const ANSI_PATTERN = /\u001b\[[0-9;]*m/g;
+/**
+ * Reads the failing tests out of `cargo test`, Jest, Vitest or pytest output, with the file and
+ * line each failed at when the output says. Output of any other runner yields none.
+ */
+export function parseTestFailures(output) {
+    const lines = output.replace(ANSI_PATTERN, '').split(/\r?\n/);
+    const failures = [
+        ...parseCargoFailures(lines),
+        ...parseJestFailures(lines),
+        ...parseVitestFailures(lines),
+        ...parsePytestFailures(lines),
+    ];
+    const seen = new Set();
+    return failures.filter((failure) => {
+        const key = `${failure.framework}\0${failure.file ?? ''}\0${failure.name}`;
+        if (seen.has(key)) {
+            return false;
+        }
+        seen.add(key);
+        return true;
+    });
+}
+// ---- tests::adds stdout ----
+// thread 'tests::adds' panicked at src/lib.rs:10:9:        (Rust 1.73+; older: panicked at 'message', src/lib.rs:10:9)
+function parseCargoFailures(lines) {
+    const failures = [];
+    for (let index = 0; index < lines.length; index += 1) {
+        const header = /^---- (\S+) stdout ----$/.exec(lines[index].trim());
+        if (header === null) {
+            continue;
+        }
+        const failure = { framework: 'cargo', name: header[1], message: '' };
+        const message = [];
+        for (let next = index + 1; next < lines.length; next += 1) {
+            const line = lines[next];
+            if (/^---- \S+ stdout ----$/.test(line.trim()) || /^failures:\s*$/.test(line) || line.startsWith('note: ')) {
+                break;
+            }
+            const panic = /panicked at (?:'(.*)', )?([^\s:]+):(\d+):\d+:?$/.exec(line);
+            if (panic !== null) {
+                failure.file = panic[2];
+                failure.line = Number(panic[3]);
+                if (panic[1] !== undefined) {
+                    message.push(panic[1]);
+                }
+                continue;
+            }
+            if (line.trim().length > 0) {
+                message.push(line.trim());
+            }
+        }
+        failure.message = message.slice(0, MAX_MESSAGE_LINES).join('\n');
+        failures.push(failure);
+    }
+    return failures;
+}
+// FAIL src/cart.test.js
+//   ● Cart › adds items
+//     expect(received).toBe(expected)
+//       at Object.<anonymous> (src/cart.test.js:12:20)
+function parseJestFailures(lines) {
+    const failures = [];
+    let suiteFile;
+    let current;
+    const finish = () => {
+        if (current !== undefined) {
+            current.failure.message = current.message.slice(0, MAX_MESSAGE_LINES).join('\n');
+            failures.push(current.failure);
+            current = undefined;
+        }
+    };
+    for (const line of lines) {
+        const suite = /^\s*(?:FAIL|PASS)\s+(\S+)\s*(?:\(.*\))?$/.exec(line);
+        if (suite !== null) {
+            finish();
+            suiteFile = suite[1];
+            continue;
+        }
+        const bullet = /^\s*● (.+)$/.exec(line);
+        if (bullet !== null) {
+            finish();
+            current = { failure: { framework: 'jest', name: bullet[1].trim(), file: suiteFile, message: '' }, message: [], inFrame: false };
+            continue;
+        }
+        if (current === undefined) {
+            continue;
+        }
+        if (/^(?:Test Suites|Tests|Snapshots|Time):/.test(line.trim())) {
+            finish();
+            continue;
+        }
+        const frame = /^\s*at .*?\(?([^\s()]+):(\d+):\d+\)?$/.exec(line);
+        if (frame !== null) {
+            current.inFrame = true;
+            if (current.failure.line === undefined && !frame[1].includes('node_modules') && (suiteFile === undefined || frame[1].endsWith(suiteFile))) {
+                current.failure.line = Number(frame[2]);
+            }
+            continue;
+        }
+        // The code frame (`> 12 | expect(...)`) and stack end the message.
+        if (/^\s*>?\s*\d+ \|/.test(line) || /^\s*\|/.test(line)) {
+            current.inFrame = true;
+            continue;
+        }
+        if (!current.inFrame && line.trim().length > 0) {
+            current.message.push(line.trim());
+        }
+    }
+    finish();
+    return failures;
+}
+//  FAIL  src/cart.test.ts > Cart > adds items
+// AssertionError: expected 2 to be 3
+//  ❯ src/cart.test.ts:12:20
+function parseVitestFailures(lines) {
+    const failures = [];
+    for (let index = 0; index < lines.length; index += 1) {
+        const header = /^\s*FAIL\s+(\S+) > (.+)$/.exec(lines[index]);
+        if (header === null) {
+            continue;
+        }
+        const failure = { framework: 'vitest', name: header[2].trim(), file: header[1], message: '' };
+        const message = [];
+        for (let next = index + 1; next < lines.length; next += 1) {
+            const line = lines[next];
+            if (/^\s*FAIL\s+\S+ > /.test(line) || /^[⎯-]{3,}/.test(line.trim())) {
+                break;
+            }
+            const location = /^\s*❯ (\S+):(\d+):\d+/.exec(line);
+            if (location !== null) {
+                if (failure.line === undefined && location[1] === failure.file) {
+                    failure.line = Number(location[2]);
+                }
+                continue;
+            }
+            if (failure.line === undefined && line.trim().length > 0 && !/^\s*\d+\s*\|/.test(line)) {
+                message.push(line.trim());
+            }
+        }
+        failure.message = message.slice(0, MAX_MESSAGE_LINES).join('\n');
+        failures.push(failure);
+    }
+    return failures;
+}
+// ____________________ test_add ____________________
+// tests/test_cart.py:5: AssertionError
+// FAILED tests/test_cart.py::test_add - assert 2 == 3
+function parsePytestFailures(lines) {
+    const failures = [];
+    for (const line of lines) {
+        const summary = /^FAILED (\S+?)::(\S+)(?: - (.*))?$/.exec(line.trim());
+        if (summary === null) {
+            continue;
+        }
+        const file = summary[1];
+        const name = summary[2].split('::').join('.');
+        failures.push({ framework: 'pytest', name, file, line: findPytestLine(lines, file, name), message: summary[3]?.trim() ?? '' });
+    }
+    return failures;
+}
+// The `file:line:` of the failure, inside the section pytest heads with the test's name.
+function findPytestLine(lines, file, name) {
+    const start = lines.findIndex((line) => new RegExp(`^_{3,} ${escapeRegExp(name)} _{3,}$`).test(line.trim()));
+    if (start === -1) {
+        return undefined;
+    }
+    for (let index = start + 1; index < lines.length && !/^_{3,} \S+ _{3,}$/.test(lines[index].trim()) && !/^={3,}/.test(lines[index].trim()); index += 1) {
+        const location = new RegExp(`^${escapeRegExp(file)}:(\\d+): `).exec(lines[index]);
+        if (location !== null) {
+            return Number(location[1]);
+        }
+    }
+    return undefined;
+}
+function escapeRegExp(value) {
+    return value.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+}
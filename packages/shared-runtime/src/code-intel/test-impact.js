@@ -0,0 +1,311 @@
+import { execFile } from 'node:child_process';
+import { readFile } from 'node:fs/promises';
+import { basename, join, posix } from 'node:path';
+import { promisify } from 'node:util';
+import { readWorkspaceConfig } from '../config-layers.js';
+import { extractCodeSymbols, IGNORED_DIRS, readCodeIndex } from './index.js';
+import { buildModuleGraph, isEmittedSibling, listWorkspaceFiles } from './module-graph.js';
+import { loadWorkspaceLanguageRegistry } from './registry.js';
+import { isTestFile } from './test-detection.js';
+const execFileAsync = promisify(execFile);
+const DEFAULT_TEST_TIMEOUT_MS = 10 * 60_000;
+const REFERENCE_KINDS = new Set(['function', 'method', 'class', 'trait', 'record', 'module', 'namespace', 'property']);
+const MIN_REFERENCE_NAME_LENGTH = 3;
+const TEST_NAME_AFFIXES = [/^test_/, /_test$/, /_spec$/, /\.test$/, /\.spec$/, /Tests?$/];
+/**
+ * Works out which tests a change set can affect: changed test files, tests importing a changed
+ * module (directly or transitively), tests naming a changed symbol, and tests named after a
+ * changed file (`cart.ts` / `cart.test.ts`, `Cart.php` / `CartTest.php`).
+ */
+export async function analyzeTestImpact(request) {
+    const base = request.base ?? 'HEAD';
+    return { base, ...await findImpactedTests(request.basePath, await collectChangedFiles(request.basePath, base)) };
+}
+/** The test selection of `analyzeTestImpact` for a change set that did not come from git. */
+export async function findImpactedTests(basePath, changes) {
+    const changedSymbols = await collectChangedSymbols(basePath, changes);
+    const snapshot = await readCodeIndex(basePath);
+    const taggedTestFiles = new Set((snapshot?.files ?? [])
+        .filter((entry) => entry.symbols.some((symbol) => symbol.isTest === true))
+        .map((entry) => entry.file));
+    const workspaceFiles = await listWorkspaceFiles(basePath, { maxFiles: 20_000 });
+    const known = new Set(workspaceFiles);
+    const testFiles = workspaceFiles.filter((file) => (isTestFile(file) || taggedTestFiles.has(file)) && !isEmittedSibling(file, known));
+    const testFileSet = new Set(testFiles);
+    const changedSources = changes.filter((change) => !testFileSet.has(change.file) && !isTestFile(change.file)).map((change) => change.file);
+    const impacted = new Map();
+    const addReason = (file, reason) => {
+        const reasons = impacted.get(file) ?? [];
+        if (!reasons.includes(reason)) {
+            reasons.push(reason);
+        }
+        impacted.set(file, reasons);
+    };
+    for (const change of changes) {
+        if (!change.deleted && (testFileSet.has(change.file) || (isTestFile(change.file) && !isEmittedSibling(change.file, known)))) {
+            addReason(change.file, 'changed');
+        }
+    }
+    if (changedSources.length > 0) {
+        const graph = await buildModuleGraph(basePath, { maxFiles: 20_000 });
+        const importsByFile = new Map(graph.modules.map((module) => [
+            module.file,
+            module.imports.flatMap((entry) => entry.resolved !== undefined ? [entry.resolved] : []),
+        ]));
+        const changedSet = new Set(changedSources);
+        const referenceNames = [...new Set(changedSymbols
+            .filter((symbol) => REFERENCE_KINDS.has(symbol.kind) && symbol.name.length >= MIN_REFERENCE_NAME_LENGTH && !isTestFile(symbol.file))
+            .map((symbol) => symbol.name))];
+        for (const testFile of testFiles) {
+            for (const [changed, via] of findImportedChanges(testFile, importsByFile, changedSet)) {
+                addReason(testFile, via === undefined ? `imports ${changed}` : `imports ${changed} via ${via}`);
+            }
+            for (const changed of changedSources) {
+                if (testSubjectStem(testFile) === sourceStem(changed)) {
+                    addReason(testFile, `named after ${changed}`);
+                }
+            }
+            if (referenceNames.length > 0) {
+                let content;
+                try {
+                    content = await readFile(join(basePath, testFile), 'utf8');
+                }
+                catch {
+                    continue;
+                }
+                for (const name of referenceNames) {
+                    if (new RegExp(`\\b${escapeRegExp(name)}\\b`).test(content)) {
+                        addReason(testFile, `references ${name}`);
+                    }
+                }
+            }
+        }
+    }
+    return {
+        changedFiles: changes.map((change) => change.file),
+        changedSymbols,
+        impactedTests: [...impacted.entries()]
+            .map(([file, reasons]) => ({ file, reasons }))
+            .sort((left, right) => left.file.localeCompare(right.file)),
+        testFileCount: testFiles.length,
+    };
+}
+/**
+ * Runs `testing.command` from `.automatosx/config.json` (or the given command) with the impacted
+ * test files appended.
+ */
+export async function runImpactedTests(basePath, impact, options = {}) {
+    const files = impact.impactedTests.map((test) => test.file);
+    // With no files most runners fall back to the whole suite, which is what this avoids.
+    if (files.length === 0) {
+        await resolveTestCommand(basePath, options.command);
+        return { command: [], exitCode: 0, passed: true, durationMs: 0, output: '' };
+    }
+    return runTestCommand(basePath, { ...options, files });
+}
+/**
+ * Runs `testing.command` from `.automatosx/config.json` (or the given command), with `files`
+ * appended when given. A failing run resolves with its exit code; only a command that cannot be
+ * started, or times out, rejects.
+ */
+export async function runTestCommand(basePath, options = {}) {
+    const { command, timeoutMs } = await resolveTestCommand(basePath, options.command);
+    const files = options.files ?? [];
+    const [executable, ...args] = command;
+    const startedAt = Date.now();
+    try {
+        const { stdout, stderr } = await execFileAsync(executable, [...args, ...files], {
+            cwd: basePath,
+            timeout: options.timeoutMs ?? timeoutMs ?? DEFAULT_TEST_TIMEOUT_MS,
+            maxBuffer: 1024 * 1024 * 16,
+            signal: options.signal,
+        });
+        return { command: [...command, ...files], exitCode: 0, passed: true, durationMs: Date.now() - startedAt, output: `${stdout}${stderr}` };
+    }
+    catch (error) {
+        const failure = error;
+        if (typeof failure.code !== 'number') {
+            throw new Error(`Could not run ${executable}: ${failure.message}`);
+        }
+        return {
+            command: [...command, ...files],
+            exitCode: failure.code,
+            passed: false,
+            durationMs: Date.now() - startedAt,
+            output: `${failure.stdout ?? ''}${failure.stderr ?? ''}`,
+        };
+    }
+}
+async function resolveTestCommand(basePath, command) {
+    const config = await readTestingConfig(basePath);
+    const resolved = command ?? config.command;
+    if (resolved === undefined || resolved.length === 0) {
+        throw new Error('No test command configured. Set "testing.command" in .automatosx/config.json (e.g. "npx vitest run").');
+    }
+    return { command: resolved, timeoutMs: config.timeoutMs };
+}
+async function collectChangedFiles(basePath, base) {
+    const [diff, untracked] = await Promise.all([
+        git(basePath, ['diff', '--unified=0', '--no-color', '--no-ext-diff', '--no-renames', base, '--']),
+        git(basePath, ['ls-files', '--others', '--exclude-standard']),
+    ]);
+    const changes = new Map(parseUnifiedDiff(diff).map((change) => [change.file, change]));
+    for (const file of untracked.split('\n').map((entry) => entry.trim()).filter((entry) => entry.length > 0)) {
+        if (!changes.has(file)) {
+            changes.set(file, { file, deleted: false });
+        }
+    }
+    return withoutIgnored([...changes.values()]);
+}
+/**
+ * Reads the files a unified diff touches, from `git diff` or plain `diff -u` output, with the
+ * new-side line ranges and text of each hunk. Files the diff creates have neither: all of them changed.
+ */
+export function parseUnifiedDiff(diff) {
+    const changes = new Map();
+    let current;
+    let oldPath;
+    // Lines still to come in the current hunk; removed lines such as `-- comment` look like file headers.
+    let oldLeft = 0;
+    let newLeft = 0;
+    for (const line of diff.split(/\r?\n/)) {
+        if (oldLeft > 0 || newLeft > 0) {
+            if (current?.hunks !== undefined) {
+                current.hunks[current.hunks.length - 1] += `\n${line}`;
+            }
+            if (line.startsWith('-')) {
+                oldLeft -= 1;
+            }
+            else if (line.startsWith('+')) {
+                newLeft -= 1;
+            }
+            else if (!line.startsWith('\\')) {
+                oldLeft -= 1;
+                newLeft -= 1;
+            }
+            continue;
+        }
+        if (line.startsWith('--- ')) {
+            oldPath = line.startsWith('--- /dev/null') ? undefined : stripDiffPrefix(line.slice(4));
+            continue;
+        }
+        if (line.startsWith('+++ ')) {
+            const newPath = line.startsWith('+++ /dev/null') ? undefined : stripDiffPrefix(line.slice(4));
+            const file = newPath ?? oldPath;
+            current = file === undefined ? undefined : { file, deleted: newPath === undefined, ...(oldPath === undefined ? {} : { ranges: [], hunks: [] }) };
+            if (current !== undefined) {
+                changes.set(current.file, current);
+            }
+            continue;
+        }
+        const hunk = /^@@ -\d+(?:,(\d+))? \+(\d+)(?:,(\d+))? @@/.exec(line);
+        if (hunk !== null) {
+            oldLeft = hunk[1] === undefined ? 1 : Number(hunk[1]);
+            newLeft = hunk[3] === undefined ? 1 : Number(hunk[3]);
+            if (current?.ranges !== undefined) {
+                const start = Number(hunk[2]);
+                // Pure deletions report the line before the removed block; count it as touched.
+                current.ranges.push(newLeft === 0 ? [Math.max(start, 1), Math.max(start, 1)] : [start, start + newLeft - 1]);
+                current.hunks?.push(line);
+            }
+        }
+    }
+    return withoutIgnored([...changes.values()]);
+}
+// Runtime state under .automatosx/ and vendored code never select tests.
+function withoutIgnored(changes) {
+    return changes
+        .filter((change) => !change.file.split('/').some((segment) => IGNORED_DIRS.has(segment)))
+        .sort((left, right) => left.file.localeCompare(right.file));
+}
+async function collectChangedSymbols(basePath, changes) {
+    const { registry } = await loadWorkspaceLanguageRegistry(basePath);
+    const symbols = [];
+    for (const change of changes) {
+        if (change.deleted) {
+            continue;
+        }
+        let content;
+        try {
+            content = await readFile(join(basePath, change.file), 'utf8');
+        }
+        catch {
+            continue;
+        }
+        const indexed = extractCodeSymbols(change.file, content, registry);
+        for (const symbol of indexed?.symbols ?? []) {
+            if (change.ranges === undefined || change.ranges.some((range) => overlaps(symbol, range))) {
+                symbols.push({
+                    name: symbol.name,
+                    kind: symbol.kind,
+                    file: symbol.file,
+                    line: symbol.line,
+                    ...(symbol.container !== undefined ? { container: symbol.container } : {}),
+                });
+            }
+        }
+    }
+    return symbols;
+}
+/**
+ * Breadth-first walk of the test file's imports; yields each changed module it reaches with the
+ * first intermediate module when the import is indirect.
+ */
+function findImportedChanges(testFile, importsByFile, changed) {
+    const found = [];
+    const visited = new Set([testFile]);
+    const queue = (importsByFile.get(testFile) ?? []).map((file) => ({ file }));
+    while (queue.length > 0) {
+        const next = queue.shift();
+        if (visited.has(next.file)) {
+            continue;
+        }
+        visited.add(next.file);
+        if (changed.has(next.file)) {
+            found.push([next.file, next.via]);
+        }
+        for (const imported of importsByFile.get(next.file) ?? []) {
+            queue.push({ file: imported, via: next.via ?? next.file });
+        }
+    }
+    return found;
+}
+function overlaps(symbol, [start, end]) {
+    return symbol.line <= end && (symbol.endLine ?? symbol.line) >= start;
+}
+function testSubjectStem(file) {
+    const stem = sourceStem(file);
+    const stripped = TEST_NAME_AFFIXES.reduce((name, affix) => name.replace(affix, ''), stem);
+    return stripped !== stem && stripped.length > 0 ? stripped : undefined;
+}
+function sourceStem(file) {
+    const name = basename(file);
+    const dot = name.lastIndexOf('.');
+    return dot <= 0 ? name : name.slice(0, dot);
+}
+function stripDiffPrefix(path) {
+    // Plain `diff -u` headers end with a tab and the file's timestamp.
+    const unquoted = path.startsWith('"') ? JSON.parse(path) : path.replace(/\t.*$/, '');
+    return posix.normalize(unquoted.replace(/^[ab]\//, ''));
+}
+function escapeRegExp(value) {
+    return value.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+}
+async function git(basePath, args) {
+    try {
+        return (await execFileAsync('git', args, { cwd: basePath, maxBuffer: 1024 * 1024 * 16 })).stdout;
+    }
+    catch (error) {
+        const message = error instanceof Error ? error.message : String(error);
+        throw new Error(`git ${args[0] ?? 'command'} failed: ${message}`);
+    }
+}
+async function readTestingConfig(basePath) {
+    const settings = (await readWorkspaceConfig(basePath)).testing;
+    const testing = typeof settings === 'object' && settings !== null ? settings : undefined;
+    const command = Array.isArray(testing?.command)
+        ? testing.command.filter((entry) => typeof entry === 'string' && entry.length > 0)
+        : typeof testing?.command === 'string' ? testing.command.trim().split(/\s+/).filter((entry) => entry.length > 0) : undefined;
+    const timeoutMs = typeof testing?.timeoutMs === 'number' && testing.timeoutMs > 0 ? testing.timeoutMs : undefined;
+    return { command, timeoutMs };
+}
@@ -0,0 +1,303 @@
+import { mkdir, readdir, readFile, stat, writeFile } from 'node:fs/promises';
+import { join } from 'node:path';
+import { IGNORED_DIRS } from './index.js';
+const DEFAULT_LIMIT = 100;
+const MAX_TEXT_FILE_BYTES = 1024 * 1024;
+const MAX_LINE_LENGTH = 300;
+const BINARY_SNIFF_BYTES = 8000;
+// Loaded snapshots by index path, reused while the file on disk is unchanged.
+const loadedIndexes = new Map();
+export function getTextIndexPath(basePath) {
+    return join(basePath, '.automatosx', 'index', 'text-index.json');
+}
+/**
+ * Indexes the trigrams of every text file in the workspace, skipping ignored directories, binary
+ * files and files over 1 MB, so a search reads only the files that can match.
+ */
+export async function buildTextIndex(basePath) {
+    const files = [];
+    const postings = new Map();
+    for (const path of await listWorkspaceFiles(basePath, '')) {
+        const absolute = join(basePath, path);
+        const stats = await stat(absolute);
+        if (stats.size > MAX_TEXT_FILE_BYTES) {
+            continue;
+        }
+        const buffer = await readFile(absolute);
+        if (buffer.subarray(0, BINARY_SNIFF_BYTES).includes(0)) {
+            continue;
+        }
+        const id = files.length;
+        files.push({ path, size: stats.size, mtimeMs: stats.mtimeMs });
+        for (const trigram of trigramsOf(buffer.toString('utf8').toLowerCase())) {
+            const list = postings.get(trigram);
+            if (list === undefined) {
+                postings.set(trigram, [id]);
+            }
+            else {
+                list.push(id);
+            }
+        }
+    }
+    const snapshot = {
+        version: 1,
+        generatedAt: new Date().toISOString(),
+        files,
+        trigrams: Object.fromEntries(postings),
+    };
+    const indexPath = getTextIndexPath(basePath);
+    await mkdir(join(basePath, '.automatosx', 'index'), { recursive: true });
+    await writeFile(indexPath, JSON.stringify(snapshot), 'utf8');
+    return snapshot;
+}
+/**
+ * Searches file contents for a literal or regular expression, building the trigram index on
+ * first use. Only files holding every trigram of the pattern's required literals are read, and
+ * matches are checked against their current content, so results are never wrong but can miss
+ * text written since the index was built; `reindex` refreshes it.
+ */
+export async function searchText(request) {
+    if (request.pattern.length === 0) {
+        throw new Error('Search pattern must not be empty.');
+    }
+    let matcher;
+    try {
+        matcher = new RegExp(request.regex === true ? request.pattern : escapeRegExp(request.pattern), request.caseSensitive === true ? '' : 'i');
+    }
+    catch (error) {
+        throw new Error(`Invalid regular expression: ${error instanceof Error ? error.message : String(error)}`);
+    }
+    const snapshot = request.reindex === true ? await buildTextIndex(request.basePath) : await loadTextIndex(request.basePath);
+    const limit = request.limit ?? DEFAULT_LIMIT;
+    const scopes = (request.paths ?? []).map((path) => path.replace(/^\.\/|\/+$/g, '')).filter((path) => path.length > 0 && path !== '.');
+    const alternatives = request.regex === true ? requiredLiterals(request.pattern) : [[request.pattern]];
+    const candidates = candidateFiles(snapshot, alternatives)
+        .filter((file) => scopes.length === 0 || scopes.some((scope) => file.path === scope || file.path.startsWith(`${scope}/`)));
+    const matches = [];
+    let filesScanned = 0;
+    for (const file of candidates) {
+        let content;
+        try {
+            content = await readFile(join(request.basePath, file.path), 'utf8');
+        }
+        catch {
+            continue;
+        }
+        filesScanned += 1;
+        const lines = content.split(/\r?\n/);
+        const fileMatches = [];
+        for (let index = 0; index < lines.length; index += 1) {
+            const found = matcher.exec(lines[index]);
+            if (found === null) {
+                continue;
+            }
+            if (matches.length + fileMatches.length === limit) {
+                matches.push(...fileMatches);
+                if (fileMatches.length > 0) {
+                    request.onMatches?.(fileMatches);
+                }
+                return { pattern: request.pattern, filesIndexed: snapshot.files.length, filesScanned, matches, truncated: true };
+            }
+            fileMatches.push({ file: file.path, line: index + 1, column: found.index + 1, text: lines[index].trim().slice(0, MAX_LINE_LENGTH) });
+        }
+        matches.push(...fileMatches);
+        if (fileMatches.length > 0) {
+            request.onMatches?.(fileMatches);
+        }
+    }
+    return { pattern: request.pattern, filesIndexed: snapshot.files.length, filesScanned, matches, truncated: false };
+}
+async function loadTextIndex(basePath) {
+    const indexPath = getTextIndexPath(basePath);
+    let mtimeMs;
+    try {
+        mtimeMs = (await stat(indexPath)).mtimeMs;
+    }
+    catch {
+        return buildTextIndex(basePath);
+    }
+    const loaded = loadedIndexes.get(indexPath);
+    if (loaded?.mtimeMs === mtimeMs) {
+        return loaded.snapshot;
+    }
+    try {
+        const snapshot = JSON.parse(await readFile(indexPath, 'utf8'));
+        if (snapshot.version === 1 && Array.isArray(snapshot.files)) {
+            loadedIndexes.set(indexPath, { mtimeMs, snapshot });
+            return snapshot;
+        }
+    }
+    catch {
+        // Rebuilt below.
+    }
+    return buildTextIndex(basePath);
+}
+// Files holding every trigram of some alternative; `undefined` alternatives mean no literal narrows the search.
+function candidateFiles(snapshot, alternatives) {
+    if (alternatives === undefined || alternatives.some((literals) => literals.every((literal) => literal.length < 3))) {
+        return snapshot.files;
+    }
+    const selected = new Set();
+    for (const literals of alternatives) {
+        let ids;
+        for (const trigram of literals.flatMap((literal) => [...trigramsOf(literal.toLowerCase())])) {
+            const posting = snapshot.trigrams[trigram] ?? [];
+            ids = new Set(ids === undefined ? posting : posting.filter((id) => ids.has(id)));
+            if (ids.size === 0) {
+                break;
+            }
+        }
+        for (const id of ids ?? []) {
+            selected.add(id);
+        }
+    }
+    return snapshot.files.filter((_file, id) => selected.has(id));
+}
+/**
+ * The literals a match of `source` must contain: one list per top-level alternative, each
+ * needing all of its literals. `undefined` when some alternative requires no literal at all.
+ */
+export function requiredLiterals(source) {
+    const alternatives = [];
+    for (const alternative of splitAlternatives(source)) {
+        const literals = literalRuns(alternative).filter((literal) => literal.length >= 3);
+        if (literals.length === 0) {
+            return undefined;
+        }
+        alternatives.push(literals);
+    }
+    return alternatives;
+}
+function splitAlternatives(source) {
+    const alternatives = [];
+    let depth = 0;
+    let inClass = false;
+    let start = 0;
+    for (let index = 0; index < source.length; index += 1) {
+        const char = source[index];
+        if (char === '\\') {
+            index += 1;
+        }
+        else if (inClass) {
+            inClass = char !== ']';
+        }
+        else if (char === '[') {
+            inClass = true;
+        }
+        else if (char === '(') {
+            depth += 1;
+        }
+        else if (char === ')') {
+            depth -= 1;
+        }
+        else if (char === '|' && depth === 0) {
+            alternatives.push(source.slice(start, index));
+            start = index + 1;
+        }
+    }
+    alternatives.push(source.slice(start));
+    return alternatives;
+}
+// `fn\s+parse_(\w+)\(` -> ['fn', 'parse_', '(']: runs of literal characters, broken by classes,
+// groups and escapes like `\s`, with characters a `?`, `*` or `{` quantifier makes optional dropped.
+function literalRuns(source) {
+    const runs = [];
+    let run = '';
+    const endRun = () => {
+        if (run.length > 0) {
+            runs.push(run);
+        }
+        run = '';
+    };
+    let index = 0;
+    while (index < source.length) {
+        const char = source[index];
+        let literal;
+        if (char === '\\') {
+            const escaped = source[index + 1];
+            literal = escaped !== undefined && !/[A-Za-z0-9]/.test(escaped) ? escaped : undefined;
+            index += 2;
+        }
+        else if (char === '[' || char === '(') {
+            index = skipGroup(source, index);
+        }
+        else if ('.^$)|]'.includes(char)) {
+            index += 1;
+        }
+        else {
+            literal = char;
+            index += 1;
+        }
+        const quantifier = source[index];
+        const optional = quantifier === '?' || quantifier === '*' || quantifier === '{';
+        if (literal === undefined || optional) {
+            endRun();
+        }
+        else {
+            run += literal;
+        }
+        if (quantifier === '+' || optional) {
+            index = quantifier === '{' ? source.indexOf('}', index) + 1 || source.length : index + 1;
+            if (source[index] === '?') {
+                index += 1;
+            }
+            if (quantifier === '+') {
+                // `ab+c` still needs "ab" and "bc".
+                endRun();
+                run = literal ?? '';
+            }
+        }
+    }
+    endRun();
+    return runs;
+}
+function skipGroup(source, start) {
+    const close = source[start] === '[' ? ']' : ')';
+    let depth = 0;
+    for (let index = start; index < source.length; index += 1) {
+        const char = source[index];
+        if (char === '\\') {
+            index += 1;
+        }
+        else if (char === source[start]) {
+            depth += 1;
+        }
+        else if (char === close) {
+            depth -= 1;
+            if (depth === 0) {
+                return index + 1;
+            }
+        }
+    }
+    return source.length;
+}
+function trigramsOf(text) {
+    const trigrams = new Set();
+    for (let index = 0; index + 3 <= text.length; index += 1) {
+        trigrams.add(text.slice(index, index + 3));
+    }
+    return trigrams;
+}
+async function listWorkspaceFiles(basePath, directory) {
+    let entries;
+    try {
+        entries = await readdir(join(basePath, directory), { withFileTypes: true, encoding: 'utf8' });
+    }
+    catch {
+        return [];
+    }
+    const files = [];
+    for (const entry of entries.sort((left, right) => left.name.localeCompare(right.name))) {
+        const path = directory === '' ? entry.name : `${directory}/${entry.name}`;
+        if (entry.isDirectory() && !IGNORED_DIRS.has(entry.name)) {
+            files.push(...await listWorkspaceFiles(basePath, path));
+        }
+        else if (entry.isFile()) {
+            files.push(path);
+        }
+    }
+    return files;
+}
+function escapeRegExp(value) {
+    return value.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+}
@@ -0,0 +1,46 @@
+export const CODE_SYMBOL_KINDS = [
+    'stage',
+    'base-image',
+    'port',
+    'copy',
+    'function',
+    'variable',
+    'resource',
+    'data',
+    'module',
+    'output',
+    'class',
+    'method',
+    'attribute',
+    'trait',
+    'namespace',
+    'record',
+    'property',
+];
+export function joinContinuationLines(content, commentPrefix = '#') {
+    const physical = content.split(/\r?\n/);
+    const logical = [];
+    let buffer = '';
+    let startLine = 0;
+    for (let index = 0; index < physical.length; index += 1) {
+        const raw = physical[index] ?? '';
+        const trimmed = raw.trim();
+        if (trimmed.startsWith(commentPrefix) || (buffer.length === 0 && trimmed.length === 0)) {
+            continue;
+        }
+        if (buffer.length === 0) {
+            startLine = index + 1;
+        }
+        if (trimmed.endsWith('\\')) {
+            buffer += `${trimmed.slice(0, -1).trimEnd()} `;
+            continue;
+        }
+        buffer += trimmed;
+        logical.push({ text: buffer.trim(), line: startLine });
+        buffer = '';
+    }
+    if (buffer.trim().length > 0) {
+        logical.push({ text: buffer.trim(), line: startLine });
+    }
+    return logical;
+}
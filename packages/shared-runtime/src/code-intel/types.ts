@@ -0,0 +1,122 @@
+import type { OwnershipSummary } from './ownership.js';
+import type { SymbolAnnotation } from './rust-attributes.js';
+import type { SourceSpan } from './positions.js';
+
+export type BuiltinCodeLanguage = 'dockerfile' | 'shell' | 'hcl' | 'ruby' | 'php' | 'csharp' | 'rust';
+
+// Grammars registered through the LanguageRegistry may introduce languages beyond the built-ins.
+export type CodeLanguage = BuiltinCodeLanguage | (string & {});
+
+export const CODE_SYMBOL_KINDS = [
+  'stage',
+  'base-image',
+  'port',
+  'copy',
+  'function',
+  'variable',
+  'resource',
+  'data',
+  'module',
+  'output',
+  'class',
+  'method',
+  'attribute',
+  'trait',
+  'namespace',
+  'record',
+  'property',
+] as const;
+
+export type CodeSymbolKind = typeof CODE_SYMBOL_KINDS[number];
+
+export interface CodeSymbol {
+  name: string;
+  kind: CodeSymbolKind;
+  language: CodeLanguage;
+  file: string;
+  line: number;
+  endLine?: number;
+  container?: string;
+  /** Content-derived id that survives edits above the symbol and moves between files; see `addStableSymbolIds`. */
+  stableId?: string;
+  attributes?: Record<string, string>;
+  /** Set on test code: symbols in test files, annotated tests and members of test classes. */
+  isTest?: boolean;
+  /** Set on functions and methods declared `async`. */
+  isAsync?: boolean;
+  /** Concurrency primitives (`Arc`, `Mutex`, `channel`, `spawn`, ...) a function or method uses. */
+  concurrency?: string[];
+  /** Rust attributes written on the item, except doc comments and `cfg`/`cfg_attr`. */
+  annotations?: SymbolAnnotation[];
+  /** Traits named by the item's `#[derive(...)]`, as written (`Debug`, `serde::Serialize`). */
+  derives?: string[];
+  /** Rust `cfg` predicates (`feature = "serde"`, `windows`, `not(test)`) that must all hold for the symbol to be compiled. */
+  cfg?: string[];
+  /** Rust `#[cfg_attr(predicate, attributes)]` on the symbol: attributes applied only when the predicate holds. */
+  cfgAttr?: Array<{ predicate: string; attributes: string }>;
+  /** Rust functions and methods: how `self` and each parameter are taken and what a returned borrow is tied to. */
+  ownership?: OwnershipSummary;
+  locations?: CodeSymbolLocation[];
+  /** The whole declaration, from its first line to its last, with byte offsets and UTF-16 columns. */
+  span?: SourceSpan;
+  /** The symbol's name on its declaration line, when spelled out there. */
+  nameSpan?: SourceSpan;
+}
+
+export interface CodeSymbolLocation {
+  file: string;
+  line: number;
+  endLine?: number;
+  span?: SourceSpan;
+}
+
+export interface CodeFileIndex {
+  file: string;
+  language: CodeLanguage;
+  symbols: CodeSymbol[];
+}
+
+export interface CodeLanguageExtractor {
+  language: CodeLanguage;
+  /** Changes whenever the symbols it extracts may change; the parse cache skips extractors without one, built-ins aside. */
+  version?: string;
+  matchesFile(fileName: string): boolean;
+  matchesShebang?(firstLine: string): boolean;
+  extract(content: string, file: string): CodeSymbol[];
+}
+
+export interface LogicalLine {
+  text: string;
+  line: number;
+}
+
+export function joinContinuationLines(content: string, commentPrefix = '#'): LogicalLine[] {
+  const physical = content.split(/\r?\n/);
+  const logical: LogicalLine[] = [];
+  let buffer = '';
+  let startLine = 0;
+
+  for (let index = 0; index < physical.length; index += 1) {
+    const raw = physical[index] ?? '';
+    const trimmed = raw.trim();
+    if (trimmed.startsWith(commentPrefix) || (buffer.length === 0 && trimmed.length === 0)) {
+      continue;
+    }
+    if (buffer.length === 0) {
+      startLine = index + 1;
+    }
+    if (trimmed.endsWith('\\')) {
+      buffer += `${trimmed.slice(0, -1).trimEnd()} `;
+      continue;
+    }
+    buffer += trimmed;
+    logical.push({ text: buffer.trim(), line: startLine });
+    buffer = '';
+  }
+
+  if (buffer.trim().length > 0) {
+    logical.push({ text: buffer.trim(), line: startLine });
+  }
+
+  return logical;
+}
@@ -0,0 +1,263 @@
+import { existsSync, readFileSync } from 'node:fs';
+import { mkdir, readFile, writeFile } from 'node:fs/promises';
+import { homedir } from 'node:os';
+import { dirname, extname, join, resolve } from 'node:path';
+import { parse as parseYaml, stringify as stringifyYaml } from 'yaml';
+import { formatConfigIssue, migrateWorkspaceConfig, validateWorkspaceConfig } from './config-schema.js';
+import { asRecord } from './records.js';
+/** Lowest first: each layer overrides the ones before it. */
+export const CONFIG_LAYERS = ['system', 'user', 'project', 'profile', 'env', 'flags'];
+/** The layers read from config files. */
+export const CONFIG_FILE_LAYERS = ['system', 'user', 'project'];
+/** A config file to use in place of the system one, `/etc/automatosx/config.json` by default. */
+export const SYSTEM_CONFIG_ENV_VAR = 'AUTOMATOSX_SYSTEM_CONFIG';
+/** A config file to use in place of the user one, `~/.automatosx/config.json` by default. */
+export const USER_CONFIG_ENV_VAR = 'AUTOMATOSX_USER_CONFIG';
+/** The profile to apply, from `profiles.<name>` in the config files; `--profile` sets it. */
+export const CONFIG_PROFILE_ENV_VAR = 'AUTOMATOSX_PROFILE';
+/** A JSON object of dotted paths to values; `--config <path>=<value>` sets it. */
+export const CONFIG_FLAGS_ENV_VAR = 'AUTOMATOSX_CONFIG_FLAGS';
+/** `AUTOMATOSX_CONFIG__providers__default=gemini` sets `providers.default`. */
+export const CONFIG_ENV_PREFIX = 'AUTOMATOSX_CONFIG__';
+// Looked for in this order in each config directory; the first one found is used.
+const CONFIG_FILE_NAMES = ['config.json', 'config.yaml', 'config.yml'];
+/** The workspace config with every layer applied. */
+export async function readWorkspaceConfig(basePath, env = process.env) {
+    return (await loadLayeredConfig(basePath, env)).config;
+}
+/** `readWorkspaceConfig` for callers that cannot wait. */
+export function readWorkspaceConfigSync(basePath, env = process.env) {
+    const files = configFileCandidates(basePath, env).flatMap(({ layer, paths }) => {
+        const path = paths.find((candidate) => existsSync(candidate));
+        if (path === undefined) {
+            return [];
+        }
+        let raw;
+        try {
+            raw = readFileSync(path, 'utf8');
+        }
+        catch (error) {
+            return [{ layer, source: path, values: {}, error: error instanceof Error ? error.message : String(error) }];
+        }
+        return [parseConfigFile(layer, path, raw)];
+    });
+    return layerConfig(files, env).config;
+}
+/**
+ * Reads the system, user and project config files and applies the profile, the
+ * `AUTOMATOSX_CONFIG__*` variables and the `--config` flags over them, keeping each layer so
+ * `explainConfigKey` can say which one set a value.
+ */
+export async function loadLayeredConfig(basePath, env = process.env) {
+    const files = [];
+    for (const { layer, paths } of configFileCandidates(basePath, env)) {
+        for (const path of paths) {
+            const read = await readFile(path, 'utf8').then((content) => ({ content }), (error) => ({ error }));
+            if ('content' in read) {
+                files.push(parseConfigFile(layer, path, read.content));
+                break;
+            }
+            if (read.error.code !== 'ENOENT') {
+                files.push({ layer, source: path, values: {}, error: read.error.message });
+                break;
+            }
+        }
+    }
+    return layerConfig(files, env);
+}
+/** Which layers set `key`, a dotted path, and which of them won. */
+export function explainConfigKey(layered, key) {
+    const layers = layered.layers.flatMap((layer) => {
+        const value = getConfigValue(layer.values, key);
+        return value === undefined ? [] : [{ layer: layer.layer, source: layer.source, value }];
+    });
+    const winner = layers.at(-1);
+    return {
+        key,
+        value: getConfigValue(layered.config, key),
+        ...(winner !== undefined ? { layer: winner.layer, source: winner.source } : {}),
+        layers,
+    };
+}
+/** Checks each config file against the workspace config schema, then the config with every layer applied. */
+export function validateLayeredConfig(layered) {
+    const files = layered.layers.filter((layer) => CONFIG_FILE_LAYERS.includes(layer.layer)).map((layer) => ({
+        layer: layer.layer,
+        source: layer.source,
+        issues: layer.error !== undefined ? [{ path: '', message: layer.error }] : validateWorkspaceConfig(layer.values),
+        migrations: layer.migrations ?? [],
+    }));
+    // What a file gets wrong shows up again in the merged config; it is reported once, against the file.
+    const reported = new Set(files.flatMap((file) => file.issues.map(formatConfigIssue)));
+    const overrides = validateWorkspaceConfig(layered.config)
+        .filter((issue) => !reported.has(formatConfigIssue(issue)))
+        .map((issue) => {
+        const { layer, source } = explainConfigKey(layered, issue.path);
+        return { ...issue, ...(layer !== undefined ? { layer, source } : {}) };
+    });
+    return {
+        valid: overrides.length === 0 && files.every((file) => file.issues.length === 0),
+        files,
+        overrides,
+    };
+}
+/**
+ * The project's own config file, without the other layers, for changing it: `.automatosx/config.json`
+ * or whichever of `config.yaml` and `config.yml` the project has. A file of an older `schemaVersion`
+ * comes back migrated, so writing it back upgrades it.
+ */
+export async function readProjectConfig(basePath) {
+    const paths = CONFIG_FILE_NAMES.map((name) => join(basePath, '.automatosx', name));
+    for (const path of paths) {
+        const raw = await readFile(path, 'utf8').catch(() => undefined);
+        if (raw !== undefined) {
+            const parsed = parseConfigFile('project', path, raw);
+            // Writing over a file that did not parse would lose what is in it.
+            if (parsed.error !== undefined) {
+                throw new Error(`Cannot update ${path}: ${parsed.error}`);
+            }
+            return { path, config: parsed.values };
+        }
+    }
+    return { path: paths[0], config: {} };
+}
+/** Writes a project config file back in the format its name says. */
+export async function writeProjectConfig(path, config) {
+    await mkdir(dirname(path), { recursive: true });
+    await writeFile(path, isYamlFile(path) ? stringifyYaml(config) : `${JSON.stringify(config, null, 2)}\n`, 'utf8');
+}
+function configFileCandidates(basePath, env) {
+    const systemDir = process.platform === 'win32'
+        ? join(env.ProgramData ?? 'C:\\ProgramData', 'automatosx')
+        : '/etc/automatosx';
+    const userDir = join(env.HOME ?? homedir(), '.automatosx');
+    const projectDir = join(basePath, '.automatosx');
+    const candidates = [
+        { layer: 'system', paths: pathsIn(systemDir, env[SYSTEM_CONFIG_ENV_VAR]) },
+        { layer: 'user', paths: pathsIn(userDir, env[USER_CONFIG_ENV_VAR]) },
+        { layer: 'project', paths: pathsIn(projectDir) },
+    ];
+    // In the home directory the project's files are the user's; read them once, as the project's.
+    return resolve(userDir) === resolve(projectDir) && env[USER_CONFIG_ENV_VAR] === undefined
+        ? candidates.filter((candidate) => candidate.layer !== 'user')
+        : candidates;
+}
+function pathsIn(dir, override) {
+    return override !== undefined && override.length > 0 ? [override] : CONFIG_FILE_NAMES.map((name) => join(dir, name));
+}
+function parseConfigFile(layer, path, raw) {
+    let parsed;
+    try {
+        parsed = isYamlFile(path) ? parseYaml(raw) : JSON.parse(raw);
+    }
+    catch (error) {
+        return { layer, source: path, values: {}, error: `not valid ${isYamlFile(path) ? 'YAML' : 'JSON'} (${error instanceof Error ? error.message : String(error)})` };
+    }
+    // An empty YAML file is no config rather than a broken one.
+    if (parsed === null || parsed === undefined) {
+        return { layer, source: path, values: {} };
+    }
+    const values = asRecord(parsed);
+    if (values === undefined) {
+        return { layer, source: path, values: {}, error: 'the top level is not an object' };
+    }
+    const { config, migrations } = migrateWorkspaceConfig(values);
+    return { layer, source: path, values: config, ...(migrations.length > 0 ? { migrations } : {}) };
+}
+function layerConfig(files, env) {
+    const layers = [...files];
+    const fileConfig = files.reduce((merged, layer) => mergeConfig(merged, layer.values), {});
+    const definedProfiles = asRecord(fileConfig.profiles) ?? {};
+    const profile = env[CONFIG_PROFILE_ENV_VAR]?.trim() || undefined;
+    const profileValues = profile === undefined ? undefined : asRecord(definedProfiles[profile]);
+    if (profile !== undefined && profileValues !== undefined) {
+        layers.push({ layer: 'profile', source: `profiles.${profile}`, values: profileValues });
+    }
+    let config = layers.reduce((merged, layer) => mergeConfig(merged, layer.values), {});
+    const variables = Object.entries(env)
+        .filter((entry) => entry[0].startsWith(CONFIG_ENV_PREFIX) && entry[1] !== undefined)
+        .sort(([left], [right]) => left.localeCompare(right));
+    for (const [name, raw] of variables) {
+        // Variable names are often upper case; match the keys already in the config regardless of case.
+        const path = matchKeyCase(config, name.slice(CONFIG_ENV_PREFIX.length).split('__').filter((segment) => segment.length > 0));
+        if (path.length > 0) {
+            const layer = { layer: 'env', source: name, values: valueAtPath(path, parseValue(raw)) };
+            layers.push(layer);
+            config = mergeConfig(config, layer.values);
+        }
+    }
+    for (const [key, value] of Object.entries(parseFlags(env[CONFIG_FLAGS_ENV_VAR]))) {
+        const path = key.split('.').filter((segment) => segment.length > 0);
+        if (path.length > 0) {
+            const layer = { layer: 'flags', source: `--config ${key}`, values: valueAtPath(path, value) };
+            layers.push(layer);
+            config = mergeConfig(config, layer.values);
+        }
+    }
+    return {
+        config,
+        layers,
+        ...(profile !== undefined ? { profile } : {}),
+        profiles: Object.keys(definedProfiles).filter((name) => asRecord(definedProfiles[name]) !== undefined),
+    };
+}
+function mergeConfig(base, override) {
+    const merged = { ...base };
+    for (const [key, value] of Object.entries(override)) {
+        const current = asRecord(merged[key]);
+        const next = asRecord(value);
+        merged[key] = current !== undefined && next !== undefined ? mergeConfig(current, next) : value;
+    }
+    return merged;
+}
+function matchKeyCase(config, segments) {
+    const path = [];
+    let current = config;
+    for (const segment of segments) {
+        const key = Object.keys(current ?? {}).find((candidate) => candidate.toLowerCase() === segment.toLowerCase()) ?? segment;
+        path.push(key);
+        current = asRecord(current?.[key]);
+    }
+    return path;
+}
+function valueAtPath(path, value) {
+    let nested = value;
+    for (const segment of [...path].reverse()) {
+        nested = { [segment]: nested };
+    }
+    return nested;
+}
+function getConfigValue(config, key) {
+    let current = config;
+    for (const part of key.split('.').filter((segment) => segment.length > 0)) {
+        const record = asRecord(current);
+        if (record === undefined || !(part in record)) {
+            return undefined;
+        }
+        current = record[part];
+    }
+    return current;
+}
+// JSON when it parses, so numbers, booleans and objects can be given; a plain string otherwise.
+function parseValue(raw) {
+    try {
+        return JSON.parse(raw);
+    }
+    catch {
+        return raw;
+    }
+}
+function parseFlags(raw) {
+    if (raw === undefined || raw.length === 0) {
+        return {};
+    }
+    try {
+        return asRecord(JSON.parse(raw)) ?? {};
+    }
+    catch {
+        return {};
+    }
+}
+function isYamlFile(path) {
+    return ['.yaml', '.yml'].includes(extname(path).toLowerCase());
+}
@@ -0,0 +1,203 @@
+import { WORKSPACE_CONFIG_VERSION, WorkspaceConfigSchema } from '@defai.digital/contracts';
+import { asRecord } from './records.js';
+const CONFIG_MIGRATIONS = [
+    {
+        from: 1,
+        description: 'defaultProvider moved to providers.default',
+        migrate: ({ defaultProvider, ...config }) => {
+            if (defaultProvider === undefined) {
+                return undefined;
+            }
+            const providers = asRecord(config.providers) ?? {};
+            // A providers.default already there was the one the runtime used, so it stays.
+            return { ...config, providers: providers.default === undefined ? { ...providers, default: defaultProvider } : providers };
+        },
+    },
+];
+// Keys a migration moved, so a config that sets one where the migrations do not reach is told where it went.
+const MOVED_KEYS = {
+    defaultProvider: 'providers.default',
+};
+const TYPE_NAMES = {
+    string: 'a string',
+    number: 'a number',
+    integer: 'a whole number',
+    boolean: 'true or false',
+    object: 'an object',
+    array: 'an array',
+    null: 'null',
+};
+/**
+ * Brings a config written for an older `schemaVersion` up to the current one. A config without a
+ * `schemaVersion` is read as the oldest, since the steps only change keys that are present, and
+ * is left without one.
+ */
+export function migrateWorkspaceConfig(config) {
+    const version = config.schemaVersion ?? 1;
+    if (typeof version !== 'number' || version >= WORKSPACE_CONFIG_VERSION) {
+        return { config, migrations: [] };
+    }
+    let migrated = config;
+    const migrations = config.schemaVersion === undefined ? [] : [`schemaVersion ${version} became ${WORKSPACE_CONFIG_VERSION}`];
+    for (const step of CONFIG_MIGRATIONS.filter((migration) => migration.from >= version)) {
+        const next = step.migrate(migrated);
+        if (next !== undefined) {
+            migrated = next;
+            migrations.push(step.description);
+        }
+    }
+    return {
+        config: config.schemaVersion === undefined ? migrated : { ...migrated, schemaVersion: WORKSPACE_CONFIG_VERSION },
+        migrations,
+    };
+}
+/** Checks a config, as migrated, against the workspace config schema. */
+export function validateWorkspaceConfig(config) {
+    // The keys of a newer version are unknown here, so they are not reported one by one.
+    if (typeof config.schemaVersion === 'number' && config.schemaVersion > WORKSPACE_CONFIG_VERSION) {
+        return [{
+            path: 'schemaVersion',
+            message: `is ${config.schemaVersion}, newer than the ${WORKSPACE_CONFIG_VERSION} this version of AutomatosX reads; upgrade AutomatosX`,
+        }];
+    }
+    const result = WorkspaceConfigSchema.safeParse(config);
+    return result.success ? [] : result.error.issues.flatMap(describeIssue);
+}
+export function formatConfigIssue(issue) {
+    return issue.path.length === 0 ? issue.message : `${issue.path} ${issue.message}`;
+}
+function describeIssue(issue) {
+    const path = issue.path.join('.');
+    switch (issue.code) {
+        case 'unrecognized_keys':
+            return (issue.keys ?? []).map((key) => ({ path: path.length === 0 ? key : `${path}.${key}`, message: describeUnknownKey(issue.path, key), unknownKey: true }));
+        case 'invalid_type':
+            return [{ path, message: issue.received === 'undefined' ? 'is required' : `must be ${TYPE_NAMES[String(issue.expected)] ?? issue.expected}` }];
+        case 'invalid_enum_value':
+            return [{ path, message: `must be one of ${(issue.options ?? []).map(String).join(', ')}` }];
+        case 'invalid_literal':
+            return [{ path, message: `must be ${JSON.stringify(issue.expected)}` }];
+        case 'too_small':
+            return [{
+                path,
+                message: issue.type === 'string'
+                    ? 'must not be empty'
+                    : issue.type === 'array'
+                        ? `must have at least ${issue.minimum} ${issue.minimum === 1 ? 'entry' : 'entries'}`
+                        : `must be ${issue.inclusive === false ? 'above' : 'at least'} ${issue.minimum}`,
+            }];
+        case 'too_big':
+            return [{
+                path,
+                message: issue.type === 'string' || issue.type === 'array'
+                    ? `must have at most ${issue.maximum} ${issue.type === 'string' ? 'characters' : 'entries'}`
+                    : `must be ${issue.inclusive === false ? 'below' : 'at most'} ${issue.maximum}`,
+            }];
+        case 'invalid_union':
+            return describeUnionIssue(issue, path);
+        default:
+            return [{ path, message: issue.message }];
+    }
+}
+function describeUnionIssue(issue, path) {
+    const branches = (issue.unionErrors ?? []).map((error) => error.issues);
+    const atUnion = (entry) => entry.path.join('.') === path;
+    const mismatched = (entry) => atUnion(entry) && ['invalid_type', 'invalid_literal', 'invalid_enum_value'].includes(entry.code);
+    // A branch the value has the shape of says what is wrong inside it.
+    const matched = branches.find((entries) => !entries.some(mismatched));
+    if (matched !== undefined) {
+        return matched.flatMap(describeIssue);
+    }
+    const expected = [...new Set(branches.flatMap((entries) => entries.filter(atUnion).flatMap((entry) => entry.code === 'invalid_enum_value'
+        ? (entry.options ?? []).map((option) => JSON.stringify(option))
+        : entry.code === 'invalid_literal'
+            ? [JSON.stringify(entry.expected)]
+            : [TYPE_NAMES[String(entry.expected)] ?? String(entry.expected)])))];
+    return [{
+        path,
+        message: expected.length === 0 ? issue.message : `must be ${expected.length === 1 ? expected[0] : `${expected.slice(0, -1).join(', ')} or ${expected.at(-1)}`}`,
+    }];
+}
+function describeUnknownKey(parent, key) {
+    const moved = parent.length === 0 ? MOVED_KEYS[key] : undefined;
+    if (moved !== undefined) {
+        return `is not a config key; it moved to ${moved}`;
+    }
+    const suggestion = closestKey(key, knownKeysAt(parent));
+    return suggestion === undefined ? 'is not a config key' : `is not a config key; did you mean "${suggestion}"?`;
+}
+function closestKey(key, candidates) {
+    // About one edit in three characters, so a short key is not matched to an unrelated one.
+    const limit = Math.max(1, Math.floor(key.length / 3));
+    let best;
+    for (const candidate of candidates) {
+        const distance = editDistance(key.toLowerCase(), candidate.toLowerCase());
+        if (distance <= limit && (best === undefined || distance < best.distance)) {
+            best = { key: candidate, distance };
+        }
+    }
+    return best?.key;
+}
+function editDistance(left, right) {
+    let previous = Array.from({ length: right.length + 1 }, (_, index) => index);
+    for (let row = 1; row <= left.length; row += 1) {
+        const current = [row];
+        for (let column = 1; column <= right.length; column += 1) {
+            current[column] = Math.min(
+                previous[column] + 1,
+                current[column - 1] + 1,
+                previous[column - 1] + (left[row - 1] === right[column - 1] ? 0 : 1),
+            );
+        }
+        previous = current;
+    }
+    return previous[right.length];
+}
+function knownKeysAt(path) {
+    let schema = WorkspaceConfigSchema;
+    for (const segment of path) {
+        schema = childSchema(schema, segment);
+    }
+    return Object.keys(objectShape(schema) ?? {});
+}
+function childSchema(schema, segment) {
+    const def = schemaDef(schema);
+    switch (def?.typeName) {
+        case 'ZodOptional':
+        case 'ZodNullable':
+        case 'ZodDefault':
+            return childSchema(def.innerType, segment);
+        case 'ZodEffects':
+            return childSchema(def.schema, segment);
+        case 'ZodUnion':
+            return def.options?.map((option) => childSchema(option, segment)).find((child) => child !== undefined);
+        case 'ZodObject':
+            return def.shape?.()[String(segment)];
+        case 'ZodRecord':
+            return def.valueType;
+        case 'ZodArray':
+            return def.type;
+        default:
+            return undefined;
+    }
+}
+function objectShape(schema) {
+    const def = schemaDef(schema);
+    switch (def?.typeName) {
+        case 'ZodOptional':
+        case 'ZodNullable':
+        case 'ZodDefault':
+            return objectShape(def.innerType);
+        case 'ZodEffects':
+            return objectShape(def.schema);
+        case 'ZodUnion':
+            return def.options?.map(objectShape).find((shape) => shape !== undefined);
+        case 'ZodObject':
+            return def.shape?.();
+        default:
+            return undefined;
+    }
+}
+function schemaDef(schema) {
+    return typeof schema === 'object' && schema !== null ? schema._def : undefined;
+}
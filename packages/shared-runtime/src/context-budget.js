@@ -0,0 +1,211 @@
+import { extname } from 'node:path';
+import { extractCodeSymbols } from './code-intel/index.js';
+import { renderSignatureSkeleton } from './code-intel/signatures.js';
+import { asRecord } from './records.js';
+export const CONTEXT_TRIM_STRATEGIES = ['signature-only', 'drop-oldest', 'summarize-middle'];
+const CHARS_PER_TOKEN = 4;
+const DEFAULT_RESERVE_TOKENS = 4096;
+// summarize-middle keeps this many turns at each end of the history word for word.
+const KEPT_FIRST_TURNS = 1;
+const KEPT_LAST_TURNS = 2;
+const SUMMARY_LINE_CHARS = 120;
+// A section cut by summarize-middle keeps at least this share of itself.
+const MIN_KEPT_SHARE = 0.25;
+// Room for the note that replaces the cut.
+const CUT_MARKER_CHARS = 64;
+/** The `providers.contextBudget` section. */
+export function readContextBudgetSettings(workspaceConfig) {
+    const section = asRecord(asRecord(workspaceConfig.providers)?.contextBudget) ?? {};
+    const strategies = Array.isArray(section.strategies)
+        ? section.strategies.filter((entry) => CONTEXT_TRIM_STRATEGIES.includes(entry))
+        : [];
+    return {
+        reserveTokens: typeof section.reserveTokens === 'number' && section.reserveTokens >= 0 ? section.reserveTokens : DEFAULT_RESERVE_TOKENS,
+        strategies: Array.isArray(section.strategies) ? strategies : [...CONTEXT_TRIM_STRATEGIES],
+    };
+}
+export function estimateContextTokens(text) {
+    return Math.ceil(text.length / CHARS_PER_TOKEN);
+}
+/**
+ * Writes the prompt for `input` and trims it to fit `budget`. The strategies run in their
+ * configured order, each only as far as needed; if the context is still too long, whole sections
+ * are dropped: memory hits, then the repo map, the history and the largest files. The report lists
+ * every cut with its size before and after. `registry` parses files for `signature-only`.
+ */
+export function assembleContext(input, budget, registry) {
+    const context = {
+        memory: [...(input.memory ?? [])],
+        ...(input.repoMap !== undefined && input.repoMap.trim().length > 0 ? { repoMap: input.repoMap } : {}),
+        files: (input.files ?? []).map((entry) => ({ ...entry, signaturesOnly: false })),
+        history: [...(input.history ?? [])],
+    };
+    const systemTokens = estimateContextTokens(input.systemPrompt ?? '');
+    const total = () => systemTokens + estimateContextTokens(renderPrompt(context, input.task));
+    const available = budget.maxTokens === undefined ? Number.POSITIVE_INFINITY : budget.maxTokens - budget.reserveTokens;
+    const over = () => total() - available;
+    const tokensBefore = total();
+    const cuts = [];
+    for (const strategy of budget.strategies) {
+        if (over() <= 0) {
+            break;
+        }
+        switch (strategy) {
+            case 'signature-only':
+                elideFileBodies(context, over, cuts, registry);
+                break;
+            case 'drop-oldest':
+                dropOldest(context, over, cuts);
+                break;
+            case 'summarize-middle':
+                summarizeMiddle(context, over, cuts);
+                break;
+        }
+    }
+    if (over() > 0) {
+        dropSections(context, over, cuts);
+    }
+    const tokensAfter = total();
+    return {
+        ...(input.systemPrompt !== undefined ? { systemPrompt: input.systemPrompt } : {}),
+        prompt: renderPrompt(context, input.task),
+        report: {
+            ...(budget.maxTokens !== undefined ? { maxTokens: budget.maxTokens } : {}),
+            reserveTokens: budget.reserveTokens,
+            tokensBefore,
+            tokensAfter,
+            fits: tokensAfter <= available,
+            cuts,
+        },
+    };
+}
+/** One line per cut, for warnings and the CLI. */
+export function describeContextCuts(report) {
+    return report.cuts.map((cut) => `Context: ${cut.source} ${cut.label} ${cut.strategy === 'dropped' ? 'left out' : `trimmed by ${cut.strategy}`} (${cut.tokensBefore} -> ${cut.tokensAfter} tokens).`);
+}
+function elideFileBodies(context, over, cuts, registry) {
+    for (const entry of [...context.files].sort((left, right) => right.content.length - left.content.length)) {
+        if (over() <= 0) {
+            return;
+        }
+        const indexed = extractCodeSymbols(entry.file, entry.content, registry);
+        if (indexed === undefined) {
+            continue;
+        }
+        const skeleton = renderSignatureSkeleton(entry.content, indexed.symbols, indexed.language);
+        if (skeleton.elidedBodies === 0 || skeleton.content.length >= entry.content.length) {
+            continue;
+        }
+        cuts.push(cut('file', entry.file, 'signature-only', entry.content, skeleton.content));
+        entry.content = skeleton.content;
+        entry.signaturesOnly = true;
+    }
+}
+function dropOldest(context, over, cuts) {
+    const entries = context.history.length;
+    const dropped = [];
+    // The latest turn is what the task follows on from; it goes last, in dropSections.
+    while (over() > 0 && context.history.length > 1) {
+        dropped.push(context.history.shift());
+    }
+    if (dropped.length > 0) {
+        cuts.push(cut('history', `oldest ${dropped.length} of ${entries} turns`, 'drop-oldest', dropped.map(renderTurn).join('\n'), ''));
+    }
+    while (over() > 0 && context.memory.length > 0) {
+        const hit = context.memory.pop();
+        cuts.push({ source: 'memory', label: hit.key, strategy: 'drop-oldest', tokensBefore: estimateContextTokens(hit.content), tokensAfter: 0 });
+    }
+}
+function summarizeMiddle(context, over, cuts) {
+    if (!context.history.some((entry) => entry.role === 'summary') && context.history.length > KEPT_FIRST_TURNS + KEPT_LAST_TURNS) {
+        const middle = context.history.slice(KEPT_FIRST_TURNS, -KEPT_LAST_TURNS);
+        const summary = { role: 'summary', content: middle.map((turn) => `- ${turn.role}: ${firstLine(turn.content)}`).join('\n') };
+        cuts.push(cut('history', `turns ${KEPT_FIRST_TURNS + 1}-${KEPT_FIRST_TURNS + middle.length} of ${context.history.length}`, 'summarize-middle', middle.map(renderTurn).join('\n'), renderTurn(summary)));
+        context.history = [...context.history.slice(0, KEPT_FIRST_TURNS), summary, ...context.history.slice(-KEPT_LAST_TURNS)];
+    }
+    // Then the longest texts, each cut only as much as still needed.
+    const sections = [
+        ...context.files.map((entry) => ({ source: 'file', label: entry.file, get: () => entry.content, set: (text) => { entry.content = text; } })),
+        ...context.memory.map((hit) => ({ source: 'memory', label: hit.key, get: () => hit.content, set: (text) => { hit.content = text; } })),
+        ...(context.repoMap !== undefined ? [{ source: 'repo-map', label: 'repo map', get: () => context.repoMap ?? '', set: (text) => { context.repoMap = text; } }] : []),
+    ];
+    for (const section of sections.sort((left, right) => right.get().length - left.get().length)) {
+        if (over() <= 0) {
+            return;
+        }
+        const text = section.get();
+        const keep = Math.max(Math.floor(text.length * MIN_KEPT_SHARE), text.length - over() * CHARS_PER_TOKEN - CUT_MARKER_CHARS);
+        const shortened = cutMiddle(text, keep);
+        if (shortened.length < text.length) {
+            cuts.push(cut(section.source, section.label, 'summarize-middle', text, shortened));
+            section.set(shortened);
+        }
+    }
+}
+function dropSections(context, over, cuts) {
+    while (over() > 0 && context.memory.length > 0) {
+        const hit = context.memory.pop();
+        cuts.push(cut('memory', hit.key, 'dropped', hit.content, ''));
+    }
+    if (over() > 0 && context.repoMap !== undefined) {
+        cuts.push(cut('repo-map', 'repo map', 'dropped', context.repoMap, ''));
+        delete context.repoMap;
+    }
+    if (over() > 0 && context.history.length > 0) {
+        cuts.push(cut('history', 'all turns', 'dropped', context.history.map(renderTurn).join('\n'), ''));
+        context.history = [];
+    }
+    for (const entry of [...context.files].sort((left, right) => right.content.length - left.content.length)) {
+        if (over() <= 0) {
+            return;
+        }
+        cuts.push(cut('file', entry.file, 'dropped', entry.content, ''));
+        context.files = context.files.filter((other) => other !== entry);
+    }
+}
+// Keeps the start and end of `text`, `keep` characters in all, on line boundaries where it can.
+function cutMiddle(text, keep) {
+    const headEnd = text.lastIndexOf('\n', Math.floor(keep / 2));
+    const head = text.slice(0, headEnd > 0 ? headEnd + 1 : Math.floor(keep / 2));
+    const tailStart = text.indexOf('\n', text.length - (keep - head.length));
+    const tail = text.slice(tailStart >= 0 && tailStart < text.length - 1 ? tailStart + 1 : text.length - (keep - head.length));
+    const omitted = text.slice(head.length, text.length - tail.length);
+    if (omitted.length === 0) {
+        return text;
+    }
+    return `${head}[... ${omitted.split('\n').length} lines left out to fit the context window ...]\n${tail}`;
+}
+function renderPrompt(context, task) {
+    const sections = [
+        context.memory.length > 0 ? `Relevant memory:\n${context.memory.map((hit) => `- ${hit.key}: ${hit.content}`).join('\n')}` : undefined,
+        context.repoMap !== undefined ? `Repository map:\n${context.repoMap.replace(/\n$/, '')}` : undefined,
+        context.files.length > 0 ? renderContextFiles(context.files) : undefined,
+        context.history.length > 0 ? `Conversation so far:\n${context.history.map(renderTurn).join('\n')}` : undefined,
+        task,
+    ];
+    return sections.filter((section) => section !== undefined && section.length > 0).join('\n\n');
+}
+/** Files fenced by extension, each under its path; files cut to signatures say so. */
+export function renderContextFiles(files) {
+    return `Attached files, with paths from the workspace root:\n\n${files.map((entry) => {
+        const fence = entry.content.includes('```') ? '````' : '```';
+        return `${entry.file}${entry.signaturesOnly === true ? ' (signatures only; bodies elided)' : ''}\n${fence}${extname(entry.file).slice(1)}\n${entry.content.replace(/\n$/, '')}\n${fence}`;
+    }).join('\n\n')}`;
+}
+function renderTurn(entry) {
+    switch (entry.role) {
+        case 'user':
+            return `User: ${entry.content.trim()}`;
+        case 'assistant':
+            return `Assistant: ${entry.content.trim()}`;
+        case 'summary':
+            return `[Earlier turns, one line each]\n${entry.content}`;
+    }
+}
+function firstLine(text) {
+    const line = text.trim().split('\n')[0] ?? '';
+    return line.length > SUMMARY_LINE_CHARS ? `${line.slice(0, SUMMARY_LINE_CHARS - 3)}...` : line;
+}
+function cut(source, label, strategy, before, after) {
+    return { source, label, strategy, tokensBefore: estimateContextTokens(before), tokensAfter: estimateContextTokens(after) };
+}
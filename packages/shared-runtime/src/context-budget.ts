@@ -0,0 +1,306 @@
+import { extname } from 'node:path';
+import { extractCodeSymbols, type LanguageRegistry } from './code-intel/index.js';
+import { renderSignatureSkeleton } from './code-intel/signatures.js';
+import { asRecord } from './records.js';
+
+/**
+ * How an over-long context is brought under the model's window, tried in the configured order:
+ * `signature-only` elides the bodies of attached code files, `drop-oldest` drops the oldest
+ * history turns and then the least relevant memory hits, and `summarize-middle` shortens the
+ * middle of the history to one line per turn and cuts the middle out of the longest sections.
+ */
+export type ContextTrimStrategy = 'drop-oldest' | 'summarize-middle' | 'signature-only';
+
+export const CONTEXT_TRIM_STRATEGIES: readonly ContextTrimStrategy[] = ['signature-only', 'drop-oldest', 'summarize-middle'];
+
+/** What goes into a prompt, in the order it is written, the task last. */
+export interface ContextInput {
+  systemPrompt?: string;
+  /** Memory hits, most relevant first. */
+  memory?: Array<{ key: string; content: string }>;
+  repoMap?: string;
+  /** Workspace files, with paths from the workspace root. */
+  files?: Array<{ file: string; content: string }>;
+  /** Earlier turns of the conversation, oldest first. */
+  history?: Array<{ role: 'user' | 'assistant'; content: string }>;
+  /** The request itself; it and the system prompt are never trimmed. */
+  task: string;
+}
+
+export interface ContextBudget {
+  /** The model's context window in tokens; unset, nothing is trimmed. */
+  maxTokens?: number;
+  /** Tokens left free for the reply. */
+  reserveTokens: number;
+  strategies: ContextTrimStrategy[];
+}
+
+/** One section that was shortened or left out. */
+export interface ContextCut {
+  source: 'memory' | 'repo-map' | 'file' | 'history';
+  /** The memory key, file path or turns the cut applies to. */
+  label: string;
+  /** `dropped` when the strategies were not enough and the section was left out whole. */
+  strategy: ContextTrimStrategy | 'dropped';
+  tokensBefore: number;
+  tokensAfter: number;
+}
+
+export interface ContextBudgetReport {
+  maxTokens?: number;
+  reserveTokens: number;
+  tokensBefore: number;
+  tokensAfter: number;
+  /** False when the system prompt and task alone are over the budget. */
+  fits: boolean;
+  cuts: ContextCut[];
+}
+
+export interface AssembledContext {
+  systemPrompt?: string;
+  prompt: string;
+  report: ContextBudgetReport;
+}
+
+const CHARS_PER_TOKEN = 4;
+const DEFAULT_RESERVE_TOKENS = 4096;
+// summarize-middle keeps this many turns at each end of the history word for word.
+const KEPT_FIRST_TURNS = 1;
+const KEPT_LAST_TURNS = 2;
+const SUMMARY_LINE_CHARS = 120;
+// A section cut by summarize-middle keeps at least this share of itself.
+const MIN_KEPT_SHARE = 0.25;
+// Room for the note that replaces the cut.
+const CUT_MARKER_CHARS = 64;
+
+/** The `providers.contextBudget` section. */
+export function readContextBudgetSettings(workspaceConfig: Record<string, unknown>): Omit<ContextBudget, 'maxTokens'> {
+  const section = asRecord(asRecord(workspaceConfig.providers)?.contextBudget) ?? {};
+  const strategies = Array.isArray(section.strategies)
+    ? section.strategies.filter((entry): entry is ContextTrimStrategy => (CONTEXT_TRIM_STRATEGIES as readonly unknown[]).includes(entry))
+    : [];
+  return {
+    reserveTokens: typeof section.reserveTokens === 'number' && section.reserveTokens >= 0 ? section.reserveTokens : DEFAULT_RESERVE_TOKENS,
+    strategies: Array.isArray(section.strategies) ? strategies : [...CONTEXT_TRIM_STRATEGIES],
+  };
+}
+
+export function estimateContextTokens(text: string): number {
+  return Math.ceil(text.length / CHARS_PER_TOKEN);
+}
+
+interface WorkingFile {
+  file: string;
+  content: string;
+  signaturesOnly: boolean;
+}
+
+// `summary` stands for the middle turns summarize-middle folded into one line each.
+interface HistoryEntry {
+  role: 'user' | 'assistant' | 'summary';
+  content: string;
+}
+
+interface WorkingContext {
+  memory: Array<{ key: string; content: string }>;
+  repoMap?: string;
+  files: WorkingFile[];
+  history: HistoryEntry[];
+}
+
+/**
+ * Writes the prompt for `input` and trims it to fit `budget`. The strategies run in their
+ * configured order, each only as far as needed; if the context is still too long, whole sections
+ * are dropped: memory hits, then the repo map, the history and the largest files. The report lists
+ * every cut with its size before and after. `registry` parses files for `signature-only`.
+ */
+export function assembleContext(input: ContextInput, budget: ContextBudget, registry?: LanguageRegistry): AssembledContext {
+  const context: WorkingContext = {
+    memory: [...(input.memory ?? [])],
+    ...(input.repoMap !== undefined && input.repoMap.trim().length > 0 ? { repoMap: input.repoMap } : {}),
+    files: (input.files ?? []).map((entry) => ({ ...entry, signaturesOnly: false })),
+    history: [...(input.history ?? [])],
+  };
+  const systemTokens = estimateContextTokens(input.systemPrompt ?? '');
+  const total = () => systemTokens + estimateContextTokens(renderPrompt(context, input.task));
+  const available = budget.maxTokens === undefined ? Number.POSITIVE_INFINITY : budget.maxTokens - budget.reserveTokens;
+  const over = () => total() - available;
+  const tokensBefore = total();
+  const cuts: ContextCut[] = [];
+
+  for (const strategy of budget.strategies) {
+    if (over() <= 0) {
+      break;
+    }
+    switch (strategy) {
+      case 'signature-only':
+        elideFileBodies(context, over, cuts, registry);
+        break;
+      case 'drop-oldest':
+        dropOldest(context, over, cuts);
+        break;
+      case 'summarize-middle':
+        summarizeMiddle(context, over, cuts);
+        break;
+    }
+  }
+  if (over() > 0) {
+    dropSections(context, over, cuts);
+  }
+
+  const tokensAfter = total();
+  return {
+    ...(input.systemPrompt !== undefined ? { systemPrompt: input.systemPrompt } : {}),
+    prompt: renderPrompt(context, input.task),
+    report: {
+      ...(budget.maxTokens !== undefined ? { maxTokens: budget.maxTokens } : {}),
+      reserveTokens: budget.reserveTokens,
+      tokensBefore,
+      tokensAfter,
+      fits: tokensAfter <= available,
+      cuts,
+    },
+  };
+}
+
+/** One line per cut, for warnings and the CLI. */
+export function describeContextCuts(report: ContextBudgetReport): string[] {
+  return report.cuts.map((cut) => `Context: ${cut.source} ${cut.label} ${cut.strategy === 'dropped' ? 'left out' : `trimmed by ${cut.strategy}`} (${cut.tokensBefore} -> ${cut.tokensAfter} tokens).`);
+}
+
+function elideFileBodies(context: WorkingContext, over: () => number, cuts: ContextCut[], registry: LanguageRegistry | undefined): void {
+  for (const entry of [...context.files].sort((left, right) => right.content.length - left.content.length)) {
+    if (over() <= 0) {
+      return;
+    }
+    const indexed = extractCodeSymbols(entry.file, entry.content, registry);
+    if (indexed === undefined) {
+      continue;
+    }
+    const skeleton = renderSignatureSkeleton(entry.content, indexed.symbols, indexed.language);
+    if (skeleton.elidedBodies === 0 || skeleton.content.length >= entry.content.length) {
+      continue;
+    }
+    cuts.push(cut('file', entry.file, 'signature-only', entry.content, skeleton.content));
+    entry.content = skeleton.content;
+    entry.signaturesOnly = true;
+  }
+}
+
+function dropOldest(context: WorkingContext, over: () => number, cuts: ContextCut[]): void {
+  const entries = context.history.length;
+  const dropped: HistoryEntry[] = [];
+  // The latest turn is what the task follows on from; it goes last, in dropSections.
+  while (over() > 0 && context.history.length > 1) {
+    dropped.push(context.history.shift()!);
+  }
+  if (dropped.length > 0) {
+    cuts.push(cut('history', `oldest ${dropped.length} of ${entries} turns`, 'drop-oldest', dropped.map(renderTurn).join('\n'), ''));
+  }
+  while (over() > 0 && context.memory.length > 0) {
+    const hit = context.memory.pop()!;
+    cuts.push({ source: 'memory', label: hit.key, strategy: 'drop-oldest', tokensBefore: estimateContextTokens(hit.content), tokensAfter: 0 });
+  }
+}
+
+function summarizeMiddle(context: WorkingContext, over: () => number, cuts: ContextCut[]): void {
+  if (!context.history.some((entry) => entry.role === 'summary') && context.history.length > KEPT_FIRST_TURNS + KEPT_LAST_TURNS) {
+    const middle = context.history.slice(KEPT_FIRST_TURNS, -KEPT_LAST_TURNS);
+    const summary: HistoryEntry = { role: 'summary', content: middle.map((turn) => `- ${turn.role}: ${firstLine(turn.content)}`).join('\n') };
+    cuts.push(cut('history', `turns ${KEPT_FIRST_TURNS + 1}-${KEPT_FIRST_TURNS + middle.length} of ${context.history.length}`, 'summarize-middle', middle.map(renderTurn).join('\n'), renderTurn(summary)));
+    context.history = [...context.history.slice(0, KEPT_FIRST_TURNS), summary, ...context.history.slice(-KEPT_LAST_TURNS)];
+  }
+
+  // Then the longest texts, each cut only as much as still needed.
+  const sections: Array<{ source: ContextCut['source']; label: string; get: () => string; set: (text: string) => void }> = [
+    ...context.files.map((entry) => ({ source: 'file' as const, label: entry.file, get: () => entry.content, set: (text: string) => { entry.content = text; } })),
+    ...context.memory.map((hit) => ({ source: 'memory' as const, label: hit.key, get: () => hit.content, set: (text: string) => { hit.content = text; } })),
+    ...(context.repoMap !== undefined ? [{ source: 'repo-map' as const, label: 'repo map', get: () => context.repoMap ?? '', set: (text: string) => { context.repoMap = text; } }] : []),
+  ];
+  for (const section of sections.sort((left, right) => right.get().length - left.get().length)) {
+    if (over() <= 0) {
+      return;
+    }
+    const text = section.get();
+    const keep = Math.max(Math.floor(text.length * MIN_KEPT_SHARE), text.length - over() * CHARS_PER_TOKEN - CUT_MARKER_CHARS);
+    const shortened = cutMiddle(text, keep);
+    if (shortened.length < text.length) {
+      cuts.push(cut(section.source, section.label, 'summarize-middle', text, shortened));
+      section.set(shortened);
+    }
+  }
+}
+
+function dropSections(context: WorkingContext, over: () => number, cuts: ContextCut[]): void {
+  while (over() > 0 && context.memory.length > 0) {
+    const hit = context.memory.pop()!;
+    cuts.push(cut('memory', hit.key, 'dropped', hit.content, ''));
+  }
+  if (over() > 0 && context.repoMap !== undefined) {
+    cuts.push(cut('repo-map', 'repo map', 'dropped', context.repoMap, ''));
+    delete context.repoMap;
+  }
+  if (over() > 0 && context.history.length > 0) {
+    cuts.push(cut('history', 'all turns', 'dropped', context.history.map(renderTurn).join('\n'), ''));
+    context.history = [];
+  }
+  for (const entry of [...context.files].sort((left, right) => right.content.length - left.content.length)) {
+    if (over() <= 0) {
+      return;
+    }
+    cuts.push(cut('file', entry.file, 'dropped', entry.content, ''));
+    context.files = context.files.filter((other) => other !== entry);
+  }
+}
+
+// Keeps the start and end of `text`, `keep` characters in all, on line boundaries where it can.
+function cutMiddle(text: string, keep: number): string {
+  const headEnd = text.lastIndexOf('\n', Math.floor(keep / 2));
+  const head = text.slice(0, headEnd > 0 ? headEnd + 1 : Math.floor(keep / 2));
+  const tailStart = text.indexOf('\n', text.length - (keep - head.length));
+  const tail = text.slice(tailStart >= 0 && tailStart < text.length - 1 ? tailStart + 1 : text.length - (keep - head.length));
+  const omitted = text.slice(head.length, text.length - tail.length);
+  if (omitted.length === 0) {
+    return text;
+  }
+  return `${head}[... ${omitted.split('\n').length} lines left out to fit the context window ...]\n${tail}`;
+}
+
+function renderPrompt(context: WorkingContext, task: string): string {
+  const sections = [
+    context.memory.length > 0 ? `Relevant memory:\n${context.memory.map((hit) => `- ${hit.key}: ${hit.content}`).join('\n')}` : undefined,
+    context.repoMap !== undefined ? `Repository map:\n${context.repoMap.replace(/\n$/, '')}` : undefined,
+    context.files.length > 0 ? renderContextFiles(context.files) : undefined,
+    context.history.length > 0 ? `Conversation so far:\n${context.history.map(renderTurn).join('\n')}` : undefined,
+    task,
+  ];
+  return sections.filter((section): section is string => section !== undefined && section.length > 0).join('\n\n');
+}
+
+/** Files fenced by extension, each under its path; files cut to signatures say so. */
+export function renderContextFiles(files: Array<{ file: string; content: string; signaturesOnly?: boolean }>): string {
+  return `Attached files, with paths from the workspace root:\n\n${files.map((entry) => {
+    const fence = entry.content.includes('```') ? '````' : '```';
+    return `${entry.file}${entry.signaturesOnly === true ? ' (signatures only; bodies elided)' : ''}\n${fence}${extname(entry.file).slice(1)}\n${entry.content.replace(/\n$/, '')}\n${fence}`;
+  }).join('\n\n')}`;
+}
+
+function renderTurn(entry: HistoryEntry): string {
+  switch (entry.role) {
+    case 'user':
+      return `User: ${entry.content.trim()}`;
+    case 'assistant':
+      return `Assistant: ${entry.content.trim()}`;
+    case 'summary':
+      return `[Earlier turns, one line each]\n${entry.content}`;
+  }
+}
+
+function firstLine(text: string): string {
+  const line = text.trim().split('\n')[0] ?? '';
+  return line.length > SUMMARY_LINE_CHARS ? `${line.slice(0, SUMMARY_LINE_CHARS - 3)}...` : line;
+}
+
+function cut(source: ContextCut['source'], label: string, strategy: ContextCut['strategy'], before: string, after: string): ContextCut {
+  return { source, label, strategy, tokensBefore: estimateContextTokens(before), tokensAfter: estimateContextTokens(after) };
+}
@@ -0,0 +1,276 @@
+import { mkdir, readFile, readdir, stat, writeFile } from 'node:fs/promises';
+import { arch, platform, release, type as osType } from 'node:os';
+import { basename, dirname, join, resolve } from 'node:path';
+import { gzipSync } from 'node:zlib';
+import type { TraceRecord, TraceStore } from '@defai.digital/trace-store';
+import { loadLayeredConfig } from './config-layers.js';
+import { scanSecrets } from './git-hooks.js';
+import type { createProviderBridge, ProviderResolutionDetails } from './provider-bridge.js';
+import { asRecord } from './records.js';
+
+export interface RuntimeDebugBundleRequest {
+  basePath: string;
+  traceId?: string;
+  outputPath?: string;
+  cliVersion?: string;
+  env?: NodeJS.ProcessEnv;
+}
+
+export interface RuntimeDebugBundleResponse {
+  bundlePath: string;
+  traceId?: string;
+  entries: string[];
+  bytes: number;
+  warnings: string[];
+}
+
+interface BundleEntry {
+  path: string;
+  content: string;
+}
+
+const REDACTED = '[REDACTED]';
+const SENSITIVE_KEY_PATTERN = /(?:api[-_]?key|memory[-_]?key|token|secret|password|passwd|credential|authorization|cookie|private[-_]?key)/i;
+const DEFAULT_PROVIDERS = ['claude', 'gemini', 'codex', 'grok'];
+const MAX_LOG_BYTES = 256 * 1024;
+const MAX_LOG_FILES = 20;
+
+export async function createDebugBundle(
+  traceStore: TraceStore,
+  providerBridge: ReturnType<typeof createProviderBridge>,
+  request: RuntimeDebugBundleRequest,
+): Promise<RuntimeDebugBundleResponse> {
+  const createdAt = new Date();
+  const stamp = createdAt.toISOString().replace(/[:.]/g, '-');
+  const root = `ax-debug-${stamp}`;
+  const env = request.env ?? process.env;
+  const automatosxDir = join(request.basePath, '.automatosx');
+  const warnings: string[] = [];
+  const entries: BundleEntry[] = [];
+
+  // The config every layer adds up to, which is what the run saw, and where each part came from.
+  const layered = await loadLayeredConfig(request.basePath, env);
+  if (!layered.layers.some((layer) => layer.layer === 'project' && layer.error === undefined)) {
+    warnings.push('Workspace config .automatosx/config.json is missing or invalid.');
+  }
+  for (const layer of layered.layers.filter((entry) => entry.error !== undefined && entry.layer !== 'project')) {
+    warnings.push(`Config ${layer.source} is invalid: ${layer.error}`);
+  }
+  entries.push(jsonEntry('config.json', redactValue(layered.config)));
+  entries.push(jsonEntry('config-layers.json', layered.layers.map((layer) => ({ layer: layer.layer, source: layer.source, ...(layer.error !== undefined ? { error: layer.error } : {}) }))));
+
+  const providerSummary = await readJson(join(automatosxDir, 'providers.json'));
+  if (providerSummary !== undefined) {
+    entries.push(jsonEntry('providers-summary.json', redactValue(providerSummary)));
+  }
+
+  const trace = await selectTrace(traceStore, request.traceId);
+  if (trace !== undefined) {
+    const related = (await traceStore.listTraces())
+      .filter((candidate) => candidate.metadata?.parentTraceId === trace.traceId);
+    entries.push(jsonEntry(`traces/${trace.traceId}.json`, redactValue(trace)));
+    for (const child of related) {
+      entries.push(jsonEntry(`traces/${child.traceId}.json`, redactValue(child)));
+    }
+  } else if (request.traceId !== undefined) {
+    warnings.push(`Trace "${request.traceId}" was not found.`);
+  } else {
+    warnings.push('No failed trace found; bundle contains no trace records.');
+  }
+
+  const providers = collectProviderIds(layered.config, trace);
+  const resolutions: Array<ProviderResolutionDetails | { provider: string; error: string }> = [];
+  for (const provider of providers) {
+    try {
+      resolutions.push(await providerBridge.describeResolution(provider));
+    } catch (error) {
+      resolutions.push({ provider, error: error instanceof Error ? error.message : String(error) });
+    }
+  }
+  entries.push(jsonEntry('providers.json', redactValue(resolutions)));
+
+  entries.push(jsonEntry('platform.json', {
+    createdAt: createdAt.toISOString(),
+    cliVersion: request.cliVersion,
+    os: {
+      platform: platform(),
+      type: osType(),
+      release: release(),
+      arch: arch(),
+    },
+    node: {
+      version: process.version,
+      execPath: process.execPath,
+    },
+    basePath: resolve(request.basePath),
+    shell: env.SHELL ?? env.ComSpec,
+    path: (env.PATH ?? env.Path ?? '').split(platform() === 'win32' ? ';' : ':').filter((entry) => entry.length > 0),
+    environment: redactValue(Object.fromEntries(
+      Object.entries(env).filter(([key]) => key.startsWith('AUTOMATOSX_')),
+    )),
+  }));
+
+  for (const log of await collectLogs(join(automatosxDir, 'logs'))) {
+    entries.push(log);
+  }
+
+  const manifest = {
+    createdAt: createdAt.toISOString(),
+    traceId: trace?.traceId,
+    warnings,
+    entries: ['manifest.json', ...entries.map((entry) => entry.path)],
+  };
+  entries.unshift(jsonEntry('manifest.json', manifest));
+
+  const bundlePath = request.outputPath !== undefined
+    ? resolve(request.basePath, request.outputPath)
+    : join(automatosxDir, 'debug', `${root}.tar.gz`);
+  const archive = gzipSync(createTarArchive(entries.map((entry) => ({
+    path: `${root}/${entry.path}`,
+    content: entry.content,
+  })), createdAt));
+  await mkdir(dirname(bundlePath), { recursive: true });
+  await writeFile(bundlePath, archive);
+
+  return {
+    bundlePath,
+    traceId: trace?.traceId,
+    entries: manifest.entries,
+    bytes: archive.length,
+    warnings,
+  };
+}
+
+export function redactValue(value: unknown): unknown {
+  if (Array.isArray(value)) {
+    return value.map((entry) => redactValue(entry));
+  }
+  if (typeof value === 'object' && value !== null) {
+    return Object.fromEntries(Object.entries(value).map(([key, entry]) => [
+      key,
+      SENSITIVE_KEY_PATTERN.test(key) && entry !== undefined && entry !== null && typeof entry !== 'object'
+        ? REDACTED
+        : redactValue(entry),
+    ]));
+  }
+  if (typeof value === 'string' && scanSecrets('value', value).length > 0) {
+    return REDACTED;
+  }
+  return value;
+}
+
+async function selectTrace(traceStore: TraceStore, traceId: string | undefined): Promise<TraceRecord | undefined> {
+  if (traceId !== undefined) {
+    return traceStore.getTrace(traceId);
+  }
+  const traces = await traceStore.listTraces();
+  return traces.find((trace) => trace.status === 'failed');
+}
+
+function collectProviderIds(config: Record<string, unknown> | undefined, trace: TraceRecord | undefined): string[] {
+  const providers = new Set(DEFAULT_PROVIDERS);
+  const providerConfig = asRecord(config?.providers);
+  for (const providerId of Object.keys(asRecord(providerConfig?.executors) ?? {})) {
+    providers.add(providerId);
+  }
+  if (typeof providerConfig?.default === 'string') {
+    providers.add(providerConfig.default);
+  }
+  for (const candidate of [trace?.input?.provider, trace?.metadata?.provider]) {
+    if (typeof candidate === 'string' && candidate.length > 0) {
+      providers.add(candidate);
+    }
+  }
+  return Array.from(providers);
+}
+
+async function collectLogs(logDir: string): Promise<BundleEntry[]> {
+  let names: string[];
+  try {
+    names = (await readdir(logDir)).sort();
+  } catch {
+    return [];
+  }
+
+  const logs: BundleEntry[] = [];
+  for (const name of names.slice(-MAX_LOG_FILES)) {
+    const filePath = join(logDir, name);
+    try {
+      const stats = await stat(filePath);
+      if (!stats.isFile()) {
+        continue;
+      }
+      const content = await readFile(filePath, 'utf8');
+      const tail = content.length > MAX_LOG_BYTES ? content.slice(-MAX_LOG_BYTES) : content;
+      logs.push({
+        path: `logs/${basename(name)}`,
+        content: tail.split('\n').map((line) => (scanSecrets(name, line).length > 0 ? REDACTED : line)).join('\n'),
+      });
+    } catch {
+      continue;
+    }
+  }
+  return logs;
+}
+
+async function readJson(filePath: string): Promise<Record<string, unknown> | undefined> {
+  try {
+    return asRecord(JSON.parse(await readFile(filePath, 'utf8')));
+  } catch {
+    return undefined;
+  }
+}
+
+function jsonEntry(path: string, value: unknown): BundleEntry {
+  return {
+    path,
+    content: `${JSON.stringify(value, null, 2)}\n`,
+  };
+}
+
+function createTarArchive(entries: BundleEntry[], modifiedAt: Date): Buffer {
+  const blocks: Buffer[] = [];
+  const mtime = Math.floor(modifiedAt.getTime() / 1000);
+
+  for (const entry of entries) {
+    const content = Buffer.from(entry.content, 'utf8');
+    const header = Buffer.alloc(512);
+    const { name, prefix } = splitTarPath(entry.path);
+    header.write(name, 0, 100, 'utf8');
+    header.write('0000644\0', 100, 8, 'ascii');
+    header.write('0000000\0', 108, 8, 'ascii');
+    header.write('0000000\0', 116, 8, 'ascii');
+    header.write(`${content.length.toString(8).padStart(11, '0')}\0`, 124, 12, 'ascii');
+    header.write(`${mtime.toString(8).padStart(11, '0')}\0`, 136, 12, 'ascii');
+    header.write('        ', 148, 8, 'ascii');
+    header.write('0', 156, 1, 'ascii');
+    header.write('ustar\0', 257, 6, 'ascii');
+    header.write('00', 263, 2, 'ascii');
+    header.write(prefix, 345, 155, 'utf8');
+    let checksum = 0;
+    for (const byte of header) {
+      checksum += byte;
+    }
+    header.write(`${checksum.toString(8).padStart(6, '0')}\0 `, 148, 8, 'ascii');
+
+    blocks.push(header, content);
+    const padding = (512 - (content.length % 512)) % 512;
+    if (padding > 0) {
+      blocks.push(Buffer.alloc(padding));
+    }
+  }
+
+  blocks.push(Buffer.alloc(1024));
+  return Buffer.concat(blocks);
+}
+
+function splitTarPath(path: string): { name: string; prefix: string } {
+  if (Buffer.byteLength(path) <= 100) {
+    return { name: path, prefix: '' };
+  }
+  const separator = path.lastIndexOf('/', path.length - 2);
+  return {
+    name: path.slice(separator + 1),
+    prefix: path.slice(0, separator),
+  };
+}
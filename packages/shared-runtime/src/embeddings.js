@@ -0,0 +1,179 @@
+import { createHash } from 'node:crypto';
+import { chunkCodeIndex } from './code-intel/chunks.js';
+import { readCodeIndex } from './code-intel/index.js';
+import { readCodeIntelConfig } from './code-intel/registry.js';
+export const EMBEDDING_BACKENDS = ['openai', 'ollama', 'onnx'];
+/** Memory namespace holding one entry per embedded code chunk, keyed by the chunk id. */
+export const EMBEDDINGS_NAMESPACE = 'embeddings';
+const DEFAULT_MODELS = {
+    openai: 'text-embedding-3-small',
+    ollama: 'nomic-embed-text',
+    onnx: 'Xenova/bge-small-en-v1.5',
+};
+const TRANSFORMERS_MODULE = '@huggingface/transformers';
+const DEFAULT_BATCH_SIZE = 32;
+// Keeps a chunk of long lines under every backend's input limit.
+const MAX_INPUT_CHARS = 8000;
+/**
+ * An embedder for the OpenAI embeddings API (`OPENAI_API_KEY`), an Ollama server (`OLLAMA_HOST`,
+ * default `http://127.0.0.1:11434`), or an ONNX model run locally through the optional
+ * `@huggingface/transformers` package, downloaded from the Hugging Face hub on first use.
+ */
+export function createEmbedder(config, options = {}) {
+    const env = options.env ?? process.env;
+    const fetchJson = async (name, url, headers, body) => {
+        let response;
+        try {
+            response = await (options.fetch ?? fetch)(url, {
+                method: 'POST',
+                headers: { 'content-type': 'application/json', ...headers },
+                body: JSON.stringify(body),
+            });
+        }
+        catch (error) {
+            throw new Error(`Could not reach ${name} at ${url}: ${error instanceof Error ? error.message : String(error)}`);
+        }
+        if (!response.ok) {
+            throw new Error(`${name} embeddings request failed (${response.status}): ${(await response.text()).slice(0, 200)}`);
+        }
+        return response.json();
+    };
+    const model = config.model ?? DEFAULT_MODELS[config.backend];
+    switch (config.backend) {
+        case 'openai': {
+            const apiKey = env.OPENAI_API_KEY;
+            if (apiKey === undefined || apiKey.length === 0) {
+                throw new Error('OpenAI embeddings need an API key. Set OPENAI_API_KEY.');
+            }
+            const baseUrl = (config.baseUrl ?? env.OPENAI_BASE_URL ?? 'https://api.openai.com/v1').replace(/\/+$/, '');
+            return {
+                backend: 'openai',
+                model,
+                async embed(texts) {
+                    const body = await fetchJson('OpenAI', `${baseUrl}/embeddings`, { authorization: `Bearer ${apiKey}` }, { model, input: texts });
+                    return [...body.data ?? []].sort((left, right) => left.index - right.index).map((item) => item.embedding);
+                },
+            };
+        }
+        case 'ollama': {
+            const baseUrl = (config.baseUrl ?? env.OLLAMA_HOST ?? 'http://127.0.0.1:11434').replace(/\/+$/, '');
+            return {
+                backend: 'ollama',
+                model,
+                async embed(texts) {
+                    const body = await fetchJson('Ollama', `${baseUrl}/api/embed`, {}, { model, input: texts });
+                    return body.embeddings ?? [];
+                },
+            };
+        }
+        case 'onnx': {
+            let extractor;
+            return {
+                backend: 'onnx',
+                model,
+                async embed(texts) {
+                    extractor ??= loadFeatureExtractor(model);
+                    // BGE models embed with the [CLS] token; sentence-transformers models average every token.
+                    const output = await (await extractor)(texts, { pooling: /bge/i.test(model) ? 'cls' : 'mean', normalize: true });
+                    return output.tolist();
+                },
+            };
+        }
+    }
+}
+async function loadFeatureExtractor(model) {
+    let transformers;
+    try {
+        transformers = await import(TRANSFORMERS_MODULE);
+    }
+    catch {
+        throw new Error(`Local ONNX embeddings need the optional "${TRANSFORMERS_MODULE}" package (npm install ${TRANSFORMERS_MODULE}).`);
+    }
+    return transformers.pipeline('feature-extraction', model);
+}
+/** The `codeIntel.embeddings` section of `.automatosx/config.json`, when it names a known backend. */
+export async function readEmbedderConfig(basePath) {
+    const section = (await readCodeIntelConfig(basePath))?.embeddings;
+    if (typeof section !== 'object' || section === null) {
+        return undefined;
+    }
+    const { backend, model, baseUrl } = section;
+    if (!EMBEDDING_BACKENDS.includes(backend)) {
+        return undefined;
+    }
+    return {
+        backend: backend,
+        ...(typeof model === 'string' ? { model } : {}),
+        ...(typeof baseUrl === 'string' ? { baseUrl } : {}),
+    };
+}
+/**
+ * Embeds the code index chunk by chunk into the `embeddings` memory namespace, in batches.
+ * Chunks already embedded by the same model from the same text are skipped and entries for code
+ * that no longer exists are deleted, so re-running after an edit only embeds what changed.
+ */
+export async function embedCodeChunks(request) {
+    const snapshot = await readCodeIndex(request.basePath);
+    if (snapshot === undefined) {
+        throw new Error('No symbol index found. Run "ax code index" first.');
+    }
+    const { embedder, stateStore } = request;
+    const chunks = await chunkCodeIndex(request.basePath, snapshot, { paths: request.paths });
+    const stored = new Map((await stateStore.listMemory(EMBEDDINGS_NAMESPACE)).map((entry) => [entry.key, entry]));
+    const pending = chunks.flatMap((chunk) => {
+        const input = `${chunk.file}\n${chunk.text}`.slice(0, MAX_INPUT_CHARS);
+        const contentHash = createHash('sha256').update(input).digest('hex').slice(0, 16);
+        const previous = stored.get(chunk.id);
+        const value = previous?.value;
+        if (previous?.embedding !== undefined && value?.model === embedder.model && value.contentHash === contentHash) {
+            return [];
+        }
+        return [{ chunk, input, contentHash }];
+    });
+    let dimensions;
+    const batchSize = request.batchSize ?? DEFAULT_BATCH_SIZE;
+    for (let start = 0; start < pending.length; start += batchSize) {
+        const batch = pending.slice(start, start + batchSize);
+        const vectors = await embedder.embed(batch.map((item) => item.input));
+        if (vectors.length !== batch.length) {
+            throw new Error(`${embedder.backend} returned ${vectors.length} embeddings for ${batch.length} chunks.`);
+        }
+        for (const [index, { chunk, contentHash }] of batch.entries()) {
+            dimensions ??= vectors[index].length;
+            await stateStore.storeMemory({
+                key: chunk.id,
+                namespace: EMBEDDINGS_NAMESPACE,
+                value: {
+                    file: chunk.file,
+                    language: chunk.language,
+                    startLine: chunk.startLine,
+                    endLine: chunk.endLine,
+                    ...(chunk.symbol !== undefined ? { symbol: chunk.symbol } : {}),
+                    backend: embedder.backend,
+                    model: embedder.model,
+                    contentHash,
+                },
+                embedding: vectors[index],
+            });
+        }
+    }
+    const current = new Set(chunks.map((chunk) => chunk.id));
+    const scopes = (request.paths ?? []).map((path) => path.replace(/^\.\/|\/+$/g, '')).filter((path) => path.length > 0 && path !== '.');
+    let removed = 0;
+    for (const entry of stored.values()) {
+        const file = String(entry.value?.file ?? '');
+        const inScope = scopes.length === 0 || scopes.some((scope) => file === scope || file.startsWith(`${scope}/`));
+        if (inScope && !current.has(entry.key) && await stateStore.deleteMemory(entry.key, EMBEDDINGS_NAMESPACE)) {
+            removed += 1;
+        }
+    }
+    return {
+        backend: embedder.backend,
+        model: embedder.model,
+        ...(dimensions !== undefined ? { dimensions } : {}),
+        chunks: chunks.length,
+        embedded: pending.length,
+        unchanged: chunks.length - pending.length,
+        removed,
+    };
+}
@@ -0,0 +1,314 @@
+import { constants } from 'node:fs';
+import { access, readdir, rm, stat, writeFile } from 'node:fs/promises';
+import { homedir } from 'node:os';
+import { join, relative, sep } from 'node:path';
+import { loadWorkspaceLanguageRegistry } from './code-intel/registry.js';
+import { loadLayeredConfig, validateLayeredConfig } from './config-layers.js';
+import { formatConfigIssue } from './config-schema.js';
+import { collectProviderIds } from './maintenance.js';
+import { createProviderBridge, type ProviderResolutionDetails } from './provider-bridge.js';
+import { asRecord } from './records.js';
+
+export type EnvironmentCheckStatus = 'ok' | 'warn' | 'fail';
+
+export interface EnvironmentCheck {
+  id: string;
+  status: EnvironmentCheckStatus;
+  message: string;
+  /** What to do about a check that is not ok. */
+  fix?: string;
+}
+
+export interface RuntimeEnvironmentRequest {
+  basePath: string;
+  env?: NodeJS.ProcessEnv;
+  /** The platform whose PATH rules apply; Windows also finds `.cmd` and `.bat` shims. */
+  platform?: NodeJS.Platform;
+  /** Where provider CLIs keep their logins. */
+  homeDir?: string;
+}
+
+export interface RuntimeEnvironmentReport {
+  checks: EnvironmentCheck[];
+}
+
+interface ProviderCli {
+  command: string;
+  install: string;
+  /** Any one of these being set counts as logged in. */
+  authEnv: string[];
+  /** Relative to the home directory; where the CLI keeps the login it made. */
+  authFiles: string[];
+  login: string;
+}
+
+const PROVIDER_CLIS: Record<string, ProviderCli> = {
+  claude: {
+    command: 'claude',
+    install: 'npm install -g @anthropic-ai/claude-code',
+    authEnv: ['ANTHROPIC_API_KEY', 'CLAUDE_CODE_OAUTH_TOKEN'],
+    authFiles: ['.claude/.credentials.json', '.claude.json'],
+    login: 'Run "claude" once and log in, or set ANTHROPIC_API_KEY.',
+  },
+  gemini: {
+    command: 'gemini',
+    install: 'npm install -g @google/gemini-cli',
+    authEnv: ['GEMINI_API_KEY', 'GOOGLE_API_KEY', 'GOOGLE_APPLICATION_CREDENTIALS'],
+    authFiles: ['.gemini/oauth_creds.json'],
+    login: 'Run "gemini" once and sign in with Google, or set GEMINI_API_KEY.',
+  },
+  codex: {
+    command: 'codex',
+    install: 'npm install -g @openai/codex',
+    authEnv: ['OPENAI_API_KEY'],
+    authFiles: ['.codex/auth.json'],
+    login: 'Run "codex login", or set OPENAI_API_KEY.',
+  },
+  grok: {
+    command: 'ax-grok',
+    install: 'Install ax-cli from https://github.com/defai-digital/ax-cli',
+    authEnv: ['XAI_API_KEY', 'GROK_API_KEY'],
+    authFiles: [],
+    login: 'Set XAI_API_KEY.',
+  },
+};
+const DEFAULT_PATHEXT = '.COM;.EXE;.BAT;.CMD';
+const WINDOWS_SHIM = /\.(?:cmd|bat)$/i;
+const PROBE_FILE = '.ax-doctor-probe';
+
+/**
+ * Checks what the workspace needs from the machine: provider CLIs on the PATH and logged in, a
+ * config file that parses and has the shape the runtime reads, state directories it can write,
+ * and custom grammars that load. Every check that is not ok says how to fix it.
+ */
+export async function diagnoseEnvironment(request: RuntimeEnvironmentRequest): Promise<RuntimeEnvironmentReport> {
+  const env = request.env ?? process.env;
+  const platform = request.platform ?? process.platform;
+  const homeDir = request.homeDir ?? homedir();
+  const checks: EnvironmentCheck[] = [];
+
+  // Each config file is checked on its own, so the message names the one to correct.
+  const layered = await loadLayeredConfig(request.basePath, { ...env, HOME: homeDir });
+  for (const file of validateLayeredConfig(layered).files) {
+    const configPath = file.layer === 'project' ? relative(request.basePath, file.source).split(sep).join('/') : file.source;
+    const label = file.layer === 'project' ? 'Workspace' : file.layer === 'user' ? 'User' : 'System';
+    const issues = file.issues.map(formatConfigIssue);
+    checks.push(issues.length === 0
+      ? { id: 'config-schema', status: 'ok', message: `${label} config is valid (${configPath}).` }
+      : {
+        id: 'config-schema',
+        status: 'fail',
+        message: `${label} config is invalid (${configPath}): ${issues.join('; ')}.`,
+        fix: file.layer === 'project'
+          ? `Correct ${configPath} by hand, or move it aside and run "ax setup" to write a new one.`
+          : `Correct ${configPath} by hand; it applies to every project on this machine.`,
+      });
+  }
+  const config = layered.layers.length === 0 ? undefined : layered.config;
+
+  const providers = asRecord(config?.providers);
+  const defaultProvider = typeof providers?.default === 'string'
+    ? providers.default
+    : typeof config?.defaultProvider === 'string' ? config.defaultProvider : undefined;
+  const providerBridge = createProviderBridge({ basePath: request.basePath, env });
+  const requireReal = providerBridge.getExecutionMode() === 'require-real';
+  for (const provider of await collectProviderIds(request.basePath)) {
+    checks.push(...await checkProvider(provider, {
+      details: await providerBridge.describeResolution(provider),
+      isDefault: provider === defaultProvider,
+      requireReal,
+      env,
+      platform,
+      homeDir,
+    }));
+  }
+
+  checks.push(await checkStateDirs(request.basePath, config, platform));
+
+  const { warnings } = await loadWorkspaceLanguageRegistry(request.basePath, env);
+  const grammars = asRecord(config?.codeIntel)?.grammars;
+  const grammarCount = Array.isArray(grammars) ? grammars.length : 0;
+  checks.push(warnings.length === 0
+    ? {
+      id: 'grammars',
+      status: 'ok',
+      message: grammarCount === 0
+        ? 'No custom grammars are configured; the built-in languages need none.'
+        : `Custom grammars load (${grammarCount}).`,
+    }
+    : {
+      id: 'grammars',
+      status: 'warn',
+      message: `${warnings.length} of ${grammarCount} custom grammar${grammarCount === 1 ? '' : 's'} did not load: ${warnings.join(' ')}`,
+      fix: 'Install the package the message names, or correct the paths under codeIntel.grammars; files in those languages are skipped until then.',
+    });
+
+  return { checks };
+}
+
+/**
+ * Finds `command` on the PATH the way the platform's shell would. On Windows each PATHEXT
+ * extension is tried as well, so the `.cmd` and `.bat` shims npm installs are found.
+ */
+export async function findExecutable(
+  command: string,
+  env: NodeJS.ProcessEnv = process.env,
+  platform: NodeJS.Platform = process.platform,
+): Promise<string | undefined> {
+  const windows = platform === 'win32';
+  // Windows keeps the variables' names in whatever case they were set.
+  const lookup = (name: string) => windows
+    ? Object.entries(env).find(([key]) => key.toUpperCase() === name)?.[1]
+    : env[name];
+  const pathext = (lookup('PATHEXT') ?? DEFAULT_PATHEXT).split(';').filter((extension) => extension.length > 0).map((extension) => extension.toLowerCase());
+  // Without a PATHEXT extension a file is not a program on Windows, whatever else it is.
+  const extensions = windows && !pathext.some((extension) => command.toLowerCase().endsWith(extension)) ? pathext : [''];
+  const directories = command.includes('/') || (windows && command.includes('\\'))
+    ? ['']
+    : (lookup('PATH') ?? '').split(windows ? ';' : ':').filter((directory) => directory.length > 0);
+  for (const directory of directories) {
+    for (const extension of extensions) {
+      const candidate = directory.length === 0 ? `${command}${extension}` : join(directory, `${command}${extension}`);
+      if (await isExecutable(candidate, windows)) {
+        return candidate;
+      }
+    }
+  }
+  return undefined;
+}
+
+async function checkProvider(
+  provider: string,
+  context: {
+    details: ProviderResolutionDetails;
+    isDefault: boolean;
+    requireReal: boolean;
+    env: NodeJS.ProcessEnv;
+    platform: NodeJS.Platform;
+    homeDir: string;
+  },
+): Promise<EnvironmentCheck[]> {
+  const { details, env } = context;
+  const cli = PROVIDER_CLIS[provider];
+  if (details.api !== undefined) {
+    const checks: EnvironmentCheck[] = [{
+      id: `provider-cli:${provider}`,
+      status: 'ok',
+      message: `Provider "${provider}" calls the ${details.api} API; no CLI is needed.`,
+    }];
+    if (details.apiKeyEnv !== undefined) {
+      checks.push(details.apiKeySet === true
+        ? { id: `provider-auth:${provider}`, status: 'ok', message: `Provider "${provider}" has its API key (${details.apiKeyEnv}).` }
+        : {
+          id: `provider-auth:${provider}`,
+          status: 'fail',
+          message: `Provider "${provider}" calls the ${details.api} API, but ${details.apiKeyEnv} is not set.`,
+          fix: `Export ${details.apiKeyEnv} in the shell that runs ax, or set providers.executors.${provider}.apiKeyEnv to the variable that holds the key.`,
+        });
+    }
+    return checks;
+  }
+
+  const command = details.command ?? cli?.command;
+  if (command === undefined) {
+    return [{
+      id: `provider-cli:${provider}`,
+      status: context.isDefault ? 'fail' : 'warn',
+      message: `Provider "${provider}" has no executor.`,
+      fix: `Add providers.executors.${provider} to .automatosx/config.json with a "command" or an "api".`,
+    }];
+  }
+  const path = await findExecutable(command, env, context.platform);
+  if (path === undefined) {
+    const install = details.command === undefined && cli !== undefined
+      ? `${cli.install}.`
+      : `Install "${command}", or correct the command of providers.executors.${provider}.`;
+    return [{
+      id: `provider-cli:${provider}`,
+      status: context.isDefault && context.requireReal ? 'fail' : 'warn',
+      message: `Provider "${provider}" CLI "${command}" is not on PATH${context.isDefault ? '; it is the default provider' : ''}.`,
+      fix: `${install} If it is installed, add the directory it is in to PATH.`,
+    }];
+  }
+
+  const checks: EnvironmentCheck[] = [context.platform === 'win32' && WINDOWS_SHIM.test(path)
+    ? {
+      id: `provider-cli:${provider}`,
+      status: 'warn',
+      message: `Provider "${provider}" CLI "${command}" is a Windows shim (${path}), which Node cannot start without a shell.`,
+      fix: `Set providers.executors.${provider}.command to "node" and its args to the script the shim runs (the last line of ${path} names it).`,
+    }
+    : { id: `provider-cli:${provider}`, status: 'ok', message: `Provider "${provider}" CLI found (${path}).` }];
+
+  // A command of the workspace's own may log in some other way.
+  if (cli !== undefined && (details.command === undefined || details.command === cli.command)) {
+    checks.push(await checkProviderLogin(provider, cli, env, context.homeDir));
+  }
+  return checks;
+}
+
+async function checkProviderLogin(provider: string, cli: ProviderCli, env: NodeJS.ProcessEnv, homeDir: string): Promise<EnvironmentCheck> {
+  const variable = cli.authEnv.find((name) => (env[name] ?? '').trim().length > 0);
+  if (variable !== undefined) {
+    return { id: `provider-auth:${provider}`, status: 'ok', message: `Provider "${provider}" is authenticated (${variable}).` };
+  }
+  for (const file of cli.authFiles) {
+    if (await stat(join(homeDir, file)).then((entry) => entry.isFile(), () => false)) {
+      return { id: `provider-auth:${provider}`, status: 'ok', message: `Provider "${provider}" is logged in (~/${file}).` };
+    }
+  }
+  return {
+    id: `provider-auth:${provider}`,
+    status: 'warn',
+    message: `Provider "${provider}" CLI has no login: none of ${[...cli.authEnv, ...cli.authFiles.map((file) => `~/${file}`)].join(', ')} is set.`,
+    fix: cli.login,
+  };
+}
+
+async function checkStateDirs(basePath: string, config: Record<string, unknown> | undefined, platform: NodeJS.Platform): Promise<EnvironmentCheck> {
+  const stateDir = join(basePath, '.automatosx');
+  const entries = await readdir(stateDir, { withFileTypes: true }).catch(() => undefined);
+  if (entries === undefined) {
+    return { id: 'state-dirs', status: 'ok', message: 'No state directory yet; .automatosx is created on first use.' };
+  }
+  const directories = new Set(['.automatosx', ...entries.filter((entry) => entry.isDirectory()).map((entry) => `.automatosx/${entry.name}`)]);
+  for (const configured of [config?.runtimeStoreDir, config?.workflowArtifactDir]) {
+    if (typeof configured === 'string' && await stat(join(basePath, configured)).then((entry) => entry.isDirectory(), () => false)) {
+      directories.add(configured);
+    }
+  }
+  const readOnly: string[] = [];
+  for (const directory of directories) {
+    const probe = join(basePath, directory, PROBE_FILE);
+    try {
+      await writeFile(probe, '', 'utf8');
+      await rm(probe, { force: true });
+    } catch {
+      readOnly.push(directory);
+    }
+  }
+  if (readOnly.length === 0) {
+    return { id: 'state-dirs', status: 'ok', message: `State directories are writable (${directories.size}).` };
+  }
+  const owner = await stat(stateDir).then((entry) => entry.uid, () => undefined);
+  const ownedByOther = platform !== 'win32' && owner !== undefined && process.getuid !== undefined && owner !== process.getuid();
+  return {
+    id: 'state-dirs',
+    status: 'fail',
+    message: `State directories are not writable: ${readOnly.join(', ')}.`,
+    fix: platform === 'win32'
+      ? `Give your account write access: icacls .automatosx /grant "%USERNAME%":(OI)(CI)M /T`
+      : ownedByOther
+        ? 'They belong to another user, often after running ax with sudo: sudo chown -R "$(id -un)" .automatosx'
+        : 'Make them writable: chmod -R u+w .automatosx',
+  };
+}
+
+
+async function isExecutable(candidate: string, windows: boolean): Promise<boolean> {
+  if (!await stat(candidate).then((entry) => entry.isFile(), () => false)) {
+    return false;
+  }
+  // Windows has no execute bit; the extension decides.
+  return windows || access(candidate, constants.X_OK).then(() => true, () => false);
+}
@@ -0,0 +1,382 @@
+import { mkdir, readdir, readFile, rm, rmdir, stat, writeFile } from 'node:fs/promises';
+import { basename, dirname, extname, isAbsolute, join, normalize, sep } from 'node:path';
+import { parse as parseYaml } from 'yaml';
+import { parseCodeSource } from './code-intel/index.js';
+import { loadWorkspaceLanguageRegistry } from './code-intel/registry.js';
+import { runTestCommand } from './code-intel/test-impact.js';
+export const EVAL_SUITE_INVALID_CODE = 'EVAL_SUITE_INVALID';
+export const EVAL_RUNS_DIR = join('.automatosx', 'evals', 'runs');
+const EVAL_WORK_DIR = join('.automatosx', 'evals', 'work');
+const SUITE_FIELDS = new Set(['name', 'description', 'agent', 'agents', 'providers', 'cases']);
+const CASE_FIELDS = new Set(['id', 'task', 'input', 'fixtures', 'assertions', 'timeoutMs']);
+const ASSERTION_FIELDS = {
+    contains: ['value', 'file', 'ignoreCase'],
+    regex: ['pattern', 'flags', 'file'],
+    symbol: ['name', 'kind', 'file'],
+    command: ['command', 'timeoutMs'],
+    file: ['path'],
+};
+const CASE_ID_PATTERN = /^[A-Za-z0-9_-]+$/;
+const CODE_BLOCK_PATTERN = /^```[ \t]*([\w#+.-]*)[^\n]*\n([\s\S]*?)^```/gm;
+// Code block languages spelled as file extensions or other common names.
+const FENCE_LANGUAGES = {
+    rs: 'rust',
+    ts: 'typescript',
+    js: 'javascript',
+    py: 'python',
+    rb: 'ruby',
+    cs: 'csharp',
+    'c#': 'csharp',
+    sh: 'shell',
+    bash: 'shell',
+    tf: 'hcl',
+    terraform: 'hcl',
+    docker: 'dockerfile',
+};
+const MAX_DETAIL_LENGTH = 400;
+/**
+ * Reads an eval suite from YAML. Throws an error with the `EVAL_SUITE_INVALID` code, naming
+ * `source`, when a field is unknown or has the wrong type.
+ */
+export function parseEvalSuite(text, source = 'eval suite') {
+    let document;
+    try {
+        document = parseYaml(text);
+    }
+    catch (error) {
+        throw suiteError(`${source} is not valid YAML: ${error instanceof Error ? error.message : String(error)}`);
+    }
+    if (!isObject(document)) {
+        throw suiteError(`${source} has to be a mapping with "agents" and "cases".`);
+    }
+    const unknownField = Object.keys(document).find((field) => !SUITE_FIELDS.has(field));
+    if (unknownField !== undefined) {
+        throw suiteError(`${source} has an unknown field "${unknownField}"; fields are ${[...SUITE_FIELDS].join(', ')}.`);
+    }
+    if (document.name !== undefined && typeof document.name !== 'string') {
+        throw suiteError(`${source} has a "name" that is not a string.`);
+    }
+    const agents = readNames(document.agents ?? document.agent, `${source} "agents"`);
+    if (agents.length === 0) {
+        throw suiteError(`${source} needs an "agent" or a list of "agents" to run the cases with.`);
+    }
+    const providers = document.providers === undefined ? [] : readNames(document.providers, `${source} "providers"`);
+    if (!Array.isArray(document.cases) || document.cases.length === 0) {
+        throw suiteError(`${source} needs a non-empty list of "cases".`);
+    }
+    const cases = [];
+    for (const [index, value] of document.cases.entries()) {
+        const parsed = parseCase(value, source, index);
+        if (cases.some((other) => other.id === parsed.id)) {
+            throw suiteError(`${source} has more than one case "${parsed.id}".`);
+        }
+        cases.push(parsed);
+    }
+    return {
+        name: typeof document.name === 'string' ? document.name : basename(source, extname(source)),
+        ...(typeof document.description === 'string' ? { description: document.description } : {}),
+        agents,
+        providers,
+        cases,
+    };
+}
+function parseCase(value, source, index) {
+    if (!isObject(value)) {
+        throw suiteError(`${source} case ${index + 1} is not a mapping.`);
+    }
+    if (typeof value.id !== 'string' || !CASE_ID_PATTERN.test(value.id)) {
+        throw suiteError(`${source} case ${index + 1} needs an "id" of letters, digits, "-" and "_".`);
+    }
+    const where = `${source} case "${value.id}"`;
+    const unknownField = Object.keys(value).find((field) => !CASE_FIELDS.has(field));
+    if (unknownField !== undefined) {
+        throw suiteError(`${where} has an unknown field "${unknownField}"; fields are ${[...CASE_FIELDS].join(', ')}.`);
+    }
+    if (typeof value.task !== 'string' || value.task.trim().length === 0) {
+        throw suiteError(`${where} needs a "task".`);
+    }
+    if (value.input !== undefined && !isObject(value.input)) {
+        throw suiteError(`${where} has an "input" that is not a mapping.`);
+    }
+    const fixtures = value.fixtures ?? {};
+    if (!isObject(fixtures) || !Object.values(fixtures).every((content) => typeof content === 'string')) {
+        throw suiteError(`${where} has "fixtures" that are not a mapping of paths to file contents.`);
+    }
+    const escaping = Object.keys(fixtures).find((path) => !isInside(path));
+    if (escaping !== undefined) {
+        throw suiteError(`${where} has a fixture "${escaping}" outside the case's workspace.`);
+    }
+    if (!Array.isArray(value.assertions) || value.assertions.length === 0) {
+        throw suiteError(`${where} needs a non-empty list of "assertions".`);
+    }
+    if (value.timeoutMs !== undefined && !isPositiveInteger(value.timeoutMs)) {
+        throw suiteError(`${where} has a "timeoutMs" that is not a positive integer.`);
+    }
+    return {
+        id: value.id,
+        task: value.task,
+        ...(isObject(value.input) ? { input: value.input } : {}),
+        fixtures: fixtures,
+        assertions: value.assertions.map((assertion, position) => parseAssertion(assertion, `${where} assertion ${position + 1}`)),
+        ...(isPositiveInteger(value.timeoutMs) ? { timeoutMs: value.timeoutMs } : {}),
+    };
+}
+function parseAssertion(value, where) {
+    if (!isObject(value) || typeof value.type !== 'string' || !(value.type in ASSERTION_FIELDS)) {
+        throw suiteError(`${where} needs a "type", one of ${Object.keys(ASSERTION_FIELDS).join(', ')}.`);
+    }
+    const type = value.type;
+    const fields = new Set(['type', 'not', ...ASSERTION_FIELDS[type]]);
+    const unknownField = Object.keys(value).find((field) => !fields.has(field));
+    if (unknownField !== undefined) {
+        throw suiteError(`${where} has an unknown field "${unknownField}"; fields of a ${type} assertion are ${[...fields].join(', ')}.`);
+    }
+    const required = ASSERTION_FIELDS[type][0];
+    const main = value[required];
+    if (type === 'command'
+        ? !Array.isArray(main) || main.length === 0 || !main.every((part) => typeof part === 'string')
+        : typeof main !== 'string' || main.length === 0) {
+        throw suiteError(type === 'command'
+            ? `${where} needs a "command" as a list of the program and its arguments.`
+            : `${where} needs a "${required}".`);
+    }
+    for (const field of ['file', 'path']) {
+        if (typeof value[field] === 'string' && !isInside(value[field])) {
+            throw suiteError(`${where} has a "${field}" outside the case's workspace.`);
+        }
+    }
+    if (type === 'regex') {
+        try {
+            new RegExp(String(value.pattern), typeof value.flags === 'string' ? value.flags : undefined);
+        }
+        catch (error) {
+            throw suiteError(`${where} has a pattern that is not a regular expression: ${error instanceof Error ? error.message : String(error)}`);
+        }
+    }
+    return value;
+}
+/**
+ * Runs every case of a suite once per agent and provider, one after another. Each run gets a
+ * workspace of its own under `.automatosx/evals/work/`, holding the case's fixtures, which the
+ * agent is confined to and the assertions are checked in; it is removed afterwards. The report is
+ * saved to `.automatosx/evals/runs/<run-id>.json`, and each variant is compared with the suite's
+ * previous run.
+ */
+export async function runEvalSuite(suite, options, runtime) {
+    const startedAt = new Date();
+    const agents = options.agents !== undefined && options.agents.length > 0 ? options.agents : suite.agents;
+    const providers = options.providers !== undefined && options.providers.length > 0
+        ? options.providers
+        : suite.providers.length > 0 ? suite.providers : [undefined];
+    const unknownCase = options.caseIds?.find((caseId) => !suite.cases.some((entry) => entry.id === caseId));
+    if (unknownCase !== undefined) {
+        throw suiteError(`The suite "${suite.name}" has no case "${unknownCase}".`);
+    }
+    const cases = suite.cases.filter((entry) => options.caseIds === undefined || options.caseIds.length === 0 || options.caseIds.includes(entry.id));
+    const { registry } = await loadWorkspaceLanguageRegistry(options.basePath);
+    const baseline = await readPreviousRun(options.basePath, suite.name);
+    const results = [];
+    const workRoot = join(EVAL_WORK_DIR, options.runId);
+    try {
+        for (const entry of cases) {
+            for (const agentId of agents) {
+                for (const provider of providers) {
+                    const scope = join(workRoot, `${results.length + 1}-${entry.id}`);
+                    const result = await runCase(entry, { agentId, provider, scope, registry, options, runtime });
+                    results.push(result);
+                    options.onResult?.(result);
+                }
+            }
+        }
+    }
+    finally {
+        await rm(join(options.basePath, workRoot), { recursive: true, force: true });
+        await rmdir(join(options.basePath, EVAL_WORK_DIR)).catch(() => undefined);
+    }
+    const reportFile = join(EVAL_RUNS_DIR, `${options.runId}.json`);
+    const report = {
+        runId: options.runId,
+        suite: suite.name,
+        file: options.file,
+        startedAt: startedAt.toISOString(),
+        durationMs: Date.now() - startedAt.getTime(),
+        variants: summarize(results, agents, providers, baseline),
+        results,
+        ...(baseline !== undefined ? { baseline: { runId: baseline.runId, startedAt: baseline.startedAt } } : {}),
+        reportFile,
+    };
+    await mkdir(join(options.basePath, EVAL_RUNS_DIR), { recursive: true });
+    await writeFile(join(options.basePath, reportFile), `${JSON.stringify(report, null, 2)}\n`, 'utf8');
+    return report;
+}
+async function runCase(entry, context) {
+    const { agentId, provider, scope, options, runtime } = context;
+    const workspace = join(options.basePath, scope);
+    await mkdir(workspace, { recursive: true });
+    for (const [path, content] of Object.entries(entry.fixtures)) {
+        await mkdir(dirname(join(workspace, path)), { recursive: true });
+        await writeFile(join(workspace, path), content, 'utf8');
+    }
+    const variant = { caseId: entry.id, agentId, ...(provider !== undefined ? { provider } : {}) };
+    let run;
+    try {
+        run = await runtime.runAgent({
+            agentId,
+            task: entry.task,
+            basePath: options.basePath,
+            input: entry.input,
+            files: Object.keys(entry.fixtures).map((path) => join(scope, path)),
+            scope,
+            provider,
+            timeoutMs: entry.timeoutMs,
+            surface: options.surface,
+            signal: options.signal,
+        });
+    }
+    catch (error) {
+        const failure = error;
+        return { ...variant, passed: false, assertions: [], costUsd: 0, totalTokens: 0, latencyMs: 0, error: { code: failure.code, message: failure.message } };
+    }
+    const measured = {
+        traceId: run.traceId,
+        costUsd: await runtime.costOf(run.traceId),
+        totalTokens: run.usage?.totalTokens ?? 0,
+        latencyMs: run.latencyMs,
+    };
+    if (!run.success) {
+        return { ...variant, ...measured, passed: false, assertions: [], ...(run.error !== undefined ? { error: run.error } : {}) };
+    }
+    const assertions = [];
+    for (const assertion of entry.assertions) {
+        assertions.push(await checkAssertion(assertion, run.content, workspace, context.registry, options.signal));
+    }
+    return { ...variant, ...measured, passed: assertions.every((result) => result.passed), assertions };
+}
+async function checkAssertion(assertion, reply, workspace, registry, signal) {
+    const description = describeAssertion(assertion);
+    let outcome;
+    try {
+        outcome = await evaluate();
+    }
+    catch (error) {
+        return { assertion: description, passed: false, detail: error instanceof Error ? error.message : String(error) };
+    }
+    const passed = assertion.not === true ? !outcome.holds : outcome.holds;
+    return {
+        assertion: description,
+        passed,
+        ...(!passed ? { detail: assertion.not === true ? 'It held.' : outcome.detail ?? 'It did not hold.' } : {}),
+    };
+    async function evaluate() {
+        switch (assertion.type) {
+            case 'contains': {
+                const text = assertion.file === undefined ? reply : await readFile(join(workspace, assertion.file), 'utf8');
+                return { holds: assertion.ignoreCase === true ? text.toLowerCase().includes(assertion.value.toLowerCase()) : text.includes(assertion.value) };
+            }
+            case 'regex': {
+                const text = assertion.file === undefined ? reply : await readFile(join(workspace, assertion.file), 'utf8');
+                return { holds: new RegExp(assertion.pattern, assertion.flags).test(text) };
+            }
+            case 'symbol': {
+                const sources = assertion.file === undefined
+                    ? [...reply.matchAll(CODE_BLOCK_PATTERN)].map((match) => ({ language: languageOf(match[1]), content: match[2] }))
+                    : [{ file: assertion.file, content: await readFile(join(workspace, assertion.file), 'utf8') }];
+                const symbols = sources.flatMap((source) => {
+                    try {
+                        return parseCodeSource(source.content, { ...source, registry }).symbols;
+                    }
+                    catch {
+                        return [];
+                    }
+                });
+                const found = symbols.some((symbol) => symbol.name === assertion.name && (assertion.kind === undefined || symbol.kind === assertion.kind));
+                return { holds: found, detail: sources.length === 0 ? 'The reply has no code blocks.' : `Declared: ${symbols.map((symbol) => `${symbol.kind} ${symbol.name}`).join(', ') || 'nothing'}.` };
+            }
+            case 'command': {
+                const run = await runTestCommand(workspace, { command: assertion.command, timeoutMs: assertion.timeoutMs, signal });
+                return { holds: run.passed, detail: `Exit code ${run.exitCode}: ${run.output.trim().slice(-MAX_DETAIL_LENGTH)}` };
+            }
+            case 'file':
+                return { holds: await stat(join(workspace, assertion.path)).then((info) => info.isFile(), () => false) };
+        }
+    }
+}
+function describeAssertion(assertion) {
+    const where = 'file' in assertion && assertion.file !== undefined ? assertion.file : 'the reply';
+    const text = (() => {
+        switch (assertion.type) {
+            case 'contains':
+                return `${where} contains ${JSON.stringify(assertion.value)}`;
+            case 'regex':
+                return `${where} matches /${assertion.pattern}/${assertion.flags ?? ''}`;
+            case 'symbol':
+                return `${where} declares ${assertion.kind ?? 'symbol'} ${assertion.name}`;
+            case 'command':
+                return `\`${assertion.command.join(' ')}\` succeeds`;
+            case 'file':
+                return `${assertion.path} exists`;
+        }
+    })();
+    return assertion.not === true ? `not: ${text}` : text;
+}
+function summarize(results, agents, providers, baseline) {
+    return agents.flatMap((agentId) => providers.map((provider) => {
+        const runs = results.filter((result) => result.agentId === agentId && result.provider === provider);
+        const passed = runs.filter((result) => result.passed).length;
+        const before = baseline?.variants.find((variant) => variant.agentId === agentId && variant.provider === provider);
+        return {
+            agentId,
+            ...(provider !== undefined ? { provider } : {}),
+            cases: runs.length,
+            passed,
+            passRate: runs.length === 0 ? 0 : passed / runs.length,
+            costUsd: runs.reduce((total, result) => total + result.costUsd, 0),
+            totalTokens: runs.reduce((total, result) => total + result.totalTokens, 0),
+            averageLatencyMs: runs.length === 0 ? 0 : Math.round(runs.reduce((total, result) => total + result.latencyMs, 0) / runs.length),
+            ...(before !== undefined ? { baseline: { passRate: before.passRate, costUsd: before.costUsd } } : {}),
+        };
+    }));
+}
+/** The latest saved run of the suite. */
+async function readPreviousRun(basePath, suite) {
+    const dir = join(basePath, EVAL_RUNS_DIR);
+    const files = await readdir(dir).catch(() => []);
+    let latest;
+    for (const file of files.filter((name) => name.endsWith('.json'))) {
+        try {
+            const report = JSON.parse(await readFile(join(dir, file), 'utf8'));
+            if (report.suite === suite && Array.isArray(report.variants) && (latest === undefined || report.startedAt > latest.startedAt)) {
+                latest = report;
+            }
+        }
+        catch {
+            continue;
+        }
+    }
+    return latest;
+}
+function languageOf(fence) {
+    const tag = fence.toLowerCase();
+    return tag.length === 0 ? undefined : FENCE_LANGUAGES[tag] ?? tag;
+}
+function readNames(value, where) {
+    const names = typeof value === 'string' ? [value] : value ?? [];
+    if (!Array.isArray(names) || !names.every((name) => typeof name === 'string' && name.trim().length > 0)) {
+        throw suiteError(`${where} has to be a name or a list of them.`);
+    }
+    return names.map((name) => name.trim());
+}
+// A relative path that stays inside the directory it is relative to.
+function isInside(path) {
+    const normalized = normalize(path);
+    return !isAbsolute(path) && normalized !== '.' && normalized !== '..' && !normalized.startsWith(`..${sep}`);
+}
+function isObject(value) {
+    return typeof value === 'object' && value !== null && !Array.isArray(value);
+}
+function isPositiveInteger(value) {
+    return typeof value === 'number' && Number.isInteger(value) && value > 0;
+}
+function suiteError(message) {
+    return Object.assign(new Error(message), { code: EVAL_SUITE_INVALID_CODE });
+}
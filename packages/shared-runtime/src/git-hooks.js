@@ -0,0 +1,381 @@
+import { execFile } from 'node:child_process';
+import { chmod, mkdir, mkdtemp, readFile, rename, rm, writeFile } from 'node:fs/promises';
+import { tmpdir } from 'node:os';
+import { dirname, extname, join, resolve } from 'node:path';
+import { promisify } from 'node:util';
+import { detectCodeLanguage } from './code-intel/index.js';
+import { validateDockerfile } from './code-intel/dockerfile.js';
+import { asRecord } from './records.js';
+const execFileAsync = promisify(execFile);
+const HOOK_MARKER = '# ax-managed-hook: pre-commit';
+const DEFAULT_REVIEW_AGENT_ID = 'quality';
+const DEFAULT_REVIEW_TIME_BUDGET_MS = 30_000;
+const MAX_REVIEW_DIFF_LINES = 400;
+const JS_EXTENSIONS = new Set(['.js', '.mjs', '.cjs']);
+const SECRET_PATTERNS = [
+    { pattern: /\b(?:AKIA|ASIA)[0-9A-Z]{16}\b/, message: 'AWS access key id detected.' },
+    { pattern: /-----BEGIN (?:RSA |EC |DSA |OPENSSH |PGP )?PRIVATE KEY-----/, message: 'Private key block detected.' },
+    { pattern: /\bgh[pousr]_[A-Za-z0-9]{36,}\b/, message: 'GitHub token detected.' },
+    { pattern: /\bxox[abprs]-[A-Za-z0-9-]{10,}\b/, message: 'Slack token detected.' },
+    { pattern: /\bsk-(?:ant-|proj-)?[A-Za-z0-9_-]{20,}\b/, message: 'Provider API key detected.' },
+    { pattern: /(?:api[_-]?key|secret|token|password)\s*[:=]\s*['"`][^'"`\s]{8,}['"`]/i, message: 'Potential hardcoded secret detected.' },
+];
+const SECRET_ALLOW_MARKER = 'pragma: allowlist secret';
+export function resolvePreCommitConfig(config) {
+    const hooks = asRecord(config.hooks);
+    const preCommit = asRecord(hooks?.preCommit) ?? {};
+    const review = asRecord(preCommit.review) ?? {};
+    const focus = review.focus;
+    const timeBudgetMs = review.timeBudgetMs;
+    const agent = review.agent;
+    return {
+        mode: preCommit.mode === 'annotate' ? 'annotate' : 'block',
+        syntax: preCommit.syntax !== false,
+        secrets: preCommit.secrets !== false,
+        review: {
+            enabled: review.enabled === true,
+            agent: typeof agent === 'string' && agent.trim().length > 0 ? agent.trim() : DEFAULT_REVIEW_AGENT_ID,
+            focus: focus === 'security' || focus === 'correctness' || focus === 'maintainability' ? focus : 'all',
+            timeBudgetMs: typeof timeBudgetMs === 'number' && Number.isFinite(timeBudgetMs) && timeBudgetMs > 0
+                ? timeBudgetMs
+                : DEFAULT_REVIEW_TIME_BUDGET_MS,
+        },
+    };
+}
+export async function installPreCommitHook(request) {
+    const hookPath = join(await resolveHooksDir(request.basePath), 'pre-commit');
+    const existing = await readOptionalFile(hookPath);
+    let backupPath;
+    if (existing !== undefined && !existing.includes(HOOK_MARKER)) {
+        if (request.force !== true) {
+            throw new Error(`hook install failed: ${hookPath} already exists. Re-run with --force to back it up and replace it.`);
+        }
+        backupPath = `${hookPath}.ax-backup`;
+        await rename(hookPath, backupPath);
+    }
+    await mkdir(dirname(hookPath), { recursive: true });
+    await writeFile(hookPath, buildHookScript(), 'utf8');
+    await chmod(hookPath, 0o755);
+    return {
+        hook: 'pre-commit',
+        hookPath,
+        installed: true,
+        backupPath,
+    };
+}
+export async function uninstallPreCommitHook(request) {
+    const hookPath = join(await resolveHooksDir(request.basePath), 'pre-commit');
+    const existing = await readOptionalFile(hookPath);
+    if (existing === undefined || !existing.includes(HOOK_MARKER)) {
+        return { hook: 'pre-commit', hookPath, installed: false };
+    }
+    await rm(hookPath, { force: true });
+    const backupPath = `${hookPath}.ax-backup`;
+    if (await readOptionalFile(backupPath) !== undefined) {
+        await rename(backupPath, hookPath);
+    }
+    return { hook: 'pre-commit', hookPath, installed: false };
+}
+/**
+ * Checks the staged files: syntax and secrets locally, then, when enabled, a quick review by an
+ * agent of the staged diff, whose run is cancelled once its time budget is spent.
+ */
+export async function runPreCommitPipeline(request, runAgent) {
+    const startedAt = Date.now();
+    const { config } = request;
+    const basePath = await resolveRepoRoot(request.basePath);
+    const files = await listStagedFiles(basePath);
+    const issues = [];
+    const checks = {
+        syntax: config.syntax ? 'passed' : 'skipped',
+        secrets: config.secrets ? 'passed' : 'skipped',
+        review: config.review.enabled ? 'passed' : 'skipped',
+    };
+    for (const file of files) {
+        const content = await readStagedFile(basePath, file);
+        if (content === undefined) {
+            continue;
+        }
+        if (config.syntax) {
+            issues.push(...await checkSyntax(basePath, file, content));
+        }
+        if (config.secrets) {
+            issues.push(...scanSecrets(file, content));
+        }
+    }
+    if (config.syntax && issues.some((issue) => issue.check === 'syntax')) {
+        checks.syntax = 'failed';
+    }
+    if (config.secrets && issues.some((issue) => issue.check === 'secrets')) {
+        checks.secrets = 'failed';
+    }
+    if (config.review.enabled && files.length > 0) {
+        const review = await runQuickReview(basePath, files, config.review, runAgent, request.sessionId);
+        checks.review = review.status;
+        issues.push(...review.issues);
+    }
+    const passed = !issues.some((issue) => issue.severity === 'error');
+    const reportPath = join(request.basePath, '.automatosx', 'hooks', 'pre-commit.json');
+    const response = {
+        mode: config.mode,
+        passed,
+        blocked: !passed && config.mode === 'block',
+        files,
+        checks,
+        issues,
+        durationMs: Date.now() - startedAt,
+        reportPath,
+    };
+    await mkdir(dirname(reportPath), { recursive: true });
+    await writeFile(reportPath, `${JSON.stringify(response, null, 2)}\n`, 'utf8');
+    return response;
+}
+export function scanSecrets(file, content) {
+    const issues = [];
+    const lines = content.split('\n');
+    for (let index = 0; index < lines.length; index += 1) {
+        const line = lines[index] ?? '';
+        if (line.includes(SECRET_ALLOW_MARKER)) {
+            continue;
+        }
+        const match = SECRET_PATTERNS.find((entry) => entry.pattern.test(line));
+        if (match !== undefined) {
+            issues.push({
+                check: 'secrets',
+                severity: 'error',
+                file,
+                line: index + 1,
+                message: match.message,
+            });
+        }
+    }
+    return issues;
+}
+async function checkSyntax(basePath, file, content) {
+    const extension = extname(file).toLowerCase();
+    if (extension === '.json') {
+        try {
+            JSON.parse(content);
+            return [];
+        }
+        catch (error) {
+            return [syntaxIssue(file, error instanceof Error ? error.message : String(error))];
+        }
+    }
+    if (JS_EXTENSIONS.has(extension)) {
+        const moduleExtension = extension === '.js'
+            ? (await resolvePackageType(basePath, file)) === 'module' ? '.mjs' : '.cjs'
+            : extension;
+        const failure = await runCheckCommand('node', ['--check'], content, moduleExtension);
+        return failure === undefined ? [] : [syntaxIssue(file, failure)];
+    }
+    const language = detectCodeLanguage(file, content)?.language;
+    if (language === 'dockerfile') {
+        return validateDockerfile(content).map((issue) => syntaxIssue(file, issue.message, issue.line));
+    }
+    if (language === 'shell') {
+        const failure = await runCheckCommand('bash', ['-n'], content, '.sh');
+        return failure === undefined ? [] : [syntaxIssue(file, failure)];
+    }
+    return [];
+}
+function syntaxIssue(file, message, line) {
+    return {
+        check: 'syntax',
+        severity: 'error',
+        file,
+        line,
+        message,
+    };
+}
+async function runCheckCommand(command, args, content, extension) {
+    const tempDir = await mkdtemp(join(tmpdir(), 'ax-pre-commit-'));
+    const tempFile = join(tempDir, `staged${extension}`);
+    try {
+        await writeFile(tempFile, content, 'utf8');
+        await execFileAsync(command, [...args, tempFile], { timeout: 10_000 });
+        return undefined;
+    }
+    catch (error) {
+        const details = error;
+        if (details.code === 'ENOENT') {
+            return undefined;
+        }
+        const stderr = typeof details.stderr === 'string' ? details.stderr.split(tempFile).join('<staged>').trim() : '';
+        return stderr.length > 0 ? stderr.split('\n').slice(0, 5).join('\n') : details.message;
+    }
+    finally {
+        await rm(tempDir, { recursive: true, force: true });
+    }
+}
+async function resolvePackageType(basePath, file) {
+    const root = resolve(basePath);
+    let current = dirname(resolve(root, file));
+    while (current.startsWith(root)) {
+        const manifest = await readOptionalFile(join(current, 'package.json'));
+        if (manifest !== undefined) {
+            try {
+                const parsed = JSON.parse(manifest);
+                return typeof parsed.type === 'string' ? parsed.type : undefined;
+            }
+            catch {
+                return undefined;
+            }
+        }
+        const parent = dirname(current);
+        if (parent === current) {
+            break;
+        }
+        current = parent;
+    }
+    return undefined;
+}
+async function runQuickReview(basePath, files, review, runAgent, sessionId) {
+    const notice = (status, message) => ({
+        status,
+        issues: [{ check: 'review', severity: 'warning', file: '.', message }],
+    });
+    const { stdout: diff } = await execFileAsync('git', ['diff', '--cached', '--unified=3', '--no-color', '--no-ext-diff', '--', ...files], {
+        cwd: basePath,
+        maxBuffer: 1024 * 1024 * 16,
+    });
+    const controller = new AbortController();
+    const timer = setTimeout(() => controller.abort(), review.timeBudgetMs);
+    let run;
+    let failure;
+    try {
+        run = await runAgent({
+            agentId: review.agent,
+            task: buildQuickReviewPrompt(files, diff, review.focus),
+            basePath,
+            timeoutMs: review.timeBudgetMs,
+            sessionId,
+            surface: 'cli',
+            signal: controller.signal,
+        });
+    }
+    catch (error) {
+        failure = error instanceof Error ? error.message : String(error);
+    }
+    finally {
+        clearTimeout(timer);
+    }
+    if (controller.signal.aborted) {
+        return notice('timed-out', `Quick review by agent "${review.agent}" cancelled after exceeding its ${review.timeBudgetMs}ms time budget.`);
+    }
+    if (run === undefined || !run.success) {
+        return notice('failed', `Quick review by agent "${review.agent}" failed: ${failure ?? run?.error?.message ?? 'Unknown error'}`);
+    }
+    const findings = parseQuickReviewFindings(run.content, files);
+    if (findings === undefined) {
+        return notice('failed', `Quick review by agent "${review.agent}" failed: it did not reply with a JSON array of findings.`);
+    }
+    return { status: findings.some((finding) => finding.severity === 'error') ? 'failed' : 'passed', issues: findings };
+}
+function buildQuickReviewPrompt(files, diff, focus) {
+    const lines = diff.replace(/\n$/, '').split('\n');
+    const shown = lines.slice(0, MAX_REVIEW_DIFF_LINES).join('\n');
+    return [
+        `Review the change staged for commit in ${files.join(', ')}. Look for ${focus === 'all' ? 'bugs, security problems, unhandled errors and risky code' : `${focus} problems`} it introduces. Leave formatting and naming alone. Be quick: the commit waits for your answer.`,
+        `Diff:\n\`\`\`diff\n${shown}${lines.length > MAX_REVIEW_DIFF_LINES ? `\n... ${lines.length - MAX_REVIEW_DIFF_LINES} more lines` : ''}\n\`\`\``,
+        [
+            'Reply with only a JSON array of findings, each like:',
+            '{"file": "src/app.ts", "line": 14, "severity": "warning", "message": "What is wrong and how to fix it."}',
+            'file is one of the staged files and line a line of it after the change. severity is "critical", "warning" or "note".',
+            'Reply [] when nothing needs fixing before the commit.',
+        ].join('\n'),
+    ].join('\n\n');
+}
+// Reads the JSON array out of the reply, around any code fence or explanation. Critical findings
+// are errors, which block the commit in block mode; the rest are warnings.
+function parseQuickReviewFindings(content, files) {
+    const start = content.indexOf('[');
+    const end = content.lastIndexOf(']');
+    if (start === -1 || end < start) {
+        return undefined;
+    }
+    let parsed;
+    try {
+        parsed = JSON.parse(content.slice(start, end + 1));
+    }
+    catch {
+        return undefined;
+    }
+    if (!Array.isArray(parsed)) {
+        return undefined;
+    }
+    return parsed.flatMap((entry) => {
+        const finding = asRecord(entry);
+        if (finding === undefined || typeof finding.message !== 'string' || finding.message.trim().length === 0) {
+            return [];
+        }
+        const severity = typeof finding.severity === 'string' ? finding.severity.trim().toLowerCase() : '';
+        return [{
+                check: 'review',
+                severity: severity === 'critical' || severity === 'error' || severity === 'high' ? 'error' : 'warning',
+                file: typeof finding.file === 'string' && files.includes(finding.file) ? finding.file : '.',
+                ...(typeof finding.line === 'number' && Number.isInteger(finding.line) && finding.line > 0 ? { line: finding.line } : {}),
+                message: finding.message.trim(),
+            }];
+    });
+}
+async function listStagedFiles(basePath) {
+    const { stdout } = await execFileAsync('git', ['diff', '--cached', '--name-only', '--diff-filter=ACMR', '-z'], {
+        cwd: basePath,
+        maxBuffer: 1024 * 1024 * 4,
+    });
+    return stdout.split('\0').filter((entry) => entry.length > 0);
+}
+async function readStagedFile(basePath, file) {
+    try {
+        const { stdout } = await execFileAsync('git', ['show', `:${file}`], {
+            cwd: basePath,
+            maxBuffer: 1024 * 1024 * 16,
+        });
+        return stdout.includes('\0') ? undefined : stdout;
+    }
+    catch {
+        return undefined;
+    }
+}
+async function resolveRepoRoot(basePath) {
+    try {
+        const { stdout } = await execFileAsync('git', ['rev-parse', '--show-toplevel'], { cwd: basePath });
+        return stdout.trim();
+    }
+    catch (error) {
+        const message = error instanceof Error ? error.message : String(error);
+        throw new Error(`pre-commit hook failed: ${basePath} is not a git repository (${message})`);
+    }
+}
+async function resolveHooksDir(basePath) {
+    try {
+        const { stdout } = await execFileAsync('git', ['rev-parse', '--git-path', 'hooks'], { cwd: basePath });
+        return resolve(basePath, stdout.trim());
+    }
+    catch (error) {
+        const message = error instanceof Error ? error.message : String(error);
+        throw new Error(`hook install failed: ${basePath} is not a git repository (${message})`);
+    }
+}
+async function readOptionalFile(filePath) {
+    try {
+        return await readFile(filePath, 'utf8');
+    }
+    catch {
+        return undefined;
+    }
+}
+function buildHookScript() {
+    return [
+        '#!/bin/sh',
+        HOOK_MARKER,
+        '# Installed by `ax hook install`; remove with `ax hook uninstall`.',
+        'if command -v ax >/dev/null 2>&1; then',
+        '  exec ax hook run pre-commit',
+        'fi',
+        'echo "AutomatosX pre-commit hook skipped: ax is not on PATH." >&2',
+        'exit 0',
+        '',
+    ].join('\n');
+}
@@ -0,0 +1,129 @@
+import type { MemoryEntry } from '@defai.digital/state-store';
+import { readCodeIndex } from './code-intel/index.js';
+import { createSymbolSearch } from './code-intel/symbol-search.js';
+import { searchText } from './code-intel/text-search.js';
+import type { CodeSymbol } from './code-intel/types.js';
+
+export type HybridSearchSource = 'memory' | 'symbol' | 'text';
+
+export interface HybridSearchResult {
+  /** Reciprocal-rank fusion score: the sum of 1 / (60 + rank) over every ranking the result is in. */
+  score: number;
+  /** The searches that found it, strongest first. */
+  sources: HybridSearchSource[];
+  file?: string;
+  line?: number;
+  symbol?: CodeSymbol;
+  /** The matching line, for text matches. */
+  text?: string;
+  memory?: MemoryEntry;
+}
+
+export interface RuntimeHybridSearch {
+  query: string;
+  /** The query words searched on their own besides the whole query. */
+  terms: string[];
+  results: HybridSearchResult[];
+  warnings: string[];
+}
+
+export interface SearchService {
+  hybrid(query: string, k?: number): Promise<RuntimeHybridSearch>;
+}
+
+const DEFAULT_K = 10;
+// The usual RRF constant: it keeps the first few ranks of one list from outweighing agreement between lists.
+const RRF_CONSTANT = 60;
+const PER_RANKING_LIMIT = 50;
+const STOP_WORDS = new Set([
+  'the', 'and', 'for', 'how', 'what', 'where', 'which', 'who', 'why', 'when', 'does', 'are', 'was', 'with',
+  'from', 'into', 'that', 'this', 'there', 'their', 'has', 'have', 'not', 'all', 'any', 'can', 'get', 'our',
+  'code', 'file', 'files', 'find', 'show', 'used', 'use', 'uses',
+]);
+
+/**
+ * Searches memory, the symbol index and file contents together and fuses their rankings with
+ * reciprocal-rank fusion, so a natural-language question and an identifier fragment both surface
+ * what they refer to. The whole query and each of its words are searched separately; results
+ * found by several searches, or for several words, rank first. A symbol and a text match on the
+ * same line are one result.
+ */
+export function createSearchService(options: {
+  basePath: string;
+  searchMemory(query: string): Promise<MemoryEntry[]>;
+}): SearchService {
+  return {
+    async hybrid(query, k = DEFAULT_K) {
+      const trimmed = query.trim();
+      if (trimmed.length === 0) {
+        throw new Error('Search query must not be empty.');
+      }
+      const terms = searchTerms(trimmed);
+      const queries = [trimmed, ...terms.filter((term) => term !== trimmed.toLowerCase())];
+      const warnings: string[] = [];
+      const rankings: Array<Array<{ key: string; source: HybridSearchSource; result: HybridSearchResult }>> = [];
+
+      const snapshot = await readCodeIndex(options.basePath);
+      if (snapshot === undefined) {
+        warnings.push('No symbol index found; run "ax code index" to include symbols.');
+      }
+      const symbols = snapshot !== undefined ? createSymbolSearch(snapshot) : undefined;
+      for (const text of queries) {
+        if (symbols !== undefined) {
+          const hits = symbols.search(text, { limit: PER_RANKING_LIMIT });
+          rankings.push(hits.map(({ symbol }) => ({
+            key: `${symbol.file}:${symbol.line}`,
+            source: 'symbol',
+            result: { score: 0, sources: [], file: symbol.file, line: symbol.line, symbol },
+          })));
+        }
+        const found = await searchText({ basePath: options.basePath, pattern: text, limit: PER_RANKING_LIMIT });
+        rankings.push(found.matches.map((match) => ({
+          key: `${match.file}:${match.line}`,
+          source: 'text',
+          result: { score: 0, sources: [], file: match.file, line: match.line, text: match.text },
+        })));
+        const entries = await options.searchMemory(text);
+        rankings.push(entries.slice(0, PER_RANKING_LIMIT).map((entry) => ({
+          key: `memory:${entry.namespace ?? ''}:${entry.key}`,
+          source: 'memory',
+          result: { score: 0, sources: [], memory: entry },
+        })));
+      }
+
+      return { query: trimmed, terms, results: fuseRankings(rankings).slice(0, k), warnings };
+    },
+  };
+}
+
+// Words worth searching alone: `where is the retry backoff computed` -> ['retry', 'backoff', 'computed'].
+function searchTerms(query: string): string[] {
+  const words = query.toLowerCase().split(/[^a-z0-9_]+/).filter((word) => word.length >= 3 && !STOP_WORDS.has(word));
+  return [...new Set(words)];
+}
+
+function fuseRankings(rankings: Array<Array<{ key: string; source: HybridSearchSource; result: HybridSearchResult }>>): HybridSearchResult[] {
+  const fused = new Map<string, { result: HybridSearchResult; bySource: Map<HybridSearchSource, number> }>();
+  for (const ranking of rankings) {
+    ranking.forEach(({ key, source, result }, index) => {
+      const contribution = 1 / (RRF_CONSTANT + index + 1);
+      const existing = fused.get(key);
+      if (existing === undefined) {
+        fused.set(key, { result: { ...result, score: contribution }, bySource: new Map([[source, contribution]]) });
+        return;
+      }
+      existing.result.score += contribution;
+      existing.bySource.set(source, (existing.bySource.get(source) ?? 0) + contribution);
+      existing.result.symbol ??= result.symbol;
+      existing.result.text ??= result.text;
+    });
+  }
+  return [...fused.values()]
+    .map(({ result, bySource }) => ({
+      ...result,
+      sources: [...bySource.entries()].sort((left, right) => right[1] - left[1]).map(([source]) => source),
+    }))
+    .sort((left, right) => right.score - left.score
+      || (left.file ?? '').localeCompare(right.file ?? '')
+      || (left.line ?? 0) - (right.line ?? 0));
+}
@@ -0,0 +1,254 @@
+import { createHash } from 'node:crypto';
+import { mkdir, rm } from 'node:fs/promises';
+import { createConnection, createServer } from 'node:net';
+import { tmpdir } from 'node:os';
+import { dirname, join, resolve } from 'node:path';
+import { buildCodeIndex, filterCodeSymbols, readCodeIndex, } from './code-intel/index.js';
+import { createSymbolSearch } from './code-intel/symbol-search.js';
+import { startIndexWatcher } from './index-watcher.js';
+const DEFAULT_REQUEST_TIMEOUT_MS = 30_000;
+const CONNECT_TIMEOUT_MS = 500;
+// Unix domain socket paths are capped at 104-108 bytes depending on the platform.
+const MAX_SOCKET_PATH_LENGTH = 100;
+export function getIndexServerSocketPath(basePath) {
+    const resolvedBase = resolve(basePath);
+    const digest = createHash('sha256').update(resolvedBase).digest('hex').slice(0, 16);
+    if (process.platform === 'win32') {
+        return `\\\\.\\pipe\\automatosx-index-${digest}`;
+    }
+    const socketPath = join(resolvedBase, '.automatosx', 'runtime', 'index.sock');
+    return socketPath.length <= MAX_SOCKET_PATH_LENGTH ? socketPath : join(tmpdir(), `automatosx-index-${digest}.sock`);
+}
+export async function startIndexServer(config) {
+    const basePath = resolve(config.basePath);
+    const socketPath = getIndexServerSocketPath(basePath);
+    const existing = await requestIndexServer(basePath, 'status');
+    if (existing !== undefined) {
+        throw new Error(`An index server is already running for ${basePath} (pid ${existing.pid}).`);
+    }
+    if (process.platform !== 'win32') {
+        await mkdir(dirname(socketPath), { recursive: true });
+        await rm(socketPath, { force: true });
+    }
+    const startedAt = new Date().toISOString();
+    let snapshot;
+    let loaded = false;
+    let requestsServed = 0;
+    let pending = Promise.resolve();
+    let watcher;
+    // Rebuilt only when the snapshot is replaced.
+    let search;
+    const serialize = (task) => {
+        const next = pending.then(task, task);
+        pending = next.catch(() => undefined);
+        return next;
+    };
+    const ensureSnapshot = () => serialize(async () => {
+        if (watcher !== undefined) {
+            // Changes still inside the debounce window are applied before answering.
+            await watcher.flush();
+            snapshot = watcher.snapshot();
+            return snapshot;
+        }
+        if (!loaded) {
+            snapshot = await readCodeIndex(basePath);
+            loaded = true;
+        }
+        return snapshot;
+    });
+    const status = () => ({
+        pid: process.pid,
+        basePath,
+        socketPath,
+        startedAt,
+        generatedAt: snapshot?.generatedAt,
+        filesIndexed: snapshot?.files.length ?? 0,
+        symbolCount: snapshot?.files.reduce((total, entry) => total + entry.symbols.length, 0) ?? 0,
+        requestsServed,
+        watching: watcher !== undefined,
+    });
+    if (config.watch === true) {
+        watcher = await startIndexWatcher({
+            basePath,
+            onUpdate: () => {
+                snapshot = watcher?.snapshot();
+            },
+        });
+        snapshot = watcher.snapshot();
+        loaded = true;
+    }
+    let closeServer = async () => undefined;
+    const dispatch = async (request) => {
+        switch (request.method) {
+            case 'status':
+                return status();
+            case 'index': {
+                const params = (request.params ?? {});
+                return serialize(async () => {
+                    const result = await buildCodeIndex({ paths: params.paths, maxFiles: params.maxFiles, basePath });
+                    snapshot = await readCodeIndex(basePath);
+                    loaded = true;
+                    return result;
+                });
+            }
+            // Another process rewrote the index file, e.g. a streaming `ax code index`.
+            case 'reload':
+                return serialize(async () => {
+                    snapshot = await readCodeIndex(basePath);
+                    loaded = true;
+                    return status();
+                });
+            case 'symbols': {
+                const current = await ensureSnapshot();
+                const params = (request.params ?? {});
+                return current === undefined ? [] : filterCodeSymbols(current, params);
+            }
+            case 'search': {
+                const current = await ensureSnapshot();
+                const params = request.params;
+                if (current === undefined) {
+                    throw new Error('No symbol index found. Run "ax code index" first.');
+                }
+                if (search?.snapshot !== current) {
+                    search = { snapshot: current, engine: createSymbolSearch(current) };
+                }
+                return search.engine.search(params.query, { limit: params.limit });
+            }
+            case 'shutdown':
+                setImmediate(() => { void closeServer(); });
+                return { stopped: true };
+            default:
+                throw new Error(`Unknown index server method: ${String(request.method)}`);
+        }
+    };
+    const sockets = new Set();
+    const server = createServer((socket) => {
+        sockets.add(socket);
+        socket.on('close', () => sockets.delete(socket));
+        socket.setEncoding('utf8');
+        let buffer = '';
+        socket.on('data', (chunk) => {
+            buffer += chunk;
+            const newline = buffer.indexOf('\n');
+            if (newline === -1) {
+                return;
+            }
+            const line = buffer.slice(0, newline);
+            buffer = '';
+            void respond(line).then((response) => {
+                socket.end(`${JSON.stringify(response)}\n`);
+            });
+        });
+        socket.on('error', () => undefined);
+    });
+    const respond = async (line) => {
+        try {
+            const request = JSON.parse(line);
+            const result = await dispatch(request);
+            requestsServed += 1;
+            return { ok: true, result };
+        }
+        catch (error) {
+            return { ok: false, error: error instanceof Error ? error.message : String(error) };
+        }
+    };
+    await new Promise((resolveListen, rejectListen) => {
+        server.once('error', rejectListen);
+        server.listen(socketPath, () => {
+            server.off('error', rejectListen);
+            resolveListen();
+        });
+    }).catch(async (error) => {
+        await watcher?.close();
+        throw error;
+    });
+    let resolveClosed;
+    const closed = new Promise((resolvePromise) => {
+        resolveClosed = resolvePromise;
+    });
+    let closing;
+    closeServer = () => {
+        closing ??= new Promise((resolveClose) => {
+            server.close(() => resolveClose());
+            for (const socket of sockets) {
+                socket.destroy();
+            }
+        }).then(async () => {
+            await watcher?.close();
+            if (process.platform !== 'win32') {
+                await rm(socketPath, { force: true });
+            }
+            resolveClosed();
+        });
+        return closing;
+    };
+    return {
+        socketPath,
+        status,
+        close: () => closeServer(),
+        closed,
+    };
+}
+/**
+ * Sends one request to the index server for `basePath`. Resolves `undefined` when no server is
+ * listening so callers can fall back to reading the on-disk index themselves.
+ */
+export function requestIndexServer(basePath, method, params, options = {}) {
+    const socketPath = getIndexServerSocketPath(basePath);
+    return new Promise((resolveRequest, rejectRequest) => {
+        const socket = createConnection(socketPath);
+        let connected = false;
+        let buffer = '';
+        let settled = false;
+        const settle = (action) => {
+            if (!settled) {
+                settled = true;
+                clearTimeout(timer);
+                socket.destroy();
+                action();
+            }
+        };
+        const timer = setTimeout(() => {
+            settle(() => resolveRequest(undefined));
+        }, CONNECT_TIMEOUT_MS);
+        socket.setEncoding('utf8');
+        socket.once('connect', () => {
+            connected = true;
+            clearTimeout(timer);
+            socket.setTimeout(options.timeoutMs ?? DEFAULT_REQUEST_TIMEOUT_MS, () => {
+                settle(() => rejectRequest(new Error(`Index server did not respond to "${method}" in time.`)));
+            });
+            socket.write(`${JSON.stringify({ method, params })}\n`);
+        });
+        socket.on('data', (chunk) => {
+            buffer += chunk;
+        });
+        socket.once('end', () => {
+            settle(() => {
+                try {
+                    const response = JSON.parse(buffer);
+                    if (response.ok) {
+                        resolveRequest(response.result);
+                    }
+                    else {
+                        rejectRequest(new Error(response.error));
+                    }
+                }
+                catch {
+                    rejectRequest(new Error(`Index server returned an invalid response to "${method}".`));
+                }
+            });
+        });
+        socket.once('error', (error) => {
+            settle(() => {
+                if (!connected) {
+                    // ENOENT / ECONNREFUSED: no server, or a stale socket left behind by a crashed one.
+                    resolveRequest(undefined);
+                }
+                else {
+                    rejectRequest(error);
+                }
+            });
+        });
+    });
+}
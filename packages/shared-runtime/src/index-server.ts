@@ -0,0 +1,322 @@
+import { createHash } from 'node:crypto';
+import { mkdir, rm } from 'node:fs/promises';
+import { createConnection, createServer, type Socket } from 'node:net';
+import { tmpdir } from 'node:os';
+import { dirname, join, resolve } from 'node:path';
+import {
+  buildCodeIndex,
+  filterCodeSymbols,
+  readCodeIndex,
+  type CodeIndexSnapshot,
+  type CodeLanguage,
+  type CodeSymbolKind,
+  type CodeTestFilter,
+  type RuntimeCodeIndexResponse,
+} from './code-intel/index.js';
+import { createSymbolSearch, type SymbolSearch } from './code-intel/symbol-search.js';
+import { startIndexWatcher, type IndexWatcher } from './index-watcher.js';
+
+export type IndexServerMethod = 'status' | 'index' | 'symbols' | 'search' | 'reload' | 'shutdown';
+
+export interface IndexServerStatus {
+  pid: number;
+  basePath: string;
+  socketPath: string;
+  startedAt: string;
+  generatedAt?: string;
+  filesIndexed: number;
+  symbolCount: number;
+  requestsServed: number;
+  /** True when file changes are re-indexed as they happen. */
+  watching: boolean;
+}
+
+export interface IndexServerHandle {
+  socketPath: string;
+  status(): IndexServerStatus;
+  close(): Promise<void>;
+  closed: Promise<void>;
+}
+
+interface IndexServerRequest {
+  method: IndexServerMethod;
+  params?: IndexServerParams[keyof IndexServerParams];
+}
+
+type IndexServerResponse =
+  | { ok: true; result: unknown }
+  | { ok: false; error: string };
+
+interface IndexServerParams {
+  index: { paths?: string[]; maxFiles?: number };
+  search: { query: string; limit?: number };
+  symbols: { query?: string; id?: string; kind?: CodeSymbolKind; language?: CodeLanguage; file?: string; tests?: CodeTestFilter; concurrent?: boolean; features?: string[]; target?: string; derive?: string; attribute?: string; limit?: number };
+}
+
+const DEFAULT_REQUEST_TIMEOUT_MS = 30_000;
+const CONNECT_TIMEOUT_MS = 500;
+// Unix domain socket paths are capped at 104-108 bytes depending on the platform.
+const MAX_SOCKET_PATH_LENGTH = 100;
+
+export function getIndexServerSocketPath(basePath: string): string {
+  const resolvedBase = resolve(basePath);
+  const digest = createHash('sha256').update(resolvedBase).digest('hex').slice(0, 16);
+  if (process.platform === 'win32') {
+    return `\\\\.\\pipe\\automatosx-index-${digest}`;
+  }
+  const socketPath = join(resolvedBase, '.automatosx', 'runtime', 'index.sock');
+  return socketPath.length <= MAX_SOCKET_PATH_LENGTH ? socketPath : join(tmpdir(), `automatosx-index-${digest}.sock`);
+}
+
+export async function startIndexServer(config: { basePath: string; watch?: boolean }): Promise<IndexServerHandle> {
+  const basePath = resolve(config.basePath);
+  const socketPath = getIndexServerSocketPath(basePath);
+  const existing = await requestIndexServer<IndexServerStatus>(basePath, 'status');
+  if (existing !== undefined) {
+    throw new Error(`An index server is already running for ${basePath} (pid ${existing.pid}).`);
+  }
+
+  if (process.platform !== 'win32') {
+    await mkdir(dirname(socketPath), { recursive: true });
+    await rm(socketPath, { force: true });
+  }
+
+  const startedAt = new Date().toISOString();
+  let snapshot: CodeIndexSnapshot | undefined;
+  let loaded = false;
+  let requestsServed = 0;
+  let pending: Promise<unknown> = Promise.resolve();
+  let watcher: IndexWatcher | undefined;
+  // Rebuilt only when the snapshot is replaced.
+  let search: { snapshot: CodeIndexSnapshot; engine: SymbolSearch } | undefined;
+
+  const serialize = <T>(task: () => Promise<T>): Promise<T> => {
+    const next = pending.then(task, task);
+    pending = next.catch(() => undefined);
+    return next;
+  };
+
+  const ensureSnapshot = (): Promise<CodeIndexSnapshot | undefined> => serialize(async () => {
+    if (watcher !== undefined) {
+      // Changes still inside the debounce window are applied before answering.
+      await watcher.flush();
+      snapshot = watcher.snapshot();
+      return snapshot;
+    }
+    if (!loaded) {
+      snapshot = await readCodeIndex(basePath);
+      loaded = true;
+    }
+    return snapshot;
+  });
+
+  const status = (): IndexServerStatus => ({
+    pid: process.pid,
+    basePath,
+    socketPath,
+    startedAt,
+    generatedAt: snapshot?.generatedAt,
+    filesIndexed: snapshot?.files.length ?? 0,
+    symbolCount: snapshot?.files.reduce((total, entry) => total + entry.symbols.length, 0) ?? 0,
+    requestsServed,
+    watching: watcher !== undefined,
+  });
+
+  if (config.watch === true) {
+    watcher = await startIndexWatcher({
+      basePath,
+      onUpdate: () => {
+        snapshot = watcher?.snapshot();
+      },
+    });
+    snapshot = watcher.snapshot();
+    loaded = true;
+  }
+
+  let closeServer: () => Promise<void> = async () => undefined;
+
+  const dispatch = async (request: IndexServerRequest): Promise<unknown> => {
+    switch (request.method) {
+      case 'status':
+        return status();
+      case 'index': {
+        const params = (request.params ?? {}) as IndexServerParams['index'];
+        return serialize(async (): Promise<RuntimeCodeIndexResponse> => {
+          const result = await buildCodeIndex({ paths: params.paths, maxFiles: params.maxFiles, basePath });
+          snapshot = await readCodeIndex(basePath);
+          loaded = true;
+          return result;
+        });
+      }
+      // Another process rewrote the index file, e.g. a streaming `ax code index`.
+      case 'reload':
+        return serialize(async (): Promise<IndexServerStatus> => {
+          snapshot = await readCodeIndex(basePath);
+          loaded = true;
+          return status();
+        });
+      case 'symbols': {
+        const current = await ensureSnapshot();
+        const params = (request.params ?? {}) as IndexServerParams['symbols'];
+        return current === undefined ? [] : filterCodeSymbols(current, params);
+      }
+      case 'search': {
+        const current = await ensureSnapshot();
+        const params = request.params as IndexServerParams['search'];
+        if (current === undefined) {
+          throw new Error('No symbol index found. Run "ax code index" first.');
+        }
+        if (search?.snapshot !== current) {
+          search = { snapshot: current, engine: createSymbolSearch(current) };
+        }
+        return search.engine.search(params.query, { limit: params.limit });
+      }
+      case 'shutdown':
+        setImmediate(() => { void closeServer(); });
+        return { stopped: true };
+      default:
+        throw new Error(`Unknown index server method: ${String((request as { method?: unknown }).method)}`);
+    }
+  };
+
+  const sockets = new Set<Socket>();
+  const server = createServer((socket) => {
+    sockets.add(socket);
+    socket.on('close', () => sockets.delete(socket));
+    socket.setEncoding('utf8');
+    let buffer = '';
+    socket.on('data', (chunk: string) => {
+      buffer += chunk;
+      const newline = buffer.indexOf('\n');
+      if (newline === -1) {
+        return;
+      }
+      const line = buffer.slice(0, newline);
+      buffer = '';
+      void respond(line).then((response) => {
+        socket.end(`${JSON.stringify(response)}\n`);
+      });
+    });
+    socket.on('error', () => undefined);
+  });
+
+  const respond = async (line: string): Promise<IndexServerResponse> => {
+    try {
+      const request = JSON.parse(line) as IndexServerRequest;
+      const result = await dispatch(request);
+      requestsServed += 1;
+      return { ok: true, result };
+    } catch (error) {
+      return { ok: false, error: error instanceof Error ? error.message : String(error) };
+    }
+  };
+
+  await new Promise<void>((resolveListen, rejectListen) => {
+    server.once('error', rejectListen);
+    server.listen(socketPath, () => {
+      server.off('error', rejectListen);
+      resolveListen();
+    });
+  }).catch(async (error: unknown) => {
+    await watcher?.close();
+    throw error;
+  });
+
+  let resolveClosed!: () => void;
+  const closed = new Promise<void>((resolvePromise) => {
+    resolveClosed = resolvePromise;
+  });
+  let closing: Promise<void> | undefined;
+  closeServer = () => {
+    closing ??= new Promise<void>((resolveClose) => {
+      server.close(() => resolveClose());
+      for (const socket of sockets) {
+        socket.destroy();
+      }
+    }).then(async () => {
+      await watcher?.close();
+      if (process.platform !== 'win32') {
+        await rm(socketPath, { force: true });
+      }
+      resolveClosed();
+    });
+    return closing;
+  };
+
+  return {
+    socketPath,
+    status,
+    close: () => closeServer(),
+    closed,
+  };
+}
+
+/**
+ * Sends one request to the index server for `basePath`. Resolves `undefined` when no server is
+ * listening so callers can fall back to reading the on-disk index themselves.
+ */
+export function requestIndexServer<T>(
+  basePath: string,
+  method: IndexServerMethod,
+  params?: IndexServerParams[keyof IndexServerParams],
+  options: { timeoutMs?: number } = {},
+): Promise<T | undefined> {
+  const socketPath = getIndexServerSocketPath(basePath);
+
+  return new Promise<T | undefined>((resolveRequest, rejectRequest) => {
+    const socket = createConnection(socketPath);
+    let connected = false;
+    let buffer = '';
+    let settled = false;
+
+    const settle = (action: () => void): void => {
+      if (!settled) {
+        settled = true;
+        clearTimeout(timer);
+        socket.destroy();
+        action();
+      }
+    };
+
+    const timer = setTimeout(() => {
+      settle(() => resolveRequest(undefined));
+    }, CONNECT_TIMEOUT_MS);
+
+    socket.setEncoding('utf8');
+    socket.once('connect', () => {
+      connected = true;
+      clearTimeout(timer);
+      socket.setTimeout(options.timeoutMs ?? DEFAULT_REQUEST_TIMEOUT_MS, () => {
+        settle(() => rejectRequest(new Error(`Index server did not respond to "${method}" in time.`)));
+      });
+      socket.write(`${JSON.stringify({ method, params } satisfies IndexServerRequest)}\n`);
+    });
+    socket.on('data', (chunk: string) => {
+      buffer += chunk;
+    });
+    socket.once('end', () => {
+      settle(() => {
+        try {
+          const response = JSON.parse(buffer) as IndexServerResponse;
+          if (response.ok) {
+            resolveRequest(response.result as T);
+          } else {
+            rejectRequest(new Error(response.error));
+          }
+        } catch {
+          rejectRequest(new Error(`Index server returned an invalid response to "${method}".`));
+        }
+      });
+    });
+    socket.once('error', (error: NodeJS.ErrnoException) => {
+      settle(() => {
+        if (!connected) {
+          // ENOENT / ECONNREFUSED: no server, or a stale socket left behind by a crashed one.
+          resolveRequest(undefined);
+        } else {
+          rejectRequest(error);
+        }
+      });
+    });
+  });
+}
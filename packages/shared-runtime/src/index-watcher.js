@@ -0,0 +1,157 @@
+import { watch } from 'node:fs';
+import { stat } from 'node:fs/promises';
+import { join, relative, resolve, sep } from 'node:path';
+import { collectCodeFiles, IGNORED_DIRS, indexCodeFile, loadWorkspaceLanguageRegistry, writeCodeIndex, } from './code-intel/index.js';
+export const SYMBOL_CHANGE_TYPES = ['added', 'removed', 'changed'];
+const DEFAULT_DEBOUNCE_MS = 200;
+/**
+ * Builds the symbol index, then keeps it current: file system events are debounced, only the
+ * files they name are re-parsed, and each batch is written back to the on-disk index and
+ * reported as symbol-level `added`/`removed`/`changed` events.
+ */
+export async function startIndexWatcher(config) {
+    const basePath = resolve(config.basePath);
+    const { registry } = await loadWorkspaceLanguageRegistry(basePath);
+    const roots = (config.paths !== undefined && config.paths.length > 0 ? config.paths : ['.'])
+        .map((path) => relative(basePath, resolve(basePath, path)).split(sep).join('/'));
+    const inScope = (file) => !file.split('/').some((segment) => IGNORED_DIRS.has(segment))
+        && roots.some((root) => root === '' || file === root || file.startsWith(`${root}/`));
+    const entries = new Map((await collectCodeFiles({ basePath, paths: config.paths, maxFiles: config.maxFiles, registry }))
+        .map((entry) => [entry.file, entry]));
+    let snapshot = (await writeCodeIndex(basePath, copyEntries(entries))).snapshot;
+    const pending = new Set();
+    let timer;
+    let running = Promise.resolve();
+    const apply = async () => {
+        clearTimeout(timer);
+        timer = undefined;
+        const paths = [...pending];
+        pending.clear();
+        // A directory event stands for everything under it: created, moved in or deleted.
+        const parsed = new Map();
+        for (const path of paths) {
+            const stats = await stat(join(basePath, path)).catch(() => undefined);
+            if (stats?.isDirectory() === true) {
+                for (const entry of await collectCodeFiles({ basePath, paths: [path], maxFiles: Number.MAX_SAFE_INTEGER, registry })) {
+                    parsed.set(entry.file, entry);
+                }
+            }
+            for (const file of entries.keys()) {
+                if (file === path || file.startsWith(`${path}/`)) {
+                    parsed.set(file, parsed.get(file));
+                }
+            }
+            if (stats?.isFile() === true) {
+                parsed.set(path, await indexCodeFile(basePath, path, registry));
+            }
+        }
+        const files = [];
+        const changes = [];
+        for (const [file, next] of [...parsed].sort(([left], [right]) => left.localeCompare(right))) {
+            const previous = entries.get(file);
+            const current = next !== undefined && inScope(file) ? next : undefined;
+            if (previous === undefined && current === undefined) {
+                continue;
+            }
+            if (current === undefined) {
+                entries.delete(file);
+            }
+            else {
+                entries.set(file, current);
+            }
+            files.push(file);
+            changes.push(...diffSymbols(file, previous?.symbols ?? [], current?.symbols ?? []));
+        }
+        if (files.length === 0) {
+            return undefined;
+        }
+        snapshot = (await writeCodeIndex(basePath, copyEntries(entries))).snapshot;
+        const update = {
+            generatedAt: snapshot.generatedAt,
+            files,
+            changes,
+            filesIndexed: snapshot.files.length,
+            symbolCount: snapshot.files.reduce((total, entry) => total + entry.symbols.length, 0),
+        };
+        config.onUpdate?.(update);
+        return update;
+    };
+    const flush = () => {
+        const next = running.then(apply, apply);
+        running = next.catch(() => undefined);
+        return next;
+    };
+    const reportError = (error) => {
+        config.onError?.(error instanceof Error ? error : new Error(String(error)));
+    };
+    const watcher = watch(basePath, { recursive: true }, (_event, filename) => {
+        if (filename === null) {
+            return;
+        }
+        const path = filename.split(sep).join('/');
+        if (path.split('/').some((segment) => IGNORED_DIRS.has(segment))) {
+            return;
+        }
+        pending.add(path);
+        clearTimeout(timer);
+        timer = setTimeout(() => {
+            flush().catch(reportError);
+        }, config.debounceMs ?? DEFAULT_DEBOUNCE_MS);
+    });
+    watcher.on('error', reportError);
+    let resolveClosed;
+    const closed = new Promise((resolvePromise) => {
+        resolveClosed = resolvePromise;
+    });
+    let closing;
+    return {
+        snapshot: () => snapshot,
+        flush,
+        close: () => {
+            closing ??= (async () => {
+                clearTimeout(timer);
+                watcher.close();
+                await running;
+                resolveClosed();
+            })();
+            return closing;
+        },
+        closed,
+    };
+}
+// Symbols are matched by kind, container and name; overloads sharing all three pair up in order.
+function diffSymbols(file, previous, current) {
+    const before = keySymbols(previous);
+    const after = keySymbols(current);
+    const changes = [];
+    for (const [key, symbol] of after) {
+        const old = before.get(key);
+        if (old === undefined) {
+            changes.push({ type: 'added', file, symbol });
+        }
+        else if (JSON.stringify(old) !== JSON.stringify(symbol)) {
+            changes.push({ type: 'changed', file, symbol });
+        }
+    }
+    for (const [key, symbol] of before) {
+        if (!after.has(key)) {
+            changes.push({ type: 'removed', file, symbol });
+        }
+    }
+    return changes;
+}
+function keySymbols(symbols) {
+    const seen = new Map();
+    const keyed = new Map();
+    for (const symbol of symbols) {
+        const key = `${symbol.kind}:${symbol.container ?? ''}:${symbol.name}`;
+        const occurrence = seen.get(key) ?? 0;
+        seen.set(key, occurrence + 1);
+        keyed.set(`${key}#${occurrence}`, symbol);
+    }
+    return keyed;
+}
+// Merging C# partial types rewrites entries in place; the watcher keeps the unmerged ones.
+function copyEntries(entries) {
+    return [...entries.values()].map((entry) => ({ ...entry, symbols: entry.symbols.map((symbol) => ({ ...symbol })) }));
+}
@@ -1,18 +1,73 @@
 import { randomUUID } from 'node:crypto';
 import { execFile } from 'node:child_process';
 import { mkdir, readFile, writeFile } from 'node:fs/promises';
-import { join } from 'node:path';
+import { dirname, isAbsolute, join } from 'node:path';
 import { promisify } from 'node:util';
-import { createRealStepExecutor, createWorkflowLoader, createWorkflowRunner, createStepGuardEngine, findWorkflowDir, } from '@defai.digital/workflow-engine';
+import { createRealStepExecutor, createWorkflowLoader, createWorkflowRunner, createStepGuardEngine, findWorkflowDir, runScheduled, } from '@defai.digital/workflow-engine';
 import { StepGuardPolicySchema } from '@defai.digital/contracts';
 import { createTraceStore, } from '@defai.digital/trace-store';
-import { createStateStore, } from '@defai.digital/state-store';
+import { createStateStore, SCRATCHPAD_CONFLICT_CODE, scopedNamespace, } from '@defai.digital/state-store';
 import { listReviewTraces, runReviewAnalysis, } from './review.js';
 import { createProviderBridge } from './provider-bridge.js';
+import { createProviderResponseCache, readProviderCacheSettings } from './provider-cache.js';
+import { readFallbackProviders } from './provider-quota.js';
+import { parseRoutingOptions } from './provider-routing.js';
+import { createAbilityIncludeResolver, PROMPT_TEMPLATE_ERROR_CODE, renderPromptTemplate } from './prompt-template.js';
+import { assertWritable, isMutatingTool, isReadOnlyEnv, READ_ONLY_ERROR_CODE, readOnlyViolation } from './read-only.js';
+import { createUsageTracker } from './usage-tracker.js';
+import { buildCodeIndex, loadWorkspaceLanguageRegistry, parseWorkspaceCodeSource, readCodeIndex, searchCodeSymbols, } from './code-intel/index.js';
+import { installPreCommitHook, resolvePreCommitConfig, runPreCommitPipeline, uninstallPreCommitHook, } from './git-hooks.js';
+import { explainConfigKey, loadLayeredConfig, readProjectConfig, readWorkspaceConfig, validateLayeredConfig, writeProjectConfig, } from './config-layers.js';
+import { formatConfigIssue, validateWorkspaceConfig } from './config-schema.js';
+import { createDebugBundle } from './debug-bundle.js';
+import { diagnoseEnvironment, } from './environment-doctor.js';
+import { answerProjectQuestion } from './project-query.js';
+import { generateImplementationScaffold } from './code-intel/implementation-scaffold.js';
+import { analyzeTestImpact, runImpactedTests } from './code-intel/test-impact.js';
+import { runTestAndFix } from './test-fix.js';
+import { runReviewAndAnnotate } from './review-annotate.js';
+import { AGENT_SCOPE_ERROR_CODE, createAgentWorkspaceTools, describeAgentWorkspace, offersAgentCommands, resolveAgentWorkspace, } from './agent-workspace.js';
+import { assembleContext, describeContextCuts, readContextBudgetSettings } from './context-budget.js';
+import { readConcurrencySettings, workflowStepProvider } from './provider-concurrency.js';
+import { checkDelegationChain, createHandoffTools, readAgentHandoffs, validateHandoffInput, } from './agent-delegation.js';
+import { parsePipeline, pipelineRunDir, readPipelineCheckpoint, runPipelineStages, writePipelineCheckpoint, } from './pipeline.js';
+import { parseEvalSuite, runEvalSuite } from './eval-harness.js';
+import { createRunRecorder, readReplayLog } from './run-replay.js';
+import { branchSessionThread, readSessionThread, SESSION_AGENT_MISSING_CODE, SESSION_NOT_FOUND_CODE, updateSessionThread, } from './session-threads.js';
+import { claimTaskForRun, describeScratchpad, postRunResult, requireScratchpadSession } from './session-scratchpad.js';
+import { buildSessionTranscript, renderSessionTranscript, } from './session-transcript.js';
+import { AGENT_PERMISSION_DENIED_CODE, AGENT_PERMISSIONS_ENV_VAR, describeViolation, findPathViolations, listChangedFiles, permitsPath, readAgentPermissions, snapshotWholeWorkspace, } from './agent-permissions.js';
+import { AGENT_PROFILE_INVALID_CODE, describePackAbilities, profileLayers, resolveAgentProfile, } from './agent-profiles.js';
+import { suggestAgents } from './agent-suggest.js';
+import { APPROVAL_DENIED_CODE, APPROVALS_INVALID_CODE, APPROVALS_UNSUPPORTED_CODE, createApprovalGate, createCostGate, describeApproval, gatesWrite, loadApprovalPolicy, readApprovalGates, unheldApprovals, } from './approval-gates.js';
+import { BUDGET_EXCEEDED_CODE, BUDGET_INVALID_CODE, combineBudgets, createBudgetMeter, describeExceeded, parseRunBudget, } from './run-budget.js';
+import { buildUnsafeReports } from './code-intel/rust-unsafe.js';
+import { findUnreferencedSymbols } from './code-intel/dead-code.js';
+import { findSymbolReferences } from './code-intel/references.js';
+import { renderFileSkeleton, renderSignatures } from './code-intel/signatures.js';
+import { readSymbolBody } from './code-intel/symbol-body.js';
+import { buildCallHierarchy } from './code-intel/call-hierarchy.js';
+import { analyzeChangeImpact } from './code-intel/change-impact.js';
+import { renderOutline } from './code-intel/outline.js';
+import { renderRepoMap } from './code-intel/repo-map.js';
+import { exportCodeGraph } from './code-intel/graph-export.js';
+import { describeSymbolHits, searchSymbolIndex } from './code-intel/symbol-search.js';
+import { searchText } from './code-intel/text-search.js';
+import { createSearchService } from './hybrid-search.js';
+import { readMemoryArchive, writeMemoryArchive } from './memory-archive.js';
+import { memoryText, resolveMemoryDedupPolicy, storeDeduplicatedMemory } from './memory-dedup.js';
+import { memoryCipherFor } from './memory-encryption.js';
+import { findStaleMemory, recordMemorySource } from './memory-provenance.js';
+import { createEmbedder, embedCodeChunks, readEmbedderConfig } from './embeddings.js';
+import { checkProviderHealth, collectProviderIds, isMaintenanceDue, MAINTENANCE_TASKS, MAINTENANCE_WORKFLOW, pruneCaches, pruneMemoryEntries, readMaintenanceConfig, readMaintenanceState, rotateLogs, summarizeMemoryEvictions, writeMaintenanceState, } from './maintenance.js';
+import { requestIndexServer, startIndexServer, } from './index-server.js';
+import { startIndexWatcher } from './index-watcher.js';
+import { createWorkspaceCheckpoint, restoreWorkspaceCheckpoint } from './workspace-checkpoint.js';
 const execFileAsync = promisify(execFile);
 const DEFAULT_DISCUSSION_CONCURRENCY = 2;
 const DEFAULT_DISCUSSION_PROVIDER_BUDGET = 3;
 const DEFAULT_DISCUSSION_ROUNDS = 3;
+const DEFAULT_CONTEXT_MEMORY_HITS = 5;
 const BUILTIN_GUARD_POLICIES = [
     {
         policyId: 'step-validation',
@@ -94,6 +149,13 @@ const BUILTIN_ABILITIES = [
         tags: ['review', 'correctness', 'security', 'maintainability'],
         content: 'Prioritize concrete findings with file references, severity ordering, and missing-test risks. Prefer actionable defects over narrative summaries.',
     },
+    {
+        abilityId: 'concurrency-review',
+        name: 'Concurrency Review',
+        category: 'concurrency',
+        tags: ['concurrency', 'async', 'locking', 'race-conditions'],
+        content: 'Start from the async functions and the functions that use shared-state primitives (`ax code symbols --concurrent`). Check lock ordering, locks held across await points, unbounded channels, and spawned tasks whose errors are dropped.',
+    },
     {
         abilityId: 'git-hygiene',
         name: 'Git Hygiene',
@@ -119,8 +181,50 @@ const BUILTIN_ABILITIES = [
 export function createSharedRuntimeService(config = {}) {
     const basePath = config.basePath ?? process.cwd();
     const traceStore = config.traceStore ?? createTraceStore({ basePath });
-    const stateStore = config.stateStore ?? createStateStore({ basePath });
-    const providerBridge = createProviderBridge({ basePath });
+    const stateStore = config.stateStore ?? createStateStore({ basePath, encryption: memoryCipherFor(basePath) });
+    const readOnly = config.readOnly ?? isReadOnlyEnv();
+    // Runs with a budget or a cost approval threshold by trace id, and the parent of each agent run in
+    // flight, so a provider call is charged to those of its run and of the runs above it.
+    const budgetMeters = new Map();
+    const costGates = new Map();
+    const runParents = new Map();
+    const recordedUsage = createUsageTracker({ stateStore });
+    const chargedRuns = (traceId) => {
+        const chain = [];
+        for (let current = traceId; current !== undefined; current = runParents.get(current)) {
+            chain.push(current);
+        }
+        return chain;
+    };
+    const usageTracker = {
+        ...recordedUsage,
+        async record(entry) {
+            const chain = chargedRuns(entry.traceId);
+            for (const traceId of chain) {
+                budgetMeters.get(traceId)?.charge(entry);
+            }
+            const recorded = await recordedUsage.record(entry);
+            // The bridge waits for this, so a run over its approval threshold pauses until it is answered.
+            for (const traceId of chain) {
+                await costGates.get(traceId)?.charge(entry.costUsd ?? 0);
+            }
+            return recorded;
+        },
+    };
+    // A replayed call spends no tokens, so it is not recorded; it still counts toward its runs' budgets.
+    const runRecorder = createRunRecorder({
+        async charge(entry) {
+            const chain = chargedRuns(entry.traceId);
+            for (const traceId of chain) {
+                budgetMeters.get(traceId)?.charge(entry);
+            }
+            for (const traceId of chain) {
+                await costGates.get(traceId)?.charge(entry.costUsd ?? 0);
+            }
+        },
+    });
+    const responseCache = createProviderResponseCache({ stateStore });
+    const providerBridge = createProviderBridge({ basePath, readOnly, usageTracker, responseCache, recorder: runRecorder });
     const discussionCoordinator = createDiscussionCoordinator({
         maxConcurrentDiscussions: config.maxConcurrentDiscussions ?? DEFAULT_DISCUSSION_CONCURRENCY,
         maxProvidersPerDiscussion: config.maxProvidersPerDiscussion ?? DEFAULT_DISCUSSION_PROVIDER_BUDGET,
@@ -137,7 +241,7 @@ export function createSharedRuntimeService(config = {}) {
         if (cached !== undefined) {
             return cached;
         }
-        const created = createProviderBridge({ basePath: resolvedBasePath });
+        const created = createProviderBridge({ basePath: resolvedBasePath, readOnly, usageTracker, responseCache, recorder: runRecorder });
         providerBridgeCache.set(resolvedBasePath, created);
         return created;
     };
@@ -156,12 +260,224 @@ export function createSharedRuntimeService(config = {}) {
         discussionCoordinatorCache.set(resolvedBasePath, created);
         return created;
     };
+    const scaffoldImplementation = async (request) => {
+        const scaffoldBasePath = request.basePath ?? basePath;
+        const scaffold = await generateImplementationScaffold({
+            basePath: scaffoldBasePath,
+            symbol: request.symbol,
+            className: request.className,
+            output: request.output,
+        });
+        if (request.dryRun === true) {
+            return { ...scaffold, written: false };
+        }
+        assertWritable(readOnly, 'Implementation scaffolding');
+        const filePath = join(scaffoldBasePath, scaffold.file);
+        await mkdir(dirname(filePath), { recursive: true });
+        try {
+            await writeFile(filePath, scaffold.content, { encoding: 'utf8', flag: request.overwrite === true ? 'w' : 'wx' });
+        }
+        catch (error) {
+            if (error.code === 'EEXIST') {
+                throw new Error(`File already exists: ${scaffold.file}`);
+            }
+            throw error;
+        }
+        return { ...scaffold, written: true };
+    };
+    const analyzeWorkspaceTestImpact = async (request) => {
+        const impactBasePath = request?.basePath ?? basePath;
+        const impact = await analyzeTestImpact({ basePath: impactBasePath, base: request?.base });
+        return request?.run === true ? { ...impact, run: await runImpactedTests(impactBasePath, impact) } : impact;
+    };
+    const runMaintenanceTask = async (task, options) => {
+        const startedAt = Date.now();
+        const config = await readMaintenanceConfig(options.basePath);
+        const done = (summary, details) => ({
+            task,
+            success: true,
+            summary,
+            details,
+            durationMs: Date.now() - startedAt,
+        });
+        const verb = (past, planned) => (options.dryRun ? planned : past);
+        try {
+            switch (task) {
+                case 'index': {
+                    if (await readCodeIndex(options.basePath) === undefined) {
+                        return done('No code index to refresh.', { refreshed: false });
+                    }
+                    if (options.dryRun) {
+                        return done('Would rebuild the code index.', { refreshed: false });
+                    }
+                    const result = await requestIndexServer(options.basePath, 'index', {})
+                        ?? await buildCodeIndex({ basePath: options.basePath });
+                    return done(`Re-indexed ${result.filesIndexed} file(s) with ${result.symbolCount} symbol(s).`, { refreshed: true, ...result });
+                }
+                case 'memory': {
+                    const details = await pruneMemoryEntries(stateStore, config, options);
+                    const count = details.evicted.length;
+                    return done(`${verb('Deleted', 'Would delete')} ${count} memory entr${count === 1 ? 'y' : 'ies'}${count > 0 ? ` (${summarizeMemoryEvictions(details.evicted)})` : ''}.`, details);
+                }
+                case 'logs': {
+                    const details = await rotateLogs(options.basePath, config, options);
+                    const rotated = details.rotated;
+                    const removed = details.removed;
+                    return done(`${verb('Rotated', 'Would rotate')} ${rotated.length} log(s) and ${verb('removed', 'remove')} ${removed.length} old run file(s).`, details);
+                }
+                case 'providers': {
+                    const providers = await checkProviderHealth(options.basePath, resolveProviderBridge(options.basePath));
+                    const healthy = providers.filter((provider) => provider.status === 'ok').map((provider) => provider.provider);
+                    return done(`${healthy.length} of ${providers.length} provider(s) healthy${healthy.length > 0 ? ` (${healthy.join(', ')})` : ''}.`, { providers });
+                }
+                case 'caches': {
+                    const details = await pruneCaches(options.basePath, config, options);
+                    const removed = details.removed;
+                    return done(`${verb('Pruned', 'Would prune')} ${removed.length} cached artifact(s) older than ${config.cacheMaxAgeDays} day(s).`, details);
+                }
+            }
+        }
+        catch (error) {
+            const message = error instanceof Error ? error.message : String(error);
+            return { task, success: false, summary: `Failed: ${message}`, error: message, durationMs: Date.now() - startedAt };
+        }
+    };
+    const maintenanceTools = Object.fromEntries(MAINTENANCE_TASKS.map((task) => [
+        `maintain.${task}`,
+        (workflowBasePath) => async (args) => {
+            const result = await runMaintenanceTask(task, { dryRun: args.dryRun === true, basePath: workflowBasePath, now: new Date() });
+            if (!result.success) {
+                throw new Error(`Maintenance task ${task} failed: ${result.error}`);
+            }
+            return result;
+        },
+    ]));
+    // An agent's metadata with its bases layered in, so a provider a base sets applies; the agent's own
+    // when its profile does not resolve, as its run then fails anyway.
+    const layeredMetadata = async (agentId, root) => {
+        const agent = await stateStore.getAgent(agentId);
+        return agent === undefined
+            ? undefined
+            : (await resolveAgentProfile(agent, (baseId) => stateStore.getAgent(baseId), root).catch(() => ({ agent }))).agent.metadata;
+    };
+    // The checkpoint is saved before the first stage and after each one, so a run that dies (a crash,
+    // a reboot, Ctrl+C) can be resumed from the stages it had completed.
+    const runCheckpointedPipeline = async (pipeline, run, runAgent) => {
+        const { checkpoint } = run;
+        const traceId = checkpoint.runId;
+        const startedAtMs = Date.now();
+        const resumed = checkpoint.stages.length > 0;
+        const traceInput = { file: checkpoint.file, pipeline: pipeline.name, input: checkpoint.input };
+        const metadata = { sessionId: checkpoint.sessionId, command: 'pipeline.run', ...(resumed ? { resumedAt: new Date().toISOString() } : {}) };
+        const warnings = [];
+        const finished = new Map(checkpoint.stages.filter((stage) => stage.status === 'succeeded').map((stage) => [stage.id, stage]));
+        let saves = Promise.resolve();
+        const save = (status) => {
+            const snapshot = { ...checkpoint, status, updatedAt: new Date().toISOString(), stages: [...finished.values()] };
+            saves = saves.then(() => writePipelineCheckpoint(run.basePath, snapshot)).catch((error) => {
+                warnings.push(`The checkpoint could not be saved: ${error instanceof Error ? error.message : String(error)}`);
+            });
+            return saves;
+        };
+        await traceStore.upsertTrace({
+            traceId,
+            workflowId: 'pipeline.run',
+            surface: run.surface ?? 'cli',
+            status: 'running',
+            startedAt: checkpoint.startedAt,
+            input: traceInput,
+            stepResults: [],
+            metadata,
+        });
+        await save('running');
+        const concurrency = readConcurrencySettings(await readWorkspaceConfig(run.basePath));
+        // As with parallel tasks, a stage counts against the limit of the provider it runs on.
+        const providers = new Map(await Promise.all(pipeline.stages.map(async (stage) => {
+            const provider = stage.provider ?? checkpoint.provider;
+            const agentMetadata = provider === undefined ? await layeredMetadata(stage.agent, run.basePath) : undefined;
+            return [stage.id, provider ?? (isRecord(agentMetadata) ? asOptionalString(agentMetadata.provider) : undefined) ?? 'claude'];
+        })));
+        // Stage runs are children of the pipeline's trace, so their provider calls are charged to its budget.
+        const budget = combineBudgets(pipeline.budget, checkpoint.budget);
+        const meter = budget === undefined ? undefined : createBudgetMeter(budget, run.signal);
+        if (meter !== undefined) {
+            budgetMeters.set(traceId, meter);
+        }
+        // The stages' provider calls are logged for `replayRun`; a resumed run adds to its log.
+        const recording = await runRecorder.start(run.basePath, {
+            traceId,
+            command: 'pipeline.run',
+            request: { file: checkpoint.file, source: checkpoint.source, input: checkpoint.input, provider: checkpoint.provider, budget: checkpoint.budget },
+        }, { resume: resumed });
+        if (recording !== undefined) {
+            warnings.push(recording);
+        }
+        const stagesRun = runPipelineStages(pipeline, {
+            basePath: run.basePath,
+            completed: [...finished.values()],
+            input: checkpoint.input,
+            artifactDir: pipelineRunDir(traceId),
+            limits: { maxConcurrent: concurrency.maxConcurrent, poolLimits: concurrency.perProvider },
+            poolOf: (stage) => providers.get(stage.id),
+            provider: checkpoint.provider,
+            traceId,
+            sessionId: checkpoint.sessionId,
+            surface: run.surface ?? 'cli',
+            signal: meter?.signal ?? run.signal,
+            onStageStart: run.onStageStart,
+            onStageEnd: (result) => {
+                finished.set(result.id, result);
+                void save('running');
+                run.onStageEnd?.(result);
+            },
+            onText: run.onText,
+        }, runAgent);
+        const stageReport = await stagesRun.finally(() => {
+            meter?.stop();
+            budgetMeters.delete(traceId);
+            runRecorder.finish(traceId);
+        });
+        const budgetReport = meter?.report();
+        const exceeded = budgetReport?.exceeded;
+        const report = exceeded === undefined
+            ? stageReport
+            : { ...stageReport, success: false, error: { code: BUDGET_EXCEEDED_CODE, message: describeExceeded(exceeded) } };
+        await save(report.success ? 'completed' : 'failed');
+        await traceStore.upsertTrace({
+            traceId,
+            workflowId: 'pipeline.run',
+            surface: run.surface ?? 'cli',
+            status: report.success ? 'completed' : 'failed',
+            startedAt: checkpoint.startedAt,
+            completedAt: new Date().toISOString(),
+            input: traceInput,
+            stepResults: report.stages.map((stage) => ({
+                stepId: stage.id,
+                success: stage.status !== 'failed',
+                durationMs: stage.durationMs,
+                retryCount: stage.runs.reduce((count, stageRun) => count + Math.max(0, stageRun.attempts - 1), 0),
+                error: stage.status === 'failed' ? stage.runs.at(-1)?.error?.message : undefined,
+            })),
+            output: report,
+            error: report.error,
+            metadata: { ...metadata, budget: budgetReport },
+        });
+        return { traceId, ...report, totalDurationMs: Date.now() - startedAtMs, warnings, budget: budgetReport };
+    };
     return {
+        async listProviderModels(request = {}) {
+            const runtimeProviderBridge = resolveProviderBridge(request.basePath);
+            const provider = request.provider ?? (await runtimeProviderBridge.selectProvider('claude')).provider;
+            return runtimeProviderBridge.listModels(provider);
+        },
         async callProvider(request) {
             const runtimeProviderBridge = resolveProviderBridge(request.basePath);
             const traceId = request.traceId ?? randomUUID();
             const startedAt = new Date().toISOString();
-            const resolvedProvider = request.provider ?? 'claude';
+            const routing = request.provider === undefined
+                ? await runtimeProviderBridge.selectProvider('claude')
+                : { provider: request.provider, warnings: [] };
+            const resolvedProvider = routing.provider;
             await traceStore.upsertTrace({
                 traceId,
                 workflowId: 'call',
@@ -184,17 +500,31 @@ export function createSharedRuntimeService(config = {}) {
                     command: 'call',
                 },
             });
-            const bridgeResult = await runtimeProviderBridge.executePrompt({
+            const executionRequest = {
                 provider: resolvedProvider,
                 prompt: request.prompt,
                 systemPrompt: request.systemPrompt,
                 model: request.model ?? 'v14-direct-call',
                 maxTokens: request.maxTokens,
                 temperature: request.temperature,
-            });
+                stopSequences: request.stopSequences,
+                traceId,
+                noCache: request.noCache,
+                onText: request.onText,
+                responseFormat: request.responseFormat,
+            };
+            const bridgeResult = request.outputSchema !== undefined
+                ? await runtimeProviderBridge.executeStructured(executionRequest, request.outputSchema, request.consensus)
+                : request.consensus === undefined
+                    ? await runtimeProviderBridge.executePrompt(executionRequest)
+                    : await runtimeProviderBridge.executeConsensus(executionRequest, request.consensus);
             const completedAt = new Date().toISOString();
             if (bridgeResult.type === 'response' || bridgeResult.type === 'failure') {
-                const warnings = bridgeResult.type === 'failure' ? [bridgeResult.response.error ?? 'Provider execution failed.'] : [];
+                const warnings = [
+                    ...routing.warnings,
+                    ...(bridgeResult.response.warnings ?? []),
+                    ...(bridgeResult.type === 'failure' ? [bridgeResult.response.error ?? 'Provider execution failed.'] : []),
+                ];
                 await traceStore.upsertTrace({
                     traceId,
                     workflowId: 'call',
@@ -234,6 +564,10 @@ export function createSharedRuntimeService(config = {}) {
                         provider: bridgeResult.response.provider,
                         model: bridgeResult.response.model,
                         command: 'call',
+                        cache: bridgeResult.response.cache,
+                        providerFailovers: bridgeResult.response.failovers,
+                        consensus: bridgeResult.response.consensus,
+                        structuredOutput: bridgeResult.response.structured,
                     },
                 });
                 return {
@@ -246,6 +580,10 @@ export function createSharedRuntimeService(config = {}) {
                     executionMode: 'subprocess',
                     warnings,
                     usage: bridgeResult.response.usage,
+                    cache: bridgeResult.response.cache,
+                    failovers: bridgeResult.response.failovers,
+                    consensus: bridgeResult.response.consensus,
+                    structured: bridgeResult.response.structured,
                     error: bridgeResult.response.success ? undefined : {
                         code: bridgeResult.response.errorCode,
                         message: bridgeResult.response.error,
@@ -261,7 +599,7 @@ export function createSharedRuntimeService(config = {}) {
                 outputTokens: tokenize(content),
                 totalTokens: tokenize(request.prompt) + tokenize(content),
             };
-            const warnings = [`No provider executor configured for "${resolvedProvider}". Returned simulated output.`];
+            const warnings = [...routing.warnings, `No provider executor configured for "${resolvedProvider}". Returned simulated output.`];
             await traceStore.upsertTrace({
                 traceId,
                 workflowId: 'call',
@@ -315,7 +653,7 @@ export function createSharedRuntimeService(config = {}) {
             const runtimeDiscussionCoordinator = resolveDiscussionCoordinator(request.basePath);
             const workflowDir = resolveWorkflowDir(request.workflowDir, request.basePath, basePath);
             const loader = createWorkflowLoader({ workflowsDir: workflowDir });
-            const workflow = await loader.load(request.workflowId);
+            const workflow = await loader.load(request.workflowId) ?? getBuiltinWorkflow(request.workflowId);
             if (workflow === undefined) {
                 const traceId = request.traceId ?? randomUUID();
                 const failed = {
@@ -343,6 +681,37 @@ export function createSharedRuntimeService(config = {}) {
                     workflowDir,
                 };
             }
+            // A workflow that claims a task of its session's scratchpad holds it while it runs, then posts how it ended.
+            if (request.claim !== undefined) {
+                const traceId = request.traceId ?? randomUUID();
+                let claimed;
+                try {
+                    claimed = await claimTaskForRun(stateStore, request.sessionId, request.claim, { claimant: request.workflowId, traceId });
+                }
+                catch (error) {
+                    const failed = {
+                        traceId,
+                        workflowId: request.workflowId,
+                        surface: request.surface ?? 'cli',
+                        status: 'failed',
+                        startedAt: new Date().toISOString(),
+                        completedAt: new Date().toISOString(),
+                        input: request.input,
+                        stepResults: [],
+                        error: {
+                            code: error.code ?? SCRATCHPAD_CONFLICT_CODE,
+                            message: error instanceof Error ? error.message : String(error),
+                        },
+                        metadata: { sessionId: request.sessionId },
+                    };
+                    await traceStore.upsertTrace(failed);
+                    return { traceId, workflowId: request.workflowId, success: false, stepResults: [], error: failed.error, totalDurationMs: 0, workflowDir };
+                }
+                const input = request.input?.task === undefined ? { ...request.input, task: claimed.title } : request.input;
+                const response = await this.runWorkflow({ ...request, traceId, claim: undefined, input });
+                const posted = await postRunResult(stateStore, claimed, response.success, response.success ? describeWorkflowOutput(response.output) : response.error?.message ?? 'The workflow failed.');
+                return { ...response, claimed: posted.task, ...(posted.warnings.length > 0 ? { warnings: posted.warnings } : {}) };
+            }
             const traceId = request.traceId ?? randomUUID();
             const startedAt = new Date().toISOString();
             await traceStore.upsertTrace({
@@ -360,18 +729,118 @@ export function createSharedRuntimeService(config = {}) {
                     sessionId: request.sessionId,
                 },
             });
+            const providerFailovers = [];
+            const concurrency = readConcurrencySettings(await readWorkspaceConfig(request.basePath ?? basePath));
+            // The steps' provider calls are charged to the run's budget; the meter's signal stops the ones in flight.
+            const meter = request.budget === undefined ? undefined : createBudgetMeter(request.budget);
+            if (meter !== undefined) {
+                budgetMeters.set(traceId, meter);
+            }
             const runner = createWorkflowRunner({
                 executionId: traceId,
                 agentId: request.surface ?? 'cli',
+                // Steps that declare `dependencies` run as a graph, bounded overall and per provider.
+                maxConcurrentSteps: concurrency.maxConcurrent,
+                concurrencyPool: (step) => workflowStepProvider(step, request.provider),
+                poolLimits: concurrency.perProvider,
+                onStepStart: request.onStepStart === undefined ? undefined : (step) => request.onStepStart?.(step.stepId),
+                onStepComplete: request.onStepComplete === undefined ? undefined : (step, stepResult) => request.onStepComplete?.({
+                    stepId: step.stepId,
+                    success: stepResult.success,
+                    durationMs: stepResult.durationMs,
+                    error: stepResult.error?.message,
+                }),
                 stepExecutor: createRealStepExecutor({
-                    promptExecutor: createPromptExecutor(runtimeProviderBridge, request.provider, request.model),
-                    toolExecutor: createToolExecutor(),
+                    promptExecutor: createPromptExecutor(runtimeProviderBridge, {
+                        provider: request.provider,
+                        model: request.model,
+                        traceId,
+                        noCache: request.noCache,
+                        signal: meter?.signal,
+                        onText: request.onText,
+                        onFailover: (failover, stepId) => {
+                            const event = { ...failover, ...(stepId !== undefined ? { stepId } : {}) };
+                            providerFailovers.push(event);
+                            request.onProviderFailover?.(event);
+                        },
+                    }),
+                    toolExecutor: createToolExecutor(readOnly, {
+                        // Lets a workflow stage scaffold stubs from an indexed interface before an agent fills them in.
+                        'scaffold.impl': (args) => scaffoldImplementation({
+                            symbol: String(args.symbol ?? ''),
+                            className: typeof args.className === 'string' ? args.className : undefined,
+                            output: typeof args.output === 'string' ? args.output : undefined,
+                            dryRun: args.dryRun === true,
+                            overwrite: args.overwrite === true,
+                            basePath: request.basePath ?? basePath,
+                        }),
+                        // Puts the API surface of many files into a prompt: signatures only, bodies elided.
+                        'code.signatures': (args) => renderSignatures({
+                            paths: Array.isArray(args.paths) ? args.paths.map(String) : [],
+                            basePath: request.basePath ?? basePath,
+                        }),
+                        // A documentation stage starts from the symbol tree as Markdown.
+                        'code.outline': (args) => renderOutline({
+                            paths: Array.isArray(args.paths) ? args.paths.map(String) : [],
+                            basePath: request.basePath ?? basePath,
+                        }),
+                        // Lets an architecture review draw the import or call graph it is reasoning about.
+                        'code.graph': (args) => exportCodeGraph({
+                            graph: args.graph === 'calls' ? 'calls' : 'modules',
+                            format: args.format === 'dot' ? 'dot' : 'mermaid',
+                            paths: Array.isArray(args.paths) ? args.paths.map(String) : undefined,
+                            basePath: request.basePath ?? basePath,
+                        }),
+                        // Lets an agent find a symbol it only half remembers: `DatProc`, `kind:trait Handler`.
+                        'code.search': (args) => searchSymbolIndex({
+                            query: String(args.query ?? ''),
+                            limit: typeof args.limit === 'number' ? args.limit : undefined,
+                            basePath: request.basePath ?? basePath,
+                        }),
+                        // Greps the workspace through the trigram index instead of reading every file.
+                        'code.grep': (args) => searchText({
+                            pattern: String(args.pattern ?? ''),
+                            regex: args.regex === true,
+                            caseSensitive: args.caseSensitive === true,
+                            paths: Array.isArray(args.paths) ? args.paths.map(String) : undefined,
+                            limit: typeof args.limit === 'number' ? args.limit : undefined,
+                            basePath: request.basePath ?? basePath,
+                        }),
+                        // Gives a cleanup stage its starting list of unreferenced symbols.
+                        'code.unreferenced': (args) => findUnreferencedSymbols({
+                            paths: Array.isArray(args.paths) ? args.paths.map(String) : undefined,
+                            kind: typeof args.kind === 'string' ? args.kind : undefined,
+                            limit: typeof args.limit === 'number' ? args.limit : undefined,
+                            basePath: request.basePath ?? basePath,
+                        }),
+                        // Validation stage: runs only the tests the working-tree changes can affect and fails the step when they fail.
+                        'test.impacted': async (args) => {
+                            const impact = await analyzeWorkspaceTestImpact({
+                                base: typeof args.base === 'string' ? args.base : undefined,
+                                run: args.run !== false,
+                                basePath: request.basePath ?? basePath,
+                            });
+                            if (impact.run !== undefined && !impact.run.passed) {
+                                throw new Error(`Impacted tests failed (exit ${impact.run.exitCode}): ${impact.impactedTests.map((test) => test.file).join(', ')}`);
+                            }
+                            return impact;
+                        },
+                        ...Object.fromEntries(Object.entries(maintenanceTools).map(([toolName, bind]) => [toolName, bind(request.basePath ?? basePath)])),
+                    }),
                     discussionExecutor: createDiscussionExecutor(traceId, request.provider, runtimeDiscussionCoordinator),
                     defaultProvider: request.provider ?? 'claude',
                     defaultModel: request.model ?? 'v14-shared-runtime',
                 }),
             });
-            const result = await runner.run(workflow, request.input ?? {});
+            const run = await runner.run(workflow, request.input ?? {}).finally(() => {
+                meter?.stop();
+                budgetMeters.delete(traceId);
+            });
+            const budgetReport = meter?.report();
+            const exceeded = budgetReport?.exceeded;
+            const result = exceeded === undefined
+                ? run
+                : { ...run, success: false, error: { code: BUDGET_EXCEEDED_CODE, message: describeExceeded(exceeded) } };
             const completedAt = new Date().toISOString();
             await traceStore.upsertTrace({
                 traceId,
@@ -396,6 +865,8 @@ export function createSharedRuntimeService(config = {}) {
                     model: request.model,
                     totalDurationMs: result.totalDurationMs,
                     sessionId: request.sessionId,
+                    ...(providerFailovers.length > 0 ? { providerFailovers } : {}),
+                    ...(budgetReport !== undefined ? { budget: budgetReport } : {}),
                 },
             });
             return {
@@ -407,6 +878,7 @@ export function createSharedRuntimeService(config = {}) {
                 error: result.error,
                 totalDurationMs: result.totalDurationMs,
                 workflowDir,
+                ...(budgetReport !== undefined ? { budget: budgetReport } : {}),
             };
         },
         async runDiscussion(request) {
@@ -649,13 +1121,9 @@ export function createSharedRuntimeService(config = {}) {
         async runAgent(request) {
             const runtimeProviderBridge = resolveProviderBridge(request.basePath);
             const traceId = request.traceId ?? randomUUID();
-            const agent = await stateStore.getAgent(request.agentId);
+            const registered = await stateStore.getAgent(request.agentId);
             const startedAt = new Date().toISOString();
-            if (agent === undefined) {
-                const error = {
-                    code: 'AGENT_NOT_FOUND',
-                    message: `Agent "${request.agentId}" is not registered.`,
-                };
+            const rejectRun = async (error) => {
                 await traceStore.upsertTrace({
                     traceId,
                     workflowId: 'agent.run',
@@ -667,6 +1135,8 @@ export function createSharedRuntimeService(config = {}) {
                         agentId: request.agentId,
                         task: request.task,
                         input: request.input,
+                        scope: request.scope,
+                        files: request.files,
                     },
                     stepResults: [],
                     error,
@@ -689,13 +1159,160 @@ export function createSharedRuntimeService(config = {}) {
                     warnings: [],
                     error,
                 };
+            };
+            if (registered === undefined) {
+                return rejectRun({ code: 'AGENT_NOT_FOUND', message: `Agent "${request.agentId}" is not registered.` });
+            }
+            // A run that claims a task of its session's scratchpad holds it while it runs, then posts how it ended.
+            if (request.claim !== undefined) {
+                let claimed;
+                try {
+                    claimed = await claimTaskForRun(stateStore, request.sessionId, request.claim, { claimant: request.agentId, traceId });
+                }
+                catch (error) {
+                    return rejectRun({
+                        code: error.code ?? SCRATCHPAD_CONFLICT_CODE,
+                        message: error instanceof Error ? error.message : String(error),
+                    });
+                }
+                const response = await this.runAgent({ ...request, traceId, claim: undefined, task: request.task ?? claimed.title });
+                const posted = await postRunResult(stateStore, claimed, response.success, response.success ? response.content : response.error?.message ?? 'The run failed.');
+                return { ...response, claimed: posted.task, warnings: [...response.warnings, ...posted.warnings] };
+            }
+            // The profile runs with its `extends` bases and `abilityPacks` layered in.
+            let profile;
+            try {
+                profile = await resolveAgentProfile(registered, (agentId) => stateStore.getAgent(agentId), request.basePath ?? basePath);
             }
+            catch (error) {
+                return rejectRun({
+                    code: error.code ?? AGENT_PROFILE_INVALID_CODE,
+                    message: error instanceof Error ? error.message : String(error),
+                });
+            }
+            const { agent } = profile;
+            const layers = profileLayers(profile);
             const metadata = isRecord(agent.metadata) ? agent.metadata : {};
-            const resolvedProvider = request.provider ?? asOptionalString(metadata.provider) ?? 'claude';
+            // A top-level run in a session continues its thread: the conversation so far goes with the
+            // task, and the session's scope and memory namespace apply unless the request sets a scope.
+            const thread = request.sessionId === undefined || request.parentTraceId !== undefined
+                ? undefined
+                : await readSessionThread(request.basePath ?? basePath, request.sessionId);
+            const scope = request.scope ?? thread?.scope;
+            let workspace;
+            try {
+                const permissions = readAgentPermissions(metadata, agent.agentId);
+                workspace = await resolveAgentWorkspace(request.basePath ?? basePath, { scope, files: request.files, permissions });
+            }
+            catch (error) {
+                return rejectRun({
+                    code: error.code ?? AGENT_SCOPE_ERROR_CODE,
+                    message: error instanceof Error ? error.message : String(error),
+                });
+            }
+            let budget;
+            try {
+                budget = combineBudgets(metadata.budget === undefined ? undefined : parseRunBudget(metadata.budget, `The budget of agent "${agent.agentId}"`), request.budget);
+            }
+            catch (error) {
+                return rejectRun({
+                    code: error.code ?? BUDGET_INVALID_CODE,
+                    message: error instanceof Error ? error.message : String(error),
+                });
+            }
+            let approvals;
+            let approvalPolicy;
+            try {
+                approvals = readApprovalGates(metadata, agent.agentId);
+                approvalPolicy = approvals === undefined ? undefined : await loadApprovalPolicy(request.basePath ?? basePath, request.approvalPolicy);
+            }
+            catch (error) {
+                return rejectRun({
+                    code: error.code ?? APPROVALS_INVALID_CODE,
+                    message: error instanceof Error ? error.message : String(error),
+                });
+            }
+            const pinnedProvider = request.provider ?? asOptionalString(metadata.provider);
+            // An agent profile's `routing` overrides the workspace policy for the agent's tasks.
+            const routing = pinnedProvider === undefined
+                ? await runtimeProviderBridge.selectProvider('claude', parseRoutingOptions(metadata.routing))
+                : { provider: pinnedProvider, warnings: [] };
+            const resolvedProvider = routing.provider;
             const resolvedModel = request.model ?? asOptionalString(metadata.model) ?? 'v14-agent-run';
             const task = resolveAgentTask(request.task, request.input, agent);
-            const prompt = buildAgentPrompt(agent, task, request.input, metadata);
-            const systemPrompt = resolveAgentSystemPrompt(agent, metadata);
+            let systemPrompt;
+            let promptWarnings;
+            try {
+                ({ systemPrompt, warnings: promptWarnings } = await renderAgentSystemPrompt(agent, metadata, task, request.input, request.basePath ?? basePath, profile.abilities));
+            }
+            catch (error) {
+                return rejectRun({
+                    code: error.code ?? PROMPT_TEMPLATE_ERROR_CODE,
+                    message: error instanceof Error ? error.message : String(error),
+                });
+            }
+            const resolution = await runtimeProviderBridge.describeResolution(resolvedProvider);
+            // A CLI executor writes files and runs commands itself, before anyone could be asked about them.
+            const unheld = resolution.command === undefined
+                ? []
+                : unheldApprovals(approvals, { readOnly, shell: workspace.permissions?.shell !== false });
+            if (unheld.length > 0) {
+                return rejectRun({
+                    code: APPROVALS_UNSUPPORTED_CODE,
+                    message: `Agent "${agent.agentId}" holds ${unheld.join(' and ')} for approval, but provider "${resolvedProvider}" runs the CLI "${resolution.command}", which cannot wait for approval; configure an API executor for it.`,
+                });
+            }
+            // The session's memory: entries matching the task first, then the rest of its namespace.
+            const memory = thread?.memoryNamespace === undefined
+                ? []
+                : [...new Map([
+                        ...await stateStore.searchMemory(task, thread.memoryNamespace),
+                        ...await stateStore.listMemory(thread.memoryNamespace),
+                    ].map((entry) => [entry.key, entry])).values()].slice(0, DEFAULT_CONTEXT_MEMORY_HITS);
+            // Any run in a session, handed work included, sees what the session's concurrent runs hold.
+            const scratchpad = request.sessionId === undefined
+                ? undefined
+                : describeScratchpad(await stateStore.listScratchpad(request.sessionId), [traceId, request.parentTraceId, request.rootTraceId].filter((id) => id !== undefined));
+            // Attached files are trimmed to the provider's context window rather than cut blindly by it.
+            const context = assembleContext({
+                systemPrompt,
+                memory: [
+                    ...(scratchpad === undefined ? [] : [{ key: 'session scratchpad', content: scratchpad }]),
+                    ...memory.map((entry) => ({ key: entry.key, content: memoryText(entry.value) })),
+                ],
+                files: workspace.attachments,
+                history: thread?.turns.map((turn) => ({ role: turn.role, content: turn.content })),
+                task: buildAgentPrompt(agent, task, request.input, metadata, { ...workspace, attachments: [] }),
+            }, {
+                ...readContextBudgetSettings(await readWorkspaceConfig(request.basePath ?? basePath)),
+                maxTokens: resolution.capabilities?.maxContextTokens,
+            }, workspace.attachments.length > 0 ? (await loadWorkspaceLanguageRegistry(request.basePath ?? basePath)).registry : undefined);
+            const prompt = context.prompt;
+            promptWarnings.push(...describeContextCuts(context.report));
+            // A successful run adds its task and reply to the session's thread; the first one also sets
+            // the session's agent, and its scope when it had one.
+            const continueThread = async (content) => {
+                if (thread === undefined) {
+                    return [];
+                }
+                const at = new Date().toISOString();
+                try {
+                    await updateSessionThread(request.basePath ?? basePath, thread.sessionId, (stored) => ({
+                        ...stored,
+                        agentId: stored.agentId ?? agent.agentId,
+                        scope: stored.scope ?? scope,
+                        turns: [
+                            ...stored.turns,
+                            { role: 'user', content: task, agentId: agent.agentId, traceId, at },
+                            { role: 'assistant', content, agentId: agent.agentId, traceId, at },
+                        ],
+                    }));
+                    return [];
+                }
+                catch (error) {
+                    return [`Could not add the run to session "${thread.sessionId}": ${error instanceof Error ? error.message : String(error)}`];
+                }
+            };
             await traceStore.upsertTrace({
                 traceId,
                 workflowId: 'agent.run',
@@ -706,6 +1323,8 @@ export function createSharedRuntimeService(config = {}) {
                     agentId: agent.agentId,
                     task,
                     input: request.input,
+                    scope,
+                    files: request.files,
                 },
                 stepResults: [],
                 metadata: {
@@ -717,37 +1336,190 @@ export function createSharedRuntimeService(config = {}) {
                     model: resolvedModel,
                     capabilities: agent.capabilities,
                     command: 'agent.run',
+                    profile: layers,
                 },
             });
-            const bridgeResult = await runtimeProviderBridge.executePrompt({
+            request.onProgress?.(10, `Running agent "${agent.agentId}" on ${resolvedProvider}`);
+            // Workspace tools refuse what the permissions do not allow. A CLI executor has its own file
+            // access, and `run_command` lets an API executor change files, so the files a run changes are
+            // compared with the permissions and the write gates once it is done. The checkpoint keeps the
+            // files outside the paths, and those the gates hold, so what the run changed there can be put back.
+            const { permissions } = workspace;
+            const refusals = [];
+            const changesFiles = resolution.command !== undefined || offersAgentCommands(workspace, readOnly);
+            const audit = changesFiles && (permissions !== undefined || approvals?.writes !== undefined)
+                ? await createWorkspaceCheckpoint(request.basePath ?? basePath, { whole: true, keep: (file) => !permitsPath(permissions, file) || gatesWrite(approvals, file) })
+                : undefined;
+            if (permissions !== undefined && resolution.command !== undefined && (!permissions.shell || !permissions.network) && resolution.protocol !== 'json-stdio' && resolution.restrictedByFlags !== true) {
+                promptWarnings.push(`Provider "${resolvedProvider}" learns the agent's permissions only from ${AGENT_PERMISSIONS_ENV_VAR}; its shell and network use cannot be checked.`);
+            }
+            // Actions the profile's `approvals` hold wait for the approval policy or `request.approve`; a
+            // run over its cost threshold pauses when the call that went over is charged, and stops if refused.
+            const approvalDecisions = [];
+            const approve = createApprovalGate({
+                policy: approvalPolicy,
+                approver: request.approve,
+                onDecision: (record) => approvalDecisions.push(record),
+            });
+            const costGate = approvals?.costUsd === undefined
+                ? undefined
+                : createCostGate(approvals.costUsd, (spent) => approve({ action: 'cost', agentId: agent.agentId, target: `$${spent.toFixed(4)}`, costUsd: spent, traceId }), request.signal);
+            if (costGate !== undefined) {
+                costGates.set(traceId, costGate);
+            }
+            const approveCommand = approvals?.shell === true
+                ? (command) => approve({ action: 'shell', agentId: agent.agentId, target: command, traceId })
+                : undefined;
+            // Provider calls of this run and of the runs it hands work to are charged to its budget, and to
+            // those of the runs above it; the meter's signal stops the run when one is used up.
+            const meter = budget === undefined ? undefined : createBudgetMeter(budget, costGate?.signal ?? request.signal);
+            const signal = meter?.signal ?? costGate?.signal ?? request.signal;
+            if (meter !== undefined) {
+                budgetMeters.set(traceId, meter);
+            }
+            if (request.parentTraceId !== undefined) {
+                runParents.set(traceId, request.parentTraceId);
+            }
+            // The agents of the profile's `handoffs` are tools; each handoff is traced as a child of this run.
+            const delegationChain = [...(request.delegationChain ?? []), agent.agentId];
+            const handoffTools = createHandoffTools(readAgentHandoffs(metadata), (handoff, subtask, input) => this.delegateTask({
+                agentId: handoff.agentId,
+                task: subtask,
+                input,
+                contract: handoff,
+                fromAgentId: agent.agentId,
+                delegationChain,
+                sessionId: request.sessionId,
+                basePath: request.basePath,
+                surface: request.surface,
+                parentTraceId: traceId,
+                rootTraceId: request.rootTraceId ?? traceId,
+                signal,
+                approve: request.approve,
+                approvalPolicy: request.approvalPolicy,
+            }));
+            // A top-level run logs its provider calls, and those of the runs it hands work to, for `replayRun`.
+            if (request.parentTraceId === undefined && request.rootTraceId === undefined) {
+                const warning = await runRecorder.start(request.basePath ?? basePath, {
+                    traceId,
+                    command: 'agent.run',
+                    request: {
+                        agentId: request.agentId,
+                        task: request.task,
+                        input: request.input,
+                        scope,
+                        files: request.files,
+                        provider: request.provider,
+                        model: request.model,
+                        timeoutMs: request.timeoutMs,
+                        budget: request.budget,
+                        outputSchema: request.outputSchema,
+                    },
+                });
+                if (warning !== undefined) {
+                    promptWarnings.push(warning);
+                }
+            }
+            const executionRequest = {
                 provider: resolvedProvider,
                 prompt,
                 systemPrompt,
                 model: resolvedModel,
                 timeoutMs: request.timeoutMs,
+                signal,
+                scope: workspace.scope,
+                permissions,
+                tools: [
+                    ...createAgentWorkspaceTools(request.basePath ?? basePath, workspace, { onViolation: (violation) => refusals.push(violation), approveCommand, readOnly }),
+                    ...handoffTools,
+                ],
+                agentId: agent.agentId,
+                traceId,
+                rootTraceId: request.rootTraceId,
+                noCache: request.noCache,
+                onText: request.onText,
+            };
+            const execution = request.outputSchema === undefined
+                ? runtimeProviderBridge.executePrompt(executionRequest)
+                : runtimeProviderBridge.executeStructured(executionRequest, request.outputSchema);
+            const bridgeResult = await execution.finally(() => {
+                meter?.stop();
+                costGate?.stop();
+                budgetMeters.delete(traceId);
+                costGates.delete(traceId);
+                runParents.delete(traceId);
+                runRecorder.finish(traceId);
             });
+            const budgetReport = meter?.report();
+            const after = audit === undefined ? undefined : await snapshotWholeWorkspace(request.basePath ?? basePath);
+            const breaches = audit === undefined || after === undefined || permissions === undefined
+                ? []
+                : findPathViolations(audit.stamps, after, permissions);
+            // Writes the gates hold are put to the approver once the run is done, as its spending is; a
+            // refused one fails the run.
+            const refusedWrites = [];
+            for (const file of audit === undefined || after === undefined ? [] : listChangedFiles(audit.stamps, after)) {
+                if (gatesWrite(approvals, file) && permitsPath(permissions, file) && !await approve({ action: 'write', agentId: agent.agentId, target: file, traceId })) {
+                    refusedWrites.push(file);
+                }
+            }
+            // What the run changed outside its paths, or wrote without approval, is put back as it was before the run.
+            const rollback = audit === undefined || breaches.length + refusedWrites.length === 0
+                ? undefined
+                : await restoreWorkspaceCheckpoint(audit, { files: [...breaches.map((violation) => violation.target), ...refusedWrites] });
+            const approvalRefusal = approvalDecisions.find((record) => record.action !== 'shell' && !record.approved);
+            const violations = [...refusals, ...breaches];
             const completedAt = new Date().toISOString();
+            request.onProgress?.(90, 'Recording the trace');
             if (bridgeResult.type === 'response' || bridgeResult.type === 'failure') {
-                const warnings = bridgeResult.type === 'failure' ? [bridgeResult.response.error ?? 'Agent execution failed.'] : [];
+                // A run that went over its budget or its permissions, or whose spending or writes were not
+                // approved, fails even when the call that did it returned; its content is kept. Refused tool
+                // calls are only reported.
+                const exceeded = budgetReport?.exceeded;
+                const success = bridgeResult.response.success && exceeded === undefined && breaches.length === 0 && approvalRefusal === undefined;
+                const error = breaches.length > 0
+                    ? { code: AGENT_PERMISSION_DENIED_CODE, message: `Agent "${agent.agentId}" went beyond its permissions: ${breaches.map(describeViolation).join('; ')}.` }
+                    : approvalRefusal !== undefined
+                        ? { code: APPROVAL_DENIED_CODE, message: `Agent "${agent.agentId}" was not approved to ${describeApproval(approvalRefusal)}.` }
+                        : exceeded !== undefined
+                            ? { code: BUDGET_EXCEEDED_CODE, message: describeExceeded(exceeded) }
+                            : bridgeResult.response.success ? undefined : { code: bridgeResult.response.errorCode, message: bridgeResult.response.error };
+                const warnings = [
+                    ...routing.warnings,
+                    ...promptWarnings,
+                    ...(bridgeResult.response.warnings ?? []),
+                    ...(bridgeResult.type === 'failure' ? [bridgeResult.response.error ?? 'Agent execution failed.'] : []),
+                    ...violations.map((violation) => `Permission violation: ${describeViolation(violation)}.`),
+                    ...(rollback === undefined || rollback.restored.length === 0 ? [] : [`Put back as they were before the run: ${rollback.restored.join(', ')}.`]),
+                    ...(rollback === undefined || rollback.skipped.length === 0 ? [] : [`Could not put back, being too large or binary to keep: ${rollback.skipped.join(', ')}.`]),
+                    ...approvalDecisions
+                        .filter((record) => !record.approved)
+                        .map((record) => `Not approved: ${describeApproval(record)}${record.by === 'none' ? ' (nobody could be asked)' : ''}.`),
+                ];
+                if (success) {
+                    warnings.push(...await continueThread(bridgeResult.response.content ?? ''));
+                }
                 await traceStore.upsertTrace({
                     traceId,
                     workflowId: 'agent.run',
                     surface: request.surface ?? 'cli',
-                    status: bridgeResult.response.success ? 'completed' : 'failed',
+                    status: success ? 'completed' : 'failed',
                     startedAt,
                     completedAt,
                     input: {
                         agentId: agent.agentId,
                         task,
                         input: request.input,
+                        scope,
+                        files: request.files,
                     },
                     stepResults: [
                         {
                             stepId: 'agent-execution',
-                            success: bridgeResult.response.success,
+                            success,
                             durationMs: bridgeResult.response.latencyMs,
                             retryCount: 0,
-                            error: bridgeResult.response.error,
+                            error: error?.message,
                         },
                     ],
                     output: {
@@ -755,12 +1527,10 @@ export function createSharedRuntimeService(config = {}) {
                         content: bridgeResult.response.content ?? '',
                         usage: bridgeResult.response.usage,
                         executionMode: 'subprocess',
+                        toolCalls: bridgeResult.response.toolCalls,
                         warnings,
                     },
-                    error: bridgeResult.response.success ? undefined : {
-                        code: bridgeResult.response.errorCode,
-                        message: bridgeResult.response.error,
-                    },
+                    error,
                     metadata: {
                         sessionId: request.sessionId,
                         parentTraceId: request.parentTraceId,
@@ -770,12 +1540,22 @@ export function createSharedRuntimeService(config = {}) {
                         model: bridgeResult.response.model,
                         capabilities: agent.capabilities,
                         command: 'agent.run',
+                        routing: routing.routing,
+                        profile: layers,
+                        cache: bridgeResult.response.cache,
+                        contextBudget: context.report.cuts.length > 0 ? context.report : undefined,
+                        delegationChain: request.delegationChain,
+                        structuredOutput: bridgeResult.response.structured,
+                        budget: budgetReport,
+                        permissions,
+                        permissionViolations: violations.length > 0 ? violations : undefined,
+                        approvals: approvalDecisions.length > 0 ? approvalDecisions : undefined,
                     },
                 });
                 return {
                     traceId,
                     agentId: agent.agentId,
-                    success: bridgeResult.response.success,
+                    success,
                     provider: bridgeResult.response.provider,
                     model: bridgeResult.response.model,
                     content: bridgeResult.response.content ?? '',
@@ -783,14 +1563,19 @@ export function createSharedRuntimeService(config = {}) {
                     executionMode: 'subprocess',
                     warnings,
                     usage: bridgeResult.response.usage,
-                    error: bridgeResult.response.success ? undefined : {
-                        code: bridgeResult.response.errorCode,
-                        message: bridgeResult.response.error,
-                    },
+                    cache: bridgeResult.response.cache,
+                    structured: bridgeResult.response.structured,
+                    profile: layers,
+                    budget: budgetReport,
+                    permissions,
+                    permissionViolations: violations.length > 0 ? violations : undefined,
+                    approvals,
+                    approvalDecisions: approvalDecisions.length > 0 ? approvalDecisions : undefined,
+                    error,
                 };
             }
             const content = buildSimulatedAgentOutput(agent, task, request.input);
-            const warnings = [`No provider executor configured for "${resolvedProvider}". Returned simulated agent output.`];
+            const warnings = [...routing.warnings, ...promptWarnings, `No provider executor configured for "${resolvedProvider}". Returned simulated agent output.`, ...await continueThread(content)];
             const usage = {
                 inputTokens: tokenize(prompt),
                 outputTokens: tokenize(content),
@@ -807,6 +1592,8 @@ export function createSharedRuntimeService(config = {}) {
                     agentId: agent.agentId,
                     task,
                     input: request.input,
+                    scope,
+                    files: request.files,
                 },
                 stepResults: [
                     {
@@ -832,6 +1619,7 @@ export function createSharedRuntimeService(config = {}) {
                     model: resolvedModel,
                     capabilities: agent.capabilities,
                     command: 'agent.run',
+                    profile: layers,
                 },
             });
             return {
@@ -845,10 +1633,124 @@ export function createSharedRuntimeService(config = {}) {
                 executionMode: 'simulated',
                 warnings,
                 usage,
+                profile: layers,
+                budget: budgetReport,
+                permissions,
+                approvals,
+            };
+        },
+        async delegateTask(request) {
+            const refuse = (code, message) => ({
+                agentId: request.agentId,
+                success: false,
+                latencyMs: 0,
+                warnings: [],
+                error: { code, message },
+            });
+            const chain = request.delegationChain ?? (request.fromAgentId !== undefined ? [request.fromAgentId] : []);
+            const refusal = checkDelegationChain(chain, request.agentId);
+            if (refusal !== undefined) {
+                return refuse(refusal.code, refusal.message);
+            }
+            const inputErrors = validateHandoffInput(request.contract, request.input);
+            if (inputErrors.length > 0) {
+                return refuse('DELEGATION_INPUT_INVALID', `The input does not fit the contract: ${inputErrors.slice(0, 3).join('; ')}`);
+            }
+            const run = await this.runAgent({
+                agentId: request.agentId,
+                task: request.task,
+                input: request.input,
+                provider: request.contract.provider,
+                timeoutMs: request.contract.budget?.timeoutMs,
+                outputSchema: request.contract.output,
+                delegationChain: chain,
+                traceId: request.traceId,
+                sessionId: request.sessionId,
+                basePath: request.basePath,
+                surface: request.surface,
+                parentTraceId: request.parentTraceId,
+                rootTraceId: request.rootTraceId,
+                signal: request.signal,
+                approve: request.approve,
+                approvalPolicy: request.approvalPolicy,
+            });
+            const response = {
+                traceId: run.traceId,
+                agentId: run.agentId,
+                success: false,
+                provider: run.provider,
+                model: run.model,
+                structured: run.structured,
+                usage: run.usage,
+                latencyMs: run.latencyMs,
+                warnings: run.warnings,
+            };
+            if (!run.success) {
+                return {
+                    ...response,
+                    error: { code: run.error?.code ?? 'DELEGATION_FAILED', message: run.error?.message ?? `Agent "${request.agentId}" failed.` },
+                };
+            }
+            // A simulated run never saw the schema, so its reply cannot stand for the result.
+            if (run.executionMode === 'simulated' || run.structured?.valid !== true) {
+                return {
+                    ...response,
+                    error: {
+                        code: 'DELEGATION_OUTPUT_INVALID',
+                        message: run.executionMode === 'simulated'
+                            ? `No provider executor is configured for "${run.provider}", so the reply could not be held to the contract.`
+                            : 'The reply does not fit the contract\'s output schema.',
+                    },
+                };
+            }
+            const maxTokens = request.contract.budget?.maxTokens;
+            if (maxTokens !== undefined && run.usage !== undefined && run.usage.totalTokens > maxTokens) {
+                return {
+                    ...response,
+                    error: { code: 'DELEGATION_BUDGET_EXCEEDED', message: `Agent "${request.agentId}" used ${run.usage.totalTokens} tokens; the contract allows ${maxTokens}.` },
+                };
+            }
+            return { ...response, success: true, output: run.structured.value };
+        },
+        async assembleContext(request) {
+            const root = request.basePath ?? basePath;
+            const workspace = await resolveAgentWorkspace(root, { files: request.files });
+            const memory = request.memoryQuery === undefined
+                ? []
+                : (await this.searchMemory(request.memoryQuery, request.memoryNamespace)).slice(0, request.memoryLimit ?? DEFAULT_CONTEXT_MEMORY_HITS);
+            const maxTokens = request.maxTokens
+                ?? (await resolveProviderBridge(request.basePath).describeResolution(request.provider ?? 'claude')).capabilities?.maxContextTokens;
+            return assembleContext({
+                systemPrompt: request.systemPrompt,
+                memory: memory.map((entry) => ({ key: entry.key, content: memoryText(entry.value) })),
+                repoMap: request.repoMap === true ? (await renderRepoMap({ basePath: root })).text : undefined,
+                files: workspace.attachments,
+                history: request.history,
+                task: request.task,
+            }, {
+                ...readContextBudgetSettings(await readWorkspaceConfig(root)),
+                maxTokens,
+            }, (await loadWorkspaceLanguageRegistry(root)).registry);
+        },
+        async renderAgentPrompt(request) {
+            const profile = await this.resolveAgentProfile(request.agentId, request.basePath);
+            if (profile === undefined) {
+                return undefined;
+            }
+            const { agent } = profile;
+            const metadata = isRecord(agent.metadata) ? agent.metadata : {};
+            const task = resolveAgentTask(request.task, request.input, agent);
+            const rendered = await renderAgentSystemPrompt(agent, metadata, task, request.input, request.basePath ?? basePath, profile.abilities);
+            const permissions = readAgentPermissions(metadata, agent.agentId);
+            return {
+                agentId: agent.agentId,
+                ...rendered,
+                prompt: buildAgentPrompt(agent, task, request.input, metadata, permissions === undefined ? undefined : { attachments: [], permissions }),
             };
         },
         async recommendAgents(request) {
-            const agents = await stateStore.listAgents();
+            // Agents are ranked on the capabilities they inherit too.
+            const agents = await Promise.all((await stateStore.listAgents()).map(async (agent) => (await resolveAgentProfile(agent, (baseId) => stateStore.getAgent(baseId), basePath).catch(() => ({ agent }))).agent));
             const ranked = rankAgents(agents, request);
             return request.limit === undefined ? ranked : ranked.slice(0, Math.max(0, request.limit));
         },
@@ -915,76 +1817,72 @@ export function createSharedRuntimeService(config = {}) {
             }
             const failureStrategy = request.failureStrategy ?? 'failSafe';
             const resultAggregation = request.resultAggregation ?? 'list';
-            const maxConcurrent = Math.max(1, request.maxConcurrent ?? 3);
+            const concurrency = readConcurrencySettings(await readWorkspaceConfig(basePath));
+            const maxConcurrent = Math.max(1, request.maxConcurrent ?? concurrency.maxConcurrent);
             const taskMap = new Map(request.tasks.map((task) => [task.taskId, task]));
-            const results = new Map();
+            // Each task counts against the limit of the provider it runs on: its own, else its agent's.
+            const providers = new Map(await Promise.all(request.tasks.map(async (task) => {
+                const metadata = task.provider === undefined ? await layeredMetadata(task.agentId, basePath) : undefined;
+                return [task.taskId, task.provider ?? (isRecord(metadata) ? asOptionalString(metadata.provider) : undefined) ?? 'claude'];
+            })));
             let stopExecution = false;
-            for (const layer of plan.layers) {
+            // A task starts as soon as its own dependencies are done, not when their whole layer is.
+            const results = await runScheduled(plan.orderedTaskIds.map((taskId) => ({ id: taskId, dependencies: taskMap.get(taskId).dependencies, pool: providers.get(taskId) })), { maxConcurrent, poolLimits: concurrency.perProvider }, async ({ id: taskId }, dependencyResults) => {
+                const task = taskMap.get(taskId);
                 if (stopExecution) {
-                    for (const taskId of layer) {
-                        const task = taskMap.get(taskId);
-                        results.set(taskId, {
-                            taskId,
-                            agentId: task.agentId,
-                            status: 'skipped',
-                            dependencies: task.dependencies ?? [],
-                            error: {
-                                code: 'PARALLEL_SKIPPED_AFTER_FAILURE',
-                                message: 'Skipped because failFast stopped the orchestration after a prior failure.',
-                            },
-                        });
-                    }
-                    continue;
+                    return {
+                        taskId,
+                        agentId: task.agentId,
+                        status: 'skipped',
+                        dependencies: task.dependencies ?? [],
+                        error: {
+                            code: 'PARALLEL_SKIPPED_AFTER_FAILURE',
+                            message: 'Skipped because failFast stopped the orchestration after a prior failure.',
+                        },
+                    };
                 }
-                for (const batch of chunkArray(layer, maxConcurrent)) {
-                    const batchResults = await Promise.all(batch.map(async (taskId) => {
-                        const task = taskMap.get(taskId);
-                        const blockedDependency = (task.dependencies ?? []).find((dependency) => {
-                            const dependencyResult = results.get(dependency);
-                            return dependencyResult !== undefined && dependencyResult.status !== 'completed';
-                        });
-                        if (blockedDependency !== undefined) {
-                            return {
-                                taskId,
-                                agentId: task.agentId,
-                                status: 'skipped',
-                                dependencies: task.dependencies ?? [],
-                                error: {
-                                    code: 'PARALLEL_DEPENDENCY_FAILED',
-                                    message: `Skipped because dependency "${blockedDependency}" did not complete successfully.`,
-                                },
-                            };
-                        }
-                        const runResult = await this.runAgent({
-                            agentId: task.agentId,
-                            task: task.task,
-                            input: task.input,
-                            provider: task.provider,
-                            model: task.model,
-                            timeoutMs: task.timeoutMs,
-                            sessionId: request.sessionId,
-                            surface: request.surface ?? 'mcp',
-                            parentTraceId: traceId,
-                            rootTraceId: traceId,
-                        });
-                        return {
-                            taskId,
-                            agentId: task.agentId,
-                            status: runResult.success ? 'completed' : 'failed',
-                            traceId: runResult.traceId,
-                            dependencies: task.dependencies ?? [],
-                            result: runResult,
-                            error: runResult.success ? undefined : runResult.error,
-                        };
-                    }));
-                    for (const taskResult of batchResults) {
-                        results.set(taskResult.taskId, taskResult);
-                    }
-                    if (failureStrategy === 'failFast' && batchResults.some((entry) => entry.status === 'failed')) {
-                        stopExecution = true;
-                    }
+                const blockedDependency = [...dependencyResults.values()].find((dependency) => dependency.status !== 'completed');
+                if (blockedDependency !== undefined) {
+                    return {
+                        taskId,
+                        agentId: task.agentId,
+                        status: 'skipped',
+                        dependencies: task.dependencies ?? [],
+                        error: {
+                            code: 'PARALLEL_DEPENDENCY_FAILED',
+                            message: `Skipped because dependency "${blockedDependency.taskId}" did not complete successfully.`,
+                        },
+                    };
                 }
-            }
+                // What the tasks it depends on produced goes to the agent with its own input.
+                const runResult = await this.runAgent({
+                    agentId: task.agentId,
+                    task: task.task,
+                    input: dependencyResults.size === 0 ? task.input : {
+                        ...task.input,
+                        dependencyOutputs: Object.fromEntries([...dependencyResults.values()].map((dependency) => [dependency.taskId, dependency.result?.content ?? ''])),
+                    },
+                    provider: task.provider,
+                    model: task.model,
+                    timeoutMs: task.timeoutMs,
+                    sessionId: request.sessionId,
+                    surface: request.surface ?? 'mcp',
+                    parentTraceId: traceId,
+                    rootTraceId: traceId,
+                });
+                if (failureStrategy === 'failFast' && !runResult.success) {
+                    stopExecution = true;
+                }
+                return {
+                    taskId,
+                    agentId: task.agentId,
+                    status: runResult.success ? 'completed' : 'failed',
+                    traceId: runResult.traceId,
+                    dependencies: task.dependencies ?? [],
+                    result: runResult,
+                    error: runResult.success ? undefined : runResult.error,
+                };
+            });
             const orderedResults = plan.orderedTaskIds.map((taskId) => results.get(taskId)).filter((entry) => entry !== undefined);
             const success = orderedResults.every((entry) => entry.status === 'completed');
             const completedAt = new Date().toISOString();
@@ -1038,13 +1936,146 @@ export function createSharedRuntimeService(config = {}) {
                 },
             };
         },
+        async runPipeline(request) {
+            const root = request.basePath ?? basePath;
+            let source;
+            try {
+                source = await readFile(isAbsolute(request.file) ? request.file : join(root, request.file), 'utf8');
+            }
+            catch {
+                throw Object.assign(new Error(`Pipeline file "${request.file}" was not found.`), { code: 'PIPELINE_NOT_FOUND' });
+            }
+            const pipeline = parsePipeline(source, request.file);
+            const startedAt = new Date().toISOString();
+            return runCheckpointedPipeline(pipeline, {
+                checkpoint: {
+                    runId: request.traceId ?? randomUUID(),
+                    file: request.file,
+                    source,
+                    input: request.input,
+                    provider: request.provider,
+                    budget: request.budget,
+                    sessionId: request.sessionId,
+                    status: 'running',
+                    startedAt,
+                    updatedAt: startedAt,
+                    stages: [],
+                },
+                basePath: root,
+                surface: request.surface,
+                signal: request.signal,
+                onStageStart: request.onStageStart,
+                onStageEnd: request.onStageEnd,
+                onText: request.onText,
+            }, (agentRequest) => this.runAgent({ ...agentRequest, approve: request.approve, approvalPolicy: request.approvalPolicy }));
+        },
+        async resumePipeline(request) {
+            const root = request.basePath ?? basePath;
+            const checkpoint = await readPipelineCheckpoint(root, request.runId);
+            if (checkpoint === undefined) {
+                throw Object.assign(new Error(`Pipeline run "${request.runId}" has no checkpoint to resume from.`), { code: 'PIPELINE_CHECKPOINT_NOT_FOUND' });
+            }
+            return runCheckpointedPipeline(parsePipeline(checkpoint.source, checkpoint.file), {
+                checkpoint: { ...checkpoint, budget: request.budget ?? checkpoint.budget, status: 'running' },
+                basePath: root,
+                surface: request.surface,
+                signal: request.signal,
+                onStageStart: request.onStageStart,
+                onStageEnd: request.onStageEnd,
+                onText: request.onText,
+            }, (agentRequest) => this.runAgent({ ...agentRequest, approve: request.approve, approvalPolicy: request.approvalPolicy }));
+        },
+        async runEval(request) {
+            const root = request.basePath ?? basePath;
+            let source;
+            try {
+                source = await readFile(isAbsolute(request.file) ? request.file : join(root, request.file), 'utf8');
+            }
+            catch {
+                throw Object.assign(new Error(`Eval suite "${request.file}" was not found.`), { code: 'EVAL_SUITE_NOT_FOUND' });
+            }
+            return runEvalSuite(parseEvalSuite(source, request.file), {
+                basePath: root,
+                runId: randomUUID(),
+                file: request.file,
+                agents: request.agents,
+                providers: request.providers,
+                caseIds: request.cases,
+                surface: request.surface,
+                signal: request.signal,
+                onResult: request.onResult,
+            }, {
+                runAgent: (agentRequest) => this.runAgent(agentRequest),
+                costOf: async (traceId) => (await usageTracker.report({ traceId })).total.costUsd,
+            });
+        },
+        async replayRun(request) {
+            const root = request.basePath ?? basePath;
+            const { run, calls } = await readReplayLog(root, request.runId);
+            const traceId = randomUUID();
+            runRecorder.beginReplay(root, traceId, calls);
+            let result;
+            let summary;
+            try {
+                if (run.command === 'agent.run') {
+                    result = await this.runAgent({
+                        ...run.request,
+                        traceId,
+                        basePath: root,
+                        surface: request.surface,
+                        signal: request.signal,
+                        approve: request.approve,
+                        approvalPolicy: request.approvalPolicy,
+                    });
+                }
+                else {
+                    const startedAt = new Date().toISOString();
+                    result = await runCheckpointedPipeline(parsePipeline(run.request.source, run.request.file), {
+                        checkpoint: {
+                            runId: traceId,
+                            file: run.request.file,
+                            source: run.request.source,
+                            input: run.request.input,
+                            provider: run.request.provider,
+                            budget: run.request.budget,
+                            status: 'running',
+                            startedAt,
+                            updatedAt: startedAt,
+                            stages: [],
+                        },
+                        basePath: root,
+                        surface: request.surface,
+                        signal: request.signal,
+                    }, (agentRequest) => this.runAgent({ ...agentRequest, approve: request.approve, approvalPolicy: request.approvalPolicy }));
+                }
+            }
+            finally {
+                summary = runRecorder.endReplay(traceId);
+            }
+            return { runId: request.runId, traceId, command: run.command, success: result.success, ...summary, result };
+        },
         async getStatus(request) {
             const limit = request?.limit ?? 10;
-            const [sessions, traces, config] = await Promise.all([
+            const [sessions, traces, config, providerQuotas, providerRateLimits] = await Promise.all([
                 stateStore.listSessions(),
                 traceStore.listTraces(Math.max(limit * 3, limit)),
                 readWorkspaceConfig(basePath),
+                providerBridge.getQuotaStatus(),
+                providerBridge.getRateLimitStatus(),
             ]);
+            const defaultProvider = typeof config.providers === 'object' && config.providers !== null && typeof config.providers.default === 'string'
+                ? config.providers.default
+                : typeof config.defaultProvider === 'string'
+                    ? config.defaultProvider
+                    : undefined;
+            if (request?.probe === true) {
+                await providerBridge.probeProviders([
+                    ...listConfiguredExecutors(config),
+                    ...(defaultProvider !== undefined ? [defaultProvider] : []),
+                    ...readFallbackProviders(config),
+                ], request.signal);
+            }
+            const providerHealth = await providerBridge.getHealthStatus();
             const activeSessions = sessions.filter((session) => session.status === 'active').slice(0, limit);
             const runningTraces = traces.filter((trace) => trace.status === 'running').slice(0, limit);
             const recentFailedTraces = traces.filter((trace) => trace.status === 'failed').slice(0, limit);
@@ -1062,19 +2093,21 @@ export function createSharedRuntimeService(config = {}) {
                     failed: traces.filter((trace) => trace.status === 'failed').length,
                 },
                 runtime: {
-                    defaultProvider: typeof config.providers === 'object' && config.providers !== null && typeof config.providers.default === 'string'
-                        ? config.providers.default
-                        : typeof config.defaultProvider === 'string'
-                            ? config.defaultProvider
-                            : undefined,
+                    defaultProvider,
                     providerExecutionMode: providerBridge.getExecutionMode(),
                     configuredExecutors: listConfiguredExecutors(config),
+                    providerQuotas,
+                    providerRateLimits,
+                    providerHealth,
                 },
                 activeSessions,
                 runningTraces,
                 recentFailedTraces,
             };
         },
+        isReadOnly() {
+            return readOnly;
+        },
         gitStatus(request) {
             return getGitStatus(request?.basePath ?? basePath);
         },
@@ -1106,7 +2139,10 @@ export function createSharedRuntimeService(config = {}) {
                 throw new Error(`git diff failed: ${message}`);
             }
         },
-        commitPrepare(request) {
+        async commitPrepare(request) {
+            if (request?.stageAll === true || (request?.paths?.length ?? 0) > 0) {
+                assertWritable(readOnly, 'Staging files');
+            }
             return prepareCommit({
                 basePath: request?.basePath ?? basePath,
                 paths: request?.paths,
@@ -1122,7 +2158,8 @@ export function createSharedRuntimeService(config = {}) {
                 head: request?.head,
             });
         },
-        createPullRequest(request) {
+        async createPullRequest(request) {
+            assertWritable(readOnly, 'Creating pull requests');
             return createPullRequest({
                 basePath: request.basePath ?? basePath,
                 title: request.title,
@@ -1146,7 +2183,7 @@ export function createSharedRuntimeService(config = {}) {
         async describeWorkflow(request) {
             const workflowDir = resolveWorkflowDir(request.workflowDir, request.basePath, basePath);
             const loader = createWorkflowLoader({ workflowsDir: workflowDir });
-            const workflow = await loader.load(request.workflowId);
+            const workflow = await loader.load(request.workflowId) ?? getBuiltinWorkflow(request.workflowId);
             if (workflow === undefined) {
                 return undefined;
             }
@@ -1176,6 +2213,286 @@ export function createSharedRuntimeService(config = {}) {
         listReviewTraces(limit) {
             return listReviewTraces(traceStore, limit);
         },
+        async indexCode(request) {
+            const indexBasePath = request?.basePath ?? basePath;
+            const params = { paths: request?.paths, maxFiles: request?.maxFiles };
+            if (request?.onFile !== undefined || request?.maxMemoryMb !== undefined || request?.signal !== undefined) {
+                // Streaming, a memory limit and cancellation apply to this process's parse; a running daemon then picks up the new file.
+                const result = await buildCodeIndex({ ...params, basePath: indexBasePath, onFile: request.onFile, maxMemoryMb: request.maxMemoryMb, signal: request.signal });
+                await requestIndexServer(indexBasePath, 'reload');
+                return result;
+            }
+            return await requestIndexServer(indexBasePath, 'index', params)
+                ?? buildCodeIndex({ ...params, basePath: indexBasePath });
+        },
+        async searchCodeSymbols(request) {
+            const indexBasePath = request?.basePath ?? basePath;
+            const query = {
+                query: request?.query,
+                id: request?.id,
+                kind: request?.kind,
+                language: request?.language,
+                file: request?.file,
+                tests: request?.tests,
+                concurrent: request?.concurrent,
+                features: request?.features,
+                target: request?.target,
+                derive: request?.derive,
+                attribute: request?.attribute,
+                limit: request?.limit,
+            };
+            return await requestIndexServer(indexBasePath, 'symbols', query)
+                ?? searchCodeSymbols({ ...query, basePath: indexBasePath });
+        },
+        parseCodeSource(request) {
+            return parseWorkspaceCodeSource({ ...request, basePath: request.basePath ?? basePath });
+        },
+        watchCodeIndex(request) {
+            return startIndexWatcher({ ...request, basePath: request?.basePath ?? basePath });
+        },
+        startIndexServer(request) {
+            return startIndexServer({ basePath: request?.basePath ?? basePath, watch: request?.watch });
+        },
+        getIndexServerStatus(request) {
+            return requestIndexServer(request?.basePath ?? basePath, 'status');
+        },
+        async stopIndexServer(request) {
+            const stopped = await requestIndexServer(request?.basePath ?? basePath, 'shutdown');
+            return stopped?.stopped === true;
+        },
+        async askProject(request) {
+            const askBasePath = request.basePath ?? basePath;
+            const runtimeProviderBridge = resolveProviderBridge(askBasePath);
+            const traceId = request.traceId ?? randomUUID();
+            const startedAt = new Date().toISOString();
+            const routing = request.provider === undefined
+                ? await runtimeProviderBridge.selectProvider('claude')
+                : { provider: request.provider, warnings: [] };
+            const result = await answerProjectQuestion({
+                question: request.question,
+                basePath: askBasePath,
+                limit: request.limit,
+                planner: async (prompt) => {
+                    const outcome = await runtimeProviderBridge.executePrompt({
+                        provider: routing.provider,
+                        prompt,
+                        model: 'v14-ask-planner',
+                        temperature: 0,
+                        traceId,
+                    });
+                    if (outcome.type === 'unavailable') {
+                        return undefined;
+                    }
+                    if (!outcome.response.success) {
+                        throw new Error(outcome.response.error ?? `Provider "${routing.provider}" failed to plan the query.`);
+                    }
+                    return outcome.response.content ?? '';
+                },
+            });
+            const warnings = [...routing.warnings, ...result.warnings];
+            await traceStore.upsertTrace({
+                traceId,
+                workflowId: 'ask',
+                surface: request.surface ?? 'cli',
+                status: 'completed',
+                startedAt,
+                completedAt: new Date().toISOString(),
+                input: { question: request.question, provider: routing.provider },
+                stepResults: [
+                    { stepId: 'plan-and-query', success: true, durationMs: Date.now() - Date.parse(startedAt), retryCount: 0 },
+                ],
+                output: {
+                    plan: result.plan,
+                    planner: result.planner,
+                    answer: result.answer,
+                    referenceCount: result.references.length,
+                    warnings,
+                },
+                metadata: {
+                    provider: result.planner === 'provider' ? routing.provider : undefined,
+                    command: 'ask',
+                },
+            });
+            return { ...result, warnings, traceId };
+        },
+        async installHook(request) {
+            assertWritable(readOnly, 'Installing git hooks');
+            return installPreCommitHook({
+                basePath: request?.basePath ?? basePath,
+                force: request?.force,
+            });
+        },
+        async uninstallHook(request) {
+            assertWritable(readOnly, 'Removing git hooks');
+            return uninstallPreCommitHook({
+                basePath: request?.basePath ?? basePath,
+            });
+        },
+        async runPreCommitHook(request) {
+            const hookBasePath = request?.basePath ?? basePath;
+            const config = resolvePreCommitConfig(await readWorkspaceConfig(hookBasePath));
+            return runPreCommitPipeline({
+                basePath: hookBasePath,
+                config: request?.mode === undefined ? config : { ...config, mode: request.mode },
+                sessionId: request?.sessionId,
+            }, (agentRequest) => this.runAgent(agentRequest));
+        },
+        scaffoldImplementation,
+        analyzeTestImpact: analyzeWorkspaceTestImpact,
+        async testAndFix(request) {
+            assertWritable(readOnly, 'Test-and-fix');
+            return runTestAndFix({ ...request, basePath: request?.basePath ?? basePath }, (agentRequest) => this.runAgent({ ...agentRequest, approve: request?.approve, approvalPolicy: request?.approvalPolicy }));
+        },
+        reviewAndAnnotate(request) {
+            return runReviewAndAnnotate({ ...request, basePath: request?.basePath ?? basePath }, (agentRequest) => this.runAgent(agentRequest));
+        },
+        analyzeChangeImpact(request) {
+            return analyzeChangeImpact({
+                diff: request?.diff,
+                files: request?.files,
+                base: request?.base,
+                depth: request?.depth,
+                basePath: request?.basePath ?? basePath,
+            });
+        },
+        async runMaintenance(request) {
+            const maintenanceBasePath = request?.basePath ?? basePath;
+            const dryRun = request?.dryRun === true;
+            const now = new Date();
+            const previous = await readMaintenanceState(maintenanceBasePath);
+            if (request?.ifDue === true && !isMaintenanceDue(previous, await readMaintenanceConfig(maintenanceBasePath), now)) {
+                return { success: true, dryRun, skipped: 'Maintenance is not due yet.', lastRunAt: previous?.lastRunAt, tasks: [] };
+            }
+            if (!dryRun) {
+                assertWritable(readOnly, 'Workspace maintenance');
+            }
+            const traceId = randomUUID();
+            const startedAt = now.toISOString();
+            const input = { tasks: request?.tasks ?? [...MAINTENANCE_TASKS], dryRun };
+            await traceStore.upsertTrace({
+                traceId,
+                workflowId: MAINTENANCE_WORKFLOW.workflowId,
+                surface: request?.surface ?? 'cli',
+                status: 'running',
+                startedAt,
+                input,
+                stepResults: [],
+            });
+            const tasks = [];
+            for (const task of MAINTENANCE_TASKS.filter((candidate) => input.tasks.includes(candidate))) {
+                tasks.push(await runMaintenanceTask(task, { dryRun, basePath: maintenanceBasePath, now }));
+            }
+            const success = tasks.every((task) => task.success);
+            const completedAt = new Date().toISOString();
+            await traceStore.upsertTrace({
+                traceId,
+                workflowId: MAINTENANCE_WORKFLOW.workflowId,
+                surface: request?.surface ?? 'cli',
+                status: success ? 'completed' : 'failed',
+                startedAt,
+                completedAt,
+                input,
+                stepResults: tasks.map((task) => ({
+                    stepId: task.task,
+                    success: task.success,
+                    durationMs: task.durationMs,
+                    retryCount: 0,
+                    error: task.error,
+                })),
+                output: { tasks },
+                ...(success ? {} : { error: { code: 'MAINTENANCE_FAILED', message: tasks.filter((task) => !task.success).map((task) => task.summary).join('; ') } }),
+            });
+            if (!dryRun) {
+                await writeMaintenanceState(maintenanceBasePath, { lastRunAt: completedAt, traceId, tasks });
+            }
+            return { traceId, success, dryRun, lastRunAt: dryRun ? previous?.lastRunAt : completedAt, tasks };
+        },
+        reportUnsafeCode(request) {
+            return buildUnsafeReports({ paths: request?.paths, crate: request?.crate, basePath: request?.basePath ?? basePath });
+        },
+        findUnreferencedSymbols(request) {
+            return findUnreferencedSymbols({ paths: request?.paths, kind: request?.kind, limit: request?.limit, basePath: request?.basePath ?? basePath });
+        },
+        findReferences(request) {
+            return findSymbolReferences({ symbol: request.symbol, basePath: request.basePath ?? basePath });
+        },
+        buildCallHierarchy(request) {
+            return buildCallHierarchy({ symbol: request.symbol, direction: request.direction, depth: request.depth, basePath: request.basePath ?? basePath });
+        },
+        renderSignatures(request) {
+            return renderSignatures({ paths: request.paths, basePath: request.basePath ?? basePath });
+        },
+        renderFileSkeleton(request) {
+            return renderFileSkeleton({ path: request.path, basePath: request.basePath ?? basePath });
+        },
+        readSymbolBody(request) {
+            return readSymbolBody({ symbol: request.symbol, basePath: request.basePath ?? basePath });
+        },
+        renderOutline(request) {
+            return renderOutline({ paths: request.paths, basePath: request.basePath ?? basePath });
+        },
+        renderRepoMap(request) {
+            return renderRepoMap({ basePath: request?.basePath ?? basePath });
+        },
+        exportCodeGraph(request) {
+            return exportCodeGraph({ graph: request.graph, format: request.format, paths: request.paths, basePath: request.basePath ?? basePath });
+        },
+        async searchSymbols(request) {
+            const indexBasePath = request.basePath ?? basePath;
+            const params = { query: request.query, limit: request.limit };
+            return await requestIndexServer(indexBasePath, 'search', params)
+                ?? searchSymbolIndex({ ...params, basePath: indexBasePath });
+        },
+        async describeSymbols(request) {
+            const indexBasePath = request.basePath ?? basePath;
+            const query = [
+                request.query ?? '',
+                ...(request.kind !== undefined ? [`kind:${request.kind}`] : []),
+                ...(request.language !== undefined ? [`lang:${request.language}`] : []),
+            ].join(' ');
+            const params = { query, limit: request.limit };
+            const hits = await requestIndexServer(indexBasePath, 'search', params)
+                ?? await searchSymbolIndex({ ...params, basePath: indexBasePath });
+            return describeSymbolHits(indexBasePath, hits);
+        },
+        searchText(request) {
+            return searchText({ ...request, basePath: request.basePath ?? basePath });
+        },
+        hybridSearch(request) {
+            return createSearchService({
+                basePath: request.basePath ?? basePath,
+                searchMemory: (query) => stateStore.searchMemory(query, request.namespace),
+            }).hybrid(request.query, request.k);
+        },
+        async embedCode(request = {}) {
+            const embedBasePath = request.basePath ?? basePath;
+            let embedder = request.embedder;
+            if (embedder === undefined) {
+                const configured = await readEmbedderConfig(embedBasePath);
+                const backend = request.backend ?? configured?.backend;
+                if (backend === undefined) {
+                    throw new Error('No embedding backend configured. Pass --embedder openai|ollama|onnx or set codeIntel.embeddings.backend in .automatosx/config.json.');
+                }
+                embedder = createEmbedder({
+                    backend,
+                    model: request.model ?? (backend === configured?.backend ? configured.model : undefined),
+                    baseUrl: backend === configured?.backend ? configured.baseUrl : undefined,
+                });
+            }
+            return embedCodeChunks({ basePath: embedBasePath, embedder, stateStore, paths: request.paths });
+        },
+        createDebugBundle(request) {
+            const bundleBasePath = request?.basePath ?? basePath;
+            return createDebugBundle(traceStore, resolveProviderBridge(bundleBasePath), {
+                basePath: bundleBasePath,
+                traceId: request?.traceId,
+                outputPath: request?.outputPath,
+                cliVersion: request?.cliVersion,
+            });
+        },
+        diagnoseEnvironment(request) {
+            return diagnoseEnvironment({ ...request, basePath: request?.basePath ?? basePath });
+        },
         async getConfig(path) {
             const config = await readWorkspaceConfig(basePath);
             if (path === undefined || path.length === 0) {
@@ -1187,11 +2504,25 @@ export function createSharedRuntimeService(config = {}) {
             return readWorkspaceConfig(basePath);
         },
         async setConfig(path, value) {
-            const config = await readWorkspaceConfig(basePath);
+            assertWritable(readOnly, 'Updating workspace config');
+            const { path: configPath, config } = await readProjectConfig(basePath);
             setValueAtPath(config, path, value);
-            await writeWorkspaceConfig(basePath, config);
+            // Only what the new value gets wrong stops it, so a file already wrong elsewhere can still be corrected.
+            const issues = validateWorkspaceConfig(config).filter((issue) => issue.path === path
+                || issue.path.startsWith(`${path}.`)
+                || (issue.unknownKey === true && path.startsWith(`${issue.path}.`)));
+            if (issues.length > 0) {
+                throw Object.assign(new Error(issues.map(formatConfigIssue).join('; ')), { code: 'CONFIG_INVALID' });
+            }
+            await writeProjectConfig(configPath, config);
             return config;
         },
+        async explainConfig(path) {
+            return explainConfigKey(await loadLayeredConfig(basePath), path);
+        },
+        async validateConfig() {
+            return validateLayeredConfig(await loadLayeredConfig(basePath));
+        },
         getTrace(traceId) {
             return traceStore.getTrace(traceId);
         },
@@ -1217,20 +2548,121 @@ export function createSharedRuntimeService(config = {}) {
             const filtered = traces.filter((trace) => trace.metadata?.sessionId === sessionId);
             return limit === undefined ? filtered : filtered.slice(0, limit);
         },
-        storeMemory(entry) {
-            return stateStore.storeMemory(entry);
+        async storeMemory({ scope, ttlSeconds, dedup, source, ...entry }) {
+            if (ttlSeconds !== undefined && !(ttlSeconds > 0)) {
+                throw new Error(`Memory TTL must be a positive number of seconds, got ${ttlSeconds}.`);
+            }
+            // Agent-scoped entries cite their agent without being told.
+            const agent = scope?.agent?.trim();
+            const cited = source !== undefined && source.agent === undefined && agent !== undefined && agent.length > 0 ? { ...source, agent } : source;
+            const stored = {
+                ...entry,
+                namespace: scopedNamespace(entry.namespace, scope),
+                ...(ttlSeconds !== undefined ? { expiresAt: new Date(Date.now() + ttlSeconds * 1000).toISOString() } : {}),
+                ...(cited !== undefined ? { source: await recordMemorySource(basePath, cited) } : {}),
+            };
+            const policy = resolveMemoryDedupPolicy(getValueAtPath(await readWorkspaceConfig(basePath), 'memory.dedup'), dedup);
+            if (policy === undefined) {
+                return stateStore.storeMemory(stored);
+            }
+            let embedding = stored.embedding;
+            if (embedding === undefined) {
+                const configured = await readEmbedderConfig(basePath);
+                if (configured === undefined) {
+                    throw new Error('Memory deduplication compares embeddings: pass one with the entry or set codeIntel.embeddings.backend in .automatosx/config.json.');
+                }
+                [embedding] = await createEmbedder(configured).embed([memoryText(stored.value)]);
+            }
+            return storeDeduplicatedMemory(stateStore, { ...stored, embedding: embedding }, policy);
+        },
+        getMemory(key, namespace, scope) {
+            return stateStore.getMemory(key, scopedNamespace(namespace, scope));
+        },
+        async searchMemory(query, namespace, scope) {
+            if (namespace !== undefined || scope === undefined) {
+                return stateStore.searchMemory(query, scopedNamespace(namespace, scope));
+            }
+            return withinScope(await stateStore.searchMemory(query), scope);
+        },
+        deleteMemory(key, namespace, scope) {
+            return stateStore.deleteMemory(key, scopedNamespace(namespace, scope));
+        },
+        async listMemory(namespace, scope) {
+            if (namespace !== undefined || scope === undefined) {
+                return stateStore.listMemory(scopedNamespace(namespace, scope));
+            }
+            return withinScope(await stateStore.listMemory(), scope);
+        },
+        listMemoryNamespaces() {
+            return stateStore.listMemoryNamespaces();
+        },
+        copyMemoryNamespace(request) {
+            return stateStore.copyMemoryNamespace(request.source, request.target, { overwrite: request.overwrite });
+        },
+        mergeMemoryNamespaces(request) {
+            return stateStore.mergeMemoryNamespaces(request.sources, request.target);
+        },
+        async exportMemory(request) {
+            const entries = request.namespaces !== undefined && request.namespaces.length > 0
+                ? (await Promise.all(request.namespaces.map((namespace) => stateStore.listMemory(scopedNamespace(namespace, request.scope))))).flat()
+                : request.scope !== undefined
+                    ? withinScope(await stateStore.listMemory(), request.scope)
+                    : await stateStore.listMemory();
+            return writeMemoryArchive(request.basePath ?? basePath, request.path, entries, { includeEmbeddings: request.includeEmbeddings });
+        },
+        async importMemory(request) {
+            assertWritable(readOnly, 'Importing memory');
+            const archive = await readMemoryArchive(request.basePath ?? basePath, request.path);
+            const { copied, skipped } = await stateStore.importMemory(archive.entries, { mode: request.overwrite === true ? 'overwrite' : 'newer' });
+            return { path: archive.path, manifest: archive.manifest, imported: copied, skipped };
         },
-        getMemory(key, namespace) {
-            return stateStore.getMemory(key, namespace);
+        async pruneMemory(request) {
+            const dryRun = request?.dryRun === true;
+            if (!dryRun) {
+                assertWritable(readOnly, 'Pruning memory');
+            }
+            const config = await readMaintenanceConfig(request?.basePath ?? basePath);
+            const { evicted, policy } = await pruneMemoryEntries(stateStore, config, { dryRun, now: new Date() });
+            return { dryRun, policy, evicted };
+        },
+        async encryptMemory() {
+            assertWritable(readOnly, 'Encrypting memory');
+            if (config.stateStore === undefined && memoryCipherFor(basePath) === undefined) {
+                throw new Error('Memory encryption is off. Set "memory": { "encryption": "env" } or "keychain" in .automatosx/config.json first.');
+            }
+            return stateStore.encryptMemory();
+        },
+        async checkMemorySources(request = {}) {
+            if (request.invalidate === true) {
+                assertWritable(readOnly, 'Invalidating memory');
+            }
+            const entries = request.namespace !== undefined || request.scope === undefined
+                ? await stateStore.listMemory(scopedNamespace(request.namespace, request.scope))
+                : withinScope(await stateStore.listMemory(), request.scope);
+            const { checked, stale } = await findStaleMemory(request.basePath ?? basePath, entries);
+            let invalidated = 0;
+            if (request.invalidate === true) {
+                for (const entry of stale) {
+                    if (await stateStore.deleteMemory(entry.key, entry.namespace)) {
+                        invalidated += 1;
+                    }
+                }
+            }
+            return { checked, stale, invalidated };
         },
-        searchMemory(query, namespace) {
-            return stateStore.searchMemory(query, namespace);
+        storeFact({ scope, ...input }) {
+            return stateStore.storeFact({ ...input, namespace: scopedNamespace(input.namespace, scope) });
         },
-        deleteMemory(key, namespace) {
-            return stateStore.deleteMemory(key, namespace);
+        async queryFacts({ scope, ...query } = {}) {
+            if (query.namespace !== undefined || scope === undefined) {
+                return stateStore.queryFacts({ ...query, namespace: scopedNamespace(query.namespace, scope) });
+            }
+            const prefix = scopedNamespace('', scope);
+            const facts = (await stateStore.queryFacts({ ...query, limit: undefined })).filter((fact) => fact.namespace?.startsWith(prefix) === true);
+            return query.limit !== undefined ? facts.slice(0, query.limit) : facts;
         },
-        listMemory(namespace) {
-            return stateStore.listMemory(namespace);
+        deleteFact(factId) {
+            return stateStore.deleteFact(factId);
         },
         storeSemantic(entry) {
             return stateStore.storeSemantic(entry);
@@ -1271,6 +2703,18 @@ export function createSharedRuntimeService(config = {}) {
             const entries = await stateStore.listFeedback({ agentId });
             return buildFeedbackAdjustment(agentId, entries);
         },
+        getUsageReport(options) {
+            return usageTracker.report(options);
+        },
+        async getProviderCacheStats(options = {}) {
+            return responseCache.stats(readProviderCacheSettings(await readWorkspaceConfig(options.basePath ?? basePath)));
+        },
+        clearProviderCache() {
+            return responseCache.clear();
+        },
+        listProviders(root) {
+            return collectProviderIds(root ?? basePath);
+        },
         async listAbilities(options) {
             return filterAbilities(BUILTIN_ABILITIES, options);
         },
@@ -1335,6 +2779,16 @@ export function createSharedRuntimeService(config = {}) {
         getAgent(agentId) {
             return stateStore.getAgent(agentId);
         },
+        async resolveAgentProfile(agentId, root) {
+            const agent = await stateStore.getAgent(agentId);
+            return agent === undefined ? undefined : resolveAgentProfile(agent, (baseId) => stateStore.getAgent(baseId), root ?? basePath);
+        },
+        async suggestAgents(request = {}) {
+            if (request.dryRun !== true) {
+                assertWritable(readOnly, 'Writing suggested agents');
+            }
+            return suggestAgents({ basePath: request.basePath ?? basePath, dryRun: request.dryRun });
+        },
         listAgents() {
             return stateStore.listAgents();
         },
@@ -1344,12 +2798,117 @@ export function createSharedRuntimeService(config = {}) {
         listAgentCapabilities() {
             return stateStore.listAgentCapabilities();
         },
-        createSession(entry) {
-            return stateStore.createSession(entry);
+        async createSession(entry) {
+            const { scope, memoryNamespace, basePath: root, ...stored } = entry;
+            const session = await stateStore.createSession(stored);
+            if (scope !== undefined || memoryNamespace !== undefined) {
+                await updateSessionThread(root ?? basePath, session.sessionId, (thread) => ({ ...thread, scope, memoryNamespace }));
+            }
+            return session;
         },
         getSession(sessionId) {
             return stateStore.getSession(sessionId);
         },
+        getSessionThread(sessionId, root) {
+            return readSessionThread(root ?? basePath, sessionId);
+        },
+        async resumeSession(request) {
+            const root = request.basePath ?? basePath;
+            const stored = await stateStore.getSession(request.sessionId);
+            if (stored === undefined) {
+                throw Object.assign(new Error(`Session "${request.sessionId}" does not exist.`), { code: SESSION_NOT_FOUND_CODE });
+            }
+            const thread = await readSessionThread(root, request.sessionId);
+            const agentId = request.agentId ?? thread.agentId;
+            if (request.task !== undefined && agentId === undefined) {
+                throw Object.assign(new Error(`No agent has run in session "${request.sessionId}" yet; name the agent to continue it with.`), { code: SESSION_AGENT_MISSING_CODE });
+            }
+            const session = stored.status === 'active' ? stored : await stateStore.reopenSession(request.sessionId);
+            if (request.task === undefined || agentId === undefined) {
+                return { session, thread };
+            }
+            const run = await this.runAgent({
+                agentId,
+                task: request.task,
+                sessionId: request.sessionId,
+                provider: request.provider,
+                model: request.model,
+                basePath: request.basePath,
+                surface: request.surface,
+                signal: request.signal,
+                approve: request.approve,
+                approvalPolicy: request.approvalPolicy,
+            });
+            return {
+                session: await stateStore.getSession(request.sessionId) ?? session,
+                thread: await readSessionThread(root, request.sessionId),
+                run,
+            };
+        },
+        async branchSession(request) {
+            const root = request.basePath ?? basePath;
+            const original = await stateStore.getSession(request.sessionId);
+            if (original === undefined) {
+                throw Object.assign(new Error(`Session "${request.sessionId}" does not exist.`), { code: SESSION_NOT_FOUND_CODE });
+            }
+            const branchId = request.branchId ?? randomUUID();
+            if (await stateStore.getSession(branchId) !== undefined) {
+                throw new Error(`Session "${branchId}" already exists.`);
+            }
+            const fork = branchSessionThread(await readSessionThread(root, request.sessionId), branchId, request.turns);
+            const thread = await updateSessionThread(root, branchId, () => fork);
+            const session = await stateStore.createSession({
+                sessionId: branchId,
+                task: request.task ?? original.task,
+                initiator: original.initiator,
+                workspace: original.workspace,
+                metadata: { ...original.metadata, branchedFrom: original.sessionId },
+            });
+            return { session, thread };
+        },
+        async rewindSession(sessionId, turns, root) {
+            if (await stateStore.getSession(sessionId) === undefined) {
+                throw Object.assign(new Error(`Session "${sessionId}" does not exist.`), { code: SESSION_NOT_FOUND_CODE });
+            }
+            return updateSessionThread(root ?? basePath, sessionId, (thread) => ({ ...thread, turns: thread.turns.slice(0, Math.max(turns, 0)) }));
+        },
+        async exportSession(request) {
+            const root = request.basePath ?? basePath;
+            const session = await stateStore.getSession(request.sessionId);
+            if (session === undefined) {
+                throw Object.assign(new Error(`Session "${request.sessionId}" does not exist.`), { code: SESSION_NOT_FOUND_CODE });
+            }
+            const thread = await readSessionThread(root, request.sessionId);
+            const traceIds = [...new Set(thread.turns.flatMap((turn) => turn.traceId !== undefined ? [turn.traceId] : []))];
+            const sources = new Map(await Promise.all(traceIds.map(async (traceId) => [traceId, {
+                    trace: await traceStore.getTrace(traceId),
+                    usage: await stateStore.listUsage({ traceId }),
+                    calls: await readReplayLog(root, traceId).then((log) => log.calls, () => []),
+                }])));
+            const format = request.format ?? 'md';
+            const transcript = buildSessionTranscript(session, thread, sources);
+            return { format, transcript, content: renderSessionTranscript(transcript, format) };
+        },
+        async addScratchpadTasks(sessionId, tasks) {
+            await requireScratchpadSession(stateStore, sessionId);
+            return stateStore.addScratchpadTasks(sessionId, tasks);
+        },
+        async getScratchpad(sessionId) {
+            await requireScratchpadSession(stateStore, sessionId);
+            return stateStore.listScratchpad(sessionId);
+        },
+        async claimScratchpadTask(sessionId, claim) {
+            await requireScratchpadSession(stateStore, sessionId);
+            return stateStore.claimScratchpadTask(sessionId, claim);
+        },
+        async postScratchpadResult(sessionId, post) {
+            await requireScratchpadSession(stateStore, sessionId);
+            return stateStore.postScratchpadResult(sessionId, post);
+        },
+        async releaseScratchpadTask(sessionId, taskId, claimant) {
+            await requireScratchpadSession(stateStore, sessionId);
+            return stateStore.releaseScratchpadTask(sessionId, taskId, claimant);
+        },
         listSessions() {
             return stateStore.listSessions();
         },
@@ -1382,6 +2941,12 @@ function normalizeProviders(explicitProviders, providerOverride) {
     }
     return ['claude', 'openai', 'gemini'];
 }
+/**
+ * Workflows that ship with the runtime; a workspace workflow with the same id overrides them.
+ */
+export function getBuiltinWorkflow(workflowId) {
+    return workflowId === MAINTENANCE_WORKFLOW.workflowId ? MAINTENANCE_WORKFLOW : undefined;
+}
 function resolveWorkflowDir(explicitWorkflowDir, requestBasePath, defaultBasePath) {
     const resolvedBasePath = requestBasePath ?? defaultBasePath;
     return explicitWorkflowDir ?? findWorkflowDir(resolvedBasePath) ?? join(resolvedBasePath, 'workflows');
@@ -1735,7 +3300,11 @@ function resolveAgentTask(task, input, agent) {
     }
     return `Run the ${agent.name} agent.`;
 }
-function resolveAgentSystemPrompt(agent, metadata) {
+/**
+ * The instructions an agent runs under: its profile's `systemPrompt` (or `instructions`), else a
+ * persona built from its name and capabilities.
+ */
+export function resolveAgentSystemPrompt(agent, metadata = isRecord(agent.metadata) ? agent.metadata : {}) {
     const explicit = asOptionalString(metadata.systemPrompt) ?? asOptionalString(metadata.instructions);
     if (explicit !== undefined && explicit.trim().length > 0) {
         return explicit;
@@ -1745,7 +3314,36 @@ function resolveAgentSystemPrompt(agent, metadata) {
         : 'Capabilities: general assistance.';
     return `You are ${agent.name} (${agent.agentId}). ${capabilityLine} Respond concisely and focus on the task.`;
 }
-function buildAgentPrompt(agent, task, input, metadata) {
+/**
+ * The agent's system prompt with its template rendered, when it is one: the variables are the
+ * agent (`agent.id`, `agent.name`, `agent.capabilities`, `agent.team`), `task`, `input`, `date`
+ * and the profile's `variables`, and includes are ability files or built-in abilities. Missing
+ * variables and cut budget sections come back as warnings.
+ */
+async function renderAgentSystemPrompt(agent, metadata, task, input, workspacePath, abilities = []) {
+    const source = resolveAgentSystemPrompt(agent, metadata);
+    // The abilities of the profile's packs follow its own instructions.
+    const withAbilities = (text) => [text, describePackAbilities(abilities)].filter((part) => part !== undefined).join('\n\n');
+    if (!source.includes('{{')) {
+        return { systemPrompt: withAbilities(source), warnings: [] };
+    }
+    const { text, ...template } = await renderPromptTemplate(source, {
+        ...(isRecord(metadata.variables) ? metadata.variables : {}),
+        agent: { id: agent.agentId, name: agent.name, capabilities: agent.capabilities, team: asOptionalString(metadata.team) },
+        task,
+        input,
+        date: new Date().toISOString().slice(0, 10),
+    }, createAbilityIncludeResolver(workspacePath, Object.fromEntries(BUILTIN_ABILITIES.map((ability) => [ability.abilityId, ability.content]))));
+    return {
+        systemPrompt: withAbilities(text),
+        template,
+        warnings: [
+            ...(template.missingVariables.length > 0 ? [`The prompt template of agent "${agent.agentId}" uses variables with no value: ${template.missingVariables.join(', ')}.`] : []),
+            ...template.truncations.map((cut) => `A {{#budget ${cut.budgetTokens}}} section of agent "${agent.agentId}" was cut from about ${cut.originalTokens} tokens.`),
+        ],
+    };
+}
+function buildAgentPrompt(agent, task, input, metadata, workspace) {
     const team = asOptionalString(metadata.team);
     const sections = [
         `Agent: ${agent.agentId}`,
@@ -1753,6 +3351,7 @@ function buildAgentPrompt(agent, task, input, metadata) {
         agent.capabilities.length > 0 ? `Capabilities: ${agent.capabilities.join(', ')}` : undefined,
         team !== undefined ? `Team: ${team}` : undefined,
         input !== undefined ? `Input:\n${JSON.stringify(input, null, 2)}` : undefined,
+        ...(workspace === undefined ? [] : describeAgentWorkspace(workspace)),
     ];
     return sections.filter((value) => value !== undefined && value.length > 0).join('\n\n');
 }
@@ -1962,13 +3561,6 @@ function compareParallelTasks(left, right) {
     const rightPriority = right.priority ?? 0;
     return rightPriority - leftPriority || left.taskId.localeCompare(right.taskId);
 }
-function chunkArray(items, chunkSize) {
-    const chunks = [];
-    for (let index = 0; index < items.length; index += chunkSize) {
-        chunks.push(items.slice(index, index + chunkSize));
-    }
-    return chunks;
-}
 function aggregateParallelResults(results, mode) {
     if (mode === 'list') {
         return results;
@@ -2067,12 +3659,13 @@ function resolveTraceDuration(trace) {
     }
     return trace.stepResults.reduce((sum, step) => sum + step.durationMs, 0);
 }
-function createPromptExecutor(providerBridge, provider, model) {
+function createPromptExecutor(providerBridge, options) {
+    const { provider, model, traceId, noCache, signal, onText, onFailover } = options;
     return {
         getDefaultProvider: () => provider ?? 'claude',
         execute: async (request) => {
             const resolvedProvider = request.provider ?? provider ?? 'claude';
-            const bridgeResult = await providerBridge.executePrompt({
+            const executionRequest = {
                 provider: resolvedProvider,
                 prompt: request.prompt,
                 systemPrompt: request.systemPrompt,
@@ -2080,8 +3673,20 @@ function createPromptExecutor(providerBridge, provider, model) {
                 maxTokens: request.maxTokens,
                 temperature: request.temperature,
                 timeoutMs: request.timeout,
-            });
+                traceId,
+                noCache,
+                signal,
+                onText: onText === undefined ? undefined : (text) => onText(text, request.stepId),
+            };
+            const bridgeResult = request.outputSchema !== undefined
+                ? await providerBridge.executeStructured(executionRequest, request.outputSchema, request.consensus)
+                : request.consensus === undefined
+                    ? await providerBridge.executePrompt(executionRequest)
+                    : await providerBridge.executeConsensus(executionRequest, request.consensus);
             if (bridgeResult.type === 'response' || bridgeResult.type === 'failure') {
+                for (const failover of bridgeResult.response.failovers ?? []) {
+                    onFailover?.(failover, request.stepId);
+                }
                 return bridgeResult.response;
             }
             const content = [
@@ -2103,19 +3708,45 @@ function createPromptExecutor(providerBridge, provider, model) {
         },
     };
 }
-function createToolExecutor() {
+function createToolExecutor(readOnly, runtimeTools = {}) {
     return {
         isToolAvailable: (toolName) => toolName.trim().length > 0,
         getAvailableTools: () => ['*'],
-        execute: async (toolName, args) => ({
-            success: true,
-            output: {
-                toolName,
-                args,
-                mode: 'shared-runtime-simulated',
-            },
-            durationMs: 0,
-        }),
+        execute: async (toolName, args) => {
+            if (readOnly && isMutatingTool(toolName)) {
+                return {
+                    success: false,
+                    error: readOnlyViolation(`Tool "${toolName}"`).message,
+                    errorCode: READ_ONLY_ERROR_CODE,
+                    retryable: false,
+                    durationMs: 0,
+                };
+            }
+            const runtimeTool = runtimeTools[toolName];
+            if (runtimeTool !== undefined) {
+                const startedAt = Date.now();
+                try {
+                    return { success: true, output: await runtimeTool(args), durationMs: Date.now() - startedAt };
+                }
+                catch (error) {
+                    return {
+                        success: false,
+                        error: error instanceof Error ? error.message : String(error),
+                        retryable: false,
+                        durationMs: Date.now() - startedAt,
+                    };
+                }
+            }
+            return {
+                success: true,
+                output: {
+                    toolName,
+                    args,
+                    mode: 'shared-runtime-simulated',
+                },
+                durationMs: 0,
+            };
+        },
     };
 }
 function createDiscussionExecutor(traceId, provider, coordinator) {
@@ -2182,6 +3813,7 @@ function createDiscussionCoordinator(config) {
                             prompt,
                             temperature: request.config.temperature,
                             timeoutMs: request.config.providerTimeout > 0 ? request.config.providerTimeout : undefined,
+                            traceId: request.traceId,
                         });
                         if (bridgeResult.type === 'response' && bridgeResult.response.success) {
                             usedRealProvider = true;
@@ -2445,22 +4077,6 @@ function yieldToEventLoop() {
         setTimeout(resolve, 10);
     });
 }
-async function readWorkspaceConfig(basePath) {
-    const configPath = join(basePath, '.automatosx', 'config.json');
-    try {
-        const raw = await readFile(configPath, 'utf8');
-        const parsed = JSON.parse(raw);
-        return isRecord(parsed) ? parsed : {};
-    }
-    catch {
-        return {};
-    }
-}
-async function writeWorkspaceConfig(basePath, config) {
-    const configPath = join(basePath, '.automatosx', 'config.json');
-    await mkdir(join(basePath, '.automatosx'), { recursive: true });
-    await writeFile(configPath, `${JSON.stringify(config, null, 2)}\n`, 'utf8');
-}
 function getValueAtPath(config, path) {
     const parts = path.split('.').filter((part) => part.length > 0);
     let current = config;
@@ -2488,9 +4104,65 @@ function setValueAtPath(config, path, value) {
     }
     current[parts[parts.length - 1]] = value;
 }
+// What a workflow posts to the scratchpad task it claimed.
+function describeWorkflowOutput(output) {
+    if (output === undefined) {
+        return 'The workflow completed.';
+    }
+    return typeof output === 'string' ? output : JSON.stringify(output);
+}
 function asOptionalString(value) {
     return typeof value === 'string' && value.length > 0 ? value : undefined;
 }
 function isRecord(value) {
     return value !== null && typeof value === 'object' && !Array.isArray(value);
 }
+// Every namespace inside a scope, when no single namespace is named.
+function withinScope(entries, scope) {
+    const prefix = scopedNamespace('', scope);
+    return prefix === '' ? entries : entries.filter((entry) => entry.namespace?.startsWith(prefix) === true);
+}
+export { BORROW_MODES, CACHE_DIR_ENV_VAR, CONCURRENCY_PRIMITIVES, createLanguageRegistry, createPositionMapper, createQueryExtractor, GRAMMAR_RUNTIME_ENV_VAR, isCfgActive, isTestFile, loadWorkspaceLanguageRegistry, parseCodeSource, } from './code-intel/index.js';
+export { CONFIG_ENV_PREFIX, CONFIG_FLAGS_ENV_VAR, CONFIG_LAYERS, CONFIG_PROFILE_ENV_VAR, SYSTEM_CONFIG_ENV_VAR, USER_CONFIG_ENV_VAR, explainConfigKey, loadLayeredConfig, validateLayeredConfig, } from './config-layers.js';
+export { formatConfigIssue, migrateWorkspaceConfig, validateWorkspaceConfig } from './config-schema.js';
+export { WORKSPACE_CONFIG_VERSION } from '@defai.digital/contracts';
+export { findExecutable } from './environment-doctor.js';
+export { SYMBOL_CHANGE_TYPES } from './index-watcher.js';
+export { parseTestFailures } from './code-intel/test-failures.js';
+export { UNSAFE_FINDING_KINDS } from './code-intel/rust-unsafe.js';
+export { getCodeSymbolId, REFERENCE_RESOLUTIONS } from './code-intel/references.js';
+export { GRAPH_FORMATS, GRAPH_KINDS } from './code-intel/graph-export.js';
+export { CALL_HIERARCHY_DIRECTIONS } from './code-intel/call-hierarchy.js';
+export { createSymbolSearch, describeSymbolHits, fuzzyMatch, parseSymbolSearchQuery } from './code-intel/symbol-search.js';
+export { buildTextIndex, getTextIndexPath, requiredLiterals } from './code-intel/text-search.js';
+export { createSearchService } from './hybrid-search.js';
+export { chunkCodeIndex } from './code-intel/chunks.js';
+export { MEMORY_ARCHIVE_FORMAT, MEMORY_ARCHIVE_VERSION, readMemoryArchive, writeMemoryArchive } from './memory-archive.js';
+export { DEFAULT_DEDUP_THRESHOLD, MEMORY_DEDUP_ACTIONS, mergeMemoryValues } from './memory-dedup.js';
+export { findStaleMemory, hashMemorySource } from './memory-provenance.js';
+export { createMemoryKey, decodeMemoryKey, MEMORY_KEY_ENV_VAR, MEMORY_KEY_SOURCES, memoryCipherFor } from './memory-encryption.js';
+export { createEmbedder, EMBEDDING_BACKENDS, EMBEDDINGS_NAMESPACE, embedCodeChunks, readEmbedderConfig } from './embeddings.js';
+export { MAINTENANCE_TASKS } from './maintenance.js';
+export { adaptProviderPrompt, PROVIDER_PROMPT_DIALECTS, resolvePromptDialect } from './provider-prompt.js';
+export { PROVIDER_CACHE_NAMESPACE } from './provider-cache.js';
+export { resolveProviderCapabilities } from './provider-capabilities.js';
+export { MAX_CONSENSUS_PROVIDERS, MIN_CONSENSUS_PROVIDERS } from './provider-consensus.js';
+export { DEFAULT_MODEL_PRICING, PROVIDER_ROUTING_POLICIES } from './provider-routing.js';
+export { isMutatingTool, isReadOnlyEnv, READ_ONLY_ENV_VAR, READ_ONLY_ERROR_CODE } from './read-only.js';
+export { validateJsonSchema } from './structured-output.js';
+export { renderPromptTemplate } from './prompt-template.js';
+export { parsePipeline, PIPELINE_ERROR_CODE, } from './pipeline.js';
+export { EVAL_RUNS_DIR, EVAL_SUITE_INVALID_CODE, parseEvalSuite, } from './eval-harness.js';
+export { REPLAY_DIR, REPLAY_DIVERGED_CODE, REPLAY_NOT_FOUND_CODE, } from './run-replay.js';
+export { SESSION_AGENT_MISSING_CODE, SESSION_ID_INVALID_CODE, SESSION_NOT_FOUND_CODE, SESSION_THREAD_DIR, } from './session-threads.js';
+export { NEXT_SCRATCHPAD_TASK, SCRATCHPAD_NO_OPEN_TASK_CODE } from './session-scratchpad.js';
+export { TRANSCRIPT_FORMATS, } from './session-transcript.js';
+export { createWorkspaceCheckpoint, diffWorkspaceCheckpoint, restoreWorkspaceCheckpoint, } from './workspace-checkpoint.js';
+export { BUDGET_EXCEEDED_CODE, BUDGET_INVALID_CODE, parseDurationMs, parseRunBudget, } from './run-budget.js';
+export { ABILITY_PACKS_DIR, AGENT_PROFILE_INVALID_CODE, } from './agent-profiles.js';
+export { SUGGESTED_AGENTS_DIR, } from './agent-suggest.js';
+export { APPROVAL_DENIED_CODE, APPROVAL_POLICY_FILE, APPROVALS_INVALID_CODE, APPROVALS_UNSUPPORTED_CODE, describeApproval, } from './approval-gates.js';
+export { AGENT_PERMISSION_DENIED_CODE, AGENT_PERMISSIONS_ENV_VAR, AGENT_PERMISSIONS_INVALID_CODE, readAgentPermissions, } from './agent-permissions.js';
+export { MAX_DELEGATION_DEPTH, parseHandoffContract, } from './agent-delegation.js';
+export { assembleContext, CONTEXT_TRIM_STRATEGIES } from './context-budget.js';
+export { USAGE_GROUPINGS } from './usage-tracker.js';
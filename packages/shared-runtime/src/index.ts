@@ -1,7 +1,7 @@
 import { randomUUID } from 'node:crypto';
 import { execFile } from 'node:child_process';
 import { mkdir, readFile, writeFile } from 'node:fs/promises';
-import { join } from 'node:path';
+import { dirname, isAbsolute, join } from 'node:path';
 import { promisify } from 'node:util';
 import {
   createRealStepExecutor,
@@ -9,6 +9,7 @@ import {
   createWorkflowRunner,
   createStepGuardEngine,
   findWorkflowDir,
+  runScheduled,
   type StepResult,
   type StepGuardContext,
   type StepGuardPolicy,
@@ -23,10 +24,23 @@ import {
 } from '@defai.digital/trace-store';
 import {
   createStateStore,
+  SCRATCHPAD_CONFLICT_CODE,
+  scopedNamespace,
   type AgentEntry,
+  type Fact,
+  type FactInput,
+  type FactQuery,
   type FeedbackEntry,
   type MemoryEntry,
+  type MemoryNamespaceStats,
+  type MemoryNamespaceTransfer,
+  type MemoryScope,
+  type MemorySource,
   type PolicyEntry,
+  type ScratchpadClaim,
+  type ScratchpadPost,
+  type ScratchpadTask,
+  type ScratchpadTaskInput,
   type SemanticEntry,
   type SemanticNamespaceStats,
   type SemanticSearchResult,
@@ -42,7 +56,200 @@ import {
   type ReviewSeverity,
   type RuntimeReviewResponse,
 } from './review.js';
-import { createProviderBridge } from './provider-bridge.js';
+import { createProviderBridge, type ProviderModelList } from './provider-bridge.js';
+import { createProviderResponseCache, readProviderCacheSettings, type ProviderCacheHit, type ProviderCacheStats } from './provider-cache.js';
+import type { ConsensusOptions, ProviderConsensus } from './provider-consensus.js';
+import type { ProviderFailover } from './provider-failover.js';
+import type { ProviderHealthStatus } from './provider-health.js';
+import type { JsonSchema, StructuredOutput } from './structured-output.js';
+import { readFallbackProviders, type ProviderQuotaSelection, type ProviderQuotaStatus } from './provider-quota.js';
+import type { ProviderRateLimitStatus } from './provider-rate-limit.js';
+import { parseRoutingOptions } from './provider-routing.js';
+import { createAbilityIncludeResolver, PROMPT_TEMPLATE_ERROR_CODE, renderPromptTemplate, type RenderedPromptTemplate } from './prompt-template.js';
+import { assertWritable, isMutatingTool, isReadOnlyEnv, READ_ONLY_ERROR_CODE, readOnlyViolation } from './read-only.js';
+import { createUsageTracker, type UsageReport, type UsageReportOptions } from './usage-tracker.js';
+import {
+  buildCodeIndex,
+  loadWorkspaceLanguageRegistry,
+  parseWorkspaceCodeSource,
+  readCodeIndex,
+  searchCodeSymbols,
+  type CodeLanguage,
+  type CodeSymbol,
+  type CodeFileIndex,
+  type CodeSymbolKind,
+  type CodeTestFilter,
+  type RuntimeCodeIndexResponse,
+} from './code-intel/index.js';
+import {
+  installPreCommitHook,
+  resolvePreCommitConfig,
+  runPreCommitPipeline,
+  uninstallPreCommitHook,
+  type PreCommitMode,
+  type RuntimeHookInstallResponse,
+  type RuntimePreCommitResponse,
+} from './git-hooks.js';
+import {
+  explainConfigKey,
+  loadLayeredConfig,
+  readProjectConfig,
+  readWorkspaceConfig,
+  validateLayeredConfig,
+  writeProjectConfig,
+  type ConfigExplanation,
+  type ConfigValidation,
+} from './config-layers.js';
+import { formatConfigIssue, validateWorkspaceConfig } from './config-schema.js';
+import { createDebugBundle, type RuntimeDebugBundleResponse } from './debug-bundle.js';
+import { diagnoseEnvironment, type RuntimeEnvironmentReport } from './environment-doctor.js';
+import { answerProjectQuestion, type RuntimeProjectAnswer } from './project-query.js';
+import { generateImplementationScaffold, type ImplementationScaffold } from './code-intel/implementation-scaffold.js';
+import { analyzeTestImpact, runImpactedTests, type RuntimeTestImpact } from './code-intel/test-impact.js';
+import { runTestAndFix, type RuntimeTestFixReport, type RuntimeTestFixRequest } from './test-fix.js';
+import { runReviewAndAnnotate, type RuntimeReviewAnnotateRequest, type RuntimeReviewAnnotationReport } from './review-annotate.js';
+import {
+  AGENT_SCOPE_ERROR_CODE,
+  createAgentWorkspaceTools,
+  describeAgentWorkspace,
+  offersAgentCommands,
+  resolveAgentWorkspace,
+  type AgentWorkspace,
+} from './agent-workspace.js';
+import { assembleContext, describeContextCuts, readContextBudgetSettings, type AssembledContext, type ContextInput } from './context-budget.js';
+import { readConcurrencySettings, workflowStepProvider } from './provider-concurrency.js';
+import {
+  checkDelegationChain,
+  createHandoffTools,
+  readAgentHandoffs,
+  validateHandoffInput,
+  type HandoffContract,
+} from './agent-delegation.js';
+import {
+  parsePipeline,
+  pipelineRunDir,
+  readPipelineCheckpoint,
+  runPipelineStages,
+  writePipelineCheckpoint,
+  type PipelineAgentRunner,
+  type PipelineCheckpoint,
+  type PipelineDefinition,
+  type PipelineRunReport,
+  type PipelineStage,
+  type PipelineStageResult,
+} from './pipeline.js';
+import { parseEvalSuite, runEvalSuite, type EvalCaseResult, type EvalRunReport } from './eval-harness.js';
+import { createRunRecorder, readReplayLog, type ReplayRun, type ReplaySummary } from './run-replay.js';
+import {
+  branchSessionThread,
+  readSessionThread,
+  SESSION_AGENT_MISSING_CODE,
+  SESSION_NOT_FOUND_CODE,
+  updateSessionThread,
+  type SessionThread,
+} from './session-threads.js';
+import { claimTaskForRun, describeScratchpad, postRunResult, requireScratchpadSession } from './session-scratchpad.js';
+import {
+  buildSessionTranscript,
+  renderSessionTranscript,
+  type SessionTranscript,
+  type TranscriptFormat,
+  type TranscriptSource,
+} from './session-transcript.js';
+import {
+  AGENT_PERMISSION_DENIED_CODE,
+  AGENT_PERMISSIONS_ENV_VAR,
+  describeViolation,
+  findPathViolations,
+  listChangedFiles,
+  permitsPath,
+  readAgentPermissions,
+  snapshotWholeWorkspace,
+  type AgentPermissions,
+  type AgentPermissionViolation,
+} from './agent-permissions.js';
+import {
+  AGENT_PROFILE_INVALID_CODE,
+  describePackAbilities,
+  profileLayers,
+  resolveAgentProfile,
+  type AgentProfileLayers,
+  type PackAbility,
+  type ResolvedAgentProfile,
+} from './agent-profiles.js';
+import { suggestAgents, type AgentSuggestResponse } from './agent-suggest.js';
+import {
+  APPROVAL_DENIED_CODE,
+  APPROVALS_INVALID_CODE,
+  APPROVALS_UNSUPPORTED_CODE,
+  createApprovalGate,
+  createCostGate,
+  describeApproval,
+  gatesWrite,
+  loadApprovalPolicy,
+  readApprovalGates,
+  unheldApprovals,
+  type ApprovalGates,
+  type ApprovalPolicy,
+  type ApprovalRecord,
+  type Approver,
+  type CostGate,
+} from './approval-gates.js';
+import {
+  BUDGET_EXCEEDED_CODE,
+  BUDGET_INVALID_CODE,
+  combineBudgets,
+  createBudgetMeter,
+  describeExceeded,
+  parseRunBudget,
+  type BudgetMeter,
+  type BudgetReport,
+  type RunBudget,
+} from './run-budget.js';
+import { buildUnsafeReports, type UnsafeReport } from './code-intel/rust-unsafe.js';
+import { findUnreferencedSymbols, type RuntimeUnreferencedSymbols } from './code-intel/dead-code.js';
+import { findSymbolReferences, type RuntimeSymbolReferences } from './code-intel/references.js';
+import { renderFileSkeleton, renderSignatures, type FileSkeleton, type SignatureView } from './code-intel/signatures.js';
+import { readSymbolBody, type SymbolBody } from './code-intel/symbol-body.js';
+import { buildCallHierarchy, type CallHierarchy, type CallHierarchyDirection } from './code-intel/call-hierarchy.js';
+import { analyzeChangeImpact, type RuntimeChangeImpact } from './code-intel/change-impact.js';
+import { renderOutline, type CodeOutline } from './code-intel/outline.js';
+import { renderRepoMap, type RepoMap } from './code-intel/repo-map.js';
+import { exportCodeGraph, type GraphFormat, type GraphKind, type RuntimeGraphExport } from './code-intel/graph-export.js';
+import { describeSymbolHits, searchSymbolIndex, type SymbolSearchHit, type SymbolSearchResult } from './code-intel/symbol-search.js';
+import { searchText, type RuntimeTextSearch, type TextSearchMatch } from './code-intel/text-search.js';
+import { createSearchService, type RuntimeHybridSearch } from './hybrid-search.js';
+import { readMemoryArchive, writeMemoryArchive, type RuntimeMemoryExportResponse, type RuntimeMemoryImportResponse } from './memory-archive.js';
+import { memoryText, resolveMemoryDedupPolicy, storeDeduplicatedMemory, type MemoryDedupAction, type RuntimeMemoryStoreResponse } from './memory-dedup.js';
+import { memoryCipherFor } from './memory-encryption.js';
+import { findStaleMemory, recordMemorySource, type RuntimeMemoryStaleResponse } from './memory-provenance.js';
+import { createEmbedder, embedCodeChunks, readEmbedderConfig, type Embedder, type EmbeddingBackend, type RuntimeEmbeddingResponse } from './embeddings.js';
+import {
+  checkProviderHealth,
+  collectProviderIds,
+  isMaintenanceDue,
+  MAINTENANCE_TASKS,
+  MAINTENANCE_WORKFLOW,
+  pruneCaches,
+  pruneMemoryEntries,
+  readMaintenanceConfig,
+  readMaintenanceState,
+  rotateLogs,
+  summarizeMemoryEvictions,
+  writeMaintenanceState,
+  type MaintenanceTaskId,
+  type MaintenanceTaskResult,
+  type RuntimeMaintenanceResponse,
+  type RuntimeMemoryPruneResponse,
+} from './maintenance.js';
+import {
+  requestIndexServer,
+  startIndexServer,
+  type IndexServerHandle,
+  type IndexServerStatus,
+} from './index-server.js';
+import { startIndexWatcher, type IndexWatcher, type IndexWatcherConfig } from './index-watcher.js';
+import { createWorkspaceCheckpoint, restoreWorkspaceCheckpoint } from './workspace-checkpoint.js';
 
 const execFileAsync = promisify(execFile);
 
@@ -56,6 +263,32 @@ export interface RuntimeWorkflowRequest {
   model?: string;
   input?: Record<string, unknown>;
   surface?: TraceSurface;
+  /**
+   * A task of the session's scratchpad to hold while the workflow runs, or `next` for the next free
+   * one; its `title` is the input's `task` unless that is set, and the output is posted to it.
+   */
+  claim?: string;
+  /** Skips the provider response cache lookup; fresh responses still refresh the cache. */
+  noCache?: boolean;
+  /** Limits on the run's provider calls; a run that goes over one is stopped and fails with `BUDGET_EXCEEDED`. */
+  budget?: RunBudget;
+  /** Receives provider reply text as it streams, with the prompt step it belongs to. */
+  onText?: (text: string, stepId?: string) => void;
+  /** Told when a provider fails partway through a step's reply and a fallback provider continues it. */
+  onProviderFailover?: (failover: RuntimeProviderFailover) => void;
+  onStepStart?: (stepId: string) => void;
+  onStepComplete?: (step: RuntimeWorkflowStepProgress) => void;
+}
+
+export interface RuntimeProviderFailover extends ProviderFailover {
+  stepId?: string;
+}
+
+export interface RuntimeWorkflowStepProgress {
+  stepId: string;
+  success: boolean;
+  durationMs: number;
+  error?: string;
 }
 
 export interface RuntimeDiscussionRequest {
@@ -90,6 +323,12 @@ export interface RuntimeWorkflowResponse {
   };
   totalDurationMs?: number;
   workflowDir: string;
+  /** The scratchpad task the workflow claimed, with the result it posted. */
+  claimed?: ScratchpadTask;
+  /** Set when the result could not be posted to the claimed task. */
+  warnings?: string[];
+  /** Set for runs with a budget: what the run used, and the limit that stopped it if one did. */
+  budget?: BudgetReport;
 }
 
 export interface RuntimeDiscussionResponse {
@@ -156,7 +395,18 @@ export interface RuntimeCallRequest {
   systemPrompt?: string;
   maxTokens?: number;
   temperature?: number;
+  stopSequences?: string[];
   surface?: TraceSurface;
+  /** Skips the provider response cache lookup; fresh responses still refresh the cache. */
+  noCache?: boolean;
+  /** Receives the reply text as the provider streams it. Not called under `consensus`. */
+  onText?: (text: string) => void;
+  /** Sends the prompt to several providers at once; see `ax call --consensus`. */
+  consensus?: Partial<ConsensusOptions>;
+  /** Asks for a reply that is one JSON value; see `ax call --response-format json`. */
+  responseFormat?: 'json';
+  /** Asks for a reply that fits this JSON Schema, repairing replies that do not; see `ax call --schema`. */
+  outputSchema?: JsonSchema;
 }
 
 export interface RuntimeCallResponse {
@@ -173,6 +423,14 @@ export interface RuntimeCallResponse {
     outputTokens: number;
     totalTokens: number;
   };
+  /** Set when the response came from the response cache. */
+  cache?: ProviderCacheHit;
+  /** Set when the provider failed partway through the reply and fallback providers finished it. */
+  failovers?: ProviderFailover[];
+  /** Set for consensus calls: which providers answered and whose answer was returned. */
+  consensus?: ProviderConsensus;
+  /** Set for calls with an `outputSchema`: the parsed reply and how many attempts it took. */
+  structured?: StructuredOutput;
   error?: {
     code?: string;
     message?: string;
@@ -196,6 +454,11 @@ export interface RuntimeStatusResponse {
     defaultProvider?: string;
     providerExecutionMode: 'auto' | 'simulate' | 'require-real';
     configuredExecutors: string[];
+    providerQuotas: ProviderQuotaStatus[];
+    /** Providers with queued calls or a rate-limit cooldown; empty when every call goes straight through. */
+    providerRateLimits: ProviderRateLimitStatus[];
+    /** The last health probe of each probed provider; fresh when the status was asked for with `probe`. */
+    providerHealth: ProviderHealthStatus[];
   };
   activeSessions: SessionEntry[];
   runningTraces: TraceRecord[];
@@ -239,6 +502,33 @@ export interface RuntimeAgentRunRequest {
   surface?: TraceSurface;
   parentTraceId?: string;
   rootTraceId?: string;
+  /** Aborting stops the provider call; the run then fails with `PROVIDER_CANCELLED`. */
+  signal?: AbortSignal;
+  /** Called as the run moves through its stages, with a percentage and what it is doing. */
+  onProgress?: (percent: number, message: string) => void;
+  /** Receives the reply text as the provider streams it. */
+  onText?: (text: string) => void;
+  /** Workspace files whose contents go to the agent with the task; they must lie inside `scope` when it is set. */
+  files?: string[];
+  /** A directory of the workspace the agent is confined to: the provider runs there and is told to stay inside it. */
+  scope?: string;
+  /** Skips the provider response cache lookup; fresh responses still refresh the cache. */
+  noCache?: boolean;
+  /** Holds the reply to this JSON Schema, repairing it as `callProvider` does; the parsed value is in `structured`. */
+  outputSchema?: JsonSchema;
+  /** The agents that handed this run its task, first delegator first; set by `delegateTask`. */
+  delegationChain?: string[];
+  /** Limits on the run, on top of the `budget` of the agent's profile; the tighter of each applies. */
+  budget?: RunBudget;
+  /** Asked about the actions the `approvals` of the agent's profile hold that the approval policy does not allow. */
+  approve?: Approver;
+  /** The approval policy file, relative to `basePath`; `.automatosx/approval-policy.json` by default. */
+  approvalPolicy?: string;
+  /**
+   * A task of the session's scratchpad to hold while the agent runs, or `next` for the next free
+   * one; its `title` is the task unless one is given, and the reply is posted to it.
+   */
+  claim?: string;
 }
 
 export interface RuntimeAgentRunResponse {
@@ -256,12 +546,105 @@ export interface RuntimeAgentRunResponse {
     outputTokens: number;
     totalTokens: number;
   };
+  /** Set when the response came from the response cache. */
+  cache?: ProviderCacheHit;
+  /** Set for runs with an `outputSchema`: the parsed reply and how many attempts it took. */
+  structured?: StructuredOutput;
+  /** Set for runs with a budget: what the run used, and the limit that stopped it if one did. */
+  budget?: BudgetReport;
+  /** The permissions of the agent's profile, when it declares them. */
+  permissions?: AgentPermissions;
+  /** What the run did or tried beyond those permissions. */
+  permissionViolations?: AgentPermissionViolation[];
+  /** The profiles and ability packs the agent's profile is layered from, when it has any. */
+  profile?: AgentProfileLayers;
+  /** The approval gates of the agent's profile, when it declares them. */
+  approvals?: ApprovalGates;
+  /** The approvals the run asked for, and how each was decided. */
+  approvalDecisions?: ApprovalRecord[];
+  /** The scratchpad task the run claimed, with the result it posted. */
+  claimed?: ScratchpadTask;
   error?: {
     code?: string;
     message?: string;
   };
 }
 
+/** A sub-task handed to an agent under a contract. */
+export interface RuntimeDelegationRequest {
+  /** The agent the sub-task goes to. */
+  agentId: string;
+  task: string;
+  input?: Record<string, unknown>;
+  contract: HandoffContract;
+  /** The delegating agent, when an agent hands the work over rather than a person or workflow. */
+  fromAgentId?: string;
+  /** The agents above this handoff, first delegator first; defaults to `fromAgentId` alone. */
+  delegationChain?: string[];
+  traceId?: string;
+  sessionId?: string;
+  basePath?: string;
+  surface?: TraceSurface;
+  parentTraceId?: string;
+  rootTraceId?: string;
+  signal?: AbortSignal;
+  /** Passed on to the sub-task's run; see `RuntimeAgentRunRequest`. */
+  approve?: Approver;
+  approvalPolicy?: string;
+}
+
+export interface RuntimeDelegationResponse {
+  /** The trace of the sub-task's run; unset when the handoff was refused before it ran. */
+  traceId?: string;
+  agentId: string;
+  success: boolean;
+  provider?: string;
+  model?: string;
+  /** The result, which fits the contract's output schema. */
+  output?: unknown;
+  structured?: StructuredOutput;
+  usage?: {
+    inputTokens: number;
+    outputTokens: number;
+    totalTokens: number;
+  };
+  latencyMs: number;
+  warnings: string[];
+  error?: {
+    code: string;
+    message: string;
+  };
+}
+
+export interface RuntimeContextRequest {
+  task: string;
+  systemPrompt?: string;
+  /** Whose context window to fit; defaults to `claude`. */
+  provider?: string;
+  /** Overrides the provider's context window. */
+  maxTokens?: number;
+  /** Searches memory for hits to include. */
+  memoryQuery?: string;
+  memoryNamespace?: string;
+  memoryLimit?: number;
+  /** Includes the repo map; needs a symbol index. */
+  repoMap?: boolean;
+  /** Workspace files to attach. */
+  files?: string[];
+  history?: ContextInput['history'];
+  basePath?: string;
+}
+
+/** What an agent would be sent for a task, with its profile's prompt template rendered. */
+export interface RuntimeAgentPromptPreview {
+  agentId: string;
+  systemPrompt: string;
+  prompt: string;
+  /** Set when the system prompt is a template: what it included, lacked and cut. */
+  template?: Omit<RenderedPromptTemplate, 'text'>;
+  warnings: string[];
+}
+
 export interface RuntimeAgentRecommendation {
   agentId: string;
   name: string;
@@ -336,6 +719,150 @@ export interface RuntimeParallelRunResponse {
   };
 }
 
+/** A pipeline YAML file to run; see `runPipeline`. */
+export interface RuntimePipelineRequest {
+  /** The pipeline file, relative to `basePath` unless absolute. */
+  file: string;
+  /** Overrides of the pipeline's `inputs`. */
+  input?: Record<string, unknown>;
+  /** The provider of stages that do not name one. */
+  provider?: string;
+  /** Limits on the whole run, on top of the pipeline's `budget`; the tighter of each applies. */
+  budget?: RunBudget;
+  traceId?: string;
+  sessionId?: string;
+  basePath?: string;
+  surface?: TraceSurface;
+  signal?: AbortSignal;
+  onStageStart?: (stage: PipelineStage) => void;
+  onStageEnd?: (result: PipelineStageResult) => void;
+  /** Receives the reply text of each stage as the provider streams it. */
+  onText?: (text: string, stageId: string) => void;
+  /** Passed on to the stages' agent runs; see `RuntimeAgentRunRequest`. */
+  approve?: Approver;
+  approvalPolicy?: string;
+}
+
+/** A pipeline run to continue from its checkpoint; see `resumePipeline`. */
+export interface RuntimePipelineResumeRequest {
+  /** The trace id of the run, which names its directory under `.automatosx/pipelines/`. */
+  runId: string;
+  /** Replaces the budget the run was started with; the resumed part of the run gets it afresh. */
+  budget?: RunBudget;
+  basePath?: string;
+  surface?: TraceSurface;
+  signal?: AbortSignal;
+  onStageStart?: (stage: PipelineStage) => void;
+  onStageEnd?: (result: PipelineStageResult) => void;
+  onText?: (text: string, stageId: string) => void;
+  approve?: Approver;
+  approvalPolicy?: string;
+}
+
+/** An eval suite YAML file to run; see `runEval`. */
+export interface RuntimeEvalRequest {
+  /** The suite file, relative to `basePath` unless absolute. */
+  file: string;
+  /** Replace the suite's agents. */
+  agents?: string[];
+  /** Replace the suite's providers. */
+  providers?: string[];
+  /** Run only these cases. */
+  cases?: string[];
+  basePath?: string;
+  surface?: TraceSurface;
+  signal?: AbortSignal;
+  /** Called as each case finishes for an agent and provider. */
+  onResult?: (result: EvalCaseResult) => void;
+}
+
+/** A recorded run to run again against its replay log; see `replayRun`. */
+export interface RuntimeReplayRequest {
+  /** The trace id of the recorded top-level agent run or pipeline. */
+  runId: string;
+  basePath?: string;
+  surface?: TraceSurface;
+  signal?: AbortSignal;
+  /** Asked again about the actions the agents' `approvals` hold, as the recorded run was. */
+  approve?: Approver;
+  approvalPolicy?: string;
+}
+
+export interface RuntimeReplayResponse extends ReplaySummary {
+  /** The recorded run. */
+  runId: string;
+  /** The trace of the replay. */
+  traceId: string;
+  command: ReplayRun['command'];
+  /** Whether the replayed run succeeded; it may have, and still have diverged from the recording. */
+  success: boolean;
+  result: RuntimeAgentRunResponse | RuntimePipelineResponse;
+}
+
+/** A session to open again and, with a `task`, to continue; see `resumeSession`. */
+export interface RuntimeSessionResumeRequest {
+  sessionId: string;
+  /** The next turn; without one the session is only opened again. */
+  task?: string;
+  /** Runs the turn with this agent rather than the session's; needed before the session's first run. */
+  agentId?: string;
+  provider?: string;
+  model?: string;
+  basePath?: string;
+  surface?: TraceSurface;
+  signal?: AbortSignal;
+  approve?: Approver;
+  approvalPolicy?: string;
+}
+
+export interface RuntimeSessionResumeResponse {
+  session: SessionEntry;
+  /** The thread as the turn left it. */
+  thread: SessionThread;
+  /** The run of the `task`, when one was given. */
+  run?: RuntimeAgentRunResponse;
+}
+
+/** A session to fork; see `branchSession`. */
+export interface RuntimeSessionBranchRequest {
+  sessionId: string;
+  /** The branch's session id; a new UUID by default. */
+  branchId?: string;
+  /** How many turns of the session the branch keeps; all of them by default. */
+  turns?: number;
+  /** The branch's task; the session's by default. */
+  task?: string;
+  basePath?: string;
+}
+
+export interface RuntimeSessionBranchResponse {
+  session: SessionEntry;
+  thread: SessionThread;
+}
+
+/** A session to write a transcript of; see `exportSession`. */
+export interface RuntimeSessionExportRequest {
+  sessionId: string;
+  /** `md` by default. */
+  format?: TranscriptFormat;
+  basePath?: string;
+}
+
+export interface RuntimeSessionExport {
+  format: TranscriptFormat;
+  transcript: SessionTranscript;
+  /** The transcript rendered in `format`. */
+  content: string;
+}
+
+export interface RuntimePipelineResponse extends PipelineRunReport {
+  traceId: string;
+  totalDurationMs: number;
+  warnings: string[];
+  /** Set for runs with a budget: what this attempt of the run used, and the limit that stopped it if one did. */
+  budget?: BudgetReport;
+}
+
 export interface RuntimeWorkflowDescription {
   workflowId: string;
   name?: string;
@@ -444,6 +971,10 @@ export interface RuntimeGitStatusResponse {
   clean: boolean;
 }
 
+export interface RuntimeImplementationScaffold extends ImplementationScaffold {
+  written: boolean;
+}
+
 export interface RuntimeCommitPrepareResponse {
   message: string;
   stagedPaths: string[];
@@ -473,15 +1004,61 @@ export interface RuntimePrCreateResponse {
 
 export interface SharedRuntimeService {
   callProvider(request: RuntimeCallRequest): Promise<RuntimeCallResponse>;
+  listProviderModels(request?: { provider?: string; basePath?: string }): Promise<ProviderModelList>;
   runWorkflow(request: RuntimeWorkflowRequest): Promise<RuntimeWorkflowResponse>;
   runDiscussion(request: RuntimeDiscussionRequest): Promise<RuntimeDiscussionResponse>;
   runDiscussionQuick(request: RuntimeDiscussionRequest): Promise<RuntimeDiscussionResponse>;
   runDiscussionRecursive(request: RuntimeRecursiveDiscussionRequest): Promise<RuntimeRecursiveDiscussionResponse>;
+  /**
+   * Runs an agent on a task. A run with a budget, from the `budget` of the agent's profile or the
+   * request, is stopped when it goes over a limit and fails with `BUDGET_EXCEEDED`, reporting what it used.
+   */
   runAgent(request: RuntimeAgentRunRequest): Promise<RuntimeAgentRunResponse>;
+  /**
+   * Hands a sub-task to an agent under a contract: the input is checked against the contract, the
+   * agent runs on the contract's provider within its budget, and the result is returned only when
+   * it fits the output schema. Chains of handoffs are limited in depth and may not loop.
+   */
+  delegateTask(request: RuntimeDelegationRequest): Promise<RuntimeDelegationResponse>;
+  /** Gathers memory hits, the repo map, files and history for a task and trims them to the provider's context window. */
+  assembleContext(request: RuntimeContextRequest): Promise<AssembledContext>;
+  /** Renders the prompts `runAgent` would send without calling a provider; undefined for an unknown agent. */
+  renderAgentPrompt(request: { agentId: string; task?: string; input?: Record<string, unknown>; basePath?: string }): Promise<RuntimeAgentPromptPreview | undefined>;
   recommendAgents(request: RuntimeAgentRecommendRequest): Promise<RuntimeAgentRecommendation[]>;
   planParallel(request: { tasks: RuntimeParallelTask[] }): Promise<RuntimeParallelPlan>;
   runParallel(request: RuntimeParallelRunRequest): Promise<RuntimeParallelRunResponse>;
-  getStatus(request?: { limit?: number }): Promise<RuntimeStatusResponse>;
+  /**
+   * Runs a YAML pipeline of agent stages with conditions, loops over files and retries, saving each
+   * stage's output as an artifact, and the run's checkpoint, under `.automatosx/pipelines/<trace-id>/`.
+   * A run that goes over its budget, from the pipeline or the request, fails with `BUDGET_EXCEEDED`
+   * and skips the stages it had not started. Throws an error with the `PIPELINE_NOT_FOUND` or `PIPELINE_INVALID` code when the file cannot be
+   * read or is malformed.
+   */
+  runPipeline(request: RuntimePipelineRequest): Promise<RuntimePipelineResponse>;
+  /**
+   * Continues a pipeline run from its checkpoint, with the pipeline, input and provider it started
+   * with: stages that succeeded keep their results and artifacts, the rest run again under the same
+   * trace id. Throws an error with the `PIPELINE_CHECKPOINT_NOT_FOUND` code for an unknown run.
+   */
+  resumePipeline(request: RuntimePipelineResumeRequest): Promise<RuntimePipelineResponse>;
+  /**
+   * Runs the cases of an eval suite with each of its agents and providers and checks their
+   * assertions, for pass rates and costs to compare; the report, saved under
+   * `.automatosx/evals/runs/`, sets each agent and provider against the suite's previous run.
+   * Throws an error with the `EVAL_SUITE_NOT_FOUND` or `EVAL_SUITE_INVALID` code when the file
+   * cannot be read or is malformed.
+   */
+  runEval(request: RuntimeEvalRequest): Promise<EvalRunReport>;
+  /**
+   * Runs a recorded agent run or pipeline again without calling a provider: each provider call
+   * gets the outcome recorded for it, once the tools the model called are run again and the files
+   * the call changed are written back. Reports where the replay went differently from the
+   * recording. Throws an error with the `REPLAY_NOT_FOUND` code when the run has no replay log.
+   */
+  replayRun(request: RuntimeReplayRequest): Promise<RuntimeReplayResponse>;
+  /** With `probe`, health-probes the configured, default and fallback providers first. */
+  getStatus(request?: { limit?: number; probe?: boolean; signal?: AbortSignal }): Promise<RuntimeStatusResponse>;
+  isReadOnly(): boolean;
   gitStatus(request?: { basePath?: string }): Promise<RuntimeGitStatusResponse>;
   gitDiff(request?: { basePath?: string; paths?: string[]; staged?: boolean; commit?: string; stat?: boolean }): Promise<RuntimeGitDiffResponse>;
   commitPrepare(request?: { basePath?: string; paths?: string[]; stageAll?: boolean; type?: string; scope?: string }): Promise<RuntimeCommitPrepareResponse>;
@@ -491,20 +1068,90 @@ export interface SharedRuntimeService {
   describeWorkflow(request: { workflowId: string; workflowDir?: string; basePath?: string }): Promise<RuntimeWorkflowDescription | undefined>;
   analyzeReview(request: { paths: string[]; focus?: ReviewFocus; maxFiles?: number; traceId?: string; sessionId?: string; basePath?: string; surface?: TraceSurface }): Promise<RuntimeReviewResponse>;
   listReviewTraces(limit?: number): Promise<TraceRecord[]>;
+  indexCode(request?: { paths?: string[]; maxFiles?: number; maxMemoryMb?: number; basePath?: string; onFile?: (entry: CodeFileIndex) => void | Promise<void>; signal?: AbortSignal }): Promise<RuntimeCodeIndexResponse>;
+  searchCodeSymbols(request?: { query?: string; id?: string; kind?: CodeSymbolKind; language?: CodeLanguage; file?: string; tests?: CodeTestFilter; concurrent?: boolean; features?: string[]; target?: string; derive?: string; attribute?: string; limit?: number; basePath?: string }): Promise<CodeSymbol[]>;
+  parseCodeSource(request: { content: string; language?: CodeLanguage; file?: string; basePath?: string }): Promise<CodeFileIndex & { warnings?: string[] }>;
+  watchCodeIndex(request?: Omit<IndexWatcherConfig, 'basePath'> & { basePath?: string }): Promise<IndexWatcher>;
+  startIndexServer(request?: { basePath?: string; watch?: boolean }): Promise<IndexServerHandle>;
+  getIndexServerStatus(request?: { basePath?: string }): Promise<IndexServerStatus | undefined>;
+  stopIndexServer(request?: { basePath?: string }): Promise<boolean>;
+  askProject(request: { question: string; provider?: string; limit?: number; traceId?: string; basePath?: string; surface?: TraceSurface }): Promise<RuntimeProjectAnswer & { traceId: string }>;
+  scaffoldImplementation(request: { symbol: string; className?: string; output?: string; dryRun?: boolean; overwrite?: boolean; basePath?: string }): Promise<RuntimeImplementationScaffold>;
+  analyzeTestImpact(request?: { base?: string; run?: boolean; basePath?: string }): Promise<RuntimeTestImpact>;
+  /** Runs the tests and lets an agent fix the failures, running them again after each attempt. */
+  testAndFix(request?: Omit<RuntimeTestFixRequest, 'basePath'> & { basePath?: string }): Promise<RuntimeTestFixReport>;
+  /** Has an agent review each symbol changed between two refs, returning its findings as line annotations. */
+  reviewAndAnnotate(request?: Omit<RuntimeReviewAnnotateRequest, 'basePath'> & { basePath?: string }): Promise<RuntimeReviewAnnotationReport>;
+  /** Changed symbols, their transitive callers, impacted tests and suggested reviewers for a diff, a file list, or the working tree. */
+  analyzeChangeImpact(request?: { diff?: string; files?: string[]; base?: string; depth?: number; basePath?: string }): Promise<RuntimeChangeImpact>;
+  reportUnsafeCode(request?: { paths?: string[]; crate?: string; basePath?: string }): Promise<UnsafeReport[]>;
+  findUnreferencedSymbols(request?: { paths?: string[]; kind?: CodeSymbolKind; limit?: number; basePath?: string }): Promise<RuntimeUnreferencedSymbols>;
+  findReferences(request: { symbol: string; basePath?: string }): Promise<RuntimeSymbolReferences>;
+  /** Callers (`incoming`) or callees (`outgoing`) of a symbol, transitively up to `depth` calls away. */
+  buildCallHierarchy(request: { symbol: string; direction?: CallHierarchyDirection; depth?: number; basePath?: string }): Promise<CallHierarchy>;
+  renderSignatures(request: { paths: string[]; basePath?: string }): Promise<SignatureView[]>;
+  /** One file's skeleton plus its declarations, whose ids `readSymbolBody` takes. */
+  renderFileSkeleton(request: { path: string; basePath?: string }): Promise<FileSkeleton>;
+  readSymbolBody(request: { symbol: string; basePath?: string }): Promise<SymbolBody>;
+  renderOutline(request: { paths: string[]; basePath?: string }): Promise<CodeOutline>;
+  /** Every indexed file with its declarations, one signature per line. */
+  renderRepoMap(request?: { basePath?: string }): Promise<RepoMap>;
+  exportCodeGraph(request: { graph: GraphKind; format: GraphFormat; paths?: string[]; basePath?: string }): Promise<RuntimeGraphExport>;
+  searchSymbols(request: { query: string; limit?: number; basePath?: string }): Promise<SymbolSearchHit[]>;
+  /** Fuzzy symbol search narrowed by kind and language, each hit with its qualified name, signature, doc line and span. */
+  describeSymbols(request: { query?: string; kind?: CodeSymbolKind; language?: CodeLanguage; limit?: number; basePath?: string }): Promise<SymbolSearchResult[]>;
+  searchText(request: { pattern: string; regex?: boolean; caseSensitive?: boolean; paths?: string[]; limit?: number; reindex?: boolean; basePath?: string; onMatches?: (matches: TextSearchMatch[]) => void }): Promise<RuntimeTextSearch>;
+  hybridSearch(request: { query: string; k?: number; namespace?: string; basePath?: string }): Promise<RuntimeHybridSearch>;
+  embedCode(request?: { backend?: EmbeddingBackend; model?: string; embedder?: Embedder; paths?: string[]; basePath?: string }): Promise<RuntimeEmbeddingResponse>;
+  runMaintenance(request?: { tasks?: MaintenanceTaskId[]; dryRun?: boolean; ifDue?: boolean; basePath?: string; surface?: TraceSurface }): Promise<RuntimeMaintenanceResponse>;
+  installHook(request?: { basePath?: string; force?: boolean }): Promise<RuntimeHookInstallResponse>;
+  uninstallHook(request?: { basePath?: string }): Promise<RuntimeHookInstallResponse>;
+  runPreCommitHook(request?: { basePath?: string; mode?: PreCommitMode; sessionId?: string }): Promise<RuntimePreCommitResponse>;
+  createDebugBundle(request?: { basePath?: string; traceId?: string; outputPath?: string; cliVersion?: string }): Promise<RuntimeDebugBundleResponse>;
+  /** Provider CLIs and their logins, config shape, writable state directories and custom grammars, each with a fix when not ok. */
+  diagnoseEnvironment(request?: { basePath?: string; env?: NodeJS.ProcessEnv; platform?: NodeJS.Platform; homeDir?: string }): Promise<RuntimeEnvironmentReport>;
   getConfig(path?: string): Promise<unknown>;
   showConfig(): Promise<Record<string, unknown>>;
+  /** Sets a value in the project's own config file and returns that file's config. */
   setConfig(path: string, value: unknown): Promise<Record<string, unknown>>;
+  /** Which config layer set `path`, from the system file up to `--config` flags, and what each layer had. */
+  explainConfig(path: string): Promise<ConfigExplanation>;
+  /** Checks each config file, then the config with every layer applied, against the workspace config schema. */
+  validateConfig(): Promise<ConfigValidation>;
   getTrace(traceId: string): Promise<TraceRecord | undefined>;
   analyzeTrace(traceId: string): Promise<RuntimeTraceAnalysis | undefined>;
   getTraceTree(traceId: string): Promise<RuntimeTraceTreeNode | undefined>;
   listTracesBySession(sessionId: string, limit?: number): Promise<TraceRecord[]>;
   listTraces(limit?: number): Promise<TraceRecord[]>;
   closeStuckTraces(maxAgeMs?: number): Promise<TraceRecord[]>;
-  storeMemory(entry: { key: string; namespace?: string; value: unknown }): Promise<MemoryEntry>;
-  getMemory(key: string, namespace?: string): Promise<MemoryEntry | undefined>;
-  searchMemory(query: string, namespace?: string): Promise<MemoryEntry[]>;
-  deleteMemory(key: string, namespace?: string): Promise<boolean>;
-  listMemory(namespace?: string): Promise<MemoryEntry[]>;
+  /**
+   * `ttlSeconds` expires the entry that long after storing it; `importance` (0-1) decays while it goes unused.
+   * With `memory.dedup` configured, or `dedup` given, a near-identical entry in the namespace is skipped,
+   * merged or superseded; the entry is compared by `embedding`, or embedded with the configured backend.
+   */
+  /** A `source` naming a file is stored with a hash of what it spans, which `checkMemorySources` compares later. */
+  storeMemory(entry: { key: string; namespace?: string; value: unknown; scope?: MemoryScope; ttlSeconds?: number; importance?: number; embedding?: number[]; source?: MemorySource; dedup?: MemoryDedupAction | 'off' }): Promise<RuntimeMemoryStoreResponse>;
+  getMemory(key: string, namespace?: string, scope?: MemoryScope): Promise<MemoryEntry | undefined>;
+  searchMemory(query: string, namespace?: string, scope?: MemoryScope): Promise<MemoryEntry[]>;
+  deleteMemory(key: string, namespace?: string, scope?: MemoryScope): Promise<boolean>;
+  listMemory(namespace?: string, scope?: MemoryScope): Promise<MemoryEntry[]>;
+  listMemoryNamespaces(): Promise<MemoryNamespaceStats[]>;
+  copyMemoryNamespace(request: { source: string; target: string; overwrite?: boolean }): Promise<MemoryNamespaceTransfer>;
+  mergeMemoryNamespaces(request: { sources: string[]; target: string }): Promise<MemoryNamespaceTransfer & { deleted: number }>;
+  /** Writes memory, all of it or the named namespaces within an optional scope, to a portable `.axm` archive. */
+  exportMemory(request: { path: string; namespaces?: string[]; scope?: MemoryScope; includeEmbeddings?: boolean; basePath?: string }): Promise<RuntimeMemoryExportResponse>;
+  /** Loads a `.axm` archive, replacing an entry only with a newer one unless `overwrite`. */
+  importMemory(request: { path: string; overwrite?: boolean; basePath?: string }): Promise<RuntimeMemoryImportResponse>;
+  /** Applies the workspace's memory retention settings from the `maintenance` config. */
+  pruneMemory(request?: { basePath?: string; dryRun?: boolean }): Promise<RuntimeMemoryPruneResponse>;
+  /** Encrypts memory stored before `memory.encryption` was set in the workspace config. */
+  encryptMemory(): Promise<{ encrypted: number }>;
+  /** Finds entries whose source file changed or is gone since they were stored, and deletes them with `invalidate`. */
+  checkMemorySources(request?: { namespace?: string; scope?: MemoryScope; basePath?: string; invalidate?: boolean }): Promise<RuntimeMemoryStaleResponse>;
+  /** Stores a subject-predicate-object fact, such as "billing-api calls ledger-api", for exact lookup. */
+  storeFact(input: FactInput & { scope?: MemoryScope }): Promise<Fact>;
+  queryFacts(query?: FactQuery & { scope?: MemoryScope }): Promise<Fact[]>;
+  deleteFact(factId: string): Promise<boolean>;
   storeSemantic(entry: { key: string; namespace?: string; content: string; tags?: string[]; metadata?: Record<string, unknown> }): Promise<SemanticEntry>;
   searchSemantic(query: string, options?: { namespace?: string; filterTags?: string[]; topK?: number; minSimilarity?: number }): Promise<SemanticSearchResult[]>;
   getSemantic(key: string, namespace?: string): Promise<SemanticEntry | undefined>;
@@ -528,6 +1175,14 @@ export interface SharedRuntimeService {
   getFeedbackStats(agentId: string): Promise<RuntimeFeedbackStats>;
   getFeedbackOverview(): Promise<RuntimeFeedbackOverview>;
   getFeedbackAdjustments(agentId: string): Promise<RuntimeFeedbackAdjustment>;
+  /** Tokens, latency and cost of provider calls, totalled by agent, provider or project. */
+  getUsageReport(options?: UsageReportOptions): Promise<UsageReport>;
+  /** Entries and hits of the provider response cache, and the tokens the hits saved. */
+  getProviderCacheStats(options?: { basePath?: string }): Promise<ProviderCacheStats>;
+  /** Deletes every cached provider response; returns how many there were. */
+  clearProviderCache(): Promise<number>;
+  /** The built-in providers and those the workspace config adds, as executors or as the default. */
+  listProviders(basePath?: string): Promise<string[]>;
   listAbilities(options?: { category?: string; tags?: string[] }): Promise<RuntimeAbility[]>;
   injectAbilities(request: {
     task: string;
@@ -557,11 +1212,58 @@ export interface SharedRuntimeService {
   }): Promise<RuntimeGuardCheckResponse>;
   registerAgent(entry: { agentId: string; name: string; capabilities?: string[]; metadata?: Record<string, unknown> }): Promise<AgentEntry>;
   getAgent(agentId: string): Promise<AgentEntry | undefined>;
+  /**
+   * An agent's profile with the agents it `extends` and its `abilityPacks` layered in, as its runs
+   * use it; undefined for an unknown agent. Throws an error with the `AGENT_PROFILE_INVALID` code
+   * when a base or pack cannot be resolved.
+   */
+  resolveAgentProfile(agentId: string, basePath?: string): Promise<ResolvedAgentProfile | undefined>;
+  /**
+   * Agent profiles tailored to the indexed workspace's languages and frameworks, written to
+   * `.automatosx/agents/` unless `dryRun` is set. Needs the symbol index.
+   */
+  suggestAgents(request?: { basePath?: string; dryRun?: boolean }): Promise<AgentSuggestResponse>;
   listAgents(): Promise<AgentEntry[]>;
   removeAgent(agentId: string): Promise<boolean>;
   listAgentCapabilities(): Promise<string[]>;
-  createSession(entry: { sessionId?: string; task: string; initiator: string; workspace?: string; metadata?: Record<string, unknown> }): Promise<SessionEntry>;
+  /** Creates a session; a `scope` or `memoryNamespace` is kept in its thread for the runs of the session. */
+  createSession(entry: { sessionId?: string; task: string; initiator: string; workspace?: string; metadata?: Record<string, unknown>; scope?: string; memoryNamespace?: string; basePath?: string }): Promise<SessionEntry>;
   getSession(sessionId: string): Promise<SessionEntry | undefined>;
+  /** The conversation of a session and the agent, scope and memory namespace its runs use. */
+  getSessionThread(sessionId: string, basePath?: string): Promise<SessionThread>;
+  /**
+   * Opens a session again, making it active if it was completed or failed, and runs the next turn
+   * when a `task` is given: the session's agent gets the conversation so far, within the session's
+   * scope and with hits from its memory namespace. Fails with `SESSION_NOT_FOUND` for an unknown
+   * session, and with `SESSION_AGENT_MISSING` for a task in a session no agent has run in yet.
+   */
+  resumeSession(request: RuntimeSessionResumeRequest): Promise<RuntimeSessionResumeResponse>;
+  /**
+   * Forks a session into a new one that keeps its first `turns` turns, its agent, scope and memory
+   * namespace, so another approach can be tried; the original session and its thread are left as they are.
+   */
+  branchSession(request: RuntimeSessionBranchRequest): Promise<RuntimeSessionBranchResponse>;
+  /**
+   * Cuts a session's thread back to its first `turns` turns, so its next run no longer sees the
+   * ones after, as when a turn is undone. Fails with `SESSION_NOT_FOUND` for an unknown session.
+   */
+  rewindSession(sessionId: string, turns: number, basePath?: string): Promise<SessionThread>;
+  /**
+   * A transcript of a session: each turn with its reply, the tool calls and file changes of its
+   * run, and the tokens, cost and time it took. Fails with `SESSION_NOT_FOUND` for an unknown session.
+   */
+  exportSession(request: RuntimeSessionExportRequest): Promise<RuntimeSessionExport>;
+  /**
+   * Adds tasks to a session's scratchpad, where concurrent runs of the session claim them and post
+   * their results. Fails with `SESSION_NOT_FOUND` for an unknown session, as do the other scratchpad calls.
+   */
+  addScratchpadTasks(sessionId: string, tasks: ScratchpadTaskInput[]): Promise<ScratchpadTask[]>;
+  getScratchpad(sessionId: string): Promise<ScratchpadTask[]>;
+  /** Claims the named task, or the next free one; undefined when none is free. Fails with `SCRATCHPAD_CONFLICT` when it is taken. */
+  claimScratchpadTask(sessionId: string, claim: ScratchpadClaim): Promise<ScratchpadTask | undefined>;
+  postScratchpadResult(sessionId: string, post: ScratchpadPost): Promise<ScratchpadTask>;
+  /** Opens a claimed task again; only its holder can release it when a `claimant` is given. */
+  releaseScratchpadTask(sessionId: string, taskId: string, claimant?: string): Promise<ScratchpadTask>;
   listSessions(): Promise<SessionEntry[]>;
   joinSession(entry: { sessionId: string; agentId: string; role?: SessionParticipantRole }): Promise<SessionEntry>;
   leaveSession(sessionId: string, agentId: string): Promise<SessionEntry>;
@@ -578,11 +1280,13 @@ export interface SharedRuntimeConfig {
   maxConcurrentDiscussions?: number;
   maxProvidersPerDiscussion?: number;
   maxDiscussionRounds?: number;
+  readOnly?: boolean;
 }
 
 const DEFAULT_DISCUSSION_CONCURRENCY = 2;
 const DEFAULT_DISCUSSION_PROVIDER_BUDGET = 3;
 const DEFAULT_DISCUSSION_ROUNDS = 3;
+const DEFAULT_CONTEXT_MEMORY_HITS = 5;
 const BUILTIN_GUARD_POLICIES: StepGuardPolicy[] = [
   {
     policyId: 'step-validation',
@@ -665,6 +1369,13 @@ const BUILTIN_ABILITIES: RuntimeAbility[] = [
     tags: ['review', 'correctness', 'security', 'maintainability'],
     content: 'Prioritize concrete findings with file references, severity ordering, and missing-test risks. Prefer actionable defects over narrative summaries.',
   },
+  {
+    abilityId: 'concurrency-review',
+    name: 'Concurrency Review',
+    category: 'concurrency',
+    tags: ['concurrency', 'async', 'locking', 'race-conditions'],
+    content: 'Start from the async functions and the functions that use shared-state primitives (`ax code symbols --concurrent`). Check lock ordering, locks held across await points, unbounded channels, and spawned tasks whose errors are dropped.',
+  },
   {
     abilityId: 'git-hygiene',
     name: 'Git Hygiene',
@@ -691,8 +1402,50 @@ const BUILTIN_ABILITIES: RuntimeAbility[] = [
 export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): SharedRuntimeService {
   const basePath = config.basePath ?? process.cwd();
   const traceStore = config.traceStore ?? createTraceStore({ basePath });
-  const stateStore = config.stateStore ?? createStateStore({ basePath });
-  const providerBridge = createProviderBridge({ basePath });
+  const stateStore = config.stateStore ?? createStateStore({ basePath, encryption: memoryCipherFor(basePath) });
+  const readOnly = config.readOnly ?? isReadOnlyEnv();
+  // Runs with a budget or a cost approval threshold by trace id, and the parent of each agent run in
+  // flight, so a provider call is charged to those of its run and of the runs above it.
+  const budgetMeters = new Map<string, BudgetMeter>();
+  const costGates = new Map<string, CostGate>();
+  const runParents = new Map<string, string>();
+  const recordedUsage = createUsageTracker({ stateStore });
+  const chargedRuns = (traceId: string | undefined) => {
+    const chain: string[] = [];
+    for (let current = traceId; current !== undefined; current = runParents.get(current)) {
+      chain.push(current);
+    }
+    return chain;
+  };
+  const usageTracker: typeof recordedUsage = {
+    ...recordedUsage,
+    async record(entry) {
+      const chain = chargedRuns(entry.traceId);
+      for (const traceId of chain) {
+        budgetMeters.get(traceId)?.charge(entry);
+      }
+      const recorded = await recordedUsage.record(entry);
+      // The bridge waits for this, so a run over its approval threshold pauses until it is answered.
+      for (const traceId of chain) {
+        await costGates.get(traceId)?.charge(entry.costUsd ?? 0);
+      }
+      return recorded;
+    },
+  };
+  // A replayed call spends no tokens, so it is not recorded; it still counts toward its runs' budgets.
+  const runRecorder = createRunRecorder({
+    async charge(entry) {
+      const chain = chargedRuns(entry.traceId);
+      for (const traceId of chain) {
+        budgetMeters.get(traceId)?.charge(entry);
+      }
+      for (const traceId of chain) {
+        await costGates.get(traceId)?.charge(entry.costUsd ?? 0);
+      }
+    },
+  });
+  const responseCache = createProviderResponseCache({ stateStore });
+  const providerBridge = createProviderBridge({ basePath, readOnly, usageTracker, responseCache, recorder: runRecorder });
   const discussionCoordinator = createDiscussionCoordinator({
     maxConcurrentDiscussions: config.maxConcurrentDiscussions ?? DEFAULT_DISCUSSION_CONCURRENCY,
     maxProvidersPerDiscussion: config.maxProvidersPerDiscussion ?? DEFAULT_DISCUSSION_PROVIDER_BUDGET,
@@ -710,7 +1463,7 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
     if (cached !== undefined) {
       return cached;
     }
-    const created = createProviderBridge({ basePath: resolvedBasePath });
+    const created = createProviderBridge({ basePath: resolvedBasePath, readOnly, usageTracker, responseCache, recorder: runRecorder });
     providerBridgeCache.set(resolvedBasePath, created);
     return created;
   };
@@ -731,12 +1484,250 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
     return created;
   };
 
+  const scaffoldImplementation: SharedRuntimeService['scaffoldImplementation'] = async (request) => {
+    const scaffoldBasePath = request.basePath ?? basePath;
+    const scaffold = await generateImplementationScaffold({
+      basePath: scaffoldBasePath,
+      symbol: request.symbol,
+      className: request.className,
+      output: request.output,
+    });
+    if (request.dryRun === true) {
+      return { ...scaffold, written: false };
+    }
+
+    assertWritable(readOnly, 'Implementation scaffolding');
+    const filePath = join(scaffoldBasePath, scaffold.file);
+    await mkdir(dirname(filePath), { recursive: true });
+    try {
+      await writeFile(filePath, scaffold.content, { encoding: 'utf8', flag: request.overwrite === true ? 'w' : 'wx' });
+    } catch (error) {
+      if ((error as NodeJS.ErrnoException).code === 'EEXIST') {
+        throw new Error(`File already exists: ${scaffold.file}`);
+      }
+      throw error;
+    }
+    return { ...scaffold, written: true };
+  };
+
+  const analyzeWorkspaceTestImpact: SharedRuntimeService['analyzeTestImpact'] = async (request) => {
+    const impactBasePath = request?.basePath ?? basePath;
+    const impact = await analyzeTestImpact({ basePath: impactBasePath, base: request?.base });
+    return request?.run === true ? { ...impact, run: await runImpactedTests(impactBasePath, impact) } : impact;
+  };
+
+  const runMaintenanceTask = async (
+    task: MaintenanceTaskId,
+    options: { dryRun: boolean; basePath: string; now: Date },
+  ): Promise<MaintenanceTaskResult> => {
+    const startedAt = Date.now();
+    const config = await readMaintenanceConfig(options.basePath);
+    const done = (summary: string, details: Record<string, unknown>): MaintenanceTaskResult => ({
+      task,
+      success: true,
+      summary,
+      details,
+      durationMs: Date.now() - startedAt,
+    });
+    const verb = (past: string, planned: string) => (options.dryRun ? planned : past);
+
+    try {
+      switch (task) {
+        case 'index': {
+          if (await readCodeIndex(options.basePath) === undefined) {
+            return done('No code index to refresh.', { refreshed: false });
+          }
+          if (options.dryRun) {
+            return done('Would rebuild the code index.', { refreshed: false });
+          }
+          const result = await requestIndexServer<RuntimeCodeIndexResponse>(options.basePath, 'index', {})
+            ?? await buildCodeIndex({ basePath: options.basePath });
+          return done(`Re-indexed ${result.filesIndexed} file(s) with ${result.symbolCount} symbol(s).`, { refreshed: true, ...result });
+        }
+        case 'memory': {
+          const details = await pruneMemoryEntries(stateStore, config, options);
+          const count = details.evicted.length;
+          return done(`${verb('Deleted', 'Would delete')} ${count} memory entr${count === 1 ? 'y' : 'ies'}${count > 0 ? ` (${summarizeMemoryEvictions(details.evicted)})` : ''}.`, details);
+        }
+        case 'logs': {
+          const details = await rotateLogs(options.basePath, config, options);
+          const rotated = details.rotated as string[];
+          const removed = details.removed as string[];
+          return done(`${verb('Rotated', 'Would rotate')} ${rotated.length} log(s) and ${verb('removed', 'remove')} ${removed.length} old run file(s).`, details);
+        }
+        case 'providers': {
+          const providers = await checkProviderHealth(options.basePath, resolveProviderBridge(options.basePath));
+          const healthy = providers.filter((provider) => provider.status === 'ok').map((provider) => provider.provider);
+          return done(`${healthy.length} of ${providers.length} provider(s) healthy${healthy.length > 0 ? ` (${healthy.join(', ')})` : ''}.`, { providers });
+        }
+        case 'caches': {
+          const details = await pruneCaches(options.basePath, config, options);
+          const removed = details.removed as string[];
+          return done(`${verb('Pruned', 'Would prune')} ${removed.length} cached artifact(s) older than ${config.cacheMaxAgeDays} day(s).`, details);
+        }
+      }
+    } catch (error) {
+      const message = error instanceof Error ? error.message : String(error);
+      return { task, success: false, summary: `Failed: ${message}`, error: message, durationMs: Date.now() - startedAt };
+    }
+  };
+
+  const maintenanceTools = Object.fromEntries(MAINTENANCE_TASKS.map((task) => [
+    `maintain.${task}`,
+    (workflowBasePath: string) => async (args: Record<string, unknown>) => {
+      const result = await runMaintenanceTask(task, { dryRun: args.dryRun === true, basePath: workflowBasePath, now: new Date() });
+      if (!result.success) {
+        throw new Error(`Maintenance task ${task} failed: ${result.error}`);
+      }
+      return result;
+    },
+  ])) as Record<string, (workflowBasePath: string) => (args: Record<string, unknown>) => Promise<MaintenanceTaskResult>>;
+
+  // An agent's metadata with its bases layered in, so a provider a base sets applies; the agent's own
+  // when its profile does not resolve, as its run then fails anyway.
+  const layeredMetadata = async (agentId: string, root: string): Promise<Record<string, unknown> | undefined> => {
+    const agent = await stateStore.getAgent(agentId);
+    return agent === undefined
+      ? undefined
+      : (await resolveAgentProfile(agent, (baseId) => stateStore.getAgent(baseId), root).catch(() => ({ agent }))).agent.metadata;
+  };
+
+  // The checkpoint is saved before the first stage and after each one, so a run that dies (a crash,
+  // a reboot, Ctrl+C) can be resumed from the stages it had completed.
+  const runCheckpointedPipeline = async (
+    pipeline: PipelineDefinition,
+    run: {
+      checkpoint: PipelineCheckpoint;
+      basePath: string;
+      surface?: TraceSurface;
+      signal?: AbortSignal;
+      onStageStart?: (stage: PipelineStage) => void;
+      onStageEnd?: (result: PipelineStageResult) => void;
+      onText?: (text: string, stageId: string) => void;
+    },
+    runAgent: PipelineAgentRunner,
+  ): Promise<RuntimePipelineResponse> => {
+    const { checkpoint } = run;
+    const traceId = checkpoint.runId;
+    const startedAtMs = Date.now();
+    const resumed = checkpoint.stages.length > 0;
+    const traceInput = { file: checkpoint.file, pipeline: pipeline.name, input: checkpoint.input };
+    const metadata = { sessionId: checkpoint.sessionId, command: 'pipeline.run', ...(resumed ? { resumedAt: new Date().toISOString() } : {}) };
+    const warnings: string[] = [];
+    const finished = new Map(checkpoint.stages.filter((stage) => stage.status === 'succeeded').map((stage) => [stage.id, stage] as const));
+    let saves = Promise.resolve();
+    const save = (status: PipelineCheckpoint['status']) => {
+      const snapshot = { ...checkpoint, status, updatedAt: new Date().toISOString(), stages: [...finished.values()] };
+      saves = saves.then(() => writePipelineCheckpoint(run.basePath, snapshot)).catch((error: unknown) => {
+        warnings.push(`The checkpoint could not be saved: ${error instanceof Error ? error.message : String(error)}`);
+      });
+      return saves;
+    };
+
+    await traceStore.upsertTrace({
+      traceId,
+      workflowId: 'pipeline.run',
+      surface: run.surface ?? 'cli',
+      status: 'running',
+      startedAt: checkpoint.startedAt,
+      input: traceInput,
+      stepResults: [],
+      metadata,
+    });
+    await save('running');
+
+    const concurrency = readConcurrencySettings(await readWorkspaceConfig(run.basePath));
+    // As with parallel tasks, a stage counts against the limit of the provider it runs on.
+    const providers = new Map(await Promise.all(pipeline.stages.map(async (stage) => {
+      const provider = stage.provider ?? checkpoint.provider;
+      const agentMetadata = provider === undefined ? await layeredMetadata(stage.agent, run.basePath) : undefined;
+      return [stage.id, provider ?? (isRecord(agentMetadata) ? asOptionalString(agentMetadata.provider) : undefined) ?? 'claude'] as const;
+    })));
+    // Stage runs are children of the pipeline's trace, so their provider calls are charged to its budget.
+    const budget = combineBudgets(pipeline.budget, checkpoint.budget);
+    const meter = budget === undefined ? undefined : createBudgetMeter(budget, run.signal);
+    if (meter !== undefined) {
+      budgetMeters.set(traceId, meter);
+    }
+    // The stages' provider calls are logged for `replayRun`; a resumed run adds to its log.
+    const recording = await runRecorder.start(run.basePath, {
+      traceId,
+      command: 'pipeline.run',
+      request: { file: checkpoint.file, source: checkpoint.source, input: checkpoint.input, provider: checkpoint.provider, budget: checkpoint.budget },
+    }, { resume: resumed });
+    if (recording !== undefined) {
+      warnings.push(recording);
+    }
+    const stagesRun = runPipelineStages(pipeline, {
+      basePath: run.basePath,
+      completed: [...finished.values()],
+      input: checkpoint.input,
+      artifactDir: pipelineRunDir(traceId),
+      limits: { maxConcurrent: concurrency.maxConcurrent, poolLimits: concurrency.perProvider },
+      poolOf: (stage) => providers.get(stage.id),
+      provider: checkpoint.provider,
+      traceId,
+      sessionId: checkpoint.sessionId,
+      surface: run.surface ?? 'cli',
+      signal: meter?.signal ?? run.signal,
+      onStageStart: run.onStageStart,
+      onStageEnd: (result) => {
+        finished.set(result.id, result);
+        void save('running');
+        run.onStageEnd?.(result);
+      },
+      onText: run.onText,
+    }, runAgent);
+    const stageReport = await stagesRun.finally(() => {
+      meter?.stop();
+      budgetMeters.delete(traceId);
+      runRecorder.finish(traceId);
+    });
+    const budgetReport = meter?.report();
+    const exceeded = budgetReport?.exceeded;
+    const report = exceeded === undefined
+      ? stageReport
+      : { ...stageReport, success: false, error: { code: BUDGET_EXCEEDED_CODE, message: describeExceeded(exceeded) } };
+    await save(report.success ? 'completed' : 'failed');
+
+    await traceStore.upsertTrace({
+      traceId,
+      workflowId: 'pipeline.run',
+      surface: run.surface ?? 'cli',
+      status: report.success ? 'completed' : 'failed',
+      startedAt: checkpoint.startedAt,
+      completedAt: new Date().toISOString(),
+      input: traceInput,
+      stepResults: report.stages.map((stage) => ({
+        stepId: stage.id,
+        success: stage.status !== 'failed',
+        durationMs: stage.durationMs,
+        retryCount: stage.runs.reduce((count, stageRun) => count + Math.max(0, stageRun.attempts - 1), 0),
+        error: stage.status === 'failed' ? stage.runs.at(-1)?.error?.message : undefined,
+      })),
+      output: report,
+      error: report.error,
+      metadata: { ...metadata, budget: budgetReport },
+    });
+
+    return { traceId, ...report, totalDurationMs: Date.now() - startedAtMs, warnings, budget: budgetReport };
+  };
+
   return {
+    async listProviderModels(request = {}) {
+      const runtimeProviderBridge = resolveProviderBridge(request.basePath);
+      const provider = request.provider ?? (await runtimeProviderBridge.selectProvider('claude')).provider;
+      return runtimeProviderBridge.listModels(provider);
+    },
+
     async callProvider(request) {
       const runtimeProviderBridge = resolveProviderBridge(request.basePath);
       const traceId = request.traceId ?? randomUUID();
       const startedAt = new Date().toISOString();
-      const resolvedProvider = request.provider ?? 'claude';
+      const routing = request.provider === undefined
+        ? await runtimeProviderBridge.selectProvider('claude')
+        : { provider: request.provider, warnings: [] };
+      const resolvedProvider = routing.provider;
       await traceStore.upsertTrace({
         traceId,
         workflowId: 'call',
@@ -760,18 +1751,32 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
         },
       });
 
-      const bridgeResult = await runtimeProviderBridge.executePrompt({
+      const executionRequest = {
         provider: resolvedProvider,
         prompt: request.prompt,
         systemPrompt: request.systemPrompt,
         model: request.model ?? 'v14-direct-call',
         maxTokens: request.maxTokens,
         temperature: request.temperature,
-      });
+        stopSequences: request.stopSequences,
+        traceId,
+        noCache: request.noCache,
+        onText: request.onText,
+        responseFormat: request.responseFormat,
+      };
+      const bridgeResult = request.outputSchema !== undefined
+        ? await runtimeProviderBridge.executeStructured(executionRequest, request.outputSchema, request.consensus)
+        : request.consensus === undefined
+          ? await runtimeProviderBridge.executePrompt(executionRequest)
+          : await runtimeProviderBridge.executeConsensus(executionRequest, request.consensus);
       const completedAt = new Date().toISOString();
 
       if (bridgeResult.type === 'response' || bridgeResult.type === 'failure') {
-        const warnings = bridgeResult.type === 'failure' ? [bridgeResult.response.error ?? 'Provider execution failed.'] : [];
+        const warnings = [
+          ...routing.warnings,
+          ...(bridgeResult.response.warnings ?? []),
+          ...(bridgeResult.type === 'failure' ? [bridgeResult.response.error ?? 'Provider execution failed.'] : []),
+        ];
         await traceStore.upsertTrace({
           traceId,
           workflowId: 'call',
@@ -811,6 +1816,10 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
             provider: bridgeResult.response.provider,
             model: bridgeResult.response.model,
             command: 'call',
+            cache: bridgeResult.response.cache,
+            providerFailovers: bridgeResult.response.failovers,
+            consensus: bridgeResult.response.consensus,
+            structuredOutput: bridgeResult.response.structured,
           },
         });
 
@@ -824,6 +1833,10 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
           executionMode: 'subprocess',
           warnings,
           usage: bridgeResult.response.usage,
+          cache: bridgeResult.response.cache,
+          failovers: bridgeResult.response.failovers,
+          consensus: bridgeResult.response.consensus,
+          structured: bridgeResult.response.structured,
           error: bridgeResult.response.success ? undefined : {
             code: bridgeResult.response.errorCode,
             message: bridgeResult.response.error,
@@ -840,7 +1853,7 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
         outputTokens: tokenize(content),
         totalTokens: tokenize(request.prompt) + tokenize(content),
       };
-      const warnings = [`No provider executor configured for "${resolvedProvider}". Returned simulated output.`];
+      const warnings = [...routing.warnings, `No provider executor configured for "${resolvedProvider}". Returned simulated output.`];
       await traceStore.upsertTrace({
         traceId,
         workflowId: 'call',
@@ -896,7 +1909,7 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
       const runtimeDiscussionCoordinator = resolveDiscussionCoordinator(request.basePath);
       const workflowDir = resolveWorkflowDir(request.workflowDir, request.basePath, basePath);
       const loader = createWorkflowLoader({ workflowsDir: workflowDir });
-      const workflow = await loader.load(request.workflowId);
+      const workflow = await loader.load(request.workflowId) ?? getBuiltinWorkflow(request.workflowId);
 
       if (workflow === undefined) {
         const traceId = request.traceId ?? randomUUID();
@@ -925,6 +1938,36 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
           workflowDir,
         };
       }
+      // A workflow that claims a task of its session's scratchpad holds it while it runs, then posts how it ended.
+      if (request.claim !== undefined) {
+        const traceId = request.traceId ?? randomUUID();
+        let claimed: ScratchpadTask;
+        try {
+          claimed = await claimTaskForRun(stateStore, request.sessionId, request.claim, { claimant: request.workflowId, traceId });
+        } catch (error) {
+          const failed: TraceRecord = {
+            traceId,
+            workflowId: request.workflowId,
+            surface: request.surface ?? 'cli',
+            status: 'failed',
+            startedAt: new Date().toISOString(),
+            completedAt: new Date().toISOString(),
+            input: request.input,
+            stepResults: [],
+            error: {
+              code: (error as { code?: string }).code ?? SCRATCHPAD_CONFLICT_CODE,
+              message: error instanceof Error ? error.message : String(error),
+            },
+            metadata: { sessionId: request.sessionId },
+          };
+          await traceStore.upsertTrace(failed);
+          return { traceId, workflowId: request.workflowId, success: false, stepResults: [], error: failed.error, totalDurationMs: 0, workflowDir };
+        }
+        const input = request.input?.task === undefined ? { ...request.input, task: claimed.title } : request.input;
+        const response = await this.runWorkflow({ ...request, traceId, claim: undefined, input });
+        const posted = await postRunResult(stateStore, claimed, response.success, response.success ? describeWorkflowOutput(response.output) : response.error?.message ?? 'The workflow failed.');
+        return { ...response, claimed: posted.task, ...(posted.warnings.length > 0 ? { warnings: posted.warnings } : {}) };
+      }
 
       const traceId = request.traceId ?? randomUUID();
       const startedAt = new Date().toISOString();
@@ -944,19 +1987,119 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
         },
       });
 
+      const providerFailovers: RuntimeProviderFailover[] = [];
+      const concurrency = readConcurrencySettings(await readWorkspaceConfig(request.basePath ?? basePath));
+      // The steps' provider calls are charged to the run's budget; the meter's signal stops the ones in flight.
+      const meter = request.budget === undefined ? undefined : createBudgetMeter(request.budget);
+      if (meter !== undefined) {
+        budgetMeters.set(traceId, meter);
+      }
       const runner = createWorkflowRunner({
         executionId: traceId,
         agentId: request.surface ?? 'cli',
+        // Steps that declare `dependencies` run as a graph, bounded overall and per provider.
+        maxConcurrentSteps: concurrency.maxConcurrent,
+        concurrencyPool: (step) => workflowStepProvider(step, request.provider),
+        poolLimits: concurrency.perProvider,
+        onStepStart: request.onStepStart === undefined ? undefined : (step) => request.onStepStart?.(step.stepId),
+        onStepComplete: request.onStepComplete === undefined ? undefined : (step, stepResult) => request.onStepComplete?.({
+          stepId: step.stepId,
+          success: stepResult.success,
+          durationMs: stepResult.durationMs,
+          error: stepResult.error?.message,
+        }),
         stepExecutor: createRealStepExecutor({
-          promptExecutor: createPromptExecutor(runtimeProviderBridge, request.provider, request.model),
-          toolExecutor: createToolExecutor(),
+          promptExecutor: createPromptExecutor(runtimeProviderBridge, {
+            provider: request.provider,
+            model: request.model,
+            traceId,
+            noCache: request.noCache,
+            signal: meter?.signal,
+            onText: request.onText,
+            onFailover: (failover, stepId) => {
+              const event = { ...failover, ...(stepId !== undefined ? { stepId } : {}) };
+              providerFailovers.push(event);
+              request.onProviderFailover?.(event);
+            },
+          }),
+          toolExecutor: createToolExecutor(readOnly, {
+            // Lets a workflow stage scaffold stubs from an indexed interface before an agent fills them in.
+            'scaffold.impl': (args) => scaffoldImplementation({
+              symbol: String(args.symbol ?? ''),
+              className: typeof args.className === 'string' ? args.className : undefined,
+              output: typeof args.output === 'string' ? args.output : undefined,
+              dryRun: args.dryRun === true,
+              overwrite: args.overwrite === true,
+              basePath: request.basePath ?? basePath,
+            }),
+            // Puts the API surface of many files into a prompt: signatures only, bodies elided.
+            'code.signatures': (args) => renderSignatures({
+              paths: Array.isArray(args.paths) ? args.paths.map(String) : [],
+              basePath: request.basePath ?? basePath,
+            }),
+            // A documentation stage starts from the symbol tree as Markdown.
+            'code.outline': (args) => renderOutline({
+              paths: Array.isArray(args.paths) ? args.paths.map(String) : [],
+              basePath: request.basePath ?? basePath,
+            }),
+            // Lets an architecture review draw the import or call graph it is reasoning about.
+            'code.graph': (args) => exportCodeGraph({
+              graph: args.graph === 'calls' ? 'calls' : 'modules',
+              format: args.format === 'dot' ? 'dot' : 'mermaid',
+              paths: Array.isArray(args.paths) ? args.paths.map(String) : undefined,
+              basePath: request.basePath ?? basePath,
+            }),
+            // Lets an agent find a symbol it only half remembers: `DatProc`, `kind:trait Handler`.
+            'code.search': (args) => searchSymbolIndex({
+              query: String(args.query ?? ''),
+              limit: typeof args.limit === 'number' ? args.limit : undefined,
+              basePath: request.basePath ?? basePath,
+            }),
+            // Greps the workspace through the trigram index instead of reading every file.
+            'code.grep': (args) => searchText({
+              pattern: String(args.pattern ?? ''),
+              regex: args.regex === true,
+              caseSensitive: args.caseSensitive === true,
+              paths: Array.isArray(args.paths) ? args.paths.map(String) : undefined,
+              limit: typeof args.limit === 'number' ? args.limit : undefined,
+              basePath: request.basePath ?? basePath,
+            }),
+            // Gives a cleanup stage its starting list of unreferenced symbols.
+            'code.unreferenced': (args) => findUnreferencedSymbols({
+              paths: Array.isArray(args.paths) ? args.paths.map(String) : undefined,
+              kind: typeof args.kind === 'string' ? args.kind as CodeSymbolKind : undefined,
+              limit: typeof args.limit === 'number' ? args.limit : undefined,
+              basePath: request.basePath ?? basePath,
+            }),
+            // Validation stage: runs only the tests the working-tree changes can affect and fails the step when they fail.
+            'test.impacted': async (args) => {
+              const impact = await analyzeWorkspaceTestImpact({
+                base: typeof args.base === 'string' ? args.base : undefined,
+                run: args.run !== false,
+                basePath: request.basePath ?? basePath,
+              });
+              if (impact.run !== undefined && !impact.run.passed) {
+                throw new Error(`Impacted tests failed (exit ${impact.run.exitCode}): ${impact.impactedTests.map((test) => test.file).join(', ')}`);
+              }
+              return impact;
+            },
+            ...Object.fromEntries(Object.entries(maintenanceTools).map(([toolName, bind]) => [toolName, bind(request.basePath ?? basePath)])),
+          }),
           discussionExecutor: createDiscussionExecutor(traceId, request.provider, runtimeDiscussionCoordinator),
           defaultProvider: request.provider ?? 'claude',
           defaultModel: request.model ?? 'v14-shared-runtime',
         }),
       });
 
-      const result = await runner.run(workflow, request.input ?? {});
+      const run = await runner.run(workflow, request.input ?? {}).finally(() => {
+        meter?.stop();
+        budgetMeters.delete(traceId);
+      });
+      const budgetReport = meter?.report();
+      const exceeded = budgetReport?.exceeded;
+      const result = exceeded === undefined
+        ? run
+        : { ...run, success: false, error: { code: BUDGET_EXCEEDED_CODE, message: describeExceeded(exceeded) } };
       const completedAt = new Date().toISOString();
       await traceStore.upsertTrace({
         traceId,
@@ -981,6 +2124,8 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
           model: request.model,
           totalDurationMs: result.totalDurationMs,
           sessionId: request.sessionId,
+          ...(providerFailovers.length > 0 ? { providerFailovers } : {}),
+          ...(budgetReport !== undefined ? { budget: budgetReport } : {}),
         },
       });
 
@@ -993,6 +2138,7 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
         error: result.error,
         totalDurationMs: result.totalDurationMs,
         workflowDir,
+        ...(budgetReport !== undefined ? { budget: budgetReport } : {}),
       };
     },
 
@@ -1249,14 +2395,10 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
     async runAgent(request) {
       const runtimeProviderBridge = resolveProviderBridge(request.basePath);
       const traceId = request.traceId ?? randomUUID();
-      const agent = await stateStore.getAgent(request.agentId);
+      const registered = await stateStore.getAgent(request.agentId);
       const startedAt = new Date().toISOString();
 
-      if (agent === undefined) {
-        const error = {
-          code: 'AGENT_NOT_FOUND',
-          message: `Agent "${request.agentId}" is not registered.`,
-        };
+      const rejectRun = async (error: { code: string; message: string }): Promise<RuntimeAgentRunResponse> => {
         await traceStore.upsertTrace({
           traceId,
           workflowId: 'agent.run',
@@ -1268,6 +2410,8 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
             agentId: request.agentId,
             task: request.task,
             input: request.input,
+            scope: request.scope,
+            files: request.files,
           },
           stepResults: [],
           error,
@@ -1291,14 +2435,158 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
           warnings: [],
           error,
         };
-      }
+      };
 
+      if (registered === undefined) {
+        return rejectRun({ code: 'AGENT_NOT_FOUND', message: `Agent "${request.agentId}" is not registered.` });
+      }
+      // A run that claims a task of its session's scratchpad holds it while it runs, then posts how it ended.
+      if (request.claim !== undefined) {
+        let claimed: ScratchpadTask;
+        try {
+          claimed = await claimTaskForRun(stateStore, request.sessionId, request.claim, { claimant: request.agentId, traceId });
+        } catch (error) {
+          return rejectRun({
+            code: (error as { code?: string }).code ?? SCRATCHPAD_CONFLICT_CODE,
+            message: error instanceof Error ? error.message : String(error),
+          });
+        }
+        const response = await this.runAgent({ ...request, traceId, claim: undefined, task: request.task ?? claimed.title });
+        const posted = await postRunResult(stateStore, claimed, response.success, response.success ? response.content : response.error?.message ?? 'The run failed.');
+        return { ...response, claimed: posted.task, warnings: [...response.warnings, ...posted.warnings] };
+      }
+      // The profile runs with its `extends` bases and `abilityPacks` layered in.
+      let profile: ResolvedAgentProfile;
+      try {
+        profile = await resolveAgentProfile(registered, (agentId) => stateStore.getAgent(agentId), request.basePath ?? basePath);
+      } catch (error) {
+        return rejectRun({
+          code: (error as { code?: string }).code ?? AGENT_PROFILE_INVALID_CODE,
+          message: error instanceof Error ? error.message : String(error),
+        });
+      }
+      const { agent } = profile;
+      const layers = profileLayers(profile);
       const metadata = isRecord(agent.metadata) ? agent.metadata : {};
-      const resolvedProvider = request.provider ?? asOptionalString(metadata.provider) ?? 'claude';
+      // A top-level run in a session continues its thread: the conversation so far goes with the
+      // task, and the session's scope and memory namespace apply unless the request sets a scope.
+      const thread = request.sessionId === undefined || request.parentTraceId !== undefined
+        ? undefined
+        : await readSessionThread(request.basePath ?? basePath, request.sessionId);
+      const scope = request.scope ?? thread?.scope;
+      let workspace: AgentWorkspace;
+      try {
+        const permissions = readAgentPermissions(metadata, agent.agentId);
+        workspace = await resolveAgentWorkspace(request.basePath ?? basePath, { scope, files: request.files, permissions });
+      } catch (error) {
+        return rejectRun({
+          code: (error as { code?: string }).code ?? AGENT_SCOPE_ERROR_CODE,
+          message: error instanceof Error ? error.message : String(error),
+        });
+      }
+
+      let budget: RunBudget | undefined;
+      try {
+        budget = combineBudgets(
+          metadata.budget === undefined ? undefined : parseRunBudget(metadata.budget, `The budget of agent "${agent.agentId}"`),
+          request.budget,
+        );
+      } catch (error) {
+        return rejectRun({
+          code: (error as { code?: string }).code ?? BUDGET_INVALID_CODE,
+          message: error instanceof Error ? error.message : String(error),
+        });
+      }
+      let approvals: ApprovalGates | undefined;
+      let approvalPolicy: ApprovalPolicy | undefined;
+      try {
+        approvals = readApprovalGates(metadata, agent.agentId);
+        approvalPolicy = approvals === undefined ? undefined : await loadApprovalPolicy(request.basePath ?? basePath, request.approvalPolicy);
+      } catch (error) {
+        return rejectRun({
+          code: (error as { code?: string }).code ?? APPROVALS_INVALID_CODE,
+          message: error instanceof Error ? error.message : String(error),
+        });
+      }
+      const pinnedProvider = request.provider ?? asOptionalString(metadata.provider);
+      // An agent profile's `routing` overrides the workspace policy for the agent's tasks.
+      const routing: ProviderQuotaSelection = pinnedProvider === undefined
+        ? await runtimeProviderBridge.selectProvider('claude', parseRoutingOptions(metadata.routing))
+        : { provider: pinnedProvider, warnings: [] };
+      const resolvedProvider = routing.provider;
       const resolvedModel = request.model ?? asOptionalString(metadata.model) ?? 'v14-agent-run';
       const task = resolveAgentTask(request.task, request.input, agent);
-      const prompt = buildAgentPrompt(agent, task, request.input, metadata);
-      const systemPrompt = resolveAgentSystemPrompt(agent, metadata);
+      let systemPrompt: string;
+      let promptWarnings: string[];
+      try {
+        ({ systemPrompt, warnings: promptWarnings } = await renderAgentSystemPrompt(agent, metadata, task, request.input, request.basePath ?? basePath, profile.abilities));
+      } catch (error) {
+        return rejectRun({
+          code: (error as { code?: string }).code ?? PROMPT_TEMPLATE_ERROR_CODE,
+          message: error instanceof Error ? error.message : String(error),
+        });
+      }
+      const resolution = await runtimeProviderBridge.describeResolution(resolvedProvider);
+      // A CLI executor writes files and runs commands itself, before anyone could be asked about them.
+      const unheld = resolution.command === undefined
+        ? []
+        : unheldApprovals(approvals, { readOnly, shell: workspace.permissions?.shell !== false });
+      if (unheld.length > 0) {
+        return rejectRun({
+          code: APPROVALS_UNSUPPORTED_CODE,
+          message: `Agent "${agent.agentId}" holds ${unheld.join(' and ')} for approval, but provider "${resolvedProvider}" runs the CLI "${resolution.command}", which cannot wait for approval; configure an API executor for it.`,
+        });
+      }
+      // The session's memory: entries matching the task first, then the rest of its namespace.
+      const memory = thread?.memoryNamespace === undefined
+        ? []
+        : [...new Map([
+          ...await stateStore.searchMemory(task, thread.memoryNamespace),
+          ...await stateStore.listMemory(thread.memoryNamespace),
+        ].map((entry) => [entry.key, entry] as const)).values()].slice(0, DEFAULT_CONTEXT_MEMORY_HITS);
+      // Any run in a session, handed work included, sees what the session's concurrent runs hold.
+      const scratchpad = request.sessionId === undefined
+        ? undefined
+        : describeScratchpad(await stateStore.listScratchpad(request.sessionId), [traceId, request.parentTraceId, request.rootTraceId].filter((id): id is string => id !== undefined));
+      // Attached files are trimmed to the provider's context window rather than cut blindly by it.
+      const context = assembleContext({
+        systemPrompt,
+        memory: [
+          ...(scratchpad === undefined ? [] : [{ key: 'session scratchpad', content: scratchpad }]),
+          ...memory.map((entry) => ({ key: entry.key, content: memoryText(entry.value) })),
+        ],
+        files: workspace.attachments,
+        history: thread?.turns.map((turn) => ({ role: turn.role, content: turn.content })),
+        task: buildAgentPrompt(agent, task, request.input, metadata, { ...workspace, attachments: [] }),
+      }, {
+        ...readContextBudgetSettings(await readWorkspaceConfig(request.basePath ?? basePath)),
+        maxTokens: resolution.capabilities?.maxContextTokens,
+      }, workspace.attachments.length > 0 ? (await loadWorkspaceLanguageRegistry(request.basePath ?? basePath)).registry : undefined);
+      const prompt = context.prompt;
+      promptWarnings.push(...describeContextCuts(context.report));
+      // A successful run adds its task and reply to the session's thread; the first one also sets
+      // the session's agent, and its scope when it had one.
+      const continueThread = async (content: string): Promise<string[]> => {
+        if (thread === undefined) {
+          return [];
+        }
+        const at = new Date().toISOString();
+        try {
+          await updateSessionThread(request.basePath ?? basePath, thread.sessionId, (stored) => ({
+            ...stored,
+            agentId: stored.agentId ?? agent.agentId,
+            scope: stored.scope ?? scope,
+            turns: [
+              ...stored.turns,
+              { role: 'user', content: task, agentId: agent.agentId, traceId, at },
+              { role: 'assistant', content, agentId: agent.agentId, traceId, at },
+            ],
+          }));
+          return [];
+        } catch (error) {
+          return [`Could not add the run to session "${thread.sessionId}": ${error instanceof Error ? error.message : String(error)}`];
+        }
+      };
 
       await traceStore.upsertTrace({
         traceId,
@@ -1310,6 +2598,8 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
           agentId: agent.agentId,
           task,
           input: request.input,
+          scope,
+          files: request.files,
         },
         stepResults: [],
         metadata: {
@@ -1321,39 +2611,192 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
           model: resolvedModel,
           capabilities: agent.capabilities,
           command: 'agent.run',
+          profile: layers,
         },
       });
 
-      const bridgeResult = await runtimeProviderBridge.executePrompt({
+      request.onProgress?.(10, `Running agent "${agent.agentId}" on ${resolvedProvider}`);
+      // Workspace tools refuse what the permissions do not allow. A CLI executor has its own file
+      // access, and `run_command` lets an API executor change files, so the files a run changes are
+      // compared with the permissions and the write gates once it is done. The checkpoint keeps the
+      // files outside the paths, and those the gates hold, so what the run changed there can be put back.
+      const { permissions } = workspace;
+      const refusals: AgentPermissionViolation[] = [];
+      const changesFiles = resolution.command !== undefined || offersAgentCommands(workspace, readOnly);
+      const audit = changesFiles && (permissions !== undefined || approvals?.writes !== undefined)
+        ? await createWorkspaceCheckpoint(request.basePath ?? basePath, { whole: true, keep: (file) => !permitsPath(permissions, file) || gatesWrite(approvals, file) })
+        : undefined;
+      if (permissions !== undefined && resolution.command !== undefined && (!permissions.shell || !permissions.network) && resolution.protocol !== 'json-stdio' && resolution.restrictedByFlags !== true) {
+        promptWarnings.push(`Provider "${resolvedProvider}" learns the agent's permissions only from ${AGENT_PERMISSIONS_ENV_VAR}; its shell and network use cannot be checked.`);
+      }
+      // Actions the profile's `approvals` hold wait for the approval policy or `request.approve`; a
+      // run over its cost threshold pauses when the call that went over is charged, and stops if refused.
+      const approvalDecisions: ApprovalRecord[] = [];
+      const approve = createApprovalGate({
+        policy: approvalPolicy,
+        approver: request.approve,
+        onDecision: (record) => approvalDecisions.push(record),
+      });
+      const costGate = approvals?.costUsd === undefined
+        ? undefined
+        : createCostGate(approvals.costUsd, (spent) => approve({ action: 'cost', agentId: agent.agentId, target: `$${spent.toFixed(4)}`, costUsd: spent, traceId }), request.signal);
+      if (costGate !== undefined) {
+        costGates.set(traceId, costGate);
+      }
+      const approveCommand = approvals?.shell === true
+        ? (command: string) => approve({ action: 'shell', agentId: agent.agentId, target: command, traceId })
+        : undefined;
+      // Provider calls of this run and of the runs it hands work to are charged to its budget, and to
+      // those of the runs above it; the meter's signal stops the run when one is used up.
+      const meter = budget === undefined ? undefined : createBudgetMeter(budget, costGate?.signal ?? request.signal);
+      const signal = meter?.signal ?? costGate?.signal ?? request.signal;
+      if (meter !== undefined) {
+        budgetMeters.set(traceId, meter);
+      }
+      if (request.parentTraceId !== undefined) {
+        runParents.set(traceId, request.parentTraceId);
+      }
+      // The agents of the profile's `handoffs` are tools; each handoff is traced as a child of this run.
+      const delegationChain = [...(request.delegationChain ?? []), agent.agentId];
+      const handoffTools = createHandoffTools(readAgentHandoffs(metadata), (handoff, subtask, input) => this.delegateTask({
+        agentId: handoff.agentId,
+        task: subtask,
+        input,
+        contract: handoff,
+        fromAgentId: agent.agentId,
+        delegationChain,
+        sessionId: request.sessionId,
+        basePath: request.basePath,
+        surface: request.surface,
+        parentTraceId: traceId,
+        rootTraceId: request.rootTraceId ?? traceId,
+        signal,
+        approve: request.approve,
+        approvalPolicy: request.approvalPolicy,
+      }));
+      // A top-level run logs its provider calls, and those of the runs it hands work to, for `replayRun`.
+      if (request.parentTraceId === undefined && request.rootTraceId === undefined) {
+        const warning = await runRecorder.start(request.basePath ?? basePath, {
+          traceId,
+          command: 'agent.run',
+          request: {
+            agentId: request.agentId,
+            task: request.task,
+            input: request.input,
+            scope,
+            files: request.files,
+            provider: request.provider,
+            model: request.model,
+            timeoutMs: request.timeoutMs,
+            budget: request.budget,
+            outputSchema: request.outputSchema,
+          },
+        });
+        if (warning !== undefined) {
+          promptWarnings.push(warning);
+        }
+      }
+      const executionRequest = {
         provider: resolvedProvider,
         prompt,
         systemPrompt,
         model: resolvedModel,
         timeoutMs: request.timeoutMs,
+        signal,
+        scope: workspace.scope,
+        permissions,
+        tools: [
+          ...createAgentWorkspaceTools(request.basePath ?? basePath, workspace, { onViolation: (violation) => refusals.push(violation), approveCommand, readOnly }),
+          ...handoffTools,
+        ],
+        agentId: agent.agentId,
+        traceId,
+        rootTraceId: request.rootTraceId,
+        noCache: request.noCache,
+        onText: request.onText,
+      };
+      const execution = request.outputSchema === undefined
+        ? runtimeProviderBridge.executePrompt(executionRequest)
+        : runtimeProviderBridge.executeStructured(executionRequest, request.outputSchema);
+      const bridgeResult = await execution.finally(() => {
+        meter?.stop();
+        costGate?.stop();
+        budgetMeters.delete(traceId);
+        costGates.delete(traceId);
+        runParents.delete(traceId);
+        runRecorder.finish(traceId);
       });
-      const completedAt = new Date().toISOString();
+      const budgetReport = meter?.report();
+      const after = audit === undefined ? undefined : await snapshotWholeWorkspace(request.basePath ?? basePath);
+      const breaches = audit === undefined || after === undefined || permissions === undefined
+        ? []
+        : findPathViolations(audit.stamps, after, permissions);
+      // Writes the gates hold are put to the approver once the run is done, as its spending is; a
+      // refused one fails the run.
+      const refusedWrites: string[] = [];
+      for (const file of audit === undefined || after === undefined ? [] : listChangedFiles(audit.stamps, after)) {
+        if (gatesWrite(approvals, file) && permitsPath(permissions, file) && !await approve({ action: 'write', agentId: agent.agentId, target: file, traceId })) {
+          refusedWrites.push(file);
+        }
+      }
+      // What the run changed outside its paths, or wrote without approval, is put back as it was before the run.
+      const rollback = audit === undefined || breaches.length + refusedWrites.length === 0
+        ? undefined
+        : await restoreWorkspaceCheckpoint(audit, { files: [...breaches.map((violation) => violation.target), ...refusedWrites] });
+      const approvalRefusal = approvalDecisions.find((record) => record.action !== 'shell' && !record.approved);
+      const violations = [...refusals, ...breaches];
+      const completedAt = new Date().toISOString();
+      request.onProgress?.(90, 'Recording the trace');
 
       if (bridgeResult.type === 'response' || bridgeResult.type === 'failure') {
-        const warnings = bridgeResult.type === 'failure' ? [bridgeResult.response.error ?? 'Agent execution failed.'] : [];
+        // A run that went over its budget or its permissions, or whose spending or writes were not
+        // approved, fails even when the call that did it returned; its content is kept. Refused tool
+        // calls are only reported.
+        const exceeded = budgetReport?.exceeded;
+        const success = bridgeResult.response.success && exceeded === undefined && breaches.length === 0 && approvalRefusal === undefined;
+        const error = breaches.length > 0
+          ? { code: AGENT_PERMISSION_DENIED_CODE, message: `Agent "${agent.agentId}" went beyond its permissions: ${breaches.map(describeViolation).join('; ')}.` }
+          : approvalRefusal !== undefined
+            ? { code: APPROVAL_DENIED_CODE, message: `Agent "${agent.agentId}" was not approved to ${describeApproval(approvalRefusal)}.` }
+            : exceeded !== undefined
+              ? { code: BUDGET_EXCEEDED_CODE, message: describeExceeded(exceeded) }
+              : bridgeResult.response.success ? undefined : { code: bridgeResult.response.errorCode, message: bridgeResult.response.error };
+        const warnings = [
+          ...routing.warnings,
+          ...promptWarnings,
+          ...(bridgeResult.response.warnings ?? []),
+          ...(bridgeResult.type === 'failure' ? [bridgeResult.response.error ?? 'Agent execution failed.'] : []),
+          ...violations.map((violation) => `Permission violation: ${describeViolation(violation)}.`),
+          ...(rollback === undefined || rollback.restored.length === 0 ? [] : [`Put back as they were before the run: ${rollback.restored.join(', ')}.`]),
+          ...(rollback === undefined || rollback.skipped.length === 0 ? [] : [`Could not put back, being too large or binary to keep: ${rollback.skipped.join(', ')}.`]),
+          ...approvalDecisions
+            .filter((record) => !record.approved)
+            .map((record) => `Not approved: ${describeApproval(record)}${record.by === 'none' ? ' (nobody could be asked)' : ''}.`),
+        ];
+        if (success) {
+          warnings.push(...await continueThread(bridgeResult.response.content ?? ''));
+        }
         await traceStore.upsertTrace({
           traceId,
           workflowId: 'agent.run',
           surface: request.surface ?? 'cli',
-          status: bridgeResult.response.success ? 'completed' : 'failed',
+          status: success ? 'completed' : 'failed',
           startedAt,
           completedAt,
           input: {
             agentId: agent.agentId,
             task,
             input: request.input,
+            scope,
+            files: request.files,
           },
           stepResults: [
             {
               stepId: 'agent-execution',
-              success: bridgeResult.response.success,
+              success,
               durationMs: bridgeResult.response.latencyMs,
               retryCount: 0,
-              error: bridgeResult.response.error,
+              error: error?.message,
             },
           ],
           output: {
@@ -1361,12 +2804,10 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
             content: bridgeResult.response.content ?? '',
             usage: bridgeResult.response.usage,
             executionMode: 'subprocess',
+            toolCalls: bridgeResult.response.toolCalls,
             warnings,
           },
-          error: bridgeResult.response.success ? undefined : {
-            code: bridgeResult.response.errorCode,
-            message: bridgeResult.response.error,
-          },
+          error,
           metadata: {
             sessionId: request.sessionId,
             parentTraceId: request.parentTraceId,
@@ -1376,13 +2817,23 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
             model: bridgeResult.response.model,
             capabilities: agent.capabilities,
             command: 'agent.run',
+            routing: routing.routing,
+            profile: layers,
+            cache: bridgeResult.response.cache,
+            contextBudget: context.report.cuts.length > 0 ? context.report : undefined,
+            delegationChain: request.delegationChain,
+            structuredOutput: bridgeResult.response.structured,
+            budget: budgetReport,
+            permissions,
+            permissionViolations: violations.length > 0 ? violations : undefined,
+            approvals: approvalDecisions.length > 0 ? approvalDecisions : undefined,
           },
         });
 
         return {
           traceId,
           agentId: agent.agentId,
-          success: bridgeResult.response.success,
+          success,
           provider: bridgeResult.response.provider,
           model: bridgeResult.response.model,
           content: bridgeResult.response.content ?? '',
@@ -1390,15 +2841,20 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
           executionMode: 'subprocess',
           warnings,
           usage: bridgeResult.response.usage,
-          error: bridgeResult.response.success ? undefined : {
-            code: bridgeResult.response.errorCode,
-            message: bridgeResult.response.error,
-          },
+          cache: bridgeResult.response.cache,
+          structured: bridgeResult.response.structured,
+          profile: layers,
+          budget: budgetReport,
+          permissions,
+          permissionViolations: violations.length > 0 ? violations : undefined,
+          approvals,
+          approvalDecisions: approvalDecisions.length > 0 ? approvalDecisions : undefined,
+          error,
         };
       }
 
       const content = buildSimulatedAgentOutput(agent, task, request.input);
-      const warnings = [`No provider executor configured for "${resolvedProvider}". Returned simulated agent output.`];
+      const warnings = [...routing.warnings, ...promptWarnings, `No provider executor configured for "${resolvedProvider}". Returned simulated agent output.`, ...await continueThread(content)];
       const usage = {
         inputTokens: tokenize(prompt),
         outputTokens: tokenize(content),
@@ -1415,6 +2871,8 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
           agentId: agent.agentId,
           task,
           input: request.input,
+          scope,
+          files: request.files,
         },
         stepResults: [
           {
@@ -1440,6 +2898,7 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
           model: resolvedModel,
           capabilities: agent.capabilities,
           command: 'agent.run',
+          profile: layers,
         },
       });
 
@@ -1454,11 +2913,131 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
         executionMode: 'simulated',
         warnings,
         usage,
+        profile: layers,
+        budget: budgetReport,
+        permissions,
+        approvals,
+      };
+    },
+
+    async delegateTask(request) {
+      const refuse = (code: string, message: string): RuntimeDelegationResponse => ({
+        agentId: request.agentId,
+        success: false,
+        latencyMs: 0,
+        warnings: [],
+        error: { code, message },
+      });
+      const chain = request.delegationChain ?? (request.fromAgentId !== undefined ? [request.fromAgentId] : []);
+      const refusal = checkDelegationChain(chain, request.agentId);
+      if (refusal !== undefined) {
+        return refuse(refusal.code, refusal.message);
+      }
+      const inputErrors = validateHandoffInput(request.contract, request.input);
+      if (inputErrors.length > 0) {
+        return refuse('DELEGATION_INPUT_INVALID', `The input does not fit the contract: ${inputErrors.slice(0, 3).join('; ')}`);
+      }
+
+      const run = await this.runAgent({
+        agentId: request.agentId,
+        task: request.task,
+        input: request.input,
+        provider: request.contract.provider,
+        timeoutMs: request.contract.budget?.timeoutMs,
+        outputSchema: request.contract.output,
+        delegationChain: chain,
+        traceId: request.traceId,
+        sessionId: request.sessionId,
+        basePath: request.basePath,
+        surface: request.surface,
+        parentTraceId: request.parentTraceId,
+        rootTraceId: request.rootTraceId,
+        signal: request.signal,
+        approve: request.approve,
+        approvalPolicy: request.approvalPolicy,
+      });
+      const response: RuntimeDelegationResponse = {
+        traceId: run.traceId,
+        agentId: run.agentId,
+        success: false,
+        provider: run.provider,
+        model: run.model,
+        structured: run.structured,
+        usage: run.usage,
+        latencyMs: run.latencyMs,
+        warnings: run.warnings,
+      };
+      if (!run.success) {
+        return {
+          ...response,
+          error: { code: run.error?.code ?? 'DELEGATION_FAILED', message: run.error?.message ?? `Agent "${request.agentId}" failed.` },
+        };
+      }
+      // A simulated run never saw the schema, so its reply cannot stand for the result.
+      if (run.executionMode === 'simulated' || run.structured?.valid !== true) {
+        return {
+          ...response,
+          error: {
+            code: 'DELEGATION_OUTPUT_INVALID',
+            message: run.executionMode === 'simulated'
+              ? `No provider executor is configured for "${run.provider}", so the reply could not be held to the contract.`
+              : 'The reply does not fit the contract\'s output schema.',
+          },
+        };
+      }
+      const maxTokens = request.contract.budget?.maxTokens;
+      if (maxTokens !== undefined && run.usage !== undefined && run.usage.totalTokens > maxTokens) {
+        return {
+          ...response,
+          error: { code: 'DELEGATION_BUDGET_EXCEEDED', message: `Agent "${request.agentId}" used ${run.usage.totalTokens} tokens; the contract allows ${maxTokens}.` },
+        };
+      }
+      return { ...response, success: true, output: run.structured.value };
+    },
+
+    async assembleContext(request) {
+      const root = request.basePath ?? basePath;
+      const workspace = await resolveAgentWorkspace(root, { files: request.files });
+      const memory = request.memoryQuery === undefined
+        ? []
+        : (await this.searchMemory(request.memoryQuery, request.memoryNamespace)).slice(0, request.memoryLimit ?? DEFAULT_CONTEXT_MEMORY_HITS);
+      const maxTokens = request.maxTokens
+        ?? (await resolveProviderBridge(request.basePath).describeResolution(request.provider ?? 'claude')).capabilities?.maxContextTokens;
+      return assembleContext({
+        systemPrompt: request.systemPrompt,
+        memory: memory.map((entry) => ({ key: entry.key, content: memoryText(entry.value) })),
+        repoMap: request.repoMap === true ? (await renderRepoMap({ basePath: root })).text : undefined,
+        files: workspace.attachments,
+        history: request.history,
+        task: request.task,
+      }, {
+        ...readContextBudgetSettings(await readWorkspaceConfig(root)),
+        maxTokens,
+      }, (await loadWorkspaceLanguageRegistry(root)).registry);
+    },
+
+    async renderAgentPrompt(request) {
+      const profile = await this.resolveAgentProfile(request.agentId, request.basePath);
+      if (profile === undefined) {
+        return undefined;
+      }
+      const { agent } = profile;
+      const metadata = isRecord(agent.metadata) ? agent.metadata : {};
+      const task = resolveAgentTask(request.task, request.input, agent);
+      const rendered = await renderAgentSystemPrompt(agent, metadata, task, request.input, request.basePath ?? basePath, profile.abilities);
+      const permissions = readAgentPermissions(metadata, agent.agentId);
+      return {
+        agentId: agent.agentId,
+        ...rendered,
+        prompt: buildAgentPrompt(agent, task, request.input, metadata, permissions === undefined ? undefined : { attachments: [], permissions }),
       };
     },
 
     async recommendAgents(request) {
-      const agents = await stateStore.listAgents();
+      // Agents are ranked on the capabilities they inherit too.
+      const agents = await Promise.all((await stateStore.listAgents()).map(async (agent) => (
+        await resolveAgentProfile(agent, (baseId) => stateStore.getAgent(baseId), basePath).catch(() => ({ agent }))
+      ).agent));
       const ranked = rankAgents(agents, request);
       return request.limit === undefined ? ranked : ranked.slice(0, Math.max(0, request.limit));
     },
@@ -1531,16 +3110,24 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
 
       const failureStrategy = request.failureStrategy ?? 'failSafe';
       const resultAggregation = request.resultAggregation ?? 'list';
-      const maxConcurrent = Math.max(1, request.maxConcurrent ?? 3);
+      const concurrency = readConcurrencySettings(await readWorkspaceConfig(basePath));
+      const maxConcurrent = Math.max(1, request.maxConcurrent ?? concurrency.maxConcurrent);
       const taskMap = new Map(request.tasks.map((task) => [task.taskId, task] as const));
-      const results = new Map<string, RuntimeParallelTaskResult>();
+      // Each task counts against the limit of the provider it runs on: its own, else its agent's.
+      const providers = new Map(await Promise.all(request.tasks.map(async (task) => {
+        const metadata = task.provider === undefined ? await layeredMetadata(task.agentId, basePath) : undefined;
+        return [task.taskId, task.provider ?? (isRecord(metadata) ? asOptionalString(metadata.provider) : undefined) ?? 'claude'] as const;
+      })));
       let stopExecution = false;
 
-      for (const layer of plan.layers) {
-        if (stopExecution) {
-          for (const taskId of layer) {
-            const task = taskMap.get(taskId)!;
-            results.set(taskId, {
+      // A task starts as soon as its own dependencies are done, not when their whole layer is.
+      const results = await runScheduled(
+        plan.orderedTaskIds.map((taskId) => ({ id: taskId, dependencies: taskMap.get(taskId)!.dependencies, pool: providers.get(taskId) })),
+        { maxConcurrent, poolLimits: concurrency.perProvider },
+        async ({ id: taskId }, dependencyResults): Promise<RuntimeParallelTaskResult> => {
+          const task = taskMap.get(taskId)!;
+          if (stopExecution) {
+            return {
               taskId,
               agentId: task.agentId,
               status: 'skipped',
@@ -1549,65 +3136,53 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
                 code: 'PARALLEL_SKIPPED_AFTER_FAILURE',
                 message: 'Skipped because failFast stopped the orchestration after a prior failure.',
               },
-            });
+            };
           }
-          continue;
-        }
-
-        for (const batch of chunkArray(layer, maxConcurrent)) {
-          const batchResults = await Promise.all(batch.map(async (taskId) => {
-            const task = taskMap.get(taskId)!;
-            const blockedDependency = (task.dependencies ?? []).find((dependency) => {
-              const dependencyResult = results.get(dependency);
-              return dependencyResult !== undefined && dependencyResult.status !== 'completed';
-            });
-
-            if (blockedDependency !== undefined) {
-              return {
-                taskId,
-                agentId: task.agentId,
-                status: 'skipped',
-                dependencies: task.dependencies ?? [],
-                error: {
-                  code: 'PARALLEL_DEPENDENCY_FAILED',
-                  message: `Skipped because dependency "${blockedDependency}" did not complete successfully.`,
-                },
-              } satisfies RuntimeParallelTaskResult;
-            }
-
-            const runResult = await this.runAgent({
-              agentId: task.agentId,
-              task: task.task,
-              input: task.input,
-              provider: task.provider,
-              model: task.model,
-              timeoutMs: task.timeoutMs,
-              sessionId: request.sessionId,
-              surface: request.surface ?? 'mcp',
-              parentTraceId: traceId,
-              rootTraceId: traceId,
-            });
-
+          const blockedDependency = [...dependencyResults.values()].find((dependency) => dependency.status !== 'completed');
+          if (blockedDependency !== undefined) {
             return {
               taskId,
               agentId: task.agentId,
-              status: runResult.success ? 'completed' : 'failed',
-              traceId: runResult.traceId,
+              status: 'skipped',
               dependencies: task.dependencies ?? [],
-              result: runResult,
-              error: runResult.success ? undefined : runResult.error,
-            } satisfies RuntimeParallelTaskResult;
-          }));
-
-          for (const taskResult of batchResults) {
-            results.set(taskResult.taskId, taskResult);
+              error: {
+                code: 'PARALLEL_DEPENDENCY_FAILED',
+                message: `Skipped because dependency "${blockedDependency.taskId}" did not complete successfully.`,
+              },
+            };
           }
 
-          if (failureStrategy === 'failFast' && batchResults.some((entry) => entry.status === 'failed')) {
+          // What the tasks it depends on produced goes to the agent with its own input.
+          const runResult = await this.runAgent({
+            agentId: task.agentId,
+            task: task.task,
+            input: dependencyResults.size === 0 ? task.input : {
+              ...task.input,
+              dependencyOutputs: Object.fromEntries([...dependencyResults.values()].map((dependency) => [dependency.taskId, dependency.result?.content ?? ''])),
+            },
+            provider: task.provider,
+            model: task.model,
+            timeoutMs: task.timeoutMs,
+            sessionId: request.sessionId,
+            surface: request.surface ?? 'mcp',
+            parentTraceId: traceId,
+            rootTraceId: traceId,
+          });
+          if (failureStrategy === 'failFast' && !runResult.success) {
             stopExecution = true;
           }
-        }
-      }
+
+          return {
+            taskId,
+            agentId: task.agentId,
+            status: runResult.success ? 'completed' : 'failed',
+            traceId: runResult.traceId,
+            dependencies: task.dependencies ?? [],
+            result: runResult,
+            error: runResult.success ? undefined : runResult.error,
+          };
+        },
+      );
 
       const orderedResults = plan.orderedTaskIds.map((taskId) => results.get(taskId)).filter((entry): entry is RuntimeParallelTaskResult => entry !== undefined);
       const success = orderedResults.every((entry) => entry.status === 'completed');
@@ -1665,13 +3240,146 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
       };
     },
 
+    async runPipeline(request) {
+      const root = request.basePath ?? basePath;
+      let source: string;
+      try {
+        source = await readFile(isAbsolute(request.file) ? request.file : join(root, request.file), 'utf8');
+      } catch {
+        throw Object.assign(new Error(`Pipeline file "${request.file}" was not found.`), { code: 'PIPELINE_NOT_FOUND' });
+      }
+      const pipeline = parsePipeline(source, request.file);
+      const startedAt = new Date().toISOString();
+      return runCheckpointedPipeline(pipeline, {
+        checkpoint: {
+          runId: request.traceId ?? randomUUID(),
+          file: request.file,
+          source,
+          input: request.input,
+          provider: request.provider,
+          budget: request.budget,
+          sessionId: request.sessionId,
+          status: 'running',
+          startedAt,
+          updatedAt: startedAt,
+          stages: [],
+        },
+        basePath: root,
+        surface: request.surface,
+        signal: request.signal,
+        onStageStart: request.onStageStart,
+        onStageEnd: request.onStageEnd,
+        onText: request.onText,
+      }, (agentRequest) => this.runAgent({ ...agentRequest, approve: request.approve, approvalPolicy: request.approvalPolicy }));
+    },
+
+    async resumePipeline(request) {
+      const root = request.basePath ?? basePath;
+      const checkpoint = await readPipelineCheckpoint(root, request.runId);
+      if (checkpoint === undefined) {
+        throw Object.assign(new Error(`Pipeline run "${request.runId}" has no checkpoint to resume from.`), { code: 'PIPELINE_CHECKPOINT_NOT_FOUND' });
+      }
+      return runCheckpointedPipeline(parsePipeline(checkpoint.source, checkpoint.file), {
+        checkpoint: { ...checkpoint, budget: request.budget ?? checkpoint.budget, status: 'running' },
+        basePath: root,
+        surface: request.surface,
+        signal: request.signal,
+        onStageStart: request.onStageStart,
+        onStageEnd: request.onStageEnd,
+        onText: request.onText,
+      }, (agentRequest) => this.runAgent({ ...agentRequest, approve: request.approve, approvalPolicy: request.approvalPolicy }));
+    },
+
+    async runEval(request) {
+      const root = request.basePath ?? basePath;
+      let source: string;
+      try {
+        source = await readFile(isAbsolute(request.file) ? request.file : join(root, request.file), 'utf8');
+      } catch {
+        throw Object.assign(new Error(`Eval suite "${request.file}" was not found.`), { code: 'EVAL_SUITE_NOT_FOUND' });
+      }
+      return runEvalSuite(parseEvalSuite(source, request.file), {
+        basePath: root,
+        runId: randomUUID(),
+        file: request.file,
+        agents: request.agents,
+        providers: request.providers,
+        caseIds: request.cases,
+        surface: request.surface,
+        signal: request.signal,
+        onResult: request.onResult,
+      }, {
+        runAgent: (agentRequest) => this.runAgent(agentRequest),
+        costOf: async (traceId) => (await usageTracker.report({ traceId })).total.costUsd,
+      });
+    },
+
+    async replayRun(request) {
+      const root = request.basePath ?? basePath;
+      const { run, calls } = await readReplayLog(root, request.runId);
+      const traceId = randomUUID();
+      runRecorder.beginReplay(root, traceId, calls);
+      let result: RuntimeAgentRunResponse | RuntimePipelineResponse;
+      let summary: ReplaySummary;
+      try {
+        if (run.command === 'agent.run') {
+          result = await this.runAgent({
+            ...run.request,
+            traceId,
+            basePath: root,
+            surface: request.surface,
+            signal: request.signal,
+            approve: request.approve,
+            approvalPolicy: request.approvalPolicy,
+          });
+        } else {
+          const startedAt = new Date().toISOString();
+          result = await runCheckpointedPipeline(parsePipeline(run.request.source, run.request.file), {
+            checkpoint: {
+              runId: traceId,
+              file: run.request.file,
+              source: run.request.source,
+              input: run.request.input,
+              provider: run.request.provider,
+              budget: run.request.budget,
+              status: 'running',
+              startedAt,
+              updatedAt: startedAt,
+              stages: [],
+            },
+            basePath: root,
+            surface: request.surface,
+            signal: request.signal,
+          }, (agentRequest) => this.runAgent({ ...agentRequest, approve: request.approve, approvalPolicy: request.approvalPolicy }));
+        }
+      } finally {
+        summary = runRecorder.endReplay(traceId);
+      }
+      return { runId: request.runId, traceId, command: run.command, success: result.success, ...summary, result };
+    },
+
     async getStatus(request) {
       const limit = request?.limit ?? 10;
-      const [sessions, traces, config] = await Promise.all([
+      const [sessions, traces, config, providerQuotas, providerRateLimits] = await Promise.all([
         stateStore.listSessions(),
         traceStore.listTraces(Math.max(limit * 3, limit)),
         readWorkspaceConfig(basePath),
+        providerBridge.getQuotaStatus(),
+        providerBridge.getRateLimitStatus(),
       ]);
+      const defaultProvider = typeof config.providers === 'object' && config.providers !== null && typeof (config.providers as Record<string, unknown>).default === 'string'
+        ? (config.providers as Record<string, string>).default
+        : typeof config.defaultProvider === 'string'
+          ? config.defaultProvider
+          : undefined;
+      if (request?.probe === true) {
+        await providerBridge.probeProviders([
+          ...listConfiguredExecutors(config),
+          ...(defaultProvider !== undefined ? [defaultProvider] : []),
+          ...readFallbackProviders(config),
+        ], request.signal);
+      }
+      const providerHealth = await providerBridge.getHealthStatus();
       const activeSessions = sessions.filter((session) => session.status === 'active').slice(0, limit);
       const runningTraces = traces.filter((trace) => trace.status === 'running').slice(0, limit);
       const recentFailedTraces = traces.filter((trace) => trace.status === 'failed').slice(0, limit);
@@ -1690,13 +3398,12 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
           failed: traces.filter((trace) => trace.status === 'failed').length,
         },
         runtime: {
-          defaultProvider: typeof config.providers === 'object' && config.providers !== null && typeof (config.providers as Record<string, unknown>).default === 'string'
-            ? (config.providers as Record<string, string>).default
-            : typeof config.defaultProvider === 'string'
-              ? config.defaultProvider
-              : undefined,
+          defaultProvider,
           providerExecutionMode: providerBridge.getExecutionMode(),
           configuredExecutors: listConfiguredExecutors(config),
+          providerQuotas,
+          providerRateLimits,
+          providerHealth,
         },
         activeSessions,
         runningTraces,
@@ -1704,115 +3411,445 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
       };
     },
 
-    gitStatus(request) {
-      return getGitStatus(request?.basePath ?? basePath);
+    isReadOnly() {
+      return readOnly;
+    },
+
+    gitStatus(request) {
+      return getGitStatus(request?.basePath ?? basePath);
+    },
+
+    async gitDiff(request) {
+      const diffBasePath = request?.basePath ?? basePath;
+      const command = ['diff'];
+      if (request?.staged === true) {
+        command.push('--cached');
+      }
+      if (request?.stat === true) {
+        command.push('--stat');
+      }
+      if (typeof request?.commit === 'string' && request.commit.length > 0) {
+        command.push(request.commit);
+      }
+      if (Array.isArray(request?.paths) && request.paths.length > 0) {
+        command.push('--', ...request.paths);
+      }
+
+      try {
+        const { stdout } = await execFileAsync('git', command, { cwd: diffBasePath, maxBuffer: 1024 * 1024 * 4 });
+        return {
+          diff: stdout,
+          command: ['git', ...command],
+          basePath: diffBasePath,
+        };
+      } catch (error) {
+        const message = error instanceof Error ? error.message : String(error);
+        throw new Error(`git diff failed: ${message}`);
+      }
+    },
+
+    async commitPrepare(request) {
+      if (request?.stageAll === true || (request?.paths?.length ?? 0) > 0) {
+        assertWritable(readOnly, 'Staging files');
+      }
+      return prepareCommit({
+        basePath: request?.basePath ?? basePath,
+        paths: request?.paths,
+        stageAll: request?.stageAll,
+        type: request?.type,
+        scope: request?.scope,
+      });
+    },
+
+    reviewPullRequest(request) {
+      return reviewPullRequest({
+        basePath: request?.basePath ?? basePath,
+        base: request?.base,
+        head: request?.head,
+      });
+    },
+
+    async createPullRequest(request) {
+      assertWritable(readOnly, 'Creating pull requests');
+      return createPullRequest({
+        basePath: request.basePath ?? basePath,
+        title: request.title,
+        body: request.body,
+        base: request.base,
+        head: request.head,
+        draft: request.draft,
+      });
+    },
+
+    async listWorkflows(options) {
+      const workflowDir = resolveWorkflowDir(options?.workflowDir, options?.basePath, basePath);
+      const loader = createWorkflowLoader({ workflowsDir: workflowDir });
+      const workflows = await loader.loadAll();
+      return workflows.map((workflow) => ({
+        workflowId: workflow.workflowId,
+        name: workflow.name,
+        version: workflow.version,
+        steps: workflow.steps.length,
+      }));
+    },
+
+    async describeWorkflow(request) {
+      const workflowDir = resolveWorkflowDir(request.workflowDir, request.basePath, basePath);
+      const loader = createWorkflowLoader({ workflowsDir: workflowDir });
+      const workflow = await loader.load(request.workflowId) ?? getBuiltinWorkflow(request.workflowId);
+      if (workflow === undefined) {
+        return undefined;
+      }
+
+      return {
+        workflowId: workflow.workflowId,
+        name: workflow.name,
+        description: workflow.description,
+        version: workflow.version,
+        workflowDir,
+        steps: workflow.steps.map((step) => ({
+          stepId: step.stepId,
+          type: step.type,
+        })),
+      };
+    },
+
+    analyzeReview(request) {
+      return runReviewAnalysis(traceStore, {
+        paths: request.paths,
+        focus: request.focus,
+        maxFiles: request.maxFiles,
+        traceId: request.traceId,
+        sessionId: request.sessionId,
+        basePath: request.basePath ?? basePath,
+        surface: request.surface ?? 'cli',
+      });
+    },
+
+    listReviewTraces(limit) {
+      return listReviewTraces(traceStore, limit);
+    },
+
+    async indexCode(request) {
+      const indexBasePath = request?.basePath ?? basePath;
+      const params = { paths: request?.paths, maxFiles: request?.maxFiles };
+      if (request?.onFile !== undefined || request?.maxMemoryMb !== undefined || request?.signal !== undefined) {
+        // Streaming, a memory limit and cancellation apply to this process's parse; a running daemon then picks up the new file.
+        const result = await buildCodeIndex({ ...params, basePath: indexBasePath, onFile: request.onFile, maxMemoryMb: request.maxMemoryMb, signal: request.signal });
+        await requestIndexServer(indexBasePath, 'reload');
+        return result;
+      }
+      return await requestIndexServer<RuntimeCodeIndexResponse>(indexBasePath, 'index', params)
+        ?? buildCodeIndex({ ...params, basePath: indexBasePath });
+    },
+
+    async searchCodeSymbols(request) {
+      const indexBasePath = request?.basePath ?? basePath;
+      const query = {
+        query: request?.query,
+        id: request?.id,
+        kind: request?.kind,
+        language: request?.language,
+        file: request?.file,
+        tests: request?.tests,
+        concurrent: request?.concurrent,
+        features: request?.features,
+        target: request?.target,
+        derive: request?.derive,
+        attribute: request?.attribute,
+        limit: request?.limit,
+      };
+      return await requestIndexServer<CodeSymbol[]>(indexBasePath, 'symbols', query)
+        ?? searchCodeSymbols({ ...query, basePath: indexBasePath });
+    },
+
+    parseCodeSource(request) {
+      return parseWorkspaceCodeSource({ ...request, basePath: request.basePath ?? basePath });
+    },
+
+    watchCodeIndex(request) {
+      return startIndexWatcher({ ...request, basePath: request?.basePath ?? basePath });
+    },
+
+    startIndexServer(request) {
+      return startIndexServer({ basePath: request?.basePath ?? basePath, watch: request?.watch });
+    },
+
+    getIndexServerStatus(request) {
+      return requestIndexServer<IndexServerStatus>(request?.basePath ?? basePath, 'status');
+    },
+
+    async stopIndexServer(request) {
+      const stopped = await requestIndexServer<{ stopped: boolean }>(request?.basePath ?? basePath, 'shutdown');
+      return stopped?.stopped === true;
+    },
+
+    async askProject(request) {
+      const askBasePath = request.basePath ?? basePath;
+      const runtimeProviderBridge = resolveProviderBridge(askBasePath);
+      const traceId = request.traceId ?? randomUUID();
+      const startedAt = new Date().toISOString();
+      const routing = request.provider === undefined
+        ? await runtimeProviderBridge.selectProvider('claude')
+        : { provider: request.provider, warnings: [] };
+
+      const result = await answerProjectQuestion({
+        question: request.question,
+        basePath: askBasePath,
+        limit: request.limit,
+        planner: async (prompt) => {
+          const outcome = await runtimeProviderBridge.executePrompt({
+            provider: routing.provider,
+            prompt,
+            model: 'v14-ask-planner',
+            temperature: 0,
+            traceId,
+          });
+          if (outcome.type === 'unavailable') {
+            return undefined;
+          }
+          if (!outcome.response.success) {
+            throw new Error(outcome.response.error ?? `Provider "${routing.provider}" failed to plan the query.`);
+          }
+          return outcome.response.content ?? '';
+        },
+      });
+      const warnings = [...routing.warnings, ...result.warnings];
+
+      await traceStore.upsertTrace({
+        traceId,
+        workflowId: 'ask',
+        surface: request.surface ?? 'cli',
+        status: 'completed',
+        startedAt,
+        completedAt: new Date().toISOString(),
+        input: { question: request.question, provider: routing.provider },
+        stepResults: [
+          { stepId: 'plan-and-query', success: true, durationMs: Date.now() - Date.parse(startedAt), retryCount: 0 },
+        ],
+        output: {
+          plan: result.plan,
+          planner: result.planner,
+          answer: result.answer,
+          referenceCount: result.references.length,
+          warnings,
+        },
+        metadata: {
+          provider: result.planner === 'provider' ? routing.provider : undefined,
+          command: 'ask',
+        },
+      });
+
+      return { ...result, warnings, traceId };
+    },
+
+    async installHook(request) {
+      assertWritable(readOnly, 'Installing git hooks');
+      return installPreCommitHook({
+        basePath: request?.basePath ?? basePath,
+        force: request?.force,
+      });
+    },
+
+    async uninstallHook(request) {
+      assertWritable(readOnly, 'Removing git hooks');
+      return uninstallPreCommitHook({
+        basePath: request?.basePath ?? basePath,
+      });
+    },
+
+    async runPreCommitHook(request) {
+      const hookBasePath = request?.basePath ?? basePath;
+      const config = resolvePreCommitConfig(await readWorkspaceConfig(hookBasePath));
+      return runPreCommitPipeline({
+        basePath: hookBasePath,
+        config: request?.mode === undefined ? config : { ...config, mode: request.mode },
+        sessionId: request?.sessionId,
+      }, (agentRequest) => this.runAgent(agentRequest));
+    },
+
+    scaffoldImplementation,
+
+    analyzeTestImpact: analyzeWorkspaceTestImpact,
+
+    async testAndFix(request) {
+      assertWritable(readOnly, 'Test-and-fix');
+      return runTestAndFix(
+        { ...request, basePath: request?.basePath ?? basePath },
+        (agentRequest) => this.runAgent({ ...agentRequest, approve: request?.approve, approvalPolicy: request?.approvalPolicy }),
+      );
+    },
+
+    reviewAndAnnotate(request) {
+      return runReviewAndAnnotate({ ...request, basePath: request?.basePath ?? basePath }, (agentRequest) => this.runAgent(agentRequest));
+    },
+
+    analyzeChangeImpact(request) {
+      return analyzeChangeImpact({
+        diff: request?.diff,
+        files: request?.files,
+        base: request?.base,
+        depth: request?.depth,
+        basePath: request?.basePath ?? basePath,
+      });
+    },
+
+    async runMaintenance(request) {
+      const maintenanceBasePath = request?.basePath ?? basePath;
+      const dryRun = request?.dryRun === true;
+      const now = new Date();
+      const previous = await readMaintenanceState(maintenanceBasePath);
+      if (request?.ifDue === true && !isMaintenanceDue(previous, await readMaintenanceConfig(maintenanceBasePath), now)) {
+        return { success: true, dryRun, skipped: 'Maintenance is not due yet.', lastRunAt: previous?.lastRunAt, tasks: [] };
+      }
+      if (!dryRun) {
+        assertWritable(readOnly, 'Workspace maintenance');
+      }
+
+      const traceId = randomUUID();
+      const startedAt = now.toISOString();
+      const input = { tasks: request?.tasks ?? [...MAINTENANCE_TASKS], dryRun };
+      await traceStore.upsertTrace({
+        traceId,
+        workflowId: MAINTENANCE_WORKFLOW.workflowId,
+        surface: request?.surface ?? 'cli',
+        status: 'running',
+        startedAt,
+        input,
+        stepResults: [],
+      });
+
+      const tasks: MaintenanceTaskResult[] = [];
+      for (const task of MAINTENANCE_TASKS.filter((candidate) => input.tasks.includes(candidate))) {
+        tasks.push(await runMaintenanceTask(task, { dryRun, basePath: maintenanceBasePath, now }));
+      }
+      const success = tasks.every((task) => task.success);
+      const completedAt = new Date().toISOString();
+      await traceStore.upsertTrace({
+        traceId,
+        workflowId: MAINTENANCE_WORKFLOW.workflowId,
+        surface: request?.surface ?? 'cli',
+        status: success ? 'completed' : 'failed',
+        startedAt,
+        completedAt,
+        input,
+        stepResults: tasks.map((task) => ({
+          stepId: task.task,
+          success: task.success,
+          durationMs: task.durationMs,
+          retryCount: 0,
+          error: task.error,
+        })),
+        output: { tasks },
+        ...(success ? {} : { error: { code: 'MAINTENANCE_FAILED', message: tasks.filter((task) => !task.success).map((task) => task.summary).join('; ') } }),
+      });
+      if (!dryRun) {
+        await writeMaintenanceState(maintenanceBasePath, { lastRunAt: completedAt, traceId, tasks });
+      }
+      return { traceId, success, dryRun, lastRunAt: dryRun ? previous?.lastRunAt : completedAt, tasks };
+    },
+
+    reportUnsafeCode(request) {
+      return buildUnsafeReports({ paths: request?.paths, crate: request?.crate, basePath: request?.basePath ?? basePath });
     },
 
-    async gitDiff(request) {
-      const diffBasePath = request?.basePath ?? basePath;
-      const command = ['diff'];
-      if (request?.staged === true) {
-        command.push('--cached');
-      }
-      if (request?.stat === true) {
-        command.push('--stat');
-      }
-      if (typeof request?.commit === 'string' && request.commit.length > 0) {
-        command.push(request.commit);
-      }
-      if (Array.isArray(request?.paths) && request.paths.length > 0) {
-        command.push('--', ...request.paths);
-      }
+    findUnreferencedSymbols(request) {
+      return findUnreferencedSymbols({ paths: request?.paths, kind: request?.kind, limit: request?.limit, basePath: request?.basePath ?? basePath });
+    },
 
-      try {
-        const { stdout } = await execFileAsync('git', command, { cwd: diffBasePath, maxBuffer: 1024 * 1024 * 4 });
-        return {
-          diff: stdout,
-          command: ['git', ...command],
-          basePath: diffBasePath,
-        };
-      } catch (error) {
-        const message = error instanceof Error ? error.message : String(error);
-        throw new Error(`git diff failed: ${message}`);
-      }
+    findReferences(request) {
+      return findSymbolReferences({ symbol: request.symbol, basePath: request.basePath ?? basePath });
     },
 
-    commitPrepare(request) {
-      return prepareCommit({
-        basePath: request?.basePath ?? basePath,
-        paths: request?.paths,
-        stageAll: request?.stageAll,
-        type: request?.type,
-        scope: request?.scope,
-      });
+    buildCallHierarchy(request) {
+      return buildCallHierarchy({ symbol: request.symbol, direction: request.direction, depth: request.depth, basePath: request.basePath ?? basePath });
     },
 
-    reviewPullRequest(request) {
-      return reviewPullRequest({
-        basePath: request?.basePath ?? basePath,
-        base: request?.base,
-        head: request?.head,
-      });
+    renderSignatures(request) {
+      return renderSignatures({ paths: request.paths, basePath: request.basePath ?? basePath });
     },
 
-    createPullRequest(request) {
-      return createPullRequest({
-        basePath: request.basePath ?? basePath,
-        title: request.title,
-        body: request.body,
-        base: request.base,
-        head: request.head,
-        draft: request.draft,
-      });
+    renderFileSkeleton(request) {
+      return renderFileSkeleton({ path: request.path, basePath: request.basePath ?? basePath });
     },
 
-    async listWorkflows(options) {
-      const workflowDir = resolveWorkflowDir(options?.workflowDir, options?.basePath, basePath);
-      const loader = createWorkflowLoader({ workflowsDir: workflowDir });
-      const workflows = await loader.loadAll();
-      return workflows.map((workflow) => ({
-        workflowId: workflow.workflowId,
-        name: workflow.name,
-        version: workflow.version,
-        steps: workflow.steps.length,
-      }));
+    readSymbolBody(request) {
+      return readSymbolBody({ symbol: request.symbol, basePath: request.basePath ?? basePath });
     },
 
-    async describeWorkflow(request) {
-      const workflowDir = resolveWorkflowDir(request.workflowDir, request.basePath, basePath);
-      const loader = createWorkflowLoader({ workflowsDir: workflowDir });
-      const workflow = await loader.load(request.workflowId);
-      if (workflow === undefined) {
-        return undefined;
-      }
+    renderOutline(request) {
+      return renderOutline({ paths: request.paths, basePath: request.basePath ?? basePath });
+    },
 
-      return {
-        workflowId: workflow.workflowId,
-        name: workflow.name,
-        description: workflow.description,
-        version: workflow.version,
-        workflowDir,
-        steps: workflow.steps.map((step) => ({
-          stepId: step.stepId,
-          type: step.type,
-        })),
-      };
+    renderRepoMap(request) {
+      return renderRepoMap({ basePath: request?.basePath ?? basePath });
     },
 
-    analyzeReview(request) {
-      return runReviewAnalysis(traceStore, {
-        paths: request.paths,
-        focus: request.focus,
-        maxFiles: request.maxFiles,
-        traceId: request.traceId,
-        sessionId: request.sessionId,
+    exportCodeGraph(request) {
+      return exportCodeGraph({ graph: request.graph, format: request.format, paths: request.paths, basePath: request.basePath ?? basePath });
+    },
+
+    async searchSymbols(request) {
+      const indexBasePath = request.basePath ?? basePath;
+      const params = { query: request.query, limit: request.limit };
+      return await requestIndexServer<SymbolSearchHit[]>(indexBasePath, 'search', params)
+        ?? searchSymbolIndex({ ...params, basePath: indexBasePath });
+    },
+
+    async describeSymbols(request) {
+      const indexBasePath = request.basePath ?? basePath;
+      const query = [
+        request.query ?? '',
+        ...(request.kind !== undefined ? [`kind:${request.kind}`] : []),
+        ...(request.language !== undefined ? [`lang:${request.language}`] : []),
+      ].join(' ');
+      const params = { query, limit: request.limit };
+      const hits = await requestIndexServer<SymbolSearchHit[]>(indexBasePath, 'search', params)
+        ?? await searchSymbolIndex({ ...params, basePath: indexBasePath });
+      return describeSymbolHits(indexBasePath, hits);
+    },
+
+    searchText(request) {
+      return searchText({ ...request, basePath: request.basePath ?? basePath });
+    },
+
+    hybridSearch(request) {
+      return createSearchService({
         basePath: request.basePath ?? basePath,
-        surface: request.surface ?? 'cli',
+        searchMemory: (query) => stateStore.searchMemory(query, request.namespace),
+      }).hybrid(request.query, request.k);
+    },
+
+    async embedCode(request = {}) {
+      const embedBasePath = request.basePath ?? basePath;
+      let embedder = request.embedder;
+      if (embedder === undefined) {
+        const configured = await readEmbedderConfig(embedBasePath);
+        const backend = request.backend ?? configured?.backend;
+        if (backend === undefined) {
+          throw new Error('No embedding backend configured. Pass --embedder openai|ollama|onnx or set codeIntel.embeddings.backend in .automatosx/config.json.');
+        }
+        embedder = createEmbedder({
+          backend,
+          model: request.model ?? (backend === configured?.backend ? configured.model : undefined),
+          baseUrl: backend === configured?.backend ? configured.baseUrl : undefined,
+        });
+      }
+      return embedCodeChunks({ basePath: embedBasePath, embedder, stateStore, paths: request.paths });
+    },
+
+    createDebugBundle(request) {
+      const bundleBasePath = request?.basePath ?? basePath;
+      return createDebugBundle(traceStore, resolveProviderBridge(bundleBasePath), {
+        basePath: bundleBasePath,
+        traceId: request?.traceId,
+        outputPath: request?.outputPath,
+        cliVersion: request?.cliVersion,
       });
     },
 
-    listReviewTraces(limit) {
-      return listReviewTraces(traceStore, limit);
+    diagnoseEnvironment(request) {
+      return diagnoseEnvironment({ ...request, basePath: request?.basePath ?? basePath });
     },
 
     async getConfig(path) {
@@ -1828,12 +3865,28 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
     },
 
     async setConfig(path, value) {
-      const config = await readWorkspaceConfig(basePath);
+      assertWritable(readOnly, 'Updating workspace config');
+      const { path: configPath, config } = await readProjectConfig(basePath);
       setValueAtPath(config, path, value);
-      await writeWorkspaceConfig(basePath, config);
+      // Only what the new value gets wrong stops it, so a file already wrong elsewhere can still be corrected.
+      const issues = validateWorkspaceConfig(config).filter((issue) => issue.path === path
+        || issue.path.startsWith(`${path}.`)
+        || (issue.unknownKey === true && path.startsWith(`${issue.path}.`)));
+      if (issues.length > 0) {
+        throw Object.assign(new Error(issues.map(formatConfigIssue).join('; ')), { code: 'CONFIG_INVALID' });
+      }
+      await writeProjectConfig(configPath, config);
       return config;
     },
 
+    async explainConfig(path) {
+      return explainConfigKey(await loadLayeredConfig(basePath), path);
+    },
+
+    async validateConfig() {
+      return validateLayeredConfig(await loadLayeredConfig(basePath));
+    },
+
     getTrace(traceId) {
       return traceStore.getTrace(traceId);
     },
@@ -1866,24 +3919,136 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
       return limit === undefined ? filtered : filtered.slice(0, limit);
     },
 
-    storeMemory(entry) {
-      return stateStore.storeMemory(entry);
+    async storeMemory({ scope, ttlSeconds, dedup, source, ...entry }) {
+      if (ttlSeconds !== undefined && !(ttlSeconds > 0)) {
+        throw new Error(`Memory TTL must be a positive number of seconds, got ${ttlSeconds}.`);
+      }
+      // Agent-scoped entries cite their agent without being told.
+      const agent = scope?.agent?.trim();
+      const cited = source !== undefined && source.agent === undefined && agent !== undefined && agent.length > 0 ? { ...source, agent } : source;
+      const stored = {
+        ...entry,
+        namespace: scopedNamespace(entry.namespace, scope),
+        ...(ttlSeconds !== undefined ? { expiresAt: new Date(Date.now() + ttlSeconds * 1000).toISOString() } : {}),
+        ...(cited !== undefined ? { source: await recordMemorySource(basePath, cited) } : {}),
+      };
+      const policy = resolveMemoryDedupPolicy(getValueAtPath(await readWorkspaceConfig(basePath), 'memory.dedup'), dedup);
+      if (policy === undefined) {
+        return stateStore.storeMemory(stored);
+      }
+      let embedding = stored.embedding;
+      if (embedding === undefined) {
+        const configured = await readEmbedderConfig(basePath);
+        if (configured === undefined) {
+          throw new Error('Memory deduplication compares embeddings: pass one with the entry or set codeIntel.embeddings.backend in .automatosx/config.json.');
+        }
+        [embedding] = await createEmbedder(configured).embed([memoryText(stored.value)]);
+      }
+      return storeDeduplicatedMemory(stateStore, { ...stored, embedding: embedding! }, policy);
+    },
+
+    getMemory(key, namespace, scope) {
+      return stateStore.getMemory(key, scopedNamespace(namespace, scope));
+    },
+
+    async searchMemory(query, namespace, scope) {
+      if (namespace !== undefined || scope === undefined) {
+        return stateStore.searchMemory(query, scopedNamespace(namespace, scope));
+      }
+      return withinScope(await stateStore.searchMemory(query), scope);
+    },
+
+    deleteMemory(key, namespace, scope) {
+      return stateStore.deleteMemory(key, scopedNamespace(namespace, scope));
+    },
+
+    async listMemory(namespace, scope) {
+      if (namespace !== undefined || scope === undefined) {
+        return stateStore.listMemory(scopedNamespace(namespace, scope));
+      }
+      return withinScope(await stateStore.listMemory(), scope);
+    },
+
+    listMemoryNamespaces() {
+      return stateStore.listMemoryNamespaces();
+    },
+
+    copyMemoryNamespace(request) {
+      return stateStore.copyMemoryNamespace(request.source, request.target, { overwrite: request.overwrite });
+    },
+
+    mergeMemoryNamespaces(request) {
+      return stateStore.mergeMemoryNamespaces(request.sources, request.target);
+    },
+
+    async exportMemory(request) {
+      const entries = request.namespaces !== undefined && request.namespaces.length > 0
+        ? (await Promise.all(request.namespaces.map((namespace) => stateStore.listMemory(scopedNamespace(namespace, request.scope))))).flat()
+        : request.scope !== undefined
+          ? withinScope(await stateStore.listMemory(), request.scope)
+          : await stateStore.listMemory();
+      return writeMemoryArchive(request.basePath ?? basePath, request.path, entries, { includeEmbeddings: request.includeEmbeddings });
+    },
+
+    async importMemory(request) {
+      assertWritable(readOnly, 'Importing memory');
+      const archive = await readMemoryArchive(request.basePath ?? basePath, request.path);
+      const { copied, skipped } = await stateStore.importMemory(archive.entries, { mode: request.overwrite === true ? 'overwrite' : 'newer' });
+      return { path: archive.path, manifest: archive.manifest, imported: copied, skipped };
+    },
+
+    async pruneMemory(request) {
+      const dryRun = request?.dryRun === true;
+      if (!dryRun) {
+        assertWritable(readOnly, 'Pruning memory');
+      }
+      const config = await readMaintenanceConfig(request?.basePath ?? basePath);
+      const { evicted, policy } = await pruneMemoryEntries(stateStore, config, { dryRun, now: new Date() });
+      return { dryRun, policy, evicted };
+    },
+
+    async encryptMemory() {
+      assertWritable(readOnly, 'Encrypting memory');
+      if (config.stateStore === undefined && memoryCipherFor(basePath) === undefined) {
+        throw new Error('Memory encryption is off. Set "memory": { "encryption": "env" } or "keychain" in .automatosx/config.json first.');
+      }
+      return stateStore.encryptMemory();
     },
 
-    getMemory(key, namespace) {
-      return stateStore.getMemory(key, namespace);
+    async checkMemorySources(request = {}) {
+      if (request.invalidate === true) {
+        assertWritable(readOnly, 'Invalidating memory');
+      }
+      const entries = request.namespace !== undefined || request.scope === undefined
+        ? await stateStore.listMemory(scopedNamespace(request.namespace, request.scope))
+        : withinScope(await stateStore.listMemory(), request.scope);
+      const { checked, stale } = await findStaleMemory(request.basePath ?? basePath, entries);
+      let invalidated = 0;
+      if (request.invalidate === true) {
+        for (const entry of stale) {
+          if (await stateStore.deleteMemory(entry.key, entry.namespace)) {
+            invalidated += 1;
+          }
+        }
+      }
+      return { checked, stale, invalidated };
     },
 
-    searchMemory(query, namespace) {
-      return stateStore.searchMemory(query, namespace);
+    storeFact({ scope, ...input }) {
+      return stateStore.storeFact({ ...input, namespace: scopedNamespace(input.namespace, scope) });
     },
 
-    deleteMemory(key, namespace) {
-      return stateStore.deleteMemory(key, namespace);
+    async queryFacts({ scope, ...query } = {}) {
+      if (query.namespace !== undefined || scope === undefined) {
+        return stateStore.queryFacts({ ...query, namespace: scopedNamespace(query.namespace, scope) });
+      }
+      const prefix = scopedNamespace('', scope)!;
+      const facts = (await stateStore.queryFacts({ ...query, limit: undefined })).filter((fact) => fact.namespace?.startsWith(prefix) === true);
+      return query.limit !== undefined ? facts.slice(0, query.limit) : facts;
     },
 
-    listMemory(namespace) {
-      return stateStore.listMemory(namespace);
+    deleteFact(factId) {
+      return stateStore.deleteFact(factId);
     },
 
     storeSemantic(entry) {
@@ -1937,6 +4102,22 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
       return buildFeedbackAdjustment(agentId, entries);
     },
 
+    getUsageReport(options) {
+      return usageTracker.report(options);
+    },
+
+    async getProviderCacheStats(options = {}) {
+      return responseCache.stats(readProviderCacheSettings(await readWorkspaceConfig(options.basePath ?? basePath)));
+    },
+
+    clearProviderCache() {
+      return responseCache.clear();
+    },
+
+    listProviders(root) {
+      return collectProviderIds(root ?? basePath);
+    },
+
     async listAbilities(options) {
       return filterAbilities(BUILTIN_ABILITIES, options);
     },
@@ -2012,6 +4193,18 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
       return stateStore.getAgent(agentId);
     },
 
+    async resolveAgentProfile(agentId, root) {
+      const agent = await stateStore.getAgent(agentId);
+      return agent === undefined ? undefined : resolveAgentProfile(agent, (baseId) => stateStore.getAgent(baseId), root ?? basePath);
+    },
+
+    async suggestAgents(request = {}) {
+      if (request.dryRun !== true) {
+        assertWritable(readOnly, 'Writing suggested agents');
+      }
+      return suggestAgents({ basePath: request.basePath ?? basePath, dryRun: request.dryRun });
+    },
+
     listAgents() {
       return stateStore.listAgents();
     },
@@ -2024,14 +4217,129 @@ export function createSharedRuntimeService(config: SharedRuntimeConfig = {}): Sh
       return stateStore.listAgentCapabilities();
     },
 
-    createSession(entry) {
-      return stateStore.createSession(entry);
+    async createSession(entry) {
+      const { scope, memoryNamespace, basePath: root, ...stored } = entry;
+      const session = await stateStore.createSession(stored);
+      if (scope !== undefined || memoryNamespace !== undefined) {
+        await updateSessionThread(root ?? basePath, session.sessionId, (thread) => ({ ...thread, scope, memoryNamespace }));
+      }
+      return session;
     },
 
     getSession(sessionId) {
       return stateStore.getSession(sessionId);
     },
 
+    getSessionThread(sessionId, root) {
+      return readSessionThread(root ?? basePath, sessionId);
+    },
+
+    async resumeSession(request) {
+      const root = request.basePath ?? basePath;
+      const stored = await stateStore.getSession(request.sessionId);
+      if (stored === undefined) {
+        throw Object.assign(new Error(`Session "${request.sessionId}" does not exist.`), { code: SESSION_NOT_FOUND_CODE });
+      }
+      const thread = await readSessionThread(root, request.sessionId);
+      const agentId = request.agentId ?? thread.agentId;
+      if (request.task !== undefined && agentId === undefined) {
+        throw Object.assign(new Error(`No agent has run in session "${request.sessionId}" yet; name the agent to continue it with.`), { code: SESSION_AGENT_MISSING_CODE });
+      }
+      const session = stored.status === 'active' ? stored : await stateStore.reopenSession(request.sessionId);
+      if (request.task === undefined || agentId === undefined) {
+        return { session, thread };
+      }
+      const run = await this.runAgent({
+        agentId,
+        task: request.task,
+        sessionId: request.sessionId,
+        provider: request.provider,
+        model: request.model,
+        basePath: request.basePath,
+        surface: request.surface,
+        signal: request.signal,
+        approve: request.approve,
+        approvalPolicy: request.approvalPolicy,
+      });
+      return {
+        session: await stateStore.getSession(request.sessionId) ?? session,
+        thread: await readSessionThread(root, request.sessionId),
+        run,
+      };
+    },
+
+    async branchSession(request) {
+      const root = request.basePath ?? basePath;
+      const original = await stateStore.getSession(request.sessionId);
+      if (original === undefined) {
+        throw Object.assign(new Error(`Session "${request.sessionId}" does not exist.`), { code: SESSION_NOT_FOUND_CODE });
+      }
+      const branchId = request.branchId ?? randomUUID();
+      if (await stateStore.getSession(branchId) !== undefined) {
+        throw new Error(`Session "${branchId}" already exists.`);
+      }
+      const fork = branchSessionThread(await readSessionThread(root, request.sessionId), branchId, request.turns);
+      const thread = await updateSessionThread(root, branchId, () => fork);
+      const session = await stateStore.createSession({
+        sessionId: branchId,
+        task: request.task ?? original.task,
+        initiator: original.initiator,
+        workspace: original.workspace,
+        metadata: { ...original.metadata, branchedFrom: original.sessionId },
+      });
+      return { session, thread };
+    },
+
+    async rewindSession(sessionId, turns, root) {
+      if (await stateStore.getSession(sessionId) === undefined) {
+        throw Object.assign(new Error(`Session "${sessionId}" does not exist.`), { code: SESSION_NOT_FOUND_CODE });
+      }
+      return updateSessionThread(root ?? basePath, sessionId, (thread) => ({ ...thread, turns: thread.turns.slice(0, Math.max(turns, 0)) }));
+    },
+
+    async exportSession(request) {
+      const root = request.basePath ?? basePath;
+      const session = await stateStore.getSession(request.sessionId);
+      if (session === undefined) {
+        throw Object.assign(new Error(`Session "${request.sessionId}" does not exist.`), { code: SESSION_NOT_FOUND_CODE });
+      }
+      const thread = await readSessionThread(root, request.sessionId);
+      const traceIds = [...new Set(thread.turns.flatMap((turn) => turn.traceId !== undefined ? [turn.traceId] : []))];
+      const sources = new Map<string, TranscriptSource>(await Promise.all(traceIds.map(async (traceId) => [traceId, {
+        trace: await traceStore.getTrace(traceId),
+        usage: await stateStore.listUsage({ traceId }),
+        calls: await readReplayLog(root, traceId).then((log) => log.calls, () => []),
+      }] as const)));
+      const format = request.format ?? 'md';
+      const transcript = buildSessionTranscript(session, thread, sources);
+      return { format, transcript, content: renderSessionTranscript(transcript, format) };
+    },
+
+    async addScratchpadTasks(sessionId, tasks) {
+      await requireScratchpadSession(stateStore, sessionId);
+      return stateStore.addScratchpadTasks(sessionId, tasks);
+    },
+
+    async getScratchpad(sessionId) {
+      await requireScratchpadSession(stateStore, sessionId);
+      return stateStore.listScratchpad(sessionId);
+    },
+
+    async claimScratchpadTask(sessionId, claim) {
+      await requireScratchpadSession(stateStore, sessionId);
+      return stateStore.claimScratchpadTask(sessionId, claim);
+    },
+
+    async postScratchpadResult(sessionId, post) {
+      await requireScratchpadSession(stateStore, sessionId);
+      return stateStore.postScratchpadResult(sessionId, post);
+    },
+
+    async releaseScratchpadTask(sessionId, taskId, claimant) {
+      await requireScratchpadSession(stateStore, sessionId);
+      return stateStore.releaseScratchpadTask(sessionId, taskId, claimant);
+    },
+
     listSessions() {
       return stateStore.listSessions();
     },
@@ -2074,6 +4382,13 @@ function normalizeProviders(explicitProviders: string[] | undefined, providerOve
   return ['claude', 'openai', 'gemini'];
 }
 
+/**
+ * Workflows that ship with the runtime; a workspace workflow with the same id overrides them.
+ */
+export function getBuiltinWorkflow(workflowId: string) {
+  return workflowId === MAINTENANCE_WORKFLOW.workflowId ? MAINTENANCE_WORKFLOW : undefined;
+}
+
 function resolveWorkflowDir(
   explicitWorkflowDir: string | undefined,
   requestBasePath: string | undefined,
@@ -2506,7 +4821,14 @@ function resolveAgentTask(
   return `Run the ${agent.name} agent.`;
 }
 
-function resolveAgentSystemPrompt(agent: AgentEntry, metadata: Record<string, unknown>): string {
+/**
+ * The instructions an agent runs under: its profile's `systemPrompt` (or `instructions`), else a
+ * persona built from its name and capabilities.
+ */
+export function resolveAgentSystemPrompt(
+  agent: AgentEntry,
+  metadata: Record<string, unknown> = isRecord(agent.metadata) ? agent.metadata : {},
+): string {
   const explicit = asOptionalString(metadata.systemPrompt) ?? asOptionalString(metadata.instructions);
   if (explicit !== undefined && explicit.trim().length > 0) {
     return explicit;
@@ -2518,11 +4840,49 @@ function resolveAgentSystemPrompt(agent: AgentEntry, metadata: Record<string, un
   return `You are ${agent.name} (${agent.agentId}). ${capabilityLine} Respond concisely and focus on the task.`;
 }
 
+/**
+ * The agent's system prompt with its template rendered, when it is one: the variables are the
+ * agent (`agent.id`, `agent.name`, `agent.capabilities`, `agent.team`), `task`, `input`, `date`
+ * and the profile's `variables`, and includes are ability files or built-in abilities. Missing
+ * variables and cut budget sections come back as warnings.
+ */
+async function renderAgentSystemPrompt(
+  agent: AgentEntry,
+  metadata: Record<string, unknown>,
+  task: string,
+  input: Record<string, unknown> | undefined,
+  workspacePath: string,
+  abilities: PackAbility[] = [],
+): Promise<{ systemPrompt: string; template?: Omit<RenderedPromptTemplate, 'text'>; warnings: string[] }> {
+  const source = resolveAgentSystemPrompt(agent, metadata);
+  // The abilities of the profile's packs follow its own instructions.
+  const withAbilities = (text: string) => [text, describePackAbilities(abilities)].filter((part) => part !== undefined).join('\n\n');
+  if (!source.includes('{{')) {
+    return { systemPrompt: withAbilities(source), warnings: [] };
+  }
+  const { text, ...template } = await renderPromptTemplate(source, {
+    ...(isRecord(metadata.variables) ? metadata.variables : {}),
+    agent: { id: agent.agentId, name: agent.name, capabilities: agent.capabilities, team: asOptionalString(metadata.team) },
+    task,
+    input,
+    date: new Date().toISOString().slice(0, 10),
+  }, createAbilityIncludeResolver(workspacePath, Object.fromEntries(BUILTIN_ABILITIES.map((ability) => [ability.abilityId, ability.content]))));
+  return {
+    systemPrompt: withAbilities(text),
+    template,
+    warnings: [
+      ...(template.missingVariables.length > 0 ? [`The prompt template of agent "${agent.agentId}" uses variables with no value: ${template.missingVariables.join(', ')}.`] : []),
+      ...template.truncations.map((cut) => `A {{#budget ${cut.budgetTokens}}} section of agent "${agent.agentId}" was cut from about ${cut.originalTokens} tokens.`),
+    ],
+  };
+}
+
 function buildAgentPrompt(
   agent: AgentEntry,
   task: string,
   input: Record<string, unknown> | undefined,
   metadata: Record<string, unknown>,
+  workspace?: AgentWorkspace,
 ): string {
   const team = asOptionalString(metadata.team);
   const sections = [
@@ -2531,6 +4891,7 @@ function buildAgentPrompt(
     agent.capabilities.length > 0 ? `Capabilities: ${agent.capabilities.join(', ')}` : undefined,
     team !== undefined ? `Team: ${team}` : undefined,
     input !== undefined ? `Input:\n${JSON.stringify(input, null, 2)}` : undefined,
+    ...(workspace === undefined ? [] : describeAgentWorkspace(workspace)),
   ];
 
   return sections.filter((value): value is string => value !== undefined && value.length > 0).join('\n\n');
@@ -2785,14 +5146,6 @@ function compareParallelTasks(left: RuntimeParallelTask, right: RuntimeParallelT
   return rightPriority - leftPriority || left.taskId.localeCompare(right.taskId);
 }
 
-function chunkArray<T>(items: T[], chunkSize: number): T[][] {
-  const chunks: T[][] = [];
-  for (let index = 0; index < items.length; index += chunkSize) {
-    chunks.push(items.slice(index, index + chunkSize));
-  }
-  return chunks;
-}
-
 function aggregateParallelResults(
   results: RuntimeParallelTaskResult[],
   mode: 'list' | 'merge',
@@ -2909,22 +5262,33 @@ function resolveTraceDuration(trace: TraceRecord): number {
 
 function createPromptExecutor(
   providerBridge: ReturnType<typeof createProviderBridge>,
-  provider?: string,
-  model?: string,
+  options: {
+    provider?: string;
+    model?: string;
+    traceId?: string;
+    noCache?: boolean;
+    signal?: AbortSignal;
+    onText?: (text: string, stepId?: string) => void;
+    onFailover?: (failover: ProviderFailover, stepId?: string) => void;
+  },
 ) {
+  const { provider, model, traceId, noCache, signal, onText, onFailover } = options;
   return {
     getDefaultProvider: () => provider ?? 'claude',
     execute: async (request: {
       prompt: string;
+      stepId?: string;
       systemPrompt?: string;
       provider?: string;
       model?: string;
       maxTokens?: number;
       temperature?: number;
       timeout?: number;
+      consensus?: Partial<ConsensusOptions>;
+      outputSchema?: JsonSchema;
     }) => {
       const resolvedProvider = request.provider ?? provider ?? 'claude';
-      const bridgeResult = await providerBridge.executePrompt({
+      const executionRequest = {
         provider: resolvedProvider,
         prompt: request.prompt,
         systemPrompt: request.systemPrompt,
@@ -2932,9 +5296,21 @@ function createPromptExecutor(
         maxTokens: request.maxTokens,
         temperature: request.temperature,
         timeoutMs: request.timeout,
-      });
+        traceId,
+        noCache,
+        signal,
+        onText: onText === undefined ? undefined : (text: string) => onText(text, request.stepId),
+      };
+      const bridgeResult = request.outputSchema !== undefined
+        ? await providerBridge.executeStructured(executionRequest, request.outputSchema, request.consensus)
+        : request.consensus === undefined
+          ? await providerBridge.executePrompt(executionRequest)
+          : await providerBridge.executeConsensus(executionRequest, request.consensus);
 
       if (bridgeResult.type === 'response' || bridgeResult.type === 'failure') {
+        for (const failover of bridgeResult.response.failovers ?? []) {
+          onFailover?.(failover, request.stepId);
+        }
         return bridgeResult.response;
       }
 
@@ -2958,19 +5334,47 @@ function createPromptExecutor(
   };
 }
 
-function createToolExecutor() {
+function createToolExecutor(
+  readOnly: boolean,
+  runtimeTools: Record<string, (args: Record<string, unknown>) => Promise<unknown>> = {},
+) {
   return {
     isToolAvailable: (toolName: string) => toolName.trim().length > 0,
     getAvailableTools: () => ['*'],
-    execute: async (toolName: string, args: Record<string, unknown>) => ({
-      success: true,
-      output: {
-        toolName,
-        args,
-        mode: 'shared-runtime-simulated',
-      },
-      durationMs: 0,
-    }),
+    execute: async (toolName: string, args: Record<string, unknown>) => {
+      if (readOnly && isMutatingTool(toolName)) {
+        return {
+          success: false,
+          error: readOnlyViolation(`Tool "${toolName}"`).message,
+          errorCode: READ_ONLY_ERROR_CODE,
+          retryable: false,
+          durationMs: 0,
+        };
+      }
+      const runtimeTool = runtimeTools[toolName];
+      if (runtimeTool !== undefined) {
+        const startedAt = Date.now();
+        try {
+          return { success: true, output: await runtimeTool(args), durationMs: Date.now() - startedAt };
+        } catch (error) {
+          return {
+            success: false,
+            error: error instanceof Error ? error.message : String(error),
+            retryable: false,
+            durationMs: Date.now() - startedAt,
+          };
+        }
+      }
+      return {
+        success: true,
+        output: {
+          toolName,
+          args,
+          mode: 'shared-runtime-simulated',
+        },
+        durationMs: 0,
+      };
+    },
   };
 }
 
@@ -3145,6 +5549,7 @@ function createDiscussionCoordinator(config: {
               prompt,
               temperature: request.config.temperature,
               timeoutMs: request.config.providerTimeout > 0 ? request.config.providerTimeout : undefined,
+              traceId: request.traceId,
             });
 
             if (bridgeResult.type === 'response' && bridgeResult.response.success) {
@@ -3448,23 +5853,6 @@ function yieldToEventLoop(): Promise<void> {
   });
 }
 
-async function readWorkspaceConfig(basePath: string): Promise<Record<string, unknown>> {
-  const configPath = join(basePath, '.automatosx', 'config.json');
-  try {
-    const raw = await readFile(configPath, 'utf8');
-    const parsed = JSON.parse(raw) as unknown;
-    return isRecord(parsed) ? parsed : {};
-  } catch {
-    return {};
-  }
-}
-
-async function writeWorkspaceConfig(basePath: string, config: Record<string, unknown>): Promise<void> {
-  const configPath = join(basePath, '.automatosx', 'config.json');
-  await mkdir(join(basePath, '.automatosx'), { recursive: true });
-  await writeFile(configPath, `${JSON.stringify(config, null, 2)}\n`, 'utf8');
-}
-
 function getValueAtPath(config: Record<string, unknown>, path: string): unknown {
   const parts = path.split('.').filter((part) => part.length > 0);
   let current: unknown = config;
@@ -3494,6 +5882,14 @@ function setValueAtPath(config: Record<string, unknown>, path: string, value: un
   current[parts[parts.length - 1]!] = value;
 }
 
+// What a workflow posts to the scratchpad task it claimed.
+function describeWorkflowOutput(output: unknown): string {
+  if (output === undefined) {
+    return 'The workflow completed.';
+  }
+  return typeof output === 'string' ? output : JSON.stringify(output);
+}
+
 function asOptionalString(value: unknown): string | undefined {
   return typeof value === 'string' && value.length > 0 ? value : undefined;
 }
@@ -3502,9 +5898,264 @@ function isRecord(value: unknown): value is Record<string, unknown> {
   return value !== null && typeof value === 'object' && !Array.isArray(value);
 }
 
+// Every namespace inside a scope, when no single namespace is named.
+function withinScope(entries: MemoryEntry[], scope: MemoryScope): MemoryEntry[] {
+  const prefix = scopedNamespace('', scope)!;
+  return prefix === '' ? entries : entries.filter((entry) => entry.namespace?.startsWith(prefix) === true);
+}
+
 export type {
   ReviewFinding,
   ReviewFocus,
   ReviewSeverity,
   RuntimeReviewResponse,
 } from './review.js';
+export type {
+  BuiltinCodeLanguage,
+  CodeFileIndex,
+  CodeLanguage,
+  CodeLanguageExtractor,
+  CodeSymbol,
+  CodeSymbolKind,
+  CodeSymbolLocation,
+  BorrowMode,
+  CodeTestFilter,
+  ConcurrencyPrimitive,
+  GrammarRegistration,
+  GrammarRuntime,
+  LanguageRegistry,
+  OwnershipParameter,
+  OwnershipSummary,
+  PositionMapper,
+  ReceiverMode,
+  RuntimeCodeIndexResponse,
+  RustCfgOptions,
+  SourcePosition,
+  SourceSpan,
+  SymbolAnnotation,
+} from './code-intel/index.js';
+export {
+  BORROW_MODES,
+  CACHE_DIR_ENV_VAR,
+  CONCURRENCY_PRIMITIVES,
+  createLanguageRegistry,
+  createPositionMapper,
+  createQueryExtractor,
+  GRAMMAR_RUNTIME_ENV_VAR,
+  isCfgActive,
+  isTestFile,
+  loadWorkspaceLanguageRegistry,
+  parseCodeSource,
+} from './code-intel/index.js';
+export type {
+  PreCommitCheck,
+  PreCommitCheckStatus,
+  PreCommitIssue,
+  PreCommitMode,
+  RuntimeHookInstallResponse,
+  RuntimePreCommitResponse,
+} from './git-hooks.js';
+export {
+  CONFIG_ENV_PREFIX,
+  CONFIG_FLAGS_ENV_VAR,
+  CONFIG_LAYERS,
+  CONFIG_PROFILE_ENV_VAR,
+  SYSTEM_CONFIG_ENV_VAR,
+  USER_CONFIG_ENV_VAR,
+  explainConfigKey,
+  loadLayeredConfig,
+  validateLayeredConfig,
+} from './config-layers.js';
+export type { ConfigExplanation, ConfigLayer, ConfigLayerName, ConfigValidation, LayeredConfig } from './config-layers.js';
+export { formatConfigIssue, migrateWorkspaceConfig, validateWorkspaceConfig } from './config-schema.js';
+export { WORKSPACE_CONFIG_VERSION } from '@defai.digital/contracts';
+export type { ConfigIssue, ConfigMigrationResult } from './config-schema.js';
+export type { RuntimeDebugBundleResponse } from './debug-bundle.js';
+export { findExecutable } from './environment-doctor.js';
+export type { EnvironmentCheck, EnvironmentCheckStatus, RuntimeEnvironmentReport } from './environment-doctor.js';
+export type { IndexServerHandle, IndexServerStatus } from './index-server.js';
+export type { IndexWatcher, IndexWatcherConfig, IndexWatchUpdate, SymbolChangeEvent, SymbolChangeType } from './index-watcher.js';
+export { SYMBOL_CHANGE_TYPES } from './index-watcher.js';
+export type { ProjectQueryPlan, ProjectQueryReference, RuntimeProjectAnswer } from './project-query.js';
+export type { ImplementationScaffold } from './code-intel/implementation-scaffold.js';
+export type { ImpactedTest, RuntimeTestImpact, TestImpactChangedSymbol, TestImpactRun } from './code-intel/test-impact.js';
+export { parseTestFailures, type TestFailure, type TestFramework } from './code-intel/test-failures.js';
+export type { RuntimeTestFixReport, RuntimeTestFixRequest, TestFixEdit, TestFixIteration } from './test-fix.js';
+export type { ReviewAnnotation, RuntimeReviewAnnotateRequest, RuntimeReviewAnnotationReport, SymbolReview } from './review-annotate.js';
+export type { AffectedSymbol, RuntimeChangeImpact, SuggestedReviewer } from './code-intel/change-impact.js';
+export type { UnsafeFinding, UnsafeFindingKind, UnsafeReport } from './code-intel/rust-unsafe.js';
+export { UNSAFE_FINDING_KINDS } from './code-intel/rust-unsafe.js';
+export type { RuntimeUnreferencedSymbols, UnreferencedSymbol } from './code-intel/dead-code.js';
+export type { ReferenceResolution, RuntimeSymbolReferences, SymbolReference } from './code-intel/references.js';
+export { getCodeSymbolId, REFERENCE_RESOLUTIONS } from './code-intel/references.js';
+export type { FileSkeleton, SignatureView } from './code-intel/signatures.js';
+export type { SymbolBody } from './code-intel/symbol-body.js';
+export type { CodeOutline, FileOutline, OutlineNode } from './code-intel/outline.js';
+export type { RepoMap } from './code-intel/repo-map.js';
+export type { GraphFormat, GraphKind, RuntimeGraphExport } from './code-intel/graph-export.js';
+export { GRAPH_FORMATS, GRAPH_KINDS } from './code-intel/graph-export.js';
+export type { CallGraph, CallGraphEdge, CallGraphNode } from './code-intel/references.js';
+export { CALL_HIERARCHY_DIRECTIONS, type CallHierarchy, type CallHierarchyDirection, type CallHierarchyItem } from './code-intel/call-hierarchy.js';
+export type { SymbolSearch, SymbolSearchHit, SymbolSearchQuery, SymbolSearchResult } from './code-intel/symbol-search.js';
+export { createSymbolSearch, describeSymbolHits, fuzzyMatch, parseSymbolSearchQuery } from './code-intel/symbol-search.js';
+export type { RuntimeTextSearch, TextIndexFile, TextIndexSnapshot, TextSearchMatch, TextSearchRequest } from './code-intel/text-search.js';
+export { buildTextIndex, getTextIndexPath, requiredLiterals } from './code-intel/text-search.js';
+export type { HybridSearchResult, HybridSearchSource, RuntimeHybridSearch, SearchService } from './hybrid-search.js';
+export { createSearchService } from './hybrid-search.js';
+export type { CodeChunk } from './code-intel/chunks.js';
+export { chunkCodeIndex } from './code-intel/chunks.js';
+export type { MemoryArchiveManifest, RuntimeMemoryExportResponse, RuntimeMemoryImportResponse } from './memory-archive.js';
+export { MEMORY_ARCHIVE_FORMAT, MEMORY_ARCHIVE_VERSION, readMemoryArchive, writeMemoryArchive } from './memory-archive.js';
+export type { MemoryDedupAction, MemoryDedupPolicy, MemoryDuplicate, RuntimeMemoryStoreResponse } from './memory-dedup.js';
+export { DEFAULT_DEDUP_THRESHOLD, MEMORY_DEDUP_ACTIONS, mergeMemoryValues } from './memory-dedup.js';
+export type { MemoryStaleReason, RuntimeMemoryStaleResponse, StaleMemoryEntry } from './memory-provenance.js';
+export { findStaleMemory, hashMemorySource } from './memory-provenance.js';
+export { createMemoryKey, decodeMemoryKey, MEMORY_KEY_ENV_VAR, MEMORY_KEY_SOURCES, memoryCipherFor } from './memory-encryption.js';
+export type { Embedder, EmbedderConfig, EmbeddingBackend, RuntimeEmbeddingResponse } from './embeddings.js';
+export { createEmbedder, EMBEDDING_BACKENDS, EMBEDDINGS_NAMESPACE, embedCodeChunks, readEmbedderConfig } from './embeddings.js';
+export type {
+  MaintenanceConfig,
+  MaintenanceTaskId,
+  MaintenanceTaskResult,
+  ProviderHealth,
+  RuntimeMaintenanceResponse,
+  RuntimeMemoryPruneResponse,
+} from './maintenance.js';
+export { MAINTENANCE_TASKS } from './maintenance.js';
+export type {
+  ProviderApi,
+  ProviderModelList,
+  ProviderResolutionDetails,
+  ProviderTool,
+  ProviderToolCall,
+} from './provider-bridge.js';
+export type { OllamaModel } from './provider-ollama.js';
+export type { ProviderPromptAdapter, ProviderPromptDialect } from './provider-prompt.js';
+export { adaptProviderPrompt, PROVIDER_PROMPT_DIALECTS, resolvePromptDialect } from './provider-prompt.js';
+export type { ProviderCacheHit, ProviderCacheSettings, ProviderCacheStats } from './provider-cache.js';
+export { PROVIDER_CACHE_NAMESPACE } from './provider-cache.js';
+export type { ProviderCapabilities } from './provider-capabilities.js';
+export { resolveProviderCapabilities } from './provider-capabilities.js';
+export type { ProviderQuotaStatus, ProviderQuotaWindowStatus } from './provider-quota.js';
+export type { ConsensusOptions, ConsensusStrategy, ProviderConsensus } from './provider-consensus.js';
+export { MAX_CONSENSUS_PROVIDERS, MIN_CONSENSUS_PROVIDERS } from './provider-consensus.js';
+export type { ProviderFailover } from './provider-failover.js';
+export type { ProviderHealthQuota, ProviderHealthState, ProviderHealthStatus } from './provider-health.js';
+export type { ProviderRateLimitStatus } from './provider-rate-limit.js';
+export type { ProviderModelPricing, ProviderRoutingOptions, ProviderRoutingPolicy } from './provider-routing.js';
+export { DEFAULT_MODEL_PRICING, PROVIDER_ROUTING_POLICIES } from './provider-routing.js';
+export { isMutatingTool, isReadOnlyEnv, READ_ONLY_ENV_VAR, READ_ONLY_ERROR_CODE } from './read-only.js';
+export type { JsonSchema, StructuredOutput } from './structured-output.js';
+export { validateJsonSchema } from './structured-output.js';
+export { renderPromptTemplate, type PromptIncludeResolver, type RenderedPromptTemplate } from './prompt-template.js';
+export {
+  parsePipeline,
+  PIPELINE_ERROR_CODE,
+  type PipelineCheckpoint,
+  type PipelineDefinition,
+  type PipelineRetryPolicy,
+  type PipelineStage,
+  type PipelineStageResult,
+  type PipelineStageRun,
+  type PipelineStageStatus,
+} from './pipeline.js';
+export {
+  EVAL_RUNS_DIR,
+  EVAL_SUITE_INVALID_CODE,
+  parseEvalSuite,
+  type EvalAssertion,
+  type EvalAssertionResult,
+  type EvalCase,
+  type EvalCaseResult,
+  type EvalRunReport,
+  type EvalSuite,
+  type EvalVariantSummary,
+} from './eval-harness.js';
+export {
+  REPLAY_DIR,
+  REPLAY_DIVERGED_CODE,
+  REPLAY_NOT_FOUND_CODE,
+  type ReplayCall,
+  type ReplayDivergence,
+  type ReplayEdit,
+  type ReplayRun,
+  type ReplaySummary,
+  type ReplayToolCall,
+} from './run-replay.js';
+export {
+  SESSION_AGENT_MISSING_CODE,
+  SESSION_ID_INVALID_CODE,
+  SESSION_NOT_FOUND_CODE,
+  SESSION_THREAD_DIR,
+  type SessionThread,
+  type SessionTurn,
+} from './session-threads.js';
+export { NEXT_SCRATCHPAD_TASK, SCRATCHPAD_NO_OPEN_TASK_CODE } from './session-scratchpad.js';
+export type { ScratchpadClaim, ScratchpadPost, ScratchpadTask, ScratchpadTaskInput, ScratchpadTaskStatus } from '@defai.digital/state-store';
+export {
+  TRANSCRIPT_FORMATS,
+  type SessionTranscript,
+  type TranscriptEdit,
+  type TranscriptFormat,
+  type TranscriptRun,
+  type TranscriptToolCall,
+} from './session-transcript.js';
+export {
+  createWorkspaceCheckpoint,
+  diffWorkspaceCheckpoint,
+  restoreWorkspaceCheckpoint,
+  type WorkspaceChange,
+  type WorkspaceCheckpoint,
+  type WorkspaceRestore,
+} from './workspace-checkpoint.js';
+export {
+  BUDGET_EXCEEDED_CODE,
+  BUDGET_INVALID_CODE,
+  parseDurationMs,
+  parseRunBudget,
+  type BudgetConsumption,
+  type BudgetReport,
+  type RunBudget,
+} from './run-budget.js';
+export {
+  ABILITY_PACKS_DIR,
+  AGENT_PROFILE_INVALID_CODE,
+  type AbilityPackRef,
+  type AgentProfileLayers,
+  type PackAbility,
+  type ResolvedAgentProfile,
+} from './agent-profiles.js';
+export {
+  SUGGESTED_AGENTS_DIR,
+  type AgentSuggestResponse,
+  type DetectedFramework,
+  type SuggestedAgent,
+} from './agent-suggest.js';
+export {
+  APPROVAL_DENIED_CODE,
+  APPROVAL_POLICY_FILE,
+  APPROVALS_INVALID_CODE,
+  describeApproval,
+  type ApprovalGates,
+  type ApprovalRecord,
+  type ApprovalRequest,
+  type Approver,
+} from './approval-gates.js';
+export {
+  AGENT_PERMISSION_DENIED_CODE,
+  AGENT_PERMISSIONS_ENV_VAR,
+  AGENT_PERMISSIONS_INVALID_CODE,
+  readAgentPermissions,
+  type AgentPermissions,
+  type AgentPermissionViolation,
+} from './agent-permissions.js';
+export {
+  MAX_DELEGATION_DEPTH,
+  parseHandoffContract,
+  type AgentHandoff,
+  type HandoffBudget,
+  type HandoffContract,
+} from './agent-delegation.js';
+export { assembleContext, CONTEXT_TRIM_STRATEGIES, type AssembledContext, type ContextBudget, type ContextBudgetReport, type ContextCut, type ContextInput, type ContextTrimStrategy } from './context-budget.js';
+export type { UsageGrouping, UsageReport, UsageReportGroup, UsageReportOptions, UsageTotals } from './usage-tracker.js';
+export { USAGE_GROUPINGS } from './usage-tracker.js';
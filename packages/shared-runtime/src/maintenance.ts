@@ -0,0 +1,386 @@
+import { mkdir, readFile, readdir, rename, rm, stat, writeFile } from 'node:fs/promises';
+import { dirname, join, relative } from 'node:path';
+import type { MemoryEviction, MemoryRetentionPolicy, StateStore } from '@defai.digital/state-store';
+import { readWorkspaceConfig } from './config-layers.js';
+import type { createProviderBridge } from './provider-bridge.js';
+import { asRecord } from './records.js';
+
+export const MAINTENANCE_TASKS = ['index', 'memory', 'logs', 'providers', 'caches'] as const;
+
+export type MaintenanceTaskId = typeof MAINTENANCE_TASKS[number];
+
+export interface MaintenanceConfig {
+  /** `ax maintain --if-due` skips the run when the last one finished less than this many hours ago. */
+  intervalHours: number;
+  /** Memory entries not updated for this many days are deleted; 0 keeps them forever. */
+  memoryMaxAgeDays: number;
+  /** Past this many memory entries the least recently used are deleted; 0 sets no cap. */
+  memoryMaxEntries: number;
+  /** Past this many bytes of memory values and embeddings the least recently used are deleted; 0 sets no cap. */
+  memoryMaxBytes: number;
+  /** Days after which an unused entry's importance has halved. */
+  memoryImportanceHalfLifeDays: number;
+  /** Entries stored with an importance are deleted once it decays below this. */
+  memoryMinImportance: number;
+  logMaxBytes: number;
+  logGenerations: number;
+  /** Logs and records of finished detached runs older than this many days are deleted. */
+  logMaxAgeDays: number;
+  /** Debug bundles, replay logs and dry-run workflow previews older than this many days are deleted. */
+  cacheMaxAgeDays: number;
+}
+
+export interface MaintenanceTaskResult {
+  task: MaintenanceTaskId;
+  success: boolean;
+  summary: string;
+  details?: Record<string, unknown>;
+  error?: string;
+  durationMs: number;
+}
+
+export interface RuntimeMaintenanceResponse {
+  traceId?: string;
+  success: boolean;
+  dryRun: boolean;
+  /** Set when `ifDue` skipped the run; the previous run's time is in `lastRunAt`. */
+  skipped?: string;
+  lastRunAt?: string;
+  tasks: MaintenanceTaskResult[];
+}
+
+export interface RuntimeMemoryPruneResponse {
+  dryRun: boolean;
+  policy: MemoryRetentionPolicy;
+  /** Entries deleted, or that would be with `dryRun`. */
+  evicted: MemoryEviction[];
+}
+
+export interface MaintenanceState {
+  lastRunAt: string;
+  traceId?: string;
+  tasks: MaintenanceTaskResult[];
+}
+
+export interface ProviderHealth {
+  provider: string;
+  status: 'ok' | 'unavailable' | 'exhausted';
+  command?: string;
+  commandPath?: string;
+  message?: string;
+}
+
+const DEFAULT_MAINTENANCE_CONFIG: MaintenanceConfig = {
+  intervalHours: 24,
+  memoryMaxAgeDays: 90,
+  memoryMaxEntries: 0,
+  memoryMaxBytes: 0,
+  memoryImportanceHalfLifeDays: 30,
+  memoryMinImportance: 0.1,
+  logMaxBytes: 5 * 1024 * 1024,
+  logGenerations: 3,
+  logMaxAgeDays: 14,
+  cacheMaxAgeDays: 30,
+};
+const DAY_MS = 86_400_000;
+const EVICTION_LABELS: Record<MemoryEviction['reason'], string> = {
+  expired: 'expired',
+  age: 'past the maximum age',
+  importance: 'no longer important',
+  capacity: 'least recently used over the size cap',
+};
+const DEFAULT_PROVIDERS = ['claude', 'gemini', 'codex', 'grok'];
+
+/**
+ * Built-in `maintain` workflow. A `maintain` workflow in the workspace's workflow directory
+ * takes precedence, so projects can drop or reorder steps.
+ */
+export const MAINTENANCE_WORKFLOW = {
+  workflowId: 'maintain',
+  version: '1.0.0',
+  name: 'Workspace Maintenance',
+  description: 'Refresh the code index, expire old memory, rotate logs, re-check providers, and prune caches.',
+  category: 'maintenance',
+  steps: MAINTENANCE_TASKS.map((task) => ({
+    stepId: task,
+    type: 'tool' as const,
+    config: { toolName: `maintain.${task}`, toolInput: {} },
+  })),
+};
+
+export function getMaintenanceStatePath(basePath: string): string {
+  return join(basePath, '.automatosx', 'runtime', 'maintenance.json');
+}
+
+export async function readMaintenanceConfig(basePath: string): Promise<MaintenanceConfig> {
+  const maintenance = asRecord((await readWorkspaceConfig(basePath)).maintenance);
+  const config = { ...DEFAULT_MAINTENANCE_CONFIG };
+  for (const key of Object.keys(DEFAULT_MAINTENANCE_CONFIG) as Array<keyof MaintenanceConfig>) {
+    const value = maintenance?.[key];
+    if (typeof value === 'number' && Number.isFinite(value) && value >= 0) {
+      config[key] = value;
+    }
+  }
+  return config;
+}
+
+export async function readMaintenanceState(basePath: string): Promise<MaintenanceState | undefined> {
+  try {
+    const parsed = JSON.parse(await readFile(getMaintenanceStatePath(basePath), 'utf8')) as Partial<MaintenanceState>;
+    return typeof parsed.lastRunAt === 'string' ? parsed as MaintenanceState : undefined;
+  } catch {
+    return undefined;
+  }
+}
+
+export async function writeMaintenanceState(basePath: string, state: MaintenanceState): Promise<void> {
+  const statePath = getMaintenanceStatePath(basePath);
+  await mkdir(dirname(statePath), { recursive: true });
+  await writeFile(statePath, `${JSON.stringify(state, null, 2)}\n`, 'utf8');
+}
+
+export function isMaintenanceDue(state: MaintenanceState | undefined, config: MaintenanceConfig, now: Date): boolean {
+  return state === undefined || now.getTime() - Date.parse(state.lastRunAt) >= config.intervalHours * 3_600_000;
+}
+
+export function memoryRetentionPolicy(config: MaintenanceConfig): MemoryRetentionPolicy {
+  return {
+    maxAgeDays: config.memoryMaxAgeDays,
+    maxEntries: config.memoryMaxEntries,
+    maxBytes: config.memoryMaxBytes,
+    importanceHalfLifeDays: config.memoryImportanceHalfLifeDays,
+    minImportance: config.memoryMinImportance,
+  };
+}
+
+/**
+ * Deletes expired memory, entries past the maximum age or whose importance decayed away, and the
+ * least recently used entries beyond the size caps.
+ */
+export async function pruneMemoryEntries(
+  stateStore: StateStore,
+  config: MaintenanceConfig,
+  options: { dryRun: boolean; now: Date },
+): Promise<{ deleted: string[]; evicted: MemoryEviction[]; policy: MemoryRetentionPolicy }> {
+  const policy = memoryRetentionPolicy(config);
+  const evicted = await stateStore.pruneMemory(policy, options);
+  return {
+    deleted: evicted.map((entry) => (entry.namespace !== undefined ? `${entry.namespace}/${entry.key}` : entry.key)),
+    evicted,
+    policy,
+  };
+}
+
+export function summarizeMemoryEvictions(evicted: MemoryEviction[]): string {
+  const counts = new Map<MemoryEviction['reason'], number>();
+  for (const entry of evicted) {
+    counts.set(entry.reason, (counts.get(entry.reason) ?? 0) + 1);
+  }
+  return [...counts].map(([reason, count]) => `${count} ${EVICTION_LABELS[reason]}`).join(', ');
+}
+
+/**
+ * Rotates oversized logs under `.automatosx/logs` and `.automatosx/runtime/runs` (`x.log` becomes
+ * `x.log.1`) and deletes the logs and records of detached runs that finished long ago. Logs of
+ * runs that are still alive are left alone.
+ */
+export async function rotateLogs(
+  basePath: string,
+  config: MaintenanceConfig,
+  options: { dryRun: boolean; now: Date },
+): Promise<Record<string, unknown>> {
+  const rotated: string[] = [];
+  const removed: string[] = [];
+  const runsDir = join(basePath, '.automatosx', 'runtime', 'runs');
+  const cutoff = options.now.getTime() - config.logMaxAgeDays * DAY_MS;
+  const activeLogs = new Set<string>();
+
+  const runFiles = await listDir(runsDir);
+  for (const name of runFiles) {
+    if (!name.endsWith('.json')) {
+      continue;
+    }
+    const recordPath = join(runsDir, name);
+    const record = await readJson(recordPath);
+    const pid = typeof record?.pid === 'number' ? record.pid : undefined;
+    const logPath = typeof record?.logPath === 'string' ? record.logPath : join(runsDir, `${name.slice(0, -'.json'.length)}.log`);
+    if (pid !== undefined && isProcessAlive(pid)) {
+      activeLogs.add(logPath);
+      continue;
+    }
+    const startedAt = typeof record?.startedAt === 'string' ? Date.parse(record.startedAt) : Number.NaN;
+    if (config.logMaxAgeDays > 0 && Number.isFinite(startedAt) && startedAt < cutoff) {
+      const generations = runFiles.filter((candidate) => candidate.startsWith(`${name.slice(0, -'.json'.length)}.log`));
+      for (const target of [name, ...generations]) {
+        removed.push(relativeToWorkspace(basePath, join(runsDir, target)));
+        if (!options.dryRun) {
+          await rm(join(runsDir, target), { force: true });
+        }
+      }
+    }
+  }
+
+  for (const dir of [join(basePath, '.automatosx', 'logs'), runsDir]) {
+    for (const name of await listDir(dir)) {
+      const logPath = join(dir, name);
+      if (!name.endsWith('.log') || activeLogs.has(logPath) || removed.includes(relativeToWorkspace(basePath, logPath))) {
+        continue;
+      }
+      const size = await fileSize(logPath);
+      if (size === undefined || size <= config.logMaxBytes) {
+        continue;
+      }
+      rotated.push(relativeToWorkspace(basePath, logPath));
+      if (!options.dryRun) {
+        await rotateFile(logPath, Math.max(1, config.logGenerations));
+      }
+    }
+  }
+
+  return { rotated, removed, maxBytes: config.logMaxBytes };
+}
+
+/**
+ * Re-resolves every known provider executor and checks its quota, without sending a prompt.
+ */
+export async function checkProviderHealth(
+  basePath: string,
+  providerBridge: ReturnType<typeof createProviderBridge>,
+): Promise<ProviderHealth[]> {
+  const providers = await collectProviderIds(basePath);
+  const quotas = await providerBridge.getQuotaStatus(providers);
+  const health: ProviderHealth[] = [];
+  for (const provider of providers) {
+    const resolution = await providerBridge.describeResolution(provider);
+    const quota = quotas.find((candidate) => candidate.provider === provider);
+    if (!resolution.resolved) {
+      health.push({ provider, status: 'unavailable', message: 'No executor configured.' });
+    } else if (resolution.api !== undefined && resolution.apiKeySet === false) {
+      health.push({ provider, status: 'unavailable', message: `${resolution.apiKeyEnv} is not set for the ${resolution.api} API.` });
+    } else if (resolution.api === undefined && resolution.commandPath === undefined) {
+      health.push({ provider, status: 'unavailable', command: resolution.command, message: `Command "${resolution.command}" was not found on PATH.` });
+    } else if (quota?.exhausted === true) {
+      health.push({ provider, status: 'exhausted', command: resolution.command, commandPath: resolution.commandPath, message: 'Quota exhausted for the current window.' });
+    } else {
+      health.push({ provider, status: 'ok', command: resolution.command, commandPath: resolution.commandPath });
+    }
+  }
+  return health;
+}
+
+/**
+ * Deletes regenerable output: debug bundles, replay logs and the artifacts of dry-run workflow previews.
+ */
+export async function pruneCaches(
+  basePath: string,
+  config: MaintenanceConfig,
+  options: { dryRun: boolean; now: Date },
+): Promise<Record<string, unknown>> {
+  const removed: string[] = [];
+  if (config.cacheMaxAgeDays === 0) {
+    return { removed, maxAgeDays: 0 };
+  }
+  const cutoff = options.now.getTime() - config.cacheMaxAgeDays * DAY_MS;
+  const automatosxDir = join(basePath, '.automatosx');
+
+  const debugDir = join(automatosxDir, 'debug');
+  for (const name of await listDir(debugDir)) {
+    const bundlePath = join(debugDir, name);
+    if (name.endsWith('.tar.gz') && (await modifiedAt(bundlePath) ?? Infinity) < cutoff) {
+      removed.push(relativeToWorkspace(basePath, bundlePath));
+    }
+  }
+
+  const replayDir = join(automatosxDir, 'replays');
+  for (const name of await listDir(replayDir)) {
+    const logPath = join(replayDir, name);
+    if (name.endsWith('.jsonl') && (await modifiedAt(logPath) ?? Infinity) < cutoff) {
+      removed.push(relativeToWorkspace(basePath, logPath));
+    }
+  }
+
+  const workflowsDir = join(automatosxDir, 'workflows');
+  for (const command of await listDir(workflowsDir)) {
+    for (const traceId of await listDir(join(workflowsDir, command))) {
+      const outputDir = join(workflowsDir, command, traceId);
+      const manifest = await readJson(join(outputDir, 'manifest.json'));
+      if (manifest?.status === 'preview' && (await modifiedAt(outputDir) ?? Infinity) < cutoff) {
+        removed.push(relativeToWorkspace(basePath, outputDir));
+      }
+    }
+  }
+
+  if (!options.dryRun) {
+    for (const target of removed) {
+      await rm(join(basePath, target), { recursive: true, force: true });
+    }
+  }
+  return { removed, maxAgeDays: config.cacheMaxAgeDays };
+}
+
+/** The built-in providers and those the workspace config names, as executors or the default. */
+export async function collectProviderIds(basePath: string): Promise<string[]> {
+  const providers = new Set(DEFAULT_PROVIDERS);
+  const providerConfig = asRecord((await readWorkspaceConfig(basePath)).providers);
+  for (const providerId of Object.keys(asRecord(providerConfig?.executors) ?? {})) {
+    providers.add(providerId);
+  }
+  if (typeof providerConfig?.default === 'string') {
+    providers.add(providerConfig.default);
+  }
+  return Array.from(providers);
+}
+
+async function rotateFile(filePath: string, generations: number): Promise<void> {
+  await rm(`${filePath}.${generations}`, { force: true });
+  for (let generation = generations - 1; generation >= 1; generation -= 1) {
+    await rename(`${filePath}.${generation}`, `${filePath}.${generation + 1}`).catch(() => undefined);
+  }
+  await rename(filePath, `${filePath}.1`);
+}
+
+function isProcessAlive(pid: number): boolean {
+  try {
+    process.kill(pid, 0);
+    return true;
+  } catch (error) {
+    return (error as NodeJS.ErrnoException).code === 'EPERM';
+  }
+}
+
+async function listDir(dir: string): Promise<string[]> {
+  try {
+    return (await readdir(dir)).sort();
+  } catch {
+    return [];
+  }
+}
+
+async function fileSize(filePath: string): Promise<number | undefined> {
+  try {
+    const stats = await stat(filePath);
+    return stats.isFile() ? stats.size : undefined;
+  } catch {
+    return undefined;
+  }
+}
+
+async function modifiedAt(filePath: string): Promise<number | undefined> {
+  try {
+    return (await stat(filePath)).mtimeMs;
+  } catch {
+    return undefined;
+  }
+}
+
+async function readJson(filePath: string): Promise<Record<string, unknown> | undefined> {
+  try {
+    return asRecord(JSON.parse(await readFile(filePath, 'utf8')));
+  } catch {
+    return undefined;
+  }
+}
+
+function relativeToWorkspace(basePath: string, filePath: string): string {
+  return relative(basePath, filePath).split('\\').join('/');
+}
@@ -0,0 +1,76 @@
+import { mkdir, readFile, writeFile } from 'node:fs/promises';
+import { dirname, resolve } from 'node:path';
+import { gunzipSync, gzipSync } from 'node:zlib';
+import { projectScopeId } from '@defai.digital/state-store';
+export const MEMORY_ARCHIVE_FORMAT = 'automatosx-memory';
+export const MEMORY_ARCHIVE_VERSION = 1;
+const PROJECT_PREFIX = '@project:';
+/**
+ * Writes entries to a `.axm` archive: gzipped JSON lines, a manifest first and then one entry per
+ * line with its timestamps, expiry, importance, source and, unless left out, embedding. Access times are
+ * local to a store and are not exported.
+ */
+export async function writeMemoryArchive(basePath, path, entries, options = {}) {
+    const includeEmbeddings = options.includeEmbeddings !== false;
+    const lines = entries.map((entry) => JSON.stringify({
+        key: entry.key,
+        ...(entry.namespace !== undefined ? { namespace: entry.namespace } : {}),
+        value: entry.value,
+        ...(includeEmbeddings && entry.embedding !== undefined ? { embedding: entry.embedding } : {}),
+        ...(entry.expiresAt !== undefined ? { expiresAt: entry.expiresAt } : {}),
+        ...(entry.importance !== undefined ? { importance: entry.importance } : {}),
+        ...(entry.source !== undefined ? { source: entry.source } : {}),
+        updatedAt: entry.updatedAt,
+    }));
+    const manifest = {
+        format: MEMORY_ARCHIVE_FORMAT,
+        version: MEMORY_ARCHIVE_VERSION,
+        exportedAt: new Date().toISOString(),
+        project: projectScopeId(basePath),
+        entries: entries.length,
+        embedded: includeEmbeddings ? entries.filter((entry) => entry.embedding !== undefined).length : 0,
+        namespaces: [...new Set(entries.map((entry) => entry.namespace ?? 'default'))].sort(),
+    };
+    const archivePath = resolve(basePath, path);
+    const archive = gzipSync(`${[JSON.stringify(manifest), ...lines].join('\n')}\n`);
+    await mkdir(dirname(archivePath), { recursive: true });
+    await writeFile(archivePath, archive);
+    return { path: archivePath, bytes: archive.length, manifest };
+}
+/**
+ * Reads a `.axm` archive back. Entries the exporting workspace had scoped to its project are
+ * rescoped to `basePath`'s project, so project memory follows a checkout to another path or machine.
+ */
+export async function readMemoryArchive(basePath, path) {
+    const archivePath = resolve(basePath, path);
+    let lines;
+    let manifest;
+    try {
+        lines = gunzipSync(await readFile(archivePath)).toString('utf8').split('\n').filter((line) => line.length > 0);
+        manifest = JSON.parse(lines[0] ?? '{}');
+    }
+    catch (error) {
+        if (error.code === 'ENOENT') {
+            throw new Error(`Memory archive not found: ${archivePath}`);
+        }
+        throw new Error(`${archivePath} is not a memory archive. Create one with \`ax memory export <file.axm>\`.`);
+    }
+    if (manifest.format !== MEMORY_ARCHIVE_FORMAT || typeof manifest.version !== 'number') {
+        throw new Error(`${archivePath} is not a memory archive. Create one with \`ax memory export <file.axm>\`.`);
+    }
+    if (manifest.version > MEMORY_ARCHIVE_VERSION) {
+        throw new Error(`${archivePath} uses memory archive version ${manifest.version}; this AutomatosX reads up to version ${MEMORY_ARCHIVE_VERSION}. Upgrade to import it.`);
+    }
+    const exportedPrefix = `${PROJECT_PREFIX}${manifest.project}/`;
+    const importedPrefix = `${PROJECT_PREFIX}${projectScopeId(basePath)}/`;
+    const entries = lines.slice(1).map((line, index) => {
+        const entry = JSON.parse(line);
+        if (typeof entry.key !== 'string' || typeof entry.updatedAt !== 'string') {
+            throw new Error(`Entry ${index + 1} of memory archive ${archivePath} has no key or updatedAt.`);
+        }
+        return entry.namespace?.startsWith(exportedPrefix) === true
+            ? { ...entry, namespace: `${importedPrefix}${entry.namespace.slice(exportedPrefix.length)}` }
+            : entry;
+    });
+    return { path: archivePath, manifest: manifest, entries };
+}
@@ -0,0 +1,119 @@
+import type { MemoryEntry, MemoryInput, StateStore } from '@defai.digital/state-store';
+
+export const MEMORY_DEDUP_ACTIONS = ['skip', 'merge', 'supersede'] as const;
+export type MemoryDedupAction = typeof MEMORY_DEDUP_ACTIONS[number];
+
+/** `memory.dedup` in `.automatosx/config.json`. */
+export interface MemoryDedupPolicy {
+  action: MemoryDedupAction;
+  /** Cosine similarity from which an existing entry counts as the same fact. */
+  threshold: number;
+}
+
+/** The existing entry an insert was found to duplicate, and what was done about it. */
+export interface MemoryDuplicate {
+  action: MemoryDedupAction;
+  key: string;
+  namespace?: string;
+  score: number;
+}
+
+export type RuntimeMemoryStoreResponse = MemoryEntry & { duplicate?: MemoryDuplicate };
+
+export const DEFAULT_DEDUP_THRESHOLD = 0.95;
+// Nearest neighbours looked at; the closest may be the entry being updated or in another namespace.
+const DEDUP_CANDIDATES = 10;
+
+/**
+ * The policy an insert runs under: `requested` (`off` turning deduplication off) over the configured
+ * section, which may be `true` for the defaults or `{ action, threshold }`. Skipping is the default
+ * action, as it is the only one that never changes stored memory.
+ */
+export function resolveMemoryDedupPolicy(section: unknown, requested?: string): MemoryDedupPolicy | undefined {
+  if (requested === 'off' || (requested === undefined && (section === undefined || section === false))) {
+    return undefined;
+  }
+  const configured = typeof section === 'object' && section !== null ? section as Record<string, unknown> : {};
+  const action = requested ?? configured.action ?? 'skip';
+  const threshold = configured.threshold ?? DEFAULT_DEDUP_THRESHOLD;
+  if (!(MEMORY_DEDUP_ACTIONS as readonly unknown[]).includes(action)) {
+    throw new Error(`Memory dedup action must be one of ${MEMORY_DEDUP_ACTIONS.join(', ')} or off, got ${JSON.stringify(action)}.`);
+  }
+  if (typeof threshold !== 'number' || !(threshold > 0 && threshold <= 1)) {
+    throw new Error(`memory.dedup.threshold must be a cosine similarity above 0 and at most 1, got ${JSON.stringify(threshold)}.`);
+  }
+  return { action: action as MemoryDedupAction, threshold };
+}
+
+/** The text a memory value is embedded from. */
+export function memoryText(value: unknown): string {
+  return typeof value === 'string' ? value : JSON.stringify(value) ?? String(value);
+}
+
+/**
+ * Stores `entry` unless another entry of its namespace is at least `threshold` similar. Then `skip`
+ * keeps the existing entry and drops the new one, `merge` folds the new value into the existing
+ * entry, and `supersede` stores the new entry and deletes the existing one. Rewriting an entry under
+ * its own key is an update, never a duplicate.
+ */
+export async function storeDeduplicatedMemory(
+  stateStore: Pick<StateStore, 'storeMemory' | 'deleteMemory' | 'searchMemoryByVector'>,
+  entry: MemoryInput & { embedding: number[] },
+  policy: MemoryDedupPolicy,
+): Promise<RuntimeMemoryStoreResponse> {
+  const candidates = await stateStore.searchMemoryByVector(entry.embedding, { namespace: entry.namespace, k: DEDUP_CANDIDATES, minScore: policy.threshold });
+  const match = candidates.find((candidate) => candidate.namespace === entry.namespace && candidate.key !== entry.key);
+  if (match === undefined) {
+    return stateStore.storeMemory(entry);
+  }
+
+  const { score, ...existing } = match;
+  const duplicate: MemoryDuplicate = {
+    action: policy.action,
+    key: existing.key,
+    ...(existing.namespace !== undefined ? { namespace: existing.namespace } : {}),
+    score: Number(score.toFixed(4)),
+  };
+  switch (policy.action) {
+    case 'skip':
+      return { ...existing, duplicate };
+    case 'merge': {
+      const importance = Math.max(existing.importance ?? -1, entry.importance ?? -1);
+      const source = entry.source ?? existing.source;
+      const merged = await stateStore.storeMemory({
+        key: existing.key,
+        namespace: existing.namespace,
+        value: mergeMemoryValues(existing.value, entry.value),
+        embedding: entry.embedding,
+        // The merged entry lasts as long as the longer-lived of the two.
+        ...(existing.expiresAt !== undefined && entry.expiresAt !== undefined
+          ? { expiresAt: existing.expiresAt > entry.expiresAt ? existing.expiresAt : entry.expiresAt }
+          : {}),
+        ...(importance >= 0 ? { importance } : {}),
+        ...(source !== undefined ? { source } : {}),
+      });
+      return { ...merged, duplicate };
+    }
+    case 'supersede': {
+      const stored = await stateStore.storeMemory(entry);
+      await stateStore.deleteMemory(existing.key, existing.namespace);
+      return { ...stored, duplicate };
+    }
+  }
+}
+
+/** Objects are merged key by key and arrays concatenated without repeats; otherwise the newer value wins. */
+export function mergeMemoryValues(existing: unknown, incoming: unknown): unknown {
+  if (Array.isArray(existing) && Array.isArray(incoming)) {
+    const seen = new Set(existing.map((item) => JSON.stringify(item)));
+    return [...existing, ...incoming.filter((item) => !seen.has(JSON.stringify(item)))];
+  }
+  if (isPlainObject(existing) && isPlainObject(incoming)) {
+    return { ...existing, ...incoming };
+  }
+  return incoming;
+}
+
+function isPlainObject(value: unknown): value is Record<string, unknown> {
+  return typeof value === 'object' && value !== null && !Array.isArray(value);
+}
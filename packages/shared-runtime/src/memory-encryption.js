@@ -0,0 +1,96 @@
+import { spawnSync } from 'node:child_process';
+import { randomBytes, scryptSync } from 'node:crypto';
+import { createMemoryCipher } from '@defai.digital/state-store';
+import { readWorkspaceConfigSync } from './config-layers.js';
+export const MEMORY_KEY_SOURCES = ['env', 'keychain'];
+export const MEMORY_KEY_ENV_VAR = 'AUTOMATOSX_MEMORY_KEY';
+const KEYCHAIN_SERVICE = 'automatosx';
+const KEYCHAIN_ACCOUNT = 'memory';
+// Fixed, so a passphrase derives the same key on every machine it is shared with.
+const PASSPHRASE_SALT = 'automatosx-memory';
+/**
+ * The cipher for a workspace whose `.automatosx/config.json` sets `memory.encryption`. The config is
+ * read synchronously, as the runtime opens its store when it is created; the key itself is only
+ * loaded once memory is used.
+ */
+export function memoryCipherFor(basePath, env = process.env) {
+    const source = readMemoryKeySource(basePath);
+    return source !== undefined ? createMemoryCipher(() => loadMemoryKey(source, env)) : undefined;
+}
+function readMemoryKeySource(basePath) {
+    const config = readWorkspaceConfigSync(basePath);
+    const source = config.memory?.encryption;
+    return source === false || source === null ? undefined : source;
+}
+/** 32 bytes as hex or base64 are used as they are; anything else is a passphrase the key is derived from. */
+export function decodeMemoryKey(secret) {
+    if (/^[0-9a-f]{64}$/i.test(secret)) {
+        return Buffer.from(secret, 'hex');
+    }
+    if (/^[A-Za-z0-9+/]{43}=$/.test(secret)) {
+        return Buffer.from(secret, 'base64');
+    }
+    return scryptSync(secret, PASSPHRASE_SALT, 32);
+}
+/** A new random key, saved to the OS keychain with `keychain`, or returned for `AUTOMATOSX_MEMORY_KEY`. */
+export function createMemoryKey(options = {}) {
+    const key = randomBytes(32).toString('base64');
+    if (options.keychain !== true) {
+        return { key, keychain: false };
+    }
+    if (readKeychainSecret() !== undefined) {
+        throw new Error(`The OS keychain already holds a memory key (service "${KEYCHAIN_SERVICE}", account "${KEYCHAIN_ACCOUNT}"); replacing it would leave memory encrypted with it unreadable.`);
+    }
+    const [command, ...args] = keychainCommand('store');
+    const result = spawnSync(command, args, { input: keychainInput(key), encoding: 'utf8', stdio: ['pipe', 'ignore', 'pipe'] });
+    // `security -i` exits 0 whether its command worked or not, so the key is read back.
+    if (result.status !== 0 || readKeychainSecret() !== key) {
+        throw new Error(`Could not save the memory key to the OS keychain with ${command}: ${result.error?.code === 'ENOENT' ? 'it is not installed' : result.error?.message ?? (result.stderr.trim() || 'the key could not be read back')}.`);
+    }
+    return { key, keychain: true };
+}
+function loadMemoryKey(source, env) {
+    if (source === 'env') {
+        const secret = env[MEMORY_KEY_ENV_VAR];
+        if (secret === undefined || secret.length === 0) {
+            throw new Error(`Memory is encrypted with the key in ${MEMORY_KEY_ENV_VAR}, which is not set. Create a key with \`ax memory key\`.`);
+        }
+        return decodeMemoryKey(secret);
+    }
+    if (source === 'keychain') {
+        const secret = readKeychainSecret();
+        if (secret === undefined) {
+            throw new Error(`No memory key in the OS keychain (service "${KEYCHAIN_SERVICE}", account "${KEYCHAIN_ACCOUNT}"). Create one with \`ax memory key --keychain\`.`);
+        }
+        return decodeMemoryKey(secret);
+    }
+    throw new Error(`memory.encryption in the workspace config must be one of ${MEMORY_KEY_SOURCES.join(', ')}, got ${JSON.stringify(source)}.`);
+}
+function readKeychainSecret() {
+    const [command, ...args] = keychainCommand('lookup');
+    const result = spawnSync(command, args, { encoding: 'utf8', stdio: ['ignore', 'pipe', 'ignore'] });
+    const secret = result.status === 0 ? result.stdout.trim() : '';
+    return secret.length > 0 ? secret : undefined;
+}
+// macOS keeps the key in the login keychain; Linux in the Secret Service through libsecret's secret-tool.
+// Neither is given the key as an argument, where the process list would show it.
+function keychainCommand(action) {
+    switch (process.platform) {
+        case 'darwin':
+            return action === 'lookup'
+                ? ['security', 'find-generic-password', '-s', KEYCHAIN_SERVICE, '-a', KEYCHAIN_ACCOUNT, '-w']
+                : ['security', '-i'];
+        case 'linux':
+            return action === 'lookup'
+                ? ['secret-tool', 'lookup', 'service', KEYCHAIN_SERVICE, 'account', KEYCHAIN_ACCOUNT]
+                : ['secret-tool', 'store', '--label=AutomatosX memory key', 'service', KEYCHAIN_SERVICE, 'account', KEYCHAIN_ACCOUNT];
+        default:
+            throw new Error(`The OS keychain is not supported on ${process.platform}; set memory.encryption to "env" and the key in ${MEMORY_KEY_ENV_VAR}.`);
+    }
+}
+// What the store command reads on stdin: secret-tool the key itself, `security -i` the command adding it.
+function keychainInput(key) {
+    return process.platform === 'darwin'
+        ? `add-generic-password -s ${KEYCHAIN_SERVICE} -a ${KEYCHAIN_ACCOUNT} -w "${key}"\n`
+        : key;
+}
@@ -0,0 +1,50 @@
+import { createHash } from 'node:crypto';
+import { readFile } from 'node:fs/promises';
+import { resolve } from 'node:path';
+/**
+ * Hashes the lines a source spans, a single line when it has no end, or the whole file when it has
+ * no lines. Undefined when the file cannot be read.
+ */
+export async function hashMemorySource(basePath, source) {
+    let text;
+    try {
+        text = await readFile(resolve(basePath, source.file), 'utf8');
+    }
+    catch {
+        return undefined;
+    }
+    if (source.startLine !== undefined) {
+        text = text.split('\n').slice(source.startLine - 1, source.endLine ?? source.startLine).join('\n');
+    }
+    return createHash('sha256').update(text).digest('hex').slice(0, 16);
+}
+/** `source` with the hash of what it spans now, for sources naming a file that carry none yet. */
+export async function recordMemorySource(basePath, source) {
+    if (source.file === undefined || source.contentHash !== undefined) {
+        return source;
+    }
+    const contentHash = await hashMemorySource(basePath, { ...source, file: source.file });
+    return contentHash !== undefined ? { ...source, contentHash } : source;
+}
+/**
+ * Entries whose source file is gone, or whose span no longer hashes to what it did when they were
+ * stored. Entries citing no file, or a file that could not be read when they were stored, are
+ * only ever reported missing.
+ */
+export async function findStaleMemory(basePath, entries) {
+    const stale = [];
+    let checked = 0;
+    for (const entry of entries) {
+        const source = entry.source;
+        if (source?.file === undefined) {
+            continue;
+        }
+        checked += 1;
+        const current = await hashMemorySource(basePath, { ...source, file: source.file });
+        const reason = current === undefined ? 'missing' : source.contentHash !== undefined && current !== source.contentHash ? 'changed' : undefined;
+        if (reason !== undefined) {
+            stale.push({ key: entry.key, ...(entry.namespace !== undefined ? { namespace: entry.namespace } : {}), source, reason });
+        }
+    }
+    return { checked, stale };
+}
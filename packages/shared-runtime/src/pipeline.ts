@@ -0,0 +1,607 @@
+import { mkdir, readFile, rename, writeFile } from 'node:fs/promises';
+import { basename, dirname, extname, join } from 'node:path';
+import type { TraceSurface } from '@defai.digital/trace-store';
+import { findDependencyErrors, runScheduled, type SchedulerLimits } from '@defai.digital/workflow-engine';
+import { parse as parseYaml } from 'yaml';
+import { listWorkspaceFiles, workspaceGlob } from './code-intel/module-graph.js';
+import { createAbilityIncludeResolver, lookupTemplateVariable, renderPromptTemplate } from './prompt-template.js';
+import { asRecord } from './records.js';
+import { BUDGET_EXCEEDED_CODE, parseRunBudget, type RunBudget } from './run-budget.js';
+
+export const PIPELINE_ERROR_CODE = 'PIPELINE_INVALID';
+
+/** A pipeline read from YAML, with each stage's dependencies worked out. */
+export interface PipelineDefinition {
+  name: string;
+  description?: string;
+  /** Defaults of the pipeline's inputs; the inputs a run is given override them. */
+  inputs: Record<string, unknown>;
+  /** Limits on the whole run; a run that goes over one is stopped and its remaining stages skipped. */
+  budget?: RunBudget;
+  stages: PipelineStage[];
+}
+
+export interface PipelineStage {
+  id: string;
+  agent: string;
+  /** A prompt template, rendered with `inputs`, `stages` and, in a loop, `item` and `index`. */
+  task: string;
+  /** The input of the agent run; string values are templates like `task`. */
+  inputs?: Record<string, unknown>;
+  /** Stages that have to succeed first: `needs` plus `artifacts`, else the stage before. */
+  dependencies: string[];
+  /** Stages whose artifacts go to the agent as attached files. */
+  artifacts: string[];
+  /** A condition on the variables; the stage, or the item of a loop, runs only when it holds. */
+  when?: string;
+  /** A glob of workspace files, a `{{path}}` to a list, or a list; the agent runs once per item. */
+  foreach?: string | unknown[];
+  retry: PipelineRetryPolicy;
+  provider?: string;
+  timeoutMs?: number;
+  /** Limits on each agent run of the stage. */
+  budget?: RunBudget;
+}
+
+export interface PipelineRetryPolicy {
+  maxAttempts: number;
+  backoffMs: number;
+  backoffMultiplier: number;
+}
+
+export type PipelineStageStatus = 'succeeded' | 'failed' | 'skipped';
+
+/** One agent run of a stage: the only one, or the one for an item of its loop. */
+export interface PipelineStageRun {
+  item?: unknown;
+  traceId?: string;
+  success: boolean;
+  attempts: number;
+  content: string;
+  error?: {
+    code?: string;
+    message?: string;
+  };
+}
+
+export interface PipelineStageResult {
+  id: string;
+  agent: string;
+  status: PipelineStageStatus;
+  /** Why the stage did not run. */
+  reason?: string;
+  runs: PipelineStageRun[];
+  /** What the stage's runs replied; a loop's replies each under a heading naming the item. */
+  output: string;
+  /** The workspace file the output was saved to. */
+  artifact?: string;
+  durationMs: number;
+  /** Set when the result was taken from the checkpoint of an earlier attempt of the run. */
+  resumed?: boolean;
+}
+
+export interface PipelineRunReport {
+  pipeline: string;
+  success: boolean;
+  /** Where stage artifacts are saved, relative to the workspace. */
+  artifactDir: string;
+  /** In declaration order. */
+  stages: PipelineStageResult[];
+  error?: {
+    code: string;
+    message: string;
+  };
+}
+
+/** What `.automatosx/pipelines/<run-id>/checkpoint.json` keeps of a run so it can be resumed. */
+export interface PipelineCheckpoint {
+  runId: string;
+  file: string;
+  /** The YAML the run started with; a resume runs it even when the file has changed since. */
+  source: string;
+  input?: Record<string, unknown>;
+  provider?: string;
+  /** The budget the run was started with; a resume gets it afresh unless it is given another. */
+  budget?: RunBudget;
+  sessionId?: string;
+  status: 'running' | 'completed' | 'failed';
+  startedAt: string;
+  updatedAt: string;
+  /** The stages that have finished so far, in the order they did. */
+  stages: PipelineStageResult[];
+}
+
+export interface PipelineRunOptions {
+  basePath: string;
+  /** Results of stages that succeeded in an earlier attempt of the run; they are not run again. */
+  completed?: PipelineStageResult[];
+  /** Overrides of the pipeline's input defaults. */
+  input?: Record<string, unknown>;
+  /** Where stage artifacts are saved, relative to `basePath`. */
+  artifactDir: string;
+  limits: SchedulerLimits;
+  /** The concurrency pool a stage counts against, usually the provider it runs on. */
+  poolOf?: (stage: PipelineStage) => string | undefined;
+  /** The provider of stages that do not name one. */
+  provider?: string;
+  traceId?: string;
+  sessionId?: string;
+  surface?: TraceSurface;
+  signal?: AbortSignal;
+  onStageStart?: (stage: PipelineStage) => void;
+  onStageEnd?: (result: PipelineStageResult) => void;
+  /** Receives the reply text of each stage's agent runs as the provider streams it. */
+  onText?: (text: string, stageId: string) => void;
+}
+
+/** What a pipeline needs from the runtime: an agent run, as `SharedRuntimeService.runAgent` does it. */
+export type PipelineAgentRunner = (request: {
+  agentId: string;
+  task: string;
+  basePath: string;
+  input?: Record<string, unknown>;
+  files?: string[];
+  provider?: string;
+  timeoutMs?: number;
+  budget?: RunBudget;
+  sessionId?: string;
+  surface?: TraceSurface;
+  parentTraceId?: string;
+  rootTraceId?: string;
+  signal?: AbortSignal;
+  onText?: (text: string) => void;
+}) => Promise<{ traceId: string; success: boolean; content: string; error?: { code?: string; message?: string } }>;
+
+const STAGE_FIELDS = new Set(['id', 'agent', 'task', 'inputs', 'needs', 'artifacts', 'when', 'foreach', 'retry', 'provider', 'timeoutMs', 'budget']);
+const PIPELINE_FIELDS = new Set(['name', 'description', 'inputs', 'budget', 'stages']);
+const STAGE_ID_PATTERN = /^[A-Za-z0-9_-]+$/;
+const LIST_REFERENCE_PATTERN = /^\{\{\s*([^{}\s]+)\s*\}\}$/;
+const CONDITION_PATTERN = /^(.+?)\s*(==|!=)\s*(.+)$/;
+const RUN_ID_PATTERN = /^(?!\.{1,2}$)[A-Za-z0-9._-]+$/;
+
+/**
+ * Reads a pipeline from YAML. A stage without `needs` waits for the stage before it; `needs: []`
+ * lets it start at once. Throws an error with the `PIPELINE_INVALID` code, naming `source`, when a
+ * field is unknown or has the wrong type, or the stages' dependencies are unknown or circular.
+ */
+export function parsePipeline(text: string, source = 'pipeline'): PipelineDefinition {
+  let document: unknown;
+  try {
+    document = parseYaml(text);
+  } catch (error) {
+    throw pipelineError(`${source} is not valid YAML: ${error instanceof Error ? error.message : String(error)}`);
+  }
+  if (!isObject(document)) {
+    throw pipelineError(`${source} has to be a mapping with "stages".`);
+  }
+  const unknownField = Object.keys(document).find((field) => !PIPELINE_FIELDS.has(field));
+  if (unknownField !== undefined) {
+    throw pipelineError(`${source} has an unknown field "${unknownField}"; fields are ${[...PIPELINE_FIELDS].join(', ')}.`);
+  }
+  if (document.name !== undefined && typeof document.name !== 'string') {
+    throw pipelineError(`${source} has a "name" that is not a string.`);
+  }
+  if (document.inputs !== undefined && !isObject(document.inputs)) {
+    throw pipelineError(`${source} has "inputs" that are not a mapping.`);
+  }
+  if (!Array.isArray(document.stages) || document.stages.length === 0) {
+    throw pipelineError(`${source} needs a non-empty list of "stages".`);
+  }
+
+  const stages: PipelineStage[] = [];
+  for (const [index, value] of document.stages.entries()) {
+    const stage = parseStage(value, source, index, stages.at(-1)?.id);
+    if (stages.some((other) => other.id === stage.id)) {
+      throw pipelineError(`${source} has more than one stage "${stage.id}".`);
+    }
+    stages.push(stage);
+  }
+  const dependencyErrors = findDependencyErrors(stages.map((stage) => ({ id: stage.id, dependencies: stage.dependencies })));
+  if (dependencyErrors.length > 0) {
+    throw pipelineError(`${source} has stages that cannot run: ${dependencyErrors.join('; ')}.`);
+  }
+
+  return {
+    name: typeof document.name === 'string' ? document.name : basename(source, extname(source)),
+    ...(typeof document.description === 'string' ? { description: document.description } : {}),
+    inputs: asRecord(document.inputs) ?? {},
+    ...(document.budget !== undefined ? { budget: readBudget(document.budget, source) } : {}),
+    stages,
+  };
+}
+
+function parseStage(value: unknown, source: string, index: number, previousId: string | undefined): PipelineStage {
+  if (!isObject(value)) {
+    throw pipelineError(`${source} stage ${index + 1} is not a mapping.`);
+  }
+  if (typeof value.id !== 'string' || !STAGE_ID_PATTERN.test(value.id)) {
+    throw pipelineError(`${source} stage ${index + 1} needs an "id" of letters, digits, "-" and "_".`);
+  }
+  const where = `${source} stage "${value.id}"`;
+  const unknownField = Object.keys(value).find((field) => !STAGE_FIELDS.has(field));
+  if (unknownField !== undefined) {
+    throw pipelineError(`${where} has an unknown field "${unknownField}"; fields are ${[...STAGE_FIELDS].join(', ')}.`);
+  }
+  for (const field of ['agent', 'task']) {
+    const text = value[field];
+    if (typeof text !== 'string' || text.trim().length === 0) {
+      throw pipelineError(`${where} needs a "${field}".`);
+    }
+  }
+  for (const field of ['when', 'provider']) {
+    if (value[field] !== undefined && typeof value[field] !== 'string') {
+      throw pipelineError(`${where} has a "${field}" that is not a string.`);
+    }
+  }
+  if (value.inputs !== undefined && !isObject(value.inputs)) {
+    throw pipelineError(`${where} has "inputs" that are not a mapping.`);
+  }
+  if (value.foreach !== undefined && typeof value.foreach !== 'string' && !Array.isArray(value.foreach)) {
+    throw pipelineError(`${where} has a "foreach" that is neither a glob, a {{path}} nor a list.`);
+  }
+  if (value.timeoutMs !== undefined && !isPositiveInteger(value.timeoutMs)) {
+    throw pipelineError(`${where} has a "timeoutMs" that is not a positive integer.`);
+  }
+  const needs = value.needs === undefined ? undefined : readStageIds(value.needs, `${where} "needs"`);
+  const artifacts = value.artifacts === undefined ? [] : readStageIds(value.artifacts, `${where} "artifacts"`);
+
+  return {
+    id: value.id,
+    agent: String(value.agent),
+    task: String(value.task),
+    ...(isObject(value.inputs) ? { inputs: value.inputs } : {}),
+    dependencies: [...new Set([...(needs ?? (previousId !== undefined ? [previousId] : [])), ...artifacts])],
+    artifacts,
+    ...(typeof value.when === 'string' ? { when: value.when } : {}),
+    ...(typeof value.foreach === 'string' || Array.isArray(value.foreach) ? { foreach: value.foreach } : {}),
+    retry: parseRetryPolicy(value.retry, where),
+    ...(typeof value.provider === 'string' ? { provider: value.provider } : {}),
+    ...(isPositiveInteger(value.timeoutMs) ? { timeoutMs: value.timeoutMs } : {}),
+    ...(value.budget !== undefined ? { budget: readBudget(value.budget, where) } : {}),
+  };
+}
+
+function readBudget(value: unknown, where: string): RunBudget {
+  try {
+    return parseRunBudget(value, `${where} "budget"`);
+  } catch (error) {
+    throw pipelineError(error instanceof Error ? error.message : String(error));
+  }
+}
+
+// `retry: 3` is short for `retry: { maxAttempts: 3 }`.
+function parseRetryPolicy(value: unknown, where: string): PipelineRetryPolicy {
+  const policy = typeof value === 'number' ? { maxAttempts: value } : value === undefined ? {} : value;
+  if (!isObject(policy)) {
+    throw pipelineError(`${where} has a "retry" that is neither a number of attempts nor a mapping.`);
+  }
+  const { maxAttempts = 1, backoffMs = 1000, backoffMultiplier = 2 } = policy;
+  if (!isPositiveInteger(maxAttempts)) {
+    throw pipelineError(`${where} has a retry "maxAttempts" that is not a positive integer.`);
+  }
+  if (typeof backoffMs !== 'number' || backoffMs < 0 || typeof backoffMultiplier !== 'number' || backoffMultiplier < 1) {
+    throw pipelineError(`${where} has a retry "backoffMs" below 0 or a "backoffMultiplier" below 1.`);
+  }
+  return { maxAttempts, backoffMs, backoffMultiplier };
+}
+
+function readStageIds(value: unknown, where: string): string[] {
+  const ids = typeof value === 'string' ? [value] : value;
+  if (!Array.isArray(ids) || !ids.every((id): id is string => typeof id === 'string')) {
+    throw pipelineError(`${where} has to be a stage id or a list of them.`);
+  }
+  return ids;
+}
+
+/**
+ * Runs the stages of a pipeline, each as soon as the stages it depends on have succeeded, within
+ * `limits`. A stage runs its agent once, or once per item of its `foreach`, retrying failed runs
+ * by its policy; its output is saved to `<artifactDir>/<stage>.md` for later stages. A stage whose
+ * condition does not hold is skipped without failing the pipeline; one that fails skips the stages
+ * that depend on it, and a failed item skips the rest of its loop. Aborting `signal` skips the
+ * stages not yet started and fails the run.
+ */
+export async function runPipelineStages(
+  pipeline: PipelineDefinition,
+  options: PipelineRunOptions,
+  runAgent: PipelineAgentRunner,
+): Promise<PipelineRunReport> {
+  const inputs = { ...pipeline.inputs, ...options.input };
+  const finished = new Map<string, PipelineStageResult>();
+  const completed = new Map((options.completed ?? []).map((result) => [result.id, result] as const));
+  // Stages that failed or were skipped because a stage they depend on did; a stage whose condition
+  // did not hold is not among them, so what depends on it still runs.
+  const broken = new Set<string>();
+  const resolveInclude = createAbilityIncludeResolver(options.basePath, {});
+
+  const runStage = async (stage: PipelineStage): Promise<Omit<PipelineStageResult, 'durationMs'>> => {
+    const skip = (reason: string): Omit<PipelineStageResult, 'durationMs'> => ({ id: stage.id, agent: stage.agent, status: 'skipped', reason, runs: [], output: '' });
+    const blocker = stage.dependencies.find((id) => broken.has(id));
+    if (blocker !== undefined) {
+      broken.add(stage.id);
+      return skip(`Stage "${blocker}" ${finished.get(blocker)?.status === 'failed' ? 'failed' : 'did not run'}.`);
+    }
+    if (options.signal?.aborted === true) {
+      broken.add(stage.id);
+      return skip(options.signal.reason instanceof Error ? options.signal.reason.message : 'The pipeline was cancelled.');
+    }
+    const variables = {
+      inputs,
+      stages: Object.fromEntries([...finished.values()].map((result) => [result.id, {
+        status: result.status,
+        output: result.output,
+        outputs: result.runs.map((run) => run.content),
+        artifact: result.artifact,
+      }])),
+    };
+
+    let items: Array<{ item?: unknown; index?: number }> = [{}];
+    let itemFiles = false;
+    if (stage.foreach !== undefined) {
+      try {
+        const loop = await resolveLoopItems(stage.foreach, variables, options.basePath);
+        items = loop.items.map((item, index) => ({ item, index }));
+        itemFiles = loop.files;
+      } catch (error) {
+        return failed(stage, [], error);
+      }
+      if (items.length === 0) {
+        return skip('There was nothing to loop over.');
+      }
+    }
+    items = items.filter((entry) => stage.when === undefined || evaluateCondition(stage.when, { ...variables, ...entry }));
+    if (items.length === 0) {
+      return skip(stage.foreach === undefined ? `"${stage.when}" does not hold.` : `"${stage.when}" holds for no item.`);
+    }
+
+    options.onStageStart?.(stage);
+    const artifactFiles = stage.artifacts.flatMap((id) => {
+      const artifact = finished.get(id)?.artifact;
+      return artifact !== undefined ? [artifact] : [];
+    });
+    const provider = stage.provider ?? options.provider;
+    const runs: PipelineStageRun[] = [];
+    for (const entry of items) {
+      const scope = { ...variables, ...entry };
+      const files = [...artifactFiles, ...(itemFiles ? [String(entry.item)] : [])];
+      try {
+        const task = (await renderPromptTemplate(stage.task, scope, resolveInclude)).text;
+        const input = stage.inputs === undefined ? undefined : await renderInputs(stage.inputs, scope, resolveInclude);
+        const run = await runWithRetry(stage, options, () => runAgent({
+          agentId: stage.agent,
+          task,
+          basePath: options.basePath,
+          ...(input !== undefined ? { input } : {}),
+          ...(files.length > 0 ? { files } : {}),
+          ...(provider !== undefined ? { provider } : {}),
+          ...(stage.timeoutMs !== undefined ? { timeoutMs: stage.timeoutMs } : {}),
+          ...(stage.budget !== undefined ? { budget: stage.budget } : {}),
+          ...(options.sessionId !== undefined ? { sessionId: options.sessionId } : {}),
+          ...(options.surface !== undefined ? { surface: options.surface } : {}),
+          ...(options.traceId !== undefined ? { parentTraceId: options.traceId, rootTraceId: options.traceId } : {}),
+          ...(options.signal !== undefined ? { signal: options.signal } : {}),
+          ...(options.onText !== undefined ? { onText: (text: string) => options.onText?.(text, stage.id) } : {}),
+        }));
+        runs.push({ ...(stage.foreach !== undefined ? { item: entry.item } : {}), ...run });
+      } catch (error) {
+        return failed(stage, runs, error);
+      }
+      if (!runs.at(-1)!.success) {
+        break;
+      }
+    }
+
+    const output = stage.foreach === undefined
+      ? runs[0]?.content ?? ''
+      : runs.map((run) => `## ${formatItem(run.item)}\n\n${run.content}`).join('\n\n');
+    const artifact = `${options.artifactDir}/${stage.id}.md`;
+    await mkdir(dirname(join(options.basePath, artifact)), { recursive: true });
+    await writeFile(join(options.basePath, artifact), output.endsWith('\n') ? output : `${output}\n`, 'utf8');
+    return {
+      id: stage.id,
+      agent: stage.agent,
+      status: runs.every((run) => run.success) ? 'succeeded' : 'failed',
+      runs,
+      output,
+      artifact,
+    };
+  };
+
+  const results = await runScheduled(
+    pipeline.stages.map((stage) => ({ ...stage, pool: options.poolOf?.(stage) })),
+    options.limits,
+    async (stage) => {
+      const previous = completed.get(stage.id);
+      if (previous !== undefined && previous.status === 'succeeded') {
+        finished.set(stage.id, previous);
+        return { ...previous, resumed: true };
+      }
+      const startedAt = Date.now();
+      const result = { ...await runStage(stage), durationMs: Date.now() - startedAt };
+      if (result.status === 'failed') {
+        broken.add(stage.id);
+      }
+      finished.set(stage.id, result);
+      options.onStageEnd?.(result);
+      return result;
+    },
+  );
+
+  const stages = pipeline.stages.map((stage) => results.get(stage.id)!);
+  const failedStage = stages.find((stage) => stage.status === 'failed');
+  // Stages left out because the run was cancelled fail it even when none of those that ran did.
+  const cancelled = options.signal?.aborted === true && stages.some((stage) => stage.status === 'skipped' && broken.has(stage.id));
+  return {
+    pipeline: pipeline.name,
+    success: failedStage === undefined && !cancelled,
+    artifactDir: options.artifactDir,
+    stages,
+    ...(failedStage !== undefined ? {
+      error: {
+        code: 'PIPELINE_STAGE_FAILED',
+        message: `Stage "${failedStage.id}" failed: ${failedStage.runs.at(-1)?.error?.message ?? failedStage.reason ?? 'the agent run did not succeed'}`,
+      },
+    } : cancelled ? {
+      error: { code: 'PIPELINE_CANCELLED', message: options.signal?.reason instanceof Error ? options.signal.reason.message : 'The pipeline was cancelled.' },
+    } : {}),
+  };
+}
+
+/** The checkpoint of run `runId`, or undefined when it has none. */
+export async function readPipelineCheckpoint(basePath: string, runId: string): Promise<PipelineCheckpoint | undefined> {
+  if (!RUN_ID_PATTERN.test(runId)) {
+    return undefined;
+  }
+  try {
+    const checkpoint = JSON.parse(await readFile(checkpointPath(basePath, runId), 'utf8')) as unknown;
+    return isObject(checkpoint) && typeof checkpoint.source === 'string' && Array.isArray(checkpoint.stages)
+      ? checkpoint as unknown as PipelineCheckpoint
+      : undefined;
+  } catch {
+    return undefined;
+  }
+}
+
+// Written under a temporary name and renamed, so a run killed mid-write leaves the last checkpoint whole.
+export async function writePipelineCheckpoint(basePath: string, checkpoint: PipelineCheckpoint): Promise<void> {
+  const path = checkpointPath(basePath, checkpoint.runId);
+  const tempPath = `${path}.${process.pid}.tmp`;
+  await mkdir(dirname(path), { recursive: true });
+  await writeFile(tempPath, `${JSON.stringify(checkpoint, null, 2)}\n`, 'utf8');
+  await rename(tempPath, path);
+}
+
+/** Where the artifacts and checkpoint of run `runId` are kept, relative to the workspace. */
+export function pipelineRunDir(runId: string): string {
+  return `.automatosx/pipelines/${runId}`;
+}
+
+function checkpointPath(basePath: string, runId: string): string {
+  return join(basePath, pipelineRunDir(runId), 'checkpoint.json');
+}
+
+async function runWithRetry(
+  stage: PipelineStage,
+  options: PipelineRunOptions,
+  run: () => ReturnType<PipelineAgentRunner>,
+): Promise<Omit<PipelineStageRun, 'item'>> {
+  let delay = stage.retry.backoffMs;
+  for (let attempt = 1; ; attempt += 1) {
+    const result = await run();
+    // A run stopped by its budget would most likely be stopped again, having spent as much.
+    const final = result.success || result.error?.code === BUDGET_EXCEEDED_CODE || attempt >= stage.retry.maxAttempts;
+    if (final || options.signal?.aborted === true) {
+      return {
+        traceId: result.traceId,
+        success: result.success,
+        attempts: attempt,
+        content: result.content,
+        ...(result.error !== undefined ? { error: result.error } : {}),
+      };
+    }
+    await sleep(delay, options.signal);
+    delay *= stage.retry.backoffMultiplier;
+  }
+}
+
+// A glob lists workspace files, which then go to the agent attached; `{{path}}` names a list variable.
+async function resolveLoopItems(foreach: string | unknown[], variables: Record<string, unknown>, basePath: string): Promise<{ items: unknown[]; files: boolean }> {
+  if (Array.isArray(foreach)) {
+    return { items: foreach, files: false };
+  }
+  const reference = LIST_REFERENCE_PATTERN.exec(foreach.trim());
+  if (reference !== null) {
+    const value = lookupTemplateVariable(variables, reference[1]!);
+    if (!Array.isArray(value)) {
+      throw pipelineError(`"foreach: ${foreach}" is not a list.`);
+    }
+    return { items: value, files: false };
+  }
+  const pattern = workspaceGlob(foreach.trim());
+  return { items: await listWorkspaceFiles(basePath, { accept: (file) => pattern.test(file) }), files: true };
+}
+
+/**
+ * Whether a `when` condition holds: `path` when the value is set and not false, empty or zero,
+ * `!path` when it is not, and `path == value` or `path != value` comparing it with a quoted
+ * string, a number, `true`, `false`, `null` or a bare word.
+ */
+function evaluateCondition(condition: string, variables: Record<string, unknown>): boolean {
+  const comparison = CONDITION_PATTERN.exec(condition.trim());
+  if (comparison !== null) {
+    const value = lookupTemplateVariable(variables, comparison[1]!.trim()) ?? null;
+    const expected = parseLiteral(comparison[3]!.trim());
+    const equal = value === expected || (typeof value !== 'object' && expected !== null && String(value) === String(expected));
+    return comparison[2] === '==' ? equal : !equal;
+  }
+  const negated = condition.trim().startsWith('!');
+  const value = lookupTemplateVariable(variables, condition.trim().replace(/^!\s*/, ''));
+  const holds = Array.isArray(value) ? value.length > 0 : Boolean(value);
+  return negated ? !holds : holds;
+}
+
+function parseLiteral(text: string): unknown {
+  if (/^(['"]).*\1$/.test(text)) {
+    return text.slice(1, -1);
+  }
+  if (text === 'true' || text === 'false') {
+    return text === 'true';
+  }
+  if (text === 'null') {
+    return null;
+  }
+  return text.length > 0 && Number.isFinite(Number(text)) ? Number(text) : text;
+}
+
+async function renderInputs(
+  inputs: Record<string, unknown>,
+  scope: Record<string, unknown>,
+  resolveInclude: ReturnType<typeof createAbilityIncludeResolver>,
+): Promise<Record<string, unknown>> {
+  const rendered: Record<string, unknown> = {};
+  for (const [key, value] of Object.entries(inputs)) {
+    rendered[key] = typeof value === 'string' ? (await renderPromptTemplate(value, scope, resolveInclude)).text : value;
+  }
+  return rendered;
+}
+
+function failed(stage: PipelineStage, runs: PipelineStageRun[], error: unknown): Omit<PipelineStageResult, 'durationMs'> {
+  const code = isObject(error) && typeof error.code === 'string' ? error.code : PIPELINE_ERROR_CODE;
+  return {
+    id: stage.id,
+    agent: stage.agent,
+    status: 'failed',
+    runs: [...runs, { success: false, attempts: 0, content: '', error: { code, message: error instanceof Error ? error.message : String(error) } }],
+    output: '',
+  };
+}
+
+function formatItem(item: unknown): string {
+  return typeof item === 'object' && item !== null ? JSON.stringify(item) : String(item);
+}
+
+function isObject(value: unknown): value is Record<string, unknown> {
+  return typeof value === 'object' && value !== null && !Array.isArray(value);
+}
+
+function isPositiveInteger(value: unknown): value is number {
+  return typeof value === 'number' && Number.isInteger(value) && value > 0;
+}
+
+function sleep(ms: number, signal: AbortSignal | undefined): Promise<void> {
+  return new Promise((resolve) => {
+    const onAbort = () => {
+      clearTimeout(timer);
+      resolve();
+    };
+    const timer = setTimeout(() => {
+      signal?.removeEventListener('abort', onAbort);
+      resolve();
+    }, ms);
+    signal?.addEventListener('abort', onAbort, { once: true });
+  });
+}
+
+function pipelineError(message: string): Error {
+  return Object.assign(new Error(message), { code: PIPELINE_ERROR_CODE });
+}
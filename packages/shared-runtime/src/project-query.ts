@@ -0,0 +1,261 @@
+import { readCodeIndex, filterCodeSymbols } from './code-intel/index.js';
+import { buildModuleGraph, type ModuleGraph } from './code-intel/module-graph.js';
+import { CODE_SYMBOL_KINDS, type CodeSymbolKind } from './code-intel/types.js';
+
+export type ProjectQueryIntent = 'dependents' | 'dependencies' | 'symbols' | 'files';
+
+export interface ProjectQueryPlan {
+  intent: ProjectQueryIntent;
+  target: string;
+  kind?: CodeSymbolKind;
+  language?: string;
+}
+
+export interface ProjectQueryReference {
+  file: string;
+  line?: number;
+  detail: string;
+}
+
+export interface RuntimeProjectAnswer {
+  question: string;
+  plan: ProjectQueryPlan;
+  planner: 'provider' | 'heuristic';
+  answer: string;
+  references: ProjectQueryReference[];
+  warnings: string[];
+}
+
+export type ProjectQueryPlanner = (prompt: string) => Promise<string | undefined>;
+
+const PROJECT_QUERY_INTENTS: ProjectQueryIntent[] = ['dependents', 'dependencies', 'symbols', 'files'];
+const DEFAULT_REFERENCE_LIMIT = 50;
+const STOP_WORDS = new Set([
+  'a', 'an', 'the', 'which', 'what', 'where', 'who', 'is', 'are', 'does', 'do', 'of', 'in', 'on', 'to', 'for',
+  'me', 'show', 'list', 'find', 'all', 'any', 'that', 'this', 'there', 'modules', 'module', 'files', 'file',
+  'code', 'defined', 'define', 'defines', 'declared', 'declare', 'depend', 'depends', 'dependent', 'dependents',
+  'import', 'imports', 'imported', 'use', 'uses', 'used', 'require', 'requires', 'required', 'reference',
+  'references', 'referenced', 'by', 'from', 'have', 'has', 'our', 'we', 'it', 'its', 'project', 'repo',
+  'mention', 'mentions', 'contain', 'contains', 'about', 'named', 'called',
+]);
+const KIND_SYNONYMS: Record<string, CodeSymbolKind> = {
+  classes: 'class',
+  methods: 'method',
+  functions: 'function',
+  interfaces: 'trait',
+  interface: 'trait',
+  traits: 'trait',
+  resources: 'resource',
+  ports: 'port',
+  stages: 'stage',
+  variables: 'variable',
+  properties: 'property',
+  records: 'record',
+  namespaces: 'namespace',
+  outputs: 'output',
+};
+
+export function buildProjectQueryPlannerPrompt(question: string): string {
+  return [
+    'Translate the question about this code base into a JSON query plan. Respond with JSON only.',
+    `Schema: {"intent": ${PROJECT_QUERY_INTENTS.map((intent) => `"${intent}"`).join(' | ')}, "target": string, "kind"?: ${CODE_SYMBOL_KINDS.map((kind) => `"${kind}"`).join(' | ')}, "language"?: string}`,
+    '- dependents: modules that import the target module.',
+    '- dependencies: modules the target module imports.',
+    '- symbols: indexed symbols whose name matches the target (optionally filtered by kind/language).',
+    '- files: files whose path matches the target.',
+    `Question: ${question}`,
+  ].join('\n');
+}
+
+export function parseProjectQueryPlan(content: string): ProjectQueryPlan | undefined {
+  const json = /\{[\s\S]*\}/.exec(content)?.[0];
+  if (json === undefined) {
+    return undefined;
+  }
+  let parsed: Record<string, unknown>;
+  try {
+    parsed = JSON.parse(json) as Record<string, unknown>;
+  } catch {
+    return undefined;
+  }
+  if (
+    !PROJECT_QUERY_INTENTS.includes(parsed.intent as ProjectQueryIntent)
+    || typeof parsed.target !== 'string'
+    || parsed.target.trim().length === 0
+  ) {
+    return undefined;
+  }
+  return {
+    intent: parsed.intent as ProjectQueryIntent,
+    target: parsed.target.trim(),
+    ...((CODE_SYMBOL_KINDS as readonly unknown[]).includes(parsed.kind) ? { kind: parsed.kind as CodeSymbolKind } : {}),
+    ...(typeof parsed.language === 'string' && parsed.language.length > 0 ? { language: parsed.language } : {}),
+  };
+}
+
+export function planProjectQueryHeuristically(question: string): ProjectQueryPlan {
+  const normalized = question.toLowerCase();
+  const quoted = /["'`]([^"'`]+)["'`]/.exec(question)?.[1];
+  const words = normalized.replace(/[?!.,]/g, ' ').split(/\s+/).filter((word) => word.length > 0);
+  const kind = words.map((word) => KIND_SYNONYMS[word] ?? ((CODE_SYMBOL_KINDS as readonly string[]).includes(word) ? word as CodeSymbolKind : undefined))
+    .find((candidate) => candidate !== undefined);
+
+  let intent: ProjectQueryIntent = 'symbols';
+  let subject = normalized;
+  const dependents = /\b(?:depends?|dependents?|relies|rely)\s+on\b(.*)|\b(?:imports?|uses|requires|references)\b(.*)/.exec(normalized);
+  const dependencies = /\b(?:what|which)\b.*\b(?:does|do)\b(.*)\b(?:depend\s+on|import|use|require)\b|\bdependencies\s+of\b(.*)|\bimports\s+of\b(.*)/.exec(normalized);
+  if (dependencies !== null) {
+    intent = 'dependencies';
+    subject = dependencies[1] ?? dependencies[2] ?? dependencies[3] ?? normalized;
+  } else if (dependents !== null) {
+    intent = 'dependents';
+    subject = dependents[1] ?? dependents[2] ?? normalized;
+  } else if (/\b(?:files?|paths?)\b/.test(normalized) && kind === undefined) {
+    intent = 'files';
+  }
+
+  const target = quoted ?? subject
+    .replace(/[?!.,]/g, ' ')
+    .split(/\s+/)
+    .filter((word) => word.length > 0 && !STOP_WORDS.has(word) && KIND_SYNONYMS[word] === undefined && !(CODE_SYMBOL_KINDS as readonly string[]).includes(word))
+    .join(' ');
+
+  return {
+    intent,
+    target: target.length > 0 ? target : question.trim(),
+    ...(kind !== undefined && intent === 'symbols' ? { kind } : {}),
+  };
+}
+
+export async function answerProjectQuestion(request: {
+  question: string;
+  basePath: string;
+  limit?: number;
+  planner?: ProjectQueryPlanner;
+}): Promise<RuntimeProjectAnswer> {
+  const warnings: string[] = [];
+  let plan: ProjectQueryPlan | undefined;
+  let planner: RuntimeProjectAnswer['planner'] = 'heuristic';
+
+  if (request.planner !== undefined) {
+    try {
+      const content = await request.planner(buildProjectQueryPlannerPrompt(request.question));
+      if (content !== undefined) {
+        plan = parseProjectQueryPlan(content);
+        if (plan === undefined) {
+          warnings.push('Provider returned an unusable query plan; fell back to the heuristic planner.');
+        } else {
+          planner = 'provider';
+        }
+      }
+    } catch (error) {
+      warnings.push(`Query planning failed: ${error instanceof Error ? error.message : String(error)}`);
+    }
+  }
+  plan ??= planProjectQueryHeuristically(request.question);
+
+  const limit = request.limit ?? DEFAULT_REFERENCE_LIMIT;
+  const references = await executeProjectQueryPlan(plan, request.basePath, warnings);
+  const shown = references.slice(0, limit);
+
+  return {
+    question: request.question,
+    plan,
+    planner,
+    answer: describeAnswer(plan, references.length, shown.length),
+    references: shown,
+    warnings,
+  };
+}
+
+async function executeProjectQueryPlan(plan: ProjectQueryPlan, basePath: string, warnings: string[]): Promise<ProjectQueryReference[]> {
+  if (plan.intent === 'symbols') {
+    const snapshot = await readCodeIndex(basePath);
+    if (snapshot === undefined) {
+      warnings.push('No symbol index found. Run "ax code index" to answer symbol questions.');
+      return [];
+    }
+    const terms = plan.target.split(/\s+/).filter((term) => term.length > 0);
+    const seen = new Set<string>();
+    return terms.flatMap((term) => filterCodeSymbols(snapshot, {
+      query: term,
+      kind: plan.kind,
+      language: plan.language,
+      limit: Number.MAX_SAFE_INTEGER,
+    })).flatMap((symbol) => {
+      const key = `${symbol.file}:${symbol.line}:${symbol.name}`;
+      if (seen.has(key)) {
+        return [];
+      }
+      seen.add(key);
+      return [{
+        file: symbol.file,
+        line: symbol.line,
+        detail: `${symbol.kind} ${symbol.name}${symbol.container !== undefined ? ` in ${symbol.container}` : ''}`,
+      }];
+    });
+  }
+
+  const graph = await buildModuleGraph(basePath);
+  const matcher = createTargetMatcher(plan.target);
+
+  switch (plan.intent) {
+    case 'dependents':
+      return graph.modules.flatMap((module) => module.imports
+        .filter((entry) => matcher(entry.resolved ?? entry.specifier) && entry.resolved !== module.file)
+        .map((entry) => ({
+          file: module.file,
+          line: entry.line,
+          detail: `imports ${entry.specifier}${entry.resolved !== undefined && entry.resolved !== entry.specifier ? ` (${entry.resolved})` : ''}`,
+        })));
+    case 'dependencies':
+      return findModules(graph, matcher).flatMap((module) => module.imports.map((entry) => ({
+        file: module.file,
+        line: entry.line,
+        detail: `depends on ${entry.resolved ?? entry.specifier}`,
+      })));
+    case 'files':
+      return findModules(graph, matcher).map((module) => ({
+        file: module.file,
+        detail: `${module.imports.length} import(s)`,
+      }));
+  }
+}
+
+function findModules(graph: ModuleGraph, matcher: (value: string) => boolean): ModuleGraph['modules'] {
+  return graph.modules.filter((module) => matcher(module.file));
+}
+
+/**
+ * Matches "provider router" against `provider-router.ts`, `providerRouter`, or `provider_router/index.ts`.
+ */
+function createTargetMatcher(target: string): (value: string) => boolean {
+  const compact = compactIdentifier(target);
+  const tokens = target.toLowerCase().split(/[^a-z0-9]+/).filter((token) => token.length > 0);
+  return (value) => {
+    const candidate = compactIdentifier(value);
+    return (compact.length > 0 && candidate.includes(compact))
+      || (tokens.length > 1 && tokens.every((token) => candidate.includes(token)));
+  };
+}
+
+function compactIdentifier(value: string): string {
+  return value.toLowerCase().replace(/[^a-z0-9]/g, '');
+}
+
+function describeAnswer(plan: ProjectQueryPlan, total: number, shown: number): string {
+  const more = total > shown ? ` (showing ${shown})` : '';
+  if (total === 0) {
+    return `No grounded matches for "${plan.target}" (${plan.intent}) in this workspace.`;
+  }
+  switch (plan.intent) {
+    case 'dependents':
+      return `${total} import(s) of "${plan.target}" found${more}.`;
+    case 'dependencies':
+      return `Modules matching "${plan.target}" have ${total} import(s)${more}.`;
+    case 'files':
+      return `${total} file(s) match "${plan.target}"${more}.`;
+    case 'symbols':
+      return `${total} symbol(s) match "${plan.target}"${plan.kind !== undefined ? ` of kind ${plan.kind}` : ''}${more}.`;
+  }
+}
@@ -0,0 +1,224 @@
+import { readFile, realpath } from 'node:fs/promises';
+import { isAbsolute, join, relative, resolve, sep } from 'node:path';
+export const PROMPT_TEMPLATE_ERROR_CODE = 'PROMPT_TEMPLATE_INVALID';
+const SECTION_TAGS = ['if', 'unless', 'each', 'budget'];
+const CHARS_PER_TOKEN = 4;
+const MAX_INCLUDE_DEPTH = 8;
+/**
+ * Renders a handlebars-like prompt template:
+ *
+ * - `{{path.to.value}}` is replaced by the value, lists joined with commas and objects as JSON;
+ * - `{{#if path}}…{{else}}…{{/if}}` and `{{#unless path}}…{{/unless}}` keep a section or not;
+ * - `{{#each path}}…{{this}}…{{/each}}` repeats a section for each item of a list;
+ * - `{{> name}}` includes another template, rendered with the same variables;
+ * - `{{#budget 500}}…{{/budget}}` cuts its content to about 500 tokens, keeping the start
+ *   (`{{#budget 500 tail}}` keeps the end);
+ * - `{{! note}}` is a comment.
+ *
+ * A template that does not parse, or an include that cannot be found or includes itself, throws
+ * an error with the `PROMPT_TEMPLATE_INVALID` code.
+ */
+export async function renderPromptTemplate(template, variables, resolveInclude) {
+    const rendered = { text: '', includes: [], missingVariables: [], truncations: [] };
+    rendered.text = await renderNodes(parsePromptTemplate(template), variables, {
+        resolveInclude,
+        rendered,
+        includeChain: [],
+    });
+    return rendered;
+}
+function parsePromptTemplate(template) {
+    const root = [];
+    const open = [];
+    const target = () => {
+        const current = open.at(-1);
+        return current === undefined ? root : current.inElse ? current.node.otherwise : current.node.children;
+    };
+    const pattern = /\{\{\s*([\s\S]*?)\s*\}\}/g;
+    let position = 0;
+    let match;
+    while ((match = pattern.exec(template)) !== null) {
+        if (match.index > position) {
+            target().push({ kind: 'text', text: template.slice(position, match.index) });
+        }
+        position = match.index + match[0].length;
+        const tag = match[1];
+        if (tag.startsWith('!')) {
+            continue;
+        }
+        if (tag.startsWith('>')) {
+            const name = tag.slice(1).trim();
+            if (name.length === 0) {
+                throw templateError('An include tag "{{>}}" names no template.');
+            }
+            target().push({ kind: 'include', name });
+            continue;
+        }
+        if (tag.startsWith('#')) {
+            const [keyword = '', ...rest] = tag.slice(1).trim().split(/\s+/);
+            if (!SECTION_TAGS.includes(keyword)) {
+                throw templateError(`Unknown section "{{#${keyword}}}"; sections are ${SECTION_TAGS.map((name) => `#${name}`).join(', ')}.`);
+            }
+            const argument = rest.join(' ');
+            if (argument.length === 0) {
+                throw templateError(`Section "{{#${keyword}}}" needs ${keyword === 'budget' ? 'a token count' : 'a variable'}.`);
+            }
+            if (keyword === 'budget' && !/^\d+(\s+tail)?$/.test(argument)) {
+                throw templateError(`Section "{{#budget ${argument}}}" needs a token count, optionally followed by "tail".`);
+            }
+            const node = { kind: 'section', tag: keyword, argument, children: [], otherwise: [] };
+            target().push(node);
+            open.push({ node, inElse: false });
+            continue;
+        }
+        if (tag === 'else') {
+            const current = open.at(-1);
+            if (current === undefined || current.inElse || (current.node.tag !== 'if' && current.node.tag !== 'unless')) {
+                throw templateError('"{{else}}" is only allowed once inside {{#if}} or {{#unless}}.');
+            }
+            current.inElse = true;
+            continue;
+        }
+        if (tag.startsWith('/')) {
+            const keyword = tag.slice(1).trim();
+            const current = open.pop();
+            if (current === undefined || current.node.tag !== keyword) {
+                throw templateError(`"{{/${keyword}}}" closes ${current === undefined ? 'no open section' : `{{#${current.node.tag}}}`}.`);
+            }
+            continue;
+        }
+        target().push({ kind: 'variable', path: tag });
+    }
+    if (open.length > 0) {
+        throw templateError(`Section "{{#${open.at(-1).node.tag} ${open.at(-1).node.argument}}}" is never closed.`);
+    }
+    if (position < template.length) {
+        target().push({ kind: 'text', text: template.slice(position) });
+    }
+    return root;
+}
+async function renderNodes(nodes, scope, state) {
+    let text = '';
+    for (const node of nodes) {
+        text += await renderNode(node, scope, state);
+    }
+    return text;
+}
+async function renderNode(node, scope, state) {
+    switch (node.kind) {
+        case 'text':
+            return node.text;
+        case 'variable': {
+            const value = lookupTemplateVariable(scope, node.path);
+            if (value === undefined) {
+                if (!state.rendered.missingVariables.includes(node.path)) {
+                    state.rendered.missingVariables.push(node.path);
+                }
+                return '';
+            }
+            return formatValue(value);
+        }
+        case 'include': {
+            if (state.includeChain.includes(node.name)) {
+                throw templateError(`Include "${node.name}" includes itself (${[...state.includeChain, node.name].join(' > ')}).`);
+            }
+            if (state.includeChain.length >= MAX_INCLUDE_DEPTH) {
+                throw templateError(`Includes nest deeper than ${MAX_INCLUDE_DEPTH} levels at "${node.name}".`);
+            }
+            const included = await state.resolveInclude(node.name);
+            if (included === undefined) {
+                throw templateError(`Include "${node.name}" was not found.`);
+            }
+            if (!state.rendered.includes.includes(node.name)) {
+                state.rendered.includes.push(node.name);
+            }
+            return renderNodes(parsePromptTemplate(included), scope, { ...state, includeChain: [...state.includeChain, node.name] });
+        }
+        case 'section':
+            return renderSection(node, scope, state);
+    }
+}
+async function renderSection(node, scope, state) {
+    switch (node.tag) {
+        case 'if':
+        case 'unless': {
+            const shown = isTruthy(lookupTemplateVariable(scope, node.argument)) === (node.tag === 'if');
+            return renderNodes(shown ? node.children : node.otherwise, scope, state);
+        }
+        case 'each': {
+            const items = lookupTemplateVariable(scope, node.argument);
+            if (!Array.isArray(items)) {
+                return '';
+            }
+            let text = '';
+            for (const [index, item] of items.entries()) {
+                text += await renderNodes(node.children, { ...scope, this: item, '@index': index }, state);
+            }
+            return text;
+        }
+        case 'budget': {
+            const [count = '0', keep] = node.argument.split(/\s+/);
+            const budgetTokens = Number.parseInt(count, 10);
+            const text = await renderNodes(node.children, scope, state);
+            const originalTokens = Math.ceil(text.length / CHARS_PER_TOKEN);
+            if (originalTokens <= budgetTokens) {
+                return text;
+            }
+            state.rendered.truncations.push({ budgetTokens, originalTokens });
+            const marker = `[... cut to about ${budgetTokens} tokens ...]`;
+            const kept = Math.max(0, budgetTokens * CHARS_PER_TOKEN - marker.length - 1);
+            return keep === 'tail' ? `${marker}\n${text.slice(text.length - kept)}` : `${text.slice(0, kept)}\n${marker}`;
+        }
+    }
+}
+/**
+ * Finds the includes of agent prompt templates. A name with a `/` or a `.md` or `.txt` extension
+ * is a workspace file; any other name is the ability file `.automatosx/abilities/<name>.md`, else
+ * the built-in ability with that id. Files outside the workspace are refused.
+ */
+export function createAbilityIncludeResolver(basePath, builtinAbilities) {
+    return async (name) => {
+        const isFile = name.includes('/') || /\.(md|txt)$/i.test(name);
+        const text = await readWorkspaceFile(basePath, isFile ? name : join('.automatosx', 'abilities', `${name}.md`));
+        return text ?? (isFile ? undefined : builtinAbilities[name]);
+    };
+}
+async function readWorkspaceFile(basePath, path) {
+    let root;
+    let file;
+    try {
+        root = await realpath(basePath);
+        file = await realpath(resolve(root, path));
+    }
+    catch {
+        return undefined;
+    }
+    const inside = relative(root, file);
+    if (inside === '..' || inside.startsWith(`..${sep}`) || isAbsolute(inside)) {
+        throw templateError(`Include "${path}" is outside the workspace.`);
+    }
+    return readFile(file, 'utf8').catch(() => undefined);
+}
+/** The value at a dotted path of the variables, such as `this.x` inside {{#each}}; undefined when there is none. */
+export function lookupTemplateVariable(scope, path) {
+    let current = scope;
+    for (const part of path.split('.')) {
+        if (typeof current !== 'object' || current === null || !Object.prototype.hasOwnProperty.call(current, part)) {
+            return undefined;
+        }
+        current = current[part];
+    }
+    return current;
+}
+function isTruthy(value) {
+    return Array.isArray(value) ? value.length > 0 : Boolean(value);
+}
+function formatValue(value) {
+    if (Array.isArray(value)) {
+        return value.map((item) => typeof item === 'object' && item !== null ? JSON.stringify(item) : String(item)).join(', ');
+    }
+    return typeof value === 'object' && value !== null ? JSON.stringify(value, null, 2) : String(value);
+}
+function templateError(message) {
+    return Object.assign(new Error(message), { code: PROMPT_TEMPLATE_ERROR_CODE });
+}
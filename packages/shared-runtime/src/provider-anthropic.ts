@@ -0,0 +1,212 @@
+import type { ProviderExecutionRequest, ProviderExecutionResponse, ProviderToolCall } from './provider-bridge.js';
+import type { AdaptedProviderPrompt } from './provider-prompt.js';
+import {
+  asCount,
+  createProviderCallSignal,
+  describeHttpError,
+  describeHttpException,
+  httpFailure,
+  MAX_TOOL_ROUNDS,
+  postJson,
+  readServerSentEvents,
+  resolveApiModel,
+  runProviderTool,
+  type ProviderHttpConfig,
+} from './provider-http.js';
+import { asRecord } from './records.js';
+
+export const ANTHROPIC_DEFAULT_BASE_URL = 'https://api.anthropic.com';
+export const ANTHROPIC_DEFAULT_MODEL = 'claude-sonnet-4-5';
+export const ANTHROPIC_VERSION = '2023-06-01';
+// The Messages API requires `max_tokens`.
+const ANTHROPIC_DEFAULT_MAX_TOKENS = 4096;
+
+type AnthropicContentBlock =
+  | { type: 'text'; text: string }
+  | { type: 'tool_use'; id: string; name: string; input: Record<string, unknown> };
+
+type AnthropicMessage =
+  | { role: 'user'; content: string | Array<{ type: 'tool_result'; tool_use_id: string; content: string; is_error?: boolean }> }
+  | { role: 'assistant'; content: AnthropicContentBlock[] };
+
+/** Where a Messages request goes; Vertex AI serves the same API at its own URL. */
+export interface AnthropicEndpoint {
+  url: string;
+  headers: Record<string, string>;
+  /** Vertex AI: the version goes in the body as `anthropic_version`, and the model is in the URL. */
+  bodyVersion?: string;
+}
+
+interface AnthropicTurn {
+  blocks: AnthropicContentBlock[];
+  stopReason?: string;
+  inputTokens: number;
+  outputTokens: number;
+  error?: string;
+}
+
+/**
+ * Calls the Messages API with a streamed reply. Text is passed to `request.onText` as it
+ * arrives; when the model asks for one of `request.tools`, the tool runs and its result is sent
+ * back, until the model answers without tools. Usage is summed over every round.
+ */
+export async function executeAnthropicMessages(
+  config: ProviderHttpConfig,
+  request: ProviderExecutionRequest,
+  adapted: AdaptedProviderPrompt,
+  endpoint: AnthropicEndpoint = {
+    url: `${config.baseUrl.replace(/\/+$/, '')}/v1/messages`,
+    headers: { 'x-api-key': config.apiKey, 'anthropic-version': ANTHROPIC_VERSION },
+  },
+): Promise<ProviderExecutionResponse> {
+  const startedAt = Date.now();
+  const model = resolveApiModel(request.model, config.model);
+  const maxTokens = request.maxTokens ?? config.maxTokens ?? ANTHROPIC_DEFAULT_MAX_TOKENS;
+  const timeoutMs = request.timeoutMs ?? config.timeoutMs;
+  const tools = request.tools ?? [];
+  const messages: AnthropicMessage[] = [{ role: 'user', content: adapted.prompt }];
+  const toolCalls: ProviderToolCall[] = [];
+  let inputTokens = 0;
+  let outputTokens = 0;
+  const call = createProviderCallSignal(request.signal, timeoutMs);
+
+  try {
+    for (let round = 1; ; round += 1) {
+      const response = await postJson(endpoint.url, endpoint.headers, {
+        ...(endpoint.bodyVersion === undefined ? { model } : { anthropic_version: endpoint.bodyVersion }),
+        max_tokens: maxTokens,
+        system: adapted.systemPrompt,
+        messages,
+        temperature: request.temperature,
+        stop_sequences: adapted.stopSequences,
+        tools: tools.length === 0 ? undefined : tools.map((tool) => ({
+          name: tool.name,
+          description: tool.description,
+          input_schema: tool.inputSchema,
+        })),
+        stream: true,
+      }, call.signal);
+      if (!response.ok) {
+        const { errorCode, error, retryAfterMs } = await describeHttpError(response, 'Anthropic API');
+        return httpFailure(request, model, startedAt, errorCode, error, retryAfterMs);
+      }
+      if (response.body === null) {
+        return httpFailure(request, model, startedAt, 'PROVIDER_EMPTY_RESPONSE', 'Anthropic API returned no body.');
+      }
+
+      const turn = await readAnthropicStream(response.body, request.onText);
+      inputTokens += turn.inputTokens;
+      outputTokens += turn.outputTokens;
+      if (turn.error !== undefined) {
+        return httpFailure(request, model, startedAt, 'PROVIDER_STREAM_ERROR', `Anthropic API stream failed: ${turn.error}`);
+      }
+
+      const uses = turn.blocks.filter((block): block is Extract<AnthropicContentBlock, { type: 'tool_use' }> => block.type === 'tool_use');
+      if (turn.stopReason !== 'tool_use' || uses.length === 0) {
+        const warnings = [
+          ...adapted.warnings,
+          ...(turn.stopReason === 'max_tokens' ? [`The reply was cut off at the token limit (${maxTokens}).`] : []),
+        ];
+        return {
+          success: true,
+          content: turn.blocks.map((block) => block.type === 'text' ? block.text : '').join(''),
+          provider: request.provider,
+          model,
+          latencyMs: Date.now() - startedAt,
+          usage: { inputTokens, outputTokens, totalTokens: inputTokens + outputTokens },
+          ...(toolCalls.length > 0 ? { toolCalls } : {}),
+          ...(warnings.length > 0 ? { warnings } : {}),
+          mode: 'http',
+        };
+      }
+      if (round >= MAX_TOOL_ROUNDS) {
+        return httpFailure(request, model, startedAt, 'PROVIDER_TOOL_LOOP', `The model was still calling tools after ${MAX_TOOL_ROUNDS} rounds.`);
+      }
+
+      messages.push({ role: 'assistant', content: turn.blocks });
+      const results: Array<{ type: 'tool_result'; tool_use_id: string; content: string; is_error?: boolean }> = [];
+      for (const use of uses) {
+        const result = await runProviderTool(tools, use.name, use.input);
+        toolCalls.push({ name: use.name, input: use.input, ...(result.isError ? { isError: true } : {}) });
+        results.push({ type: 'tool_result', tool_use_id: use.id, content: result.content, ...(result.isError ? { is_error: true } : {}) });
+      }
+      messages.push({ role: 'user', content: results });
+    }
+  } catch (error) {
+    return describeHttpException(request, model, startedAt, call, timeoutMs, error);
+  } finally {
+    call.dispose();
+  }
+}
+
+async function readAnthropicStream(
+  body: ReadableStream<Uint8Array>,
+  onText: ((text: string) => void) | undefined,
+): Promise<AnthropicTurn> {
+  const turn: AnthropicTurn = { blocks: [], inputTokens: 0, outputTokens: 0 };
+  const blocks = new Map<number, AnthropicContentBlock>();
+  const partialInputs = new Map<number, string>();
+
+  for await (const event of readServerSentEvents(body)) {
+    let data: Record<string, unknown>;
+    try {
+      data = asRecord(JSON.parse(event.data)) ?? {};
+    } catch {
+      continue;
+    }
+    const index = asCount(data.index);
+    const delta = asRecord(data.delta) ?? {};
+    switch (data.type) {
+      case 'message_start': {
+        const usage = asRecord(asRecord(data.message)?.usage) ?? {};
+        turn.inputTokens = asCount(usage.input_tokens);
+        turn.outputTokens = asCount(usage.output_tokens);
+        break;
+      }
+      case 'content_block_start': {
+        const block = asRecord(data.content_block) ?? {};
+        if (block.type === 'text') {
+          blocks.set(index, { type: 'text', text: typeof block.text === 'string' ? block.text : '' });
+        } else if (block.type === 'tool_use') {
+          blocks.set(index, { type: 'tool_use', id: String(block.id), name: String(block.name), input: {} });
+        }
+        break;
+      }
+      case 'content_block_delta': {
+        const block = blocks.get(index);
+        if (delta.type === 'text_delta' && block?.type === 'text' && typeof delta.text === 'string') {
+          block.text += delta.text;
+          onText?.(delta.text);
+        } else if (delta.type === 'input_json_delta' && typeof delta.partial_json === 'string') {
+          partialInputs.set(index, (partialInputs.get(index) ?? '') + delta.partial_json);
+        }
+        break;
+      }
+      case 'content_block_stop': {
+        const block = blocks.get(index);
+        const partial = partialInputs.get(index);
+        if (block?.type === 'tool_use' && partial !== undefined && partial.trim().length > 0) {
+          try {
+            block.input = asRecord(JSON.parse(partial)) ?? {};
+          } catch {
+            block.input = {};
+          }
+        }
+        break;
+      }
+      case 'message_delta':
+        turn.stopReason = typeof delta.stop_reason === 'string' ? delta.stop_reason : turn.stopReason;
+        // The count here is the message's total so far, not an increment.
+        turn.outputTokens = asCount(asRecord(data.usage)?.output_tokens) || turn.outputTokens;
+        break;
+      case 'error': {
+        const message = asRecord(data.error)?.message;
+        turn.error = typeof message === 'string' ? message : 'unknown error';
+        break;
+      }
+    }
+  }
+
+  turn.blocks = [...blocks.entries()].sort(([left], [right]) => left - right).map(([, block]) => block);
+  return turn;
+}
@@ -0,0 +1,179 @@
+import { createHash, createHmac } from 'node:crypto';
+import { readFile } from 'node:fs/promises';
+import { homedir } from 'node:os';
+import { join } from 'node:path';
+
+export interface AwsCredentials {
+  accessKeyId: string;
+  secretAccessKey: string;
+  sessionToken?: string;
+}
+
+export interface AwsEventStreamMessage {
+  headers: Record<string, string>;
+  payload: Uint8Array;
+}
+
+/**
+ * Static credentials from `AWS_ACCESS_KEY_ID`/`AWS_SECRET_ACCESS_KEY` (and `AWS_SESSION_TOKEN`),
+ * else from the `profile` (or `AWS_PROFILE`, else `default`) section of the shared credentials
+ * file. SSO and instance roles are not followed; export their credentials into the environment.
+ */
+export async function resolveAwsCredentials(env: NodeJS.ProcessEnv, profile?: string): Promise<AwsCredentials | undefined> {
+  if (profile === undefined && env.AWS_ACCESS_KEY_ID !== undefined && env.AWS_SECRET_ACCESS_KEY !== undefined) {
+    return {
+      accessKeyId: env.AWS_ACCESS_KEY_ID,
+      secretAccessKey: env.AWS_SECRET_ACCESS_KEY,
+      ...(env.AWS_SESSION_TOKEN !== undefined ? { sessionToken: env.AWS_SESSION_TOKEN } : {}),
+    };
+  }
+  const path = env.AWS_SHARED_CREDENTIALS_FILE ?? join(env.HOME ?? homedir(), '.aws', 'credentials');
+  let raw: string;
+  try {
+    raw = await readFile(path, 'utf8');
+  } catch {
+    return undefined;
+  }
+  const section = readIniSection(raw, profile ?? env.AWS_PROFILE ?? 'default');
+  return section.aws_access_key_id !== undefined && section.aws_secret_access_key !== undefined
+    ? {
+      accessKeyId: section.aws_access_key_id,
+      secretAccessKey: section.aws_secret_access_key,
+      ...(section.aws_session_token !== undefined ? { sessionToken: section.aws_session_token } : {}),
+    }
+    : undefined;
+}
+
+/**
+ * Signature Version 4: the headers to send with the request, `authorization` and `x-amz-date`
+ * included. Path segments are encoded twice, as every service but S3 expects.
+ */
+export function signAwsRequest(
+  request: { method: string; url: string; headers: Record<string, string>; body: string },
+  credentials: AwsCredentials,
+  options: { region: string; service: string; now?: Date },
+): Record<string, string> {
+  const url = new URL(request.url);
+  const amzDate = (options.now ?? new Date()).toISOString().replace(/[-:]|\.\d{3}/g, '');
+  const date = amzDate.slice(0, 8);
+  const headers: Record<string, string> = {
+    ...Object.fromEntries(Object.entries(request.headers).map(([name, value]) => [name.toLowerCase(), value])),
+    'x-amz-date': amzDate,
+    ...(credentials.sessionToken !== undefined ? { 'x-amz-security-token': credentials.sessionToken } : {}),
+  };
+  const signed = { ...headers, host: url.host };
+  const names = Object.keys(signed).sort();
+  const canonicalRequest = [
+    request.method,
+    url.pathname.split('/').map(encodeAwsComponent).join('/') || '/',
+    [...url.searchParams]
+      .map(([name, value]) => [encodeAwsComponent(name), encodeAwsComponent(value)] as const)
+      .sort(([leftName, leftValue], [rightName, rightValue]) => leftName === rightName ? compare(leftValue, rightValue) : compare(leftName, rightName))
+      .map(([name, value]) => `${name}=${value}`)
+      .join('&'),
+    names.map((name) => `${name}:${signed[name]!.trim().replace(/\s+/g, ' ')}\n`).join(''),
+    names.join(';'),
+    sha256(request.body),
+  ].join('\n');
+  const scope = `${date}/${options.region}/${options.service}/aws4_request`;
+  const stringToSign = ['AWS4-HMAC-SHA256', amzDate, scope, sha256(canonicalRequest)].join('\n');
+  const key = [date, options.region, options.service, 'aws4_request']
+    .reduce<Buffer | string>((current, part) => createHmac('sha256', current).update(part).digest(), `AWS4${credentials.secretAccessKey}`);
+  const signature = createHmac('sha256', key).update(stringToSign).digest('hex');
+  return {
+    ...headers,
+    authorization: `AWS4-HMAC-SHA256 Credential=${credentials.accessKeyId}/${scope}, SignedHeaders=${names.join(';')}, Signature=${signature}`,
+  };
+}
+
+/**
+ * Yields the messages of an `application/vnd.amazon.eventstream` body: a length prelude, typed
+ * headers, the payload and a checksum per message. Checksums are not verified; TLS covers that.
+ */
+export async function* readAwsEventStream(body: ReadableStream<Uint8Array>): AsyncGenerator<AwsEventStreamMessage> {
+  const reader = body.getReader();
+  let buffer = new Uint8Array(0);
+  try {
+    for (;;) {
+      const { done, value } = await reader.read();
+      if (value !== undefined) {
+        const next = new Uint8Array(buffer.length + value.length);
+        next.set(buffer);
+        next.set(value, buffer.length);
+        buffer = next;
+      }
+      while (buffer.length >= 12) {
+        const view = new DataView(buffer.buffer, buffer.byteOffset, buffer.byteLength);
+        const totalLength = view.getUint32(0);
+        if (buffer.length < totalLength) {
+          break;
+        }
+        const headersLength = view.getUint32(4);
+        yield {
+          headers: readEventHeaders(buffer.subarray(12, 12 + headersLength)),
+          payload: buffer.slice(12 + headersLength, totalLength - 4),
+        };
+        buffer = buffer.slice(totalLength);
+      }
+      if (done) {
+        return;
+      }
+    }
+  } finally {
+    reader.releaseLock();
+  }
+}
+
+// Only string headers (type 7) carry what callers read; the others are skipped by size.
+function readEventHeaders(bytes: Uint8Array): Record<string, string> {
+  const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  const decoder = new TextDecoder();
+  const headers: Record<string, string> = {};
+  let offset = 0;
+  while (offset < bytes.length) {
+    const nameLength = view.getUint8(offset);
+    const name = decoder.decode(bytes.subarray(offset + 1, offset + 1 + nameLength));
+    offset += 1 + nameLength;
+    const type = view.getUint8(offset);
+    offset += 1;
+    if (type === 6 || type === 7) {
+      const length = view.getUint16(offset);
+      if (type === 7) {
+        headers[name] = decoder.decode(bytes.subarray(offset + 2, offset + 2 + length));
+      }
+      offset += 2 + length;
+    } else {
+      offset += [0, 0, 1, 2, 4, 8, 0, 0, 8, 16][type] ?? bytes.length;
+    }
+  }
+  return headers;
+}
+
+function readIniSection(raw: string, name: string): Record<string, string> {
+  const values: Record<string, string> = {};
+  let inside = false;
+  for (const line of raw.split(/\r?\n/)) {
+    const header = /^\s*\[\s*(?:profile\s+)?([^\]]+?)\s*\]\s*$/.exec(line);
+    if (header !== null) {
+      inside = header[1] === name;
+      continue;
+    }
+    const entry = /^\s*([\w.-]+)\s*=\s*(.*?)\s*$/.exec(line);
+    if (inside && entry !== null) {
+      values[entry[1]!] = entry[2]!;
+    }
+  }
+  return values;
+}
+
+function encodeAwsComponent(value: string): string {
+  return encodeURIComponent(value).replace(/[!'()*]/g, (character) => `%${character.charCodeAt(0).toString(16).toUpperCase()}`);
+}
+
+function sha256(value: string): string {
+  return createHash('sha256').update(value, 'utf8').digest('hex');
+}
+
+function compare(left: string, right: string): number {
+  return left < right ? -1 : left > right ? 1 : 0;
+}
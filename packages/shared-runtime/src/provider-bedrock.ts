@@ -0,0 +1,233 @@
+import { readAwsEventStream, resolveAwsCredentials, signAwsRequest, type AwsCredentials } from './provider-aws.js';
+import type { ProviderExecutionRequest, ProviderExecutionResponse, ProviderToolCall } from './provider-bridge.js';
+import type { AdaptedProviderPrompt } from './provider-prompt.js';
+import {
+  asCount,
+  createProviderCallSignal,
+  describeHttpError,
+  describeHttpException,
+  httpFailure,
+  MAX_TOOL_ROUNDS,
+  resolveApiModel,
+  runProviderTool,
+  type ProviderHttpConfig,
+} from './provider-http.js';
+import { asRecord } from './records.js';
+
+export const BEDROCK_DEFAULT_MODEL = 'anthropic.claude-3-5-sonnet-20240620-v1:0';
+export const BEDROCK_DEFAULT_REGION = 'us-east-1';
+
+type BedrockContentBlock =
+  | { text: string }
+  | { toolUse: { toolUseId: string; name: string; input: Record<string, unknown> } }
+  | { toolResult: { toolUseId: string; content: Array<{ text: string }>; status?: 'error' } };
+
+interface BedrockMessage {
+  role: 'user' | 'assistant';
+  content: BedrockContentBlock[];
+}
+
+interface BedrockTurn {
+  blocks: BedrockContentBlock[];
+  stopReason?: string;
+  inputTokens: number;
+  outputTokens: number;
+  error?: string;
+}
+
+export function bedrockBaseUrl(region: string): string {
+  return `https://bedrock-runtime.${region}.amazonaws.com`;
+}
+
+/**
+ * Calls Bedrock's ConverseStream API, which speaks one format for every model family it hosts.
+ * Requests are signed with SigV4 from the AWS credential chain, or carry
+ * `AWS_BEARER_TOKEN_BEDROCK` when that is set. Tools and usage work as for the Anthropic API.
+ */
+export async function executeBedrockConverse(
+  config: ProviderHttpConfig,
+  request: ProviderExecutionRequest,
+  adapted: AdaptedProviderPrompt,
+): Promise<ProviderExecutionResponse> {
+  const startedAt = Date.now();
+  const env = config.env ?? process.env;
+  const model = resolveApiModel(request.model, config.model);
+  const region = config.region ?? BEDROCK_DEFAULT_REGION;
+  const maxTokens = request.maxTokens ?? config.maxTokens;
+  const timeoutMs = request.timeoutMs ?? config.timeoutMs;
+  const tools = request.tools ?? [];
+  const url = `${config.baseUrl.replace(/\/+$/, '')}/model/${encodeURIComponent(model)}/converse-stream`;
+
+  const bearerToken = (env.AWS_BEARER_TOKEN_BEDROCK ?? '').trim();
+  let credentials: AwsCredentials | undefined;
+  if (bearerToken.length === 0) {
+    credentials = await resolveAwsCredentials(env, config.profile);
+    if (credentials === undefined) {
+      return httpFailure(
+        request,
+        model,
+        startedAt,
+        'PROVIDER_CREDENTIALS_MISSING',
+        `Provider "${request.provider}" calls Bedrock, but no AWS credentials were found${config.profile === undefined ? '' : ` for profile "${config.profile}"`}. Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, AWS_PROFILE, or AWS_BEARER_TOKEN_BEDROCK.`,
+      );
+    }
+  }
+
+  const messages: BedrockMessage[] = [{ role: 'user', content: [{ text: adapted.prompt }] }];
+  const toolCalls: ProviderToolCall[] = [];
+  let inputTokens = 0;
+  let outputTokens = 0;
+  const call = createProviderCallSignal(request.signal, timeoutMs);
+
+  try {
+    for (let round = 1; ; round += 1) {
+      // Signed requests must send exactly the bytes that were hashed.
+      const body = JSON.stringify({
+        messages,
+        system: adapted.systemPrompt === undefined ? undefined : [{ text: adapted.systemPrompt }],
+        inferenceConfig: {
+          maxTokens,
+          temperature: request.temperature,
+          stopSequences: adapted.stopSequences,
+        },
+        toolConfig: tools.length === 0 ? undefined : {
+          tools: tools.map((tool) => ({
+            toolSpec: { name: tool.name, description: tool.description, inputSchema: { json: tool.inputSchema } },
+          })),
+        },
+      });
+      const headers = credentials === undefined
+        ? { 'content-type': 'application/json', authorization: `Bearer ${bearerToken}` }
+        : signAwsRequest({ method: 'POST', url, headers: { 'content-type': 'application/json' }, body }, credentials, { region, service: 'bedrock' });
+      const response = await fetch(url, { method: 'POST', headers, body, signal: call.signal });
+      if (!response.ok) {
+        const { errorCode, error, retryAfterMs } = await describeHttpError(response, 'Bedrock');
+        return httpFailure(request, model, startedAt, errorCode, error, retryAfterMs);
+      }
+      if (response.body === null) {
+        return httpFailure(request, model, startedAt, 'PROVIDER_EMPTY_RESPONSE', 'Bedrock returned no body.');
+      }
+
+      const turn = await readConverseStream(response.body, request.onText);
+      inputTokens += turn.inputTokens;
+      outputTokens += turn.outputTokens;
+      if (turn.error !== undefined) {
+        return httpFailure(request, model, startedAt, 'PROVIDER_STREAM_ERROR', `Bedrock stream failed: ${turn.error}`);
+      }
+
+      const uses = turn.blocks.flatMap((block) => 'toolUse' in block ? [block.toolUse] : []);
+      if (turn.stopReason !== 'tool_use' || uses.length === 0) {
+        const warnings = [
+          ...adapted.warnings,
+          ...(turn.stopReason === 'max_tokens' ? [`The reply was cut off at the token limit${maxTokens === undefined ? '' : ` (${maxTokens})`}.`] : []),
+        ];
+        return {
+          success: true,
+          content: turn.blocks.map((block) => 'text' in block ? block.text : '').join(''),
+          provider: request.provider,
+          model,
+          latencyMs: Date.now() - startedAt,
+          usage: { inputTokens, outputTokens, totalTokens: inputTokens + outputTokens },
+          ...(toolCalls.length > 0 ? { toolCalls } : {}),
+          ...(warnings.length > 0 ? { warnings } : {}),
+          mode: 'http',
+        };
+      }
+      if (round >= MAX_TOOL_ROUNDS) {
+        return httpFailure(request, model, startedAt, 'PROVIDER_TOOL_LOOP', `The model was still calling tools after ${MAX_TOOL_ROUNDS} rounds.`);
+      }
+
+      messages.push({ role: 'assistant', content: turn.blocks });
+      const results: BedrockContentBlock[] = [];
+      for (const use of uses) {
+        const result = await runProviderTool(tools, use.name, use.input);
+        toolCalls.push({ name: use.name, input: use.input, ...(result.isError ? { isError: true } : {}) });
+        results.push({
+          toolResult: { toolUseId: use.toolUseId, content: [{ text: result.content }], ...(result.isError ? { status: 'error' as const } : {}) },
+        });
+      }
+      messages.push({ role: 'user', content: results });
+    }
+  } catch (error) {
+    return describeHttpException(request, model, startedAt, call, timeoutMs, error);
+  } finally {
+    call.dispose();
+  }
+}
+
+// Each event-stream message names its event in `:event-type`; exceptions arrive as messages
+// whose `:message-type` is `exception`.
+async function readConverseStream(
+  body: ReadableStream<Uint8Array>,
+  onText: ((text: string) => void) | undefined,
+): Promise<BedrockTurn> {
+  const turn: BedrockTurn = { blocks: [], inputTokens: 0, outputTokens: 0 };
+  const blocks = new Map<number, BedrockContentBlock>();
+  const partialInputs = new Map<number, string>();
+  const decoder = new TextDecoder();
+
+  for await (const message of readAwsEventStream(body)) {
+    let data: Record<string, unknown>;
+    try {
+      data = asRecord(JSON.parse(decoder.decode(message.payload))) ?? {};
+    } catch {
+      continue;
+    }
+    if (message.headers[':message-type'] === 'exception' || message.headers[':message-type'] === 'error') {
+      const type = message.headers[':exception-type'] ?? message.headers[':error-code'];
+      const text = typeof data.message === 'string' ? data.message : 'unknown error';
+      turn.error = type === undefined ? text : `${type}: ${text}`;
+      break;
+    }
+    const index = asCount(data.contentBlockIndex);
+    switch (message.headers[':event-type']) {
+      case 'contentBlockStart': {
+        const toolUse = asRecord(asRecord(data.start)?.toolUse) ?? {};
+        if (typeof toolUse.name === 'string') {
+          blocks.set(index, { toolUse: { toolUseId: String(toolUse.toolUseId), name: toolUse.name, input: {} } });
+        }
+        break;
+      }
+      case 'contentBlockDelta': {
+        const delta = asRecord(data.delta) ?? {};
+        if (typeof delta.text === 'string') {
+          const block = blocks.get(index) ?? { text: '' };
+          if ('text' in block) {
+            block.text += delta.text;
+            blocks.set(index, block);
+            onText?.(delta.text);
+          }
+        }
+        const input = asRecord(delta.toolUse)?.input;
+        if (typeof input === 'string') {
+          partialInputs.set(index, (partialInputs.get(index) ?? '') + input);
+        }
+        break;
+      }
+      case 'contentBlockStop': {
+        const block = blocks.get(index);
+        const partial = partialInputs.get(index);
+        if (block !== undefined && 'toolUse' in block && partial !== undefined && partial.trim().length > 0) {
+          try {
+            block.toolUse.input = asRecord(JSON.parse(partial)) ?? {};
+          } catch {
+            block.toolUse.input = {};
+          }
+        }
+        break;
+      }
+      case 'messageStop':
+        turn.stopReason = typeof data.stopReason === 'string' ? data.stopReason : turn.stopReason;
+        break;
+      case 'metadata': {
+        const usage = asRecord(data.usage) ?? {};
+        turn.inputTokens = asCount(usage.inputTokens);
+        turn.outputTokens = asCount(usage.outputTokens);
+        break;
+      }
+    }
+  }
+
+  turn.blocks = [...blocks.entries()].sort(([left], [right]) => left - right).map(([, block]) => block);
+  return turn;
+}
@@ -1,51 +1,520 @@
 import { spawn, spawnSync } from 'node:child_process';
-import { readFile } from 'node:fs/promises';
-import { join } from 'node:path';
+import { basename, join } from 'node:path';
+import { AGENT_PERMISSIONS_ENV_VAR } from './agent-permissions.js';
+import { readWorkspaceConfig } from './config-layers.js';
+import { createEmbedder, readEmbedderConfig } from './embeddings.js';
+import { ANTHROPIC_DEFAULT_BASE_URL, ANTHROPIC_DEFAULT_MODEL, executeAnthropicMessages, } from './provider-anthropic.js';
+import { BEDROCK_DEFAULT_MODEL, BEDROCK_DEFAULT_REGION, bedrockBaseUrl, executeBedrockConverse } from './provider-bedrock.js';
+import { readProviderCacheSettings } from './provider-cache.js';
+import { extractJsonReply, fitPromptToContext, isToolsUnsupportedError, resolveProviderCapabilities, runInlineTools, withJsonInstruction, } from './provider-capabilities.js';
+import { readConsensusDefaults, runConsensus } from './provider-consensus.js';
+import { buildContinuationPrompt, readFailoverSettings } from './provider-failover.js';
+import { createProviderHealthMonitor, probeProviderApi, readProviderHealthSettings, } from './provider-health.js';
+import { resolveApiModel } from './provider-http.js';
+import { executeOllamaChat, listOllamaModels, OLLAMA_DEFAULT_BASE_URL, OLLAMA_DEFAULT_MODEL, } from './provider-ollama.js';
+import { executeOpenAiChat, OPENAI_DEFAULT_BASE_URL, OPENAI_DEFAULT_MODEL } from './provider-openai.js';
+import { adaptProviderPrompt, resolvePromptDialect, } from './provider-prompt.js';
+import { createProviderQuotaTracker, readFallbackProviders, } from './provider-quota.js';
+import { createProviderRateLimiter, readRateLimitSettings, } from './provider-rate-limit.js';
+import { estimateCallCost, rankProviders, readModelPricing, readRoutingOptions } from './provider-routing.js';
+import { executeVertex, VERTEX_DEFAULT_MODEL, VERTEX_DEFAULT_REGION, vertexBaseUrl } from './provider-vertex.js';
+import { isReadOnlyEnv, READ_ONLY_ENV_VAR } from './read-only.js';
+import { asRecord } from './records.js';
+import { readStructuredOutputSettings, runStructuredOutput } from './structured-output.js';
 const DEFAULT_PROVIDER_TIMEOUT_MS = 30_000;
+const RATE_LIMIT_OUTPUT = /\b429\b|rate[ _-]?limit|too many requests/i;
+// An API call covers every tool round of the reply, so it gets longer than a CLI call.
+const DEFAULT_API_TIMEOUT_MS = 300_000;
+const PROVIDER_APIS = {
+    anthropic: {
+        execute: executeAnthropicMessages,
+        baseUrl: ANTHROPIC_DEFAULT_BASE_URL,
+        baseUrlEnv: 'ANTHROPIC_BASE_URL',
+        apiKeyEnv: 'ANTHROPIC_API_KEY',
+        model: ANTHROPIC_DEFAULT_MODEL,
+        promptDialect: 'claude',
+    },
+    openai: {
+        execute: executeOpenAiChat,
+        baseUrl: OPENAI_DEFAULT_BASE_URL,
+        baseUrlEnv: 'OPENAI_BASE_URL',
+        apiKeyEnv: 'OPENAI_API_KEY',
+        model: OPENAI_DEFAULT_MODEL,
+        promptDialect: 'openai',
+        jsonMode: true,
+    },
+    ollama: {
+        execute: executeOllamaChat,
+        baseUrl: OLLAMA_DEFAULT_BASE_URL,
+        baseUrlEnv: 'OLLAMA_HOST',
+        command: 'ollama',
+        model: OLLAMA_DEFAULT_MODEL,
+        promptDialect: 'plain',
+        jsonMode: true,
+    },
+    bedrock: {
+        execute: executeBedrockConverse,
+        baseUrl: bedrockBaseUrl,
+        baseUrlEnv: 'AWS_ENDPOINT_URL_BEDROCK_RUNTIME',
+        regionEnv: ['AWS_REGION', 'AWS_DEFAULT_REGION'],
+        region: BEDROCK_DEFAULT_REGION,
+        model: BEDROCK_DEFAULT_MODEL,
+        promptDialect: (model) => /(^|\.)anthropic\./.test(model) ? 'claude' : 'plain',
+    },
+    vertex: {
+        execute: executeVertex,
+        baseUrl: vertexBaseUrl,
+        baseUrlEnv: 'VERTEX_AI_BASE_URL',
+        regionEnv: ['GOOGLE_CLOUD_LOCATION', 'CLOUD_ML_REGION'],
+        region: VERTEX_DEFAULT_REGION,
+        projectEnv: ['GOOGLE_CLOUD_PROJECT', 'ANTHROPIC_VERTEX_PROJECT_ID'],
+        model: VERTEX_DEFAULT_MODEL,
+        promptDialect: (model) => /^claude-/.test(model) ? 'claude' : /^gemini-/.test(model) ? 'gemini' : 'plain',
+    },
+};
+// Used when native adapters are on, the provider's CLI is missing and the API key is set
+// (or, for Ollama, the server is installed).
+const PROVIDER_NATIVE_APIS = {
+    claude: 'anthropic',
+    anthropic: 'anthropic',
+    codex: 'openai',
+    openai: 'openai',
+    ollama: 'ollama',
+};
 const PROVIDER_NATIVE_COMMANDS = {
     claude: { command: 'claude', protocol: 'raw-stdin' },
     gemini: { command: 'gemini', protocol: 'raw-stdin' },
     codex: { command: 'codex', protocol: 'raw-stdin' },
     grok: { command: 'ax-grok', protocol: 'raw-stdin' },
 };
+// The flags that hold each provider CLI to what a run may do, by command name; `undefined` when the
+// CLI has none for it. Other commands learn it from the environment and the `json-stdio` payload.
+const PROVIDER_CLI_RESTRICTIONS = {
+    // Plan mode reads and answers without editing or running anything; the tools are denied as well.
+    // Commands Claude may run can reach the network, so those cannot be allowed without it.
+    claude: ({ readOnly, shell, network }) => {
+        const runsCommands = !readOnly && shell;
+        if (runsCommands && !network) {
+            return undefined;
+        }
+        const denied = [
+            ...(runsCommands ? [] : ['Bash']),
+            ...(readOnly ? ['Edit', 'MultiEdit', 'Write', 'NotebookEdit'] : []),
+            ...(network ? [] : ['WebFetch', 'WebSearch']),
+        ];
+        return [...(readOnly ? ['--permission-mode', 'plan'] : []), ...(denied.length > 0 ? [`--disallowedTools=${denied.join(',')}`] : [])];
+    },
+    // Codex always runs commands, in a sandbox that keeps them off the network unless full access is given.
+    codex: ({ readOnly, shell, network }) => !shell
+        ? undefined
+        : readOnly ? ['--sandbox', 'read-only'] : network ? [] : ['--sandbox', 'workspace-write'],
+    // Without a terminal, Gemini leaves out the tools it would ask about: its shell, edit and write
+    // tools by default, and the shell alone when it approves edits itself. Its web tools stay.
+    gemini: ({ readOnly, shell, network }) => !network
+        ? undefined
+        : readOnly ? ['--approval-mode', 'default'] : shell ? [] : ['--approval-mode', 'auto_edit'],
+    'ax-grok': ({ readOnly, shell, network }) => readOnly || !shell || !network ? undefined : [],
+};
 export function createProviderBridge(config) {
-    const env = config.env ?? process.env;
+    const env = config.readOnly === true
+        ? { ...(config.env ?? process.env), [READ_ONLY_ENV_VAR]: '1' }
+        : config.env ?? process.env;
     const executionMode = resolveExecutionMode(env);
+    const quotaTracker = createProviderQuotaTracker({ basePath: config.basePath, now: config.now });
+    const rateLimiter = createProviderRateLimiter({ basePath: config.basePath, now: config.now });
+    const healthMonitor = createProviderHealthMonitor({ basePath: config.basePath, now: config.now });
+    let cacheEmbedder;
+    // Semantic matches embed prompts with the workspace's `codeIntel.embeddings` backend.
+    const resolveCacheEmbedder = () => {
+        cacheEmbedder ??= readEmbedderConfig(config.basePath).then((configured) => configured === undefined ? undefined : createEmbedder(configured, { env }));
+        return cacheEmbedder;
+    };
+    // One call to one provider. A call of a run being replayed gets its recorded outcome and reaches
+    // no provider; a call of a run being recorded is added to the run's log.
+    const executeOnce = async (request) => {
+        const replayed = await config.recorder?.replay(request);
+        if (replayed !== undefined) {
+            return replayed;
+        }
+        const capture = await config.recorder?.capture(request);
+        const outcome = await executeCall(capture?.request ?? request, capture);
+        await capture?.finish(outcome);
+        return outcome;
+    };
+    // The cache, the rate limiter, and the quota and usage records around a call.
+    const executeCall = async (requested, capture) => {
+        // The JSON instruction is part of the prompt, and so of the cache key.
+        const request = requested.responseFormat === 'json' ? { ...requested, prompt: withJsonInstruction(requested.prompt) } : requested;
+        const providerConfig = await resolveProviderExecutor(config.basePath, request.provider, env);
+        if (providerConfig === undefined) {
+            if (executionMode === 'require-real') {
+                return {
+                    type: 'failure',
+                    response: {
+                        success: false,
+                        provider: request.provider,
+                        model: request.model,
+                        latencyMs: 0,
+                        errorCode: 'PROVIDER_EXECUTOR_NOT_CONFIGURED',
+                        error: `No provider executor configured for "${request.provider}". Set .automatosx/config.json or AUTOMATOSX_PROVIDER_<PROVIDER>_CMD.`,
+                        mode: 'subprocess',
+                    },
+                };
+            }
+            return {
+                type: 'unavailable',
+                error: `No provider executor configured for "${request.provider}".`,
+            };
+        }
+        const cacheSettings = config.responseCache === undefined ? undefined : readProviderCacheSettings(await readWorkspaceConfig(config.basePath));
+        const cacheCall = {
+            provider: request.provider,
+            model: 'api' in providerConfig
+                ? resolveApiModel(request.model, providerConfig.model)
+                : request.model !== undefined && !/^v14-/.test(request.model) ? request.model : undefined,
+            prompt: request.prompt,
+            systemPrompt: request.systemPrompt,
+            tools: request.tools,
+        };
+        const embedder = cacheSettings?.semanticThreshold === undefined ? undefined : await resolveCacheEmbedder();
+        const cacheWarnings = [];
+        if (config.responseCache !== undefined && cacheSettings !== undefined && request.noCache !== true) {
+            try {
+                const cached = await config.responseCache.lookup(cacheCall, cacheSettings, embedder);
+                if (cached !== undefined) {
+                    if (cached.content !== undefined) {
+                        request.onText?.(cached.content);
+                    }
+                    return { type: 'response', response: cached };
+                }
+            }
+            catch (error) {
+                cacheWarnings.push(`Response cache lookup failed: ${error instanceof Error ? error.message : String(error)}`);
+            }
+        }
+        const workspaceConfig = await readWorkspaceConfig(config.basePath);
+        const outcome = await rateLimiter.run(request.provider, readRateLimitSettings(workspaceConfig), request.signal, () => 'api' in providerConfig
+            ? executeProviderApi(providerConfig, request, env, workspaceConfig)
+            : executeProviderSubprocess(providerConfig, request, config.basePath, env));
+        if (request.responseFormat === 'json' && outcome.type === 'response' && outcome.response.success) {
+            const json = extractJsonReply(outcome.response.content ?? '');
+            if (json === undefined) {
+                outcome.response.warnings = [...(outcome.response.warnings ?? []), 'The reply was asked to be JSON but is not valid JSON; it was returned as written.'];
+            }
+            else {
+                outcome.response.content = json;
+            }
+        }
+        // A reply reached through tool calls depends on what the tools did, so replaying it would skip their effects.
+        if (config.responseCache !== undefined && cacheSettings !== undefined && outcome.type === 'response'
+            && outcome.response.success && (outcome.response.toolCalls ?? []).length === 0) {
+            try {
+                await config.responseCache.store(cacheCall, outcome.response, cacheSettings, embedder);
+            }
+            catch (error) {
+                cacheWarnings.push(`Could not cache the response: ${error instanceof Error ? error.message : String(error)}`);
+            }
+        }
+        if (cacheWarnings.length > 0 && outcome.type !== 'unavailable') {
+            outcome.response.warnings = [...(outcome.response.warnings ?? []), ...cacheWarnings];
+        }
+        if (outcome.type === 'response' && outcome.response.success) {
+            const warnings = await quotaTracker.recordUsage(request.provider, outcome.response.usage ?? {});
+            if (warnings.length > 0) {
+                outcome.response.warnings = [...(outcome.response.warnings ?? []), ...warnings];
+            }
+        }
+        if (config.usageTracker !== undefined && outcome.type !== 'unavailable') {
+            const { response } = outcome;
+            const model = 'api' in providerConfig ? response.model ?? providerConfig.model : response.model ?? request.model;
+            const inputTokens = response.usage?.inputTokens ?? 0;
+            const outputTokens = response.usage?.outputTokens ?? 0;
+            const pricing = readModelPricing(await readWorkspaceConfig(config.basePath));
+            const costUsd = estimateCallCost(pricing, {
+                provider: request.provider,
+                model,
+                inputTokens,
+                outputTokens,
+                local: 'api' in providerConfig && providerConfig.api === 'ollama',
+            });
+            capture?.setUsage({ model, inputTokens, outputTokens, costUsd });
+            try {
+                await config.usageTracker.record({
+                    provider: request.provider,
+                    model,
+                    agentId: request.agentId,
+                    traceId: request.traceId,
+                    rootTraceId: request.rootTraceId,
+                    project: config.basePath,
+                    inputTokens,
+                    outputTokens,
+                    latencyMs: response.latencyMs,
+                    costUsd,
+                    success: response.success,
+                });
+            }
+            catch (error) {
+                response.warnings = [
+                    ...(response.warnings ?? []),
+                    `Could not record provider usage: ${error instanceof Error ? error.message : String(error)}`,
+                ];
+            }
+        }
+        return outcome;
+    };
     return {
         getExecutionMode() {
             return executionMode;
         },
-        async executePrompt(request) {
-            const providerConfig = await resolveProviderCommand(config.basePath, request.provider, env);
-            if (providerConfig === undefined) {
-                if (executionMode === 'require-real') {
-                    return {
-                        type: 'failure',
-                        response: {
-                            success: false,
-                            provider: request.provider,
-                            model: request.model,
-                            latencyMs: 0,
-                            errorCode: 'PROVIDER_EXECUTOR_NOT_CONFIGURED',
-                            error: `No provider executor configured for "${request.provider}". Set .automatosx/config.json or AUTOMATOSX_PROVIDER_<PROVIDER>_CMD.`,
-                            mode: 'subprocess',
-                        },
-                    };
+        getQuotaStatus(providers) {
+            return quotaTracker.getStatus(providers);
+        },
+        /** Providers with calls queued, in flight behind a queue, or cooling down after a rate limit. */
+        getRateLimitStatus() {
+            return rateLimiter.getStatus();
+        },
+        /** The last health probe of each provider that has been probed. */
+        getHealthStatus() {
+            return healthMonitor.getStatus();
+        },
+        /**
+         * Probes each provider's executor and records the result: API executors get a round trip
+         * (credentials only for Bedrock and Vertex AI), CLI executors a check that the command is
+         * installed. Providers with no executor are left out.
+         */
+        async probeProviders(providers, signal) {
+            const settings = readProviderHealthSettings(await readWorkspaceConfig(config.basePath));
+            const results = await Promise.all([...new Set(providers)].map(async (provider) => {
+                const providerConfig = await resolveProviderExecutor(config.basePath, provider, env);
+                if (providerConfig === undefined) {
+                    return undefined;
+                }
+                return healthMonitor.record(provider, await probeProviderExecutor(providerConfig, settings, env, signal));
+            }));
+            return results.filter((result) => result !== undefined);
+        },
+        /**
+         * The provider to call when none is pinned: under `priority`, `preferred` then the fallback
+         * list; under another policy, the same providers ranked by their models' pricing. Either way a
+         * provider whose quota is exhausted is passed over.
+         */
+        async selectProvider(preferred, routing) {
+            const workspaceConfig = await readWorkspaceConfig(config.basePath);
+            const options = readRoutingOptions(workspaceConfig, routing);
+            const unhealthy = await healthMonitor.getUnhealthy(readProviderHealthSettings(workspaceConfig));
+            // A provider that failed its last health probe is passed over while a healthy candidate remains.
+            const selectHealthy = async (order) => {
+                const healthy = order.filter((provider) => !unhealthy.has(provider));
+                if (healthy.length === 0 || healthy[0] === order[0]) {
+                    return quotaTracker.selectProvider(order[0], (healthy.length === 0 ? order : healthy).slice(1));
                 }
+                const selection = await quotaTracker.selectProvider(healthy[0], healthy.slice(1));
+                const skipped = order.slice(0, order.indexOf(healthy[0]));
+                return {
+                    ...selection,
+                    warnings: [
+                        ...skipped.map((provider) => `Provider "${provider}" failed its last health probe${describeProbeFailure(unhealthy.get(provider))}; routed to "${selection.provider}".`),
+                        ...selection.warnings,
+                    ],
+                };
+            };
+            const priorityOrder = [...new Set([preferred, ...readFallbackProviders(workspaceConfig)])];
+            if (options.policy === 'priority') {
+                return selectHealthy(priorityOrder);
+            }
+            const candidates = await Promise.all(priorityOrder.map(async (provider) => {
+                const executor = await resolveProviderExecutor(config.basePath, provider, env);
+                return {
+                    provider,
+                    resolved: executor !== undefined,
+                    ...(executor !== undefined && 'api' in executor ? { model: executor.model, local: executor.api === 'ollama' } : {}),
+                };
+            }));
+            // With nothing configured every call is simulated; there is nothing to rank.
+            if (!candidates.some((candidate) => candidate.resolved)) {
+                return selectHealthy(priorityOrder);
+            }
+            const ranked = rankProviders(candidates, readModelPricing(workspaceConfig), options);
+            if (ranked.length === 0) {
+                const selection = await selectHealthy(priorityOrder);
+                return {
+                    ...selection,
+                    warnings: [...selection.warnings, `No configured provider meets the ${options.policy} routing requirements; used the priority order.`],
+                };
+            }
+            const selection = await selectHealthy(ranked.map((entry) => entry.provider));
+            const model = ranked.find((entry) => entry.provider === selection.provider)?.model;
+            return { ...selection, routing: { policy: options.policy, ...(model !== undefined ? { model } : {}) } };
+        },
+        async describeResolution(provider) {
+            const workspaceConfig = await readWorkspaceConfig(config.basePath);
+            const providerConfig = await resolveProviderExecutor(config.basePath, provider, env);
+            const details = {
+                provider,
+                lookupOrder: getProviderLookupOrder(provider),
+                executionMode,
+                nativeAdaptersEnabled: nativeAdaptersEnabled(workspaceConfig, env),
+                resolved: providerConfig !== undefined,
+                adapterSource: providerConfig?.adapterSource,
+                promptDialect: providerConfig?.promptDialect,
+                timeoutMs: providerConfig?.timeoutMs,
+            };
+            if (providerConfig === undefined) {
+                return details;
+            }
+            if ('api' in providerConfig) {
+                return {
+                    ...details,
+                    api: providerConfig.api,
+                    baseUrl: providerConfig.baseUrl,
+                    model: providerConfig.model,
+                    apiKeyEnv: providerConfig.apiKeyEnv,
+                    apiKeySet: providerConfig.apiKeyEnv === undefined ? undefined : (env[providerConfig.apiKeyEnv] ?? '').trim().length > 0,
+                    apiVersion: providerConfig.apiVersion,
+                    region: providerConfig.region,
+                    project: providerConfig.project,
+                    profile: providerConfig.profile,
+                    capabilities: resolveProviderCapabilities(workspaceConfig, provider, providerConfig.model, providerConfig.contextLength),
+                };
+            }
+            return {
+                ...details,
+                capabilities: resolveProviderCapabilities(workspaceConfig, provider, undefined),
+                command: providerConfig.command,
+                commandPath: locateCommand(providerConfig.command, env),
+                args: providerConfig.args,
+                protocol: providerConfig.protocol,
+                restrictedByFlags: PROVIDER_CLI_RESTRICTIONS[providerCliName(providerConfig.command)] !== undefined,
+            };
+        },
+        /**
+         * Sends the request to several providers at once and returns the fastest answer or the one a
+         * judge picks or merges. What `requested` leaves unset comes from `providers.consensus`; the
+         * providers default to the request's provider and the fallback list.
+         */
+        async executeConsensus(request, requested) {
+            const workspaceConfig = await readWorkspaceConfig(config.basePath);
+            const defaults = readConsensusDefaults(workspaceConfig);
+            return runConsensus((call) => this.executePrompt(call), request, {
+                providers: requested.providers ?? defaults.providers ?? [request.provider, ...readFallbackProviders(workspaceConfig)],
+                strategy: requested.strategy ?? defaults.strategy ?? 'judge',
+                judge: requested.judge ?? defaults.judge,
+            });
+        },
+        /**
+         * Calls the provider, or several under `consensus`, for a reply that fits `schema`. A reply
+         * that does not is sent back with its validation errors, as many times as
+         * `providers.structuredOutput.repairAttempts` allows.
+         */
+        async executeStructured(request, schema, consensus) {
+            const settings = readStructuredOutputSettings(await readWorkspaceConfig(config.basePath));
+            return runStructuredOutput((call) => consensus === undefined ? this.executePrompt(call) : this.executeConsensus(call, consensus), request, schema, settings);
+        },
+        /** The models an Ollama executor's server has; other executors cannot list theirs. */
+        async listModels(provider, signal) {
+            const providerConfig = await resolveProviderExecutor(config.basePath, provider, env);
+            if (providerConfig === undefined || !('api' in providerConfig) || providerConfig.api !== 'ollama') {
                 return {
-                    type: 'unavailable',
-                    error: `No provider executor configured for "${request.provider}".`,
+                    provider,
+                    ...(providerConfig !== undefined && 'api' in providerConfig ? { api: providerConfig.api, baseUrl: providerConfig.baseUrl } : {}),
+                    models: [],
+                    errorCode: 'PROVIDER_MODELS_UNSUPPORTED',
+                    error: providerConfig === undefined
+                        ? `No provider executor configured for "${provider}".`
+                        : `Provider "${provider}" is not an Ollama executor; only Ollama models can be listed.`,
+                };
+            }
+            const base = { provider, api: providerConfig.api, baseUrl: providerConfig.baseUrl };
+            try {
+                const apiKey = providerConfig.apiKeyEnv === undefined ? '' : (env[providerConfig.apiKeyEnv] ?? '').trim();
+                return { ...base, models: await listOllamaModels({ baseUrl: providerConfig.baseUrl, apiKey }, signal) };
+            }
+            catch (error) {
+                const cause = error.cause;
+                const message = error instanceof Error ? error.message : String(error);
+                return {
+                    ...base,
+                    models: [],
+                    errorCode: error.code ?? 'PROVIDER_UNREACHABLE',
+                    error: cause instanceof Error ? `${message}: ${cause.message}` : message,
                 };
             }
-            return executeProviderSubprocess(providerConfig, request, config.basePath, env);
+        },
+        /**
+         * Calls the provider. When it fails partway through a streamed reply, the fallback providers
+         * are asked in turn to continue from the text it had produced, and the parts are joined into
+         * one response that lists each switch in `failovers`.
+         */
+        async executePrompt(request) {
+            let partial = '';
+            const onText = (text) => {
+                partial += text;
+                request.onText?.(text);
+            };
+            let outcome = await executeOnce({ ...request, onText });
+            if (outcome.type !== 'failure' || partial.length === 0 || outcome.response.errorCode === 'PROVIDER_CANCELLED') {
+                return outcome;
+            }
+            const workspaceConfig = await readWorkspaceConfig(config.basePath);
+            const settings = readFailoverSettings(workspaceConfig);
+            if (!settings.enabled) {
+                return outcome;
+            }
+            const unhealthy = await healthMonitor.getUnhealthy(readProviderHealthSettings(workspaceConfig));
+            const candidates = readFallbackProviders(workspaceConfig)
+                .filter((provider) => provider !== request.provider && !unhealthy.has(provider));
+            const failovers = [];
+            let failed = outcome.response;
+            for (const candidate of candidates) {
+                if (failovers.length >= settings.attempts) {
+                    break;
+                }
+                if (await resolveProviderExecutor(config.basePath, candidate, env) === undefined) {
+                    continue;
+                }
+                const written = partial;
+                failovers.push({
+                    from: failed.provider,
+                    to: candidate,
+                    errorCode: failed.errorCode,
+                    error: failed.error,
+                    partialChars: written.length,
+                    at: (config.now?.() ?? new Date()).toISOString(),
+                });
+                outcome = await executeOnce({
+                    ...request,
+                    provider: candidate,
+                    // A model named for the failed provider means nothing to the next one.
+                    model: request.model !== undefined && /^v14-/.test(request.model) ? request.model : undefined,
+                    prompt: buildContinuationPrompt(request.prompt, written),
+                    noCache: true,
+                    onText,
+                });
+                if (outcome.type !== 'failure') {
+                    outcome.response.content = `${written}${outcome.response.content ?? ''}`;
+                    break;
+                }
+                failed = outcome.response;
+                if (failed.errorCode === 'PROVIDER_CANCELLED') {
+                    break;
+                }
+            }
+            if (failovers.length > 0 && outcome.type !== 'unavailable') {
+                outcome.response.failovers = failovers;
+                outcome.response.warnings = [
+                    ...failovers.map((failover) => `Provider "${failover.from}" failed after ${failover.partialChars} characters of its reply${failover.errorCode === undefined ? '' : ` (${failover.errorCode})`}; "${failover.to}" continued it.`),
+                    ...(outcome.response.warnings ?? []),
+                ];
+            }
+            return outcome;
         },
     };
 }
-async function resolveProviderCommand(basePath, provider, env) {
+async function resolveProviderExecutor(basePath, provider, env) {
     const providerIds = getProviderLookupOrder(provider);
     const workspaceConfig = await readWorkspaceConfig(basePath);
     for (const providerId of providerIds) {
-        const configured = getConfiguredProviderCommand(workspaceConfig, providerId);
+        const configured = getConfiguredProviderCommand(workspaceConfig, providerId, env);
         if (configured !== undefined) {
             return configured;
         }
@@ -66,25 +535,190 @@ async function resolveProviderCommand(basePath, provider, env) {
     }
     return undefined;
 }
+async function probeProviderExecutor(providerConfig, settings, env, signal) {
+    if (!('api' in providerConfig)) {
+        const startedAt = Date.now();
+        const commandPath = locateCommand(providerConfig.command, env);
+        return commandPath === undefined
+            ? {
+                status: 'unhealthy',
+                probe: 'command',
+                latencyMs: Date.now() - startedAt,
+                errorCode: 'PROVIDER_COMMAND_NOT_FOUND',
+                error: `Command "${providerConfig.command}" was not found on PATH.`,
+            }
+            : { status: 'healthy', probe: 'command', latencyMs: Date.now() - startedAt };
+    }
+    const apiKey = providerConfig.apiKeyEnv === undefined ? '' : (env[providerConfig.apiKeyEnv] ?? '').trim();
+    if (providerConfig.apiKeyEnv !== undefined && apiKey.length === 0) {
+        return {
+            status: 'unhealthy',
+            probe: 'credentials',
+            latencyMs: 0,
+            auth: 'missing',
+            errorCode: 'PROVIDER_API_KEY_MISSING',
+            error: `${providerConfig.apiKeyEnv} is not set.`,
+        };
+    }
+    return probeProviderApi(providerConfig.api, {
+        baseUrl: providerConfig.baseUrl,
+        apiKey,
+        apiVersion: providerConfig.apiVersion,
+        profile: providerConfig.profile,
+        env,
+    }, settings, signal);
+}
+function describeProbeFailure(status) {
+    if (status === undefined) {
+        return '';
+    }
+    return status.auth === 'expired' ? ' (its credentials were rejected)'
+        : status.auth === 'missing' ? ' (no credentials are set)'
+            : status.errorCode !== undefined ? ` (${status.errorCode})` : '';
+}
+// Models whose server refused native tool calls; their later calls go straight to inlined tools.
+const toolsRefused = new Set();
+/**
+ * Calls the API, falling back on what the model cannot do natively: a prompt too long for its
+ * context loses its middle, tools it cannot call are described in the prompt and called through
+ * text, and a JSON reply it has no mode for is asked for in the prompt alone.
+ */
+async function executeProviderApi(providerConfig, request, env, workspaceConfig) {
+    const apiKey = providerConfig.apiKeyEnv === undefined ? '' : env[providerConfig.apiKeyEnv] ?? '';
+    if (providerConfig.apiKeyEnv !== undefined && apiKey.trim().length === 0) {
+        return {
+            type: 'failure',
+            response: {
+                success: false,
+                provider: request.provider,
+                model: request.model,
+                latencyMs: 0,
+                errorCode: 'PROVIDER_API_KEY_MISSING',
+                error: `Provider "${request.provider}" calls the ${providerConfig.api} API, but ${providerConfig.apiKeyEnv} is not set.`,
+                mode: 'http',
+            },
+        };
+    }
+    const definition = PROVIDER_APIS[providerConfig.api];
+    const model = resolveApiModel(request.model, providerConfig.model);
+    const capabilities = resolveProviderCapabilities(workspaceConfig, request.provider, model, providerConfig.contextLength);
+    const fitted = fitPromptToContext(request, capabilities, model);
+    const tools = request.tools ?? [];
+    const refusedKey = `${providerConfig.baseUrl}\0${model}`;
+    const inline = tools.length > 0 && (!capabilities.tools || toolsRefused.has(refusedKey));
+    const execute = (call) => definition.execute({
+        baseUrl: providerConfig.baseUrl,
+        apiKey: apiKey.trim(),
+        model: providerConfig.model,
+        maxTokens: providerConfig.maxTokens,
+        apiVersion: providerConfig.apiVersion,
+        pull: providerConfig.pull,
+        contextLength: providerConfig.contextLength,
+        region: providerConfig.region,
+        project: providerConfig.project,
+        profile: providerConfig.profile,
+        env,
+        timeoutMs: providerConfig.timeoutMs,
+    }, call, adaptProviderPrompt(call, providerConfig.promptDialect, {
+        separateSystemPrompt: true,
+        supportsStopSequences: true,
+    }));
+    // Tool calls come back as text when tools are inlined, so the JSON mode is left off then.
+    const nativeJson = capabilities.jsonMode && definition.jsonMode === true && !inline;
+    const degraded = {
+        ...request,
+        prompt: fitted.prompt,
+        responseFormat: nativeJson ? request.responseFormat : undefined,
+    };
+    const warnings = fitted.warning === undefined ? [] : [fitted.warning];
+    let response;
+    if (inline) {
+        response = await runInlineTools(execute, degraded, tools);
+        warnings.push(`Model "${model}" cannot call tools natively; its tools were described in the prompt instead.`);
+    }
+    else {
+        response = await execute(degraded);
+        if (tools.length > 0 && isToolsUnsupportedError(response)) {
+            toolsRefused.add(refusedKey);
+            response = await runInlineTools(execute, { ...degraded, responseFormat: undefined }, tools);
+            warnings.push(`Model "${model}" refused native tool calls; its tools were described in the prompt instead.`);
+        }
+    }
+    if (warnings.length > 0) {
+        response.warnings = [...warnings, ...(response.warnings ?? [])];
+    }
+    return response.success ? { type: 'response', response } : { type: 'failure', response };
+}
 async function executeProviderSubprocess(providerConfig, request, basePath, env) {
     const startedAt = Date.now();
     const timeoutMs = request.timeoutMs ?? providerConfig.timeoutMs;
+    // Only the JSON protocol has separate fields for the system prompt and stop sequences.
+    const structured = providerConfig.protocol === 'json-stdio';
+    const adapted = adaptProviderPrompt(request, providerConfig.promptDialect, {
+        separateSystemPrompt: structured,
+        supportsStopSequences: structured,
+    });
     let stdout = '';
     let stderr = '';
     let timedOut = false;
+    let cancelled = false;
+    const cancelledFailure = () => ({
+        type: 'failure',
+        response: {
+            success: false,
+            provider: request.provider,
+            model: request.model,
+            latencyMs: Date.now() - startedAt,
+            errorCode: 'PROVIDER_CANCELLED',
+            error: `Provider "${request.provider}" call was cancelled.`,
+            mode: 'subprocess',
+        },
+    });
+    if (request.signal?.aborted === true) {
+        return cancelledFailure();
+    }
+    // Known provider CLIs change files, run commands and reach the network themselves, so they get
+    // their own flags for read-only mode and the agent's permissions; one that has none is not started.
+    const restrictions = {
+        readOnly: isReadOnlyEnv(env),
+        shell: request.permissions?.shell ?? true,
+        network: request.permissions?.network ?? true,
+    };
+    const restrictionArgs = restrictProviderCli(providerConfig.command, restrictions);
+    if (restrictionArgs === undefined) {
+        return {
+            type: 'failure',
+            response: {
+                success: false,
+                provider: request.provider,
+                model: request.model,
+                latencyMs: 0,
+                errorCode: 'PROVIDER_UNRESTRICTABLE',
+                error: `Provider "${request.provider}" runs "${providerConfig.command}", which cannot be kept ${describeRestrictions(restrictions)}; configure an API or json-stdio executor for it.`,
+                mode: 'subprocess',
+            },
+        };
+    }
     return new Promise((resolve) => {
-        const child = spawn(providerConfig.command, buildProviderSpawnArgs(providerConfig, request), {
-            cwd: basePath,
-            env,
+        const child = spawn(providerConfig.command, buildProviderSpawnArgs(providerConfig, adapted, restrictionArgs), {
+            cwd: request.scope === undefined ? basePath : join(basePath, request.scope),
+            env: request.permissions === undefined ? env : { ...env, [AGENT_PERMISSIONS_ENV_VAR]: JSON.stringify(request.permissions) },
             stdio: ['pipe', 'pipe', 'pipe'],
         });
         const timer = setTimeout(() => {
             timedOut = true;
             child.kill('SIGKILL');
         }, timeoutMs);
+        const onAbort = () => {
+            cancelled = true;
+            child.kill('SIGTERM');
+        };
+        request.signal?.addEventListener('abort', onAbort, { once: true });
+        const streamText = structured || request.onText === undefined ? undefined : createPlainTextStream(request.onText);
         child.stdout.setEncoding('utf8');
         child.stdout.on('data', (chunk) => {
             stdout += chunk;
+            streamText?.(chunk);
         });
         child.stderr.setEncoding('utf8');
         child.stderr.on('data', (chunk) => {
@@ -92,6 +726,7 @@ async function executeProviderSubprocess(providerConfig, request, basePath, env)
         });
         child.on('error', (error) => {
             clearTimeout(timer);
+            request.signal?.removeEventListener('abort', onAbort);
             child.stdin?.destroy();
             resolve({
                 type: 'failure',
@@ -108,6 +743,11 @@ async function executeProviderSubprocess(providerConfig, request, basePath, env)
         });
         child.on('close', (code) => {
             clearTimeout(timer);
+            request.signal?.removeEventListener('abort', onAbort);
+            if (cancelled) {
+                resolve(cancelledFailure());
+                return;
+            }
             if (timedOut) {
                 resolve({
                     type: 'failure',
@@ -131,29 +771,32 @@ async function executeProviderSubprocess(providerConfig, request, basePath, env)
                         provider: request.provider,
                         model: request.model,
                         latencyMs: Date.now() - startedAt,
-                        errorCode: 'PROVIDER_EXIT_NON_ZERO',
+                        // CLIs report the API's 429 in their own words; this is what they have in common.
+                        errorCode: RATE_LIMIT_OUTPUT.test(stderr) ? 'PROVIDER_RATE_LIMITED' : 'PROVIDER_EXIT_NON_ZERO',
                         error: stderr.trim() || `Provider "${request.provider}" exited with code ${code}.`,
                         mode: 'subprocess',
                     },
                 });
                 return;
             }
-            resolve({
-                type: 'response',
-                response: normalizeProviderOutput(stdout, request, Date.now() - startedAt),
-            });
+            const response = normalizeProviderOutput(stdout, request, Date.now() - startedAt);
+            if (adapted.warnings.length > 0) {
+                response.warnings = [...adapted.warnings, ...(response.warnings ?? [])];
+            }
+            resolve({ type: 'response', response });
         });
         try {
             if (providerConfig.protocol === 'argv-last') {
                 child.stdin.end();
             }
             else {
-                child.stdin.write(buildProviderStdinPayload(providerConfig, request, timeoutMs), 'utf8');
+                child.stdin.write(buildProviderStdinPayload(providerConfig, request, adapted, timeoutMs, isReadOnlyEnv(env)), 'utf8');
                 child.stdin.end();
             }
         }
         catch (writeError) {
             clearTimeout(timer);
+            request.signal?.removeEventListener('abort', onAbort);
             child.stdin?.destroy();
             resolve({
                 type: 'failure',
@@ -170,6 +813,27 @@ async function executeProviderSubprocess(providerConfig, request, basePath, env)
         }
     });
 }
+/**
+ * Passes plain-text executor output on as it is written. Leading whitespace is dropped, as the
+ * response trims it, and output that opens with `{` is held back: it is a JSON envelope whose
+ * content only the parsed response carries.
+ */
+function createPlainTextStream(onText) {
+    let state = 'leading';
+    return (chunk) => {
+        let text = chunk;
+        if (state === 'leading') {
+            text = text.trimStart();
+            if (text.length === 0) {
+                return;
+            }
+            state = text.startsWith('{') ? 'json' : 'text';
+        }
+        if (state === 'text') {
+            onText(text);
+        }
+    };
+}
 function normalizeProviderOutput(stdout, request, latencyMs) {
     const trimmed = stdout.trim();
     if (trimmed.length === 0) {
@@ -232,10 +896,27 @@ function normalizeUsage(value, prompt, content) {
         totalTokens,
     };
 }
-function getConfiguredProviderCommand(config, providerId) {
+function getConfiguredProviderCommand(config, providerId, env) {
     const providers = asRecord(config.providers);
     const executors = asRecord(providers?.executors);
     const executor = asRecord(executors?.[providerId]);
+    const api = normalizeApi(executor?.api);
+    if (api !== undefined) {
+        return buildProviderApiConfig(api, providerId, {
+            baseUrl: executor?.baseUrl,
+            apiKeyEnv: executor?.apiKeyEnv,
+            model: executor?.model,
+            maxTokens: asNumber(executor?.maxTokens),
+            apiVersion: executor?.apiVersion,
+            pull: typeof executor?.pull === 'boolean' ? executor.pull : undefined,
+            contextLength: asNumber(executor?.contextLength),
+            region: executor?.region,
+            project: executor?.project,
+            profile: executor?.profile,
+            timeoutMs: asNumber(executor?.timeoutMs),
+            promptDialect: executor?.promptDialect,
+        }, 'config', env);
+    }
     const command = typeof executor?.command === 'string' ? executor.command : undefined;
     if (command === undefined || command.trim().length === 0) {
         return undefined;
@@ -245,11 +926,25 @@ function getConfiguredProviderCommand(config, providerId) {
         args: normalizeArgs(executor?.args),
         timeoutMs: asNumber(executor?.timeoutMs) ?? DEFAULT_PROVIDER_TIMEOUT_MS,
         protocol: normalizeProtocol(executor?.protocol) ?? 'json-stdio',
+        promptDialect: resolvePromptDialect(providerId, executor?.promptDialect),
         adapterSource: 'config',
     };
 }
 function getEnvProviderCommand(env, providerId) {
     const prefix = `AUTOMATOSX_PROVIDER_${providerId.toUpperCase().replace(/[^A-Z0-9]+/g, '_')}`;
+    const api = normalizeApi(env[`${prefix}_API`]);
+    if (api !== undefined) {
+        return buildProviderApiConfig(api, providerId, {
+            baseUrl: env[`${prefix}_BASE_URL`],
+            model: env[`${prefix}_MODEL`],
+            apiVersion: env[`${prefix}_API_VERSION`],
+            region: env[`${prefix}_REGION`],
+            project: env[`${prefix}_PROJECT`],
+            profile: env[`${prefix}_PROFILE`],
+            timeoutMs: env[`${prefix}_TIMEOUT_MS`] === undefined ? undefined : parseTimeout(env[`${prefix}_TIMEOUT_MS`]),
+            promptDialect: env[`${prefix}_PROMPT_DIALECT`],
+        }, 'env', env);
+    }
     const command = env[`${prefix}_CMD`];
     if (typeof command !== 'string' || command.trim().length === 0) {
         return undefined;
@@ -259,30 +954,75 @@ function getEnvProviderCommand(env, providerId) {
         args: parseArgs(env[`${prefix}_ARGS`]),
         timeoutMs: parseTimeout(env[`${prefix}_TIMEOUT_MS`]),
         protocol: normalizeProtocol(env[`${prefix}_PROTOCOL`]) ?? 'json-stdio',
+        promptDialect: resolvePromptDialect(providerId, env[`${prefix}_PROMPT_DIALECT`]),
         adapterSource: 'env',
     };
 }
 function getNativeProviderCommand(env, providerId) {
     const preset = PROVIDER_NATIVE_COMMANDS[providerId];
-    if (preset === undefined) {
-        return undefined;
-    }
-    const lookupCommand = process.platform === 'win32' ? 'where' : 'which';
-    const detection = spawnSync(lookupCommand, [preset.command], {
-        env,
-        stdio: 'ignore',
-    });
-    if (detection.status !== 0) {
-        return undefined;
+    if (preset === undefined || locateCommand(preset.command, env) === undefined) {
+        const api = PROVIDER_NATIVE_APIS[providerId];
+        return api !== undefined && nativeApiAvailable(PROVIDER_APIS[api], env)
+            ? buildProviderApiConfig(api, providerId, {}, 'native', env)
+            : undefined;
     }
     return {
         command: preset.command,
         args: preset.args ?? [],
         timeoutMs: DEFAULT_PROVIDER_TIMEOUT_MS,
         protocol: preset.protocol,
+        promptDialect: resolvePromptDialect(providerId),
         adapterSource: 'native',
     };
 }
+function nativeApiAvailable(preset, env) {
+    if (preset.apiKeyEnv !== undefined) {
+        return (env[preset.apiKeyEnv] ?? '').trim().length > 0;
+    }
+    return (env[preset.baseUrlEnv] ?? '').trim().length > 0
+        || (preset.command !== undefined && locateCommand(preset.command, env) !== undefined);
+}
+function buildProviderApiConfig(api, providerId, options, adapterSource, env) {
+    const preset = PROVIDER_APIS[api];
+    const apiVersion = firstString(options.apiVersion);
+    const apiKeyEnv = firstString(options.apiKeyEnv, preset.apiKeyEnv);
+    const region = preset.region === undefined
+        ? undefined
+        : firstString(options.region, ...(preset.regionEnv ?? []).map((name) => env?.[name]), preset.region);
+    const project = firstString(options.project, ...(preset.projectEnv ?? []).map((name) => env?.[name]));
+    const profile = firstString(options.profile);
+    const defaultBaseUrl = typeof preset.baseUrl === 'string' ? preset.baseUrl : preset.baseUrl(region ?? '');
+    const model = firstString(options.model, preset.model) ?? preset.model;
+    return {
+        api,
+        baseUrl: firstString(options.baseUrl, env?.[preset.baseUrlEnv], defaultBaseUrl) ?? defaultBaseUrl,
+        ...(apiKeyEnv !== undefined ? { apiKeyEnv } : {}),
+        model,
+        ...(options.maxTokens !== undefined ? { maxTokens: options.maxTokens } : {}),
+        ...(apiVersion !== undefined ? { apiVersion } : {}),
+        ...(options.pull !== undefined ? { pull: options.pull } : {}),
+        ...(options.contextLength !== undefined ? { contextLength: options.contextLength } : {}),
+        ...(region !== undefined ? { region } : {}),
+        ...(project !== undefined ? { project } : {}),
+        ...(profile !== undefined ? { profile } : {}),
+        timeoutMs: options.timeoutMs ?? DEFAULT_API_TIMEOUT_MS,
+        // The wire format decides the dialect unless the executor names one.
+        promptDialect: resolvePromptDialect(providerId, options.promptDialect ?? (typeof preset.promptDialect === 'string' ? preset.promptDialect : preset.promptDialect(model))),
+        adapterSource,
+    };
+}
+function locateCommand(command, env) {
+    const lookupCommand = process.platform === 'win32' ? 'where' : 'which';
+    const detection = spawnSync(lookupCommand, [command], {
+        env,
+        encoding: 'utf8',
+        stdio: ['ignore', 'pipe', 'ignore'],
+    });
+    if (detection.status !== 0) {
+        return undefined;
+    }
+    return detection.stdout.split(/\r?\n/).find((line) => line.trim().length > 0)?.trim() ?? command;
+}
 function resolveExecutionMode(env) {
     const value = env.AUTOMATOSX_PROVIDER_EXECUTION_MODE;
     if (value === 'simulate' || value === 'require-real' || value === 'auto') {
@@ -295,6 +1035,11 @@ function normalizeProtocol(value) {
         ? value
         : undefined;
 }
+function normalizeApi(value) {
+    return value === 'anthropic' || value === 'openai' || value === 'ollama' || value === 'bedrock' || value === 'vertex'
+        ? value
+        : undefined;
+}
 function nativeAdaptersEnabled(config, env) {
     const envValue = env.AUTOMATOSX_PROVIDER_NATIVE_ADAPTERS;
     if (envValue === '1' || envValue === 'true' || envValue === 'enabled') {
@@ -306,17 +1051,6 @@ function nativeAdaptersEnabled(config, env) {
     const providers = asRecord(config.providers);
     return providers?.nativeAdapters === true;
 }
-async function readWorkspaceConfig(basePath) {
-    const configPath = join(basePath, '.automatosx', 'config.json');
-    try {
-        const raw = await readFile(configPath, 'utf8');
-        const parsed = JSON.parse(raw);
-        return asRecord(parsed) ?? {};
-    }
-    catch {
-        return {};
-    }
-}
 function parseArgs(value) {
     if (typeof value !== 'string' || value.trim().length === 0) {
         return [];
@@ -333,37 +1067,46 @@ function parseArgs(value) {
     }
     return trimmed.split(/\s+/).filter((entry) => entry.length > 0);
 }
-function buildProviderSpawnArgs(providerConfig, request) {
-    if (providerConfig.protocol !== 'argv-last') {
-        return providerConfig.args;
-    }
-    return [
-        ...providerConfig.args,
-        [
-            typeof request.systemPrompt === 'string' && request.systemPrompt.length > 0
-                ? `System: ${request.systemPrompt}`
-                : undefined,
-            request.prompt,
-        ].filter((value) => typeof value === 'string' && value.length > 0).join('\n\n'),
+function restrictProviderCli(command, restrictions) {
+    const restrict = PROVIDER_CLI_RESTRICTIONS[providerCliName(command)];
+    return restrict === undefined ? [] : restrict(restrictions);
+}
+function providerCliName(command) {
+    return basename(command).replace(/\.(?:cmd|exe|bat)$/i, '').toLowerCase();
+}
+// e.g. `read-only and off the network`.
+function describeRestrictions(restrictions) {
+    const parts = [
+        ...(restrictions.readOnly ? ['read-only'] : []),
+        ...(restrictions.shell ? [] : ['from running shell commands']),
+        ...(restrictions.network ? [] : ['off the network']),
     ];
+    return parts.length > 1 ? `${parts.slice(0, -1).join(', ')} and ${parts.at(-1)}` : parts.join('');
+}
+// The restriction flags follow the configured arguments, which may name a subcommand, and precede the prompt.
+function buildProviderSpawnArgs(providerConfig, adapted, restrictionArgs) {
+    if (providerConfig.protocol !== 'argv-last') {
+        return [...providerConfig.args, ...restrictionArgs];
+    }
+    return [...providerConfig.args, ...restrictionArgs, adapted.prompt];
 }
-function buildProviderStdinPayload(providerConfig, request, timeoutMs) {
+function buildProviderStdinPayload(providerConfig, request, adapted, timeoutMs, readOnly) {
     if (providerConfig.protocol === 'raw-stdin') {
-        return [
-            typeof request.systemPrompt === 'string' && request.systemPrompt.length > 0
-                ? `System: ${request.systemPrompt}`
-                : undefined,
-            request.prompt,
-        ].filter((value) => typeof value === 'string' && value.length > 0).join('\n\n');
+        return adapted.prompt;
     }
     return `${JSON.stringify({
         provider: request.provider,
-        prompt: request.prompt,
-        systemPrompt: request.systemPrompt,
+        prompt: adapted.prompt,
+        systemPrompt: adapted.systemPrompt,
+        promptDialect: providerConfig.promptDialect,
         model: request.model,
         maxTokens: request.maxTokens,
         temperature: request.temperature,
+        stopSequences: adapted.stopSequences,
         timeoutMs,
+        readOnly: readOnly ? true : undefined,
+        scope: request.scope,
+        permissions: request.permissions,
     })}\n`;
 }
 function normalizeArgs(value) {
@@ -383,13 +1126,14 @@ function getProviderLookupOrder(provider) {
     if (normalized === 'codex') {
         return ['codex', 'openai'];
     }
+    if (normalized === 'claude') {
+        return ['claude', 'anthropic'];
+    }
+    if (normalized === 'anthropic') {
+        return ['anthropic', 'claude'];
+    }
     return [normalized];
 }
-function asRecord(value) {
-    return typeof value === 'object' && value !== null && !Array.isArray(value)
-        ? value
-        : undefined;
-}
 function asNumber(value) {
     return typeof value === 'number' && Number.isFinite(value) ? value : undefined;
 }
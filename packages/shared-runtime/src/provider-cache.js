@@ -0,0 +1,188 @@
+import { createHash } from 'node:crypto';
+import { asRecord } from './records.js';
+/** Memory namespace holding one entry per cached provider response, keyed by the hash of the call. */
+export const PROVIDER_CACHE_NAMESPACE = 'provider-cache';
+export const DEFAULT_PROVIDER_CACHE_TTL_SECONDS = 86_400;
+export const DEFAULT_SEMANTIC_CACHE_THRESHOLD = 0.97;
+// Nearest neighbours looked at; the closest may be for another provider, model or tool set.
+const SEMANTIC_CANDIDATES = 10;
+/** The cache settings of a workspace config, or undefined when `providers.cache` is unset or false. */
+export function readProviderCacheSettings(workspaceConfig) {
+    const section = asRecord(workspaceConfig.providers)?.cache;
+    if (section === undefined || section === false) {
+        return undefined;
+    }
+    const configured = asRecord(section) ?? {};
+    if (configured.enabled === false) {
+        return undefined;
+    }
+    const ttlSeconds = typeof configured.ttlSeconds === 'number' && configured.ttlSeconds > 0
+        ? configured.ttlSeconds
+        : DEFAULT_PROVIDER_CACHE_TTL_SECONDS;
+    const semantic = configured.semantic === true ? {} : configured.semantic;
+    const threshold = asRecord(semantic)?.threshold ?? DEFAULT_SEMANTIC_CACHE_THRESHOLD;
+    if (semantic !== undefined && semantic !== false && !(typeof threshold === 'number' && threshold > 0 && threshold <= 1)) {
+        throw new Error(`providers.cache.semantic.threshold must be a cosine similarity above 0 and at most 1, got ${JSON.stringify(threshold)}.`);
+    }
+    return {
+        ttlSeconds,
+        ...(semantic !== undefined && semantic !== false ? { semanticThreshold: threshold } : {}),
+    };
+}
+/**
+ * Caches successful provider responses in the state store's `provider-cache` memory namespace.
+ * An exact match needs the same provider, model, tools and prompts; a semantic match, when a
+ * threshold and an embedder are given, needs the same provider, model and tools and prompts whose
+ * embeddings are at least that similar. Entries expire after the configured TTL.
+ */
+export function createProviderResponseCache(config) {
+    const now = () => config.now?.() ?? new Date();
+    const recordHit = async (entry, cached, match) => {
+        await config.stateStore.storeMemory({
+            key: entry.key,
+            namespace: PROVIDER_CACHE_NAMESPACE,
+            value: {
+                ...cached,
+                exactHits: cached.exactHits + (match === 'exact' ? 1 : 0),
+                semanticHits: cached.semanticHits + (match === 'semantic' ? 1 : 0),
+            },
+            ...(entry.embedding !== undefined ? { embedding: entry.embedding } : {}),
+            ...(entry.expiresAt !== undefined ? { expiresAt: entry.expiresAt } : {}),
+        });
+    };
+    const replay = (cached, hit) => ({
+        ...cached.response,
+        latencyMs: 0,
+        cache: hit,
+    });
+    return {
+        async lookup(call, settings, embedder) {
+            const key = cacheKey(call);
+            const exact = await config.stateStore.getMemory(key, PROVIDER_CACHE_NAMESPACE);
+            const exactValue = exact === undefined ? undefined : asCachedResponse(exact.value);
+            if (exact !== undefined && exactValue !== undefined) {
+                await recordHit(exact, exactValue, 'exact');
+                return replay(exactValue, { match: 'exact', storedAt: exact.updatedAt });
+            }
+            if (settings.semanticThreshold === undefined || embedder === undefined) {
+                return undefined;
+            }
+            const [vector] = await embedder.embed([promptText(call)]);
+            if (vector === undefined) {
+                return undefined;
+            }
+            const scope = cacheScope(call);
+            const candidates = await config.stateStore.searchMemoryByVector(vector, {
+                namespace: PROVIDER_CACHE_NAMESPACE,
+                k: SEMANTIC_CANDIDATES,
+                minScore: settings.semanticThreshold,
+            });
+            for (const candidate of candidates) {
+                const cached = asCachedResponse(candidate.value);
+                if (cached?.scope === scope && candidate.namespace === PROVIDER_CACHE_NAMESPACE) {
+                    const { score, ...entry } = candidate;
+                    await recordHit(entry, cached, 'semantic');
+                    return replay(cached, { match: 'semantic', score: Number(score.toFixed(4)), storedAt: entry.updatedAt });
+                }
+            }
+            return undefined;
+        },
+        async store(call, response, settings, embedder) {
+            // Warnings were about the call that produced the response, not about its content.
+            const stored = { ...response };
+            delete stored.cache;
+            delete stored.warnings;
+            const key = cacheKey(call);
+            // A refreshed entry keeps the hits of the one it replaces, so bypassed calls don't reset the stats.
+            const previous = await config.stateStore.getMemory(key, PROVIDER_CACHE_NAMESPACE);
+            const previousValue = previous === undefined ? undefined : asCachedResponse(previous.value);
+            const value = {
+                scope: cacheScope(call),
+                provider: call.provider,
+                ...(call.model !== undefined ? { model: call.model } : {}),
+                prompt: normalizePrompt(call.prompt),
+                response: stored,
+                exactHits: previousValue?.exactHits ?? 0,
+                semanticHits: previousValue?.semanticHits ?? 0,
+            };
+            const embedding = settings.semanticThreshold !== undefined && embedder !== undefined
+                ? (await embedder.embed([promptText(call)]))[0]
+                : undefined;
+            await config.stateStore.storeMemory({
+                key,
+                namespace: PROVIDER_CACHE_NAMESPACE,
+                value,
+                ...(embedding !== undefined ? { embedding } : {}),
+                expiresAt: new Date(now().getTime() + settings.ttlSeconds * 1000).toISOString(),
+            });
+        },
+        async stats(settings) {
+            const entries = await config.stateStore.listMemory(PROVIDER_CACHE_NAMESPACE);
+            const stats = {
+                enabled: settings !== undefined,
+                ...(settings !== undefined ? { ttlSeconds: settings.ttlSeconds } : {}),
+                ...(settings?.semanticThreshold !== undefined ? { semanticThreshold: settings.semanticThreshold } : {}),
+                entries: 0,
+                exactHits: 0,
+                semanticHits: 0,
+                savedInputTokens: 0,
+                savedOutputTokens: 0,
+            };
+            for (const entry of entries) {
+                const cached = asCachedResponse(entry.value);
+                if (cached === undefined) {
+                    continue;
+                }
+                const hits = cached.exactHits + cached.semanticHits;
+                stats.entries += 1;
+                stats.exactHits += cached.exactHits;
+                stats.semanticHits += cached.semanticHits;
+                stats.savedInputTokens += hits * (cached.response.usage?.inputTokens ?? 0);
+                stats.savedOutputTokens += hits * (cached.response.usage?.outputTokens ?? 0);
+                if (stats.oldestAt === undefined || entry.updatedAt < stats.oldestAt) {
+                    stats.oldestAt = entry.updatedAt;
+                }
+                if (stats.newestAt === undefined || entry.updatedAt > stats.newestAt) {
+                    stats.newestAt = entry.updatedAt;
+                }
+            }
+            return stats;
+        },
+        async clear() {
+            const entries = await config.stateStore.listMemory(PROVIDER_CACHE_NAMESPACE);
+            let removed = 0;
+            for (const entry of entries) {
+                if (await config.stateStore.deleteMemory(entry.key, PROVIDER_CACHE_NAMESPACE)) {
+                    removed += 1;
+                }
+            }
+            return removed;
+        },
+    };
+}
+function normalizePrompt(text) {
+    return text.replace(/\s+/g, ' ').trim();
+}
+// The system prompt and the prompt, as one text to hash or embed.
+function promptText(call) {
+    return [normalizePrompt(call.systemPrompt ?? ''), normalizePrompt(call.prompt)].join('\n');
+}
+// Provider, model and tool definitions: what a semantic match must share exactly.
+function cacheScope(call) {
+    return createHash('sha256')
+        .update(JSON.stringify([call.provider, call.model ?? '', (call.tools ?? []).map((tool) => [tool.name, tool.description, tool.inputSchema])]))
+        .digest('hex');
+}
+function cacheKey(call) {
+    return createHash('sha256').update(`${cacheScope(call)}\0${promptText(call)}`).digest('hex');
+}
+function asCachedResponse(value) {
+    const record = asRecord(value) ?? {};
+    return typeof record.scope === 'string' && typeof record.response === 'object' && record.response !== null
+        ? {
+            ...record,
+            exactHits: typeof record.exactHits === 'number' ? record.exactHits : 0,
+            semanticHits: typeof record.semanticHits === 'number' ? record.semanticHits : 0,
+        }
+        : undefined;
+}
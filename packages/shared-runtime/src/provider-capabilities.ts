@@ -0,0 +1,260 @@
+import type { ProviderExecutionRequest, ProviderExecutionResponse, ProviderTool, ProviderToolCall } from './provider-bridge.js';
+import { MAX_TOOL_ROUNDS, runProviderTool } from './provider-http.js';
+import { PROVIDER_DEFAULT_MODELS } from './provider-routing.js';
+import { asRecord } from './records.js';
+
+/** What a model accepts and can do natively, which decides what a request falls back on. */
+export interface ProviderCapabilities {
+  /** The context window in tokens, when known. */
+  maxContextTokens?: number;
+  /** Native function calling; without it tools are described in the prompt and called through text. */
+  tools: boolean;
+  /** Image input. */
+  vision: boolean;
+  /** A response mode that only returns JSON; without it the prompt asks for JSON and the reply is pulled out of the text. */
+  jsonMode: boolean;
+}
+
+// Matched against the model name without any `vendor/` prefix, first match winning;
+// `providers.capabilities` overrides or extends it.
+const DEFAULT_MODEL_CAPABILITIES: Array<[RegExp, ProviderCapabilities]> = [
+  [/^([\w-]+\.)?(anthropic\.)?claude-/, { maxContextTokens: 200_000, tools: true, vision: true, jsonMode: false }],
+  [/^gpt-5/, { maxContextTokens: 400_000, tools: true, vision: true, jsonMode: true }],
+  [/^gpt-4\.1/, { maxContextTokens: 1_000_000, tools: true, vision: true, jsonMode: true }],
+  [/^gpt-4o/, { maxContextTokens: 128_000, tools: true, vision: true, jsonMode: true }],
+  [/^gpt-3\.5/, { maxContextTokens: 16_000, tools: true, vision: false, jsonMode: true }],
+  [/^o1-(mini|preview)/, { maxContextTokens: 128_000, tools: false, vision: false, jsonMode: false }],
+  [/^o[134]/, { maxContextTokens: 200_000, tools: true, vision: true, jsonMode: true }],
+  [/^gemini-/, { maxContextTokens: 1_000_000, tools: true, vision: true, jsonMode: false }],
+  [/^grok-/, { maxContextTokens: 256_000, tools: true, vision: true, jsonMode: true }],
+  [/^deepseek-reasoner/, { maxContextTokens: 128_000, tools: false, vision: false, jsonMode: false }],
+  [/^deepseek-chat/, { maxContextTokens: 128_000, tools: true, vision: false, jsonMode: true }],
+  // Ollama tags; every Ollama model can be held to JSON.
+  [/^(llama3\.2-vision|llava|bakllava|moondream)/, { tools: false, vision: true, jsonMode: true }],
+  [/^llama3\.[123]/, { maxContextTokens: 128_000, tools: true, vision: false, jsonMode: true }],
+  [/^(qwen2\.5|qwen3|mistral|mixtral|command-r|granite3|firefunction|hermes3|smollm2)/, { tools: true, vision: false, jsonMode: true }],
+  [/^(gemma|phi|codellama|llama2|llama3(:|$)|deepseek-r1|deepseek-coder|starcoder|tinyllama|vicuna|orca)/, { tools: false, vision: false, jsonMode: true }],
+];
+
+// A model missing from the table is taken to call tools natively; a server that refuses the
+// tools then has them inlined for the rest of the process.
+const UNKNOWN_MODEL_CAPABILITIES: ProviderCapabilities = { tools: true, vision: false, jsonMode: false };
+
+const CHARS_PER_TOKEN = 4;
+const DEFAULT_REPLY_RESERVE = 4096;
+
+/**
+ * The capabilities of `model` on `provider`: the built-in table, then the `providers.capabilities`
+ * entry for the provider, then the one for the model, each overriding the fields it sets. A
+ * context length configured on the executor wins over all of them.
+ */
+export function resolveProviderCapabilities(
+  workspaceConfig: Record<string, unknown>,
+  provider: string,
+  model: string | undefined,
+  contextLength?: number,
+): ProviderCapabilities {
+  const overrides = asRecord(asRecord(workspaceConfig.providers)?.capabilities) ?? {};
+  const name = (model ?? PROVIDER_DEFAULT_MODELS[provider] ?? '').toLowerCase().replace(/^.*\//, '');
+  const builtIn = DEFAULT_MODEL_CAPABILITIES.find(([pattern]) => pattern.test(name))?.[1] ?? UNKNOWN_MODEL_CAPABILITIES;
+  const resolved: ProviderCapabilities = {
+    ...builtIn,
+    ...parseCapabilities(overrides[provider]),
+    ...(model !== undefined ? parseCapabilities(overrides[model]) : {}),
+  };
+  return contextLength === undefined ? resolved : { ...resolved, maxContextTokens: contextLength };
+}
+
+function parseCapabilities(value: unknown): Partial<ProviderCapabilities> {
+  const entry = asRecord(value) ?? {};
+  return {
+    ...(typeof entry.maxContextTokens === 'number' && entry.maxContextTokens > 0 ? { maxContextTokens: entry.maxContextTokens } : {}),
+    ...(typeof entry.tools === 'boolean' ? { tools: entry.tools } : {}),
+    ...(typeof entry.vision === 'boolean' ? { vision: entry.vision } : {}),
+    ...(typeof entry.jsonMode === 'boolean' ? { jsonMode: entry.jsonMode } : {}),
+  };
+}
+
+/**
+ * Cuts the middle out of a prompt too long for the model's context, keeping its start (usually
+ * the instructions) and its end (usually the task), so the call is made instead of refused or
+ * silently truncated from the front. Room is left for the system prompt, tools and the reply.
+ */
+export function fitPromptToContext(
+  request: ProviderExecutionRequest,
+  capabilities: ProviderCapabilities,
+  model: string,
+): { prompt: string; warning?: string } {
+  if (capabilities.maxContextTokens === undefined) {
+    return { prompt: request.prompt };
+  }
+  const fixedChars = (request.systemPrompt ?? '').length
+    + JSON.stringify((request.tools ?? []).map((tool) => [tool.name, tool.description, tool.inputSchema])).length;
+  const budgetChars = (capabilities.maxContextTokens - (request.maxTokens ?? DEFAULT_REPLY_RESERVE)) * CHARS_PER_TOKEN - fixedChars;
+  if (request.prompt.length <= budgetChars || budgetChars <= 0) {
+    return { prompt: request.prompt };
+  }
+  const omitted = request.prompt.length - budgetChars;
+  const marker = `\n\n[... ${omitted} characters omitted to fit the context window ...]\n\n`;
+  const keep = Math.max(0, budgetChars - marker.length);
+  const head = Math.floor(keep / 4);
+  return {
+    prompt: `${request.prompt.slice(0, head)}${marker}${request.prompt.slice(request.prompt.length - (keep - head))}`,
+    warning: `The prompt (about ${Math.ceil((request.prompt.length + fixedChars) / CHARS_PER_TOKEN)} tokens) is longer than the ${capabilities.maxContextTokens}-token context of "${model}"; ${omitted} characters from its middle were left out.`,
+  };
+}
+
+/** The prompt of a call that wants a JSON reply; models with a JSON mode are held to it as well. */
+export function withJsonInstruction(prompt: string): string {
+  return `${prompt}\n\nReply with a single valid JSON value and nothing else: no prose and no code fences.`;
+}
+
+/**
+ * The JSON in a reply: the whole reply when it parses, else the first fenced block or the
+ * outermost object or array that does. Undefined when none does.
+ */
+export function extractJsonReply(content: string): string | undefined {
+  const candidates = [
+    content.trim(),
+    /```(?:json)?\s*\n([\s\S]*?)\n?```/i.exec(content)?.[1]?.trim(),
+    sliceBetween(content, '{', '}'),
+    sliceBetween(content, '[', ']'),
+  ];
+  return candidates.find((candidate): candidate is string => candidate !== undefined && candidate.length > 0 && parsesAsJson(candidate));
+}
+
+function sliceBetween(content: string, open: string, close: string): string | undefined {
+  const start = content.indexOf(open);
+  const end = content.lastIndexOf(close);
+  return start === -1 || end <= start ? undefined : content.slice(start, end + 1);
+}
+
+function parsesAsJson(text: string): boolean {
+  try {
+    JSON.parse(text);
+    return true;
+  } catch {
+    return false;
+  }
+}
+
+/** Whether a failed call was refused because the model cannot take tools. */
+export function isToolsUnsupportedError(response: ProviderExecutionResponse): boolean {
+  return !response.success
+    && /does not support (?:tools|function calling)|(?:tools?|function calling) (?:are|is) not supported/i.test(response.error ?? '');
+}
+
+interface InlineToolRound {
+  reply: string;
+  results: Array<{ name: string; content: string; isError: boolean }>;
+}
+
+/**
+ * Gives tools to a model without function calling: the tools are described in the prompt, the
+ * model asks for them in `<tool_call>` blocks, and each round's results go back in a fresh prompt
+ * until it answers without calling any. `execute` makes one plain call; the rounds' usage is
+ * summed. The reply is not streamed as it arrives, since a round may turn out to be tool calls;
+ * the final answer is passed to `onText` whole.
+ */
+export async function runInlineTools(
+  execute: (request: ProviderExecutionRequest) => Promise<ProviderExecutionResponse>,
+  request: ProviderExecutionRequest,
+  tools: ProviderTool[],
+): Promise<ProviderExecutionResponse> {
+  const startedAt = Date.now();
+  const rounds: InlineToolRound[] = [];
+  const toolCalls: ProviderToolCall[] = [];
+  let inputTokens = 0;
+  let outputTokens = 0;
+  const finish = (response: ProviderExecutionResponse): ProviderExecutionResponse => ({
+    ...response,
+    latencyMs: Date.now() - startedAt,
+    ...(inputTokens + outputTokens > 0 ? { usage: { inputTokens, outputTokens, totalTokens: inputTokens + outputTokens } } : {}),
+    ...(toolCalls.length > 0 ? { toolCalls } : {}),
+  });
+
+  for (let round = 1; ; round += 1) {
+    const response = await execute({
+      ...request,
+      prompt: buildInlineToolPrompt(request.prompt, tools, rounds),
+      tools: undefined,
+      onText: undefined,
+    });
+    inputTokens += response.usage?.inputTokens ?? 0;
+    outputTokens += response.usage?.outputTokens ?? 0;
+    if (!response.success) {
+      return finish(response);
+    }
+    const reply = response.content ?? '';
+    const calls = parseInlineToolCalls(reply);
+    if (calls.length === 0) {
+      request.onText?.(reply);
+      return finish(response);
+    }
+    if (round >= MAX_TOOL_ROUNDS) {
+      return finish({
+        success: false,
+        provider: response.provider,
+        model: response.model,
+        latencyMs: 0,
+        errorCode: 'PROVIDER_TOOL_LOOP',
+        error: `The model was still calling tools after ${MAX_TOOL_ROUNDS} rounds.`,
+        mode: response.mode,
+      });
+    }
+    const results: InlineToolRound['results'] = [];
+    for (const call of calls) {
+      const result = call.input === undefined
+        ? { content: `The input of "${call.name}" is not a JSON object.`, isError: true }
+        : await runProviderTool(tools, call.name, call.input);
+      toolCalls.push({ name: call.name, input: call.input ?? {}, ...(result.isError ? { isError: true } : {}) });
+      results.push({ name: call.name, ...result });
+    }
+    rounds.push({ reply, results });
+  }
+}
+
+export function buildInlineToolPrompt(prompt: string, tools: ProviderTool[], rounds: InlineToolRound[] = []): string {
+  return [
+    prompt,
+    '',
+    'You can use the tools below. To call them, reply with nothing but one or more blocks like',
+    '<tool_call>{"name": "<tool name>", "input": {<the tool input as JSON>}}</tool_call>',
+    'and you will be sent the results. Once you have what you need, reply with your answer and no tool_call blocks.',
+    '',
+    '<tools>',
+    ...tools.flatMap((tool) => [
+      `<tool name="${tool.name}">`,
+      ...(tool.description !== undefined ? [tool.description] : []),
+      `Input schema: ${JSON.stringify(tool.inputSchema)}`,
+      '</tool>',
+    ]),
+    '</tools>',
+    ...rounds.flatMap((round) => [
+      '',
+      '<your-reply>',
+      round.reply.trim(),
+      '</your-reply>',
+      ...round.results.map((result) => `<tool_result name="${result.name}"${result.isError ? ' error="true"' : ''}>\n${result.content}\n</tool_result>`),
+    ]),
+  ].join('\n');
+}
+
+export function parseInlineToolCalls(content: string): Array<{ name: string; input?: Record<string, unknown> }> {
+  const calls: Array<{ name: string; input?: Record<string, unknown> }> = [];
+  for (const match of content.matchAll(/<tool_call>\s*([\s\S]*?)\s*<\/tool_call>/g)) {
+    let parsed: Record<string, unknown>;
+    try {
+      parsed = asRecord(JSON.parse(match[1]!)) ?? {};
+    } catch {
+      calls.push({ name: 'tool_call' });
+      continue;
+    }
+    const input = parsed.input ?? parsed.arguments ?? {};
+    calls.push({
+      name: typeof parsed.name === 'string' ? parsed.name : 'tool_call',
+      ...(typeof input === 'object' && input !== null && !Array.isArray(input) ? { input: input as Record<string, unknown> } : {}),
+    });
+  }
+  return calls;
+}
@@ -0,0 +1,19 @@
+import { asRecord } from './records.js';
+const DEFAULT_MAX_CONCURRENT = 3;
+export function readConcurrencySettings(workspaceConfig) {
+    const section = asRecord(asRecord(workspaceConfig.providers)?.concurrency) ?? {};
+    const isLimit = (value) => typeof value === 'number' && Number.isInteger(value) && value > 0;
+    return {
+        maxConcurrent: isLimit(section.maxConcurrent) ? section.maxConcurrent : DEFAULT_MAX_CONCURRENT,
+        perProvider: Object.fromEntries(Object.entries(asRecord(section.perProvider) ?? {}).filter((entry) => isLimit(entry[1]))),
+    };
+}
+// Prompt and delegate steps count against their provider's limit; tool and control steps, and
+// discussions, which spread over several providers, count against none.
+export function workflowStepProvider(step, defaultProvider) {
+    if (step.type !== 'prompt' && step.type !== 'delegate') {
+        return undefined;
+    }
+    const provider = asRecord(step.config)?.provider;
+    return typeof provider === 'string' ? provider : defaultProvider ?? 'claude';
+}
@@ -0,0 +1,31 @@
+import type { WorkflowStep } from '@defai.digital/workflow-engine';
+import { asRecord } from './records.js';
+
+/** The `providers.concurrency` section: how much agent and workflow work runs at once. */
+export interface ConcurrencySettings {
+  /** Parallel agent tasks, or workflow steps, running at once. */
+  maxConcurrent: number;
+  /** Tasks running at once per provider, such as `{ "ollama": 1 }` for a local model. */
+  perProvider: Record<string, number>;
+}
+
+const DEFAULT_MAX_CONCURRENT = 3;
+
+export function readConcurrencySettings(workspaceConfig: Record<string, unknown>): ConcurrencySettings {
+  const section = asRecord(asRecord(workspaceConfig.providers)?.concurrency) ?? {};
+  const isLimit = (value: unknown): value is number => typeof value === 'number' && Number.isInteger(value) && value > 0;
+  return {
+    maxConcurrent: isLimit(section.maxConcurrent) ? section.maxConcurrent : DEFAULT_MAX_CONCURRENT,
+    perProvider: Object.fromEntries(Object.entries(asRecord(section.perProvider) ?? {}).filter((entry): entry is [string, number] => isLimit(entry[1]))),
+  };
+}
+
+// Prompt and delegate steps count against their provider's limit; tool and control steps, and
+// discussions, which spread over several providers, count against none.
+export function workflowStepProvider(step: WorkflowStep, defaultProvider: string | undefined): string | undefined {
+  if (step.type !== 'prompt' && step.type !== 'delegate') {
+    return undefined;
+  }
+  const provider = asRecord(step.config)?.provider;
+  return typeof provider === 'string' ? provider : defaultProvider ?? 'claude';
+}
@@ -0,0 +1,189 @@
+import { asRecord } from './records.js';
+export const MIN_CONSENSUS_PROVIDERS = 2;
+export const MAX_CONSENSUS_PROVIDERS = 3;
+/** The `providers.consensus` section: what a call asking for consensus without naming providers gets. */
+export function readConsensusDefaults(workspaceConfig) {
+    const section = asRecord(asRecord(workspaceConfig.providers)?.consensus) ?? {};
+    const providers = Array.isArray(section.providers)
+        ? section.providers.filter((entry) => typeof entry === 'string' && entry.length > 0)
+        : [];
+    return {
+        ...(providers.length > 0 ? { providers } : {}),
+        ...(section.strategy === 'fastest' || section.strategy === 'judge' ? { strategy: section.strategy } : {}),
+        ...(typeof section.judge === 'string' && section.judge.length > 0 ? { judge: section.judge } : {}),
+    };
+}
+/**
+ * Sends one request to several providers at once. Under `fastest` the first successful answer
+ * wins and the other calls are cancelled; under `judge` every answer is collected and the judge
+ * provider picks the best or merges them. The response carries the summed usage of every call
+ * and a `consensus` record of who answered and what was chosen. Streaming is off: answers race,
+ * so there is no single reply to stream until one is picked.
+ */
+export async function runConsensus(execute, request, options) {
+    const startedAt = Date.now();
+    const providers = [...new Set(options.providers)].slice(0, MAX_CONSENSUS_PROVIDERS);
+    const controllers = providers.map(() => new AbortController());
+    const onAbort = () => controllers.forEach((controller) => controller.abort());
+    request.signal?.addEventListener('abort', onAbort, { once: true });
+    let winner;
+    let outcomes;
+    try {
+        outcomes = await Promise.all(providers.map(async (provider, index) => {
+            const outcome = await execute({ ...request, provider, signal: controllers[index].signal, onText: undefined });
+            if (options.strategy === 'fastest' && winner === undefined && outcome.type === 'response') {
+                winner = index;
+                controllers.forEach((controller, other) => other === index ? undefined : controller.abort());
+            }
+            return outcome;
+        }));
+    }
+    finally {
+        request.signal?.removeEventListener('abort', onAbort);
+    }
+    // With no executor for any of them every call is simulated, and so is the consensus.
+    const unavailable = outcomes.find((outcome) => outcome.type === 'unavailable');
+    if (unavailable !== undefined && outcomes.every((outcome) => outcome.type === 'unavailable')) {
+        return unavailable;
+    }
+    const responses = outcomes.map((outcome, index) => outcome.type === 'unavailable'
+        ? {
+            success: false,
+            provider: providers[index],
+            latencyMs: 0,
+            errorCode: 'PROVIDER_EXECUTOR_NOT_CONFIGURED',
+            error: outcome.error,
+            mode: 'subprocess',
+        }
+        : outcome.response);
+    const candidates = responses.map((response, index) => ({
+        provider: providers[index],
+        label: String.fromCharCode(65 + index),
+        success: response.success,
+        latencyMs: response.latencyMs,
+        ...(response.errorCode !== undefined ? { errorCode: response.errorCode } : {}),
+        ...(response.error !== undefined ? { error: response.error } : {}),
+        chosen: false,
+    }));
+    const answered = responses
+        .map((response, index) => ({ response, index }))
+        .filter((entry) => entry.response.success);
+    if (answered.length === 0) {
+        return {
+            type: 'failure',
+            response: combine(request, responses, {
+                success: false,
+                provider: providers[0],
+                latencyMs: Date.now() - startedAt,
+                errorCode: 'PROVIDER_CONSENSUS_FAILED',
+                error: `None of the consensus providers answered: ${candidates.map((candidate) => `${candidate.provider}: ${candidate.error ?? candidate.errorCode ?? 'failed'}`).join('; ')}`,
+                mode: responses[0].mode,
+            }, { strategy: options.strategy, candidates, verdict: options.strategy === 'fastest' ? 'fastest' : 'chosen' }),
+        };
+    }
+    if (options.strategy === 'fastest' || answered.length === 1) {
+        const index = winner ?? answered.sort((left, right) => left.response.latencyMs - right.response.latencyMs)[0].index;
+        candidates[index].chosen = true;
+        return {
+            type: 'response',
+            response: combine(request, responses, { ...responses[index], latencyMs: Date.now() - startedAt }, {
+                strategy: options.strategy,
+                candidates,
+                verdict: options.strategy === 'fastest' ? 'fastest' : 'chosen',
+            }, answered.length === 1 && options.strategy === 'judge' ? [`Only "${providers[index]}" answered; it was returned without judging.`] : []),
+        };
+    }
+    const judge = options.judge ?? providers[0];
+    const judged = await execute({
+        ...request,
+        provider: judge,
+        onText: undefined,
+        prompt: buildJudgePrompt(request.prompt, answered.map((entry) => ({ label: candidates[entry.index].label, content: entry.response.content ?? '' }))),
+    });
+    const judgeResponse = judged.type === 'unavailable' ? undefined : judged.response;
+    const allResponses = judgeResponse === undefined ? responses : [...responses, judgeResponse];
+    const verdict = judgeResponse?.success === true ? parseVerdict(judgeResponse.content ?? '', answered.map((entry) => candidates[entry.index].label)) : undefined;
+    if (verdict === undefined) {
+        const fastest = answered.sort((left, right) => left.response.latencyMs - right.response.latencyMs)[0];
+        candidates[fastest.index].chosen = true;
+        return {
+            type: 'response',
+            response: combine(request, allResponses, { ...fastest.response, latencyMs: Date.now() - startedAt }, {
+                strategy: 'judge',
+                candidates,
+                judge,
+                verdict: 'chosen',
+            }, [`Judge "${judge}" gave no verdict${judgeResponse?.error !== undefined ? ` (${judgeResponse.error})` : ''}; returned the fastest answer, from "${providers[fastest.index]}".`]),
+        };
+    }
+    for (const candidate of candidates) {
+        candidate.chosen = verdict.labels.includes(candidate.label);
+    }
+    if (verdict.kind === 'chosen') {
+        const index = candidates.findIndex((candidate) => candidate.chosen);
+        return {
+            type: 'response',
+            response: combine(request, allResponses, { ...responses[index], latencyMs: Date.now() - startedAt }, { strategy: 'judge', candidates, judge, verdict: 'chosen' }),
+        };
+    }
+    return {
+        type: 'response',
+        response: combine(request, allResponses, {
+            ...judgeResponse,
+            content: verdict.content,
+            latencyMs: Date.now() - startedAt,
+        }, { strategy: 'judge', candidates, judge, verdict: 'merged' }),
+    };
+}
+export function buildJudgePrompt(prompt, answers) {
+    return [
+        'Several models answered the same request. Pick the best answer, or merge them when each gets a different part right.',
+        '',
+        '<request>',
+        prompt,
+        '</request>',
+        '',
+        ...answers.flatMap((answer) => [`<answer label="${answer.label}">`, answer.content, '</answer>', '']),
+        'Reply with one first line, either "CHOSEN: <label>" for an answer that is best as it is, or "MERGED: <labels>" followed by the merged answer in full. Write nothing else.',
+    ].join('\n');
+}
+function parseVerdict(content, labels) {
+    const [firstLine = '', ...rest] = content.trim().split('\n');
+    const match = /^\**\s*(CHOSEN|MERGED)\s*:\s*(.+?)\**\s*$/i.exec(firstLine.trim());
+    if (match === null) {
+        return undefined;
+    }
+    const named = match[2].split(/[\s,]+/).map((label) => label.replace(/[^A-Za-z]/g, '').toUpperCase()).filter((label) => labels.includes(label));
+    if (named.length === 0) {
+        return undefined;
+    }
+    if (match[1].toUpperCase() === 'CHOSEN') {
+        return { kind: 'chosen', labels: named.slice(0, 1) };
+    }
+    const merged = rest.join('\n').trim();
+    return merged.length === 0 ? undefined : { kind: 'merged', labels: named, content: merged };
+}
+// The returned response with the usage of every call made for it and the consensus record.
+function combine(request, responses, response, consensus, warnings = []) {
+    const usages = responses.flatMap((entry) => entry.usage === undefined ? [] : [entry.usage]);
+    const failed = consensus.candidates.filter((candidate) => !candidate.success && candidate.errorCode !== 'PROVIDER_CANCELLED');
+    return {
+        ...response,
+        model: response.model ?? request.model,
+        ...(usages.length > 0 ? {
+            usage: {
+                inputTokens: usages.reduce((sum, usage) => sum + usage.inputTokens, 0),
+                outputTokens: usages.reduce((sum, usage) => sum + usage.outputTokens, 0),
+                totalTokens: usages.reduce((sum, usage) => sum + usage.totalTokens, 0),
+            },
+        } : {}),
+        warnings: [
+            ...new Set([
+                ...responses.flatMap((entry) => entry.warnings ?? []),
+                ...(response.success ? failed.map((candidate) => `Consensus provider "${candidate.provider}" failed: ${candidate.error ?? candidate.errorCode ?? 'unknown error'}`) : []),
+                ...warnings,
+            ]),
+        ],
+        consensus,
+    };
+}
@@ -0,0 +1,27 @@
+import { asRecord } from './records.js';
+const DEFAULT_FAILOVER_ATTEMPTS = 2;
+export function readFailoverSettings(workspaceConfig) {
+    const section = asRecord(asRecord(workspaceConfig.providers)?.failover) ?? {};
+    return {
+        enabled: section.enabled !== false,
+        attempts: typeof section.attempts === 'number' && Number.isInteger(section.attempts) && section.attempts >= 0
+            ? section.attempts
+            : DEFAULT_FAILOVER_ATTEMPTS,
+    };
+}
+/**
+ * The prompt that asks the next provider to pick up a reply where the failed one stopped. The
+ * partial reply goes in as text already written, so the continuation can be appended to it as is.
+ */
+export function buildContinuationPrompt(prompt, partial) {
+    return [
+        prompt,
+        '',
+        'A reply to the request above was cut off partway. This is what had been written when it stopped:',
+        '<partial-reply>',
+        partial,
+        '</partial-reply>',
+        '',
+        'Continue the reply from exactly where it stops. Do not repeat any of it, start over, or mention the interruption.',
+    ].join('\n');
+}
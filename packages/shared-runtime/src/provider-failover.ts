@@ -0,0 +1,49 @@
+import { asRecord } from './records.js';
+
+/** The `providers.failover` section. */
+export interface ProviderFailoverSettings {
+  /** Whether a reply cut off partway is continued by a fallback provider. On unless false. */
+  enabled: boolean;
+  /** Fallback providers tried, one after another, before the failure is returned. */
+  attempts: number;
+}
+
+/** A provider that failed partway through a reply, and the one that continued it. */
+export interface ProviderFailover {
+  from: string;
+  to: string;
+  errorCode?: string;
+  error?: string;
+  /** Characters of the reply the failed provider had produced, passed on as the start of the reply. */
+  partialChars: number;
+  at: string;
+}
+
+const DEFAULT_FAILOVER_ATTEMPTS = 2;
+
+export function readFailoverSettings(workspaceConfig: Record<string, unknown>): ProviderFailoverSettings {
+  const section = asRecord(asRecord(workspaceConfig.providers)?.failover) ?? {};
+  return {
+    enabled: section.enabled !== false,
+    attempts: typeof section.attempts === 'number' && Number.isInteger(section.attempts) && section.attempts >= 0
+      ? section.attempts
+      : DEFAULT_FAILOVER_ATTEMPTS,
+  };
+}
+
+/**
+ * The prompt that asks the next provider to pick up a reply where the failed one stopped. The
+ * partial reply goes in as text already written, so the continuation can be appended to it as is.
+ */
+export function buildContinuationPrompt(prompt: string, partial: string): string {
+  return [
+    prompt,
+    '',
+    'A reply to the request above was cut off partway. This is what had been written when it stopped:',
+    '<partial-reply>',
+    partial,
+    '</partial-reply>',
+    '',
+    'Continue the reply from exactly where it stops. Do not repeat any of it, start over, or mention the interruption.',
+  ].join('\n');
+}
@@ -0,0 +1,138 @@
+import { createSign } from 'node:crypto';
+import { readFile } from 'node:fs/promises';
+import { homedir } from 'node:os';
+import { join } from 'node:path';
+import { asCount } from './provider-http.js';
+import { asRecord } from './records.js';
+
+export interface GoogleAccessToken {
+  token: string;
+  /** The project of the credentials, when they name one. */
+  projectId?: string;
+}
+
+const GOOGLE_SCOPE = 'https://www.googleapis.com/auth/cloud-platform';
+const GOOGLE_TOKEN_URL = 'https://oauth2.googleapis.com/token';
+const METADATA_TOKEN_URL = 'http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token';
+// Off Google Cloud the metadata host does not resolve; do not make every call wait on it.
+const METADATA_TIMEOUT_MS = 1_500;
+// Tokens are renewed this long before they expire so an in-flight call does not outlive one.
+const TOKEN_REFRESH_MARGIN_MS = 60_000;
+
+const cachedTokens = new Map<string, { token: GoogleAccessToken; expiresAt: number }>();
+
+/**
+ * An OAuth access token from Application Default Credentials, in the order Google's own
+ * libraries use: `GOOGLE_APPLICATION_CREDENTIALS` (a service-account key or an authorized-user
+ * file), the file `gcloud auth application-default login` writes, then the metadata server of
+ * the VM the runtime is on. `GOOGLE_OAUTH_ACCESS_TOKEN` short-circuits all of them. Tokens are
+ * cached until shortly before they expire. Throws `PROVIDER_CREDENTIALS_MISSING` when no source
+ * has credentials.
+ */
+export async function getGoogleAccessToken(env: NodeJS.ProcessEnv = process.env): Promise<GoogleAccessToken> {
+  const explicit = (env.GOOGLE_OAUTH_ACCESS_TOKEN ?? '').trim();
+  if (explicit.length > 0) {
+    return { token: explicit };
+  }
+
+  const path = env.GOOGLE_APPLICATION_CREDENTIALS ?? (env.CLOUDSDK_CONFIG !== undefined
+    ? join(env.CLOUDSDK_CONFIG, 'application_default_credentials.json')
+    : process.platform === 'win32' && env.APPDATA !== undefined
+      ? join(env.APPDATA, 'gcloud', 'application_default_credentials.json')
+      : join(env.HOME ?? homedir(), '.config', 'gcloud', 'application_default_credentials.json'));
+  let credentials: Record<string, unknown> | undefined;
+  try {
+    credentials = asRecord(JSON.parse(await readFile(path, 'utf8'))) ?? {};
+  } catch (error) {
+    if (env.GOOGLE_APPLICATION_CREDENTIALS !== undefined) {
+      throw Object.assign(new Error(`Could not read GOOGLE_APPLICATION_CREDENTIALS (${path}): ${error instanceof Error ? error.message : String(error)}`), {
+        code: 'PROVIDER_CREDENTIALS_MISSING',
+      });
+    }
+  }
+
+  const cacheKey = credentials === undefined ? 'metadata' : `${path}:${String(credentials.client_email ?? credentials.client_id)}`;
+  const cached = cachedTokens.get(cacheKey);
+  if (cached !== undefined && cached.expiresAt - TOKEN_REFRESH_MARGIN_MS > Date.now()) {
+    return cached.token;
+  }
+
+  const projectId = typeof credentials?.project_id === 'string'
+    ? credentials.project_id
+    : typeof credentials?.quota_project_id === 'string' ? credentials.quota_project_id : undefined;
+  const granted = credentials === undefined
+    ? await requestMetadataToken()
+    : credentials.type === 'service_account'
+      ? await requestServiceAccountToken(credentials)
+      : credentials.type === 'authorized_user'
+        ? await requestToken(GOOGLE_TOKEN_URL, {
+          grant_type: 'refresh_token',
+          client_id: String(credentials.client_id),
+          client_secret: String(credentials.client_secret),
+          refresh_token: String(credentials.refresh_token),
+        })
+        : undefined;
+  if (granted === undefined) {
+    throw Object.assign(new Error(`Google credentials of type "${String(credentials?.type)}" in ${path} are not supported; use a service-account key or application-default login.`), {
+      code: 'PROVIDER_CREDENTIALS_MISSING',
+    });
+  }
+
+  const token = { token: granted.accessToken, ...(projectId !== undefined ? { projectId } : {}) };
+  cachedTokens.set(cacheKey, { token, expiresAt: Date.now() + granted.expiresInSeconds * 1000 });
+  return token;
+}
+
+// A JWT signed with the key, exchanged at the key's token URI (RFC 7523).
+async function requestServiceAccountToken(credentials: Record<string, unknown>): Promise<{ accessToken: string; expiresInSeconds: number }> {
+  const tokenUri = typeof credentials.token_uri === 'string' ? credentials.token_uri : GOOGLE_TOKEN_URL;
+  const issuedAt = Math.floor(Date.now() / 1000);
+  const encode = (value: Record<string, unknown>) => Buffer.from(JSON.stringify(value)).toString('base64url');
+  const unsigned = `${encode({ alg: 'RS256', typ: 'JWT', ...(typeof credentials.private_key_id === 'string' ? { kid: credentials.private_key_id } : {}) })}.${encode({
+    iss: credentials.client_email,
+    scope: GOOGLE_SCOPE,
+    aud: tokenUri,
+    iat: issuedAt,
+    exp: issuedAt + 3600,
+  })}`;
+  const signature = createSign('RSA-SHA256').update(unsigned).sign(String(credentials.private_key), 'base64url');
+  return requestToken(tokenUri, {
+    grant_type: 'urn:ietf:params:oauth:grant-type:jwt-bearer',
+    assertion: `${unsigned}.${signature}`,
+  });
+}
+
+async function requestMetadataToken(): Promise<{ accessToken: string; expiresInSeconds: number }> {
+  let response: Response;
+  try {
+    response = await fetch(METADATA_TOKEN_URL, {
+      headers: { 'metadata-flavor': 'Google' },
+      signal: AbortSignal.timeout(METADATA_TIMEOUT_MS),
+    });
+  } catch {
+    throw Object.assign(new Error('No Google credentials found. Set GOOGLE_APPLICATION_CREDENTIALS or run `gcloud auth application-default login`.'), {
+      code: 'PROVIDER_CREDENTIALS_MISSING',
+    });
+  }
+  return readTokenResponse(response, 'The metadata server');
+}
+
+async function requestToken(url: string, form: Record<string, string>): Promise<{ accessToken: string; expiresInSeconds: number }> {
+  const response = await fetch(url, {
+    method: 'POST',
+    headers: { 'content-type': 'application/x-www-form-urlencoded' },
+    body: new URLSearchParams(form).toString(),
+  });
+  return readTokenResponse(response, 'Google token exchange');
+}
+
+async function readTokenResponse(response: Response, label: string): Promise<{ accessToken: string; expiresInSeconds: number }> {
+  const body = asRecord(await response.json().catch(() => undefined)) ?? {};
+  if (!response.ok || typeof body.access_token !== 'string') {
+    const detail = typeof body.error_description === 'string' ? body.error_description : typeof body.error === 'string' ? body.error : undefined;
+    throw Object.assign(new Error(`${label} returned HTTP ${response.status}${detail === undefined ? '' : `: ${detail}`}`), {
+      code: 'PROVIDER_AUTH_FAILED',
+    });
+  }
+  return { accessToken: body.access_token, expiresInSeconds: asCount(body.expires_in) || 3600 };
+}
@@ -0,0 +1,211 @@
+import { mkdir, readFile, writeFile } from 'node:fs/promises';
+import { dirname, join } from 'node:path';
+import { ANTHROPIC_VERSION } from './provider-anthropic.js';
+import { resolveAwsCredentials } from './provider-aws.js';
+import { getGoogleAccessToken } from './provider-google-auth.js';
+import { createProviderCallSignal, describeHttpError } from './provider-http.js';
+import { asRecord } from './records.js';
+const DEFAULT_HEALTH_MAX_AGE_MS = 15 * 60_000;
+const DEFAULT_HEALTH_TIMEOUT_MS = 10_000;
+const DEFAULT_HEALTH_SLOW_MS = 5_000;
+export function getProviderHealthPath(basePath) {
+    return join(basePath, '.automatosx', 'runtime', 'provider-health.json');
+}
+export function readProviderHealthSettings(workspaceConfig) {
+    const section = asRecord(asRecord(workspaceConfig.providers)?.health) ?? {};
+    return {
+        maxAgeMs: asPositiveInteger(section.maxAgeMs) ?? DEFAULT_HEALTH_MAX_AGE_MS,
+        timeoutMs: asPositiveInteger(section.timeoutMs) ?? DEFAULT_HEALTH_TIMEOUT_MS,
+        slowMs: asPositiveInteger(section.slowMs) ?? DEFAULT_HEALTH_SLOW_MS,
+    };
+}
+/**
+ * The last probe result for each provider, kept in `.automatosx/runtime/provider-health.json` so
+ * `ax status` can show it and the router can pass over a provider that failed its probe recently.
+ */
+export function createProviderHealthMonitor(config) {
+    const now = config.now ?? (() => new Date());
+    const healthPath = getProviderHealthPath(config.basePath);
+    let pending = Promise.resolve();
+    return {
+        async getStatus() {
+            const ledger = await readHealthLedger(healthPath);
+            return Object.keys(ledger.providers).sort().map((provider) => ledger.providers[provider]);
+        },
+        record(provider, result) {
+            const task = async () => {
+                const status = { provider, ...result, checkedAt: now().toISOString() };
+                const ledger = await readHealthLedger(healthPath);
+                ledger.providers[provider] = status;
+                await mkdir(dirname(healthPath), { recursive: true });
+                await writeFile(healthPath, `${JSON.stringify(ledger, null, 2)}\n`, 'utf8');
+                return status;
+            };
+            const next = pending.then(task, task);
+            pending = next.catch(() => undefined);
+            return next;
+        },
+        /** Providers whose last probe, taken within `maxAgeMs`, found them unhealthy. */
+        async getUnhealthy(settings) {
+            const ledger = await readHealthLedger(healthPath);
+            const current = now().getTime();
+            return new Map(Object.entries(ledger.providers).filter(([, status]) => status.status === 'unhealthy'
+                && current - Date.parse(status.checkedAt) <= settings.maxAgeMs));
+        },
+    };
+}
+/**
+ * Probes an API executor with the cheapest authenticated request it has: the model list for
+ * Anthropic and OpenAI, the local tag list for Ollama. Bedrock and Vertex AI only have their
+ * credentials resolved, since their model calls cost tokens.
+ */
+export async function probeProviderApi(api, config, settings, signal) {
+    const startedAt = Date.now();
+    if (api === 'bedrock' || api === 'vertex') {
+        return probeCloudCredentials(api, config, startedAt);
+    }
+    const { url, headers, label } = describeProbeEndpoint(api, config);
+    const call = createProviderCallSignal(signal, settings.timeoutMs);
+    try {
+        const response = await fetch(url, { headers, signal: call.signal });
+        const latencyMs = Date.now() - startedAt;
+        const quota = readQuotaHeaders(response.headers, Date.now());
+        const base = { probe: 'http', latencyMs, ...(quota !== undefined ? { quota } : {}) };
+        if (response.ok) {
+            await response.body?.cancel();
+            const exhausted = quota?.requestsRemaining === 0 || quota?.tokensRemaining === 0;
+            return {
+                ...base,
+                status: exhausted || latencyMs > settings.slowMs ? 'degraded' : 'healthy',
+                ...(api !== 'ollama' || config.apiKey.length > 0 ? { auth: 'ok' } : {}),
+                ...(exhausted ? { error: `${label} reports no ${quota?.requestsRemaining === 0 ? 'requests' : 'tokens'} left until ${quota?.resetsAt ?? 'its limit resets'}.` } : {}),
+            };
+        }
+        const failure = await describeHttpError(response, label);
+        return {
+            ...base,
+            // A rate-limited provider still answers; its calls queue behind the limiter.
+            status: failure.errorCode === 'PROVIDER_RATE_LIMITED' ? 'degraded' : 'unhealthy',
+            ...(failure.errorCode === 'PROVIDER_AUTH_FAILED' ? { auth: 'expired' } : failure.errorCode === 'PROVIDER_RATE_LIMITED' ? { auth: 'ok' } : {}),
+            errorCode: failure.errorCode,
+            error: failure.error,
+        };
+    }
+    catch (error) {
+        const cause = error.cause;
+        const message = error instanceof Error ? error.message : String(error);
+        return {
+            status: 'unhealthy',
+            probe: 'http',
+            latencyMs: Date.now() - startedAt,
+            errorCode: call.timedOut() ? 'PROVIDER_TIMEOUT' : 'PROVIDER_UNREACHABLE',
+            error: call.timedOut()
+                ? `${label} did not answer the health probe within ${settings.timeoutMs}ms.`
+                : cause instanceof Error ? `${message}: ${cause.message}` : message,
+        };
+    }
+    finally {
+        call.dispose();
+    }
+}
+function describeProbeEndpoint(api, config) {
+    const baseUrl = config.baseUrl.replace(/\/+$/, '');
+    if (api === 'anthropic') {
+        return { url: `${baseUrl}/v1/models?limit=1`, headers: { 'x-api-key': config.apiKey, 'anthropic-version': ANTHROPIC_VERSION }, label: 'Anthropic' };
+    }
+    if (api === 'openai') {
+        return {
+            url: `${baseUrl}/models${config.apiVersion === undefined ? '' : `?api-version=${encodeURIComponent(config.apiVersion)}`}`,
+            headers: config.apiVersion === undefined ? { authorization: `Bearer ${config.apiKey}` } : { 'api-key': config.apiKey },
+            label: 'OpenAI',
+        };
+    }
+    return { url: `${baseUrl}/api/tags`, headers: config.apiKey.length > 0 ? { authorization: `Bearer ${config.apiKey}` } : {}, label: 'Ollama' };
+}
+async function probeCloudCredentials(api, config, startedAt) {
+    const env = config.env ?? process.env;
+    if (api === 'bedrock') {
+        const found = (env.AWS_BEARER_TOKEN_BEDROCK ?? '').trim().length > 0
+            || await resolveAwsCredentials(env, config.profile) !== undefined;
+        return found
+            ? { status: 'healthy', probe: 'credentials', latencyMs: Date.now() - startedAt, auth: 'ok' }
+            : {
+                status: 'unhealthy',
+                probe: 'credentials',
+                latencyMs: Date.now() - startedAt,
+                auth: 'missing',
+                errorCode: 'PROVIDER_CREDENTIALS_MISSING',
+                error: `No AWS credentials were found${config.profile === undefined ? '' : ` for profile "${config.profile}"`}.`,
+            };
+    }
+    try {
+        await getGoogleAccessToken(env);
+        return { status: 'healthy', probe: 'credentials', latencyMs: Date.now() - startedAt, auth: 'ok' };
+    }
+    catch (error) {
+        const code = error.code ?? 'PROVIDER_UNREACHABLE';
+        return {
+            status: 'unhealthy',
+            probe: 'credentials',
+            latencyMs: Date.now() - startedAt,
+            ...(code === 'PROVIDER_CREDENTIALS_MISSING' ? { auth: 'missing' } : code === 'PROVIDER_AUTH_FAILED' ? { auth: 'expired' } : {}),
+            errorCode: code,
+            error: error instanceof Error ? error.message : String(error),
+        };
+    }
+}
+// Anthropic sends `anthropic-ratelimit-*` with RFC 3339 resets; OpenAI sends `x-ratelimit-*`
+// with resets as durations such as `6m0s`.
+function readQuotaHeaders(headers, now) {
+    const read = (name) => {
+        const value = headers.get(name);
+        return value !== null && /^\d+$/.test(value.trim()) ? Number(value) : undefined;
+    };
+    const quota = {};
+    for (const [field, names] of [
+        ['requestsRemaining', ['anthropic-ratelimit-requests-remaining', 'x-ratelimit-remaining-requests']],
+        ['requestLimit', ['anthropic-ratelimit-requests-limit', 'x-ratelimit-limit-requests']],
+        ['tokensRemaining', ['anthropic-ratelimit-tokens-remaining', 'x-ratelimit-remaining-tokens']],
+        ['tokenLimit', ['anthropic-ratelimit-tokens-limit', 'x-ratelimit-limit-tokens']],
+    ]) {
+        const value = read(names[0]) ?? read(names[1]);
+        if (value !== undefined) {
+            quota[field] = value;
+        }
+    }
+    if (Object.keys(quota).length === 0) {
+        return undefined;
+    }
+    const anthropicReset = Date.parse(headers.get('anthropic-ratelimit-requests-reset') ?? '');
+    const openAiReset = parseResetDuration(headers.get('x-ratelimit-reset-requests'));
+    if (!Number.isNaN(anthropicReset)) {
+        quota.resetsAt = new Date(anthropicReset).toISOString();
+    }
+    else if (openAiReset !== undefined) {
+        quota.resetsAt = new Date(now + openAiReset).toISOString();
+    }
+    return quota;
+}
+function parseResetDuration(value) {
+    const match = value?.trim().match(/^(?:(\d+)h)?(?:(\d+)m(?!s))?(?:(\d+(?:\.\d+)?)s)?(?:(\d+)ms)?$/);
+    if (match === undefined || match === null || match[0].length === 0) {
+        return undefined;
+    }
+    const [, hours, minutes, seconds, milliseconds] = match;
+    return Math.ceil(Number(hours ?? 0) * 3_600_000 + Number(minutes ?? 0) * 60_000 + Number(seconds ?? 0) * 1000 + Number(milliseconds ?? 0));
+}
+async function readHealthLedger(healthPath) {
+    try {
+        const parsed = asRecord(JSON.parse(await readFile(healthPath, 'utf8'))) ?? {};
+        if (parsed.version === 1 && typeof parsed.providers === 'object' && parsed.providers !== null) {
+            return parsed;
+        }
+    }
+    catch {
+        // Providers that were never probed have no health to report.
+    }
+    return { version: 1, providers: {} };
+}
+function asPositiveInteger(value) {
+    return typeof value === 'number' && Number.isInteger(value) && value > 0 ? value : undefined;
+}
@@ -0,0 +1,261 @@
+import type { ProviderExecutionRequest, ProviderExecutionResponse, ProviderTool } from './provider-bridge.js';
+import { asRecord } from './records.js';
+
+export interface ProviderHttpConfig {
+  baseUrl: string;
+  apiKey: string;
+  model: string;
+  maxTokens?: number;
+  timeoutMs: number;
+  /** Azure OpenAI's `api-version`; set, the key goes in an `api-key` header instead of a bearer token. */
+  apiVersion?: string;
+  /** Ollama: pull a model the server does not have yet. On unless false, for servers without network access. */
+  pull?: boolean;
+  /** Ollama: overrides the context window read from the model. */
+  contextLength?: number;
+  /** Bedrock and Vertex AI: the cloud region the model is served from. */
+  region?: string;
+  /** Vertex AI: the Google Cloud project billed for the calls. */
+  project?: string;
+  /** Bedrock: the shared-credentials profile to sign with. */
+  profile?: string;
+  /** Where cloud credentials are looked up (`AWS_*`, `GOOGLE_*`); defaults to the process environment. */
+  env?: NodeJS.ProcessEnv;
+}
+
+// Each round is one request; models stop asking for tools long before this in practice.
+export const MAX_TOOL_ROUNDS = 10;
+
+export interface ServerSentEvent {
+  event?: string;
+  data: string;
+}
+
+export interface ProviderCallSignal {
+  signal: AbortSignal;
+  timedOut(): boolean;
+  dispose(): void;
+}
+
+/** The model to ask the API for: the runtime's placeholder names (`v14-agent-run`, ...) are not API models. */
+export function resolveApiModel(requested: string | undefined, configured: string): string {
+  return requested === undefined || /^v14-/.test(requested) ? configured : requested;
+}
+
+/** Aborts when the caller's signal does or when `timeoutMs` passes, remembering which. */
+export function createProviderCallSignal(signal: AbortSignal | undefined, timeoutMs: number): ProviderCallSignal {
+  const controller = new AbortController();
+  let timedOut = false;
+  const timer = setTimeout(() => {
+    timedOut = true;
+    controller.abort();
+  }, timeoutMs);
+  const onAbort = () => controller.abort();
+  if (signal?.aborted === true) {
+    controller.abort();
+  } else {
+    signal?.addEventListener('abort', onAbort, { once: true });
+  }
+  return {
+    signal: controller.signal,
+    timedOut: () => timedOut,
+    dispose() {
+      clearTimeout(timer);
+      signal?.removeEventListener('abort', onAbort);
+    },
+  };
+}
+
+export function postJson(
+  url: string,
+  headers: Record<string, string>,
+  body: Record<string, unknown>,
+  signal: AbortSignal,
+): Promise<Response> {
+  return fetch(url, {
+    method: 'POST',
+    headers: { 'content-type': 'application/json', ...headers },
+    body: JSON.stringify(body),
+    signal,
+  });
+}
+
+/** Yields the events of a `text/event-stream` body as they arrive. */
+export async function* readServerSentEvents(body: ReadableStream<Uint8Array>): AsyncGenerator<ServerSentEvent> {
+  const reader = body.getReader();
+  const decoder = new TextDecoder();
+  let buffer = '';
+  let event: string | undefined;
+  let data: string[] = [];
+  try {
+    for (;;) {
+      const { done, value } = await reader.read();
+      buffer += done ? decoder.decode() : decoder.decode(value, { stream: true });
+      const lines = buffer.split(/\r?\n/);
+      buffer = done ? '' : lines.pop() ?? '';
+      for (const line of lines) {
+        if (line === '') {
+          if (data.length > 0) {
+            yield { ...(event !== undefined ? { event } : {}), data: data.join('\n') };
+          }
+          event = undefined;
+          data = [];
+        } else if (line.startsWith('data:')) {
+          data.push(line.slice(5).replace(/^ /, ''));
+        } else if (line.startsWith('event:')) {
+          event = line.slice(6).trim();
+        }
+      }
+      if (done) {
+        if (data.length > 0) {
+          yield { ...(event !== undefined ? { event } : {}), data: data.join('\n') };
+        }
+        return;
+      }
+    }
+  } finally {
+    reader.releaseLock();
+  }
+}
+
+/** Yields each JSON line of a newline-delimited body as it arrives; lines that are not JSON are skipped. */
+export async function* readJsonLines(body: ReadableStream<Uint8Array>): AsyncGenerator<Record<string, unknown>> {
+  const reader = body.getReader();
+  const decoder = new TextDecoder();
+  let buffer = '';
+  try {
+    for (;;) {
+      const { done, value } = await reader.read();
+      buffer += done ? decoder.decode() : decoder.decode(value, { stream: true });
+      const lines = buffer.split('\n');
+      buffer = done ? '' : lines.pop() ?? '';
+      for (const line of lines) {
+        if (line.trim().length === 0) {
+          continue;
+        }
+        try {
+          yield asRecord(JSON.parse(line)) ?? {};
+        } catch {
+          continue;
+        }
+      }
+      if (done) {
+        return;
+      }
+    }
+  } finally {
+    reader.releaseLock();
+  }
+}
+
+/**
+ * The error code and message for a non-2xx reply: `PROVIDER_AUTH_FAILED` for 401/403,
+ * `PROVIDER_RATE_LIMITED` for 429, else `PROVIDER_HTTP_ERROR`, with the API's own message when
+ * the body carries one. A 429 also carries the wait the server asked for, when it named one.
+ */
+export async function describeHttpError(
+  response: Response,
+  label: string,
+): Promise<{ errorCode: string; error: string; retryAfterMs?: number }> {
+  const text = await response.text().catch(() => '');
+  let message: string | undefined;
+  try {
+    const parsed = JSON.parse(text) as { error?: unknown; message?: unknown };
+    const error = parsed.error;
+    message = typeof error === 'string'
+      ? error
+      : typeof (error as { message?: unknown } | undefined)?.message === 'string'
+        ? (error as { message: string }).message
+        : typeof parsed.message === 'string' ? parsed.message : undefined;
+  } catch {
+    message = text.trim().slice(0, 500) || undefined;
+  }
+  const errorCode = response.status === 401 || response.status === 403
+    ? 'PROVIDER_AUTH_FAILED'
+    : response.status === 429 ? 'PROVIDER_RATE_LIMITED' : 'PROVIDER_HTTP_ERROR';
+  const retryAfterMs = response.status === 429 ? readRetryAfter(response.headers) : undefined;
+  return {
+    errorCode,
+    error: `${label} returned HTTP ${response.status}${message === undefined ? '.' : `: ${message}`}`,
+    ...(retryAfterMs !== undefined ? { retryAfterMs } : {}),
+  };
+}
+
+/** Milliseconds to wait from `retry-after-ms` (OpenAI) or `retry-after`, in seconds or as an HTTP date. */
+export function readRetryAfter(headers: Headers, now = Date.now()): number | undefined {
+  const milliseconds = headers.get('retry-after-ms');
+  if (milliseconds !== null && milliseconds.trim().length > 0 && Number(milliseconds) >= 0) {
+    return Math.ceil(Number(milliseconds));
+  }
+  const value = headers.get('retry-after')?.trim();
+  if (value === undefined || value.length === 0) {
+    return undefined;
+  }
+  if (/^\d+(\.\d+)?$/.test(value)) {
+    return Math.ceil(Number(value) * 1000);
+  }
+  const date = Date.parse(value);
+  return Number.isNaN(date) ? undefined : Math.max(0, date - now);
+}
+
+/** Runs a tool the model asked for; unknown tools and thrown errors come back as error results. */
+export async function runProviderTool(
+  tools: ProviderTool[],
+  name: string,
+  input: Record<string, unknown>,
+): Promise<{ content: string; isError: boolean }> {
+  const tool = tools.find((candidate) => candidate.name === name);
+  if (tool === undefined) {
+    return { content: `Unknown tool "${name}".`, isError: true };
+  }
+  try {
+    return { content: await tool.run(input), isError: false };
+  } catch (error) {
+    return { content: error instanceof Error ? error.message : String(error), isError: true };
+  }
+}
+
+export function httpFailure(
+  request: ProviderExecutionRequest,
+  model: string | undefined,
+  startedAt: number,
+  errorCode: string,
+  error: string,
+  retryAfterMs?: number,
+): ProviderExecutionResponse {
+  return {
+    success: false,
+    provider: request.provider,
+    model,
+    latencyMs: Date.now() - startedAt,
+    errorCode,
+    error,
+    ...(retryAfterMs !== undefined ? { retryAfterMs } : {}),
+    mode: 'http',
+  };
+}
+
+/** The failure for a request that threw: timed out, cancelled, or the server could not be reached. */
+export function describeHttpException(
+  request: ProviderExecutionRequest,
+  model: string | undefined,
+  startedAt: number,
+  call: ProviderCallSignal,
+  timeoutMs: number,
+  error: unknown,
+): ProviderExecutionResponse {
+  if (call.timedOut()) {
+    return httpFailure(request, model, startedAt, 'PROVIDER_TIMEOUT', `Provider "${request.provider}" exceeded timeout (${timeoutMs}ms).`);
+  }
+  if (request.signal?.aborted === true) {
+    return httpFailure(request, model, startedAt, 'PROVIDER_CANCELLED', `Provider "${request.provider}" call was cancelled.`);
+  }
+  const cause = (error as { cause?: unknown }).cause;
+  const message = error instanceof Error ? error.message : String(error);
+  return httpFailure(request, model, startedAt, 'PROVIDER_UNREACHABLE', cause instanceof Error ? `${message}: ${cause.message}` : message);
+}
+
+/** A JSON number field, or 0. */
+export function asCount(value: unknown): number {
+  return typeof value === 'number' && Number.isFinite(value) ? value : 0;
+}
@@ -0,0 +1,222 @@
+import { asCount, createProviderCallSignal, describeHttpError, describeHttpException, httpFailure, MAX_TOOL_ROUNDS, postJson, readJsonLines, resolveApiModel, runProviderTool, } from './provider-http.js';
+import { asRecord } from './records.js';
+export const OLLAMA_DEFAULT_BASE_URL = 'http://127.0.0.1:11434';
+export const OLLAMA_DEFAULT_MODEL = 'llama3.2';
+// Ollama runs every model with a small window (2048 or 4096 tokens) unless a request asks for
+// more, and silently drops the start of longer prompts.
+const OLLAMA_MIN_CONTEXT = 8192;
+const OLLAMA_REPLY_RESERVE = 2048;
+const contextLengths = new Map();
+/** The models on the server, with each one's context length when the server reports it. */
+export async function listOllamaModels(config, signal) {
+    return Promise.all((await fetchOllamaTags(config, signal)).map(async (model) => {
+        const details = asRecord(model.details) ?? {};
+        const name = String(model.name ?? model.model ?? '');
+        const contextLength = await detectContextLength(config, name, signal).catch(() => undefined);
+        return {
+            name,
+            ...(typeof model.size === 'number' ? { sizeBytes: model.size } : {}),
+            ...(typeof model.modified_at === 'string' ? { modifiedAt: model.modified_at } : {}),
+            ...(typeof details.family === 'string' ? { family: details.family } : {}),
+            ...(typeof details.parameter_size === 'string' ? { parameterSize: details.parameter_size } : {}),
+            ...(typeof details.quantization_level === 'string' ? { quantization: details.quantization_level } : {}),
+            ...(contextLength !== undefined ? { contextLength } : {}),
+        };
+    }));
+}
+/**
+ * Chats with a model through Ollama's `/api/chat`, streaming the reply. A model the server does
+ * not have is pulled first unless `pull` is off, and the context window is sized to the
+ * prompt, up to what the model supports. Tool calls run like the other API executors'.
+ */
+export async function executeOllamaChat(config, request, adapted) {
+    const startedAt = Date.now();
+    const model = resolveApiModel(request.model, config.model);
+    const baseUrl = trimBaseUrl(config.baseUrl);
+    const timeoutMs = request.timeoutMs ?? config.timeoutMs;
+    const tools = request.tools ?? [];
+    const warnings = [...adapted.warnings];
+    // Pulling can take far longer than a reply, so only cancelling stops it.
+    try {
+        const installed = await fetchOllamaTags(config, request.signal);
+        if (!installed.some((candidate) => sameModel(String(candidate.name ?? candidate.model ?? ''), model))) {
+            if (config.pull === false) {
+                return httpFailure(request, model, startedAt, 'PROVIDER_MODEL_MISSING', `Ollama has no model "${model}". Run "ollama pull ${model}" or enable pulling for this executor.`);
+            }
+            const pulled = await pullOllamaModel(config, model, request.signal);
+            if (pulled !== undefined) {
+                return httpFailure(request, model, startedAt, 'PROVIDER_MODEL_MISSING', `Could not pull "${model}" into Ollama: ${pulled}`);
+            }
+            warnings.push(`Pulled model "${model}" into Ollama.`);
+        }
+    }
+    catch (error) {
+        if (request.signal?.aborted === true) {
+            return httpFailure(request, model, startedAt, 'PROVIDER_CANCELLED', `Provider "${request.provider}" call was cancelled.`);
+        }
+        const code = error.code;
+        const message = error instanceof Error ? error.message : String(error);
+        const cause = error.cause;
+        return httpFailure(request, model, startedAt, typeof code === 'string' ? code : 'PROVIDER_UNREACHABLE', cause instanceof Error ? `${message}: ${cause.message}` : message);
+    }
+    const contextLength = config.contextLength ?? await detectContextLength(config, model, request.signal).catch(() => undefined);
+    // About four characters a token; the tool descriptions and replies of later rounds need room too.
+    const promptTokens = Math.ceil(((adapted.systemPrompt ?? '').length + adapted.prompt.length + JSON.stringify(tools.map((tool) => tool.inputSchema)).length) / 4);
+    const wanted = Math.max(OLLAMA_MIN_CONTEXT, 2 ** Math.ceil(Math.log2(promptTokens + (request.maxTokens ?? config.maxTokens ?? OLLAMA_REPLY_RESERVE))));
+    const numCtx = contextLength === undefined ? wanted : Math.min(wanted, contextLength);
+    if (contextLength !== undefined && promptTokens > contextLength) {
+        warnings.push(`The prompt (about ${promptTokens} tokens) is longer than the ${contextLength}-token context of "${model}"; its start will be cut.`);
+    }
+    const messages = [
+        ...(adapted.systemPrompt !== undefined ? [{ role: 'system', content: adapted.systemPrompt }] : []),
+        { role: 'user', content: adapted.prompt },
+    ];
+    const toolCalls = [];
+    let inputTokens = 0;
+    let outputTokens = 0;
+    const call = createProviderCallSignal(request.signal, timeoutMs);
+    try {
+        for (let round = 1; ; round += 1) {
+            const response = await postJson(`${baseUrl}/api/chat`, authHeaders(config.apiKey), {
+                model,
+                messages,
+                tools: tools.length === 0 ? undefined : tools.map((tool) => ({
+                    type: 'function',
+                    function: { name: tool.name, description: tool.description, parameters: tool.inputSchema },
+                })),
+                format: request.responseFormat === 'json' ? 'json' : undefined,
+                stream: true,
+                options: {
+                    num_ctx: numCtx,
+                    num_predict: request.maxTokens ?? config.maxTokens,
+                    temperature: request.temperature,
+                    stop: adapted.stopSequences,
+                },
+            }, call.signal);
+            if (!response.ok) {
+                const { errorCode, error, retryAfterMs } = await describeHttpError(response, 'Ollama');
+                return httpFailure(request, model, startedAt, errorCode, error, retryAfterMs);
+            }
+            if (response.body === null) {
+                return httpFailure(request, model, startedAt, 'PROVIDER_EMPTY_RESPONSE', 'Ollama returned no body.');
+            }
+            let content = '';
+            let doneReason;
+            const calls = [];
+            for await (const line of readJsonLines(response.body)) {
+                if (typeof line.error === 'string') {
+                    return httpFailure(request, model, startedAt, 'PROVIDER_STREAM_ERROR', `Ollama stream failed: ${line.error}`);
+                }
+                const message = asRecord(line.message) ?? {};
+                if (typeof message.content === 'string' && message.content.length > 0) {
+                    content += message.content;
+                    request.onText?.(message.content);
+                }
+                for (const entry of Array.isArray(message.tool_calls) ? message.tool_calls : []) {
+                    const fn = asRecord(asRecord(entry)?.function) ?? {};
+                    if (typeof fn.name === 'string') {
+                        calls.push({ name: fn.name, arguments: asRecord(fn.arguments) ?? {} });
+                    }
+                }
+                if (line.done === true) {
+                    inputTokens += asCount(line.prompt_eval_count);
+                    outputTokens += asCount(line.eval_count);
+                    doneReason = typeof line.done_reason === 'string' ? line.done_reason : undefined;
+                }
+            }
+            if (calls.length === 0) {
+                if (doneReason === 'length') {
+                    warnings.push('The reply was cut off at the token limit.');
+                }
+                return {
+                    success: true,
+                    content,
+                    provider: request.provider,
+                    model,
+                    latencyMs: Date.now() - startedAt,
+                    usage: { inputTokens, outputTokens, totalTokens: inputTokens + outputTokens },
+                    ...(toolCalls.length > 0 ? { toolCalls } : {}),
+                    ...(warnings.length > 0 ? { warnings } : {}),
+                    mode: 'http',
+                };
+            }
+            if (round >= MAX_TOOL_ROUNDS) {
+                return httpFailure(request, model, startedAt, 'PROVIDER_TOOL_LOOP', `The model was still calling tools after ${MAX_TOOL_ROUNDS} rounds.`);
+            }
+            messages.push({ role: 'assistant', content, tool_calls: calls.map((entry) => ({ function: entry })) });
+            for (const entry of calls) {
+                const result = await runProviderTool(tools, entry.name, entry.arguments);
+                toolCalls.push({ name: entry.name, input: entry.arguments, ...(result.isError ? { isError: true } : {}) });
+                messages.push({ role: 'tool', tool_name: entry.name, content: result.isError ? `Error: ${result.content}` : result.content });
+            }
+        }
+    }
+    catch (error) {
+        return describeHttpException(request, model, startedAt, call, timeoutMs, error);
+    }
+    finally {
+        call.dispose();
+    }
+}
+async function fetchOllamaTags(config, signal) {
+    const response = await fetch(`${trimBaseUrl(config.baseUrl)}/api/tags`, { headers: authHeaders(config.apiKey), signal });
+    if (!response.ok) {
+        throw Object.assign(new Error((await describeHttpError(response, 'Ollama')).error), { code: 'PROVIDER_HTTP_ERROR' });
+    }
+    const models = asRecord(await response.json())?.models;
+    return (Array.isArray(models) ? models : []).map(asRecord);
+}
+// The error Ollama gave, or undefined once the model is in place.
+async function pullOllamaModel(config, model, signal) {
+    const response = await fetch(`${trimBaseUrl(config.baseUrl)}/api/pull`, {
+        method: 'POST',
+        headers: { 'content-type': 'application/json', ...authHeaders(config.apiKey) },
+        body: JSON.stringify({ model, stream: true }),
+        signal,
+    });
+    if (!response.ok || response.body === null) {
+        return (await describeHttpError(response, 'Ollama')).error;
+    }
+    let status;
+    for await (const line of readJsonLines(response.body)) {
+        if (typeof line.error === 'string') {
+            return line.error;
+        }
+        status = typeof line.status === 'string' ? line.status : status;
+    }
+    return status === 'success' ? undefined : `the pull ended with "${status ?? 'no status'}".`;
+}
+// `model_info` keys the window by architecture: `llama.context_length`, `qwen2.context_length`.
+async function detectContextLength(config, model, signal) {
+    const key = `${trimBaseUrl(config.baseUrl)}\0${model}`;
+    if (contextLengths.has(key)) {
+        return contextLengths.get(key);
+    }
+    const response = await fetch(`${trimBaseUrl(config.baseUrl)}/api/show`, {
+        method: 'POST',
+        headers: { 'content-type': 'application/json', ...authHeaders(config.apiKey) },
+        body: JSON.stringify({ model }),
+        signal,
+    });
+    if (!response.ok) {
+        return undefined;
+    }
+    const info = asRecord(asRecord(await response.json())?.model_info) ?? {};
+    const entry = Object.entries(info).find(([name, value]) => name.endsWith('.context_length') && typeof value === 'number');
+    const contextLength = entry === undefined ? undefined : entry[1];
+    contextLengths.set(key, contextLength);
+    return contextLength;
+}
+// Ollama names a model without a tag `name:latest`.
+function sameModel(installed, requested) {
+    return installed === requested || installed === `${requested}:latest`;
+}
+function trimBaseUrl(baseUrl) {
+    const trimmed = baseUrl.replace(/\/+$/, '');
+    // OLLAMA_HOST is often just `host:port`.
+    return /^https?:\/\//.test(trimmed) ? trimmed : `http://${trimmed}`;
+}
+// Ollama itself takes no key; one is only sent for a server behind an authenticating proxy.
+function authHeaders(apiKey) {
+    return apiKey.length > 0 ? { authorization: `Bearer ${apiKey}` } : {};
+}
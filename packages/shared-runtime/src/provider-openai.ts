@@ -0,0 +1,216 @@
+import type { ProviderExecutionRequest, ProviderExecutionResponse, ProviderToolCall } from './provider-bridge.js';
+import type { AdaptedProviderPrompt } from './provider-prompt.js';
+import {
+  asCount,
+  createProviderCallSignal,
+  describeHttpError,
+  describeHttpException,
+  httpFailure,
+  MAX_TOOL_ROUNDS,
+  postJson,
+  readServerSentEvents,
+  resolveApiModel,
+  runProviderTool,
+  type ProviderHttpConfig,
+} from './provider-http.js';
+import { asRecord } from './records.js';
+
+export const OPENAI_DEFAULT_BASE_URL = 'https://api.openai.com/v1';
+export const OPENAI_DEFAULT_MODEL = 'gpt-4o';
+
+interface OpenAiToolCall {
+  id: string;
+  type: 'function';
+  function: { name: string; arguments: string };
+}
+
+type OpenAiMessage =
+  | { role: 'system' | 'user'; content: string }
+  | { role: 'assistant'; content: string | null; tool_calls?: OpenAiToolCall[] }
+  | { role: 'tool'; tool_call_id: string; content: string };
+
+interface OpenAiTurn {
+  content: string;
+  toolCalls: OpenAiToolCall[];
+  finishReason?: string;
+  usage?: { inputTokens: number; outputTokens: number };
+  error?: string;
+}
+
+/**
+ * Calls a chat-completions endpoint (OpenAI, Azure OpenAI, OpenRouter, Groq, DeepSeek and
+ * other compatible servers) with a streamed reply. `request.tools` are offered as functions;
+ * each call runs and its result goes back as a `tool` message until the model answers
+ * without calling any. Usage is summed over every round when the server reports it.
+ */
+export async function executeOpenAiChat(
+  config: ProviderHttpConfig,
+  request: ProviderExecutionRequest,
+  adapted: AdaptedProviderPrompt,
+): Promise<ProviderExecutionResponse> {
+  const startedAt = Date.now();
+  const model = resolveApiModel(request.model, config.model);
+  const maxTokens = request.maxTokens ?? config.maxTokens;
+  const timeoutMs = request.timeoutMs ?? config.timeoutMs;
+  const tools = request.tools ?? [];
+  const messages: OpenAiMessage[] = [
+    ...(adapted.systemPrompt !== undefined ? [{ role: 'system' as const, content: adapted.systemPrompt }] : []),
+    { role: 'user', content: adapted.prompt },
+  ];
+  const url = `${config.baseUrl.replace(/\/+$/, '')}/chat/completions${config.apiVersion === undefined ? '' : `?api-version=${encodeURIComponent(config.apiVersion)}`}`;
+  const headers: Record<string, string> = config.apiVersion === undefined
+    ? { authorization: `Bearer ${config.apiKey}` }
+    : { 'api-key': config.apiKey };
+  const toolCalls: ProviderToolCall[] = [];
+  let usage: { inputTokens: number; outputTokens: number } | undefined;
+  const call = createProviderCallSignal(request.signal, timeoutMs);
+
+  try {
+    for (let round = 1; ; round += 1) {
+      const response = await postJson(url, headers, {
+        model,
+        messages,
+        max_tokens: maxTokens,
+        temperature: request.temperature,
+        stop: adapted.stopSequences,
+        response_format: request.responseFormat === 'json' ? { type: 'json_object' } : undefined,
+        tools: tools.length === 0 ? undefined : tools.map((tool) => ({
+          type: 'function',
+          function: { name: tool.name, description: tool.description, parameters: tool.inputSchema },
+        })),
+        stream: true,
+        stream_options: { include_usage: true },
+      }, call.signal);
+      if (!response.ok) {
+        const { errorCode, error, retryAfterMs } = await describeHttpError(response, 'Chat completions API');
+        return httpFailure(request, model, startedAt, errorCode, error, retryAfterMs);
+      }
+      if (response.body === null) {
+        return httpFailure(request, model, startedAt, 'PROVIDER_EMPTY_RESPONSE', 'Chat completions API returned no body.');
+      }
+
+      const turn = await readChatCompletionStream(response.body, request.onText);
+      if (turn.usage !== undefined) {
+        usage = {
+          inputTokens: (usage?.inputTokens ?? 0) + turn.usage.inputTokens,
+          outputTokens: (usage?.outputTokens ?? 0) + turn.usage.outputTokens,
+        };
+      }
+      if (turn.error !== undefined) {
+        return httpFailure(request, model, startedAt, 'PROVIDER_STREAM_ERROR', `Chat completions API stream failed: ${turn.error}`);
+      }
+
+      // Some servers finish with `stop` even when the reply calls tools.
+      if (turn.toolCalls.length === 0) {
+        const warnings = [
+          ...adapted.warnings,
+          ...(turn.finishReason === 'length' ? [`The reply was cut off at the token limit${maxTokens === undefined ? '' : ` (${maxTokens})`}.`] : []),
+        ];
+        return {
+          success: true,
+          content: turn.content,
+          provider: request.provider,
+          model,
+          latencyMs: Date.now() - startedAt,
+          ...(usage !== undefined ? { usage: { ...usage, totalTokens: usage.inputTokens + usage.outputTokens } } : {}),
+          ...(toolCalls.length > 0 ? { toolCalls } : {}),
+          ...(warnings.length > 0 ? { warnings } : {}),
+          mode: 'http',
+        };
+      }
+      if (round >= MAX_TOOL_ROUNDS) {
+        return httpFailure(request, model, startedAt, 'PROVIDER_TOOL_LOOP', `The model was still calling tools after ${MAX_TOOL_ROUNDS} rounds.`);
+      }
+
+      messages.push({ role: 'assistant', content: turn.content.length > 0 ? turn.content : null, tool_calls: turn.toolCalls });
+      for (const toolCall of turn.toolCalls) {
+        const input = parseArguments(toolCall.function.arguments);
+        const result = input === undefined
+          ? { content: `The arguments of "${toolCall.function.name}" are not a JSON object.`, isError: true }
+          : await runProviderTool(tools, toolCall.function.name, input);
+        toolCalls.push({ name: toolCall.function.name, input: input ?? {}, ...(result.isError ? { isError: true } : {}) });
+        // The tool message has no error flag; the text says it.
+        messages.push({ role: 'tool', tool_call_id: toolCall.id, content: result.isError ? `Error: ${result.content}` : result.content });
+      }
+    }
+  } catch (error) {
+    return describeHttpException(request, model, startedAt, call, timeoutMs, error);
+  } finally {
+    call.dispose();
+  }
+}
+
+// Tool calls arrive in pieces keyed by `index`: the first carries the id and name, the rest
+// more of the arguments.
+async function readChatCompletionStream(
+  body: ReadableStream<Uint8Array>,
+  onText: ((text: string) => void) | undefined,
+): Promise<OpenAiTurn> {
+  const turn: OpenAiTurn = { content: '', toolCalls: [] };
+  const toolCalls = new Map<number, OpenAiToolCall>();
+
+  for await (const event of readServerSentEvents(body)) {
+    if (event.data.trim() === '[DONE]') {
+      break;
+    }
+    let data: Record<string, unknown>;
+    try {
+      data = asRecord(JSON.parse(event.data)) ?? {};
+    } catch {
+      continue;
+    }
+    if (data.error !== undefined) {
+      const message = asRecord(data.error)?.message;
+      turn.error = typeof message === 'string' ? message : typeof data.error === 'string' ? data.error : 'unknown error';
+      break;
+    }
+    const usage = asRecord(data.usage) ?? {};
+    if (typeof usage.prompt_tokens === 'number' || typeof usage.completion_tokens === 'number') {
+      turn.usage = { inputTokens: asCount(usage.prompt_tokens), outputTokens: asCount(usage.completion_tokens) };
+    }
+
+    const choice = asRecord(Array.isArray(data.choices) ? data.choices[0] : undefined) ?? {};
+    const delta = asRecord(choice.delta) ?? {};
+    if (typeof delta.content === 'string' && delta.content.length > 0) {
+      turn.content += delta.content;
+      onText?.(delta.content);
+    }
+    for (const piece of Array.isArray(delta.tool_calls) ? delta.tool_calls : []) {
+      const fragment = asRecord(piece) ?? {};
+      const index = asCount(fragment.index);
+      const fn = asRecord(fragment.function) ?? {};
+      const current = toolCalls.get(index) ?? { id: '', type: 'function' as const, function: { name: '', arguments: '' } };
+      if (typeof fragment.id === 'string') {
+        current.id = fragment.id;
+      }
+      if (typeof fn.name === 'string') {
+        current.function.name += fn.name;
+      }
+      if (typeof fn.arguments === 'string') {
+        current.function.arguments += fn.arguments;
+      }
+      toolCalls.set(index, current);
+    }
+    if (typeof choice.finish_reason === 'string') {
+      turn.finishReason = choice.finish_reason;
+    }
+  }
+
+  turn.toolCalls = [...toolCalls.entries()]
+    .sort(([left], [right]) => left - right)
+    .map(([index, toolCall]) => ({ ...toolCall, id: toolCall.id || `call_${index}` }))
+    .filter((toolCall) => toolCall.function.name.length > 0);
+  return turn;
+}
+
+function parseArguments(value: string): Record<string, unknown> | undefined {
+  if (value.trim().length === 0) {
+    return {};
+  }
+  try {
+    const parsed: unknown = JSON.parse(value);
+    return typeof parsed === 'object' && parsed !== null && !Array.isArray(parsed) ? parsed as Record<string, unknown> : undefined;
+  } catch {
+    return undefined;
+  }
+}
@@ -0,0 +1,73 @@
+export const PROVIDER_PROMPT_DIALECTS = ['plain', 'claude', 'gemini', 'openai'];
+const PROVIDER_PROMPT_ADAPTERS = {
+    plain: {
+        dialect: 'plain',
+        inlineSystemPrompt: (systemPrompt, prompt) => `System: ${systemPrompt}\n\n${prompt}`,
+    },
+    claude: {
+        dialect: 'claude',
+        inlineSystemPrompt: (systemPrompt, prompt) => `<instructions>\n${systemPrompt}\n</instructions>\n\n${prompt}`,
+    },
+    gemini: {
+        dialect: 'gemini',
+        inlineSystemPrompt: (systemPrompt, prompt) => `System instructions:\n${systemPrompt}\n\n---\n\n${prompt}`,
+        maxStopSequences: 5,
+    },
+    openai: {
+        dialect: 'openai',
+        inlineSystemPrompt: (systemPrompt, prompt) => `# Instructions\n\n${systemPrompt}\n\n# Task\n\n${prompt}`,
+        maxStopSequences: 4,
+    },
+};
+const PROVIDER_DEFAULT_DIALECTS = {
+    claude: 'claude',
+    anthropic: 'claude',
+    gemini: 'gemini',
+    codex: 'openai',
+    openai: 'openai',
+    // xAI's API follows the OpenAI chat conventions.
+    grok: 'openai',
+};
+export function getProviderPromptAdapter(dialect) {
+    return PROVIDER_PROMPT_ADAPTERS[dialect];
+}
+export function normalizePromptDialect(value) {
+    return typeof value === 'string' && PROVIDER_PROMPT_DIALECTS.includes(value)
+        ? value
+        : undefined;
+}
+/**
+ * Providers without a known dialect get `plain`, which keeps the historical `System:` prefix.
+ */
+export function resolvePromptDialect(providerId, configured) {
+    return normalizePromptDialect(configured) ?? PROVIDER_DEFAULT_DIALECTS[providerId.trim().toLowerCase()] ?? 'plain';
+}
+/**
+ * Rewrites a provider-agnostic request into the dialect of the backend that will run it.
+ * `separateSystemPrompt` is true when the executor protocol carries the system prompt on its own.
+ */
+export function adaptProviderPrompt(request, dialect, options) {
+    const adapter = getProviderPromptAdapter(dialect);
+    const warnings = [];
+    const systemPrompt = typeof request.systemPrompt === 'string' && request.systemPrompt.length > 0
+        ? request.systemPrompt
+        : undefined;
+    let stopSequences = request.stopSequences?.filter((sequence) => sequence.length > 0);
+    if (stopSequences !== undefined && stopSequences.length > 0) {
+        if (!options.supportsStopSequences) {
+            warnings.push('Stop sequences were ignored: the provider executor protocol cannot pass them.');
+            stopSequences = undefined;
+        }
+        else if (adapter.maxStopSequences !== undefined && stopSequences.length > adapter.maxStopSequences) {
+            warnings.push(`Only the first ${adapter.maxStopSequences} stop sequences were sent: the ${dialect} dialect accepts no more.`);
+            stopSequences = stopSequences.slice(0, adapter.maxStopSequences);
+        }
+    }
+    else {
+        stopSequences = undefined;
+    }
+    if (systemPrompt === undefined || options.separateSystemPrompt) {
+        return { prompt: request.prompt, systemPrompt, stopSequences, warnings };
+    }
+    return { prompt: adapter.inlineSystemPrompt(systemPrompt, request.prompt), stopSequences, warnings };
+}
@@ -0,0 +1,197 @@
+import { mkdir, readFile, writeFile } from 'node:fs/promises';
+import { dirname, join } from 'node:path';
+import { readWorkspaceConfig } from './config-layers.js';
+import { asRecord } from './records.js';
+const DEFAULT_WARNING_THRESHOLD = 0.8;
+const QUOTA_WINDOWS = ['daily', 'monthly'];
+export function getProviderUsagePath(basePath) {
+    return join(basePath, '.automatosx', 'runtime', 'provider-usage.json');
+}
+export function createProviderQuotaTracker(config) {
+    const now = config.now ?? (() => new Date());
+    const usagePath = getProviderUsagePath(config.basePath);
+    let pending = Promise.resolve();
+    const serialize = (task) => {
+        const next = pending.then(task, task);
+        pending = next.catch(() => undefined);
+        return next;
+    };
+    const describe = async (provider) => {
+        const [workspaceConfig, ledger] = await Promise.all([
+            readWorkspaceConfig(config.basePath),
+            readUsageLedger(usagePath),
+        ]);
+        return buildStatus(provider, ledger, workspaceConfig, now());
+    };
+    return {
+        async getStatus(providers) {
+            const [workspaceConfig, ledger] = await Promise.all([
+                readWorkspaceConfig(config.basePath),
+                readUsageLedger(usagePath),
+            ]);
+            const providerIds = providers ?? Array.from(new Set([
+                ...Object.keys(readQuotaConfigs(workspaceConfig)),
+                ...Object.keys(ledger.providers),
+            ])).sort();
+            const current = now();
+            return providerIds.map((provider) => buildStatus(provider, ledger, workspaceConfig, current));
+        },
+        recordUsage(provider, usage) {
+            return serialize(async () => {
+                const workspaceConfig = await readWorkspaceConfig(config.basePath);
+                const ledger = await readUsageLedger(usagePath);
+                const current = now();
+                const before = buildStatus(provider, ledger, workspaceConfig, current);
+                const entry = ledger.providers[provider] ?? {};
+                for (const window of QUOTA_WINDOWS) {
+                    const period = getPeriod(window, current);
+                    const counter = entry[window]?.period === period ? entry[window] : { period, requests: 0, tokens: 0 };
+                    entry[window] = {
+                        period,
+                        requests: counter.requests + 1,
+                        tokens: counter.tokens + Math.max(0, usage.totalTokens ?? 0),
+                    };
+                }
+                ledger.providers[provider] = entry;
+                await mkdir(dirname(usagePath), { recursive: true });
+                await writeFile(usagePath, `${JSON.stringify(ledger, null, 2)}\n`, 'utf8');
+                const after = buildStatus(provider, ledger, workspaceConfig, current);
+                if (after.exhausted && !before.exhausted) {
+                    return [`Provider "${provider}" has exhausted its ${describeExhaustedWindows(after)} quota.`];
+                }
+                if (after.nearLimit && !before.nearLimit && !after.exhausted) {
+                    return [`Provider "${provider}" is close to its quota limit (${describeUsage(after)}).`];
+                }
+                return [];
+            });
+        },
+        /**
+         * `preferred` unless its quota is exhausted, else the first fallback with quota left.
+         * `candidates`, when given, replaces the fallback list and is tried in its own order.
+         */
+        async selectProvider(preferred, candidates) {
+            const workspaceConfig = await readWorkspaceConfig(config.basePath);
+            const preferredStatus = await describe(preferred);
+            if (!preferredStatus.exhausted) {
+                return {
+                    provider: preferred,
+                    warnings: preferredStatus.nearLimit
+                        ? [`Provider "${preferred}" is close to its quota limit (${describeUsage(preferredStatus)}).`]
+                        : [],
+                };
+            }
+            for (const candidate of candidates ?? readFallbackProviders(workspaceConfig)) {
+                if (candidate === preferred) {
+                    continue;
+                }
+                const candidateStatus = await describe(candidate);
+                if (!candidateStatus.exhausted) {
+                    return {
+                        provider: candidate,
+                        warnings: [`Provider "${preferred}" has exhausted its ${describeExhaustedWindows(preferredStatus)} quota; routed to "${candidate}".`],
+                    };
+                }
+            }
+            return {
+                provider: preferred,
+                warnings: [`Provider "${preferred}" has exhausted its ${describeExhaustedWindows(preferredStatus)} quota and no fallback provider has quota remaining.`],
+            };
+        },
+    };
+}
+function buildStatus(provider, ledger, workspaceConfig, current) {
+    const quota = readQuotaConfigs(workspaceConfig)[provider] ?? {};
+    const threshold = readWarningThreshold(workspaceConfig);
+    const entry = ledger.providers[provider];
+    const windows = Object.fromEntries(QUOTA_WINDOWS.map((window) => {
+        const period = getPeriod(window, current);
+        const counter = entry?.[window]?.period === period ? entry[window] : { period, requests: 0, tokens: 0 };
+        const limits = quota[window] ?? {};
+        return [window, {
+            period,
+            requests: counter.requests,
+            tokens: counter.tokens,
+            requestLimit: limits.requests,
+            tokenLimit: limits.tokens,
+            remainingRequests: limits.requests === undefined ? undefined : Math.max(0, limits.requests - counter.requests),
+            remainingTokens: limits.tokens === undefined ? undefined : Math.max(0, limits.tokens - counter.tokens),
+        }];
+    }));
+    const ratios = QUOTA_WINDOWS.flatMap((window) => {
+        const status = windows[window];
+        return [
+            status.requestLimit === undefined ? undefined : status.requests / status.requestLimit,
+            status.tokenLimit === undefined ? undefined : status.tokens / status.tokenLimit,
+        ].filter((ratio) => ratio !== undefined);
+    });
+    return {
+        provider,
+        daily: windows.daily,
+        monthly: windows.monthly,
+        nearLimit: ratios.some((ratio) => ratio >= threshold),
+        exhausted: ratios.some((ratio) => ratio >= 1),
+    };
+}
+function describeExhaustedWindows(status) {
+    return QUOTA_WINDOWS
+        .filter((window) => status[window].remainingRequests === 0 || status[window].remainingTokens === 0)
+        .join(' and ');
+}
+function describeUsage(status) {
+    return QUOTA_WINDOWS.flatMap((window) => {
+        const entry = status[window];
+        return [
+            entry.requestLimit === undefined ? undefined : `${window} requests ${entry.requests}/${entry.requestLimit}`,
+            entry.tokenLimit === undefined ? undefined : `${window} tokens ${entry.tokens}/${entry.tokenLimit}`,
+        ].filter((part) => part !== undefined);
+    }).join(', ');
+}
+function getPeriod(window, current) {
+    const iso = current.toISOString();
+    return window === 'daily' ? iso.slice(0, 10) : iso.slice(0, 7);
+}
+function readQuotaConfigs(workspaceConfig) {
+    const quotas = asRecord(asRecord(workspaceConfig.providers)?.quotas) ?? {};
+    return Object.fromEntries(Object.entries(quotas).map(([provider, value]) => {
+        const quota = asRecord(value);
+        return [provider, {
+            daily: readLimits(quota?.daily),
+            monthly: readLimits(quota?.monthly),
+        }];
+    }));
+}
+function readLimits(value) {
+    const limits = asRecord(value);
+    if (limits === undefined) {
+        return undefined;
+    }
+    return {
+        requests: asPositiveNumber(limits.requests),
+        tokens: asPositiveNumber(limits.tokens),
+    };
+}
+function readWarningThreshold(workspaceConfig) {
+    const value = asRecord(workspaceConfig.providers)?.quotaWarningThreshold;
+    return typeof value === 'number' && value > 0 && value <= 1 ? value : DEFAULT_WARNING_THRESHOLD;
+}
+export function readFallbackProviders(workspaceConfig) {
+    const fallback = asRecord(workspaceConfig.providers)?.fallback;
+    return Array.isArray(fallback)
+        ? fallback.filter((entry) => typeof entry === 'string' && entry.length > 0)
+        : [];
+}
+async function readUsageLedger(usagePath) {
+    try {
+        const parsed = JSON.parse(await readFile(usagePath, 'utf8'));
+        if (parsed.version === 1 && asRecord(parsed.providers) !== undefined) {
+            return parsed;
+        }
+    }
+    catch {
+        // Missing or unreadable ledgers start from zero usage.
+    }
+    return { version: 1, providers: {} };
+}
+function asPositiveNumber(value) {
+    return typeof value === 'number' && Number.isFinite(value) && value > 0 ? value : undefined;
+}
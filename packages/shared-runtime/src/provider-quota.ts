@@ -0,0 +1,276 @@
+import { mkdir, readFile, writeFile } from 'node:fs/promises';
+import { dirname, join } from 'node:path';
+import { readWorkspaceConfig } from './config-layers.js';
+import type { ProviderRoutingPolicy } from './provider-routing.js';
+import { asRecord } from './records.js';
+
+export type ProviderQuotaWindow = 'daily' | 'monthly';
+
+export interface ProviderQuotaLimits {
+  requests?: number;
+  tokens?: number;
+}
+
+export interface ProviderQuotaConfig {
+  daily?: ProviderQuotaLimits;
+  monthly?: ProviderQuotaLimits;
+}
+
+export interface ProviderQuotaWindowStatus {
+  period: string;
+  requests: number;
+  tokens: number;
+  requestLimit?: number;
+  tokenLimit?: number;
+  remainingRequests?: number;
+  remainingTokens?: number;
+}
+
+export interface ProviderQuotaStatus {
+  provider: string;
+  daily: ProviderQuotaWindowStatus;
+  monthly: ProviderQuotaWindowStatus;
+  nearLimit: boolean;
+  exhausted: boolean;
+}
+
+export interface ProviderQuotaSelection {
+  provider: string;
+  warnings: string[];
+  /** Set when a cost, speed or quality policy ranked the candidates. */
+  routing?: { policy: ProviderRoutingPolicy; model?: string };
+}
+
+interface ProviderUsageCounter {
+  period: string;
+  requests: number;
+  tokens: number;
+}
+
+interface ProviderUsageLedger {
+  version: 1;
+  providers: Record<string, { daily?: ProviderUsageCounter; monthly?: ProviderUsageCounter }>;
+}
+
+const DEFAULT_WARNING_THRESHOLD = 0.8;
+const QUOTA_WINDOWS: ProviderQuotaWindow[] = ['daily', 'monthly'];
+
+export function getProviderUsagePath(basePath: string): string {
+  return join(basePath, '.automatosx', 'runtime', 'provider-usage.json');
+}
+
+export function createProviderQuotaTracker(config: {
+  basePath: string;
+  now?: () => Date;
+}) {
+  const now = config.now ?? (() => new Date());
+  const usagePath = getProviderUsagePath(config.basePath);
+  let pending: Promise<unknown> = Promise.resolve();
+
+  const serialize = <T>(task: () => Promise<T>): Promise<T> => {
+    const next = pending.then(task, task);
+    pending = next.catch(() => undefined);
+    return next;
+  };
+
+  const describe = async (provider: string): Promise<ProviderQuotaStatus> => {
+    const [workspaceConfig, ledger] = await Promise.all([
+      readWorkspaceConfig(config.basePath),
+      readUsageLedger(usagePath),
+    ]);
+    return buildStatus(provider, ledger, workspaceConfig, now());
+  };
+
+  return {
+    async getStatus(providers?: string[]): Promise<ProviderQuotaStatus[]> {
+      const [workspaceConfig, ledger] = await Promise.all([
+        readWorkspaceConfig(config.basePath),
+        readUsageLedger(usagePath),
+      ]);
+      const providerIds = providers ?? Array.from(new Set([
+        ...Object.keys(readQuotaConfigs(workspaceConfig)),
+        ...Object.keys(ledger.providers),
+      ])).sort();
+      const current = now();
+      return providerIds.map((provider) => buildStatus(provider, ledger, workspaceConfig, current));
+    },
+
+    recordUsage(provider: string, usage: { totalTokens?: number }): Promise<string[]> {
+      return serialize(async () => {
+        const workspaceConfig = await readWorkspaceConfig(config.basePath);
+        const ledger = await readUsageLedger(usagePath);
+        const current = now();
+        const before = buildStatus(provider, ledger, workspaceConfig, current);
+        const entry = ledger.providers[provider] ?? {};
+        for (const window of QUOTA_WINDOWS) {
+          const period = getPeriod(window, current);
+          const counter = entry[window]?.period === period ? entry[window]! : { period, requests: 0, tokens: 0 };
+          entry[window] = {
+            period,
+            requests: counter.requests + 1,
+            tokens: counter.tokens + Math.max(0, usage.totalTokens ?? 0),
+          };
+        }
+        ledger.providers[provider] = entry;
+        await mkdir(dirname(usagePath), { recursive: true });
+        await writeFile(usagePath, `${JSON.stringify(ledger, null, 2)}\n`, 'utf8');
+
+        const after = buildStatus(provider, ledger, workspaceConfig, current);
+        if (after.exhausted && !before.exhausted) {
+          return [`Provider "${provider}" has exhausted its ${describeExhaustedWindows(after)} quota.`];
+        }
+        if (after.nearLimit && !before.nearLimit && !after.exhausted) {
+          return [`Provider "${provider}" is close to its quota limit (${describeUsage(after)}).`];
+        }
+        return [];
+      });
+    },
+
+    /**
+     * `preferred` unless its quota is exhausted, else the first fallback with quota left.
+     * `candidates`, when given, replaces the fallback list and is tried in its own order.
+     */
+    async selectProvider(preferred: string, candidates?: string[]): Promise<ProviderQuotaSelection> {
+      const workspaceConfig = await readWorkspaceConfig(config.basePath);
+      const preferredStatus = await describe(preferred);
+      if (!preferredStatus.exhausted) {
+        return {
+          provider: preferred,
+          warnings: preferredStatus.nearLimit
+            ? [`Provider "${preferred}" is close to its quota limit (${describeUsage(preferredStatus)}).`]
+            : [],
+        };
+      }
+
+      for (const candidate of candidates ?? readFallbackProviders(workspaceConfig)) {
+        if (candidate === preferred) {
+          continue;
+        }
+        const candidateStatus = await describe(candidate);
+        if (!candidateStatus.exhausted) {
+          return {
+            provider: candidate,
+            warnings: [`Provider "${preferred}" has exhausted its ${describeExhaustedWindows(preferredStatus)} quota; routed to "${candidate}".`],
+          };
+        }
+      }
+
+      return {
+        provider: preferred,
+        warnings: [`Provider "${preferred}" has exhausted its ${describeExhaustedWindows(preferredStatus)} quota and no fallback provider has quota remaining.`],
+      };
+    },
+  };
+}
+
+function buildStatus(
+  provider: string,
+  ledger: ProviderUsageLedger,
+  workspaceConfig: Record<string, unknown>,
+  current: Date,
+): ProviderQuotaStatus {
+  const quota = readQuotaConfigs(workspaceConfig)[provider] ?? {};
+  const threshold = readWarningThreshold(workspaceConfig);
+  const entry = ledger.providers[provider];
+  const windows = Object.fromEntries(QUOTA_WINDOWS.map((window) => {
+    const period = getPeriod(window, current);
+    const counter = entry?.[window]?.period === period ? entry[window]! : { period, requests: 0, tokens: 0 };
+    const limits = quota[window] ?? {};
+    return [window, {
+      period,
+      requests: counter.requests,
+      tokens: counter.tokens,
+      requestLimit: limits.requests,
+      tokenLimit: limits.tokens,
+      remainingRequests: limits.requests === undefined ? undefined : Math.max(0, limits.requests - counter.requests),
+      remainingTokens: limits.tokens === undefined ? undefined : Math.max(0, limits.tokens - counter.tokens),
+    } satisfies ProviderQuotaWindowStatus];
+  })) as Record<ProviderQuotaWindow, ProviderQuotaWindowStatus>;
+
+  const ratios = QUOTA_WINDOWS.flatMap((window) => {
+    const status = windows[window];
+    return [
+      status.requestLimit === undefined ? undefined : status.requests / status.requestLimit,
+      status.tokenLimit === undefined ? undefined : status.tokens / status.tokenLimit,
+    ].filter((ratio): ratio is number => ratio !== undefined);
+  });
+
+  return {
+    provider,
+    daily: windows.daily,
+    monthly: windows.monthly,
+    nearLimit: ratios.some((ratio) => ratio >= threshold),
+    exhausted: ratios.some((ratio) => ratio >= 1),
+  };
+}
+
+function describeExhaustedWindows(status: ProviderQuotaStatus): string {
+  return QUOTA_WINDOWS
+    .filter((window) => status[window].remainingRequests === 0 || status[window].remainingTokens === 0)
+    .join(' and ');
+}
+
+function describeUsage(status: ProviderQuotaStatus): string {
+  return QUOTA_WINDOWS.flatMap((window) => {
+    const entry = status[window];
+    return [
+      entry.requestLimit === undefined ? undefined : `${window} requests ${entry.requests}/${entry.requestLimit}`,
+      entry.tokenLimit === undefined ? undefined : `${window} tokens ${entry.tokens}/${entry.tokenLimit}`,
+    ].filter((part): part is string => part !== undefined);
+  }).join(', ');
+}
+
+function getPeriod(window: ProviderQuotaWindow, current: Date): string {
+  const iso = current.toISOString();
+  return window === 'daily' ? iso.slice(0, 10) : iso.slice(0, 7);
+}
+
+function readQuotaConfigs(workspaceConfig: Record<string, unknown>): Record<string, ProviderQuotaConfig> {
+  const quotas = asRecord(asRecord(workspaceConfig.providers)?.quotas) ?? {};
+  return Object.fromEntries(Object.entries(quotas).map(([provider, value]) => {
+    const quota = asRecord(value);
+    return [provider, {
+      daily: readLimits(quota?.daily),
+      monthly: readLimits(quota?.monthly),
+    }];
+  }));
+}
+
+function readLimits(value: unknown): ProviderQuotaLimits | undefined {
+  const limits = asRecord(value);
+  if (limits === undefined) {
+    return undefined;
+  }
+  return {
+    requests: asPositiveNumber(limits.requests),
+    tokens: asPositiveNumber(limits.tokens),
+  };
+}
+
+function readWarningThreshold(workspaceConfig: Record<string, unknown>): number {
+  const value = asRecord(workspaceConfig.providers)?.quotaWarningThreshold;
+  return typeof value === 'number' && value > 0 && value <= 1 ? value : DEFAULT_WARNING_THRESHOLD;
+}
+
+export function readFallbackProviders(workspaceConfig: Record<string, unknown>): string[] {
+  const fallback = asRecord(workspaceConfig.providers)?.fallback;
+  return Array.isArray(fallback)
+    ? fallback.filter((entry): entry is string => typeof entry === 'string' && entry.length > 0)
+    : [];
+}
+
+async function readUsageLedger(usagePath: string): Promise<ProviderUsageLedger> {
+  try {
+    const parsed = JSON.parse(await readFile(usagePath, 'utf8')) as Partial<ProviderUsageLedger>;
+    if (parsed.version === 1 && asRecord(parsed.providers) !== undefined) {
+      return parsed as ProviderUsageLedger;
+    }
+  } catch {
+    // Missing or unreadable ledgers start from zero usage.
+  }
+  return { version: 1, providers: {} };
+}
+
+function asPositiveNumber(value: unknown): number | undefined {
+  return typeof value === 'number' && Number.isFinite(value) && value > 0 ? value : undefined;
+}
@@ -0,0 +1,274 @@
+import { mkdir, readFile, writeFile } from 'node:fs/promises';
+import { dirname, join } from 'node:path';
+import { asRecord } from './records.js';
+export const DEFAULT_RATE_LIMIT_RETRIES = 3;
+export const DEFAULT_RATE_LIMIT_BASE_DELAY_MS = 1_000;
+export const DEFAULT_RATE_LIMIT_MAX_DELAY_MS = 60_000;
+export function getProviderRateLimitPath(basePath) {
+    return join(basePath, '.automatosx', 'runtime', 'provider-rate-limits.json');
+}
+export function readRateLimitSettings(workspaceConfig) {
+    const section = asRecord(asRecord(workspaceConfig.providers)?.rateLimit) ?? {};
+    const maxConcurrent = asPositiveInteger(section.maxConcurrent);
+    return {
+        retries: typeof section.retries === 'number' && Number.isInteger(section.retries) && section.retries >= 0
+            ? section.retries
+            : DEFAULT_RATE_LIMIT_RETRIES,
+        baseDelayMs: asPositiveInteger(section.baseDelayMs) ?? DEFAULT_RATE_LIMIT_BASE_DELAY_MS,
+        maxDelayMs: asPositiveInteger(section.maxDelayMs) ?? DEFAULT_RATE_LIMIT_MAX_DELAY_MS,
+        ...(maxConcurrent !== undefined ? { maxConcurrent } : {}),
+    };
+}
+/**
+ * Per-provider queues and backoff for one workspace. A rate limit puts the provider into a cooldown
+ * that every call to it waits out; the cooldown and each process's queue depth are written to
+ * `.automatosx/runtime/provider-rate-limits.json`, so other processes wait too and `ax status`
+ * can report them. Calls that meet no contention never touch the file.
+ */
+export function createProviderRateLimiter(config) {
+    const now = () => (config.now?.() ?? new Date()).getTime();
+    const random = config.random ?? Math.random;
+    const ledgerPath = getProviderRateLimitPath(config.basePath);
+    const lanes = new Map();
+    const dirty = new Set();
+    let pending = Promise.resolve();
+    let flushScheduled = false;
+    const laneFor = (provider) => {
+        let lane = lanes.get(provider);
+        if (lane === undefined) {
+            lane = { queued: 0, active: 0, waiting: [], cooldownUntil: 0, consecutive: 0, recorded: false };
+            lanes.set(provider, lane);
+        }
+        return lane;
+    };
+    // Writes the lanes changed since the last write, merged into what other processes wrote.
+    const flush = async () => {
+        const ledger = await readLedger(ledgerPath);
+        const updatedAt = new Date(now()).toISOString();
+        for (const provider of dirty) {
+            const lane = laneFor(provider);
+            const entry = ledger.providers[provider] ?? {};
+            const processes = { ...entry.processes };
+            if (lane.queued > 0 || lane.active > 0) {
+                processes[String(process.pid)] = { queued: lane.queued, active: lane.active, updatedAt };
+            }
+            else {
+                delete processes[String(process.pid)];
+            }
+            const cooldownUntil = Math.max(lane.cooldownUntil, parseTime(entry.cooldownUntil));
+            ledger.providers[provider] = {
+                ...(cooldownUntil > now() ? { cooldownUntil: new Date(cooldownUntil).toISOString() } : {}),
+                consecutiveRateLimits: lane.consecutive,
+                ...(lane.lastRateLimitedAt !== undefined
+                    ? { lastRateLimitedAt: new Date(lane.lastRateLimitedAt).toISOString() }
+                    : entry.lastRateLimitedAt !== undefined ? { lastRateLimitedAt: entry.lastRateLimitedAt } : {}),
+                ...(Object.keys(processes).length > 0 ? { processes } : {}),
+            };
+            lane.recorded = lane.queued > 0 || lane.cooldownUntil > now() || lane.consecutive > 0;
+        }
+        dirty.clear();
+        await mkdir(dirname(ledgerPath), { recursive: true });
+        await writeFile(ledgerPath, `${JSON.stringify(ledger, null, 2)}\n`, 'utf8');
+    };
+    // Only lanes with a queue or a rate limit, and the lanes settling back from one, are written.
+    const touch = (provider) => {
+        const lane = laneFor(provider);
+        if (!lane.recorded && lane.queued === 0 && lane.consecutive === 0 && lane.cooldownUntil <= now()) {
+            return;
+        }
+        lane.recorded = true;
+        dirty.add(provider);
+        if (!flushScheduled) {
+            flushScheduled = true;
+            pending = pending.then(() => {
+                flushScheduled = false;
+                return flush();
+            }).catch(() => undefined);
+        }
+    };
+    // Resolves true once the call may start, or false when the signal aborted the wait.
+    const acquire = async (provider, settings, signal) => {
+        const lane = laneFor(provider);
+        let waited = false;
+        try {
+            for (;;) {
+                if (signal?.aborted === true) {
+                    return false;
+                }
+                const waitMs = lane.cooldownUntil - now();
+                const full = settings.maxConcurrent !== undefined && lane.active >= settings.maxConcurrent;
+                if (waitMs <= 0 && !full) {
+                    return true;
+                }
+                if (!waited) {
+                    waited = true;
+                    lane.queued += 1;
+                    touch(provider);
+                }
+                await (waitMs > 0 ? sleep(waitMs, signal) : waitForSlot(lane, signal));
+            }
+        }
+        finally {
+            if (waited) {
+                lane.queued -= 1;
+            }
+            if (signal?.aborted !== true) {
+                lane.active += 1;
+            }
+            if (waited || lane.recorded) {
+                touch(provider);
+            }
+        }
+    };
+    const release = (provider) => {
+        const lane = laneFor(provider);
+        lane.active -= 1;
+        lane.waiting.shift()?.();
+        touch(provider);
+    };
+    return {
+        async run(provider, settings, signal, execute) {
+            const lane = laneFor(provider);
+            for (let retries = 0; ; retries += 1) {
+                // Another process may have been rate limited by the same provider.
+                const shared = parseTime((await readLedger(ledgerPath)).providers[provider]?.cooldownUntil);
+                lane.cooldownUntil = Math.max(lane.cooldownUntil, shared);
+                if (lane.cooldownUntil - now() > settings.maxDelayMs) {
+                    return rateLimitedFailure(provider, `Provider "${provider}" is rate limited until ${new Date(lane.cooldownUntil).toISOString()}.`);
+                }
+                if (!(await acquire(provider, settings, signal))) {
+                    return cancelledFailure(provider);
+                }
+                let outcome;
+                try {
+                    outcome = await execute();
+                }
+                finally {
+                    release(provider);
+                }
+                if (outcome.type === 'unavailable' || outcome.response.errorCode !== 'PROVIDER_RATE_LIMITED') {
+                    if (lane.consecutive > 0) {
+                        lane.consecutive = 0;
+                        touch(provider);
+                    }
+                    if (retries > 0 && outcome.type !== 'unavailable') {
+                        outcome.response.warnings = [
+                            ...(outcome.response.warnings ?? []),
+                            `Provider "${provider}" was rate limited; the call went through after ${retries} ${retries === 1 ? 'retry' : 'retries'}.`,
+                        ];
+                    }
+                    return outcome;
+                }
+                lane.consecutive += 1;
+                lane.lastRateLimitedAt = now();
+                const ceiling = Math.min(settings.maxDelayMs, settings.baseDelayMs * 2 ** (lane.consecutive - 1));
+                // Half the backoff is fixed and half random, so queued calls do not all retry at once.
+                const delayMs = outcome.response.retryAfterMs ?? Math.round(ceiling / 2 + random() * ceiling / 2);
+                lane.cooldownUntil = Math.max(lane.cooldownUntil, now() + delayMs);
+                touch(provider);
+                if (retries >= settings.retries || delayMs > settings.maxDelayMs) {
+                    outcome.response.warnings = [
+                        ...(outcome.response.warnings ?? []),
+                        retries > 0
+                            ? `Provider "${provider}" was still rate limited after ${retries} ${retries === 1 ? 'retry' : 'retries'}.`
+                            : `Provider "${provider}" is rate limited for ${Math.ceil(delayMs / 1000)}s.`,
+                    ];
+                    return outcome;
+                }
+            }
+        },
+        async getStatus() {
+            await pending;
+            const ledger = await readLedger(ledgerPath);
+            const current = now();
+            return Object.entries(ledger.providers).flatMap(([provider, entry]) => {
+                const lane = lanes.get(provider);
+                let queued = lane?.queued ?? 0;
+                let active = lane?.active ?? 0;
+                for (const [pid, depth] of Object.entries(entry.processes ?? {})) {
+                    if (Number(pid) !== process.pid && isProcessAlive(Number(pid))) {
+                        queued += depth.queued;
+                        active += depth.active;
+                    }
+                }
+                const cooldownUntil = Math.max(lane?.cooldownUntil ?? 0, parseTime(entry.cooldownUntil));
+                const consecutiveRateLimits = lane?.consecutive ?? entry.consecutiveRateLimits ?? 0;
+                if (queued === 0 && active === 0 && cooldownUntil <= current && consecutiveRateLimits === 0) {
+                    return [];
+                }
+                return [{
+                    provider,
+                    queued,
+                    active,
+                    ...(cooldownUntil > current ? { cooldownUntil: new Date(cooldownUntil).toISOString() } : {}),
+                    consecutiveRateLimits,
+                    ...(entry.lastRateLimitedAt !== undefined ? { lastRateLimitedAt: entry.lastRateLimitedAt } : {}),
+                }];
+            }).sort((left, right) => left.provider.localeCompare(right.provider));
+        },
+    };
+}
+function waitForSlot(lane, signal) {
+    return new Promise((resolve) => {
+        const onAbort = () => {
+            lane.waiting.splice(lane.waiting.indexOf(wake), 1);
+            resolve();
+        };
+        const wake = () => {
+            signal?.removeEventListener('abort', onAbort);
+            resolve();
+        };
+        lane.waiting.push(wake);
+        signal?.addEventListener('abort', onAbort, { once: true });
+    });
+}
+function sleep(ms, signal) {
+    return new Promise((resolve) => {
+        const onAbort = () => {
+            clearTimeout(timer);
+            resolve();
+        };
+        const timer = setTimeout(() => {
+            signal?.removeEventListener('abort', onAbort);
+            resolve();
+        }, ms);
+        signal?.addEventListener('abort', onAbort, { once: true });
+    });
+}
+function rateLimitedFailure(provider, error) {
+    return {
+        type: 'failure',
+        response: { success: false, provider, latencyMs: 0, errorCode: 'PROVIDER_RATE_LIMITED', error, mode: 'subprocess' },
+    };
+}
+function cancelledFailure(provider) {
+    return {
+        type: 'failure',
+        response: { success: false, provider, latencyMs: 0, errorCode: 'PROVIDER_CANCELLED', error: `Provider "${provider}" call was cancelled.`, mode: 'subprocess' },
+    };
+}
+async function readLedger(ledgerPath) {
+    try {
+        const parsed = asRecord(JSON.parse(await readFile(ledgerPath, 'utf8'))) ?? {};
+        return { version: 1, providers: asRecord(parsed.providers) ?? {} };
+    }
+    catch {
+        return { version: 1, providers: {} };
+    }
+}
+function parseTime(value) {
+    const parsed = value === undefined ? Number.NaN : Date.parse(value);
+    return Number.isNaN(parsed) ? 0 : parsed;
+}
+function isProcessAlive(pid) {
+    try {
+        process.kill(pid, 0);
+        return true;
+    }
+    catch (error) {
+        return error.code === 'EPERM';
+    }
+}
+function asPositiveInteger(value) {
+    return typeof value === 'number' && Number.isInteger(value) && value > 0 ? value : undefined;
+}
@@ -0,0 +1,155 @@
+import { asRecord } from './records.js';
+export const PROVIDER_ROUTING_POLICIES = ['priority', 'cheapest-capable', 'fastest', 'quality-first'];
+// Public list prices when this table was written; `providers.pricing` overrides or extends it.
+export const DEFAULT_MODEL_PRICING = {
+    'claude-opus-4-1': { input: 15, output: 75, quality: 10, tokensPerSecond: 40, contextWindow: 200_000, capabilities: ['tools', 'vision'] },
+    'claude-sonnet-4-5': { input: 3, output: 15, quality: 9, tokensPerSecond: 70, contextWindow: 200_000, capabilities: ['tools', 'vision'] },
+    'claude-haiku-4-5': { input: 1, output: 5, quality: 7, tokensPerSecond: 120, contextWindow: 200_000, capabilities: ['tools', 'vision'] },
+    'claude-3-5-sonnet': { input: 3, output: 15, quality: 8, tokensPerSecond: 70, contextWindow: 200_000, capabilities: ['tools', 'vision'] },
+    'gpt-5': { input: 1.25, output: 10, quality: 9, tokensPerSecond: 60, contextWindow: 400_000, capabilities: ['tools', 'vision'] },
+    'gpt-4o': { input: 2.5, output: 10, quality: 8, tokensPerSecond: 90, contextWindow: 128_000, capabilities: ['tools', 'vision'] },
+    'gpt-4o-mini': { input: 0.15, output: 0.6, quality: 6, tokensPerSecond: 120, contextWindow: 128_000, capabilities: ['tools', 'vision'] },
+    'gemini-2.5-pro': { input: 1.25, output: 10, quality: 9, tokensPerSecond: 90, contextWindow: 1_000_000, capabilities: ['tools', 'vision'] },
+    'gemini-2.5-flash': { input: 0.3, output: 2.5, quality: 7, tokensPerSecond: 200, contextWindow: 1_000_000, capabilities: ['tools', 'vision'] },
+    'grok-4': { input: 3, output: 15, quality: 9, tokensPerSecond: 50, contextWindow: 256_000, capabilities: ['tools', 'vision'] },
+    'deepseek-chat': { input: 0.27, output: 1.1, quality: 7, tokensPerSecond: 40, contextWindow: 128_000, capabilities: ['tools'] },
+};
+// The model each CLI provider runs by default, for pricing providers whose executor names no model.
+export const PROVIDER_DEFAULT_MODELS = {
+    claude: 'claude-sonnet-4-5',
+    gemini: 'gemini-2.5-pro',
+    codex: 'gpt-5',
+    grok: 'grok-4',
+};
+// Prices are compared for a typical call, which reads about three tokens for each it writes.
+const INPUT_TOKEN_WEIGHT = 3;
+export function normalizeRoutingPolicy(value) {
+    return typeof value === 'string' && PROVIDER_ROUTING_POLICIES.includes(value)
+        ? value
+        : undefined;
+}
+/** `providers.routing` merged with an agent profile's `routing` (as parsed, without unset fields), the agent's winning. */
+export function readRoutingOptions(workspaceConfig, override) {
+    const configured = parseRoutingOptions(asRecord(workspaceConfig.providers)?.routing);
+    return {
+        ...configured,
+        ...override,
+        policy: override?.policy ?? configured.policy ?? 'priority',
+    };
+}
+/** Routing options from an agent profile's `routing` or the workspace's `providers.routing`. */
+export function parseRoutingOptions(value) {
+    const record = asRecord(value) ?? {};
+    const policy = normalizeRoutingPolicy(record.policy);
+    const requires = Array.isArray(record.requires)
+        ? record.requires.filter((entry) => typeof entry === 'string' && entry.length > 0)
+        : undefined;
+    return {
+        ...(policy !== undefined ? { policy } : {}),
+        ...(typeof record.minQuality === 'number' ? { minQuality: record.minQuality } : {}),
+        ...(requires !== undefined && requires.length > 0 ? { requires } : {}),
+        ...(typeof record.minContext === 'number' ? { minContext: record.minContext } : {}),
+    };
+}
+/** The built-in table with `providers.pricing` entries, keyed by model or provider id, laid over it. */
+export function readModelPricing(workspaceConfig) {
+    const configured = asRecord(asRecord(workspaceConfig.providers)?.pricing) ?? {};
+    const pricing = { ...DEFAULT_MODEL_PRICING };
+    for (const [key, value] of Object.entries(configured)) {
+        const entry = asRecord(value) ?? {};
+        if (typeof entry.input !== 'number' || typeof entry.output !== 'number') {
+            continue;
+        }
+        pricing[key] = {
+            ...pricing[key],
+            input: entry.input,
+            output: entry.output,
+            ...(typeof entry.quality === 'number' ? { quality: entry.quality } : {}),
+            ...(typeof entry.tokensPerSecond === 'number' ? { tokensPerSecond: entry.tokensPerSecond } : {}),
+            ...(typeof entry.contextWindow === 'number' ? { contextWindow: entry.contextWindow } : {}),
+            ...(Array.isArray(entry.capabilities) ? { capabilities: entry.capabilities.filter((item) => typeof item === 'string') } : {}),
+        };
+    }
+    return pricing;
+}
+/**
+ * The pricing of a model: an exact entry, then the entry for the model without its vendor
+ * prefix, region, version or date suffix (`anthropic.claude-3-5-sonnet-20240620-v1:0` is
+ * `claude-3-5-sonnet`), then the entry for the provider id.
+ */
+export function lookupModelPricing(pricing, model, provider) {
+    if (model !== undefined) {
+        const exact = pricing[model];
+        if (exact !== undefined) {
+            return exact;
+        }
+        const base = model
+            .toLowerCase()
+            .replace(/^.*\//, '')
+            .replace(/^(?:[a-z]{2,6}\.)?(?:anthropic|meta|amazon|mistral|cohere|ai21|deepseek)\./, '')
+            .replace(/@.*$/, '')
+            .replace(/:latest$/, '')
+            .replace(/-v\d+(?::\d+)?$/, '')
+            .replace(/-\d{8}$/, '');
+        if (pricing[base] !== undefined) {
+            return pricing[base];
+        }
+    }
+    return provider === undefined ? undefined : pricing[provider];
+}
+/**
+ * What a call's tokens cost in USD at its model's price, or at the price of the model the
+ * provider runs by default when its own is unknown. Local servers cost nothing unless the
+ * table prices them; anything else unpriced is undefined.
+ */
+export function estimateCallCost(pricing, call) {
+    const defaultModel = PROVIDER_DEFAULT_MODELS[call.provider];
+    const price = lookupModelPricing(pricing, call.model, call.provider)
+        ?? (defaultModel !== undefined ? lookupModelPricing(pricing, defaultModel) : undefined);
+    if (price === undefined) {
+        return call.local === true ? 0 : undefined;
+    }
+    return (call.inputTokens * price.input + call.outputTokens * price.output) / 1_000_000;
+}
+/**
+ * Orders candidates by the policy. Candidates without an executor, or that fall short of the
+ * requirements, are dropped; those the table does not know sort after those it does. Ties keep
+ * the priority order. `priority` returns the candidates as given.
+ */
+export function rankProviders(candidates, pricing, options) {
+    const described = candidates
+        .filter((candidate) => candidate.resolved)
+        .map((candidate, index) => {
+            const model = candidate.model ?? PROVIDER_DEFAULT_MODELS[candidate.provider];
+            const price = lookupModelPricing(pricing, model, candidate.provider)
+                ?? (candidate.local === true ? { input: 0, output: 0 } : undefined);
+            return { provider: candidate.provider, model, pricing: price, index };
+        });
+    if (options.policy === 'priority') {
+        return described.map(({ provider, model, pricing: price }) => ({ provider, model, pricing: price }));
+    }
+    const capable = described.filter(({ pricing: price }) =>
+        (options.minQuality === undefined || (price?.quality ?? 0) >= options.minQuality)
+        && (options.minContext === undefined || (price?.contextWindow ?? 0) >= options.minContext)
+        && (options.requires ?? []).every((capability) => price?.capabilities?.includes(capability) === true));
+    const score = (price) => {
+        if (price === undefined) {
+            return undefined;
+        }
+        switch (options.policy) {
+            case 'cheapest-capable':
+                return (INPUT_TOKEN_WEIGHT * price.input + price.output) / (INPUT_TOKEN_WEIGHT + 1);
+            case 'fastest':
+                return price.tokensPerSecond === undefined ? undefined : -price.tokensPerSecond;
+            default:
+                return price.quality === undefined ? undefined : -price.quality;
+        }
+    };
+    return capable
+        .map((candidate) => ({ ...candidate, score: score(candidate.pricing) }))
+        .sort((left, right) =>
+            left.score === undefined || right.score === undefined
+                ? (left.score === undefined ? 1 : 0) - (right.score === undefined ? 1 : 0) || left.index - right.index
+                : left.score - right.score || left.index - right.index)
+        .map(({ provider, model, pricing: price }) => ({ provider, model, pricing: price }));
+}
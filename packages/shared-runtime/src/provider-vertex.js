@@ -0,0 +1,44 @@
+import { executeAnthropicMessages } from './provider-anthropic.js';
+import { getGoogleAccessToken } from './provider-google-auth.js';
+import { httpFailure, resolveApiModel } from './provider-http.js';
+import { executeOpenAiChat } from './provider-openai.js';
+export const VERTEX_DEFAULT_MODEL = 'gemini-2.5-flash';
+export const VERTEX_DEFAULT_REGION = 'us-central1';
+const VERTEX_ANTHROPIC_VERSION = 'vertex-2023-10-16';
+export function vertexBaseUrl(region) {
+    return region === 'global' ? 'https://aiplatform.googleapis.com' : `https://${region}-aiplatform.googleapis.com`;
+}
+/**
+ * Calls a model on Vertex AI with an Application Default Credentials token. Claude models go
+ * through Anthropic's Messages API as Vertex serves it; Gemini and other model-garden models
+ * through Vertex's OpenAI-compatible endpoint. The project is the executor's, else the one the
+ * credentials belong to.
+ */
+export async function executeVertex(config, request, adapted) {
+    const startedAt = Date.now();
+    const model = resolveApiModel(request.model, config.model);
+    let token;
+    try {
+        token = await getGoogleAccessToken(config.env);
+    }
+    catch (error) {
+        const cause = error.cause;
+        const message = error instanceof Error ? error.message : String(error);
+        return httpFailure(request, model, startedAt, error.code ?? 'PROVIDER_UNREACHABLE', cause instanceof Error ? `${message}: ${cause.message}` : message);
+    }
+    const project = config.project ?? token.projectId;
+    if (project === undefined) {
+        return httpFailure(request, model, startedAt, 'PROVIDER_CREDENTIALS_MISSING', `Provider "${request.provider}" calls Vertex AI, but no Google Cloud project is set. Set the executor's "project" or GOOGLE_CLOUD_PROJECT.`);
+    }
+    const location = `${config.baseUrl.replace(/\/+$/, '')}/v1/projects/${encodeURIComponent(project)}/locations/${encodeURIComponent(config.region ?? VERTEX_DEFAULT_REGION)}`;
+    if (/^claude-/.test(model)) {
+        return executeAnthropicMessages({ ...config, model }, request, adapted, {
+            url: `${location}/publishers/anthropic/models/${model}:streamRawPredict`,
+            headers: { authorization: `Bearer ${token.token}` },
+            bodyVersion: VERTEX_ANTHROPIC_VERSION,
+        });
+    }
+    // The OpenAI-compatible endpoint names models `publisher/model`.
+    const qualified = model.includes('/') ? model : `google/${model}`;
+    return executeOpenAiChat({ ...config, baseUrl: `${location}/endpoints/openapi`, apiKey: token.token, model: qualified, apiVersion: undefined }, { ...request, model: qualified }, adapted);
+}
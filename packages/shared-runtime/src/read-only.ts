@@ -0,0 +1,69 @@
+export const READ_ONLY_ENV_VAR = 'AUTOMATOSX_READ_ONLY';
+export const READ_ONLY_ERROR_CODE = 'READ_ONLY_MODE';
+
+const MUTATING_TOOL_NAMES = new Set([
+  'file.write',
+  'directory.create',
+  'commit.prepare',
+  'pr.create',
+  'config.set',
+  'scaffold.contract',
+  'scaffold.domain',
+  'scaffold.guard',
+  'scaffold.impl',
+  'maintain.index',
+  'maintain.memory',
+  'maintain.logs',
+  'maintain.caches',
+  'memory.prune',
+  'test.and_fix',
+  // Agents and discussions start providers, whose CLIs may write files and run commands.
+  'agent.run',
+  'agent.delegate',
+  'workflow.run',
+  'parallel.run',
+  'discuss.run',
+  'discuss.quick',
+  'discuss.recursive',
+  // Stored memory, agents and server registrations.
+  'agent.register',
+  'agent.remove',
+  'ability.register',
+  'ability.remove',
+  'memory.store',
+  'memory.clear',
+  'memory.bulk_delete',
+  'memory.import',
+  'memory.merge',
+  'memory.copy',
+  'fact.store',
+  'fact.delete',
+  'semantic.store',
+  'semantic.clear',
+  'mcp.server_register',
+  'mcp.server_unregister',
+]);
+const MUTATING_TOOL_PATTERN = /(?:^|[._:-])(?:write|edit|patch|delete|remove|rename|move|mkdir|apply|exec|execute|shell|bash|command|terminal|commit|push|merge|rebase|checkout|reset|stash)(?:$|[._:-])/i;
+
+export function isReadOnlyEnv(env: NodeJS.ProcessEnv = process.env): boolean {
+  const value = env[READ_ONLY_ENV_VAR]?.trim().toLowerCase();
+  return value === '1' || value === 'true' || value === 'enabled';
+}
+
+export function isMutatingTool(toolName: string): boolean {
+  const normalized = toolName.trim();
+  return MUTATING_TOOL_NAMES.has(normalized) || MUTATING_TOOL_PATTERN.test(normalized);
+}
+
+export function readOnlyViolation(operation: string): Error & { code: string } {
+  return Object.assign(
+    new Error(`${operation} is disabled in read-only mode.`),
+    { code: READ_ONLY_ERROR_CODE },
+  );
+}
+
+export function assertWritable(readOnly: boolean, operation: string): void {
+  if (readOnly) {
+    throw readOnlyViolation(operation);
+  }
+}
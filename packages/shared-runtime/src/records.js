@@ -0,0 +1,4 @@
+/** The value as a JSON object, or undefined for arrays, null and anything else that is not one. */
+export function asRecord(value) {
+    return typeof value === 'object' && value !== null && !Array.isArray(value) ? value : undefined;
+}
@@ -0,0 +1,261 @@
+import { execFile } from 'node:child_process';
+import { promisify } from 'node:util';
+import { extractCodeSymbols } from './code-intel/index.js';
+import { loadWorkspaceLanguageRegistry } from './code-intel/registry.js';
+import { parseUnifiedDiff } from './code-intel/test-impact.js';
+const execFileAsync = promisify(execFile);
+const DEFAULT_AGENT_ID = 'quality';
+const DEFAULT_MAX_CONCURRENT = 4;
+const DEFAULT_MAX_SYMBOLS = 40;
+const MAX_CODE_LINES = 200;
+const SEVERITY_ORDER = ['critical', 'warning', 'note'];
+// Words agents use for a severity instead of the three asked for.
+const SEVERITY_ALIASES = { error: 'critical', high: 'critical', medium: 'warning', low: 'note', info: 'note' };
+/**
+ * Reviews the change between `base` and `head` one symbol at a time: each function, method or
+ * other symbol the diff touches goes to an agent with its part of the diff and its code at `head`,
+ * and the findings come back as annotations on lines of the changed files, ready to post as pull
+ * request review comments. Changed lines outside any symbol are reviewed together per file.
+ */
+export async function runReviewAndAnnotate(request, runAgent) {
+    const base = request.base ?? 'main';
+    const head = request.head ?? 'HEAD';
+    const agentId = request.agentId ?? DEFAULT_AGENT_ID;
+    const maxConcurrent = Math.max(1, request.maxConcurrent ?? DEFAULT_MAX_CONCURRENT);
+    request.onProgress?.(0, `Diffing ${base}...${head}`);
+    const changes = parseUnifiedDiff(await git(request.basePath, ['diff', '--unified=0', '--no-color', '--no-ext-diff', '--no-renames', `${base}...${head}`, '--']));
+    const units = await collectReviewUnits(request.basePath, head, changes);
+    const reviewed = units.slice(0, Math.max(0, request.maxSymbols ?? DEFAULT_MAX_SYMBOLS));
+    const reviews = [];
+    const annotations = [];
+    for (let start = 0; start < reviewed.length; start += maxConcurrent) {
+        request.signal?.throwIfAborted();
+        const batch = reviewed.slice(start, start + maxConcurrent);
+        request.onProgress?.(Math.round((start / reviewed.length) * 100), `Reviewing ${batch.map(describeUnit).join(', ')}`);
+        await Promise.all(batch.map(async (unit) => {
+            const agentRun = await runAgent({
+                agentId,
+                task: buildReviewPrompt(unit, base, head),
+                basePath: request.basePath,
+                provider: request.provider,
+                model: request.model,
+                timeoutMs: request.timeoutMs,
+                sessionId: request.sessionId,
+                surface: request.surface,
+                signal: request.signal,
+            });
+            const review = {
+                file: unit.file,
+                ...(unit.symbol !== undefined ? { symbol: unit.symbol, kind: unit.kind } : {}),
+                status: unit.status,
+                startLine: unit.startLine,
+                endLine: unit.endLine,
+                agentTraceId: agentRun.traceId,
+                findings: 0,
+            };
+            reviews.push(review);
+            if (!agentRun.success) {
+                review.error = agentRun.error ?? { message: 'The agent run failed.' };
+                return;
+            }
+            const findings = parseFindings(agentRun.content, unit, agentRun.traceId);
+            if (findings === undefined) {
+                review.error = { code: 'INVALID_REVIEW_REPLY', message: 'The agent did not reply with a JSON array of findings.' };
+                return;
+            }
+            review.findings = findings.length;
+            annotations.push(...findings);
+        }));
+    }
+    request.signal?.throwIfAborted();
+    request.onProgress?.(100, `Reviewed ${reviewed.length} changed symbol(s)`);
+    const summary = { critical: 0, warning: 0, note: 0 };
+    for (const annotation of annotations) {
+        summary[annotation.severity] += 1;
+    }
+    return {
+        success: reviews.every((review) => review.error === undefined),
+        base,
+        head,
+        changedFiles: changes.map((change) => change.file),
+        reviews: reviews.sort((left, right) => left.file.localeCompare(right.file) || left.startLine - right.startLine),
+        annotations: annotations.sort((left, right) => left.path.localeCompare(right.path)
+            || left.span.startLine - right.span.startLine
+            || SEVERITY_ORDER.indexOf(left.severity) - SEVERITY_ORDER.indexOf(right.severity)),
+        summary,
+        skipped: units.length - reviewed.length,
+    };
+}
+/**
+ * Splits the change into what each review looks at. A hunk belongs to the innermost symbols it
+ * touches, so a method edit is reviewed as the method and not again as its class. A new file is
+ * reviewed by its outermost symbols, or whole when it has none.
+ */
+async function collectReviewUnits(basePath, head, changes) {
+    const { registry } = await loadWorkspaceLanguageRegistry(basePath);
+    const units = [];
+    for (const change of changes) {
+        if (change.deleted) {
+            continue;
+        }
+        let content;
+        try {
+            content = await git(basePath, ['show', `${head}:${change.file}`]);
+        }
+        catch {
+            continue;
+        }
+        const lines = content.replace(/\r?\n$/, '').split(/\r?\n/);
+        const symbols = extractCodeSymbols(change.file, content, registry)?.symbols ?? [];
+        const seen = new Set();
+        const add = (unit) => {
+            const key = `${unit.startLine}:${unit.endLine}`;
+            if (!seen.has(key)) {
+                seen.add(key);
+                units.push({ file: change.file, lines, ...unit });
+            }
+        };
+        if (change.ranges === undefined) {
+            const outermost = symbols.filter((symbol) => !symbols.some((other) => contains(other, symbol)));
+            for (const symbol of outermost) {
+                add({ ...describeSymbol(symbol), status: 'added', hunks: [] });
+            }
+            if (outermost.length === 0 && content.trim().length > 0) {
+                add({ status: 'added', startLine: 1, endLine: lines.length, hunks: [] });
+            }
+            continue;
+        }
+        const assigned = new Map();
+        const outside = [];
+        change.ranges.forEach((range, index) => {
+            const touched = symbols.filter((symbol) => symbol.line <= range[1] && endLine(symbol) >= range[0]);
+            const innermost = touched.filter((symbol) => !touched.some((other) => contains(symbol, other)));
+            if (innermost.length === 0) {
+                outside.push(index);
+            }
+            for (const symbol of innermost) {
+                assigned.set(symbol, [...assigned.get(symbol) ?? [], index]);
+            }
+        });
+        const hunksOf = (indexes) => indexes.map((index) => change.hunks?.[index] ?? '');
+        for (const [symbol, indexes] of [...assigned.entries()].sort(([left], [right]) => left.line - right.line)) {
+            // `@@ -12,0 +13,9 @@` adds lines without removing any; such a hunk around the whole symbol added it.
+            const added = indexes.some((index) => {
+                const [start, end] = change.ranges[index];
+                return /^@@ -\d+,0 /.test(change.hunks?.[index] ?? '') && start <= symbol.line && end >= endLine(symbol);
+            });
+            add({ ...describeSymbol(symbol), status: added ? 'added' : 'modified', hunks: hunksOf(indexes) });
+        }
+        if (outside.length > 0) {
+            add({
+                status: 'modified',
+                startLine: Math.min(...outside.map((index) => change.ranges[index][0])),
+                endLine: Math.max(...outside.map((index) => change.ranges[index][1])),
+                hunks: hunksOf(outside),
+            });
+        }
+    }
+    return units;
+}
+function buildReviewPrompt(unit, base, head) {
+    const subject = unit.symbol === undefined
+        ? `lines ${unit.startLine}-${unit.endLine} of ${unit.file}`
+        : `the ${unit.kind} ${unit.symbol} in ${unit.file} (lines ${unit.startLine}-${unit.endLine})`;
+    const sections = [
+        `Review the change to ${subject} from ${base} to ${head}${unit.status === 'added' ? ', where it is new' : ''}. Look for bugs, security problems, unhandled errors and risky code the change introduces. Leave formatting and naming alone.`,
+    ];
+    if (unit.hunks.length > 0) {
+        sections.push(`Diff:\n\`\`\`diff\n${unit.hunks.join('\n')}\n\`\`\``);
+    }
+    const end = Math.min(unit.endLine, unit.startLine - 1 + MAX_CODE_LINES);
+    const width = String(end).length;
+    const code = unit.lines.slice(unit.startLine - 1, end)
+        .map((line, offset) => `${String(unit.startLine + offset).padStart(width)}| ${line}`)
+        .join('\n');
+    sections.push(`The code at ${head}, with line numbers:\n\`\`\`\n${code}${end < unit.endLine ? '\n...' : ''}\n\`\`\``);
+    sections.push([
+        'Reply with only a JSON array of findings, each like:',
+        '{"line": 14, "endLine": 16, "severity": "warning", "message": "What is wrong and how to fix it."}',
+        `line and endLine are line numbers from ${unit.startLine} to ${unit.endLine} as shown above. severity is "critical", "warning" or "note".`,
+        'Reply [] when nothing deserves a review comment.',
+    ].join('\n'));
+    return sections.join('\n\n');
+}
+/**
+ * Reads the JSON array out of the reply, around any code fence or explanation. Lines are kept
+ * within the reviewed span, since a review comment must land on the change.
+ */
+function parseFindings(content, unit, agentTraceId) {
+    const start = content.indexOf('[');
+    const end = content.lastIndexOf(']');
+    if (start === -1 || end < start) {
+        return undefined;
+    }
+    let parsed;
+    try {
+        parsed = JSON.parse(content.slice(start, end + 1));
+    }
+    catch {
+        return undefined;
+    }
+    if (!Array.isArray(parsed)) {
+        return undefined;
+    }
+    const clamp = (line, fallback) => typeof line === 'number' && Number.isInteger(line)
+        ? Math.min(Math.max(line, unit.startLine), unit.endLine)
+        : fallback;
+    return parsed.flatMap((entry) => {
+        if (typeof entry !== 'object' || entry === null) {
+            return [];
+        }
+        const finding = entry;
+        if (typeof finding.message !== 'string' || finding.message.trim().length === 0) {
+            return [];
+        }
+        const startLine = clamp(finding.line, unit.startLine);
+        return [{
+            path: unit.file,
+            span: { startLine, endLine: Math.max(startLine, clamp(finding.endLine, startLine)) },
+            severity: normalizeSeverity(finding.severity),
+            message: finding.message.trim(),
+            ...(unit.symbol !== undefined ? { symbol: unit.symbol } : {}),
+            agentTraceId,
+        }];
+    });
+}
+function normalizeSeverity(value) {
+    const severity = typeof value === 'string' ? value.trim().toLowerCase() : '';
+    return SEVERITY_ORDER.includes(severity)
+        ? severity
+        : SEVERITY_ALIASES[severity] ?? 'note';
+}
+function describeSymbol(symbol) {
+    return {
+        symbol: symbol.container === undefined ? symbol.name : `${symbol.container}::${symbol.name}`,
+        kind: symbol.kind,
+        startLine: symbol.line,
+        endLine: endLine(symbol),
+    };
+}
+function describeUnit(unit) {
+    return unit.symbol ?? `${unit.file}:${unit.startLine}`;
+}
+// Strictly: symbols with the same span do not contain each other.
+function contains(outer, inner) {
+    return outer !== inner
+        && outer.line <= inner.line
+        && endLine(outer) >= endLine(inner)
+        && (outer.line < inner.line || endLine(outer) > endLine(inner));
+}
+function endLine(symbol) {
+    return symbol.endLine ?? symbol.line;
+}
+async function git(basePath, args) {
+    try {
+        return (await execFileAsync('git', args, { cwd: basePath, maxBuffer: 1024 * 1024 * 16 })).stdout;
+    }
+    catch (error) {
+        const message = error instanceof Error ? error.message : String(error);
+        throw new Error(`git ${args[0] ?? 'command'} failed: ${message}`);
+    }
+}
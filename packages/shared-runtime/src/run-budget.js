@@ -0,0 +1,137 @@
+import { asRecord } from './records.js';
+export const BUDGET_EXCEEDED_CODE = 'BUDGET_EXCEEDED';
+export const BUDGET_INVALID_CODE = 'BUDGET_INVALID';
+const BUDGET_FIELDS = {
+    max_cost_usd: 'maxCostUsd',
+    maxCostUsd: 'maxCostUsd',
+    max_tokens: 'maxTokens',
+    maxTokens: 'maxTokens',
+    max_duration: 'maxDurationMs',
+    maxDurationMs: 'maxDurationMs',
+};
+const DURATION_PATTERN = /^(\d+(?:\.\d+)?)\s*(ms|s|m|h)?$/;
+const DURATION_UNITS = { ms: 1, s: 1000, m: 60_000, h: 3_600_000 };
+/**
+ * Reads a budget as written in an agent profile or a pipeline: `max_cost_usd`, `max_tokens` and
+ * `max_duration`, the last in milliseconds or with a unit such as `90s`, `15m` or `2h`. The camelCase
+ * names of `RunBudget` are accepted too. Throws an error with the `BUDGET_INVALID` code on an
+ * unknown field or a limit that is not a positive number.
+ */
+export function parseRunBudget(value, where = 'The budget') {
+    if (typeof value !== 'object' || value === null || Array.isArray(value)) {
+        throw budgetError(`${where} has to be a mapping of max_cost_usd, max_tokens and max_duration.`);
+    }
+    const budget = {};
+    for (const [field, raw] of Object.entries(asRecord(value) ?? {})) {
+        const limit = BUDGET_FIELDS[field];
+        if (limit === undefined) {
+            throw budgetError(`${where} has an unknown field "${field}"; limits are max_cost_usd, max_tokens and max_duration.`);
+        }
+        const amount = limit === 'maxDurationMs' ? parseDurationMs(raw) : raw;
+        if (typeof amount !== 'number' || !Number.isFinite(amount) || amount <= 0) {
+            throw budgetError(`${where} has a "${field}" that is not a positive ${limit === 'maxDurationMs' ? 'duration' : 'number'}.`);
+        }
+        budget[limit] = amount;
+    }
+    return budget;
+}
+/** Milliseconds of a duration such as `1500`, `"90s"` or `"15m"`; undefined when it is not one. */
+export function parseDurationMs(value) {
+    if (typeof value === 'number') {
+        return value;
+    }
+    const match = typeof value === 'string' ? DURATION_PATTERN.exec(value.trim()) : null;
+    return match === null ? undefined : Number.parseFloat(match[1]) * DURATION_UNITS[match[2] ?? 'ms'];
+}
+/** The tightest of each limit the budgets set, or undefined when none sets any. */
+export function combineBudgets(...budgets) {
+    const combined = {};
+    for (const budget of budgets) {
+        for (const limit of ['maxCostUsd', 'maxTokens', 'maxDurationMs']) {
+            const max = budget?.[limit];
+            if (max !== undefined) {
+                combined[limit] = Math.min(max, combined[limit] ?? max);
+            }
+        }
+    }
+    return Object.keys(combined).length > 0 ? combined : undefined;
+}
+/**
+ * Starts metering a run. Cost and tokens are checked as calls are charged, so the call that goes
+ * over the limit completes and the next one is refused; the duration is checked by a timer that
+ * aborts a call in flight. Aborting `parent` aborts the meter's signal too.
+ */
+export function createBudgetMeter(limits, parent) {
+    const controller = new AbortController();
+    const startedAt = Date.now();
+    let stoppedAt;
+    const used = { calls: 0, inputTokens: 0, outputTokens: 0, costUsd: 0 };
+    let exceeded;
+    const exceed = (limit, max, amount) => {
+        if (exceeded !== undefined) {
+            return;
+        }
+        exceeded = { limit, max, used: amount };
+        controller.abort(Object.assign(new Error(describeExceeded(exceeded)), { code: BUDGET_EXCEEDED_CODE }));
+    };
+    const onParentAbort = () => controller.abort(parent?.reason);
+    if (parent?.aborted === true) {
+        onParentAbort();
+    }
+    else {
+        parent?.addEventListener('abort', onParentAbort, { once: true });
+    }
+    const timer = limits.maxDurationMs === undefined
+        ? undefined
+        : setTimeout(() => exceed('maxDurationMs', limits.maxDurationMs, Date.now() - startedAt), limits.maxDurationMs);
+    timer?.unref();
+    return {
+        signal: controller.signal,
+        charge(usage) {
+            if (stoppedAt !== undefined) {
+                return;
+            }
+            used.calls += 1;
+            used.inputTokens += usage.inputTokens;
+            used.outputTokens += usage.outputTokens;
+            used.costUsd += usage.costUsd ?? 0;
+            const tokens = used.inputTokens + used.outputTokens;
+            if (limits.maxTokens !== undefined && tokens > limits.maxTokens) {
+                exceed('maxTokens', limits.maxTokens, tokens);
+            }
+            else if (limits.maxCostUsd !== undefined && used.costUsd > limits.maxCostUsd) {
+                exceed('maxCostUsd', limits.maxCostUsd, used.costUsd);
+            }
+        },
+        report() {
+            return {
+                limits,
+                consumption: {
+                    ...used,
+                    totalTokens: used.inputTokens + used.outputTokens,
+                    durationMs: (stoppedAt ?? Date.now()) - startedAt,
+                },
+                ...(exceeded !== undefined ? { exceeded } : {}),
+            };
+        },
+        stop() {
+            stoppedAt ??= Date.now();
+            clearTimeout(timer);
+            parent?.removeEventListener('abort', onParentAbort);
+        },
+    };
+}
+/** A sentence on the limit a run went over, e.g. "The token budget of 1000 was exceeded (1240 used)." */
+export function describeExceeded(exceeded) {
+    switch (exceeded.limit) {
+        case 'maxCostUsd':
+            return `The cost budget of $${exceeded.max} was exceeded ($${exceeded.used.toFixed(4)} spent).`;
+        case 'maxTokens':
+            return `The token budget of ${exceeded.max} was exceeded (${exceeded.used} used).`;
+        case 'maxDurationMs':
+            return `The time budget of ${exceeded.max}ms was exceeded (${exceeded.used}ms elapsed).`;
+    }
+}
+function budgetError(message) {
+    return Object.assign(new Error(message), { code: BUDGET_INVALID_CODE });
+}
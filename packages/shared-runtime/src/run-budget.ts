@@ -0,0 +1,183 @@
+import { asRecord } from './records.js';
+
+export const BUDGET_EXCEEDED_CODE = 'BUDGET_EXCEEDED';
+export const BUDGET_INVALID_CODE = 'BUDGET_INVALID';
+
+/** Limits on what a run may use; a run that goes over one is stopped. */
+export interface RunBudget {
+  /** USD, of the calls whose model has a known price. */
+  maxCostUsd?: number;
+  /** Input and output tokens together. */
+  maxTokens?: number;
+  maxDurationMs?: number;
+}
+
+export interface BudgetConsumption {
+  calls: number;
+  inputTokens: number;
+  outputTokens: number;
+  totalTokens: number;
+  costUsd: number;
+  durationMs: number;
+}
+
+/** What a run with a budget used, and the limit it went over, if any. */
+export interface BudgetReport {
+  limits: RunBudget;
+  consumption: BudgetConsumption;
+  exceeded?: {
+    limit: keyof RunBudget;
+    max: number;
+    used: number;
+  };
+}
+
+/** Tracks what a run uses and aborts `signal` when it goes over a limit. */
+export interface BudgetMeter {
+  readonly signal: AbortSignal;
+  charge(usage: { inputTokens: number; outputTokens: number; costUsd?: number }): void;
+  report(): BudgetReport;
+  /** Stops the clock; the meter charges nothing more. */
+  stop(): void;
+}
+
+const BUDGET_FIELDS: Record<string, keyof RunBudget> = {
+  max_cost_usd: 'maxCostUsd',
+  maxCostUsd: 'maxCostUsd',
+  max_tokens: 'maxTokens',
+  maxTokens: 'maxTokens',
+  max_duration: 'maxDurationMs',
+  maxDurationMs: 'maxDurationMs',
+};
+const DURATION_PATTERN = /^(\d+(?:\.\d+)?)\s*(ms|s|m|h)?$/;
+const DURATION_UNITS: Record<string, number> = { ms: 1, s: 1000, m: 60_000, h: 3_600_000 };
+
+/**
+ * Reads a budget as written in an agent profile or a pipeline: `max_cost_usd`, `max_tokens` and
+ * `max_duration`, the last in milliseconds or with a unit such as `90s`, `15m` or `2h`. The camelCase
+ * names of `RunBudget` are accepted too. Throws an error with the `BUDGET_INVALID` code on an
+ * unknown field or a limit that is not a positive number.
+ */
+export function parseRunBudget(value: unknown, where = 'The budget'): RunBudget {
+  if (typeof value !== 'object' || value === null || Array.isArray(value)) {
+    throw budgetError(`${where} has to be a mapping of max_cost_usd, max_tokens and max_duration.`);
+  }
+  const budget: RunBudget = {};
+  for (const [field, raw] of Object.entries(asRecord(value) ?? {})) {
+    const limit = BUDGET_FIELDS[field];
+    if (limit === undefined) {
+      throw budgetError(`${where} has an unknown field "${field}"; limits are max_cost_usd, max_tokens and max_duration.`);
+    }
+    const amount = limit === 'maxDurationMs' ? parseDurationMs(raw) : raw;
+    if (typeof amount !== 'number' || !Number.isFinite(amount) || amount <= 0) {
+      throw budgetError(`${where} has a "${field}" that is not a positive ${limit === 'maxDurationMs' ? 'duration' : 'number'}.`);
+    }
+    budget[limit] = amount;
+  }
+  return budget;
+}
+
+/** Milliseconds of a duration such as `1500`, `"90s"` or `"15m"`; undefined when it is not one. */
+export function parseDurationMs(value: unknown): number | undefined {
+  if (typeof value === 'number') {
+    return value;
+  }
+  const match = typeof value === 'string' ? DURATION_PATTERN.exec(value.trim()) : null;
+  return match === null ? undefined : Number.parseFloat(match[1]!) * DURATION_UNITS[match[2] ?? 'ms']!;
+}
+
+/** The tightest of each limit the budgets set, or undefined when none sets any. */
+export function combineBudgets(...budgets: Array<RunBudget | undefined>): RunBudget | undefined {
+  const combined: RunBudget = {};
+  for (const budget of budgets) {
+    for (const limit of ['maxCostUsd', 'maxTokens', 'maxDurationMs'] as const) {
+      const max = budget?.[limit];
+      if (max !== undefined) {
+        combined[limit] = Math.min(max, combined[limit] ?? max);
+      }
+    }
+  }
+  return Object.keys(combined).length > 0 ? combined : undefined;
+}
+
+/**
+ * Starts metering a run. Cost and tokens are checked as calls are charged, so the call that goes
+ * over the limit completes and the next one is refused; the duration is checked by a timer that
+ * aborts a call in flight. Aborting `parent` aborts the meter's signal too.
+ */
+export function createBudgetMeter(limits: RunBudget, parent?: AbortSignal): BudgetMeter {
+  const controller = new AbortController();
+  const startedAt = Date.now();
+  let stoppedAt: number | undefined;
+  const used = { calls: 0, inputTokens: 0, outputTokens: 0, costUsd: 0 };
+  let exceeded: BudgetReport['exceeded'];
+  const exceed = (limit: keyof RunBudget, max: number, amount: number) => {
+    if (exceeded !== undefined) {
+      return;
+    }
+    exceeded = { limit, max, used: amount };
+    controller.abort(Object.assign(new Error(describeExceeded(exceeded)), { code: BUDGET_EXCEEDED_CODE }));
+  };
+
+  const onParentAbort = () => controller.abort(parent?.reason);
+  if (parent?.aborted === true) {
+    onParentAbort();
+  } else {
+    parent?.addEventListener('abort', onParentAbort, { once: true });
+  }
+  const timer = limits.maxDurationMs === undefined
+    ? undefined
+    : setTimeout(() => exceed('maxDurationMs', limits.maxDurationMs!, Date.now() - startedAt), limits.maxDurationMs);
+  timer?.unref();
+
+  return {
+    signal: controller.signal,
+    charge(usage) {
+      if (stoppedAt !== undefined) {
+        return;
+      }
+      used.calls += 1;
+      used.inputTokens += usage.inputTokens;
+      used.outputTokens += usage.outputTokens;
+      used.costUsd += usage.costUsd ?? 0;
+      const tokens = used.inputTokens + used.outputTokens;
+      if (limits.maxTokens !== undefined && tokens > limits.maxTokens) {
+        exceed('maxTokens', limits.maxTokens, tokens);
+      } else if (limits.maxCostUsd !== undefined && used.costUsd > limits.maxCostUsd) {
+        exceed('maxCostUsd', limits.maxCostUsd, used.costUsd);
+      }
+    },
+    report() {
+      return {
+        limits,
+        consumption: {
+          ...used,
+          totalTokens: used.inputTokens + used.outputTokens,
+          durationMs: (stoppedAt ?? Date.now()) - startedAt,
+        },
+        ...(exceeded !== undefined ? { exceeded } : {}),
+      };
+    },
+    stop() {
+      stoppedAt ??= Date.now();
+      clearTimeout(timer);
+      parent?.removeEventListener('abort', onParentAbort);
+    },
+  };
+}
+
+/** A sentence on the limit a run went over, e.g. "The token budget of 1000 was exceeded (1240 used)." */
+export function describeExceeded(exceeded: NonNullable<BudgetReport['exceeded']>): string {
+  switch (exceeded.limit) {
+    case 'maxCostUsd':
+      return `The cost budget of $${exceeded.max} was exceeded ($${exceeded.used.toFixed(4)} spent).`;
+    case 'maxTokens':
+      return `The token budget of ${exceeded.max} was exceeded (${exceeded.used} used).`;
+    case 'maxDurationMs':
+      return `The time budget of ${exceeded.max}ms was exceeded (${exceeded.used}ms elapsed).`;
+  }
+}
+
+function budgetError(message: string): Error {
+  return Object.assign(new Error(message), { code: BUDGET_INVALID_CODE });
+}
@@ -0,0 +1,422 @@
+import { createHash } from 'node:crypto';
+import { appendFile, mkdir, readFile, rm, stat, writeFile } from 'node:fs/promises';
+import { dirname, isAbsolute, join, normalize, sep } from 'node:path';
+import { snapshotWorkspace, type WorkspaceSnapshot } from './agent-permissions.js';
+import { readWorkspaceConfig } from './config-layers.js';
+import type { ProviderExecutionOutcome, ProviderExecutionRequest, ProviderExecutionResponse } from './provider-bridge.js';
+import { asRecord } from './records.js';
+import type { RunBudget } from './run-budget.js';
+import type { JsonSchema } from './structured-output.js';
+
+export const REPLAY_DIR = join('.automatosx', 'replays');
+export const REPLAY_NOT_FOUND_CODE = 'REPLAY_NOT_FOUND';
+export const REPLAY_DIVERGED_CODE = 'REPLAY_DIVERGED';
+
+/** The top-level run a replay log belongs to, with what it takes to run it again. */
+export type ReplayRun =
+  | {
+    command: 'agent.run';
+    request: {
+      agentId: string;
+      task?: string;
+      input?: Record<string, unknown>;
+      scope?: string;
+      files?: string[];
+      provider?: string;
+      model?: string;
+      timeoutMs?: number;
+      budget?: RunBudget;
+      outputSchema?: JsonSchema;
+    };
+  }
+  | {
+    command: 'pipeline.run';
+    /** The pipeline as the run read it, so a replay does not depend on the file since. */
+    request: { file: string; source: string; input?: Record<string, unknown>; provider?: string; budget?: RunBudget };
+  };
+
+export type ReplayRunHeader = ReplayRun & { type: 'run'; traceId: string; startedAt: string };
+
+/** A tool the model called during a provider call, and what the tool returned or threw. */
+export interface ReplayToolCall {
+  name: string;
+  input: Record<string, unknown>;
+  output?: string;
+  error?: string;
+}
+
+/** A workspace file created, changed or deleted while a provider call was in flight. */
+export interface ReplayEdit {
+  /** Relative to the workspace root. */
+  file: string;
+  /** The file's content after the call; unset for deleted files and for ones too large or binary to keep. */
+  content?: string;
+  /** Set when the file did not exist before the call. */
+  created?: boolean;
+  deleted?: boolean;
+}
+
+/** One provider call of a recorded run. */
+export interface ReplayCall {
+  type: 'call';
+  traceId?: string;
+  agentId?: string;
+  provider: string;
+  model?: string;
+  systemPrompt?: string;
+  prompt: string;
+  /** Identifies the prompt when the call is matched during a replay. */
+  promptHash: string;
+  outcome: ProviderExecutionOutcome['type'];
+  response?: ProviderExecutionResponse;
+  /** The error of an `unavailable` outcome. */
+  error?: string;
+  usage?: { model?: string; inputTokens: number; outputTokens: number; costUsd?: number };
+  tools: ReplayToolCall[];
+  edits: ReplayEdit[];
+}
+
+/** Where a replay went differently from the recorded run. */
+export interface ReplayDivergence {
+  kind: 'prompt' | 'provider' | 'tool' | 'missing-call' | 'unused-call';
+  /** The number of the recorded call, counting from 1. */
+  call?: number;
+  agentId?: string;
+  message: string;
+}
+
+/** What a replay used of its log, and where it diverged from it. */
+export interface ReplaySummary {
+  calls: { recorded: number; replayed: number };
+  toolCalls: number;
+  edits: number;
+  divergences: ReplayDivergence[];
+}
+
+/** A provider call of a recorded run, underway; `finish` appends it to the run's log. */
+export interface ReplayCapture {
+  /** The request to execute: its tools record what they are called with. */
+  request: ProviderExecutionRequest;
+  setUsage(usage: NonNullable<ReplayCall['usage']>): void;
+  finish(outcome: ProviderExecutionOutcome): Promise<void>;
+}
+
+export interface RunRecorder {
+  /**
+   * Starts logging the provider calls of a top-level run, and of the runs inside it, to
+   * `.automatosx/replays/<trace-id>.jsonl`; `resume` adds to an existing log. Nothing is logged
+   * while `replay.record` is false in the workspace config. Returns a warning when the log cannot
+   * be started.
+   */
+  start(basePath: string, run: ReplayRun & { traceId: string }, options?: { resume?: boolean }): Promise<string | undefined>;
+  finish(traceId: string): void;
+  /** Starts a call of a run being logged; undefined for the calls of other runs. */
+  capture(request: ProviderExecutionRequest): Promise<ReplayCapture | undefined>;
+  /**
+   * Lets the run `traceId` and the runs inside it take their provider calls from `calls` instead
+   * of the providers; `endReplay` returns what the replay used.
+   */
+  beginReplay(basePath: string, traceId: string, calls: ReplayCall[]): void;
+  endReplay(traceId: string): ReplaySummary;
+  /** The recorded outcome of a call of a run being replayed; undefined for the calls of other runs. */
+  replay(request: ProviderExecutionRequest): Promise<ProviderExecutionOutcome | undefined>;
+}
+
+interface ReplaySession {
+  basePath: string;
+  calls: ReplayCall[];
+  used: Set<number>;
+  toolCalls: number;
+  edits: number;
+  divergences: ReplayDivergence[];
+}
+
+const MAX_EDIT_BYTES = 256 * 1024;
+const RUN_ID_PATTERN = /^[\w-]+$/;
+
+/**
+ * Records the provider calls of top-level runs with the tools they called and the files they
+ * changed, and replays such a log: each call gets its recorded outcome, after the recorded tool
+ * calls are run again against the live tools and the recorded edits are written. `charge` bills a
+ * replayed call to the budgets of its runs without recording usage, since no tokens were spent.
+ */
+export function createRunRecorder(config: {
+  charge?: (call: { traceId?: string; inputTokens: number; outputTokens: number; costUsd?: number }) => Promise<void>;
+}): RunRecorder {
+  const recordings = new Map<string, { basePath: string; file: string; pending: Promise<void> }>();
+  const sessions = new Map<string, ReplaySession>();
+  const rootOf = (request: ProviderExecutionRequest) => request.rootTraceId ?? request.traceId;
+
+  return {
+    async start(basePath, run, options = {}) {
+      if (sessions.has(run.traceId) || !(await readRecordSetting(basePath))) {
+        return undefined;
+      }
+      const file = getReplayLogPath(basePath, run.traceId);
+      try {
+        await mkdir(dirname(file), { recursive: true });
+        const exists = options.resume === true && await stat(file).then(() => true, () => false);
+        if (!exists) {
+          const header: ReplayRunHeader = { type: 'run', traceId: run.traceId, startedAt: new Date().toISOString(), command: run.command, request: run.request } as ReplayRunHeader;
+          await writeFile(file, `${JSON.stringify(header)}\n`, 'utf8');
+        }
+      } catch (error) {
+        return `Could not start the replay log: ${error instanceof Error ? error.message : String(error)}`;
+      }
+      recordings.set(run.traceId, { basePath, file, pending: Promise.resolve() });
+      return undefined;
+    },
+
+    finish(traceId) {
+      recordings.delete(traceId);
+    },
+
+    async capture(request) {
+      const root = rootOf(request);
+      const recording = root === undefined ? undefined : recordings.get(root);
+      if (recording === undefined) {
+        return undefined;
+      }
+      const tools: ReplayToolCall[] = [];
+      const before = await snapshotWorkspace(recording.basePath, request.scope).catch(() => undefined);
+      let usage: ReplayCall['usage'];
+      return {
+        request: request.tools === undefined ? request : {
+          ...request,
+          tools: request.tools.map((tool) => ({
+            ...tool,
+            async run(input: Record<string, unknown>) {
+              const call: ReplayToolCall = { name: tool.name, input };
+              tools.push(call);
+              try {
+                call.output = await tool.run(input);
+                return call.output;
+              } catch (error) {
+                call.error = error instanceof Error ? error.message : String(error);
+                throw error;
+              }
+            },
+          })),
+        },
+        setUsage(value) {
+          usage = value;
+        },
+        async finish(outcome) {
+          const edits = before === undefined
+            ? []
+            : await collectEdits(recording.basePath, before, await snapshotWorkspace(recording.basePath, request.scope).catch(() => before));
+          const call: ReplayCall = {
+            type: 'call',
+            traceId: request.traceId,
+            agentId: request.agentId,
+            provider: request.provider,
+            model: request.model,
+            systemPrompt: request.systemPrompt,
+            prompt: request.prompt,
+            promptHash: hashPrompt(request),
+            outcome: outcome.type,
+            ...(outcome.type === 'unavailable' ? { error: outcome.error } : { response: outcome.response }),
+            usage,
+            tools,
+            edits,
+          };
+          // Calls of parallel stages finish in any order; each line is appended whole.
+          recording.pending = recording.pending.then(() => appendFile(recording.file, `${JSON.stringify(call)}\n`, 'utf8'));
+          try {
+            await recording.pending;
+          } catch (error) {
+            recording.pending = Promise.resolve();
+            if (outcome.type !== 'unavailable') {
+              outcome.response.warnings = [
+                ...(outcome.response.warnings ?? []),
+                `Could not add the call to the replay log: ${error instanceof Error ? error.message : String(error)}`,
+              ];
+            }
+          }
+        },
+      };
+    },
+
+    beginReplay(basePath, traceId, calls) {
+      sessions.set(traceId, { basePath, calls, used: new Set(), toolCalls: 0, edits: 0, divergences: [] });
+    },
+
+    endReplay(traceId) {
+      const session = sessions.get(traceId);
+      sessions.delete(traceId);
+      if (session === undefined) {
+        return { calls: { recorded: 0, replayed: 0 }, toolCalls: 0, edits: 0, divergences: [] };
+      }
+      const unused = session.calls.flatMap((call, index) => session.used.has(index) ? [] : [{
+        kind: 'unused-call' as const,
+        call: index + 1,
+        agentId: call.agentId,
+        message: `Recorded call ${index + 1}${call.agentId !== undefined ? ` of agent "${call.agentId}"` : ''} to "${call.provider}" was never made.`,
+      }]);
+      return {
+        calls: { recorded: session.calls.length, replayed: session.used.size },
+        toolCalls: session.toolCalls,
+        edits: session.edits,
+        divergences: [...session.divergences, ...unused],
+      };
+    },
+
+    async replay(request) {
+      const root = rootOf(request);
+      const session = root === undefined ? undefined : sessions.get(root);
+      if (session === undefined) {
+        return undefined;
+      }
+      const diverge = (divergence: ReplayDivergence) => session.divergences.push(divergence);
+      // The same agent with the same prompt first; then the agent's next call, whatever it was asked.
+      const hash = hashPrompt(request);
+      const unused = (match: (call: ReplayCall) => boolean) => session.calls.findIndex((call, index) => !session.used.has(index) && match(call));
+      let index = unused((call) => call.agentId === request.agentId && call.promptHash === hash);
+      if (index === -1) {
+        index = unused((call) => call.agentId === request.agentId);
+        if (index !== -1) {
+          diverge({ kind: 'prompt', call: index + 1, agentId: request.agentId, message: `The prompt of recorded call ${index + 1} changed.` });
+        }
+      }
+      if (index === -1) {
+        const message = `The recording has no more calls${request.agentId !== undefined ? ` of agent "${request.agentId}"` : ''}.`;
+        diverge({ kind: 'missing-call', agentId: request.agentId, message });
+        return {
+          type: 'failure',
+          response: { success: false, provider: request.provider, model: request.model, latencyMs: 0, errorCode: REPLAY_DIVERGED_CODE, error: message, mode: 'subprocess' },
+        };
+      }
+      session.used.add(index);
+      const call = session.calls[index]!;
+      if (call.provider !== request.provider) {
+        diverge({ kind: 'provider', call: index + 1, agentId: call.agentId, message: `Recorded call ${index + 1} went to "${call.provider}"; the replay asked "${request.provider}".` });
+      }
+
+      for (const recorded of call.tools) {
+        session.toolCalls += 1;
+        const tool = request.tools?.find((candidate) => candidate.name === recorded.name);
+        if (tool === undefined) {
+          diverge({ kind: 'tool', call: index + 1, agentId: call.agentId, message: `The tool "${recorded.name}" is not offered any more.` });
+          continue;
+        }
+        let output: string | undefined;
+        let error: string | undefined;
+        try {
+          output = await tool.run(recorded.input);
+        } catch (failure) {
+          error = failure instanceof Error ? failure.message : String(failure);
+        }
+        if (output !== recorded.output || error !== recorded.error) {
+          diverge({ kind: 'tool', call: index + 1, agentId: call.agentId, message: describeToolChange(recorded, error) });
+        }
+      }
+      for (const edit of call.edits) {
+        if (!isInside(edit.file)) {
+          continue;
+        }
+        const target = join(session.basePath, edit.file);
+        if (edit.deleted === true) {
+          await rm(target, { force: true });
+        } else if (edit.content !== undefined) {
+          await mkdir(dirname(target), { recursive: true });
+          await writeFile(target, edit.content, 'utf8');
+        } else {
+          continue;
+        }
+        session.edits += 1;
+      }
+
+      if (call.usage !== undefined) {
+        await config.charge?.({ traceId: request.traceId, ...call.usage });
+      }
+      if (call.outcome === 'unavailable') {
+        return { type: 'unavailable', error: call.error ?? `No provider executor configured for "${call.provider}".` };
+      }
+      const response = { ...call.response!, warnings: [...(call.response?.warnings ?? [])] };
+      if (response.content !== undefined) {
+        request.onText?.(response.content);
+      }
+      return { type: call.outcome, response };
+    },
+  };
+}
+
+export function getReplayLogPath(basePath: string, traceId: string): string {
+  return join(basePath, REPLAY_DIR, `${traceId}.jsonl`);
+}
+
+/** The run a replay log records and its provider calls, in the order they finished. */
+export async function readReplayLog(basePath: string, runId: string): Promise<{ run: ReplayRunHeader; calls: ReplayCall[] }> {
+  let text: string;
+  try {
+    if (!RUN_ID_PATTERN.test(runId)) {
+      throw new Error('not a run id');
+    }
+    text = await readFile(getReplayLogPath(basePath, runId), 'utf8');
+  } catch {
+    throw Object.assign(new Error(`Run "${runId}" has no replay log.`), { code: REPLAY_NOT_FOUND_CODE });
+  }
+  const entries = text.split('\n').filter((line) => line.trim().length > 0).flatMap((line) => {
+    try {
+      return [asRecord(JSON.parse(line)) ?? {}];
+    } catch {
+      return [];
+    }
+  });
+  const run = entries[0];
+  if (run?.type !== 'run' || (run.command !== 'agent.run' && run.command !== 'pipeline.run')) {
+    throw Object.assign(new Error(`The replay log of run "${runId}" does not start with its run.`), { code: REPLAY_NOT_FOUND_CODE });
+  }
+  return {
+    run: run as unknown as ReplayRunHeader,
+    calls: entries.slice(1).filter((entry) => entry.type === 'call') as unknown as ReplayCall[],
+  };
+}
+
+async function readRecordSetting(basePath: string): Promise<boolean> {
+  return asRecord((await readWorkspaceConfig(basePath)).replay)?.record !== false;
+}
+
+// Whatever else changed the workspace while the call was in flight is counted as the call's doing.
+async function collectEdits(basePath: string, before: WorkspaceSnapshot, after: WorkspaceSnapshot): Promise<ReplayEdit[]> {
+  const edits: ReplayEdit[] = [];
+  for (const [file, stamp] of after) {
+    if (before.get(file) === stamp) {
+      continue;
+    }
+    const content = await readFile(join(basePath, file)).catch(() => undefined);
+    edits.push({
+      file,
+      ...(content === undefined || content.length > MAX_EDIT_BYTES || content.includes(0) ? {} : { content: content.toString('utf8') }),
+      ...(before.has(file) ? {} : { created: true }),
+    });
+  }
+  for (const file of before.keys()) {
+    if (!after.has(file)) {
+      edits.push({ file, deleted: true });
+    }
+  }
+  return edits;
+}
+
+function describeToolChange(recorded: ReplayToolCall, error: string | undefined): string {
+  const tool = `The tool "${recorded.name}"`;
+  if (error === undefined) {
+    return recorded.error === undefined
+      ? `${tool} returned something else than when it was recorded.`
+      : `${tool} succeeded; when it was recorded, it failed: ${recorded.error}`;
+  }
+  return recorded.error === undefined
+    ? `${tool} failed: ${error}; when it was recorded, it succeeded.`
+    : `${tool} failed with "${error}"; when it was recorded, it failed with "${recorded.error}".`;
+}
+
+function hashPrompt(request: { systemPrompt?: string; prompt: string }): string {
+  return createHash('sha256').update(`${request.systemPrompt ?? ''}\0${request.prompt}`).digest('hex').slice(0, 16);
+}
+
+// A relative path that stays inside the workspace.
+function isInside(path: string): boolean {
+  const normalized = normalize(path);
+  return !isAbsolute(path) && normalized !== '..' && !normalized.startsWith(`..${sep}`);
+}
@@ -0,0 +1,71 @@
+import { isLiveClaim } from '@defai.digital/state-store';
+import { SESSION_NOT_FOUND_CODE } from './session-threads.js';
+export const SCRATCHPAD_NO_OPEN_TASK_CODE = 'SCRATCHPAD_NO_OPEN_TASK';
+/** Claims the first open task of the scratchpad whose paths no live claim covers. */
+export const NEXT_SCRATCHPAD_TASK = 'next';
+const MAX_POSTED_RESULT_CHARS = 4_000;
+/**
+ * Claims a task of the session's scratchpad for a run: the named one, or the next free one for
+ * `next`. Fails with `SESSION_NOT_FOUND` outside a known session, with `SCRATCHPAD_CONFLICT` when
+ * the task is taken, and with `SCRATCHPAD_NO_OPEN_TASK` when `next` finds none.
+ */
+export async function claimTaskForRun(stateStore, sessionId, claim, run) {
+    if (sessionId === undefined) {
+        throw Object.assign(new Error('A run can only claim a scratchpad task within a session.'), { code: SESSION_NOT_FOUND_CODE });
+    }
+    await requireScratchpadSession(stateStore, sessionId);
+    const task = await stateStore.claimScratchpadTask(sessionId, {
+        ...(claim === NEXT_SCRATCHPAD_TASK ? {} : { taskId: claim }),
+        claimant: run.claimant,
+        traceId: run.traceId,
+    });
+    if (task === undefined) {
+        throw Object.assign(new Error(`Session "${sessionId}" has no open scratchpad task that no other run's paths cover.`), { code: SCRATCHPAD_NO_OPEN_TASK_CODE });
+    }
+    return task;
+}
+/** Fails with `SESSION_NOT_FOUND` unless the session exists. */
+export async function requireScratchpadSession(stateStore, sessionId) {
+    if (await stateStore.getSession(sessionId) === undefined) {
+        throw Object.assign(new Error(`Session "${sessionId}" does not exist.`), { code: SESSION_NOT_FOUND_CODE });
+    }
+}
+/** Posts how a claiming run ended: its output when it succeeded, its error when it failed. */
+export async function postRunResult(stateStore, task, success, result) {
+    const trimmed = result.length > MAX_POSTED_RESULT_CHARS ? `${result.slice(0, MAX_POSTED_RESULT_CHARS)}…` : result;
+    try {
+        const posted = await stateStore.postScratchpadResult(task.sessionId, {
+            taskId: task.taskId,
+            claimant: task.claimedBy ?? '',
+            result: trimmed,
+            status: success ? 'done' : 'failed',
+        });
+        return { task: posted, warnings: [] };
+    }
+    catch (error) {
+        return { task, warnings: [`Could not post the result of scratchpad task "${task.taskId}": ${error instanceof Error ? error.message : String(error)}`] };
+    }
+}
+/**
+ * The scratchpad as an agent of the session is told about it: the tasks other runs hold, with the
+ * paths it should leave alone, the open ones, and what finished tasks posted. `ownTraceIds` are the
+ * run and the runs above it, whose claims are the agent's own.
+ */
+export function describeScratchpad(tasks, ownTraceIds, now = new Date()) {
+    if (tasks.length === 0) {
+        return undefined;
+    }
+    const paths = (task) => task.paths.length === 0 ? '' : ` (${task.paths.join(', ')})`;
+    const lines = tasks.map((task) => {
+        if (isLiveClaim(task, now)) {
+            return task.traceId !== undefined && ownTraceIds.includes(task.traceId)
+                ? `- ${task.taskId}: ${task.title}${paths(task)}: yours, claimed by this run.`
+                : `- ${task.taskId}: ${task.title}${paths(task)}: claimed by ${task.claimedBy}; leave its paths alone.`;
+        }
+        if (task.status === 'done' || task.status === 'failed') {
+            return `- ${task.taskId}: ${task.title}${paths(task)}: ${task.status}, posted by ${task.claimedBy}: ${task.result ?? ''}`;
+        }
+        return `- ${task.taskId}: ${task.title}${paths(task)}: open.`;
+    });
+    return ['Other runs of this session work in parallel; the session scratchpad shows who works on what.', ...lines].join('\n');
+}
@@ -0,0 +1,80 @@
+import { mkdir, readFile, rename, writeFile } from 'node:fs/promises';
+import { dirname, join } from 'node:path';
+import { asRecord } from './records.js';
+export const SESSION_THREAD_DIR = join('.automatosx', 'sessions');
+export const SESSION_NOT_FOUND_CODE = 'SESSION_NOT_FOUND';
+export const SESSION_AGENT_MISSING_CODE = 'SESSION_AGENT_MISSING';
+export const SESSION_ID_INVALID_CODE = 'SESSION_ID_INVALID';
+const SESSION_ID_PATTERN = /^[\w-][\w.-]*$/;
+const threadUpdates = new Map();
+/** The stored thread of a session; an empty one when it has none yet. */
+export async function readSessionThread(basePath, sessionId) {
+    if (!SESSION_ID_PATTERN.test(sessionId)) {
+        return { sessionId, turns: [] };
+    }
+    let parsed;
+    try {
+        parsed = asRecord(JSON.parse(await readFile(sessionThreadPath(basePath, sessionId), 'utf8'))) ?? {};
+    }
+    catch {
+        return { sessionId, turns: [] };
+    }
+    const branchedFrom = asRecord(parsed.branchedFrom) ?? {};
+    return {
+        sessionId,
+        ...(typeof parsed.agentId === 'string' ? { agentId: parsed.agentId } : {}),
+        ...(typeof parsed.scope === 'string' ? { scope: parsed.scope } : {}),
+        ...(typeof parsed.memoryNamespace === 'string' ? { memoryNamespace: parsed.memoryNamespace } : {}),
+        ...(typeof branchedFrom.sessionId === 'string' && typeof branchedFrom.turns === 'number'
+            ? { branchedFrom: { sessionId: branchedFrom.sessionId, turns: branchedFrom.turns } }
+            : {}),
+        turns: Array.isArray(parsed.turns) ? parsed.turns.filter(isTurn) : [],
+        ...(typeof parsed.updatedAt === 'string' ? { updatedAt: parsed.updatedAt } : {}),
+    };
+}
+/**
+ * Applies `update` to the stored thread of a session and writes it back whole. Updates of one
+ * session run one at a time, so turns of overlapping runs are all kept.
+ */
+export async function updateSessionThread(basePath, sessionId, update) {
+    if (!SESSION_ID_PATTERN.test(sessionId)) {
+        throw Object.assign(new Error(`Session "${sessionId}" cannot keep a thread: its id is not a file name.`), { code: SESSION_ID_INVALID_CODE });
+    }
+    const path = sessionThreadPath(basePath, sessionId);
+    const next = (threadUpdates.get(path) ?? Promise.resolve()).catch(() => undefined).then(async () => {
+        const thread = { ...update(await readSessionThread(basePath, sessionId)), sessionId, updatedAt: new Date().toISOString() };
+        const tempPath = `${path}.${process.pid}.tmp`;
+        await mkdir(dirname(path), { recursive: true });
+        await writeFile(tempPath, `${JSON.stringify(thread, null, 2)}\n`, 'utf8');
+        await rename(tempPath, path);
+        return thread;
+    });
+    threadUpdates.set(path, next);
+    try {
+        return await next;
+    }
+    finally {
+        if (threadUpdates.get(path) === next) {
+            threadUpdates.delete(path);
+        }
+    }
+}
+/** A fork of `thread` as session `sessionId` with its first `turns` turns, all of them by default. */
+export function branchSessionThread(thread, sessionId, turns = thread.turns.length) {
+    const kept = Math.max(0, Math.min(turns, thread.turns.length));
+    return {
+        sessionId,
+        ...(thread.agentId !== undefined ? { agentId: thread.agentId } : {}),
+        ...(thread.scope !== undefined ? { scope: thread.scope } : {}),
+        ...(thread.memoryNamespace !== undefined ? { memoryNamespace: thread.memoryNamespace } : {}),
+        branchedFrom: { sessionId: thread.sessionId, turns: kept },
+        turns: thread.turns.slice(0, kept),
+    };
+}
+function sessionThreadPath(basePath, sessionId) {
+    return join(basePath, SESSION_THREAD_DIR, `${sessionId}.json`);
+}
+function isTurn(value) {
+    const turn = asRecord(value) ?? {};
+    return (turn.role === 'user' || turn.role === 'assistant') && typeof turn.content === 'string' && typeof turn.at === 'string';
+}
@@ -0,0 +1,436 @@
+import type { SessionEntry, UsageRecord } from '@defai.digital/state-store';
+import type { TraceRecord } from '@defai.digital/trace-store';
+import type { ReplayCall, ReplayEdit, ReplayToolCall } from './run-replay.js';
+import type { SessionThread } from './session-threads.js';
+
+export type TranscriptFormat = 'md' | 'html' | 'json';
+
+export const TRANSCRIPT_FORMATS: readonly TranscriptFormat[] = ['md', 'html', 'json'];
+
+/** A file a run created, changed or deleted. */
+export interface TranscriptEdit {
+  file: string;
+  change: 'created' | 'modified' | 'deleted';
+  /**
+   * A unified diff against the file's content before the change; unset when that content is not
+   * known, as for the first change to a file that existed before the session, or for a file too
+   * large or binary to record.
+   */
+  diff?: string;
+}
+
+export interface TranscriptToolCall extends ReplayToolCall {
+  /** The agent whose provider call made the tool call. */
+  agentId?: string;
+}
+
+/** One turn of the session: the task put to the agent, its reply, and what the run did and cost. */
+export interface TranscriptRun {
+  traceId?: string;
+  agentId?: string;
+  task: string;
+  reply: string;
+  provider?: string;
+  model?: string;
+  status?: string;
+  startedAt: string;
+  completedAt?: string;
+  durationMs: number;
+  /** Provider calls of the run and of the runs it handed work to. */
+  calls: number;
+  inputTokens: number;
+  outputTokens: number;
+  costUsd: number;
+  toolCalls: TranscriptToolCall[];
+  edits: TranscriptEdit[];
+}
+
+export interface SessionTranscript {
+  sessionId: string;
+  task: string;
+  status: string;
+  createdAt: string;
+  updatedAt: string;
+  agentId?: string;
+  scope?: string;
+  memoryNamespace?: string;
+  branchedFrom?: { sessionId: string; turns: number };
+  runs: TranscriptRun[];
+  totals: {
+    runs: number;
+    calls: number;
+    inputTokens: number;
+    outputTokens: number;
+    costUsd: number;
+    durationMs: number;
+    toolCalls: number;
+    edits: number;
+  };
+}
+
+/** What is known of one run of a session: its trace, its usage records and the calls of its replay log. */
+export interface TranscriptSource {
+  trace?: TraceRecord;
+  usage: UsageRecord[];
+  /** Empty when the run has no replay log. */
+  calls: ReplayCall[];
+}
+
+const DIFF_CONTEXT_LINES = 3;
+// Past this many line pairs, a changed region is shown as removed and added whole.
+const MAX_DIFF_CELLS = 4_000_000;
+const MAX_TOOL_OUTPUT_CHARS = 2000;
+
+/** The transcript of a session's thread, each pair of turns filled in from the sources of its run. */
+export function buildSessionTranscript(
+  session: SessionEntry,
+  thread: SessionThread,
+  sources: ReadonlyMap<string, TranscriptSource>,
+): SessionTranscript {
+  // The last known content of each file, so a later change to it can be shown as a diff.
+  const contents = new Map<string, string>();
+  const runs: TranscriptRun[] = [];
+  let task: SessionThread['turns'][number] | undefined;
+  for (const turn of thread.turns) {
+    if (turn.role === 'user') {
+      task = turn;
+      continue;
+    }
+    const traceId = turn.traceId ?? task?.traceId;
+    const source = traceId === undefined ? undefined : sources.get(traceId);
+    const startedAt = source?.trace?.startedAt ?? task?.at ?? turn.at;
+    const completedAt = source?.trace?.completedAt ?? turn.at;
+    const metadata = source?.trace?.metadata ?? {};
+    const usage = source?.usage ?? [];
+    const calls = source?.calls ?? [];
+    runs.push({
+      ...(traceId !== undefined ? { traceId } : {}),
+      ...(turn.agentId !== undefined ? { agentId: turn.agentId } : {}),
+      task: task?.content ?? '',
+      reply: turn.content,
+      ...(typeof metadata.provider === 'string' ? { provider: metadata.provider } : {}),
+      ...(typeof metadata.model === 'string' ? { model: metadata.model } : {}),
+      ...(source?.trace !== undefined ? { status: source.trace.status } : {}),
+      startedAt,
+      completedAt,
+      durationMs: Math.max(0, Date.parse(completedAt) - Date.parse(startedAt)) || 0,
+      calls: usage.length,
+      inputTokens: sum(usage, (record) => record.inputTokens),
+      outputTokens: sum(usage, (record) => record.outputTokens),
+      costUsd: sum(usage, (record) => record.costUsd ?? 0),
+      toolCalls: calls.flatMap((call) => call.tools.map((tool) => ({ ...tool, ...(call.agentId !== undefined ? { agentId: call.agentId } : {}) }))),
+      edits: calls.flatMap((call) => call.edits).map((edit) => describeEdit(edit, contents)),
+    });
+    task = undefined;
+  }
+  return {
+    sessionId: session.sessionId,
+    task: session.task,
+    status: session.status,
+    createdAt: session.createdAt,
+    updatedAt: session.updatedAt,
+    ...(thread.agentId !== undefined ? { agentId: thread.agentId } : {}),
+    ...(thread.scope !== undefined ? { scope: thread.scope } : {}),
+    ...(thread.memoryNamespace !== undefined ? { memoryNamespace: thread.memoryNamespace } : {}),
+    ...(thread.branchedFrom !== undefined ? { branchedFrom: thread.branchedFrom } : {}),
+    runs,
+    totals: {
+      runs: runs.length,
+      calls: sum(runs, (run) => run.calls),
+      inputTokens: sum(runs, (run) => run.inputTokens),
+      outputTokens: sum(runs, (run) => run.outputTokens),
+      costUsd: sum(runs, (run) => run.costUsd),
+      durationMs: sum(runs, (run) => run.durationMs),
+      toolCalls: sum(runs, (run) => run.toolCalls.length),
+      edits: sum(runs, (run) => run.edits.length),
+    },
+  };
+}
+
+export function renderSessionTranscript(transcript: SessionTranscript, format: TranscriptFormat): string {
+  switch (format) {
+    case 'json':
+      return `${JSON.stringify(transcript, null, 2)}\n`;
+    case 'html':
+      return renderHtml(transcript);
+    default:
+      return renderMarkdown(transcript);
+  }
+}
+
+function renderMarkdown(transcript: SessionTranscript): string {
+  const lines = [`# Session ${transcript.sessionId}`, '', transcript.task, '', ...describeSession(transcript).map((line) => `- ${line}`)];
+  transcript.runs.forEach((run, index) => {
+    lines.push('', `## ${index + 1}. ${firstLine(run.task)}`, '', `_${describeRun(run)}_`, '', '### Task', '', quote(run.task), '', '### Reply', '', run.reply.trim() || '_No reply._');
+    if (run.toolCalls.length > 0) {
+      lines.push('', '### Tool calls');
+      for (const call of run.toolCalls) {
+        lines.push('', `- \`${call.name}\` \`${JSON.stringify(call.input)}\`${call.error !== undefined ? ` failed: ${call.error}` : ''}`);
+        if (call.output !== undefined && call.output.length > 0) {
+          lines.push('', indent(fence(truncate(call.output), ''), '  '));
+        }
+      }
+    }
+    if (run.edits.length > 0) {
+      lines.push('', '### File changes');
+      for (const edit of run.edits) {
+        lines.push('', `#### \`${edit.file}\` (${edit.change})`, '', edit.diff !== undefined ? fence(edit.diff, 'diff') : describeMissingDiff(edit));
+      }
+    }
+  });
+  return `${lines.join('\n')}\n`;
+}
+
+function renderHtml(transcript: SessionTranscript): string {
+  const sections = transcript.runs.map((run, index) => [
+    '<section>',
+    `<h2>${index + 1}. ${escapeHtml(firstLine(run.task))}</h2>`,
+    `<p class="meta">${escapeHtml(describeRun(run))}</p>`,
+    '<h3>Task</h3>',
+    `<pre class="task">${escapeHtml(run.task)}</pre>`,
+    '<h3>Reply</h3>',
+    `<pre class="reply">${escapeHtml(run.reply.trim() || 'No reply.')}</pre>`,
+    ...(run.toolCalls.length === 0 ? [] : [
+      '<h3>Tool calls</h3>',
+      '<ul>',
+      ...run.toolCalls.map((call) => [
+        `<li><code>${escapeHtml(call.name)}</code> <code>${escapeHtml(JSON.stringify(call.input))}</code>`,
+        call.error !== undefined ? ` failed: ${escapeHtml(call.error)}` : '',
+        call.output !== undefined && call.output.length > 0 ? `<pre>${escapeHtml(truncate(call.output))}</pre>` : '',
+        '</li>',
+      ].join('')),
+      '</ul>',
+    ]),
+    ...(run.edits.length === 0 ? [] : [
+      '<h3>File changes</h3>',
+      ...run.edits.flatMap((edit) => [
+        `<h4><code>${escapeHtml(edit.file)}</code> (${edit.change})</h4>`,
+        edit.diff !== undefined
+          ? `<pre class="diff">${edit.diff.split('\n').map(renderDiffLine).join('\n')}</pre>`
+          : `<p>${escapeHtml(describeMissingDiff(edit))}</p>`,
+      ]),
+    ]),
+    '</section>',
+  ].join('\n'));
+  return [
+    '<!DOCTYPE html>',
+    '<html lang="en">',
+    '<head>',
+    '<meta charset="utf-8">',
+    `<title>Session ${escapeHtml(transcript.sessionId)}</title>`,
+    '<style>',
+    'body { font-family: system-ui, sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.5; }',
+    'pre { background: #f6f8fa; padding: 0.75rem; overflow-x: auto; white-space: pre-wrap; }',
+    '.meta { color: #57606a; font-size: 0.9rem; }',
+    '.add { color: #116329; background: #dafbe1; }',
+    '.del { color: #82071e; background: #ffebe9; }',
+    '.hunk { color: #0550ae; }',
+    'section { border-top: 1px solid #d0d7de; margin-top: 2rem; }',
+    '</style>',
+    '</head>',
+    '<body>',
+    `<h1>Session <code>${escapeHtml(transcript.sessionId)}</code></h1>`,
+    `<p>${escapeHtml(transcript.task)}</p>`,
+    '<ul>',
+    ...describeSession(transcript).map((line) => `<li>${escapeHtml(line.replace(/`/g, ''))}</li>`),
+    '</ul>',
+    ...sections,
+    '</body>',
+    '</html>',
+    '',
+  ].join('\n');
+}
+
+function describeSession(transcript: SessionTranscript): string[] {
+  const { totals } = transcript;
+  return [
+    `Status: ${transcript.status}`,
+    `Created: ${transcript.createdAt}; last updated: ${transcript.updatedAt}`,
+    ...(transcript.agentId !== undefined ? [`Agent: ${transcript.agentId}`] : []),
+    ...(transcript.scope !== undefined ? [`Scope: \`${transcript.scope}\``] : []),
+    ...(transcript.memoryNamespace !== undefined ? [`Memory namespace: \`${transcript.memoryNamespace}\``] : []),
+    ...(transcript.branchedFrom !== undefined ? [`Branched from \`${transcript.branchedFrom.sessionId}\` after ${transcript.branchedFrom.turns} turns`] : []),
+    `Totals: ${totals.runs} run(s), ${totals.calls} provider call(s), ${totals.inputTokens + totals.outputTokens} tokens (${totals.inputTokens} in, ${totals.outputTokens} out), $${totals.costUsd.toFixed(4)}, ${formatDuration(totals.durationMs)}, ${totals.toolCalls} tool call(s), ${totals.edits} file change(s)`,
+  ];
+}
+
+function describeRun(run: TranscriptRun): string {
+  return [
+    [run.agentId, run.provider !== undefined ? `on ${run.provider}${run.model !== undefined ? ` (${run.model})` : ''}` : undefined].filter(Boolean).join(' ') || undefined,
+    run.startedAt,
+    formatDuration(run.durationMs),
+    `${run.calls} call(s)`,
+    `${run.inputTokens + run.outputTokens} tokens`,
+    `$${run.costUsd.toFixed(4)}`,
+    run.status !== undefined && run.status !== 'completed' ? run.status : undefined,
+    run.traceId !== undefined ? `trace ${run.traceId}` : undefined,
+  ].filter((part): part is string => part !== undefined).join(', ');
+}
+
+function describeMissingDiff(edit: TranscriptEdit): string {
+  return edit.change === 'deleted'
+    ? 'The file was deleted; its content before was not recorded.'
+    : 'The content before or after the change was not recorded, so there is no diff.';
+}
+
+function describeEdit(edit: ReplayEdit, contents: Map<string, string>): TranscriptEdit {
+  const before = edit.created === true ? '' : contents.get(edit.file);
+  if (edit.deleted === true) {
+    contents.delete(edit.file);
+    const diff = before === undefined ? undefined : unifiedDiff(edit.file, before, '', { deleted: true });
+    return { file: edit.file, change: 'deleted', ...(diff !== undefined ? { diff } : {}) };
+  }
+  const change = edit.created === true ? 'created' : 'modified';
+  if (edit.content === undefined) {
+    contents.delete(edit.file);
+    return { file: edit.file, change };
+  }
+  contents.set(edit.file, edit.content);
+  const diff = before === undefined ? undefined : unifiedDiff(edit.file, before, edit.content, { created: edit.created === true });
+  return { file: edit.file, change, ...(diff !== undefined ? { diff } : {}) };
+}
+
+/** A unified diff of two versions of a file; undefined when their lines are the same. */
+export function unifiedDiff(file: string, before: string, after: string, options: { created?: boolean; deleted?: boolean } = {}): string | undefined {
+  const oldLines = splitLines(before);
+  const newLines = splitLines(after);
+  let head = 0;
+  while (head < oldLines.length && head < newLines.length && oldLines[head] === newLines[head]) {
+    head += 1;
+  }
+  let tail = 0;
+  while (tail < oldLines.length - head && tail < newLines.length - head && oldLines[oldLines.length - 1 - tail] === newLines[newLines.length - 1 - tail]) {
+    tail += 1;
+  }
+  const ops: Array<{ type: ' ' | '-' | '+'; line: string }> = [
+    ...oldLines.slice(0, head).map((line) => ({ type: ' ' as const, line })),
+    ...compareLines(oldLines.slice(head, oldLines.length - tail), newLines.slice(head, newLines.length - tail)),
+    ...oldLines.slice(oldLines.length - tail).map((line) => ({ type: ' ' as const, line })),
+  ];
+  const hunks: string[] = [];
+  let next = 0;
+  let oldLine = 0;
+  let newLine = 0;
+  while (true) {
+    const start = ops.findIndex((op, index) => index >= next && op.type !== ' ');
+    if (start === -1) {
+      break;
+    }
+    // Changes less than two contexts apart share a hunk.
+    let end = start;
+    for (let index = start + 1; index < ops.length && index <= end + 2 * DIFF_CONTEXT_LINES; index += 1) {
+      if (ops[index]!.type !== ' ') {
+        end = index;
+      }
+    }
+    const from = Math.max(next, start - DIFF_CONTEXT_LINES);
+    const to = Math.min(ops.length, end + DIFF_CONTEXT_LINES + 1);
+    for (const op of ops.slice(next, from)) {
+      oldLine += op.type === '+' ? 0 : 1;
+      newLine += op.type === '-' ? 0 : 1;
+    }
+    const hunk = ops.slice(from, to);
+    const oldCount = hunk.filter((op) => op.type !== '+').length;
+    const newCount = hunk.filter((op) => op.type !== '-').length;
+    hunks.push(
+      `@@ -${oldCount === 0 ? oldLine : oldLine + 1},${oldCount} +${newCount === 0 ? newLine : newLine + 1},${newCount} @@`,
+      ...hunk.map((op) => `${op.type}${op.line}`),
+    );
+    oldLine += oldCount;
+    newLine += newCount;
+    next = to;
+  }
+  if (hunks.length === 0) {
+    return undefined;
+  }
+  return [options.created === true ? '--- /dev/null' : `--- a/${file}`, options.deleted === true ? '+++ /dev/null' : `+++ b/${file}`, ...hunks].join('\n');
+}
+
+// The longest common subsequence of lines; a region too large for it is replaced whole.
+function compareLines(oldLines: string[], newLines: string[]): Array<{ type: ' ' | '-' | '+'; line: string }> {
+  const removed = oldLines.map((line) => ({ type: '-' as const, line }));
+  const added = newLines.map((line) => ({ type: '+' as const, line }));
+  if (oldLines.length === 0 || newLines.length === 0 || oldLines.length * newLines.length > MAX_DIFF_CELLS) {
+    return [...removed, ...added];
+  }
+  const width = newLines.length + 1;
+  const common = new Uint32Array((oldLines.length + 1) * width);
+  for (let i = oldLines.length - 1; i >= 0; i -= 1) {
+    for (let j = newLines.length - 1; j >= 0; j -= 1) {
+      common[i * width + j] = oldLines[i] === newLines[j]
+        ? common[(i + 1) * width + j + 1]! + 1
+        : Math.max(common[(i + 1) * width + j]!, common[i * width + j + 1]!);
+    }
+  }
+  const ops: Array<{ type: ' ' | '-' | '+'; line: string }> = [];
+  let i = 0;
+  let j = 0;
+  while (i < oldLines.length && j < newLines.length) {
+    if (oldLines[i] === newLines[j]) {
+      ops.push({ type: ' ', line: oldLines[i]! });
+      i += 1;
+      j += 1;
+    } else if (common[(i + 1) * width + j]! >= common[i * width + j + 1]!) {
+      ops.push(removed[i]!);
+      i += 1;
+    } else {
+      ops.push(added[j]!);
+      j += 1;
+    }
+  }
+  return [...ops, ...removed.slice(i), ...added.slice(j)];
+}
+
+function renderDiffLine(line: string): string {
+  const escaped = escapeHtml(line);
+  if (line.startsWith('@@')) {
+    return `<span class="hunk">${escaped}</span>`;
+  }
+  if (line.startsWith('+') && !line.startsWith('+++')) {
+    return `<span class="add">${escaped}</span>`;
+  }
+  if (line.startsWith('-') && !line.startsWith('---')) {
+    return `<span class="del">${escaped}</span>`;
+  }
+  return escaped;
+}
+
+function splitLines(text: string): string[] {
+  return text.length === 0 ? [] : text.replace(/\n$/, '').split('\n');
+}
+
+// A fence longer than any run of backticks in the text, so the text cannot close it.
+function fence(text: string, language: string): string {
+  const longest = Math.max(2, ...(text.match(/`+/g) ?? []).map((run) => run.length));
+  const marker = '`'.repeat(longest + 1);
+  return `${marker}${language}\n${text.replace(/\n$/, '')}\n${marker}`;
+}
+
+function quote(text: string): string {
+  return text.split('\n').map((line) => `> ${line}`.trimEnd()).join('\n');
+}
+
+function indent(text: string, prefix: string): string {
+  return text.split('\n').map((line) => `${prefix}${line}`).join('\n');
+}
+
+function truncate(text: string): string {
+  return text.length > MAX_TOOL_OUTPUT_CHARS ? `${text.slice(0, MAX_TOOL_OUTPUT_CHARS)}\n… ${text.length - MAX_TOOL_OUTPUT_CHARS} more characters` : text;
+}
+
+function firstLine(text: string): string {
+  const line = text.trim().split('\n')[0] ?? '';
+  return line.length > 80 ? `${line.slice(0, 77)}...` : line;
+}
+
+function formatDuration(ms: number): string {
+  return ms < 1000 ? `${ms}ms` : `${(ms / 1000).toFixed(1)}s`;
+}
+
+function escapeHtml(text: string): string {
+  return text.replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;').replace(/"/g, '&quot;');
+}
+
+function sum<T>(items: T[], value: (item: T) => number): number {
+  return items.reduce((total, item) => total + value(item), 0);
+}
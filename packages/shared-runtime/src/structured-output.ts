@@ -0,0 +1,279 @@
+import type { ProviderExecutionOutcome, ProviderExecutionRequest, ProviderExecutionResponse } from './provider-bridge.js';
+import { asRecord } from './records.js';
+
+/** A JSON Schema, of which `validateJsonSchema` checks the common keywords. */
+export type JsonSchema = Record<string, unknown>;
+
+/** The parsed reply of a call made against a schema, and how many calls it took to get one that fits. */
+export interface StructuredOutput<T = unknown> {
+  /** Set when the reply parsed, whether or not it fits the schema. */
+  value?: T;
+  valid: boolean;
+  /** The first call and each repair. */
+  attempts: number;
+  /** What was wrong with the last reply, when it still did not fit. */
+  errors?: string[];
+}
+
+/** The `providers.structuredOutput` section. */
+export interface StructuredOutputSettings {
+  /** Re-prompts with the validation errors before the call fails. */
+  repairAttempts: number;
+}
+
+const DEFAULT_REPAIR_ATTEMPTS = 2;
+// The repair prompt lists this many errors; more are noise to the model.
+const MAX_REPORTED_ERRORS = 10;
+
+export function readStructuredOutputSettings(workspaceConfig: Record<string, unknown>): StructuredOutputSettings {
+  const section = asRecord(asRecord(workspaceConfig.providers)?.structuredOutput) ?? {};
+  return {
+    repairAttempts: typeof section.repairAttempts === 'number' && Number.isInteger(section.repairAttempts) && section.repairAttempts >= 0
+      ? section.repairAttempts
+      : DEFAULT_REPAIR_ATTEMPTS,
+  };
+}
+
+/**
+ * Asks for a reply that fits `schema`: the schema goes into the prompt, the reply is parsed and
+ * validated, and a reply that does not fit is sent back with what is wrong with it, up to
+ * `repairAttempts` times. The response carries the parsed value in `structured` and the summed
+ * usage of every attempt; when no attempt fits, the call fails with `PROVIDER_OUTPUT_INVALID`.
+ * Nothing is streamed, since a reply may be thrown away.
+ */
+export async function runStructuredOutput(
+  execute: (request: ProviderExecutionRequest) => Promise<ProviderExecutionOutcome>,
+  request: ProviderExecutionRequest,
+  schema: JsonSchema,
+  settings: StructuredOutputSettings,
+): Promise<ProviderExecutionOutcome> {
+  const startedAt = Date.now();
+  const prompt = buildStructuredPrompt(request.prompt, schema);
+  const responses: ProviderExecutionResponse[] = [];
+  let repair: { reply: string; errors: string[] } | undefined;
+
+  for (let attempt = 1; ; attempt += 1) {
+    const outcome = await execute({
+      ...request,
+      prompt: repair === undefined ? prompt : buildRepairPrompt(prompt, repair.reply, repair.errors),
+      responseFormat: 'json',
+      onText: undefined,
+      // A repair has to reach the model; the cached reply is the one being repaired.
+      noCache: request.noCache === true || attempt > 1,
+    });
+    if (outcome.type === 'unavailable') {
+      return outcome;
+    }
+    responses.push(outcome.response);
+    if (outcome.type === 'failure') {
+      return { type: 'failure', response: combineAttempts(responses, outcome.response, startedAt) };
+    }
+
+    const reply = outcome.response.content ?? '';
+    const parsed = parseJson(reply);
+    const errors = parsed.ok ? validateJsonSchema(parsed.value, schema) : [`The reply is not valid JSON: ${parsed.error}`];
+    const structured: StructuredOutput = {
+      ...(parsed.ok ? { value: parsed.value } : {}),
+      valid: errors.length === 0,
+      attempts: attempt,
+      ...(errors.length > 0 ? { errors } : {}),
+    };
+    if (errors.length === 0) {
+      return {
+        type: 'response',
+        response: {
+          ...combineAttempts(responses, outcome.response, startedAt),
+          structured,
+          ...(attempt > 1 ? { warnings: [...(outcome.response.warnings ?? []), `The reply fit the output schema after ${attempt - 1} repair${attempt === 2 ? '' : 's'}.`] } : {}),
+        },
+      };
+    }
+    if (attempt > settings.repairAttempts) {
+      return {
+        type: 'failure',
+        response: {
+          ...combineAttempts(responses, outcome.response, startedAt),
+          success: false,
+          errorCode: 'PROVIDER_OUTPUT_INVALID',
+          error: `The reply did not fit the output schema after ${attempt} attempt${attempt === 1 ? '' : 's'}: ${errors.slice(0, 3).join('; ')}`,
+          structured,
+        },
+      };
+    }
+    repair = { reply, errors };
+  }
+}
+
+export function buildStructuredPrompt(prompt: string, schema: JsonSchema): string {
+  return [
+    prompt,
+    '',
+    'Reply with JSON that fits this JSON Schema:',
+    '<json-schema>',
+    JSON.stringify(schema, null, 2),
+    '</json-schema>',
+  ].join('\n');
+}
+
+export function buildRepairPrompt(prompt: string, reply: string, errors: string[]): string {
+  return [
+    prompt,
+    '',
+    'An earlier reply did not fit the schema:',
+    '<reply>',
+    reply,
+    '</reply>',
+    '<errors>',
+    ...errors.slice(0, MAX_REPORTED_ERRORS).map((error) => `- ${error}`),
+    ...(errors.length > MAX_REPORTED_ERRORS ? [`- and ${errors.length - MAX_REPORTED_ERRORS} more`] : []),
+    '</errors>',
+    '',
+    'Reply again with the corrected JSON in full.',
+  ].join('\n');
+}
+
+/**
+ * Where `value` breaks `schema`, one message per problem with the JSON path it is at; empty when
+ * it fits. Covers `type`, `enum`, `const`, `properties`, `required`, `additionalProperties`,
+ * `items`, the length and range bounds, `pattern`, and `allOf`, `anyOf` and `oneOf`. Other
+ * keywords, `$ref` included, are not checked.
+ */
+export function validateJsonSchema(value: unknown, schema: JsonSchema, path = '$'): string[] {
+  const errors: string[] = [];
+  const types = typeof schema.type === 'string' ? [schema.type] : Array.isArray(schema.type) ? schema.type.map(String) : [];
+  if (types.length > 0 && !types.some((type) => matchesType(value, type))) {
+    return [`${path}: expected ${types.join(' or ')}, got ${describeType(value)}`];
+  }
+  if (Array.isArray(schema.enum) && !schema.enum.some((option) => sameJson(option, value))) {
+    errors.push(`${path}: must be one of ${schema.enum.map((option) => JSON.stringify(option)).join(', ')}`);
+  }
+  if ('const' in schema && !sameJson(schema.const, value)) {
+    errors.push(`${path}: must be ${JSON.stringify(schema.const)}`);
+  }
+
+  if (typeof value === 'string') {
+    if (typeof schema.minLength === 'number' && value.length < schema.minLength) {
+      errors.push(`${path}: must be at least ${schema.minLength} characters`);
+    }
+    if (typeof schema.maxLength === 'number' && value.length > schema.maxLength) {
+      errors.push(`${path}: must be at most ${schema.maxLength} characters`);
+    }
+    if (typeof schema.pattern === 'string' && compilePattern(schema.pattern)?.test(value) === false) {
+      errors.push(`${path}: must match /${schema.pattern}/`);
+    }
+  }
+  if (typeof value === 'number') {
+    if (typeof schema.minimum === 'number' && value < schema.minimum) {
+      errors.push(`${path}: must be at least ${schema.minimum}`);
+    }
+    if (typeof schema.maximum === 'number' && value > schema.maximum) {
+      errors.push(`${path}: must be at most ${schema.maximum}`);
+    }
+  }
+  if (Array.isArray(value)) {
+    if (typeof schema.minItems === 'number' && value.length < schema.minItems) {
+      errors.push(`${path}: must have at least ${schema.minItems} items`);
+    }
+    if (typeof schema.maxItems === 'number' && value.length > schema.maxItems) {
+      errors.push(`${path}: must have at most ${schema.maxItems} items`);
+    }
+    const items = asRecord(schema.items) ?? {};
+    if (Object.keys(items).length > 0) {
+      value.forEach((item, index) => errors.push(...validateJsonSchema(item, items, `${path}[${index}]`)));
+    }
+  }
+  if (typeof value === 'object' && value !== null && !Array.isArray(value)) {
+    const record = value as Record<string, unknown>;
+    const properties = asRecord(schema.properties) ?? {};
+    for (const name of Array.isArray(schema.required) ? schema.required.map(String) : []) {
+      if (!(name in record)) {
+        errors.push(`${path}.${name}: is required`);
+      }
+    }
+    for (const [name, entry] of Object.entries(record)) {
+      if (name in properties) {
+        errors.push(...validateJsonSchema(entry, asRecord(properties[name]) ?? {}, `${path}.${name}`));
+      } else if (schema.additionalProperties === false) {
+        errors.push(`${path}.${name}: is not allowed`);
+      } else if (typeof schema.additionalProperties === 'object' && schema.additionalProperties !== null) {
+        errors.push(...validateJsonSchema(entry, asRecord(schema.additionalProperties) ?? {}, `${path}.${name}`));
+      }
+    }
+  }
+
+  const subschemas = (keyword: string) => Array.isArray(schema[keyword]) ? (schema[keyword] as unknown[]).map(asRecord) : undefined;
+  for (const subschema of subschemas('allOf') ?? []) {
+    errors.push(...validateJsonSchema(value, subschema, path));
+  }
+  const anyOf = subschemas('anyOf');
+  if (anyOf !== undefined && !anyOf.some((subschema) => validateJsonSchema(value, subschema, path).length === 0)) {
+    errors.push(`${path}: must match at least one of the anyOf schemas`);
+  }
+  const oneOf = subschemas('oneOf');
+  if (oneOf !== undefined && oneOf.filter((subschema) => validateJsonSchema(value, subschema, path).length === 0).length !== 1) {
+    errors.push(`${path}: must match exactly one of the oneOf schemas`);
+  }
+  return errors;
+}
+
+function matchesType(value: unknown, type: string): boolean {
+  switch (type) {
+    case 'integer':
+      return Number.isInteger(value);
+    case 'number':
+      return typeof value === 'number' && Number.isFinite(value);
+    case 'array':
+      return Array.isArray(value);
+    case 'object':
+      return typeof value === 'object' && value !== null && !Array.isArray(value);
+    case 'null':
+      return value === null;
+    default:
+      return typeof value === type;
+  }
+}
+
+// A pattern that does not compile is not checked rather than failing every reply.
+function compilePattern(pattern: string): RegExp | undefined {
+  try {
+    return new RegExp(pattern, 'u');
+  } catch {
+    return undefined;
+  }
+}
+
+function describeType(value: unknown): string {
+  return value === null ? 'null' : Array.isArray(value) ? 'array' : typeof value;
+}
+
+function sameJson(left: unknown, right: unknown): boolean {
+  return JSON.stringify(left) === JSON.stringify(right);
+}
+
+function parseJson(text: string): { ok: true; value: unknown } | { ok: false; error: string } {
+  try {
+    return { ok: true, value: JSON.parse(text) };
+  } catch (error) {
+    return { ok: false, error: error instanceof Error ? error.message : String(error) };
+  }
+}
+
+// The last attempt's response with the time and usage of all of them.
+function combineAttempts(
+  responses: ProviderExecutionResponse[],
+  response: ProviderExecutionResponse,
+  startedAt: number,
+): ProviderExecutionResponse {
+  const usages = responses.flatMap((entry) => entry.usage === undefined ? [] : [entry.usage]);
+  return {
+    ...response,
+    latencyMs: Date.now() - startedAt,
+    ...(usages.length > 0 ? {
+      usage: {
+        inputTokens: usages.reduce((sum, usage) => sum + usage.inputTokens, 0),
+        outputTokens: usages.reduce((sum, usage) => sum + usage.outputTokens, 0),
+        totalTokens: usages.reduce((sum, usage) => sum + usage.totalTokens, 0),
+      },
+    } : {}),
+  };
+}
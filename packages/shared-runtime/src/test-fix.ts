@@ -0,0 +1,382 @@
+import { mkdir, readFile, writeFile } from 'node:fs/promises';
+import { dirname, relative, resolve } from 'node:path';
+import type { TraceSurface } from '@defai.digital/trace-store';
+import { permitsPath, type AgentPermissions } from './agent-permissions.js';
+import { createApprovalGate, gatesWrite, loadApprovalPolicy, type ApprovalGates, type Approver } from './approval-gates.js';
+import { indexCodeFile, readCodeIndex } from './code-intel/index.js';
+import { loadWorkspaceLanguageRegistry, type LanguageRegistry } from './code-intel/registry.js';
+import { isTestFile } from './code-intel/test-detection.js';
+import { parseTestFailures, type TestFailure } from './code-intel/test-failures.js';
+import { runTestCommand, type TestImpactRun } from './code-intel/test-impact.js';
+import type { CodeSymbol, CodeSymbolKind } from './code-intel/types.js';
+
+export interface RuntimeTestFixRequest {
+  basePath: string;
+  /** The agent asked for fixes; `bug-hunter` by default. */
+  agentId?: string;
+  /** Fix attempts before giving up; 3 by default. */
+  maxIterations?: number;
+  /** The test command; `testing.command` from `.automatosx/config.json` by default. */
+  command?: string[];
+  provider?: string;
+  model?: string;
+  /** Per agent call. */
+  timeoutMs?: number;
+  sessionId?: string;
+  surface?: TraceSurface;
+  signal?: AbortSignal;
+  onProgress?: (percent: number, message: string) => void;
+  /** Asked about edits outside the write scope of the agent's `approvals` that the approval policy does not allow. */
+  approve?: Approver;
+  /** The approval policy file, relative to `basePath`; `.automatosx/approval-policy.json` by default. */
+  approvalPolicy?: string;
+}
+
+export interface TestFixEdit {
+  file: string;
+  applied: boolean;
+  /** Why an edit was not applied. */
+  reason?: string;
+}
+
+export interface TestFixIteration {
+  iteration: number;
+  failures: TestFailure[];
+  agentTraceId: string;
+  edits: TestFixEdit[];
+  /** Whether the tests passed when run again after the edits; unset when none applied. */
+  passedAfter?: boolean;
+}
+
+export interface RuntimeTestFixReport {
+  success: boolean;
+  command: string[];
+  stopReason: 'passed' | 'max-iterations' | 'no-edits' | 'agent-failed';
+  initialFailures: TestFailure[];
+  remainingFailures: TestFailure[];
+  iterations: TestFixIteration[];
+  filesChanged: string[];
+  /** The end of the last run's output. */
+  output: string;
+  error?: {
+    code?: string;
+    message?: string;
+  };
+}
+
+/** What agent-driven tools need from the runtime: an agent run, as `SharedRuntimeService.runAgent` does it. */
+export type AgentRunner = (request: {
+  agentId: string;
+  task: string;
+  basePath: string;
+  provider?: string;
+  model?: string;
+  timeoutMs?: number;
+  sessionId?: string;
+  surface?: TraceSurface;
+  signal?: AbortSignal;
+}) => Promise<{
+  traceId: string;
+  success: boolean;
+  content: string;
+  permissions?: AgentPermissions;
+  approvals?: ApprovalGates;
+  error?: { code?: string; message?: string };
+}>;
+
+interface ProposedEdit {
+  file: string;
+  search: string;
+  replace: string;
+}
+
+const DEFAULT_AGENT_ID = 'bug-hunter';
+const DEFAULT_MAX_ITERATIONS = 3;
+const MAX_PROMPT_FAILURES = 5;
+const MAX_RELATED_SYMBOLS = 6;
+const MAX_SNIPPET_LINES = 60;
+const OUTPUT_TAIL_LINES = 80;
+const CONTEXT_KINDS = new Set<CodeSymbolKind>(['function', 'method', 'class', 'trait', 'record', 'module', 'namespace']);
+const EDIT_BLOCK_PATTERN = /^FILE:[ \t]*(.+?)[ \t]*\n<<<<<<< SEARCH\n([\s\S]*?)^=======\n([\s\S]*?)^>>>>>>> REPLACE[ \t]*$/gm;
+
+/**
+ * Runs the test command and, while it fails, hands the failing tests and the code around them to
+ * an agent, applies the edits it proposes and runs the tests again, up to `maxIterations` times.
+ * Edits stay in the working tree whatever the outcome, for the caller to review or revert.
+ */
+export async function runTestAndFix(request: RuntimeTestFixRequest, runAgent: AgentRunner): Promise<RuntimeTestFixReport> {
+  const agentId = request.agentId ?? DEFAULT_AGENT_ID;
+  const maxIterations = Math.max(1, request.maxIterations ?? DEFAULT_MAX_ITERATIONS);
+  const iterations: TestFixIteration[] = [];
+  const filesChanged = new Set<string>();
+  const runTests = () => runTestCommand(request.basePath, { command: request.command, signal: request.signal });
+
+  request.onProgress?.(0, 'Running tests');
+  let run = await runTests();
+  const initialFailures = parseTestFailures(run.output);
+  let failures = initialFailures;
+  const report = (stopReason: RuntimeTestFixReport['stopReason'], error?: RuntimeTestFixReport['error']): RuntimeTestFixReport => ({
+    success: run.passed,
+    command: run.command,
+    stopReason,
+    initialFailures,
+    remainingFailures: run.passed ? [] : failures,
+    iterations,
+    filesChanged: [...filesChanged].sort(),
+    output: tail(run.output),
+    ...(error !== undefined ? { error } : {}),
+  });
+
+  for (let iteration = 1; !run.passed && iteration <= maxIterations; iteration += 1) {
+    const progress = (step: number) => Math.round(((iteration - 1 + step) / maxIterations) * 100);
+    request.onProgress?.(progress(0.1), `Asking ${agentId} to fix ${describeFailures(failures)} (attempt ${iteration} of ${maxIterations})`);
+    const agentRun = await runAgent({
+      agentId,
+      task: await buildFixPrompt(request.basePath, run, failures),
+      basePath: request.basePath,
+      provider: request.provider,
+      model: request.model,
+      timeoutMs: request.timeoutMs,
+      sessionId: request.sessionId,
+      surface: request.surface,
+      signal: request.signal,
+    });
+    if (!agentRun.success) {
+      iterations.push({ iteration, failures, agentTraceId: agentRun.traceId, edits: [] });
+      return report('agent-failed', agentRun.error);
+    }
+
+    const { approvals } = agentRun;
+    const approveWrite = approvals === undefined ? undefined : async (file: string) => !gatesWrite(approvals, file) || createApprovalGate({
+      policy: await loadApprovalPolicy(request.basePath, request.approvalPolicy),
+      approver: request.approve,
+    })({ action: 'write', agentId, target: file, traceId: agentRun.traceId });
+    const edits = await applyProposedEdits(request.basePath, parseProposedEdits(agentRun.content), agentRun.permissions, approveWrite);
+    for (const edit of edits.filter((candidate) => candidate.applied)) {
+      filesChanged.add(edit.file);
+    }
+    if (!edits.some((edit) => edit.applied)) {
+      iterations.push({ iteration, failures, agentTraceId: agentRun.traceId, edits });
+      return report('no-edits');
+    }
+
+    request.onProgress?.(progress(0.8), 'Running tests again');
+    const attempted = failures;
+    run = await runTests();
+    failures = parseTestFailures(run.output);
+    iterations.push({ iteration, failures: attempted, agentTraceId: agentRun.traceId, edits, passedAfter: run.passed });
+  }
+
+  return report(run.passed ? 'passed' : 'max-iterations');
+}
+
+/**
+ * Reads `FILE:` / `<<<<<<< SEARCH` / `=======` / `>>>>>>> REPLACE` blocks out of an agent's reply.
+ * Text around the blocks, such as an explanation or code fences, is ignored.
+ */
+function parseProposedEdits(content: string): ProposedEdit[] {
+  const edits: ProposedEdit[] = [];
+  for (const match of content.replace(/\r\n/g, '\n').matchAll(EDIT_BLOCK_PATTERN)) {
+    edits.push({ file: match[1]!.replace(/^[`'"]|[`'"]$/g, ''), search: match[2]!, replace: match[3]! });
+  }
+  return edits;
+}
+
+/**
+ * Applies edits in order. Each SEARCH text must occur exactly once in its file; an empty one
+ * creates the file. Paths outside the workspace or inside `.git` are refused, and so are paths the
+ * agent's permissions do not cover and edits `approveWrite` does not allow.
+ */
+async function applyProposedEdits(
+  basePath: string,
+  edits: ProposedEdit[],
+  permissions: AgentPermissions | undefined,
+  approveWrite?: (file: string) => Promise<boolean>,
+): Promise<TestFixEdit[]> {
+  const refused = (file: string): TestFixEdit => ({ file, applied: false, reason: 'Outside the agent\'s write scope, and not approved.' });
+  const results: TestFixEdit[] = [];
+  for (const edit of edits) {
+    const target = resolve(basePath, edit.file);
+    const file = relative(basePath, target).split('\\').join('/');
+    if (file.startsWith('../') || file === '..' || file === '.git' || file.startsWith('.git/')) {
+      results.push({ file: edit.file, applied: false, reason: 'Outside the workspace, or inside .git.' });
+      continue;
+    }
+    if (!permitsPath(permissions, file)) {
+      results.push({ file, applied: false, reason: 'Outside the paths the agent may change.' });
+      continue;
+    }
+
+    let content: string | undefined;
+    try {
+      content = await readFile(target, 'utf8');
+    } catch {
+      content = undefined;
+    }
+    if (edit.search.length === 0) {
+      if (content !== undefined) {
+        results.push({ file, applied: false, reason: 'The file exists; an empty SEARCH only creates files.' });
+        continue;
+      }
+      if (approveWrite !== undefined && !(await approveWrite(file))) {
+        results.push(refused(file));
+        continue;
+      }
+      await mkdir(dirname(target), { recursive: true });
+      await writeFile(target, edit.replace, 'utf8');
+      results.push({ file, applied: true });
+      continue;
+    }
+    if (content === undefined) {
+      results.push({ file, applied: false, reason: 'No such file.' });
+      continue;
+    }
+
+    const crlf = content.includes('\r\n');
+    const search = crlf ? edit.search.replace(/\n/g, '\r\n') : edit.search;
+    const replace = crlf ? edit.replace.replace(/\n/g, '\r\n') : edit.replace;
+    const at = content.indexOf(search);
+    if (at === -1) {
+      results.push({ file, applied: false, reason: 'SEARCH text not found.' });
+      continue;
+    }
+    if (content.indexOf(search, at + 1) !== -1) {
+      results.push({ file, applied: false, reason: 'SEARCH text occurs more than once; include more context.' });
+      continue;
+    }
+    if (approveWrite !== undefined && !(await approveWrite(file))) {
+      results.push(refused(file));
+      continue;
+    }
+    await writeFile(target, `${content.slice(0, at)}${replace}${content.slice(at + search.length)}`, 'utf8');
+    results.push({ file, applied: true });
+  }
+  return results;
+}
+
+async function buildFixPrompt(basePath: string, run: TestImpactRun, failures: TestFailure[]): Promise<string> {
+  const sections = [
+    `The test command \`${run.command.join(' ')}\` failed with exit code ${run.exitCode}. Fix the code so the tests pass. Change a test only when the test itself is wrong.`,
+  ];
+  if (failures.length > 0) {
+    sections.push(`Failing tests:\n${failures.slice(0, MAX_PROMPT_FAILURES).map((failure) => {
+      const location = failure.file === undefined ? '' : `${failure.file}${failure.line === undefined ? '' : `:${failure.line}`} `;
+      return `- ${location}${failure.name}${failure.message.length > 0 ? `\n  ${failure.message.split('\n').join('\n  ')}` : ''}`;
+    }).join('\n')}`);
+  }
+  const context = await collectFailureContext(basePath, failures.slice(0, MAX_PROMPT_FAILURES));
+  if (context.length > 0) {
+    sections.push(`Relevant code:\n\n${context.join('\n\n')}`);
+  }
+  sections.push(`Test output (last ${OUTPUT_TAIL_LINES} lines):\n\`\`\`\n${tail(run.output)}\n\`\`\``);
+  sections.push([
+    'Reply with the edits as blocks like this, one per change, with paths relative to the workspace root:',
+    'FILE: path/to/file',
+    '<<<<<<< SEARCH',
+    'the exact lines to replace, copied from the file',
+    '=======',
+    'the lines to put in their place',
+    '>>>>>>> REPLACE',
+    'The SEARCH lines must occur exactly once in the file. An empty SEARCH creates a new file.',
+  ].join('\n'));
+  return sections.join('\n\n');
+}
+
+/**
+ * The test (or function) each failure happened in, read fresh from disk since earlier attempts
+ * may have moved it, and the workspace symbols those tests name.
+ */
+async function collectFailureContext(basePath: string, failures: TestFailure[]): Promise<string[]> {
+  const { registry } = await loadWorkspaceLanguageRegistry(basePath);
+  const parsed = new Map<string, Promise<CodeSymbol[]>>();
+  const symbolsOf = (file: string) => {
+    let symbols = parsed.get(file);
+    if (symbols === undefined) {
+      symbols = parseFileSymbols(basePath, file, registry);
+      parsed.set(file, symbols);
+    }
+    return symbols;
+  };
+
+  const sections: string[] = [];
+  const shown = new Set<string>();
+  const identifiers = new Set<string>();
+  for (const failure of failures) {
+    if (failure.file === undefined) {
+      continue;
+    }
+    const lines = await readLines(basePath, failure.file);
+    if (lines === undefined) {
+      continue;
+    }
+    const symbol = findFailingSymbol(await symbolsOf(failure.file), failure);
+    const start = symbol?.line ?? Math.max(1, (failure.line ?? 1) - 10);
+    const end = symbol?.endLine ?? (failure.line === undefined ? start + 20 : failure.line + 10);
+    const key = `${failure.file}:${start}`;
+    if (shown.has(key)) {
+      continue;
+    }
+    shown.add(key);
+    const snippet = lines.slice(start - 1, Math.min(end, start - 1 + MAX_SNIPPET_LINES)).join('\n');
+    sections.push(`${failure.file}:${start}${symbol === undefined ? '' : ` ${symbol.kind} ${symbol.name}`}\n\`\`\`\n${snippet}\n\`\`\``);
+    for (const identifier of snippet.match(/[A-Za-z_]\w{2,}/g) ?? []) {
+      identifiers.add(identifier);
+    }
+  }
+
+  const snapshot = await readCodeIndex(basePath);
+  const related = (snapshot?.files ?? [])
+    .filter((entry) => !isTestFile(entry.file))
+    .flatMap((entry) => entry.symbols.filter((symbol) => symbol.isTest !== true && CONTEXT_KINDS.has(symbol.kind) && identifiers.has(symbol.name)))
+    .slice(0, MAX_RELATED_SYMBOLS);
+  for (const indexed of related) {
+    // The index can be stale; the symbol's current lines come from parsing the file again.
+    const current = (await symbolsOf(indexed.file)).find((symbol) => symbol.name === indexed.name && symbol.kind === indexed.kind && symbol.container === indexed.container);
+    const lines = current === undefined ? undefined : await readLines(basePath, indexed.file);
+    if (current === undefined || lines === undefined || shown.has(`${indexed.file}:${current.line}`)) {
+      continue;
+    }
+    shown.add(`${indexed.file}:${current.line}`);
+    const snippet = lines.slice(current.line - 1, Math.min(current.endLine ?? current.line, current.line - 1 + MAX_SNIPPET_LINES)).join('\n');
+    sections.push(`${indexed.file}:${current.line} ${current.kind} ${current.container === undefined ? '' : `${current.container}::`}${current.name}\n\`\`\`\n${snippet}\n\`\`\``);
+  }
+  return sections;
+}
+
+// The innermost symbol around the failing line, else one named like the test's last segment.
+function findFailingSymbol(symbols: CodeSymbol[], failure: TestFailure): CodeSymbol | undefined {
+  const line = failure.line;
+  if (line !== undefined) {
+    const enclosing = symbols
+      .filter((symbol) => symbol.line <= line && (symbol.endLine ?? symbol.line) >= line)
+      .sort((left, right) => right.line - left.line)[0];
+    if (enclosing !== undefined) {
+      return enclosing;
+    }
+  }
+  const name = failure.name.split(/::|›|>|\./).map((segment) => segment.trim()).filter((segment) => segment.length > 0).pop();
+  return symbols.find((symbol) => symbol.name === name);
+}
+
+async function parseFileSymbols(basePath: string, file: string, registry: LanguageRegistry): Promise<CodeSymbol[]> {
+  return (await indexCodeFile(basePath, file, registry))?.symbols ?? [];
+}
+
+async function readLines(basePath: string, file: string): Promise<string[] | undefined> {
+  try {
+    return (await readFile(resolve(basePath, file), 'utf8')).split(/\r?\n/);
+  } catch {
+    return undefined;
+  }
+}
+
+function describeFailures(failures: TestFailure[]): string {
+  if (failures.length === 0) {
+    return 'the failing run';
+  }
+  return failures.length === 1 ? '1 failing test' : `${failures.length} failing tests`;
+}
+
+function tail(output: string): string {
+  return output.trimEnd().split(/\r?\n/).slice(-OUTPUT_TAIL_LINES).join('\n');
+}
@@ -0,0 +1,46 @@
+export const USAGE_GROUPINGS = ['agent', 'provider', 'project'];
+const UNATTRIBUTED_KEY = '(none)';
+/** Persists one usage record per provider call to the state store and totals them for reports. */
+export function createUsageTracker(config) {
+    return {
+        record(entry) {
+            return config.stateStore.recordUsage(entry);
+        },
+        async report(options = {}) {
+            const by = options.by ?? 'provider';
+            const records = await config.stateStore.listUsage({
+                since: options.since,
+                traceId: options.traceId,
+                project: options.project,
+            });
+            const grouped = new Map();
+            for (const record of records) {
+                const key = (by === 'agent' ? record.agentId : by === 'project' ? record.project : record.provider) ?? UNATTRIBUTED_KEY;
+                grouped.set(key, [...(grouped.get(key) ?? []), record]);
+            }
+            const groups = [...grouped.entries()]
+                .map(([key, entries]) => ({ key, ...totalUsage(entries) }))
+                .sort((left, right) => right.costUsd - left.costUsd || right.calls - left.calls || left.key.localeCompare(right.key));
+            return {
+                by,
+                ...(options.since !== undefined ? { since: options.since } : {}),
+                ...(options.traceId !== undefined ? { traceId: options.traceId } : {}),
+                groups,
+                total: totalUsage(records),
+            };
+        },
+    };
+}
+function totalUsage(records) {
+    const totals = records.reduce((sum, record) => ({
+        calls: sum.calls + 1,
+        failures: sum.failures + (record.success ? 0 : 1),
+        inputTokens: sum.inputTokens + record.inputTokens,
+        outputTokens: sum.outputTokens + record.outputTokens,
+        costUsd: sum.costUsd + (record.costUsd ?? 0),
+        unpricedCalls: sum.unpricedCalls + (record.costUsd === undefined ? 1 : 0),
+        latencyMs: sum.latencyMs + record.latencyMs,
+    }), { calls: 0, failures: 0, inputTokens: 0, outputTokens: 0, costUsd: 0, unpricedCalls: 0, latencyMs: 0 });
+    const { latencyMs, ...rest } = totals;
+    return { ...rest, averageLatencyMs: totals.calls === 0 ? 0 : Math.round(latencyMs / totals.calls) };
+}
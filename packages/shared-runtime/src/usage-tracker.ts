@@ -0,0 +1,94 @@
+import type { StateStore, UsageInput, UsageRecord } from '@defai.digital/state-store';
+
+export const USAGE_GROUPINGS = ['agent', 'provider', 'project'] as const;
+
+export type UsageGrouping = typeof USAGE_GROUPINGS[number];
+
+export interface UsageTotals {
+  calls: number;
+  failures: number;
+  inputTokens: number;
+  outputTokens: number;
+  /** USD of the calls whose model has a known price. */
+  costUsd: number;
+  /** Calls left out of `costUsd` because their model has no known price. */
+  unpricedCalls: number;
+  averageLatencyMs: number;
+}
+
+export interface UsageReportGroup extends UsageTotals {
+  /** The agent, provider or project; calls made without an agent are grouped under `(none)`. */
+  key: string;
+}
+
+export interface UsageReport {
+  by: UsageGrouping;
+  since?: string;
+  traceId?: string;
+  /** Most expensive first. */
+  groups: UsageReportGroup[];
+  total: UsageTotals;
+}
+
+export interface UsageReportOptions {
+  /** ISO timestamp; calls before it are left out. */
+  since?: string;
+  by?: UsageGrouping;
+  /** Only the calls of this run and the runs inside it. */
+  traceId?: string;
+  project?: string;
+}
+
+export interface UsageTracker {
+  record(entry: UsageInput): Promise<UsageRecord>;
+  report(options?: UsageReportOptions): Promise<UsageReport>;
+}
+
+const UNATTRIBUTED_KEY = '(none)';
+
+/** Persists one usage record per provider call to the state store and totals them for reports. */
+export function createUsageTracker(config: { stateStore: StateStore }): UsageTracker {
+  return {
+    record(entry) {
+      return config.stateStore.recordUsage(entry);
+    },
+
+    async report(options = {}) {
+      const by = options.by ?? 'provider';
+      const records = await config.stateStore.listUsage({
+        since: options.since,
+        traceId: options.traceId,
+        project: options.project,
+      });
+      const grouped = new Map<string, UsageRecord[]>();
+      for (const record of records) {
+        const key = (by === 'agent' ? record.agentId : by === 'project' ? record.project : record.provider) ?? UNATTRIBUTED_KEY;
+        grouped.set(key, [...(grouped.get(key) ?? []), record]);
+      }
+      const groups = [...grouped.entries()]
+        .map(([key, entries]) => ({ key, ...totalUsage(entries) }))
+        .sort((left, right) => right.costUsd - left.costUsd || right.calls - left.calls || left.key.localeCompare(right.key));
+      return {
+        by,
+        ...(options.since !== undefined ? { since: options.since } : {}),
+        ...(options.traceId !== undefined ? { traceId: options.traceId } : {}),
+        groups,
+        total: totalUsage(records),
+      };
+    },
+  };
+}
+
+function totalUsage(records: UsageRecord[]): UsageTotals {
+  const totals = records.reduce((sum, record) => ({
+    calls: sum.calls + 1,
+    failures: sum.failures + (record.success ? 0 : 1),
+    inputTokens: sum.inputTokens + record.inputTokens,
+    outputTokens: sum.outputTokens + record.outputTokens,
+    costUsd: sum.costUsd + (record.costUsd ?? 0),
+    unpricedCalls: sum.unpricedCalls + (record.costUsd === undefined ? 1 : 0),
+    latencyMs: sum.latencyMs + record.latencyMs,
+  }), { calls: 0, failures: 0, inputTokens: 0, outputTokens: 0, costUsd: 0, unpricedCalls: 0, latencyMs: 0 });
+  const { latencyMs, ...rest } = totals;
+  return { ...rest, averageLatencyMs: totals.calls === 0 ? 0 : Math.round(latencyMs / totals.calls) };
+}
@@ -0,0 +1,103 @@
+import { mkdir, readFile, rm, writeFile } from 'node:fs/promises';
+import { dirname, join } from 'node:path';
+import { snapshotWholeWorkspace, snapshotWorkspace } from './agent-permissions.js';
+import { unifiedDiff } from './session-transcript.js';
+const MAX_CHECKPOINT_FILE_BYTES = 256 * 1024;
+const MAX_CHECKPOINT_BYTES = 64 * 1024 * 1024;
+/**
+ * Takes a checkpoint of the workspace, or of `scope` in it. The content of files that have not
+ * changed since a `previous` checkpoint is taken from it rather than read again, so a checkpoint
+ * before each turn of a conversation costs little more than stamping the files. A `whole` one
+ * stamps every file `snapshotWholeWorkspace` does, and keeps the content only of those `keep` accepts.
+ */
+export async function createWorkspaceCheckpoint(basePath, options = {}) {
+    const stamps = options.whole === true ? await snapshotWholeWorkspace(basePath) : await snapshotWorkspace(basePath, options.scope);
+    const contents = new Map();
+    let total = 0;
+    for (const [file, stamp] of stamps) {
+        if (options.keep !== undefined && !options.keep(file)) {
+            continue;
+        }
+        const previous = options.previous;
+        const content = previous !== undefined && previous.stamps.get(file) === stamp
+            ? previous.contents.get(file)
+            : await readTextFile(join(basePath, file));
+        if (content !== undefined && total + content.length <= MAX_CHECKPOINT_BYTES) {
+            contents.set(file, content);
+            total += content.length;
+        }
+    }
+    return {
+        basePath,
+        ...(options.whole === true ? { whole: true } : options.scope !== undefined ? { scope: options.scope } : {}),
+        stamps,
+        contents,
+        takenAt: new Date().toISOString(),
+    };
+}
+/**
+ * The files created, changed or deleted since the checkpoint, with a diff of each text file whose
+ * content it kept. Whatever else changed the workspace since is listed too.
+ */
+export async function diffWorkspaceCheckpoint(checkpoint) {
+    const current = checkpoint.whole === true
+        ? await snapshotWholeWorkspace(checkpoint.basePath)
+        : await snapshotWorkspace(checkpoint.basePath, checkpoint.scope);
+    const changes = [];
+    for (const [file, stamp] of current) {
+        if (checkpoint.stamps.get(file) === stamp) {
+            continue;
+        }
+        const created = !checkpoint.stamps.has(file);
+        const before = created ? '' : checkpoint.contents.get(file);
+        const after = await readTextFile(join(checkpoint.basePath, file));
+        const diff = before === undefined || after === undefined ? undefined : unifiedDiff(file, before, after, { created });
+        // A file written back with the content it had is not a change.
+        if (!created && before !== undefined && after !== undefined && diff === undefined) {
+            continue;
+        }
+        changes.push({ file, change: created ? 'created' : 'modified', ...(diff !== undefined ? { diff } : {}) });
+    }
+    for (const file of checkpoint.stamps.keys()) {
+        if (!current.has(file)) {
+            const before = checkpoint.contents.get(file);
+            const diff = before === undefined ? undefined : unifiedDiff(file, before, '', { deleted: true });
+            changes.push({ file, change: 'deleted', ...(diff !== undefined ? { diff } : {}) });
+        }
+    }
+    return changes.sort((left, right) => left.file.localeCompare(right.file));
+}
+/**
+ * Puts the workspace, or only its `files`, back as it was at the checkpoint: changed and deleted
+ * files get their content back and files created since are removed. Files whose content the
+ * checkpoint did not keep are skipped.
+ */
+export async function restoreWorkspaceCheckpoint(checkpoint, options = {}) {
+    const files = options.files === undefined ? undefined : new Set(options.files);
+    const restored = [];
+    const skipped = [];
+    for (const change of await diffWorkspaceCheckpoint(checkpoint)) {
+        if (files !== undefined && !files.has(change.file)) {
+            continue;
+        }
+        const path = join(checkpoint.basePath, change.file);
+        if (change.change === 'created') {
+            await rm(path, { force: true });
+            restored.push(change.file);
+            continue;
+        }
+        const content = checkpoint.contents.get(change.file);
+        if (content === undefined) {
+            skipped.push(change.file);
+            continue;
+        }
+        await mkdir(dirname(path), { recursive: true });
+        await writeFile(path, content, 'utf8');
+        restored.push(change.file);
+    }
+    return { restored, skipped };
+}
+async function readTextFile(path) {
+    const content = await readFile(path).catch(() => undefined);
+    return content === undefined || content.length > MAX_CHECKPOINT_FILE_BYTES || content.includes(0) ? undefined : content.toString('utf8');
+}
@@ -0,0 +1,2351 @@
+import { mkdirSync } from 'node:fs';
+import { mkdir, readFile, readdir, rm, writeFile } from 'node:fs/promises';
+import { join } from 'node:path';
+import { execFile } from 'node:child_process';
+import { promisify } from 'node:util';
+import { afterEach, describe, expect, it } from 'vitest';
+import { chunkCodeIndex, createEmbedder, createLanguageRegistry, createPositionMapper, createQueryExtractor, createSharedRuntimeService, GRAMMAR_RUNTIME_ENV_VAR, isCfgActive, loadWorkspaceLanguageRegistry, parseCodeSource, parseSymbolSearchQuery, parseTestFailures, requiredLiterals, } from '../src/index.js';
+import { answerProjectQuestion } from '../src/project-query.js';
+import { extractCSharpSymbols } from '../src/code-intel/csharp.js';
+import { extractDockerfileSymbols } from '../src/code-intel/dockerfile.js';
+import { extractHclSymbols } from '../src/code-intel/hcl.js';
+import { extractPhpSymbols } from '../src/code-intel/php.js';
+import { extractRubySymbols } from '../src/code-intel/ruby.js';
+import { extractRustSymbols } from '../src/code-intel/rust.js';
+import { extractShellSymbols } from '../src/code-intel/shell.js';
+import { isTestFile, tagTestSymbols } from '../src/code-intel/test-detection.js';
+const execFileAsync = promisify(execFile);
+function createTempDir() {
+    const dir = join(process.cwd(), '.tmp', `code-intel-${Date.now()}-${Math.random().toString(16).slice(2, 8)}`);
+    mkdirSync(dir, { recursive: true });
+    return dir;
+}
+async function waitUntil(condition, timeoutMs = 5_000) {
+    const deadline = Date.now() + timeoutMs;
+    while (!await condition()) {
+        if (Date.now() > deadline) {
+            throw new Error('Timed out waiting for the index watcher.');
+        }
+        await new Promise((resolve) => setTimeout(resolve, 25));
+    }
+}
+const SAMPLE_DOCKERFILE = [
+    '# syntax=docker/dockerfile:1',
+    'FROM --platform=linux/amd64 node:22-alpine AS builder',
+    'WORKDIR /app',
+    'COPY package.json package-lock.json ./',
+    'RUN npm ci \\',
+    '  && npm run build',
+    '',
+    'FROM nginx:1.27',
+    'COPY --from=builder /app/dist /usr/share/nginx/html',
+    'ADD ["conf/nginx.conf", "/etc/nginx/nginx.conf"]',
+    'EXPOSE 80 443/udp',
+].join('\n');
+const SAMPLE_SCRIPT = [
+    '#!/usr/bin/env bash',
+    'set -euo pipefail',
+    'export REGISTRY="ghcr.io/acme" IMAGE_TAG=latest',
+    'declare -x DEPLOY_ENV=staging',
+    '',
+    'build_image() {',
+    '  docker build -t "$REGISTRY/app:$IMAGE_TAG" .',
+    '}',
+    '',
+    'function push_image {',
+    '  cat <<EOF',
+    'fake() {',
+    'EOF',
+    '}',
+].join('\n');
+const SAMPLE_TERRAFORM = [
+    'variable "region" {',
+    '  type        = string',
+    '  description = "AWS region" # primary',
+    '}',
+    '',
+    'variable "tags" {',
+    '  type = object({',
+    '    team = string',
+    '    env  = optional(string)',
+    '  })',
+    '  default = {}',
+    '}',
+    '',
+    '/* resource "aws_s3_bucket" "ignored" {} */',
+    'resource "aws_s3_bucket" "logs" {',
+    '  bucket = "${var.region}-logs"',
+    '  policy = <<EOT',
+    '  { "Statement": [ }',
+    'EOT',
+    '  lifecycle {',
+    '    prevent_destroy = true',
+    '  }',
+    '}',
+    '',
+    'data "aws_ami" "ubuntu" {',
+    '  most_recent = true',
+    '}',
+    '',
+    'module "vpc" {',
+    '  source  = "git::https://example.com/vpc.git?ref=v1"',
+    '  version = "5.0.0"',
+    '}',
+    '',
+    'output "bucket_arn" {',
+    '  value     = aws_s3_bucket.logs.arn',
+    '  sensitive = true',
+    '}',
+].join('\n');
+const SAMPLE_RUBY = [
+    'module Billing',
+    '  class Invoice < ApplicationRecord',
+    '    attr_reader :total, :currency',
+    '    attr_accessor :notes',
+    '',
+    '    SQL = <<~SQL',
+    '      def not_a_method',
+    '    SQL',
+    '',
+    '    def self.build(attrs) = new(attrs)',
+    '',
+    '    def paid?',
+    '      if settled_at',
+    '        true',
+    '      else',
+    '        payments.any? do |payment|',
+    '          payment.amount > 0 # end',
+    '        end',
+    '      end',
+    '    end',
+    '',
+    '    class << self',
+    '      def overdue',
+    '        where(status: "late")',
+    '      end',
+    '    end',
+    '',
+    '    private',
+    '',
+    '    def recalculate; end',
+    '  end',
+    'end',
+].join('\n');
+const SAMPLE_PHP = [
+    '<?php',
+    '',
+    'namespace App\\Models;',
+    '',
+    'use Illuminate\\Database\\Eloquent\\Model;',
+    '',
+    '/* class Ignored {} */',
+    'final class Invoice extends Model implements HasTotals, Auditable',
+    '{',
+    '    use Billable;',
+    '',
+    '    public static function findOverdue(): array',
+    '    {',
+    '        return array_filter([], function ($invoice) { return "}"; });',
+    '    }',
+    '',
+    '    protected function total(): int',
+    '    {',
+    '        $sql = <<<SQL',
+    '        SELECT { FROM invoices',
+    '        SQL;',
+    '        return 0;',
+    '    }',
+    '}',
+    '',
+    'trait Billable',
+    '{',
+    '    abstract public function currency(): string;',
+    '}',
+    '',
+    'function format_money(int $cents): string',
+    '{',
+    '    return number_format($cents / 100, 2);',
+    '}',
+].join('\n');
+const SAMPLE_CSHARP_ORDER = [
+    'using System.Threading.Tasks;',
+    '',
+    'namespace Shop.Orders;',
+    '',
+    '/// <summary>Order aggregate.</summary>',
+    '[Serializable]',
+    'public partial class Order : Entity, IAuditable',
+    '{',
+    '    private readonly List<Line> _lines = new();',
+    '    public Guid Id { get; init; }',
+    '    public decimal Total => _lines.Sum(line => line.Amount);',
+    '',
+    '    public Order(Guid id) : base(id)',
+    '    {',
+    '        var label = "{ not a block";',
+    '    }',
+    '',
+    '    [HttpPost]',
+    '    public async Task<bool> SubmitAsync(CancellationToken token = default)',
+    '    {',
+    '        if (Total > 0) { await Task.Delay(1, token); }',
+    '        return true;',
+    '    }',
+    '}',
+    '',
+    'public record OrderLine(string Sku, int Quantity = 1);',
+    '',
+    'internal interface IAuditable',
+    '{',
+    '    void Audit(string actor);',
+    '}',
+].join('\n');
+const SAMPLE_RUST = [
+    '/// Shared settings.',
+    '#[derive(Debug, Clone)]',
+    'pub struct Config {',
+    '    pub name: String,',
+    '    retries: HashMap<String, u32>,',
+    '}',
+    '',
+    'pub enum Mode { Fast, Safe }',
+    '',
+    'pub trait Store {',
+    '    type Key;',
+    '    fn get(&self, key: &Self::Key) -> Option<String>;',
+    '}',
+    '',
+    'impl Config {',
+    '    pub const fn new() -> Self {',
+    '        let label = "fn not_an_item() {";',
+    '        Config { name: String::new(), retries: HashMap::new() }',
+    '    }',
+    '}',
+    '',
+    'pub mod io {',
+    '    pub type Bytes = Vec<u8>;',
+    '    pub static LIMIT: usize = 64;',
+    '    macro_rules! skip { () => { fn hidden() {} }; }',
+    '    pub async fn read(path: &str) -> Bytes { vec![] }',
+    '}',
+    '',
+    'const MAX: u32 = 3;',
+].join('\n');
+const SAMPLE_CSHARP_PARTIAL = [
+    'namespace Shop.Orders',
+    '{',
+    '    public partial class Order : IValidatable',
+    '    {',
+    '        public static Order Empty() => new(Guid.Empty);',
+    '    }',
+    '}',
+].join('\n');
+describe('code intelligence extractors', () => {
+    const tempDirs = [];
+    afterEach(async () => {
+        await Promise.all(tempDirs.splice(0).map((tempDir) => rm(tempDir, { recursive: true, force: true })));
+    });
+    it('extracts stages, base images, exposed ports, and copied paths from Dockerfiles', () => {
+        const symbols = extractDockerfileSymbols(SAMPLE_DOCKERFILE, 'Dockerfile');
+        expect(symbols.filter((symbol) => symbol.kind === 'stage').map((symbol) => symbol.name)).toEqual(['builder', 'stage-1']);
+        expect(symbols.filter((symbol) => symbol.kind === 'base-image')).toMatchObject([
+            { name: 'node:22-alpine', line: 2, container: 'builder' },
+            { name: 'nginx:1.27', line: 8, container: 'stage-1' },
+        ]);
+        expect(symbols.filter((symbol) => symbol.kind === 'port')).toMatchObject([
+            { name: '80', attributes: { protocol: 'tcp' } },
+            { name: '443', attributes: { protocol: 'udp' } },
+        ]);
+        expect(symbols.filter((symbol) => symbol.kind === 'copy')).toMatchObject([
+            { name: 'package.json', container: 'builder', attributes: { destination: './' } },
+            { name: 'package-lock.json', container: 'builder', attributes: { destination: './' } },
+            { name: '/app/dist', container: 'stage-1', attributes: { from: 'builder', destination: '/usr/share/nginx/html' } },
+            { name: 'conf/nginx.conf', attributes: { instruction: 'add', destination: '/etc/nginx/nginx.conf' } },
+        ]);
+    });
+    it('extracts functions and exported variables from shell scripts', () => {
+        const symbols = extractShellSymbols(SAMPLE_SCRIPT, 'scripts/deploy.sh');
+        expect(symbols.filter((symbol) => symbol.kind === 'function').map((symbol) => [symbol.name, symbol.line])).toEqual([
+            ['build_image', 6],
+            ['push_image', 10],
+        ]);
+        expect(symbols.filter((symbol) => symbol.kind === 'variable').map((symbol) => symbol.name)).toEqual([
+            'REGISTRY',
+            'IMAGE_TAG',
+            'DEPLOY_ENV',
+        ]);
+    });
+    it('extracts Terraform resources, modules, variables, and outputs with their types', () => {
+        const symbols = extractHclSymbols(SAMPLE_TERRAFORM, 'infra/main.tf');
+        expect(symbols.map((symbol) => [symbol.kind, symbol.name, symbol.line, symbol.endLine])).toEqual([
+            ['variable', 'region', 1, 4],
+            ['variable', 'tags', 6, 12],
+            ['resource', 'aws_s3_bucket.logs', 15, 23],
+            ['data', 'aws_ami.ubuntu', 25, 27],
+            ['module', 'vpc', 29, 32],
+            ['output', 'bucket_arn', 34, 37],
+        ]);
+        expect(symbols[0]?.attributes).toEqual({ type: 'string', description: 'AWS region', address: 'var.region' });
+        expect(symbols[1]?.attributes).toMatchObject({ type: 'object({ team = string env = optional(string) })', default: '{}' });
+        expect(symbols[2]?.attributes).toEqual({ type: 'aws_s3_bucket', address: 'aws_s3_bucket.logs' });
+        expect(symbols[3]?.attributes).toMatchObject({ address: 'data.aws_ami.ubuntu' });
+        expect(symbols[4]?.attributes).toEqual({
+            source: 'git::https://example.com/vpc.git?ref=v1',
+            version: '5.0.0',
+            address: 'module.vpc',
+        });
+        expect(symbols[5]?.attributes).toEqual({ value: 'aws_s3_bucket.logs.arn', sensitive: 'true' });
+    });
+    it('extracts Ruby classes, modules, methods, and attribute accessors', () => {
+        const symbols = extractRubySymbols(SAMPLE_RUBY, 'app/models/invoice.rb');
+        expect(symbols.map((symbol) => [symbol.kind, symbol.name, symbol.container, symbol.line, symbol.endLine])).toEqual([
+            ['module', 'Billing', undefined, 1, 32],
+            ['class', 'Invoice', 'Billing', 2, 31],
+            ['attribute', 'total', 'Billing::Invoice', 3, undefined],
+            ['attribute', 'currency', 'Billing::Invoice', 3, undefined],
+            ['attribute', 'notes', 'Billing::Invoice', 4, undefined],
+            ['method', 'build', 'Billing::Invoice', 10, 10],
+            ['method', 'paid?', 'Billing::Invoice', 12, 20],
+            ['method', 'overdue', 'Billing::Invoice', 23, 25],
+            ['method', 'recalculate', 'Billing::Invoice', 30, 30],
+        ]);
+        expect(symbols[1]?.attributes).toEqual({ superclass: 'ApplicationRecord' });
+        expect(symbols[4]?.attributes).toEqual({ access: 'accessor' });
+        expect(symbols[5]?.attributes).toEqual({ scope: 'class' });
+        expect(symbols[7]?.attributes).toEqual({ scope: 'class' });
+        expect(symbols[8]?.attributes).toEqual({ visibility: 'private' });
+    });
+    it('extracts PHP namespaces, classes, traits, methods, and functions', () => {
+        const symbols = extractPhpSymbols(SAMPLE_PHP, 'app/Models/Invoice.php');
+        expect(symbols.map((symbol) => [symbol.kind, symbol.name, symbol.container, symbol.line, symbol.endLine])).toEqual([
+            ['namespace', 'App\\Models', undefined, 3, undefined],
+            ['class', 'Invoice', 'App\\Models', 8, 24],
+            ['method', 'findOverdue', 'App\\Models\\Invoice', 12, 15],
+            ['method', 'total', 'App\\Models\\Invoice', 17, 23],
+            ['trait', 'Billable', 'App\\Models', 26, 29],
+            ['method', 'currency', 'App\\Models\\Billable', 28, 28],
+            ['function', 'format_money', 'App\\Models', 31, 34],
+        ]);
+        expect(symbols[1]?.attributes).toEqual({
+            modifiers: 'final',
+            extends: 'Model',
+            implements: 'HasTotals, Auditable',
+        });
+        expect(symbols[2]?.attributes).toEqual({ visibility: 'public', static: 'true' });
+        expect(symbols[3]?.attributes).toEqual({ visibility: 'protected' });
+        expect(symbols[5]?.attributes).toEqual({ visibility: 'public', abstract: 'true' });
+    });
+    it('extracts C# namespaces, types, records, properties, and async methods', () => {
+        const symbols = extractCSharpSymbols(SAMPLE_CSHARP_ORDER, 'src/Order.cs');
+        expect(symbols.map((symbol) => [symbol.kind, symbol.name, symbol.container, symbol.line, symbol.endLine])).toEqual([
+            ['namespace', 'Shop.Orders', undefined, 3, undefined],
+            ['class', 'Order', 'Shop.Orders', 7, 24],
+            ['property', 'Id', 'Shop.Orders.Order', 10, 10],
+            ['property', 'Total', 'Shop.Orders.Order', 11, 11],
+            ['method', 'Order', 'Shop.Orders.Order', 13, 16],
+            ['method', 'SubmitAsync', 'Shop.Orders.Order', 19, 23],
+            ['record', 'OrderLine', 'Shop.Orders', 26, 26],
+            ['property', 'Sku', 'Shop.Orders.OrderLine', 26, undefined],
+            ['property', 'Quantity', 'Shop.Orders.OrderLine', 26, undefined],
+            ['class', 'IAuditable', 'Shop.Orders', 28, 31],
+            ['method', 'Audit', 'Shop.Orders.IAuditable', 30, 30],
+        ]);
+        expect(symbols[1]?.attributes).toEqual({ visibility: 'public', partial: 'true', bases: 'Entity, IAuditable' });
+        expect(symbols[2]?.attributes).toEqual({ type: 'Guid', visibility: 'public' });
+        expect(symbols[4]?.attributes).toEqual({ visibility: 'public', role: 'constructor' });
+        expect(symbols[5]?.attributes).toEqual({ visibility: 'public', returns: 'Task<bool>', async: 'true' });
+        expect(symbols[8]?.attributes).toEqual({ type: 'int', positional: 'true' });
+        expect(symbols[9]?.attributes).toEqual({ declaration: 'interface', visibility: 'internal' });
+    });
+    it('extracts Rust structs, fields, enums, traits, methods, modules, aliases, and constants', () => {
+        const symbols = extractRustSymbols(SAMPLE_RUST, 'src/lib.rs');
+        expect(symbols.map((symbol) => [symbol.kind, symbol.name, symbol.container, symbol.line, symbol.endLine])).toEqual([
+            ['class', 'Config', undefined, 3, 6],
+            ['property', 'name', 'Config', 4, 4],
+            ['property', 'retries', 'Config', 5, 5],
+            ['class', 'Mode', undefined, 8, 8],
+            ['trait', 'Store', undefined, 10, 13],
+            ['method', 'get', 'Store', 12, 12],
+            ['method', 'new', undefined, 16, 19],
+            ['module', 'io', undefined, 22, 27],
+            ['class', 'Bytes', 'io', 23, 23],
+            ['variable', 'LIMIT', 'io', 24, 24],
+            ['function', 'read', 'io', 26, 26],
+            ['variable', 'MAX', undefined, 29, 29],
+        ]);
+        expect(symbols[0]?.attributes).toEqual({ declaration: 'struct' });
+        expect(symbols[8]?.attributes).toEqual({ declaration: 'type' });
+        expect(symbols[9]?.attributes).toEqual({ declaration: 'static' });
+    });
+    it('merges C# partial classes declared across files into one indexed symbol', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'Order.cs'), SAMPLE_CSHARP_ORDER, 'utf8');
+        await writeFile(join(tempDir, 'src', 'Order.Factory.cs'), SAMPLE_CSHARP_PARTIAL, 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await runtime.indexCode();
+        const classes = await runtime.searchCodeSymbols({ query: 'order', kind: 'class', language: 'csharp' });
+        expect(classes).toHaveLength(1);
+        expect(classes[0]).toMatchObject({
+            name: 'Order',
+            file: 'src/Order.cs',
+            container: 'Shop.Orders',
+            attributes: { partial: 'true', bases: 'Entity, IAuditable, IValidatable' },
+            locations: [
+                { file: 'src/Order.cs', line: 7, endLine: 24 },
+                { file: 'src/Order.Factory.cs', line: 3, endLine: 6 },
+            ],
+        });
+        const scoped = await runtime.searchCodeSymbols({ kind: 'class', file: 'src/Order.cs' });
+        expect(scoped.map((symbol) => symbol.name)).toEqual(['Order', 'IAuditable']);
+        const members = await runtime.searchCodeSymbols({ kind: 'method', query: 'empty' });
+        expect(members).toMatchObject([{ name: 'Empty', container: 'Shop.Orders.Order', attributes: { static: 'true' } }]);
+    });
+    it('maps tree-sitter query captures from a registered grammar to nested symbols', () => {
+        const source = 'const Vec = struct {\n  len: usize,\n  fn push() void {}\n};\nfn main() void {}\n';
+        const node = (text, line, endLine = line) => {
+            const startIndex = source.indexOf(text);
+            return { text, startIndex, endIndex: startIndex + text.length, startPosition: { row: line - 1 }, endPosition: { row: endLine - 1 } };
+        };
+        const body = source.slice(0, source.indexOf('};') + 2);
+        const extractor = createQueryExtractor({
+            language: 'zig',
+            extensions: ['zig'],
+            captures: { 'definition.container': 'class' },
+            parser: { parse: () => ({ rootNode: node(source, 1, 5) }) },
+            query: {
+                matches: () => [
+                    { captures: [{ name: 'definition.container', node: node(body, 1, 4) }, { name: 'name', node: node('Vec', 1) }] },
+                    { captures: [{ name: 'definition.field', node: node('len: usize', 2) }, { name: 'name', node: node('len', 2) }] },
+                    { captures: [{ name: 'definition.function', node: node('fn push() void {}', 3) }, { name: 'name', node: node('push', 3) }] },
+                    { captures: [{ name: 'definition.function', node: node('fn main() void {}', 5) }, { name: 'name', node: node('main', 5) }] },
+                    { captures: [{ name: 'reference.call', node: node('main', 5) }, { name: 'name', node: node('main', 5) }] },
+                ],
+            },
+        });
+        const registry = createLanguageRegistry();
+        registry.register(extractor);
+        expect(registry.detect('src/vec.zig')?.language).toBe('zig');
+        expect(registry.detect('Dockerfile')?.language).toBe('dockerfile');
+        expect(extractor.extract(source, 'src/vec.zig')).toMatchObject([
+            { name: 'Vec', kind: 'class', language: 'zig', line: 1, endLine: 4 },
+            { name: 'len', kind: 'property', container: 'Vec', line: 2 },
+            { name: 'push', kind: 'function', container: 'Vec', line: 3 },
+            { name: 'main', kind: 'function', line: 5 },
+        ]);
+        expect(extractor.extract(source, 'src/vec.zig')[3]?.container).toBeUndefined();
+        expect(() => createQueryExtractor({
+            language: 'zig',
+            extensions: ['.zig'],
+            captures: { 'definition.macro': 'macro' },
+            parser: { parse: () => ({ rootNode: node(source, 1) }) },
+            query: { matches: () => [] },
+        })).toThrow('unknown symbol kind "macro"');
+        expect(registry.unregister('zig')).toBe(true);
+        expect(registry.detect('src/vec.zig')).toBeUndefined();
+    });
+    it('reports grammars from workspace config that cannot be loaded without failing the index', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, '.automatosx'), { recursive: true });
+        await writeFile(join(tempDir, 'Dockerfile'), SAMPLE_DOCKERFILE, 'utf8');
+        await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({
+            codeIntel: {
+                grammars: [{ language: 'nim', extensions: ['.nim'], grammar: 'grammars/tree-sitter-nim.node', query: 'grammars/missing-tags.scm' }],
+            },
+        }), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const result = await runtime.indexCode();
+        expect(result.languages).toEqual({ dockerfile: 1 });
+        expect(result.warnings).toHaveLength(1);
+        expect(result.warnings?.[0]).toContain('Grammar "nim" was not loaded');
+    });
+    it('refuses native grammars under the wasm grammar runtime but still routes .wasm grammars to the WASM loader', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, '.automatosx'), { recursive: true });
+        await mkdir(join(tempDir, 'grammars'), { recursive: true });
+        await writeFile(join(tempDir, 'grammars', 'tags.scm'), '(function_declaration name: (identifier) @name) @definition.function\n', 'utf8');
+        await writeFile(join(tempDir, 'grammars', 'tree-sitter-zig.wasm'), new Uint8Array([0, 97, 115, 109]));
+        await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({
+            codeIntel: {
+                grammarRuntime: 'wasm',
+                grammars: [
+                    { language: 'nim', extensions: ['.nim'], grammar: 'grammars/tree-sitter-nim.node', query: 'grammars/tags.scm' },
+                    { language: 'zig', extensions: ['.zig'], grammar: 'grammars/tree-sitter-zig.wasm', query: 'grammars/tags.scm' },
+                ],
+            },
+        }), 'utf8');
+        const sandboxed = await loadWorkspaceLanguageRegistry(tempDir, {});
+        expect(sandboxed.warnings).toHaveLength(2);
+        expect(sandboxed.warnings[0]).toContain('Grammar "nim" was not loaded: Native grammars are disabled');
+        expect(sandboxed.warnings[1]).toContain('Grammar "zig" was not loaded');
+        expect(sandboxed.warnings[1]).not.toContain('Native grammars are disabled');
+        await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({
+            codeIntel: {
+                grammars: [{ language: 'nim', extensions: ['.nim'], grammar: 'grammars/tree-sitter-nim.node', query: 'grammars/tags.scm' }],
+            },
+        }), 'utf8');
+        const fromEnv = await loadWorkspaceLanguageRegistry(tempDir, { [GRAMMAR_RUNTIME_ENV_VAR]: 'wasm' });
+        expect(fromEnv.warnings[0]).toContain('Native grammars are disabled');
+    });
+    it('tags test code by file convention, test annotations, and test base classes', async () => {
+        expect(['pkg/server_test.go', 'tests/test_api.py', 'src/app.spec.ts', 'spec/user_spec.rb', 'UserServiceTests.cs', 'main.tftest.hcl']
+            .filter((file) => isTestFile(file))).toHaveLength(6);
+        expect(['pkg/server.go', 'src/contest.py', 'src/latest.ts', 'Testing.cs'].some((file) => isTestFile(file))).toBe(false);
+        const csharp = parseCodeSource([
+            'namespace Acme.Tests;',
+            '',
+            'public class CalculatorSpec',
+            '{',
+            '    [Fact]',
+            '    public void Adds() {}',
+            '',
+            '    [Theory, InlineData(1)]',
+            '    public void Subtracts(int value) {}',
+            '',
+            '    private int Seed() { return 1; }',
+            '}',
+            '',
+            'public class Calculator',
+            '{',
+            '    public int Add(int left, int right) { return left + right; }',
+            '}',
+        ].join('\n'), { language: 'csharp', file: 'src/Calculator.cs' });
+        expect(csharp.symbols.filter((symbol) => symbol.isTest === true).map((symbol) => symbol.name))
+            .toEqual(['CalculatorSpec', 'Adds', 'Subtracts', 'Seed']);
+        const php = parseCodeSource([
+            '<?php',
+            'namespace App\\Tests;',
+            'final class CartTest extends \\PHPUnit\\Framework\\TestCase',
+            '{',
+            '    public function testTotals(): void {}',
+            '}',
+            'final class Cart',
+            '{',
+            '    public function testable(): bool { return true; }',
+            '}',
+        ].join('\n'), { language: 'php' });
+        expect(php.symbols.filter((symbol) => symbol.isTest === true).map((symbol) => symbol.name)).toEqual(['CartTest', 'testTotals']);
+        const ruby = parseCodeSource([
+            'class CartTest < Minitest::Test',
+            '  def test_totals',
+            '  end',
+            'end',
+            'class Cart',
+            '  def total',
+            '  end',
+            'end',
+        ].join('\n'), { language: 'ruby' });
+        expect(ruby.symbols.filter((symbol) => symbol.isTest === true).map((symbol) => symbol.name)).toEqual(['CartTest', 'test_totals']);
+        const rust = tagTestSymbols([
+            { name: 'parse', kind: 'function', language: 'rust', file: 'src/lib.rs', line: 1 },
+            { name: 'tests', kind: 'module', language: 'rust', file: 'src/lib.rs', line: 4 },
+            { name: 'helper', kind: 'function', language: 'rust', file: 'src/lib.rs', line: 5, container: 'tests' },
+            { name: 'parses', kind: 'function', language: 'rust', file: 'src/lib.rs', line: 7, container: 'tests' },
+        ], 'src/lib.rs', 'fn parse() {}\n\n#[cfg(test)]\nmod tests {\n    fn helper() {}\n    #[test]\n    fn parses() {}\n}\n');
+        expect(rust.map((symbol) => symbol.isTest)).toEqual([undefined, true, true, true]);
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'spec'), { recursive: true });
+        await writeFile(join(tempDir, 'cart.rb'), 'class Cart\n  def total\n  end\nend\n', 'utf8');
+        await writeFile(join(tempDir, 'spec', 'cart_spec.rb'), 'def build_cart\nend\n', 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await runtime.indexCode();
+        expect((await runtime.searchCodeSymbols({ tests: 'only' })).map((symbol) => symbol.name)).toEqual(['build_cart']);
+        expect((await runtime.searchCodeSymbols({ tests: 'exclude' })).map((symbol) => symbol.name)).toEqual(['Cart', 'total']);
+    });
+    it('tags async functions and the concurrency primitives each function uses', async () => {
+        const rust = [
+            'pub struct ThreadSafe<T>(Arc<Mutex<T>>);',
+            'impl<T> ThreadSafe<Arc<T>> {',
+            '    pub fn share(inner: ThreadSafe<Arc<T>>) -> Self {',
+            '        inner',
+            '    }',
+            '    pub async fn drain(&self) {',
+            '        let (tx, mut rx) = tokio::sync::mpsc::channel(8);',
+            '        tokio::spawn(async move { tx.send(1).await });',
+            '        let _guard = self.0.lock().unwrap(); // not an RwLock',
+            '    }',
+            '}',
+            'fn plain() -> &\'static str {',
+            '    "a Mutex in a string"',
+            '}',
+        ].join('\n');
+        const parsed = parseCodeSource(rust, { file: 'src/sync.rs' });
+        expect(parsed.symbols.map((symbol) => [symbol.name, symbol.isAsync, symbol.concurrency])).toEqual([
+            ['ThreadSafe', undefined, undefined],
+            ['share', undefined, ['Arc']],
+            ['drain', true, ['channel', 'spawn', 'lock']],
+            ['plain', undefined, undefined],
+        ]);
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await writeFile(join(tempDir, 'Worker.cs'), [
+            'namespace Jobs;',
+            'public class Worker',
+            '{',
+            '    private readonly SemaphoreSlim _gate = new(1);',
+            '    public async Task RunAsync()',
+            '    {',
+            '        await _gate.WaitAsync();',
+            '        await Task.Run(() => Tick());',
+            '    }',
+            '    public void Tick()',
+            '    {',
+            '        lock (_sync) { _count++; }',
+            '    }',
+            '    public int Count() => _count;',
+            '}',
+            '',
+        ].join('\n'), 'utf8');
+        await writeFile(join(tempDir, 'pool.rb'), 'class Pool\n  def start\n    @lock = Mutex.new\n    Thread.new { work }\n  end\nend\n', 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await runtime.indexCode();
+        const concurrent = await runtime.searchCodeSymbols({ concurrent: true });
+        expect(concurrent.map((symbol) => [symbol.name, symbol.isAsync, symbol.concurrency])).toEqual([
+            ['start', undefined, ['Mutex', 'spawn']],
+            ['RunAsync', true, ['spawn']],
+            ['Tick', undefined, ['lock']],
+        ]);
+        const injected = await runtime.injectAbilities({ task: 'Check the async workers for lock ordering and concurrency bugs', maxAbilities: 1 });
+        expect(injected.abilities.map((ability) => ability.abilityId)).toEqual(['concurrency-review']);
+    });
+    it('summarizes receivers, parameter borrows, and returned lifetimes of Rust functions', () => {
+        const rust = [
+            'pub struct DataProcessor<\'a> {',
+            '    data: &\'a [u8],',
+            '}',
+            'impl<\'a> DataProcessor<\'a> {',
+            '    pub fn new(data: &\'a [u8]) -> Self {',
+            '        DataProcessor { data }',
+            '    }',
+            '    pub fn window(&self, start: usize) -> &\'a [u8] {',
+            '        &self.data[start..]',
+            '    }',
+            '    pub fn first(&self) -> Option<&u8> {',
+            '        self.data.first()',
+            '    }',
+            '    pub fn reset(&mut self, data: &\'a [u8]) {',
+            '        self.data = data;',
+            '    }',
+            '    pub fn into_inner(self) -> &\'a [u8] {',
+            '        self.data',
+            '    }',
+            '}',
+            'pub fn longest<\'x, \'y: \'x>(left: &\'x str, right: &\'y str) -> &\'x str {',
+            '    if left.len() > right.len() { left } else { right }',
+            '}',
+            'fn label(name: &str, mut out: String) -> &str {',
+            '    name',
+            '}',
+            'fn collect<T>(',
+            '    items: &mut Vec<T>, // grows (in place)',
+            '    extra: Vec<T>,',
+            ') -> usize',
+            'where',
+            '    T: Clone,',
+            '{',
+            '    0',
+            '}',
+        ].join('\n');
+        const parsed = parseCodeSource(rust, { file: 'src/processor.rs' });
+        const ownership = Object.fromEntries(parsed.symbols.map((symbol) => [symbol.name, symbol.ownership]));
+        expect(ownership.DataProcessor).toBeUndefined();
+        expect(ownership.new).toEqual({
+            parameters: [{ name: 'data', type: '&\'a [u8]', mode: 'borrowed', lifetime: '\'a' }],
+            returns: { type: 'Self', mode: 'owned', lifetime: '\'a', borrowsFrom: ['data'] },
+            lifetimes: ['\'a'],
+        });
+        expect(ownership.window).toEqual({
+            receiver: '&self',
+            parameters: [{ name: 'start', type: 'usize', mode: 'owned' }],
+            returns: { type: '&\'a [u8]', mode: 'borrowed', lifetime: '\'a', borrowsFrom: ['self'] },
+            lifetimes: ['\'a'],
+        });
+        expect(ownership.first?.returns).toEqual({ type: 'Option<&u8>', mode: 'owned', borrowsFrom: ['self'] });
+        expect(ownership.reset).toEqual({
+            receiver: '&mut self',
+            parameters: [{ name: 'data', type: '&\'a [u8]', mode: 'borrowed', lifetime: '\'a' }],
+            lifetimes: ['\'a'],
+        });
+        expect([ownership.into_inner?.receiver, ownership.into_inner?.returns?.borrowsFrom]).toEqual(['self', ['self']]);
+        expect(ownership.longest).toEqual({
+            parameters: [
+                { name: 'left', type: '&\'x str', mode: 'borrowed', lifetime: '\'x' },
+                { name: 'right', type: '&\'y str', mode: 'borrowed', lifetime: '\'y' },
+            ],
+            returns: { type: '&\'x str', mode: 'borrowed', lifetime: '\'x', borrowsFrom: ['left'] },
+            lifetimes: ['\'x', '\'y: \'x'],
+        });
+        expect(ownership.label?.parameters.map((parameter) => [parameter.name, parameter.mode])).toEqual([['name', 'borrowed'], ['out', 'owned']]);
+        expect(ownership.label?.returns?.borrowsFrom).toEqual(['name']);
+        expect(ownership.collect).toEqual({
+            parameters: [
+                { name: 'items', type: '&mut Vec<T>', mode: 'borrowed-mut' },
+                { name: 'extra', type: 'Vec<T>', mode: 'owned' },
+            ],
+            returns: { type: 'usize', mode: 'owned' },
+            lifetimes: [],
+        });
+    });
+    it('records Rust cfg conditions on symbols and filters them by features and target', async () => {
+        const rust = [
+            '#![cfg(feature = "net")]',
+            'use std::io;',
+            '',
+            '#[cfg(windows)]',
+            'pub fn open_pipe() {}',
+            '',
+            '#[cfg(all(unix, not(target_os = "macos")))]',
+            'pub fn open_socket() {}',
+            '',
+            '#[derive(Debug)]',
+            '#[cfg_attr(feature = "serde", derive(Serialize))]',
+            'pub struct Config {',
+            '    #[cfg(feature = "tls")]',
+            '    pub cert: String,',
+            '    pub port: u16,',
+            '}',
+            '',
+            '#[cfg(feature = "tls")]',
+            'mod tls {',
+            '    pub fn handshake() { let _ = "}"; }',
+            '    #[cfg_attr(target_os = "linux", cfg(feature = "epoll"))]',
+            '    pub fn poll() {}',
+            '}',
+            '',
+            'pub fn always() {}',
+        ].join('\n');
+        const parsed = parseCodeSource(rust, { file: 'src/net.rs' });
+        const cfgOf = Object.fromEntries(parsed.symbols.map((symbol) => [symbol.name, symbol.cfg]));
+        expect(cfgOf).toEqual({
+            open_pipe: ['feature = "net"', 'windows'],
+            open_socket: ['feature = "net"', 'all(unix, not(target_os = "macos"))'],
+            Config: ['feature = "net"'],
+            cert: ['feature = "net"', 'feature = "tls"'],
+            port: ['feature = "net"'],
+            tls: ['feature = "net"', 'feature = "tls"'],
+            handshake: ['feature = "net"', 'feature = "tls"'],
+            poll: ['feature = "net"', 'feature = "tls"', 'any(not(target_os = "linux"), feature = "epoll")'],
+            always: ['feature = "net"'],
+        });
+        expect(parsed.symbols.find((symbol) => symbol.name === 'Config')?.cfgAttr).toEqual([{ predicate: 'feature = "serde"', attributes: 'derive(Serialize)' }]);
+        expect(isCfgActive(cfgOf.open_socket, { target: 'x86_64-unknown-linux-gnu' })).toBe(true);
+        expect(isCfgActive(cfgOf.open_socket, { target: 'aarch64-apple-darwin' })).toBe(false);
+        expect(isCfgActive(cfgOf.open_pipe, { target: 'x86_64-pc-windows-msvc', features: ['net'] })).toBe(true);
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, '.automatosx', 'index'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'index', 'code-index.json'), JSON.stringify({
+            version: 1,
+            generatedAt: new Date().toISOString(),
+            files: [{ file: 'src/net.rs', language: 'rust', symbols: parsed.symbols }],
+        }), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const names = async (query) => (await runtime.searchCodeSymbols({ kind: 'function', ...query })).map((symbol) => symbol.name);
+        expect(await names({})).toEqual(['open_pipe', 'open_socket', 'handshake', 'poll', 'always']);
+        expect(await names({ target: 'windows' })).toEqual(['open_pipe', 'handshake', 'poll', 'always']);
+        expect(await names({ features: ['net'], target: 'linux' })).toEqual(['open_socket', 'always']);
+        expect(await names({ features: ['net', 'tls'], target: 'linux' })).toEqual(['open_socket', 'handshake', 'always']);
+        expect(await names({ features: [] })).toEqual([]);
+    });
+    it('records Rust attributes and derived traits as symbol metadata', async () => {
+        const rust = [
+            '/// Primary colors.',
+            '#[derive(Debug, Clone, Copy)]',
+            'pub enum Color { Red, Green }',
+            '',
+            '#[derive(Debug, serde::Serialize, serde::Deserialize)]',
+            '#[serde(rename_all = "camelCase")]',
+            'pub struct Order {',
+            '    #[serde(rename = "id")]',
+            '    pub order_id: u64,',
+            '}',
+            '',
+            '#[cfg_attr(feature = "serde", derive(Serialize))]',
+            '#[deprecated(since = "1.2", note = "use Order")]',
+            'pub struct Legacy;',
+            '',
+            '#[tokio::main]',
+            'async fn main() {}',
+        ].join('\n');
+        const parsed = parseCodeSource(rust, { file: 'src/model.rs' });
+        const byName = Object.fromEntries(parsed.symbols.map((symbol) => [symbol.name, symbol]));
+        expect(byName.Color?.derives).toEqual(['Debug', 'Clone', 'Copy']);
+        expect(byName.Order?.annotations).toEqual([
+            { name: 'derive', args: 'Debug, serde::Serialize, serde::Deserialize' },
+            { name: 'serde', args: 'rename_all = "camelCase"' },
+        ]);
+        expect(byName.order_id?.annotations).toEqual([{ name: 'serde', args: 'rename = "id"' }]);
+        expect([byName.Legacy?.derives, byName.Legacy?.annotations]).toEqual([undefined, [{ name: 'deprecated', args: 'since = "1.2", note = "use Order"' }]]);
+        expect(byName.main?.annotations).toEqual([{ name: 'tokio::main' }]);
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, '.automatosx', 'index'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'index', 'code-index.json'), JSON.stringify({
+            version: 1,
+            generatedAt: new Date().toISOString(),
+            files: [{ file: 'src/model.rs', language: 'rust', symbols: parsed.symbols }],
+        }), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        expect((await runtime.searchCodeSymbols({ derive: 'Serialize' })).map((symbol) => symbol.name)).toEqual(['Order', 'Legacy']);
+        expect((await runtime.searchCodeSymbols({ derive: 'Copy' })).map((symbol) => symbol.name)).toEqual(['Color']);
+        expect((await runtime.searchCodeSymbols({ attribute: 'main' })).map((symbol) => symbol.name)).toEqual(['main']);
+        expect((await runtime.searchCodeSymbols({ attribute: 'serde' })).map((symbol) => symbol.name)).toEqual(['Order', 'order_id']);
+    });
+    it('captures aliased types and constant values of Rust items', () => {
+        const rust = [
+            'pub type CalcResult = Result<f64, String>;',
+            'type Pair<T> = (T, T);',
+            'pub const MAX_RETRIES: u32 = 3;',
+            'pub(crate) static GREETING: &str = "hi; there";',
+            'static mut COUNTER: usize = 1 << 4;',
+            'const LIMITS: [u8; 2] = [',
+            '    1,',
+            '    2,',
+            '];',
+            'const TABLE: &[&str] = &["' + 'x'.repeat(200) + '"];',
+        ].join('\n');
+        const parsed = parseCodeSource(rust, { file: 'src/config.rs' });
+        expect(Object.fromEntries(parsed.symbols.map((symbol) => [symbol.name, symbol.attributes]))).toEqual({
+            CalcResult: { declaration: 'type', aliasOf: 'Result<f64, String>' },
+            Pair: { declaration: 'type', generics: 'T', aliasOf: '(T, T)' },
+            MAX_RETRIES: { declaration: 'const', type: 'u32', value: '3' },
+            GREETING: { declaration: 'static', type: '&str', value: '"hi; there"' },
+            COUNTER: { declaration: 'static', type: 'usize', value: '1 << 4' },
+            LIMITS: { declaration: 'const', type: '[u8; 2]', value: '[ 1, 2, ]' },
+            TABLE: { declaration: 'const', type: '&[&str]' },
+        });
+    });
+    it('maps symbol spans to byte offsets and UTF-16 columns across CRLF and multibyte text', () => {
+        const content = [
+            '// Café 🚀',
+            'namespace Shop',
+            '{',
+            '    public class Cart',
+            '    {',
+            '        public string Label() => "☕";',
+            '    }',
+            '}',
+            '',
+        ].join('\r\n');
+        const symbols = parseCodeSource(content, { language: 'csharp', file: 'src/Cart.cs' }).symbols;
+        const cart = symbols.find((symbol) => symbol.name === 'Cart');
+        const label = symbols.find((symbol) => symbol.name === 'Label');
+        expect(cart.span).toEqual({
+            start: { line: 4, column: 5, utf16Column: 4, byteOffset: 38 },
+            end: { line: 7, column: 6, utf16Column: 5, byteOffset: 110 },
+        });
+        expect(label.nameSpan).toEqual({
+            start: { line: 6, column: 23, utf16Column: 22, byteOffset: 86 },
+            end: { line: 6, column: 28, utf16Column: 27, byteOffset: 91 },
+        });
+        expect(label.nameSpan.start.byteOffset).toBe(Buffer.byteLength(content.slice(0, content.indexOf('Label'))));
+        // `☕` is one UTF-16 unit but three bytes; the CRLF before each line counts two bytes.
+        expect(label.span.end).toEqual({ line: 6, column: 38, utf16Column: 37, byteOffset: 103 });
+        const positions = createPositionMapper(content);
+        // `🚀` is a surrogate pair: two UTF-16 units, one character, four bytes.
+        expect(positions.at(1, 10)).toEqual({ line: 1, column: 10, utf16Column: 10, byteOffset: 13 });
+        expect(positions.fromOffset(content.indexOf('namespace'))).toEqual({ line: 2, column: 1, utf16Column: 0, byteOffset: 15 });
+        expect(positions.lineText(6)).toBe('        public string Label() => "☕";');
+    });
+    it('derives stable symbol ids that survive inserted lines and moves but not signature changes', async () => {
+        const original = parseCodeSource('<?php\nclass Cart {\n    public function add(Item $item, int $qty) {}\n    public function clear() {}\n}\n', { file: 'src/Cart.php' });
+        const edited = parseCodeSource('<?php\n// Shopping cart.\n\nclass Cart {\n    public function clear() {}\n\n    public function add(\n        Item $item,\n        int $qty\n    ) {}\n}\n', { file: 'lib/Cart.php' });
+        const changed = parseCodeSource('<?php\nclass Cart {\n    public function add(Item $item) {}\n}\n', { file: 'src/Cart.php' });
+        const idOf = (symbols, name) => symbols.find((symbol) => symbol.name === name)?.stableId;
+        expect(idOf(original.symbols, 'add')).toMatch(/^sym_[0-9a-f]{16}$/);
+        for (const name of ['Cart', 'add', 'clear']) {
+            expect(idOf(edited.symbols, name)).toBe(idOf(original.symbols, name));
+        }
+        expect(idOf(changed.symbols, 'add')).not.toBe(idOf(original.symbols, 'add'));
+        expect(idOf(changed.symbols, 'Cart')).toBe(idOf(original.symbols, 'Cart'));
+        const duplicates = parseCodeSource('run() {\n  :\n}\nrun() {\n  :\n}\n', { file: 'build.sh' }).symbols.map((symbol) => symbol.stableId);
+        expect(duplicates[1]).toBe(`${duplicates[0]}~2`);
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'Cart.php'), '<?php\nclass Cart {\n    public function add(Item $item, int $qty) {}\n}\n', 'utf8');
+        await writeFile(join(tempDir, 'src', 'Shop.php'), '<?php\nclass Shop {\n    public function buy(Cart $cart) {\n        $cart->add(new Item(), 1);\n    }\n}\n', 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await runtime.indexCode();
+        const id = idOf(original.symbols, 'add');
+        expect((await runtime.searchCodeSymbols({ id })).map((symbol) => [symbol.name, symbol.file, symbol.line])).toEqual([['add', 'src/Cart.php', 3]]);
+        const references = await runtime.findReferences({ symbol: id });
+        expect(references.references.map((reference) => [reference.file, reference.line])).toEqual([['src/Shop.php', 4]]);
+    });
+    it('indexes a workspace and queries symbols through the shared runtime', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'scripts'), { recursive: true });
+        await mkdir(join(tempDir, 'node_modules', 'pkg'), { recursive: true });
+        await writeFile(join(tempDir, 'Dockerfile'), SAMPLE_DOCKERFILE, 'utf8');
+        await writeFile(join(tempDir, 'scripts', 'deploy.sh'), SAMPLE_SCRIPT, 'utf8');
+        await writeFile(join(tempDir, 'scripts', 'entrypoint'), '#!/bin/sh\nexport PORT=8080\n', 'utf8');
+        await writeFile(join(tempDir, 'node_modules', 'pkg', 'install.sh'), 'setup() {\n}\n', 'utf8');
+        await writeFile(join(tempDir, 'README'), 'not a script\n', 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const result = await runtime.indexCode();
+        expect(result.filesIndexed).toBe(3);
+        expect(result.languages).toEqual({ dockerfile: 1, shell: 2 });
+        const snapshot = JSON.parse(await readFile(result.indexPath, 'utf8'));
+        expect(snapshot.files.map((entry) => entry.file)).toEqual(['Dockerfile', 'scripts/deploy.sh', 'scripts/entrypoint']);
+        const ports = await runtime.searchCodeSymbols({ kind: 'port' });
+        expect(ports.map((symbol) => symbol.name)).toEqual(['80', '443']);
+        const exported = await runtime.searchCodeSymbols({ query: 'port', language: 'shell' });
+        expect(exported).toMatchObject([{ name: 'PORT', file: 'scripts/entrypoint' }]);
+        const scoped = await runtime.searchCodeSymbols({ file: 'scripts', kind: 'function' });
+        expect(scoped.map((symbol) => symbol.name)).toEqual(['build_image', 'push_image']);
+    });
+    it('serves index builds and symbol queries from a shared in-memory index daemon', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await writeFile(join(tempDir, 'Dockerfile'), SAMPLE_DOCKERFILE, 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const other = createSharedRuntimeService({ basePath: tempDir });
+        expect(await runtime.getIndexServerStatus()).toBeUndefined();
+        const server = await runtime.startIndexServer();
+        try {
+            await expect(other.startIndexServer()).rejects.toThrow('already running');
+            const result = await other.indexCode();
+            expect(result.filesIndexed).toBe(1);
+            expect(server.status()).toMatchObject({ filesIndexed: 1, requestsServed: 2 });
+            await rm(result.indexPath);
+            const ports = await runtime.searchCodeSymbols({ kind: 'port' });
+            expect(ports.map((symbol) => symbol.name)).toEqual(['80', '443']);
+            expect(await other.getIndexServerStatus()).toMatchObject({ pid: process.pid, symbolCount: server.status().symbolCount });
+            expect(await other.stopIndexServer()).toBe(true);
+            await server.closed;
+        }
+        finally {
+            await server.close();
+        }
+        expect(await runtime.getIndexServerStatus()).toBeUndefined();
+        expect(await runtime.searchCodeSymbols({ kind: 'port' })).toEqual([]);
+        expect(await runtime.stopIndexServer()).toBe(false);
+    });
+    it('re-indexes changed files from watch events and reports symbol-level changes', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'lib'), { recursive: true });
+        await writeFile(join(tempDir, 'Dockerfile'), SAMPLE_DOCKERFILE, 'utf8');
+        await writeFile(join(tempDir, 'lib', 'cart.rb'), 'class Cart\n  def add(item)\n  end\n\n  def clear\n  end\nend\n', 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const updates = [];
+        const watcher = await runtime.watchCodeIndex({ debounceMs: 20, onUpdate: (update) => updates.push(update) });
+        try {
+            expect(watcher.snapshot().files.map((entry) => entry.file)).toEqual(['Dockerfile', 'lib/cart.rb']);
+            await writeFile(join(tempDir, 'lib', 'cart.rb'), 'class Cart\n  def add(item, quantity)\n  end\n\n  def total\n  end\nend\n', 'utf8');
+            await rm(join(tempDir, 'Dockerfile'));
+            await waitUntil(() => watcher.snapshot().files.length === 1 && updates.some((update) => update.files.includes('lib/cart.rb')));
+            const changes = updates.flatMap((update) => update.changes).map((change) => [change.type, change.file, change.symbol.name]);
+            expect(changes).toEqual(expect.arrayContaining([
+                ['changed', 'lib/cart.rb', 'add'],
+                ['added', 'lib/cart.rb', 'total'],
+                ['removed', 'lib/cart.rb', 'clear'],
+                ['removed', 'Dockerfile', '443'],
+            ]));
+            const onDisk = JSON.parse(await readFile(join(tempDir, '.automatosx', 'index', 'code-index.json'), 'utf8'));
+            expect(onDisk.files.map((entry) => entry.file)).toEqual(['lib/cart.rb']);
+        }
+        finally {
+            await watcher.close();
+        }
+        const server = await runtime.startIndexServer({ watch: true });
+        try {
+            expect(server.status()).toMatchObject({ watching: true, filesIndexed: 1 });
+            await writeFile(join(tempDir, 'lib', 'order.rb'), 'class Order\nend\n', 'utf8');
+            await waitUntil(async () => (await runtime.searchCodeSymbols({ query: 'Order' })).length === 1);
+        }
+        finally {
+            await server.close();
+        }
+    });
+    it('answers project questions from the module graph and symbol index with file references', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src', 'providers'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'providers', 'router.ts'), "import { quota } from './quota.js';\nexport const route = quota;\n", 'utf8');
+        await writeFile(join(tempDir, 'src', 'providers', 'router.js'), "import { quota } from './quota.js';\n", 'utf8');
+        await writeFile(join(tempDir, 'src', 'providers', 'quota.ts'), 'export const quota = 1;\n', 'utf8');
+        await writeFile(join(tempDir, 'src', 'cli.ts'), "import { route } from './providers/router.js';\nimport { readFile } from 'node:fs/promises';\n", 'utf8');
+        await writeFile(join(tempDir, 'src', 'server.ts'), "// boot\nimport * as router from './providers/router';\n", 'utf8');
+        await writeFile(join(tempDir, 'Dockerfile'), SAMPLE_DOCKERFILE, 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const dependents = await runtime.askProject({ question: 'Which modules depend on the provider router?' });
+        expect(dependents.planner).toBe('heuristic');
+        expect(dependents.plan).toEqual({ intent: 'dependents', target: 'provider router' });
+        expect(dependents.references).toEqual([
+            { file: 'src/cli.ts', line: 1, detail: 'imports ./providers/router.js (src/providers/router.ts)' },
+            { file: 'src/server.ts', line: 2, detail: 'imports ./providers/router (src/providers/router.ts)' },
+        ]);
+        expect(await runtime.getTrace(dependents.traceId)).toMatchObject({ workflowId: 'ask', status: 'completed' });
+        const dependencies = await runtime.askProject({ question: 'What does the providers router import?' });
+        expect(dependencies.plan.intent).toBe('dependencies');
+        expect(dependencies.references).toEqual([
+            { file: 'src/providers/router.ts', line: 1, detail: 'depends on src/providers/quota.ts' },
+        ]);
+        const missing = await runtime.askProject({ question: 'Where are the ports defined?' });
+        expect(missing.references).toEqual([]);
+        expect(missing.warnings).toContain('No symbol index found. Run "ax code index" to answer symbol questions.');
+        await runtime.indexCode();
+        const planned = await answerProjectQuestion({
+            question: 'which ports does the web image expose?',
+            basePath: tempDir,
+            planner: async (prompt) => {
+                expect(prompt).toContain('Question: which ports does the web image expose?');
+                return 'Plan:\n{"intent": "symbols", "target": "4", "kind": "port"}';
+            },
+        });
+        expect(planned.planner).toBe('provider');
+        expect(planned.references).toEqual([{ file: 'Dockerfile', line: 11, detail: 'port 443 in stage-1' }]);
+        const fallback = await answerProjectQuestion({
+            question: 'which files mention quota?',
+            basePath: tempDir,
+            planner: async () => 'I think it is in quota.ts',
+        });
+        expect(fallback.planner).toBe('heuristic');
+        expect(fallback.plan).toEqual({ intent: 'files', target: 'quota' });
+        expect(fallback.references).toEqual([{ file: 'src/providers/quota.ts', detail: '0 import(s)' }]);
+        expect(fallback.warnings).toEqual(['Provider returned an unusable query plan; fell back to the heuristic planner.']);
+    });
+    it('scaffolds implementation stubs from indexed PHP and C# interfaces and as a workflow tool stage', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src', 'Contracts'), { recursive: true });
+        await mkdir(join(tempDir, 'Data'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'Contracts', 'UserRepositoryInterface.php'), [
+            '<?php',
+            'namespace App\\Contracts;',
+            '',
+            'use App\\Models\\User;',
+            '',
+            'interface UserRepositoryInterface',
+            '{',
+            '    const TABLE = "users";',
+            '',
+            '    /**',
+            '     * Finds a user by id.',
+            '     */',
+            '    public function find(int $id): ?User;',
+            '',
+            '    public static function create(',
+            '        array $attributes,',
+            '    ): static;',
+            '}',
+            '',
+        ].join('\n'), 'utf8');
+        await writeFile(join(tempDir, 'Data', 'IUserStore.cs'), [
+            'using System.Threading.Tasks;',
+            '',
+            'namespace Acme.Data;',
+            '',
+            'public interface IUserStore<TKey> where TKey : notnull',
+            '{',
+            '    /// <summary>Loads a user.</summary>',
+            '    Task<string?> LoadAsync(TKey id);',
+            '    int Count { get; }',
+            '    string Describe() => "store";',
+            '}',
+            '',
+        ].join('\n'), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await expect(runtime.scaffoldImplementation({ symbol: 'IUserStore', dryRun: true })).rejects.toThrow('Run "ax code index" first');
+        await runtime.indexCode();
+        const php = await runtime.scaffoldImplementation({ symbol: 'App\\Contracts\\UserRepositoryInterface' });
+        expect(php.written).toBe(true);
+        expect(php.className).toBe('UserRepository');
+        expect(php.file).toBe('src/Contracts/UserRepository.php');
+        expect(php.members).toEqual(['find', 'create']);
+        expect(await readFile(join(tempDir, php.file), 'utf8')).toBe([
+            '<?php',
+            '',
+            'declare(strict_types=1);',
+            '',
+            'namespace App\\Contracts;',
+            '',
+            'use App\\Models\\User;',
+            '',
+            'final class UserRepository implements UserRepositoryInterface',
+            '{',
+            '    /**',
+            '     * Finds a user by id.',
+            '     */',
+            '    public function find(int $id): ?User',
+            '    {',
+            "        throw new \\LogicException('Not implemented: UserRepository::find()');",
+            '    }',
+            '',
+            '    public static function create(array $attributes): static',
+            '    {',
+            "        throw new \\LogicException('Not implemented: UserRepository::create()');",
+            '    }',
+            '}',
+            '',
+        ].join('\n'));
+        await expect(runtime.scaffoldImplementation({ symbol: 'UserRepositoryInterface' })).rejects.toThrow('File already exists: src/Contracts/UserRepository.php');
+        const csharp = await runtime.scaffoldImplementation({ symbol: 'Acme.Data.IUserStore', dryRun: true });
+        expect(csharp.written).toBe(false);
+        expect(csharp.members).toEqual(['LoadAsync', 'Count']);
+        expect(csharp.content).toBe([
+            'using System;',
+            'using System.Threading.Tasks;',
+            '',
+            'namespace Acme.Data;',
+            '',
+            'public class UserStore<TKey> : IUserStore<TKey> where TKey : notnull',
+            '{',
+            '    /// <summary>Loads a user.</summary>',
+            '    public Task<string?> LoadAsync(TKey id)',
+            '    {',
+            '        throw new NotImplementedException();',
+            '    }',
+            '',
+            '    public int Count { get => throw new NotImplementedException(); }',
+            '}',
+            '',
+        ].join('\n'));
+        await writeFile(join(tempDir, 'scaffold-stage.json'), JSON.stringify({
+            workflowId: 'scaffold-stage',
+            name: 'Scaffold Stage',
+            version: '1.0.0',
+            steps: [{
+                stepId: 'stub',
+                type: 'tool',
+                config: { toolName: 'scaffold.impl', toolInput: { symbol: 'IUserStore', className: 'SqlUserStore', output: 'Data/Sql/SqlUserStore.cs' } },
+            }],
+        }), 'utf8');
+        const workflow = await runtime.runWorkflow({ workflowId: 'scaffold-stage', workflowDir: tempDir });
+        expect(workflow.success).toBe(true);
+        expect(await readFile(join(tempDir, 'Data', 'Sql', 'SqlUserStore.cs'), 'utf8')).toContain('public class SqlUserStore<TKey> : IUserStore<TKey>');
+    });
+    it('traces a diff or edited files to callers, impacted tests and suggested reviewers', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const cart = [
+            '<?php',
+            'namespace Shop;',
+            '',
+            'class Cart',
+            '{',
+            '    public function add(int $price): void',
+            '    {',
+            '        $this->total += tax($price);',
+            '    }',
+            '}',
+            '',
+            'function tax(int $price): int',
+            '{',
+            '    return $price;',
+            '}',
+        ].join('\n');
+        await mkdir(join(tempDir, 'src'), { recursive: true });
+        await mkdir(join(tempDir, 'tests'), { recursive: true });
+        await mkdir(join(tempDir, '.github'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'Cart.php'), cart, 'utf8');
+        await writeFile(join(tempDir, 'tests', 'CheckoutTest.php'), [
+            '<?php',
+            'class CheckoutTest',
+            '{',
+            '    public function testAdd(): void',
+            '    {',
+            '        $cart = new \\Shop\\Cart();',
+            '        $cart->add(1);',
+            '    }',
+            '}',
+        ].join('\n'), 'utf8');
+        await writeFile(join(tempDir, '.github', 'CODEOWNERS'), '* @org/core\n/src/ @org/shop # shop team\n', 'utf8');
+        const commitAs = async (name, message) => {
+            await execFileAsync('git', ['add', '.'], { cwd: tempDir });
+            await execFileAsync('git', ['-c', `user.name=${name}`, '-c', `user.email=${name.toLowerCase()}@example.com`, 'commit', '-m', message], { cwd: tempDir });
+        };
+        await execFileAsync('git', ['init', '-b', 'main'], { cwd: tempDir });
+        await execFileAsync('git', ['config', 'user.email', 'me@example.com'], { cwd: tempDir });
+        await commitAs('Alice', 'init');
+        for (const [name, total] of [['Bob', '2'], ['Bob', '3'], ['Me', '4']]) {
+            await writeFile(join(tempDir, 'src', 'Cart.php'), `${cart}\n// ${total}\n`, 'utf8');
+            await commitAs(name, `touch ${total}`);
+        }
+        await writeFile(join(tempDir, 'src', 'Cart.php'), cart, 'utf8');
+        await commitAs('Me', 'restore');
+        const diff = [
+            'diff --git a/src/Cart.php b/src/Cart.php',
+            '--- a/src/Cart.php',
+            '+++ b/src/Cart.php',
+            '@@ -13,3 +13,3 @@ function tax(int $price): int',
+            ' {',
+            '-    return $price;',
+            '+    return $price * 2;',
+            ' }',
+        ].join('\n');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        expect((await runtime.analyzeChangeImpact({ diff })).warnings).toEqual([
+            'No symbol index found, so callers of the changed symbols were not traced. Run "ax code index" first.',
+        ]);
+        await runtime.indexCode();
+        const impact = await runtime.analyzeChangeImpact({ diff });
+        expect(impact).toMatchObject({ source: 'diff', changedFiles: ['src/Cart.php'] });
+        expect(impact.changedSymbols.map((symbol) => symbol.name)).toEqual(['tax']);
+        expect(impact.affectedSymbols.map((symbol) => [symbol.name, symbol.distance, symbol.calls])).toEqual([
+            ['Shop.Cart.add', 1, 'Shop.tax'],
+            ['CheckoutTest.testAdd', 2, 'Shop.Cart.add'],
+        ]);
+        expect(impact.impactedTests).toEqual([{ file: 'tests/CheckoutTest.php', reasons: ['calls Shop.Cart.add'] }]);
+        expect(impact.reviewers).toEqual([
+            { reviewer: '@org/shop', owner: true, commits: 0, reasons: ['owns src/Cart.php'] },
+            { reviewer: 'Bob <bob@example.com>', owner: false, commits: 2, reasons: ['2 recent commit(s) to the changed files'] },
+            { reviewer: 'Alice <alice@example.com>', owner: false, commits: 1, reasons: ['1 recent commit(s) to the changed files'] },
+        ]);
+        const edited = await runtime.analyzeChangeImpact({ files: ['src/Cart.php'], depth: 1 });
+        expect(edited.source).toBe('files');
+        expect(edited.changedSymbols.map((symbol) => symbol.name)).toEqual(['Shop', 'Cart', 'add', 'tax']);
+        expect(edited.affectedSymbols.map((symbol) => symbol.name)).toEqual(['CheckoutTest.testAdd']);
+        expect((await runtime.analyzeChangeImpact()).changedFiles).toEqual([]);
+        await expect(runtime.analyzeChangeImpact({ diff: 'not a diff' })).rejects.toThrow('The diff names no files.');
+        await expect(runtime.analyzeChangeImpact({ files: ['../outside.php'] })).rejects.toThrow('Path escapes workspace: ../outside.php');
+    });
+    it('selects tests impacted by changed files and symbols and runs them as a workflow validation stage', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const files = {
+            'src/cart.ts': 'export function total(items: number[]) {\n  return items.length;\n}\n',
+            'src/checkout.ts': "import { total } from './cart.js';\nexport const checkout = () => total([]);\n",
+            'src/banner.ts': 'export const banner = "hi";\n',
+            'tests/checkout.test.ts': "import { checkout } from '../src/checkout.js';\ncheckout();\n",
+            'tests/banner.test.ts': "import { banner } from '../src/banner.js';\nbanner;\n",
+            'app/Models/Order.php': '<?php\nnamespace App\\Models;\nclass Order\n{\n    public function placeOrder(): void\n    {\n    }\n\n    public function cancel(): void\n    {\n    }\n}\n',
+            'tests/Feature/FlowTest.php': '<?php\n$order->placeOrder();\n',
+            'tests/Feature/RefundTest.php': '<?php\n$order->cancel();\n',
+            'run-tests.mjs': "console.log(`ran ${process.argv.slice(2).join(' ')}`);\nprocess.exit(process.env.FAIL_TESTS === '1' ? 1 : 0);\n",
+        };
+        for (const [file, content] of Object.entries(files)) {
+            await mkdir(join(tempDir, file, '..'), { recursive: true });
+            await writeFile(join(tempDir, file), content, 'utf8');
+        }
+        await execFileAsync('git', ['init', '-b', 'main'], { cwd: tempDir });
+        await execFileAsync('git', ['add', '.'], { cwd: tempDir });
+        await execFileAsync('git', ['-c', 'user.name=ax', '-c', 'user.email=ax@example.com', 'commit', '-m', 'init'], { cwd: tempDir });
+        await writeFile(join(tempDir, 'src', 'cart.ts'), 'export function total(items: number[]) {\n  return items.length * 2;\n}\n', 'utf8');
+        await writeFile(join(tempDir, 'app', 'Models', 'Order.php'), files['app/Models/Order.php'].replace('    {\n    }\n\n    public function cancel', '    {\n        $this->placed = true;\n    }\n\n    public function cancel'), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const impact = await runtime.analyzeTestImpact();
+        expect(impact.changedFiles).toEqual(['app/Models/Order.php', 'src/cart.ts']);
+        expect(impact.changedSymbols.map((symbol) => symbol.name)).toEqual(['Order', 'placeOrder']);
+        expect(impact.testFileCount).toBe(4);
+        expect(impact.impactedTests).toEqual([
+            { file: 'tests/checkout.test.ts', reasons: ['imports src/cart.ts via src/checkout.ts'] },
+            { file: 'tests/Feature/FlowTest.php', reasons: ['references placeOrder'] },
+        ]);
+        await mkdir(join(tempDir, '.automatosx'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({ testing: { command: ['node', 'run-tests.mjs'] } }), 'utf8');
+        const run = await runtime.analyzeTestImpact({ run: true });
+        expect(run.run).toMatchObject({ passed: true, exitCode: 0 });
+        expect(run.run?.output).toContain('ran tests/checkout.test.ts tests/Feature/FlowTest.php');
+        await writeFile(join(tempDir, 'verify.json'), JSON.stringify({
+            workflowId: 'verify',
+            name: 'Verify',
+            version: '1.0.0',
+            steps: [{ stepId: 'impacted-tests', type: 'tool', config: { toolName: 'test.impacted', toolInput: {} } }],
+        }), 'utf8');
+        expect((await runtime.runWorkflow({ workflowId: 'verify', workflowDir: tempDir })).success).toBe(true);
+        process.env.FAIL_TESTS = '1';
+        try {
+            const failed = await runtime.runWorkflow({ workflowId: 'verify', workflowDir: tempDir });
+            expect(failed.success).toBe(false);
+            expect(failed.error?.message).toContain('Impacted tests failed (exit 1)');
+        }
+        finally {
+            delete process.env.FAIL_TESTS;
+        }
+    });
+    it('reports unsafe code, FFI items, and raw pointers per Rust crate with spans', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const files = {
+            'Cargo.toml': '[workspace]\nmembers = ["crates/*"]\n',
+            'crates/ffi/Cargo.toml': '[package]\nname = "ffi"\nversion = "0.1.0"\n',
+            'crates/ffi/src/lib.rs': [
+                '// unsafe { not code }',
+                'extern "C" {',
+                '    fn strlen(s: *const c_char) -> usize;',
+                '}',
+                '',
+                '#[no_mangle]',
+                'pub extern "C" fn ax_add(a: i32, b: i32) -> i32 {',
+                '    a + b',
+                '}',
+                '',
+                'pub unsafe fn read_raw(ptr: *mut u8) -> u8 {',
+                '    *ptr',
+                '}',
+                '',
+                'unsafe impl Send for Handle {}',
+                '',
+                'fn length(s: &str) -> usize {',
+                '    let msg = "unsafe { fake }";',
+                "    let brace = '{';",
+                '    unsafe {',
+                '        strlen(s.as_ptr() as *const c_char)',
+                '    }',
+                '}',
+                '',
+            ].join('\n'),
+            'crates/ffi/target/debug/build/out.rs': 'unsafe fn generated() {}\n',
+            'crates/app/Cargo.toml': '[package]\nname = "app"\n',
+            'crates/app/src/main.rs': 'fn main() {\n    println!("safe");\n}\n',
+        };
+        for (const [file, content] of Object.entries(files)) {
+            await mkdir(join(tempDir, file, '..'), { recursive: true });
+            await writeFile(join(tempDir, file), content, 'utf8');
+        }
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const reports = await runtime.reportUnsafeCode();
+        expect(reports.map((report) => [report.crate, report.manifest, report.files, report.findings.length])).toEqual([
+            ['app', 'crates/app/Cargo.toml', 1, 0],
+            ['ffi', 'crates/ffi/Cargo.toml', 1, 9],
+        ]);
+        const ffi = reports[1];
+        expect(ffi.counts).toMatchObject({ 'extern-block': 1, 'foreign-item': 1, 'extern-fn': 1, 'unsafe-fn': 1, 'unsafe-impl': 1, 'unsafe-block': 1, 'raw-pointer': 3 });
+        expect(ffi.findings.map((finding) => [finding.kind, finding.line, finding.column, finding.endLine, finding.endColumn])).toEqual([
+            ['extern-block', 2, 1, 4, 2],
+            ['foreign-item', 3, 5, 3, 42],
+            ['raw-pointer', 3, 18, 3, 24],
+            ['extern-fn', 7, 5, 9, 2],
+            ['unsafe-fn', 11, 5, 13, 2],
+            ['raw-pointer', 11, 29, 11, 33],
+            ['unsafe-impl', 15, 1, 15, 31],
+            ['unsafe-block', 20, 5, 22, 6],
+            ['raw-pointer', 21, 30, 21, 36],
+        ]);
+        expect(ffi.findings.find((finding) => finding.kind === 'extern-fn')).toMatchObject({ name: 'ax_add', abi: 'C' });
+        expect(ffi.findings.find((finding) => finding.kind === 'unsafe-block')).toMatchObject({ function: 'length', snippet: 'unsafe {' });
+        expect((await runtime.reportUnsafeCode({ crate: 'ffi', paths: ['crates'] })).map((report) => report.crate)).toEqual(['ffi']);
+    });
+    it('lists unreferenced symbols while skipping entry points, tests, and interface implementations', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const files = {
+            'src/Store.cs': [
+                'namespace Shop;',
+                'public interface IStore',
+                '{',
+                '    void Save(string item);',
+                '    void Purge();',
+                '}',
+                'public class MemoryStore : IStore',
+                '{',
+                '    public MemoryStore() { }',
+                '    public void Save(string item) => Log(item);',
+                '    public void Purge() { }',
+                '    private void Log(string item) { }',
+                '    private void Unused() { Unused(); }',
+                '    public override string ToString() => "store";',
+                '}',
+                'public class Checkout',
+                '{',
+                '    public void Run(IStore store) => store.Save("x");',
+                '}',
+                '',
+            ].join('\n'),
+            'tests/CheckoutTests.cs': [
+                'public class CheckoutTests',
+                '{',
+                '    [Fact]',
+                '    public void Runs() => new Checkout().Run(new MemoryStore());',
+                '}',
+                '',
+            ].join('\n'),
+            'src/Money.php': [
+                '<?php',
+                'namespace App;',
+                'final class Money',
+                '{',
+                '    public function __construct(private int $cents) {}',
+                '    public function add(Money $other): Money { return new Money($this->cents + $other->cents); }',
+                '    private function round(): int { return $this->cents; }',
+                '}',
+                '',
+            ].join('\n'),
+            'lib/worker.rb': [
+                'class Worker',
+                '  def initialize(store)',
+                '    @store = store',
+                '  end',
+                '',
+                '  def ready?',
+                '    true',
+                '  end',
+                '',
+                '  def perform',
+                '    run if ready?',
+                '  end',
+                'end',
+                '',
+            ].join('\n'),
+        };
+        for (const [file, content] of Object.entries(files)) {
+            await mkdir(join(tempDir, file, '..'), { recursive: true });
+            await writeFile(join(tempDir, file), content, 'utf8');
+        }
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await expect(runtime.findUnreferencedSymbols()).rejects.toThrow('Run "ax code index" first');
+        await runtime.indexCode();
+        const result = await runtime.findUnreferencedSymbols();
+        expect(result.checked).toBe(22);
+        expect(result.excluded).toEqual({ entryPoints: 3, tests: 2, traitImpls: 3 });
+        expect(result.candidates.map((symbol) => [symbol.kind, symbol.name, `${symbol.file}:${symbol.line}`, symbol.visibility])).toEqual([
+            ['class', 'Worker', 'lib/worker.rb:1', undefined],
+            ['method', 'perform', 'lib/worker.rb:10', undefined],
+            ['class', 'Money', 'src/Money.php:3', undefined],
+            ['method', 'add', 'src/Money.php:6', 'public'],
+            ['method', 'round', 'src/Money.php:7', 'private'],
+            ['method', 'Purge', 'src/Store.cs:5', undefined],
+            ['method', 'Unused', 'src/Store.cs:13', 'private'],
+        ]);
+        const scoped = await runtime.findUnreferencedSymbols({ paths: ['src/'], kind: 'method' });
+        expect(scoped.candidates.map((symbol) => symbol.name)).toEqual(['add', 'round', 'Purge', 'Unused']);
+    });
+    it('finds symbol references resolved through imports, namespaces, and method receivers', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const files = {
+            'src/Billing/Money.php': [
+                '<?php',
+                'namespace App\\Billing;',
+                'final class Money',
+                '{',
+                '    public function add(Money $other): Money',
+                '    {',
+                '        return $this->add($other);',
+                '    }',
+                '}',
+                '',
+            ].join('\n'),
+            'src/Stats/Counter.php': [
+                '<?php',
+                'namespace App\\Stats;',
+                'final class Counter',
+                '{',
+                '    public function add(int $step): void {}',
+                '}',
+                '',
+            ].join('\n'),
+            'src/Checkout.php': [
+                '<?php',
+                'namespace App;',
+                'use App\\Billing\\Money as Cash;',
+                'use App\\Stats\\Counter;',
+                'final class Checkout',
+                '{',
+                '    public function total(Cash $price, Counter $counter, $unknown): Cash',
+                '    {',
+                '        $counter->add(1);',
+                '        $unknown->add(2);',
+                '        // $price->add($price) in a comment',
+                '        return (new Cash())->add($price);',
+                '    }',
+                '}',
+                '',
+            ].join('\n'),
+            'src/Store.cs': [
+                'namespace Shop;',
+                'public interface IStore',
+                '{',
+                '    void Save(string item);',
+                '}',
+                'public class MemoryStore : IStore',
+                '{',
+                '    public void Save(string item) { Flush(); }',
+                '    private void Flush() { }',
+                '}',
+                '',
+            ].join('\n'),
+            'src/Checkout.cs': [
+                'using Shop;',
+                'namespace Shop.Web;',
+                'public class Cart',
+                '{',
+                '    public void Save(string item) { }',
+                '    public void Run(IStore store, Cart cart)',
+                '    {',
+                '        store.Save("a");',
+                '        cart.Save("b");',
+                '        new MemoryStore().Save("c");',
+                '    }',
+                '}',
+                '',
+            ].join('\n'),
+        };
+        for (const [file, content] of Object.entries(files)) {
+            await mkdir(join(tempDir, file, '..'), { recursive: true });
+            await writeFile(join(tempDir, file), content, 'utf8');
+        }
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await runtime.indexCode();
+        const moneyAdd = await runtime.findReferences({ symbol: 'App\\Billing\\Money::add' });
+        expect(moneyAdd.symbol).toMatchObject({ id: 'src/Billing/Money.php:5', kind: 'method', container: 'App\\Billing\\Money' });
+        expect(moneyAdd.references.map((reference) => [`${reference.file}:${reference.line}:${reference.column}`, reference.via, reference.within])).toEqual([
+            ['src/Billing/Money.php:7:23', 'receiver', 'App.Billing.Money.add'],
+            ['src/Checkout.php:12:30', 'receiver', 'App.Checkout.total'],
+        ]);
+        expect(moneyAdd.unresolved).toBe(1);
+        const money = await runtime.findReferences({ symbol: 'src/Billing/Money.php:3' });
+        expect(money.references.map((reference) => [`${reference.file}:${reference.line}`, reference.via])).toEqual([
+            ['src/Billing/Money.php:5', 'same-file'],
+            ['src/Billing/Money.php:5', 'same-file'],
+            ['src/Checkout.php:3', 'qualified'],
+            ['src/Checkout.php:7', 'import'],
+            ['src/Checkout.php:7', 'import'],
+            ['src/Checkout.php:12', 'import'],
+        ]);
+        const storeSave = await runtime.findReferences({ symbol: 'Shop.MemoryStore.Save' });
+        expect(storeSave.references.map((reference) => [`${reference.file}:${reference.line}`, reference.via])).toEqual([
+            ['src/Checkout.cs:8', 'receiver'],
+            ['src/Checkout.cs:10', 'receiver'],
+        ]);
+        const flush = await runtime.findReferences({ symbol: 'Flush' });
+        expect(flush.references.map((reference) => [`${reference.file}:${reference.line}`, reference.via, reference.within])).toEqual([
+            ['src/Store.cs:8', 'receiver', 'Shop.MemoryStore.Save'],
+        ]);
+        await expect(runtime.findReferences({ symbol: 'Save' })).rejects.toThrow('"Save" is ambiguous');
+    });
+    it('resolves Rust references through use declarations and impl receivers', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const files = {
+            'src/money.rs': [
+                'pub struct Money(i64);',
+                'impl Money {',
+                '    pub fn new(cents: i64) -> Self { Money(cents) }',
+                '    pub fn add(&self, other: &Money) -> Money { Money(self.0 + other.0) }',
+                '}',
+                '',
+            ].join('\n'),
+            'src/counter.rs': [
+                'pub struct Counter(u32);',
+                'impl Counter {',
+                '    pub fn add(&mut self, step: u32) { self.0 += step; }',
+                '}',
+                '',
+            ].join('\n'),
+            'src/main.rs': [
+                'use crate::{counter::Counter, money::Money as Cash};',
+                'fn main() {',
+                '    let price = Cash::new(5);',
+                '    let mut hits = Counter(0);',
+                '    hits.add(1);',
+                '    let total = price.add(&price);',
+                '    println!("{}", "price.add(&price)");',
+                '}',
+                '',
+            ].join('\n'),
+        };
+        for (const [file, content] of Object.entries(files)) {
+            await mkdir(join(tempDir, file, '..'), { recursive: true });
+            await writeFile(join(tempDir, file), content, 'utf8');
+        }
+        const symbol = (name, kind, file, line, endLine = line) => ({ name, kind, language: 'rust', file, line, endLine });
+        await mkdir(join(tempDir, '.automatosx', 'index'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'index', 'code-index.json'), JSON.stringify({
+            version: 1,
+            generatedAt: new Date().toISOString(),
+            files: [
+                { file: 'src/counter.rs', language: 'rust', symbols: [symbol('Counter', 'class', 'src/counter.rs', 1), symbol('add', 'function', 'src/counter.rs', 3)] },
+                { file: 'src/main.rs', language: 'rust', symbols: [symbol('main', 'function', 'src/main.rs', 2, 8)] },
+                {
+                    file: 'src/money.rs',
+                    language: 'rust',
+                    symbols: [symbol('Money', 'class', 'src/money.rs', 1), symbol('new', 'function', 'src/money.rs', 3), symbol('add', 'function', 'src/money.rs', 4)],
+                },
+            ],
+        }), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const add = await runtime.findReferences({ symbol: 'src/money.rs:4' });
+        expect(add.references.map((reference) => [`${reference.file}:${reference.line}`, reference.via, reference.within])).toEqual([
+            ['src/main.rs:6', 'receiver', 'main'],
+        ]);
+        const money = await runtime.findReferences({ symbol: 'money.Money' });
+        expect(money.references.map((reference) => [`${reference.file}:${reference.line}`, reference.via])).toEqual([
+            ['src/main.rs:1', 'qualified'],
+            ['src/main.rs:3', 'import'],
+            ['src/money.rs:2', 'same-file'],
+            ['src/money.rs:3', 'same-file'],
+            ['src/money.rs:4', 'same-file'],
+            ['src/money.rs:4', 'same-file'],
+            ['src/money.rs:4', 'same-file'],
+        ]);
+    });
+    it('resolves method calls to impl blocks and types declared in other files', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        const files = {
+            'src/money.rs': 'pub struct Money(pub i64);\n',
+            'src/ops.rs': [
+                'use crate::money::Money;',
+                '',
+                'impl Money {',
+                '    pub fn add(&self, other: &Money) -> Money {',
+                '        Money(self.0 + other.0)',
+                '    }',
+                '}',
+                '',
+            ].join('\n'),
+            'src/legacy.rs': [
+                'pub struct Money(i64);',
+                'impl Money {',
+                '    pub fn add(&self, other: &Money) -> Money { Money(0) }',
+                '}',
+                '',
+            ].join('\n'),
+            'src/bank.rs': [
+                'use crate::money::Money;',
+                '',
+                'pub struct Account {',
+                '    pub balance: Money,',
+                '}',
+                '',
+                'pub fn open() -> Money {',
+                '    Money(0)',
+                '}',
+                '',
+            ].join('\n'),
+            'src/teller.rs': [
+                'use crate::bank::{open, Account};',
+                '',
+                'impl Account {',
+                '    pub fn deposit(&mut self) {',
+                '        self.balance = self.balance.add(&open());',
+                '    }',
+                '}',
+                '',
+                'fn main() {',
+                '    let cash = open();',
+                '    let total = cash.add(&cash);',
+                '}',
+                '',
+            ].join('\n'),
+        };
+        for (const [file, content] of Object.entries(files)) {
+            await mkdir(join(tempDir, file, '..'), { recursive: true });
+            await writeFile(join(tempDir, file), content, 'utf8');
+        }
+        const symbol = (name, kind, file, line, endLine = line) => ({ name, kind, language: 'rust', file, line, endLine });
+        const entry = (file, ...symbols) => ({ file, language: 'rust', symbols });
+        await mkdir(join(tempDir, '.automatosx', 'index'), { recursive: true });
+        await writeFile(join(tempDir, '.automatosx', 'index', 'code-index.json'), JSON.stringify({
+            version: 1,
+            generatedAt: new Date().toISOString(),
+            files: [
+                entry('src/bank.rs', symbol('Account', 'class', 'src/bank.rs', 3, 5), symbol('open', 'function', 'src/bank.rs', 7, 9)),
+                entry('src/legacy.rs', symbol('Money', 'class', 'src/legacy.rs', 1), symbol('add', 'function', 'src/legacy.rs', 3)),
+                entry('src/money.rs', symbol('Money', 'class', 'src/money.rs', 1)),
+                entry('src/ops.rs', symbol('add', 'function', 'src/ops.rs', 4, 6)),
+                entry('src/teller.rs', symbol('deposit', 'function', 'src/teller.rs', 4, 6), symbol('main', 'function', 'src/teller.rs', 9, 12)),
+            ],
+        }), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        // `add` is declared for two unrelated `Money` types; the import in ops.rs decides which one it extends.
+        const add = await runtime.findReferences({ symbol: 'src/ops.rs:4' });
+        expect(add.references.map((reference) => [`${reference.file}:${reference.line}`, reference.via, reference.within])).toEqual([
+            ['src/teller.rs:5', 'receiver', 'teller.deposit'],
+            ['src/teller.rs:11', 'receiver', 'teller.main'],
+        ]);
+        expect(add.unresolved).toBe(0);
+        expect((await runtime.findReferences({ symbol: 'src/legacy.rs:3' })).references).toEqual([]);
+        await mkdir(join(tempDir, 'Shop'), { recursive: true });
+        const csharpType = (name, ...members) => ['namespace Shop', '{', `    public class ${name}`, '    {', ...members, '    }', '}', ''].join('\n');
+        await writeFile(join(tempDir, 'Shop', 'Cart.cs'), csharpType('Cart', '        public void Add(int quantity) { }'), 'utf8');
+        await writeFile(join(tempDir, 'Shop', 'Ledger.cs'), csharpType('Ledger', '        public void Add(int quantity) { }'), 'utf8');
+        await writeFile(join(tempDir, 'Shop', 'CartFactory.cs'), csharpType('CartFactory', '        public Cart Create() => new Cart();'), 'utf8');
+        await writeFile(join(tempDir, 'Shop', 'Checkout.cs'), csharpType('Checkout', '        private readonly CartFactory _factory = new CartFactory();', '', '        public void Run()', '        {', '            var cart = _factory.Create();', '            cart.Add(1);', '            _factory.Create().Add(2);', '        }'), 'utf8');
+        await runtime.indexCode({ paths: ['Shop'] });
+        const cartAdd = await runtime.findReferences({ symbol: 'Shop.Cart.Add' });
+        expect(cartAdd.references.map((reference) => [`${reference.file}:${reference.line}`, reference.via])).toEqual([
+            ['Shop/Checkout.cs:10', 'receiver'],
+            ['Shop/Checkout.cs:11', 'receiver'],
+        ]);
+        expect((await runtime.findReferences({ symbol: 'Shop.Ledger.Add' })).references).toEqual([]);
+    });
+    it('renders signature-only skeletons with function bodies elided', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'Cart.cs'), [
+            'namespace Shop',
+            '{',
+            '    public class Cart',
+            '    {',
+            '        private readonly decimal _rate = 0.2m;',
+            '        public decimal Total { get; private set; }',
+            '',
+            '        public decimal Tax() => Total * _rate;',
+            '',
+            '        public void Add(decimal price)',
+            '        {',
+            '            if (price < 0)',
+            '            {',
+            '                throw new ArgumentException("negative {price}");',
+            '            }',
+            '',
+            '            Total += price;',
+            '        }',
+            '    }',
+            '}',
+        ].join('\n'), 'utf8');
+        await writeFile(join(tempDir, 'src', 'cart.rb'), [
+            'class Cart',
+            '  attr_reader :total',
+            '',
+            '  def add(price)',
+            '    raise ArgumentError if price.negative?',
+            '    @total += price',
+            '  end',
+            'end',
+        ].join('\n'), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const views = await runtime.renderSignatures({ paths: ['src'] });
+        expect(views.map((view) => [view.file, view.lines, view.renderedLines, view.elidedBodies])).toEqual([
+            ['src/Cart.cs', 20, 15, 2],
+            ['src/cart.rb', 8, 7, 1],
+        ]);
+        expect(views[0].content).toBe([
+            'namespace Shop',
+            '{',
+            '    public class Cart',
+            '    {',
+            '        private readonly decimal _rate = 0.2m;',
+            '        public decimal Total { get; private set; }',
+            '',
+            '        public decimal Tax() => ...;',
+            '',
+            '        public void Add(decimal price)',
+            '        {',
+            '            // ...',
+            '        }',
+            '    }',
+            '}',
+        ].join('\n'));
+        expect(views[1].content).toContain('  def add(price)\n    # ...\n  end');
+        await expect(runtime.renderSignatures({ paths: ['../outside'] })).rejects.toThrow(/escapes workspace/);
+    });
+    it('outlines files as nested Markdown headings with signatures and doc lines', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'Cart.cs'), [
+            'namespace Shop',
+            '{',
+            '    /// <summary>',
+            '    /// A shopping cart with running totals.',
+            '    /// </summary>',
+            '    public class Cart : ICart',
+            '    {',
+            '        public decimal Total { get; private set; }',
+            '',
+            '        // Adds one line item.',
+            '        [Obsolete]',
+            '        public void Add(',
+            '            string sku,',
+            '            decimal price)',
+            '        {',
+            '            Total += price;',
+            '        }',
+            '    }',
+            '}',
+        ].join('\n'), 'utf8');
+        await writeFile(join(tempDir, 'src', 'ledger.rb'), [
+            '# frozen_string_literal: true',
+            '',
+            '# Totals line items.',
+            'class Ledger',
+            '  # Records a payment.',
+            '  def pay(amount) = @paid += amount',
+            'end',
+        ].join('\n'), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const outline = await runtime.renderOutline({ paths: ['src'] });
+        expect(outline.files.map((file) => file.file)).toEqual(['src/Cart.cs', 'src/ledger.rb']);
+        const cart = outline.files[0].symbols[0].children[0];
+        expect(cart).toMatchObject({ name: 'Cart', signature: 'public class Cart : ICart', doc: 'A shopping cart with running totals.' });
+        expect(cart.children.map((child) => [child.name, child.signature, child.doc])).toEqual([
+            ['Total', 'public decimal Total', undefined],
+            ['Add', 'public void Add(string sku, decimal price)', 'Adds one line item.'],
+        ]);
+        expect(outline.markdown).toContain([
+            '## src/Cart.cs',
+            '',
+            '_csharp, 4 symbol(s)_',
+            '',
+            '### Shop',
+            '',
+            '`namespace Shop`',
+            '',
+            '#### Cart',
+            '',
+            '`public class Cart : ICart`',
+            '',
+            'A shopping cart with running totals.',
+        ].join('\n'));
+        expect(outline.markdown).toContain('### Ledger\n\n`class Ledger`\n\nTotals line items.\n\n#### pay\n\n`def pay(amount) = @paid += amount`\n\nRecords a payment.');
+        await expect(runtime.renderOutline({ paths: ['../outside'] })).rejects.toThrow(/escapes workspace/);
+    });
+    it('outlines one file with symbol ids and reads a symbol body with its doc comment', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'Cart.cs'), [
+            'namespace Shop',
+            '{',
+            '    public class Cart',
+            '    {',
+            '        // Adds one line item.',
+            '        [Obsolete]',
+            '        public void Add(decimal price)',
+            '        {',
+            '            Total += price;',
+            '        }',
+            '',
+            '        public void Clear()',
+            '        {',
+            '            Total = 0;',
+            '        }',
+            '    }',
+            '}',
+        ].join('\n'), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const skeleton = await runtime.renderFileSkeleton({ path: 'src/Cart.cs' });
+        expect(skeleton.content).toContain('public void Add(decimal price)\n        {\n            // ...\n        }');
+        expect(skeleton.symbols.map((symbol) => [symbol.id, symbol.name, symbol.kind])).toEqual([
+            ['src/Cart.cs:1', 'Shop', 'namespace'],
+            ['src/Cart.cs:3', 'Cart', 'class'],
+            ['src/Cart.cs:7', 'Add', 'method'],
+            ['src/Cart.cs:12', 'Clear', 'method'],
+        ]);
+        const body = await runtime.readSymbolBody({ symbol: 'src/Cart.cs:7' });
+        expect(body).toMatchObject({ id: 'src/Cart.cs:7', qualifiedName: 'Shop.Cart.Add', startLine: 5, line: 7, endLine: 10 });
+        expect(body.source).toBe([
+            '        // Adds one line item.',
+            '        [Obsolete]',
+            '        public void Add(decimal price)',
+            '        {',
+            '            Total += price;',
+            '        }',
+        ].join('\n'));
+        await expect(runtime.readSymbolBody({ symbol: 'Cart.Clear' })).rejects.toThrow('Run "ax code index" first');
+        await runtime.indexCode();
+        expect((await runtime.readSymbolBody({ symbol: 'Cart.Clear' })).source).toBe('        public void Clear()\n        {\n            Total = 0;\n        }');
+        await expect(runtime.readSymbolBody({ symbol: 'src/Cart.cs:8' })).rejects.toThrow('No symbol is declared on line 8 of src/Cart.cs.');
+        await expect(runtime.renderFileSkeleton({ path: 'src' })).rejects.toThrow('Not a file: src');
+        await expect(runtime.readSymbolBody({ symbol: '../outside.cs:1' })).rejects.toThrow(/escapes workspace/);
+    });
+    it('exports the call graph and module graph as Graphviz DOT and Mermaid', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src', 'Pricing'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'Cart.php'), [
+            '<?php',
+            'namespace Shop;',
+            '',
+            'use Shop\\Pricing\\Tax;',
+            '',
+            'class Cart',
+            '{',
+            '    public function add(string $sku, int $price): void',
+            '    {',
+            '        $this->total += Tax::apply($price);',
+            '        $this->log($sku);',
+            '        $this->log($sku);',
+            '    }',
+            '',
+            '    private function log(string $sku): void',
+            '    {',
+            '        audit($sku);',
+            '    }',
+            '}',
+            '',
+            'function audit(string $sku): void',
+            '{',
+            '}',
+        ].join('\n'), 'utf8');
+        await writeFile(join(tempDir, 'src', 'Pricing', 'Tax.php'), [
+            '<?php',
+            'namespace Shop\\Pricing;',
+            '',
+            'class Tax',
+            '{',
+            '    public static function apply(int $price): int',
+            '    {',
+            '        return round($price);',
+            '    }',
+            '}',
+        ].join('\n'), 'utf8');
+        await writeFile(join(tempDir, 'src', 'app.ts'), "import { cart } from './cart.js';\nimport express from 'express';\n", 'utf8');
+        await writeFile(join(tempDir, 'src', 'cart.ts'), 'export const cart = 1;\n', 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await runtime.indexCode({ paths: ['src'] });
+        const dot = await runtime.exportCodeGraph({ graph: 'calls', format: 'dot' });
+        expect(dot).toMatchObject({ nodes: 4, edges: 3, unresolved: 0 });
+        expect(dot.content).toContain('  subgraph "cluster_src/Cart.php" {\n    label="src/Cart.php";\n    "src/Cart.php:8" [label="Shop.Cart.add"];');
+        expect(dot.content).toContain('  "src/Cart.php:8" -> "src/Cart.php:15" [label="2"];\n  "src/Cart.php:8" -> "src/Pricing/Tax.php:6";\n  "src/Cart.php:15" -> "src/Cart.php:21";\n}');
+        const mermaid = await runtime.exportCodeGraph({ graph: 'calls', format: 'mermaid', paths: ['src/Pricing'] });
+        expect(mermaid.content).toBe([
+            'flowchart LR',
+            '  subgraph g0["src/Cart.php"]',
+            '    n0["Shop.Cart.add"]',
+            '  end',
+            '  subgraph g1["src/Pricing/Tax.php"]',
+            '    n1["Shop.Pricing.Tax.apply"]',
+            '  end',
+            '  n0 --> n1',
+            '',
+        ].join('\n'));
+        const modules = await runtime.exportCodeGraph({ graph: 'modules', format: 'mermaid', paths: ['src/app.ts'] });
+        expect(modules).toMatchObject({ nodes: 2, edges: 1, unresolved: 1 });
+        expect(modules.content).toBe('flowchart LR\n  n0["src/app.ts"]\n  n1["src/cart.ts"]\n  n0 --> n1\n');
+    });
+    it('walks incoming and outgoing call hierarchies, expanding each function once', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'Cart.php'), [
+            '<?php',
+            'namespace Shop;',
+            '',
+            'class Cart',
+            '{',
+            '    public function checkout(): void',
+            '    {',
+            '        $this->add(1);',
+            '        $this->total();',
+            '    }',
+            '',
+            '    public function add(int $price): void',
+            '    {',
+            '        $this->total();',
+            '    }',
+            '',
+            '    public function total(): int',
+            '    {',
+            '        return audit();',
+            '    }',
+            '}',
+            '',
+            'function audit(): int',
+            '{',
+            '    return 0;',
+            '}',
+        ].join('\n'), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await runtime.indexCode();
+        const incoming = await runtime.buildCallHierarchy({ symbol: 'Shop.audit' });
+        expect(incoming).toMatchObject({ root: { id: 'src/Cart.php:23', name: 'Shop.audit' }, direction: 'incoming', depth: 3, functions: 3, unresolved: 0 });
+        const total = incoming.items[0];
+        expect([total.name, total.calls, total.via]).toEqual(['Shop.Cart.total', 1, ['same-file']]);
+        expect(total.children.map((item) => [item.name, item.repeated])).toEqual([['Shop.Cart.checkout', undefined], ['Shop.Cart.add', undefined]]);
+        expect(total.children[1].children.map((item) => [item.name, item.repeated, item.children])).toEqual([['Shop.Cart.checkout', true, []]]);
+        const outgoing = await runtime.buildCallHierarchy({ symbol: 'src/Cart.php:6', direction: 'outgoing', depth: 1 });
+        expect(outgoing.items.map((item) => [item.name, item.children.length])).toEqual([['Shop.Cart.add', 0], ['Shop.Cart.total', 0]]);
+        expect(outgoing.functions).toBe(2);
+        await expect(runtime.buildCallHierarchy({ symbol: 'audit', direction: 'sideways' })).rejects.toThrow('Unknown call hierarchy direction "sideways"');
+    });
+    it('streams each parsed file to onFile and reloads a running index daemon', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'lib'), { recursive: true });
+        await writeFile(join(tempDir, 'Dockerfile'), SAMPLE_DOCKERFILE, 'utf8');
+        await writeFile(join(tempDir, 'lib', 'cart.rb'), 'class Cart\n  def add(item)\n  end\nend\n', 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const server = await runtime.startIndexServer();
+        try {
+            expect(server.status()).toMatchObject({ filesIndexed: 0 });
+            const streamed = [];
+            const result = await runtime.indexCode({
+                onFile: async (entry) => {
+                    await new Promise((resolve) => setImmediate(resolve));
+                    streamed.push(...entry.symbols.map((symbol) => `${entry.file}:${symbol.name}`));
+                },
+            });
+            expect(streamed.slice(-2)).toEqual(['lib/cart.rb:Cart', 'lib/cart.rb:add']);
+            expect(streamed).toEqual(expect.arrayContaining(['Dockerfile:80', 'Dockerfile:443']));
+            expect(result.symbolCount).toBe(streamed.length);
+            expect(server.status()).toMatchObject({ filesIndexed: 2, symbolCount: streamed.length });
+            expect((await runtime.searchCodeSymbols({ kind: 'method' })).map((symbol) => `${symbol.file}:${symbol.name}`)).toEqual(['lib/cart.rb:add']);
+            // The build spilled each file to disk as it was parsed and wrote the index from there.
+            const indexDir = join(tempDir, '.automatosx', 'index');
+            expect(await readdir(indexDir)).toEqual(['code-index.json']);
+            expect(JSON.parse(await readFile(join(indexDir, 'code-index.json'), 'utf8')).files.map((entry) => entry.file)).toEqual(['Dockerfile', 'lib/cart.rb']);
+        }
+        finally {
+            await server.close();
+        }
+    });
+    it('writes the same index within a memory limit by spilling parsed files to disk', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src'), { recursive: true });
+        await mkdir(join(tempDir, 'lib'), { recursive: true });
+        await writeFile(join(tempDir, 'Dockerfile'), SAMPLE_DOCKERFILE, 'utf8');
+        await writeFile(join(tempDir, 'src', 'Order.cs'), SAMPLE_CSHARP_ORDER, 'utf8');
+        await writeFile(join(tempDir, 'src', 'Order.Factory.cs'), SAMPLE_CSHARP_PARTIAL, 'utf8');
+        await writeFile(join(tempDir, 'lib', 'cart.rb'), 'class Cart\n  def add(item)\n  end\nend\n', 'utf8');
+        const indexPath = join(tempDir, '.automatosx', 'index', 'code-index.json');
+        const withoutTimestamp = async () => (await readFile(indexPath, 'utf8')).replace(/"generatedAt": "[^"]+"/, '');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const unbounded = await runtime.indexCode();
+        const expected = await withoutTimestamp();
+        // 2 KB: 256-byte batches, and files over 512 bytes are skipped.
+        const bounded = await runtime.indexCode({ maxMemoryMb: 2 / 1024, paths: ['lib', 'src'] });
+        expect(bounded.warnings).toEqual([expect.stringContaining('Skipped src/Order.cs (1 KB): too large to parse')]);
+        expect(bounded).toMatchObject({ filesIndexed: 2, languages: { ruby: 1, csharp: 1 } });
+        await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({ codeIntel: { maxMemoryMb: 0.01 } }), 'utf8');
+        const configured = await runtime.indexCode();
+        expect(configured).toMatchObject({ filesIndexed: unbounded.filesIndexed, symbolCount: unbounded.symbolCount, languages: unbounded.languages });
+        expect(await withoutTimestamp()).toBe(expected);
+        expect((await readdir(join(tempDir, '.automatosx', 'index'))).sort()).toEqual(['code-index.json']);
+    });
+    it('searches symbols fuzzily with kind, language, path and qualified-path queries', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src', 'Pipeline'), { recursive: true });
+        await mkdir(join(tempDir, 'lib'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'Pipeline', 'DataProcessor.php'), [
+            '<?php',
+            'namespace App\\Pipeline;',
+            '',
+            'interface Processor',
+            '{',
+            '    public function process(array $rows): array;',
+            '}',
+            '',
+            'class DataProcessor implements Processor',
+            '{',
+            '    public function process(array $rows): array',
+            '    {',
+            '        return $rows;',
+            '    }',
+            '}',
+        ].join('\n'), 'utf8');
+        await writeFile(join(tempDir, 'lib', 'data_proc.rb'), 'module DataProc\n  def self.process(rows)\n  end\nend\n', 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await expect(runtime.searchSymbols({ query: 'DatProc' })).rejects.toThrow('Run "ax code index" first');
+        await runtime.indexCode();
+        const fuzzy = await runtime.searchSymbols({ query: 'DatProc' });
+        expect(fuzzy.map((hit) => hit.symbol.name)).toEqual(['DataProc', 'DataProcessor']);
+        expect(fuzzy[1].matches).toEqual([0, 1, 2, 4, 5, 6, 7]);
+        const classes = await runtime.searchSymbols({ query: 'kind:class lang:php DatProc' });
+        expect(classes.map((hit) => hit.symbol.name)).toEqual(['DataProcessor']);
+        const scoped = await runtime.searchSymbols({ query: 'path:src process' });
+        expect(scoped.map((hit) => `${hit.symbol.kind} ${hit.symbol.name}`)).toEqual(['method process', 'method process', 'class Processor', 'class DataProcessor']);
+        const qualified = await runtime.searchSymbols({ query: 'Pipeline::DataProcessor::process' });
+        expect(qualified.map((hit) => `${hit.symbol.container}.${hit.symbol.name}`)).toEqual(['App\\Pipeline\\DataProcessor.process']);
+        expect(parseSymbolSearchQuery('kind:trait  lang:Rust path:./crates/core/ parser::Parser::new')).toEqual({
+            name: 'new',
+            qualifier: ['parser', 'Parser'],
+            kind: 'trait',
+            language: 'rust',
+            paths: ['crates/core'],
+        });
+        await expect(runtime.searchSymbols({ query: 'kind:widget' })).rejects.toThrow('Unknown symbol kind "widget"');
+        const server = await runtime.startIndexServer();
+        try {
+            expect(await runtime.searchSymbols({ query: 'DatProc', limit: 1 })).toEqual(fuzzy.slice(0, 1));
+        }
+        finally {
+            await server.close();
+        }
+    });
+    it('describes symbol search hits with qualified names, signatures, docs and spans', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'src'), { recursive: true });
+        await writeFile(join(tempDir, 'src', 'Parser.php'), [
+            '<?php',
+            'namespace App\\Parsing;',
+            '',
+            'class Parser',
+            '{',
+            '    /**',
+            '     * Parses one document into nodes.',
+            '     * @param string $input',
+            '     */',
+            '    public function parse(',
+            '        string $input,',
+            '    ): array {',
+            '        return [];',
+            '    }',
+            '}',
+        ].join('\n'), 'utf8');
+        await writeFile(join(tempDir, 'src', 'parse_all.rb'), [
+            'module Batch',
+            '  # Parses every document.',
+            '  def self.parse_all(documents)',
+            '    documents',
+            '  end',
+            'end',
+        ].join('\n'), 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await runtime.indexCode();
+        const [described] = await runtime.describeSymbols({ query: 'parse', kind: 'method', language: 'php' });
+        expect(described).toMatchObject({
+            name: 'parse',
+            qualifiedName: 'App.Parsing.Parser.parse',
+            kind: 'method',
+            language: 'php',
+            file: 'src/Parser.php',
+            line: 10,
+            endLine: 14,
+            signature: 'public function parse(string $input,): array',
+            doc: 'Parses one document into nodes.',
+        });
+        expect(described?.span?.start).toMatchObject({ line: 10, column: 5 });
+        expect((await runtime.describeSymbols({ query: 'parse' })).map((result) => result.qualifiedName)).toEqual([
+            'App.Parsing.Parser.parse',
+            'Batch.parse_all',
+            'App.Parsing.Parser',
+        ]);
+        expect((await runtime.describeSymbols({ query: 'parse', language: 'ruby', limit: 1 }))[0]).toMatchObject({
+            signature: 'def self.parse_all(documents)',
+            doc: 'Parses every document.',
+        });
+        expect((await runtime.describeSymbols({ kind: 'class' })).map((result) => result.signature)).toEqual(['class Parser']);
+        await expect(runtime.describeSymbols({ kind: 'widget' })).rejects.toThrow('Unknown symbol kind "widget"');
+    });
+    it('reuses parsed symbols from the content-hash parse cache across runs', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'lib'), { recursive: true });
+        await writeFile(join(tempDir, 'Dockerfile'), SAMPLE_DOCKERFILE, 'utf8');
+        await writeFile(join(tempDir, 'lib', 'cart.rb'), 'class Cart\n  def add(item)\n  end\nend\n', 'utf8');
+        const readIndexedFiles = async () => JSON.parse(await readFile(join(tempDir, '.automatosx', 'index', 'code-index.json'), 'utf8')).files;
+        const originalCacheDir = process.env.AUTOMATOSX_CACHE_DIR;
+        process.env.AUTOMATOSX_CACHE_DIR = join(tempDir, 'cache');
+        try {
+            const runtime = createSharedRuntimeService({ basePath: tempDir });
+            const first = await runtime.indexCode();
+            expect(first).toMatchObject({ filesIndexed: 2, cached: 0 });
+            const firstIndex = await readIndexedFiles();
+            const second = await runtime.indexCode();
+            expect(second).toMatchObject({ filesIndexed: 2, symbolCount: first.symbolCount, cached: 2 });
+            expect(await readIndexedFiles()).toEqual(firstIndex);
+            await writeFile(join(tempDir, 'lib', 'cart.rb'), 'class Cart\n  def add(item)\n  end\n\n  def total\n  end\nend\n', 'utf8');
+            expect(await runtime.indexCode()).toMatchObject({ cached: 1, symbolCount: first.symbolCount + 1 });
+            await mkdir(join(tempDir, '.automatosx'), { recursive: true });
+            await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({ codeIntel: { parseCache: false } }), 'utf8');
+            expect((await runtime.indexCode()).cached).toBeUndefined();
+        }
+        finally {
+            if (originalCacheDir === undefined) {
+                delete process.env.AUTOMATOSX_CACHE_DIR;
+            }
+            else {
+                process.env.AUTOMATOSX_CACHE_DIR = originalCacheDir;
+            }
+        }
+    });
+    it('reads failing tests out of cargo test, Jest, Vitest and pytest output', () => {
+        const cargo = [
+            'running 2 tests',
+            'test tests::adds ... FAILED',
+            'test tests::subtracts ... ok',
+            '',
+            'failures:',
+            '',
+            '---- tests::adds stdout ----',
+            "thread 'tests::adds' panicked at src/lib.rs:10:9:",
+            'assertion `left == right` failed',
+            '  left: 4',
+            ' right: 5',
+            'note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace',
+            '',
+            '---- tests::legacy stdout ----',
+            "thread 'tests::legacy' panicked at 'explicit panic', src/old.rs:3:5",
+            '',
+            'failures:',
+            '    tests::adds',
+        ].join('\n');
+        expect(parseTestFailures(cargo)).toEqual([
+            { framework: 'cargo', name: 'tests::adds', file: 'src/lib.rs', line: 10, message: 'assertion `left == right` failed\nleft: 4\nright: 5' },
+            { framework: 'cargo', name: 'tests::legacy', file: 'src/old.rs', line: 3, message: 'explicit panic' },
+        ]);
+        const jest = [
+            'FAIL src/cart.test.js',
+            '  \u001b[1m● Cart › adds items\u001b[22m',
+            '',
+            '    expect(received).toBe(expected) // Object.is equality',
+            '',
+            '    Expected: 3',
+            '    Received: 2',
+            '',
+            '      10 |   const cart = new Cart();',
+            '    > 12 |   expect(cart.total()).toBe(3);',
+            '         |                        ^',
+            '',
+            '      at Object.<anonymous> (src/cart.test.js:12:24)',
+            '',
+            'Tests:       1 failed, 3 passed, 4 total',
+        ].join('\n');
+        expect(parseTestFailures(jest)).toEqual([{
+            framework: 'jest',
+            name: 'Cart › adds items',
+            file: 'src/cart.test.js',
+            line: 12,
+            message: 'expect(received).toBe(expected) // Object.is equality\nExpected: 3\nReceived: 2',
+        }]);
+        const vitest = [
+            ' FAIL  src/cart.test.ts > Cart > adds items',
+            'AssertionError: expected 2 to be 3 // Object.is equality',
+            ' ❯ src/cart.test.ts:12:24',
+            '     12|   expect(cart.total()).toBe(3);',
+            '⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯⎯[1/1]⎯',
+        ].join('\n');
+        expect(parseTestFailures(vitest)).toEqual([{
+            framework: 'vitest',
+            name: 'Cart > adds items',
+            file: 'src/cart.test.ts',
+            line: 12,
+            message: 'AssertionError: expected 2 to be 3 // Object.is equality',
+        }]);
+        const pytest = [
+            '=================================== FAILURES ===================================',
+            '___________________________ TestCart.test_total ____________________________',
+            '',
+            '    def test_total(self):',
+            '>       assert total([2, 3]) == 6',
+            'E       assert 5 == 6',
+            '',
+            'tests/test_cart.py:8: AssertionError',
+            '___________________________ test_empty[none] ____________________________',
+            'tests/test_cart.py:14: AssertionError',
+            '=========================== short test summary info ============================',
+            'FAILED tests/test_cart.py::TestCart::test_total - assert 5 == 6',
+            'FAILED tests/test_cart.py::test_empty[none]',
+            '========================= 2 failed, 1 passed in 0.03s ==========================',
+        ].join('\n');
+        expect(parseTestFailures(pytest)).toEqual([
+            { framework: 'pytest', name: 'TestCart.test_total', file: 'tests/test_cart.py', line: 8, message: 'assert 5 == 6' },
+            { framework: 'pytest', name: 'test_empty[none]', file: 'tests/test_cart.py', line: 14, message: '' },
+        ]);
+        expect(parseTestFailures('All 12 tests passed.')).toEqual([]);
+    });
+    it('greps file contents through the trigram index, reading only files that can match', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'crates', 'parser'), { recursive: true });
+        await mkdir(join(tempDir, 'docs'), { recursive: true });
+        await writeFile(join(tempDir, 'crates', 'parser', 'lib.rs'), 'pub fn parse_expr(input: &str) {}\npub fn parse_stmt(input: &str) {}\n', 'utf8');
+        await writeFile(join(tempDir, 'crates', 'lexer.rs'), 'pub fn tokenize(input: &str) {}\n', 'utf8');
+        await writeFile(join(tempDir, 'docs', 'notes.md'), 'Call Parse_Expr before parse_stmt.\n', 'utf8');
+        await writeFile(join(tempDir, 'logo.png'), Buffer.from([0x89, 0x50, 0x4e, 0x47, 0x00, 0x70, 0x61, 0x72, 0x73, 0x65]));
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        const literal = await runtime.searchText({ pattern: 'parse_expr' });
+        expect(literal).toMatchObject({ filesIndexed: 3, filesScanned: 2, truncated: false });
+        expect(literal.matches).toEqual([
+            { file: 'crates/parser/lib.rs', line: 1, column: 8, text: 'pub fn parse_expr(input: &str) {}' },
+            { file: 'docs/notes.md', line: 1, column: 6, text: 'Call Parse_Expr before parse_stmt.' },
+        ]);
+        expect((await runtime.searchText({ pattern: 'Parse_Expr', caseSensitive: true })).matches.map((match) => match.file)).toEqual(['docs/notes.md']);
+        const regex = await runtime.searchText({ pattern: 'fn\\s+parse_\\w+\\(', regex: true });
+        expect(regex.filesScanned).toBe(2);
+        expect(regex.matches.map((match) => `${match.file}:${match.line}`)).toEqual(['crates/parser/lib.rs:1', 'crates/parser/lib.rs:2']);
+        expect((await runtime.searchText({ pattern: 'fn \\w+', regex: true, paths: ['./crates/'] })).filesScanned).toBe(2);
+        const truncated = await runtime.searchText({ pattern: 'input', limit: 2 });
+        expect(truncated).toMatchObject({ truncated: true, matches: [{ file: 'crates/lexer.rs', line: 1 }, { file: 'crates/parser/lib.rs', line: 1 }] });
+        await writeFile(join(tempDir, 'crates', 'lexer.rs'), 'pub fn parse_token(input: &str) {}\n', 'utf8');
+        expect((await runtime.searchText({ pattern: 'parse_token' })).matches).toEqual([]);
+        expect((await runtime.searchText({ pattern: 'parse_token', reindex: true })).matches.map((match) => match.file)).toEqual(['crates/lexer.rs']);
+        await expect(runtime.searchText({ pattern: '(unclosed', regex: true })).rejects.toThrow('Invalid regular expression');
+        expect(requiredLiterals('fn\\s+parse_(\\w+)\\(')).toEqual([['parse_']]);
+        expect(requiredLiterals('colou?r|hues+')).toEqual([['colo'], ['hues']]);
+        expect(requiredLiterals('impl\\.Display|\\w+')).toBeUndefined();
+    });
+    it('fuses memory, symbol and text rankings in a hybrid search', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'lib'), { recursive: true });
+        await mkdir(join(tempDir, 'docs'), { recursive: true });
+        await writeFile(join(tempDir, 'lib', 'retry.rb'), 'class RetryPolicy\n  def backoff_delay(attempt)\n    2 ** attempt\n  end\nend\n', 'utf8');
+        await writeFile(join(tempDir, 'docs', 'ops.md'), 'Retries use exponential backoff.\n', 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await runtime.storeMemory({ key: 'decision:retry', value: 'Retry backoff is capped at 30 seconds' });
+        const unindexed = await runtime.hybridSearch({ query: 'retry backoff' });
+        expect(unindexed.warnings).toEqual([expect.stringContaining('ax code index')]);
+        expect(unindexed.results.map((result) => result.sources)).toEqual([['memory'], ['text'], ['text'], ['text']]);
+        await runtime.indexCode();
+        const question = await runtime.hybridSearch({ query: 'where is the retry backoff computed', k: 3 });
+        expect(question.terms).toEqual(['retry', 'backoff', 'computed']);
+        expect(question.warnings).toEqual([]);
+        expect(question.results.map((result) => [result.sources.join('+'), result.memory?.key ?? `${result.file}:${result.line} ${result.symbol?.name}`])).toEqual([
+            ['memory', 'decision:retry'],
+            ['symbol+text', 'lib/retry.rb:1 RetryPolicy'],
+            ['symbol+text', 'lib/retry.rb:2 backoff_delay'],
+        ]);
+        expect(question.results[1].text).toBe('class RetryPolicy');
+        const identifier = await runtime.hybridSearch({ query: 'backoff_delay' });
+        expect(identifier.terms).toEqual(['backoff_delay']);
+        expect(identifier.results.map((result) => `${result.file}:${result.line}`)).toEqual(['lib/retry.rb:2']);
+        await expect(runtime.hybridSearch({ query: '  ' })).rejects.toThrow('must not be empty');
+    });
+    it('embeds declaration-sized code chunks into memory and re-embeds only what changed', async () => {
+        const tempDir = createTempDir();
+        tempDirs.push(tempDir);
+        await mkdir(join(tempDir, 'lib'), { recursive: true });
+        const retrySource = '# Retry helpers\nrequire "json"\n\nclass RetryPolicy\n  def initialize(max)\n    @max = max\n  end\n\n  def backoff_delay(attempt)\n    2 ** attempt\n  end\nend\n';
+        await writeFile(join(tempDir, 'lib', 'retry.rb'), retrySource, 'utf8');
+        await writeFile(join(tempDir, 'lib', 'cart.rb'), 'class Cart\nend\n', 'utf8');
+        const runtime = createSharedRuntimeService({ basePath: tempDir });
+        await runtime.indexCode();
+        const snapshot = JSON.parse(await readFile(join(tempDir, '.automatosx', 'index', 'code-index.json'), 'utf8'));
+        expect((await chunkCodeIndex(tempDir, snapshot, { paths: ['lib/retry.rb'] })).map((chunk) => `${chunk.id} ${chunk.symbol?.name ?? '-'}`)).toEqual([
+            'lib/retry.rb:1-3 -',
+            'lib/retry.rb:4-12 RetryPolicy',
+        ]);
+        expect((await chunkCodeIndex(tempDir, snapshot, { paths: ['lib/retry.rb'], maxLines: 4 })).map((chunk) => `${chunk.id} ${chunk.symbol?.name ?? '-'}`)).toEqual([
+            'lib/retry.rb:1-4 -',
+            'lib/retry.rb:5-7 initialize',
+            'lib/retry.rb:9-11 backoff_delay',
+            'lib/retry.rb:12-12 -',
+        ]);
+        const batches = [];
+        const embedder = {
+            backend: 'test',
+            model: 'length-v1',
+            embed: async (texts) => {
+                batches.push(texts);
+                return texts.map((text) => [text.length, text.split('\n').length]);
+            },
+        };
+        await expect(runtime.embedCode()).rejects.toThrow('No embedding backend configured');
+        expect(await runtime.embedCode({ embedder })).toEqual({ backend: 'test', model: 'length-v1', dimensions: 2, chunks: 3, embedded: 3, unchanged: 0, removed: 0 });
+        expect(batches[0][0]).toBe('lib/cart.rb\nclass Cart\nend');
+        const entry = await runtime.getMemory('lib/retry.rb:4-12', 'embeddings');
+        expect(entry).toMatchObject({
+            value: { file: 'lib/retry.rb', startLine: 4, endLine: 12, symbol: { name: 'RetryPolicy', kind: 'class' }, model: 'length-v1' },
+            embedding: [expect.any(Number), 10],
+        });
+        await writeFile(join(tempDir, 'lib', 'retry.rb'), retrySource.replace('2 ** attempt', '3 ** attempt'), 'utf8');
+        await rm(join(tempDir, 'lib', 'cart.rb'));
+        await runtime.indexCode();
+        expect(await runtime.embedCode({ embedder })).toMatchObject({ chunks: 2, embedded: 1, unchanged: 1, removed: 1 });
+        expect(batches.at(-1)).toEqual([expect.stringContaining('3 ** attempt')]);
+        expect(await runtime.getMemory('lib/cart.rb:1-2', 'embeddings')).toBeUndefined();
+        const requests = [];
+        const fetch = async (url, init) => {
+            requests.push({ url, body: JSON.parse(init.body), headers: init.headers });
+            const payload = url.endsWith('/api/embed')
+                ? { embeddings: [[0.1, 0.2]] }
+                : { data: [{ index: 1, embedding: [2] }, { index: 0, embedding: [1] }] };
+            return { ok: true, status: 200, json: async () => payload, text: async () => '' };
+        };
+        const ollama = createEmbedder({ backend: 'ollama' }, { env: { OLLAMA_HOST: 'http://gpu-box:11434/' }, fetch });
+        expect(await ollama.embed(['a'])).toEqual([[0.1, 0.2]]);
+        const openai = createEmbedder({ backend: 'openai', model: 'text-embedding-3-large' }, { env: { OPENAI_API_KEY: 'sk-test' }, fetch });
+        expect(await openai.embed(['a', 'b'])).toEqual([[1], [2]]);
+        expect(requests).toEqual([
+            { url: 'http://gpu-box:11434/api/embed', body: { model: 'nomic-embed-text', input: ['a'] }, headers: { 'content-type': 'application/json' } },
+            { url: 'https://api.openai.com/v1/embeddings', body: { model: 'text-embedding-3-large', input: ['a', 'b'] }, headers: { 'content-type': 'application/json', authorization: 'Bearer sk-test' } },
+        ]);
+        expect(() => createEmbedder({ backend: 'openai' }, { env: {} })).toThrow('OPENAI_API_KEY');
+    });
+});
@@ -1,11 +1,18 @@
-import { mkdirSync } from 'node:fs';
-import { rm, writeFile } from 'node:fs/promises';
+import { createVerify, generateKeyPairSync } from 'node:crypto';
+import { existsSync, mkdirSync } from 'node:fs';
+import { readFile, rm, stat, utimes, writeFile } from 'node:fs/promises';
 import { join } from 'node:path';
 import { execFile } from 'node:child_process';
+import { createServer, type IncomingMessage } from 'node:http';
+import type { AddressInfo } from 'node:net';
 import { promisify } from 'node:util';
 import { afterEach, describe, expect, it } from 'vitest';
+import { createStateStore, scopedNamespace } from '@defai.digital/state-store';
 import type { TraceRecord, TraceStore } from '@defai.digital/trace-store';
-import { createSharedRuntimeService } from '../src/index.js';
+import { createMemoryKey, createSharedRuntimeService, createWorkspaceCheckpoint, decodeMemoryKey, diffWorkspaceCheckpoint, explainConfigKey, findExecutable, formatConfigIssue, loadLayeredConfig, memoryCipherFor, parseHandoffContract, parsePipeline, restoreWorkspaceCheckpoint, validateLayeredConfig, validateWorkspaceConfig } from '../src/index.js';
+import { resolvePreCommitConfig, runPreCommitPipeline } from '../src/git-hooks.js';
+import { signAwsRequest } from '../src/provider-aws.js';
+import type { AgentRunner } from '../src/test-fix.js';
 
 const execFileAsync = promisify(execFile);
 
@@ -235,6 +242,54 @@ describe('shared runtime service', () => {
     expect((result.output as { content?: string })?.content).toContain('REAL:claude:');
   });
 
+  it('streams plain-text provider output to workflow and call listeners as it is written', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const slowPath = join(tempDir, 'slow-provider.mjs');
+    await writeFile(slowPath, [
+      "process.stdin.resume();",
+      "process.stdin.on('end', () => {",
+      "  if (process.argv[2] === 'json') { process.stdout.write(JSON.stringify({ success: true, content: 'Risk: low.' })); return; }",
+      "  process.stdout.write('\\n Risk: ');",
+      "  setTimeout(() => process.stdout.write('low.\\n'), 100);",
+      "});",
+    ].join('\n'), 'utf8');
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          claude: { command: 'node', args: [slowPath], protocol: 'raw-stdin' },
+          codex: { command: 'node', args: [slowPath, 'json'] },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+    await writeFile(join(tempDir, 'streamed.json'), `${JSON.stringify({
+      workflowId: 'streamed',
+      name: 'Streamed',
+      version: '1.0.0',
+      steps: [
+        { stepId: 'assess', type: 'prompt', config: { prompt: 'Assess the risk.', provider: 'claude' } },
+        { stepId: 'confirm', type: 'prompt', config: { prompt: 'Confirm the risk.', provider: 'codex' } },
+      ],
+    }, null, 2)}\n`, 'utf8');
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    const streamed: Array<[string, string | undefined]> = [];
+    const result = await runtime.runWorkflow({
+      workflowId: 'streamed',
+      workflowDir: tempDir,
+      onText: (text, stepId) => streamed.push([text, stepId]),
+    });
+    expect(result.success).toBe(true);
+    // The JSON executor's reply is one object, so only the plain-text step streams.
+    expect(streamed).toEqual([['Risk: ', 'assess'], ['low.\n', 'assess']]);
+
+    const chunks: string[] = [];
+    const call = await runtime.callProvider({ prompt: 'Assess the risk.', provider: 'claude', onText: (text) => chunks.push(text) });
+    expect(chunks).toEqual(['Risk: ', 'low.\n']);
+    expect(call.content).toBe('Risk: low.');
+  });
+
   it('runs top-level discussions through configured provider subprocesses when available', async () => {
     const tempDir = createTempDir();
     tempDirs.push(tempDir);
@@ -300,28 +355,1968 @@ describe('shared runtime service', () => {
     ].join('\n'), 'utf8');
     mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
     await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
-      providers: {
-        executors: {
-          claude: {
-            command: 'node',
-            args: [scriptPath],
-          },
-        },
-      },
+      providers: {
+        executors: {
+          claude: {
+            command: 'node',
+            args: [scriptPath],
+          },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+
+    const runtime = createSharedRuntimeService({ basePath: join(process.cwd(), 'tmp', 'unrelated-runtime-root') });
+    const result = await runtime.callProvider({
+      prompt: 'workspace scoped prompt',
+      provider: 'claude',
+      traceId: 'workspace-call-001',
+      basePath: tempDir,
+      surface: 'cli',
+    });
+
+    expect(result.success).toBe(true);
+    expect(result.executionMode).toBe('subprocess');
+    expect(result.content).toContain('WORKSPACE:claude:workspace scoped prompt');
+  });
+
+  it('adapts system prompts and stop sequences to each provider dialect at the executor boundary', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const echoPath = join(tempDir, 'echo-provider.mjs');
+    await writeFile(echoPath, [
+      "let input = '';",
+      "process.stdin.setEncoding('utf8');",
+      "process.stdin.on('data', (chunk) => { input += chunk; });",
+      "process.stdin.on('end', () => {",
+      "  if (process.argv[2] !== 'json') { process.stdout.write(input); return; }",
+      "  process.stdout.write(JSON.stringify({ success: true, content: input }));",
+      "});",
+    ].join('\n'), 'utf8');
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          claude: { command: 'node', args: [echoPath], protocol: 'raw-stdin' },
+          gemini: { command: 'node', args: [echoPath], protocol: 'raw-stdin' },
+          codex: { command: 'node', args: [echoPath, 'json'] },
+          local: { command: 'node', args: [echoPath], protocol: 'raw-stdin', promptDialect: 'openai' },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    const call = (provider: string, stopSequences?: string[]) => runtime.callProvider({
+      prompt: 'Review the diff.',
+      systemPrompt: 'You are a strict reviewer.',
+      provider,
+      stopSequences,
+    });
+
+    expect((await call('claude')).content).toBe('<instructions>\nYou are a strict reviewer.\n</instructions>\n\nReview the diff.');
+    expect((await call('local')).content).toBe('# Instructions\n\nYou are a strict reviewer.\n\n# Task\n\nReview the diff.');
+
+    const gemini = await call('gemini', ['END']);
+    expect(gemini.content).toBe('System instructions:\nYou are a strict reviewer.\n\n---\n\nReview the diff.');
+    expect(gemini.warnings).toContain('Stop sequences were ignored: the provider executor protocol cannot pass them.');
+
+    const codex = await call('codex', ['a', 'b', 'c', 'd', 'e']);
+    expect(JSON.parse(codex.content)).toMatchObject({
+      prompt: 'Review the diff.',
+      systemPrompt: 'You are a strict reviewer.',
+      promptDialect: 'openai',
+      stopSequences: ['a', 'b', 'c', 'd'],
+    });
+    expect(codex.warnings).toContain('Only the first 4 stop sequences were sent: the openai dialect accepts no more.');
+  });
+
+  it('tracks provider quotas and routes default calls to a fallback with remaining quota', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const scriptPath = join(process.cwd(), 'packages/shared-runtime/tests/mock-provider.mjs');
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        fallback: ['gemini'],
+        quotas: {
+          claude: { daily: { requests: 2 } },
+          gemini: { monthly: { tokens: 1000 } },
+        },
+        executors: {
+          claude: { command: 'node', args: [scriptPath] },
+          gemini: { command: 'node', args: [scriptPath] },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    const first = await runtime.callProvider({ prompt: 'first' });
+    const second = await runtime.callProvider({ prompt: 'second' });
+    const third = await runtime.callProvider({ prompt: 'third' });
+
+    expect(first.provider).toBe('claude');
+    expect(first.warnings).toEqual([]);
+    expect(second.provider).toBe('claude');
+    expect(second.warnings).toEqual(['Provider "claude" has exhausted its daily quota.']);
+    expect(third.provider).toBe('gemini');
+    expect(third.content).toContain('REAL:gemini:third');
+    expect(third.warnings).toEqual(['Provider "claude" has exhausted its daily quota; routed to "gemini".']);
+
+    const status = await runtime.getStatus();
+    expect(status.runtime.providerQuotas).toMatchObject([
+      { provider: 'claude', exhausted: true, daily: { requests: 2, requestLimit: 2, remainingRequests: 0, tokens: 16 } },
+      { provider: 'gemini', exhausted: false, monthly: { requests: 1, tokens: 8, tokenLimit: 1000, remainingTokens: 992 } },
+    ]);
+    const ledger = JSON.parse(await readFile(join(tempDir, '.automatosx', 'runtime', 'provider-usage.json'), 'utf8')) as {
+      providers: Record<string, unknown>;
+    };
+    expect(Object.keys(ledger.providers).sort()).toEqual(['claude', 'gemini']);
+  });
+
+  it('routes unpinned calls by cost, speed or quality policy, with agent profile overrides', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const scriptPath = join(process.cwd(), 'packages/shared-runtime/tests/mock-provider.mjs');
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        fallback: ['gemini', 'budget'],
+        routing: { policy: 'cheapest-capable' },
+        pricing: { budget: { input: 0.1, output: 0.4, quality: 5, tokensPerSecond: 300 } },
+        quotas: { budget: { daily: { requests: 1 } } },
+        executors: {
+          claude: { command: 'node', args: [scriptPath] },
+          gemini: { command: 'node', args: [scriptPath] },
+          budget: { command: 'node', args: [scriptPath] },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    const cheapest = await runtime.callProvider({ prompt: 'first' });
+    expect(cheapest).toMatchObject({ provider: 'budget', content: 'REAL:budget:first' });
+    expect(cheapest.warnings).toEqual(['Provider "budget" has exhausted its daily quota.']);
+    const afterQuota = await runtime.callProvider({ prompt: 'second' });
+    expect(afterQuota.provider).toBe('gemini');
+    expect(afterQuota.warnings).toEqual(['Provider "budget" has exhausted its daily quota; routed to "gemini".']);
+
+    await runtime.registerAgent({
+      agentId: 'architect',
+      name: 'Architect',
+      capabilities: ['design'],
+      metadata: { routing: { policy: 'quality-first', minQuality: 9, requires: ['vision'] } },
+    });
+    const architect = await runtime.runAgent({ agentId: 'architect', task: 'Design the cache.', traceId: 'routing-agent-001', surface: 'cli' });
+    // claude-sonnet-4-5 and gemini-2.5-pro tie on quality; the priority order breaks the tie.
+    expect(architect.provider).toBe('claude');
+    expect((await runtime.getTrace('routing-agent-001'))?.metadata?.routing).toEqual({ policy: 'quality-first', model: 'claude-sonnet-4-5' });
+
+    await runtime.registerAgent({
+      agentId: 'transcriber',
+      name: 'Transcriber',
+      capabilities: ['audio'],
+      metadata: { routing: { requires: ['audio'] } },
+    });
+    const transcriber = await runtime.runAgent({ agentId: 'transcriber', task: 'Transcribe the meeting.', surface: 'cli' });
+    expect(transcriber.provider).toBe('claude');
+    expect(transcriber.warnings).toContain('No configured provider meets the cheapest-capable routing requirements; used the priority order.');
+  });
+
+  it('records tokens, latency and cost per provider call and reports them by agent, provider or project', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const scriptPath = join(process.cwd(), 'packages/shared-runtime/tests/mock-provider.mjs');
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          claude: { command: 'node', args: [scriptPath] },
+          mystery: { command: 'node', args: [scriptPath] },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    const startedAt = new Date().toISOString();
+    await runtime.callProvider({ prompt: 'direct', provider: 'claude', traceId: 'usage-call-001' });
+    await runtime.callProvider({ prompt: 'unpriced', provider: 'mystery' });
+    await runtime.registerAgent({ agentId: 'architect', name: 'Architect', capabilities: ['design'] });
+    await runtime.runAgent({ agentId: 'architect', task: 'Design the cache.', provider: 'claude', traceId: 'usage-agent-001', rootTraceId: 'usage-run-001', surface: 'cli' });
+
+    const byProvider = await runtime.getUsageReport({ since: startedAt });
+    // The mock reports 3 input and 5 output tokens; claude is priced as claude-sonnet-4-5 at $3/$15 per million.
+    expect(byProvider.groups).toMatchObject([
+      { key: 'claude', calls: 2, failures: 0, inputTokens: 6, outputTokens: 10, unpricedCalls: 0 },
+      { key: 'mystery', calls: 1, costUsd: 0, unpricedCalls: 1 },
+    ]);
+    expect(byProvider.groups[0]!.costUsd).toBeCloseTo(2 * (3 * 3 + 5 * 15) / 1_000_000, 12);
+    expect(byProvider.total).toMatchObject({ calls: 3, inputTokens: 9, outputTokens: 15, unpricedCalls: 1 });
+
+    const byAgent = await runtime.getUsageReport({ by: 'agent' });
+    expect(byAgent.groups.map((group) => [group.key, group.calls])).toEqual([['(none)', 2], ['architect', 1]]);
+    const byProject = await runtime.getUsageReport({ by: 'project' });
+    expect(byProject.groups).toMatchObject([{ key: tempDir, calls: 3 }]);
+
+    expect((await runtime.getUsageReport({ traceId: 'usage-run-001' })).total.calls).toBe(1);
+    expect((await runtime.getUsageReport({ traceId: 'usage-call-001' })).total.calls).toBe(1);
+    expect((await runtime.getUsageReport({ since: new Date(Date.now() + 60_000).toISOString() })).total.calls).toBe(0);
+  });
+
+  it('replays cached provider responses on exact and semantic matches unless the cache is bypassed', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const scriptPath = join(process.cwd(), 'packages/shared-runtime/tests/mock-provider.mjs');
+    // Prompts about the release embed to the same vector; anything else points elsewhere.
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk) => { raw += chunk; });
+      request.on('end', () => {
+        const body = JSON.parse(raw) as { input: string[] };
+        response.writeHead(200, { 'content-type': 'application/json' });
+        response.end(JSON.stringify({ embeddings: body.input.map((text) => text.includes('release') ? [1, 0] : [0, 1]) }));
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const { port } = server.address() as AddressInfo;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: { claude: { command: 'node', args: [scriptPath] } },
+        cache: { ttlSeconds: 3600, semantic: { threshold: 0.9 } },
+      },
+      codeIntel: { embeddings: { backend: 'ollama', baseUrl: `http://127.0.0.1:${port}` } },
+    }, null, 2)}\n`, 'utf8');
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      const first = await runtime.callProvider({ prompt: 'Summarize the release.', provider: 'claude' });
+      expect(first.cache).toBeUndefined();
+      const exact = await runtime.callProvider({ prompt: '  Summarize   the release.\n', provider: 'claude', traceId: 'cache-call-001' });
+      expect(exact).toMatchObject({ success: true, content: 'REAL:claude:Summarize the release.', cache: { match: 'exact' } });
+      expect((await runtime.getTrace('cache-call-001'))?.metadata?.cache).toMatchObject({ match: 'exact' });
+      const semantic = await runtime.callProvider({ prompt: 'Summarize this release, please.', provider: 'claude' });
+      expect(semantic).toMatchObject({ content: 'REAL:claude:Summarize the release.', cache: { match: 'semantic', score: 1 } });
+      const other = await runtime.callProvider({ prompt: 'List open issues.', provider: 'claude' });
+      expect(other.cache).toBeUndefined();
+      const bypassed = await runtime.callProvider({ prompt: 'Summarize the release.', provider: 'claude', noCache: true });
+      expect(bypassed.cache).toBeUndefined();
+
+      // Replays are not billed: only the first, unrelated and bypassed calls reached the provider.
+      expect((await runtime.getUsageReport()).total.calls).toBe(3);
+      expect(await runtime.getProviderCacheStats()).toMatchObject({
+        enabled: true,
+        ttlSeconds: 3600,
+        semanticThreshold: 0.9,
+        entries: 2,
+        exactHits: 1,
+        semanticHits: 1,
+        savedInputTokens: 6,
+        savedOutputTokens: 10,
+      });
+      expect(await runtime.clearProviderCache()).toBe(2);
+      expect((await runtime.getProviderCacheStats()).entries).toBe(0);
+    } finally {
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('queues calls per provider and retries rate-limited ones after the wait the provider asks for', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    // Each entry answers one request: a 429 with its headers, or a reply after a delay.
+    const replies: Array<{ status: 429; headers: Record<string, string> } | { status: 200; delayMs: number }> = [
+      { status: 429, headers: { 'retry-after-ms': '60' } },
+      { status: 429, headers: {} },
+      { status: 200, delayMs: 0 },
+      { status: 200, delayMs: 150 },
+      { status: 200, delayMs: 0 },
+      { status: 429, headers: { 'retry-after': '3600' } },
+    ];
+    let served = 0;
+    const server = createServer((request, response) => {
+      request.resume();
+      request.on('end', () => {
+        const reply = replies[served++]!;
+        if (reply.status === 429) {
+          response.writeHead(429, { 'content-type': 'application/json', ...reply.headers });
+          response.end(JSON.stringify({ error: { message: 'Rate limit reached for requests' } }));
+          return;
+        }
+        setTimeout(() => {
+          response.writeHead(200, { 'content-type': 'text/event-stream' });
+          response.end(`data: ${JSON.stringify({ choices: [{ index: 0, delta: { content: 'ok' }, finish_reason: 'stop' }] })}\n\ndata: [DONE]\n\n`);
+        }, reply.delayMs);
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          groq: { api: 'openai', baseUrl: `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'llama-test' },
+        },
+        rateLimit: { baseDelayMs: 20, maxDelayMs: 1000, maxConcurrent: 1 },
+      },
+    }, null, 2)}\n`, 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      const startedAt = Date.now();
+      const retried = await runtime.callProvider({ prompt: 'First', provider: 'groq' });
+      expect(retried).toMatchObject({ success: true, content: 'ok' });
+      expect(retried.warnings).toContain('Provider "groq" was rate limited; the call went through after 2 retries.');
+      expect(Date.now() - startedAt).toBeGreaterThanOrEqual(60);
+
+      // With one call at a time, the second waits in the queue while the first is answered.
+      const slow = runtime.callProvider({ prompt: 'Slow', provider: 'groq' });
+      const queued = runtime.callProvider({ prompt: 'Queued', provider: 'groq' });
+      let depth = (await runtime.getStatus()).runtime.providerRateLimits;
+      for (let attempt = 0; attempt < 50 && depth[0]?.queued !== 1; attempt += 1) {
+        await new Promise((resolve) => setTimeout(resolve, 10));
+        depth = (await runtime.getStatus()).runtime.providerRateLimits;
+      }
+      expect(depth).toEqual([{ provider: 'groq', queued: 1, active: 1, consecutiveRateLimits: 0, lastRateLimitedAt: expect.any(String) }]);
+      expect((await Promise.all([slow, queued])).map((result) => result.success)).toEqual([true, true]);
+      expect((await runtime.getStatus()).runtime.providerRateLimits).toEqual([]);
+
+      // A wait longer than maxDelayMs fails the call, and later calls fail without reaching the provider.
+      const limited = await runtime.callProvider({ prompt: 'Limited', provider: 'groq' });
+      expect(limited.success).toBe(false);
+      expect(limited.error?.code).toBe('PROVIDER_RATE_LIMITED');
+      expect(limited.warnings).toContain('Provider "groq" is rate limited for 3600s.');
+      const elsewhere = await createSharedRuntimeService({ basePath: tempDir }).callProvider({ prompt: 'Elsewhere', provider: 'groq' });
+      expect(elsewhere.error).toMatchObject({ code: 'PROVIDER_RATE_LIMITED', message: expect.stringContaining('Provider "groq" is rate limited until') });
+      expect(served).toBe(replies.length);
+      const [cooling] = (await runtime.getStatus()).runtime.providerRateLimits;
+      expect(cooling).toMatchObject({ provider: 'groq', queued: 0, active: 0, consecutiveRateLimits: 1 });
+      expect(Date.parse(cooling!.cooldownUntil!)).toBeGreaterThan(Date.now() + 3_000_000);
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('probes provider health and routes around providers whose credentials were rejected', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const requests: string[] = [];
+    const server = createServer((request, response) => {
+      requests.push(`${request.method} ${request.url}`);
+      request.resume();
+      request.on('end', () => {
+        if (request.headers.authorization !== 'Bearer sk-test') {
+          response.writeHead(401, { 'content-type': 'application/json' });
+          response.end(JSON.stringify({ error: { message: 'Incorrect API key provided' } }));
+          return;
+        }
+        if (request.method === 'GET') {
+          response.writeHead(200, {
+            'content-type': 'application/json',
+            'x-ratelimit-limit-requests': '100',
+            'x-ratelimit-remaining-requests': '99',
+            'x-ratelimit-remaining-tokens': '39000',
+            'x-ratelimit-reset-requests': '1m30s',
+          });
+          response.end(JSON.stringify({ data: [{ id: 'llama-test' }] }));
+          return;
+        }
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        response.end(`data: ${JSON.stringify({ choices: [{ index: 0, delta: { content: 'ok' }, finish_reason: 'stop' }] })}\n\ndata: [DONE]\n\n`);
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          claude: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_STALE_KEY', model: 'llama-test' },
+          groq: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'llama-test' },
+          localcli: { command: 'ax-test-missing-provider-cli' },
+        },
+        fallback: ['groq'],
+      },
+    }, null, 2)}\n`, 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+    process.env.AX_TEST_STALE_KEY = 'sk-expired';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      expect((await runtime.getStatus()).runtime.providerHealth).toEqual([]);
+
+      const status = await runtime.getStatus({ probe: true });
+      const [claude, groq, localcli] = status.runtime.providerHealth;
+      expect(claude).toMatchObject({ provider: 'claude', status: 'unhealthy', probe: 'http', auth: 'expired', errorCode: 'PROVIDER_AUTH_FAILED' });
+      expect(claude!.error).toContain('Incorrect API key provided');
+      expect(groq).toMatchObject({
+        provider: 'groq',
+        status: 'healthy',
+        probe: 'http',
+        auth: 'ok',
+        quota: { requestsRemaining: 99, requestLimit: 100, tokensRemaining: 39000 },
+      });
+      expect(Date.parse(groq!.quota!.resetsAt!) - Date.parse(groq!.checkedAt)).toBeGreaterThan(80_000);
+      expect(localcli).toMatchObject({ provider: 'localcli', status: 'unhealthy', probe: 'command', errorCode: 'PROVIDER_COMMAND_NOT_FOUND' });
+      expect(requests).toEqual(['GET /v1/models', 'GET /v1/models']);
+
+      // Later processes read the recorded probes: the router skips the provider whose key was rejected.
+      const routed = await createSharedRuntimeService({ basePath: tempDir }).callProvider({ prompt: 'Hello' });
+      expect(routed).toMatchObject({ success: true, provider: 'groq', content: 'ok' });
+      expect(routed.warnings).toContain('Provider "claude" failed its last health probe (its credentials were rejected); routed to "groq".');
+      expect((await runtime.getStatus()).runtime.providerHealth).toEqual(status.runtime.providerHealth);
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      delete process.env.AX_TEST_STALE_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('has a fallback provider continue a reply the first provider dropped partway through', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const continuationPrompts: string[] = [];
+    const server = createServer((request, response) => {
+      let body = '';
+      request.on('data', (chunk: Buffer) => {
+        body += chunk.toString('utf8');
+      });
+      request.on('end', () => {
+        const parsed = JSON.parse(body) as { model: string; messages: Array<{ role: string; content: string }> };
+        const delta = (content: string) => `data: ${JSON.stringify({ choices: [{ index: 0, delta: { content }, finish_reason: null }] })}\n\n`;
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        if (parsed.model === 'flaky-model') {
+          response.write(delta('The answer '));
+          setTimeout(() => response.destroy(), 20);
+          return;
+        }
+        continuationPrompts.push(parsed.messages.at(-1)!.content);
+        response.end(`${delta('is 42.')}data: [DONE]\n\n`);
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          flaky: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'flaky-model' },
+          steady: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'steady-model' },
+        },
+        fallback: ['unconfigured', 'steady'],
+      },
+    }, null, 2)}\n`, 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      const streamed: string[] = [];
+      const result = await runtime.callProvider({ prompt: 'What is the answer?', provider: 'flaky', onText: (text) => streamed.push(text) });
+      expect(result).toMatchObject({ success: true, provider: 'steady', content: 'The answer is 42.' });
+      expect(streamed.join('')).toBe('The answer is 42.');
+      expect(continuationPrompts).toHaveLength(1);
+      expect(continuationPrompts[0]).toContain('What is the answer?');
+      expect(continuationPrompts[0]).toContain('<partial-reply>\nThe answer \n</partial-reply>');
+      expect(result.failovers).toEqual([{
+        from: 'flaky',
+        to: 'steady',
+        errorCode: 'PROVIDER_UNREACHABLE',
+        error: expect.any(String),
+        partialChars: 11,
+        at: expect.any(String),
+      }]);
+      expect(result.warnings).toContain('Provider "flaky" failed after 11 characters of its reply (PROVIDER_UNREACHABLE); "steady" continued it.');
+      const trace = await runtime.getTrace(result.traceId);
+      expect(trace?.metadata?.providerFailovers).toEqual(result.failovers);
+
+      // With failover off, the cut-off reply fails the call.
+      await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+        providers: {
+          executors: {
+            flaky: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'flaky-model' },
+            steady: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'steady-model' },
+          },
+          fallback: ['steady'],
+          failover: { enabled: false },
+        },
+      }, null, 2)}\n`, 'utf8');
+      const failed = await runtime.callProvider({ prompt: 'What is the answer?', provider: 'flaky' });
+      expect(failed).toMatchObject({ success: false, error: { code: 'PROVIDER_UNREACHABLE' } });
+      expect(failed.failovers).toBeUndefined();
+      expect(continuationPrompts).toHaveLength(1);
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('sends consensus calls to several providers and returns the fastest or the judged answer', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const judgePrompts: string[] = [];
+    const judgeReplies = ['MERGED: A, B\nUse a map, keyed by id.', 'CHOSEN: B'];
+    const server = createServer((request, response) => {
+      let body = '';
+      request.on('data', (chunk: Buffer) => {
+        body += chunk.toString('utf8');
+      });
+      request.on('end', () => {
+        const parsed = JSON.parse(body) as { model: string; messages: Array<{ content: string }> };
+        let content = parsed.model === 'alpha-model' ? 'Use a map.' : 'Key it by id.';
+        if (parsed.model === 'judge-model') {
+          judgePrompts.push(parsed.messages.at(-1)!.content);
+          content = judgeReplies[judgePrompts.length - 1]!;
+        }
+        setTimeout(() => {
+          response.writeHead(200, { 'content-type': 'text/event-stream' });
+          response.end([
+            `data: ${JSON.stringify({ choices: [{ index: 0, delta: { content }, finish_reason: 'stop' }] })}`,
+            `data: ${JSON.stringify({ choices: [], usage: { prompt_tokens: 10, completion_tokens: 5 } })}`,
+            'data: [DONE]',
+            '',
+          ].join('\n\n'));
+        }, parsed.model === 'alpha-model' ? 150 : 0);
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          alpha: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'alpha-model' },
+          beta: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'beta-model' },
+          broken: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_UNSET_KEY', model: 'broken-model' },
+          umpire: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'judge-model' },
+        },
+        consensus: { providers: ['alpha', 'beta', 'broken'], judge: 'umpire' },
+      },
+    }, null, 2)}\n`, 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      const fastest = await runtime.callProvider({ prompt: 'How do I dedupe?', consensus: { strategy: 'fastest', providers: ['alpha', 'beta'] } });
+      expect(fastest).toMatchObject({ success: true, provider: 'beta', content: 'Key it by id.' });
+      expect(fastest.consensus).toMatchObject({
+        strategy: 'fastest',
+        verdict: 'fastest',
+        candidates: [
+          { provider: 'alpha', label: 'A', success: false, errorCode: 'PROVIDER_CANCELLED', chosen: false },
+          { provider: 'beta', label: 'B', success: true, chosen: true },
+        ],
+      });
+
+      // The providers and judge come from providers.consensus; the failed provider is left out of the judging.
+      const merged = await runtime.callProvider({ prompt: 'How do I dedupe?', consensus: { strategy: 'judge' } });
+      expect(merged).toMatchObject({ success: true, provider: 'umpire', content: 'Use a map, keyed by id.' });
+      expect(merged.consensus).toMatchObject({ strategy: 'judge', verdict: 'merged', judge: 'umpire' });
+      expect(merged.consensus!.candidates.map((candidate) => [candidate.provider, candidate.success, candidate.chosen])).toEqual([
+        ['alpha', true, true],
+        ['beta', true, true],
+        ['broken', false, false],
+      ]);
+      expect(merged.usage).toEqual({ inputTokens: 30, outputTokens: 15, totalTokens: 45 });
+      expect(merged.warnings).toContain('Consensus provider "broken" failed: Provider "broken" calls the openai API, but AX_TEST_UNSET_KEY is not set.');
+      expect(judgePrompts[0]).toContain('<request>\nHow do I dedupe?\n</request>');
+      expect(judgePrompts[0]).toContain('<answer label="A">\nUse a map.\n</answer>');
+      expect(judgePrompts[0]).toContain('<answer label="B">\nKey it by id.\n</answer>');
+      expect((await runtime.getTrace(merged.traceId))?.metadata?.consensus).toEqual(merged.consensus);
+
+      // A judge that picks one answer gets that answer back as it was written.
+      const chosen = await runtime.callProvider({ prompt: 'How do I dedupe?', consensus: { strategy: 'judge', providers: ['alpha', 'beta'] } });
+      expect(chosen).toMatchObject({ success: true, provider: 'beta', content: 'Key it by id.' });
+      expect(chosen.consensus).toMatchObject({ verdict: 'chosen', judge: 'umpire' });
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('falls back on prompt-described tools, prompt-only JSON and a trimmed prompt for what a model cannot do', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    await writeFile(join(tempDir, 'CHANGELOG.md'), '## 1.2.0\n- Faster search\n', 'utf8');
+    const bodies: Array<{ model: string; tools?: unknown[]; response_format?: unknown; messages: Array<{ role: string; content: string }> }> = [];
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk: Buffer) => {
+        raw += chunk.toString('utf8');
+      });
+      request.on('end', () => {
+        const body = JSON.parse(raw) as typeof bodies[number];
+        bodies.push(body);
+        const prompt = body.messages.at(-1)!.content;
+        if (body.model === 'mystery-model' && body.tools !== undefined) {
+          response.writeHead(400, { 'content-type': 'application/json' });
+          response.end(JSON.stringify({ error: { message: 'mystery-model does not support tools' } }));
+          return;
+        }
+        const content = prompt.includes('<tools>') && !prompt.includes('<tool_result')
+          ? 'Let me look.\n<tool_call>{"name": "read_file", "input": {"path": "CHANGELOG.md"}}</tool_call>'
+          : prompt.includes('valid JSON') ? 'Here it is:\n```json\n{"version": "1.2.0"}\n```' : 'Version 1.2.0 adds faster search.';
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        response.end([
+          `data: ${JSON.stringify({ choices: [{ index: 0, delta: { content }, finish_reason: 'stop' }] })}`,
+          `data: ${JSON.stringify({ choices: [], usage: { prompt_tokens: 20, completion_tokens: 5 } })}`,
+          'data: [DONE]',
+          '',
+        ].join('\n\n'));
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          reasoner: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'o1-mini' },
+          mystery: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'mystery-model' },
+          omni: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'gpt-4o' },
+          tiny: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'tiny-model' },
+        },
+        capabilities: { 'tiny-model': { maxContextTokens: 200 } },
+      },
+    }, null, 2)}\n`, 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      for (const provider of ['reasoner', 'mystery']) {
+        await runtime.registerAgent({ agentId: `${provider}-agent`, name: provider, capabilities: ['release'], metadata: { provider } });
+      }
+
+      // A model known to lack function calling gets its tools described in the prompt.
+      const inlined = await runtime.runAgent({ agentId: 'reasoner-agent', task: 'What is in the latest release?', surface: 'cli' });
+      expect(inlined).toMatchObject({ success: true, content: 'Version 1.2.0 adds faster search.', usage: { inputTokens: 40, outputTokens: 10 } });
+      expect(inlined.warnings).toContain('Model "o1-mini" cannot call tools natively; its tools were described in the prompt instead.');
+      expect(bodies.map((body) => body.tools)).toEqual([undefined, undefined]);
+      expect(bodies[0]!.messages.at(-1)!.content).toContain('<tool name="read_file">');
+      expect(bodies[1]!.messages.at(-1)!.content).toContain('<tool_result name="read_file">\n## 1.2.0\n- Faster search\n\n</tool_result>');
+
+      // A model the table does not know is tried natively and switched over when its server refuses the tools.
+      bodies.length = 0;
+      const refused = await runtime.runAgent({ agentId: 'mystery-agent', task: 'What is in the latest release?', surface: 'cli' });
+      expect(refused).toMatchObject({ success: true, content: 'Version 1.2.0 adds faster search.' });
+      expect(refused.warnings).toContain('Model "mystery-model" refused native tool calls; its tools were described in the prompt instead.');
+      expect(bodies.map((body) => body.tools === undefined)).toEqual([false, true, true]);
+
+      // JSON replies: native JSON mode where the model has one, pulled out of the text where it does not.
+      bodies.length = 0;
+      const prompted = await runtime.callProvider({ prompt: 'Current version?', provider: 'reasoner', responseFormat: 'json' });
+      const native = await runtime.callProvider({ prompt: 'Current version?', provider: 'omni', responseFormat: 'json' });
+      expect([prompted.content, native.content]).toEqual(['{"version": "1.2.0"}', '{"version": "1.2.0"}']);
+      expect(bodies.map((body) => body.response_format)).toEqual([undefined, { type: 'json_object' }]);
+
+      // A prompt too long for the configured context loses its middle, not its task.
+      bodies.length = 0;
+      const trimmed = await runtime.callProvider({ prompt: `Context: ${'x'.repeat(2_000)} Task: summarize.`, provider: 'tiny', maxTokens: 50 });
+      expect(trimmed.success).toBe(true);
+      expect(trimmed.warnings.some((warning) => warning.includes('longer than the 200-token context of "tiny-model"'))).toBe(true);
+      const sent = bodies[0]!.messages.at(-1)!.content;
+      expect(sent.length).toBeLessThanOrEqual(600);
+      expect(sent.startsWith('Context: ')).toBe(true);
+      expect(sent.endsWith('Task: summarize.')).toBe(true);
+      expect(sent).toContain('characters omitted to fit the context window');
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('validates replies against an output schema and sends invalid ones back for repair', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const prompts: string[] = [];
+    const replies = [
+      'Sure:\n```json\n{"version": 1, "changes": []}\n```',
+      '{"version": "1.2.0", "changes": ["Faster search"]}',
+      '{"version": "1.3.0"}',
+      'not json',
+      '{"version": "1.3.0", "changes": "none"}',
+    ];
+    const server = createServer((request, response) => {
+      let body = '';
+      request.on('data', (chunk: Buffer) => {
+        body += chunk.toString('utf8');
+      });
+      request.on('end', () => {
+        prompts.push((JSON.parse(body) as { messages: Array<{ content: string }> }).messages.at(-1)!.content);
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        response.end([
+          `data: ${JSON.stringify({ choices: [{ index: 0, delta: { content: replies[prompts.length - 1] }, finish_reason: 'stop' }] })}`,
+          `data: ${JSON.stringify({ choices: [], usage: { prompt_tokens: 40, completion_tokens: 10 } })}`,
+          'data: [DONE]',
+          '',
+        ].join('\n\n'));
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: { executors: { local: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'local-model' } } },
+    }, null, 2)}\n`, 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+    const outputSchema = {
+      type: 'object',
+      required: ['version', 'changes'],
+      properties: { version: { type: 'string', pattern: '^\\d+\\.\\d+\\.\\d+$' }, changes: { type: 'array', items: { type: 'string' } } },
+      additionalProperties: false,
+    };
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      const repaired = await runtime.callProvider({ prompt: 'Describe the latest release.', provider: 'local', outputSchema });
+      expect(repaired).toMatchObject({
+        success: true,
+        content: '{"version": "1.2.0", "changes": ["Faster search"]}',
+        structured: { value: { version: '1.2.0', changes: ['Faster search'] }, valid: true, attempts: 2 },
+        usage: { inputTokens: 80, outputTokens: 20, totalTokens: 100 },
+      });
+      expect(repaired.warnings).toContain('The reply fit the output schema after 1 repair.');
+      expect(prompts[0]).toContain('<json-schema>');
+      expect(prompts[1]).toContain('<reply>\n{"version": 1, "changes": []}\n</reply>');
+      expect(prompts[1]).toContain('- $.version: expected string, got number');
+      expect((await runtime.getTrace(repaired.traceId))?.metadata?.structuredOutput).toEqual(repaired.structured);
+
+      // With the repairs spent the call fails, reporting what was still wrong.
+      const invalid = await runtime.callProvider({ prompt: 'Describe the next release.', provider: 'local', outputSchema });
+      expect(invalid).toMatchObject({
+        success: false,
+        error: { code: 'PROVIDER_OUTPUT_INVALID' },
+        structured: { valid: false, attempts: 3, errors: ['$.changes: expected array, got string'] },
+      });
+      expect(prompts[3]).toContain('- $.changes: is required');
+      expect(prompts[4]).toContain('- The reply is not valid JSON:');
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('renders agent prompt templates with variables, sections, ability includes and token budgets', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    mkdirSync(join(tempDir, '.automatosx', 'abilities'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'abilities', 'house-style.md'), 'Write for {{audience}}.', 'utf8');
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.registerAgent({
+      agentId: 'writer',
+      name: 'Writer',
+      capabilities: ['docs', 'release-notes'],
+      metadata: {
+        variables: { audience: 'operators', notes: 'x'.repeat(400) },
+        systemPrompt: [
+          'You are {{agent.name}} ({{agent.capabilities}}).',
+          '{{#if agent.team}}Team: {{agent.team}}.{{else}}No team.{{/if}}',
+          '{{> house-style}} {{> code-review}}',
+          '{{#each input.files}}- {{this}}\n{{/each}}{{! not rendered }}Owner: {{owner}}',
+          '{{#budget 20}}{{notes}}{{/budget}}',
+        ].join('\n'),
+      },
+    });
+
+    const preview = await runtime.renderAgentPrompt({ agentId: 'writer', task: 'Draft the notes', input: { files: ['a.md', 'b.md'] } });
+    expect(preview?.systemPrompt.split('\n').slice(0, 6)).toEqual([
+      'You are Writer (docs, release-notes).',
+      'No team.',
+      'Write for operators. Prioritize concrete findings with file references, severity ordering, and missing-test risks. Prefer actionable defects over narrative summaries.',
+      '- a.md',
+      '- b.md',
+      'Owner: ',
+    ]);
+    expect(preview?.systemPrompt).toContain('[... cut to about 20 tokens ...]');
+    expect(preview?.prompt).toContain('Task: Draft the notes');
+    expect(preview?.template).toEqual({
+      includes: ['house-style', 'code-review'],
+      missingVariables: ['owner'],
+      truncations: [{ budgetTokens: 20, originalTokens: 100 }],
+    });
+    expect(preview?.warnings).toEqual([
+      'The prompt template of agent "writer" uses variables with no value: owner.',
+      'A {{#budget 20}} section of agent "writer" was cut from about 100 tokens.',
+    ]);
+    expect(await runtime.renderAgentPrompt({ agentId: 'nobody' })).toBeUndefined();
+
+    // A template that does not render stops the run before any provider is called.
+    await runtime.registerAgent({ agentId: 'broken', name: 'Broken', metadata: { systemPrompt: '{{#if ready}}{{> ../secrets.txt}}{{/if}} {{> missing}}' } });
+    const run = await runtime.runAgent({ agentId: 'broken', task: 'Anything' });
+    expect(run).toMatchObject({ success: false, error: { code: 'PROMPT_TEMPLATE_INVALID', message: 'Include "missing" was not found.' } });
+    await expect(runtime.renderAgentPrompt({ agentId: 'broken' })).rejects.toThrow('Include "missing" was not found.');
+  });
+
+  it('layers agent profiles over the agents they extend and the ability packs they name', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const packs = join(tempDir, '.automatosx', 'ability-packs');
+    for (const [dir, file, text] of [
+      ['rust/2.0.0', 'ownership.md', 'Outdated ownership notes.'],
+      ['rust/2.10.1', 'ownership.md', 'Prefer borrowing to cloning.'],
+      ['rust/2.10.1', 'testing.md', 'Cover every unsafe block with a test.'],
+      ['rust/3.0.0', 'ownership.md', 'Not released yet.'],
+      ['payments/1.2.0', 'testing.md', 'Test every currency path.'],
+    ]) {
+      mkdirSync(join(packs, dir), { recursive: true });
+      await writeFile(join(packs, dir, file), text, 'utf8');
+    }
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.registerAgent({
+      agentId: 'rust-engineer',
+      name: 'Rust Engineer',
+      capabilities: ['rust', 'implementation'],
+      metadata: {
+        systemPrompt: 'You are {{agent.name}}, working on {{project}}.',
+        variables: { project: 'a Rust codebase', style: 'terse' },
+        permissions: { paths: ['src/**'], shell: true },
+        abilityPacks: ['rust@2'],
+      },
+    });
+    await runtime.registerAgent({
+      agentId: 'payments-rust',
+      name: 'Payments Engineer',
+      capabilities: ['payments'],
+      metadata: { extends: 'rust-engineer', variables: { project: 'the payments service' }, permissions: { shell: false }, abilityPacks: 'payments' },
+    });
+
+    const profile = await runtime.resolveAgentProfile('payments-rust');
+    expect(profile).toMatchObject({
+      agent: {
+        agentId: 'payments-rust',
+        capabilities: ['rust', 'implementation', 'payments'],
+        metadata: {
+          variables: { project: 'the payments service', style: 'terse' },
+          permissions: { paths: ['src/**'], shell: false },
+          abilityPacks: ['rust@2', 'payments'],
+        },
+      },
+      extends: ['rust-engineer'],
+      abilityPacks: [{ name: 'rust', version: '2.10.1', abilities: ['ownership', 'testing'] }, { name: 'payments', version: '1.2.0', abilities: ['testing'] }],
+    });
+    expect(profile?.agent.metadata).not.toHaveProperty('extends');
+
+    // The later pack wins an ability both have; abilities follow the profile's own instructions.
+    const preview = await runtime.renderAgentPrompt({ agentId: 'payments-rust', task: 'Add refunds' });
+    expect(preview?.systemPrompt).toBe([
+      'You are Payments Engineer, working on the payments service.',
+      '## ownership (rust@2.10.1)\nPrefer borrowing to cloning.',
+      '## testing (payments@1.2.0)\nTest every currency path.',
+    ].join('\n\n'));
+    const run = await runtime.runAgent({ agentId: 'payments-rust', task: 'Add refunds', traceId: 'profile-001' });
+    expect(run).toMatchObject({ success: true, profile: { extends: ['rust-engineer'], abilityPacks: ['rust@2.10.1', 'payments@1.2.0'] } });
+    expect((await runtime.getTrace('profile-001'))?.metadata).toMatchObject({ capabilities: ['rust', 'implementation', 'payments'], profile: run.profile });
+    expect((await runtime.recommendAgents({ task: 'Fix the rust ownership bug in payments' }))[0]?.agentId).toBe('payments-rust');
+
+    // Bases that extend each other, missing bases and pack versions stop the run.
+    await runtime.registerAgent({ agentId: 'loop-a', name: 'Loop A', metadata: { extends: 'loop-b' } });
+    await runtime.registerAgent({ agentId: 'loop-b', name: 'Loop B', metadata: { extends: 'loop-a' } });
+    await runtime.registerAgent({ agentId: 'orphan', name: 'Orphan', metadata: { extends: 'nobody' } });
+    await runtime.registerAgent({ agentId: 'future', name: 'Future', metadata: { abilityPacks: ['rust@4'] } });
+    expect((await runtime.runAgent({ agentId: 'loop-a', task: 'Anything' })).error).toEqual({
+      code: 'AGENT_PROFILE_INVALID',
+      message: 'Agent "loop-b" extends "loop-a", which extends it back: loop-a -> loop-b -> loop-a.',
+    });
+    expect((await runtime.runAgent({ agentId: 'orphan', task: 'Anything' })).error?.message).toBe('Agent "orphan" extends "nobody", which is not registered.');
+    await expect(runtime.resolveAgentProfile('future')).rejects.toThrow(`Ability pack "rust" has no version matching "4" in ${join('.automatosx', 'ability-packs', 'rust')}.`);
+  });
+
+  it('suggests agent profiles for the frameworks and languages of the indexed workspace', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    mkdirSync(join(tempDir, 'app', 'models'), { recursive: true });
+    await writeFile(join(tempDir, 'Gemfile'), "source 'https://rubygems.org'\ngem 'rails', '~> 7.1'\n", 'utf8');
+    await writeFile(join(tempDir, 'app', 'models', 'invoice.rb'), 'class Invoice < ApplicationRecord\n  def total\n    1\n  end\nend\n', 'utf8');
+    await writeFile(join(tempDir, 'Dockerfile'), 'FROM ruby:3.3 AS build\nRUN bundle install\n', 'utf8');
+    await writeFile(join(tempDir, 'deploy.sh'), '#!/bin/sh\ndeploy() {\n  echo deploying\n}\n', 'utf8');
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await expect(runtime.suggestAgents()).rejects.toThrow('No symbol index found. Run "ax code index" first.');
+    await runtime.indexCode();
+
+    const preview = await runtime.suggestAgents({ dryRun: true });
+    expect(preview.frameworks).toEqual([
+      { id: 'rails', evidence: ['Gemfile depends on rails', '1 symbol in app/models/invoice.rb'] },
+      { id: 'docker', evidence: ['1 symbol in Dockerfile'] },
+    ]);
+    expect(preview.agents.map((agent) => [agent.agentId, agent.status])).toEqual([
+      ['rails-app', 'planned'],
+      ['container-build', 'planned'],
+      ['shell-engineer', 'planned'],
+    ]);
+    await expect(stat(join(tempDir, '.automatosx', 'agents'))).rejects.toThrow();
+
+    // Written profiles register as they are, and a rerun keeps the files already there.
+    const suggested = await runtime.suggestAgents();
+    expect(suggested.agents.map((agent) => agent.status)).toEqual(['written', 'written', 'written']);
+    const rails = JSON.parse(await readFile(join(tempDir, '.automatosx', 'agents', 'rails-app.json'), 'utf8'));
+    expect(rails).toMatchObject({ agentId: 'rails-app', capabilities: ['ruby', 'rails', 'backend', 'orm'], metadata: { languages: ['ruby'], frameworks: ['rails'], suggestedBy: 'ax agent suggest' } });
+    await writeFile(join(tempDir, '.automatosx', 'agents', 'rails-app.json'), JSON.stringify({ ...rails, name: 'Billing Rails Engineer' }), 'utf8');
+    expect((await runtime.suggestAgents()).agents.map((agent) => agent.status)).toEqual(['exists', 'exists', 'exists']);
+    await runtime.registerAgent(JSON.parse(await readFile(join(tempDir, '.automatosx', 'agents', 'rails-app.json'), 'utf8')));
+    const prompt = await runtime.renderAgentPrompt({ agentId: 'rails-app', task: 'Add late fees' });
+    expect(prompt?.systemPrompt).toContain('You are Billing Rails Engineer. Build Rails models');
+    expect(prompt?.systemPrompt).toContain('Prioritize concrete findings with file references');
+  });
+
+  it('runs eval suites across providers and compares pass rates with the previous run', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    await configureMockProviders(tempDir, ['claude', 'gemini']);
+    mkdirSync(join(tempDir, 'evals'), { recursive: true });
+    await writeFile(join(tempDir, 'evals', 'review.yaml'), [
+      'name: review',
+      'agent: reviewer',
+      'providers: [claude, gemini]',
+      'cases:',
+      '  - id: reply',
+      '    task: Review the change',
+      '    assertions:',
+      '      - { type: contains, value: "REAL:claude" }',
+      '      - { type: regex, pattern: "^real:", flags: i }',
+      '  - id: fixtures',
+      '    task: Check the notes',
+      '    fixtures:',
+      '      docs/notes.txt: "ready"',
+      '    assertions:',
+      '      - { type: file, path: docs/notes.txt }',
+      "      - { type: command, command: [node, -e, \"require('fs').statSync('docs/notes.txt')\"] }",
+      '      - { type: contains, value: TODO, not: true }',
+    ].join('\n'), 'utf8');
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.registerAgent({ agentId: 'reviewer', name: 'Reviewer', capabilities: ['review'] });
+
+    const first = await runtime.runEval({ file: 'evals/review.yaml' });
+    expect(first.variants).toEqual([
+      expect.objectContaining({ agentId: 'reviewer', provider: 'claude', cases: 2, passed: 2, passRate: 1, totalTokens: 16 }),
+      expect.objectContaining({ agentId: 'reviewer', provider: 'gemini', cases: 2, passed: 1, passRate: 0.5, totalTokens: 16 }),
+    ]);
+    expect(first.baseline).toBeUndefined();
+    expect(first.results.find((result) => !result.passed)).toMatchObject({
+      caseId: 'reply',
+      provider: 'gemini',
+      assertions: [{ assertion: 'the reply contains "REAL:claude"', passed: false }, { passed: true }],
+    });
+    expect(JSON.parse(await readFile(join(tempDir, first.reportFile), 'utf8'))).toMatchObject({ runId: first.runId, suite: 'review' });
+    await expect(stat(join(tempDir, '.automatosx', 'evals', 'work'))).rejects.toThrow();
+
+    // A narrower run is compared with the variants of the run before it.
+    const second = await runtime.runEval({ file: 'evals/review.yaml', providers: ['gemini'], cases: ['fixtures'] });
+    expect(second.baseline).toEqual({ runId: first.runId, startedAt: first.startedAt });
+    expect(second.variants).toEqual([
+      expect.objectContaining({ provider: 'gemini', cases: 1, passed: 1, passRate: 1, baseline: { passRate: 0.5, costUsd: 0 } }),
+    ]);
+
+    await expect(runtime.runEval({ file: 'evals/review.yaml', cases: ['missing'] })).rejects.toMatchObject({ code: 'EVAL_SUITE_INVALID' });
+    await expect(runtime.runEval({ file: 'evals/missing.yaml' })).rejects.toMatchObject({ code: 'EVAL_SUITE_NOT_FOUND' });
+    await writeFile(join(tempDir, 'evals', 'escape.yaml'), 'agent: reviewer\ncases:\n  - id: escape\n    task: Read it\n    fixtures: { ../outside.txt: x }\n    assertions: [{ type: file, path: outside.txt }]\n', 'utf8');
+    await expect(runtime.runEval({ file: 'evals/escape.yaml' })).rejects.toThrow('has a fixture "../outside.txt" outside the case\'s workspace');
+  });
+
+  it('records agent runs and replays them against the recorded provider replies', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    await configureMockProviders(tempDir, ['claude']);
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.registerAgent({ agentId: 'reviewer', name: 'Reviewer', capabilities: ['review'] });
+
+    const run = await runtime.runAgent({ agentId: 'reviewer', task: 'Review the change', provider: 'claude' });
+    expect(run.success).toBe(true);
+    const log = (await readFile(join(tempDir, '.automatosx', 'replays', `${run.traceId}.jsonl`), 'utf8')).trim().split('\n').map((line) => JSON.parse(line));
+    expect(log).toEqual([
+      expect.objectContaining({ type: 'run', traceId: run.traceId, command: 'agent.run' }),
+      expect.objectContaining({ type: 'call', agentId: 'reviewer', provider: 'claude', outcome: 'success', tools: [], edits: [] }),
+    ]);
+
+    // Without the provider's executor, the replay can only get the recorded reply.
+    await rm(join(tempDir, 'mock-provider.mjs'));
+    const replay = await runtime.replayRun({ runId: run.traceId });
+    expect(replay).toMatchObject({
+      runId: run.traceId,
+      command: 'agent.run',
+      success: true,
+      calls: { recorded: 1, replayed: 1 },
+      toolCalls: 0,
+      edits: 0,
+      divergences: [],
+      result: { agentId: 'reviewer', content: run.content },
+    });
+    expect(replay.traceId).not.toBe(run.traceId);
+    await expect(stat(join(tempDir, '.automatosx', 'replays', `${replay.traceId}.jsonl`))).rejects.toThrow();
+
+    await expect(runtime.replayRun({ runId: 'missing' })).rejects.toMatchObject({ code: 'REPLAY_NOT_FOUND' });
+    await expect(runtime.replayRun({ runId: '../config' })).rejects.toMatchObject({ code: 'REPLAY_NOT_FOUND' });
+  });
+
+  it('resumes sessions with their conversation, scope and memory namespace and branches them', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    await configureMockProviders(tempDir, ['claude']);
+    mkdirSync(join(tempDir, 'services', 'api'), { recursive: true });
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.registerAgent({ agentId: 'backend', name: 'Backend', capabilities: ['api'] });
+    await runtime.storeMemory({ key: 'limits', namespace: 'api', value: 'Rate limits are per API key.' });
+    const session = await runtime.createSession({ task: 'Add rate limiting', initiator: 'cli', scope: 'services/api', memoryNamespace: 'api' });
+
+    await expect(runtime.resumeSession({ sessionId: session.sessionId, task: 'Limit requests' })).rejects.toMatchObject({ code: 'SESSION_AGENT_MISSING' });
+    const first = await runtime.resumeSession({ sessionId: session.sessionId, task: 'Limit requests', agentId: 'backend' });
+    expect(first.run?.success).toBe(true);
+    await runtime.completeSession(session.sessionId, 'paused');
+
+    // A completed session opens again, and its next turn runs with the conversation so far.
+    const second = await runtime.resumeSession({ sessionId: session.sessionId, task: 'Now add tests' });
+    expect(second.session.status).toBe('active');
+    expect(second.thread).toMatchObject({ agentId: 'backend', scope: 'services/api', memoryNamespace: 'api' });
+    expect(second.thread.turns.map((turn) => [turn.role, turn.content])).toEqual([
+      ['user', 'Limit requests'],
+      ['assistant', first.run?.content],
+      ['user', 'Now add tests'],
+      ['assistant', second.run?.content],
+    ]);
+    const log = (await readFile(join(tempDir, '.automatosx', 'replays', `${second.run?.traceId}.jsonl`), 'utf8')).trim().split('\n').map((line) => JSON.parse(line));
+    expect(log[1].prompt).toContain('Conversation so far:');
+    expect(log[1].prompt).toContain(first.run?.content);
+    expect(log[1].prompt).toContain('Rate limits are per API key.');
+
+    const branch = await runtime.branchSession({ sessionId: session.sessionId, branchId: 'redis', turns: 2 });
+    expect(branch.session).toMatchObject({ sessionId: 'redis', task: 'Add rate limiting', metadata: { branchedFrom: session.sessionId } });
+    expect(branch.thread).toMatchObject({ agentId: 'backend', scope: 'services/api', branchedFrom: { sessionId: session.sessionId, turns: 2 } });
+    const forked = await runtime.resumeSession({ sessionId: 'redis', task: 'Use Redis instead' });
+    expect(forked.thread.turns.map((turn) => turn.content)).toEqual(['Limit requests', first.run?.content, 'Use Redis instead', forked.run?.content]);
+    expect((await runtime.getSessionThread(session.sessionId)).turns).toHaveLength(4);
+
+    await expect(runtime.branchSession({ sessionId: session.sessionId, branchId: 'redis' })).rejects.toThrow('Session "redis" already exists.');
+    await expect(runtime.resumeSession({ sessionId: 'missing' })).rejects.toMatchObject({ code: 'SESSION_NOT_FOUND' });
+  });
+
+  it('rewinds sessions and puts back the workspace files changed since a checkpoint', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    await configureMockProviders(tempDir, ['claude']);
+    mkdirSync(join(tempDir, 'src'), { recursive: true });
+    await writeFile(join(tempDir, 'src', 'limits.ts'), 'export const LIMIT = 100;\n', 'utf8');
+    await writeFile(join(tempDir, 'README.md'), '# Limits\n', 'utf8');
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.registerAgent({ agentId: 'backend', name: 'Backend', capabilities: ['api'] });
+    const session = await runtime.createSession({ task: 'Tune the limits', initiator: 'cli' });
+    await runtime.resumeSession({ sessionId: session.sessionId, task: 'Explain the limit', agentId: 'backend' });
+
+    const first = await createWorkspaceCheckpoint(tempDir);
+    await runtime.resumeSession({ sessionId: session.sessionId, task: 'Lower the limit' });
+    // The mock provider changes no files, so the turn's changes are made here.
+    await writeFile(join(tempDir, 'src', 'limits.ts'), 'export const LIMIT = 10;\n', 'utf8');
+    await writeFile(join(tempDir, 'src', 'burst.ts'), 'export const BURST = 5;\n', 'utf8');
+    await rm(join(tempDir, 'README.md'));
+
+    const changes = await diffWorkspaceCheckpoint(first);
+    expect(changes.map((change) => [change.file, change.change])).toEqual([
+      ['README.md', 'deleted'],
+      ['src/burst.ts', 'created'],
+      ['src/limits.ts', 'modified'],
+    ]);
+    expect(changes[2]?.diff).toBe('--- a/src/limits.ts\n+++ b/src/limits.ts\n@@ -1,1 +1,1 @@\n-export const LIMIT = 100;\n+export const LIMIT = 10;');
+
+    // A later checkpoint keeps the content of the files that did not change.
+    const second = await createWorkspaceCheckpoint(tempDir, { previous: first });
+    expect(second.contents.get('src/limits.ts')).toBe('export const LIMIT = 10;\n');
+    expect(await diffWorkspaceCheckpoint(second)).toEqual([]);
+
+    expect(await restoreWorkspaceCheckpoint(first)).toEqual({ restored: ['README.md', 'src/burst.ts', 'src/limits.ts'], skipped: [] });
+    expect(await readFile(join(tempDir, 'src', 'limits.ts'), 'utf8')).toBe('export const LIMIT = 100;\n');
+    expect(await readFile(join(tempDir, 'README.md'), 'utf8')).toBe('# Limits\n');
+    await expect(stat(join(tempDir, 'src', 'burst.ts'))).rejects.toThrow();
+    expect(await diffWorkspaceCheckpoint(first)).toEqual([]);
+
+    const rewound = await runtime.rewindSession(session.sessionId, 2);
+    expect(rewound.turns.map((turn) => turn.content)).toEqual(['Explain the limit', expect.any(String)]);
+    expect((await runtime.getSessionThread(session.sessionId)).turns).toHaveLength(2);
+    await expect(runtime.rewindSession('missing', 0)).rejects.toMatchObject({ code: 'SESSION_NOT_FOUND' });
+  });
+
+  it('exports session transcripts with each run\'s usage and file changes as Markdown, HTML or JSON', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    await configureMockProviders(tempDir, ['claude']);
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.registerAgent({ agentId: 'backend', name: 'Backend', capabilities: ['api'] });
+    const session = await runtime.createSession({ task: 'Add <rate> limiting', initiator: 'cli' });
+    const first = await runtime.resumeSession({ sessionId: session.sessionId, task: 'Write the limiter', agentId: 'backend' });
+    const second = await runtime.resumeSession({ sessionId: session.sessionId, task: 'Lower the limit' });
+
+    // The mock provider changes no files, so the recorded calls get the edits a real run would have made.
+    for (const [run, edit] of [
+      [first.run, { file: 'limits.txt', content: 'a\nb\nlimit=100\nc\n', created: true }],
+      [second.run, { file: 'limits.txt', content: 'a\nb\nlimit=10\nc\n' }],
+    ] as const) {
+      const path = join(tempDir, '.automatosx', 'replays', `${run?.traceId}.jsonl`);
+      const [header, call] = (await readFile(path, 'utf8')).trim().split('\n').map((line) => JSON.parse(line));
+      await writeFile(path, `${JSON.stringify(header)}\n${JSON.stringify({ ...call, edits: [edit] })}\n`, 'utf8');
+    }
+
+    const markdown = await runtime.exportSession({ sessionId: session.sessionId });
+    expect(markdown.format).toBe('md');
+    expect(markdown.content).toContain(`# Session ${session.sessionId}`);
+    expect(markdown.content).toContain('## 2. Lower the limit');
+    expect(markdown.content).toContain(second.run?.content);
+    expect(markdown.content).toContain('Totals: 2 run(s), 2 provider call(s), 16 tokens (6 in, 10 out)');
+    expect(markdown.content).toContain('```diff\n--- /dev/null\n+++ b/limits.txt\n@@ -0,0 +1,4 @@\n+a\n');
+    expect(markdown.content).toContain('--- a/limits.txt\n+++ b/limits.txt\n@@ -1,4 +1,4 @@\n a\n b\n-limit=100\n+limit=10\n c\n```');
+
+    const html = await runtime.exportSession({ sessionId: session.sessionId, format: 'html' });
+    expect(html.content.startsWith('<!DOCTYPE html>')).toBe(true);
+    expect(html.content).toContain('<p>Add &lt;rate&gt; limiting</p>');
+    expect(html.content).toContain('<span class="add">+limit=10</span>');
+
+    const json = await runtime.exportSession({ sessionId: session.sessionId, format: 'json' });
+    expect(JSON.parse(json.content)).toEqual(json.transcript);
+    expect(json.transcript.runs[0]).toMatchObject({ traceId: first.run?.traceId, agentId: 'backend', task: 'Write the limiter', calls: 1, inputTokens: 3, outputTokens: 5 });
+    expect(json.transcript.runs[1].edits).toEqual([expect.objectContaining({ file: 'limits.txt', change: 'modified' })]);
+    expect(json.transcript.totals).toMatchObject({ runs: 2, calls: 2, edits: 2 });
+
+    await expect(runtime.exportSession({ sessionId: 'missing' })).rejects.toMatchObject({ code: 'SESSION_NOT_FOUND' });
+  });
+
+  it('coordinates concurrent runs of a session through its scratchpad of claimed tasks', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    await configureMockProviders(tempDir, ['claude']);
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.registerAgent({ agentId: 'frontend', name: 'Frontend', capabilities: ['ui'] });
+    const session = await runtime.createSession({ task: 'Add rate limiting', initiator: 'cli' });
+    await runtime.addScratchpadTasks(session.sessionId, [
+      { taskId: 'api', title: 'Limit the API', paths: ['services/api'] },
+      { taskId: 'ui', title: 'Show the limit in the UI', paths: ['web'] },
+      { taskId: 'api-tests', title: 'Test the limits', paths: ['services/api/tests'] },
+    ]);
+    // Another terminal holds the API; the next free task skips the tests under its paths.
+    await runtime.claimScratchpadTask(session.sessionId, { taskId: 'api', claimant: 'terminal-1' });
+
+    const run = await runtime.runAgent({ agentId: 'frontend', sessionId: session.sessionId, claim: 'next', provider: 'claude' });
+    expect(run.success).toBe(true);
+    expect(run.claimed).toMatchObject({ taskId: 'ui', status: 'done', claimedBy: 'frontend', traceId: run.traceId, result: run.content });
+    const log = (await readFile(join(tempDir, '.automatosx', 'replays', `${run.traceId}.jsonl`), 'utf8')).trim().split('\n').map((line) => JSON.parse(line));
+    expect(log[1].prompt).toContain('Show the limit in the UI');
+    expect(log[1].prompt).toContain('- api: Limit the API (services/api): claimed by terminal-1; leave its paths alone.');
+    expect(log[1].prompt).toContain('- ui: Show the limit in the UI (web): yours, claimed by this run.');
+    expect(log[1].prompt).toContain('- api-tests: Test the limits (services/api/tests): open.');
+
+    const none = await runtime.runAgent({ agentId: 'frontend', sessionId: session.sessionId, claim: 'next' });
+    expect(none).toMatchObject({ success: false, error: { code: 'SCRATCHPAD_NO_OPEN_TASK' } });
+    const taken = await runtime.runAgent({ agentId: 'frontend', sessionId: session.sessionId, claim: 'api' });
+    expect(taken).toMatchObject({ success: false, error: { code: 'SCRATCHPAD_CONFLICT' } });
+    const outside = await runtime.runWorkflow({ workflowId: 'maintain', claim: 'next' });
+    expect(outside).toMatchObject({ success: false, error: { code: 'SESSION_NOT_FOUND' } });
+
+    await runtime.postScratchpadResult(session.sessionId, { taskId: 'api', claimant: 'terminal-1', result: 'Limited to 100 requests a minute.' });
+    expect(await runtime.claimScratchpadTask(session.sessionId, { claimant: 'terminal-2' })).toMatchObject({ taskId: 'api-tests' });
+    expect((await runtime.getScratchpad(session.sessionId)).map((task) => [task.taskId, task.status])).toEqual([
+      ['api', 'done'],
+      ['ui', 'done'],
+      ['api-tests', 'claimed'],
+    ]);
+    await expect(runtime.getScratchpad('missing')).rejects.toMatchObject({ code: 'SESSION_NOT_FOUND' });
+  });
+
+  it('assembles context from memory, files and history and trims it to the window with each strategy', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    mkdirSync(join(tempDir, 'src'), { recursive: true });
+    const body = Array.from({ length: 40 }, (_, index) => `    value${index} = input.length * ${index}`).join('\n');
+    await writeFile(join(tempDir, 'src', 'pipeline.rb'), `class Pipeline\n${[0, 1, 2].map((step) => `  def step${step}(input)\n${body}\n    value1\n  end`).join('\n\n')}\nend\n`, 'utf8');
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.storeMemory({ key: 'release-1', value: 'Release 1.0 shipped search. '.repeat(20) });
+    await runtime.storeMemory({ key: 'release-2', value: 'Release 2.0 shipped sync. '.repeat(20) });
+    const history = Array.from({ length: 6 }, (_, index) => ({
+      role: index % 2 === 0 ? 'user' as const : 'assistant' as const,
+      content: `Turn ${index + 1}: ${'detail '.repeat(60)}`,
+    }));
+    const request = { task: 'Summarize the release', systemPrompt: 'You write notes.', memoryQuery: 'release', files: ['src/pipeline.rb'], history };
+
+    const roomy = await runtime.assembleContext({ ...request, maxTokens: 100_000 });
+    expect(roomy.report).toMatchObject({ tokensBefore: 1949, tokensAfter: 1949, fits: true, cuts: [] });
+    expect(roomy.prompt.indexOf('Relevant memory:\n- release-1:')).toBeLessThan(roomy.prompt.indexOf('src/pipeline.rb\n```rb\nclass Pipeline'));
+    expect(roomy.prompt.endsWith('\n\nSummarize the release')).toBe(true);
+
+    // By default code files lose their bodies first, then the oldest turns go.
+    const tight = await runtime.assembleContext({ ...request, maxTokens: 4900 });
+    expect(tight.report.cuts).toEqual([
+      { source: 'file', label: 'src/pipeline.rb', strategy: 'signature-only', tokensBefore: 978, tokensAfter: 32 },
+      { source: 'history', label: 'oldest 2 of 6 turns', strategy: 'drop-oldest', tokensBefore: 218, tokensAfter: 0 },
+    ]);
+    expect(tight.report).toMatchObject({ maxTokens: 4900, reserveTokens: 4096, fits: true });
+    expect(tight.prompt).toContain('src/pipeline.rb (signatures only; bodies elided)\n```rb\nclass Pipeline\n  def step0(input)\n    # ...\n  end');
+    expect(tight.prompt).not.toContain('Turn 2:');
+    expect(tight.prompt).toContain('User: Turn 3:');
+
+    // summarize-middle folds the middle turns to a line each, then cuts the middle of the longest file.
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: { contextBudget: { strategies: ['summarize-middle'], reserveTokens: 1000 } },
+    }, null, 2)}\n`, 'utf8');
+    const summarized = await runtime.assembleContext({ ...request, maxTokens: 2400 });
+    expect(summarized.report.cuts).toEqual([
+      { source: 'history', label: 'turns 2-4 of 6', strategy: 'summarize-middle', tokensBefore: 328, tokensAfter: 107 },
+      { source: 'file', label: 'src/pipeline.rb', strategy: 'summarize-middle', tokensBefore: 978, tokensAfter: 642 },
+    ]);
+    expect(summarized.report.tokensAfter).toBeLessThanOrEqual(1400);
+    expect(summarized.prompt).toContain('[Earlier turns, one line each]\n- assistant: Turn 2: detail');
+    expect(summarized.prompt).toMatch(/\[\.\.\. \d+ lines left out to fit the context window \.\.\.\]/);
+    expect(summarized.prompt).toContain('Release 2.0 shipped sync.');
+
+    // Past what the strategies can do, whole sections are left out; the task never is.
+    const cramped = await runtime.assembleContext({ ...request, maxTokens: 1100 });
+    expect(cramped.report.cuts.filter((cut) => cut.strategy === 'dropped').map((cut) => `${cut.source} ${cut.label}`)).toEqual([
+      'memory release-2',
+      'memory release-1',
+      'history all turns',
+      'file src/pipeline.rb',
+    ]);
+    expect(cramped.prompt).toBe('Summarize the release');
+    expect(cramped.report.fits).toBe(true);
+  });
+
+  it('delegates sub-tasks to other agents under typed handoff contracts', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const bodies: Array<{ model: string; tools?: Array<{ function: { name: string } }>; messages: Array<{ role: string; content: string | null }> }> = [];
+    const reviews = ['{"verdict": "maybe"}'];
+    const chunks = (payloads: Array<Record<string, unknown>>) => `${payloads.map((payload) => `data: ${JSON.stringify(payload)}\n\n`).join('')}data: [DONE]\n\n`;
+    const reply = (content: string) => chunks([
+      { choices: [{ index: 0, delta: { role: 'assistant', content }, finish_reason: 'stop' }] },
+      { choices: [], usage: { prompt_tokens: 40, completion_tokens: 10 } },
+    ]);
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk) => { raw += chunk; });
+      request.on('end', () => {
+        const body = JSON.parse(raw) as (typeof bodies)[number];
+        bodies.push(body);
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        if (body.model === 'review-model') {
+          response.end(reply(reviews.shift() ?? '{"verdict": "approve"}'));
+          return;
+        }
+        const result = body.messages.find((message) => message.role === 'tool');
+        response.end(result === undefined
+          ? chunks([
+            { choices: [{ index: 0, delta: { role: 'assistant', tool_calls: [{ index: 0, id: 'call_1', type: 'function', function: { name: 'delegate_reviewer', arguments: '{"task":"Review the patch","input":{"path":"src/auth.ts"}}' } }] }, finish_reason: 'tool_calls' }] },
+            { choices: [], usage: { prompt_tokens: 30, completion_tokens: 10 } },
+          ])
+          : reply(`The reviewer says ${result.content ?? ''}`));
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          lead: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'lead-model' },
+          review: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'review-model' },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+    const contract = {
+      input: { type: 'object', required: ['path'], properties: { path: { type: 'string' } } },
+      output: { type: 'object', required: ['verdict'], properties: { verdict: { enum: ['approve', 'reject'] } }, additionalProperties: false },
+      provider: 'review',
+    };
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      await runtime.registerAgent({
+        agentId: 'lead',
+        name: 'Lead',
+        capabilities: ['planning'],
+        metadata: { provider: 'lead', handoffs: [{ agentId: 'reviewer', description: 'Use it for code review.', ...contract }] },
+      });
+      await runtime.registerAgent({ agentId: 'reviewer', name: 'Reviewer', capabilities: ['review'], metadata: { provider: 'lead' } });
+
+      // The lead calls its handoff tool; the reviewer runs on the contract's provider and is repaired until it fits.
+      const lead = await runtime.runAgent({ agentId: 'lead', task: 'Ship the auth patch', traceId: 'delegation-lead-001' });
+      expect(lead).toMatchObject({ success: true, content: 'The reviewer says {"verdict":"approve"}' });
+      expect(bodies[0]!.tools?.map((tool) => tool.function.name)).toEqual(['read_file', 'list_files', 'delegate_reviewer']);
+      expect(bodies.filter((body) => body.model === 'review-model')).toHaveLength(2);
+      expect(bodies[1]!.messages.at(-1)!.content).toContain('Review the patch');
+      const tree = await runtime.getTraceTree('delegation-lead-001');
+      expect(tree?.children).toHaveLength(1);
+      const child = await runtime.getTrace(tree!.children[0]!.traceId);
+      expect(child?.metadata).toMatchObject({
+        agentId: 'reviewer',
+        provider: 'review',
+        delegationChain: ['lead'],
+        structuredOutput: { value: { verdict: 'approve' }, valid: true, attempts: 2 },
+      });
+
+      const delegated = await runtime.delegateTask({ agentId: 'reviewer', task: 'Review the fix', input: { path: 'src/auth.ts' }, contract, fromAgentId: 'lead' });
+      expect(delegated).toMatchObject({ success: true, provider: 'review', output: { verdict: 'approve' }, usage: { totalTokens: 50 } });
+
+      // Input, budget and chain violations fail the handoff with their own codes.
+      const refused = await Promise.all([
+        runtime.delegateTask({ agentId: 'reviewer', task: 'Review', input: { path: 3 }, contract }),
+        runtime.delegateTask({ agentId: 'reviewer', task: 'Review', input: { path: 'a.ts' }, contract: { ...contract, budget: { maxTokens: 20 } } }),
+        runtime.delegateTask({ agentId: 'reviewer', task: 'Review', input: { path: 'a.ts' }, contract, delegationChain: ['lead', 'reviewer'] }),
+        runtime.delegateTask({ agentId: 'reviewer', task: 'Review', input: { path: 'a.ts' }, contract, delegationChain: ['a', 'b', 'c'] }),
+      ]);
+      expect(refused.map((result) => result.error?.code)).toEqual([
+        'DELEGATION_INPUT_INVALID',
+        'DELEGATION_BUDGET_EXCEEDED',
+        'DELEGATION_CIRCULAR',
+        'DELEGATION_DEPTH_EXCEEDED',
+      ]);
+      expect(refused[0]!.error?.message).toContain('$.path: expected string, got number');
+      expect(refused[0]!.traceId).toBeUndefined();
+      expect(() => parseHandoffContract({ input: { type: 'object' } })).toThrow('The contract needs an "output" JSON Schema object.');
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('runs yaml pipelines with conditions, loops over files, retries and artifacts', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const prompts: string[] = [];
+    let failures = 1;
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk) => { raw += chunk; });
+      request.on('end', () => {
+        const body = JSON.parse(raw) as { messages: Array<{ role: string; content: string }> };
+        const prompt = body.messages.filter((message) => message.role === 'user').map((message) => message.content).join('\n');
+        prompts.push(prompt);
+        if (prompt.includes('Review src/lib/b.ts') && failures > 0) {
+          failures -= 1;
+          response.writeHead(500, { 'content-type': 'application/json' });
+          response.end('{"error":{"message":"overloaded"}}');
+          return;
+        }
+        const content = prompt.includes('Summarize') ? 'All clear.' : `Looks fine: ${/Review (\S+)/.exec(prompt)?.[1] ?? ''}`;
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        response.end([
+          { choices: [{ index: 0, delta: { role: 'assistant', content }, finish_reason: 'stop' }] },
+          { choices: [], usage: { prompt_tokens: 20, completion_tokens: 5 } },
+        ].map((payload) => `data: ${JSON.stringify(payload)}\n\n`).join('') + 'data: [DONE]\n\n');
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    mkdirSync(join(tempDir, 'src', 'lib'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: { executors: { local: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'local-model' } } },
+    }, null, 2)}\n`, 'utf8');
+    await writeFile(join(tempDir, 'src', 'a.ts'), 'export const a = 1;\n', 'utf8');
+    await writeFile(join(tempDir, 'src', 'lib', 'b.ts'), 'export const b = 2;\n', 'utf8');
+    await writeFile(join(tempDir, 'README.md'), '# Demo\n', 'utf8');
+    await writeFile(join(tempDir, 'pipeline.yaml'), [
+      'name: style-review',
+      'inputs:',
+      '  strict: false',
+      'stages:',
+      '  - id: review',
+      '    agent: reviewer',
+      '    task: "Review {{item}}"',
+      '    foreach: "src/**/*.ts"',
+      '    retry: { maxAttempts: 2, backoffMs: 0 }',
+      '  - id: fix',
+      '    agent: reviewer',
+      '    task: Fix what the review found',
+      '    when: inputs.strict',
+      '  - id: summary',
+      '    agent: reviewer',
+      '    needs: [fix]',
+      '    artifacts: [review]',
+      '    task: "Summarize the review of {{stages.review.outputs.length}} files for {{inputs.team}}"',
+      '',
+    ].join('\n'), 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      await runtime.registerAgent({ agentId: 'reviewer', name: 'Reviewer', capabilities: ['review'], metadata: { provider: 'local' } });
+
+      const streamed: Array<[string, string]> = [];
+      const result = await runtime.runPipeline({ file: 'pipeline.yaml', input: { team: 'core' }, traceId: 'pipeline-001', onText: (text, stageId) => streamed.push([stageId, text]) });
+      expect(result).toMatchObject({ traceId: 'pipeline-001', pipeline: 'style-review', success: true, artifactDir: '.automatosx/pipelines/pipeline-001' });
+      expect(result.stages.map((stage) => [stage.id, stage.status])).toEqual([['review', 'succeeded'], ['fix', 'skipped'], ['summary', 'succeeded']]);
+      // The loop covers the matching files only, and the item that failed once was retried.
+      expect(result.stages[0]!.runs.map((run) => [run.item, run.attempts])).toEqual([['src/a.ts', 1], ['src/lib/b.ts', 2]]);
+      expect(result.stages[1]!.reason).toBe('"inputs.strict" does not hold.');
+      // Each stage's replies stream as they arrive, tagged with the stage.
+      expect(streamed).toEqual([['review', 'Looks fine: src/a.ts'], ['review', 'Looks fine: src/lib/b.ts'], ['summary', 'All clear.']]);
+      const artifact = await readFile(join(tempDir, '.automatosx', 'pipelines', 'pipeline-001', 'review.md'), 'utf8');
+      expect(artifact).toBe('## src/a.ts\n\nLooks fine: src/a.ts\n\n## src/lib/b.ts\n\nLooks fine: src/lib/b.ts\n');
+      // The review's artifact goes to the summary attached, after a skipped stage it needs.
+      expect(prompts.at(-1)).toContain('Summarize the review of 2 files for core');
+      expect(prompts.at(-1)).toContain('Looks fine: src/lib/b.ts');
+      expect((await runtime.getTrace('pipeline-001'))?.stepResults.map((step) => [step.stepId, step.retryCount])).toEqual([['review', 1], ['fix', 0], ['summary', 0]]);
+
+      expect(() => parsePipeline('stages:\n  - id: a\n    agent: x\n    task: t\n    needs: [b]\n', 'bad.yaml'))
+        .toThrow('bad.yaml has stages that cannot run: "a" depends on unknown "b".');
+      await expect(runtime.runPipeline({ file: 'missing.yaml' })).rejects.toMatchObject({ code: 'PIPELINE_NOT_FOUND' });
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('resumes pipeline runs from the last completed stage', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const tasks: string[] = [];
+    let outage = true;
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk) => { raw += chunk; });
+      request.on('end', () => {
+        const body = JSON.parse(raw) as { messages: Array<{ role: string; content: string }> };
+        const task = /(?:Plan|Write) [^\n]*/.exec(body.messages.filter((message) => message.role === 'user').map((message) => message.content).join('\n'))?.[0] ?? '';
+        tasks.push(task);
+        if (task.startsWith('Write the tests') && outage) {
+          response.writeHead(503, { 'content-type': 'application/json' });
+          response.end('{"error":{"message":"unavailable"}}');
+          return;
+        }
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        response.end([
+          { choices: [{ index: 0, delta: { role: 'assistant', content: `Done: ${task}` }, finish_reason: 'stop' }] },
+          { choices: [], usage: { prompt_tokens: 20, completion_tokens: 5 } },
+        ].map((payload) => `data: ${JSON.stringify(payload)}\n\n`).join('') + 'data: [DONE]\n\n');
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: { executors: { local: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'local-model' } } },
+    }, null, 2)}\n`, 'utf8');
+    await writeFile(join(tempDir, 'pipeline.yaml'), [
+      'stages:',
+      '  - id: plan',
+      '    agent: builder',
+      '    task: Plan the feature',
+      '  - id: tests',
+      '    agent: builder',
+      '    task: "Write the tests for {{inputs.feature}}"',
+      '  - id: code',
+      '    agent: builder',
+      '    task: "Write the code after {{stages.tests.output}}"',
+      '',
+    ].join('\n'), 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      await runtime.registerAgent({ agentId: 'builder', name: 'Builder', capabilities: ['coding'], metadata: { provider: 'local' } });
+
+      const failed = await runtime.runPipeline({ file: 'pipeline.yaml', input: { feature: 'login' }, traceId: 'pipeline-resume-001' });
+      expect(failed.stages.map((stage) => stage.status)).toEqual(['succeeded', 'failed', 'skipped']);
+      const checkpointPath = join(tempDir, '.automatosx', 'pipelines', 'pipeline-resume-001', 'checkpoint.json');
+      expect(JSON.parse(await readFile(checkpointPath, 'utf8'))).toMatchObject({ status: 'failed', file: 'pipeline.yaml', input: { feature: 'login' } });
+
+      // The resume runs the pipeline it started with, even with the file gone, and skips the plan it already has.
+      outage = false;
+      await rm(join(tempDir, 'pipeline.yaml'));
+      const before = tasks.length;
+      const resumed = await runtime.resumePipeline({ runId: 'pipeline-resume-001' });
+      expect(resumed).toMatchObject({ traceId: 'pipeline-resume-001', success: true });
+      expect(resumed.stages.map((stage) => [stage.id, stage.status, stage.resumed === true])).toEqual([
+        ['plan', 'succeeded', true],
+        ['tests', 'succeeded', false],
+        ['code', 'succeeded', false],
+      ]);
+      expect(tasks.slice(before)).toEqual([
+        'Write the tests for login',
+        'Write the code after Done: Write the tests for login',
+      ]);
+      expect(JSON.parse(await readFile(checkpointPath, 'utf8'))).toMatchObject({ status: 'completed' });
+      expect((await runtime.getTrace('pipeline-resume-001'))?.status).toBe('completed');
+      await expect(runtime.resumePipeline({ runId: '../escape' })).rejects.toMatchObject({ code: 'PIPELINE_CHECKPOINT_NOT_FOUND' });
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('stops agent runs and pipelines that go over their budget', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const server = createServer((request, response) => {
+      request.resume();
+      request.on('end', () => {
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        response.end([
+          { choices: [{ index: 0, delta: { role: 'assistant', content: 'Done.' }, finish_reason: 'stop' }] },
+          { choices: [], usage: { prompt_tokens: 20, completion_tokens: 5 } },
+        ].map((payload) => `data: ${JSON.stringify(payload)}\n\n`).join('') + 'data: [DONE]\n\n');
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: { executors: { local: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'local-model' } } },
+    }, null, 2)}\n`, 'utf8');
+    await writeFile(join(tempDir, 'pipeline.yaml'), [
+      'budget:',
+      '  max_tokens: 40',
+      'stages:',
+      '  - id: plan',
+      '    agent: writer',
+      '    task: Plan the feature',
+      '  - id: code',
+      '    agent: writer',
+      '    task: Write the code',
+      '  - id: docs',
+      '    agent: writer',
+      '    task: Write the docs',
+      '',
+    ].join('\n'), 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      await runtime.registerAgent({ agentId: 'writer', name: 'Writer', capabilities: ['coding'], metadata: { provider: 'local' } });
+      await runtime.registerAgent({ agentId: 'terse', name: 'Terse', capabilities: ['coding'], metadata: { provider: 'local', budget: { max_tokens: 10 } } });
+      await runtime.registerAgent({ agentId: 'misconfigured', name: 'Misconfigured', capabilities: ['coding'], metadata: { provider: 'local', budget: { max_tokens: -1 } } });
+
+      // The profile's limit is the tighter one; the reply is kept, but the run fails and says what it used.
+      const agentRun = await runtime.runAgent({ agentId: 'terse', task: 'Summarize', budget: { maxTokens: 1000, maxDurationMs: 60_000 } });
+      expect(agentRun).toMatchObject({
+        success: false,
+        content: 'Done.',
+        error: { code: 'BUDGET_EXCEEDED', message: 'The token budget of 10 was exceeded (25 used).' },
+        budget: {
+          limits: { maxTokens: 10, maxDurationMs: 60_000 },
+          consumption: { calls: 1, inputTokens: 20, outputTokens: 5, totalTokens: 25 },
+          exceeded: { limit: 'maxTokens', max: 10, used: 25 },
+        },
+      });
+      expect((await runtime.getTrace(agentRun.traceId))?.status).toBe('failed');
+      expect((await runtime.runAgent({ agentId: 'misconfigured', task: 'Summarize' })).error).toMatchObject({ code: 'BUDGET_INVALID' });
+
+      // Stage runs are charged to the pipeline: the second one goes over, and the third never starts.
+      const stopped = await runtime.runPipeline({ file: 'pipeline.yaml', traceId: 'pipeline-budget-001' });
+      expect(stopped).toMatchObject({
+        success: false,
+        error: { code: 'BUDGET_EXCEEDED' },
+        budget: { consumption: { calls: 2, totalTokens: 50 }, exceeded: { limit: 'maxTokens', max: 40 } },
+      });
+      expect(stopped.stages.map((stage) => [stage.id, stage.status])).toEqual([['plan', 'succeeded'], ['code', 'succeeded'], ['docs', 'skipped']]);
+      expect(stopped.stages[2]?.reason).toBe('The token budget of 40 was exceeded (50 used).');
+
+      // A resume gets the budget afresh, and the rest of the run fits in it.
+      const resumed = await runtime.resumePipeline({ runId: 'pipeline-budget-001' });
+      expect(resumed).toMatchObject({ success: true, budget: { consumption: { calls: 1, totalTokens: 25 } } });
+
+      // A workflow's prompt steps are charged to its budget: once the first goes over, the second is stopped.
+      await writeFile(join(tempDir, 'drafts.json'), `${JSON.stringify({
+        workflowId: 'drafts',
+        name: 'Drafts',
+        version: '1.0.0',
+        steps: [
+          { stepId: 'outline', type: 'prompt', config: { prompt: 'Outline the feature.', provider: 'local' } },
+          { stepId: 'draft', type: 'prompt', config: { prompt: 'Draft the feature.', provider: 'local' } },
+        ],
+      }, null, 2)}\n`, 'utf8');
+      const workflowRun = await runtime.runWorkflow({ workflowId: 'drafts', workflowDir: tempDir, budget: { maxTokens: 20 } });
+      expect(workflowRun).toMatchObject({
+        success: false,
+        error: { code: 'BUDGET_EXCEEDED', message: 'The token budget of 20 was exceeded (25 used).' },
+        budget: { consumption: { totalTokens: 25 }, exceeded: { limit: 'maxTokens', max: 20 } },
+      });
+      expect(workflowRun.stepResults.map((step) => [step.stepId, step.success])).toEqual([['outline', true], ['draft', false]]);
+      expect((await runtime.getTrace(workflowRun.traceId))?.status).toBe('failed');
+
+      expect(() => parsePipeline('stages:\n  - id: plan\n    agent: writer\n    task: Plan\n    budget:\n      max_duration: soon\n', 'bad.yaml'))
+        .toThrow(expect.objectContaining({ code: 'PIPELINE_INVALID', message: 'bad.yaml stage "plan" "budget" has a "max_duration" that is not a positive duration.' }));
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('holds agents to the paths, shell and network access their profile permits', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const toolResults: unknown[] = [];
+    const chunks = (payloads: Array<Record<string, unknown>>) => `${payloads.map((payload) => `data: ${JSON.stringify(payload)}\n\n`).join('')}data: [DONE]\n\n`;
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk) => { raw += chunk; });
+      request.on('end', () => {
+        const body = JSON.parse(raw) as { messages: Array<{ role: string; content: unknown }> };
+        const toolMessage = body.messages.find((message) => message.role === 'tool');
+        toolResults.push(toolMessage?.content);
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        response.end(toolMessage === undefined
+          ? chunks([
+            { choices: [{ index: 0, delta: { role: 'assistant', tool_calls: [{ index: 0, id: 'call_1', type: 'function', function: { name: 'read_file', arguments: '{"path":"src/secret.ts"}' } }] }, finish_reason: 'tool_calls' }] },
+          ])
+          : chunks([{ choices: [{ index: 0, delta: { role: 'assistant', content: 'I may not read it.' }, finish_reason: 'stop' }] }]));
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    const scriptPath = join(tempDir, 'writer.mjs');
+    await writeFile(scriptPath, [
+      "import { mkdirSync, writeFileSync } from 'node:fs';",
+      "let input = '';",
+      "process.stdin.setEncoding('utf8');",
+      "process.stdin.on('data', (chunk) => { input += chunk; });",
+      "process.stdin.on('end', () => {",
+      "  const payload = JSON.parse(input || '{}');",
+      "  mkdirSync('docs', { recursive: true });",
+      "  writeFileSync('docs/guide.md', '# Guide\\n');",
+      "  writeFileSync('src/app.ts', 'export const patched = true;\\n');",
+      "  process.stdout.write(JSON.stringify({",
+      "    success: true,",
+      "    provider: payload.provider,",
+      "    content: JSON.stringify({ payload: payload.permissions, env: JSON.parse(process.env.AUTOMATOSX_AGENT_PERMISSIONS) })",
+      "  }));",
+      "});",
+    ].join('\n'), 'utf8');
+    mkdirSync(join(tempDir, 'src'), { recursive: true });
+    await writeFile(join(tempDir, 'src', 'app.ts'), 'export const patched = false;\n', 'utf8');
+    await writeFile(join(tempDir, 'src', 'secret.ts'), 'export const token = "hunter2";\n', 'utf8');
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          local: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'local-model' },
+          claude: { command: 'node', args: [scriptPath] },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      const permissions = { paths: ['docs/**'] };
+      await runtime.registerAgent({ agentId: 'reader', name: 'Reader', capabilities: ['docs'], metadata: { provider: 'local', permissions } });
+      await runtime.registerAgent({ agentId: 'writer', name: 'Writer', capabilities: ['docs'], metadata: { provider: 'claude', permissions } });
+      await runtime.registerAgent({ agentId: 'loose', name: 'Loose', capabilities: ['docs'], metadata: { provider: 'claude', permissions: { shell: 'yes' } } });
+
+      // Reading outside the paths is refused through the tool and logged, but the run goes on.
+      const read = await runtime.runAgent({ agentId: 'reader', task: 'Show me the secret', traceId: 'permissions-read-001' });
+      expect(read).toMatchObject({
+        success: true,
+        content: 'I may not read it.',
+        permissions: { paths: ['docs/**'], shell: false, network: false },
+        permissionViolations: [{ kind: 'path', operation: 'read', target: 'src/secret.ts' }],
+      });
+      expect(toolResults[1]).toBe('Error: "src/secret.ts" is outside the paths this agent may read.');
+      expect(read.warnings).toContain('Permission violation: read of "src/secret.ts" outside the agent\'s paths.');
+      expect((await runtime.getTrace('permissions-read-001'))?.metadata).toMatchObject({
+        permissionViolations: [{ kind: 'path', operation: 'read', target: 'src/secret.ts' }],
+      });
+
+      // A CLI executor is told its permissions, and changing a file outside them fails the run.
+      const written = await runtime.runAgent({ agentId: 'writer', task: 'Write the guide', traceId: 'permissions-write-001' });
+      const expected = { paths: ['docs/**'], shell: false, network: false };
+      expect(JSON.parse(written.content)).toEqual({ payload: expected, env: expected });
+      expect(written).toMatchObject({
+        success: false,
+        error: { code: 'AGENT_PERMISSION_DENIED', message: 'Agent "writer" went beyond its permissions: write of "src/app.ts" outside the agent\'s paths.' },
+        permissionViolations: [{ kind: 'path', operation: 'write', target: 'src/app.ts' }],
+      });
+      expect((await runtime.getTrace('permissions-write-001'))?.status).toBe('failed');
+
+      expect((await runtime.runAgent({ agentId: 'loose', task: 'Anything' })).error).toMatchObject({
+        code: 'AGENT_PERMISSIONS_INVALID',
+        message: 'The permissions of agent "loose" have a "shell" that is not true or false.',
+      });
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('audits the whole workspace after CLI agent runs and holds provider CLIs to the agent\'s shell and network permissions', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const writerPath = join(tempDir, 'writer.mjs');
+    await writeFile(writerPath, [
+      "import { mkdirSync, writeFileSync } from 'node:fs';",
+      "process.stdin.resume();",
+      "process.stdin.on('end', () => {",
+      "  writeFileSync('guide.md', 'Guide.\\n');",
+      "  writeFileSync('../src/app.ts', 'export const patched = true;\\n');",
+      "  mkdirSync('../node_modules/left-pad', { recursive: true });",
+      "  writeFileSync('../node_modules/left-pad/index.js', 'module.exports = 1;\\n');",
+      "  writeFileSync('../.git/hooks/pre-commit', '#!/bin/sh\\n');",
+      "  writeFileSync('../.git/HEAD', 'ref: refs/heads/patched\\n');",
+      "  writeFileSync('../.automatosx/runtime/notes.json', '{}');",
+      "  process.stdout.write(JSON.stringify({ success: true, content: 'Wrote the guide.' }));",
+      "});",
+    ].join('\n'), 'utf8');
+    const argvScriptPath = join(tempDir, 'print-argv.mjs');
+    await writeFile(argvScriptPath, "process.stdin.resume();\nprocess.stdin.on('end', () => process.stdout.write(`ARGV:${process.argv.slice(2).join(' ')}`));\n", 'utf8');
+    for (const command of ['claude', 'gemini']) {
+      const shimPath = join(tempDir, process.platform === 'win32' ? `${command}.cmd` : command);
+      await writeFile(
+        shimPath,
+        process.platform === 'win32'
+          ? `@echo off\r\nnode "${argvScriptPath}" %*\r\n`
+          : `#!/bin/sh\nnode "${argvScriptPath}" "$@"\n`,
+        'utf8',
+      );
+      if (process.platform !== 'win32') {
+        await execFileAsync('chmod', ['+x', shimPath]);
+      }
+    }
+    mkdirSync(join(tempDir, 'docs'), { recursive: true });
+    mkdirSync(join(tempDir, 'src'), { recursive: true });
+    await writeFile(join(tempDir, 'src', 'app.ts'), 'export const patched = false;\n', 'utf8');
+    mkdirSync(join(tempDir, '.git', 'hooks'), { recursive: true });
+    await writeFile(join(tempDir, '.git', 'HEAD'), 'ref: refs/heads/main\n', 'utf8');
+    mkdirSync(join(tempDir, '.automatosx', 'runtime'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: { executors: { local: { command: 'node', args: [writerPath] } } },
+    }, null, 2)}\n`, 'utf8');
+
+    const originalPath = process.env.PATH;
+    const originalNativeAdapters = process.env.AUTOMATOSX_PROVIDER_NATIVE_ADAPTERS;
+    process.env.PATH = `${tempDir}${process.platform === 'win32' ? ';' : ':'}${originalPath ?? ''}`;
+    process.env.AUTOMATOSX_PROVIDER_NATIVE_ADAPTERS = 'true';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      const register = (agentId: string, provider: string, permissions: Record<string, unknown>) => runtime.registerAgent({
+        agentId,
+        name: agentId,
+        capabilities: ['docs'],
+        metadata: { provider, permissions },
+      });
+      await register('scribe', 'local', { paths: ['docs/**'] });
+      await register('reviewer', 'claude', { paths: ['docs/**'] });
+      await register('operator', 'claude', { shell: true });
+      await register('researcher', 'gemini', { network: true });
+
+      // Writes outside the scope, in ignored directories and git hooks too, are seen and put back.
+      const scoped = await runtime.runAgent({ agentId: 'scribe', task: 'Write the guide', scope: 'docs' });
+      expect(scoped).toMatchObject({ success: false, error: { code: 'AGENT_PERMISSION_DENIED' } });
+      expect(scoped.permissionViolations?.map((violation) => violation.target).sort()).toEqual(['.git/hooks/pre-commit', 'node_modules/left-pad/index.js', 'src/app.ts']);
+      expect(scoped.warnings).toContain('Put back as they were before the run: .git/hooks/pre-commit, node_modules/left-pad/index.js, src/app.ts.');
+      expect(await readFile(join(tempDir, 'src', 'app.ts'), 'utf8')).toBe('export const patched = false;\n');
+      expect(existsSync(join(tempDir, 'node_modules', 'left-pad', 'index.js'))).toBe(false);
+      expect(existsSync(join(tempDir, '.git', 'hooks', 'pre-commit'))).toBe(false);
+      expect(await readFile(join(tempDir, 'docs', 'guide.md'), 'utf8')).toBe('Guide.\n');
+
+      expect((await runtime.runAgent({ agentId: 'reviewer', task: 'Review the docs' })).content).toBe('ARGV:--disallowedTools=Bash,WebFetch,WebSearch');
+      expect((await runtime.runAgent({ agentId: 'researcher', task: 'Look it up' })).content).toBe('ARGV:--approval-mode auto_edit');
+      expect((await runtime.runAgent({ agentId: 'operator', task: 'Run the build' })).error).toEqual({
+        code: 'PROVIDER_UNRESTRICTABLE',
+        message: 'Provider "claude" runs "claude", which cannot be kept off the network; configure an API or json-stdio executor for it.',
+      });
+    } finally {
+      process.env.PATH = originalPath;
+      process.env.AUTOMATOSX_PROVIDER_NATIVE_ADAPTERS = originalNativeAdapters;
+    }
+  });
+
+  it('holds agents\' shell commands, writes and spending until they are approved', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const toolResults: unknown[] = [];
+    const chunks = (payloads: Array<Record<string, unknown>>) => `${payloads.map((payload) => `data: ${JSON.stringify(payload)}\n\n`).join('')}data: [DONE]\n\n`;
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk) => { raw += chunk; });
+      request.on('end', () => {
+        const body = JSON.parse(raw) as { messages: Array<{ role: string; content: unknown }>; tools?: Array<{ function: { name: string } }> };
+        const toolMessages = body.messages.filter((message) => message.role === 'tool');
+        toolResults.push(...toolMessages.slice(toolResults.length).map((message) => message.content));
+        const command = ['echo approved', 'echo refused'][toolMessages.length];
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        response.end(command !== undefined && (body.tools ?? []).some((tool) => tool.function.name === 'run_command')
+          ? chunks([
+            { choices: [{ index: 0, delta: { role: 'assistant', tool_calls: [{ index: 0, id: `call_${toolMessages.length}`, type: 'function', function: { name: 'run_command', arguments: JSON.stringify({ command }) } }] }, finish_reason: 'tool_calls' }] },
+          ])
+          : chunks([
+            { choices: [{ index: 0, delta: { role: 'assistant', content: 'Done.' }, finish_reason: 'stop' }] },
+            { choices: [], usage: { prompt_tokens: 20, completion_tokens: 5 } },
+          ]));
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        pricing: { 'local-model': { input: 10_000, output: 10_000 } },
+        executors: { local: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'local-model' } },
+      },
+    }, null, 2)}\n`, 'utf8');
+    await writeFile(join(tempDir, '.automatosx', 'approval-policy.json'), JSON.stringify({ shell: ['echo app*'] }), 'utf8');
+    await writeFile(join(tempDir, 'ci-approvals.json'), JSON.stringify({ max_cost_usd: 1 }), 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      await runtime.registerAgent({
+        agentId: 'operator',
+        name: 'Operator',
+        capabilities: ['ops'],
+        metadata: { provider: 'local', permissions: { shell: true }, approvals: { shell: true } },
+      });
+      await runtime.registerAgent({ agentId: 'spender', name: 'Spender', capabilities: ['ops'], metadata: { provider: 'local', approvals: { cost_usd: 0.1 } } });
+      await runtime.registerAgent({ agentId: 'strict', name: 'Strict', capabilities: ['ops'], metadata: { provider: 'local', approvals: { shell: 'always' } } });
+
+      // The policy lets the first command through; the second is put to the approver, who refuses it.
+      const asked: string[] = [];
+      const operated = await runtime.runAgent({
+        agentId: 'operator',
+        task: 'Check the build',
+        traceId: 'approvals-shell-001',
+        approve: async (request) => {
+          asked.push(request.target);
+          return false;
+        },
+      });
+      expect(operated).toMatchObject({ success: true, content: 'Done.', approvals: { shell: true } });
+      expect(asked).toEqual(['echo refused']);
+      expect(toolResults).toEqual(['Exit code 0\napproved\n', 'Error: The command "echo refused" was not approved.']);
+      expect(operated.approvalDecisions).toMatchObject([
+        { action: 'shell', target: 'echo approved', approved: true, by: 'policy' },
+        { action: 'shell', target: 'echo refused', approved: false, by: 'user' },
+      ]);
+      expect(operated.warnings).toContain('Not approved: run the shell command "echo refused".');
+      expect((await runtime.getTrace('approvals-shell-001'))?.metadata).toMatchObject({ approvals: [{ approved: true }, { approved: false }] });
+
+      // Spending over the threshold with nobody to ask fails the run, keeping its reply; the policy can allow it.
+      const refused = await runtime.runAgent({ agentId: 'spender', task: 'Summarize' });
+      expect(refused).toMatchObject({
+        success: false,
+        content: 'Done.',
+        error: { code: 'APPROVAL_DENIED', message: 'Agent "spender" was not approved to go on after spending $0.2500.' },
+        approvalDecisions: [{ action: 'cost', costUsd: 0.25, approved: false, by: 'none' }],
+      });
+      expect(refused.warnings).toContain('Not approved: go on after spending $0.2500 (nobody could be asked).');
+      const allowed = await runtime.runAgent({ agentId: 'spender', task: 'Summarize', approvalPolicy: 'ci-approvals.json' });
+      expect(allowed).toMatchObject({ success: true, approvalDecisions: [{ action: 'cost', approved: true, by: 'policy' }] });
+
+      expect((await runtime.runAgent({ agentId: 'strict', task: 'Anything' })).error).toMatchObject({
+        code: 'APPROVALS_INVALID',
+        message: 'The approvals of agent "strict" have a "shell" that is not true or false.',
+      });
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('checks the files API agents change with run_command against their paths and write gates, and leaves it out in read-only mode', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const offered: string[][] = [];
+    let commands: string[] = [];
+    const chunks = (payloads: Array<Record<string, unknown>>) => `${payloads.map((payload) => `data: ${JSON.stringify(payload)}\n\n`).join('')}data: [DONE]\n\n`;
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk) => { raw += chunk; });
+      request.on('end', () => {
+        const body = JSON.parse(raw) as { messages: Array<{ role: string }>; tools?: Array<{ function: { name: string } }> };
+        const round = body.messages.filter((message) => message.role === 'tool').length;
+        const tools = (body.tools ?? []).map((tool) => tool.function.name);
+        if (round === 0) {
+          offered.push(tools);
+        }
+        const command = commands[round];
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        response.end(command !== undefined && tools.includes('run_command')
+          ? chunks([
+            { choices: [{ index: 0, delta: { role: 'assistant', tool_calls: [{ index: 0, id: `call_${round}`, type: 'function', function: { name: 'run_command', arguments: JSON.stringify({ command }) } }] }, finish_reason: 'tool_calls' }] },
+          ])
+          : chunks([
+            { choices: [{ index: 0, delta: { role: 'assistant', content: 'Done.' }, finish_reason: 'stop' }] },
+            { choices: [], usage: { prompt_tokens: 20, completion_tokens: 5 } },
+          ]));
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}/v1`;
+    mkdirSync(join(tempDir, 'docs', 'drafts'), { recursive: true });
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: { executors: { local: { api: 'openai', baseUrl, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'local-model' } } },
+    }, null, 2)}\n`, 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      await runtime.registerAgent({
+        agentId: 'scribe',
+        name: 'Scribe',
+        capabilities: ['docs'],
+        metadata: { provider: 'local', permissions: { paths: ['docs/**'], shell: true }, approvals: { writes: ['docs/drafts/**'] } },
+      });
+
+      // Once the run is done, a write outside the write gates is put to the approver; refused, it is
+      // put back and fails the run.
+      const asked: string[] = [];
+      commands = ['echo draft > docs/drafts/plan.md && echo guide > docs/guide.md'];
+      const gated = await runtime.runAgent({
+        agentId: 'scribe',
+        task: 'Draft the guide',
+        approve: async (request) => {
+          asked.push(request.target);
+          return false;
+        },
+      });
+      expect(asked).toEqual(['docs/guide.md']);
+      expect(gated).toMatchObject({
+        success: false,
+        content: 'Done.',
+        error: { code: 'APPROVAL_DENIED', message: 'Agent "scribe" was not approved to write "docs/guide.md" outside its write scope.' },
+        approvalDecisions: [{ action: 'write', target: 'docs/guide.md', approved: false, by: 'user' }],
+      });
+      expect(gated.warnings).toContain('Put back as they were before the run: docs/guide.md.');
+      expect(existsSync(join(tempDir, 'docs', 'guide.md'))).toBe(false);
+      expect(await readFile(join(tempDir, 'docs', 'drafts', 'plan.md'), 'utf8')).toBe('draft\n');
+
+      // A write outside the paths fails the run.
+      commands = ['echo oops > notes.txt'];
+      expect((await runtime.runAgent({ agentId: 'scribe', task: 'Take notes' })).error).toEqual({
+        code: 'AGENT_PERMISSION_DENIED',
+        message: 'Agent "scribe" went beyond its permissions: write of "notes.txt" outside the agent\'s paths.',
+      });
+
+      // Read-only mode offers no run_command at all.
+      commands = ['echo late > docs/drafts/late.md'];
+      const readOnly = createSharedRuntimeService({ basePath: tempDir, readOnly: true });
+      expect(await readOnly.runAgent({ agentId: 'scribe', task: 'Draft more' })).toMatchObject({ success: true, content: 'Done.' });
+      expect(offered.map((tools) => tools.includes('run_command'))).toEqual([true, true, false]);
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise<void>((resolve) => server.close(() => resolve()));
+    }
+  });
+
+  it('refuses to start CLI agent runs whose writes or shell commands wait for approval', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const replyPath = join(tempDir, 'reply.mjs');
+    await writeFile(replyPath, "process.stdin.resume();\nprocess.stdin.on('end', () => process.stdout.write('Done.'));\n", 'utf8');
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: { executors: { local: { command: 'node', args: [replyPath] } } },
     }, null, 2)}\n`, 'utf8');
 
-    const runtime = createSharedRuntimeService({ basePath: join(process.cwd(), 'tmp', 'unrelated-runtime-root') });
-    const result = await runtime.callProvider({
-      prompt: 'workspace scoped prompt',
-      provider: 'claude',
-      traceId: 'workspace-call-001',
-      basePath: tempDir,
-      surface: 'cli',
-    });
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.registerAgent({ agentId: 'drafter', name: 'Drafter', capabilities: ['docs'], metadata: { provider: 'local', approvals: { writes: ['docs/**'], shell: true } } });
+    await runtime.registerAgent({ agentId: 'reader', name: 'Reader', capabilities: ['docs'], metadata: { provider: 'local', permissions: { paths: ['docs/**'] }, approvals: { shell: true } } });
 
-    expect(result.success).toBe(true);
-    expect(result.executionMode).toBe('subprocess');
-    expect(result.content).toContain('WORKSPACE:claude:workspace scoped prompt');
+    expect((await runtime.runAgent({ agentId: 'drafter', task: 'Draft the guide' })).error).toEqual({
+      code: 'APPROVALS_UNSUPPORTED',
+      message: 'Agent "drafter" holds writes and shell for approval, but provider "local" runs the CLI "node", which cannot wait for approval; configure an API executor for it.',
+    });
+    // Commands of agents that may not use the shell are not held, nor writes in read-only mode.
+    expect(await runtime.runAgent({ agentId: 'reader', task: 'Read the docs' })).toMatchObject({ success: true, content: 'Done.' });
+    const readOnly = createSharedRuntimeService({ basePath: tempDir, readOnly: true });
+    expect((await readOnly.runAgent({ agentId: 'drafter', task: 'Draft the guide' })).error).toMatchObject({
+      code: 'APPROVALS_UNSUPPORTED',
+      message: expect.stringContaining('holds shell for approval'),
+    });
   });
 
   it('uses native provider presets when a matching CLI is installed', async () => {
@@ -364,6 +2359,512 @@ describe('shared runtime service', () => {
     }
   });
 
+  it('holds native provider CLIs to read-only mode with their own flags', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const argvScriptPath = join(tempDir, 'print-argv.mjs');
+    await writeFile(argvScriptPath, "process.stdin.resume();\nprocess.stdin.on('end', () => process.stdout.write(`ARGV:${process.argv.slice(2).join(' ')}`));\n", 'utf8');
+    for (const command of ['claude', 'codex', 'ax-grok']) {
+      const shimPath = join(tempDir, process.platform === 'win32' ? `${command}.cmd` : command);
+      await writeFile(
+        shimPath,
+        process.platform === 'win32'
+          ? `@echo off\r\nnode "${argvScriptPath}" %*\r\n`
+          : `#!/bin/sh\nnode "${argvScriptPath}" "$@"\n`,
+        'utf8',
+      );
+      if (process.platform !== 'win32') {
+        await execFileAsync('chmod', ['+x', shimPath]);
+      }
+    }
+
+    const originalPath = process.env.PATH;
+    const originalNativeAdapters = process.env.AUTOMATOSX_PROVIDER_NATIVE_ADAPTERS;
+    process.env.PATH = `${tempDir}${process.platform === 'win32' ? ';' : ':'}${originalPath ?? ''}`;
+    process.env.AUTOMATOSX_PROVIDER_NATIVE_ADAPTERS = 'true';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir, readOnly: true });
+      const call = (provider: string) => runtime.callProvider({ prompt: 'Summarize release risk.', provider });
+      expect((await call('claude')).content).toBe('ARGV:--permission-mode plan --disallowedTools=Bash,Edit,MultiEdit,Write,NotebookEdit');
+      expect((await call('codex')).content).toBe('ARGV:--sandbox read-only');
+      expect(await call('grok')).toMatchObject({
+        success: false,
+        error: {
+          code: 'PROVIDER_UNRESTRICTABLE',
+          message: 'Provider "grok" runs "ax-grok", which cannot be kept read-only; configure an API or json-stdio executor for it.',
+        },
+      });
+
+      const writable = createSharedRuntimeService({ basePath: tempDir });
+      expect((await writable.callProvider({ prompt: 'Summarize release risk.', provider: 'claude' })).content).toBe('ARGV:');
+    } finally {
+      process.env.PATH = originalPath;
+      process.env.AUTOMATOSX_PROVIDER_NATIVE_ADAPTERS = originalNativeAdapters;
+    }
+  });
+
+  it('runs an agent through the Anthropic Messages API with streamed text and tool use', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    mkdirSync(join(tempDir, 'docs'), { recursive: true });
+    await writeFile(join(tempDir, 'docs', 'release.md'), 'Ship on Friday.\n', 'utf8');
+
+    const requests: Array<{ headers: IncomingMessage['headers']; body: Record<string, unknown> }> = [];
+    const sse = (events: Array<Record<string, unknown>>) => events.map((event) => `event: ${event.type}\ndata: ${JSON.stringify(event)}\n\n`).join('');
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk) => { raw += chunk; });
+      request.on('end', () => {
+        requests.push({ headers: request.headers, body: JSON.parse(raw) });
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        response.end(requests.length === 1
+          ? sse([
+            { type: 'message_start', message: { usage: { input_tokens: 40, output_tokens: 1 } } },
+            { type: 'content_block_start', index: 0, content_block: { type: 'tool_use', id: 'toolu_1', name: 'read_file', input: {} } },
+            { type: 'content_block_delta', index: 0, delta: { type: 'input_json_delta', partial_json: '{"path": "docs/' } },
+            { type: 'content_block_delta', index: 0, delta: { type: 'input_json_delta', partial_json: 'release.md"}' } },
+            { type: 'content_block_stop', index: 0 },
+            { type: 'message_delta', delta: { stop_reason: 'tool_use' }, usage: { output_tokens: 12 } },
+            { type: 'message_stop' },
+          ])
+          : sse([
+            { type: 'message_start', message: { usage: { input_tokens: 60, output_tokens: 1 } } },
+            { type: 'content_block_start', index: 0, content_block: { type: 'text', text: '' } },
+            { type: 'content_block_delta', index: 0, delta: { type: 'text_delta', text: 'The release ' } },
+            { type: 'content_block_delta', index: 0, delta: { type: 'text_delta', text: 'ships on Friday.' } },
+            { type: 'content_block_stop', index: 0 },
+            { type: 'message_delta', delta: { stop_reason: 'end_turn' }, usage: { output_tokens: 8 } },
+            { type: 'message_stop' },
+          ]));
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const port = (server.address() as AddressInfo).port;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          claude: { api: 'anthropic', baseUrl: `http://127.0.0.1:${port}`, apiKeyEnv: 'AX_TEST_ANTHROPIC_KEY', model: 'claude-test' },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+    process.env.AX_TEST_ANTHROPIC_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      await runtime.registerAgent({
+        agentId: 'release-manager',
+        name: 'Release Manager',
+        capabilities: ['release'],
+        metadata: { provider: 'claude', systemPrompt: 'You answer release questions.' },
+      });
+      const result = await runtime.runAgent({
+        agentId: 'release-manager',
+        task: 'When does the release ship?',
+        scope: 'docs',
+        traceId: 'anthropic-agent-001',
+        surface: 'cli',
+      });
+
+      expect(result).toMatchObject({
+        success: true,
+        provider: 'claude',
+        model: 'claude-test',
+        content: 'The release ships on Friday.',
+        usage: { inputTokens: 100, outputTokens: 20, totalTokens: 120 },
+      });
+      expect(requests).toHaveLength(2);
+      expect(requests[0]!.headers).toMatchObject({ 'x-api-key': 'sk-test', 'anthropic-version': '2023-06-01' });
+      expect(requests[0]!.body).toMatchObject({ model: 'claude-test', system: 'You answer release questions.', stream: true });
+      expect((requests[0]!.body.tools as Array<{ name: string }>).map((tool) => tool.name)).toEqual(['read_file', 'list_files']);
+      expect((requests[1]!.body.messages as unknown[]).at(-1)).toEqual({
+        role: 'user',
+        content: [{ type: 'tool_result', tool_use_id: 'toolu_1', content: 'Ship on Friday.\n' }],
+      });
+
+      const trace = await runtime.getTrace('anthropic-agent-001');
+      expect(trace?.output).toMatchObject({ toolCalls: [{ name: 'read_file', input: { path: 'docs/release.md' } }] });
+
+      delete process.env.AX_TEST_ANTHROPIC_KEY;
+      const missingKey = await runtime.callProvider({ prompt: 'Hello', provider: 'claude', surface: 'cli' });
+      expect(missingKey).toMatchObject({ success: false, error: { code: 'PROVIDER_API_KEY_MISSING' } });
+    } finally {
+      delete process.env.AX_TEST_ANTHROPIC_KEY;
+      await new Promise((resolve) => server.close(resolve));
+    }
+  });
+
+  it('runs an agent through an OpenAI-compatible chat-completions endpoint with function calling', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    await writeFile(join(tempDir, 'CHANGELOG.md'), '## 1.2.0\n- Faster search\n', 'utf8');
+
+    const requests: Array<{ url?: string; headers: IncomingMessage['headers']; body: Record<string, unknown> }> = [];
+    const chunks = (payloads: Array<Record<string, unknown>>) => `${payloads.map((payload) => `data: ${JSON.stringify(payload)}\n\n`).join('')}data: [DONE]\n\n`;
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk) => { raw += chunk; });
+      request.on('end', () => {
+        const body = JSON.parse(raw) as { tools?: unknown[]; messages: Array<{ role: string }> };
+        requests.push({ url: request.url, headers: request.headers, body });
+        response.writeHead(200, { 'content-type': 'text/event-stream' });
+        const callsTool = body.tools !== undefined && !body.messages.some((message) => message.role === 'tool');
+        response.end(callsTool
+          ? chunks([
+            { choices: [{ index: 0, delta: { role: 'assistant', tool_calls: [{ index: 0, id: 'call_1', type: 'function', function: { name: 'read_file', arguments: '' } }] } }] },
+            { choices: [{ index: 0, delta: { tool_calls: [{ index: 0, function: { arguments: '{"path":' } }] } }] },
+            { choices: [{ index: 0, delta: { tool_calls: [{ index: 0, function: { arguments: '"CHANGELOG.md"}' } }] }, finish_reason: 'tool_calls' }] },
+            { choices: [], usage: { prompt_tokens: 30, completion_tokens: 10 } },
+          ])
+          : chunks([
+            { choices: [{ index: 0, delta: { role: 'assistant', content: 'Version 1.2.0 ' } }] },
+            { choices: [{ index: 0, delta: { content: 'adds faster search.' }, finish_reason: 'stop' }] },
+            { choices: [], usage: { prompt_tokens: 50, completion_tokens: 6 } },
+          ]));
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}`;
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          groq: { api: 'openai', baseUrl: `${baseUrl}/openai/v1`, apiKeyEnv: 'AX_TEST_OPENAI_KEY', model: 'llama-test' },
+          azure: { api: 'openai', baseUrl: `${baseUrl}/openai/deployments/gpt-4o`, apiKeyEnv: 'AX_TEST_OPENAI_KEY', apiVersion: '2024-10-21' },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+    process.env.AX_TEST_OPENAI_KEY = 'sk-test';
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      await runtime.registerAgent({
+        agentId: 'release-manager',
+        name: 'Release Manager',
+        capabilities: ['release'],
+        metadata: { provider: 'groq', systemPrompt: 'You answer release questions.' },
+      });
+      const result = await runtime.runAgent({
+        agentId: 'release-manager',
+        task: 'What is in the latest release?',
+        traceId: 'openai-agent-001',
+        surface: 'cli',
+      });
+
+      expect(result).toMatchObject({
+        success: true,
+        provider: 'groq',
+        model: 'llama-test',
+        content: 'Version 1.2.0 adds faster search.',
+        usage: { inputTokens: 80, outputTokens: 16, totalTokens: 96 },
+      });
+      expect(requests).toHaveLength(2);
+      expect(requests[0]).toMatchObject({ url: '/openai/v1/chat/completions', headers: { authorization: 'Bearer sk-test' } });
+      expect(requests[0]!.body).toMatchObject({
+        model: 'llama-test',
+        stream: true,
+        messages: [{ role: 'system', content: 'You answer release questions.' }, { role: 'user' }],
+      });
+      expect((requests[0]!.body.tools as Array<{ function: { name: string } }>).map((tool) => tool.function.name)).toEqual(['read_file', 'list_files']);
+      expect((requests[1]!.body.messages as unknown[]).slice(-2)).toEqual([
+        { role: 'assistant', content: null, tool_calls: [{ id: 'call_1', type: 'function', function: { name: 'read_file', arguments: '{"path":"CHANGELOG.md"}' } }] },
+        { role: 'tool', tool_call_id: 'call_1', content: '## 1.2.0\n- Faster search\n' },
+      ]);
+
+      const azure = await runtime.callProvider({ prompt: 'Hello', provider: 'azure', surface: 'cli' });
+      expect(azure).toMatchObject({ success: true, content: 'Version 1.2.0 adds faster search.' });
+      expect(requests[2]).toMatchObject({
+        url: '/openai/deployments/gpt-4o/chat/completions?api-version=2024-10-21',
+        headers: { 'api-key': 'sk-test' },
+      });
+      expect(requests[2]!.headers.authorization).toBeUndefined();
+    } finally {
+      delete process.env.AX_TEST_OPENAI_KEY;
+      await new Promise((resolve) => server.close(resolve));
+    }
+  });
+
+  it('runs an agent on a local Ollama model, pulling it first and sizing the context window', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    await writeFile(join(tempDir, 'VERSION'), '3.1.0\n', 'utf8');
+
+    const installed: string[] = [];
+    const chats: Array<Record<string, unknown>> = [];
+    const pulls: string[] = [];
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk) => { raw += chunk; });
+      request.on('end', () => {
+        const body = raw.length > 0 ? JSON.parse(raw) as Record<string, unknown> : {};
+        const lines = (entries: Array<Record<string, unknown>>) => {
+          response.writeHead(200, { 'content-type': 'application/x-ndjson' });
+          response.end(entries.map((entry) => `${JSON.stringify(entry)}\n`).join(''));
+        };
+        if (request.url === '/api/tags') {
+          response.writeHead(200, { 'content-type': 'application/json' });
+          response.end(JSON.stringify({ models: installed.map((name) => ({ name, size: 2_019_393_189, details: { family: 'llama', parameter_size: '3.2B', quantization_level: 'Q4_K_M' } })) }));
+        } else if (request.url === '/api/pull') {
+          pulls.push(String(body.model));
+          installed.push(`${String(body.model)}:latest`);
+          lines([{ status: 'pulling manifest' }, { status: 'verifying sha256 digest' }, { status: 'success' }]);
+        } else if (request.url === '/api/show') {
+          response.writeHead(200, { 'content-type': 'application/json' });
+          response.end(JSON.stringify({ model_info: { 'general.architecture': 'llama', 'llama.context_length': 131072 } }));
+        } else {
+          chats.push(body);
+          lines(chats.length === 1
+            ? [
+              { message: { role: 'assistant', content: '', tool_calls: [{ function: { name: 'read_file', arguments: { path: 'VERSION' } } }] }, done: false },
+              { message: { role: 'assistant', content: '' }, done: true, done_reason: 'stop', prompt_eval_count: 120, eval_count: 15 },
+            ]
+            : [
+              { message: { role: 'assistant', content: 'The version ' }, done: false },
+              { message: { role: 'assistant', content: 'is 3.1.0.' }, done: false },
+              { message: { role: 'assistant', content: '' }, done: true, done_reason: 'stop', prompt_eval_count: 150, eval_count: 7 },
+            ]);
+        }
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        executors: {
+          ollama: { api: 'ollama', baseUrl: `127.0.0.1:${(server.address() as AddressInfo).port}`, model: 'llama3.2' },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      await runtime.registerAgent({
+        agentId: 'release-manager',
+        name: 'Release Manager',
+        capabilities: ['release'],
+        metadata: { provider: 'ollama', systemPrompt: 'You answer release questions.' },
+      });
+      const result = await runtime.runAgent({ agentId: 'release-manager', task: 'Which version is this?', surface: 'cli' });
+
+      expect(result).toMatchObject({
+        success: true,
+        provider: 'ollama',
+        model: 'llama3.2',
+        content: 'The version is 3.1.0.',
+        usage: { inputTokens: 270, outputTokens: 22, totalTokens: 292 },
+      });
+      expect(result.warnings).toContain('Pulled model "llama3.2" into Ollama.');
+      expect(pulls).toEqual(['llama3.2']);
+      expect(chats[0]).toMatchObject({
+        model: 'llama3.2',
+        stream: true,
+        options: { num_ctx: 8192 },
+        messages: [{ role: 'system', content: 'You answer release questions.' }, { role: 'user' }],
+      });
+      expect((chats[1]!.messages as unknown[]).at(-1)).toEqual({ role: 'tool', tool_name: 'read_file', content: '3.1.0\n' });
+
+      await runtime.runAgent({ agentId: 'release-manager', task: 'Which version is this?', surface: 'cli' });
+      expect(pulls).toHaveLength(1);
+
+      const listed = await runtime.listProviderModels({ provider: 'ollama' });
+      expect(listed.models).toEqual([{
+        name: 'llama3.2:latest',
+        sizeBytes: 2_019_393_189,
+        family: 'llama',
+        parameterSize: '3.2B',
+        quantization: 'Q4_K_M',
+        contextLength: 131072,
+      }]);
+    } finally {
+      await new Promise((resolve) => server.close(resolve));
+    }
+  });
+
+  it('routes agent traffic through Bedrock and Vertex AI with cloud credentials and provider fallback', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    await writeFile(join(tempDir, 'VERSION'), '4.0.0\n', 'utf8');
+
+    const { privateKey, publicKey } = generateKeyPairSync('rsa', { modulusLength: 2048 });
+    const requests: Array<{ url?: string; headers: IncomingMessage['headers']; body: Record<string, unknown> }> = [];
+    const assertions: string[] = [];
+    const frame = (eventType: string, payload: Record<string, unknown>) => {
+      const headers = Buffer.concat([[':event-type', eventType], [':content-type', 'application/json'], [':message-type', 'event']].map(([name, value]) => {
+        const header = Buffer.alloc(4 + name!.length + value!.length);
+        header.writeUInt8(name!.length, 0);
+        header.write(name!, 1);
+        header.writeUInt8(7, 1 + name!.length);
+        header.writeUInt16BE(value!.length, 2 + name!.length);
+        header.write(value!, 4 + name!.length);
+        return header;
+      }));
+      const body = Buffer.from(JSON.stringify(payload));
+      const message = Buffer.alloc(16 + headers.length + body.length);
+      message.writeUInt32BE(message.length, 0);
+      message.writeUInt32BE(headers.length, 4);
+      headers.copy(message, 12);
+      body.copy(message, 12 + headers.length);
+      return message;
+    };
+    const server = createServer((request, response) => {
+      let raw = '';
+      request.on('data', (chunk) => { raw += chunk; });
+      request.on('end', () => {
+        if (request.url === '/token') {
+          const [header, claims, signature] = new URLSearchParams(raw).get('assertion')!.split('.');
+          assertions.push(String(createVerify('RSA-SHA256').update(`${header}.${claims}`).verify(publicKey, signature!, 'base64url')));
+          response.writeHead(200, { 'content-type': 'application/json' });
+          response.end(JSON.stringify({ access_token: 'ya29.test', expires_in: 3599, token_type: 'Bearer' }));
+          return;
+        }
+        const body = JSON.parse(raw) as Record<string, unknown>;
+        requests.push({ url: request.url, headers: request.headers, body });
+        if (request.url!.startsWith('/bedrock/')) {
+          response.writeHead(200, { 'content-type': 'application/vnd.amazon.eventstream' });
+          const answered = (body.messages as unknown[]).length > 1;
+          response.end(Buffer.concat(answered
+            ? [
+              frame('messageStart', { role: 'assistant' }),
+              frame('contentBlockDelta', { contentBlockIndex: 0, delta: { text: 'Release ' } }),
+              frame('contentBlockDelta', { contentBlockIndex: 0, delta: { text: '4.0.0.' } }),
+              frame('contentBlockStop', { contentBlockIndex: 0 }),
+              frame('messageStop', { stopReason: 'end_turn' }),
+              frame('metadata', { usage: { inputTokens: 90, outputTokens: 4, totalTokens: 94 } }),
+            ]
+            : [
+              frame('messageStart', { role: 'assistant' }),
+              frame('contentBlockStart', { contentBlockIndex: 0, start: { toolUse: { toolUseId: 'tooluse_1', name: 'read_file' } } }),
+              frame('contentBlockDelta', { contentBlockIndex: 0, delta: { toolUse: { input: '{"path":"VER' } } }),
+              frame('contentBlockDelta', { contentBlockIndex: 0, delta: { toolUse: { input: 'SION"}' } } }),
+              frame('contentBlockStop', { contentBlockIndex: 0 }),
+              frame('messageStop', { stopReason: 'tool_use' }),
+              frame('metadata', { usage: { inputTokens: 60, outputTokens: 12, totalTokens: 72 } }),
+            ]));
+        } else if (request.url!.endsWith(':streamRawPredict')) {
+          response.writeHead(200, { 'content-type': 'text/event-stream' });
+          response.end([
+            { type: 'message_start', message: { usage: { input_tokens: 8, output_tokens: 1 } } },
+            { type: 'content_block_start', index: 0, content_block: { type: 'text', text: '' } },
+            { type: 'content_block_delta', index: 0, delta: { type: 'text_delta', text: 'Hello from Vertex.' } },
+            { type: 'content_block_stop', index: 0 },
+            { type: 'message_delta', delta: { stop_reason: 'end_turn' }, usage: { output_tokens: 5 } },
+          ].map((event) => `event: ${event.type}\ndata: ${JSON.stringify(event)}\n\n`).join(''));
+        } else {
+          response.writeHead(200, { 'content-type': 'text/event-stream' });
+          response.end([
+            { choices: [{ index: 0, delta: { role: 'assistant', content: 'Gemini here.' }, finish_reason: 'stop' }] },
+            { choices: [], usage: { prompt_tokens: 5, completion_tokens: 3 } },
+          ].map((chunk) => `data: ${JSON.stringify(chunk)}\n\n`).join('') + 'data: [DONE]\n\n');
+        }
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+    const baseUrl = `http://127.0.0.1:${(server.address() as AddressInfo).port}`;
+    await writeFile(join(tempDir, 'aws-credentials'), '[default]\naws_access_key_id = AKIDDEFAULT\naws_secret_access_key = default\n\n[ax-prod]\naws_access_key_id = AKIDPROD\naws_secret_access_key = prod-secret\naws_session_token = prod-session\n', 'utf8');
+    await writeFile(join(tempDir, 'service-account.json'), JSON.stringify({
+      type: 'service_account',
+      project_id: 'ax-prod',
+      private_key_id: 'key-1',
+      private_key: privateKey.export({ type: 'pkcs8', format: 'pem' }),
+      client_email: 'automatosx@ax-prod.iam.gserviceaccount.com',
+      token_uri: `${baseUrl}/token`,
+    }), 'utf8');
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), `${JSON.stringify({
+      providers: {
+        fallback: ['gemini'],
+        quotas: { claude: { daily: { requests: 1 } } },
+        executors: {
+          claude: { api: 'bedrock', baseUrl: `${baseUrl}/bedrock`, region: 'eu-west-1', profile: 'ax-prod' },
+          gemini: { api: 'vertex', baseUrl: `${baseUrl}/vertex`, region: 'europe-west4' },
+          'vertex-claude': { api: 'vertex', baseUrl: `${baseUrl}/vertex`, region: 'europe-west4', model: 'claude-sonnet-4-5@20250929' },
+        },
+      },
+    }, null, 2)}\n`, 'utf8');
+    const savedEnv = { AWS_SHARED_CREDENTIALS_FILE: process.env.AWS_SHARED_CREDENTIALS_FILE, GOOGLE_APPLICATION_CREDENTIALS: process.env.GOOGLE_APPLICATION_CREDENTIALS };
+    process.env.AWS_SHARED_CREDENTIALS_FILE = join(tempDir, 'aws-credentials');
+    process.env.GOOGLE_APPLICATION_CREDENTIALS = join(tempDir, 'service-account.json');
+
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      await runtime.registerAgent({
+        agentId: 'release-manager',
+        name: 'Release Manager',
+        capabilities: ['release'],
+        metadata: { provider: 'claude', systemPrompt: 'You answer release questions.' },
+      });
+      const result = await runtime.runAgent({ agentId: 'release-manager', task: 'Which version is this?', surface: 'cli' });
+
+      expect(result).toMatchObject({
+        success: true,
+        provider: 'claude',
+        model: 'anthropic.claude-3-5-sonnet-20240620-v1:0',
+        content: 'Release 4.0.0.',
+        usage: { inputTokens: 150, outputTokens: 16, totalTokens: 166 },
+      });
+      expect(requests[0]).toMatchObject({
+        url: '/bedrock/model/anthropic.claude-3-5-sonnet-20240620-v1%3A0/converse-stream',
+        headers: { 'x-amz-security-token': 'prod-session' },
+        body: { system: [{ text: 'You answer release questions.' }], messages: [{ role: 'user' }] },
+      });
+      expect(requests[0]!.headers.authorization).toMatch(
+        /^AWS4-HMAC-SHA256 Credential=AKIDPROD\/\d{8}\/eu-west-1\/bedrock\/aws4_request, SignedHeaders=content-type;host;x-amz-date;x-amz-security-token, Signature=[0-9a-f]{64}$/,
+      );
+      expect((requests[1]!.body.messages as unknown[]).slice(-2)).toEqual([
+        { role: 'assistant', content: [{ toolUse: { toolUseId: 'tooluse_1', name: 'read_file', input: { path: 'VERSION' } } }] },
+        { role: 'user', content: [{ toolResult: { toolUseId: 'tooluse_1', content: [{ text: '4.0.0\n' }] } }] },
+      ]);
+
+      const routed = await runtime.callProvider({ prompt: 'Say hello.', surface: 'cli' });
+      expect(routed).toMatchObject({ success: true, provider: 'gemini', content: 'Gemini here.' });
+      expect(requests[2]).toMatchObject({
+        url: '/vertex/v1/projects/ax-prod/locations/europe-west4/endpoints/openapi/chat/completions',
+        headers: { authorization: 'Bearer ya29.test' },
+        body: { model: 'google/gemini-2.5-flash' },
+      });
+
+      const direct = await runtime.callProvider({ prompt: 'Say hello.', provider: 'vertex-claude', surface: 'cli' });
+      expect(direct).toMatchObject({ success: true, content: 'Hello from Vertex.', usage: { inputTokens: 8, outputTokens: 5 } });
+      expect(requests[3]).toMatchObject({
+        url: '/vertex/v1/projects/ax-prod/locations/europe-west4/publishers/anthropic/models/claude-sonnet-4-5@20250929:streamRawPredict',
+        headers: { authorization: 'Bearer ya29.test' },
+        body: { anthropic_version: 'vertex-2023-10-16', stream: true },
+      });
+      expect(requests[3]!.body.model).toBeUndefined();
+      // One token served both Vertex calls.
+      expect(assertions).toEqual(['true']);
+    } finally {
+      for (const [name, value] of Object.entries(savedEnv)) {
+        if (value === undefined) {
+          delete process.env[name];
+        } else {
+          process.env[name] = value;
+        }
+      }
+      await new Promise((resolve) => server.close(resolve));
+    }
+  });
+
+  it('signs AWS requests with Signature Version 4', () => {
+    // The GET example from the AWS Signature Version 4 documentation.
+    const headers = signAwsRequest({
+      method: 'GET',
+      url: 'https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08',
+      headers: { 'Content-Type': 'application/x-www-form-urlencoded; charset=utf-8' },
+      body: '',
+    }, {
+      accessKeyId: 'AKIDEXAMPLE',
+      secretAccessKey: 'wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY',
+    }, { region: 'us-east-1', service: 'iam', now: new Date('2015-08-30T12:36:00Z') });
+
+    expect(headers).toEqual({
+      'content-type': 'application/x-www-form-urlencoded; charset=utf-8',
+      'x-amz-date': '20150830T123600Z',
+      authorization: 'AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, SignedHeaders=content-type;host;x-amz-date, Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7',
+    });
+  });
+
   it('shares memory and policy stores through one runtime service', async () => {
     const tempDir = createTempDir();
     tempDirs.push(tempDir);
@@ -428,6 +2929,157 @@ describe('shared runtime service', () => {
     expect(await runtime.getMemory('latest', 'release')).toBeUndefined();
   });
 
+  it('isolates memory by project and agent scope through one runtime service', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const otherProject = join(tempDir, 'other');
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.storeMemory({ namespace: 'notes', key: 'style', value: 'tabs', scope: { project: tempDir } });
+    await runtime.storeMemory({ namespace: 'notes', key: 'style', value: 'spaces', scope: { project: otherProject } });
+    await runtime.storeMemory({ namespace: 'notes', key: 'focus', value: 'tests', scope: { project: tempDir, agent: 'reviewer' } });
+    await runtime.storeMemory({ namespace: 'notes', key: 'style', value: 'global' });
+
+    expect((await runtime.getMemory('style', 'notes', { project: tempDir }))?.value).toBe('tabs');
+    expect((await runtime.getMemory('style', 'notes', { project: otherProject }))?.value).toBe('spaces');
+    expect((await runtime.getMemory('style', 'notes'))?.value).toBe('global');
+    expect((await runtime.listMemory('notes', { project: tempDir })).map((entry) => entry.value)).toEqual(['tabs']);
+    expect((await runtime.listMemory(undefined, { project: tempDir })).map((entry) => entry.key).sort()).toEqual(['focus', 'style']);
+    expect((await runtime.listMemory(undefined, { agent: 'reviewer' }))).toEqual([]);
+    expect((await runtime.searchMemory('tests', undefined, { project: tempDir, agent: 'reviewer' })).map((entry) => entry.key)).toEqual(['focus']);
+    expect(await runtime.listMemory()).toHaveLength(4);
+
+    const namespaces = (await runtime.listMemoryNamespaces()).map((stats) => stats.namespace);
+    expect(namespaces).toHaveLength(4);
+    expect(namespaces).toContain('notes');
+    const agentNotes = namespaces.find((namespace) => namespace.includes('@agent:reviewer'))!;
+    await runtime.mergeMemoryNamespaces({ sources: [agentNotes], target: 'notes' });
+    expect((await runtime.listMemory('notes')).map((entry) => entry.key).sort()).toEqual(['focus', 'style']);
+    expect(await runtime.deleteMemory('style', 'notes', { project: otherProject })).toBe(true);
+  });
+
+  it('records where memory came from and finds entries whose source changed', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    mkdirSync(join(tempDir, 'src'));
+    await writeFile(join(tempDir, 'src', 'billing.ts'), 'import { ledger } from "./ledger";\nexport const RETRIES = 3;\nexport const TIMEOUT_MS = 500;\n');
+    await writeFile(join(tempDir, 'src', 'ledger.ts'), 'export const ledger = {};\n');
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    const stored = await runtime.storeMemory({
+      key: 'retries',
+      value: 'billing retries three times',
+      source: { file: 'src/billing.ts', startLine: 2, sessionId: 'session-1', provider: 'claude' },
+      scope: { agent: 'reviewer' },
+    });
+    expect(stored.source).toMatchObject({ file: 'src/billing.ts', startLine: 2, sessionId: 'session-1', provider: 'claude', agent: 'reviewer' });
+    expect(stored.source?.contentHash).toMatch(/^[0-9a-f]{16}$/);
+    await runtime.storeMemory({ key: 'timeout', value: 'billing times out after 500ms', source: { file: 'src/billing.ts', startLine: 3, endLine: 3 } });
+    await runtime.storeMemory({ key: 'ledger', value: 'ledger is a plain object', source: { file: 'src/ledger.ts' } });
+    await runtime.storeMemory({ key: 'chat', value: 'user prefers tabs', source: { sessionId: 'session-1' } });
+    expect((await runtime.getMemory('retries', undefined, { agent: 'reviewer' }))?.source).toEqual(stored.source);
+    expect(await runtime.checkMemorySources()).toEqual({ checked: 3, stale: [], invalidated: 0 });
+
+    await writeFile(join(tempDir, 'src', 'billing.ts'), 'import { ledger } from "./ledger";\nexport const RETRIES = 3;\nexport const TIMEOUT_MS = 900;\n');
+    await rm(join(tempDir, 'src', 'ledger.ts'));
+    const check = await runtime.checkMemorySources();
+    expect(check.stale.map(({ key, reason }) => ({ key, reason })).sort((left, right) => left.key.localeCompare(right.key))).toEqual([
+      { key: 'ledger', reason: 'missing' },
+      { key: 'timeout', reason: 'changed' },
+    ]);
+    expect(await runtime.checkMemorySources({ scope: { agent: 'reviewer' } })).toMatchObject({ checked: 1, stale: [] });
+
+    expect((await runtime.checkMemorySources({ invalidate: true })).invalidated).toBe(2);
+    expect((await runtime.listMemory()).map((entry) => entry.key).sort()).toEqual(['chat', 'retries']);
+  });
+
+  it('scopes stored facts by project and agent through one runtime service', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.storeFact({ subject: 'billing-api', predicate: 'calls', object: 'ledger-api', sourceSpan: { file: 'src/billing.ts', startLine: 12 }, scope: { project: tempDir } });
+    await runtime.storeFact({ subject: 'billing-api', predicate: 'owner', object: 'payments', scope: { project: tempDir, agent: 'reviewer' } });
+    await runtime.storeFact({ subject: 'billing-api', predicate: 'calls', object: 'audit-api', scope: { project: join(tempDir, 'other') } });
+
+    expect((await runtime.queryFacts({ subject: 'billing-api', scope: { project: tempDir } })).map((fact) => fact.object).sort()).toEqual(['ledger-api', 'payments']);
+    expect((await runtime.queryFacts({ predicate: 'calls', namespace: 'default', scope: { project: tempDir } })).map((fact) => fact.object)).toEqual(['ledger-api']);
+    expect((await runtime.queryFacts({ scope: { project: tempDir, agent: 'reviewer' } })).map((fact) => fact.predicate)).toEqual(['owner']);
+    expect(await runtime.queryFacts({ subject: 'billing-api', scope: { project: tempDir }, limit: 1 })).toHaveLength(1);
+    expect(await runtime.queryFacts()).toHaveLength(3);
+  });
+
+  it('refuses writes, mutating tools, and git changes in read-only mode', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    await initializeGitRepo(tempDir);
+    const workflowDir = join(tempDir, 'workflows');
+    mkdirSync(workflowDir, { recursive: true });
+    await writeFile(join(workflowDir, 'edit.json'), JSON.stringify({
+      workflowId: 'edit',
+      name: 'Edit Workflow',
+      version: '1.0.0',
+      steps: [
+        { stepId: 'inspect', type: 'tool', config: { toolName: 'file.read' } },
+        { stepId: 'write', type: 'tool', config: { toolName: 'file.write' } },
+      ],
+    }), 'utf8');
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir, readOnly: true });
+    expect(runtime.isReadOnly()).toBe(true);
+    await expect(runtime.setConfig('providers.default', 'claude')).rejects.toMatchObject({ code: 'READ_ONLY_MODE' });
+    await expect(runtime.installHook()).rejects.toMatchObject({ code: 'READ_ONLY_MODE' });
+    await expect(runtime.suggestAgents()).rejects.toMatchObject({ code: 'READ_ONLY_MODE' });
+    await expect(runtime.commitPrepare({ paths: ['tracked.txt'] })).rejects.toMatchObject({ code: 'READ_ONLY_MODE' });
+    expect(await runtime.getConfig('providers.default')).toBeUndefined();
+
+    const result = await runtime.runWorkflow({ workflowId: 'edit', workflowDir, traceId: 'read-only-trace-001' });
+    expect(result.success).toBe(false);
+    expect(result.stepResults).toMatchObject([
+      { stepId: 'inspect', success: true },
+      { stepId: 'write', success: false },
+    ]);
+    expect(result.error?.message).toContain('read-only mode');
+  });
+
+  it('runs the pre-commit quick review as an agent and cancels it at its time budget', async () => {
+    const tempDir = createTempDir();
+    await initializeGitRepo(tempDir);
+    await writeFile(join(tempDir, 'app.js'), 'export const total = (items) => items.reduce((sum, item) => sum + item.price);\n', 'utf8');
+    await execFileAsync('git', ['add', 'app.js'], { cwd: tempDir });
+    const config = resolvePreCommitConfig({ hooks: { preCommit: { syntax: false, review: { enabled: true, timeBudgetMs: 200 } } } });
+    const requests: Parameters<AgentRunner>[0][] = [];
+
+    const reviewed = await runPreCommitPipeline({ basePath: tempDir, config }, async (request) => {
+      requests.push(request);
+      return {
+        traceId: 'review-trace',
+        success: true,
+        content: 'Found one:\n```json\n[{"file": "app.js", "line": 1, "severity": "high", "message": "reduce has no initial value."}, {"file": "other.js", "severity": "note", "message": "Name the callback."}]\n```',
+      };
+    });
+    expect(requests).toMatchObject([{ agentId: 'quality', basePath: tempDir, timeoutMs: 200 }]);
+    expect(requests[0]?.task).toContain('+export const total');
+    expect(reviewed.checks.review).toBe('failed');
+    expect(reviewed.blocked).toBe(true);
+    expect(reviewed.issues).toEqual([
+      { check: 'review', severity: 'error', file: 'app.js', line: 1, message: 'reduce has no initial value.' },
+      { check: 'review', severity: 'warning', file: '.', message: 'Name the callback.' },
+    ]);
+
+    let cancelled = false;
+    const slow = await runPreCommitPipeline({ basePath: tempDir, config }, (request) => new Promise((resolveRun) => {
+      request.signal?.addEventListener('abort', () => {
+        cancelled = true;
+        resolveRun({ traceId: 'slow-trace', success: false, content: '', error: { code: 'AGENT_CANCELLED', message: 'cancelled' } });
+      });
+    }));
+    expect(cancelled).toBe(true);
+    expect(slow.checks.review).toBe('timed-out');
+    expect(slow.passed).toBe(true);
+    expect(slow.issues[0]?.message).toContain('cancelled after exceeding its 200ms time budget');
+  });
+
   it('closes stale traces through the shared runtime', async () => {
     const tempDir = createTempDir();
     tempDirs.push(tempDir);
@@ -855,6 +3507,12 @@ describe('shared runtime service', () => {
       parentTraceId: 'parallel-run-001',
       rootTraceId: 'parallel-run-001',
     });
+
+    // A dependent task is given what the tasks it depends on produced.
+    const verifyTrace = await runtime.getTrace(result.results[1]!.traceId!);
+    expect((verifyTrace?.input as { input?: Record<string, unknown> }).input).toEqual({
+      dependencyOutputs: { design: result.results[0]!.result!.content },
+    });
   });
 
   it('stores and searches semantic context through the shared runtime', async () => {
@@ -1248,6 +3906,341 @@ describe('shared runtime service', () => {
     ]);
   });
 
+  it('runs workspace maintenance and skips it until the interval has passed', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+
+    const runsDir = join(tempDir, '.automatosx', 'runtime', 'runs');
+    const debugDir = join(tempDir, '.automatosx', 'debug');
+    mkdirSync(runsDir, { recursive: true });
+    mkdirSync(debugDir, { recursive: true });
+    mkdirSync(join(tempDir, '.automatosx', 'logs'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({
+      maintenance: { logMaxBytes: 16, logGenerations: 2 },
+    }), 'utf8');
+    await writeFile(join(tempDir, '.automatosx', 'logs', 'ax.log'), 'x'.repeat(64), 'utf8');
+    await writeFile(join(runsDir, 'old-run.json'), JSON.stringify({
+      pid: 999_999_999,
+      startedAt: '2020-01-01T00:00:00.000Z',
+      logPath: join(runsDir, 'old-run.log'),
+    }), 'utf8');
+    await writeFile(join(runsDir, 'old-run.log'), 'done\n', 'utf8');
+    const bundlePath = join(debugDir, 'ax-debug-old.tar.gz');
+    await writeFile(bundlePath, 'bundle', 'utf8');
+    const longAgo = new Date('2020-01-01T00:00:00.000Z');
+    await utimes(bundlePath, longAgo, longAgo);
+
+    const runtime = createSharedRuntimeService({
+      basePath: tempDir,
+      stateStore: createStateStore({ basePath: tempDir, backend: 'json' }),
+    });
+    await runtime.storeMemory({ key: 'stale', namespace: 'notes', value: 'old' });
+    await runtime.storeMemory({ key: 'fresh', namespace: 'notes', value: 'new' });
+    const statePath = join(tempDir, '.automatosx', 'runtime', 'state.json');
+    const state = JSON.parse(await readFile(statePath, 'utf8')) as { memory: Array<{ key: string; updatedAt: string }> };
+    state.memory.find((entry) => entry.key === 'stale')!.updatedAt = longAgo.toISOString();
+    await writeFile(statePath, JSON.stringify(state), 'utf8');
+
+    const preview = await runtime.runMaintenance({ tasks: ['memory', 'logs', 'caches'], dryRun: true });
+    expect(preview).toMatchObject({ success: true, dryRun: true });
+    expect(preview.tasks.map((task) => task.task)).toEqual(['memory', 'logs', 'caches']);
+    expect(preview.tasks[0]?.details).toMatchObject({ deleted: ['notes/stale'] });
+    expect(await runtime.listMemory('notes')).toHaveLength(2);
+    await expect(stat(bundlePath)).resolves.toBeDefined();
+
+    const result = await runtime.runMaintenance({ tasks: ['memory', 'logs', 'caches'] });
+    expect(result.success).toBe(true);
+    expect(result.tasks[1]?.details).toMatchObject({
+      rotated: ['.automatosx/logs/ax.log'],
+      removed: ['.automatosx/runtime/runs/old-run.json', '.automatosx/runtime/runs/old-run.log'],
+    });
+    expect(result.tasks[2]?.details).toMatchObject({ removed: ['.automatosx/debug/ax-debug-old.tar.gz'] });
+    expect((await runtime.listMemory('notes')).map((entry) => entry.key)).toEqual(['fresh']);
+    expect(await readFile(join(tempDir, '.automatosx', 'logs', 'ax.log.1'), 'utf8')).toHaveLength(64);
+    await expect(stat(bundlePath)).rejects.toThrow();
+    await expect(stat(join(runsDir, 'old-run.json'))).rejects.toThrow();
+
+    const trace = await runtime.getTrace(result.traceId!);
+    expect(trace).toMatchObject({ workflowId: 'maintain', status: 'completed' });
+    expect(trace?.stepResults.map((step) => step.stepId)).toEqual(['memory', 'logs', 'caches']);
+
+    const skipped = await runtime.runMaintenance({ ifDue: true });
+    expect(skipped).toMatchObject({ success: true, skipped: 'Maintenance is not due yet.', lastRunAt: result.lastRunAt, tasks: [] });
+
+    const workflow = await runtime.runWorkflow({ workflowId: 'maintain', basePath: tempDir });
+    expect(workflow.success).toBe(true);
+    expect(workflow.stepResults.map((step) => step.stepId)).toEqual(['index', 'memory', 'logs', 'providers', 'caches']);
+  });
+
+  it('diagnoses provider CLIs, their logins, the config shape and grammars, with a fix for each problem', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const binDir = join(tempDir, 'bin');
+    const homeDir = join(tempDir, 'home');
+    mkdirSync(binDir, { recursive: true });
+    mkdirSync(join(homeDir, '.codex'), { recursive: true });
+    mkdirSync(join(tempDir, '.automatosx', 'runtime'), { recursive: true });
+    await writeFile(join(binDir, 'claude'), '#!/bin/sh\n', { mode: 0o755 });
+    await writeFile(join(binDir, 'codex'), '#!/bin/sh\n', { mode: 0o755 });
+    await writeFile(join(binDir, 'gemini.cmd'), '@node "%~dp0\\gemini.js" %*\r\n', 'utf8');
+    await writeFile(join(homeDir, '.codex', 'auth.json'), '{}', 'utf8');
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({
+      providers: { default: 'claude', executors: { local: { protocol: 'raw-stdin' } } },
+      codeIntel: { grammars: [{ language: 'toml', extensions: ['.toml'], grammar: 'missing/tree-sitter-toml.wasm', query: 'missing/tags.scm' }] },
+    }), 'utf8');
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    const report = await runtime.diagnoseEnvironment({ env: { PATH: binDir, ANTHROPIC_API_KEY: 'test-key' }, platform: 'linux', homeDir });
+    const checks = new Map(report.checks.map((check) => [check.id, check]));
+    expect(checks.get('config-schema')).toMatchObject({ status: 'fail', message: expect.stringContaining('providers.executors.local needs a "command" or an "api"') });
+    expect(checks.get('provider-cli:claude')).toMatchObject({ status: 'ok', message: expect.stringContaining(join(binDir, 'claude')) });
+    expect(checks.get('provider-auth:claude')).toMatchObject({ status: 'ok', message: expect.stringContaining('ANTHROPIC_API_KEY') });
+    expect(checks.get('provider-auth:codex')).toMatchObject({ status: 'ok', message: expect.stringContaining('~/.codex/auth.json') });
+    // A .cmd shim is a program only on Windows.
+    expect(checks.get('provider-cli:gemini')).toMatchObject({ status: 'warn', fix: expect.stringContaining('npm install -g @google/gemini-cli') });
+    expect(checks.get('provider-auth:gemini')).toBeUndefined();
+    expect(checks.get('provider-cli:local')).toMatchObject({ status: 'warn', fix: expect.stringContaining('providers.executors.local') });
+    expect(checks.get('state-dirs')).toMatchObject({ status: 'ok' });
+    expect(checks.get('grammars')).toMatchObject({ status: 'warn', message: expect.stringContaining('Grammar "toml" was not loaded') });
+    expect(report.checks.filter((check) => check.status !== 'ok').every((check) => check.fix !== undefined)).toBe(true);
+
+    const windows = await runtime.diagnoseEnvironment({ env: { Path: binDir, PATHEXT: '.EXE;.CMD' }, platform: 'win32', homeDir });
+    expect(windows.checks.find((check) => check.id === 'provider-cli:gemini')).toMatchObject({
+      status: 'warn',
+      message: expect.stringContaining(join(binDir, 'gemini.cmd')),
+      fix: expect.stringContaining('"node"'),
+    });
+    // Without an extension the file is not a program on Windows.
+    expect(windows.checks.find((check) => check.id === 'provider-cli:claude')).toMatchObject({ status: 'warn' });
+    expect(await findExecutable('gemini', { Path: binDir }, 'win32')).toBe(join(binDir, 'gemini.cmd'));
+    expect(await findExecutable('gemini', { PATH: binDir }, 'linux')).toBeUndefined();
+  });
+
+  it('layers system, user and project config under a profile, environment variables and --config flags', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const systemConfig = join(tempDir, 'etc', 'config.json');
+    const userConfig = join(tempDir, 'home', '.automatosx', 'config.yaml');
+    mkdirSync(join(tempDir, 'etc'), { recursive: true });
+    mkdirSync(join(tempDir, 'home', '.automatosx'), { recursive: true });
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(systemConfig, JSON.stringify({
+      providers: { default: 'claude', executors: { local: { command: 'local-llm' } } },
+      replay: { record: false },
+    }), 'utf8');
+    await writeFile(userConfig, 'providers:\n  default: gemini\nprofiles:\n  ci:\n    providers:\n      default: ollama\n', 'utf8');
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({ providers: { default: 'codex' }, maintenance: { intervalHours: 12 } }), 'utf8');
+    const env = { HOME: join(tempDir, 'home'), AUTOMATOSX_SYSTEM_CONFIG: systemConfig };
+
+    const files = await loadLayeredConfig(tempDir, env);
+    expect(files.layers.map((layer) => layer.layer)).toEqual(['system', 'user', 'project']);
+    expect(files.config).toMatchObject({ providers: { default: 'codex', executors: { local: { command: 'local-llm' } } }, replay: { record: false } });
+    expect(files.profiles).toEqual(['ci']);
+
+    const layered = await loadLayeredConfig(tempDir, {
+      ...env,
+      AUTOMATOSX_PROFILE: 'ci',
+      AUTOMATOSX_CONFIG__MAINTENANCE__INTERVALHOURS: '6',
+      AUTOMATOSX_CONFIG_FLAGS: JSON.stringify({ 'providers.executors.local.command': 'other-llm' }),
+    });
+    expect(layered.config).toMatchObject({ providers: { default: 'ollama', executors: { local: { command: 'other-llm' } } }, maintenance: { intervalHours: 6 } });
+    expect(explainConfigKey(layered, 'providers.default')).toMatchObject({
+      value: 'ollama',
+      layer: 'profile',
+      source: 'profiles.ci',
+      layers: [
+        { layer: 'system', source: systemConfig, value: 'claude' },
+        { layer: 'user', source: userConfig, value: 'gemini' },
+        { layer: 'project', value: 'codex' },
+        { layer: 'profile', value: 'ollama' },
+      ],
+    });
+    // Variable names match the existing keys whatever their case, and their values are read as JSON.
+    expect(explainConfigKey(layered, 'maintenance.intervalHours')).toMatchObject({ value: 6, layer: 'env', source: 'AUTOMATOSX_CONFIG__MAINTENANCE__INTERVALHOURS' });
+    expect(explainConfigKey(layered, 'providers.executors.local.command')).toMatchObject({ value: 'other-llm', layer: 'flags', source: '--config providers.executors.local.command' });
+    expect(explainConfigKey(layered, 'missing.key')).toEqual({ key: 'missing.key', value: undefined, layers: [] });
+
+    process.env.AUTOMATOSX_SYSTEM_CONFIG = systemConfig;
+    process.env.AUTOMATOSX_USER_CONFIG = userConfig;
+    try {
+      const runtime = createSharedRuntimeService({ basePath: tempDir });
+      expect(await runtime.getConfig('replay.record')).toBe(false);
+      expect(await runtime.explainConfig('providers.default')).toMatchObject({ value: 'codex', layer: 'project' });
+      // Only the project's own file is written, without the values of the layers under it.
+      await runtime.setConfig('providers.default', 'claude');
+      expect(JSON.parse(await readFile(join(tempDir, '.automatosx', 'config.json'), 'utf8'))).toEqual({
+        providers: { default: 'claude' },
+        maintenance: { intervalHours: 12 },
+      });
+
+      await writeFile(userConfig, 'providers: [\n', 'utf8');
+      const report = await runtime.diagnoseEnvironment({ env: { PATH: '' }, platform: 'linux', homeDir: join(tempDir, 'home') });
+      const schemaChecks = report.checks.filter((check) => check.id === 'config-schema');
+      expect(schemaChecks).toEqual(expect.arrayContaining([
+        expect.objectContaining({ status: 'ok', message: 'Workspace config is valid (.automatosx/config.json).' }),
+        expect.objectContaining({ status: 'fail', message: expect.stringContaining(`User config is invalid (${userConfig}): not valid YAML`) }),
+      ]));
+    } finally {
+      delete process.env.AUTOMATOSX_SYSTEM_CONFIG;
+      delete process.env.AUTOMATOSX_USER_CONFIG;
+    }
+  });
+
+  it('validates config against the schema with key suggestions, and migrates older versions when read', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    const configPath = join(tempDir, '.automatosx', 'config.json');
+    await writeFile(configPath, JSON.stringify({
+      schemaVersion: 1,
+      defaultProvider: 'codex',
+      provders: { fallback: ['claude'] },
+      maintenance: { intervalHours: 'daily' },
+      providers: { executors: { local: { command: 'local-llm', protcol: 'raw-stdin' } } },
+    }), 'utf8');
+    const env = { HOME: join(tempDir, 'home'), AUTOMATOSX_SYSTEM_CONFIG: join(tempDir, 'etc', 'config.json') };
+
+    const validation = validateLayeredConfig(await loadLayeredConfig(tempDir, {
+      ...env,
+      AUTOMATOSX_CONFIG__providers__fallback: 'claude',
+    }));
+    expect(validation.valid).toBe(false);
+    expect(validation.files).toHaveLength(1);
+    expect(validation.files[0]).toMatchObject({ layer: 'project', migrations: ['schemaVersion 1 became 2', 'defaultProvider moved to providers.default'] });
+    const issues = validation.files[0]!.issues.map(formatConfigIssue);
+    expect(issues).toHaveLength(3);
+    expect(issues).toEqual(expect.arrayContaining([
+      'provders is not a config key; did you mean "providers"?',
+      'providers.executors.local.protcol is not a config key; did you mean "protocol"?',
+      'maintenance.intervalHours must be a number',
+    ]));
+    // What the file gets wrong is reported against the file; the merged config adds what the variable broke.
+    expect(validation.overrides).toEqual([
+      { path: 'providers.fallback', message: 'must be an array', layer: 'env', source: 'AUTOMATOSX_CONFIG__providers__fallback' },
+    ]);
+    expect(validateWorkspaceConfig({ schemaVersion: 3, agents: {} }).map(formatConfigIssue)).toEqual([
+      'schemaVersion is 3, newer than the 2 this version of AutomatosX reads; upgrade AutomatosX',
+    ]);
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    expect(await runtime.getConfig('providers.default')).toBe('codex');
+    await expect(runtime.setConfig('providers.defualt', 'claude')).rejects.toThrow('providers.defualt is not a config key; did you mean "default"?');
+    await expect(runtime.setConfig('maintenance.logGenerations', -1)).rejects.toMatchObject({ code: 'CONFIG_INVALID', message: 'maintenance.logGenerations must be at least 0' });
+    // The file's other problems do not stop a value that is right, and writing it upgrades the file.
+    await runtime.setConfig('maintenance.intervalHours', 6);
+    const written = JSON.parse(await readFile(configPath, 'utf8')) as Record<string, unknown>;
+    expect(written).toMatchObject({ schemaVersion: 2, providers: { default: 'codex' }, maintenance: { intervalHours: 6 } });
+    expect(written).not.toHaveProperty('defaultProvider');
+  });
+
+  it('prunes memory by the workspace retention settings', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    mkdirSync(join(tempDir, '.automatosx'), { recursive: true });
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({
+      maintenance: { memoryMaxEntries: 1 },
+    }), 'utf8');
+
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    const stored = await runtime.storeMemory({ key: 'draft', value: 'wip', ttlSeconds: 3600, importance: 0.5 });
+    expect(Date.parse(stored.expiresAt!) - Date.parse(stored.updatedAt)).toBeGreaterThanOrEqual(3_599_000);
+    await new Promise((resolve) => setTimeout(resolve, 5));
+    await runtime.storeMemory({ key: 'decision', value: 'use sqlite' });
+    await expect(runtime.storeMemory({ key: 'bad', value: 1, ttlSeconds: 0 })).rejects.toThrow('Memory TTL must be a positive number of seconds, got 0.');
+
+    const preview = await runtime.pruneMemory({ dryRun: true });
+    expect(preview).toMatchObject({ dryRun: true, policy: { maxEntries: 1, maxAgeDays: 90 }, evicted: [{ key: 'draft', reason: 'capacity', importance: 0.5 }] });
+    expect(await runtime.listMemory()).toHaveLength(2);
+
+    const result = await runtime.pruneMemory();
+    expect(result.evicted.map((entry) => entry.key)).toEqual(['draft']);
+    expect((await runtime.listMemory()).map((entry) => entry.key)).toEqual(['decision']);
+    const maintenance = await runtime.runMaintenance({ tasks: ['memory'], dryRun: true });
+    expect(maintenance.tasks[0]?.summary).toBe('Would delete 0 memory entries.');
+  });
+
+  it('exports memory to a portable archive and imports it into another workspace', async () => {
+    const sourceDir = createTempDir();
+    const targetDir = createTempDir();
+    tempDirs.push(sourceDir, targetDir);
+
+    const source = createSharedRuntimeService({ basePath: sourceDir });
+    await source.storeMemory({ namespace: 'notes', key: 'style', value: { indent: 'tabs' }, scope: { project: sourceDir }, importance: 0.8 });
+    await source.storeMemory({ namespace: 'notes', key: 'retry', value: 'exponential', scope: { project: sourceDir } });
+    await source.storeMemory({ namespace: 'scratch', key: 'todo', value: 'not shared' });
+    const exported = await source.exportMemory({ path: 'out/team.axm', namespaces: ['notes'], scope: { project: sourceDir } });
+    expect(exported.path).toBe(join(sourceDir, 'out', 'team.axm'));
+    expect(exported.manifest).toMatchObject({ format: 'automatosx-memory', version: 1, entries: 2, embedded: 0 });
+
+    const targetStore = createStateStore({ basePath: targetDir });
+    await targetStore.storeMemory({ namespace: scopedNamespace('notes', { project: targetDir }), key: 'retry', value: 'local and newer' });
+    await targetStore.importMemory([{ key: 'vector', namespace: 'embeddings', value: 'chunk', embedding: [1, 0], updatedAt: '2026-01-01T00:00:00.000Z' }]);
+    const target = createSharedRuntimeService({ basePath: targetDir, stateStore: targetStore });
+    const imported = await target.importMemory({ path: exported.path });
+    expect(imported).toMatchObject({ imported: 1, skipped: 1 });
+    const style = await target.getMemory('style', 'notes', { project: targetDir });
+    expect(style).toMatchObject({ value: { indent: 'tabs' }, importance: 0.8 });
+    expect(style?.updatedAt).toBe((await source.getMemory('style', 'notes', { project: sourceDir }))?.updatedAt);
+    expect((await target.getMemory('retry', 'notes', { project: targetDir }))?.value).toBe('local and newer');
+    expect(await target.getMemory('todo', 'scratch')).toBeUndefined();
+    expect((await targetStore.searchMemoryByVector([1, 0.1])).map((entry) => entry.key)).toEqual(['vector']);
+
+    expect(await target.importMemory({ path: exported.path, overwrite: true })).toMatchObject({ imported: 2, skipped: 0 });
+    expect((await target.getMemory('retry', 'notes', { project: targetDir }))?.value).toBe('exponential');
+    await writeFile(join(targetDir, 'bogus.axm'), 'not gzip', 'utf8');
+    await expect(target.importMemory({ path: 'bogus.axm' })).rejects.toThrow('is not a memory archive');
+  });
+
+  it('skips, merges, or supersedes near-identical memory on insert', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const runtime = createSharedRuntimeService({ basePath: tempDir });
+    await runtime.storeMemory({ namespace: 'facts', key: 'db', value: { engine: 'postgres' }, embedding: [1, 0, 0] });
+    expect(await runtime.storeMemory({ namespace: 'facts', key: 'db-copy', value: { engine: 'postgres' }, embedding: [1, 0, 0] })).not.toHaveProperty('duplicate');
+    await runtime.deleteMemory('db-copy', 'facts');
+
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({ memory: { dedup: { threshold: 0.9 } } }), 'utf8');
+    expect(await runtime.storeMemory({ namespace: 'facts', key: 'database', value: 'postgres', embedding: [0.98, 0.1, 0] }))
+      .toMatchObject({ key: 'db', value: { engine: 'postgres' }, duplicate: { action: 'skip', key: 'db', namespace: 'facts' } });
+    expect(await runtime.getMemory('database', 'facts')).toBeUndefined();
+
+    const merged = await runtime.storeMemory({ namespace: 'facts', key: 'db-version', value: { version: 16 }, embedding: [0.97, 0.12, 0], importance: 0.7, dedup: 'merge' });
+    expect(merged).toMatchObject({ key: 'db', value: { engine: 'postgres', version: 16 }, importance: 0.7, duplicate: { action: 'merge', key: 'db' } });
+    expect(merged.duplicate?.score).toBeGreaterThan(0.9);
+
+    expect(await runtime.storeMemory({ namespace: 'facts', key: 'primary-db', value: 'postgres 17', embedding: [1, 0.01, 0], dedup: 'supersede' }))
+      .toMatchObject({ key: 'primary-db', duplicate: { action: 'supersede', key: 'db' } });
+    expect((await runtime.listMemory('facts')).map((entry) => entry.key)).toEqual(['primary-db']);
+
+    expect(await runtime.storeMemory({ namespace: 'facts', key: 'primary-db', value: 'postgres 17.1', embedding: [1, 0.02, 0] })).not.toHaveProperty('duplicate');
+    expect(await runtime.storeMemory({ namespace: 'other', key: 'db', value: 'postgres', embedding: [1, 0, 0] })).not.toHaveProperty('duplicate');
+    expect(await runtime.storeMemory({ namespace: 'facts', key: 'cache', value: 'redis', embedding: [0, 1, 0] })).not.toHaveProperty('duplicate');
+    expect(await runtime.storeMemory({ namespace: 'facts', key: 'note', value: 'no vector', dedup: 'off' })).not.toHaveProperty('duplicate');
+    await expect(runtime.storeMemory({ namespace: 'facts', key: 'note', value: 'no vector' })).rejects.toThrow('Memory deduplication compares embeddings');
+    await expect(runtime.storeMemory({ key: 'x', value: 1, embedding: [1, 0, 0], dedup: 'drop' as never }))
+      .rejects.toThrow('Memory dedup action must be one of skip, merge, supersede or off, got "drop".');
+  });
+
+  it('encrypts memory at rest with the key from AUTOMATOSX_MEMORY_KEY', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+    const plain = createSharedRuntimeService({ basePath: tempDir });
+    await plain.storeMemory({ key: 'style', value: 'tabs over spaces' });
+    await expect(plain.encryptMemory()).rejects.toThrow('Memory encryption is off.');
+
+    await writeFile(join(tempDir, '.automatosx', 'config.json'), JSON.stringify({ memory: { encryption: 'env' } }), 'utf8');
+    const { key } = createMemoryKey();
+    expect(decodeMemoryKey(key)).toHaveLength(32);
+    expect(decodeMemoryKey('correct horse battery staple')).toHaveLength(32);
+    const stateStore = createStateStore({ basePath: tempDir, encryption: memoryCipherFor(tempDir, { AUTOMATOSX_MEMORY_KEY: key }) });
+    const runtime = createSharedRuntimeService({ basePath: tempDir, stateStore });
+    expect(await runtime.encryptMemory()).toEqual({ encrypted: 1 });
+    expect((await runtime.getMemory('style'))?.value).toBe('tabs over spaces');
+    expect(await readFile(join(tempDir, '.automatosx', 'runtime', 'state.db'), 'latin1')).not.toContain('tabs over spaces');
+    await expect(createStateStore({ basePath: tempDir, encryption: memoryCipherFor(tempDir, {}) }).listMemory())
+      .rejects.toThrow('Memory is encrypted with the key in AUTOMATOSX_MEMORY_KEY, which is not set.');
+  });
+
   it('closes stuck sessions through the shared runtime', async () => {
     const tempDir = createTempDir();
     tempDirs.push(tempDir);
@@ -0,0 +1,56 @@
+import { createCipheriv, createDecipheriv, randomBytes } from 'node:crypto';
+const ENCRYPTED_PREFIX = 'axenc:v1:';
+const KEY_BYTES = 32;
+const IV_BYTES = 12;
+const TAG_BYTES = 16;
+/**
+ * AES-256-GCM with a fresh IV per value, stored as `axenc:v1:<base64 of iv, tag and ciphertext>`.
+ * A key given as a function is only loaded when memory is first read or written, so a missing key
+ * does not get in the way of commands that never touch memory.
+ */
+export function createMemoryCipher(key) {
+    let loaded;
+    const keyBytes = () => {
+        loaded ??= typeof key === 'function' ? key() : key;
+        if (loaded.length !== KEY_BYTES) {
+            throw new Error(`Memory encryption keys are ${KEY_BYTES} bytes, got ${loaded.length}.`);
+        }
+        return loaded;
+    };
+    return {
+        encrypt(plaintext) {
+            const iv = randomBytes(IV_BYTES);
+            const cipher = createCipheriv('aes-256-gcm', keyBytes(), iv);
+            const body = Buffer.concat([cipher.update(plaintext, 'utf8'), cipher.final()]);
+            return `${ENCRYPTED_PREFIX}${Buffer.concat([iv, cipher.getAuthTag(), body]).toString('base64')}`;
+        },
+        decrypt(ciphertext) {
+            const raw = Buffer.from(ciphertext.slice(ENCRYPTED_PREFIX.length), 'base64');
+            const decipher = createDecipheriv('aes-256-gcm', keyBytes(), raw.subarray(0, IV_BYTES));
+            decipher.setAuthTag(raw.subarray(IV_BYTES, IV_BYTES + TAG_BYTES));
+            try {
+                return Buffer.concat([decipher.update(raw.subarray(IV_BYTES + TAG_BYTES)), decipher.final()]).toString('utf8');
+            }
+            catch {
+                throw new Error('Memory could not be decrypted: the key is not the one it was encrypted with.');
+            }
+        },
+    };
+}
+export function isEncryptedMemory(stored) {
+    return typeof stored === 'string' && stored.startsWith(ENCRYPTED_PREFIX);
+}
+/** Encrypts `text` when the store has a cipher. */
+export function sealMemoryText(text, cipher) {
+    return cipher !== undefined ? cipher.encrypt(text) : text;
+}
+/** Decrypts `stored` when it is encrypted; plaintext written before encryption was turned on passes through. */
+export function openMemoryText(stored, cipher) {
+    if (!isEncryptedMemory(stored)) {
+        return stored;
+    }
+    if (cipher === undefined) {
+        throw new Error('Memory is encrypted at rest and this store has no key to read it.');
+    }
+    return cipher.decrypt(stored);
+}
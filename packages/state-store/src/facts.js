@@ -0,0 +1,23 @@
+import { assertSourceSpan } from './provenance.js';
+export function assertFact(input) {
+    for (const field of ['subject', 'predicate']) {
+        if (typeof input[field] !== 'string' || input[field].trim().length === 0) {
+            throw new Error(`A fact needs a non-empty ${field}.`);
+        }
+    }
+    if (!['string', 'number', 'boolean'].includes(typeof input.object) || (typeof input.object === 'number' && !Number.isFinite(input.object))) {
+        throw new Error(`A fact's object must be a string, a finite number or a boolean, got ${JSON.stringify(input.object) ?? String(input.object)}.`);
+    }
+    if (input.confidence !== undefined && !(input.confidence >= 0 && input.confidence <= 1)) {
+        throw new Error(`Fact confidence must be between 0 and 1, got ${input.confidence}.`);
+    }
+    if (input.sourceSpan !== undefined) {
+        assertSourceSpan(input.sourceSpan, 'fact');
+    }
+}
+export function matchesFact(fact, query) {
+    return (query.subject === undefined || fact.subject === query.subject)
+        && (query.predicate === undefined || fact.predicate === query.predicate)
+        && (query.object === undefined || fact.object === query.object)
+        && (query.namespace === undefined || (fact.namespace ?? 'default') === query.namespace);
+}
@@ -0,0 +1,61 @@
+import { assertSourceSpan } from './provenance.js';
+
+/** Where in the workspace a fact was read from. Lines are 1-based and inclusive. */
+export interface SourceSpan {
+  file: string;
+  startLine?: number;
+  endLine?: number;
+}
+
+/** A fact's object: another entity by name, or a literal. */
+export type FactObject = string | number | boolean;
+
+export interface FactInput {
+  subject: string;
+  predicate: string;
+  object: FactObject;
+  namespace?: string;
+  sourceSpan?: SourceSpan;
+  /** How sure the agent that stored the fact was, 0-1. */
+  confidence?: number;
+}
+
+/** A subject-predicate-object triple: `{ subject: 'billing-api', predicate: 'calls', object: 'ledger-api' }`. */
+export interface Fact extends FactInput {
+  factId: string;
+  createdAt: string;
+  updatedAt: string;
+}
+
+/** Exact-match filter; fields left out match anything, and no namespace matches every namespace. */
+export interface FactQuery {
+  subject?: string;
+  predicate?: string;
+  object?: FactObject;
+  namespace?: string;
+  limit?: number;
+}
+
+export function assertFact(input: FactInput): void {
+  for (const field of ['subject', 'predicate'] as const) {
+    if (typeof input[field] !== 'string' || input[field].trim().length === 0) {
+      throw new Error(`A fact needs a non-empty ${field}.`);
+    }
+  }
+  if (!['string', 'number', 'boolean'].includes(typeof input.object) || (typeof input.object === 'number' && !Number.isFinite(input.object))) {
+    throw new Error(`A fact's object must be a string, a finite number or a boolean, got ${JSON.stringify(input.object) ?? String(input.object)}.`);
+  }
+  if (input.confidence !== undefined && !(input.confidence >= 0 && input.confidence <= 1)) {
+    throw new Error(`Fact confidence must be between 0 and 1, got ${input.confidence}.`);
+  }
+  if (input.sourceSpan !== undefined) {
+    assertSourceSpan(input.sourceSpan, 'fact');
+  }
+}
+
+export function matchesFact(fact: Fact, query: FactQuery): boolean {
+  return (query.subject === undefined || fact.subject === query.subject)
+    && (query.predicate === undefined || fact.predicate === query.predicate)
+    && (query.object === undefined || fact.object === query.object)
+    && (query.namespace === undefined || (fact.namespace ?? 'default') === query.namespace);
+}
@@ -0,0 +1,386 @@
+// Hierarchical navigable small world graph (Malkov & Yashunin) over unit vectors, for approximate
+// cosine top-k search in roughly logarithmic time.
+const MAGIC = 'AXHNSW01';
+const DEFAULT_M = 16;
+const DEFAULT_EF_CONSTRUCTION = 200;
+const DEFAULT_EF_SEARCH = 64;
+export function createHnswIndex(options = {}) {
+    return createGraph(options);
+}
+/** Exact cosine similarity, for stores that compare every vector instead of searching a graph. */
+export function cosineSimilarity(left, right) {
+    let dot = 0;
+    let leftMagnitude = 0;
+    let rightMagnitude = 0;
+    for (let index = 0; index < left.length; index += 1) {
+        dot += left[index] * right[index];
+        leftMagnitude += left[index] * left[index];
+        rightMagnitude += right[index] * right[index];
+    }
+    return leftMagnitude === 0 || rightMagnitude === 0 ? 0 : dot / Math.sqrt(leftMagnitude * rightMagnitude);
+}
+function createGraph(options, stored) {
+    const M = options.M ?? DEFAULT_M;
+    const efConstruction = options.efConstruction ?? DEFAULT_EF_CONSTRUCTION;
+    const efSearch = options.efSearch ?? DEFAULT_EF_SEARCH;
+    const random = options.random ?? Math.random;
+    const levelMultiplier = 1 / Math.log(M);
+    let dimensions;
+    let vectors = new Float32Array(0);
+    const ids = [];
+    const levels = [];
+    const links = [];
+    const deleted = [];
+    const nodeOf = new Map();
+    let entryPoint = -1;
+    let maxLevel = -1;
+    let removedCount = 0;
+    let visited = new Uint32Array(0);
+    let visitStamp = 0;
+    const distance = (query, node) => {
+        const offset = node * dimensions;
+        let dot = 0;
+        for (let index = 0; index < dimensions; index += 1) {
+            dot += query[index] * vectors[offset + index];
+        }
+        return 1 - dot;
+    };
+    const vectorOf = (node) => vectors.subarray(node * dimensions, (node + 1) * dimensions);
+    const normalize = (vector) => {
+        if (dimensions === undefined) {
+            dimensions = vector.length;
+        }
+        if (vector.length !== dimensions) {
+            throw new Error(`Vector has ${vector.length} dimensions; this index holds ${dimensions}.`);
+        }
+        const unit = Float32Array.from(vector);
+        let norm = 0;
+        for (const value of unit) {
+            norm += value * value;
+        }
+        norm = Math.sqrt(norm);
+        if (norm > 0) {
+            for (let index = 0; index < unit.length; index += 1) {
+                unit[index] = unit[index] / norm;
+            }
+        }
+        return unit;
+    };
+    // Beam search of one layer from `entries`, returning up to `ef` nodes nearest first.
+    const searchLayer = (query, entries, ef, level) => {
+        visitStamp += 1;
+        if (visitStamp === 0xffffffff) {
+            visited.fill(0);
+            visitStamp = 1;
+        }
+        const candidates = createHeap((left, right) => left.distance < right.distance);
+        const nearest = createHeap((left, right) => left.distance > right.distance);
+        for (const node of entries) {
+            visited[node] = visitStamp;
+            const candidate = { node, distance: distance(query, node) };
+            candidates.push(candidate);
+            nearest.push(candidate);
+        }
+        while (candidates.size > 0) {
+            const current = candidates.pop();
+            if (nearest.size >= ef && current.distance > nearest.peek().distance) {
+                break;
+            }
+            for (const neighbor of links[current.node][level] ?? []) {
+                if (visited[neighbor] === visitStamp) {
+                    continue;
+                }
+                visited[neighbor] = visitStamp;
+                const candidate = { node: neighbor, distance: distance(query, neighbor) };
+                if (nearest.size < ef || candidate.distance < nearest.peek().distance) {
+                    candidates.push(candidate);
+                    nearest.push(candidate);
+                    if (nearest.size > ef) {
+                        nearest.pop();
+                    }
+                }
+            }
+        }
+        return nearest.drain().reverse();
+    };
+    // The paper's neighbour heuristic: skip candidates closer to an already chosen neighbour than to
+    // the base, which keeps links spread across clusters, then top up with the skipped ones.
+    const selectNeighbors = (candidates, limit) => {
+        const chosen = [];
+        const skipped = [];
+        for (const candidate of candidates) {
+            if (chosen.length >= limit) {
+                break;
+            }
+            const query = vectorOf(candidate.node);
+            if (chosen.every((other) => distance(query, other.node) > candidate.distance)) {
+                chosen.push(candidate);
+            }
+            else {
+                skipped.push(candidate);
+            }
+        }
+        for (const candidate of skipped) {
+            if (chosen.length >= limit) {
+                break;
+            }
+            chosen.push(candidate);
+        }
+        return chosen.map((candidate) => candidate.node);
+    };
+    const grow = (needed) => {
+        if (needed * dimensions <= vectors.length) {
+            return;
+        }
+        const capacity = Math.max(needed, ids.length * 2, 64);
+        const next = new Float32Array(capacity * dimensions);
+        next.set(vectors);
+        vectors = next;
+        const marks = new Uint32Array(capacity);
+        marks.set(visited);
+        visited = marks;
+    };
+    const addNode = (id, unit, level, nodeLinks, isDeleted = false) => {
+        const node = ids.length;
+        grow(node + 1);
+        vectors.set(unit, node * dimensions);
+        ids.push(id);
+        levels.push(level);
+        links.push(nodeLinks ?? Array.from({ length: level + 1 }, () => []));
+        deleted.push(isDeleted);
+        if (isDeleted) {
+            removedCount += 1;
+        }
+        else {
+            nodeOf.set(id, node);
+        }
+        return node;
+    };
+    const insert = (id, unit) => {
+        const level = Math.floor(-Math.log(1 - random()) * levelMultiplier);
+        const node = addNode(id, unit, level);
+        if (entryPoint === -1) {
+            entryPoint = node;
+            maxLevel = level;
+            return;
+        }
+        let entries = [entryPoint];
+        for (let layer = maxLevel; layer > level; layer -= 1) {
+            entries = [searchLayer(unit, entries, 1, layer)[0].node];
+        }
+        for (let layer = Math.min(level, maxLevel); layer >= 0; layer -= 1) {
+            const nearest = searchLayer(unit, entries, efConstruction, layer);
+            const limit = layer === 0 ? M * 2 : M;
+            const neighbors = selectNeighbors(nearest, M);
+            links[node][layer] = neighbors;
+            for (const neighbor of neighbors) {
+                const neighborLinks = links[neighbor][layer];
+                neighborLinks.push(node);
+                if (neighborLinks.length > limit) {
+                    const base = vectorOf(neighbor);
+                    const ranked = neighborLinks
+                        .map((other) => ({ node: other, distance: distance(base, other) }))
+                        .sort((left, right) => left.distance - right.distance);
+                    links[neighbor][layer] = selectNeighbors(ranked, limit);
+                }
+            }
+            entries = nearest.map((candidate) => candidate.node);
+        }
+        if (level > maxLevel) {
+            entryPoint = node;
+            maxLevel = level;
+        }
+    };
+    const remove = (id) => {
+        const node = nodeOf.get(id);
+        if (node === undefined) {
+            return false;
+        }
+        // Removed nodes stay in the graph as waypoints so their neighbours remain reachable.
+        nodeOf.delete(id);
+        deleted[node] = true;
+        removedCount += 1;
+        return true;
+    };
+    for (const node of stored?.nodes ?? []) {
+        dimensions ??= node.unit.length;
+        addNode(node.id, node.unit, node.level, node.links, node.deleted);
+    }
+    if (stored !== undefined) {
+        entryPoint = stored.entryPoint;
+        maxLevel = stored.maxLevel;
+    }
+    return {
+        get dimensions() {
+            return dimensions;
+        },
+        get size() {
+            return nodeOf.size;
+        },
+        get removed() {
+            return removedCount;
+        },
+        has: (id) => nodeOf.has(id),
+        upsert(id, vector) {
+            const unit = normalize(vector);
+            remove(id);
+            insert(id, unit);
+        },
+        remove,
+        search(vector, k, filter) {
+            if (entryPoint === -1 || k <= 0) {
+                return [];
+            }
+            const query = normalize(vector);
+            let entries = [entryPoint];
+            for (let layer = maxLevel; layer > 0; layer -= 1) {
+                entries = [searchLayer(query, entries, 1, layer)[0].node];
+            }
+            // A selective filter can leave fewer than k matches in the beam; widen it until it spans the graph.
+            for (let ef = Math.max(efSearch, k); ; ef *= 4) {
+                const nearest = searchLayer(query, entries, ef, 0);
+                const matches = nearest
+                    .filter((candidate) => !deleted[candidate.node] && (filter === undefined || filter(ids[candidate.node])))
+                    .slice(0, k);
+                if (matches.length >= k || nearest.length < ef || ef >= ids.length) {
+                    return matches.map((candidate) => ({ id: ids[candidate.node], score: 1 - candidate.distance }));
+                }
+            }
+        },
+        compact() {
+            const compacted = createHnswIndex(options);
+            for (let node = 0; node < ids.length; node += 1) {
+                if (!deleted[node]) {
+                    compacted.upsert(ids[node], vectorOf(node));
+                }
+            }
+            return compacted;
+        },
+        serialize() {
+            const header = Buffer.from(JSON.stringify({ dimensions: dimensions ?? 0, M, efConstruction, efSearch, count: ids.length, entryPoint, maxLevel }), 'utf8');
+            const encodedIds = ids.map((id) => Buffer.from(id, 'utf8'));
+            let size = MAGIC.length + 4 + header.length;
+            for (let node = 0; node < ids.length; node += 1) {
+                size += 4 + encodedIds[node].length + 2 + (dimensions ?? 0) * 4;
+                for (const layer of links[node]) {
+                    size += 2 + layer.length * 4;
+                }
+            }
+            const buffer = Buffer.alloc(size);
+            let offset = buffer.write(MAGIC, 0, 'latin1');
+            offset = buffer.writeUInt32LE(header.length, offset);
+            offset += header.copy(buffer, offset);
+            for (let node = 0; node < ids.length; node += 1) {
+                offset = buffer.writeUInt32LE(encodedIds[node].length, offset);
+                offset += encodedIds[node].copy(buffer, offset);
+                offset = buffer.writeUInt8(deleted[node] ? 1 : 0, offset);
+                offset = buffer.writeUInt8(levels[node], offset);
+                for (const value of vectorOf(node)) {
+                    offset = buffer.writeFloatLE(value, offset);
+                }
+                for (const layer of links[node]) {
+                    offset = buffer.writeUInt16LE(layer.length, offset);
+                    for (const neighbor of layer) {
+                        offset = buffer.writeUInt32LE(neighbor, offset);
+                    }
+                }
+            }
+            return buffer;
+        },
+    };
+}
+/** Reads a graph written by `serialize`; throws when the data is not one. */
+export function deserializeHnswIndex(buffer, options = {}) {
+    if (buffer.toString('latin1', 0, MAGIC.length) !== MAGIC) {
+        throw new Error('Not an HNSW index file.');
+    }
+    let offset = MAGIC.length;
+    const headerLength = buffer.readUInt32LE(offset);
+    offset += 4;
+    const header = JSON.parse(buffer.toString('utf8', offset, offset + headerLength));
+    offset += headerLength;
+    const nodes = [];
+    for (let node = 0; node < header.count; node += 1) {
+        const idLength = buffer.readUInt32LE(offset);
+        offset += 4;
+        const id = buffer.toString('utf8', offset, offset + idLength);
+        offset += idLength;
+        const isDeleted = buffer.readUInt8(offset) === 1;
+        const level = buffer.readUInt8(offset + 1);
+        offset += 2;
+        const unit = new Float32Array(header.dimensions);
+        for (let index = 0; index < header.dimensions; index += 1) {
+            unit[index] = buffer.readFloatLE(offset);
+            offset += 4;
+        }
+        const nodeLinks = [];
+        for (let layer = 0; layer <= level; layer += 1) {
+            const count = buffer.readUInt16LE(offset);
+            offset += 2;
+            const neighbors = [];
+            for (let index = 0; index < count; index += 1) {
+                neighbors.push(buffer.readUInt32LE(offset));
+                offset += 4;
+            }
+            nodeLinks.push(neighbors);
+        }
+        nodes.push({ id, unit, level, links: nodeLinks, deleted: isDeleted });
+    }
+    return createGraph({ M: header.M, efConstruction: header.efConstruction, efSearch: header.efSearch, random: options.random }, { nodes, entryPoint: header.entryPoint, maxLevel: header.maxLevel });
+}
+function createHeap(before) {
+    const items = [];
+    const swap = (left, right) => {
+        [items[left], items[right]] = [items[right], items[left]];
+    };
+    const pop = () => {
+        if (items.length <= 1) {
+            return items.pop();
+        }
+        const top = items[0];
+        items[0] = items.pop();
+        for (let parent = 0; ;) {
+            const left = parent * 2 + 1;
+            const right = left + 1;
+            let first = parent;
+            if (left < items.length && before(items[left], items[first])) {
+                first = left;
+            }
+            if (right < items.length && before(items[right], items[first])) {
+                first = right;
+            }
+            if (first === parent) {
+                break;
+            }
+            swap(parent, first);
+            parent = first;
+        }
+        return top;
+    };
+    return {
+        get size() {
+            return items.length;
+        },
+        push(item) {
+            items.push(item);
+            for (let child = items.length - 1; child > 0;) {
+                const parent = (child - 1) >> 1;
+                if (!before(items[child], items[parent])) {
+                    break;
+                }
+                swap(child, parent);
+                child = parent;
+            }
+        },
+        pop,
+        peek: () => items[0],
+        // Pops everything, so the result is in heap order.
+        drain() {
+            const drained = [];
+            while (items.length > 0) {
+                drained.push(pop());
+            }
+            return drained;
+        },
+    };
+}
@@ -1,23 +1,40 @@
 import { randomUUID } from 'node:crypto';
 import { mkdir, readFile, rename, rm, stat, writeFile } from 'node:fs/promises';
 import { dirname, join } from 'node:path';
+import { isEncryptedMemory, openMemoryText, sealMemoryText } from './encryption.js';
+import { assertFact, matchesFact } from './facts.js';
+import { cosineSimilarity } from './hnsw.js';
+import { assertNamespaceTransfer } from './namespaces.js';
+import { assertMemorySource } from './provenance.js';
+import { assertMemoryRetention, isMemoryExpired, selectMemoryEvictions } from './retention.js';
+import { applyScratchpadPost, applyScratchpadRelease, createScratchpadTasks, selectScratchpadClaim, } from './scratchpad.js';
 import { createSqliteStateStore } from './sqlite.js';
+import { matchesUsage, normalizeUsage } from './usage.js';
 const DEFAULT_STATE_STORE_FILE = join('.automatosx', 'runtime', 'state.json');
 const stateStoreQueues = new Map();
 const LOCK_WAIT_TIMEOUT_MS = 5_000;
 const LOCK_STALE_AFTER_MS = 60_000;
 const LOCK_RETRY_DELAY_MS = 10;
+const DEFAULT_VECTOR_SEARCH_K = 10;
 export class FileStateStore {
     storageFile;
+    cipher;
     constructor(config = {}) {
         this.storageFile = config.storageFile ?? join(config.basePath ?? process.cwd(), DEFAULT_STATE_STORE_FILE);
+        this.cipher = config.encryption;
     }
     async storeMemory(entry) {
+        assertMemoryRetention(entry);
+        assertMemorySource(entry.source);
         return this.withMutation(async (data) => {
             const stored = {
                 key: entry.key,
                 namespace: entry.namespace,
                 value: entry.value,
+                ...(entry.embedding !== undefined ? { embedding: entry.embedding } : {}),
+                ...(entry.expiresAt !== undefined ? { expiresAt: new Date(entry.expiresAt).toISOString() } : {}),
+                ...(entry.importance !== undefined ? { importance: entry.importance } : {}),
+                ...(entry.source !== undefined ? { source: entry.source } : {}),
                 updatedAt: new Date().toISOString(),
             };
             const index = data.memory.findIndex((item) => item.key === stored.key && item.namespace === stored.namespace);
@@ -32,17 +49,27 @@ export class FileStateStore {
     }
     async listMemory(namespace) {
         const data = await this.readConsistentData();
-        return data.memory.filter((entry) => namespace === undefined || entry.namespace === namespace);
+        return data.memory.filter((entry) => (namespace === undefined || entry.namespace === namespace) && !isMemoryExpired(entry));
     }
     async getMemory(key, namespace) {
         const data = await this.readConsistentData();
-        return data.memory.find((entry) => entry.key === key && entry.namespace === namespace);
+        if (!data.memory.some((entry) => entry.key === key && entry.namespace === namespace && !isMemoryExpired(entry))) {
+            return undefined;
+        }
+        return this.withMutation(async (latest) => {
+            const entry = latest.memory.find((item) => item.key === key && item.namespace === namespace);
+            if (entry === undefined || isMemoryExpired(entry)) {
+                return undefined;
+            }
+            entry.accessedAt = new Date().toISOString();
+            return entry;
+        });
     }
     async searchMemory(query, namespace) {
         const normalized = query.trim().toLowerCase();
         const data = await this.readConsistentData();
         const matches = data.memory.filter((entry) => {
-            if (namespace !== undefined && entry.namespace !== namespace) {
+            if ((namespace !== undefined && entry.namespace !== namespace) || isMemoryExpired(entry)) {
                 return false;
             }
             if (normalized === '') {
@@ -64,6 +91,89 @@ export class FileStateStore {
             return data.memory.length !== originalLength;
         });
     }
+    async searchMemoryByVector(vector, options = {}) {
+        const data = await this.readConsistentData();
+        return data.memory
+            .filter((entry) => entry.embedding?.length === vector.length && (options.namespace === undefined || entry.namespace === options.namespace) && !isMemoryExpired(entry))
+            .map((entry) => ({ ...entry, score: cosineSimilarity(vector, entry.embedding) }))
+            .filter((entry) => entry.score >= (options.minScore ?? -1))
+            .sort((left, right) => right.score - left.score)
+            .slice(0, options.k ?? DEFAULT_VECTOR_SEARCH_K);
+    }
+    async listMemoryNamespaces() {
+        const data = await this.readConsistentData();
+        const grouped = new Map();
+        for (const entry of data.memory) {
+            const namespace = entry.namespace ?? 'default';
+            const stats = grouped.get(namespace) ?? { namespace, entries: 0, embedded: 0 };
+            stats.entries += 1;
+            stats.embedded += entry.embedding !== undefined ? 1 : 0;
+            if (stats.lastUpdatedAt === undefined || entry.updatedAt > stats.lastUpdatedAt) {
+                stats.lastUpdatedAt = entry.updatedAt;
+            }
+            grouped.set(namespace, stats);
+        }
+        return [...grouped.values()].sort((left, right) => left.namespace.localeCompare(right.namespace));
+    }
+    async copyMemoryNamespace(source, target, options = {}) {
+        return this.withMutation(async (data) => transferMemory(data, [source], target, options.overwrite === true ? 'overwrite' : 'keep'));
+    }
+    async mergeMemoryNamespaces(sources, target) {
+        return this.withMutation(async (data) => {
+            const transfer = transferMemory(data, sources, target, 'newer');
+            const originalLength = data.memory.length;
+            data.memory = data.memory.filter((entry) => !sources.includes(entry.namespace ?? 'default'));
+            return { ...transfer, deleted: originalLength - data.memory.length };
+        });
+    }
+    async importMemory(entries, options = {}) {
+        for (const entry of entries) {
+            assertMemoryRetention(entry);
+            assertMemorySource(entry.source);
+        }
+        return this.withMutation(async (data) => {
+            let copied = 0;
+            let skipped = 0;
+            for (const entry of entries) {
+                const namespace = entry.namespace === 'default' ? undefined : entry.namespace;
+                if (placeMemory(data, { ...entry, namespace }, options.mode ?? 'newer')) {
+                    copied += 1;
+                }
+                else {
+                    skipped += 1;
+                }
+            }
+            return { copied, skipped };
+        });
+    }
+    async pruneMemory(policy, options = {}) {
+        const select = (data) => selectMemoryEvictions(data.memory.map((entry) => ({
+            ...entry,
+            bytes: Buffer.byteLength(safeStringify(entry.value)) + (entry.embedding !== undefined ? Buffer.byteLength(JSON.stringify(entry.embedding)) : 0),
+        })), policy, options.now);
+        if (options.dryRun === true) {
+            return select(await this.readConsistentData());
+        }
+        return this.withMutation(async (data) => {
+            const evictions = select(data);
+            const evicted = new Set(evictions.map((eviction) => `${eviction.namespace ?? ''}\0${eviction.key}`));
+            data.memory = data.memory.filter((entry) => !evicted.has(`${entry.namespace ?? ''}\0${entry.key}`));
+            return evictions;
+        });
+    }
+    async encryptMemory() {
+        if (this.cipher === undefined) {
+            throw new Error('This store has no memory encryption key.');
+        }
+        // Every write seals all of memory, so rewriting the file is enough; this counts what was still plaintext.
+        return this.withMutation(async () => {
+            const raw = JSON.parse(await readFile(this.storageFile, 'utf8').catch(() => '{}'));
+            return {
+                encrypted: (raw.memory ?? []).filter((entry) => !isEncryptedMemory(entry.value)).length
+                    + (raw.semantic ?? []).filter((entry) => !isEncryptedMemory(entry.content)).length,
+            };
+        });
+    }
     async registerPolicy(entry) {
         return this.withMutation(async (data) => {
             const stored = {
@@ -237,6 +347,40 @@ export class FileStateStore {
         })
             .sort((left, right) => left.namespace.localeCompare(right.namespace));
     }
+    async storeFact(input) {
+        assertFact(input);
+        return this.withMutation(async (data) => {
+            const now = new Date().toISOString();
+            const existing = data.facts.find((fact) => matchesFact(fact, { ...input, namespace: input.namespace ?? 'default' }));
+            const stored = {
+                factId: existing?.factId ?? randomUUID(),
+                subject: input.subject,
+                predicate: input.predicate,
+                object: input.object,
+                ...(input.namespace !== undefined ? { namespace: input.namespace } : {}),
+                ...(input.sourceSpan !== undefined ? { sourceSpan: input.sourceSpan } : {}),
+                ...(input.confidence !== undefined ? { confidence: input.confidence } : {}),
+                createdAt: existing?.createdAt ?? now,
+                updatedAt: now,
+            };
+            data.facts = [...data.facts.filter((fact) => fact !== existing), stored];
+            return stored;
+        });
+    }
+    async queryFacts(query = {}) {
+        const data = await this.readConsistentData();
+        const matches = data.facts
+            .filter((fact) => matchesFact(fact, query))
+            .sort((left, right) => right.updatedAt.localeCompare(left.updatedAt));
+        return query.limit !== undefined ? matches.slice(0, Math.max(0, query.limit)) : matches;
+    }
+    async deleteFact(factId) {
+        return this.withMutation(async (data) => {
+            const originalLength = data.facts.length;
+            data.facts = data.facts.filter((fact) => fact.factId !== factId);
+            return data.facts.length !== originalLength;
+        });
+    }
     async submitFeedback(entry) {
         return this.withMutation(async (data) => {
             const stored = {
@@ -280,6 +424,20 @@ export class FileStateStore {
         }
         return filtered.slice(0, Math.max(0, options.limit));
     }
+    async recordUsage(entry) {
+        const stored = normalizeUsage(entry);
+        return this.withMutation(async (data) => {
+            data.usage.push(stored);
+            return stored;
+        });
+    }
+    async listUsage(query = {}) {
+        const data = await this.readConsistentData();
+        const filtered = data.usage
+            .filter((record) => matchesUsage(record, query))
+            .sort((left, right) => right.recordedAt.localeCompare(left.recordedAt));
+        return query.limit === undefined ? filtered : filtered.slice(0, Math.max(0, query.limit));
+    }
     async createSession(entry) {
         return this.withMutation(async (data) => {
             const now = new Date().toISOString();
@@ -372,6 +530,15 @@ export class FileStateStore {
             return session;
         });
     }
+    async reopenSession(sessionId) {
+        return this.withMutation(async (data) => {
+            const session = requireSession(data, sessionId);
+            session.status = 'active';
+            session.error = undefined;
+            session.updatedAt = new Date().toISOString();
+            return session;
+        });
+    }
     async closeStuckSessions(maxAgeMs = 86_400_000) {
         return this.withMutation(async (data) => {
             const threshold = Date.now() - maxAgeMs;
@@ -393,6 +560,31 @@ export class FileStateStore {
             return closed;
         });
     }
+    async addScratchpadTasks(sessionId, tasks) {
+        return this.withMutation(async (data) => {
+            requireSession(data, sessionId);
+            const created = createScratchpadTasks(sessionId, data.scratchpad.filter((task) => task.sessionId === sessionId), tasks);
+            data.scratchpad.push(...created);
+            return created;
+        });
+    }
+    async listScratchpad(sessionId) {
+        const data = await this.readConsistentData();
+        return data.scratchpad.filter((task) => task.sessionId === sessionId);
+    }
+    async claimScratchpadTask(sessionId, claim) {
+        return this.withMutation(async (data) => {
+            requireSession(data, sessionId);
+            const claimed = selectScratchpadClaim(sessionId, data.scratchpad.filter((task) => task.sessionId === sessionId), claim);
+            return claimed === undefined ? undefined : replaceScratchpadTask(data, claimed);
+        });
+    }
+    async postScratchpadResult(sessionId, post) {
+        return this.withMutation(async (data) => replaceScratchpadTask(data, applyScratchpadPost(sessionId, data.scratchpad.filter((task) => task.sessionId === sessionId), post)));
+    }
+    async releaseScratchpadTask(sessionId, taskId, claimant) {
+        return this.withMutation(async (data) => replaceScratchpadTask(data, applyScratchpadRelease(sessionId, data.scratchpad.filter((task) => task.sessionId === sessionId), taskId, claimant)));
+    }
     async readConsistentData() {
         await waitForQueue(this.storageFile, stateStoreQueues);
         return this.readData();
@@ -406,17 +598,10 @@ export class FileStateStore {
         });
     }
     async readData() {
+        let parsed;
         try {
             const raw = await readFile(this.storageFile, 'utf8');
-            const parsed = JSON.parse(raw);
-            return {
-                memory: Array.isArray(parsed.memory) ? parsed.memory : [],
-                policies: Array.isArray(parsed.policies) ? parsed.policies : [],
-                agents: Array.isArray(parsed.agents) ? parsed.agents : [],
-                semantic: Array.isArray(parsed.semantic) ? parsed.semantic : [],
-                feedback: Array.isArray(parsed.feedback) ? parsed.feedback : [],
-                sessions: Array.isArray(parsed.sessions) ? parsed.sessions : [],
-            };
+            parsed = JSON.parse(raw);
         }
         catch {
             return {
@@ -424,17 +609,73 @@ export class FileStateStore {
                 policies: [],
                 agents: [],
                 semantic: [],
+                facts: [],
                 feedback: [],
+                usage: [],
                 sessions: [],
+                scratchpad: [],
             };
         }
+        // Decrypted outside the try: a wrong key must fail loudly, not read as an empty store.
+        return {
+            memory: Array.isArray(parsed.memory) ? parsed.memory.map((entry) => this.openMemory(entry)) : [],
+            policies: Array.isArray(parsed.policies) ? parsed.policies : [],
+            agents: Array.isArray(parsed.agents) ? parsed.agents : [],
+            semantic: Array.isArray(parsed.semantic) ? parsed.semantic.map((entry) => this.openSemantic(entry)) : [],
+            facts: Array.isArray(parsed.facts) ? parsed.facts : [],
+            feedback: Array.isArray(parsed.feedback) ? parsed.feedback : [],
+            usage: Array.isArray(parsed.usage) ? parsed.usage : [],
+            sessions: Array.isArray(parsed.sessions) ? parsed.sessions : [],
+            scratchpad: Array.isArray(parsed.scratchpad) ? parsed.scratchpad : [],
+        };
     }
     async writeData(data) {
         await mkdir(dirname(this.storageFile), { recursive: true });
         const tempFile = `${this.storageFile}.${process.pid}.${randomUUID()}.tmp`;
-        await writeFile(tempFile, `${JSON.stringify(data, null, 2)}\n`, 'utf8');
+        const stored = this.cipher !== undefined
+            ? { ...data, memory: data.memory.map((entry) => this.sealMemory(entry)), semantic: data.semantic.map((entry) => this.sealSemantic(entry)) }
+            : data;
+        await writeFile(tempFile, `${JSON.stringify(stored, null, 2)}\n`, 'utf8');
         await rename(tempFile, this.storageFile);
     }
+    // Encrypted entries keep their shape, with the value and embedding replaced by ciphertext strings.
+    sealMemory(entry) {
+        return {
+            ...entry,
+            value: sealMemoryText(JSON.stringify(entry.value), this.cipher),
+            ...(entry.embedding !== undefined ? { embedding: sealMemoryText(JSON.stringify(entry.embedding), this.cipher) } : {}),
+        };
+    }
+    // Semantic items likewise, with the content and the token frequencies it is searched by.
+    sealSemantic(entry) {
+        return {
+            ...entry,
+            content: sealMemoryText(entry.content, this.cipher),
+            tokenFreq: sealMemoryText(JSON.stringify(entry.tokenFreq), this.cipher),
+        };
+    }
+    openSemantic(entry) {
+        const { content, tokenFreq } = entry;
+        if (!isEncryptedMemory(content) && !isEncryptedMemory(tokenFreq)) {
+            return entry;
+        }
+        return {
+            ...entry,
+            content: isEncryptedMemory(content) ? openMemoryText(content, this.cipher) : entry.content,
+            tokenFreq: isEncryptedMemory(tokenFreq) ? JSON.parse(openMemoryText(tokenFreq, this.cipher)) : entry.tokenFreq,
+        };
+    }
+    openMemory(entry) {
+        const { value, embedding } = entry;
+        if (!isEncryptedMemory(value) && !isEncryptedMemory(embedding)) {
+            return entry;
+        }
+        return {
+            ...entry,
+            value: isEncryptedMemory(value) ? JSON.parse(openMemoryText(value, this.cipher)) : value,
+            ...(isEncryptedMemory(embedding) ? { embedding: JSON.parse(openMemoryText(embedding, this.cipher)) } : {}),
+        };
+    }
 }
 export function createStateStore(config) {
     if (config?.backend === 'json') {
@@ -443,10 +684,17 @@ export function createStateStore(config) {
     return createSqliteStateStore({
         basePath: config?.basePath,
         dbFile: config?.storageFile,
+        ...(config?.encryption !== undefined ? { encryption: config.encryption } : {}),
     });
 }
 export { createSqliteStateStore, SqliteStateStore } from './sqlite.js';
 export { migrateJsonToSqlite } from './migrate.js';
+export { createMemoryCipher, isEncryptedMemory } from './encryption.js';
+export { cosineSimilarity, createHnswIndex, deserializeHnswIndex } from './hnsw.js';
+export { VectorStore } from './vector-store.js';
+export { parseScopedNamespace, projectScopeId, scopedNamespace } from './namespaces.js';
+export { DEFAULT_SCRATCHPAD_LEASE_MS, isLiveClaim, SCRATCHPAD_CONFLICT_CODE } from './scratchpad.js';
+export { decayedImportance, isMemoryExpired, selectMemoryEvictions } from './retention.js';
 function requireSession(data, sessionId) {
     const session = data.sessions.find((entry) => entry.sessionId === sessionId);
     if (session === undefined) {
@@ -454,6 +702,11 @@ function requireSession(data, sessionId) {
     }
     return session;
 }
+function replaceScratchpadTask(data, task) {
+    const index = data.scratchpad.findIndex((entry) => entry.sessionId === task.sessionId && entry.taskId === task.taskId);
+    data.scratchpad[index] = task;
+    return task;
+}
 function ensureActiveSession(session) {
     if (session.status !== 'active') {
         throw new Error(`Session "${session.sessionId}" is not active`);
@@ -522,6 +775,36 @@ function scoreSemanticSimilarity(queryFreq, itemFreq) {
     }
     return Number((dot / (Math.sqrt(queryMagnitude) * Math.sqrt(itemMagnitude))).toFixed(4));
 }
+// Copies the entries of `sources` into `target`; `keep` leaves keys the target holds, `newer` only replaces older entries.
+function transferMemory(data, sources, target, mode) {
+    assertNamespaceTransfer(sources, target);
+    let copied = 0;
+    let skipped = 0;
+    for (const entry of data.memory.filter((item) => sources.includes(item.namespace ?? 'default'))) {
+        if (placeMemory(data, { ...entry, namespace: target === 'default' ? undefined : target }, mode)) {
+            copied += 1;
+        }
+        else {
+            skipped += 1;
+        }
+    }
+    return { copied, skipped };
+}
+// Adds or replaces `entry` as the mode allows; false when the entry already there was kept.
+function placeMemory(data, entry, mode) {
+    const index = data.memory.findIndex((item) => item.key === entry.key && item.namespace === entry.namespace);
+    const existing = data.memory[index];
+    if (existing !== undefined && (mode === 'keep' || (mode === 'newer' && existing.updatedAt >= entry.updatedAt))) {
+        return false;
+    }
+    if (existing !== undefined) {
+        data.memory[index] = entry;
+    }
+    else {
+        data.memory.push(entry);
+    }
+    return true;
+}
 function sortRecord(record) {
     return Object.fromEntries(Object.entries(record)
         .sort(([left], [right]) => left.localeCompare(right))
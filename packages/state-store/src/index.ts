@@ -1,15 +1,75 @@
 import { randomUUID } from 'node:crypto';
 import { mkdir, readFile, rename, rm, stat, writeFile } from 'node:fs/promises';
 import { dirname, join } from 'node:path';
+import { isEncryptedMemory, openMemoryText, sealMemoryText, type MemoryCipher } from './encryption.js';
+import { assertFact, matchesFact, type Fact, type FactInput, type FactQuery } from './facts.js';
+import { cosineSimilarity } from './hnsw.js';
+import { assertNamespaceTransfer } from './namespaces.js';
+import { assertMemorySource, type MemorySource } from './provenance.js';
+import { assertMemoryRetention, isMemoryExpired, selectMemoryEvictions, type MemoryEviction, type MemoryRetentionPolicy } from './retention.js';
+import {
+  applyScratchpadPost,
+  applyScratchpadRelease,
+  createScratchpadTasks,
+  selectScratchpadClaim,
+  type ScratchpadClaim,
+  type ScratchpadPost,
+  type ScratchpadTask,
+  type ScratchpadTaskInput,
+} from './scratchpad.js';
 import { createSqliteStateStore } from './sqlite.js';
+import { matchesUsage, normalizeUsage, type UsageInput, type UsageQuery, type UsageRecord } from './usage.js';
 
 export interface MemoryEntry {
   key: string;
   namespace?: string;
   value: unknown;
+  /** Vector embedding of the entry, replaced or dropped whenever the entry is stored again. */
+  embedding?: number[];
+  /** Past this time the entry is no longer returned, and the next prune deletes it. */
+  expiresAt?: string;
+  /** How much the entry matters, from 0 to 1; decays while the entry goes unused. */
+  importance?: number;
+  /** When `getMemory` last returned the entry. */
+  accessedAt?: string;
+  /** The file span, session, agent and provider the entry came from. */
+  source?: MemorySource;
   updatedAt: string;
 }
 
+export interface MemoryVectorMatch extends MemoryEntry {
+  /** Cosine similarity of the entry's embedding to the query vector. */
+  score: number;
+}
+
+export interface MemoryNamespaceStats {
+  /** The stored name, `default` for entries stored without one. */
+  namespace: string;
+  entries: number;
+  /** Entries that carry an embedding. */
+  embedded: number;
+  lastUpdatedAt?: string;
+}
+
+export interface MemoryInput {
+  key: string;
+  namespace?: string;
+  value: unknown;
+  embedding?: number[];
+  expiresAt?: string;
+  importance?: number;
+  source?: MemorySource;
+}
+
+/** What happens to an entry whose key the target already holds: `newer` replaces it only with a more recently updated one. */
+export type MemoryConflictMode = 'keep' | 'overwrite' | 'newer';
+
+export interface MemoryNamespaceTransfer {
+  copied: number;
+  /** Entries not copied because the target held the key already, or a newer entry under it when merging. */
+  skipped: number;
+}
+
 export interface PolicyEntry {
   policyId: string;
   name: string;
@@ -92,11 +152,24 @@ export interface SessionEntry {
 }
 
 export interface StateStore {
-  storeMemory(entry: { key: string; namespace?: string; value: unknown }): Promise<MemoryEntry>;
+  storeMemory(entry: MemoryInput): Promise<MemoryEntry>;
+  /** Returns the entry unless it expired, recording the access. */
   getMemory(key: string, namespace?: string): Promise<MemoryEntry | undefined>;
   searchMemory(query: string, namespace?: string): Promise<MemoryEntry[]>;
   deleteMemory(key: string, namespace?: string): Promise<boolean>;
   listMemory(namespace?: string): Promise<MemoryEntry[]>;
+  searchMemoryByVector(vector: number[], options?: { namespace?: string; k?: number; minScore?: number }): Promise<MemoryVectorMatch[]>;
+  listMemoryNamespaces(): Promise<MemoryNamespaceStats[]>;
+  /** Copies every entry of `source` into `target`, keeping keys the target already holds unless `overwrite`. */
+  copyMemoryNamespace(source: string, target: string, options?: { overwrite?: boolean }): Promise<MemoryNamespaceTransfer>;
+  /** Moves the entries of `sources` into `target`, the most recently updated entry winning per key, and deletes the sources. */
+  mergeMemoryNamespaces(sources: string[], target: string): Promise<MemoryNamespaceTransfer & { deleted: number }>;
+  /** Writes entries as given, timestamps and embeddings included, into their namespaces; `newer` by default. */
+  importMemory(entries: MemoryEntry[], options?: { mode?: MemoryConflictMode }): Promise<MemoryNamespaceTransfer>;
+  /** Deletes the entries the policy evicts, or only lists them with `dryRun`. */
+  pruneMemory(policy: MemoryRetentionPolicy, options?: { now?: Date; dryRun?: boolean }): Promise<MemoryEviction[]>;
+  /** Encrypts the entries and semantic items written before the store had a key; fails on a store without one. */
+  encryptMemory(): Promise<{ encrypted: number }>;
   registerPolicy(entry: { policyId: string; name: string; enabled?: boolean; metadata?: Record<string, unknown> }): Promise<PolicyEntry>;
   listPolicies(): Promise<PolicyEntry[]>;
   registerAgent(entry: { agentId: string; name: string; capabilities?: string[]; metadata?: Record<string, unknown> }): Promise<AgentEntry>;
@@ -111,6 +184,11 @@ export interface StateStore {
   deleteSemantic(key: string, namespace?: string): Promise<boolean>;
   clearSemantic(namespace: string): Promise<number>;
   semanticStats(namespace?: string): Promise<SemanticNamespaceStats[]>;
+  /** Stores a fact; storing the same triple in the same namespace again updates its source and confidence. */
+  storeFact(input: FactInput): Promise<Fact>;
+  /** Facts matching every given field exactly, most recently updated first. */
+  queryFacts(query?: FactQuery): Promise<Fact[]>;
+  deleteFact(factId: string): Promise<boolean>;
   submitFeedback(entry: {
     feedbackId?: string;
     selectedAgent: string;
@@ -125,6 +203,9 @@ export interface StateStore {
     metadata?: Record<string, unknown>;
   }): Promise<FeedbackEntry>;
   listFeedback(options?: { agentId?: string; limit?: number; since?: string }): Promise<FeedbackEntry[]>;
+  recordUsage(entry: UsageInput): Promise<UsageRecord>;
+  /** Usage records matching the query, most recent first. */
+  listUsage(query?: UsageQuery): Promise<UsageRecord[]>;
   createSession(entry: { sessionId?: string; task: string; initiator: string; workspace?: string; metadata?: Record<string, unknown> }): Promise<SessionEntry>;
   getSession(sessionId: string): Promise<SessionEntry | undefined>;
   listSessions(): Promise<SessionEntry[]>;
@@ -132,7 +213,23 @@ export interface StateStore {
   leaveSession(sessionId: string, agentId: string): Promise<SessionEntry>;
   completeSession(sessionId: string, summary?: string): Promise<SessionEntry>;
   failSession(sessionId: string, message: string): Promise<SessionEntry>;
+  /** Makes a completed or failed session active again, keeping its participants and summary. */
+  reopenSession(sessionId: string): Promise<SessionEntry>;
   closeStuckSessions(maxAgeMs?: number): Promise<SessionEntry[]>;
+  /** Adds tasks to a session's scratchpad; fails for an unknown session or a task id it has already. */
+  addScratchpadTasks(sessionId: string, tasks: ScratchpadTaskInput[]): Promise<ScratchpadTask[]>;
+  /** The tasks of a session's scratchpad, oldest first. */
+  listScratchpad(sessionId: string): Promise<ScratchpadTask[]>;
+  /**
+   * Claims a task of a session's scratchpad, the named one or the next free one; undefined when none
+   * is free. The scratchpad is read and written under one lock, so of two processes claiming the same
+   * task one fails with `SCRATCHPAD_CONFLICT`.
+   */
+  claimScratchpadTask(sessionId: string, claim: ScratchpadClaim): Promise<ScratchpadTask | undefined>;
+  /** Posts the result of a claimed task, marking it done or failed. */
+  postScratchpadResult(sessionId: string, post: ScratchpadPost): Promise<ScratchpadTask>;
+  /** Makes a claimed task open again; without a claimant, whoever holds it. */
+  releaseScratchpadTask(sessionId: string, taskId: string, claimant?: string): Promise<ScratchpadTask>;
 }
 
 interface StateStoreFile {
@@ -140,8 +237,11 @@ interface StateStoreFile {
   policies: PolicyEntry[];
   agents: AgentEntry[];
   semantic: SemanticEntry[];
+  facts: Fact[];
   feedback: FeedbackEntry[];
+  usage: UsageRecord[];
   sessions: SessionEntry[];
+  scratchpad: ScratchpadTask[];
 }
 
 export interface FileStateStoreConfig {
@@ -149,6 +249,8 @@ export interface FileStateStoreConfig {
   storageFile?: string;
   /** Storage backend. Defaults to 'sqlite'. Use 'json' to keep the legacy file-based store. */
   backend?: 'sqlite' | 'json';
+  /** Encrypts memory values and embeddings, and semantic items' content, at rest; keys, namespaces, tags and facts stay readable. */
+  encryption?: MemoryCipher;
 }
 
 const DEFAULT_STATE_STORE_FILE = join('.automatosx', 'runtime', 'state.json');
@@ -156,20 +258,29 @@ const stateStoreQueues = new Map<string, Promise<void>>();
 const LOCK_WAIT_TIMEOUT_MS = 5_000;
 const LOCK_STALE_AFTER_MS = 60_000;
 const LOCK_RETRY_DELAY_MS = 10;
+const DEFAULT_VECTOR_SEARCH_K = 10;
 
 export class FileStateStore implements StateStore {
   private readonly storageFile: string;
+  private readonly cipher: MemoryCipher | undefined;
 
   constructor(config: FileStateStoreConfig = {}) {
     this.storageFile = config.storageFile ?? join(config.basePath ?? process.cwd(), DEFAULT_STATE_STORE_FILE);
+    this.cipher = config.encryption;
   }
 
-  async storeMemory(entry: { key: string; namespace?: string; value: unknown }): Promise<MemoryEntry> {
+  async storeMemory(entry: MemoryInput): Promise<MemoryEntry> {
+    assertMemoryRetention(entry);
+    assertMemorySource(entry.source);
     return this.withMutation(async (data) => {
       const stored: MemoryEntry = {
         key: entry.key,
         namespace: entry.namespace,
         value: entry.value,
+        ...(entry.embedding !== undefined ? { embedding: entry.embedding } : {}),
+        ...(entry.expiresAt !== undefined ? { expiresAt: new Date(entry.expiresAt).toISOString() } : {}),
+        ...(entry.importance !== undefined ? { importance: entry.importance } : {}),
+        ...(entry.source !== undefined ? { source: entry.source } : {}),
         updatedAt: new Date().toISOString(),
       };
       const index = data.memory.findIndex((item) => item.key === stored.key && item.namespace === stored.namespace);
@@ -184,19 +295,29 @@ export class FileStateStore implements StateStore {
 
   async listMemory(namespace?: string): Promise<MemoryEntry[]> {
     const data = await this.readConsistentData();
-    return data.memory.filter((entry) => namespace === undefined || entry.namespace === namespace);
+    return data.memory.filter((entry) => (namespace === undefined || entry.namespace === namespace) && !isMemoryExpired(entry));
   }
 
   async getMemory(key: string, namespace?: string): Promise<MemoryEntry | undefined> {
     const data = await this.readConsistentData();
-    return data.memory.find((entry) => entry.key === key && entry.namespace === namespace);
+    if (!data.memory.some((entry) => entry.key === key && entry.namespace === namespace && !isMemoryExpired(entry))) {
+      return undefined;
+    }
+    return this.withMutation(async (latest) => {
+      const entry = latest.memory.find((item) => item.key === key && item.namespace === namespace);
+      if (entry === undefined || isMemoryExpired(entry)) {
+        return undefined;
+      }
+      entry.accessedAt = new Date().toISOString();
+      return entry;
+    });
   }
 
   async searchMemory(query: string, namespace?: string): Promise<MemoryEntry[]> {
     const normalized = query.trim().toLowerCase();
     const data = await this.readConsistentData();
     const matches = data.memory.filter((entry) => {
-      if (namespace !== undefined && entry.namespace !== namespace) {
+      if ((namespace !== undefined && entry.namespace !== namespace) || isMemoryExpired(entry)) {
         return false;
       }
       if (normalized === '') {
@@ -220,6 +341,95 @@ export class FileStateStore implements StateStore {
     });
   }
 
+  async searchMemoryByVector(vector: number[], options: { namespace?: string; k?: number; minScore?: number } = {}): Promise<MemoryVectorMatch[]> {
+    const data = await this.readConsistentData();
+    return data.memory
+      .filter((entry) => entry.embedding?.length === vector.length && (options.namespace === undefined || entry.namespace === options.namespace) && !isMemoryExpired(entry))
+      .map((entry) => ({ ...entry, score: cosineSimilarity(vector, entry.embedding!) }))
+      .filter((entry) => entry.score >= (options.minScore ?? -1))
+      .sort((left, right) => right.score - left.score)
+      .slice(0, options.k ?? DEFAULT_VECTOR_SEARCH_K);
+  }
+
+  async listMemoryNamespaces(): Promise<MemoryNamespaceStats[]> {
+    const data = await this.readConsistentData();
+    const grouped = new Map<string, MemoryNamespaceStats>();
+    for (const entry of data.memory) {
+      const namespace = entry.namespace ?? 'default';
+      const stats = grouped.get(namespace) ?? { namespace, entries: 0, embedded: 0 };
+      stats.entries += 1;
+      stats.embedded += entry.embedding !== undefined ? 1 : 0;
+      if (stats.lastUpdatedAt === undefined || entry.updatedAt > stats.lastUpdatedAt) {
+        stats.lastUpdatedAt = entry.updatedAt;
+      }
+      grouped.set(namespace, stats);
+    }
+    return [...grouped.values()].sort((left, right) => left.namespace.localeCompare(right.namespace));
+  }
+
+  async copyMemoryNamespace(source: string, target: string, options: { overwrite?: boolean } = {}): Promise<MemoryNamespaceTransfer> {
+    return this.withMutation(async (data) => transferMemory(data, [source], target, options.overwrite === true ? 'overwrite' : 'keep'));
+  }
+
+  async mergeMemoryNamespaces(sources: string[], target: string): Promise<MemoryNamespaceTransfer & { deleted: number }> {
+    return this.withMutation(async (data) => {
+      const transfer = transferMemory(data, sources, target, 'newer');
+      const originalLength = data.memory.length;
+      data.memory = data.memory.filter((entry) => !sources.includes(entry.namespace ?? 'default'));
+      return { ...transfer, deleted: originalLength - data.memory.length };
+    });
+  }
+
+  async importMemory(entries: MemoryEntry[], options: { mode?: MemoryConflictMode } = {}): Promise<MemoryNamespaceTransfer> {
+    for (const entry of entries) {
+      assertMemoryRetention(entry);
+      assertMemorySource(entry.source);
+    }
+    return this.withMutation(async (data) => {
+      let copied = 0;
+      let skipped = 0;
+      for (const entry of entries) {
+        const namespace = entry.namespace === 'default' ? undefined : entry.namespace;
+        if (placeMemory(data, { ...entry, namespace }, options.mode ?? 'newer')) {
+          copied += 1;
+        } else {
+          skipped += 1;
+        }
+      }
+      return { copied, skipped };
+    });
+  }
+
+  async pruneMemory(policy: MemoryRetentionPolicy, options: { now?: Date; dryRun?: boolean } = {}): Promise<MemoryEviction[]> {
+    const select = (data: StateStoreFile) => selectMemoryEvictions(data.memory.map((entry) => ({
+      ...entry,
+      bytes: Buffer.byteLength(safeStringify(entry.value)) + (entry.embedding !== undefined ? Buffer.byteLength(JSON.stringify(entry.embedding)) : 0),
+    })), policy, options.now);
+    if (options.dryRun === true) {
+      return select(await this.readConsistentData());
+    }
+    return this.withMutation(async (data) => {
+      const evictions = select(data);
+      const evicted = new Set(evictions.map((eviction) => `${eviction.namespace ?? ''}\0${eviction.key}`));
+      data.memory = data.memory.filter((entry) => !evicted.has(`${entry.namespace ?? ''}\0${entry.key}`));
+      return evictions;
+    });
+  }
+
+  async encryptMemory(): Promise<{ encrypted: number }> {
+    if (this.cipher === undefined) {
+      throw new Error('This store has no memory encryption key.');
+    }
+    // Every write seals all of memory, so rewriting the file is enough; this counts what was still plaintext.
+    return this.withMutation(async () => {
+      const raw = JSON.parse(await readFile(this.storageFile, 'utf8').catch(() => '{}')) as { memory?: Array<{ value?: unknown }>; semantic?: Array<{ content?: unknown }> };
+      return {
+        encrypted: (raw.memory ?? []).filter((entry) => !isEncryptedMemory(entry.value)).length
+          + (raw.semantic ?? []).filter((entry) => !isEncryptedMemory(entry.content)).length,
+      };
+    });
+  }
+
   async registerPolicy(entry: { policyId: string; name: string; enabled?: boolean; metadata?: Record<string, unknown> }): Promise<PolicyEntry> {
     return this.withMutation(async (data) => {
       const stored: PolicyEntry = {
@@ -418,6 +628,43 @@ export class FileStateStore implements StateStore {
       .sort((left, right) => left.namespace.localeCompare(right.namespace));
   }
 
+  async storeFact(input: FactInput): Promise<Fact> {
+    assertFact(input);
+    return this.withMutation(async (data) => {
+      const now = new Date().toISOString();
+      const existing = data.facts.find((fact) => matchesFact(fact, { ...input, namespace: input.namespace ?? 'default' }));
+      const stored: Fact = {
+        factId: existing?.factId ?? randomUUID(),
+        subject: input.subject,
+        predicate: input.predicate,
+        object: input.object,
+        ...(input.namespace !== undefined ? { namespace: input.namespace } : {}),
+        ...(input.sourceSpan !== undefined ? { sourceSpan: input.sourceSpan } : {}),
+        ...(input.confidence !== undefined ? { confidence: input.confidence } : {}),
+        createdAt: existing?.createdAt ?? now,
+        updatedAt: now,
+      };
+      data.facts = [...data.facts.filter((fact) => fact !== existing), stored];
+      return stored;
+    });
+  }
+
+  async queryFacts(query: FactQuery = {}): Promise<Fact[]> {
+    const data = await this.readConsistentData();
+    const matches = data.facts
+      .filter((fact) => matchesFact(fact, query))
+      .sort((left, right) => right.updatedAt.localeCompare(left.updatedAt));
+    return query.limit !== undefined ? matches.slice(0, Math.max(0, query.limit)) : matches;
+  }
+
+  async deleteFact(factId: string): Promise<boolean> {
+    return this.withMutation(async (data) => {
+      const originalLength = data.facts.length;
+      data.facts = data.facts.filter((fact) => fact.factId !== factId);
+      return data.facts.length !== originalLength;
+    });
+  }
+
   async submitFeedback(entry: {
     feedbackId?: string;
     selectedAgent: string;
@@ -476,6 +723,22 @@ export class FileStateStore implements StateStore {
     return filtered.slice(0, Math.max(0, options.limit));
   }
 
+  async recordUsage(entry: UsageInput): Promise<UsageRecord> {
+    const stored = normalizeUsage(entry);
+    return this.withMutation(async (data) => {
+      data.usage.push(stored);
+      return stored;
+    });
+  }
+
+  async listUsage(query: UsageQuery = {}): Promise<UsageRecord[]> {
+    const data = await this.readConsistentData();
+    const filtered = data.usage
+      .filter((record) => matchesUsage(record, query))
+      .sort((left, right) => right.recordedAt.localeCompare(left.recordedAt));
+    return query.limit === undefined ? filtered : filtered.slice(0, Math.max(0, query.limit));
+  }
+
   async createSession(entry: { sessionId?: string; task: string; initiator: string; workspace?: string; metadata?: Record<string, unknown> }): Promise<SessionEntry> {
     return this.withMutation(async (data) => {
       const now = new Date().toISOString();
@@ -577,6 +840,16 @@ export class FileStateStore implements StateStore {
     });
   }
 
+  async reopenSession(sessionId: string): Promise<SessionEntry> {
+    return this.withMutation(async (data) => {
+      const session = requireSession(data, sessionId);
+      session.status = 'active';
+      session.error = undefined;
+      session.updatedAt = new Date().toISOString();
+      return session;
+    });
+  }
+
   async closeStuckSessions(maxAgeMs = 86_400_000): Promise<SessionEntry[]> {
     return this.withMutation(async (data) => {
       const threshold = Date.now() - maxAgeMs;
@@ -599,6 +872,42 @@ export class FileStateStore implements StateStore {
     });
   }
 
+  async addScratchpadTasks(sessionId: string, tasks: ScratchpadTaskInput[]): Promise<ScratchpadTask[]> {
+    return this.withMutation(async (data) => {
+      requireSession(data, sessionId);
+      const created = createScratchpadTasks(sessionId, data.scratchpad.filter((task) => task.sessionId === sessionId), tasks);
+      data.scratchpad.push(...created);
+      return created;
+    });
+  }
+
+  async listScratchpad(sessionId: string): Promise<ScratchpadTask[]> {
+    const data = await this.readConsistentData();
+    return data.scratchpad.filter((task) => task.sessionId === sessionId);
+  }
+
+  async claimScratchpadTask(sessionId: string, claim: ScratchpadClaim): Promise<ScratchpadTask | undefined> {
+    return this.withMutation(async (data) => {
+      requireSession(data, sessionId);
+      const claimed = selectScratchpadClaim(sessionId, data.scratchpad.filter((task) => task.sessionId === sessionId), claim);
+      return claimed === undefined ? undefined : replaceScratchpadTask(data, claimed);
+    });
+  }
+
+  async postScratchpadResult(sessionId: string, post: ScratchpadPost): Promise<ScratchpadTask> {
+    return this.withMutation(async (data) => replaceScratchpadTask(
+      data,
+      applyScratchpadPost(sessionId, data.scratchpad.filter((task) => task.sessionId === sessionId), post),
+    ));
+  }
+
+  async releaseScratchpadTask(sessionId: string, taskId: string, claimant?: string): Promise<ScratchpadTask> {
+    return this.withMutation(async (data) => replaceScratchpadTask(
+      data,
+      applyScratchpadRelease(sessionId, data.scratchpad.filter((task) => task.sessionId === sessionId), taskId, claimant),
+    ));
+  }
+
   private async readConsistentData(): Promise<StateStoreFile> {
     await waitForQueue(this.storageFile, stateStoreQueues);
     return this.readData();
@@ -614,35 +923,88 @@ export class FileStateStore implements StateStore {
   }
 
   private async readData(): Promise<StateStoreFile> {
+    let parsed: Partial<StateStoreFile>;
     try {
       const raw = await readFile(this.storageFile, 'utf8');
-      const parsed = JSON.parse(raw) as Partial<StateStoreFile>;
-      return {
-        memory: Array.isArray(parsed.memory) ? parsed.memory : [],
-        policies: Array.isArray(parsed.policies) ? parsed.policies : [],
-        agents: Array.isArray(parsed.agents) ? parsed.agents : [],
-        semantic: Array.isArray(parsed.semantic) ? parsed.semantic : [],
-        feedback: Array.isArray(parsed.feedback) ? parsed.feedback : [],
-        sessions: Array.isArray(parsed.sessions) ? parsed.sessions : [],
-      };
+      parsed = JSON.parse(raw) as Partial<StateStoreFile>;
     } catch {
       return {
         memory: [],
         policies: [],
         agents: [],
         semantic: [],
+        facts: [],
         feedback: [],
+        usage: [],
         sessions: [],
+        scratchpad: [],
       };
     }
+    // Decrypted outside the try: a wrong key must fail loudly, not read as an empty store.
+    return {
+      memory: Array.isArray(parsed.memory) ? parsed.memory.map((entry) => this.openMemory(entry)) : [],
+      policies: Array.isArray(parsed.policies) ? parsed.policies : [],
+      agents: Array.isArray(parsed.agents) ? parsed.agents : [],
+      semantic: Array.isArray(parsed.semantic) ? parsed.semantic.map((entry) => this.openSemantic(entry)) : [],
+      facts: Array.isArray(parsed.facts) ? parsed.facts : [],
+      feedback: Array.isArray(parsed.feedback) ? parsed.feedback : [],
+      usage: Array.isArray(parsed.usage) ? parsed.usage : [],
+      sessions: Array.isArray(parsed.sessions) ? parsed.sessions : [],
+      scratchpad: Array.isArray(parsed.scratchpad) ? parsed.scratchpad : [],
+    };
   }
 
   private async writeData(data: StateStoreFile): Promise<void> {
     await mkdir(dirname(this.storageFile), { recursive: true });
     const tempFile = `${this.storageFile}.${process.pid}.${randomUUID()}.tmp`;
-    await writeFile(tempFile, `${JSON.stringify(data, null, 2)}\n`, 'utf8');
+    const stored = this.cipher !== undefined
+      ? { ...data, memory: data.memory.map((entry) => this.sealMemory(entry)), semantic: data.semantic.map((entry) => this.sealSemantic(entry)) }
+      : data;
+    await writeFile(tempFile, `${JSON.stringify(stored, null, 2)}\n`, 'utf8');
     await rename(tempFile, this.storageFile);
   }
+
+  // Encrypted entries keep their shape, with the value and embedding replaced by ciphertext strings.
+  private sealMemory(entry: MemoryEntry): Record<string, unknown> {
+    return {
+      ...entry,
+      value: sealMemoryText(JSON.stringify(entry.value), this.cipher),
+      ...(entry.embedding !== undefined ? { embedding: sealMemoryText(JSON.stringify(entry.embedding), this.cipher) } : {}),
+    };
+  }
+
+  // Semantic items likewise, with the content and the token frequencies it is searched by.
+  private sealSemantic(entry: SemanticEntry): Record<string, unknown> {
+    return {
+      ...entry,
+      content: sealMemoryText(entry.content, this.cipher),
+      tokenFreq: sealMemoryText(JSON.stringify(entry.tokenFreq), this.cipher),
+    };
+  }
+
+  private openSemantic(entry: SemanticEntry): SemanticEntry {
+    const { content, tokenFreq } = entry as { content: unknown; tokenFreq: unknown };
+    if (!isEncryptedMemory(content) && !isEncryptedMemory(tokenFreq)) {
+      return entry;
+    }
+    return {
+      ...entry,
+      content: isEncryptedMemory(content) ? openMemoryText(content, this.cipher) : entry.content,
+      tokenFreq: isEncryptedMemory(tokenFreq) ? JSON.parse(openMemoryText(tokenFreq, this.cipher)) as Record<string, number> : entry.tokenFreq,
+    };
+  }
+
+  private openMemory(entry: MemoryEntry): MemoryEntry {
+    const { value, embedding } = entry as { value: unknown; embedding?: unknown };
+    if (!isEncryptedMemory(value) && !isEncryptedMemory(embedding)) {
+      return entry;
+    }
+    return {
+      ...entry,
+      value: isEncryptedMemory(value) ? JSON.parse(openMemoryText(value, this.cipher)) as unknown : value,
+      ...(isEncryptedMemory(embedding) ? { embedding: JSON.parse(openMemoryText(embedding, this.cipher)) as number[] } : {}),
+    };
+  }
 }
 
 export function createStateStore(config?: FileStateStoreConfig): StateStore {
@@ -652,12 +1014,28 @@ export function createStateStore(config?: FileStateStoreConfig): StateStore {
   return createSqliteStateStore({
     basePath: config?.basePath,
     dbFile: config?.storageFile,
+    ...(config?.encryption !== undefined ? { encryption: config.encryption } : {}),
   });
 }
 
 export { createSqliteStateStore, SqliteStateStore } from './sqlite.js';
 export type { SqliteStateStoreConfig } from './sqlite.js';
 export { migrateJsonToSqlite } from './migrate.js';
+export { createMemoryCipher, isEncryptedMemory } from './encryption.js';
+export type { MemoryCipher } from './encryption.js';
+export { cosineSimilarity, createHnswIndex, deserializeHnswIndex } from './hnsw.js';
+export type { HnswIndex, HnswOptions, VectorMatch } from './hnsw.js';
+export { VectorStore } from './vector-store.js';
+export { parseScopedNamespace, projectScopeId, scopedNamespace } from './namespaces.js';
+export type { MemoryScope, ParsedMemoryNamespace } from './namespaces.js';
+export type { Fact, FactInput, FactObject, FactQuery, SourceSpan } from './facts.js';
+export type { UsageInput, UsageQuery, UsageRecord } from './usage.js';
+export type { MemorySource } from './provenance.js';
+export { DEFAULT_SCRATCHPAD_LEASE_MS, isLiveClaim, SCRATCHPAD_CONFLICT_CODE } from './scratchpad.js';
+export type { ScratchpadClaim, ScratchpadPost, ScratchpadTask, ScratchpadTaskInput, ScratchpadTaskStatus } from './scratchpad.js';
+export { decayedImportance, isMemoryExpired, selectMemoryEvictions } from './retention.js';
+export type { MemoryEviction, MemoryEvictionReason, MemoryRetentionPolicy, RetainedMemory } from './retention.js';
+export type { VectorSource } from './vector-store.js';
 export type { MigrateJsonToSqliteOptions, MigrationResult } from './migrate.js';
 
 function requireSession(data: StateStoreFile, sessionId: string): SessionEntry {
@@ -668,6 +1046,12 @@ function requireSession(data: StateStoreFile, sessionId: string): SessionEntry {
   return session;
 }
 
+function replaceScratchpadTask(data: StateStoreFile, task: ScratchpadTask): ScratchpadTask {
+  const index = data.scratchpad.findIndex((entry) => entry.sessionId === task.sessionId && entry.taskId === task.taskId);
+  data.scratchpad[index] = task;
+  return task;
+}
+
 function ensureActiveSession(session: SessionEntry): void {
   if (session.status !== 'active') {
     throw new Error(`Session "${session.sessionId}" is not active`);
@@ -753,6 +1137,36 @@ function scoreSemanticSimilarity(queryFreq: Record<string, number>, itemFreq: Re
   return Number((dot / (Math.sqrt(queryMagnitude) * Math.sqrt(itemMagnitude))).toFixed(4));
 }
 
+// Copies the entries of `sources` into `target`; `keep` leaves keys the target holds, `newer` only replaces older entries.
+function transferMemory(data: StateStoreFile, sources: string[], target: string, mode: MemoryConflictMode): MemoryNamespaceTransfer {
+  assertNamespaceTransfer(sources, target);
+  let copied = 0;
+  let skipped = 0;
+  for (const entry of data.memory.filter((item) => sources.includes(item.namespace ?? 'default'))) {
+    if (placeMemory(data, { ...entry, namespace: target === 'default' ? undefined : target }, mode)) {
+      copied += 1;
+    } else {
+      skipped += 1;
+    }
+  }
+  return { copied, skipped };
+}
+
+// Adds or replaces `entry` as the mode allows; false when the entry already there was kept.
+function placeMemory(data: StateStoreFile, entry: MemoryEntry, mode: MemoryConflictMode): boolean {
+  const index = data.memory.findIndex((item) => item.key === entry.key && item.namespace === entry.namespace);
+  const existing = data.memory[index];
+  if (existing !== undefined && (mode === 'keep' || (mode === 'newer' && existing.updatedAt >= entry.updatedAt))) {
+    return false;
+  }
+  if (existing !== undefined) {
+    data.memory[index] = entry;
+  } else {
+    data.memory.push(entry);
+  }
+  return true;
+}
+
 function sortRecord(record: Record<string, unknown>): Record<string, unknown> {
   return Object.fromEntries(
     Object.entries(record)
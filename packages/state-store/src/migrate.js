@@ -17,31 +17,37 @@ export async function migrateJsonToSqlite(options = {}) {
         raw = await readFile(jsonFile, 'utf8');
     }
     catch {
-        return { memory: 0, policies: 0, agents: 0, semantic: 0, feedback: 0, sessions: 0, skipped: true, reason: 'No state.json found — nothing to migrate.' };
+        return { memory: 0, policies: 0, agents: 0, semantic: 0, facts: 0, feedback: 0, usage: 0, sessions: 0, scratchpad: 0, skipped: true, reason: 'No state.json found — nothing to migrate.' };
     }
     let parsed;
     try {
         parsed = JSON.parse(raw);
     }
     catch {
-        return { memory: 0, policies: 0, agents: 0, semantic: 0, feedback: 0, sessions: 0, skipped: true, reason: 'state.json is not valid JSON — skipping migration.' };
+        return { memory: 0, policies: 0, agents: 0, semantic: 0, facts: 0, feedback: 0, usage: 0, sessions: 0, scratchpad: 0, skipped: true, reason: 'state.json is not valid JSON — skipping migration.' };
     }
     const memory = Array.isArray(parsed['memory']) ? parsed['memory'] : [];
     const policies = Array.isArray(parsed['policies']) ? parsed['policies'] : [];
     const agents = Array.isArray(parsed['agents']) ? parsed['agents'] : [];
     const semantic = Array.isArray(parsed['semantic']) ? parsed['semantic'] : [];
+    const facts = Array.isArray(parsed['facts']) ? parsed['facts'] : [];
     const feedback = Array.isArray(parsed['feedback']) ? parsed['feedback'] : [];
+    const usage = Array.isArray(parsed['usage']) ? parsed['usage'] : [];
     const sessions = Array.isArray(parsed['sessions']) ? parsed['sessions'] : [];
+    const scratchpad = Array.isArray(parsed['scratchpad']) ? parsed['scratchpad'] : [];
     const store = new SqliteStateStore({ basePath, dbFile: options.dbFile });
-    await store.importFromJson({ memory, policies, agents, semantic, feedback, sessions });
+    await store.importFromJson({ memory, policies, agents, semantic, facts, feedback, usage, sessions, scratchpad });
     store.close();
     return {
         memory: memory.length,
         policies: policies.length,
         agents: agents.length,
         semantic: semantic.length,
+        facts: facts.length,
         feedback: feedback.length,
+        usage: usage.length,
         sessions: sessions.length,
+        scratchpad: scratchpad.length,
         skipped: false,
     };
 }
@@ -23,8 +23,11 @@ export interface MigrationResult {
   policies: number;
   agents: number;
   semantic: number;
+  facts: number;
   feedback: number;
+  usage: number;
   sessions: number;
+  scratchpad: number;
   skipped: boolean;
   reason?: string;
 }
@@ -39,25 +42,28 @@ export async function migrateJsonToSqlite(options: MigrateJsonToSqliteOptions =
   try {
     raw = await readFile(jsonFile, 'utf8');
   } catch {
-    return { memory: 0, policies: 0, agents: 0, semantic: 0, feedback: 0, sessions: 0, skipped: true, reason: 'No state.json found — nothing to migrate.' };
+    return { memory: 0, policies: 0, agents: 0, semantic: 0, facts: 0, feedback: 0, usage: 0, sessions: 0, scratchpad: 0, skipped: true, reason: 'No state.json found — nothing to migrate.' };
   }
 
   let parsed: Record<string, unknown>;
   try {
     parsed = JSON.parse(raw) as Record<string, unknown>;
   } catch {
-    return { memory: 0, policies: 0, agents: 0, semantic: 0, feedback: 0, sessions: 0, skipped: true, reason: 'state.json is not valid JSON — skipping migration.' };
+    return { memory: 0, policies: 0, agents: 0, semantic: 0, facts: 0, feedback: 0, usage: 0, sessions: 0, scratchpad: 0, skipped: true, reason: 'state.json is not valid JSON — skipping migration.' };
   }
 
   const memory   = Array.isArray(parsed['memory'])   ? parsed['memory']   as never[]   : [];
   const policies = Array.isArray(parsed['policies']) ? parsed['policies'] as never[]   : [];
   const agents   = Array.isArray(parsed['agents'])   ? parsed['agents']   as never[]   : [];
   const semantic = Array.isArray(parsed['semantic']) ? parsed['semantic'] as never[]   : [];
+  const facts    = Array.isArray(parsed['facts'])    ? parsed['facts']    as never[]   : [];
   const feedback = Array.isArray(parsed['feedback']) ? parsed['feedback'] as never[]   : [];
+  const usage    = Array.isArray(parsed['usage'])    ? parsed['usage']    as never[]   : [];
   const sessions = Array.isArray(parsed['sessions']) ? parsed['sessions'] as never[]   : [];
+  const scratchpad = Array.isArray(parsed['scratchpad']) ? parsed['scratchpad'] as never[] : [];
 
   const store = new SqliteStateStore({ basePath, dbFile: options.dbFile });
-  await store.importFromJson({ memory, policies, agents, semantic, feedback, sessions });
+  await store.importFromJson({ memory, policies, agents, semantic, facts, feedback, usage, sessions, scratchpad });
   store.close();
 
   return {
@@ -65,8 +71,11 @@ export async function migrateJsonToSqlite(options: MigrateJsonToSqliteOptions =
     policies: policies.length,
     agents:   agents.length,
     semantic: semantic.length,
+    facts:    facts.length,
     feedback: feedback.length,
+    usage:    usage.length,
     sessions: sessions.length,
+    scratchpad: scratchpad.length,
     skipped: false,
   };
 }
@@ -0,0 +1,54 @@
+import { createHash } from 'node:crypto';
+import { basename, resolve } from 'node:path';
+const PROJECT_PREFIX = '@project:';
+const AGENT_PREFIX = '@agent:';
+// `/work/automatosx` -> `automatosx-3f2a9c1b`: readable, yet distinct for same-named checkouts.
+export function projectScopeId(basePath) {
+    const resolved = resolve(basePath);
+    const name = basename(resolved).replace(/[^A-Za-z0-9._-]+/g, '-') || 'root';
+    return `${name}-${createHash('sha256').update(resolved).digest('hex').slice(0, 8)}`;
+}
+/**
+ * The stored namespace of `namespace` inside a scope: `notes` scoped to a workspace and agent is
+ * `@project:automatosx-3f2a9c1b/@agent:reviewer/notes`. Without a scope the namespace is unchanged,
+ * so unscoped entries keep working as the shared, global memory.
+ */
+export function scopedNamespace(namespace, scope = {}) {
+    const agent = scope.agent?.trim();
+    if (scope.project === undefined && (agent === undefined || agent.length === 0)) {
+        return namespace;
+    }
+    if (agent !== undefined && agent.includes('/')) {
+        throw new Error(`Agent name "${agent}" cannot scope memory: it contains "/".`);
+    }
+    return [
+        ...(scope.project !== undefined ? [`${PROJECT_PREFIX}${projectScopeId(scope.project)}`] : []),
+        ...(agent !== undefined && agent.length > 0 ? [`${AGENT_PREFIX}${agent}`] : []),
+        namespace ?? 'default',
+    ].join('/');
+}
+/** Splits a stored namespace back into its scope and the namespace within it. */
+export function parseScopedNamespace(stored) {
+    const parsed = {};
+    let rest = stored;
+    if (rest?.startsWith(PROJECT_PREFIX) && rest.includes('/')) {
+        parsed.project = rest.slice(PROJECT_PREFIX.length, rest.indexOf('/'));
+        rest = rest.slice(rest.indexOf('/') + 1);
+    }
+    if (rest?.startsWith(AGENT_PREFIX) && rest.includes('/')) {
+        parsed.agent = rest.slice(AGENT_PREFIX.length, rest.indexOf('/'));
+        rest = rest.slice(rest.indexOf('/') + 1);
+    }
+    if (rest !== undefined && rest !== 'default') {
+        parsed.namespace = rest;
+    }
+    return parsed;
+}
+export function assertNamespaceTransfer(sources, target) {
+    if (sources.length === 0) {
+        throw new Error('Name at least one source namespace.');
+    }
+    if (sources.includes(target)) {
+        throw new Error(`Namespace "${target}" cannot be copied or merged into itself.`);
+    }
+}
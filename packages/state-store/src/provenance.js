@@ -0,0 +1,22 @@
+export function assertSourceSpan(span, subject) {
+    const lines = [span.startLine, span.endLine].filter((line) => line !== undefined);
+    if (typeof span.file !== 'string' || span.file.length === 0 || lines.some((line) => !Number.isInteger(line) || line < 1)) {
+        throw new Error(`A ${subject} source span needs a file and, optionally, 1-based start and end lines.`);
+    }
+    if (span.startLine !== undefined && span.endLine !== undefined && span.endLine < span.startLine) {
+        throw new Error(`${subject[0].toUpperCase()}${subject.slice(1)} source span ends at line ${span.endLine}, before it starts at line ${span.startLine}.`);
+    }
+}
+export function assertMemorySource(source) {
+    if (source === undefined) {
+        return;
+    }
+    if (source.file !== undefined || source.startLine !== undefined || source.endLine !== undefined) {
+        assertSourceSpan(source, 'memory');
+    }
+    for (const field of ['contentHash', 'sessionId', 'agent', 'provider']) {
+        if (source[field] !== undefined && (typeof source[field] !== 'string' || source[field].length === 0)) {
+            throw new Error(`Memory source ${field} must be a non-empty string.`);
+        }
+    }
+}
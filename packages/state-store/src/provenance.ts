@@ -0,0 +1,37 @@
+import type { SourceSpan } from './facts.js';
+
+/**
+ * Where a memory entry came from, so an answer built on it can cite it. Every field is optional:
+ * an entry may come from a file, a session, or both.
+ */
+export interface MemorySource extends Partial<SourceSpan> {
+  /** Hash of the span (or the whole file) when the entry was stored; the source changed once it differs. */
+  contentHash?: string;
+  sessionId?: string;
+  agent?: string;
+  provider?: string;
+}
+
+export function assertSourceSpan(span: Partial<SourceSpan>, subject: string): void {
+  const lines = [span.startLine, span.endLine].filter((line) => line !== undefined);
+  if (typeof span.file !== 'string' || span.file.length === 0 || lines.some((line) => !Number.isInteger(line) || line < 1)) {
+    throw new Error(`A ${subject} source span needs a file and, optionally, 1-based start and end lines.`);
+  }
+  if (span.startLine !== undefined && span.endLine !== undefined && span.endLine < span.startLine) {
+    throw new Error(`${subject[0]!.toUpperCase()}${subject.slice(1)} source span ends at line ${span.endLine}, before it starts at line ${span.startLine}.`);
+  }
+}
+
+export function assertMemorySource(source: MemorySource | undefined): void {
+  if (source === undefined) {
+    return;
+  }
+  if (source.file !== undefined || source.startLine !== undefined || source.endLine !== undefined) {
+    assertSourceSpan(source, 'memory');
+  }
+  for (const field of ['contentHash', 'sessionId', 'agent', 'provider'] as const) {
+    if (source[field] !== undefined && (typeof source[field] !== 'string' || source[field].length === 0)) {
+      throw new Error(`Memory source ${field} must be a non-empty string.`);
+    }
+  }
+}
@@ -0,0 +1,117 @@
+/** Limits `pruneMemory` enforces; unset or 0 turns a limit off. */
+export interface MemoryRetentionPolicy {
+  /** Entries not updated for this many days are evicted. */
+  maxAgeDays?: number;
+  /** Past this many entries, the least recently used are evicted. */
+  maxEntries?: number;
+  /** Past this many bytes of stored values and embeddings, the least recently used are evicted. */
+  maxBytes?: number;
+  /** An entry's importance halves after this many days without being stored or retrieved. */
+  importanceHalfLifeDays?: number;
+  /** Entries whose decayed importance falls below this are evicted; entries without an importance never are. */
+  minImportance?: number;
+}
+
+export type MemoryEvictionReason = 'expired' | 'age' | 'importance' | 'capacity';
+
+export interface MemoryEviction {
+  key: string;
+  namespace?: string;
+  reason: MemoryEvictionReason;
+  /** The decayed importance, for entries that carry one. */
+  importance?: number;
+  lastUsedAt: string;
+}
+
+/** What retention needs to know of a stored entry. */
+export interface RetainedMemory {
+  key: string;
+  namespace?: string;
+  updatedAt: string;
+  accessedAt?: string;
+  expiresAt?: string;
+  importance?: number;
+  bytes: number;
+}
+
+const DAY_MS = 86_400_000;
+
+export function isMemoryExpired(entry: { expiresAt?: string }, now: Date = new Date()): boolean {
+  return entry.expiresAt !== undefined && Date.parse(entry.expiresAt) <= now.getTime();
+}
+
+export function assertMemoryRetention(entry: { expiresAt?: string; importance?: number }): void {
+  if (entry.importance !== undefined && !(entry.importance >= 0 && entry.importance <= 1)) {
+    throw new Error(`Memory importance must be between 0 and 1, got ${entry.importance}.`);
+  }
+  if (entry.expiresAt !== undefined && Number.isNaN(Date.parse(entry.expiresAt))) {
+    throw new Error(`Memory expiry "${entry.expiresAt}" is not a date; pass an ISO timestamp.`);
+  }
+}
+
+/** `importance` halved once per `halfLifeDays` since the entry was last stored or retrieved. */
+export function decayedImportance(entry: Pick<RetainedMemory, 'importance' | 'updatedAt' | 'accessedAt'>, halfLifeDays: number, now: Date): number | undefined {
+  if (entry.importance === undefined) {
+    return undefined;
+  }
+  if (halfLifeDays <= 0) {
+    return entry.importance;
+  }
+  const idleDays = Math.max(0, now.getTime() - Date.parse(lastUsedAt(entry))) / DAY_MS;
+  return entry.importance * 0.5 ** (idleDays / halfLifeDays);
+}
+
+/**
+ * Picks the entries the policy evicts: expired ones, then those past the maximum age, then those
+ * whose importance decayed below the minimum, then the least recently used until what is left
+ * fits both size caps.
+ */
+export function selectMemoryEvictions(entries: RetainedMemory[], policy: MemoryRetentionPolicy, now: Date = new Date()): MemoryEviction[] {
+  const evictions: MemoryEviction[] = [];
+  const kept: RetainedMemory[] = [];
+  const ageCutoff = now.getTime() - (policy.maxAgeDays ?? 0) * DAY_MS;
+  for (const entry of entries) {
+    const importance = decayedImportance(entry, policy.importanceHalfLifeDays ?? 0, now);
+    const reason: MemoryEvictionReason | undefined = isMemoryExpired(entry, now)
+      ? 'expired'
+      : (policy.maxAgeDays ?? 0) > 0 && Date.parse(entry.updatedAt) < ageCutoff
+        ? 'age'
+        : importance !== undefined && importance < (policy.minImportance ?? 0)
+          ? 'importance'
+          : undefined;
+    if (reason !== undefined) {
+      evictions.push(eviction(entry, reason, importance));
+    } else {
+      kept.push(entry);
+    }
+  }
+
+  const maxEntries = policy.maxEntries ?? 0;
+  const maxBytes = policy.maxBytes ?? 0;
+  let count = kept.length;
+  let bytes = kept.reduce((total, entry) => total + entry.bytes, 0);
+  kept.sort((left, right) => lastUsedAt(left).localeCompare(lastUsedAt(right)));
+  for (const entry of kept) {
+    if ((maxEntries === 0 || count <= maxEntries) && (maxBytes === 0 || bytes <= maxBytes)) {
+      break;
+    }
+    evictions.push(eviction(entry, 'capacity', decayedImportance(entry, policy.importanceHalfLifeDays ?? 0, now)));
+    count -= 1;
+    bytes -= entry.bytes;
+  }
+  return evictions;
+}
+
+function lastUsedAt(entry: Pick<RetainedMemory, 'updatedAt' | 'accessedAt'>): string {
+  return entry.accessedAt !== undefined && entry.accessedAt > entry.updatedAt ? entry.accessedAt : entry.updatedAt;
+}
+
+function eviction(entry: RetainedMemory, reason: MemoryEvictionReason, importance: number | undefined): MemoryEviction {
+  return {
+    key: entry.key,
+    ...(entry.namespace !== undefined ? { namespace: entry.namespace } : {}),
+    reason,
+    ...(importance !== undefined ? { importance: Number(importance.toFixed(4)) } : {}),
+    lastUsedAt: lastUsedAt(entry),
+  };
+}
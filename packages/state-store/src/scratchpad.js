@@ -0,0 +1,141 @@
+import { posix } from 'node:path';
+export const SCRATCHPAD_CONFLICT_CODE = 'SCRATCHPAD_CONFLICT';
+/** How long a claim holds when the claimant does not say; a terminal that went away frees its task after it. */
+export const DEFAULT_SCRATCHPAD_LEASE_MS = 60 * 60_000;
+export function createScratchpadTasks(sessionId, existing, inputs, now = new Date()) {
+    const taken = new Set(existing.map((task) => task.taskId));
+    const at = now.toISOString();
+    return inputs.map((input) => {
+        if (typeof input.title !== 'string' || input.title.trim().length === 0) {
+            throw new Error('A scratchpad task needs a non-empty title.');
+        }
+        const taskId = input.taskId ?? uniqueSlug(input.title, taken);
+        if (taskId.trim().length === 0 || taken.has(taskId)) {
+            throw new Error(`Session "${sessionId}" already has a scratchpad task "${taskId}".`);
+        }
+        taken.add(taskId);
+        return {
+            sessionId,
+            taskId,
+            title: input.title.trim(),
+            paths: [...new Set((input.paths ?? []).map(normalizeTaskPath))],
+            status: 'open',
+            createdAt: at,
+            updatedAt: at,
+        };
+    });
+}
+/** Whether the task is held by a claim that has not lapsed. */
+export function isLiveClaim(task, now = new Date()) {
+    return task.status === 'claimed' && (task.leaseExpiresAt === undefined || task.leaseExpiresAt > now.toISOString());
+}
+/**
+ * The task a claim takes, as it is once claimed, or undefined when no task is free. Fails with
+ * `SCRATCHPAD_CONFLICT` when a named task is done, held by someone else or another run, or works on
+ * paths a live claim of another task covers.
+ */
+export function selectScratchpadClaim(sessionId, tasks, claim, now = new Date()) {
+    const held = tasks.filter((task) => isLiveClaim(task, now));
+    const blocker = (task) => held.find((other) => other.taskId !== task.taskId && pathsOverlap(task.paths, other.paths));
+    let task;
+    if (claim.taskId === undefined) {
+        task = tasks.find((candidate) => (candidate.status === 'open' || (candidate.status === 'claimed' && !isLiveClaim(candidate, now))) && blocker(candidate) === undefined);
+        if (task === undefined) {
+            return undefined;
+        }
+    }
+    else {
+        task = tasks.find((candidate) => candidate.taskId === claim.taskId);
+        if (task === undefined) {
+            throw new Error(`Session "${sessionId}" has no scratchpad task "${claim.taskId}".`);
+        }
+        if (task.status === 'done') {
+            throw conflict(`Scratchpad task "${task.taskId}" is done already.`);
+        }
+        if (isLiveClaim(task, now) && (task.claimedBy !== claim.claimant || task.traceId !== claim.traceId)) {
+            throw conflict(`Scratchpad task "${task.taskId}" is claimed by ${task.claimedBy} until ${task.leaseExpiresAt}.`);
+        }
+        const other = blocker(task);
+        if (other !== undefined) {
+            throw conflict(`Scratchpad task "${task.taskId}" works on paths of task "${other.taskId}", which ${other.claimedBy} holds.`);
+        }
+    }
+    const leaseMs = claim.leaseMs ?? DEFAULT_SCRATCHPAD_LEASE_MS;
+    if (!Number.isFinite(leaseMs) || leaseMs <= 0) {
+        throw new Error(`A scratchpad claim's lease must be a positive number of milliseconds, got ${String(claim.leaseMs)}.`);
+    }
+    return {
+        ...describedTask(task),
+        status: 'claimed',
+        claimedBy: claim.claimant,
+        ...(claim.traceId !== undefined ? { traceId: claim.traceId } : {}),
+        claimedAt: now.toISOString(),
+        leaseExpiresAt: new Date(now.getTime() + leaseMs).toISOString(),
+        createdAt: task.createdAt,
+        updatedAt: now.toISOString(),
+    };
+}
+/** The task as it is once its holder posts the result; fails with `SCRATCHPAD_CONFLICT` for anyone else. */
+export function applyScratchpadPost(sessionId, tasks, post, now = new Date()) {
+    const task = tasks.find((candidate) => candidate.taskId === post.taskId);
+    if (task === undefined) {
+        throw new Error(`Session "${sessionId}" has no scratchpad task "${post.taskId}".`);
+    }
+    if (task.status !== 'claimed' || task.claimedBy !== post.claimant) {
+        throw conflict(`Only the holder of scratchpad task "${task.taskId}" can post its result; ${task.status === 'claimed' ? `${task.claimedBy} holds it` : `it is ${task.status}`}.`);
+    }
+    return {
+        ...describedTask(task),
+        status: post.status ?? 'done',
+        claimedBy: task.claimedBy,
+        ...(task.traceId !== undefined ? { traceId: task.traceId } : {}),
+        ...(task.claimedAt !== undefined ? { claimedAt: task.claimedAt } : {}),
+        result: post.result,
+        createdAt: task.createdAt,
+        updatedAt: now.toISOString(),
+    };
+}
+/** The task as it is once released, open for anyone again; the holder, or with no claimant anyone, can release it. */
+export function applyScratchpadRelease(sessionId, tasks, taskId, claimant, now = new Date()) {
+    const task = tasks.find((candidate) => candidate.taskId === taskId);
+    if (task === undefined) {
+        throw new Error(`Session "${sessionId}" has no scratchpad task "${taskId}".`);
+    }
+    if (task.status !== 'claimed' || (claimant !== undefined && task.claimedBy !== claimant)) {
+        throw conflict(`Scratchpad task "${taskId}" is not held by ${claimant ?? 'anyone'}.`);
+    }
+    return { ...describedTask(task), status: 'open', createdAt: task.createdAt, updatedAt: now.toISOString() };
+}
+// Paths are workspace-relative with forward slashes, `.` being the whole workspace; a task without
+// paths overlaps nothing.
+function pathsOverlap(left, right) {
+    if (left.length === 0 || right.length === 0) {
+        return false;
+    }
+    return left.some((a) => right.some((b) => a === '.' || b === '.' || a === b || a.startsWith(`${b}/`) || b.startsWith(`${a}/`)));
+}
+// What a task is, without who holds it.
+function describedTask(task) {
+    return { sessionId: task.sessionId, taskId: task.taskId, title: task.title, paths: task.paths };
+}
+function normalizeTaskPath(path) {
+    if (typeof path !== 'string' || path.trim().length === 0) {
+        throw new Error('Scratchpad task paths must be non-empty strings.');
+    }
+    const normalized = posix.normalize(path.trim().replace(/\\/g, '/')).replace(/\/+$/, '') || '.';
+    if (normalized === '..' || normalized.startsWith('../') || posix.isAbsolute(normalized)) {
+        throw new Error(`Scratchpad task path "${path}" is not inside the workspace.`);
+    }
+    return normalized;
+}
+function uniqueSlug(title, taken) {
+    const slug = title.toLowerCase().replace(/[^a-z0-9]+/g, '-').replace(/^-+|-+$/g, '').slice(0, 40).replace(/-+$/, '') || 'task';
+    let candidate = slug;
+    for (let index = 2; taken.has(candidate); index += 1) {
+        candidate = `${slug}-${index}`;
+    }
+    return candidate;
+}
+function conflict(message) {
+    return Object.assign(new Error(message), { code: SCRATCHPAD_CONFLICT_CODE });
+}
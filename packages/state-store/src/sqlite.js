@@ -1,9 +1,18 @@
 // Uses Node.js built-in sqlite (node:sqlite), available from Node 22.5+ / Node 24.
 // No native compilation required.
 import { randomUUID } from 'node:crypto';
-import { mkdirSync } from 'node:fs';
+import { mkdirSync, rmSync } from 'node:fs';
 import { dirname, join } from 'node:path';
 import { DatabaseSync } from 'node:sqlite';
+import { isEncryptedMemory, openMemoryText, sealMemoryText } from './encryption.js';
+import { assertFact } from './facts.js';
+import { cosineSimilarity } from './hnsw.js';
+import { assertNamespaceTransfer } from './namespaces.js';
+import { assertMemorySource } from './provenance.js';
+import { assertMemoryRetention, selectMemoryEvictions } from './retention.js';
+import { applyScratchpadPost, applyScratchpadRelease, createScratchpadTasks, selectScratchpadClaim, } from './scratchpad.js';
+import { normalizeUsage } from './usage.js';
+import { VectorStore } from './vector-store.js';
 const JOURNAL_MODE_SETUP_ATTEMPTS = 20;
 const JOURNAL_MODE_SETUP_INITIAL_DELAY_MS = 5;
 const atomicsWaitState = new Int32Array(new SharedArrayBuffer(4));
@@ -83,8 +92,26 @@ function asRows(value) {
     return value;
 }
 const DEFAULT_DB_FILE = join('.automatosx', 'runtime', 'state.db');
+const DEFAULT_VECTOR_SEARCH_K = 10;
+const MEMORY_COLUMNS = 'key, namespace, value, embedding, expires_at, importance, accessed_at, source, updated_at';
+// Writes a whole row, timestamps included, as copies and imports carry them over.
+const MEMORY_UPSERT = `
+      INSERT INTO memory_items (${MEMORY_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+      ON CONFLICT(key, namespace) DO UPDATE SET value = excluded.value, embedding = excluded.embedding, expires_at = excluded.expires_at,
+        importance = excluded.importance, accessed_at = excluded.accessed_at, source = excluded.source, updated_at = excluded.updated_at
+    `;
+// Takes the current time as its parameter.
+const NOT_EXPIRED = '(expires_at IS NULL OR expires_at > ?)';
+// Only content changes re-index an entry; recording an access does not.
+const MEMORY_UPDATE_TRIGGER = `
+      CREATE TRIGGER IF NOT EXISTS mem_au AFTER UPDATE OF key, namespace, value ON memory_items BEGIN
+        INSERT INTO memory_fts(memory_fts, rowid, key, namespace, value) VALUES ('delete', old.id, old.key, old.namespace, old.value);
+        INSERT INTO memory_fts(rowid, key, namespace, value) VALUES (new.id, new.key, new.namespace, new.value);
+      END;`;
 export class SqliteStateStore {
     db;
+    cipher;
+    vectors;
     constructor(config = {}) {
         const dbFile = config.dbFile ?? join(config.basePath ?? process.cwd(), DEFAULT_DB_FILE);
         mkdirSync(dirname(dbFile), { recursive: true });
@@ -93,6 +120,17 @@ export class SqliteStateStore {
         withJournalModeRetry(() => this.db.prepare(`PRAGMA journal_mode = WAL`).get());
         this.db.prepare(`PRAGMA foreign_keys = ON`).run();
         this.initialize();
+        this.cipher = config.encryption;
+        // An index would keep the embeddings in plaintext, so encrypted stores compare them one by one.
+        if (this.cipher !== undefined) {
+            rmSync(`${dbFile}.vectors`, { recursive: true, force: true });
+            return;
+        }
+        // The HNSW index of memory embeddings lives next to the database, in `state.db.vectors/`.
+        this.vectors = new VectorStore(`${dbFile}.vectors`, {
+            load: () => asRows(this.db.prepare(`SELECT key, namespace, embedding FROM memory_items WHERE embedding IS NOT NULL`).all()).map((row) => ({ id: vectorId(row.key, row.namespace), vector: safeJsonParse(row.embedding, []) })),
+            count: () => asRow(this.db.prepare(`SELECT COUNT(*) AS count FROM memory_items WHERE embedding IS NOT NULL`).get())?.count ?? 0,
+        });
     }
     initialize() {
         this.db.exec(`
@@ -101,6 +139,11 @@ export class SqliteStateStore {
         key        TEXT NOT NULL,
         namespace  TEXT NOT NULL DEFAULT 'default',
         value      TEXT NOT NULL,
+        embedding  TEXT,
+        expires_at  TEXT,
+        importance  REAL,
+        accessed_at TEXT,
+        source      TEXT,
         updated_at TEXT NOT NULL,
         UNIQUE(key, namespace)
       );
@@ -119,10 +162,7 @@ export class SqliteStateStore {
       CREATE TRIGGER IF NOT EXISTS mem_ad AFTER DELETE ON memory_items BEGIN
         INSERT INTO memory_fts(memory_fts, rowid, key, namespace, value) VALUES ('delete', old.id, old.key, old.namespace, old.value);
       END;
-      CREATE TRIGGER IF NOT EXISTS mem_au AFTER UPDATE ON memory_items BEGIN
-        INSERT INTO memory_fts(memory_fts, rowid, key, namespace, value) VALUES ('delete', old.id, old.key, old.namespace, old.value);
-        INSERT INTO memory_fts(rowid, key, namespace, value) VALUES (new.id, new.key, new.namespace, new.value);
-      END;
+      ${MEMORY_UPDATE_TRIGGER}
 
       CREATE TABLE IF NOT EXISTS policies (
         policy_id  TEXT PRIMARY KEY,
@@ -156,6 +196,22 @@ export class SqliteStateStore {
       CREATE INDEX IF NOT EXISTS idx_sem_ns  ON semantic_items(namespace);
       CREATE INDEX IF NOT EXISTS idx_sem_upd ON semantic_items(updated_at DESC);
 
+      -- Objects are stored as JSON, so 1, "1" and true stay distinct facts.
+      CREATE TABLE IF NOT EXISTS facts (
+        fact_id     TEXT PRIMARY KEY,
+        namespace   TEXT NOT NULL DEFAULT 'default',
+        subject     TEXT NOT NULL,
+        predicate   TEXT NOT NULL,
+        object      TEXT NOT NULL,
+        source_span TEXT,
+        confidence  REAL,
+        created_at  TEXT NOT NULL,
+        updated_at  TEXT NOT NULL,
+        UNIQUE(namespace, subject, predicate, object)
+      );
+      CREATE INDEX IF NOT EXISTS idx_facts_subject ON facts(subject, predicate);
+      CREATE INDEX IF NOT EXISTS idx_facts_object  ON facts(object, predicate);
+
       CREATE TABLE IF NOT EXISTS feedback (
         feedback_id       TEXT PRIMARY KEY,
         selected_agent    TEXT NOT NULL,
@@ -173,6 +229,25 @@ export class SqliteStateStore {
       CREATE INDEX IF NOT EXISTS idx_fb_agent   ON feedback(selected_agent);
       CREATE INDEX IF NOT EXISTS idx_fb_created ON feedback(created_at DESC);
 
+      CREATE TABLE IF NOT EXISTS usage_records (
+        usage_id      TEXT PRIMARY KEY,
+        provider      TEXT NOT NULL,
+        model         TEXT,
+        agent_id      TEXT,
+        trace_id      TEXT,
+        root_trace_id TEXT,
+        project       TEXT,
+        input_tokens  INTEGER NOT NULL,
+        output_tokens INTEGER NOT NULL,
+        latency_ms    INTEGER NOT NULL,
+        cost_usd      REAL,
+        success       INTEGER NOT NULL,
+        recorded_at   TEXT NOT NULL
+      );
+      CREATE INDEX IF NOT EXISTS idx_usage_recorded ON usage_records(recorded_at DESC);
+      CREATE INDEX IF NOT EXISTS idx_usage_trace    ON usage_records(trace_id);
+      CREATE INDEX IF NOT EXISTS idx_usage_root     ON usage_records(root_trace_id);
+
       CREATE TABLE IF NOT EXISTS sessions (
         session_id   TEXT PRIMARY KEY,
         task         TEXT NOT NULL,
@@ -188,53 +263,331 @@ export class SqliteStateStore {
       );
       CREATE INDEX IF NOT EXISTS idx_sess_status  ON sessions(status);
       CREATE INDEX IF NOT EXISTS idx_sess_updated ON sessions(updated_at DESC);
+
+      CREATE TABLE IF NOT EXISTS scratchpad_tasks (
+        session_id       TEXT NOT NULL,
+        task_id          TEXT NOT NULL,
+        title            TEXT NOT NULL,
+        paths            TEXT NOT NULL DEFAULT '[]',
+        status           TEXT NOT NULL DEFAULT 'open',
+        claimed_by       TEXT,
+        trace_id         TEXT,
+        claimed_at       TEXT,
+        lease_expires_at TEXT,
+        result           TEXT,
+        created_at       TEXT NOT NULL,
+        updated_at       TEXT NOT NULL,
+        PRIMARY KEY(session_id, task_id)
+      );
     `);
+        // Databases created before memory embeddings were stored lack the column.
+        const memoryColumns = asRows(this.db.prepare(`PRAGMA table_info(memory_items)`).all());
+        if (!memoryColumns.some((column) => column.name === 'embedding')) {
+            this.db.exec(`ALTER TABLE memory_items ADD COLUMN embedding TEXT`);
+        }
+        // ...and before retention, whose access times would otherwise re-index the entry on every read.
+        if (!memoryColumns.some((column) => column.name === 'accessed_at')) {
+            this.db.exec(`
+        ALTER TABLE memory_items ADD COLUMN expires_at TEXT;
+        ALTER TABLE memory_items ADD COLUMN importance REAL;
+        ALTER TABLE memory_items ADD COLUMN accessed_at TEXT;
+        DROP TRIGGER IF EXISTS mem_au;
+        ${MEMORY_UPDATE_TRIGGER}
+      `);
+        }
+        // ...and before provenance.
+        if (!memoryColumns.some((column) => column.name === 'source')) {
+            this.db.exec(`ALTER TABLE memory_items ADD COLUMN source TEXT`);
+        }
     }
     // -------------------------------------------------------------------------
     // Memory
     // -------------------------------------------------------------------------
     async storeMemory(entry) {
+        assertMemoryRetention(entry);
+        assertMemorySource(entry.source);
         const namespace = entry.namespace ?? 'default';
         const now = new Date().toISOString();
+        // Stored as ISO strings so expiry compares as text.
+        const expiresAt = entry.expiresAt !== undefined ? new Date(entry.expiresAt).toISOString() : undefined;
+        const hadEmbedding = entry.embedding === undefined && this.hasEmbedding(entry.key, namespace);
         this.db.prepare(`
-      INSERT INTO memory_items (key, namespace, value, updated_at) VALUES (?, ?, ?, ?)
-      ON CONFLICT(key, namespace) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
-    `).run(entry.key, namespace, JSON.stringify(entry.value), now);
-        return { key: entry.key, namespace: entry.namespace, value: entry.value, updatedAt: now };
+      INSERT INTO memory_items (key, namespace, value, embedding, expires_at, importance, source, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+      ON CONFLICT(key, namespace) DO UPDATE SET value = excluded.value, embedding = excluded.embedding, expires_at = excluded.expires_at,
+        importance = excluded.importance, accessed_at = NULL, source = excluded.source, updated_at = excluded.updated_at
+    `).run(entry.key, namespace, this.sealValue(entry.value), this.sealEmbedding(entry.embedding), expiresAt ?? null, entry.importance ?? null, sourceJson(entry.source), now);
+        if (entry.embedding !== undefined) {
+            this.vectors?.put(vectorId(entry.key, namespace), entry.embedding);
+        }
+        else if (hadEmbedding) {
+            this.vectors?.remove(vectorId(entry.key, namespace));
+        }
+        return {
+            key: entry.key,
+            namespace: entry.namespace,
+            value: entry.value,
+            ...(entry.embedding !== undefined ? { embedding: entry.embedding } : {}),
+            ...(expiresAt !== undefined ? { expiresAt } : {}),
+            ...(entry.importance !== undefined ? { importance: entry.importance } : {}),
+            ...(entry.source !== undefined ? { source: entry.source } : {}),
+            updatedAt: now,
+        };
     }
     async getMemory(key, namespace) {
-        const row = asRow(this.db.prepare(`SELECT key, namespace, value, updated_at FROM memory_items WHERE key = ? AND namespace = ?`).get(key, namespace ?? 'default'));
-        return row ? rowToMemory(row) : undefined;
+        const now = new Date().toISOString();
+        const row = asRow(this.db.prepare(`SELECT ${MEMORY_COLUMNS} FROM memory_items WHERE key = ? AND namespace = ? AND ${NOT_EXPIRED}`).get(key, namespace ?? 'default', now));
+        if (!row)
+            return undefined;
+        this.db.prepare(`UPDATE memory_items SET accessed_at = ? WHERE key = ? AND namespace = ?`).run(now, key, namespace ?? 'default');
+        return rowToMemory({ ...row, accessed_at: now }, this.cipher);
     }
     async searchMemory(query, namespace) {
         const trimmed = query.trim();
         if (trimmed === '')
             return this.listMemory(namespace);
+        // FTS only ever sees ciphertext in an encrypted store, so it is searched after decrypting.
+        if (this.cipher !== undefined) {
+            const needle = trimmed.toLowerCase();
+            return (await this.listMemory(namespace))
+                .filter((entry) => [entry.key, entry.namespace ?? '', JSON.stringify(entry.value)].join('\n').toLowerCase().includes(needle))
+                .slice(0, 200);
+        }
         const escaped = trimmed.replace(/"/g, '""');
         let sql = `
-      SELECT m.key, m.namespace, m.value, m.updated_at
+      SELECT ${MEMORY_COLUMNS.split(', ').map((column) => `m.${column}`).join(', ')}
       FROM memory_fts fts JOIN memory_items m ON fts.rowid = m.id
-      WHERE memory_fts MATCH ?
+      WHERE memory_fts MATCH ? AND (m.expires_at IS NULL OR m.expires_at > ?)
     `;
-        const params = [`"${escaped}"`];
+        const params = [`"${escaped}"`, new Date().toISOString()];
         if (namespace !== undefined) {
             sql += ` AND m.namespace = ?`;
             params.push(namespace);
         }
         sql += ` ORDER BY bm25(memory_fts) LIMIT 200`;
         const rows = asRows(this.db.prepare(sql).all(...params));
-        return rows.map(rowToMemory);
+        return rows.map((row) => rowToMemory(row, this.cipher));
     }
     async deleteMemory(key, namespace) {
+        const hadEmbedding = this.hasEmbedding(key, namespace ?? 'default');
         const result = this.db.prepare(`DELETE FROM memory_items WHERE key = ? AND namespace = ?`)
             .run(key, namespace ?? 'default');
+        if (hadEmbedding) {
+            this.vectors?.remove(vectorId(key, namespace ?? 'default'));
+        }
         return result.changes > 0;
     }
     async listMemory(namespace) {
         const rows = namespace !== undefined
-            ? asRows(this.db.prepare(`SELECT key, namespace, value, updated_at FROM memory_items WHERE namespace = ? ORDER BY updated_at DESC`).all(namespace))
-            : asRows(this.db.prepare(`SELECT key, namespace, value, updated_at FROM memory_items ORDER BY updated_at DESC`).all());
-        return rows.map(rowToMemory);
+            ? asRows(this.db.prepare(`SELECT ${MEMORY_COLUMNS} FROM memory_items WHERE namespace = ? AND ${NOT_EXPIRED} ORDER BY updated_at DESC`).all(namespace, new Date().toISOString()))
+            : asRows(this.db.prepare(`SELECT ${MEMORY_COLUMNS} FROM memory_items WHERE ${NOT_EXPIRED} ORDER BY updated_at DESC`).all(new Date().toISOString()));
+        return rows.map((row) => rowToMemory(row, this.cipher));
+    }
+    async searchMemoryByVector(vector, options = {}) {
+        if (this.vectors === undefined) {
+            return (await this.listMemory(options.namespace))
+                .filter((entry) => entry.embedding?.length === vector.length)
+                .map((entry) => ({ ...entry, score: cosineSimilarity(vector, entry.embedding) }))
+                .filter((entry) => entry.score >= (options.minScore ?? -1))
+                .sort((left, right) => right.score - left.score)
+                .slice(0, options.k ?? DEFAULT_VECTOR_SEARCH_K);
+        }
+        const prefix = options.namespace !== undefined ? vectorId('', options.namespace) : undefined;
+        const matches = this.vectors.search(vector, options.k ?? DEFAULT_VECTOR_SEARCH_K, prefix !== undefined ? (id) => id.startsWith(prefix) : undefined);
+        const select = this.db.prepare(`SELECT ${MEMORY_COLUMNS} FROM memory_items WHERE key = ? AND namespace = ? AND ${NOT_EXPIRED}`);
+        const now = new Date().toISOString();
+        return matches.flatMap((match) => {
+            if (match.score < (options.minScore ?? -1)) {
+                return [];
+            }
+            const separator = match.id.indexOf('\0');
+            const row = asRow(select.get(match.id.slice(separator + 1), match.id.slice(0, separator), now));
+            return row ? [{ ...rowToMemory(row, this.cipher), score: match.score }] : [];
+        });
+    }
+    async listMemoryNamespaces() {
+        const rows = asRows(this.db.prepare(`
+      SELECT namespace, COUNT(*) AS entries, COUNT(embedding) AS embedded, MAX(updated_at) AS last_updated_at
+      FROM memory_items GROUP BY namespace
+    `).all());
+        return rows
+            .map((row) => ({
+                namespace: row.namespace,
+                entries: row.entries,
+                embedded: row.embedded,
+                ...(row.last_updated_at !== null ? { lastUpdatedAt: row.last_updated_at } : {}),
+            }))
+            .sort((a, b) => a.namespace.localeCompare(b.namespace));
+    }
+    async copyMemoryNamespace(source, target, options = {}) {
+        const { copied, skipped } = this.transferMemory([source], target, options.overwrite === true ? 'overwrite' : 'keep', false);
+        return { copied, skipped };
+    }
+    async mergeMemoryNamespaces(sources, target) {
+        return this.transferMemory(sources, target, 'newer', true);
+    }
+    // Copies entries with their timestamps and embeddings in one transaction, deleting the sources when merging.
+    transferMemory(sources, target, mode, deleteSources) {
+        assertNamespaceTransfer(sources, target);
+        const select = this.db.prepare(`SELECT ${MEMORY_COLUMNS} FROM memory_items WHERE namespace = ? ORDER BY updated_at`);
+        const current = this.db.prepare(`SELECT updated_at, embedding IS NOT NULL AS embedded FROM memory_items WHERE key = ? AND namespace = ?`);
+        const upsert = this.db.prepare(MEMORY_UPSERT);
+        const remove = this.db.prepare(`DELETE FROM memory_items WHERE namespace = ?`);
+        // The index is only told once the transaction commits.
+        const vectorUpdates = [];
+        let copied = 0, skipped = 0, deleted = 0;
+        this.db.exec('BEGIN');
+        try {
+            for (const source of sources) {
+                for (const row of asRows(select.all(source))) {
+                    const existing = asRow(current.get(row.key, target));
+                    if (existing !== undefined && (mode === 'keep' || (mode === 'newer' && existing.updated_at >= row.updated_at))) {
+                        skipped += 1;
+                    }
+                    else {
+                        upsert.run(row.key, target, row.value, row.embedding, row.expires_at, row.importance, row.accessed_at, row.source, row.updated_at);
+                        if (row.embedding !== null) {
+                            vectorUpdates.push(() => this.vectors?.put(vectorId(row.key, target), safeJsonParse(row.embedding, [])));
+                        }
+                        else if (existing?.embedded) {
+                            vectorUpdates.push(() => this.vectors?.remove(vectorId(row.key, target)));
+                        }
+                        copied += 1;
+                    }
+                    if (deleteSources && row.embedding !== null) {
+                        vectorUpdates.push(() => this.vectors?.remove(vectorId(row.key, source)));
+                    }
+                }
+                if (deleteSources) {
+                    deleted += remove.run(source).changes;
+                }
+            }
+            this.db.exec('COMMIT');
+        }
+        catch (err) {
+            this.db.exec('ROLLBACK');
+            throw err;
+        }
+        for (const update of vectorUpdates)
+            update();
+        return { copied, skipped, deleted };
+    }
+    async importMemory(entries, options = {}) {
+        for (const entry of entries)
+            assertMemoryRetention(entry);
+        for (const entry of entries)
+            assertMemorySource(entry.source);
+        const mode = options.mode ?? 'newer';
+        const current = this.db.prepare(`SELECT updated_at, embedding IS NOT NULL AS embedded FROM memory_items WHERE key = ? AND namespace = ?`);
+        const upsert = this.db.prepare(MEMORY_UPSERT);
+        const vectorUpdates = [];
+        let copied = 0, skipped = 0;
+        this.db.exec('BEGIN');
+        try {
+            for (const entry of entries) {
+                const namespace = entry.namespace ?? 'default';
+                const existing = asRow(current.get(entry.key, namespace));
+                if (existing !== undefined && (mode === 'keep' || (mode === 'newer' && existing.updated_at >= entry.updatedAt))) {
+                    skipped += 1;
+                    continue;
+                }
+                upsert.run(entry.key, namespace, this.sealValue(entry.value), this.sealEmbedding(entry.embedding), entry.expiresAt ?? null, entry.importance ?? null, entry.accessedAt ?? null, sourceJson(entry.source), entry.updatedAt);
+                if (entry.embedding !== undefined) {
+                    const embedding = entry.embedding;
+                    vectorUpdates.push(() => this.vectors?.put(vectorId(entry.key, namespace), embedding));
+                }
+                else if (existing?.embedded) {
+                    vectorUpdates.push(() => this.vectors?.remove(vectorId(entry.key, namespace)));
+                }
+                copied += 1;
+            }
+            this.db.exec('COMMIT');
+        }
+        catch (err) {
+            this.db.exec('ROLLBACK');
+            throw err;
+        }
+        for (const update of vectorUpdates)
+            update();
+        return { copied, skipped };
+    }
+    async pruneMemory(policy, options = {}) {
+        const rows = asRows(this.db.prepare(`
+      SELECT key, namespace, updated_at, accessed_at, expires_at, importance, embedding IS NOT NULL AS embedded,
+        length(CAST(value AS BLOB)) + COALESCE(length(CAST(embedding AS BLOB)), 0) AS bytes
+      FROM memory_items
+    `).all());
+        const embedded = new Set(rows.filter((row) => row.embedded).map((row) => vectorId(row.key, row.namespace)));
+        const evictions = selectMemoryEvictions(rows.map((row) => ({
+            key: row.key,
+            ...(row.namespace !== 'default' ? { namespace: row.namespace } : {}),
+            updatedAt: row.updated_at,
+            ...(row.accessed_at !== null ? { accessedAt: row.accessed_at } : {}),
+            ...(row.expires_at !== null ? { expiresAt: row.expires_at } : {}),
+            ...(row.importance !== null ? { importance: row.importance } : {}),
+            bytes: row.bytes,
+        })), policy, options.now);
+        if (options.dryRun === true || evictions.length === 0) {
+            return evictions;
+        }
+        const remove = this.db.prepare(`DELETE FROM memory_items WHERE key = ? AND namespace = ?`);
+        this.db.exec('BEGIN');
+        try {
+            for (const eviction of evictions)
+                remove.run(eviction.key, eviction.namespace ?? 'default');
+            this.db.exec('COMMIT');
+        }
+        catch (err) {
+            this.db.exec('ROLLBACK');
+            throw err;
+        }
+        for (const eviction of evictions) {
+            const id = vectorId(eviction.key, eviction.namespace ?? 'default');
+            if (embedded.has(id))
+                this.vectors?.remove(id);
+        }
+        return evictions;
+    }
+    async encryptMemory() {
+        const cipher = this.cipher;
+        if (cipher === undefined) {
+            throw new Error('This store has no memory encryption key.');
+        }
+        const rows = asRows(this.db.prepare(`SELECT id, value, embedding FROM memory_items`).all())
+            .filter((row) => !isEncryptedMemory(row.value) || (row.embedding !== null && !isEncryptedMemory(row.embedding)));
+        const semanticRows = asRows(this.db.prepare(`SELECT key, namespace, content, token_freq FROM semantic_items`).all())
+            .filter((row) => !isEncryptedMemory(row.content) || (row.token_freq !== null && !isEncryptedMemory(row.token_freq)));
+        const update = this.db.prepare(`UPDATE memory_items SET value = ?, embedding = ? WHERE id = ?`);
+        const updateSemantic = this.db.prepare(`UPDATE semantic_items SET content = ?, token_freq = ? WHERE key = ? AND namespace = ?`);
+        this.db.exec('BEGIN');
+        try {
+            for (const row of rows)
+                update.run(encryptOnce(row.value, cipher), row.embedding !== null ? encryptOnce(row.embedding, cipher) : null, row.id);
+            for (const row of semanticRows)
+                updateSemantic.run(encryptOnce(row.content, cipher), row.token_freq !== null ? encryptOnce(row.token_freq, cipher) : null, row.key, row.namespace);
+            this.db.exec('COMMIT');
+        }
+        catch (err) {
+            this.db.exec('ROLLBACK');
+            throw err;
+        }
+        // The full-text index, freed pages and the WAL still hold the plaintext until they are rebuilt.
+        if (rows.length > 0) {
+            this.db.exec(`INSERT INTO memory_fts(memory_fts) VALUES ('rebuild')`);
+        }
+        if (rows.length + semanticRows.length > 0) {
+            this.db.exec('VACUUM');
+            this.db.prepare(`PRAGMA wal_checkpoint(TRUNCATE)`).get();
+        }
+        return { encrypted: rows.length + semanticRows.length };
+    }
+    sealValue(value) {
+        return sealMemoryText(JSON.stringify(value), this.cipher);
+    }
+    sealEmbedding(embedding) {
+        return embedding !== undefined ? sealMemoryText(JSON.stringify(embedding), this.cipher) : null;
+    }
+    hasEmbedding(key, namespace) {
+        return asRow(this.db.prepare(`SELECT 1 FROM memory_items WHERE key = ? AND namespace = ? AND embedding IS NOT NULL`).get(key, namespace)) !== undefined;
     }
     // -------------------------------------------------------------------------
     // Policies
@@ -302,7 +655,7 @@ export class SqliteStateStore {
       ON CONFLICT(key, namespace) DO UPDATE SET
         content = excluded.content, token_freq = excluded.token_freq, tags = excluded.tags,
         metadata = excluded.metadata, updated_at = excluded.updated_at
-    `).run(entry.key, namespace, entry.content, JSON.stringify(tokenFreq), tags.join(','), entry.metadata ? JSON.stringify(entry.metadata) : null, now);
+    `).run(entry.key, namespace, sealMemoryText(entry.content, this.cipher), sealMemoryText(JSON.stringify(tokenFreq), this.cipher), tags.join(','), entry.metadata ? JSON.stringify(entry.metadata) : null, now);
         return { key: entry.key, namespace: entry.namespace, content: entry.content, tags, metadata: entry.metadata, tokenFreq, updatedAt: now };
     }
     async searchSemantic(query, options = {}) {
@@ -319,18 +672,18 @@ export class SqliteStateStore {
             sql += ` AND (',' || tags || ',') LIKE ?`;
             params.push(`%,${tag},%`);
         }
-        const rows = asRows(this.db.prepare(sql).all(...params));
+        const rows = asRows(this.db.prepare(sql).all(...params)).map((row) => rowToSemantic(row, this.cipher));
         const ranked = rows
-            .map((row) => ({ row, score: tfCosineSimilarity(queryFreq, safeJsonParse(row.token_freq, {})) }))
+            .map((row) => ({ row, score: tfCosineSimilarity(queryFreq, row.tokenFreq) }))
             .filter((r) => r.score >= minSimilarity)
-            .sort((a, b) => b.score - a.score || b.row.updated_at.localeCompare(a.row.updated_at));
+            .sort((a, b) => b.score - a.score || b.row.updatedAt.localeCompare(a.row.updatedAt));
         const sliced = options.topK !== undefined ? ranked.slice(0, Math.max(0, options.topK)) : ranked;
-        return sliced.map(({ row, score }) => ({ ...rowToSemantic(row), score }));
+        return sliced.map(({ row, score }) => ({ ...row, score }));
     }
     async getSemantic(key, namespace) {
         const row = asRow(this.db.prepare(`SELECT key, namespace, content, token_freq, tags, metadata, updated_at FROM semantic_items WHERE key = ? AND namespace = ?`)
             .get(key, namespace ?? 'default'));
-        return row ? rowToSemantic(row) : undefined;
+        return row ? rowToSemantic(row, this.cipher) : undefined;
     }
     async listSemantic(options = {}) {
         const filterTags = normalizeTags(options.filterTags);
@@ -353,7 +706,7 @@ export class SqliteStateStore {
             sql += ` LIMIT ?`;
             params.push(Math.max(0, options.limit));
         }
-        return asRows(this.db.prepare(sql).all(...params)).map(rowToSemantic);
+        return asRows(this.db.prepare(sql).all(...params)).map((row) => rowToSemantic(row, this.cipher));
     }
     async deleteSemantic(key, namespace) {
         return this.db.prepare(`DELETE FROM semantic_items WHERE key = ? AND namespace = ?`).run(key, namespace ?? 'default').changes > 0;
@@ -389,6 +742,36 @@ export class SqliteStateStore {
         });
     }
     // -------------------------------------------------------------------------
+    // Facts
+    // -------------------------------------------------------------------------
+    async storeFact(input) {
+        assertFact(input);
+        const namespace = input.namespace ?? 'default';
+        const now = new Date().toISOString();
+        this.db.prepare(`
+      INSERT INTO facts (fact_id, namespace, subject, predicate, object, source_span, confidence, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+      ON CONFLICT(namespace, subject, predicate, object) DO UPDATE SET
+        source_span = excluded.source_span, confidence = excluded.confidence, updated_at = excluded.updated_at
+    `).run(randomUUID(), namespace, input.subject, input.predicate, JSON.stringify(input.object), input.sourceSpan !== undefined ? JSON.stringify(input.sourceSpan) : null, input.confidence ?? null, now, now);
+        const row = asRow(this.db.prepare(`SELECT * FROM facts WHERE namespace = ? AND subject = ? AND predicate = ? AND object = ?`)
+            .get(namespace, input.subject, input.predicate, JSON.stringify(input.object)));
+        return rowToFact(row);
+    }
+    async queryFacts(query = {}) {
+        let sql = `SELECT * FROM facts WHERE 1=1`;
+        const params = [];
+        if (query.subject !== undefined)   { sql += ` AND subject = ?`;   params.push(query.subject); }
+        if (query.predicate !== undefined) { sql += ` AND predicate = ?`; params.push(query.predicate); }
+        if (query.object !== undefined)    { sql += ` AND object = ?`;    params.push(JSON.stringify(query.object)); }
+        if (query.namespace !== undefined) { sql += ` AND namespace = ?`; params.push(query.namespace); }
+        sql += ` ORDER BY updated_at DESC`;
+        if (query.limit !== undefined) { sql += ` LIMIT ?`; params.push(Math.max(0, query.limit)); }
+        return asRows(this.db.prepare(sql).all(...params)).map(rowToFact);
+    }
+    async deleteFact(factId) {
+        return this.db.prepare(`DELETE FROM facts WHERE fact_id = ?`).run(factId).changes > 0;
+    }
+    // -------------------------------------------------------------------------
     // Feedback
     // -------------------------------------------------------------------------
     async submitFeedback(entry) {
@@ -435,6 +818,44 @@ export class SqliteStateStore {
         return asRows(this.db.prepare(sql).all(...params)).map(rowToFeedback);
     }
     // -------------------------------------------------------------------------
+    // Usage
+    // -------------------------------------------------------------------------
+    async recordUsage(entry) {
+        const stored = normalizeUsage(entry);
+        this.db.prepare(`INSERT INTO usage_records (${USAGE_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)`).run(...usageValues(stored));
+        return stored;
+    }
+    async listUsage(query = {}) {
+        let sql = `SELECT * FROM usage_records WHERE 1=1`;
+        const params = [];
+        if (query.since !== undefined) {
+            sql += ` AND recorded_at >= ?`;
+            params.push(query.since);
+        }
+        if (query.provider !== undefined) {
+            sql += ` AND provider = ?`;
+            params.push(query.provider);
+        }
+        if (query.agentId !== undefined) {
+            sql += ` AND agent_id = ?`;
+            params.push(query.agentId);
+        }
+        if (query.project !== undefined) {
+            sql += ` AND project = ?`;
+            params.push(query.project);
+        }
+        if (query.traceId !== undefined) {
+            sql += ` AND (trace_id = ? OR root_trace_id = ?)`;
+            params.push(query.traceId, query.traceId);
+        }
+        sql += ` ORDER BY recorded_at DESC`;
+        if (query.limit !== undefined) {
+            sql += ` LIMIT ?`;
+            params.push(Math.max(0, query.limit));
+        }
+        return asRows(this.db.prepare(sql).all(...params)).map(rowToUsage);
+    }
+    // -------------------------------------------------------------------------
     // Sessions
     // -------------------------------------------------------------------------
     async createSession(entry) {
@@ -499,6 +920,14 @@ export class SqliteStateStore {
             return s;
         });
     }
+    async reopenSession(sessionId) {
+        return this.mutateSession(sessionId, (s) => {
+            s.status = 'active';
+            s.error = undefined;
+            s.updatedAt = new Date().toISOString();
+            return s;
+        });
+    }
     async closeStuckSessions(maxAgeMs = 86_400_000) {
         const threshold = new Date(Date.now() - maxAgeMs).toISOString();
         const now = new Date().toISOString();
@@ -513,7 +942,65 @@ export class SqliteStateStore {
         }
         return closed;
     }
+    // -------------------------------------------------------------------------
+    // Scratchpad
+    // -------------------------------------------------------------------------
+    async addScratchpadTasks(sessionId, tasks) {
+        return this.withScratchpad(sessionId, (stored) => {
+            const created = createScratchpadTasks(sessionId, stored, tasks);
+            return { changed: created, result: created };
+        });
+    }
+    async listScratchpad(sessionId) {
+        return this.scratchpadRows(sessionId);
+    }
+    async claimScratchpadTask(sessionId, claim) {
+        return this.withScratchpad(sessionId, (stored) => {
+            const claimed = selectScratchpadClaim(sessionId, stored, claim);
+            return { changed: claimed === undefined ? [] : [claimed], result: claimed };
+        });
+    }
+    async postScratchpadResult(sessionId, post) {
+        return this.withScratchpad(sessionId, (stored) => {
+            const posted = applyScratchpadPost(sessionId, stored, post);
+            return { changed: [posted], result: posted };
+        });
+    }
+    async releaseScratchpadTask(sessionId, taskId, claimant) {
+        return this.withScratchpad(sessionId, (stored) => {
+            const released = applyScratchpadRelease(sessionId, stored, taskId, claimant);
+            return { changed: [released], result: released };
+        });
+    }
+    // BEGIN IMMEDIATE takes the write lock before the read, so claims from other processes wait their turn.
+    withScratchpad(sessionId, change) {
+        this.db.exec('BEGIN IMMEDIATE');
+        try {
+            if (asRow(this.db.prepare(`SELECT 1 FROM sessions WHERE session_id = ?`).get(sessionId)) === undefined) {
+                throw new Error(`Session not found: ${sessionId}`);
+            }
+            const { changed, result } = change(this.scratchpadRows(sessionId));
+            const upsert = this.db.prepare(`
+        INSERT INTO scratchpad_tasks (${SCRATCHPAD_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(session_id, task_id) DO UPDATE SET title = excluded.title, paths = excluded.paths, status = excluded.status,
+          claimed_by = excluded.claimed_by, trace_id = excluded.trace_id, claimed_at = excluded.claimed_at,
+          lease_expires_at = excluded.lease_expires_at, result = excluded.result, updated_at = excluded.updated_at
+      `);
+            for (const task of changed)
+                upsert.run(...scratchpadValues(task));
+            this.db.exec('COMMIT');
+            return result;
+        }
+        catch (err) {
+            this.db.exec('ROLLBACK');
+            throw err;
+        }
+    }
+    scratchpadRows(sessionId) {
+        return asRows(this.db.prepare(`SELECT * FROM scratchpad_tasks WHERE session_id = ? ORDER BY created_at, rowid`).all(sessionId)).map(rowToScratchpadTask);
+    }
     mutateSession(sessionId, mutate) {
+        const insertFact = this.db.prepare(`INSERT OR IGNORE INTO facts (fact_id, namespace, subject, predicate, object, source_span, confidence, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)`);
         const row = asRow(this.db.prepare(`SELECT * FROM sessions WHERE session_id = ?`).get(sessionId));
         if (!row)
             throw new Error(`Session not found: ${sessionId}`);
@@ -528,39 +1015,72 @@ export class SqliteStateStore {
     // Migration from JSON
     // -------------------------------------------------------------------------
     async importFromJson(jsonData) {
-        const insertMem = this.db.prepare(`INSERT OR IGNORE INTO memory_items (key, namespace, value, updated_at) VALUES (?, ?, ?, ?)`);
+        const insertMem = this.db.prepare(`INSERT OR IGNORE INTO memory_items (${MEMORY_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)`);
         const insertPol = this.db.prepare(`INSERT OR IGNORE INTO policies (policy_id, name, enabled, metadata, updated_at) VALUES (?, ?, ?, ?, ?)`);
         const insertAg = this.db.prepare(`INSERT OR IGNORE INTO agents (agent_id, name, capabilities, metadata, registration_key, registered_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?)`);
         const insertSem = this.db.prepare(`INSERT OR IGNORE INTO semantic_items (key, namespace, content, token_freq, tags, metadata, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?)`);
         const insertFb = this.db.prepare(`INSERT OR IGNORE INTO feedback (feedback_id, selected_agent, recommended_agent, rating, feedback_type, task_description, user_comment, outcome, duration_ms, session_id, metadata, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)`);
+        const insertUse = this.db.prepare(`INSERT OR IGNORE INTO usage_records (${USAGE_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)`);
         const insertSess = this.db.prepare(`INSERT OR IGNORE INTO sessions (session_id, task, initiator, status, workspace, metadata, summary, error_msg, participants, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)`);
+        const insertPad = this.db.prepare(`INSERT OR IGNORE INTO scratchpad_tasks (${SCRATCHPAD_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)`);
         this.db.exec('BEGIN');
         try {
             for (const m of jsonData.memory ?? [])
-                insertMem.run(m.key, m.namespace ?? 'default', JSON.stringify(m.value), m.updatedAt);
+                insertMem.run(m.key, m.namespace ?? 'default', isEncryptedMemory(m.value) ? m.value : this.sealValue(m.value), isEncryptedMemory(m.embedding) ? m.embedding : this.sealEmbedding(m.embedding), m.expiresAt ?? null, m.importance ?? null, m.accessedAt ?? null, sourceJson(m.source), m.updatedAt);
             for (const p of jsonData.policies ?? [])
                 insertPol.run(p.policyId, p.name, p.enabled ? 1 : 0, p.metadata ? JSON.stringify(p.metadata) : null, p.updatedAt);
             for (const a of jsonData.agents ?? [])
                 insertAg.run(a.agentId, a.name, JSON.stringify(a.capabilities), a.metadata ? JSON.stringify(a.metadata) : null, a.registrationKey, a.registeredAt, a.updatedAt);
             for (const s of jsonData.semantic ?? [])
-                insertSem.run(s.key, s.namespace ?? 'default', s.content, JSON.stringify(s.tokenFreq), s.tags.join(','), s.metadata ? JSON.stringify(s.metadata) : null, s.updatedAt);
+                insertSem.run(s.key, s.namespace ?? 'default', isEncryptedMemory(s.content) ? s.content : sealMemoryText(s.content, this.cipher), isEncryptedMemory(s.tokenFreq) ? s.tokenFreq : sealMemoryText(JSON.stringify(s.tokenFreq), this.cipher), s.tags.join(','), s.metadata ? JSON.stringify(s.metadata) : null, s.updatedAt);
+            for (const t of jsonData.facts ?? [])
+                insertFact.run(t.factId, t.namespace ?? 'default', t.subject, t.predicate, JSON.stringify(t.object), t.sourceSpan !== undefined ? JSON.stringify(t.sourceSpan) : null, t.confidence ?? null, t.createdAt, t.updatedAt);
             for (const f of jsonData.feedback ?? [])
                 insertFb.run(f.feedbackId, f.selectedAgent, f.recommendedAgent ?? null, f.rating ?? null, f.feedbackType, f.taskDescription, f.userComment ?? null, f.outcome ?? null, f.durationMs ?? null, f.sessionId ?? null, f.metadata ? JSON.stringify(f.metadata) : null, f.createdAt);
+            for (const u of jsonData.usage ?? [])
+                insertUse.run(...usageValues(u));
             for (const sess of jsonData.sessions ?? [])
                 insertSess.run(sess.sessionId, sess.task, sess.initiator, sess.status, sess.workspace ?? null, sess.metadata ? JSON.stringify(sess.metadata) : null, sess.summary ?? null, sess.error?.message ?? null, JSON.stringify(sess.participants), sess.createdAt, sess.updatedAt);
+            for (const task of jsonData.scratchpad ?? [])
+                insertPad.run(...scratchpadValues(task));
             this.db.exec('COMMIT');
         }
         catch (err) {
             this.db.exec('ROLLBACK');
             throw err;
         }
+        if ((jsonData.memory ?? []).some((m) => m.embedding !== undefined)) {
+            this.vectors?.reset();
+        }
     }
     close() {
         this.db.close();
     }
 }
-function rowToMemory(r) {
-    return { key: r.key, namespace: r.namespace === 'default' ? undefined : r.namespace, value: safeJsonParse(r.value, r.value), updatedAt: r.updated_at };
+// Memory namespaces are plain names without NUL, so the first one splits an id back into namespace and key.
+function vectorId(key, namespace) {
+    return `${namespace}\0${key}`;
+}
+function sourceJson(source) {
+    return source !== undefined ? JSON.stringify(source) : null;
+}
+function encryptOnce(stored, cipher) {
+    return isEncryptedMemory(stored) ? stored : cipher.encrypt(stored);
+}
+function rowToMemory(r, cipher) {
+    const value = openMemoryText(r.value, cipher);
+    const embedding = safeJsonParse(r.embedding !== null ? openMemoryText(r.embedding, cipher) : null, undefined);
+    return {
+        key: r.key,
+        namespace: r.namespace === 'default' ? undefined : r.namespace,
+        value: safeJsonParse(value, value),
+        ...(embedding !== undefined ? { embedding } : {}),
+        ...(r.expires_at !== null ? { expiresAt: r.expires_at } : {}),
+        ...(r.importance !== null ? { importance: r.importance } : {}),
+        ...(r.accessed_at !== null ? { accessedAt: r.accessed_at } : {}),
+        ...(r.source !== null ? { source: safeJsonParse(r.source, {}) } : {}),
+        updatedAt: r.updated_at,
+    };
 }
 function rowToPolicy(r) {
     return { policyId: r.policy_id, name: r.name, enabled: r.enabled !== 0, metadata: safeJsonParse(r.metadata, undefined), updatedAt: r.updated_at };
@@ -568,8 +1088,42 @@ function rowToPolicy(r) {
 function rowToAgent(r) {
     return { agentId: r.agent_id, name: r.name, capabilities: safeJsonParse(r.capabilities, []), metadata: safeJsonParse(r.metadata, undefined), registrationKey: r.registration_key, registeredAt: r.registered_at, updatedAt: r.updated_at };
 }
-function rowToSemantic(r) {
-    return { key: r.key, namespace: r.namespace === 'default' ? undefined : r.namespace, content: r.content, tags: r.tags ? r.tags.split(',').filter((t) => t.length > 0) : [], metadata: safeJsonParse(r.metadata, undefined), tokenFreq: safeJsonParse(r.token_freq, {}), updatedAt: r.updated_at };
+function rowToSemantic(r, cipher) {
+    return { key: r.key, namespace: r.namespace === 'default' ? undefined : r.namespace, content: openMemoryText(r.content, cipher), tags: r.tags ? r.tags.split(',').filter((t) => t.length > 0) : [], metadata: safeJsonParse(r.metadata, undefined), tokenFreq: safeJsonParse(r.token_freq !== null ? openMemoryText(r.token_freq, cipher) : null, {}), updatedAt: r.updated_at };
+}
+function rowToFact(r) {
+    return {
+        factId: r.fact_id,
+        subject: r.subject,
+        predicate: r.predicate,
+        object: safeJsonParse(r.object, r.object),
+        ...(r.namespace !== 'default' ? { namespace: r.namespace } : {}),
+        ...(r.source_span !== null ? { sourceSpan: safeJsonParse(r.source_span, { file: '' }) } : {}),
+        ...(r.confidence !== null ? { confidence: r.confidence } : {}),
+        createdAt: r.created_at,
+        updatedAt: r.updated_at,
+    };
+}
+const USAGE_COLUMNS = 'usage_id, provider, model, agent_id, trace_id, root_trace_id, project, input_tokens, output_tokens, latency_ms, cost_usd, success, recorded_at';
+function usageValues(u) {
+    return [u.usageId, u.provider, u.model ?? null, u.agentId ?? null, u.traceId ?? null, u.rootTraceId ?? null, u.project ?? null, u.inputTokens, u.outputTokens, u.latencyMs, u.costUsd ?? null, u.success ? 1 : 0, u.recordedAt];
+}
+function rowToUsage(r) {
+    return {
+        usageId: r.usage_id,
+        provider: r.provider,
+        ...(r.model !== null ? { model: r.model } : {}),
+        ...(r.agent_id !== null ? { agentId: r.agent_id } : {}),
+        ...(r.trace_id !== null ? { traceId: r.trace_id } : {}),
+        ...(r.root_trace_id !== null ? { rootTraceId: r.root_trace_id } : {}),
+        ...(r.project !== null ? { project: r.project } : {}),
+        inputTokens: r.input_tokens,
+        outputTokens: r.output_tokens,
+        latencyMs: r.latency_ms,
+        ...(r.cost_usd !== null ? { costUsd: r.cost_usd } : {}),
+        success: r.success === 1,
+        recordedAt: r.recorded_at,
+    };
 }
 function rowToFeedback(r) {
     return { feedbackId: r.feedback_id, selectedAgent: r.selected_agent, recommendedAgent: r.recommended_agent ?? undefined, rating: r.rating ?? undefined, feedbackType: r.feedback_type, taskDescription: r.task_description, userComment: r.user_comment ?? undefined, outcome: r.outcome ?? undefined, durationMs: r.duration_ms ?? undefined, sessionId: r.session_id ?? undefined, metadata: safeJsonParse(r.metadata, undefined), createdAt: r.created_at };
@@ -577,6 +1131,26 @@ function rowToFeedback(r) {
 function rowToSession(r) {
     return { sessionId: r.session_id, task: r.task, initiator: r.initiator, status: r.status, workspace: r.workspace ?? undefined, metadata: safeJsonParse(r.metadata, undefined), summary: r.summary ?? undefined, error: r.error_msg !== null ? { message: r.error_msg } : undefined, participants: safeJsonParse(r.participants, []), createdAt: r.created_at, updatedAt: r.updated_at };
 }
+const SCRATCHPAD_COLUMNS = 'session_id, task_id, title, paths, status, claimed_by, trace_id, claimed_at, lease_expires_at, result, created_at, updated_at';
+function scratchpadValues(t) {
+    return [t.sessionId, t.taskId, t.title, JSON.stringify(t.paths), t.status, t.claimedBy ?? null, t.traceId ?? null, t.claimedAt ?? null, t.leaseExpiresAt ?? null, t.result ?? null, t.createdAt, t.updatedAt];
+}
+function rowToScratchpadTask(r) {
+    return {
+        sessionId: r.session_id,
+        taskId: r.task_id,
+        title: r.title,
+        paths: safeJsonParse(r.paths, []),
+        status: r.status,
+        ...(r.claimed_by !== null ? { claimedBy: r.claimed_by } : {}),
+        ...(r.trace_id !== null ? { traceId: r.trace_id } : {}),
+        ...(r.claimed_at !== null ? { claimedAt: r.claimed_at } : {}),
+        ...(r.lease_expires_at !== null ? { leaseExpiresAt: r.lease_expires_at } : {}),
+        ...(r.result !== null ? { result: r.result } : {}),
+        createdAt: r.created_at,
+        updatedAt: r.updated_at,
+    };
+}
 function ensureActiveSession(s) {
     if (s.status !== 'active')
         throw new Error(`Session "${s.sessionId}" is not active`);
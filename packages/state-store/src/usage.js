@@ -0,0 +1,36 @@
+import { randomUUID } from 'node:crypto';
+export function normalizeUsage(input) {
+    if (typeof input.provider !== 'string' || input.provider.trim().length === 0) {
+        throw new Error('A usage record needs a non-empty provider.');
+    }
+    for (const field of ['inputTokens', 'outputTokens', 'latencyMs']) {
+        if (!Number.isFinite(input[field]) || input[field] < 0) {
+            throw new Error(`Usage ${field} must be a non-negative number, got ${String(input[field])}.`);
+        }
+    }
+    if (input.costUsd !== undefined && !(Number.isFinite(input.costUsd) && input.costUsd >= 0)) {
+        throw new Error(`Usage cost must be a non-negative number, got ${String(input.costUsd)}.`);
+    }
+    return {
+        usageId: input.usageId ?? randomUUID(),
+        provider: input.provider,
+        ...(input.model !== undefined ? { model: input.model } : {}),
+        ...(input.agentId !== undefined ? { agentId: input.agentId } : {}),
+        ...(input.traceId !== undefined ? { traceId: input.traceId } : {}),
+        ...(input.rootTraceId !== undefined ? { rootTraceId: input.rootTraceId } : {}),
+        ...(input.project !== undefined ? { project: input.project } : {}),
+        inputTokens: Math.round(input.inputTokens),
+        outputTokens: Math.round(input.outputTokens),
+        latencyMs: Math.round(input.latencyMs),
+        ...(input.costUsd !== undefined ? { costUsd: input.costUsd } : {}),
+        success: input.success,
+        recordedAt: input.recordedAt ?? new Date().toISOString(),
+    };
+}
+export function matchesUsage(record, query) {
+    return (query.since === undefined || record.recordedAt >= query.since)
+        && (query.provider === undefined || record.provider === query.provider)
+        && (query.agentId === undefined || record.agentId === query.agentId)
+        && (query.project === undefined || record.project === query.project)
+        && (query.traceId === undefined || record.traceId === query.traceId || record.rootTraceId === query.traceId);
+}
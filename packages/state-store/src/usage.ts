@@ -0,0 +1,72 @@
+import { randomUUID } from 'node:crypto';
+
+/** One provider call: what it ran for, the tokens it used, how long it took and what it cost. */
+export interface UsageRecord {
+  usageId: string;
+  provider: string;
+  model?: string;
+  agentId?: string;
+  traceId?: string;
+  /** The run the call belongs to when it ran inside another, e.g. an agent step of a workflow. */
+  rootTraceId?: string;
+  /** The workspace the call ran for. */
+  project?: string;
+  inputTokens: number;
+  outputTokens: number;
+  latencyMs: number;
+  /** USD; unset when the model has no known price. */
+  costUsd?: number;
+  success: boolean;
+  recordedAt: string;
+}
+
+export type UsageInput = Omit<UsageRecord, 'usageId' | 'recordedAt'> & { usageId?: string; recordedAt?: string };
+
+/** Exact-match filter; fields left out match anything. */
+export interface UsageQuery {
+  /** ISO timestamp; records before it are left out. */
+  since?: string;
+  provider?: string;
+  agentId?: string;
+  project?: string;
+  /** Matches the call's own trace or the run it belongs to. */
+  traceId?: string;
+  limit?: number;
+}
+
+export function normalizeUsage(input: UsageInput): UsageRecord {
+  if (typeof input.provider !== 'string' || input.provider.trim().length === 0) {
+    throw new Error('A usage record needs a non-empty provider.');
+  }
+  for (const field of ['inputTokens', 'outputTokens', 'latencyMs'] as const) {
+    if (!Number.isFinite(input[field]) || input[field] < 0) {
+      throw new Error(`Usage ${field} must be a non-negative number, got ${String(input[field])}.`);
+    }
+  }
+  if (input.costUsd !== undefined && !(Number.isFinite(input.costUsd) && input.costUsd >= 0)) {
+    throw new Error(`Usage cost must be a non-negative number, got ${String(input.costUsd)}.`);
+  }
+  return {
+    usageId: input.usageId ?? randomUUID(),
+    provider: input.provider,
+    ...(input.model !== undefined ? { model: input.model } : {}),
+    ...(input.agentId !== undefined ? { agentId: input.agentId } : {}),
+    ...(input.traceId !== undefined ? { traceId: input.traceId } : {}),
+    ...(input.rootTraceId !== undefined ? { rootTraceId: input.rootTraceId } : {}),
+    ...(input.project !== undefined ? { project: input.project } : {}),
+    inputTokens: Math.round(input.inputTokens),
+    outputTokens: Math.round(input.outputTokens),
+    latencyMs: Math.round(input.latencyMs),
+    ...(input.costUsd !== undefined ? { costUsd: input.costUsd } : {}),
+    success: input.success,
+    recordedAt: input.recordedAt ?? new Date().toISOString(),
+  };
+}
+
+export function matchesUsage(record: UsageRecord, query: UsageQuery): boolean {
+  return (query.since === undefined || record.recordedAt >= query.since)
+    && (query.provider === undefined || record.provider === query.provider)
+    && (query.agentId === undefined || record.agentId === query.agentId)
+    && (query.project === undefined || record.project === query.project)
+    && (query.traceId === undefined || record.traceId === query.traceId || record.rootTraceId === query.traceId);
+}
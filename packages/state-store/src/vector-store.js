@@ -0,0 +1,179 @@
+import { appendFileSync, closeSync, existsSync, mkdirSync, openSync, readdirSync, readFileSync, readSync, renameSync, rmSync, statSync, writeFileSync } from 'node:fs';
+import { join } from 'node:path';
+import { createHnswIndex, deserializeHnswIndex } from './hnsw.js';
+const JOURNAL_FILE = 'journal.jsonl';
+const SNAPSHOT_PATTERN = /^(\d+)\.hnsw$/;
+const MIN_OPS_BEFORE_SNAPSHOT = 1000;
+/**
+ * HNSW graphs over the stored vectors, one per vector length, kept in a directory as a snapshot per
+ * graph plus a journal of the puts and deletes since. Writes only append to the journal; the graphs
+ * are loaded on the first search and catch up with the journal, including lines other processes
+ * appended, before every later one. The journal is folded into fresh snapshots once it outgrows a
+ * quarter of the index, and the index is rebuilt from the source whenever its size disagrees with it.
+ */
+export class VectorStore {
+    dir;
+    source;
+    graphs;
+    journal = { ino: -1, offset: 0, ops: 0 };
+    constructor(dir, source) {
+        this.dir = dir;
+        this.source = source;
+    }
+    put(id, vector) {
+        this.append({ put: id, vector });
+    }
+    remove(id) {
+        this.append({ del: id });
+    }
+    search(vector, k, filter) {
+        const graphs = this.graphs === undefined ? this.load() : this.catchUp();
+        const graph = graphs.get(vector.length);
+        if (graph === undefined) {
+            if (graphs.size > 0) {
+                throw new Error(`No stored embeddings have ${vector.length} dimensions (stored: ${[...graphs.keys()].join(', ')}). Embed the query with the model that embedded the entries.`);
+            }
+            return [];
+        }
+        return graph.search(vector, k, filter);
+    }
+    /** Drops the index; the next search rebuilds it from the source. */
+    reset() {
+        rmSync(this.dir, { recursive: true, force: true });
+        this.graphs = undefined;
+    }
+    get journalPath() {
+        return join(this.dir, JOURNAL_FILE);
+    }
+    append(op) {
+        // Until something searches there is no index to keep current; the first search builds it.
+        if (!existsSync(this.journalPath)) {
+            return;
+        }
+        appendFileSync(this.journalPath, `${JSON.stringify(op)}\n`, 'utf8');
+        if (this.graphs !== undefined) {
+            this.catchUp();
+        }
+    }
+    load() {
+        this.graphs = undefined;
+        const graphs = new Map();
+        if (existsSync(this.journalPath)) {
+            try {
+                for (const file of readdirSync(this.dir)) {
+                    const match = SNAPSHOT_PATTERN.exec(file);
+                    if (match !== null) {
+                        graphs.set(Number(match[1]), deserializeHnswIndex(readFileSync(join(this.dir, file))));
+                    }
+                }
+                this.graphs = graphs;
+                this.journal = { ino: statSync(this.journalPath).ino, offset: 0, ops: 0 };
+                this.catchUp();
+            }
+            catch {
+                this.graphs = undefined;
+            }
+        }
+        if (this.graphs === undefined || liveCount(this.graphs) !== this.source.count()) {
+            return this.rebuild();
+        }
+        return this.graphs;
+    }
+    rebuild() {
+        const graphs = new Map();
+        for (const { id, vector } of this.source.load()) {
+            applyOp(graphs, { put: id, vector });
+        }
+        this.graphs = graphs;
+        this.writeSnapshot();
+        return graphs;
+    }
+    catchUp() {
+        const graphs = this.graphs;
+        let stats;
+        try {
+            stats = statSync(this.journalPath);
+        }
+        catch {
+            return this.load();
+        }
+        // Another process snapshotted and started a new journal.
+        if (stats.ino !== this.journal.ino || stats.size < this.journal.offset) {
+            return this.load();
+        }
+        if (stats.size === this.journal.offset) {
+            return graphs;
+        }
+        const tail = Buffer.alloc(stats.size - this.journal.offset);
+        const fd = openSync(this.journalPath, 'r');
+        try {
+            readSync(fd, tail, 0, tail.length, this.journal.offset);
+        }
+        finally {
+            closeSync(fd);
+        }
+        // A line still being written is left for the next catch-up.
+        const complete = tail.lastIndexOf(0x0a) + 1;
+        for (const line of tail.toString('utf8', 0, complete).split('\n')) {
+            if (line.length > 0) {
+                applyOp(graphs, JSON.parse(line));
+                this.journal.ops += 1;
+            }
+        }
+        this.journal.offset += complete;
+        if (this.journal.ops > Math.max(MIN_OPS_BEFORE_SNAPSHOT, liveCount(graphs) / 4)) {
+            this.writeSnapshot();
+        }
+        return graphs;
+    }
+    writeSnapshot() {
+        const graphs = this.graphs;
+        mkdirSync(this.dir, { recursive: true });
+        for (const [dimensions, graph] of graphs) {
+            if (graph.size === 0) {
+                graphs.delete(dimensions);
+                continue;
+            }
+            // Removed vectors only slow the search down once they are a sizeable share of the graph.
+            const compacted = graph.removed > graph.size / 4 ? graph.compact() : graph;
+            graphs.set(dimensions, compacted);
+            writeAtomically(join(this.dir, `${dimensions}.hnsw`), compacted.serialize());
+        }
+        for (const file of readdirSync(this.dir)) {
+            const match = SNAPSHOT_PATTERN.exec(file);
+            if (match !== null && !graphs.has(Number(match[1]))) {
+                rmSync(join(this.dir, file), { force: true });
+            }
+        }
+        writeAtomically(this.journalPath, Buffer.alloc(0));
+        this.journal = { ino: statSync(this.journalPath).ino, offset: 0, ops: 0 };
+    }
+}
+function applyOp(graphs, op) {
+    const id = 'put' in op ? op.put : op.del;
+    for (const [dimensions, graph] of graphs) {
+        if (!('put' in op) || dimensions !== op.vector.length) {
+            graph.remove(id);
+        }
+    }
+    if ('put' in op) {
+        let graph = graphs.get(op.vector.length);
+        if (graph === undefined) {
+            graph = createHnswIndex();
+            graphs.set(op.vector.length, graph);
+        }
+        graph.upsert(id, op.vector);
+    }
+}
+function liveCount(graphs) {
+    let count = 0;
+    for (const graph of graphs.values()) {
+        count += graph.size;
+    }
+    return count;
+}
+function writeAtomically(path, data) {
+    const temporary = `${path}.${process.pid}.tmp`;
+    writeFileSync(temporary, data);
+    renameSync(temporary, path);
+}
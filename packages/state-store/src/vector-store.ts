@@ -0,0 +1,201 @@
+import { appendFileSync, closeSync, existsSync, mkdirSync, openSync, readdirSync, readFileSync, readSync, renameSync, rmSync, statSync, writeFileSync } from 'node:fs';
+import { join } from 'node:path';
+import { createHnswIndex, deserializeHnswIndex, type HnswIndex, type VectorMatch } from './hnsw.js';
+
+/** Where the vectors come from when the index has to be rebuilt. */
+export interface VectorSource {
+  load(): Iterable<{ id: string; vector: number[] }>;
+  count(): number;
+}
+
+type JournalOp = { put: string; vector: number[] } | { del: string };
+
+const JOURNAL_FILE = 'journal.jsonl';
+const SNAPSHOT_PATTERN = /^(\d+)\.hnsw$/;
+const MIN_OPS_BEFORE_SNAPSHOT = 1000;
+
+/**
+ * HNSW graphs over the stored vectors, one per vector length, kept in a directory as a snapshot per
+ * graph plus a journal of the puts and deletes since. Writes only append to the journal; the graphs
+ * are loaded on the first search and catch up with the journal, including lines other processes
+ * appended, before every later one. The journal is folded into fresh snapshots once it outgrows a
+ * quarter of the index, and the index is rebuilt from the source whenever its size disagrees with it.
+ */
+export class VectorStore {
+  private readonly dir: string;
+  private readonly source: VectorSource;
+  private graphs: Map<number, HnswIndex> | undefined;
+  private journal = { ino: -1, offset: 0, ops: 0 };
+
+  constructor(dir: string, source: VectorSource) {
+    this.dir = dir;
+    this.source = source;
+  }
+
+  put(id: string, vector: number[]): void {
+    this.append({ put: id, vector });
+  }
+
+  remove(id: string): void {
+    this.append({ del: id });
+  }
+
+  search(vector: number[], k: number, filter?: (id: string) => boolean): VectorMatch[] {
+    const graphs = this.graphs === undefined ? this.load() : this.catchUp();
+    const graph = graphs.get(vector.length);
+    if (graph === undefined) {
+      if (graphs.size > 0) {
+        throw new Error(`No stored embeddings have ${vector.length} dimensions (stored: ${[...graphs.keys()].join(', ')}). Embed the query with the model that embedded the entries.`);
+      }
+      return [];
+    }
+    return graph.search(vector, k, filter);
+  }
+
+  /** Drops the index; the next search rebuilds it from the source. */
+  reset(): void {
+    rmSync(this.dir, { recursive: true, force: true });
+    this.graphs = undefined;
+  }
+
+  private get journalPath(): string {
+    return join(this.dir, JOURNAL_FILE);
+  }
+
+  private append(op: JournalOp): void {
+    // Until something searches there is no index to keep current; the first search builds it.
+    if (!existsSync(this.journalPath)) {
+      return;
+    }
+    appendFileSync(this.journalPath, `${JSON.stringify(op)}\n`, 'utf8');
+    if (this.graphs !== undefined) {
+      this.catchUp();
+    }
+  }
+
+  private load(): Map<number, HnswIndex> {
+    this.graphs = undefined;
+    const graphs = new Map<number, HnswIndex>();
+    if (existsSync(this.journalPath)) {
+      try {
+        for (const file of readdirSync(this.dir)) {
+          const match = SNAPSHOT_PATTERN.exec(file);
+          if (match !== null) {
+            graphs.set(Number(match[1]), deserializeHnswIndex(readFileSync(join(this.dir, file))));
+          }
+        }
+        this.graphs = graphs;
+        this.journal = { ino: statSync(this.journalPath).ino, offset: 0, ops: 0 };
+        this.catchUp();
+      } catch {
+        this.graphs = undefined;
+      }
+    }
+    if (this.graphs === undefined || liveCount(this.graphs) !== this.source.count()) {
+      return this.rebuild();
+    }
+    return this.graphs;
+  }
+
+  private rebuild(): Map<number, HnswIndex> {
+    const graphs = new Map<number, HnswIndex>();
+    for (const { id, vector } of this.source.load()) {
+      applyOp(graphs, { put: id, vector });
+    }
+    this.graphs = graphs;
+    this.writeSnapshot();
+    return graphs;
+  }
+
+  private catchUp(): Map<number, HnswIndex> {
+    const graphs = this.graphs!;
+    let stats;
+    try {
+      stats = statSync(this.journalPath);
+    } catch {
+      return this.load();
+    }
+    // Another process snapshotted and started a new journal.
+    if (stats.ino !== this.journal.ino || stats.size < this.journal.offset) {
+      return this.load();
+    }
+    if (stats.size === this.journal.offset) {
+      return graphs;
+    }
+
+    const tail = Buffer.alloc(stats.size - this.journal.offset);
+    const fd = openSync(this.journalPath, 'r');
+    try {
+      readSync(fd, tail, 0, tail.length, this.journal.offset);
+    } finally {
+      closeSync(fd);
+    }
+    // A line still being written is left for the next catch-up.
+    const complete = tail.lastIndexOf(0x0a) + 1;
+    for (const line of tail.toString('utf8', 0, complete).split('\n')) {
+      if (line.length > 0) {
+        applyOp(graphs, JSON.parse(line) as JournalOp);
+        this.journal.ops += 1;
+      }
+    }
+    this.journal.offset += complete;
+    if (this.journal.ops > Math.max(MIN_OPS_BEFORE_SNAPSHOT, liveCount(graphs) / 4)) {
+      this.writeSnapshot();
+    }
+    return graphs;
+  }
+
+  private writeSnapshot(): void {
+    const graphs = this.graphs!;
+    mkdirSync(this.dir, { recursive: true });
+    for (const [dimensions, graph] of graphs) {
+      if (graph.size === 0) {
+        graphs.delete(dimensions);
+        continue;
+      }
+      // Removed vectors only slow the search down once they are a sizeable share of the graph.
+      const compacted = graph.removed > graph.size / 4 ? graph.compact() : graph;
+      graphs.set(dimensions, compacted);
+      writeAtomically(join(this.dir, `${dimensions}.hnsw`), compacted.serialize());
+    }
+    for (const file of readdirSync(this.dir)) {
+      const match = SNAPSHOT_PATTERN.exec(file);
+      if (match !== null && !graphs.has(Number(match[1]))) {
+        rmSync(join(this.dir, file), { force: true });
+      }
+    }
+    writeAtomically(this.journalPath, Buffer.alloc(0));
+    this.journal = { ino: statSync(this.journalPath).ino, offset: 0, ops: 0 };
+  }
+}
+
+function applyOp(graphs: Map<number, HnswIndex>, op: JournalOp): void {
+  const id = 'put' in op ? op.put : op.del;
+  for (const [dimensions, graph] of graphs) {
+    if (!('put' in op) || dimensions !== op.vector.length) {
+      graph.remove(id);
+    }
+  }
+  if ('put' in op) {
+    let graph = graphs.get(op.vector.length);
+    if (graph === undefined) {
+      graph = createHnswIndex();
+      graphs.set(op.vector.length, graph);
+    }
+    graph.upsert(id, op.vector);
+  }
+}
+
+function liveCount(graphs: Map<number, HnswIndex>): number {
+  let count = 0;
+  for (const graph of graphs.values()) {
+    count += graph.size;
+  }
+  return count;
+}
+
+function writeAtomically(path: string, data: Buffer): void {
+  const temporary = `${path}.${process.pid}.tmp`;
+  writeFileSync(temporary, data);
+  renameSync(temporary, path);
+}
@@ -0,0 +1,78 @@
+import { describe, expect, it } from 'vitest';
+import { createHnswIndex, deserializeHnswIndex } from '../src/hnsw.js';
+
+// Deterministic vectors and graph levels, so the recall below does not vary between runs.
+function seededRandom(seed: number): () => number {
+  let state = seed;
+  return () => {
+    state = (state * 1103515245 + 12345) % 2147483648;
+    return state / 2147483648;
+  };
+}
+
+function randomVectors(count: number, dimensions: number, random: () => number): number[][] {
+  return Array.from({ length: count }, () => Array.from({ length: dimensions }, () => random() * 2 - 1));
+}
+
+function bruteForce(vectors: number[][], query: number[], k: number): string[] {
+  const cosine = (left: number[], right: number[]) => {
+    let dot = 0;
+    let leftNorm = 0;
+    let rightNorm = 0;
+    left.forEach((value, index) => {
+      dot += value * right[index]!;
+      leftNorm += value * value;
+      rightNorm += right[index]! * right[index]!;
+    });
+    return dot / Math.sqrt(leftNorm * rightNorm);
+  };
+  return vectors
+    .map((vector, index) => ({ id: `v${index}`, score: cosine(vector, query) }))
+    .sort((left, right) => right.score - left.score)
+    .slice(0, k)
+    .map((match) => match.id);
+}
+
+describe('HNSW index', () => {
+  it('finds nearly all of the exact top-k neighbours', () => {
+    const random = seededRandom(7);
+    const vectors = randomVectors(2000, 16, random);
+    const index = createHnswIndex({ random });
+    vectors.forEach((vector, position) => index.upsert(`v${position}`, vector));
+
+    let found = 0;
+    for (const query of randomVectors(50, 16, random)) {
+      const expected = bruteForce(vectors, query, 10);
+      found += index.search(query, 10).filter((match) => expected.includes(match.id)).length;
+    }
+    expect(found / 500).toBeGreaterThan(0.95);
+  });
+
+  it('replaces, removes, filters and survives a serialization round trip', () => {
+    const random = seededRandom(11);
+    const vectors = randomVectors(300, 8, random);
+    const index = createHnswIndex({ random });
+    vectors.forEach((vector, position) => index.upsert(`v${position}`, vector));
+
+    expect(index.search(vectors[42]!, 1)[0]).toMatchObject({ id: 'v42' });
+    expect(index.search(vectors[42]!, 1)[0]!.score).toBeCloseTo(1, 5);
+    index.upsert('v42', vectors[7]!);
+    expect(index.search(vectors[7]!, 2).map((match) => match.id).sort()).toEqual(['v42', 'v7']);
+    expect(index.remove('v7')).toBe(true);
+    expect(index.remove('v7')).toBe(false);
+    expect(index.size).toBe(299);
+    expect(index.removed).toBe(2);
+    expect(index.search(vectors[7]!, 1)[0]).toMatchObject({ id: 'v42' });
+    expect(index.search(vectors[0]!, 5, (id) => id.endsWith('9')).every((match) => match.id.endsWith('9'))).toBe(true);
+    expect(index.search(vectors[0]!, 5, (id) => id.endsWith('9'))).toHaveLength(5);
+    expect(() => index.search([1, 2], 1)).toThrow('Vector has 2 dimensions; this index holds 8.');
+
+    const restored = deserializeHnswIndex(index.serialize());
+    expect(restored.size).toBe(299);
+    expect(restored.search(vectors[100]!, 5)).toEqual(index.search(vectors[100]!, 5));
+    const compacted = restored.compact();
+    expect(compacted.removed).toBe(0);
+    expect(compacted.search(vectors[100]!, 1)[0]).toMatchObject({ id: 'v100' });
+    expect(() => deserializeHnswIndex(Buffer.from('nope'))).toThrow('Not an HNSW index file.');
+  });
+});
@@ -1,4 +1,4 @@
-import { mkdirSync } from 'node:fs';
+import { existsSync, mkdirSync } from 'node:fs';
 import { rm } from 'node:fs/promises';
 import { join } from 'node:path';
 import { afterEach, describe, expect, it } from 'vitest';
@@ -64,6 +64,82 @@ describe('SqliteStateStore', () => {
     expect(await s.deleteMemory('x', 'tmp')).toBe(false);
   });
 
+  it('stores a memory embedding until the entry is stored again without one', async () => {
+    const dir = createTempDir(); tempDirs.push(dir);
+    const s = store(dir);
+    await s.storeMemory({ key: 'chunk', namespace: 'embeddings', value: { file: 'a.rb' }, embedding: [0.25, -0.5, 1] });
+    expect((await s.getMemory('chunk', 'embeddings'))?.embedding).toEqual([0.25, -0.5, 1]);
+    expect((await s.searchMemory('a.rb', 'embeddings'))[0]?.embedding).toEqual([0.25, -0.5, 1]);
+    await s.storeMemory({ key: 'chunk', namespace: 'embeddings', value: { file: 'b.rb' } });
+    expect(await s.getMemory('chunk', 'embeddings')).not.toHaveProperty('embedding');
+
+    const dbFile = join(dir, 'legacy.db');
+    const { DatabaseSync } = await import('node:sqlite');
+    const legacy = new DatabaseSync(dbFile);
+    legacy.exec(`CREATE TABLE memory_items (id INTEGER PRIMARY KEY AUTOINCREMENT, key TEXT NOT NULL, namespace TEXT NOT NULL DEFAULT 'default', value TEXT NOT NULL, updated_at TEXT NOT NULL, UNIQUE(key, namespace))`);
+    legacy.close();
+    const upgraded = new SqliteStateStore({ dbFile });
+    await upgraded.storeMemory({ key: 'k', value: 'v', embedding: [1] });
+    expect((await upgraded.listMemory())[0]?.embedding).toEqual([1]);
+  });
+
+  it('finds memory by embedding through an HNSW index persisted next to the database', async () => {
+    const dir = createTempDir(); tempDirs.push(dir);
+    const s = store(dir);
+    await s.storeMemory({ key: 'retry', namespace: 'embeddings', value: 'retry backoff', embedding: [1, 0, 0] });
+    await s.storeMemory({ key: 'parser', namespace: 'embeddings', value: 'parser', embedding: [0, 1, 0] });
+    await s.storeMemory({ key: 'note', namespace: 'notes', value: 'retry note', embedding: [0.9, 0.1, 0] });
+    await s.storeMemory({ key: 'plain', value: 'no vector' });
+
+    const all = await s.searchMemoryByVector([1, 0.05, 0], { k: 2 });
+    expect(all.map((entry) => entry.key)).toEqual(['retry', 'note']);
+    expect(all[0]?.score).toBeCloseTo(0.9988, 3);
+    expect((await s.searchMemoryByVector([1, 0.05, 0], { namespace: 'embeddings' })).map((entry) => entry.key)).toEqual(['retry', 'parser']);
+    expect(await s.searchMemoryByVector([1, 0.05, 0], { namespace: 'embeddings', minScore: 0.5 })).toHaveLength(1);
+    await expect(s.searchMemoryByVector([1, 0])).rejects.toThrow('No stored embeddings have 2 dimensions (stored: 3)');
+    expect(existsSync(join(dir, '.automatosx', 'runtime', 'state.db.vectors', '3.hnsw'))).toBe(true);
+
+    // Writes through another instance reach the loaded index through the journal.
+    const other = store(dir);
+    await other.deleteMemory('retry', 'embeddings');
+    await other.storeMemory({ key: 'note', namespace: 'notes', value: 'no longer embedded' });
+    await other.storeMemory({ key: 'lexer', namespace: 'embeddings', value: 'lexer', embedding: [0.8, 0.6, 0] });
+    expect((await s.searchMemoryByVector([1, 0, 0])).map((entry) => entry.key)).toEqual(['lexer', 'parser']);
+    expect((await store(dir).searchMemoryByVector([0, 1, 0], { k: 1 }))[0]).toMatchObject({ key: 'parser', namespace: 'embeddings', score: 1 });
+  });
+
+  it('carries embeddings along when memory namespaces are copied and merged', async () => {
+    const dir = createTempDir(); tempDirs.push(dir);
+    const s = store(dir);
+    await s.storeMemory({ key: 'a', namespace: 'agent-one', value: 'a', embedding: [1, 0] });
+    await s.storeMemory({ key: 'b', namespace: 'agent-two', value: 'b', embedding: [0, 1] });
+    expect(await s.searchMemoryByVector([1, 0], { namespace: 'agent-one' })).toHaveLength(1);
+
+    await s.copyMemoryNamespace('agent-one', 'shared');
+    await s.mergeMemoryNamespaces(['agent-two'], 'shared');
+    expect((await s.searchMemoryByVector([1, 0.1], { namespace: 'shared' })).map((entry) => entry.key)).toEqual(['a', 'b']);
+    expect(await s.searchMemoryByVector([0, 1], { namespace: 'agent-two' })).toEqual([]);
+    expect(await s.listMemoryNamespaces()).toMatchObject([
+      { namespace: 'agent-one', entries: 1, embedded: 1 },
+      { namespace: 'shared', entries: 2, embedded: 2 },
+    ]);
+  });
+
+  it('drops pruned entries from the vector index and keeps access out of the full-text index', async () => {
+    const dir = createTempDir(); tempDirs.push(dir);
+    const s = store(dir);
+    await s.storeMemory({ key: 'stale', value: 'stale retry note', embedding: [1, 0] });
+    await new Promise((resolve) => setTimeout(resolve, 5));
+    await s.storeMemory({ key: 'fresh', value: 'fresh retry note', embedding: [0.9, 0.1] });
+    expect(await s.searchMemoryByVector([1, 0])).toHaveLength(2);
+
+    await new Promise((resolve) => setTimeout(resolve, 5));
+    expect((await s.getMemory('stale'))?.accessedAt).toBeDefined();
+    expect(await s.pruneMemory({ maxEntries: 1 })).toMatchObject([{ key: 'fresh', reason: 'capacity' }]);
+    expect((await s.searchMemoryByVector([1, 0])).map((entry) => entry.key)).toEqual(['stale']);
+    expect((await s.searchMemory('retry')).map((entry) => entry.key)).toEqual(['stale']);
+  });
+
   // -------------------------------------------------------------------------
   // Agents
   // -------------------------------------------------------------------------
@@ -161,6 +237,26 @@ describe('SqliteStateStore', () => {
     expect(limited).toHaveLength(1);
   });
 
+  it('records usage and filters it by provider, agent, trace and date', async () => {
+    const dir = createTempDir(); tempDirs.push(dir);
+    const s = store(dir);
+    await s.recordUsage({ provider: 'claude', model: 'claude-sonnet-4-5', agentId: 'arch', traceId: 't-1', inputTokens: 1200, outputTokens: 300, latencyMs: 850, costUsd: 0.0081, success: true, recordedAt: '2026-01-01T00:00:00.000Z' });
+    await s.recordUsage({ provider: 'gemini', agentId: 'qa', traceId: 't-2', rootTraceId: 't-1', inputTokens: 40, outputTokens: 10, latencyMs: 120, success: false });
+    await s.recordUsage({ provider: 'claude', agentId: 'qa', project: '/repo', inputTokens: 10, outputTokens: 5, latencyMs: 90, costUsd: 0.0001, success: true });
+
+    const all = await s.listUsage();
+    expect(all).toHaveLength(3);
+    expect(all[2]).toMatchObject({ provider: 'claude', model: 'claude-sonnet-4-5', inputTokens: 1200, costUsd: 0.0081, success: true });
+    expect(all.find((u) => u.provider === 'gemini')?.costUsd).toBeUndefined();
+
+    expect(await s.listUsage({ provider: 'claude' })).toHaveLength(2);
+    expect(await s.listUsage({ agentId: 'qa', project: '/repo' })).toHaveLength(1);
+    expect((await s.listUsage({ traceId: 't-1' })).map((u) => u.provider).sort()).toEqual(['claude', 'gemini']);
+    expect(await s.listUsage({ since: '2026-06-01T00:00:00.000Z' })).toHaveLength(2);
+    expect(await s.listUsage({ limit: 1 })).toHaveLength(1);
+    await expect(s.recordUsage({ provider: 'claude', inputTokens: -1, outputTokens: 0, latencyMs: 0, success: true })).rejects.toThrow('inputTokens');
+  });
+
   // -------------------------------------------------------------------------
   // Sessions
   // -------------------------------------------------------------------------
@@ -1,10 +1,11 @@
 import { mkdirSync } from 'node:fs';
-import { rm, stat } from 'node:fs/promises';
+import { randomBytes } from 'node:crypto';
+import { readFile, readdir, rm, stat } from 'node:fs/promises';
 import { execFile } from 'node:child_process';
 import { join } from 'node:path';
 import { promisify } from 'node:util';
 import { afterEach, describe, expect, it } from 'vitest';
-import { createStateStore } from '../src/index.js';
+import { createMemoryCipher, createStateStore, parseScopedNamespace, projectScopeId, scopedNamespace } from '../src/index.js';
 
 const execFileAsync = promisify(execFile);
 
@@ -137,6 +138,217 @@ describe('state store', () => {
     expect(await store.getMemory('latest', 'release')).toBeUndefined();
   });
 
+  it('lists, copies and merges memory namespaces in both backends', async () => {
+    for (const backend of ['sqlite', 'json'] as const) {
+      const tempDir = createTempDir();
+      tempDirs.push(tempDir);
+      const store = createStateStore({ basePath: tempDir, backend });
+      const notes = scopedNamespace('notes', { project: tempDir, agent: 'reviewer' })!;
+      expect(notes).toMatch(/^@project:state-store-[\w-]+-[0-9a-f]{8}\/@agent:reviewer\/notes$/);
+      expect(parseScopedNamespace(notes)).toEqual({ project: projectScopeId(tempDir), agent: 'reviewer', namespace: 'notes' });
+      expect(scopedNamespace('notes')).toBe('notes');
+
+      await store.storeMemory({ namespace: notes, key: 'style', value: 'tabs' });
+      await store.storeMemory({ namespace: notes, key: 'retry', value: 'old' });
+      await new Promise((resolve) => setTimeout(resolve, 5));
+      await store.storeMemory({ namespace: 'scratch', key: 'retry', value: 'new' });
+      await store.storeMemory({ key: 'global', value: 'shared' });
+
+      expect(await store.copyMemoryNamespace(notes, 'team')).toEqual({ copied: 2, skipped: 0 });
+      expect(await store.copyMemoryNamespace('scratch', 'team')).toEqual({ copied: 0, skipped: 1 });
+      expect((await store.getMemory('retry', 'team'))?.value).toBe('old');
+      expect((await store.getMemory('style', 'team'))?.updatedAt).toBe((await store.getMemory('style', notes))?.updatedAt);
+
+      expect(await store.mergeMemoryNamespaces(['scratch', notes], 'team')).toEqual({ copied: 1, skipped: 2, deleted: 3 });
+      expect((await store.getMemory('retry', 'team'))?.value).toBe('new');
+      expect((await store.listMemoryNamespaces()).map(({ namespace, entries }) => ({ namespace, entries }))).toEqual([
+        { namespace: 'default', entries: 1 },
+        { namespace: 'team', entries: 2 },
+      ]);
+      await expect(store.copyMemoryNamespace('team', 'team')).rejects.toThrow('Namespace "team" cannot be copied or merged into itself.');
+    }
+  });
+
+  it('expires, decays, and evicts memory by retention policy in both backends', async () => {
+    const tick = () => new Promise((resolve) => setTimeout(resolve, 5));
+    for (const backend of ['sqlite', 'json'] as const) {
+      const tempDir = createTempDir();
+      tempDirs.push(tempDir);
+      const store = createStateStore({ basePath: tempDir, backend });
+      await store.storeMemory({ key: 'session', value: 'token', expiresAt: new Date(Date.now() - 1000).toISOString() });
+      await store.storeMemory({ key: 'hunch', value: 'maybe', importance: 0.4 });
+      await store.storeMemory({ key: 'old', value: 'first' });
+      await tick();
+      await store.storeMemory({ key: 'recent', value: 'second' });
+      await tick();
+      await store.storeMemory({ key: 'kept', value: 'third', importance: 1 });
+      await tick();
+
+      expect(await store.getMemory('session')).toBeUndefined();
+      expect((await store.listMemory()).map((entry) => entry.key)).not.toContain('session');
+      expect((await store.getMemory('old'))?.accessedAt).toBeDefined();
+      await expect(store.storeMemory({ key: 'bad', value: 1, importance: 2 })).rejects.toThrow('Memory importance must be between 0 and 1, got 2.');
+
+      const policy = { importanceHalfLifeDays: 30, minImportance: 0.2, maxEntries: 2 };
+      const now = new Date(Date.now() + 60 * 86_400_000);
+      const planned = await store.pruneMemory(policy, { now, dryRun: true });
+      expect(planned.map(({ key, reason }) => ({ key, reason })).sort((left, right) => left.key.localeCompare(right.key))).toEqual([
+        { key: 'hunch', reason: 'importance' },
+        { key: 'recent', reason: 'capacity' },
+        { key: 'session', reason: 'expired' },
+      ]);
+      expect(planned.find((entry) => entry.key === 'hunch')?.importance).toBeCloseTo(0.1, 3);
+      expect(await store.listMemory()).toHaveLength(4);
+
+      expect(await store.pruneMemory(policy, { now })).toHaveLength(3);
+      expect((await store.listMemory()).map((entry) => entry.key).sort()).toEqual(['kept', 'old']);
+    }
+  });
+
+  it('encrypts memory values, embeddings and semantic items at rest in both backends', async () => {
+    const key = randomBytes(32);
+    for (const backend of ['sqlite', 'json'] as const) {
+      const tempDir = createTempDir();
+      tempDirs.push(tempDir);
+      const runtimeDir = join(tempDir, '.automatosx', 'runtime');
+      const onDisk = async () => (await Promise.all((await readdir(runtimeDir, { recursive: true, withFileTypes: true }))
+        .filter((entry) => entry.isFile())
+        .map((entry) => readFile(join(entry.parentPath, entry.name), 'latin1')))).join('');
+      const plain = createStateStore({ basePath: tempDir, backend });
+      await plain.storeMemory({ key: 'legacy', value: { note: 'plaintext-before-encryption' }, embedding: [0, 1] });
+      await plain.storeSemantic({ key: 'runbook', content: 'Restart the quillwort daemon' });
+
+      const store = createStateStore({ basePath: tempDir, backend, encryption: createMemoryCipher(key) });
+      expect(await store.encryptMemory()).toEqual({ encrypted: 2 });
+      expect(await store.encryptMemory()).toEqual({ encrypted: 0 });
+      expect(await onDisk()).not.toContain('plaintext-before-encryption');
+      expect(await onDisk()).not.toContain('quillwort');
+      await store.storeMemory({ key: 'token', namespace: 'secrets', value: 'hunter2-credential', embedding: [1, 0] });
+      expect((await store.getMemory('token', 'secrets'))?.value).toBe('hunter2-credential');
+      expect((await store.getMemory('legacy'))?.value).toEqual({ note: 'plaintext-before-encryption' });
+      expect((await store.searchMemory('hunter2')).map((entry) => entry.key)).toEqual(['token']);
+      expect((await store.searchMemoryByVector([1, 0.1]))[0]).toMatchObject({ key: 'token', embedding: [1, 0] });
+      await store.storeSemantic({ key: 'vault', namespace: 'secrets', content: 'The marmalade passphrase opens the vault', tags: ['ops'] });
+      expect((await store.searchSemantic('marmalade passphrase', { minSimilarity: 0.1 })).map((entry) => entry.key)).toEqual(['vault']);
+      expect((await store.getSemantic('runbook'))?.content).toBe('Restart the quillwort daemon');
+      expect(await onDisk()).not.toContain('hunter2-credential');
+      expect(await onDisk()).not.toContain('marmalade');
+      expect((await store.listMemory()).map((entry) => entry.key).sort()).toEqual(['legacy', 'token']);
+
+      await expect(createStateStore({ basePath: tempDir, backend }).getMemory('token', 'secrets')).rejects.toThrow('Memory is encrypted at rest and this store has no key to read it.');
+      await expect(createStateStore({ basePath: tempDir, backend, encryption: createMemoryCipher(randomBytes(32)) }).getMemory('token', 'secrets'))
+        .rejects.toThrow('Memory could not be decrypted: the key is not the one it was encrypted with.');
+      await expect(createStateStore({ basePath: tempDir, backend }).encryptMemory()).rejects.toThrow('This store has no memory encryption key.');
+    }
+  });
+
+  it('keeps the source of memory entries through reads, copies and imports in both backends', async () => {
+    for (const backend of ['sqlite', 'json'] as const) {
+      const tempDir = createTempDir();
+      tempDirs.push(tempDir);
+      const store = createStateStore({ basePath: tempDir, backend });
+      const source = { file: 'src/billing.ts', startLine: 4, endLine: 9, contentHash: 'abc123', sessionId: 'session-1', agent: 'reviewer', provider: 'claude' };
+      expect((await store.storeMemory({ key: 'retry', namespace: 'notes', value: 'billing retries three times', source })).source).toEqual(source);
+      expect((await store.getMemory('retry', 'notes'))?.source).toEqual(source);
+      expect((await store.searchMemory('retries'))[0]?.source).toEqual(source);
+
+      await store.copyMemoryNamespace('notes', 'team');
+      expect((await store.listMemory('team'))[0]?.source).toEqual(source);
+      await store.importMemory([{ key: 'imported', value: 'x', source: { sessionId: 'session-2' }, updatedAt: new Date().toISOString() }]);
+      expect((await store.getMemory('imported'))?.source).toEqual({ sessionId: 'session-2' });
+      await store.storeMemory({ key: 'retry', namespace: 'notes', value: 'rewritten' });
+      expect((await store.getMemory('retry', 'notes'))?.source).toBeUndefined();
+
+      await expect(store.storeMemory({ key: 'bad', value: 1, source: { startLine: 3 } }))
+        .rejects.toThrow('A memory source span needs a file and, optionally, 1-based start and end lines.');
+      await expect(store.storeMemory({ key: 'bad', value: 1, source: { sessionId: '' } })).rejects.toThrow('Memory source sessionId must be a non-empty string.');
+    }
+  });
+
+  it('stores, upserts, queries and deletes typed facts in both backends', async () => {
+    for (const backend of ['sqlite', 'json'] as const) {
+      const tempDir = createTempDir();
+      tempDirs.push(tempDir);
+      const store = createStateStore({ basePath: tempDir, backend });
+      const calls = await store.storeFact({ subject: 'billing-api', predicate: 'calls', object: 'ledger-api', sourceSpan: { file: 'src/billing.ts', startLine: 12, endLine: 14 } });
+      await store.storeFact({ subject: 'billing-api', predicate: 'retries', object: 3 });
+      await store.storeFact({ subject: 'billing-api', predicate: 'retries', object: '3', namespace: 'notes' });
+      await store.storeFact({ subject: 'ledger-api', predicate: 'deprecated', object: false, confidence: 0.6 });
+      await new Promise((resolve) => setTimeout(resolve, 5));
+
+      const updated = await store.storeFact({ subject: 'billing-api', predicate: 'calls', object: 'ledger-api', sourceSpan: { file: 'src/billing.ts', startLine: 20 }, confidence: 0.9 });
+      expect(updated).toMatchObject({ factId: calls.factId, createdAt: calls.createdAt, sourceSpan: { file: 'src/billing.ts', startLine: 20 }, confidence: 0.9 });
+      expect(updated.updatedAt > calls.updatedAt).toBe(true);
+
+      expect((await store.queryFacts()).map((fact) => fact.factId)[0]).toBe(calls.factId);
+      expect(await store.queryFacts({ subject: 'billing-api' })).toHaveLength(3);
+      expect((await store.queryFacts({ object: 3 })).map((fact) => fact.object)).toEqual([3]);
+      expect((await store.queryFacts({ object: '3' })).map((fact) => fact.namespace)).toEqual(['notes']);
+      expect((await store.queryFacts({ predicate: 'retries', namespace: 'default' })).map((fact) => fact.object)).toEqual([3]);
+      expect((await store.queryFacts({ object: false }))[0]).toMatchObject({ subject: 'ledger-api', confidence: 0.6 });
+      expect(await store.queryFacts({ subject: 'billing-api', limit: 1 })).toHaveLength(1);
+
+      expect(await store.deleteFact(calls.factId)).toBe(true);
+      expect(await store.deleteFact(calls.factId)).toBe(false);
+      expect(await store.queryFacts({ predicate: 'calls' })).toEqual([]);
+      await expect(store.storeFact({ subject: ' ', predicate: 'calls', object: 'x' })).rejects.toThrow('A fact needs a non-empty subject.');
+      await expect(store.storeFact({ subject: 'a', predicate: 'b', object: 'c', sourceSpan: { file: 'a.ts', startLine: 5, endLine: 2 } }))
+        .rejects.toThrow('Fact source span ends at line 2, before it starts at line 5.');
+    }
+  });
+
+  it('coordinates claims of a session scratchpad across store instances in both backends', async () => {
+    for (const backend of ['sqlite', 'json'] as const) {
+      const tempDir = createTempDir();
+      tempDirs.push(tempDir);
+      const first = createStateStore({ basePath: tempDir, backend });
+      const second = createStateStore({ basePath: tempDir, backend });
+      const session = await first.createSession({ task: 'Rate limiting', initiator: 'cli' });
+      const added = await first.addScratchpadTasks(session.sessionId, [
+        { title: 'Limiter core', paths: ['src/limits/'] },
+        { title: 'Limiter config', paths: ['src/limits/config.ts'] },
+        { taskId: 'docs', title: 'Document the limits', paths: ['docs'] },
+      ]);
+      expect(added.map((task) => [task.taskId, task.paths, task.status])).toEqual([
+        ['limiter-core', ['src/limits'], 'open'],
+        ['limiter-config', ['src/limits/config.ts'], 'open'],
+        ['docs', ['docs'], 'open'],
+      ]);
+      await expect(first.addScratchpadTasks(session.sessionId, [{ taskId: 'docs', title: 'Again' }])).rejects.toThrow(`Session "${session.sessionId}" already has a scratchpad task "docs".`);
+
+      // Two stores claiming at once get different tasks; the config task overlaps the core one, so it is skipped.
+      const [core, docs] = await Promise.all([
+        first.claimScratchpadTask(session.sessionId, { claimant: 'terminal-1' }),
+        second.claimScratchpadTask(session.sessionId, { claimant: 'terminal-2' }),
+      ]);
+      expect([core?.taskId, docs?.taskId].sort()).toEqual(['docs', 'limiter-core']);
+      expect(await second.claimScratchpadTask(session.sessionId, { claimant: 'terminal-3' })).toBeUndefined();
+      await expect(second.claimScratchpadTask(session.sessionId, { claimant: 'terminal-3', taskId: 'limiter-config' }))
+        .rejects.toMatchObject({ code: 'SCRATCHPAD_CONFLICT' });
+      await expect(second.postScratchpadResult(session.sessionId, { taskId: 'limiter-core', claimant: 'terminal-3', result: 'done' }))
+        .rejects.toMatchObject({ code: 'SCRATCHPAD_CONFLICT' });
+
+      const holder = core?.taskId === 'limiter-core' ? 'terminal-1' : 'terminal-2';
+      const posted = await second.postScratchpadResult(session.sessionId, { taskId: 'limiter-core', claimant: holder, result: 'Token bucket in src/limits/bucket.ts' });
+      expect(posted).toMatchObject({ status: 'done', claimedBy: holder, result: 'Token bucket in src/limits/bucket.ts' });
+      expect(posted.leaseExpiresAt).toBeUndefined();
+      const config = await second.claimScratchpadTask(session.sessionId, { claimant: 'terminal-3', leaseMs: 1 });
+      expect(config?.taskId).toBe('limiter-config');
+      await new Promise((resolve) => setTimeout(resolve, 5));
+      // A lapsed claim can be taken over, and a held one released by its holder.
+      expect(await first.claimScratchpadTask(session.sessionId, { claimant: 'terminal-4', taskId: 'limiter-config' })).toMatchObject({ claimedBy: 'terminal-4' });
+      expect(await first.releaseScratchpadTask(session.sessionId, 'limiter-config', 'terminal-4')).toMatchObject({ status: 'open' });
+
+      const reopened = createStateStore({ basePath: tempDir, backend });
+      expect((await reopened.listScratchpad(session.sessionId)).map((task) => [task.taskId, task.status])).toEqual([
+        ['limiter-core', 'done'],
+        ['limiter-config', 'open'],
+        ['docs', 'claimed'],
+      ]);
+      await expect(reopened.claimScratchpadTask('missing', { claimant: 'x' })).rejects.toThrow('Session not found: missing');
+    }
+  });
+
   it('uses custom storageFile for the default sqlite backend', async () => {
     const tempDir = createTempDir();
     tempDirs.push(tempDir);
@@ -231,6 +443,44 @@ describe('state store', () => {
     expect(allHistory).toHaveLength(1);
   });
 
+  it('records provider usage and lists it most recent first', async () => {
+    const tempDir = createTempDir();
+    tempDirs.push(tempDir);
+
+    const store = createStateStore({ basePath: tempDir });
+    await store.recordUsage({
+      provider: 'claude',
+      model: 'claude-sonnet-4-5',
+      agentId: 'architect',
+      traceId: 'trace-1',
+      inputTokens: 1200,
+      outputTokens: 300,
+      latencyMs: 850,
+      costUsd: 0.0081,
+      success: true,
+      recordedAt: '2026-01-01T00:00:00.000Z',
+    });
+    await store.recordUsage({
+      provider: 'gemini',
+      agentId: 'qa',
+      traceId: 'trace-2',
+      rootTraceId: 'trace-1',
+      inputTokens: 40,
+      outputTokens: 10,
+      latencyMs: 120,
+      success: false,
+    });
+
+    const reopened = createStateStore({ basePath: tempDir });
+    expect(await reopened.listUsage()).toMatchObject([
+      { provider: 'gemini', agentId: 'qa', success: false },
+      { provider: 'claude', model: 'claude-sonnet-4-5', inputTokens: 1200, outputTokens: 300, costUsd: 0.0081 },
+    ]);
+    expect(await reopened.listUsage({ traceId: 'trace-1' })).toHaveLength(2);
+    expect(await reopened.listUsage({ agentId: 'qa' })).toHaveLength(1);
+    expect(await reopened.listUsage({ since: '2026-06-01T00:00:00.000Z' })).toMatchObject([{ provider: 'gemini' }]);
+  });
+
   it('persists session lifecycle transitions', async () => {
     const tempDir = createTempDir();
     tempDirs.push(tempDir);
@@ -13,6 +13,7 @@ export {
 export {
   createRealStepExecutor,
   type PromptExecutorLike,
+  type PromptConsensusConfig,
   type ToolExecutorLike,
   type DiscussionExecutorLike,
   type DiscussStepConfigLike,
@@ -23,6 +24,12 @@ export {
   type DelegateRunResultLike,
   type RealStepExecutorConfig,
 } from './step-executor-factory.js';
+export {
+  runScheduled,
+  findDependencyErrors,
+  type ScheduledTask,
+  type SchedulerLimits,
+} from './scheduler.js';
 export {
   DEFAULT_RETRY_POLICY,
   mergeRetryPolicy,
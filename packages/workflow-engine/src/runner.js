@@ -4,14 +4,17 @@ import { WorkflowErrorCodes } from './types.js';
 import { prepareWorkflow, deepFreezeStepResult } from './validation.js';
 import { defaultStepExecutor, createStepError, normalizeError } from './executor.js';
 import { mergeRetryPolicy, shouldRetry, calculateBackoff, sleep, } from './retry.js';
+import { runScheduled } from './scheduler.js';
 const UNKNOWN_AGENT_ID = 'unknown';
 const WORKFLOW_GUARD_BLOCKED = 'WORKFLOW_GUARD_BLOCKED';
+const DEFAULT_MAX_CONCURRENT_STEPS = 3;
 export class WorkflowRunner {
     config;
     constructor(config = {}) {
         this.config = {
             stepExecutor: config.stepExecutor ?? defaultStepExecutor,
             defaultRetryPolicy: config.defaultRetryPolicy ?? DEFAULT_RETRY_POLICY,
+            maxConcurrentSteps: config.maxConcurrentSteps ?? DEFAULT_MAX_CONCURRENT_STEPS,
         };
         if (config.onStepStart !== undefined) {
             this.config.onStepStart = config.onStepStart;
@@ -28,6 +31,12 @@ export class WorkflowRunner {
         if (config.agentId !== undefined) {
             this.config.agentId = config.agentId;
         }
+        if (config.concurrencyPool !== undefined) {
+            this.config.concurrencyPool = config.concurrencyPool;
+        }
+        if (config.poolLimits !== undefined) {
+            this.config.poolLimits = config.poolLimits;
+        }
     }
     async run(workflowData, input) {
         const startTime = Date.now();
@@ -40,6 +49,9 @@ export class WorkflowRunner {
             return this.createErrorResult('unknown', startTime, [], normalizeError(error));
         }
         const { workflow } = prepared;
+        if (workflow.steps.some((step) => (step.dependencies?.length ?? 0) > 0)) {
+            return this.runDependencyGraph(prepared, input, executionId, startTime);
+        }
         const stepResults = [];
         for (let i = 0; i < workflow.steps.length; i += 1) {
             const step = workflow.steps[i];
@@ -85,19 +97,11 @@ export class WorkflowRunner {
                 }
             }
             if (!frozenResult.success) {
-                const workflowError = {
-                    code: WorkflowErrorCodes.STEP_EXECUTION_FAILED,
-                    message: `Step ${step.stepId} failed: ${frozenResult.error?.message ?? 'Unknown error'}`,
-                    failedStepId: step.stepId,
-                };
-                if (frozenResult.error?.details !== undefined) {
-                    workflowError.details = frozenResult.error.details;
-                }
                 return {
                     workflowId: workflow.workflowId,
                     success: false,
                     stepResults,
-                    error: workflowError,
+                    error: this.createStepFailedError(step, frozenResult),
                     totalDurationMs: Date.now() - startTime,
                 };
             }
@@ -111,6 +115,95 @@ export class WorkflowRunner {
             totalDurationMs: Date.now() - startTime,
         };
     }
+    /**
+     * Runs steps that declare `dependencies` as a graph: each step starts once the steps it depends
+     * on have completed, up to `maxConcurrentSteps` at once and `poolLimits` per pool. A step with
+     * no dependencies gets the workflow input, one with a single dependency that step's output, and
+     * one with several an object of their outputs by step id. After a failure no further steps
+     * start; the ones running are let finish.
+     */
+    async runDependencyGraph(prepared, input, executionId, startTime) {
+        const { workflow } = prepared;
+        const stepResults = [];
+        // Set by the first step to fail; read inside the scheduled callbacks.
+        const halt = {};
+        await runScheduled(workflow.steps.map((step, index) => ({ id: step.stepId, dependencies: step.dependencies, pool: this.config.concurrencyPool?.(step), step, index })), { maxConcurrent: this.config.maxConcurrentSteps, poolLimits: this.config.poolLimits }, async ({ step, index }, dependencyResults) => {
+            if (halt.error !== undefined) {
+                return undefined;
+            }
+            const dependencies = step.dependencies ?? [];
+            const context = {
+                workflowId: workflow.workflowId,
+                stepIndex: index,
+                previousResults: [...stepResults],
+                input: dependencies.length === 0
+                    ? (input ?? {})
+                    : dependencies.length === 1
+                        ? (dependencyResults.get(dependencies[0])?.output ?? {})
+                        : Object.fromEntries(dependencies.map((dependency) => [dependency, dependencyResults.get(dependency)?.output])),
+            };
+            if (this.config.stepGuardEngine) {
+                const guardContext = this.buildGuardContext(executionId, step, index, prepared, stepResults);
+                const beforeResults = await this.config.stepGuardEngine.runBeforeGuards(guardContext);
+                if (this.config.stepGuardEngine.shouldBlock(beforeResults)) {
+                    halt.error ??= this.createBlockedResult(prepared, stepResults, step, beforeResults, startTime).error;
+                    return undefined;
+                }
+            }
+            this.config.onStepStart?.(step, context);
+            const frozenResult = deepFreezeStepResult(await this.executeStepWithRetry(step, context));
+            stepResults.push(frozenResult);
+            this.config.onStepComplete?.(step, frozenResult);
+            if (this.config.stepGuardEngine) {
+                try {
+                    await this.config.stepGuardEngine.runAfterGuards(this.buildGuardContext(executionId, step, index, prepared, stepResults));
+                }
+                catch (guardError) {
+                    halt.error ??= {
+                        code: WorkflowErrorCodes.AFTER_GUARD_ERROR,
+                        message: `After guard check failed for step ${step.stepId}`,
+                        details: {
+                            stepId: step.stepId,
+                            error: guardError instanceof Error ? guardError.message : String(guardError),
+                        },
+                    };
+                }
+            }
+            if (!frozenResult.success) {
+                halt.error ??= this.createStepFailedError(step, frozenResult);
+            }
+            return frozenResult;
+        });
+        // Results in the order the steps are declared, whatever order they finished in.
+        const ordered = workflow.steps.flatMap((step) => stepResults.filter((result) => result.stepId === step.stepId));
+        if (halt.error !== undefined) {
+            return {
+                workflowId: workflow.workflowId,
+                success: false,
+                stepResults: ordered,
+                error: halt.error,
+                totalDurationMs: Date.now() - startTime,
+            };
+        }
+        return {
+            workflowId: workflow.workflowId,
+            success: true,
+            stepResults: ordered,
+            output: ordered[ordered.length - 1]?.output,
+            totalDurationMs: Date.now() - startTime,
+        };
+    }
+    createStepFailedError(step, result) {
+        const workflowError = {
+            code: WorkflowErrorCodes.STEP_EXECUTION_FAILED,
+            message: `Step ${step.stepId} failed: ${result.error?.message ?? 'Unknown error'}`,
+            failedStepId: step.stepId,
+        };
+        if (result.error?.details !== undefined) {
+            workflowError.details = result.error.details;
+        }
+        return workflowError;
+    }
     async executeStepWithRetry(step, context) {
         const retryPolicy = mergeRetryPolicy(step.retryPolicy ?? this.config.defaultRetryPolicy);
         let lastResult = null;
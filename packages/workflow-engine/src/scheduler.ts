@@ -0,0 +1,98 @@
+/** A unit of work that starts once every task it depends on has finished. */
+export interface ScheduledTask {
+  id: string;
+  dependencies?: readonly string[] | undefined;
+  /** The concurrency pool the task counts against, such as the provider it calls. */
+  pool?: string | undefined;
+}
+
+export interface SchedulerLimits {
+  /** Tasks running at once across all pools. */
+  maxConcurrent: number;
+  /** Tasks running at once per pool; a pool that is not listed is bounded by `maxConcurrent` alone. */
+  poolLimits?: Readonly<Record<string, number>> | undefined;
+}
+
+/**
+ * Runs `tasks` as a dependency graph. A task starts as soon as its dependencies have finished and
+ * there is room for it, overall and in its pool, so independent branches overlap instead of
+ * waiting for whole layers. Ready tasks start in the order given; one held back by a full pool
+ * does not hold back ready tasks of other pools. `run` gets the results of the task's
+ * dependencies and must not throw. Dependencies must be known and acyclic; see
+ * `findDependencyErrors`.
+ */
+export async function runScheduled<T extends ScheduledTask, R>(
+  tasks: readonly T[],
+  limits: SchedulerLimits,
+  run: (task: T, dependencyResults: ReadonlyMap<string, R>) => Promise<R>,
+): Promise<Map<string, R>> {
+  const results = new Map<string, R>();
+  const pending = [...tasks];
+  const running = new Set<Promise<void>>();
+  const poolCounts = new Map<string, number>();
+  const maxConcurrent = Math.max(1, limits.maxConcurrent);
+  const hasRoom = (task: T): boolean => {
+    const limit = task.pool === undefined ? undefined : limits.poolLimits?.[task.pool];
+    return limit === undefined || (poolCounts.get(task.pool!) ?? 0) < Math.max(1, limit);
+  };
+
+  while (pending.length > 0 || running.size > 0) {
+    for (let index = 0; index < pending.length && running.size < maxConcurrent;) {
+      const task = pending[index]!;
+      const dependencies = task.dependencies ?? [];
+      if (!dependencies.every((dependency) => results.has(dependency)) || !hasRoom(task)) {
+        index += 1;
+        continue;
+      }
+      pending.splice(index, 1);
+      if (task.pool !== undefined) {
+        poolCounts.set(task.pool, (poolCounts.get(task.pool) ?? 0) + 1);
+      }
+      const dependencyResults = new Map(dependencies.map((dependency) => [dependency, results.get(dependency)!] as const));
+      const execution: Promise<void> = run(task, dependencyResults).then((result) => {
+        results.set(task.id, result);
+        running.delete(execution);
+        if (task.pool !== undefined) {
+          poolCounts.set(task.pool, (poolCounts.get(task.pool) ?? 1) - 1);
+        }
+      });
+      running.add(execution);
+    }
+    if (running.size === 0) {
+      throw new Error(`Tasks ${pending.map((task) => `"${task.id}"`).join(', ')} wait on dependencies that never finish.`);
+    }
+    await Promise.race(running);
+  }
+  return results;
+}
+
+/** Dependencies that are unknown, on the task itself, or in a cycle; empty when the graph can run. */
+export function findDependencyErrors(tasks: readonly ScheduledTask[]): string[] {
+  const ids = new Set(tasks.map((task) => task.id));
+  const errors: string[] = [];
+  for (const task of tasks) {
+    for (const dependency of task.dependencies ?? []) {
+      if (dependency === task.id) {
+        errors.push(`"${task.id}" depends on itself`);
+      } else if (!ids.has(dependency)) {
+        errors.push(`"${task.id}" depends on unknown "${dependency}"`);
+      }
+    }
+  }
+  if (errors.length > 0) {
+    return errors;
+  }
+
+  const finished = new Set<string>();
+  for (let progressed = true; progressed;) {
+    progressed = false;
+    for (const task of tasks) {
+      if (!finished.has(task.id) && (task.dependencies ?? []).every((dependency) => finished.has(dependency))) {
+        finished.add(task.id);
+        progressed = true;
+      }
+    }
+  }
+  const cyclic = tasks.filter((task) => !finished.has(task.id));
+  return cyclic.length > 0 ? [`dependency cycle among ${cyclic.map((task) => `"${task.id}"`).join(', ')}`] : [];
+}
@@ -68,7 +68,7 @@ async function executePromptStep(step, context, promptExecutor, defaultProvider,
             retryCount: 0,
         };
     }
-    const executeRequest = { prompt };
+    const executeRequest = { prompt, stepId: step.stepId };
     if (config.systemPrompt !== undefined) {
         executeRequest.systemPrompt = config.systemPrompt;
     }
@@ -87,6 +87,12 @@ async function executePromptStep(step, context, promptExecutor, defaultProvider,
     if ((config.timeout ?? step.timeout) !== undefined) {
         executeRequest.timeout = config.timeout ?? step.timeout;
     }
+    if (isRecord(config.consensus)) {
+        executeRequest.consensus = config.consensus;
+    }
+    if (isRecord(config.outputSchema)) {
+        executeRequest.outputSchema = config.outputSchema;
+    }
     const response = await promptExecutor.execute(executeRequest);
     if (response.success) {
         return {
@@ -94,6 +100,8 @@ async function executePromptStep(step, context, promptExecutor, defaultProvider,
             success: true,
             output: {
                 content: response.content,
+                // Later steps reach into a schema-checked reply as `data` instead of re-parsing `content`.
+                ...(response.structured !== undefined ? { data: response.structured.value } : {}),
                 provider: response.provider,
                 model: response.model,
                 usage: response.usage,
@@ -108,7 +116,8 @@ async function executePromptStep(step, context, promptExecutor, defaultProvider,
         error: {
             code: response.errorCode ?? 'PROMPT_EXECUTION_FAILED',
             message: response.error ?? 'Prompt execution failed',
-            retryable: true,
+            // The repair attempts have already been spent on a reply that does not fit the schema.
+            retryable: response.errorCode !== 'PROVIDER_OUTPUT_INVALID',
         },
         durationMs: Date.now() - startTime,
         retryCount: 0,
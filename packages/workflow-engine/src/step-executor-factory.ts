@@ -4,12 +4,18 @@ import type { StepContext, StepExecutor, StepResult } from './types.js';
 export interface PromptExecutorLike {
   execute(request: {
     prompt: string;
+    /** The prompt step the call is made for. */
+    stepId?: string;
     systemPrompt?: string;
     provider?: string;
     model?: string;
     maxTokens?: number;
     temperature?: number;
     timeout?: number;
+    /** Sends the prompt to several providers at once and returns the fastest or the judged answer. */
+    consensus?: PromptConsensusConfig;
+    /** A JSON Schema the reply must fit; replies that do not are sent back for repair. */
+    outputSchema?: Record<string, unknown>;
   }): Promise<{
     success: boolean;
     content?: string;
@@ -23,6 +29,8 @@ export interface PromptExecutorLike {
       outputTokens: number;
       totalTokens: number;
     };
+    /** The parsed reply of a call with an `outputSchema`. */
+    structured?: { value?: unknown; valid: boolean; attempts: number };
   }>;
   getDefaultProvider(): string;
 }
@@ -164,6 +172,12 @@ export interface RealStepExecutorConfig {
   maxDelegationDepth?: number;
 }
 
+export interface PromptConsensusConfig {
+  providers?: string[];
+  strategy?: 'fastest' | 'judge';
+  judge?: string;
+}
+
 interface PromptStepConfig {
   prompt?: string;
   systemPrompt?: string;
@@ -172,6 +186,8 @@ interface PromptStepConfig {
   maxTokens?: number;
   temperature?: number;
   timeout?: number;
+  consensus?: PromptConsensusConfig;
+  outputSchema?: Record<string, unknown>;
 }
 
 interface ToolStepConfig {
@@ -293,7 +309,7 @@ async function executePromptStep(
     };
   }
 
-  const executeRequest: Parameters<typeof promptExecutor.execute>[0] = { prompt };
+  const executeRequest: Parameters<typeof promptExecutor.execute>[0] = { prompt, stepId: step.stepId };
   if (config.systemPrompt !== undefined) {
     executeRequest.systemPrompt = config.systemPrompt;
   }
@@ -312,6 +328,12 @@ async function executePromptStep(
   if ((config.timeout ?? step.timeout) !== undefined) {
     executeRequest.timeout = config.timeout ?? step.timeout;
   }
+  if (isRecord(config.consensus)) {
+    executeRequest.consensus = config.consensus;
+  }
+  if (isRecord(config.outputSchema)) {
+    executeRequest.outputSchema = config.outputSchema;
+  }
 
   const response = await promptExecutor.execute(executeRequest);
   if (response.success) {
@@ -320,6 +342,8 @@ async function executePromptStep(
       success: true,
       output: {
         content: response.content,
+        // Later steps reach into a schema-checked reply as `data` instead of re-parsing `content`.
+        ...(response.structured !== undefined ? { data: response.structured.value } : {}),
         provider: response.provider,
         model: response.model,
         usage: response.usage,
@@ -335,7 +359,8 @@ async function executePromptStep(
     error: {
       code: response.errorCode ?? 'PROMPT_EXECUTION_FAILED',
       message: response.error ?? 'Prompt execution failed',
-      retryable: true,
+      // The repair attempts have already been spent on a reply that does not fit the schema.
+      retryable: response.errorCode !== 'PROVIDER_OUTPUT_INVALID',
     },
     durationMs: Date.now() - startTime,
     retryCount: 0,
@@ -6,4 +6,5 @@ export const WorkflowErrorCodes = {
     MAX_RETRIES_EXCEEDED: 'WORKFLOW_MAX_RETRIES_EXCEEDED',
     UNKNOWN_STEP_TYPE: 'WORKFLOW_UNKNOWN_STEP_TYPE',
     AFTER_GUARD_ERROR: 'WORKFLOW_AFTER_GUARD_ERROR',
+    DEPENDENCY_INVALID: 'WORKFLOW_DEPENDENCY_INVALID',
 };
@@ -53,6 +53,12 @@ export interface WorkflowRunnerConfig {
   stepGuardEngine?: StepGuardEngine | undefined;
   executionId?: string | undefined;
   agentId?: string | undefined;
+  /** Steps running at once when steps declare `dependencies`. Defaults to 3. */
+  maxConcurrentSteps?: number | undefined;
+  /** The concurrency pool of a step, such as the provider it calls; see `poolLimits`. */
+  concurrencyPool?: ((step: WorkflowStep) => string | undefined) | undefined;
+  /** Steps running at once per pool. */
+  poolLimits?: Readonly<Record<string, number>> | undefined;
 }
 
 export interface PreparedWorkflow {
@@ -68,6 +74,7 @@ export const WorkflowErrorCodes = {
   MAX_RETRIES_EXCEEDED: 'WORKFLOW_MAX_RETRIES_EXCEEDED',
   UNKNOWN_STEP_TYPE: 'WORKFLOW_UNKNOWN_STEP_TYPE',
   AFTER_GUARD_ERROR: 'WORKFLOW_AFTER_GUARD_ERROR',
+  DEPENDENCY_INVALID: 'WORKFLOW_DEPENDENCY_INVALID',
 } as const;
 
 export type WorkflowErrorCode =
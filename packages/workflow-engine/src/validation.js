@@ -1,5 +1,6 @@
 import { WorkflowSchema } from '@defai.digital/contracts';
 import { WorkflowErrorCodes } from './types.js';
+import { findDependencyErrors } from './scheduler.js';
 export class WorkflowValidationError extends Error {
     code;
     details;
@@ -23,6 +24,10 @@ export function validateWorkflow(data) {
         }
         stepIds.add(step.stepId);
     }
+    const dependencyErrors = findDependencyErrors(workflow.steps.map((step) => ({ id: step.stepId, dependencies: step.dependencies })));
+    if (dependencyErrors.length > 0) {
+        throw new WorkflowValidationError(WorkflowErrorCodes.DEPENDENCY_INVALID, `Workflow step dependencies are invalid: ${dependencyErrors.join('; ')}`, { errors: dependencyErrors });
+    }
     return workflow;
 }
 function deepFreeze(obj) {
@@ -1,6 +1,7 @@
 import { WorkflowSchema, type Workflow } from '@defai.digital/contracts';
 import type { PreparedWorkflow, StepResult } from './types.js';
 import { WorkflowErrorCodes } from './types.js';
+import { findDependencyErrors } from './scheduler.js';
 
 export class WorkflowValidationError extends Error {
   constructor(
@@ -37,6 +38,15 @@ export function validateWorkflow(data: unknown): Workflow {
     stepIds.add(step.stepId);
   }
 
+  const dependencyErrors = findDependencyErrors(workflow.steps.map((step) => ({ id: step.stepId, dependencies: step.dependencies })));
+  if (dependencyErrors.length > 0) {
+    throw new WorkflowValidationError(
+      WorkflowErrorCodes.DEPENDENCY_INVALID,
+      `Workflow step dependencies are invalid: ${dependencyErrors.join('; ')}`,
+      { errors: dependencyErrors },
+    );
+  }
+
   return workflow;
 }
 
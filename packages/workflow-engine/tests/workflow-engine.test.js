@@ -406,4 +406,54 @@ describe('workflow-engine', () => {
             },
         });
     });
+    it('runs steps with dependencies as a graph, bounded overall and per pool', async () => {
+        const events = [];
+        const inputs = {};
+        const graph = {
+            workflowId: 'graph-workflow',
+            version: '1.0.0',
+            steps: [
+                { stepId: 'lint', type: 'tool', config: { pool: 'local' } },
+                { stepId: 'review', type: 'prompt', config: { pool: 'api' } },
+                { stepId: 'docs', type: 'prompt', config: { pool: 'api' } },
+                { stepId: 'summary', type: 'prompt', dependencies: ['lint', 'review'] },
+                { stepId: 'publish', type: 'tool', dependencies: ['summary'] },
+            ],
+        };
+        const runner = createWorkflowRunner({
+            maxConcurrentSteps: 3,
+            concurrencyPool: (step) => typeof step.config?.pool === 'string' ? step.config.pool : undefined,
+            poolLimits: { api: 1 },
+            stepExecutor: async (step, context) => {
+                events.push(`start ${step.stepId}`);
+                inputs[step.stepId] = context.input;
+                await new Promise((resolve) => setTimeout(resolve, step.stepId === 'lint' ? 5 : 20));
+                events.push(`end ${step.stepId}`);
+                return { stepId: step.stepId, success: step.stepId !== 'docs' || context.input !== 'fail', output: `${step.stepId} done`, durationMs: 20, retryCount: 0 };
+            },
+        });
+        const result = await runner.run(graph, 'patch');
+        expect(result.success).toBe(true);
+        // lint and review overlap; docs waits for the single api slot; summary waits for both of its dependencies.
+        expect(events.slice(0, 2)).toEqual(['start lint', 'start review']);
+        expect(events.indexOf('start docs')).toBeGreaterThan(events.indexOf('end review'));
+        expect(events.indexOf('start summary')).toBeGreaterThan(events.indexOf('end review'));
+        expect(inputs).toMatchObject({
+            lint: 'patch',
+            summary: { lint: 'lint done', review: 'review done' },
+            publish: 'summary done',
+        });
+        expect(result.stepResults.map((entry) => entry.stepId)).toEqual(['lint', 'review', 'docs', 'summary', 'publish']);
+        expect(result.output).toBe('publish done');
+        // A failure starts no further steps.
+        events.length = 0;
+        const failed = await runner.run(graph, 'fail');
+        expect(failed.error).toMatchObject({ code: 'WORKFLOW_STEP_EXECUTION_FAILED', failedStepId: 'docs' });
+        expect(events).not.toContain('start publish');
+        const cyclic = await runner.run({
+            ...graph,
+            steps: [{ stepId: 'a', type: 'tool', dependencies: ['b'] }, { stepId: 'b', type: 'tool', dependencies: ['a'] }],
+        });
+        expect(cyclic.error).toMatchObject({ code: 'WORKFLOW_DEPENDENCY_INVALID', message: expect.stringContaining('dependency cycle among "a", "b"') });
+    });
 });
@@ -1,5 +1,6 @@
 import { defineConfig } from 'vitest/config';
-import { resolve } from 'node:path';
+import { tmpdir } from 'node:os';
+import { join, resolve } from 'node:path';
 /**
  * Vite plugin to mark all `node:*` built-in specifiers as external.
  * This is necessary for newer Node built-ins like `node:sqlite` that Vite
@@ -24,6 +25,8 @@ export default defineConfig({
     plugins: [nodeBuiltinsPlugin()],
     test: {
         environment: 'node',
+        // Keep the code index parse cache out of the developer's home directory.
+        env: { AUTOMATOSX_CACHE_DIR: join(tmpdir(), 'automatosx-test-cache') },
         include: [
             'packages/**/*.test.ts',
             'tests/**/*.test.ts',
@@ -1,5 +1,6 @@
 import { defineConfig, type Plugin } from 'vitest/config';
-import { resolve } from 'node:path';
+import { tmpdir } from 'node:os';
+import { join, resolve } from 'node:path';
 
 /**
  * Vite plugin to mark all `node:*` built-in specifiers as external.
@@ -26,6 +27,8 @@ export default defineConfig({
   plugins: [nodeBuiltinsPlugin()],
   test: {
     environment: 'node',
+    // Keep the code index parse cache out of the developer's home directory.
+    env: { AUTOMATOSX_CACHE_DIR: join(tmpdir(), 'automatosx-test-cache') },
     include: [
       'packages/**/*.test.ts',
       'tests/**/*.test.ts',